@@ -0,0 +1,120 @@
+//! Rate limit cooldown lookups under concurrency.
+//!
+//! Every completion checks its model's cooldown before calling the provider,
+//! so this runs the same mix of lookups, successes, and rate limits against
+//! the sharded `Cooldowns` and against one lock around the whole table (how
+//! cooldowns used to be kept), at increasing thread counts.
+//!
+//! Run with `cargo bench --bench cooldowns`.
+
+use spacebot::llm::cooldown::{CooldownPolicy, Cooldowns};
+
+use std::sync::{Arc, Barrier, RwLock};
+use std::time::{Duration, Instant};
+
+const MODELS: usize = 200;
+const OPS_PER_THREAD: usize = 200_000;
+const THREADS: [usize; 5] = [1, 4, 16, 32, 64];
+
+const POLICY: CooldownPolicy = CooldownPolicy {
+    base: Duration::from_secs(60),
+    max: Duration::from_secs(900),
+    reset_after: Duration::from_secs(600),
+};
+
+/// The operations a completion performs on the table.
+trait Table: Send + Sync + 'static {
+    fn check(&self, model: &str, now: Instant) -> bool;
+    fn succeed(&self, model: &str, now: Instant);
+    fn rate_limit(&self, model: &str, now: Instant);
+}
+
+impl Table for Cooldowns {
+    fn check(&self, model: &str, now: Instant) -> bool {
+        self.remaining(model, now).is_some()
+    }
+
+    fn succeed(&self, model: &str, now: Instant) {
+        if self.tracks(model) {
+            Cooldowns::succeed(self, model, now);
+        }
+    }
+
+    fn rate_limit(&self, model: &str, now: Instant) {
+        self.strike(model, now, &POLICY, None);
+    }
+}
+
+/// One lock around the table.
+#[derive(Default)]
+struct Locked(RwLock<Cooldowns>);
+
+impl Table for Locked {
+    fn check(&self, model: &str, now: Instant) -> bool {
+        self.0.read().unwrap().remaining(model, now).is_some()
+    }
+
+    fn succeed(&self, model: &str, now: Instant) {
+        if self.0.read().unwrap().tracks(model) {
+            self.0.write().unwrap().succeed(model, now);
+        }
+    }
+
+    fn rate_limit(&self, model: &str, now: Instant) {
+        self.0.write().unwrap().strike(model, now, &POLICY, None);
+    }
+}
+
+/// Operations per second across `threads` threads: 97% lookups, 2%
+/// successes, 1% rate limits, spread over `MODELS` models.
+fn throughput<T: Table + Default>(threads: usize, models: &Arc<Vec<String>>) -> f64 {
+    let table = Arc::new(T::default());
+    let barrier = Arc::new(Barrier::new(threads + 1));
+    let handles: Vec<_> = (0..threads)
+        .map(|thread| {
+            let (table, barrier, models) = (table.clone(), barrier.clone(), models.clone());
+            std::thread::spawn(move || {
+                barrier.wait();
+                let mut cooling = 0usize;
+                for op in 0..OPS_PER_THREAD {
+                    let model = &models[(thread * 31 + op * 7) % MODELS];
+                    let now = Instant::now();
+                    match op % 100 {
+                        0 => table.rate_limit(model, now),
+                        1 | 2 => table.succeed(model, now),
+                        _ => cooling += usize::from(table.check(model, now)),
+                    }
+                }
+                cooling
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    let started = Instant::now();
+    for handle in handles {
+        std::hint::black_box(handle.join().expect("bench thread panicked"));
+    }
+    (threads * OPS_PER_THREAD) as f64 / started.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let models: Arc<Vec<String>> = Arc::new(
+        (0..MODELS)
+            .map(|index| format!("provider{}/model-{index}", index % 8))
+            .collect(),
+    );
+
+    println!(
+        "{:>8} {:>16} {:>16} {:>8}",
+        "threads", "sharded ops/s", "locked ops/s", "speedup"
+    );
+    for threads in THREADS {
+        let sharded = throughput::<Cooldowns>(threads, &models);
+        let locked = throughput::<Locked>(threads, &models);
+        println!(
+            "{threads:>8} {sharded:>16.0} {locked:>16.0} {:>7.1}x",
+            sharded / locked
+        );
+    }
+}
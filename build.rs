@@ -1,6 +1,10 @@
 use std::process::Command;
 
 fn main() {
+    #[cfg(feature = "grpc")]
+    compile_protos();
+    embed_build_info();
+
     if std::env::var("SPACEBOT_SKIP_FRONTEND_BUILD").is_ok() {
         return;
     }
@@ -43,6 +47,60 @@ fn main() {
     }
 }
 
+/// Generate the gRPC service from `proto/` (needs `protoc` on the PATH).
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/spacebot.proto");
+    if let Err(error) = tonic_build::configure()
+        .build_client(false)
+        .compile_protos(&["proto/spacebot.proto"], &["proto"])
+    {
+        panic!("failed to compile proto/spacebot.proto: {error}");
+    }
+}
+
+/// Give the binary its commit and build time as `SPACEBOT_GIT_HASH` and
+/// `SPACEBOT_BUILD_TIMESTAMP` (Unix seconds).
+fn embed_build_info() {
+    if std::path::Path::new(".git/HEAD").exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        println!("cargo:rerun-if-changed=.git/refs/heads");
+    }
+    println!("cargo:rerun-if-env-changed=SPACEBOT_GIT_HASH");
+    println!("cargo:rerun-if-env-changed=RAILWAY_GIT_COMMIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // Builds without .git, like the Docker image's, pass the commit in.
+    let git_hash = ["SPACEBOT_GIT_HASH", "RAILWAY_GIT_COMMIT_SHA"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|hash| !hash.trim().is_empty())
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+        })
+        .map(|hash| hash.trim().chars().take(12).collect::<String>())
+        .unwrap_or_else(|| "unknown".into());
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible.
+    let build_timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or_default()
+        });
+
+    println!("cargo:rustc-env=SPACEBOT_GIT_HASH={git_hash}");
+    println!("cargo:rustc-env=SPACEBOT_BUILD_TIMESTAMP={build_timestamp}");
+}
+
 /// rust-embed requires the folder to exist even if empty.
 fn ensure_dist_dir() {
     let dist = std::path::Path::new("interface/dist");
@@ -0,0 +1,11 @@
+-- Per-turn metadata for channel transcripts: the model the turn was routed
+-- to and the tool calls it made.
+CREATE TABLE IF NOT EXISTS channel_turns (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    model TEXT NOT NULL,
+    tool_calls TEXT NOT NULL,        -- JSON array of {name, arguments, result}
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_channel_turns_channel ON channel_turns(channel_id, created_at);
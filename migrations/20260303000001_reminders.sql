@@ -0,0 +1,17 @@
+-- One-off reminders set with the remind tool, delivered back in the channel
+-- they were set in.
+CREATE TABLE IF NOT EXISTS reminders (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    sender_id TEXT NOT NULL,
+    mention TEXT NOT NULL,          -- platform mention of the requester, e.g. <@123>
+    message TEXT NOT NULL,
+    due_at TIMESTAMP NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',  -- pending, delivered, failed
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    delivered_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_reminders_due ON reminders(status, due_at);
+CREATE INDEX IF NOT EXISTS idx_reminders_sender ON reminders(sender_id);
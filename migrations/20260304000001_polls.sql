@@ -0,0 +1,23 @@
+-- Button polls posted with the poll tool, and the votes cast on them.
+CREATE TABLE IF NOT EXISTS polls (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    question TEXT NOT NULL,
+    options TEXT NOT NULL,           -- JSON array of option labels
+    closes_at TIMESTAMP NOT NULL,
+    status TEXT NOT NULL DEFAULT 'open',  -- open, closed
+    attempts INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_polls_due ON polls(status, closes_at);
+
+CREATE TABLE IF NOT EXISTS poll_votes (
+    poll_id TEXT NOT NULL,
+    voter_id TEXT NOT NULL,
+    option_index INTEGER NOT NULL,
+    voted_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (poll_id, voter_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_poll_votes_voter ON poll_votes(voter_id);
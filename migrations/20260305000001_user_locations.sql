@@ -0,0 +1,11 @@
+-- Default locations people asked the agent to remember, for the weather tool.
+CREATE TABLE IF NOT EXISTS user_locations (
+    sender_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    admin1 TEXT,
+    country TEXT,
+    latitude REAL NOT NULL,
+    longitude REAL NOT NULL,
+    timezone TEXT,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
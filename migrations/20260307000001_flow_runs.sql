@@ -0,0 +1,14 @@
+-- Guided flow runs started with the flow tool. At most one run per channel
+-- is active at a time.
+CREATE TABLE IF NOT EXISTS flow_runs (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    flow TEXT NOT NULL,
+    step TEXT NOT NULL,
+    slots TEXT NOT NULL DEFAULT '{}',     -- JSON object of filled slots
+    status TEXT NOT NULL DEFAULT 'active',  -- active, completed, cancelled
+    started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_flow_runs_channel ON flow_runs(channel_id, status);
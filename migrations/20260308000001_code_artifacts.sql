@@ -0,0 +1,13 @@
+-- Code blocks and stack traces pasted into channels, numbered per channel.
+-- History keeps a reference; the artifact_read tool returns the content.
+CREATE TABLE IF NOT EXISTS code_artifacts (
+    channel_id TEXT NOT NULL,
+    number INTEGER NOT NULL,
+    kind TEXT NOT NULL,                 -- code, stack_trace
+    language TEXT,
+    content TEXT NOT NULL,
+    content_hash TEXT NOT NULL,         -- sha256 of content, for reuse
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (channel_id, number),
+    UNIQUE (channel_id, content_hash)
+);
@@ -0,0 +1,27 @@
+-- Full-text index over conversation messages, for `!search`. The index reads
+-- content from conversation_messages; triggers keep it in step.
+CREATE VIRTUAL TABLE IF NOT EXISTS conversation_messages_fts USING fts5(
+    content,
+    content = 'conversation_messages',
+    content_rowid = 'rowid'
+);
+
+INSERT INTO conversation_messages_fts (conversation_messages_fts) VALUES ('rebuild');
+
+CREATE TRIGGER IF NOT EXISTS conversation_messages_fts_insert
+AFTER INSERT ON conversation_messages BEGIN
+    INSERT INTO conversation_messages_fts (rowid, content) VALUES (new.rowid, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS conversation_messages_fts_delete
+AFTER DELETE ON conversation_messages BEGIN
+    INSERT INTO conversation_messages_fts (conversation_messages_fts, rowid, content)
+    VALUES ('delete', old.rowid, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS conversation_messages_fts_update
+AFTER UPDATE OF content ON conversation_messages BEGIN
+    INSERT INTO conversation_messages_fts (conversation_messages_fts, rowid, content)
+    VALUES ('delete', old.rowid, old.content);
+    INSERT INTO conversation_messages_fts (rowid, content) VALUES (new.rowid, new.content);
+END;
@@ -0,0 +1,28 @@
+-- Keyword index over memories, searched alongside vector similarity in
+-- hybrid recall. The index reads content from memories; triggers keep it in
+-- step.
+CREATE VIRTUAL TABLE IF NOT EXISTS memories_fts USING fts5(
+    content,
+    content = 'memories',
+    content_rowid = 'rowid'
+);
+
+INSERT INTO memories_fts (memories_fts) VALUES ('rebuild');
+
+CREATE TRIGGER IF NOT EXISTS memories_fts_insert
+AFTER INSERT ON memories BEGIN
+    INSERT INTO memories_fts (rowid, content) VALUES (new.rowid, new.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS memories_fts_delete
+AFTER DELETE ON memories BEGIN
+    INSERT INTO memories_fts (memories_fts, rowid, content)
+    VALUES ('delete', old.rowid, old.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS memories_fts_update
+AFTER UPDATE OF content ON memories BEGIN
+    INSERT INTO memories_fts (memories_fts, rowid, content)
+    VALUES ('delete', old.rowid, old.content);
+    INSERT INTO memories_fts (rowid, content) VALUES (new.rowid, new.content);
+END;
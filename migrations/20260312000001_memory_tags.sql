@@ -0,0 +1,5 @@
+-- Tags on memories, as a JSON array of lowercase strings, for filtering
+-- searches.
+ALTER TABLE memories ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+
+CREATE INDEX IF NOT EXISTS idx_memories_source ON memories(source);
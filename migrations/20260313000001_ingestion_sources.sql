@@ -0,0 +1,19 @@
+-- Where each ingested chunk came from and the memories extracted from it,
+-- so a changed source only re-ingests the chunks that changed.
+CREATE TABLE IF NOT EXISTS ingestion_chunks (
+    source TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    memory_ids TEXT NOT NULL DEFAULT '[]',
+    ingested_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (source, content_hash)
+);
+
+-- Web pages ingested into memory and re-fetched when stale.
+CREATE TABLE IF NOT EXISTS web_sources (
+    url TEXT PRIMARY KEY,
+    content_hash TEXT,
+    added_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    checked_at TIMESTAMP,
+    changed_at TIMESTAMP
+);
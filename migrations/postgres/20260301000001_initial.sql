@@ -0,0 +1,165 @@
+-- Postgres schema, equivalent to the SQLite migrations in the parent
+-- directory up to 20260220000002_cron_run_once. Each agent gets its own
+-- schema; the connection's search_path points at it.
+--
+-- Integer columns read as i64 are BIGINT. channels.is_active is read as i32.
+-- Later schema changes need a migration in both directories.
+
+CREATE TABLE IF NOT EXISTS memories (
+    id TEXT PRIMARY KEY,
+    content TEXT NOT NULL,
+    memory_type TEXT NOT NULL,
+    importance REAL NOT NULL DEFAULT 0.5,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_accessed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    access_count BIGINT NOT NULL DEFAULT 0,
+    source TEXT,
+    channel_id TEXT,
+    forgotten BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS idx_memories_type ON memories(memory_type);
+CREATE INDEX IF NOT EXISTS idx_memories_importance ON memories(importance);
+CREATE INDEX IF NOT EXISTS idx_memories_forgotten ON memories(forgotten);
+CREATE INDEX IF NOT EXISTS idx_memories_created_at ON memories(created_at);
+
+CREATE TABLE IF NOT EXISTS associations (
+    id TEXT PRIMARY KEY,
+    source_id TEXT NOT NULL REFERENCES memories(id) ON DELETE CASCADE,
+    target_id TEXT NOT NULL REFERENCES memories(id) ON DELETE CASCADE,
+    relation_type TEXT NOT NULL,
+    weight REAL NOT NULL DEFAULT 0.5,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(source_id, target_id, relation_type)
+);
+
+CREATE INDEX IF NOT EXISTS idx_associations_source ON associations(source_id);
+CREATE INDEX IF NOT EXISTS idx_associations_target ON associations(target_id);
+
+CREATE TABLE IF NOT EXISTS conversation_messages (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    sender_name TEXT,
+    sender_id TEXT,
+    content TEXT NOT NULL,
+    metadata TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_messages_channel ON conversation_messages(channel_id);
+CREATE INDEX IF NOT EXISTS idx_messages_channel_time ON conversation_messages(channel_id, created_at);
+
+CREATE TABLE IF NOT EXISTS channels (
+    id TEXT PRIMARY KEY,
+    platform TEXT NOT NULL,
+    display_name TEXT,
+    platform_meta TEXT,
+    bulletin TEXT,
+    permissions TEXT,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_activity_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_channels_active ON channels(is_active, last_activity_at);
+CREATE INDEX IF NOT EXISTS idx_channels_platform ON channels(platform);
+
+CREATE TABLE IF NOT EXISTS cron_jobs (
+    id TEXT PRIMARY KEY,
+    prompt TEXT NOT NULL,
+    interval_secs BIGINT NOT NULL DEFAULT 3600,
+    delivery_target TEXT NOT NULL,
+    active_start_hour BIGINT,
+    active_end_hour BIGINT,
+    enabled BIGINT NOT NULL DEFAULT 1,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    timeout_secs BIGINT,
+    run_once BIGINT NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS cron_executions (
+    id TEXT PRIMARY KEY,
+    cron_id TEXT NOT NULL REFERENCES cron_jobs(id) ON DELETE CASCADE,
+    executed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    success BIGINT NOT NULL,
+    result_summary TEXT
+);
+
+CREATE TABLE IF NOT EXISTS branch_runs (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT NOT NULL REFERENCES channels(id) ON DELETE CASCADE,
+    description TEXT NOT NULL,
+    conclusion TEXT,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_branch_runs_channel ON branch_runs(channel_id, started_at);
+
+CREATE TABLE IF NOT EXISTS worker_runs (
+    id TEXT PRIMARY KEY,
+    channel_id TEXT REFERENCES channels(id) ON DELETE CASCADE,
+    task TEXT NOT NULL,
+    result TEXT,
+    status TEXT NOT NULL DEFAULT 'running',
+    started_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_worker_runs_channel ON worker_runs(channel_id, started_at);
+
+CREATE TABLE IF NOT EXISTS cortex_events (
+    id TEXT PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    details TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_cortex_events_type ON cortex_events(event_type, created_at);
+CREATE INDEX IF NOT EXISTS idx_cortex_events_created ON cortex_events(created_at);
+
+CREATE TABLE IF NOT EXISTS cortex_chat_messages (
+    id TEXT PRIMARY KEY,
+    thread_id TEXT NOT NULL DEFAULT 'default',
+    role TEXT NOT NULL,
+    content TEXT NOT NULL,
+    channel_context TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_cortex_chat_created ON cortex_chat_messages(created_at);
+CREATE INDEX IF NOT EXISTS idx_cortex_chat_thread ON cortex_chat_messages(thread_id, created_at);
+
+CREATE TABLE IF NOT EXISTS ingestion_progress (
+    content_hash TEXT NOT NULL,
+    chunk_index BIGINT NOT NULL,
+    total_chunks BIGINT NOT NULL,
+    filename TEXT NOT NULL,
+    completed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (content_hash, chunk_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_ingestion_progress_hash ON ingestion_progress(content_hash);
+
+CREATE TABLE IF NOT EXISTS ingestion_files (
+    content_hash TEXT PRIMARY KEY,
+    filename TEXT NOT NULL,
+    file_size BIGINT NOT NULL,
+    total_chunks BIGINT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'processing',
+    started_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS agent_profile (
+    agent_id TEXT PRIMARY KEY NOT NULL,
+    display_name TEXT,
+    status TEXT,
+    bio TEXT,
+    avatar_seed TEXT,
+    generated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,8 @@
+-- Entries already seen in watched RSS and Atom feeds, so each is posted once.
+CREATE TABLE IF NOT EXISTS feed_entries (
+    feed TEXT NOT NULL,
+    entry_id TEXT NOT NULL,
+    title TEXT,
+    seen_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (feed, entry_id)
+);
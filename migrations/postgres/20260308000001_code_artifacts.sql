@@ -0,0 +1,13 @@
+-- Code blocks and stack traces pasted into channels, numbered per channel.
+-- History keeps a reference; the artifact_read tool returns the content.
+CREATE TABLE IF NOT EXISTS code_artifacts (
+    channel_id TEXT NOT NULL,
+    number BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    language TEXT,
+    content TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (channel_id, number),
+    UNIQUE (channel_id, content_hash)
+);
@@ -0,0 +1,3 @@
+-- Full-text index over conversation messages, for `!search`.
+CREATE INDEX IF NOT EXISTS idx_messages_content_fts
+    ON conversation_messages USING GIN (to_tsvector('simple', content));
@@ -0,0 +1,7 @@
+-- Who each channel turn answered and what it used, for `!stats`.
+ALTER TABLE channel_turns ADD COLUMN sender_id TEXT;
+ALTER TABLE channel_turns ADD COLUMN input_tokens BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE channel_turns ADD COLUMN output_tokens BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE channel_turns ADD COLUMN cost_usd DOUBLE PRECISION NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS idx_channel_turns_created ON channel_turns(created_at);
@@ -0,0 +1,4 @@
+-- Keyword index over memories, searched alongside vector similarity in
+-- hybrid recall.
+CREATE INDEX IF NOT EXISTS idx_memories_content_fts
+    ON memories USING GIN (to_tsvector('simple', content));
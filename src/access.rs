@@ -0,0 +1,336 @@
+//! Role-based access control.
+//!
+//! Every sender has one [`Role`]: from an `[access.users]` entry, the highest
+//! role mapped from their Discord server roles, or the default role. Each
+//! role has a [`RolePolicy`] that decides whether it can run admin commands,
+//! which tools its turns get, and how many messages it may send per hour.
+
+use crate::InboundMessage;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Tools every role keeps, so a turn can always answer or stay silent.
+const ALWAYS_ALLOWED_TOOLS: &[&str] = &["reply", "skip"];
+
+/// Window for `messages_per_hour`.
+const QUOTA_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Message counts shared by every channel.
+static QUOTAS: LazyLock<Quotas> = LazyLock::new(Quotas::default);
+
+/// What a sender is to the instance, from least to most trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Guest,
+    Member,
+    Operator,
+    Admin,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Guest, Role::Member, Role::Operator, Role::Admin];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Guest => "guest",
+            Role::Member => "member",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Role::ALL
+            .into_iter()
+            .find(|role| role.as_str() == value)
+            .ok_or_else(|| {
+                format!("must be one of guest, member, operator, or admin, got '{value}'")
+            })
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What one role may do.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RolePolicy {
+    /// Whether chat admin commands (`!debug last`, `!jobs`, `!export`) work.
+    pub admin_commands: bool,
+    /// Tools this role's turns get. `None` means every tool.
+    pub allowed_tools: Option<Vec<String>>,
+    /// Tools taken away from this role's turns, even if allowed above.
+    pub denied_tools: Vec<String>,
+    /// Messages a sender may send per hour. `None` means no limit.
+    pub messages_per_hour: Option<u32>,
+}
+
+impl RolePolicy {
+    /// The policy a role has when config doesn't override it.
+    pub fn builtin(role: Role) -> Self {
+        match role {
+            Role::Admin => Self {
+                admin_commands: true,
+                allowed_tools: None,
+                denied_tools: Vec::new(),
+                messages_per_hour: None,
+            },
+            Role::Operator => Self {
+                admin_commands: true,
+                allowed_tools: None,
+                denied_tools: vec!["email".into()],
+                messages_per_hour: None,
+            },
+            Role::Member => Self {
+                admin_commands: false,
+                allowed_tools: None,
+                denied_tools: vec!["email".into()],
+                messages_per_hour: None,
+            },
+            Role::Guest => Self {
+                admin_commands: false,
+                allowed_tools: Some(
+                    ["reply", "skip", "react", "calculate"]
+                        .map(String::from)
+                        .to_vec(),
+                ),
+                denied_tools: Vec::new(),
+                messages_per_hour: Some(20),
+            },
+        }
+    }
+
+    pub fn allows_tool(&self, name: &str) -> bool {
+        if ALWAYS_ALLOWED_TOOLS.contains(&name) {
+            return true;
+        }
+        self.allowed_tools
+            .as_ref()
+            .is_none_or(|allowed| allowed.iter().any(|tool| tool == name))
+            && !self.denied_tools.iter().any(|tool| tool == name)
+    }
+}
+
+/// Who gets which role, and what each role may do (`[access]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessConfig {
+    /// Role for senders nothing else matches.
+    pub default_role: Role,
+    /// Roles by sender, as "platform:user_id". Takes precedence over roles
+    /// from the platform.
+    pub users: BTreeMap<String, Role>,
+    /// Roles by Discord server role ID. A sender with several gets the highest.
+    pub discord_roles: BTreeMap<String, Role>,
+    /// One policy per role.
+    pub policies: BTreeMap<Role, RolePolicy>,
+}
+
+impl Default for AccessConfig {
+    fn default() -> Self {
+        Self {
+            default_role: Role::Member,
+            users: BTreeMap::new(),
+            discord_roles: BTreeMap::new(),
+            policies: Role::ALL
+                .into_iter()
+                .map(|role| (role, RolePolicy::builtin(role)))
+                .collect(),
+        }
+    }
+}
+
+impl AccessConfig {
+    /// The role of a message's sender.
+    pub fn role_of(&self, message: &InboundMessage) -> Role {
+        let sender = format!("{}:{}", message.source, message.sender_id);
+        if let Some(role) = self.users.get(&sender) {
+            return *role;
+        }
+        message
+            .metadata
+            .get("discord_role_ids")
+            .and_then(|ids| ids.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.discord_roles.get(id.as_str()?))
+            .max()
+            .copied()
+            .unwrap_or(self.default_role)
+    }
+
+    pub fn policy(&self, role: Role) -> &RolePolicy {
+        // Resolution fills in every role, so this only falls back for
+        // hand-built configs.
+        static BUILTIN: LazyLock<AccessConfig> = LazyLock::new(AccessConfig::default);
+        self.policies
+            .get(&role)
+            .or_else(|| BUILTIN.policies.get(&role))
+            .expect("every role has a built-in policy")
+    }
+
+    /// Senders given the admin role, as "platform:user_id".
+    pub fn admins(&self) -> Vec<String> {
+        self.users
+            .iter()
+            .filter(|(_, role)| **role == Role::Admin)
+            .map(|(sender, _)| sender.clone())
+            .collect()
+    }
+}
+
+/// Outcome of counting a message against its sender's hourly quota.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaCheck {
+    Allowed,
+    /// Over the limit. `notify` is true only for the first message refused
+    /// since the sender was last allowed, so they're told once.
+    Exceeded {
+        notify: bool,
+    },
+}
+
+#[derive(Debug, Default)]
+struct SenderUsage {
+    sent: VecDeque<Instant>,
+    notified: bool,
+}
+
+/// Sliding one-hour message counts per sender, for `messages_per_hour`.
+#[derive(Debug, Default)]
+pub struct Quotas {
+    usage: Mutex<HashMap<String, SenderUsage>>,
+}
+
+impl Quotas {
+    /// The counts shared by every channel in the instance.
+    pub fn global() -> &'static Quotas {
+        &QUOTAS
+    }
+
+    /// Count one message from `sender` against `limit` messages per hour.
+    /// Refused messages don't count.
+    pub fn check(&self, sender: &str, limit: u32, now: Instant) -> QuotaCheck {
+        let mut usage = self.usage.lock().expect("quota lock poisoned");
+        let entry = usage.entry(sender.to_string()).or_default();
+        while entry
+            .sent
+            .front()
+            .is_some_and(|sent| now.duration_since(*sent) >= QUOTA_WINDOW)
+        {
+            entry.sent.pop_front();
+        }
+
+        if entry.sent.len() < limit as usize {
+            entry.sent.push_back(now);
+            entry.notified = false;
+            return QuotaCheck::Allowed;
+        }
+        let notify = !entry.notified;
+        entry.notified = true;
+        QuotaCheck::Exceeded { notify }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(source: &str, sender_id: &str, role_ids: &[&str]) -> InboundMessage {
+        let mut metadata = HashMap::new();
+        if !role_ids.is_empty() {
+            metadata.insert("discord_role_ids".into(), serde_json::json!(role_ids));
+        }
+        InboundMessage {
+            id: "1".into(),
+            source: source.into(),
+            conversation_id: format!("{source}:1"),
+            sender_id: sender_id.into(),
+            agent_id: None,
+            content: crate::MessageContent::Text("hi".into()),
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: None,
+        }
+    }
+
+    #[test]
+    fn roles_resolve_from_users_then_platform_roles_then_default() {
+        let access = AccessConfig {
+            default_role: Role::Guest,
+            users: BTreeMap::from([
+                ("discord:1".into(), Role::Admin),
+                ("discord:2".into(), Role::Member),
+            ]),
+            discord_roles: BTreeMap::from([
+                ("100".into(), Role::Member),
+                ("200".into(), Role::Operator),
+            ]),
+            ..AccessConfig::default()
+        };
+
+        assert_eq!(access.role_of(&message("discord", "1", &[])), Role::Admin);
+        // An explicit entry wins over a higher server role.
+        assert_eq!(
+            access.role_of(&message("discord", "2", &["200"])),
+            Role::Member
+        );
+        assert_eq!(
+            access.role_of(&message("discord", "3", &["100", "200"])),
+            Role::Operator
+        );
+        assert_eq!(access.role_of(&message("slack", "1", &[])), Role::Guest);
+        assert_eq!(access.admins(), vec!["discord:1"]);
+    }
+
+    #[test]
+    fn policies_gate_tools() {
+        let guest = RolePolicy::builtin(Role::Guest);
+        assert!(guest.allows_tool("reply"));
+        assert!(guest.allows_tool("calculate"));
+        assert!(!guest.allows_tool("spawn_worker"));
+
+        let member = RolePolicy::builtin(Role::Member);
+        assert!(member.allows_tool("spawn_worker"));
+        assert!(!member.allows_tool("email"));
+        assert!(RolePolicy::builtin(Role::Admin).allows_tool("email"));
+
+        let locked = RolePolicy {
+            allowed_tools: Some(Vec::new()),
+            ..RolePolicy::builtin(Role::Member)
+        };
+        assert!(locked.allows_tool("skip"));
+    }
+
+    #[test]
+    fn quotas_refuse_past_the_hourly_limit_and_notify_once() {
+        let quotas = Quotas::default();
+        let start = Instant::now();
+
+        assert_eq!(quotas.check("discord:1", 2, start), QuotaCheck::Allowed);
+        assert_eq!(quotas.check("discord:1", 2, start), QuotaCheck::Allowed);
+        assert_eq!(
+            quotas.check("discord:1", 2, start),
+            QuotaCheck::Exceeded { notify: true }
+        );
+        assert_eq!(
+            quotas.check("discord:1", 2, start),
+            QuotaCheck::Exceeded { notify: false }
+        );
+        assert_eq!(quotas.check("discord:2", 2, start), QuotaCheck::Allowed);
+
+        let later = start + QUOTA_WINDOW;
+        assert_eq!(quotas.check("discord:1", 2, later), QuotaCheck::Allowed);
+    }
+}
@@ -1,10 +1,17 @@
-//! Agent processes: channels, branches, workers, compactor, cortex.
+//! Agent processes: channels, branches, workers, compactor, cortex, digest.
 
 pub mod branch;
 pub mod channel;
 pub mod compactor;
 pub mod cortex;
 pub mod cortex_chat;
+pub mod digest;
 pub mod ingestion;
+pub mod jobs;
+pub mod output_guard;
+pub mod postprocess;
+pub mod selftest;
+pub mod snapshot;
 pub mod status;
+pub mod verification;
 pub mod worker;
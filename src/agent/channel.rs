@@ -2,12 +2,19 @@
 
 use crate::agent::branch::Branch;
 use crate::agent::compactor::Compactor;
+use crate::agent::output_guard::OutputGuard;
+use crate::agent::snapshot::{PromptLayers, PromptSnapshot, RoutingDecision};
 use crate::agent::status::StatusBlock;
 use crate::agent::worker::Worker;
+use crate::conversation::transcript::{Transcript, TranscriptFormat};
 use crate::conversation::{ChannelStore, ConversationLogger, ProcessRunLogger};
 use crate::error::{AgentError, Result};
 use crate::hooks::SpacebotHook;
 use crate::llm::SpacebotModel;
+use crate::llm::canary::{CanaryConfig, Cohort};
+use crate::llm::routing::RoutingConfig;
+use crate::logging::TurnLogs;
+use crate::prompts::PromptEngine;
 use crate::{
     AgentDeps, BranchId, ChannelId, InboundMessage, OutboundResponse, ProcessEvent, ProcessId,
     ProcessType, WorkerId,
@@ -24,6 +31,9 @@ use tokio::sync::broadcast;
 use tokio::sync::{RwLock, mpsc};
 use tracing::Instrument as _;
 
+/// Log lines `!debug last` replies with; earlier lines in the turn are elided.
+const DEBUG_REPLY_LINES: usize = 40;
+
 /// Shared state that channel tools need to act on the channel.
 ///
 /// Wrapped in Arc and passed to tools (branch, spawn_worker, route, cancel)
@@ -124,6 +134,15 @@ pub struct Channel {
     coalesce_buffer: Vec<InboundMessage>,
     /// Deadline for flushing the coalesce buffer.
     coalesce_deadline: Option<tokio::time::Instant>,
+    /// Correlation ID of the most recent turn, for `!debug last`.
+    last_correlation_id: Option<String>,
+    /// What the most recent LLM turn's prompt was built from, for `!snapshot`.
+    last_snapshot: Option<PromptSnapshot>,
+    /// Sender of the most recent user message; reminders set this turn are for them.
+    last_requester: Option<crate::reminders::Requester>,
+    /// Role of the sender(s) behind the most recent user message; decides
+    /// which tools a turn gets. `None` until someone writes.
+    last_role: Option<crate::access::Role>,
 }
 
 impl Channel {
@@ -154,9 +173,10 @@ impl Channel {
         let active_workers = Arc::new(RwLock::new(HashMap::new()));
         let (message_tx, message_rx) = mpsc::channel(64);
 
-        let conversation_logger = ConversationLogger::new(deps.sqlite_pool.clone());
-        let process_run_logger = ProcessRunLogger::new(deps.sqlite_pool.clone());
-        let channel_store = ChannelStore::new(deps.sqlite_pool.clone());
+        let conversation_logger = ConversationLogger::new(deps.sql_pool.clone())
+            .batched(deps.conversation_writes.clone());
+        let process_run_logger = ProcessRunLogger::new(deps.sql_pool.clone());
+        let channel_store = ChannelStore::new(deps.sql_pool.clone());
 
         let compactor = Compactor::new(id.clone(), deps.clone(), history.clone());
 
@@ -199,6 +219,10 @@ impl Channel {
             memory_persistence_branches: HashSet::new(),
             coalesce_buffer: Vec::new(),
             coalesce_deadline: None,
+            last_correlation_id: None,
+            last_snapshot: None,
+            last_requester: None,
+            last_role: None,
         };
 
         (channel, message_tx)
@@ -224,6 +248,18 @@ impl Channel {
 
             tokio::select! {
                 Some(message) = self.message_rx.recv() => {
+                    if self.handle_admin_command(&message).await {
+                        continue;
+                    }
+                    if self.handle_chat_command(&message).await {
+                        continue;
+                    }
+                    if self.handle_slash_command(&message).await {
+                        continue;
+                    }
+                    if self.over_quota(&message).await {
+                        continue;
+                    }
                     let config = self.deps.runtime_config.coalesce.load();
                     if self.should_coalesce(&message, &config) {
                         self.coalesce_buffer.push(message);
@@ -266,6 +302,616 @@ impl Channel {
         Ok(())
     }
 
+    /// Handle a chat admin command. Returns true if the message was one, in
+    /// which case it never reaches the LLM.
+    ///
+    /// `!debug last` replies with the log lines correlated with the channel's
+    /// previous turn; `!jobs` replies with the agent's job queue depth and
+    /// recent failures; `!export [markdown|html]` replies with the channel's
+    /// transcript as a file; `!snapshot` replies with what the previous LLM
+    /// turn's prompt was built from, as a JSON file; `!apikey
+    /// list|create|revoke` manages issued API keys; `!admin canary` compares
+    /// the running canary against the control, and `!admin canary rollback`
+    /// turns it off; `!admin selftest` smoke-tests every provider and the
+    /// database; `!admin loglevel [llm=debug|reset]` shows or changes log
+    /// levels; `!admin config [status|check|promote|revert|discard]` manages
+    /// the staged config. Only senders whose role has `admin_commands` get an
+    /// answer; the command is dropped for everyone else.
+    async fn handle_admin_command(&mut self, message: &InboundMessage) -> bool {
+        let crate::MessageContent::Text(text) = &message.content else {
+            return false;
+        };
+        let command = text.trim();
+        let export_format = command
+            .strip_prefix("!export")
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+            .map(str::trim);
+        let api_key_args = command
+            .strip_prefix("!apikey")
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+            .map(str::trim);
+        let log_level_args = command
+            .strip_prefix("!admin loglevel")
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+            .map(str::trim);
+        let config_args = command
+            .strip_prefix("!admin config")
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+            .map(str::trim);
+        if command != "!debug last"
+            && command != "!jobs"
+            && command != "!snapshot"
+            && command != "!admin ratelimits"
+            && command != "!admin models"
+            && command != "!admin canary"
+            && command != "!admin canary rollback"
+            && command != "!admin selftest"
+            && export_format.is_none()
+            && api_key_args.is_none()
+            && log_level_args.is_none()
+            && config_args.is_none()
+        {
+            return false;
+        }
+
+        let access = self.deps.runtime_config.access.load_full();
+        let role = access.role_of(message);
+        if !access.policy(role).admin_commands {
+            tracing::debug!(channel_id = %self.id, sender = %message.sender_id, %role, "ignoring admin command from a role without admin commands");
+            return true;
+        }
+
+        if command == "!admin selftest" {
+            // The checks can take minutes, so they run off the channel's loop.
+            let (deps, response_tx) = (self.deps.clone(), self.response_tx.clone());
+            let channel_id = self.id.clone();
+            tokio::spawn(async move {
+                let report = crate::agent::selftest::run(&deps).await;
+                let reply = OutboundResponse::Text(crate::agent::selftest::render(&report));
+                if let Err(error) = response_tx.send(reply).await {
+                    tracing::error!(%error, %channel_id, "failed to send self-test report");
+                }
+            });
+            return true;
+        }
+
+        if let Some(args) = config_args {
+            tracing::info!(channel_id = %self.id, sender = %message.sender_id, args, "staged config command");
+            // `check` runs the preflight checks, which can take a while.
+            let config_path = self.deps.runtime_config.instance_dir.join("config.toml");
+            let (args, response_tx) = (args.to_string(), self.response_tx.clone());
+            let channel_id = self.id.clone();
+            tokio::spawn(async move {
+                let reply = crate::config::stage::command(&config_path, &args).await;
+                if let Err(error) = response_tx.send(OutboundResponse::Text(reply)).await {
+                    tracing::error!(%error, %channel_id, "failed to send staged config reply");
+                }
+            });
+            return true;
+        }
+
+        let reply = if let Some(format) = export_format {
+            match self.export_transcript(format).await {
+                Ok(response) => response,
+                Err(error) => OutboundResponse::Text(error),
+            }
+        } else if let Some(args) = api_key_args {
+            OutboundResponse::Text(self.api_key_command(args))
+        } else if let Some(args) = log_level_args {
+            tracing::info!(channel_id = %self.id, sender = %message.sender_id, args, "log level command");
+            OutboundResponse::Text(crate::log_levels::command(args))
+        } else if command == "!snapshot" {
+            self.snapshot_reply()
+        } else if command == "!admin ratelimits" {
+            OutboundResponse::Text(crate::llm::cooldown::render(
+                &self.deps.llm_manager.rate_limit_snapshot(),
+            ))
+        } else if command == "!admin models" {
+            OutboundResponse::Text(crate::llm::discovery::render(
+                &self.deps.llm_manager.model_catalog().snapshot(),
+            ))
+        } else if command == "!admin canary" {
+            let routing = self.deps.runtime_config.routing.load();
+            OutboundResponse::Text(crate::llm::canary::render(
+                routing.canary.as_ref(),
+                self.deps
+                    .llm_manager
+                    .canary_stats()
+                    .comparison(&self.deps.agent_id)
+                    .as_ref(),
+            ))
+        } else if command == "!admin canary rollback" {
+            OutboundResponse::Text(self.canary_rollback().await)
+        } else if command == "!jobs" {
+            OutboundResponse::Text(match self.deps.jobs.stats(&self.deps.agent_id).await {
+                Ok(stats) => stats.render(&self.deps.agent_id),
+                Err(error) => format!("Can't read the job queue: {error}"),
+            })
+        } else {
+            OutboundResponse::Text(match &self.last_correlation_id {
+                Some(correlation_id) => crate::logging::render_slice(
+                    correlation_id,
+                    &TurnLogs::global().lines(correlation_id),
+                    DEBUG_REPLY_LINES,
+                ),
+                None => "No turns handled in this channel yet.".to_string(),
+            })
+        };
+        if let Err(error) = self.response_tx.send(reply).await {
+            tracing::error!(%error, channel_id = %self.id, "failed to send admin command reply");
+        }
+        true
+    }
+
+    /// The previous turn's snapshot as a JSON attachment for `!snapshot`.
+    fn snapshot_reply(&self) -> OutboundResponse {
+        let Some(snapshot) = &self.last_snapshot else {
+            return OutboundResponse::Text(
+                "No snapshot to show: no turns handled in this channel yet, or memory was \
+                 under pressure at the last one."
+                    .into(),
+            );
+        };
+        match snapshot.to_json() {
+            Ok(data) => OutboundResponse::File {
+                filename: snapshot.file_name(),
+                data,
+                mime_type: "application/json".into(),
+                caption: Some(format!(
+                    "Prompt snapshot of the last turn ({}, {} history messages, {} tool calls)",
+                    snapshot.routing.model,
+                    snapshot.history.len(),
+                    snapshot.tool_calls.len()
+                )),
+            },
+            Err(error) => OutboundResponse::Text(format!("Can't serialize the snapshot: {error}")),
+        }
+    }
+
+    /// Run `!admin canary rollback`: send every conversation back to the
+    /// control now, and set the canary's percent to 0 in config.toml so a
+    /// reload or restart doesn't bring it back.
+    async fn canary_rollback(&self) -> String {
+        let rc = &self.deps.runtime_config;
+        let routing = rc.routing.load();
+        let Some(canary) = routing.canary.as_ref().filter(|canary| canary.percent > 0) else {
+            return "No canary is running.".into();
+        };
+        let variant = canary.variant();
+        let mut rolled_back = (**routing).clone();
+        if let Some(canary) = &mut rolled_back.canary {
+            canary.percent = 0;
+        }
+        rc.routing.store(Arc::new(rolled_back));
+        drop(routing);
+
+        let config_path = rc.instance_dir.join("config.toml");
+        let agent_id = self.deps.agent_id.clone();
+        let persisted = tokio::task::spawn_blocking(move || -> anyhow::Result<Option<String>> {
+            let content = std::fs::read_to_string(&config_path)?;
+            let Some((content, table)) = crate::llm::canary::rollback_toml(&content, &agent_id)?
+            else {
+                return Ok(None);
+            };
+            // Write a sibling and rename over the file, so the config watcher
+            // never reads half of it.
+            let temp_path = config_path.with_extension("toml.tmp");
+            std::fs::write(&temp_path, content)?;
+            std::fs::rename(&temp_path, &config_path)?;
+            Ok(Some(table))
+        })
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|result| result);
+        match persisted {
+            Ok(Some(table)) => {
+                format!("Rolled back the canary ({variant}): set percent = 0 in [{table}].")
+            }
+            Ok(None) => format!(
+                "Rolled back the canary ({variant}) until the next restart: config.toml has no \
+                 canary for this agent."
+            ),
+            Err(error) => format!(
+                "Rolled back the canary ({variant}) until the next restart: can't update \
+                 config.toml: {error}"
+            ),
+        }
+    }
+
+    /// Run `!apikey list`, `!apikey create <name> [scopes] [days]`, or
+    /// `!apikey revoke <id>`. Keys are only created in DMs, since the reply
+    /// shows the key to everyone who can read the conversation.
+    fn api_key_command(&self, args: &str) -> String {
+        const USAGE: &str = "Usage: !apikey list | !apikey create <name> [websocket,webhook,proxy] [days] | !apikey revoke <id>";
+        let store = crate::api_keys::ApiKeyStore::new(&self.deps.runtime_config.instance_dir);
+        let words: Vec<&str> = args.split_whitespace().collect();
+        let result = match words.as_slice() {
+            ["list"] => store
+                .list()
+                .map(|keys| crate::api_keys::render(&keys, chrono::Utc::now())),
+            ["create", name, rest @ ..] if rest.len() <= 2 => {
+                if !self.is_dm() {
+                    return "Create API keys in a DM, so the key isn't shown to the whole channel."
+                        .into();
+                }
+                let (scopes, days) = match rest {
+                    [] => ("", None),
+                    [value] if value.parse::<u32>().is_ok() => ("", Some(*value)),
+                    [scopes] => (*scopes, None),
+                    [scopes, days, ..] => (*scopes, Some(*days)),
+                };
+                let scopes = match crate::api_keys::parse_scopes(scopes) {
+                    Ok(scopes) => scopes,
+                    Err(error) => return error,
+                };
+                let expires_at = match days.map(str::parse::<u32>) {
+                    None => None,
+                    Some(Ok(days)) => {
+                        match crate::api_keys::expiry_after_days(chrono::Utc::now(), days) {
+                            Ok(expires_at) => Some(expires_at),
+                            Err(error) => return error,
+                        }
+                    }
+                    Some(Err(_)) => return USAGE.into(),
+                };
+                store.create(name, scopes, expires_at).map(|issued| {
+                    format!(
+                        "Issued key {} for {}. Store it now; it can't be shown again:\n{}",
+                        issued.record.id, issued.record.name, issued.key
+                    )
+                })
+            }
+            ["revoke", id] => store.revoke(id).map(|record| match record {
+                Some(record) => format!("Revoked key {} ({}).", record.id, record.name),
+                None => format!("No API key with ID '{id}'."),
+            }),
+            _ => return USAGE.into(),
+        };
+        result.unwrap_or_else(|error| format!("Can't manage API keys: {error}"))
+    }
+
+    /// Count a message against its sender's hourly quota. Returns true if the
+    /// sender is over it, in which case the message is dropped; the first
+    /// dropped message gets a reply saying so.
+    async fn over_quota(&self, message: &InboundMessage) -> bool {
+        if message.source == "system" {
+            return false;
+        }
+        let access = self.deps.runtime_config.access.load_full();
+        let role = access.role_of(message);
+        let Some(limit) = access.policy(role).messages_per_hour else {
+            return false;
+        };
+
+        let sender = format!("{}:{}", message.source, message.sender_id);
+        match crate::access::Quotas::global().check(&sender, limit, std::time::Instant::now()) {
+            crate::access::QuotaCheck::Allowed => false,
+            crate::access::QuotaCheck::Exceeded { notify } => {
+                tracing::debug!(channel_id = %self.id, %sender, %role, limit, "dropping message over hourly quota");
+                if notify {
+                    let text = format!(
+                        "You've reached the limit of {limit} messages per hour. Try again later."
+                    );
+                    if let Err(error) = self.response_tx.send(OutboundResponse::Text(text)).await {
+                        tracing::error!(%error, channel_id = %self.id, "failed to send quota notice");
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    /// Handle a command anyone in the conversation can run, without an LLM
+    /// turn. Returns false for any other message.
+    ///
+    /// `!search <query>` replies with the channel's earlier messages that
+    /// match; `!stats [day|week|month|all]` replies with usage per sender in
+    /// the channel, plus usage per channel for roles with `admin_commands`;
+    /// `!kb [filters] <query>` replies with the agent's best matching
+    /// memories, for roles allowed `memory_recall`; `!version` replies with
+    /// the version, commit, and build time, plus any newer release for roles
+    /// with `admin_commands`.
+    async fn handle_chat_command(&mut self, message: &InboundMessage) -> bool {
+        let crate::MessageContent::Text(text) = &message.content else {
+            return false;
+        };
+        let command = text.trim();
+        let argument = |name: &str| {
+            command
+                .strip_prefix(name)
+                .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+                .map(str::trim)
+        };
+
+        let reply = if let Some(query) = argument("!search") {
+            self.search_reply(query).await
+        } else if let Some(window) = argument("!stats") {
+            let access = self.deps.runtime_config.access.load_full();
+            let admin = access.policy(access.role_of(message)).admin_commands;
+            self.stats_reply(window, admin).await
+        } else if let Some(argument) = argument("!kb") {
+            let access = self.deps.runtime_config.access.load_full();
+            if access
+                .policy(access.role_of(message))
+                .allows_tool("memory_recall")
+            {
+                self.kb_reply(argument).await
+            } else {
+                "`!kb` isn't available to you.".into()
+            }
+        } else if argument("!version").is_some() {
+            let access = self.deps.runtime_config.access.load_full();
+            let update = access
+                .policy(access.role_of(message))
+                .admin_commands
+                .then(crate::update::latest_status)
+                .flatten();
+            crate::update::version_text(&crate::update::build_info(), update.as_deref())
+        } else {
+            return false;
+        };
+        if let Err(error) = self.response_tx.send(OutboundResponse::Text(reply)).await {
+            tracing::error!(%error, channel_id = %self.id, "failed to send chat command reply");
+        }
+        true
+    }
+
+    /// The `!search` reply for `query`.
+    async fn search_reply(&self, query: &str) -> String {
+        if query.is_empty() {
+            return "Usage: !search <words to look for>".into();
+        }
+        match crate::conversation::search::search(
+            &self.state.conversation_logger,
+            &self.deps.memory_search.embedding_model(),
+            &self.id,
+            query,
+        )
+        .await
+        {
+            Ok(hits) => {
+                let platform = self.id.split(':').next().unwrap_or_default();
+                crate::conversation::search::render(query, &hits, platform)
+            }
+            Err(error) => {
+                tracing::warn!(%error, channel_id = %self.id, "conversation search failed");
+                format!("Search failed: {error}")
+            }
+        }
+    }
+
+    /// The `!kb` reply for `argument`: filter terms, like `tag:runbook
+    /// during:2024`, and the words to search the agent's memories for.
+    async fn kb_reply(&self, argument: &str) -> String {
+        use crate::memory::MemoryFilter;
+        use crate::memory::search::{SearchConfig, SearchMode};
+
+        const USAGE: &str = "Usage: !kb [source:<prefix> channel:<id> tag:<tag> \
+                             after:<date> before:<date> during:<date>] <query>";
+        const RESULTS: usize = 5;
+        const SNIPPET_CHARS: usize = 300;
+
+        let (filter, query) = match MemoryFilter::extract(argument) {
+            Ok(parsed) => parsed,
+            Err(error) => return format!("Invalid filter: {error}\n{USAGE}"),
+        };
+        if query.is_empty() {
+            return USAGE.into();
+        }
+        let config = SearchConfig {
+            mode: SearchMode::Hybrid,
+            max_results: RESULTS,
+            filter,
+            ..SearchConfig::default()
+        };
+        let results = match self.deps.memory_search.search(&query, &config).await {
+            Ok(results) => results,
+            Err(error) => {
+                tracing::warn!(%error, channel_id = %self.id, "knowledge base search failed");
+                return format!("Search failed: {error}");
+            }
+        };
+        if results.is_empty() {
+            return format!("No memories match \"{query}\".");
+        }
+
+        let mut lines = vec![format!("Memories matching \"{query}\":")];
+        for (index, result) in results.iter().take(RESULTS).enumerate() {
+            let memory = &result.memory;
+            let mut snippet: String = memory.content.chars().take(SNIPPET_CHARS).collect();
+            if snippet.len() < memory.content.len() {
+                snippet.push('…');
+            }
+            let mut line = format!(
+                "{}. {}, {}",
+                index + 1,
+                memory.memory_type,
+                memory.created_at.format("%Y-%m-%d")
+            );
+            if let Some(source) = &memory.source {
+                line.push_str(&format!(", {source}"));
+            }
+            if !memory.tags.is_empty() {
+                line.push_str(&format!(" [{}]", memory.tags.join(", ")));
+            }
+            line.push_str(&format!(": {}", snippet.replace('\n', " ")));
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+
+    /// The `!stats` reply for `window`. Admins also see the busiest channels.
+    async fn stats_reply(&self, window: &str, admin: bool) -> String {
+        use crate::conversation::stats::{StatsWindow, UsageStats};
+
+        let Some(window) = StatsWindow::parse(window) else {
+            return "Usage: !stats [day|week|month|all]".into();
+        };
+        let since = window.since(chrono::Utc::now());
+        let stats = UsageStats::new(self.deps.sql_pool.clone());
+        let (senders, channels) =
+            match tokio::try_join!(stats.by_sender(&self.id, since), stats.by_channel(since)) {
+                Ok(tallies) => tallies,
+                Err(error) => {
+                    tracing::warn!(%error, channel_id = %self.id, "failed to compute usage stats");
+                    return format!("Can't compute usage stats: {error}");
+                }
+            };
+        let channel = channels.iter().find(|channel| channel.key == *self.id);
+        let channels = admin.then_some(&channels[..]);
+        crate::conversation::stats::render(window, channel, &senders, channels)
+    }
+
+    /// Run a slash command: call the named tool directly and reply with its
+    /// output, without an LLM turn. Returns `false` for any other message.
+    async fn handle_slash_command(&mut self, message: &InboundMessage) -> bool {
+        let crate::MessageContent::Command { name, args } = &message.content else {
+            return false;
+        };
+        if let Some(requester) = crate::reminders::Requester::from_message(message) {
+            self.last_requester = Some(requester);
+        }
+        let access = self.deps.runtime_config.access.load_full();
+        let role = access.role_of(message);
+        self.last_role = Some(role);
+
+        let sender_name = message
+            .metadata
+            .get("sender_display_name")
+            .and_then(|value| value.as_str())
+            .unwrap_or(&message.sender_id);
+        let invocation = message.content.to_string();
+        self.state.conversation_logger.log_user_message(
+            &self.state.channel_id,
+            sender_name,
+            &message.sender_id,
+            &invocation,
+            &message.metadata,
+        );
+
+        let reply = if !self.deps.runtime_config.slash_commands.contains(name) {
+            tracing::debug!(channel_id = %self.id, command = %name, "ignoring slash command for a tool that isn't enabled");
+            Some(format!("`/{name}` isn't available here."))
+        } else if !access.policy(role).allows_tool(name) {
+            tracing::debug!(channel_id = %self.id, command = %name, %role, "ignoring slash command the sender's role doesn't allow");
+            Some(format!("`/{name}` isn't available to you."))
+        } else {
+            match self
+                .call_command_tool(name, args, &message.conversation_id)
+                .await
+            {
+                Ok(reply) => reply,
+                Err(error) => {
+                    tracing::warn!(%error, channel_id = %self.id, command = %name, "slash command failed");
+                    Some(format!("`/{name}` failed: {error}"))
+                }
+            }
+        };
+        let Some(text) = reply else {
+            return true;
+        };
+
+        self.state
+            .conversation_logger
+            .log_bot_message(&self.state.channel_id, &text);
+        // Keep the exchange in history so later turns can refer to it.
+        {
+            let mut history = self.state.history.write().await;
+            history.push(rig::message::Message::user(format!(
+                "{sender_name} ran {invocation}"
+            )));
+            history.push(rig::message::Message::assistant(text.clone()));
+        }
+        if let Err(error) = self.response_tx.send(OutboundResponse::Text(text)).await {
+            tracing::error!(%error, channel_id = %self.id, "failed to send slash command reply");
+        }
+        true
+    }
+
+    /// Call one of the per-turn tools outside an LLM turn. Returns the reply
+    /// text, or `None` when the tool already responded on its own.
+    async fn call_command_tool(
+        &self,
+        name: &str,
+        args: &serde_json::Map<String, serde_json::Value>,
+        conversation_id: &str,
+    ) -> std::result::Result<Option<String>, String> {
+        let skip_flag = crate::tools::new_skip_flag();
+        let replied_flag = crate::tools::new_replied_flag();
+        self.add_turn_tools(conversation_id, &skip_flag, &replied_flag)
+            .await
+            .map_err(|error| error.to_string())?;
+        let result = self
+            .tool_server
+            .call_tool(name, &serde_json::Value::Object(args.clone()).to_string())
+            .await;
+        if let Err(error) = crate::tools::remove_channel_tools(&self.tool_server).await {
+            tracing::warn!(%error, "failed to remove channel tools");
+        }
+
+        let output = result.map_err(|error| error.to_string())?;
+        let responded = skip_flag.load(std::sync::atomic::Ordering::Relaxed)
+            || replied_flag.load(std::sync::atomic::Ordering::Relaxed);
+        Ok((!responded).then(|| crate::messaging::commands::render_output(&output)))
+    }
+
+    /// Slash commands for the tools in `defaults.slash_commands`, typed from
+    /// this channel's per-turn tool definitions. Takes a channel that never
+    /// runs; it only exists to register the tools once at startup.
+    pub async fn slash_commands(mut self) -> Vec<crate::messaging::commands::SlashCommand> {
+        // Tools that act for the sender (reminders, saved weather locations)
+        // only register with one, so stand one in.
+        self.last_requester = Some(crate::reminders::Requester {
+            sender_id: String::new(),
+            mention: String::new(),
+        });
+        let skip_flag = crate::tools::new_skip_flag();
+        let replied_flag = crate::tools::new_replied_flag();
+        if let Err(error) = self
+            .add_turn_tools(&self.id, &skip_flag, &replied_flag)
+            .await
+        {
+            tracing::warn!(%error, "failed to register tools for slash commands");
+            return Vec::new();
+        }
+        let definitions = self
+            .tool_server
+            .get_tool_defs(None)
+            .await
+            .unwrap_or_else(|error| {
+                tracing::warn!(%error, "failed to read tool definitions for slash commands");
+                Vec::new()
+            });
+        let _ = crate::tools::remove_channel_tools(&self.tool_server).await;
+
+        crate::messaging::commands::catalog(&definitions, &self.deps.runtime_config.slash_commands)
+    }
+
+    /// Render this channel's transcript as a file attachment for `!export`.
+    /// `format` is `markdown` (the default) or `html`; errors are the reply text.
+    async fn export_transcript(
+        &self,
+        format: &str,
+    ) -> std::result::Result<OutboundResponse, String> {
+        let format: TranscriptFormat = if format.is_empty() {
+            TranscriptFormat::default()
+        } else {
+            format.parse()?
+        };
+        let transcript = Transcript::load(&self.deps.sql_pool, &self.deps.agent_id, &self.id)
+            .await
+            .map_err(|error| format!("Can't load the transcript: {error}"))?;
+        Ok(OutboundResponse::File {
+            filename: transcript.file_name(format),
+            data: transcript.render(format).into_bytes(),
+            mime_type: format.mime_type().to_string(),
+            caption: Some(format!(
+                "Transcript of this channel ({} entries)",
+                transcript.entries.len()
+            )),
+        })
+    }
+
     /// Determine if a message should be coalesced (batched with other messages).
     ///
     /// Returns false for:
@@ -365,8 +1011,22 @@ impl Channel {
     /// Formats all messages with attribution and timestamps, persists each
     /// individually to conversation history, then presents them as one user turn
     /// with a coalesce hint telling the LLM this is a fast-moving conversation.
-    #[tracing::instrument(skip(self, messages), fields(channel_id = %self.id, agent_id = %self.deps.agent_id, message_count = messages.len()))]
-    async fn handle_message_batch(&mut self, messages: Vec<InboundMessage>) -> Result<()> {
+    #[tracing::instrument(skip(self, messages), fields(channel_id = %self.id, agent_id = %self.deps.agent_id, message_count = messages.len(), correlation_id = tracing::field::Empty))]
+    async fn handle_message_batch(&mut self, mut messages: Vec<InboundMessage>) -> Result<()> {
+        let received_at = std::time::Instant::now();
+        // The turn logs under the newest message's ID; the others are listed
+        // so their logs can still be found.
+        let correlation_ids: Vec<String> = messages
+            .iter_mut()
+            .map(|message| crate::logging::ensure_correlation_id(&mut message.metadata))
+            .collect();
+        let correlation_id = correlation_ids
+            .last()
+            .cloned()
+            .unwrap_or_else(crate::logging::new_correlation_id);
+        tracing::Span::current().record("correlation_id", correlation_id.as_str());
+        self.last_correlation_id = Some(correlation_id);
+
         let message_count = messages.len();
         let first_timestamp = messages
             .first()
@@ -383,9 +1043,28 @@ impl Channel {
             channel_id = %self.id,
             message_count,
             elapsed_secs,
+            ?correlation_ids,
             "handling batched messages"
         );
 
+        if let Some(requester) = messages
+            .iter()
+            .rev()
+            .find_map(crate::reminders::Requester::from_message)
+        {
+            self.last_requester = Some(requester);
+        }
+        // A batch gets the tools of its least trusted sender.
+        let access = self.deps.runtime_config.access.load_full();
+        if let Some(role) = messages
+            .iter()
+            .filter(|message| message.source != "system")
+            .map(|message| access.role_of(message))
+            .min()
+        {
+            self.last_role = Some(role);
+        }
+
         // Count unique senders for the hint
         let unique_senders: std::collections::HashSet<_> =
             messages.iter().map(|m| &m.sender_id).collect();
@@ -401,7 +1080,7 @@ impl Channel {
         // Capture conversation context from the first message
         if self.conversation_context.is_none() {
             if let Some(first) = messages.first() {
-                let prompt_engine = self.deps.runtime_config.prompts.load();
+                let prompt_engine = self.prompt_engine();
                 let server_name = first
                     .metadata
                     .get("discord_guild_name")
@@ -448,7 +1127,8 @@ impl Channel {
                         (text.clone().unwrap_or_default(), attachments.clone())
                     }
                     // Render interactions as their Display form so the LLM sees plain text.
-                    crate::MessageContent::Interaction { .. } => {
+                    crate::MessageContent::Interaction { .. }
+                    | crate::MessageContent::Command { .. } => {
                         (message.content.to_string(), Vec::new())
                     }
                 };
@@ -463,6 +1143,7 @@ impl Channel {
                 self.state
                     .channel_store
                     .upsert(&message.conversation_id, &message.metadata);
+                crate::alerts::check_message(&self.deps, message, &raw_text);
 
                 conversation_id = message.conversation_id.clone();
 
@@ -485,8 +1166,12 @@ impl Channel {
                     .and_then(|v| v.as_str())
                     .unwrap_or(&message.sender_id);
 
-                let formatted_text =
+                let mut formatted_text =
                     format!("[{}] ({}): {}", display_name, relative_text, raw_text);
+                if let Some(note) = calculator_note(&self.deps.runtime_config, &raw_text) {
+                    formatted_text.push('\n');
+                    formatted_text.push_str(&note);
+                }
 
                 // Download attachments for this message
                 if !attachments.is_empty() {
@@ -516,22 +1201,28 @@ impl Channel {
         );
 
         // Build system prompt with coalesce hint
-        let system_prompt = self
-            .build_system_prompt_with_coalesce(message_count, elapsed_secs, unique_sender_count)
-            .await;
+        let elapsed_str = format!("{:.1}s", elapsed_secs);
+        let coalesce_hint = self
+            .prompt_engine()
+            .render_coalesce_hint(message_count, &elapsed_str, unique_sender_count)
+            .ok();
+        let prompt_layers = self.build_prompt_layers(coalesce_hint).await;
 
         // Run agent turn with any image/audio attachments preserved
-        let (result, skip_flag, replied_flag) = self
+        let (result, skip_flag, replied_flag, output_guard) = self
             .run_agent_turn(
                 &combined_text,
-                &system_prompt,
+                prompt_layers,
                 &conversation_id,
                 attachment_parts,
             )
             .await?;
 
-        self.handle_agent_result(result, &skip_flag, &replied_flag)
+        self.handle_agent_result(result, &skip_flag, &replied_flag, &output_guard)
             .await;
+        self.deps
+            .llm_manager
+            .record_latency(crate::llm::slo::CHANNEL_REPLY, received_at.elapsed());
         // Check compaction
         if let Err(error) = self.compactor.check_and_compact().await {
             tracing::warn!(channel_id = %self.id, %error, "compaction check failed");
@@ -544,64 +1235,19 @@ impl Channel {
         Ok(())
     }
 
-    /// Build system prompt with coalesce hint for batched messages.
-    async fn build_system_prompt_with_coalesce(
-        &self,
-        message_count: usize,
-        elapsed_secs: f64,
-        unique_senders: usize,
-    ) -> String {
-        let rc = &self.deps.runtime_config;
-        let prompt_engine = rc.prompts.load();
-
-        let identity_context = rc.identity.load().render();
-        let memory_bulletin = rc.memory_bulletin.load();
-        let skills = rc.skills.load();
-        let skills_prompt = skills.render_channel_prompt(&prompt_engine);
-
-        let browser_enabled = rc.browser_config.load().enabled;
-        let web_search_enabled = rc.brave_search_key.load().is_some();
-        let opencode_enabled = rc.opencode.load().enabled;
-        let worker_capabilities = prompt_engine
-            .render_worker_capabilities(browser_enabled, web_search_enabled, opencode_enabled)
-            .expect("failed to render worker capabilities");
-
-        let status_text = {
-            let status = self.state.status_block.read().await;
-            status.render()
-        };
-
-        // Render coalesce hint
-        let elapsed_str = format!("{:.1}s", elapsed_secs);
-        let coalesce_hint = prompt_engine
-            .render_coalesce_hint(message_count, &elapsed_str, unique_senders)
-            .ok();
-
-        let available_channels = self.build_available_channels().await;
-
-        let empty_to_none = |s: String| if s.is_empty() { None } else { Some(s) };
-
-        prompt_engine
-            .render_channel_prompt(
-                empty_to_none(identity_context),
-                empty_to_none(memory_bulletin.to_string()),
-                empty_to_none(skills_prompt),
-                worker_capabilities,
-                self.conversation_context.clone(),
-                empty_to_none(status_text),
-                coalesce_hint,
-                available_channels,
-            )
-            .expect("failed to render channel prompt")
-    }
-
     /// Handle an incoming message by running the channel's LLM agent loop.
     ///
     /// The LLM decides which tools to call: reply (to respond), branch (to think),
     /// spawn_worker (to delegate), route (to follow up with a worker), cancel, or
     /// memory_save. The tools act on the channel's shared state directly.
-    #[tracing::instrument(skip(self, message), fields(channel_id = %self.id, agent_id = %self.deps.agent_id, message_id = %message.id))]
-    async fn handle_message(&mut self, message: InboundMessage) -> Result<()> {
+    #[tracing::instrument(skip(self, message), fields(channel_id = %self.id, agent_id = %self.deps.agent_id, message_id = %message.id, correlation_id = tracing::field::Empty))]
+    async fn handle_message(&mut self, mut message: InboundMessage) -> Result<()> {
+        let received_at = std::time::Instant::now();
+        // Re-trigger messages are created in-process and arrive without an ID.
+        let correlation_id = crate::logging::ensure_correlation_id(&mut message.metadata);
+        tracing::Span::current().record("correlation_id", correlation_id.as_str());
+        self.last_correlation_id = Some(correlation_id);
+
         tracing::info!(
             channel_id = %self.id,
             message_id = %message.id,
@@ -612,6 +1258,10 @@ impl Channel {
         if self.conversation_id.is_none() {
             self.conversation_id = Some(message.conversation_id.clone());
         }
+        if let Some(requester) = crate::reminders::Requester::from_message(&message) {
+            self.last_requester = Some(requester);
+            self.last_role = Some(self.deps.runtime_config.access.load().role_of(&message));
+        }
 
         let (raw_text, attachments) = match &message.content {
             crate::MessageContent::Text(text) => (text.clone(), Vec::new()),
@@ -619,10 +1269,18 @@ impl Channel {
                 (text.clone().unwrap_or_default(), attachments.clone())
             }
             // Render interactions as their Display form so the LLM sees plain text.
-            crate::MessageContent::Interaction { .. } => (message.content.to_string(), Vec::new()),
+            crate::MessageContent::Interaction { .. } | crate::MessageContent::Command { .. } => {
+                (message.content.to_string(), Vec::new())
+            }
         };
 
-        let user_text = format_user_message(&raw_text, &message);
+        let mut user_text = format_user_message(&raw_text, &message);
+        if message.source != "system"
+            && let Some(note) = calculator_note(&self.deps.runtime_config, &raw_text)
+        {
+            user_text.push_str("\n\n");
+            user_text.push_str(&note);
+        }
 
         let attachment_content = if !attachments.is_empty() {
             download_attachments(&self.deps, &attachments).await
@@ -647,11 +1305,12 @@ impl Channel {
             self.state
                 .channel_store
                 .upsert(&message.conversation_id, &message.metadata);
+            crate::alerts::check_message(&self.deps, &message, &raw_text);
         }
 
         // Capture conversation context from the first message (platform, channel, server)
         if self.conversation_context.is_none() {
-            let prompt_engine = self.deps.runtime_config.prompts.load();
+            let prompt_engine = self.prompt_engine();
             let server_name = message
                 .metadata
                 .get("discord_guild_name")
@@ -679,19 +1338,24 @@ impl Channel {
             );
         }
 
-        let system_prompt = self.build_system_prompt().await;
+        let prompt_layers = self.build_prompt_layers(None).await;
 
-        let (result, skip_flag, replied_flag) = self
+        let (result, skip_flag, replied_flag, output_guard) = self
             .run_agent_turn(
                 &user_text,
-                &system_prompt,
+                prompt_layers,
                 &message.conversation_id,
                 attachment_content,
             )
             .await?;
 
-        self.handle_agent_result(result, &skip_flag, &replied_flag)
+        self.handle_agent_result(result, &skip_flag, &replied_flag, &output_guard)
             .await;
+        if message.source != "system" {
+            self.deps
+                .llm_manager
+                .record_latency(crate::llm::slo::CHANNEL_REPLY, received_at.elapsed());
+        }
 
         // Check context size and trigger compaction if needed
         if let Err(error) = self.compactor.check_and_compact().await {
@@ -740,26 +1404,58 @@ impl Channel {
             return None;
         }
 
-        let prompt_engine = self.deps.runtime_config.prompts.load();
-        prompt_engine.render_available_channels(entries).ok()
+        self.prompt_engine().render_available_channels(entries).ok()
     }
 
-    /// Assemble the full system prompt using the PromptEngine.
-    async fn build_system_prompt(&self) -> String {
-        let rc = &self.deps.runtime_config;
-        let prompt_engine = rc.prompts.load();
+    /// The guided flows section: available flows and this channel's active run.
+    async fn render_flows_prompt(&self) -> String {
+        let flows = self.deps.runtime_config.flows.load();
+        if flows.is_empty() {
+            return String::new();
+        }
+        let active = crate::flows::FlowStore::new(self.deps.sql_pool.clone())
+            .active(&self.id)
+            .await
+            .unwrap_or_else(|error| {
+                tracing::warn!(channel_id = %self.id, %error, "failed to load active flow");
+                None
+            });
+        flows.render_channel_prompt(active.as_ref())
+    }
 
-        let identity_context = rc.identity.load().render();
-        let memory_bulletin = rc.memory_bulletin.load();
-        let skills = rc.skills.load();
-        let skills_prompt = skills.render_channel_prompt(&prompt_engine);
+    /// The running canary and which side of it this conversation is on.
+    fn canary<'a>(&self, routing: &'a RoutingConfig) -> Option<(&'a CanaryConfig, Cohort)> {
+        routing
+            .canary
+            .as_ref()
+            .filter(|canary| canary.percent > 0)
+            .map(|canary| (canary, canary.cohort(&self.id)))
+    }
 
-        let browser_enabled = rc.browser_config.load().enabled;
-        let web_search_enabled = rc.brave_search_key.load().is_some();
-        let opencode_enabled = rc.opencode.load().enabled;
-        let worker_capabilities = prompt_engine
-            .render_worker_capabilities(browser_enabled, web_search_enabled, opencode_enabled)
-            .expect("failed to render worker capabilities");
+    /// The prompt templates for this conversation: the canary prompt
+    /// version's if it's in a canary that changes prompts.
+    fn prompt_engine(&self) -> Arc<PromptEngine> {
+        let rc = &self.deps.runtime_config;
+        let routing = rc.routing.load();
+        let version = match self.canary(&routing) {
+            Some((canary, Cohort::Canary)) => canary.prompts.as_deref(),
+            _ => None,
+        };
+        let Some(version) = version else {
+            return rc.prompts.load_full();
+        };
+        rc.canary_prompts(version).unwrap_or_else(|error| {
+            tracing::warn!(channel_id = %self.id, %version, %error, "can't build canary prompts, using the control's");
+            rc.prompts.load_full()
+        })
+    }
+
+    /// Gather the sections of the system prompt. `coalesce_hint` is only set
+    /// for batched messages.
+    async fn build_prompt_layers(&self, coalesce_hint: Option<String>) -> PromptLayers {
+        let rc = &self.deps.runtime_config;
+        let prompt_engine = self.prompt_engine();
+        let flows_prompt = self.render_flows_prompt().await;
 
         let status_text = {
             let status = self.state.status_block.read().await;
@@ -770,37 +1466,72 @@ impl Channel {
 
         let empty_to_none = |s: String| if s.is_empty() { None } else { Some(s) };
 
-        prompt_engine
-            .render_channel_prompt(
-                empty_to_none(identity_context),
-                empty_to_none(memory_bulletin.to_string()),
-                empty_to_none(skills_prompt),
-                worker_capabilities,
-                self.conversation_context.clone(),
-                empty_to_none(status_text),
-                None, // coalesce_hint - only set for batched messages
-                available_channels,
-            )
-            .expect("failed to render channel prompt")
+        PromptLayers {
+            flows: empty_to_none(flows_prompt),
+            conversation_context: self.conversation_context.clone(),
+            status: empty_to_none(status_text),
+            coalesce_hint,
+            available_channels,
+            ..PromptLayers::from_config(rc, &prompt_engine)
+        }
     }
 
-    /// Register per-turn tools, run the LLM agentic loop, and clean up.
-    ///
-    /// Returns the prompt result and skip flag for the caller to dispatch.
-    #[tracing::instrument(skip(self, user_text, system_prompt, attachment_content), fields(channel_id = %self.id, agent_id = %self.deps.agent_id))]
-    async fn run_agent_turn(
+    /// Register the per-turn tools on the channel's ToolServer. The caller
+    /// removes them with `remove_channel_tools()` when the turn ends.
+    async fn add_turn_tools(
         &self,
-        user_text: &str,
-        system_prompt: &str,
         conversation_id: &str,
-        attachment_content: Vec<UserContent>,
-    ) -> Result<(
-        std::result::Result<String, rig::completion::PromptError>,
-        crate::tools::SkipFlag,
-        crate::tools::RepliedFlag,
-    )> {
-        let skip_flag = crate::tools::new_skip_flag();
-        let replied_flag = crate::tools::new_replied_flag();
+        skip_flag: &crate::tools::SkipFlag,
+        replied_flag: &crate::tools::RepliedFlag,
+        output_guard: &Arc<OutputGuard>,
+    ) -> Result<()> {
+        let remind_tool = self.last_requester.clone().map(|requester| {
+            crate::tools::RemindTool::new(
+                crate::reminders::ReminderStore::new(self.deps.sql_pool.clone()),
+                self.id.clone(),
+                requester,
+            )
+        });
+        // Calendar bindings and access users are both keyed "platform:sender_id".
+        let platform = self.id.split(':').next().unwrap_or_default();
+        let requester_key = self
+            .last_requester
+            .as_ref()
+            .map(|requester| format!("{platform}:{}", requester.sender_id));
+        let calendar_tool = requester_key.as_ref().and_then(|user| {
+            let client = crate::calendar::CalendarClient::new(
+                (**self.deps.runtime_config.calendar.load()).clone(),
+            )?;
+            Some(crate::tools::CalendarTool::new(client, user.clone()))
+        });
+        let policy = self
+            .last_role
+            .map(|role| self.deps.runtime_config.access.load().policy(role).clone());
+        let email_tool = requester_key
+            .as_ref()
+            .filter(|_| {
+                policy
+                    .as_ref()
+                    .is_some_and(|policy| policy.allows_tool("email"))
+            })
+            .and_then(|user| {
+                let client = crate::email::EmailClient::new(
+                    (**self.deps.runtime_config.email.load()).clone(),
+                )?;
+                Some(crate::tools::EmailTool::new(client, user.clone()))
+            });
+        let weather_tool =
+            crate::weather::WeatherClient::new((**self.deps.runtime_config.weather.load()).clone())
+                .map(|client| {
+                    let tool = crate::tools::WeatherTool::new(client);
+                    match &self.last_requester {
+                        Some(requester) => tool.with_user(
+                            crate::weather::LocationStore::new(self.deps.sql_pool.clone()),
+                            requester.sender_id.clone(),
+                        ),
+                        None => tool,
+                    }
+                });
 
         if let Err(error) = crate::tools::add_channel_tools(
             &self.tool_server,
@@ -809,26 +1540,99 @@ impl Channel {
             conversation_id,
             skip_flag.clone(),
             replied_flag.clone(),
+            output_guard.clone(),
             self.deps.cron_tool.clone(),
+            remind_tool,
+            calendar_tool,
+            email_tool,
+            weather_tool,
         )
         .await
         {
             tracing::error!(%error, "failed to add channel tools");
             return Err(AgentError::Other(error.into()).into());
         }
+        if let Some(policy) = &policy
+            && let Err(error) =
+                crate::tools::restrict_channel_tools(&self.tool_server, policy).await
+        {
+            tracing::error!(%error, "failed to restrict channel tools");
+            return Err(AgentError::Other(error.into()).into());
+        }
+        Ok(())
+    }
+
+    /// Register per-turn tools, run the LLM agentic loop, and clean up.
+    ///
+    /// Returns the prompt result, skip flag, and output guard for the caller
+    /// to dispatch, and keeps a snapshot of the turn's inputs for `!snapshot`.
+    #[tracing::instrument(skip(self, user_text, prompt_layers, attachment_content), fields(channel_id = %self.id, agent_id = %self.deps.agent_id))]
+    async fn run_agent_turn(
+        &mut self,
+        user_text: &str,
+        prompt_layers: PromptLayers,
+        conversation_id: &str,
+        attachment_content: Vec<UserContent>,
+    ) -> Result<(
+        std::result::Result<String, rig::completion::PromptError>,
+        crate::tools::SkipFlag,
+        crate::tools::RepliedFlag,
+        Arc<OutputGuard>,
+    )> {
+        let skip_flag = crate::tools::new_skip_flag();
+        let replied_flag = crate::tools::new_replied_flag();
+        // Replies are checked against the instructions alone: memories and
+        // the conversation's own context are fine to repeat.
+        let instructions = PromptLayers {
+            memory_bulletin: None,
+            conversation_context: None,
+            status: None,
+            ..prompt_layers.clone()
+        }
+        .render(&self.prompt_engine())
+        .expect("failed to render channel prompt");
+        let output_guard = Arc::new(OutputGuard::new(
+            &self.deps.runtime_config.output_guard.load(),
+            &instructions,
+            self.deps.agent_id.clone(),
+            self.id.clone(),
+        ));
+        self.add_turn_tools(conversation_id, &skip_flag, &replied_flag, &output_guard)
+            .await?;
+
+        let tools = self
+            .tool_server
+            .get_tool_defs(None)
+            .await
+            .map(|definitions| {
+                definitions
+                    .into_iter()
+                    .map(|definition| definition.name)
+                    .collect()
+            })
+            .unwrap_or_default();
 
         let rc = &self.deps.runtime_config;
+        let system_prompt = prompt_layers
+            .render(&self.prompt_engine())
+            .expect("failed to render channel prompt");
         let routing = rc.routing.load();
         let max_turns = **rc.max_turns.load();
-        let model_name = routing.resolve(ProcessType::Channel, None);
+        let canary = self.canary(&routing);
+        let model_name = match canary {
+            Some((
+                CanaryConfig {
+                    model: Some(model), ..
+                },
+                Cohort::Canary,
+            )) => model.as_str(),
+            _ => routing.resolve(ProcessType::Channel, None),
+        };
         let model = SpacebotModel::make(&self.deps.llm_manager, model_name)
-            .with_routing((**routing).clone());
+            .with_routing((**routing).clone())
+            .with_agent(self.deps.agent_id.clone());
 
-        let agent = AgentBuilder::new(model)
-            .preamble(system_prompt)
-            .default_max_turns(max_turns)
-            .tool_server_handle(self.tool_server.clone())
-            .build();
+        let agent = build_channel_agent(model, &system_prompt, max_turns, self.tool_server.clone());
 
         let _ = self
             .response_tx
@@ -852,25 +1656,123 @@ impl Channel {
             let guard = self.state.history.read().await;
             guard.clone()
         };
+        let turn_started_at = chrono::Utc::now();
+        let history_len = history.len();
 
+        let user_text = self.deps.runtime_config.scripts.load().before_llm(
+            &self.deps.agent_id,
+            conversation_id,
+            user_text,
+        );
+        let snapshot = PromptSnapshot {
+            agent_id: self.deps.agent_id.to_string(),
+            channel_id: self.id.to_string(),
+            correlation_id: self.last_correlation_id.clone(),
+            taken_at: turn_started_at,
+            routing: RoutingDecision {
+                model: model_name.to_string(),
+                thinking_effort: routing.thinking_effort_for_model(model_name).to_string(),
+                fallbacks: routing.get_fallbacks(model_name).to_vec(),
+                max_turns,
+            },
+            system_prompt,
+            layers: prompt_layers,
+            tools,
+            history: history.clone(),
+            user_message: user_text.clone(),
+            memories_retrieved: Vec::new(),
+            tool_calls: Vec::new(),
+            error: None,
+        };
+        let mut usage = rig::completion::Usage::new();
         let mut result = agent
-            .prompt(user_text)
+            .prompt(user_text.as_str())
             .with_history(&mut history)
             .with_hook(self.hook.clone())
-            .await;
+            .extended_details()
+            .await
+            .map(|response| {
+                usage += response.total_usage;
+                response.output
+            });
 
         // If the LLM responded with text that looks like tool call syntax, it failed
         // to use the tool calling API. Inject a correction and give it one more try.
         if let Ok(ref response) = result {
             if extract_reply_from_tool_syntax(response.trim()).is_some() {
                 tracing::warn!(channel_id = %self.id, "LLM emitted tool syntax as text, retrying with correction");
-                let prompt_engine = self.deps.runtime_config.prompts.load();
+                let prompt_engine = self.prompt_engine();
                 let correction = prompt_engine.render_system_tool_syntax_correction()?;
                 result = agent
                     .prompt(&correction)
                     .with_history(&mut history)
                     .with_hook(self.hook.clone())
-                    .await;
+                    .extended_details()
+                    .await
+                    .map(|response| {
+                        usage += response.total_usage;
+                        response.output
+                    });
+            }
+        }
+
+        let tool_calls = crate::conversation::history::tool_calls_in(
+            history.get(history_len..).unwrap_or_default(),
+        );
+        let cost_usd = self.deps.llm_manager.cost_of(model_name, &usage, 0);
+        if let Some((canary, cohort)) = canary {
+            self.deps.llm_manager.canary_stats().record(
+                &self.deps.agent_id,
+                &canary.variant(),
+                cohort,
+                &self.id,
+                result.is_err(),
+                cost_usd,
+            );
+        }
+        self.state.conversation_logger.log_turn(
+            &self.state.channel_id,
+            model_name,
+            &tool_calls,
+            turn_started_at,
+            crate::conversation::history::TurnUsage {
+                sender_id: self
+                    .last_requester
+                    .as_ref()
+                    .map(|requester| requester.sender_id.clone()),
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                cost_usd,
+            },
+        );
+        // A snapshot copies the whole context window; keep none under memory pressure.
+        if crate::watchdog::shedding() {
+            let dropped = self.last_snapshot.take().is_some();
+            crate::watchdog::record_evictions("prompt_snapshots", usize::from(dropped));
+        } else {
+            self.last_snapshot = Some(PromptSnapshot {
+                error: result.as_ref().err().map(ToString::to_string),
+                ..snapshot.with_tool_calls(tool_calls)
+            });
+        }
+
+        // Pastes stay in full for this turn only; later turns see references.
+        let compaction = **self.deps.runtime_config.compaction.load();
+        if compaction.artifacts
+            && let Some(turn) = history.get_mut(history_len..)
+        {
+            let store =
+                crate::conversation::artifacts::CodeArtifactStore::new(self.deps.sql_pool.clone());
+            if let Err(error) = store
+                .compact_turn(
+                    &self.state.channel_id,
+                    turn,
+                    compaction.artifact_min_lines,
+                    &self.deps.runtime_config.prompts.load(),
+                )
+                .await
+            {
+                tracing::warn!(channel_id = %self.id, %error, "failed to store pasted artifacts");
             }
         }
 
@@ -884,7 +1786,7 @@ impl Channel {
             tracing::warn!(%error, "failed to remove channel tools");
         }
 
-        Ok((result, skip_flag, replied_flag))
+        Ok((result, skip_flag, replied_flag, output_guard))
     }
 
     /// Dispatch the LLM result: send fallback text, log errors, clean up typing.
@@ -893,6 +1795,7 @@ impl Channel {
         result: std::result::Result<String, rig::completion::PromptError>,
         skip_flag: &crate::tools::SkipFlag,
         replied_flag: &crate::tools::RepliedFlag,
+        output_guard: &OutputGuard,
     ) {
         match result {
             Ok(response) => {
@@ -909,18 +1812,32 @@ impl Channel {
                     // When the text looks like tool call syntax (e.g. "[reply]\n{\"content\": \"hi\"}"),
                     // attempt to extract the reply content and send that instead.
                     let text = response.trim();
-                    let extracted = extract_reply_from_tool_syntax(text);
+                    let extracted = extract_reply_from_tool_syntax(text).is_some();
                     let source = self
                         .conversation_id
                         .as_deref()
                         .and_then(|conversation_id| conversation_id.split(':').next())
                         .unwrap_or("unknown");
-                    let final_text = crate::tools::reply::normalize_discord_mention_tokens(
-                        extracted.as_deref().unwrap_or(text),
-                        source,
-                    );
-                    if !final_text.is_empty() {
-                        if extracted.is_some() {
+                    let final_text = Some(fallback_reply_text(text, source))
+                        .filter(|final_text| !final_text.is_empty())
+                        .and_then(|final_text| {
+                            self.deps.runtime_config.scripts.load().after_llm(
+                                &self.deps.agent_id,
+                                &self.id,
+                                &final_text,
+                            )
+                        })
+                        .map(|final_text| {
+                            self.deps
+                                .runtime_config
+                                .post_processing
+                                .load()
+                                .apply(&self.id, &final_text)
+                        })
+                        .filter(|final_text| !final_text.is_empty())
+                        .and_then(|final_text| output_guard.check(&final_text));
+                    if let Some(final_text) = final_text {
+                        if extracted {
                             tracing::warn!(channel_id = %self.id, "extracted reply from malformed tool syntax in LLM text output");
                         }
                         self.state
@@ -944,8 +1861,32 @@ impl Channel {
             Err(rig::completion::PromptError::PromptCancelled { reason, .. }) => {
                 tracing::info!(channel_id = %self.id, %reason, "channel turn cancelled");
             }
+            Err(error) if crate::llm::routing::is_over_budget_error(&error.to_string()) => {
+                tracing::warn!(channel_id = %self.id, %error, "channel LLM call blocked by budget cap");
+                let prompt_engine = self.deps.runtime_config.prompts.load();
+                match prompt_engine.render_over_budget() {
+                    Ok(text) => {
+                        self.state
+                            .conversation_logger
+                            .log_bot_message(&self.state.channel_id, &text);
+                        if let Err(error) =
+                            self.response_tx.send(OutboundResponse::Text(text)).await
+                        {
+                            tracing::error!(%error, channel_id = %self.id, "failed to send over-budget reply");
+                        }
+                    }
+                    Err(error) => {
+                        tracing::error!(%error, "failed to render over-budget reply");
+                    }
+                }
+            }
             Err(error) => {
                 tracing::error!(channel_id = %self.id, %error, "channel LLM call failed");
+                crate::events::publish(crate::events::Event::ErrorOccurred {
+                    agent_id: Some(self.deps.agent_id.clone()),
+                    component: "channel".into(),
+                    message: format!("channel {} LLM call failed: {error}", self.id),
+                });
             }
         }
 
@@ -1501,6 +2442,11 @@ where
             Ok(text) => (text, true),
             Err(error) => {
                 tracing::error!(worker_id = %worker_id, %error, "worker failed");
+                crate::events::publish(crate::events::Event::ErrorOccurred {
+                    agent_id: Some(agent_id.clone()),
+                    component: "worker".into(),
+                    message: format!("worker {worker_id} failed: {error}"),
+                });
                 (format!("Worker failed: {error}"), true)
             }
         };
@@ -1520,6 +2466,36 @@ where
     })
 }
 
+/// Build the agent for one channel turn.
+///
+/// Shared with the golden transcript tests so replays drive the same agent
+/// configuration as live channels.
+pub fn build_channel_agent(
+    model: SpacebotModel,
+    system_prompt: &str,
+    max_turns: usize,
+    tool_server: rig::tool::server::ToolServerHandle,
+) -> rig::agent::Agent<SpacebotModel> {
+    AgentBuilder::new(model)
+        .preamble(system_prompt)
+        .default_max_turns(max_turns)
+        .tool_server_handle(tool_server)
+        .build()
+}
+
+/// Text sent when a turn ends in plain text rather than a reply tool call.
+///
+/// Recovers the reply from tool call syntax emitted as text and normalizes
+/// mention tokens for the platform `source`. Empty means send nothing.
+pub fn fallback_reply_text(response: &str, source: &str) -> String {
+    let text = response.trim();
+    let extracted = extract_reply_from_tool_syntax(text);
+    crate::tools::reply::normalize_discord_mention_tokens(
+        extracted.as_deref().unwrap_or(text),
+        source,
+    )
+}
+
 /// Some models emit tool call syntax as plain text instead of making actual tool calls.
 /// When the text starts with a tool-like prefix (e.g. `[reply]`, `(reply)`), try to
 /// extract the reply content so we can send it cleanly instead of showing raw JSON.
@@ -1629,6 +2605,34 @@ fn format_user_message(raw_text: &str, message: &InboundMessage) -> String {
     format!("{display_name}{bot_tag}{reply_context}: {raw_text}")
 }
 
+/// Calculator results for arithmetic in a user message, so the LLM quotes
+/// them instead of doing the math itself. `None` when there's nothing to
+/// compute or `auto_calculate` is off.
+pub(crate) fn calculator_note(rc: &crate::config::RuntimeConfig, text: &str) -> Option<String> {
+    if !rc.routing.load().auto_calculate {
+        return None;
+    }
+    let calculations = crate::calc::find_calculations(text, chrono::Local::now().date_naive());
+    if calculations.is_empty() {
+        return None;
+    }
+    tracing::debug!(
+        count = calculations.len(),
+        "precomputed arithmetic in message"
+    );
+    match rc
+        .prompts
+        .load()
+        .render_system_calculator_results(&calculations)
+    {
+        Ok(note) => Some(note),
+        Err(error) => {
+            tracing::warn!(%error, "failed to render calculator results");
+            None
+        }
+    }
+}
+
 /// Check if a ProcessEvent is targeted at a specific channel.
 ///
 /// Events from branches and workers carry a channel_id. We only process events
@@ -1668,10 +2672,15 @@ const TEXT_MIME_PREFIXES: &[&str] = &[
     "application/yaml",
 ];
 
+/// MIME types of CSV and TSV files. Some clients label CSVs as Excel, so
+/// the file extension counts too.
+const CSV_MIME_TYPES: &[&str] = &["text/csv", "text/tab-separated-values", "application/csv"];
+
 /// Download attachments and convert them to LLM-ready UserContent parts.
 ///
-/// Images become `UserContent::Image` (base64). Text files get inlined.
-/// Other file types get a metadata-only description.
+/// Images become `UserContent::Image` (base64). CSVs are saved to the
+/// workspace for `csv_analysis`. Other text files get inlined. Other file
+/// types get a metadata-only description.
 async fn download_attachments(
     deps: &AgentDeps,
     attachments: &[crate::Attachment],
@@ -1686,14 +2695,21 @@ async fn download_attachments(
         let is_text = TEXT_MIME_PREFIXES
             .iter()
             .any(|p| attachment.mime_type.starts_with(p));
+        let filename = attachment.filename.to_lowercase();
+        let is_csv = filename.ends_with(".csv")
+            || filename.ends_with(".tsv")
+            || CSV_MIME_TYPES.contains(&attachment.mime_type.as_str());
 
         let content = if is_image {
             download_image_attachment(http, attachment).await
+        } else if is_csv {
+            let workspace = &deps.runtime_config.workspace_dir;
+            download_csv_attachment(http, workspace, attachment).await
         } else if is_text {
             download_text_attachment(http, attachment).await
         } else if attachment.mime_type.starts_with("audio/") {
             // Download audio files to /tmp and provide the path
-            download_audio_attachment(http, attachment).await
+            download_audio_attachment(http, &deps.artifacts, attachment).await
         } else {
             let size_str = attachment
                 .size_bytes
@@ -1752,9 +2768,11 @@ async fn download_image_attachment(
     UserContent::image_base64(base64_data, media_type, None)
 }
 
-/// Download an audio attachment, save to /tmp, and return the file path for tool use.
+/// Download an audio attachment, save it to /tmp (or object storage), and
+/// return where it is for tool use.
 async fn download_audio_attachment(
     http: &reqwest::Client,
+    artifacts: &crate::storage::ArtifactStore,
     attachment: &crate::Attachment,
 ) -> UserContent {
     let response = match http.get(&attachment.url).send().await {
@@ -1780,22 +2798,105 @@ async fn download_audio_attachment(
     };
 
     let ext = attachment.filename.rsplit('.').next().unwrap_or("ogg");
-    let tmp_path = format!("/tmp/spacebot_audio_{}.{}", uuid::Uuid::new_v4(), ext);
-    if let Err(error) = tokio::fs::write(&tmp_path, &bytes).await {
-        tracing::warn!(%error, "failed to save audio to disk");
-        return UserContent::text(format!("[Failed to save audio: {}]", attachment.filename));
-    }
+    let filename = format!("spacebot_audio_{}.{}", uuid::Uuid::new_v4(), ext);
+    let tmp_path = std::path::Path::new("/tmp").join(&filename);
+    let size = bytes.len();
+    let location = match artifacts
+        .save(
+            &tmp_path,
+            &format!("audio/{filename}"),
+            bytes.to_vec(),
+            &attachment.mime_type,
+        )
+        .await
+    {
+        Ok(location) => location,
+        Err(error) => {
+            tracing::warn!(%error, "failed to save audio");
+            return UserContent::text(format!("[Failed to save audio: {}]", attachment.filename));
+        }
+    };
 
     tracing::info!(
         filename = %attachment.filename,
-        path = %tmp_path,
-        size = bytes.len(),
+        %location,
+        size,
         "downloaded audio attachment"
     );
 
+    if artifacts.is_remote() {
+        UserContent::text(format!(
+            "[Voice/audio message stored at: {location}] (Download it, use transcribe.sh to transcribe it, then respond to the content)"
+        ))
+    } else {
+        UserContent::text(format!(
+            "[Voice/audio message saved to: {location}] (Use transcribe.sh to transcribe it, then respond to the content)"
+        ))
+    }
+}
+
+/// Download a CSV attachment into the workspace's `uploads/` and describe
+/// it with a preview, so workers can analyze all of it with `csv_analysis`.
+async fn download_csv_attachment(
+    http: &reqwest::Client,
+    workspace: &std::path::Path,
+    attachment: &crate::Attachment,
+) -> UserContent {
+    let bytes = match http.get(&attachment.url).send().await {
+        Ok(response) => response.bytes().await,
+        Err(error) => Err(error),
+    };
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            tracing::warn!(%error, filename = %attachment.filename, "failed to download CSV");
+            return UserContent::text(format!(
+                "[Failed to download file: {}]",
+                attachment.filename
+            ));
+        }
+    };
+
+    // Keep the name recognizable but safe as a path, and don't overwrite an
+    // earlier upload with the same name.
+    let name: String = attachment
+        .filename
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let relative = format!("uploads/{}-{}", &id[..8], name.trim_start_matches('.'));
+    let path = workspace.join(&relative);
+    let saved = match path.parent() {
+        Some(parent) => tokio::fs::create_dir_all(parent).await,
+        None => Ok(()),
+    };
+    if let Err(error) = saved.and(tokio::fs::write(&path, &bytes).await) {
+        tracing::warn!(%error, path = %path.display(), "failed to save CSV");
+        return UserContent::text(format!("[Failed to save file: {}]", attachment.filename));
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    let preview: Vec<&str> = text.lines().take(6).collect();
+    let rows = text.lines().count().saturating_sub(1);
+
+    tracing::info!(
+        filename = %attachment.filename,
+        path = %relative,
+        rows,
+        "saved CSV attachment"
+    );
+
     UserContent::text(format!(
-        "[Voice/audio message saved to: {}] (Use transcribe.sh to transcribe it, then respond to the content)",
-        tmp_path
+        "<file name=\"{}\" mime=\"{}\" saved_to=\"{relative}\" rows=\"{rows}\">\n{}\n</file>\n\
+         (Preview only. For counts, totals, or other figures from this file, spawn a worker \
+         to analyze {relative} with csv_analysis rather than estimating.)",
+        attachment.filename,
+        attachment.mime_type,
+        preview.join("\n")
     ))
 }
 
@@ -3,6 +3,10 @@
 //! The compactor is NOT an LLM process. It watches a channel's context size and
 //! spawns compaction workers when thresholds are crossed. The LLM work (summarization
 //! + memory extraction) happens in the spawned worker, not here.
+//!
+//! With `dedup` enabled, each check first collapses near-duplicate messages
+//! (re-pasted logs, repeated questions), found by embedding similarity, so noisy
+//! channels reclaim budget before anything has to be summarized away.
 
 use crate::error::Result;
 use crate::llm::SpacebotModel;
@@ -11,9 +15,13 @@ use rig::agent::AgentBuilder;
 use rig::completion::{CompletionModel as _, Prompt as _};
 use rig::message::{AssistantContent, Message, UserContent};
 use rig::tool::server::{ToolServer, ToolServerHandle};
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 
+/// Characters of a collapsed message kept in its marker.
+const DEDUP_PREVIEW_CHARS: usize = 80;
+
 /// Programmatic monitor that watches channel context size and triggers compaction.
 pub struct Compactor {
     pub channel_id: ChannelId,
@@ -21,6 +29,11 @@ pub struct Compactor {
     pub history: Arc<RwLock<Vec<Message>>>,
     /// Is a compaction currently running.
     is_compacting: Arc<RwLock<bool>>,
+    /// Embeddings of the texts checked for duplicates, by text hash, so each
+    /// message is embedded once.
+    embeddings: Mutex<HashMap<u64, Arc<Vec<f32>>>>,
+    /// Hashes of the markers left by collapsing, which are never candidates.
+    markers: Mutex<HashSet<u64>>,
 }
 
 impl Compactor {
@@ -31,6 +44,8 @@ impl Compactor {
             deps,
             history,
             is_compacting: Arc::new(RwLock::new(false)),
+            embeddings: Mutex::new(HashMap::new()),
+            markers: Mutex::new(HashSet::new()),
         }
     }
 
@@ -47,6 +62,22 @@ impl Compactor {
         let context_window = **rc.context_window.load();
         let compaction_config = **rc.compaction.load();
 
+        if compaction_config.dedup {
+            match self.collapse_duplicates(&compaction_config).await {
+                Ok(0) => {}
+                Ok(collapsed) => tracing::info!(
+                    channel_id = %self.channel_id,
+                    collapsed,
+                    "collapsed near-duplicate messages"
+                ),
+                Err(error) => tracing::warn!(
+                    channel_id = %self.channel_id,
+                    %error,
+                    "duplicate collapsing failed"
+                ),
+            }
+        }
+
         let usage = {
             let history = self.history.read().await;
             let estimated_tokens = estimate_history_tokens(&history);
@@ -137,6 +168,79 @@ impl Compactor {
         });
     }
 
+    /// Replace the earlier copies of near-duplicate texts with a short marker,
+    /// keeping the latest. Returns how many were collapsed.
+    async fn collapse_duplicates(&self, config: &crate::config::CompactionConfig) -> Result<usize> {
+        let mut candidates = dedup_candidates(&self.history.read().await, config.dedup_min_chars);
+        {
+            let markers = self.markers.lock().expect("marker set poisoned");
+            candidates.retain(|c| !markers.contains(&c.hash));
+        }
+        if candidates.len() < 2 {
+            return Ok(0);
+        }
+
+        // Embed only texts not seen before, and forget texts no longer in history.
+        let missing: Vec<&DedupCandidate> = {
+            let mut cache = self.embeddings.lock().expect("embedding cache poisoned");
+            cache.retain(|hash, _| candidates.iter().any(|c| c.hash == *hash));
+            candidates
+                .iter()
+                .filter(|c| !cache.contains_key(&c.hash))
+                .collect()
+        };
+        if !missing.is_empty() {
+            let texts = missing.iter().map(|c| c.text.clone()).collect();
+            let computed = self
+                .deps
+                .memory_search
+                .embedding_model()
+                .embed_many(texts)
+                .await?;
+            let mut cache = self.embeddings.lock().expect("embedding cache poisoned");
+            for (candidate, embedding) in missing.iter().zip(computed) {
+                cache.insert(candidate.hash, Arc::new(embedding));
+            }
+        }
+        let embeddings: Vec<Arc<Vec<f32>>> = {
+            let cache = self.embeddings.lock().expect("embedding cache poisoned");
+            candidates
+                .iter()
+                .filter_map(|c| cache.get(&c.hash).cloned())
+                .collect()
+        };
+        if embeddings.len() != candidates.len() {
+            return Ok(0);
+        }
+
+        let duplicates = find_duplicates(&embeddings, config.dedup_similarity);
+        if duplicates.is_empty() {
+            return Ok(0);
+        }
+
+        let prompt_engine = self.deps.runtime_config.prompts.load();
+        let mut history = self.history.write().await;
+        let mut collapsed = 0;
+        for index in duplicates {
+            let candidate = &candidates[index];
+            let preview: String = candidate.text.chars().take(DEDUP_PREVIEW_CHARS).collect();
+            let marker = prompt_engine.render_system_duplicate_collapsed(preview.trim())?;
+            if marker.len() >= candidate.text.len() {
+                continue;
+            }
+            let marker_hash = text_hash(&marker);
+            // History may have changed while embedding; only replace the exact text.
+            if replace_text(&mut history, candidate, marker) {
+                self.markers
+                    .lock()
+                    .expect("marker set poisoned")
+                    .insert(marker_hash);
+                collapsed += 1;
+            }
+        }
+        Ok(collapsed)
+    }
+
     /// Emergency truncation: drop oldest messages without LLM summarization.
     ///
     /// Only fires at 95%+ context usage. Removes the oldest half of messages and
@@ -199,8 +303,9 @@ async fn run_compaction(
     // 3. Run the compaction LLM to produce summary + extracted memories
     let routing = deps.runtime_config.routing.load();
     let model_name = routing.resolve(ProcessType::Worker, None).to_string();
-    let model =
-        SpacebotModel::make(&deps.llm_manager, &model_name).with_routing((**routing).clone());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_routing((**routing).clone())
+        .with_agent(deps.agent_id.clone());
 
     // Give the compaction worker memory_save so it can directly persist memories
     let tool_server: ToolServerHandle = ToolServer::new()
@@ -239,6 +344,91 @@ async fn run_compaction(
     Ok(remove_count)
 }
 
+/// A user text or tool result that could be collapsed as a duplicate.
+#[derive(Debug)]
+struct DedupCandidate {
+    /// Position in history, and of the content item within the message.
+    message: usize,
+    item: usize,
+    hash: u64,
+    text: String,
+}
+
+fn dedup_candidates(history: &[Message], min_chars: usize) -> Vec<DedupCandidate> {
+    let mut candidates = Vec::new();
+    for (message, entry) in history.iter().enumerate() {
+        let Message::User { content } = entry else {
+            continue;
+        };
+        for (item, content) in content.iter().enumerate() {
+            let text = match content {
+                UserContent::Text(t) => t.text.clone(),
+                UserContent::ToolResult(tr) => tool_result_text(tr),
+                _ => continue,
+            };
+            if text.chars().count() < min_chars {
+                continue;
+            }
+            candidates.push(DedupCandidate {
+                message,
+                item,
+                hash: text_hash(&text),
+                text,
+            });
+        }
+    }
+    candidates
+}
+
+fn tool_result_text(tool_result: &rig::message::ToolResult) -> String {
+    tool_result
+        .content
+        .iter()
+        .filter_map(|c| match c {
+            rig::message::ToolResultContent::Text(t) => Some(t.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn text_hash(text: &str) -> u64 {
+    use std::hash::{Hash as _, Hasher as _};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Indices of the candidates with a near-duplicate later in history. The
+/// latest copy of each text is always kept.
+fn find_duplicates(embeddings: &[Arc<Vec<f32>>], similarity: f32) -> Vec<usize> {
+    (0..embeddings.len())
+        .filter(|&i| {
+            embeddings[i + 1..].iter().any(|later| {
+                crate::memory::embedding::cosine_similarity(&embeddings[i], later) >= similarity
+            })
+        })
+        .collect()
+}
+
+/// Swap a candidate's text for `marker`, if it's still where it was found.
+fn replace_text(history: &mut [Message], candidate: &DedupCandidate, marker: String) -> bool {
+    let Some(Message::User { content }) = history.get_mut(candidate.message) else {
+        return false;
+    };
+    let Some(item) = content.iter_mut().nth(candidate.item) else {
+        return false;
+    };
+    match item {
+        UserContent::Text(t) if t.text == candidate.text => t.text = marker,
+        UserContent::ToolResult(tr) if tool_result_text(tr) == candidate.text => {
+            tr.content = rig::OneOrMany::one(rig::message::ToolResultContent::text(marker));
+        }
+        _ => return false,
+    }
+    true
+}
+
 /// Estimate token count for a history using chars/4 heuristic.
 ///
 /// This is intentionally rough — it's only used for threshold checks, not billing.
@@ -370,3 +560,48 @@ pub enum CompactionAction {
     /// Emergency truncation (no LLM, drop oldest 50%).
     EmergencyTruncate,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earlier_copies_of_near_duplicates_are_collapsed() {
+        let log = "error: connection refused (os error 111)\n".repeat(10);
+        let mut history = vec![
+            Message::user(log.clone()),
+            Message::assistant("Is the database up?"),
+            Message::user("short"),
+            Message::user(format!("{log}again")),
+        ];
+
+        let candidates = dedup_candidates(&history, 200);
+        assert_eq!(
+            candidates.iter().map(|c| c.message).collect::<Vec<_>>(),
+            vec![0, 3]
+        );
+
+        let embeddings = vec![
+            Arc::new(vec![1.0, 0.0]),
+            Arc::new(vec![0.0, 1.0]),
+            Arc::new(vec![0.99, 0.05]),
+        ];
+        assert_eq!(find_duplicates(&embeddings, 0.95), vec![0]);
+
+        assert!(replace_text(
+            &mut history,
+            &candidates[0],
+            "[collapsed]".into()
+        ));
+        assert_eq!(
+            estimate_history_tokens(&history[..1]),
+            "[collapsed]".len() / 4
+        );
+        // Already replaced, so the candidate no longer matches.
+        assert!(!replace_text(
+            &mut history,
+            &candidates[0],
+            "[collapsed]".into()
+        ));
+    }
+}
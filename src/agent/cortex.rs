@@ -9,6 +9,7 @@
 //! The cortex also observes system-wide activity via signals for future use in
 //! health monitoring and memory consolidation.
 
+use crate::db::{SqlPool, with_pool};
 use crate::error::Result;
 use crate::hooks::CortexHook;
 use crate::llm::SpacebotModel;
@@ -19,7 +20,7 @@ use crate::{AgentDeps, ProcessEvent, ProcessType};
 use rig::agent::AgentBuilder;
 use rig::completion::{CompletionModel, Prompt};
 use serde::Serialize;
-use sqlx::{Row as _, SqlitePool};
+use sqlx::Row as _;
 
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -81,11 +82,11 @@ pub struct CortexEvent {
 /// immediately so the cortex never blocks on a DB write.
 #[derive(Debug, Clone)]
 pub struct CortexLogger {
-    pool: SqlitePool,
+    pool: SqlPool,
 }
 
 impl CortexLogger {
-    pub fn new(pool: SqlitePool) -> Self {
+    pub fn new(pool: SqlPool) -> Self {
         Self { pool }
     }
 
@@ -98,16 +99,18 @@ impl CortexLogger {
         let details_json = details.map(|d| d.to_string());
 
         tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT INTO cortex_events (id, event_type, summary, details) VALUES (?, ?, ?, ?)",
-            )
-            .bind(&id)
-            .bind(&event_type)
-            .bind(&summary)
-            .bind(&details_json)
-            .execute(&pool)
-            .await
-            {
+            if let Err(error) = with_pool!(&pool, |pool| {
+                sqlx::query(
+                    "INSERT INTO cortex_events (id, event_type, summary, details) VALUES ($1, $2, $3, $4)",
+                )
+                .bind(&id)
+                .bind(&event_type)
+                .bind(&summary)
+                .bind(&details_json)
+                .execute(pool)
+                .await
+                .map(drop)
+            }) {
                 tracing::warn!(%error, "failed to persist cortex event");
             }
         });
@@ -120,26 +123,28 @@ impl CortexLogger {
         offset: i64,
         event_type: Option<&str>,
     ) -> std::result::Result<Vec<CortexEvent>, sqlx::Error> {
-        let rows = if let Some(event_type) = event_type {
-            sqlx::query_as::<_, CortexEventRow>(
-                "SELECT id, event_type, summary, details, created_at FROM cortex_events \
-                 WHERE event_type = ? ORDER BY created_at DESC LIMIT ? OFFSET ?",
-            )
-            .bind(event_type)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
-            .await?
-        } else {
-            sqlx::query_as::<_, CortexEventRow>(
-                "SELECT id, event_type, summary, details, created_at FROM cortex_events \
-                 ORDER BY created_at DESC LIMIT ? OFFSET ?",
-            )
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
-            .await?
-        };
+        let rows: Vec<CortexEventRow> = with_pool!(&self.pool, |pool| {
+            if let Some(event_type) = event_type {
+                sqlx::query_as(
+                    "SELECT id, event_type, summary, details, created_at FROM cortex_events \
+                     WHERE event_type = $1 ORDER BY created_at DESC LIMIT $2 OFFSET $3",
+                )
+                .bind(event_type)
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            } else {
+                sqlx::query_as(
+                    "SELECT id, event_type, summary, details, created_at FROM cortex_events \
+                     ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+                )
+                .bind(limit)
+                .bind(offset)
+                .fetch_all(pool)
+                .await?
+            }
+        });
 
         Ok(rows.into_iter().map(|row| row.into_event()).collect())
     }
@@ -149,29 +154,31 @@ impl CortexLogger {
         &self,
         event_type: Option<&str>,
     ) -> std::result::Result<i64, sqlx::Error> {
-        let count: (i64,) = if let Some(event_type) = event_type {
-            sqlx::query_as("SELECT COUNT(*) FROM cortex_events WHERE event_type = ?")
-                .bind(event_type)
-                .fetch_one(&self.pool)
-                .await?
-        } else {
-            sqlx::query_as("SELECT COUNT(*) FROM cortex_events")
-                .fetch_one(&self.pool)
-                .await?
-        };
+        let count: (i64,) = with_pool!(&self.pool, |pool| {
+            if let Some(event_type) = event_type {
+                sqlx::query_as("SELECT COUNT(*) FROM cortex_events WHERE event_type = $1")
+                    .bind(event_type)
+                    .fetch_one(pool)
+                    .await?
+            } else {
+                sqlx::query_as("SELECT COUNT(*) FROM cortex_events")
+                    .fetch_one(pool)
+                    .await?
+            }
+        });
 
         Ok(count.0)
     }
 }
 
-/// Internal row type for SQLite query mapping.
+/// Internal row type for query mapping.
 #[derive(sqlx::FromRow)]
 struct CortexEventRow {
     id: String,
     event_type: String,
     summary: String,
     details: Option<String>,
-    created_at: chrono::NaiveDateTime,
+    created_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl CortexEventRow {
@@ -181,7 +188,7 @@ impl CortexEventRow {
             event_type: self.event_type,
             summary: self.summary,
             details: self.details.and_then(|d| serde_json::from_str(&d).ok()),
-            created_at: self.created_at.and_utc().to_rfc3339(),
+            created_at: self.created_at.to_rfc3339(),
         }
     }
 }
@@ -462,8 +469,9 @@ pub async fn generate_bulletin(deps: &AgentDeps, logger: &CortexLogger) -> bool
 
     let routing = deps.runtime_config.routing.load();
     let model_name = routing.resolve(ProcessType::Branch, None).to_string();
-    let model =
-        SpacebotModel::make(&deps.llm_manager, &model_name).with_routing((**routing).clone());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_routing((**routing).clone())
+        .with_agent(deps.agent_id.clone());
 
     // No tools needed — the LLM just synthesizes the pre-gathered data
     let agent = AgentBuilder::new(model).preamble(&bulletin_prompt).build();
@@ -528,13 +536,15 @@ pub struct AgentProfile {
 }
 
 /// Load the current profile for an agent, if one exists.
-pub async fn load_profile(pool: &SqlitePool, agent_id: &str) -> Option<AgentProfile> {
-    sqlx::query_as::<_, AgentProfileRow>(
-        "SELECT agent_id, display_name, status, bio, avatar_seed, generated_at, updated_at FROM agent_profile WHERE agent_id = ?",
-    )
-    .bind(agent_id)
-    .fetch_optional(pool)
-    .await
+pub async fn load_profile(pool: &SqlPool, agent_id: &str) -> Option<AgentProfile> {
+    with_pool!(pool, |pool| {
+        sqlx::query_as::<_, AgentProfileRow>(
+            "SELECT agent_id, display_name, status, bio, avatar_seed, generated_at, updated_at FROM agent_profile WHERE agent_id = $1",
+        )
+        .bind(agent_id)
+        .fetch_optional(pool)
+        .await
+    })
     .ok()
     .flatten()
     .map(|row| row.into_profile())
@@ -547,8 +557,8 @@ struct AgentProfileRow {
     status: Option<String>,
     bio: Option<String>,
     avatar_seed: Option<String>,
-    generated_at: chrono::NaiveDateTime,
-    updated_at: chrono::NaiveDateTime,
+    generated_at: chrono::DateTime<chrono::Utc>,
+    updated_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl AgentProfileRow {
@@ -559,8 +569,8 @@ impl AgentProfileRow {
             status: self.status,
             bio: self.bio,
             avatar_seed: self.avatar_seed,
-            generated_at: self.generated_at.and_utc().to_rfc3339(),
-            updated_at: self.updated_at.and_utc().to_rfc3339(),
+            generated_at: self.generated_at.to_rfc3339(),
+            updated_at: self.updated_at.to_rfc3339(),
         }
     }
 }
@@ -573,7 +583,7 @@ struct ProfileLlmResponse {
     bio: Option<String>,
 }
 
-/// Generate an agent profile card and persist it to SQLite.
+/// Generate an agent profile card and persist it to the database.
 ///
 /// Uses the current memory bulletin and identity files as context, then asks
 /// an LLM to produce a display name, status line, and short bio.
@@ -621,8 +631,9 @@ async fn generate_profile(deps: &AgentDeps, logger: &CortexLogger) {
 
     let routing = deps.runtime_config.routing.load();
     let model_name = routing.resolve(ProcessType::Branch, None).to_string();
-    let model =
-        SpacebotModel::make(&deps.llm_manager, &model_name).with_routing((**routing).clone());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_routing((**routing).clone())
+        .with_agent(deps.agent_id.clone());
 
     let agent = AgentBuilder::new(model).preamble(&profile_prompt).build();
 
@@ -644,24 +655,26 @@ async fn generate_profile(deps: &AgentDeps, logger: &CortexLogger) {
                     // Use the agent ID as a stable avatar seed
                     let avatar_seed = agent_id.to_string();
 
-                    if let Err(error) = sqlx::query(
-                        "INSERT INTO agent_profile (agent_id, display_name, status, bio, avatar_seed, generated_at, updated_at) \
-                         VALUES (?, ?, ?, ?, ?, datetime('now'), datetime('now')) \
-                         ON CONFLICT(agent_id) DO UPDATE SET \
-                         display_name = excluded.display_name, \
-                         status = excluded.status, \
-                         bio = excluded.bio, \
-                         avatar_seed = excluded.avatar_seed, \
-                         updated_at = datetime('now')",
-                    )
-                    .bind(agent_id.as_ref())
-                    .bind(&profile_data.display_name)
-                    .bind(&profile_data.status)
-                    .bind(&profile_data.bio)
-                    .bind(&avatar_seed)
-                    .execute(&deps.sqlite_pool)
-                    .await
-                    {
+                    if let Err(error) = with_pool!(&deps.sql_pool, |pool| {
+                        sqlx::query(
+                            "INSERT INTO agent_profile (agent_id, display_name, status, bio, avatar_seed, generated_at, updated_at) \
+                             VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP) \
+                             ON CONFLICT(agent_id) DO UPDATE SET \
+                             display_name = excluded.display_name, \
+                             status = excluded.status, \
+                             bio = excluded.bio, \
+                             avatar_seed = excluded.avatar_seed, \
+                             updated_at = CURRENT_TIMESTAMP",
+                        )
+                        .bind(agent_id.as_ref())
+                        .bind(&profile_data.display_name)
+                        .bind(&profile_data.status)
+                        .bind(&profile_data.bio)
+                        .bind(&avatar_seed)
+                        .execute(pool)
+                        .await
+                        .map(drop)
+                    }) {
                         tracing::warn!(%error, "failed to persist agent profile");
                         return;
                     }
@@ -787,7 +800,7 @@ async fn run_association_pass(
     let embedding_table = deps.memory_search.embedding_table();
 
     // Get the memories to process
-    let memories = match fetch_memories_for_association(&deps.sqlite_pool, since).await {
+    let memories = match fetch_memories_for_association(&deps.sql_pool, since).await {
         Ok(memories) => memories,
         Err(error) => {
             tracing::warn!(%error, "failed to fetch memories for association pass");
@@ -874,24 +887,26 @@ async fn run_association_pass(
 /// If `since` is None, returns all non-forgotten memory IDs (backfill).
 /// If `since` is Some, returns IDs of memories created or updated since that time.
 async fn fetch_memories_for_association(
-    pool: &SqlitePool,
+    pool: &SqlPool,
     since: Option<chrono::DateTime<chrono::Utc>>,
 ) -> anyhow::Result<Vec<String>> {
-    let rows = if let Some(since) = since {
-        sqlx::query(
-            "SELECT id FROM memories WHERE forgotten = 0 AND (created_at > ? OR updated_at > ?) ORDER BY created_at DESC",
-        )
-        .bind(since)
-        .bind(since)
-        .fetch_all(pool)
-        .await?
-    } else {
-        sqlx::query(
-            "SELECT id FROM memories WHERE forgotten = 0 ORDER BY importance DESC, created_at DESC",
-        )
-        .fetch_all(pool)
-        .await?
-    };
+    let ids = with_pool!(pool, |pool| {
+        let rows = if let Some(since) = since {
+            sqlx::query(
+                "SELECT id FROM memories WHERE forgotten = FALSE AND (created_at > $1 OR updated_at > $1) ORDER BY created_at DESC",
+            )
+            .bind(since)
+            .fetch_all(pool)
+            .await?
+        } else {
+            sqlx::query(
+                "SELECT id FROM memories WHERE forgotten = FALSE ORDER BY importance DESC, created_at DESC",
+            )
+            .fetch_all(pool)
+            .await?
+        };
+        rows.iter().map(|row| row.get("id")).collect()
+    });
 
-    Ok(rows.iter().map(|row| row.get("id")).collect())
+    Ok(ids)
 }
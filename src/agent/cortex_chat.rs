@@ -6,6 +6,7 @@
 //! into the system prompt as context.
 
 use crate::conversation::history::ProcessRunLogger;
+use crate::db::{SqlPool, with_pool};
 use crate::llm::SpacebotModel;
 use crate::{AgentDeps, ProcessType};
 
@@ -13,7 +14,6 @@ use rig::agent::{AgentBuilder, HookAction, PromptHook, ToolCallHookAction};
 use rig::completion::{AssistantContent, CompletionModel, CompletionResponse, Message, Prompt};
 use rig::tool::server::ToolServerHandle;
 use serde::Serialize;
-use sqlx::SqlitePool;
 use tokio::sync::mpsc;
 
 use std::sync::Arc;
@@ -112,10 +112,10 @@ impl<M: CompletionModel> PromptHook<M> for CortexChatHook {
     }
 }
 
-/// SQLite CRUD for cortex chat messages.
+/// CRUD for cortex chat messages.
 #[derive(Debug, Clone)]
 pub struct CortexChatStore {
-    pool: SqlitePool,
+    pool: SqlPool,
 }
 
 #[derive(sqlx::FromRow)]
@@ -125,7 +125,7 @@ struct ChatMessageRow {
     role: String,
     content: String,
     channel_context: Option<String>,
-    created_at: chrono::NaiveDateTime,
+    created_at: chrono::DateTime<chrono::Utc>,
 }
 
 impl ChatMessageRow {
@@ -136,13 +136,13 @@ impl ChatMessageRow {
             role: self.role,
             content: self.content,
             channel_context: self.channel_context,
-            created_at: self.created_at.and_utc().to_rfc3339(),
+            created_at: self.created_at.to_rfc3339(),
         }
     }
 }
 
 impl CortexChatStore {
-    pub fn new(pool: SqlitePool) -> Self {
+    pub fn new(pool: SqlPool) -> Self {
         Self { pool }
     }
 
@@ -152,14 +152,16 @@ impl CortexChatStore {
         thread_id: &str,
         limit: i64,
     ) -> Result<Vec<CortexChatMessage>, sqlx::Error> {
-        let rows: Vec<ChatMessageRow> = sqlx::query_as(
-            "SELECT id, thread_id, role, content, channel_context, created_at \
-             FROM cortex_chat_messages WHERE thread_id = ? ORDER BY created_at DESC LIMIT ?",
-        )
-        .bind(thread_id)
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await?;
+        let rows: Vec<ChatMessageRow> = with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT id, thread_id, role, content, channel_context, created_at \
+                 FROM cortex_chat_messages WHERE thread_id = $1 ORDER BY created_at DESC LIMIT $2",
+            )
+            .bind(thread_id)
+            .bind(limit)
+            .fetch_all(pool)
+            .await?
+        });
 
         let mut messages: Vec<CortexChatMessage> =
             rows.into_iter().map(|row| row.into_message()).collect();
@@ -176,27 +178,31 @@ impl CortexChatStore {
         channel_context: Option<&str>,
     ) -> Result<String, sqlx::Error> {
         let id = uuid::Uuid::new_v4().to_string();
-        sqlx::query(
-            "INSERT INTO cortex_chat_messages (id, thread_id, role, content, channel_context) \
-             VALUES (?, ?, ?, ?, ?)",
-        )
-        .bind(&id)
-        .bind(thread_id)
-        .bind(role)
-        .bind(content)
-        .bind(channel_context)
-        .execute(&self.pool)
-        .await?;
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "INSERT INTO cortex_chat_messages (id, thread_id, role, content, channel_context) \
+                 VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(&id)
+            .bind(thread_id)
+            .bind(role)
+            .bind(content)
+            .bind(channel_context)
+            .execute(pool)
+            .await?;
+        });
         Ok(id)
     }
 
     /// Get the most recent thread_id, or None if no threads exist.
     pub async fn latest_thread_id(&self) -> Result<Option<String>, sqlx::Error> {
-        let row: Option<(String,)> = sqlx::query_as(
-            "SELECT thread_id FROM cortex_chat_messages ORDER BY created_at DESC LIMIT 1",
-        )
-        .fetch_optional(&self.pool)
-        .await?;
+        let row: Option<(String,)> = with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT thread_id FROM cortex_chat_messages ORDER BY created_at DESC LIMIT 1",
+            )
+            .fetch_optional(pool)
+            .await?
+        });
         Ok(row.map(|r| r.0))
     }
 }
@@ -263,7 +269,8 @@ impl CortexChatSession {
         let routing = self.deps.runtime_config.routing.load();
         let model_name = routing.resolve(ProcessType::Branch, None).to_string();
         let model = SpacebotModel::make(&self.deps.llm_manager, &model_name)
-            .with_routing((**routing).clone());
+            .with_routing((**routing).clone())
+            .with_agent(self.deps.agent_id.clone());
 
         let agent = AgentBuilder::new(model)
             .preamble(&system_prompt)
@@ -322,9 +329,28 @@ impl CortexChatSession {
 
         let browser_enabled = runtime_config.browser_config.load().enabled;
         let web_search_enabled = runtime_config.brave_search_key.load().is_some();
+        let github_enabled = runtime_config.github.load().is_enabled();
+        let railway_enabled = runtime_config.railway.load().is_enabled();
+        let http_apis: Vec<String> = runtime_config.http_apis.load().keys().cloned().collect();
+        let sql_databases: Vec<String> = runtime_config
+            .sql_databases
+            .load()
+            .keys()
+            .cloned()
+            .collect();
+        let weather_enabled = runtime_config.weather.load().enabled;
         let opencode_enabled = runtime_config.opencode.load().enabled;
         let worker_capabilities = prompt_engine
-            .render_worker_capabilities(browser_enabled, web_search_enabled, opencode_enabled)
+            .render_worker_capabilities(
+                browser_enabled,
+                web_search_enabled,
+                github_enabled,
+                railway_enabled,
+                &http_apis,
+                &sql_databases,
+                weather_enabled,
+                opencode_enabled,
+            )
             .expect("failed to render worker capabilities");
 
         // Load channel transcript if a channel context is active
@@ -348,7 +374,7 @@ impl CortexChatSession {
 
     /// Load the last 50 messages from a channel as a formatted transcript.
     async fn load_channel_transcript(&self, channel_id: &str) -> Option<String> {
-        let logger = ProcessRunLogger::new(self.deps.sqlite_pool.clone());
+        let logger = ProcessRunLogger::new(self.deps.sql_pool.clone());
 
         match logger.load_channel_timeline(channel_id, 50, None).await {
             Ok(items) if !items.is_empty() => {
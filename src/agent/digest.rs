@@ -0,0 +1,328 @@
+//! Daily digest: each morning, summarizes the previous day's activity in the
+//! opted-in channels and posts it to a delivery target.
+//!
+//! A polling loop queues one `digest.daily` job per day once the configured
+//! hour has passed, so a failed summary or delivery is retried by the job
+//! queue. Each finished digest is recorded as a `digest_generated` cortex
+//! event; the loop and the job both check for it, so neither a restart nor a
+//! retry posts the same day twice.
+
+use crate::agent::cortex::CortexLogger;
+use crate::conversation::history::ConversationMessage;
+use crate::conversation::{ChannelStore, ConversationLogger};
+use crate::llm::SpacebotModel;
+use crate::{AgentDeps, OutboundResponse, ProcessType};
+
+use anyhow::Context as _;
+use chrono::{NaiveDate, NaiveDateTime, Timelike as _};
+use rig::agent::AgentBuilder;
+use rig::completion::{CompletionModel as _, Prompt as _};
+
+use std::fmt::Write as _;
+use std::time::Duration;
+
+/// Job kind for one day's digest. Payload: `{"date": "YYYY-MM-DD"}`.
+pub const DAILY_JOB: &str = "digest.daily";
+
+/// Cortex event recorded when a day's digest is posted or skipped.
+const GENERATED_EVENT: &str = "digest_generated";
+
+/// How often the loop checks whether a digest is due.
+const POLL_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Most transcript text sent to the model per channel. A busier channel
+/// drops its oldest messages.
+const CHANNEL_TRANSCRIPT_CHARS: usize = 40_000;
+
+/// What the digest prompt replies when nothing is worth posting.
+const NOTHING_TO_REPORT: &str = "Nothing to report";
+
+/// One channel's messages for the day.
+struct ChannelActivity {
+    heading: String,
+    messages: Vec<ConversationMessage>,
+}
+
+/// Spawn the loop that queues each day's digest.
+///
+/// Runs until the returned JoinHandle is dropped or aborted. The digest
+/// config is re-read on every check, so enabling it takes effect on reload.
+pub fn spawn_digest_loop(deps: AgentDeps) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move { run_digest_loop(&deps).await })
+}
+
+async fn run_digest_loop(deps: &AgentDeps) {
+    tracing::info!("digest loop started");
+    let mut last_queued: Option<NaiveDate> = None;
+
+    loop {
+        let config = deps.runtime_config.digest.load_full();
+        let ready =
+            config.enabled && config.delivery_target.is_some() && !config.channels.is_empty();
+        let due = due_date(chrono::Local::now().naive_local(), config.hour);
+
+        if ready
+            && let Some(date) = due
+            && last_queued != Some(date)
+        {
+            match already_generated(deps, date).await {
+                Ok(true) => last_queued = Some(date),
+                Ok(false) => {
+                    let queued = deps
+                        .jobs
+                        .enqueue_unique(
+                            &deps.agent_id,
+                            &format!("digest:{date}"),
+                            DAILY_JOB,
+                            serde_json::json!({ "date": date.to_string() }),
+                        )
+                        .await;
+                    match queued {
+                        Ok(_) => last_queued = Some(date),
+                        Err(error) => {
+                            tracing::error!(%error, %date, "failed to queue daily digest");
+                        }
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!(%error, %date, "failed to check for an earlier digest");
+                }
+            }
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// The day a digest is due for at local time `now`: yesterday, once `hour`
+/// has passed.
+fn due_date(now: NaiveDateTime, hour: u8) -> Option<NaiveDate> {
+    if now.hour() < u32::from(hour) {
+        return None;
+    }
+    now.date().pred_opt()
+}
+
+/// Whether a digest for `date` was already posted or skipped.
+async fn already_generated(deps: &AgentDeps, date: NaiveDate) -> anyhow::Result<bool> {
+    let events = CortexLogger::new(deps.sql_pool.clone())
+        .load_events(10, 0, Some(GENERATED_EVENT))
+        .await?;
+    let date = date.to_string();
+    Ok(events.iter().any(|event| {
+        event
+            .details
+            .as_ref()
+            .is_some_and(|details| details["date"] == date.as_str())
+    }))
+}
+
+/// Run a `digest.daily` job: summarize the day's messages and post them.
+pub async fn run_daily_job(job: &crate::jobs::Job, deps: &AgentDeps) -> anyhow::Result<()> {
+    let date: NaiveDate = job.payload["date"]
+        .as_str()
+        .context("digest job has no date")?
+        .parse()
+        .context("digest job has an invalid date")?;
+    if already_generated(deps, date).await? {
+        return Ok(());
+    }
+
+    let config = deps.runtime_config.digest.load_full();
+    let target = config
+        .delivery_target
+        .as_deref()
+        .and_then(crate::cron::scheduler::normalize_delivery_target)
+        .context("digest has no valid delivery_target")?;
+    let messaging = deps
+        .messaging_manager
+        .as_ref()
+        .context("no messaging adapters to post the digest with")?;
+
+    let start = local_midnight(date)?;
+    let end = local_midnight(date.succ_opt().context("digest date out of range")?)?;
+    let channel_store = ChannelStore::new(deps.sql_pool.clone());
+    let conversation_logger = ConversationLogger::new(deps.sql_pool.clone());
+    let mut activity = Vec::new();
+    for channel in &config.channels {
+        let info = match channel_store.get(channel).await? {
+            Some(info) => Some(info),
+            None => channel_store.find_by_name(channel).await?,
+        };
+        let Some(info) = info else {
+            tracing::warn!(channel, "digest channel not found, skipping");
+            continue;
+        };
+        let messages = conversation_logger
+            .load_between(&info.id, start, end)
+            .await?;
+        if !messages.is_empty() {
+            let heading = match info.display_name {
+                Some(name) => format!("#{name} ({})", info.id),
+                None => info.id,
+            };
+            activity.push(ChannelActivity { heading, messages });
+        }
+    }
+
+    let cortex_logger = CortexLogger::new(deps.sql_pool.clone());
+    if activity.is_empty() {
+        tracing::info!(%date, "no activity in digest channels, skipping digest");
+        cortex_logger.log(
+            GENERATED_EVENT,
+            &format!("Daily digest for {date} skipped: no activity"),
+            Some(serde_json::json!({ "date": date.to_string(), "posted": false })),
+        );
+        return Ok(());
+    }
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = config
+        .model
+        .clone()
+        .unwrap_or_else(|| routing.resolve(ProcessType::Compactor, None).to_string());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_routing((**routing).clone())
+        .with_agent(deps.agent_id.clone());
+    let preamble = deps.runtime_config.prompts.load().render_static("digest")?;
+    let agent = AgentBuilder::new(model).preamble(&preamble).build();
+    let summary = agent
+        .prompt(&render_activity(date, &activity))
+        .await
+        .context("digest summary failed")?;
+    let summary = summary.trim();
+
+    let posted = !summary
+        .trim_end_matches('.')
+        .eq_ignore_ascii_case(NOTHING_TO_REPORT);
+    if posted {
+        messaging
+            .broadcast(
+                &target.adapter,
+                &target.target,
+                OutboundResponse::Text(format!("**Daily digest for {date}**\n\n{summary}")),
+            )
+            .await
+            .with_context(|| format!("failed to post digest to {target}"))?;
+        tracing::info!(%date, target = %target, "daily digest posted");
+    }
+
+    cortex_logger.log(
+        GENERATED_EVENT,
+        &if posted {
+            format!("Daily digest for {date} posted to {target}")
+        } else {
+            format!("Daily digest for {date} skipped: nothing to report")
+        },
+        Some(serde_json::json!({
+            "date": date.to_string(),
+            "posted": posted,
+            "channels": activity.len(),
+            "model": model_name,
+        })),
+    );
+    Ok(())
+}
+
+fn local_midnight(date: NaiveDate) -> anyhow::Result<chrono::DateTime<chrono::Utc>> {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+        .map(|midnight| midnight.with_timezone(&chrono::Utc))
+        .with_context(|| format!("no local midnight on {date}"))
+}
+
+/// The prompt input: each channel's messages as `[HH:MM] speaker: text`
+/// lines in local time.
+fn render_activity(date: NaiveDate, activity: &[ChannelActivity]) -> String {
+    let mut out = format!("# Conversations on {date}\n");
+    for channel in activity {
+        let mut lines = Vec::new();
+        let mut chars = 0;
+        for message in channel.messages.iter().rev() {
+            let speaker = match message.role.as_str() {
+                "user" => message
+                    .sender_name
+                    .as_deref()
+                    .or(message.sender_id.as_deref())
+                    .unwrap_or("user"),
+                _ => "agent",
+            };
+            let line = format!(
+                "[{}] {speaker}: {}",
+                message
+                    .created_at
+                    .with_timezone(&chrono::Local)
+                    .format("%H:%M"),
+                message.content.trim()
+            );
+            chars += line.len();
+            if chars > CHANNEL_TRANSCRIPT_CHARS && !lines.is_empty() {
+                break;
+            }
+            lines.push(line);
+        }
+
+        let _ = write!(out, "\n## {}\n\n", channel.heading);
+        let omitted = channel.messages.len() - lines.len();
+        if omitted > 0 {
+            let _ = writeln!(out, "[{omitted} earlier messages omitted]");
+        }
+        for line in lines.iter().rev() {
+            let _ = writeln!(out, "{line}");
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, sender_name: Option<&str>, content: &str) -> ConversationMessage {
+        ConversationMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: "discord:1:2".into(),
+            role: role.into(),
+            sender_name: sender_name.map(Into::into),
+            sender_id: Some("42".into()),
+            content: content.into(),
+            metadata: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn digest_is_due_for_yesterday_after_the_hour() {
+        let at = |hour| {
+            NaiveDate::from_ymd_opt(2026, 3, 2)
+                .and_then(|date| date.and_hms_opt(hour, 30, 0))
+                .expect("valid time")
+        };
+        assert_eq!(due_date(at(7), 8), None);
+        assert_eq!(due_date(at(8), 8), NaiveDate::from_ymd_opt(2026, 3, 1));
+        assert_eq!(due_date(at(23), 0), NaiveDate::from_ymd_opt(2026, 3, 1));
+    }
+
+    #[test]
+    fn activity_lists_speakers_by_channel() {
+        let activity = [ChannelActivity {
+            heading: "#general (discord:1:2)".into(),
+            messages: vec![
+                message("user", Some("alice"), "ship it friday?"),
+                message("assistant", None, "  Sounds good.  "),
+                message("user", None, "agreed"),
+            ],
+        }];
+        let date = NaiveDate::from_ymd_opt(2026, 3, 1).expect("valid date");
+        let rendered = render_activity(date, &activity);
+
+        assert!(
+            rendered.starts_with("# Conversations on 2026-03-01\n\n## #general (discord:1:2)\n")
+        );
+        let lines: Vec<&str> = rendered.lines().skip(4).collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("] alice: ship it friday?"));
+        assert!(lines[1].ends_with("] agent: Sounds good."));
+        assert!(lines[2].ends_with("] 42: agreed"));
+    }
+}
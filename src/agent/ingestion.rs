@@ -1,16 +1,25 @@
 //! Memory ingestion: Background file processing for bulk memory import.
 //!
-//! Polls a directory in the agent workspace for supported files, extracts text,
-//! chunks it, and processes each chunk through the memory recall + save flow.
-//! Files are deleted after all chunks are successfully ingested.
+//! Polls a directory in the agent workspace for supported files and queues an
+//! `ingestion.file` job for each. The job extracts text, chunks it, and
+//! processes each chunk through the memory recall + save flow. Files are
+//! deleted after all chunks are successfully ingested; a file with failed
+//! chunks fails its job, so the queue retries it with backoff.
 //!
 //! Progress is tracked per-chunk in SQLite using a SHA-256 hash of the file
 //! content. If the server restarts mid-file, already-completed chunks are
 //! skipped on the next run.
+//!
+//! Every ingested chunk is recorded in `ingestion_chunks` with its source,
+//! content hash, ingestion time, and the memories extracted from it. Web pages
+//! (see [`web`]) use that to re-ingest only the chunks that changed.
+
+pub mod web;
 
 use crate::AgentDeps;
 use crate::ProcessType;
 use crate::config::IngestionConfig;
+use crate::db::{SqlPool, with_pool};
 use crate::llm::SpacebotModel;
 
 use anyhow::Context as _;
@@ -18,17 +27,20 @@ use rig::agent::AgentBuilder;
 use rig::completion::{CompletionModel, Prompt};
 use rig::tool::server::ToolServerHandle;
 use sha2::{Digest, Sha256};
-use sqlx::SqlitePool;
 
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+/// Job kind for ingesting one file. Payload: `{"path": "..."}`.
+pub const FILE_JOB: &str = "ingestion.file";
+
 /// Spawn the ingestion polling loop for an agent.
 ///
 /// Runs until the returned JoinHandle is dropped or aborted. Scans the ingest
-/// directory on a timer, processes any supported files found, and deletes them
-/// after successful ingestion.
+/// directory on a timer and queues a job for each supported file that isn't
+/// already queued, and for each web page that's due a fetch.
 pub fn spawn_ingestion_loop(ingest_dir: PathBuf, deps: AgentDeps) -> tokio::task::JoinHandle<()> {
     tokio::spawn(async move {
         if let Err(error) = run_ingestion_loop(&ingest_dir, &deps).await {
@@ -52,11 +64,21 @@ async fn run_ingestion_loop(ingest_dir: &Path, deps: &AgentDeps) -> anyhow::Resu
         match scan_ingest_dir(ingest_dir).await {
             Ok(files) if !files.is_empty() => {
                 for file_path in files {
-                    if let Err(error) = process_file(&file_path, deps, &config).await {
+                    let path = file_path.to_string_lossy();
+                    if let Err(error) = deps
+                        .jobs
+                        .enqueue_unique(
+                            &deps.agent_id,
+                            &path,
+                            FILE_JOB,
+                            serde_json::json!({ "path": path }),
+                        )
+                        .await
+                    {
                         tracing::error!(
                             path = %file_path.display(),
                             %error,
-                            "failed to ingest file"
+                            "failed to queue file for ingestion"
                         );
                     }
                 }
@@ -68,10 +90,28 @@ async fn run_ingestion_loop(ingest_dir: &Path, deps: &AgentDeps) -> anyhow::Resu
             _ => {}
         }
 
+        if let Err(error) = web::queue_due(deps, &config).await {
+            tracing::warn!(%error, "failed to queue web sources for ingestion");
+        }
+
         tokio::time::sleep(Duration::from_secs(config.poll_interval_secs)).await;
     }
 }
 
+/// Run an `ingestion.file` job. A file that's gone was ingested or removed
+/// since it was queued, so there's nothing left to do.
+pub async fn run_file_job(job: &crate::jobs::Job, deps: &AgentDeps) -> anyhow::Result<()> {
+    let path = job.payload["path"]
+        .as_str()
+        .context("ingestion job has no path")?;
+    let path = Path::new(path);
+    if !tokio::fs::try_exists(path).await.unwrap_or(true) {
+        return Ok(());
+    }
+    let config = **deps.runtime_config.ingestion.load();
+    process_file(path, deps, &config).await
+}
+
 /// Scan the ingest directory for supported ingestion files.
 ///
 /// Returns files sorted by modification time (oldest first) so ingestion
@@ -184,12 +224,12 @@ async fn process_file(
     let chunks = chunk_text(&content, config.chunk_size);
     let total_chunks = chunks.len();
 
-    let completed = load_completed_chunks(&deps.sqlite_pool, &hash).await?;
+    let completed = load_completed_chunks(&deps.sql_pool, &hash).await?;
     let remaining = total_chunks - completed.len();
 
     // Record file-level tracking (idempotent — skips if already exists from a previous run)
     upsert_ingestion_file(
-        &deps.sqlite_pool,
+        &deps.sql_pool,
         &hash,
         filename,
         file_size,
@@ -214,6 +254,7 @@ async fn process_file(
         );
     }
 
+    let source = format!("file:{filename}");
     let mut had_failure = false;
 
     for (index, chunk) in chunks.iter().enumerate() {
@@ -235,10 +276,18 @@ async fn process_file(
             "processing chunk"
         );
 
-        match process_chunk(chunk, filename, chunk_number, total_chunks, deps).await {
-            Ok(()) => {
+        match process_chunk(chunk, filename, &source, chunk_number, total_chunks, deps).await {
+            Ok(memory_ids) => {
+                record_chunk(
+                    &deps.sql_pool,
+                    &source,
+                    &content_hash(chunk),
+                    index as i64,
+                    &memory_ids,
+                )
+                .await?;
                 record_chunk_completed(
-                    &deps.sqlite_pool,
+                    &deps.sql_pool,
                     &hash,
                     index as i64,
                     total_chunks as i64,
@@ -260,22 +309,22 @@ async fn process_file(
 
     // Mark file as completed (or failed if any chunk errored)
     let final_status = if had_failure { "failed" } else { "completed" };
-    complete_ingestion_file(&deps.sqlite_pool, &hash, final_status).await?;
+    complete_ingestion_file(&deps.sql_pool, &hash, final_status).await?;
 
     if had_failure {
-        // Keep the source file and progress rows so the next poll cycle can
-        // resume from where it left off. Deleting on failure would cause data
-        // loss when a provider error interrupts mid-ingestion (fixes #48).
+        // Keep the source file and progress rows so the retry can resume from
+        // where it left off. Deleting on failure would cause data loss when a
+        // provider error interrupts mid-ingestion (fixes #48).
         tracing::warn!(
             file = %filename,
             chunks = total_chunks,
             "file ingestion had failures — keeping file and progress for retry"
         );
-        return Ok(());
+        anyhow::bail!("some chunks of {filename} failed to ingest");
     }
 
     // Full success: clean up progress rows and remove the source file.
-    delete_progress(&deps.sqlite_pool, &hash).await?;
+    delete_progress(&deps.sql_pool, &hash).await?;
 
     tokio::fs::remove_file(path)
         .await
@@ -294,17 +343,7 @@ async fn read_ingest_content(path: &Path) -> anyhow::Result<String> {
     let extension = path.extension().and_then(|extension| extension.to_str());
 
     if extension.is_some_and(|ext| ext.eq_ignore_ascii_case("pdf")) {
-        let bytes = tokio::fs::read(path)
-            .await
-            .with_context(|| format!("failed to read pdf file: {}", path.display()))?;
-
-        let extracted =
-            tokio::task::spawn_blocking(move || pdf_extract::extract_text_from_mem(&bytes))
-                .await
-                .context("pdf extraction task failed")?
-                .with_context(|| format!("failed to extract text from pdf: {}", path.display()))?;
-
-        return Ok(extracted);
+        return read_pdf_text(path).await;
     }
 
     tokio::fs::read_to_string(path)
@@ -312,16 +351,36 @@ async fn read_ingest_content(path: &Path) -> anyhow::Result<String> {
         .with_context(|| format!("failed to read file: {}", path.display()))
 }
 
+#[cfg(feature = "pdf")]
+async fn read_pdf_text(path: &Path) -> anyhow::Result<String> {
+    let bytes = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("failed to read pdf file: {}", path.display()))?;
+
+    tokio::task::spawn_blocking(move || pdf_extract::extract_text_from_mem(&bytes))
+        .await
+        .context("pdf extraction task failed")?
+        .with_context(|| format!("failed to extract text from pdf: {}", path.display()))
+}
+
+#[cfg(not(feature = "pdf"))]
+async fn read_pdf_text(path: &Path) -> anyhow::Result<String> {
+    anyhow::bail!(
+        "can't extract text from {}: this build doesn't include the `pdf` feature",
+        path.display()
+    )
+}
+
 // -- Progress tracking queries --------------------------------------------------
 
 /// Load the set of chunk indices already completed for a given content hash.
-async fn load_completed_chunks(pool: &SqlitePool, hash: &str) -> anyhow::Result<HashSet<i64>> {
-    let rows = sqlx::query_scalar::<_, i64>(
-        "SELECT chunk_index FROM ingestion_progress WHERE content_hash = ?",
-    )
-    .bind(hash)
-    .fetch_all(pool)
-    .await
+async fn load_completed_chunks(pool: &SqlPool, hash: &str) -> anyhow::Result<HashSet<i64>> {
+    let rows: Vec<i64> = with_pool!(pool, |pool| {
+        sqlx::query_scalar("SELECT chunk_index FROM ingestion_progress WHERE content_hash = $1")
+            .bind(hash)
+            .fetch_all(pool)
+            .await
+    })
     .context("failed to load ingestion progress")?;
 
     Ok(rows.into_iter().collect())
@@ -329,36 +388,155 @@ async fn load_completed_chunks(pool: &SqlitePool, hash: &str) -> anyhow::Result<
 
 /// Record a single chunk as completed.
 async fn record_chunk_completed(
-    pool: &SqlitePool,
+    pool: &SqlPool,
     hash: &str,
     chunk_index: i64,
     total_chunks: i64,
     filename: &str,
 ) -> anyhow::Result<()> {
-    sqlx::query(
-        r#"
-        INSERT OR IGNORE INTO ingestion_progress (content_hash, chunk_index, total_chunks, filename)
-        VALUES (?, ?, ?, ?)
-        "#,
-    )
-    .bind(hash)
-    .bind(chunk_index)
-    .bind(total_chunks)
-    .bind(filename)
-    .execute(pool)
-    .await
+    with_pool!(pool, |pool| {
+        sqlx::query(
+            r#"
+            INSERT INTO ingestion_progress (content_hash, chunk_index, total_chunks, filename)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(hash)
+        .bind(chunk_index)
+        .bind(total_chunks)
+        .bind(filename)
+        .execute(pool)
+        .await
+        .map(drop)
+    })
     .context("failed to record ingestion progress")?;
 
     Ok(())
 }
 
 /// Remove all progress records for a content hash after the file is fully processed.
-async fn delete_progress(pool: &SqlitePool, hash: &str) -> anyhow::Result<()> {
-    sqlx::query("DELETE FROM ingestion_progress WHERE content_hash = ?")
+async fn delete_progress(pool: &SqlPool, hash: &str) -> anyhow::Result<()> {
+    with_pool!(pool, |pool| {
+        sqlx::query("DELETE FROM ingestion_progress WHERE content_hash = $1")
+            .bind(hash)
+            .execute(pool)
+            .await
+            .map(drop)
+    })
+    .context("failed to clean up ingestion progress")?;
+
+    Ok(())
+}
+
+// -- Chunk provenance queries ---------------------------------------------------
+
+/// A chunk recorded in `ingestion_chunks`.
+#[derive(Debug, Clone, PartialEq)]
+struct IngestedChunk {
+    /// SHA-256 of the chunk text.
+    content_hash: String,
+    chunk_index: i64,
+    /// Memories saved while the chunk was processed.
+    memory_ids: Vec<String>,
+}
+
+/// Record that the chunk of `source` hashing to `hash` was ingested into
+/// `memory_ids`. Re-ingesting the same text replaces the earlier record.
+async fn record_chunk(
+    pool: &SqlPool,
+    source: &str,
+    hash: &str,
+    chunk_index: i64,
+    memory_ids: &[String],
+) -> anyhow::Result<()> {
+    let memory_ids = serde_json::to_string(memory_ids).context("failed to serialize memory ids")?;
+    with_pool!(pool, |pool| {
+        sqlx::query(
+            r#"
+            INSERT INTO ingestion_chunks (source, content_hash, chunk_index, memory_ids)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (source, content_hash) DO UPDATE SET
+                chunk_index = excluded.chunk_index,
+                memory_ids = excluded.memory_ids,
+                ingested_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(source)
         .bind(hash)
+        .bind(chunk_index)
+        .bind(&memory_ids)
         .execute(pool)
         .await
-        .context("failed to clean up ingestion progress")?;
+        .map(drop)
+    })
+    .context("failed to record ingested chunk")?;
+
+    Ok(())
+}
+
+/// The chunks ingested from `source`, in order.
+async fn load_chunks(pool: &SqlPool, source: &str) -> anyhow::Result<Vec<IngestedChunk>> {
+    use sqlx::Row as _;
+
+    let chunks = with_pool!(pool, |pool| {
+        sqlx::query(
+            "SELECT content_hash, chunk_index, memory_ids FROM ingestion_chunks \
+             WHERE source = $1 ORDER BY chunk_index",
+        )
+        .bind(source)
+        .fetch_all(pool)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| IngestedChunk {
+                    content_hash: row.get("content_hash"),
+                    chunk_index: row.get("chunk_index"),
+                    memory_ids: serde_json::from_str(row.get::<&str, _>("memory_ids"))
+                        .unwrap_or_default(),
+                })
+                .collect()
+        })
+    })
+    .context("failed to load ingested chunks")?;
+
+    Ok(chunks)
+}
+
+/// Move a chunk of `source` that's still there to its new position.
+async fn move_chunk(
+    pool: &SqlPool,
+    source: &str,
+    hash: &str,
+    chunk_index: i64,
+) -> anyhow::Result<()> {
+    with_pool!(pool, |pool| {
+        sqlx::query(
+            "UPDATE ingestion_chunks SET chunk_index = $1 WHERE source = $2 AND content_hash = $3",
+        )
+        .bind(chunk_index)
+        .bind(source)
+        .bind(hash)
+        .execute(pool)
+        .await
+        .map(drop)
+    })
+    .context("failed to update ingested chunk")?;
+
+    Ok(())
+}
+
+/// Remove the record of a chunk of `source`.
+async fn delete_chunk(pool: &SqlPool, source: &str, hash: &str) -> anyhow::Result<()> {
+    with_pool!(pool, |pool| {
+        sqlx::query("DELETE FROM ingestion_chunks WHERE source = $1 AND content_hash = $2")
+            .bind(source)
+            .bind(hash)
+            .execute(pool)
+            .await
+            .map(drop)
+    })
+    .context("failed to delete ingested chunk")?;
 
     Ok(())
 }
@@ -369,46 +547,48 @@ async fn delete_progress(pool: &SqlitePool, hash: &str) -> anyhow::Result<()> {
 /// exists (from the upload handler), update it with chunk info and flip to
 /// `processing`. Otherwise insert a fresh `processing` record.
 async fn upsert_ingestion_file(
-    pool: &SqlitePool,
+    pool: &SqlPool,
     hash: &str,
     filename: &str,
     file_size: i64,
     total_chunks: i64,
 ) -> anyhow::Result<()> {
-    sqlx::query(
-        r#"
-        INSERT INTO ingestion_files (content_hash, filename, file_size, total_chunks, status)
-        VALUES (?, ?, ?, ?, 'processing')
-        ON CONFLICT(content_hash) DO UPDATE SET
-            total_chunks = excluded.total_chunks,
-            status = 'processing'
-        WHERE status = 'queued' OR status = 'processing'
-        "#,
-    )
-    .bind(hash)
-    .bind(filename)
-    .bind(file_size)
-    .bind(total_chunks)
-    .execute(pool)
-    .await
+    with_pool!(pool, |pool| {
+        sqlx::query(
+            r#"
+            INSERT INTO ingestion_files (content_hash, filename, file_size, total_chunks, status)
+            VALUES ($1, $2, $3, $4, 'processing')
+            ON CONFLICT(content_hash) DO UPDATE SET
+                total_chunks = excluded.total_chunks,
+                status = 'processing'
+            WHERE ingestion_files.status = 'queued' OR ingestion_files.status = 'processing'
+            "#,
+        )
+        .bind(hash)
+        .bind(filename)
+        .bind(file_size)
+        .bind(total_chunks)
+        .execute(pool)
+        .await
+        .map(drop)
+    })
     .context("failed to upsert ingestion file record")?;
 
     Ok(())
 }
 
 /// Mark a file as completed or failed.
-async fn complete_ingestion_file(
-    pool: &SqlitePool,
-    hash: &str,
-    status: &str,
-) -> anyhow::Result<()> {
-    sqlx::query(
-        "UPDATE ingestion_files SET status = ?, completed_at = CURRENT_TIMESTAMP WHERE content_hash = ?",
-    )
-    .bind(status)
-    .bind(hash)
-    .execute(pool)
-    .await
+async fn complete_ingestion_file(pool: &SqlPool, hash: &str, status: &str) -> anyhow::Result<()> {
+    with_pool!(pool, |pool| {
+        sqlx::query(
+            "UPDATE ingestion_files SET status = $1, completed_at = CURRENT_TIMESTAMP WHERE content_hash = $2",
+        )
+        .bind(status)
+        .bind(hash)
+        .execute(pool)
+        .await
+        .map(drop)
+    })
     .context("failed to update ingestion file status")?;
 
     Ok(())
@@ -447,7 +627,8 @@ fn chunk_text(text: &str, chunk_size: usize) -> Vec<String> {
     chunks
 }
 
-/// Process a single chunk through the memory recall + save flow.
+/// Process a single chunk through the memory recall + save flow, recording
+/// `source` on the memories it saves. Returns their IDs.
 ///
 /// Creates a fresh LLM agent with memory tools for each chunk. No history
 /// carries over between chunks — each chunk is independent.
@@ -455,10 +636,11 @@ fn chunk_text(text: &str, chunk_size: usize) -> Vec<String> {
 async fn process_chunk(
     chunk: &str,
     filename: &str,
+    source: &str,
     chunk_number: usize,
     total_chunks: usize,
     deps: &AgentDeps,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Vec<String>> {
     let prompt_engine = deps.runtime_config.prompts.load();
     let ingestion_prompt = prompt_engine
         .render_static("ingestion")
@@ -466,16 +648,20 @@ async fn process_chunk(
 
     let routing = deps.runtime_config.routing.load();
     let model_name = routing.resolve(ProcessType::Branch, None).to_string();
-    let model =
-        SpacebotModel::make(&deps.llm_manager, &model_name).with_routing((**routing).clone());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_routing((**routing).clone())
+        .with_agent(deps.agent_id.clone());
 
     let conversation_logger =
-        crate::conversation::history::ConversationLogger::new(deps.sqlite_pool.clone());
-    let channel_store = crate::conversation::ChannelStore::new(deps.sqlite_pool.clone());
-    let tool_server: ToolServerHandle = crate::tools::create_branch_tool_server(
+        crate::conversation::history::ConversationLogger::new(deps.sql_pool.clone());
+    let channel_store = crate::conversation::ChannelStore::new(deps.sql_pool.clone());
+    let saved = Arc::new(Mutex::new(Vec::new()));
+    let tool_server: ToolServerHandle = crate::tools::create_ingestion_tool_server(
         deps.memory_search.clone(),
         conversation_logger,
         channel_store,
+        source.to_string(),
+        saved.clone(),
     );
 
     let agent = AgentBuilder::new(model)
@@ -510,7 +696,8 @@ async fn process_chunk(
         }
     }
 
-    Ok(())
+    let memory_ids = std::mem::take(&mut *saved.lock().expect("saved memory ids lock poisoned"));
+    Ok(memory_ids)
 }
 
 #[cfg(test)]
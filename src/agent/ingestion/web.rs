@@ -0,0 +1,476 @@
+//! Web pages ingested into memory, and kept current.
+//!
+//! A page added with `POST /api/agents/ingest/web` is fetched, converted to
+//! text, chunked, and ingested like a file, with its URL as the source of
+//! every memory extracted from it. Once a page hasn't been checked for
+//! `[ingestion] web_refresh_secs`, the ingestion loop queues an
+//! `ingestion.web` job that fetches it again. An unchanged page is only
+//! stamped as checked. A changed one is diffed chunk by chunk against
+//! `ingestion_chunks` by content hash: only new chunks go through the model,
+//! chunks that moved keep their memories, and the memories of chunks that
+//! are gone are forgotten. If a new chunk fails, the job fails before
+//! anything is forgotten, and the retry skips the chunks that made it.
+
+use super::{IngestedChunk, chunk_text, content_hash, process_chunk};
+use crate::AgentDeps;
+use crate::config::IngestionConfig;
+use crate::db::{SqlPool, timestamp_text, with_pool};
+use crate::injection::InjectionGuard;
+
+use anyhow::Context as _;
+use serde::Serialize;
+use sqlx::Row as _;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// Job kind for fetching and ingesting one page. Payload: `{"url": "..."}`.
+pub const WEB_JOB: &str = "ingestion.web";
+
+/// Largest page fetched.
+const MAX_PAGE_BYTES: usize = 8 * 1024 * 1024;
+
+/// Elements whose content isn't page text.
+const SKIPPED_TAGS: [&str; 6] = ["head", "script", "style", "noscript", "svg", "template"];
+
+/// Elements that start a new line of text.
+const BLOCK_TAGS: [&str; 24] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dt",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "p",
+    "pre",
+    "section",
+    "tr",
+];
+
+/// A page registered for ingestion.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebSource {
+    pub url: String,
+    pub added_at: String,
+    /// When the page was last fetched.
+    pub checked_at: Option<String>,
+    /// When a fetch last found new content and ingested it.
+    pub changed_at: Option<String>,
+    /// Chunks currently ingested from the page.
+    pub chunks: i64,
+}
+
+/// The pages registered for ingestion.
+#[derive(Debug, Clone)]
+pub struct WebSources {
+    pool: SqlPool,
+}
+
+impl WebSources {
+    pub fn new(pool: SqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Register `url`. A page that's already registered is fetched again on
+    /// the next poll.
+    pub async fn add(&self, url: &str) -> anyhow::Result<()> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "INSERT INTO web_sources (url) VALUES ($1) \
+                 ON CONFLICT (url) DO UPDATE SET checked_at = NULL",
+            )
+            .bind(url)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
+        .with_context(|| format!("failed to add web source {url}"))
+    }
+
+    /// Stop fetching `url`. Its memories stay. Returns `false` when it
+    /// wasn't registered.
+    pub async fn remove(&self, url: &str) -> anyhow::Result<bool> {
+        let removed = with_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM web_sources WHERE url = $1")
+                .bind(url)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected() > 0)
+        })
+        .with_context(|| format!("failed to remove web source {url}"))?;
+
+        Ok(removed)
+    }
+
+    pub async fn list(&self) -> anyhow::Result<Vec<WebSource>> {
+        let sources = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT w.url, w.added_at, w.checked_at, w.changed_at, \
+                        COALESCE(c.chunks, 0) AS chunks \
+                 FROM web_sources w \
+                 LEFT JOIN ( \
+                     SELECT source, COUNT(*) AS chunks FROM ingestion_chunks GROUP BY source \
+                 ) c ON c.source = w.url \
+                 ORDER BY w.added_at DESC",
+            )
+            .fetch_all(pool)
+            .await
+            .and_then(|rows| {
+                rows.iter()
+                    .map(|row| {
+                        Ok(WebSource {
+                            url: row.try_get("url")?,
+                            added_at: timestamp_text(row, "added_at")?.unwrap_or_default(),
+                            checked_at: timestamp_text(row, "checked_at")?,
+                            changed_at: timestamp_text(row, "changed_at")?,
+                            chunks: row.try_get("chunks")?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, sqlx::Error>>()
+            })
+        })
+        .context("failed to list web sources")?;
+
+        Ok(sources)
+    }
+
+    /// Pages never fetched, or, with a `refresh` interval, not fetched
+    /// within it.
+    async fn due(&self, refresh: Option<Duration>) -> anyhow::Result<Vec<String>> {
+        let cutoff = refresh
+            .and_then(|refresh| chrono::Duration::from_std(refresh).ok())
+            .map(|refresh| self.pool.timestamp_param(chrono::Utc::now() - refresh));
+        let sql = format!(
+            "SELECT url FROM web_sources WHERE checked_at IS NULL OR checked_at < {}",
+            self.pool.timestamp_placeholder(1)
+        );
+        let urls = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar(&sql).bind(&cutoff).fetch_all(pool).await
+        })
+        .context("failed to load due web sources")?;
+
+        Ok(urls)
+    }
+
+    /// The hash of the page text last ingested from `url`: `None` when the
+    /// page isn't registered, `Some(None)` when it was never ingested.
+    async fn content_hash(&self, url: &str) -> anyhow::Result<Option<Option<String>>> {
+        let hash = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar("SELECT content_hash FROM web_sources WHERE url = $1")
+                .bind(url)
+                .fetch_optional(pool)
+                .await
+        })
+        .with_context(|| format!("failed to load web source {url}"))?;
+
+        Ok(hash)
+    }
+
+    /// Stamp `url` as checked now. When it was re-ingested, `changed` is the
+    /// hash of its new text, and it's stamped as changed too.
+    async fn mark_checked(&self, url: &str, changed: Option<&str>) -> anyhow::Result<()> {
+        with_pool!(&self.pool, |pool| {
+            match changed {
+                Some(hash) => sqlx::query(
+                    "UPDATE web_sources SET content_hash = $1, checked_at = CURRENT_TIMESTAMP, \
+                     changed_at = CURRENT_TIMESTAMP WHERE url = $2",
+                )
+                .bind(hash)
+                .bind(url),
+                None => sqlx::query(
+                    "UPDATE web_sources SET checked_at = CURRENT_TIMESTAMP WHERE url = $1",
+                )
+                .bind(url),
+            }
+            .execute(pool)
+            .await
+            .map(drop)
+        })
+        .with_context(|| format!("failed to update web source {url}"))
+    }
+}
+
+/// Queue an `ingestion.web` job for every page that's due a fetch.
+pub(super) async fn queue_due(deps: &AgentDeps, config: &IngestionConfig) -> anyhow::Result<()> {
+    let refresh =
+        (config.web_refresh_secs > 0).then(|| Duration::from_secs(config.web_refresh_secs));
+    let sources = WebSources::new(deps.sql_pool.clone());
+    for url in sources.due(refresh).await? {
+        deps.jobs
+            .enqueue_unique(
+                &deps.agent_id,
+                &format!("web:{url}"),
+                WEB_JOB,
+                serde_json::json!({ "url": url }),
+            )
+            .await
+            .with_context(|| format!("failed to queue {url} for ingestion"))?;
+    }
+    Ok(())
+}
+
+/// Run an `ingestion.web` job. A page that was removed since it was queued
+/// is skipped.
+pub async fn run_web_job(job: &crate::jobs::Job, deps: &AgentDeps) -> anyhow::Result<()> {
+    let url = job.payload["url"]
+        .as_str()
+        .context("web ingestion job has no url")?;
+    let sources = WebSources::new(deps.sql_pool.clone());
+    let Some(previous_hash) = sources.content_hash(url).await? else {
+        return Ok(());
+    };
+    let config = **deps.runtime_config.ingestion.load();
+
+    let guard = InjectionGuard::new(
+        &deps.runtime_config.injection.load(),
+        deps.agent_id.clone(),
+        None,
+    );
+    let text = fetch_text(url, &guard).await?;
+    if text.trim().is_empty() {
+        anyhow::bail!("{url} has no text");
+    }
+    let hash = content_hash(&text);
+    if previous_hash.as_deref() == Some(hash.as_str()) {
+        tracing::debug!(%url, "web source unchanged");
+        return sources.mark_checked(url, None).await;
+    }
+
+    let chunks = chunk_text(&text, config.chunk_size);
+    let hashes: Vec<String> = chunks.iter().map(|chunk| content_hash(chunk)).collect();
+    let diff = diff_chunks(super::load_chunks(&deps.sql_pool, url).await?, &hashes);
+    tracing::info!(
+        %url,
+        chunks = chunks.len(),
+        added = diff.added.len(),
+        removed = diff.removed.len(),
+        "ingesting changed web source"
+    );
+
+    let total_chunks = chunks.len();
+    let mut had_failure = false;
+    for &index in &diff.added {
+        let chunk_number = index + 1;
+        match process_chunk(&chunks[index], url, url, chunk_number, total_chunks, deps).await {
+            Ok(memory_ids) => {
+                super::record_chunk(
+                    &deps.sql_pool,
+                    url,
+                    &hashes[index],
+                    index as i64,
+                    &memory_ids,
+                )
+                .await?;
+            }
+            Err(error) => {
+                tracing::error!(
+                    %url,
+                    chunk = %format!("{chunk_number}/{total_chunks}"),
+                    %error,
+                    "failed to process chunk"
+                );
+                had_failure = true;
+            }
+        }
+    }
+    if had_failure {
+        anyhow::bail!("some chunks of {url} failed to ingest");
+    }
+
+    for (hash, index) in &diff.moved {
+        super::move_chunk(&deps.sql_pool, url, hash, *index as i64).await?;
+    }
+    let store = deps.memory_search.store();
+    for chunk in &diff.removed {
+        for memory_id in &chunk.memory_ids {
+            store.forget(memory_id).await?;
+        }
+        super::delete_chunk(&deps.sql_pool, url, &chunk.content_hash).await?;
+    }
+    sources.mark_checked(url, Some(&hash)).await?;
+
+    tracing::info!(
+        %url,
+        added = diff.added.len(),
+        forgotten = diff.removed.iter().map(|chunk| chunk.memory_ids.len()).sum::<usize>(),
+        "web source ingested"
+    );
+    Ok(())
+}
+
+/// Fetch `url` as text, converting HTML pages to their visible text. The
+/// page is scanned for prompt injection first, while hidden HTML can still
+/// be told apart.
+async fn fetch_text(url: &str, guard: &InjectionGuard) -> anyhow::Result<String> {
+    let http = crate::egress::client_builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(concat!("spacebot/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("hardcoded reqwest client config");
+    let response = http.get(url).send().await?.error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if !content_type.is_empty()
+        && !content_type.starts_with("text/")
+        && !content_type.contains("json")
+        && !content_type.contains("xml")
+    {
+        anyhow::bail!("can't ingest {url}: unsupported content type {content_type}");
+    }
+    let limits = crate::llm::http::BodyLimits {
+        max_response_bytes: MAX_PAGE_BYTES,
+        ..crate::llm::http::BodyLimits::FETCH
+    };
+    let body = crate::llm::http::read_text(response, limits).await?;
+    let body = guard.inspect_text(&format!("web_source:{url}"), body);
+    Ok(if content_type.contains("html") {
+        page_text(&body)
+    } else {
+        body
+    })
+}
+
+/// The visible text of an HTML page, a line per block element.
+fn page_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if !closing && SKIPPED_TAGS.contains(&name.as_str()) {
+            // ASCII lowercasing keeps byte offsets, so they index `rest`.
+            let close = format!("</{name}");
+            rest = match rest.to_ascii_lowercase().find(&close) {
+                Some(at) => rest[at..].find('>').map_or("", |end| &rest[at + end + 1..]),
+                None => "",
+            };
+        } else if BLOCK_TAGS.contains(&name.as_str()) {
+            text.push('\n');
+        }
+    }
+    text.push_str(rest);
+
+    crate::feeds::parse::unescape(&text.replace("&nbsp;", " "))
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// How a page's chunks changed since it was last ingested.
+#[derive(Debug, Default, PartialEq)]
+struct ChunkDiff {
+    /// Positions of chunks that weren't ingested before.
+    added: Vec<usize>,
+    /// Chunks ingested before that are at a new position, by hash.
+    moved: Vec<(String, usize)>,
+    /// Chunks ingested before that are no longer on the page.
+    removed: Vec<IngestedChunk>,
+}
+
+/// Compare the chunks ingested from a page with the hashes of its chunks
+/// now. A chunk that appears twice is ingested once.
+fn diff_chunks(previous: Vec<IngestedChunk>, hashes: &[String]) -> ChunkDiff {
+    let mut previous: HashMap<String, IngestedChunk> = previous
+        .into_iter()
+        .map(|chunk| (chunk.content_hash.clone(), chunk))
+        .collect();
+    let mut diff = ChunkDiff::default();
+    let mut seen = HashSet::new();
+    for (index, hash) in hashes.iter().enumerate() {
+        if !seen.insert(hash) {
+            continue;
+        }
+        match previous.remove(hash) {
+            Some(chunk) if chunk.chunk_index != index as i64 => {
+                diff.moved.push((hash.clone(), index));
+            }
+            Some(_) => {}
+            None => diff.added.push(index),
+        }
+    }
+    diff.removed = previous.into_values().collect();
+    diff.removed.sort_by_key(|chunk| chunk.chunk_index);
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(hash: &str, index: i64) -> IngestedChunk {
+        IngestedChunk {
+            content_hash: hash.into(),
+            chunk_index: index,
+            memory_ids: vec![format!("memory-{hash}")],
+        }
+    }
+
+    #[test]
+    fn page_text_keeps_visible_text_a_block_per_line() {
+        let html = "<!doctype html><html><head><title>Runbook</title>\
+                    <style>p { color: red }</style></head>\
+                    <body><h1>Restart the <b>API</b></h1><!-- draft -->\
+                    <p>Run <code>just restart</code>&nbsp;&amp; wait.</p>\
+                    <SCRIPT>alert('<p>')</SCRIPT><ul><li>one</li><li>two</li></ul></body></html>";
+        assert_eq!(
+            page_text(html),
+            "Restart the API\nRun just restart & wait.\none\ntwo"
+        );
+    }
+
+    #[test]
+    fn diffs_only_ingest_new_chunks_and_forget_gone_ones() {
+        let hashes = ["a", "x", "c", "c"].map(String::from);
+        let diff = diff_chunks(vec![chunk("a", 0), chunk("b", 1), chunk("c", 3)], &hashes);
+        assert_eq!(
+            diff,
+            ChunkDiff {
+                added: vec![1],
+                moved: vec![("c".into(), 2)],
+                removed: vec![chunk("b", 1)],
+            }
+        );
+
+        assert_eq!(
+            diff_chunks(vec![], &hashes).added,
+            [0, 1, 2],
+            "a page ingested for the first time is all new"
+        );
+    }
+}
@@ -0,0 +1,31 @@
+//! Agent job worker: runs jobs from the agent's queue by kind.
+//!
+//! Each agent has one queue, named after its ID. Compaction stays in-process
+//! because it rewrites the live history of a running channel; only work that
+//! can be described by plain data goes through the queue.
+
+use crate::AgentDeps;
+use crate::agent::{digest, ingestion};
+use crate::alerts;
+use crate::feeds;
+use crate::jobs::Job;
+
+/// Spawn the worker that runs the agent's queued jobs.
+pub fn spawn_job_worker(deps: AgentDeps) -> tokio::task::JoinHandle<()> {
+    let queue = deps.agent_id.to_string();
+    deps.jobs.clone().spawn_worker(queue, move |job| {
+        let deps = deps.clone();
+        async move { run_job(job, &deps).await }
+    })
+}
+
+async fn run_job(job: Job, deps: &AgentDeps) -> anyhow::Result<()> {
+    match job.kind.as_str() {
+        ingestion::FILE_JOB => ingestion::run_file_job(&job, deps).await,
+        ingestion::web::WEB_JOB => ingestion::web::run_web_job(&job, deps).await,
+        digest::DAILY_JOB => digest::run_daily_job(&job, deps).await,
+        alerts::NOTIFY_JOB => alerts::run_notify_job(&job, deps).await,
+        feeds::ENTRY_JOB => feeds::run_entry_job(&job, deps).await,
+        other => anyhow::bail!("no handler for job kind {other:?}"),
+    }
+}
@@ -0,0 +1,259 @@
+//! Reply post-processing.
+//!
+//! Every reply a channel sends, through the `reply` tool or as plain text,
+//! passes through a chain of processors just before it goes out: reasoning
+//! tags some models leak are stripped, runs of blank lines collapsed, banned
+//! phrases removed, and a disclaimer appended. Which processors run, in what
+//! order, and the disclaimer text can be set per channel or per platform.
+
+use regex::Regex;
+
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+/// Closed reasoning blocks. The regex crate has no backreferences, so each
+/// tag gets its own alternative.
+static REASONING_BLOCK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<think>.*?</think>|<thinking>.*?</thinking>|<reasoning>.*?</reasoning>")
+        .expect("hardcoded reasoning block regex")
+});
+
+/// Reasoning whose opening tag was part of the prompt, so only the closing
+/// tag made it into the reply.
+static REASONING_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)^.*?</(?:think|thinking|reasoning)>")
+        .expect("hardcoded reasoning prefix regex")
+});
+
+/// One step of the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Processor {
+    /// Remove `<think>`, `<thinking>`, and `<reasoning>` blocks.
+    StripReasoning,
+    /// Collapse runs of blank lines outside code blocks to one.
+    CollapseBlankLines,
+    /// Remove the configured banned phrases.
+    BannedPhrases,
+    /// Append the channel's disclaimer.
+    Disclaimer,
+}
+
+impl Processor {
+    pub const ALL: [Self; 4] = [
+        Self::StripReasoning,
+        Self::CollapseBlankLines,
+        Self::BannedPhrases,
+        Self::Disclaimer,
+    ];
+
+    /// Name in `processors` lists.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::StripReasoning => "strip_reasoning",
+            Self::CollapseBlankLines => "collapse_blank_lines",
+            Self::BannedPhrases => "banned_phrases",
+            Self::Disclaimer => "disclaimer",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|processor| processor.name() == name)
+    }
+}
+
+/// Post-processing settings, from `[defaults.post_processing]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostProcessConfig {
+    /// Processors every reply goes through, in order.
+    pub processors: Vec<Processor>,
+    /// Phrases removed from replies, matched ignoring case.
+    pub banned_phrases: Vec<String>,
+    /// Text appended under replies by the `disclaimer` processor.
+    pub disclaimer: Option<String>,
+    /// Overrides keyed by channel ID (`discord:123`) or platform (`discord`).
+    /// A channel's own entry wins over its platform's.
+    pub channels: BTreeMap<String, ChannelPostProcess>,
+}
+
+impl Default for PostProcessConfig {
+    fn default() -> Self {
+        Self {
+            processors: Processor::ALL.to_vec(),
+            banned_phrases: Vec::new(),
+            disclaimer: None,
+            channels: BTreeMap::new(),
+        }
+    }
+}
+
+/// What one channel or platform does differently.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelPostProcess {
+    /// Replaces the chain. `None` keeps the default one.
+    pub processors: Option<Vec<Processor>>,
+    /// Replaces the disclaimer. `None` keeps the default one.
+    pub disclaimer: Option<String>,
+}
+
+impl PostProcessConfig {
+    /// Run `channel_id`'s chain over a reply. The result can be empty, for a
+    /// reply that was nothing but reasoning.
+    pub fn apply(&self, channel_id: &str, text: &str) -> String {
+        let platform = channel_id.split(':').next().unwrap_or(channel_id);
+        let overrides = [self.channels.get(channel_id), self.channels.get(platform)];
+        let processors = overrides
+            .iter()
+            .flatten()
+            .find_map(|channel| channel.processors.as_deref())
+            .unwrap_or(&self.processors);
+        let disclaimer = overrides
+            .iter()
+            .flatten()
+            .find_map(|channel| channel.disclaimer.as_deref())
+            .or(self.disclaimer.as_deref())
+            .filter(|disclaimer| !disclaimer.trim().is_empty());
+
+        let mut text = text.to_string();
+        for processor in processors {
+            text = match processor {
+                Processor::StripReasoning => strip_reasoning(&text),
+                Processor::CollapseBlankLines => collapse_blank_lines(&text),
+                Processor::BannedPhrases => remove_phrases(&text, &self.banned_phrases),
+                Processor::Disclaimer => match disclaimer {
+                    Some(disclaimer) => append_disclaimer(&text, disclaimer),
+                    None => text,
+                },
+            };
+        }
+        text
+    }
+}
+
+fn strip_reasoning(text: &str) -> String {
+    let text = REASONING_BLOCK.replace_all(text, "");
+    let text = REASONING_PREFIX.replace(&text, "");
+    text.trim().to_string()
+}
+
+/// Collapse runs of blank lines to one, leaving fenced code blocks as they
+/// are.
+fn collapse_blank_lines(text: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    let mut previous_blank = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        let blank = line.trim().is_empty();
+        if blank && previous_blank && !in_code {
+            continue;
+        }
+        previous_blank = blank;
+        lines.push(if blank && !in_code { "" } else { line });
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// Remove each phrase, ignoring case, with the punctuation and spaces that
+/// follow it, so "Well, as an AI, I can't" loses "as an AI, " whole.
+fn remove_phrases(text: &str, phrases: &[String]) -> String {
+    let mut text = text.to_string();
+    for phrase in phrases.iter().map(|phrase| phrase.trim()) {
+        if phrase.is_empty() {
+            continue;
+        }
+        // Only anchor word edges, so phrases ending in punctuation still match.
+        let edge = |character: Option<char>| {
+            if character.is_some_and(char::is_alphanumeric) {
+                r"\b"
+            } else {
+                ""
+            }
+        };
+        let pattern = format!(
+            r"(?i){}{}{}[,;:]?[ \t]*",
+            edge(phrase.chars().next()),
+            regex::escape(phrase),
+            edge(phrase.chars().last()),
+        );
+        let regex = Regex::new(&pattern).expect("escaped phrase is a valid regex");
+        text = regex.replace_all(&text, "").into_owned();
+    }
+    text.trim().to_string()
+}
+
+fn append_disclaimer(text: &str, disclaimer: &str) -> String {
+    let disclaimer = disclaimer.trim();
+    if text.is_empty() || text.ends_with(disclaimer) {
+        return text.to_string();
+    }
+    format!("{text}\n\n{disclaimer}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PostProcessConfig {
+        PostProcessConfig {
+            banned_phrases: vec!["as an AI language model".into(), "Great question!".into()],
+            disclaimer: Some("Replies may be wrong.".into()),
+            ..PostProcessConfig::default()
+        }
+    }
+
+    #[test]
+    fn the_default_chain_cleans_up_replies() {
+        let reply = "<think>The user wants a haiku.\n</think>\n\nGreat question! Well, As an AI \
+                     language model, I can't feel.\n\n\n\n```\na\n\n\nb\n```";
+        assert_eq!(
+            config().apply("discord:1", reply),
+            "Well, I can't feel.\n\n```\na\n\n\nb\n```\n\nReplies may be wrong."
+        );
+
+        // Reasoning whose opening tag was in the prompt, and nothing else.
+        assert_eq!(
+            config().apply("discord:1", "planning the answer</think>Hi."),
+            "Hi.\n\nReplies may be wrong."
+        );
+        assert_eq!(config().apply("discord:1", "<thinking>hm</thinking>"), "");
+    }
+
+    #[test]
+    fn channels_and_platforms_override_the_chain() {
+        let mut config = config();
+        config.channels.insert(
+            "discord".into(),
+            ChannelPostProcess {
+                processors: Some(vec![Processor::StripReasoning, Processor::Disclaimer]),
+                disclaimer: None,
+            },
+        );
+        config.channels.insert(
+            "discord:2".into(),
+            ChannelPostProcess {
+                processors: None,
+                disclaimer: Some("Not financial advice.".into()),
+            },
+        );
+
+        let reply = "<think>x</think>Great question! Buy low.";
+        assert_eq!(
+            config.apply("discord:1", reply),
+            "Great question! Buy low.\n\nReplies may be wrong."
+        );
+        assert_eq!(
+            config.apply("discord:2", reply),
+            "Great question! Buy low.\n\nNot financial advice."
+        );
+        assert_eq!(
+            config.apply("slack:C1", reply),
+            "Buy low.\n\nReplies may be wrong."
+        );
+        assert_eq!(Processor::parse("disclaimer"), Some(Processor::Disclaimer));
+        assert_eq!(Processor::parse("shout"), None);
+    }
+}
@@ -0,0 +1,413 @@
+//! `!admin selftest`: a one-shot smoke test to run after a deploy.
+//!
+//! Each configured provider gets a canned prompt on the first model the
+//! agent routes to it, three ways: as a plain completion, with a tool it's
+//! asked to call, and as a streaming request timed to the first streamed
+//! event. Requests go straight to that model with no fallbacks, so a broken
+//! provider can't hide behind a working one. Persistence is checked by
+//! writing a row to the agent's database in a transaction, reading it
+//! back, and rolling it back.
+
+use crate::AgentDeps;
+use crate::db::{SqlPool, with_pool};
+use crate::llm::manager::LlmManager;
+use crate::llm::routing::RoutingConfig;
+use crate::llm::{SpacebotModel, routing};
+
+use futures::StreamExt as _;
+use rig::completion::{CompletionModel, CompletionRequest, ToolDefinition};
+use rig::message::{AssistantContent, Message};
+use rig::one_or_many::OneOrMany;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Tool the tool-calling check offers and expects to be called.
+const TOOL_NAME: &str = "selftest_lookup";
+
+/// How long any one check may take before it fails.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Characters of an unexpected reply quoted in a failure.
+const QUOTED_REPLY_CHARS: usize = 80;
+
+/// One timed check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    /// Why the check failed, if it did.
+    pub outcome: Result<(), String>,
+    pub latency: Duration,
+}
+
+/// The checks run against one provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderChecks {
+    pub provider: String,
+    /// The model tested, or `None` when the agent routes nothing to the
+    /// provider and it was skipped.
+    pub model: Option<String>,
+    pub checks: Vec<Check>,
+}
+
+/// The whole self-test.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub providers: Vec<ProviderChecks>,
+    pub persistence: Check,
+    pub elapsed: Duration,
+}
+
+/// Run every check, providers concurrently.
+pub async fn run(deps: &AgentDeps) -> Report {
+    let started = Instant::now();
+    let manager = &deps.llm_manager;
+    let providers = manager.providers();
+    let models = routed_models(
+        &deps.routing(),
+        providers
+            .iter()
+            .map(|(provider_id, _)| provider_id.as_str()),
+    );
+
+    let provider_checks = providers.into_iter().map(|(provider_id, _)| {
+        let model = models.get(&provider_id).cloned();
+        async move {
+            let checks = match &model {
+                Some(model) => check_provider(manager, model).await,
+                None => Vec::new(),
+            };
+            ProviderChecks {
+                provider: provider_id,
+                model,
+                checks,
+            }
+        }
+    });
+    let (providers, persistence) = tokio::join!(
+        futures::future::join_all(provider_checks),
+        timed("write", check_persistence(&deps.sql_pool)),
+    );
+
+    Report {
+        providers,
+        persistence,
+        elapsed: started.elapsed(),
+    }
+}
+
+/// The first model `routing` sends to each of `providers`, in the order
+/// process models, task overrides, then fallbacks.
+fn routed_models<'a>(
+    routing: &RoutingConfig,
+    providers: impl Iterator<Item = &'a str>,
+) -> BTreeMap<String, String> {
+    let routed: Vec<&str> = crate::llm::discovery::routing_models(routing).collect();
+    providers
+        .filter_map(|provider| {
+            routed
+                .iter()
+                .find(|model| routing::provider_from_model(model) == provider)
+                .map(|model| (provider.to_string(), model.to_string()))
+        })
+        .collect()
+}
+
+async fn check_provider(manager: &Arc<LlmManager>, model_name: &str) -> Vec<Check> {
+    let model = SpacebotModel::make(manager, model_name);
+    vec![
+        timed("completion", check_completion(&model)).await,
+        timed("tools", check_tool_call(&model)).await,
+        timed("streaming", check_streaming(&model)).await,
+    ]
+}
+
+/// Run `check`, failing it when it takes longer than [`CHECK_TIMEOUT`].
+async fn timed(name: &'static str, check: impl Future<Output = Result<(), String>>) -> Check {
+    let started = Instant::now();
+    let outcome = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(outcome) => outcome,
+        Err(_) => Err(format!("timed out after {}s", CHECK_TIMEOUT.as_secs())),
+    };
+    Check {
+        name,
+        outcome,
+        latency: started.elapsed(),
+    }
+}
+
+fn request(tools: Vec<ToolDefinition>) -> CompletionRequest {
+    CompletionRequest {
+        preamble: Some(crate::prompts::text::get("fragments/system/selftest").to_string()),
+        chat_history: OneOrMany::one(Message::user("ping")),
+        documents: Vec::new(),
+        tools,
+        temperature: None,
+        max_tokens: None,
+        tool_choice: None,
+        additional_params: None,
+    }
+}
+
+async fn check_completion(model: &SpacebotModel) -> Result<(), String> {
+    let response = model
+        .completion(request(Vec::new()))
+        .await
+        .map_err(|error| error.to_string())?;
+    let text = reply_text(&response.choice);
+    if text.to_lowercase().contains("pong") {
+        Ok(())
+    } else {
+        Err(format!("expected \"pong\", got {:?}", quote(&text)))
+    }
+}
+
+async fn check_tool_call(model: &SpacebotModel) -> Result<(), String> {
+    let tool = ToolDefinition {
+        name: TOOL_NAME.to_string(),
+        description: "Look up a word. Call this with the query \"pong\".".to_string(),
+        parameters: serde_json::json!({
+            "type": "object",
+            "properties": { "query": { "type": "string" } },
+            "required": ["query"],
+        }),
+    };
+    let response = model
+        .completion(request(vec![tool]))
+        .await
+        .map_err(|error| error.to_string())?;
+    let called = response.choice.iter().any(|content| {
+        matches!(content, AssistantContent::ToolCall(call) if call.function.name == TOOL_NAME)
+    });
+    if called {
+        Ok(())
+    } else {
+        Err(format!(
+            "no `{TOOL_NAME}` call, replied {:?}",
+            quote(&reply_text(&response.choice))
+        ))
+    }
+}
+
+fn reply_text(choice: &OneOrMany<AssistantContent>) -> String {
+    choice
+        .iter()
+        .filter_map(|content| match content {
+            AssistantContent::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn quote(text: &str) -> String {
+    let text = text.trim();
+    if text.chars().count() <= QUOTED_REPLY_CHARS {
+        text.to_string()
+    } else {
+        let quoted: String = text.chars().take(QUOTED_REPLY_CHARS).collect();
+        format!("{quoted}…")
+    }
+}
+
+/// Stream the canned prompt and wait for the first event.
+async fn check_streaming(model: &SpacebotModel) -> Result<(), String> {
+    let mut stream = model
+        .stream_completion(request(Vec::new()))
+        .await
+        .map_err(|error| error.to_string())?;
+    match stream.events.next().await {
+        Some(Ok(_)) => Ok(()),
+        Some(Err(error)) => Err(error.to_string()),
+        None => Err("the stream ended without an event".to_string()),
+    }
+}
+
+async fn check_persistence(pool: &SqlPool) -> Result<(), String> {
+    let id = format!("selftest-{}", uuid::Uuid::new_v4());
+    let stored = with_pool!(pool, |pool| {
+        async {
+            let mut transaction = pool.begin().await?;
+            sqlx::query("INSERT INTO cortex_events (id, event_type, summary) VALUES ($1, $2, $3)")
+                .bind(&id)
+                .bind("selftest")
+                .bind("self-test write, rolled back")
+                .execute(&mut *transaction)
+                .await?;
+            let stored: Option<String> =
+                sqlx::query_scalar("SELECT id FROM cortex_events WHERE id = $1")
+                    .bind(&id)
+                    .fetch_optional(&mut *transaction)
+                    .await?;
+            transaction.rollback().await?;
+            Ok::<_, sqlx::Error>(stored)
+        }
+        .await
+    })
+    .map_err(|error| error.to_string())?;
+    match stored {
+        Some(_) => Ok(()),
+        None => Err("the row written couldn't be read back".to_string()),
+    }
+}
+
+/// The `!admin selftest` reply: a line per provider with each check's
+/// result and latency, failure reasons under it, then the database.
+pub fn render(report: &Report) -> String {
+    let checks: Vec<&Check> = report
+        .providers
+        .iter()
+        .flat_map(|provider| &provider.checks)
+        .chain(std::iter::once(&report.persistence))
+        .collect();
+    let passed = checks.iter().filter(|check| check.outcome.is_ok()).count();
+    let mut lines = vec![format!(
+        "Self-test: {passed} of {} checks passed in {:.1}s.",
+        checks.len(),
+        report.elapsed.as_secs_f64()
+    )];
+
+    for provider in &report.providers {
+        let Some(model) = &provider.model else {
+            lines.push(format!(
+                "- `{}`: skipped, no routed model",
+                provider.provider
+            ));
+            continue;
+        };
+        lines.push(format!(
+            "- `{}` ({model}): {}",
+            provider.provider,
+            summary(&provider.checks)
+        ));
+        lines.extend(failures(&provider.checks));
+    }
+    lines.push(format!(
+        "- database: {}",
+        summary(std::slice::from_ref(&report.persistence))
+    ));
+    lines.extend(failures(std::slice::from_ref(&report.persistence)));
+    lines.join("\n")
+}
+
+/// "completion pass 1.20s, tools FAIL 0.31s".
+fn summary(checks: &[Check]) -> String {
+    checks
+        .iter()
+        .map(|check| {
+            let result = if check.outcome.is_ok() {
+                "pass"
+            } else {
+                "FAIL"
+            };
+            format!(
+                "{} {result} {:.2}s",
+                check.name,
+                check.latency.as_secs_f64()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn failures(checks: &[Check]) -> impl Iterator<Item = String> + '_ {
+    checks.iter().filter_map(|check| {
+        check
+            .outcome
+            .as_ref()
+            .err()
+            .map(|error| format!("  - {}: {error}", check.name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(name: &'static str, outcome: Result<(), String>, millis: u64) -> Check {
+        Check {
+            name,
+            outcome,
+            latency: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn each_provider_is_tested_on_the_first_model_routed_to_it() {
+        let routing = RoutingConfig {
+            channel: "anthropic/claude-sonnet-4".into(),
+            branch: "anthropic/claude-haiku-4-5".into(),
+            worker: "openrouter/openai/gpt-4.1".into(),
+            fallbacks: std::collections::HashMap::from([(
+                "anthropic/claude-sonnet-4".to_string(),
+                vec!["groq/llama-3.3-70b".to_string()],
+            )]),
+            ..RoutingConfig::default()
+        };
+
+        let models = routed_models(
+            &routing,
+            ["anthropic", "groq", "openrouter", "ollama"].into_iter(),
+        );
+
+        assert_eq!(
+            models,
+            BTreeMap::from([
+                (
+                    "anthropic".to_string(),
+                    "anthropic/claude-sonnet-4".to_string()
+                ),
+                ("groq".to_string(), "groq/llama-3.3-70b".to_string()),
+                (
+                    "openrouter".to_string(),
+                    "openrouter/openai/gpt-4.1".to_string()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn render_shows_a_row_per_provider_with_failure_reasons() {
+        let report = Report {
+            providers: vec![
+                ProviderChecks {
+                    provider: "anthropic".into(),
+                    model: Some("anthropic/claude-sonnet-4".into()),
+                    checks: vec![
+                        check("completion", Ok(()), 1200),
+                        check("tools", Ok(()), 1520),
+                        check("streaming", Ok(()), 410),
+                    ],
+                },
+                ProviderChecks {
+                    provider: "ollama".into(),
+                    model: Some("ollama/llama3.1".into()),
+                    checks: vec![
+                        check("completion", Ok(()), 800),
+                        check("tools", Err("no `selftest_lookup` call".into()), 650),
+                        check("streaming", Ok(()), 90),
+                    ],
+                },
+                ProviderChecks {
+                    provider: "groq".into(),
+                    model: None,
+                    checks: Vec::new(),
+                },
+            ],
+            persistence: check("write", Ok(()), 4),
+            elapsed: Duration::from_millis(2950),
+        };
+
+        assert_eq!(
+            render(&report),
+            "Self-test: 6 of 7 checks passed in 3.0s.\n\
+             - `anthropic` (anthropic/claude-sonnet-4): completion pass 1.20s, tools pass 1.52s, \
+             streaming pass 0.41s\n\
+             - `ollama` (ollama/llama3.1): completion pass 0.80s, tools FAIL 0.65s, \
+             streaming pass 0.09s\n\
+             \x20 - tools: no `selftest_lookup` call\n\
+             - `groq`: skipped, no routed model\n\
+             - database: write pass 0.00s"
+        );
+    }
+}
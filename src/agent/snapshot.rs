@@ -0,0 +1,191 @@
+//! Snapshots of what went into a channel turn, for `!snapshot`.
+//!
+//! The channel keeps the snapshot of its most recent LLM turn: every section
+//! of the system prompt, the history the model saw, the user message, which
+//! model routing picked, the tools on offer, and the tool calls the turn made
+//! (memory recalls included). When an answer goes wrong, the snapshot shows
+//! exactly what the model was working from.
+
+use crate::config::RuntimeConfig;
+use crate::conversation::history::ToolCallRecord;
+use crate::prompts::PromptEngine;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// The sections the channel system prompt is rendered from.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PromptLayers {
+    pub identity: Option<String>,
+    pub memory_bulletin: Option<String>,
+    pub skills: Option<String>,
+    pub flows: Option<String>,
+    pub worker_capabilities: String,
+    pub conversation_context: Option<String>,
+    pub status: Option<String>,
+    /// Only set for batches of coalesced messages.
+    pub coalesce_hint: Option<String>,
+    pub available_channels: Option<String>,
+}
+
+impl PromptLayers {
+    /// The sections that come from config and workspace files alone. Flows
+    /// are listed without an active run; the conversation's own sections
+    /// are left for the caller.
+    pub fn from_config(rc: &RuntimeConfig, prompt_engine: &PromptEngine) -> Self {
+        let identity_context = rc.identity.load().render();
+        let memory_bulletin = rc.memory_bulletin.load();
+        let skills_prompt = rc.skills.load().render_channel_prompt(prompt_engine);
+        let flows_prompt = rc.flows.load().render_channel_prompt(None);
+
+        let http_apis: Vec<String> = rc.http_apis.load().keys().cloned().collect();
+        let sql_databases: Vec<String> = rc.sql_databases.load().keys().cloned().collect();
+        let worker_capabilities = prompt_engine
+            .render_worker_capabilities(
+                rc.browser_config.load().enabled,
+                rc.brave_search_key.load().is_some(),
+                rc.github.load().is_enabled(),
+                rc.railway.load().is_enabled(),
+                &http_apis,
+                &sql_databases,
+                rc.weather.load().enabled,
+                rc.opencode.load().enabled,
+            )
+            .expect("failed to render worker capabilities");
+
+        let empty_to_none = |s: String| if s.is_empty() { None } else { Some(s) };
+
+        Self {
+            identity: empty_to_none(identity_context),
+            memory_bulletin: empty_to_none(memory_bulletin.to_string()),
+            skills: empty_to_none(skills_prompt),
+            flows: empty_to_none(flows_prompt),
+            worker_capabilities,
+            ..Self::default()
+        }
+    }
+
+    pub fn render(&self, prompt_engine: &PromptEngine) -> crate::error::Result<String> {
+        prompt_engine.render_channel_prompt(
+            self.identity.clone(),
+            self.memory_bulletin.clone(),
+            self.skills.clone(),
+            self.flows.clone(),
+            self.worker_capabilities.clone(),
+            self.conversation_context.clone(),
+            self.status.clone(),
+            self.coalesce_hint.clone(),
+            self.available_channels.clone(),
+        )
+    }
+}
+
+/// The model a turn was routed to.
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutingDecision {
+    pub model: String,
+    pub thinking_effort: String,
+    /// Models tried in order if this one fails with a retriable error.
+    pub fallbacks: Vec<String>,
+    pub max_turns: usize,
+}
+
+/// Everything one channel turn's prompt was built from.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptSnapshot {
+    pub agent_id: String,
+    pub channel_id: String,
+    pub correlation_id: Option<String>,
+    pub taken_at: DateTime<Utc>,
+    pub routing: RoutingDecision,
+    /// The rendered system prompt, as sent.
+    pub system_prompt: String,
+    pub layers: PromptLayers,
+    /// Tools the model could call this turn.
+    pub tools: Vec<String>,
+    /// The context window before the turn: prior history, attachments included.
+    pub history: Vec<rig::message::Message>,
+    /// The message the turn answered, after `before_llm` scripts.
+    pub user_message: String,
+    /// Results of the `memory_recall` calls the turn made.
+    pub memories_retrieved: Vec<ToolCallRecord>,
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// Set when the turn failed.
+    pub error: Option<String>,
+}
+
+impl PromptSnapshot {
+    pub fn file_name(&self) -> String {
+        let slug: String = self
+            .channel_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        format!(
+            "snapshot-{slug}-{}.json",
+            self.taken_at.format("%Y%m%d-%H%M%S")
+        )
+    }
+
+    /// Split a turn's tool calls into memory recalls and the full list.
+    pub fn with_tool_calls(mut self, tool_calls: Vec<ToolCallRecord>) -> Self {
+        self.memories_retrieved = tool_calls
+            .iter()
+            .filter(|call| call.name == "memory_recall")
+            .cloned()
+            .collect();
+        self.tool_calls = tool_calls;
+        self
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshots_pick_out_memory_recalls() {
+        let call = |name: &str| ToolCallRecord {
+            name: name.into(),
+            arguments: serde_json::json!({ "query": "deploy schedule" }),
+            result: Some("Deploys happen on Tuesdays.".into()),
+        };
+        let snapshot = PromptSnapshot {
+            agent_id: "main".into(),
+            channel_id: "discord:123:456".into(),
+            correlation_id: Some("c-1".into()),
+            taken_at: "2026-10-15T09:30:00Z".parse().unwrap(),
+            routing: RoutingDecision {
+                model: "anthropic/claude-sonnet-4".into(),
+                thinking_effort: "auto".into(),
+                fallbacks: Vec::new(),
+                max_turns: 5,
+            },
+            system_prompt: "You are helpful.".into(),
+            layers: PromptLayers::default(),
+            tools: vec!["reply".into(), "memory_recall".into()],
+            history: vec![rig::message::Message::user("when do we deploy?")],
+            user_message: "and this week?".into(),
+            memories_retrieved: Vec::new(),
+            tool_calls: Vec::new(),
+            error: None,
+        }
+        .with_tool_calls(vec![call("memory_recall"), call("reply")]);
+
+        assert_eq!(snapshot.memories_retrieved.len(), 1);
+        assert_eq!(snapshot.tool_calls.len(), 2);
+        assert_eq!(
+            snapshot.file_name(),
+            "snapshot-discord-123-456-20261015-093000.json"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&snapshot.to_json().expect("json")).expect("valid json");
+        assert_eq!(json["routing"]["model"], "anthropic/claude-sonnet-4");
+        assert_eq!(json["history"][0]["role"], "user");
+    }
+}
@@ -0,0 +1,336 @@
+//! Claim verification for answers built from retrieved material.
+//!
+//! When a branch answers from recalled memories or past conversations, or a
+//! worker from web search results, a judge model checks each factual claim
+//! in the answer against what was actually retrieved. Claims the material
+//! doesn't support are removed from the answer or listed in a caveat under
+//! it, as configured for the knowledge base they were retrieved from. The
+//! judge runs on the `verification` task model.
+
+use crate::conversation::history::{ToolCallRecord, tool_calls_in};
+use crate::llm::SpacebotModel;
+use crate::llm::structured::OutputFormat;
+use crate::{AgentDeps, ProcessType};
+
+use rig::completion::{CompletionModel, CompletionRequest};
+use rig::message::Message;
+use rig::one_or_many::OneOrMany;
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+
+/// Task name whose `task_overrides` entry picks the judge model.
+const TASK: &str = "verification";
+
+/// Most retrieved material the judge is shown, in bytes.
+const MAX_EVIDENCE_BYTES: usize = 24_000;
+
+/// Where a branch or worker retrieved material from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KnowledgeBase {
+    /// Memories, via `memory_recall`.
+    Memory,
+    /// Past conversations, via `channel_recall`.
+    History,
+    /// Web search results, via `web_search`.
+    Web,
+}
+
+impl KnowledgeBase {
+    pub const ALL: [Self; 3] = [Self::Memory, Self::History, Self::Web];
+
+    /// The knowledge base a retrieval tool reads from.
+    pub fn of_tool(tool_name: &str) -> Option<Self> {
+        match tool_name {
+            "memory_recall" => Some(Self::Memory),
+            "channel_recall" => Some(Self::History),
+            "web_search" => Some(Self::Web),
+            _ => None,
+        }
+    }
+
+    /// Key under `[defaults.verification]`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Memory => "memory",
+            Self::History => "history",
+            Self::Web => "web",
+        }
+    }
+}
+
+/// What happens to claims the retrieved material doesn't support. Ordered
+/// from least to most strict.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VerificationAction {
+    /// Keep the answer and list the unsupported claims under it.
+    Caveat,
+    /// Cut the unsupported sentences out of the answer.
+    Remove,
+}
+
+impl VerificationAction {
+    /// Parse a config value; `"off"` is `None`.
+    pub fn parse(value: &str) -> Result<Option<Self>, String> {
+        match value {
+            "remove" => Ok(Some(Self::Remove)),
+            "caveat" => Ok(Some(Self::Caveat)),
+            "off" => Ok(None),
+            other => Err(format!(
+                "unknown action '{other}', expected \"remove\", \"caveat\", or \"off\""
+            )),
+        }
+    }
+}
+
+/// Which knowledge bases get their answers verified, and how.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VerificationConfig {
+    pub sources: BTreeMap<KnowledgeBase, VerificationAction>,
+}
+
+impl VerificationConfig {
+    /// Material retrieved from verified knowledge bases in `tool_calls`, with
+    /// the strictest action among the bases it came from.
+    pub fn evidence(
+        &self,
+        tool_calls: &[ToolCallRecord],
+    ) -> Option<(VerificationAction, Vec<String>)> {
+        let mut action = None;
+        let mut evidence = Vec::new();
+        for call in tool_calls {
+            let Some(base) = KnowledgeBase::of_tool(&call.name) else {
+                continue;
+            };
+            let (Some(base_action), Some(result)) = (self.sources.get(&base), &call.result) else {
+                continue;
+            };
+            action = action.max(Some(*base_action));
+            evidence.push(format!("[{}] {result}", base.name()));
+        }
+        action.map(|action| (action, evidence))
+    }
+}
+
+/// A claim the judge found no support for.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct UnsupportedClaim {
+    /// The claim, restated briefly.
+    pub claim: String,
+    /// The sentence in the answer that makes it, verbatim.
+    pub quote: String,
+}
+
+#[derive(Deserialize)]
+struct Verdict {
+    unsupported: Vec<UnsupportedClaim>,
+}
+
+/// Build the request asking the judge which claims in `answer` the
+/// `evidence` doesn't support.
+pub fn judge_request(answer: &str, evidence: &[String]) -> CompletionRequest {
+    let evidence = crate::tools::truncate_output(&evidence.join("\n\n"), MAX_EVIDENCE_BYTES);
+    let prompt = format!("Retrieved material:\n{evidence}\n\nAnswer:\n{answer}\n");
+
+    let output_format = OutputFormat::JsonSchema {
+        name: "claim_verdict".into(),
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "unsupported": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "claim": { "type": "string" },
+                            "quote": { "type": "string" },
+                        },
+                        "required": ["claim", "quote"],
+                    },
+                },
+            },
+            "required": ["unsupported"],
+        }),
+    };
+
+    CompletionRequest {
+        preamble: Some(crate::prompts::text::get("fragments/system/claim_judge").to_string()),
+        chat_history: OneOrMany::one(Message::user(prompt)),
+        documents: Vec::new(),
+        tools: Vec::new(),
+        temperature: Some(0.0),
+        max_tokens: None,
+        tool_choice: None,
+        additional_params: Some(output_format.to_params()),
+    }
+}
+
+/// Parse the judge's JSON verdict.
+pub fn parse_verdict(reply: &str) -> anyhow::Result<Vec<UnsupportedClaim>> {
+    let verdict: Verdict = serde_json::from_str(crate::llm::structured::strip_code_fence(reply))?;
+    Ok(verdict.unsupported)
+}
+
+/// Cut each claim's quoted sentence out of `answer`. Returns the trimmed
+/// answer and the claims whose quote couldn't be found, which still need a
+/// caveat.
+pub fn remove_claims(answer: &str, claims: Vec<UnsupportedClaim>) -> (String, Vec<String>) {
+    let mut answer = answer.to_string();
+    let mut missing = Vec::new();
+    for claim in claims {
+        let quote = claim.quote.trim();
+        let Some(mut start) = answer.find(quote).filter(|_| !quote.is_empty()) else {
+            missing.push(claim.claim);
+            continue;
+        };
+        // Take one neighbouring space with the sentence so no gap is left.
+        let mut end = start + quote.len();
+        if answer[end..].starts_with(' ') {
+            end += 1;
+        } else if answer[..start].ends_with(' ') {
+            start -= 1;
+        }
+        answer.replace_range(start..end, "");
+    }
+
+    // Drop list items the cuts emptied, then runs of blank lines.
+    let mut lines: Vec<&str> = Vec::new();
+    for line in answer.lines() {
+        if matches!(line.trim(), "-" | "*" | "•") {
+            continue;
+        }
+        if line.trim().is_empty() && lines.last().is_some_and(|last| last.trim().is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    (lines.join("\n").trim().to_string(), missing)
+}
+
+/// Check `answer` against the material retrieved in `history` and handle
+/// unsupported claims as configured. The answer is returned unchanged when
+/// nothing was retrieved from a verified knowledge base, or when the judge
+/// fails.
+pub async fn verify(deps: &AgentDeps, answer: String, history: &[Message]) -> String {
+    let config = deps.runtime_config.verification.load();
+    let tool_calls = tool_calls_in(history);
+    let Some((action, evidence)) = config.evidence(&tool_calls) else {
+        return answer;
+    };
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = routing.resolve(ProcessType::Branch, Some(TASK)).to_string();
+    let judge = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_routing((**routing).clone())
+        .with_agent(deps.agent_id.clone());
+
+    let claims = match judge.completion(judge_request(&answer, &evidence)).await {
+        Ok(response) => {
+            let reply = crate::llm::sampling::reply_text(&response.choice);
+            match parse_verdict(&reply) {
+                Ok(claims) => claims,
+                Err(error) => {
+                    tracing::warn!(%error, "unreadable claim verdict, keeping answer as is");
+                    return answer;
+                }
+            }
+        }
+        Err(error) => {
+            tracing::warn!(%error, "claim verification failed, keeping answer as is");
+            return answer;
+        }
+    };
+    if claims.is_empty() {
+        return answer;
+    }
+
+    let claim_count = claims.len();
+    let (mut answer, caveated) = match action {
+        VerificationAction::Remove => remove_claims(&answer, claims),
+        VerificationAction::Caveat => (answer, claims.into_iter().map(|c| c.claim).collect()),
+    };
+    let removed = claim_count - caveated.len();
+    tracing::info!(
+        agent_id = %deps.agent_id,
+        unsupported = claim_count,
+        removed,
+        "verified answer against retrieved material"
+    );
+
+    let prompt_engine = deps.runtime_config.prompts.load();
+    match prompt_engine.render_system_unsupported_claims(removed, &caveated) {
+        Ok(note) => {
+            answer.push_str("\n\n");
+            answer.push_str(&note);
+        }
+        Err(error) => tracing::warn!(%error, "failed to render unsupported claims note"),
+    }
+    answer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, result: &str) -> ToolCallRecord {
+        ToolCallRecord {
+            name: name.into(),
+            arguments: serde_json::json!({}),
+            result: Some(result.into()),
+        }
+    }
+
+    #[test]
+    fn evidence_comes_from_verified_bases_with_the_strictest_action() {
+        let config = VerificationConfig {
+            sources: BTreeMap::from([
+                (KnowledgeBase::Memory, VerificationAction::Caveat),
+                (KnowledgeBase::Web, VerificationAction::Remove),
+            ]),
+        };
+        let calls = vec![
+            call("memory_recall", "Deploys happen on Tuesdays."),
+            call("channel_recall", "alice: deploys moved to Wednesday"),
+            call("shell", "ok"),
+        ];
+        assert_eq!(
+            config.evidence(&calls),
+            Some((
+                VerificationAction::Caveat,
+                vec!["[memory] Deploys happen on Tuesdays.".to_string()]
+            ))
+        );
+
+        let calls = vec![
+            call("memory_recall", "Deploys happen on Tuesdays."),
+            call("web_search", "{\"results\": []}"),
+        ];
+        assert_eq!(
+            config.evidence(&calls).unwrap().0,
+            VerificationAction::Remove
+        );
+        assert_eq!(config.evidence(&[call("channel_recall", "hi")]), None);
+    }
+
+    #[test]
+    fn unsupported_sentences_are_cut_and_missing_quotes_caveated() {
+        let claims = parse_verdict(
+            "```json\n{\"unsupported\": [\
+             {\"claim\": \"Deploys are on Fridays too\", \"quote\": \"They also happen on Fridays.\"},\
+             {\"claim\": \"Bob owns deploys\", \"quote\": \"Bob is the owner.\"},\
+             {\"claim\": \"Carol approves them\", \"quote\": \"Carol signs off.\"}]}\n```",
+        )
+        .expect("verdict parses");
+
+        let (answer, missing) = remove_claims(
+            "Deploys happen on Tuesdays. They also happen on Fridays. Ask in #ops.\n\n\n\
+             - Alice runs them\n- Bob is the owner.\n\nCarol signs off on them.",
+            claims,
+        );
+        assert_eq!(
+            answer,
+            "Deploys happen on Tuesdays. Ask in #ops.\n\n- Alice runs them\n\nCarol signs off on them."
+        );
+        assert_eq!(missing, vec!["Carol approves them".to_string()]);
+    }
+}
@@ -4,13 +4,15 @@ use crate::agent::compactor::estimate_history_tokens;
 use crate::config::BrowserConfig;
 use crate::error::Result;
 use crate::hooks::SpacebotHook;
+use crate::injection::InjectionGuard;
 use crate::llm::SpacebotModel;
 use crate::llm::routing::is_context_overflow_error;
 use crate::{AgentDeps, ChannelId, ProcessId, ProcessType, WorkerId};
 use rig::agent::AgentBuilder;
 use rig::completion::{CompletionModel, Prompt};
 use std::fmt::Write as _;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::sync::{mpsc, watch};
 use uuid::Uuid;
 
@@ -191,7 +193,21 @@ impl Worker {
             self.deps.event_tx.clone(),
             self.browser_config.clone(),
             self.screenshot_dir.clone(),
+            self.deps.artifacts.clone(),
             self.brave_search_key.clone(),
+            self.deps.llm_manager.fetch_limits(),
+            (**self.deps.runtime_config.github.load()).clone(),
+            (**self.deps.runtime_config.railway.load()).clone(),
+            (**self.deps.runtime_config.http_apis.load()).clone(),
+            (**self.deps.runtime_config.sql_databases.load()).clone(),
+            (**self.deps.runtime_config.charts.load()).clone(),
+            (**self.deps.runtime_config.weather.load()).clone(),
+            &self.deps.plugins,
+            Arc::new(InjectionGuard::new(
+                &self.deps.runtime_config.injection.load(),
+                self.deps.agent_id.clone(),
+                self.channel_id.clone(),
+            )),
             self.deps.runtime_config.workspace_dir.clone(),
             self.deps.runtime_config.instance_dir.clone(),
         );
@@ -199,7 +215,8 @@ impl Worker {
         let routing = self.deps.runtime_config.routing.load();
         let model_name = routing.resolve(ProcessType::Worker, None).to_string();
         let model = SpacebotModel::make(&self.deps.llm_manager, &model_name)
-            .with_routing((**routing).clone());
+            .with_routing((**routing).clone())
+            .with_agent(self.deps.agent_id.clone());
 
         let agent = AgentBuilder::new(model)
             .preamble(&self.system_prompt)
@@ -244,7 +261,8 @@ impl Worker {
                 Err(rig::completion::PromptError::PromptCancelled { reason, .. }) => {
                     self.state = WorkerState::Failed;
                     self.hook.send_status("cancelled");
-                    self.write_failure_log(&history, &format!("cancelled: {reason}"));
+                    self.write_failure_log(&history, &format!("cancelled: {reason}"))
+                        .await;
                     tracing::info!(worker_id = %self.id, %reason, "worker cancelled");
                     return Ok(format!("Worker cancelled: {reason}"));
                 }
@@ -253,7 +271,7 @@ impl Worker {
                     if overflow_retries > MAX_OVERFLOW_RETRIES {
                         self.state = WorkerState::Failed;
                         self.hook.send_status("failed");
-                        self.write_failure_log(&history, &format!("context overflow after {MAX_OVERFLOW_RETRIES} compaction attempts: {error}"));
+                        self.write_failure_log(&history, &format!("context overflow after {MAX_OVERFLOW_RETRIES} compaction attempts: {error}")).await;
                         tracing::error!(worker_id = %self.id, %error, "worker context overflow unrecoverable");
                         return Err(crate::error::AgentError::Other(error.into()).into());
                     }
@@ -274,13 +292,15 @@ impl Worker {
                 Err(error) => {
                     self.state = WorkerState::Failed;
                     self.hook.send_status("failed");
-                    self.write_failure_log(&history, &error.to_string());
+                    self.write_failure_log(&history, &error.to_string()).await;
                     tracing::error!(worker_id = %self.id, %error, "worker LLM call failed");
                     return Err(crate::error::AgentError::Other(error.into()).into());
                 }
             }
         };
 
+        let result = crate::agent::verification::verify(&self.deps, result, &history).await;
+
         // For interactive workers, enter a follow-up loop
         if let Some(mut input_rx) = self.input_rx.take() {
             self.state = WorkerState::WaitingForInput;
@@ -307,7 +327,7 @@ impl Worker {
                         Err(error) if is_context_overflow_error(&error.to_string()) => {
                             follow_up_overflow_retries += 1;
                             if follow_up_overflow_retries > MAX_OVERFLOW_RETRIES {
-                                self.write_failure_log(&history, &format!("follow-up context overflow after {MAX_OVERFLOW_RETRIES} compaction attempts: {error}"));
+                                self.write_failure_log(&history, &format!("follow-up context overflow after {MAX_OVERFLOW_RETRIES} compaction attempts: {error}")).await;
                                 tracing::error!(worker_id = %self.id, %error, "follow-up context overflow unrecoverable");
                                 break false;
                             }
@@ -326,7 +346,8 @@ impl Worker {
                             follow_up_prompt = format!("{follow_up}\n\n{overflow_msg}");
                         }
                         Err(error) => {
-                            self.write_failure_log(&history, &format!("follow-up failed: {error}"));
+                            self.write_failure_log(&history, &format!("follow-up failed: {error}"))
+                                .await;
                             tracing::error!(worker_id = %self.id, %error, "worker follow-up failed");
                             break false;
                         }
@@ -350,7 +371,7 @@ impl Worker {
         // Write success log based on the worker log mode setting
         let log_mode = self.get_worker_log_mode();
         if log_mode != crate::settings::WorkerLogMode::ErrorsOnly {
-            self.write_success_log(&history);
+            self.write_success_log(&history).await;
         }
 
         tracing::info!(worker_id = %self.id, "worker completed");
@@ -558,60 +579,49 @@ impl Worker {
     }
 
     /// Write a structured log file for a successful worker execution.
-    fn write_success_log(&self, history: &[rig::message::Message]) {
-        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-        let filename = format!("worker_{}_{}.log", self.id, timestamp);
-        let log_dir = self.get_log_directory(true);
-        let path = log_dir.join(&filename);
-
+    async fn write_success_log(&self, history: &[rig::message::Message]) {
         let log = self.build_log_content(history, None);
-
-        // Best-effort write
-        if let Err(write_error) =
-            std::fs::create_dir_all(&log_dir).and_then(|()| std::fs::write(&path, &log))
-        {
-            tracing::warn!(
-                worker_id = %self.id,
-                path = %path.display(),
-                %write_error,
-                "failed to write worker success log"
-            );
-        } else {
-            tracing::info!(
-                worker_id = %self.id,
-                path = %path.display(),
-                "worker success log written"
-            );
-        }
+        self.save_log(self.get_log_directory(true), log, "success")
+            .await;
     }
 
     /// Write a structured log file to disk capturing the worker's execution
     /// trace (task, history, error). Called on failure so we have something
     /// to inspect after the fact.
-    fn write_failure_log(&self, history: &[rig::message::Message], error: &str) {
+    async fn write_failure_log(&self, history: &[rig::message::Message], error: &str) {
+        let log = self.build_log_content(history, Some(error));
+        self.save_log(self.get_log_directory(false), log, "failure")
+            .await;
+    }
+
+    /// Best-effort save of a worker log, to `log_dir` or to object storage
+    /// under the same relative path.
+    async fn save_log(&self, log_dir: PathBuf, log: String, outcome: &str) {
         let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
         let filename = format!("worker_{}_{}.log", self.id, timestamp);
-        let log_dir = self.get_log_directory(false);
         let path = log_dir.join(&filename);
-
-        let log = self.build_log_content(history, Some(error));
-
-        // Best-effort write
-        if let Err(write_error) =
-            std::fs::create_dir_all(&log_dir).and_then(|()| std::fs::write(&path, &log))
+        let relative = path
+            .strip_prefix(&self.logs_dir)
+            .unwrap_or(Path::new(&filename));
+        let key = format!("logs/{}", relative.display());
+
+        match self
+            .deps
+            .artifacts
+            .save(&path, &key, log.into_bytes(), "text/plain; charset=utf-8")
+            .await
         {
-            tracing::warn!(
+            Ok(location) => tracing::info!(
                 worker_id = %self.id,
-                path = %path.display(),
-                %write_error,
-                "failed to write worker failure log"
-            );
-        } else {
-            tracing::info!(
+                %location,
+                "worker {outcome} log written"
+            ),
+            Err(write_error) => tracing::warn!(
                 worker_id = %self.id,
-                path = %path.display(),
-                "worker failure log written"
-            );
+                %key,
+                %write_error,
+                "failed to write worker {outcome} log"
+            ),
         }
     }
 }
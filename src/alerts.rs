@@ -0,0 +1,449 @@
+//! Alerts: operator-defined triggers on channel messages.
+//!
+//! Every user message a channel receives is checked against the alerts in
+//! `[defaults.alerts]` that watch its channel: keywords (whole words,
+//! ignoring case), regular expressions, and a plain-language description
+//! matched by meaning with the embedding model. A match queues an
+//! `alert.notify` job that posts a notice, optionally with a model-written
+//! triage summary, to the alert's `notify` targets. Each alert fires at most
+//! once per `cooldown_secs` in a channel.
+
+use crate::config::AlertConfig;
+use crate::conversation::ConversationLogger;
+use crate::llm::SpacebotModel;
+use crate::memory::embedding::cosine_similarity;
+use crate::{AgentDeps, InboundMessage, OutboundResponse, ProcessType};
+
+use anyhow::Context as _;
+use rig::agent::AgentBuilder;
+use rig::completion::{CompletionModel as _, Prompt as _};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// Job kind for one alert firing. Payload: an [`AlertHit`].
+pub const NOTIFY_JOB: &str = "alert.notify";
+
+/// Most characters of the triggering message quoted in a notification.
+const QUOTE_CHARS: usize = 600;
+
+/// Channel messages before the trigger that triage sees.
+const CONTEXT_MESSAGES: i64 = 15;
+
+/// Agent, alert, and channel.
+type CooldownKey = (String, String, String);
+
+/// When each alert last fired in each channel.
+static LAST_FIRED: LazyLock<Mutex<HashMap<CooldownKey, Instant>>> = LazyLock::new(Default::default);
+
+/// Embeddings of `semantic` descriptions, by description.
+static DESCRIPTIONS: LazyLock<Mutex<HashMap<String, Arc<Vec<f32>>>>> =
+    LazyLock::new(Default::default);
+
+/// A message that matched an alert.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertHit {
+    pub alert: String,
+    /// What matched, e.g. `keyword "outage"`.
+    pub reasons: Vec<String>,
+    pub channel_id: String,
+    pub channel_name: Option<String>,
+    pub sender: String,
+    pub text: String,
+}
+
+/// Check a user message against the agent's alerts, in the background.
+/// System re-triggers and messages in channels no alert watches are
+/// skipped without spawning anything.
+pub fn check_message(deps: &AgentDeps, message: &InboundMessage, text: &str) {
+    if message.source == "system" || text.trim().is_empty() {
+        return;
+    }
+    let alerts = deps.runtime_config.alerts.load_full();
+    let channel_id = message.conversation_id.clone();
+    let channel_name =
+        crate::conversation::channels::display_name_from(&channel_id, &message.metadata);
+    let watching: Vec<(String, AlertConfig)> = alerts
+        .iter()
+        .filter(|(_, alert)| watches(alert, &channel_id, channel_name.as_deref()))
+        .map(|(name, alert)| (name.clone(), alert.clone()))
+        .collect();
+    if watching.is_empty() {
+        return;
+    }
+
+    let sender = message
+        .metadata
+        .get("sender_display_name")
+        .and_then(|value| value.as_str())
+        .unwrap_or(&message.sender_id)
+        .to_string();
+    let deps = deps.clone();
+    let text = text.to_string();
+    tokio::spawn(async move {
+        let mut embedding = None;
+        for (name, alert) in watching {
+            let mut reasons = text_matches(&alert, &text);
+            if reasons.is_empty()
+                && let Some(description) = &alert.semantic
+            {
+                match similarity(&deps, description, &text, &mut embedding).await {
+                    Ok(score) if score >= alert.similarity => {
+                        reasons.push(format!("similar to \"{description}\" ({score:.2})"));
+                    }
+                    Ok(_) => {}
+                    Err(error) => {
+                        tracing::warn!(alert = %name, %error, "failed to compare message to alert");
+                    }
+                }
+            }
+            if reasons.is_empty()
+                || !take_cooldown(&deps.agent_id, &name, &channel_id, alert.cooldown_secs)
+            {
+                continue;
+            }
+
+            let hit = AlertHit {
+                alert: name.clone(),
+                reasons,
+                channel_id: channel_id.clone(),
+                channel_name: channel_name.clone(),
+                sender: sender.clone(),
+                text: text.clone(),
+            };
+            tracing::info!(alert = %name, channel_id = %channel_id, "alert matched");
+            let payload = serde_json::to_value(&hit).expect("alert hit serializes");
+            if let Err(error) = deps.jobs.enqueue(&deps.agent_id, NOTIFY_JOB, payload).await {
+                tracing::error!(alert = %name, %error, "failed to queue alert notification");
+            }
+        }
+    });
+}
+
+/// Whether `alert` watches the channel. Channels are listed by ID or by
+/// name, with or without a leading `#`.
+fn watches(alert: &AlertConfig, channel_id: &str, channel_name: Option<&str>) -> bool {
+    if alert.channels.is_empty() {
+        return true;
+    }
+    let name = channel_name.map(|name| name.trim_start_matches('#'));
+    alert.channels.iter().any(|channel| {
+        channel == channel_id
+            || name.is_some_and(|name| name.eq_ignore_ascii_case(channel.trim_start_matches('#')))
+    })
+}
+
+/// The keywords and patterns of `alert` that `text` matches.
+fn text_matches(alert: &AlertConfig, text: &str) -> Vec<String> {
+    let lower = text.to_lowercase();
+    let keywords = alert
+        .keywords
+        .iter()
+        .filter(|keyword| contains_word(&lower, &keyword.to_lowercase()))
+        .map(|keyword| format!("keyword \"{keyword}\""));
+    let patterns = alert
+        .patterns
+        .iter()
+        .filter(|pattern| pattern.is_match(text))
+        .map(|pattern| format!("pattern `{}`", pattern.as_str()));
+    keywords.chain(patterns).collect()
+}
+
+/// Whether `word` appears in `text` with no letter or digit right before or
+/// after it. Both are expected in lowercase.
+fn contains_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(at, _)| {
+        let before = text[..at].chars().next_back();
+        let after = text[at + word.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Cosine similarity between `description` and `text`. The message's
+/// embedding is computed once and kept in `embedding` for the next alert.
+async fn similarity(
+    deps: &AgentDeps,
+    description: &str,
+    text: &str,
+    embedding: &mut Option<Vec<f32>>,
+) -> crate::error::Result<f32> {
+    let model = deps.memory_search.embedding_model();
+    let cached = DESCRIPTIONS
+        .lock()
+        .expect("alert description cache poisoned")
+        .get(description)
+        .cloned();
+    let description_embedding = match cached {
+        Some(cached) => cached,
+        None => {
+            let computed = Arc::new(model.embed_one(description).await?);
+            if !crate::watchdog::shedding() {
+                DESCRIPTIONS
+                    .lock()
+                    .expect("alert description cache poisoned")
+                    .insert(description.to_string(), computed.clone());
+            }
+            computed
+        }
+    };
+    if embedding.is_none() {
+        *embedding = Some(model.embed_one(text).await?);
+    }
+    let message_embedding = embedding.as_deref().unwrap_or_default();
+    Ok(cosine_similarity(&description_embedding, message_embedding))
+}
+
+/// Drop every cached description embedding. Returns how many were dropped.
+pub fn shed_cache() -> usize {
+    let mut descriptions = DESCRIPTIONS
+        .lock()
+        .expect("alert description cache poisoned");
+    let dropped = descriptions.len();
+    *descriptions = HashMap::new();
+    dropped
+}
+
+/// Start the alert's cooldown in the channel. Returns `false` when it's
+/// still cooling down from the last time it fired.
+fn take_cooldown(agent_id: &str, alert: &str, channel_id: &str, cooldown_secs: u64) -> bool {
+    let mut last_fired = LAST_FIRED.lock().expect("alert cooldowns poisoned");
+    let key = (
+        agent_id.to_string(),
+        alert.to_string(),
+        channel_id.to_string(),
+    );
+    let cooldown = Duration::from_secs(cooldown_secs);
+    if last_fired
+        .get(&key)
+        .is_some_and(|fired| fired.elapsed() < cooldown)
+    {
+        return false;
+    }
+    last_fired.insert(key, Instant::now());
+    true
+}
+
+/// Run an `alert.notify` job: triage the message if asked, and post the
+/// notice.
+pub async fn run_notify_job(job: &crate::jobs::Job, deps: &AgentDeps) -> anyhow::Result<()> {
+    let hit: AlertHit =
+        serde_json::from_value(job.payload.clone()).context("alert job has an invalid payload")?;
+    let alerts = deps.runtime_config.alerts.load_full();
+    let Some(alert) = alerts.get(&hit.alert) else {
+        tracing::info!(alert = %hit.alert, "alert was removed, dropping its notification");
+        return Ok(());
+    };
+    let messaging = deps
+        .messaging_manager
+        .as_ref()
+        .context("no messaging adapters to send the alert with")?;
+
+    // A late notice is worse than one without triage, so a failed triage
+    // doesn't hold it up.
+    let triage = if alert.triage {
+        match triage(deps, alert, &hit).await {
+            Ok(summary) => Some(summary),
+            Err(error) => {
+                tracing::warn!(alert = %hit.alert, %error, "alert triage failed");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let notice = render_notice(&hit, triage.as_deref());
+    let mut delivered = 0;
+    for raw in &alert.notify {
+        let Some(target) = crate::cron::scheduler::normalize_delivery_target(raw) else {
+            tracing::warn!(alert = %hit.alert, target = %raw, "invalid alert notify target");
+            continue;
+        };
+        match messaging
+            .broadcast(
+                &target.adapter,
+                &target.target,
+                OutboundResponse::Text(notice.clone()),
+            )
+            .await
+        {
+            Ok(()) => delivered += 1,
+            Err(error) => {
+                tracing::warn!(alert = %hit.alert, target = %target, %error, "failed to send alert");
+            }
+        }
+    }
+    // Retrying after a partial delivery would notify the targets that
+    // worked twice.
+    if delivered == 0 {
+        anyhow::bail!("couldn't send the alert to any notify target");
+    }
+    Ok(())
+}
+
+/// Ask the model what's going on around the triggering message.
+async fn triage(deps: &AgentDeps, alert: &AlertConfig, hit: &AlertHit) -> anyhow::Result<String> {
+    let channel_id: crate::ChannelId = Arc::from(hit.channel_id.as_str());
+    let recent = ConversationLogger::new(deps.sql_pool.clone())
+        .load_recent(&channel_id, CONTEXT_MESSAGES)
+        .await?;
+
+    let mut input = format!(
+        "Alert: {}\nMatched: {}\nChannel: {}\n",
+        hit.alert,
+        hit.reasons.join(", "),
+        hit.channel_name.as_deref().unwrap_or(&hit.channel_id)
+    );
+    if let Some(description) = &alert.semantic {
+        let _ = writeln!(input, "Watching for: {description}");
+    }
+    input.push_str("\n## Recent messages\n\n");
+    for message in &recent {
+        let speaker = match message.role.as_str() {
+            "user" => message
+                .sender_name
+                .as_deref()
+                .or(message.sender_id.as_deref())
+                .unwrap_or("user"),
+            _ => "agent",
+        };
+        let _ = writeln!(
+            input,
+            "[{}] {speaker}: {}",
+            message.created_at.format("%H:%M"),
+            message.content.trim()
+        );
+    }
+    let _ = write!(
+        input,
+        "\n## Triggering message\n\n{}: {}\n",
+        hit.sender, hit.text
+    );
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = alert
+        .model
+        .clone()
+        .unwrap_or_else(|| routing.resolve(ProcessType::Compactor, None).to_string());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_routing((**routing).clone())
+        .with_agent(deps.agent_id.clone());
+    let preamble = deps
+        .runtime_config
+        .prompts
+        .load()
+        .render_static("alert_triage")?;
+    let agent = AgentBuilder::new(model).preamble(&preamble).build();
+    let summary = agent.prompt(&input).await.context("alert triage failed")?;
+    Ok(summary.trim().to_string())
+}
+
+/// The notification: which alert fired where, the quoted message, and the
+/// triage summary.
+fn render_notice(hit: &AlertHit, triage: Option<&str>) -> String {
+    let channel = match &hit.channel_name {
+        Some(name) => format!("{name} ({})", hit.channel_id),
+        None => hit.channel_id.clone(),
+    };
+    let mut notice = format!(
+        "**Alert: {}** in {channel}\nMatched {}\n",
+        hit.alert,
+        hit.reasons.join(", ")
+    );
+    let mut quoted: String = hit.text.chars().take(QUOTE_CHARS).collect();
+    if quoted.len() < hit.text.len() {
+        quoted.push_str(" [...]");
+    }
+    for (index, line) in quoted.lines().enumerate() {
+        if index == 0 {
+            let _ = writeln!(notice, "> **{}:** {line}", hit.sender);
+        } else {
+            let _ = writeln!(notice, "> {line}");
+        }
+    }
+    if let Some(triage) = triage {
+        let _ = write!(notice, "\n{triage}\n");
+    }
+    notice.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(channels: &[&str], keywords: &[&str], patterns: &[&str]) -> AlertConfig {
+        AlertConfig {
+            channels: channels.iter().map(|channel| channel.to_string()).collect(),
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            patterns: patterns
+                .iter()
+                .map(|pattern| regex::Regex::new(pattern).expect("valid pattern"))
+                .collect(),
+            semantic: None,
+            similarity: 0.6,
+            notify: vec!["discord:1".into()],
+            triage: false,
+            model: None,
+            cooldown_secs: 300,
+        }
+    }
+
+    #[test]
+    fn keywords_match_whole_words_and_patterns_match_anywhere() {
+        let outage = alert(&[], &["outage", "is down"], &[r"5\d\d error"]);
+        assert_eq!(
+            text_matches(&outage, "Big OUTAGE, checkout is down"),
+            vec!["keyword \"outage\"", "keyword \"is down\""]
+        );
+        assert!(text_matches(&outage, "no outages today, nothing is downstream").is_empty());
+        assert_eq!(
+            text_matches(&outage, "seeing a 503 error on login"),
+            vec![r"pattern `5\d\d error`"]
+        );
+    }
+
+    #[test]
+    fn channels_are_watched_by_id_or_name() {
+        let everywhere = alert(&[], &["x"], &[]);
+        assert!(watches(&everywhere, "discord:1", None));
+
+        let support = alert(&["#Support", "slack:C0123"], &["x"], &[]);
+        assert!(watches(&support, "discord:1", Some("support")));
+        assert!(watches(&support, "slack:C0123", None));
+        assert!(!watches(&support, "discord:2", Some("general")));
+        assert!(!watches(&support, "discord:2", None));
+    }
+
+    #[test]
+    fn cooldowns_are_per_alert_and_channel() {
+        assert!(take_cooldown("test-agent", "outage", "discord:1", 300));
+        assert!(!take_cooldown("test-agent", "outage", "discord:1", 300));
+        assert!(take_cooldown("test-agent", "outage", "discord:2", 300));
+        assert!(take_cooldown("test-agent", "churn", "discord:1", 300));
+        assert!(take_cooldown("test-agent", "loud", "discord:1", 0));
+        assert!(take_cooldown("test-agent", "loud", "discord:1", 0));
+
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn notices_quote_the_message() {
+        let hit = AlertHit {
+            alert: "outage".into(),
+            reasons: vec!["keyword \"down\"".into()],
+            channel_id: "discord:1".into(),
+            channel_name: Some("general".into()),
+            sender: "alice".into(),
+            text: "checkout is down\nsince 9:40".into(),
+        };
+        assert_eq!(
+            render_notice(&hit, Some("Checkout failing for everyone.")),
+            "**Alert: outage** in general (discord:1)\nMatched keyword \"down\"\n\
+             > **alice:** checkout is down\n> since 9:40\n\nCheckout failing for everyone."
+        );
+    }
+}
@@ -20,7 +20,9 @@ mod settings;
 mod skills;
 mod state;
 mod system;
+mod tenants;
 mod webchat;
 
+pub(crate) use models::shed_cache as shed_models_cache;
 pub use server::start_http_server;
 pub use state::{AgentInfo, ApiEvent, ApiState};
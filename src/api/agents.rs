@@ -2,6 +2,7 @@ use super::state::{AgentInfo, ApiState};
 
 use crate::agent::cortex::CortexLogger;
 use crate::conversation::channels::ChannelStore;
+use crate::db::{DatabaseBackend, SqlPool, with_pool};
 
 use axum::Json;
 use axum::extract::{Query, State};
@@ -126,7 +127,7 @@ pub(super) struct AgentOverviewQuery {
 
 #[derive(Deserialize)]
 pub(super) struct CreateAgentRequest {
-    agent_id: String,
+    pub(super) agent_id: String,
 }
 
 #[derive(Deserialize)]
@@ -231,9 +232,20 @@ pub(super) async fn create_agent(
         memory_persistence: None,
         coalesce: None,
         ingestion: None,
+        digest: None,
         cortex: None,
         browser: None,
         brave_search_key: None,
+        github: None,
+        railway: None,
+        calendar: None,
+        email: None,
+        charts: None,
+        weather: None,
+        http_apis: std::collections::BTreeMap::new(),
+        sql_databases: std::collections::BTreeMap::new(),
+        feeds: std::collections::BTreeMap::new(),
+        alerts: std::collections::BTreeMap::new(),
         cron: Vec::new(),
     };
     let agent_config = raw_config.resolve(&instance_dir, defaults);
@@ -252,7 +264,18 @@ pub(super) async fn create_agent(
         })?;
     }
 
-    let db = crate::db::Db::connect(&agent_config.data_dir)
+    let database_config = {
+        let guard = state.database_config.read().await;
+        guard
+            .as_ref()
+            .ok_or_else(|| {
+                tracing::error!("database config not available");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .clone()
+    };
+
+    let db = crate::db::Db::connect(&agent_config.data_dir, &database_config, &agent_id)
         .await
         .map_err(|error| {
             tracing::error!(%error, agent_id = %agent_id, "failed to connect agent databases");
@@ -278,26 +301,24 @@ pub(super) async fn create_agent(
             .clone()
     };
 
-    let memory_store = crate::memory::MemoryStore::new(db.sqlite.clone());
-    let embedding_table = crate::memory::EmbeddingTable::open_or_create(&db.lance)
-        .await
-        .map_err(|error| {
-            tracing::error!(%error, agent_id = %agent_id, "failed to init embeddings");
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
-
-    if let Err(error) = embedding_table.ensure_fts_index().await {
-        tracing::warn!(%error, agent_id = %agent_id, "failed to create FTS index");
-    }
-
-    let memory_search = std::sync::Arc::new(crate::memory::MemorySearch::new(
+    let memory_store = crate::memory::MemoryStore::new(db.sql.clone());
+    let (memory_search, migration) = crate::memory::migration::open_search(
+        &agent_config.data_dir,
+        &db.lance,
         memory_store,
-        embedding_table,
         embedding_model,
-    ));
+    )
+    .await
+    .map_err(|error| {
+        tracing::error!(%error, agent_id = %agent_id, "failed to init embeddings");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
     let (event_tx, _) = tokio::sync::broadcast::channel(256);
     let arc_agent_id: crate::AgentId = std::sync::Arc::from(agent_id.as_str());
+    if let Some(migration) = migration {
+        migration.spawn(arc_agent_id.clone());
+    }
 
     crate::identity::scaffold_identity_files(&agent_config.workspace)
         .await
@@ -310,6 +331,7 @@ pub(super) async fn create_agent(
     let skills =
         crate::skills::SkillSet::load(&instance_dir.join("skills"), &agent_config.skills_dir())
             .await;
+    let flows = crate::flows::FlowSet::load(&instance_dir.join("flows"), &agent_config.flows_dir());
 
     let prompt_engine = {
         let guard = state.prompt_engine.read().await;
@@ -322,6 +344,12 @@ pub(super) async fn create_agent(
             .clone()
     };
 
+    let scripts =
+        crate::scripting::ScriptHooks::load(&instance_dir.join("scripts")).map_err(|error| {
+            tracing::error!(%error, "failed to load script hooks");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
     let defaults_for_runtime = {
         let guard = state.defaults_config.read().await;
         guard
@@ -338,8 +366,10 @@ pub(super) async fn create_agent(
         &agent_config,
         &defaults_for_runtime,
         prompt_engine,
+        scripts,
         identity,
         skills,
+        flows,
     ));
     runtime_config.set_settings(settings_store.clone());
 
@@ -353,6 +383,28 @@ pub(super) async fn create_agent(
             })?
             .clone()
     };
+    let jobs = {
+        let guard = state.job_queue.read().await;
+        guard
+            .as_ref()
+            .ok_or_else(|| {
+                tracing::error!("job queue not available");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+            .clone()
+    };
+
+    let artifacts = {
+        let guard = state.storage_config.read().await;
+        let storage_config = guard.as_ref().ok_or_else(|| {
+            tracing::error!("storage config not available");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+        crate::storage::ArtifactStore::from_config(storage_config, &agent_id).map_err(|error| {
+            tracing::error!(%error, agent_id = %agent_id, "failed to set up artifact storage");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+    };
 
     let deps = crate::AgentDeps {
         agent_id: arc_agent_id.clone(),
@@ -361,17 +413,34 @@ pub(super) async fn create_agent(
         cron_tool: None,
         runtime_config: runtime_config.clone(),
         event_tx: event_tx.clone(),
-        sqlite_pool: db.sqlite.clone(),
+        sql_pool: db.sql.clone(),
+        conversation_writes: crate::conversation::WriteBatch::new(db.sql.clone(), &database_config),
         messaging_manager: {
             let guard = state.messaging_manager.read().await;
             guard.as_ref().cloned()
         },
+        jobs,
+        artifacts: Arc::new(artifacts),
+        plugins: {
+            let guard = state.plugins.read().await;
+            guard
+                .as_ref()
+                .cloned()
+                .unwrap_or_else(crate::plugins::PluginHost::empty)
+        },
     };
 
+    let conversation_writes = deps.conversation_writes.clone();
+    crate::supervisor::spawn(
+        Some(arc_agent_id.clone()),
+        "conversation write flusher",
+        move || conversation_writes.spawn_flusher(),
+    );
+
     let event_rx = event_tx.subscribe();
     state.register_agent_events(agent_id.clone(), event_rx);
 
-    let cron_store = std::sync::Arc::new(crate::cron::CronStore::new(db.sqlite.clone()));
+    let cron_store = std::sync::Arc::new(crate::cron::CronStore::new(db.sql.clone()));
     let cron_context = crate::cron::CronContext {
         deps: deps.clone(),
         screenshot_dir: agent_config.screenshot_dir(),
@@ -392,38 +461,55 @@ pub(super) async fn create_agent(
 
     let browser_config = (**runtime_config.browser_config.load()).clone();
     let brave_search_key = (**runtime_config.brave_search_key.load()).clone();
-    let conversation_logger =
-        crate::conversation::history::ConversationLogger::new(db.sqlite.clone());
-    let channel_store = crate::conversation::ChannelStore::new(db.sqlite.clone());
+    let conversation_logger = crate::conversation::history::ConversationLogger::new(db.sql.clone());
+    let channel_store = crate::conversation::ChannelStore::new(db.sql.clone());
     let cortex_tool_server = crate::tools::create_cortex_chat_tool_server(
         memory_search.clone(),
+        deps.llm_manager.clone(),
         conversation_logger,
         channel_store,
         browser_config,
         agent_config.screenshot_dir(),
+        deps.artifacts.clone(),
         brave_search_key,
+        (**runtime_config.github.load()).clone(),
+        (**runtime_config.railway.load()).clone(),
+        (**runtime_config.http_apis.load()).clone(),
+        (**runtime_config.sql_databases.load()).clone(),
+        (**runtime_config.charts.load()).clone(),
+        (**runtime_config.weather.load()).clone(),
+        &deps.plugins,
+        std::sync::Arc::new(crate::injection::InjectionGuard::new(
+            &runtime_config.injection.load(),
+            deps.agent_id.clone(),
+            None,
+        )),
         runtime_config.workspace_dir.clone(),
         runtime_config.instance_dir.clone(),
     );
-    let cortex_store = crate::agent::cortex_chat::CortexChatStore::new(db.sqlite.clone());
+    let cortex_store = crate::agent::cortex_chat::CortexChatStore::new(db.sql.clone());
     let cortex_session = crate::agent::cortex_chat::CortexChatSession::new(
         deps.clone(),
         cortex_tool_server,
         cortex_store,
     );
 
-    let cortex_logger = crate::agent::cortex::CortexLogger::new(db.sqlite.clone());
+    let cortex_logger = crate::agent::cortex::CortexLogger::new(db.sql.clone());
     let _bulletin_loop =
         crate::agent::cortex::spawn_bulletin_loop(deps.clone(), cortex_logger.clone());
     let _association_loop =
         crate::agent::cortex::spawn_association_loop(deps.clone(), cortex_logger);
 
+    let _job_worker = crate::agent::jobs::spawn_job_worker(deps.clone());
+    let _digest_loop = crate::agent::digest::spawn_digest_loop(deps.clone());
+    let _reminder_loop = crate::reminders::spawn_reminder_loop(deps.clone());
+    let _poll_loop = crate::polls::spawn_poll_loop(deps.clone());
     let ingestion_config = **runtime_config.ingestion.load();
     if ingestion_config.enabled {
         crate::agent::ingestion::spawn_ingestion_loop(agent_config.ingest_dir(), deps.clone());
     }
 
-    let sqlite_pool = db.sqlite.clone();
+    let sql_pool = db.sql.clone();
     let mut deps_with_cron = deps.clone();
     deps_with_cron.cron_tool = Some(cron_tool);
     let agent = crate::Agent {
@@ -438,7 +524,7 @@ pub(super) async fn create_agent(
 
     {
         let mut pools = (**state.agent_pools.load()).clone();
-        pools.insert(agent_id.clone(), sqlite_pool);
+        pools.insert(agent_id.clone(), sql_pool);
         state.agent_pools.store(std::sync::Arc::new(pools));
 
         let mut searches = (**state.memory_searches.load()).clone();
@@ -557,7 +643,7 @@ pub(super) async fn delete_agent(
             })?;
     }
 
-    // Close the SQLite pool before removing state
+    // Close the database pool before removing state
     {
         let pools = state.agent_pools.load();
         if let Some(pool) = pools.get(&agent_id) {
@@ -627,11 +713,13 @@ pub(super) async fn agent_overview(
     let pools = state.agent_pools.load();
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
 
-    let memory_rows = sqlx::query(
-        "SELECT memory_type, COUNT(*) as count FROM memories WHERE forgotten = 0 GROUP BY memory_type",
-    )
-    .fetch_all(pool)
-    .await
+    let memory_rows: Vec<(String, i64)> = with_pool!(pool, |pool| {
+        sqlx::query_as(
+            "SELECT memory_type, COUNT(*) as count FROM memories WHERE forgotten = FALSE GROUP BY memory_type",
+        )
+        .fetch_all(pool)
+        .await
+    })
     .map_err(|error| {
         tracing::warn!(%error, agent_id = %query.agent_id, "failed to count memories");
         StatusCode::INTERNAL_SERVER_ERROR
@@ -639,9 +727,7 @@ pub(super) async fn agent_overview(
 
     let mut memory_counts: HashMap<String, i64> = HashMap::new();
     let mut memory_total: i64 = 0;
-    for row in &memory_rows {
-        let memory_type: String = row.get("memory_type");
-        let count: i64 = row.get("count");
+    for (memory_type, count) in memory_rows {
         memory_total += count;
         memory_counts.insert(memory_type, count);
     }
@@ -650,14 +736,13 @@ pub(super) async fn agent_overview(
     let channels = channel_store.list_active().await.unwrap_or_default();
     let channel_count = channels.len();
 
-    let cron_rows = sqlx::query(
-        "SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, run_once, timeout_secs FROM cron_jobs ORDER BY created_at ASC",
-    )
-    .fetch_all(pool)
-    .await
-    .unwrap_or_default();
-
-    let cron_jobs: Vec<CronJobInfo> = cron_rows
+    let cron_jobs: Vec<CronJobInfo> = with_pool!(pool, |pool| {
+        sqlx::query(
+            "SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, run_once, timeout_secs FROM cron_jobs ORDER BY created_at ASC",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default()
         .into_iter()
         .map(|row| {
             let active_start: Option<i64> = row.try_get("active_start_hour").ok();
@@ -680,7 +765,8 @@ pub(super) async fn agent_overview(
                     .map(|t| t as u64),
             }
         })
-        .collect();
+        .collect()
+    });
 
     let cortex_logger = CortexLogger::new(pool.clone());
     let bulletin_events = cortex_logger
@@ -701,44 +787,48 @@ pub(super) async fn agent_overview(
         })
     });
 
-    let memory_daily_rows = sqlx::query(
-        "SELECT date(created_at) as date, COUNT(*) as count FROM memories WHERE forgotten = 0 AND created_at > date('now', '-30 days') GROUP BY date ORDER BY date",
+    let backend = pool.backend();
+    let memory_daily: Vec<DayCount> = day_counts(
+        pool,
+        &format!(
+            "SELECT {} as date, COUNT(*) as count FROM memories WHERE forgotten = FALSE AND created_at > {} GROUP BY date ORDER BY date",
+            sql_day(backend, "created_at"),
+            sql_days_ago(backend, 30),
+        ),
+        None,
     )
-    .fetch_all(pool)
     .await
-    .unwrap_or_default();
-
-    let memory_daily: Vec<DayCount> = memory_daily_rows
-        .into_iter()
-        .map(|row| DayCount {
-            date: row.get("date"),
-            count: row.get("count"),
-        })
-        .collect();
-
-    let activity_window = chrono::Utc::now() - chrono::Duration::days(30);
-
-    let branch_activity = sqlx::query(
-        "SELECT date(started_at) as date, COUNT(*) as count FROM branch_runs WHERE started_at > ? GROUP BY date ORDER BY date",
+    .into_iter()
+    .map(|(date, count)| DayCount { date, count })
+    .collect();
+
+    let activity_window = (chrono::Utc::now() - chrono::Duration::days(30)).to_rfc3339();
+
+    let branch_activity = day_counts(
+        pool,
+        &format!(
+            "SELECT {} as date, COUNT(*) as count FROM branch_runs WHERE {} GROUP BY date ORDER BY date",
+            sql_day(backend, "started_at"),
+            sql_after_param(backend, "started_at"),
+        ),
+        Some(&activity_window),
     )
-    .bind(activity_window.to_rfc3339())
-    .fetch_all(pool)
-    .await
-    .unwrap_or_default();
-
-    let worker_activity = sqlx::query(
-        "SELECT date(started_at) as date, COUNT(*) as count FROM worker_runs WHERE started_at > ? GROUP BY date ORDER BY date",
+    .await;
+
+    let worker_activity = day_counts(
+        pool,
+        &format!(
+            "SELECT {} as date, COUNT(*) as count FROM worker_runs WHERE {} GROUP BY date ORDER BY date",
+            sql_day(backend, "started_at"),
+            sql_after_param(backend, "started_at"),
+        ),
+        Some(&activity_window),
     )
-    .bind(activity_window.to_rfc3339())
-    .fetch_all(pool)
-    .await
-    .unwrap_or_default();
+    .await;
 
     let activity_daily: Vec<ActivityDayCount> = {
         let mut map: HashMap<String, ActivityDayCount> = HashMap::new();
-        for row in branch_activity {
-            let date: String = row.get("date");
-            let count: i64 = row.get("count");
+        for (date, count) in branch_activity {
             map.entry(date.clone())
                 .or_insert_with(|| ActivityDayCount {
                     date,
@@ -747,9 +837,7 @@ pub(super) async fn agent_overview(
                 })
                 .branches = count;
         }
-        for row in worker_activity {
-            let date: String = row.get("date");
-            let count: i64 = row.get("count");
+        for (date, count) in worker_activity {
             map.entry(date.clone())
                 .or_insert_with(|| ActivityDayCount {
                     date,
@@ -763,21 +851,33 @@ pub(super) async fn agent_overview(
         days
     };
 
-    let heatmap_rows = sqlx::query(
-        "SELECT CAST(strftime('%w', created_at) AS INTEGER) as day, CAST(strftime('%H', created_at) AS INTEGER) as hour, COUNT(*) as count FROM conversation_messages WHERE created_at > date('now', '-90 days') GROUP BY day, hour",
-    )
-    .fetch_all(pool)
-    .await
-    .unwrap_or_default();
-
-    let activity_heatmap: Vec<HeatmapCell> = heatmap_rows
-        .into_iter()
-        .map(|row| HeatmapCell {
-            day: row.get("day"),
-            hour: row.get("hour"),
-            count: row.get("count"),
-        })
-        .collect();
+    let (day_of_week, hour_of_day) = match backend {
+        DatabaseBackend::Sqlite => (
+            "CAST(strftime('%w', created_at) AS INTEGER)",
+            "CAST(strftime('%H', created_at) AS INTEGER)",
+        ),
+        DatabaseBackend::Postgres => (
+            "CAST(EXTRACT(DOW FROM created_at) AS BIGINT)",
+            "CAST(EXTRACT(HOUR FROM created_at) AS BIGINT)",
+        ),
+    };
+    let heatmap_query = format!(
+        "SELECT {day_of_week} as day, {hour_of_day} as hour, COUNT(*) as count FROM conversation_messages WHERE created_at > {} GROUP BY day, hour",
+        sql_days_ago(backend, 90),
+    );
+    let activity_heatmap: Vec<HeatmapCell> = with_pool!(pool, |pool| {
+        sqlx::query(&heatmap_query)
+            .fetch_all(pool)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|row| HeatmapCell {
+                day: row.get("day"),
+                hour: row.get("hour"),
+                count: row.get("count"),
+            })
+            .collect()
+    });
 
     Ok(Json(AgentOverviewResponse {
         memory_counts,
@@ -793,6 +893,42 @@ pub(super) async fn agent_overview(
     }))
 }
 
+/// Run a query producing `date` and `count` columns, binding `since` when
+/// given. Failures read as no activity.
+async fn day_counts(pool: &SqlPool, sql: &str, since: Option<&str>) -> Vec<(String, i64)> {
+    with_pool!(pool, |pool| {
+        let mut query = sqlx::query_as(sql);
+        if let Some(since) = since {
+            query = query.bind(since);
+        }
+        query.fetch_all(pool).await.unwrap_or_default()
+    })
+}
+
+/// A timestamp column's UTC calendar day as `YYYY-MM-DD` text.
+fn sql_day(backend: DatabaseBackend, column: &str) -> String {
+    match backend {
+        DatabaseBackend::Sqlite => format!("date({column})"),
+        DatabaseBackend::Postgres => format!("to_char({column}, 'YYYY-MM-DD')"),
+    }
+}
+
+/// Midnight UTC `days` days ago.
+fn sql_days_ago(backend: DatabaseBackend, days: u32) -> String {
+    match backend {
+        DatabaseBackend::Sqlite => format!("date('now', '-{days} days')"),
+        DatabaseBackend::Postgres => format!("CURRENT_DATE - {days}"),
+    }
+}
+
+/// `column > $1`, where `$1` is bound as RFC 3339 text.
+fn sql_after_param(backend: DatabaseBackend, column: &str) -> String {
+    match backend {
+        DatabaseBackend::Sqlite => format!("{column} > $1"),
+        DatabaseBackend::Postgres => format!("{column} > CAST($1 AS TIMESTAMPTZ)"),
+    }
+}
+
 /// Get instance-wide overview for the main dashboard.
 pub(super) async fn instance_overview(
     State(state): State<Arc<ApiState>>,
@@ -820,32 +956,33 @@ pub(super) async fn instance_overview(
             .max()
             .map(|dt| dt.to_rfc3339());
 
-        let memory_total: i64 =
-            sqlx::query_scalar("SELECT COUNT(*) FROM memories WHERE forgotten = 0")
+        let memory_total: i64 = with_pool!(pool, |pool| {
+            sqlx::query_scalar("SELECT COUNT(*) FROM memories WHERE forgotten = FALSE")
                 .fetch_one(pool)
                 .await
-                .unwrap_or(0);
+                .unwrap_or(0)
+        });
 
-        let cron_job_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM cron_jobs")
-            .fetch_one(pool)
-            .await
-            .unwrap_or(0);
+        let cron_job_count: i64 = with_pool!(pool, |pool| {
+            sqlx::query_scalar("SELECT COUNT(*) FROM cron_jobs")
+                .fetch_one(pool)
+                .await
+                .unwrap_or(0)
+        });
 
         let activity_window = chrono::Utc::now() - chrono::Duration::days(14);
-        let activity_rows = sqlx::query(
-            "SELECT date(created_at) as date, COUNT(*) as count FROM conversation_messages WHERE created_at > ? GROUP BY date ORDER BY date",
+        let activity_map: HashMap<String, i64> = day_counts(
+            pool,
+            &format!(
+                "SELECT {} as date, COUNT(*) as count FROM conversation_messages WHERE {} GROUP BY date ORDER BY date",
+                sql_day(pool.backend(), "created_at"),
+                sql_after_param(pool.backend(), "created_at"),
+            ),
+            Some(&activity_window.to_rfc3339()),
         )
-        .bind(activity_window.to_rfc3339())
-        .fetch_all(pool)
         .await
-        .unwrap_or_default();
-
-        let mut activity_map: HashMap<String, i64> = HashMap::new();
-        for row in &activity_rows {
-            let date: String = row.get("date");
-            let count: i64 = row.get("count");
-            activity_map.insert(date, count);
-        }
+        .into_iter()
+        .collect();
 
         let mut activity_sparkline: Vec<i64> = Vec::with_capacity(14);
         for i in 0..14 {
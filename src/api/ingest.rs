@@ -1,4 +1,6 @@
 use super::state::ApiState;
+use crate::agent::ingestion::web::{WebSource, WebSources};
+use crate::db::{timestamp_text, with_pool};
 
 use axum::Json;
 use axum::extract::{Query, State};
@@ -39,6 +41,28 @@ pub(super) struct IngestQuery {
     agent_id: String,
 }
 
+#[derive(Serialize)]
+pub(super) struct WebSourcesResponse {
+    sources: Vec<WebSource>,
+}
+
+#[derive(Serialize)]
+pub(super) struct WebSourceAddResponse {
+    /// The URL as registered, normalized.
+    url: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct WebSourceRequest {
+    url: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct WebSourceDeleteQuery {
+    agent_id: String,
+    url: String,
+}
+
 #[derive(Deserialize)]
 pub(super) struct IngestDeleteQuery {
     agent_id: String,
@@ -55,42 +79,47 @@ pub(super) async fn list_ingest_files(
     let pools = state.agent_pools.load();
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
 
-    let rows = sqlx::query(
-        r#"
-        SELECT f.content_hash, f.filename, f.file_size, f.total_chunks, f.status,
-               f.started_at, f.completed_at,
-               COALESCE(p.done, 0) as chunks_completed
-        FROM ingestion_files f
-        LEFT JOIN (
-            SELECT content_hash, COUNT(*) as done
-            FROM ingestion_progress
-            GROUP BY content_hash
-        ) p ON f.content_hash = p.content_hash
-        ORDER BY f.started_at DESC
-        LIMIT 100
-        "#,
-    )
-    .fetch_all(pool)
-    .await
+    let files = with_pool!(pool, |pool| {
+        sqlx::query(
+            r#"
+            SELECT f.content_hash, f.filename, f.file_size, f.total_chunks, f.status,
+                   f.started_at, f.completed_at,
+                   COALESCE(p.done, 0) as chunks_completed
+            FROM ingestion_files f
+            LEFT JOIN (
+                SELECT content_hash, COUNT(*) as done
+                FROM ingestion_progress
+                GROUP BY content_hash
+            ) p ON f.content_hash = p.content_hash
+            ORDER BY f.started_at DESC
+            LIMIT 100
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| IngestFileInfo {
+                    content_hash: row.get("content_hash"),
+                    filename: row.get("filename"),
+                    file_size: row.get("file_size"),
+                    total_chunks: row.get("total_chunks"),
+                    chunks_completed: row.get("chunks_completed"),
+                    status: row.get("status"),
+                    started_at: timestamp_text(&row, "started_at")
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default(),
+                    completed_at: timestamp_text(&row, "completed_at").ok().flatten(),
+                })
+                .collect()
+        })
+    })
     .map_err(|error| {
         tracing::warn!(%error, "failed to list ingest files");
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    let files = rows
-        .into_iter()
-        .map(|row| IngestFileInfo {
-            content_hash: row.get("content_hash"),
-            filename: row.get("filename"),
-            file_size: row.get("file_size"),
-            total_chunks: row.get("total_chunks"),
-            chunks_completed: row.get("chunks_completed"),
-            status: row.get("status"),
-            started_at: row.get("started_at"),
-            completed_at: row.get("completed_at"),
-        })
-        .collect();
-
     Ok(Json(IngestFilesResponse { files }))
 }
 
@@ -167,17 +196,21 @@ pub(super) async fn upload_ingest_file(
             let pools = state.agent_pools.load();
             if let Some(pool) = pools.get(&query.agent_id) {
                 let file_size = data.len() as i64;
-                let _ = sqlx::query(
-                    r#"
-                    INSERT OR IGNORE INTO ingestion_files (content_hash, filename, file_size, total_chunks, status)
-                    VALUES (?, ?, ?, 0, 'queued')
-                    "#,
-                )
-                .bind(&hash)
-                .bind(safe_name)
-                .bind(file_size)
-                .execute(pool)
-                .await;
+                let _ = with_pool!(pool, |pool| {
+                    sqlx::query(
+                        r#"
+                        INSERT INTO ingestion_files (content_hash, filename, file_size, total_chunks, status)
+                        VALUES ($1, $2, $3, 0, 'queued')
+                        ON CONFLICT DO NOTHING
+                        "#,
+                    )
+                    .bind(&hash)
+                    .bind(safe_name)
+                    .bind(file_size)
+                    .execute(pool)
+                    .await
+                    .map(drop)
+                });
             }
         }
 
@@ -202,14 +235,84 @@ pub(super) async fn delete_ingest_file(
     let pools = state.agent_pools.load();
     let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
 
-    sqlx::query("DELETE FROM ingestion_files WHERE content_hash = ?")
-        .bind(&query.content_hash)
-        .execute(pool)
+    with_pool!(pool, |pool| {
+        sqlx::query("DELETE FROM ingestion_files WHERE content_hash = $1")
+            .bind(&query.content_hash)
+            .execute(pool)
+            .await
+            .map(drop)
+    })
+    .map_err(|error| {
+        tracing::warn!(%error, "failed to delete ingest file record");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(Json(IngestDeleteResponse { success: true }))
+}
+
+/// List the web pages registered for ingestion.
+pub(super) async fn list_web_sources(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<IngestQuery>,
+) -> Result<Json<WebSourcesResponse>, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    let sources = WebSources::new(pool.clone())
+        .list()
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to list web sources");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(WebSourcesResponse { sources }))
+}
+
+/// Register a web page for ingestion. It's fetched on the next poll, and
+/// again whenever it's older than `web_refresh_secs`.
+pub(super) async fn add_web_source(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<IngestQuery>,
+    Json(request): Json<WebSourceRequest>,
+) -> Result<Json<WebSourceAddResponse>, StatusCode> {
+    let url = reqwest::Url::parse(request.url.trim()).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let pools = state.agent_pools.load();
+    let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    WebSources::new(pool.clone())
+        .add(url.as_str())
         .await
         .map_err(|error| {
-            tracing::warn!(%error, "failed to delete ingest file record");
+            tracing::warn!(%error, "failed to add web source");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+    tracing::info!(agent_id = %query.agent_id, %url, "web source added for ingestion");
+    Ok(Json(WebSourceAddResponse { url: url.into() }))
+}
+
+/// Stop fetching a web page. The memories ingested from it stay.
+pub(super) async fn delete_web_source(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<WebSourceDeleteQuery>,
+) -> Result<Json<IngestDeleteResponse>, StatusCode> {
+    let pools = state.agent_pools.load();
+    let pool = pools.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+
+    let removed = WebSources::new(pool.clone())
+        .remove(&query.url)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to remove web source");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if !removed {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
     Ok(Json(IngestDeleteResponse { success: true }))
 }
@@ -1,5 +1,7 @@
 use super::state::ApiState;
 
+use crate::memory::MemoryFilter;
+use crate::memory::migration::MigrationProgress;
 use crate::memory::search::{SearchConfig, SearchMode};
 use crate::memory::types::{Association, Memory, MemorySearchResult, MemoryType};
 
@@ -44,6 +46,9 @@ pub(super) struct MemoriesListQuery {
     memory_type: Option<String>,
     #[serde(default = "default_memories_sort")]
     sort: String,
+    /// Filter expression, like `tag:runbook during:2024`.
+    #[serde(default)]
+    filter: Option<String>,
 }
 
 fn default_memories_limit() -> i64 {
@@ -62,6 +67,18 @@ pub(super) fn parse_sort(sort: &str) -> crate::memory::search::SearchSort {
     }
 }
 
+/// Parse a `filter` query parameter; an invalid expression is a bad request.
+fn parse_filter(filter: Option<&str>) -> Result<MemoryFilter, StatusCode> {
+    filter
+        .map(MemoryFilter::parse)
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(|error| {
+            tracing::debug!(%error, "rejecting invalid memory filter");
+            StatusCode::BAD_REQUEST
+        })
+}
+
 pub(super) fn parse_memory_type(type_str: &str) -> Option<MemoryType> {
     match type_str {
         "fact" => Some(MemoryType::Fact),
@@ -84,6 +101,9 @@ pub(super) struct MemoriesSearchQuery {
     limit: usize,
     #[serde(default)]
     memory_type: Option<String>,
+    /// Filter expression, like `tag:runbook during:2024`.
+    #[serde(default)]
+    filter: Option<String>,
 }
 
 fn default_search_limit() -> usize {
@@ -134,10 +154,11 @@ pub(super) async fn list_memories(
     let limit = query.limit.min(200);
     let sort = parse_sort(&query.sort);
     let memory_type = query.memory_type.as_deref().and_then(parse_memory_type);
+    let filter = parse_filter(query.filter.as_deref())?;
 
     let fetch_limit = limit + query.offset as i64;
     let all = store
-        .get_sorted(sort, fetch_limit, memory_type)
+        .get_sorted_filtered(sort, fetch_limit, memory_type, &filter)
         .await
         .map_err(|error| {
             tracing::warn!(%error, agent_id = %query.agent_id, "failed to list memories");
@@ -162,6 +183,7 @@ pub(super) async fn search_memories(
         mode: SearchMode::Hybrid,
         memory_type: query.memory_type.as_deref().and_then(parse_memory_type),
         max_results: query.limit.min(100),
+        filter: parse_filter(query.filter.as_deref())?,
         ..SearchConfig::default()
     };
 
@@ -245,3 +267,32 @@ pub(super) async fn memory_graph_neighbors(
 
     Ok(Json(MemoryGraphNeighborsResponse { nodes, edges }))
 }
+
+#[derive(Serialize)]
+pub(super) struct MemoryIndexResponse {
+    model: String,
+    dimensions: usize,
+    migration: Option<MigrationProgress>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct MemoryIndexQuery {
+    agent_id: String,
+}
+
+/// The embedding model searches use, and the progress of a re-embedding
+/// migration when one is running.
+pub(super) async fn index_status(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<MemoryIndexQuery>,
+) -> Result<Json<MemoryIndexResponse>, StatusCode> {
+    let searches = state.memory_searches.load();
+    let memory_search = searches.get(&query.agent_id).ok_or(StatusCode::NOT_FOUND)?;
+    let model = memory_search.embedding_model();
+
+    Ok(Json(MemoryIndexResponse {
+        model: model.name().to_string(),
+        dimensions: model.dimensions(),
+        migration: memory_search.migration(),
+    }))
+}
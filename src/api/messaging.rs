@@ -385,10 +385,18 @@ pub(super) async fn toggle_platform(
                     }
                     "webhook" => {
                         if let Some(webhook_config) = &new_config.messaging.webhook {
-                            let adapter = crate::messaging::webhook::WebhookAdapter::new(
+                            let mut adapter = crate::messaging::webhook::WebhookAdapter::new(
                                 webhook_config.port,
                                 &webhook_config.bind,
                             );
+                            if webhook_config.require_api_key {
+                                adapter = adapter.with_api_keys(std::sync::Arc::new(
+                                    crate::api_keys::ApiKeyStore::new(&new_config.instance_dir),
+                                ));
+                            }
+                            if let Some(signing) = &webhook_config.signing {
+                                adapter = adapter.with_signing(signing.clone());
+                            }
                             if let Err(error) = manager.register_and_start(adapter).await {
                                 tracing::error!(%error, "failed to start webhook adapter on toggle");
                             }
@@ -398,9 +398,67 @@ pub(super) async fn get_models(
         }
     }
 
+    if let Some(manager) = state.llm_manager.read().await.clone() {
+        models.extend(self_hosted_models(&manager, requested_provider).await);
+    }
+
     Ok(Json(ModelsResponse { models }))
 }
 
+/// What each configured provider listed at the last discovery, and the
+/// configured models none of them did.
+pub(super) async fn discovered_models(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<crate::llm::discovery::Discovery>, StatusCode> {
+    let manager = state
+        .llm_manager
+        .read()
+        .await
+        .clone()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    Ok(Json(manager.model_catalog().snapshot().as_ref().clone()))
+}
+
+/// Models served by `openai_compatible` providers, asked of each server since
+/// no catalog knows what a self-hosted server was started with.
+async fn self_hosted_models(
+    manager: &crate::llm::LlmManager,
+    requested_provider: Option<&str>,
+) -> Vec<ModelInfo> {
+    let mut models = Vec::new();
+    for (provider_id, provider) in manager.openai_compatible_providers() {
+        if requested_provider.is_some_and(|requested| requested != provider_id) {
+            continue;
+        }
+        let http_client = manager.http_client_for(&provider_id);
+        match crate::llm::openai_compatible::list_models(http_client, &provider).await {
+            Ok(ids) => models.extend(ids.into_iter().map(|id| ModelInfo {
+                id: format!("{provider_id}/{id}"),
+                name: id,
+                provider: provider_id.clone(),
+                context_window: None,
+                tool_call: true,
+                reasoning: false,
+            })),
+            Err(error) => {
+                tracing::warn!(%error, provider = %provider_id, "failed to list self-hosted models");
+            }
+        }
+    }
+    models
+}
+
+/// Drop the cached catalog. Returns how many models were dropped, or 0 while
+/// a request holds the cache.
+pub(crate) fn shed_cache() -> usize {
+    let Ok(mut cache) = MODELS_CACHE.try_write() else {
+        return 0;
+    };
+    let dropped = cache.0.len();
+    *cache = (Vec::new(), std::time::Instant::now() - MODELS_CACHE_TTL);
+    dropped
+}
+
 pub(super) async fn refresh_models(
     State(state): State<Arc<ApiState>>,
 ) -> Result<Json<ModelsResponse>, StatusCode> {
@@ -211,6 +211,16 @@ fn build_test_llm_config(provider: &str, credential: &str) -> crate::config::Llm
         moonshot_key: (provider == "moonshot").then(|| credential.to_string()),
         zai_coding_plan_key: (provider == "zai-coding-plan").then(|| credential.to_string()),
         providers,
+        budget: crate::llm::budget::BudgetConfig::default(),
+        ollama: crate::llm::ollama::OllamaConfig::default(),
+        chaos: crate::llm::chaos::ChaosConfig::default(),
+        shared_state: crate::llm::shared::SharedStateConfig::default(),
+        cleanup_interval_secs: crate::llm::manager::DEFAULT_CLEANUP_INTERVAL_SECS,
+        http: crate::llm::http::HttpConfig::default(),
+        aliases: crate::llm::aliases::ModelAliases::default(),
+        tenants: HashMap::new(),
+        billing: crate::tenants::billing::BillingConfig::default(),
+        slo: crate::llm::slo::SloConfig::default(),
     }
 }
 
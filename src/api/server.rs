@@ -3,7 +3,7 @@
 use super::state::ApiState;
 use super::{
     agents, bindings, channels, config, cortex, cron, ingest, memories, messaging, models,
-    providers, settings, skills, system, webchat,
+    providers, settings, skills, system, tenants, webchat,
 };
 
 use axum::Router;
@@ -41,6 +41,14 @@ pub async fn start_http_server(
         .route("/idle", get(system::idle))
         .route("/status", get(system::status))
         .route("/system/storage", get(system::storage_status))
+        .route("/system/resources", get(system::resource_status))
+        .route("/system/tasks", get(system::task_status))
+        .route(
+            "/system/log-levels",
+            get(system::log_levels)
+                .put(system::set_log_levels)
+                .delete(system::reset_log_levels),
+        )
         .route("/system/backup/export", get(system::backup_export))
         .route("/system/backup/restore", post(system::backup_restore))
         .route("/overview", get(agents::instance_overview))
@@ -52,12 +60,20 @@ pub async fn start_http_server(
                 .delete(agents::delete_agent),
         )
         .route("/agents/overview", get(agents::agent_overview))
+        .route(
+            "/tenants",
+            get(tenants::list_tenants)
+                .post(tenants::create_tenant)
+                .delete(tenants::delete_tenant),
+        )
+        .route("/tenants/usage", get(tenants::tenant_usage))
         .route("/channels", get(channels::list_channels))
         .route("/channels/messages", get(channels::channel_messages))
         .route("/channels/status", get(channels::channel_status))
         .route("/agents/memories", get(memories::list_memories))
         .route("/agents/memories/search", get(memories::search_memories))
         .route("/agents/memories/graph", get(memories::memory_graph))
+        .route("/agents/memories/index", get(memories::index_status))
         .route(
             "/agents/memories/graph/neighbors",
             get(memories::memory_graph_neighbors),
@@ -89,6 +105,12 @@ pub async fn start_http_server(
             get(ingest::list_ingest_files).delete(ingest::delete_ingest_file),
         )
         .route("/agents/ingest/upload", post(ingest::upload_ingest_file))
+        .route(
+            "/agents/ingest/web",
+            get(ingest::list_web_sources)
+                .post(ingest::add_web_source)
+                .delete(ingest::delete_web_source),
+        )
         .route("/agents/skills", get(skills::list_skills))
         .route("/agents/skills/install", post(skills::install_skill))
         .route("/agents/skills/remove", delete(skills::remove_skill))
@@ -102,6 +124,7 @@ pub async fn start_http_server(
         .route("/providers/{provider}", delete(providers::delete_provider))
         .route("/models", get(models::get_models))
         .route("/models/refresh", post(models::refresh_models))
+        .route("/models/discovered", get(models::discovered_models))
         .route("/messaging/status", get(messaging::messaging_status))
         .route(
             "/messaging/disconnect",
@@ -123,6 +146,18 @@ pub async fn start_http_server(
             "/config/raw",
             get(settings::get_raw_config).put(settings::update_raw_config),
         )
+        .route(
+            "/config/staged",
+            get(settings::get_staged_config)
+                .put(settings::stage_config)
+                .delete(settings::discard_staged_config),
+        )
+        .route("/config/staged/check", post(settings::check_staged_config))
+        .route(
+            "/config/staged/promote",
+            post(settings::promote_staged_config),
+        )
+        .route("/config/revert", post(settings::revert_config))
         .route(
             "/update/check",
             get(settings::update_check).post(settings::update_check_now),
@@ -132,18 +167,19 @@ pub async fn start_http_server(
         .route("/webchat/history", get(webchat::webchat_history));
 
     let app = Router::new()
+        .route("/healthz", get(system::healthz))
         .nest("/api", api_routes)
         .fallback(static_handler)
         .layer(cors)
         .with_state(state);
 
-    let listener = tokio::net::TcpListener::bind(bind).await?;
+    let listener = crate::listeners::bind("api", bind).await?;
     tracing::info!(%bind, "HTTP server listening");
 
     let handle = tokio::spawn(async move {
         let mut shutdown = shutdown_rx;
-        if let Err(error) = axum::serve(listener, app)
-            .with_graceful_shutdown(async move {
+        if let Err(error) = listener
+            .serve(app, async move {
                 let _ = shutdown.wait_for(|v| *v).await;
             })
             .await
@@ -83,6 +83,41 @@ pub(super) struct RawConfigUpdateResponse {
     message: String,
 }
 
+#[derive(Serialize)]
+pub(super) struct StagedConfigResponse {
+    /// `None` when nothing is staged.
+    content: Option<String>,
+    /// Settings promoting it would change.
+    changes: Vec<String>,
+    /// Why it can't be promoted.
+    error: Option<String>,
+    can_revert: bool,
+}
+
+#[derive(Serialize)]
+pub(super) struct StagedConfigActionResponse {
+    success: bool,
+    message: String,
+    changes: Vec<String>,
+}
+
+impl StagedConfigActionResponse {
+    fn from_result(result: anyhow::Result<(String, Vec<String>)>) -> Json<Self> {
+        Json(match result {
+            Ok((message, changes)) => Self {
+                success: true,
+                message,
+                changes,
+            },
+            Err(error) => Self {
+                success: false,
+                message: format!("{error:#}"),
+                changes: Vec::new(),
+            },
+        })
+    }
+}
+
 pub(super) async fn get_global_settings(
     State(state): State<Arc<ApiState>>,
 ) -> Result<Json<GlobalSettingsResponse>, StatusCode> {
@@ -446,3 +481,108 @@ pub(super) async fn update_raw_config(
         message: "Config saved and reloaded.".to_string(),
     }))
 }
+
+async fn config_stage(state: &ApiState) -> Result<crate::config::stage::ConfigStage, StatusCode> {
+    let config_path = state.config_path.read().await.clone();
+    if config_path.as_os_str().is_empty() {
+        tracing::error!("config_path not set in ApiState");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    Ok(crate::config::stage::ConfigStage::new(&config_path))
+}
+
+pub(super) async fn get_staged_config(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<StagedConfigResponse>, StatusCode> {
+    let stage = config_stage(&state).await?;
+    let content = stage.staged_content().map_err(|error| {
+        tracing::warn!(%error, "failed to read the staged config");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let (changes, error) = match content.as_ref().map(|_| stage.diff()) {
+        Some(Ok(changes)) => (changes, None),
+        Some(Err(error)) => (Vec::new(), Some(format!("{error:#}"))),
+        None => (Vec::new(), None),
+    };
+    Ok(Json(StagedConfigResponse {
+        content,
+        changes,
+        error,
+        can_revert: stage.can_revert(),
+    }))
+}
+
+pub(super) async fn stage_config(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<RawConfigUpdateRequest>,
+) -> Result<Json<StagedConfigActionResponse>, StatusCode> {
+    let stage = config_stage(&state).await?;
+    Ok(StagedConfigActionResponse::from_result(
+        stage
+            .stage(&request.content)
+            .map(|changes| ("Config staged.".to_string(), changes)),
+    ))
+}
+
+pub(super) async fn discard_staged_config(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<StagedConfigActionResponse>, StatusCode> {
+    let stage = config_stage(&state).await?;
+    Ok(StagedConfigActionResponse::from_result(
+        stage.discard().map(|discarded| {
+            let message = if discarded {
+                "Staged config discarded."
+            } else {
+                "Nothing is staged."
+            };
+            (message.to_string(), Vec::new())
+        }),
+    ))
+}
+
+/// Validate the staged config and run the preflight checks against it.
+pub(super) async fn check_staged_config(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<StagedConfigActionResponse>, StatusCode> {
+    let stage = config_stage(&state).await?;
+    Ok(Json(match stage.check().await {
+        Ok(check) => StagedConfigActionResponse {
+            success: check.passed(),
+            message: check.render(),
+            changes: check.changes,
+        },
+        Err(error) => StagedConfigActionResponse {
+            success: false,
+            message: format!("{error:#}"),
+            changes: Vec::new(),
+        },
+    }))
+}
+
+/// Make the staged config live. The file watcher applies it.
+pub(super) async fn promote_staged_config(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<StagedConfigActionResponse>, StatusCode> {
+    let stage = config_stage(&state).await?;
+    let result = stage.promote();
+    if let Ok(changes) = &result {
+        tracing::info!(?changes, "staged config promoted via API");
+    }
+    Ok(StagedConfigActionResponse::from_result(result.map(
+        |changes| ("Staged config promoted.".to_string(), changes),
+    )))
+}
+
+/// Swap the config the last promotion or revert replaced back in.
+pub(super) async fn revert_config(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<StagedConfigActionResponse>, StatusCode> {
+    let stage = config_stage(&state).await?;
+    let result = stage.revert();
+    if let Ok(changes) = &result {
+        tracing::info!(?changes, "config reverted via API");
+    }
+    Ok(StagedConfigActionResponse::from_result(result.map(
+        |changes| ("Reverted to the previous config.".to_string(), changes),
+    )))
+}
@@ -5,11 +5,15 @@ use crate::agent::cortex_chat::CortexChatSession;
 use crate::agent::status::StatusBlock;
 use crate::config::{Binding, DefaultsConfig, DiscordPermissions, RuntimeConfig, SlackPermissions};
 use crate::cron::{CronStore, Scheduler};
+use crate::db::{DatabaseConfig, SqlPool};
+use crate::jobs::JobQueue;
 use crate::llm::LlmManager;
 use crate::memory::{EmbeddingModel, MemorySearch};
 use crate::messaging::MessagingManager;
 use crate::messaging::webchat::WebChatAdapter;
+use crate::plugins::PluginHost;
 use crate::prompts::PromptEngine;
+use crate::storage::StorageConfig;
 use crate::update::SharedUpdateStatus;
 use crate::{ProcessEvent, ProcessId};
 
@@ -38,8 +42,8 @@ pub struct ApiState {
     pub started_at: Instant,
     /// Aggregated event stream from all agents. SSE clients subscribe here.
     pub event_tx: broadcast::Sender<ApiEvent>,
-    /// Per-agent SQLite pools for querying channel/conversation data.
-    pub agent_pools: arc_swap::ArcSwap<HashMap<String, sqlx::SqlitePool>>,
+    /// Per-agent database pools for querying channel/conversation data.
+    pub agent_pools: arc_swap::ArcSwap<HashMap<String, SqlPool>>,
     /// Per-agent config summaries for the agents list endpoint.
     pub agent_configs: arc_swap::ArcSwap<Vec<AgentInfo>>,
     /// Per-agent memory search instances for the memories API.
@@ -77,12 +81,20 @@ pub struct ApiState {
     pub instance_dir: ArcSwap<PathBuf>,
     /// Shared LLM manager for agent creation.
     pub llm_manager: RwLock<Option<Arc<LlmManager>>>,
+    /// Shared job queue for agent creation.
+    pub job_queue: RwLock<Option<Arc<JobQueue>>>,
     /// Shared embedding model for agent creation.
     pub embedding_model: RwLock<Option<Arc<EmbeddingModel>>>,
     /// Prompt engine snapshot for agent creation.
     pub prompt_engine: RwLock<Option<PromptEngine>>,
     /// Instance-level defaults for resolving new agent configs.
     pub defaults_config: RwLock<Option<DefaultsConfig>>,
+    /// Where new agents keep their relational data.
+    pub database_config: RwLock<Option<DatabaseConfig>>,
+    /// Where new agents keep their binary artifacts.
+    pub storage_config: RwLock<Option<StorageConfig>>,
+    /// Loaded WASM plugins, shared with new agents.
+    pub plugins: RwLock<Option<Arc<PluginHost>>>,
     /// Sender to register newly created agents with the main event loop.
     pub agent_tx: mpsc::Sender<crate::Agent>,
     /// Sender to remove agents from the main event loop.
@@ -199,9 +211,13 @@ impl ApiState {
             update_status: crate::update::new_shared_status(),
             instance_dir: ArcSwap::from_pointee(PathBuf::new()),
             llm_manager: RwLock::new(None),
+            job_queue: RwLock::new(None),
             embedding_model: RwLock::new(None),
             prompt_engine: RwLock::new(None),
             defaults_config: RwLock::new(None),
+            database_config: RwLock::new(None),
+            storage_config: RwLock::new(None),
+            plugins: RwLock::new(None),
             agent_tx,
             agent_remove_tx,
             webchat_adapter: ArcSwap::from_pointee(None),
@@ -371,7 +387,7 @@ impl ApiState {
     }
 
     /// Set the SQLite pools for all agents.
-    pub fn set_agent_pools(&self, pools: HashMap<String, sqlx::SqlitePool>) {
+    pub fn set_agent_pools(&self, pools: HashMap<String, SqlPool>) {
         self.agent_pools.store(Arc::new(pools));
     }
 
@@ -446,6 +462,11 @@ impl ApiState {
         *self.llm_manager.write().await = Some(manager);
     }
 
+    /// Set the shared job queue for runtime agent creation.
+    pub async fn set_job_queue(&self, job_queue: Arc<JobQueue>) {
+        *self.job_queue.write().await = Some(job_queue);
+    }
+
     /// Set the shared embedding model for runtime agent creation.
     pub async fn set_embedding_model(&self, model: Arc<EmbeddingModel>) {
         *self.embedding_model.write().await = Some(model);
@@ -461,6 +482,21 @@ impl ApiState {
         *self.defaults_config.write().await = Some(defaults);
     }
 
+    /// Set the database settings for runtime agent creation.
+    pub async fn set_database_config(&self, database: DatabaseConfig) {
+        *self.database_config.write().await = Some(database);
+    }
+
+    /// Set the artifact storage settings for runtime agent creation.
+    pub async fn set_storage_config(&self, storage: StorageConfig) {
+        *self.storage_config.write().await = Some(storage);
+    }
+
+    /// Share the loaded plugins with agents created at runtime.
+    pub async fn set_plugins(&self, plugins: Arc<PluginHost>) {
+        *self.plugins.write().await = Some(plugins);
+    }
+
     /// Set the shared webchat adapter for API handlers.
     pub fn set_webchat_adapter(&self, adapter: Arc<WebChatAdapter>) {
         self.webchat_adapter.store(Arc::new(Some(adapter)));
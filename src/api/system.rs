@@ -1,26 +1,30 @@
 use super::state::{ApiEvent, ApiState};
 
-use axum::body::Bytes;
 use axum::Json;
+use axum::body::Bytes;
 use axum::extract::State;
 use axum::http::header;
 use axum::response::IntoResponse;
 use axum::response::Sse;
 use futures::stream::Stream;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::convert::Infallible;
 use std::io::Write as _;
-use std::path::Component;
 use std::path::Path;
 use std::sync::Arc;
-use zip::CompressionMethod;
-use zip::write::SimpleFileOptions;
 
 #[derive(Serialize)]
 pub(super) struct HealthResponse {
     status: &'static str,
 }
 
+#[derive(Serialize)]
+pub(super) struct HealthzResponse {
+    status: &'static str,
+    #[serde(flatten)]
+    build: crate::update::BuildInfo,
+}
+
 #[derive(Serialize)]
 pub(super) struct IdleResponse {
     idle: bool,
@@ -40,6 +44,14 @@ pub(super) async fn health() -> Json<HealthResponse> {
     Json(HealthResponse { status: "ok" })
 }
 
+/// Liveness plus what's running, for load balancers and deploy checks.
+pub(super) async fn healthz() -> Json<HealthzResponse> {
+    Json(HealthzResponse {
+        status: "ok",
+        build: crate::update::build_info(),
+    })
+}
+
 /// Reports whether the instance is idle (no active workers or branches).
 /// Used by the platform to gate rolling updates.
 pub(super) async fn idle(State(state): State<Arc<ApiState>>) -> Json<IdleResponse> {
@@ -212,295 +224,64 @@ fn directory_size_bytes(root: &Path) -> anyhow::Result<u64> {
     Ok(total)
 }
 
-pub(super) async fn backup_export(
+/// Latest GPU and host resource sample. Samples on demand if the background
+/// sampler hasn't run yet (e.g. no Ollama provider configured).
+pub(super) async fn resource_status(
     State(state): State<Arc<ApiState>>,
-) -> Result<impl IntoResponse, (axum::http::StatusCode, String)> {
-    let runtime_configs = state.runtime_configs.load();
-    let Some(runtime_config) = runtime_configs.values().next() else {
-        return Err((
-            axum::http::StatusCode::SERVICE_UNAVAILABLE,
-            "no runtime config available".to_string(),
-        ));
-    };
-
-    let instance_dir = runtime_config.instance_dir.clone();
-    let archive_bytes = tokio::task::spawn_blocking(move || build_backup_zip(&instance_dir))
+) -> Result<Json<crate::llm::resources::ResourceSnapshot>, axum::http::StatusCode> {
+    let llm_manager = state
+        .llm_manager
+        .read()
         .await
-        .map_err(|error| {
-            (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("backup task failed: {error}"),
-            )
-        })
-        .and_then(|result| {
-            result.map_err(|error| {
-                (
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("backup generation failed: {error}"),
-                )
-            })
-        })?;
-
-    let headers = [
-        (header::CONTENT_TYPE, "application/zip"),
-        (
-            header::CONTENT_DISPOSITION,
-            "attachment; filename=spacebot-backup.zip",
-        ),
-    ];
+        .clone()
+        .ok_or(axum::http::StatusCode::SERVICE_UNAVAILABLE)?;
 
-    Ok((headers, archive_bytes))
-}
-
-pub(super) async fn backup_restore(
-    State(state): State<Arc<ApiState>>,
-    body: Bytes,
-) -> Result<Json<serde_json::Value>, (axum::http::StatusCode, String)> {
-    if body.is_empty() {
-        return Err((
-            axum::http::StatusCode::BAD_REQUEST,
-            "backup archive payload is empty".to_string(),
-        ));
-    }
-
-    let runtime_configs = state.runtime_configs.load();
-    let Some(runtime_config) = runtime_configs.values().next() else {
-        return Err((
-            axum::http::StatusCode::SERVICE_UNAVAILABLE,
-            "no runtime config available".to_string(),
-        ));
+    let snapshot = match llm_manager.resources().latest() {
+        Some(snapshot) => snapshot,
+        None => llm_manager.resources().refresh().await,
     };
-
-    let instance_dir = runtime_config.instance_dir.clone();
-    let archive = body.to_vec();
-
-    let restore_report = tokio::task::spawn_blocking(move || restore_backup_zip(&instance_dir, archive))
-        .await
-        .map_err(|error| {
-            (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("restore task failed: {error}"),
-            )
-        })
-        .and_then(|result| {
-            result.map_err(|error| {
-                (
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("restore failed: {error}"),
-                )
-            })
-        })?;
-
-    Ok(Json(serde_json::json!({
-        "restored": true,
-        "files_restored": restore_report.files_restored,
-        "message": "backup restored to disk; restart instance to fully apply"
-    })))
+    Ok(Json((*snapshot).clone()))
 }
 
-struct RestoreReport {
-    files_restored: usize,
+/// Supervised tasks (adapters, the file watcher, cron timers, and agent
+/// loops) with their state and restart counts.
+pub(super) async fn task_status() -> Json<Vec<crate::supervisor::TaskStatus>> {
+    Json(crate::supervisor::statuses())
 }
 
-fn build_backup_zip(instance_dir: &Path) -> anyhow::Result<Vec<u8>> {
-    let mut cursor = std::io::Cursor::new(Vec::new());
-    let mut writer = zip::ZipWriter::new(&mut cursor);
-    let options = SimpleFileOptions::default()
-        .compression_method(CompressionMethod::Deflated)
-        .unix_permissions(0o644);
-
-    let include_paths = [
-        ("config.toml", instance_dir.join("config.toml")),
-        ("agents", instance_dir.join("agents")),
-    ];
-
-    for (name, path) in include_paths {
-        if !path.exists() {
-            continue;
-        }
-
-        if path.is_file() {
-            add_file_to_zip(&mut writer, &path, name, options)?;
-        } else {
-            add_directory_to_zip(&mut writer, &path, name, options)?;
-        }
-    }
-
-    writer.finish()?;
-    Ok(cursor.into_inner())
-}
-
-fn restore_backup_zip(instance_dir: &Path, archive_bytes: Vec<u8>) -> anyhow::Result<RestoreReport> {
-    let restore_root = instance_dir.join(format!(".restore-{}", uuid::Uuid::new_v4()));
-    std::fs::create_dir_all(&restore_root)?;
-
-    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(archive_bytes))?;
-    let mut files_restored = 0usize;
-
-    for index in 0..archive.len() {
-        let mut file = archive.by_index(index)?;
-        let Some(enclosed_name) = file.enclosed_name().map(|path| path.to_path_buf()) else {
-            continue;
-        };
-
-        let allowed = enclosed_name == Path::new("config.toml")
-            || enclosed_name.starts_with(Path::new("agents"));
-        if !allowed {
-            continue;
-        }
-
-        let target = restore_root.join(&enclosed_name);
-        if file.is_dir() {
-            std::fs::create_dir_all(&target)?;
-            continue;
-        }
-
-        if let Some(parent) = target.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-
-        let mut output = std::fs::File::create(&target)?;
-        std::io::copy(&mut file, &mut output)?;
-        files_restored += 1;
-    }
-
-    let restored_config = restore_root.join("config.toml");
-    if restored_config.exists() {
-        replace_path_atomic(&restored_config, &instance_dir.join("config.toml"))?;
-    }
-
-    let restored_agents = restore_root.join("agents");
-    if restored_agents.exists() {
-        replace_directory(&restored_agents, &instance_dir.join("agents"))?;
-    }
-
-    let _ = std::fs::remove_dir_all(&restore_root);
-    Ok(RestoreReport { files_restored })
-}
-
-fn replace_path_atomic(source: &Path, destination: &Path) -> anyhow::Result<()> {
-    if let Some(parent) = destination.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-
-    let temp_destination = destination.with_extension("restore_tmp");
-    std::fs::copy(source, &temp_destination)?;
-    std::fs::rename(temp_destination, destination)?;
-    Ok(())
+#[derive(Serialize)]
+pub(super) struct LogLevelsResponse {
+    filter: String,
 }
 
-fn replace_directory(source: &Path, destination: &Path) -> anyhow::Result<()> {
-    let temp_destination = destination.with_extension("restore_tmp_dir");
-    if temp_destination.exists() {
-        std::fs::remove_dir_all(&temp_destination)?;
-    }
-
-    std::fs::rename(source, &temp_destination)?;
-
-    if destination.exists() {
-        std::fs::remove_dir_all(destination)?;
-    }
-
-    std::fs::rename(&temp_destination, destination)?;
-    Ok(())
+#[derive(Deserialize)]
+pub(super) struct LogLevelsRequest {
+    /// `llm=debug`-style changes, as for `!admin loglevel`.
+    changes: String,
 }
 
-fn add_directory_to_zip(
-    writer: &mut zip::ZipWriter<&mut std::io::Cursor<Vec<u8>>>,
-    directory_path: &Path,
-    archive_prefix: &str,
-    options: SimpleFileOptions,
-) -> anyhow::Result<()> {
-    for entry in std::fs::read_dir(directory_path)? {
-        let entry = entry?;
-        let path = entry.path();
-        let file_name = entry.file_name().to_string_lossy().to_string();
-
-        if path.is_dir() && file_name == "logs" {
-            continue;
-        }
-
-        if path.is_dir() && file_name == "workspace" {
-            let name = format!("{archive_prefix}/{file_name}");
-            add_workspace_directory_to_zip(writer, &path, &name, options)?;
-            continue;
-        }
-
-        let name = format!("{archive_prefix}/{file_name}");
-
-        if path.is_dir() {
-            add_directory_to_zip(writer, &path, &name, options)?;
-        } else if path.is_file() {
-            add_file_to_zip(writer, &path, &name, options)?;
-        }
-    }
+type LogLevelsResult = Result<Json<LogLevelsResponse>, (axum::http::StatusCode, String)>;
 
-    Ok(())
+/// The log filter in effect.
+pub(super) async fn log_levels() -> LogLevelsResult {
+    crate::log_levels::current()
+        .map(|filter| Json(LogLevelsResponse { filter }))
+        .ok_or((
+            axum::http::StatusCode::SERVICE_UNAVAILABLE,
+            crate::log_levels::NOT_RELOADABLE.to_string(),
+        ))
 }
 
-fn add_workspace_directory_to_zip(
-    writer: &mut zip::ZipWriter<&mut std::io::Cursor<Vec<u8>>>,
-    workspace_path: &Path,
-    archive_prefix: &str,
-    options: SimpleFileOptions,
-) -> anyhow::Result<()> {
-    let walk = ignore::WalkBuilder::new(workspace_path)
-        .hidden(false)
-        .git_ignore(true)
-        .git_global(true)
-        .git_exclude(true)
-        .parents(true)
-        .build();
-
-    for result in walk {
-        let entry = match result {
-            Ok(entry) => entry,
-            Err(_) => continue,
-        };
-
-        let path = entry.path();
-        if path == workspace_path {
-            continue;
-        }
-
-        if path
-            .components()
-            .any(|component| matches!(component, Component::Normal(name) if name == ".git"))
-        {
-            continue;
-        }
-
-        let metadata = match std::fs::symlink_metadata(path) {
-            Ok(metadata) => metadata,
-            Err(_) => continue,
-        };
-
-        if !metadata.is_file() {
-            continue;
-        }
-
-        let Ok(relative) = path.strip_prefix(workspace_path) else {
-            continue;
-        };
-
-        let relative_name = relative
-            .to_string_lossy()
-            .replace(std::path::MAIN_SEPARATOR, "/");
-        let archive_name = format!("{archive_prefix}/{relative_name}");
-        add_file_to_zip(writer, path, &archive_name, options)?;
-    }
-
-    Ok(())
+/// Change log levels on top of the current filter.
+pub(super) async fn set_log_levels(Json(request): Json<LogLevelsRequest>) -> LogLevelsResult {
+    crate::log_levels::set(&request.changes)
+        .map(|filter| Json(LogLevelsResponse { filter }))
+        .map_err(|error| (axum::http::StatusCode::BAD_REQUEST, error))
 }
 
-fn add_file_to_zip(
-    writer: &mut zip::ZipWriter<&mut std::io::Cursor<Vec<u8>>>,
-    file_path: &Path,
-    archive_name: &str,
-    options: SimpleFileOptions,
-) -> anyhow::Result<()> {
-    writer.start_file(archive_name, options)?;
-    let file_bytes = std::fs::read(file_path)?;
-    writer.write_all(&file_bytes)?;
-    Ok(())
+/// Go back to the log level Spacebot started with.
+pub(super) async fn reset_log_levels() -> LogLevelsResult {
+    crate::log_levels::reset()
+        .map(|filter| Json(LogLevelsResponse { filter }))
+        .map_err(|error| (axum::http::StatusCode::SERVICE_UNAVAILABLE, error))
 }
@@ -0,0 +1,360 @@
+use super::state::ApiState;
+
+use crate::llm::LlmManager;
+use crate::tenants::billing;
+
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use chrono::{Datelike as _, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Serialize)]
+pub(super) struct TenantResponse {
+    id: String,
+    name: Option<String>,
+    agent_id: String,
+    discord_guilds: Vec<String>,
+    slack_workspaces: Vec<String>,
+    /// Providers the tenant has its own key for. Keys are never returned.
+    key_providers: Vec<String>,
+    daily_limit_usd: Option<f64>,
+    monthly_limit_usd: Option<f64>,
+    daily_spend_usd: f64,
+    monthly_spend_usd: f64,
+}
+
+#[derive(Serialize)]
+pub(super) struct TenantsResponse {
+    tenants: Vec<TenantResponse>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct CreateTenantRequest {
+    tenant_id: String,
+    #[serde(default)]
+    name: Option<String>,
+    /// Defaults to the tenant ID. Created if it doesn't exist.
+    #[serde(default)]
+    agent_id: Option<String>,
+    #[serde(default)]
+    discord_guilds: Vec<String>,
+    #[serde(default)]
+    slack_workspaces: Vec<String>,
+    #[serde(default)]
+    api_keys: HashMap<String, String>,
+    #[serde(default)]
+    daily_limit_usd: Option<f64>,
+    #[serde(default)]
+    monthly_limit_usd: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct DeleteTenantQuery {
+    tenant_id: String,
+}
+
+#[derive(Deserialize)]
+pub(super) struct TenantUsageQuery {
+    /// First UTC day (YYYY-MM-DD). Defaults to the start of this month.
+    #[serde(default)]
+    from: Option<NaiveDate>,
+    /// Last UTC day, inclusive. Defaults to today.
+    #[serde(default)]
+    to: Option<NaiveDate>,
+    #[serde(default)]
+    tenant_id: Option<String>,
+    /// "json" (default) or "csv".
+    #[serde(default)]
+    format: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(super) struct TenantUsageResponse {
+    from: NaiveDate,
+    to: NaiveDate,
+    tenants: Vec<billing::TenantUsage>,
+}
+
+async fn llm_manager(state: &ApiState) -> Result<Arc<LlmManager>, StatusCode> {
+    state.llm_manager.read().await.clone().ok_or_else(|| {
+        tracing::error!("LLM manager not available");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn read_config(state: &ApiState) -> Result<toml_edit::DocumentMut, StatusCode> {
+    let config_path = state.config_path.read().await.clone();
+    let content = if config_path.exists() {
+        tokio::fs::read_to_string(&config_path)
+            .await
+            .map_err(|error| {
+                tracing::warn!(%error, "failed to read config.toml");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    } else {
+        String::new()
+    };
+    content.parse().map_err(|error| {
+        tracing::warn!(%error, "failed to parse config.toml");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+async fn write_config(state: &ApiState, doc: &toml_edit::DocumentMut) -> Result<(), StatusCode> {
+    let config_path = state.config_path.read().await.clone();
+    tokio::fs::write(&config_path, doc.to_string())
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to write config.toml");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+fn failure(message: String) -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "success": false,
+        "message": message
+    }))
+}
+
+/// List tenants with their spend this day and month.
+pub(super) async fn list_tenants(
+    State(state): State<Arc<ApiState>>,
+) -> Result<Json<TenantsResponse>, StatusCode> {
+    let llm_manager = llm_manager(&state).await?;
+    let tenants = llm_manager
+        .tenants()
+        .into_iter()
+        .map(|tenant| {
+            let spend = llm_manager.tenant_spend(&tenant.id);
+            let mut key_providers: Vec<String> = tenant.api_keys.keys().cloned().collect();
+            key_providers.sort();
+            TenantResponse {
+                id: tenant.id.clone(),
+                name: tenant.name.clone(),
+                agent_id: tenant.agent_id.clone(),
+                discord_guilds: tenant.discord_guilds.clone(),
+                slack_workspaces: tenant.slack_workspaces.clone(),
+                key_providers,
+                daily_limit_usd: tenant.daily_limit_usd,
+                monthly_limit_usd: tenant.monthly_limit_usd,
+                daily_spend_usd: spend.daily_usd,
+                monthly_spend_usd: spend.monthly_usd,
+            }
+        })
+        .collect();
+    Ok(Json(TenantsResponse { tenants }))
+}
+
+/// Usage per tenant and model over a range of days, with `[billing]` markups
+/// applied, as JSON or as a CSV download for invoicing.
+pub(super) async fn tenant_usage(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<TenantUsageQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let today = chrono::Utc::now().date_naive();
+    let to = query.to.unwrap_or(today);
+    let from = query
+        .from
+        .unwrap_or_else(|| to.with_day(1).expect("every month has a first day"));
+    if from > to {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("from ({from}) is after to ({to})"),
+        ));
+    }
+
+    let llm_manager = llm_manager(&state)
+        .await
+        .map_err(|status| (status, "LLM manager not available".to_string()))?;
+    let lines = llm_manager.tenant_usage(from, to, query.tenant_id.as_deref());
+
+    match query.format.as_deref().unwrap_or("json") {
+        "json" => Ok(Json(TenantUsageResponse {
+            from,
+            to,
+            tenants: billing::summarize(lines),
+        })
+        .into_response()),
+        "csv" => {
+            let csv = billing::to_csv(&lines).map_err(|error| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("failed to write usage CSV: {error}"),
+                )
+            })?;
+            let disposition = format!("attachment; filename=tenant-usage-{from}-{to}.csv");
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "text/csv".to_string()),
+                    (header::CONTENT_DISPOSITION, disposition),
+                ],
+                csv,
+            )
+                .into_response())
+        }
+        other => Err((
+            StatusCode::BAD_REQUEST,
+            format!("unknown format '{other}', expected json or csv"),
+        )),
+    }
+}
+
+/// Provision a tenant: create its agent if needed and add a `[[tenants]]`
+/// entry to config.toml. The file watcher applies it like any config edit.
+pub(super) async fn create_tenant(
+    State(state): State<Arc<ApiState>>,
+    Json(request): Json<CreateTenantRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let tenant_id = request.tenant_id.trim().to_string();
+    if tenant_id.is_empty()
+        || !tenant_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Ok(failure(
+            "Tenant ID must be letters, digits, '-', or '_'".into(),
+        ));
+    }
+    let agent_id = request
+        .agent_id
+        .map(|agent_id| agent_id.trim().to_string())
+        .filter(|agent_id| !agent_id.is_empty())
+        .unwrap_or_else(|| tenant_id.clone());
+
+    // Check against the running tenants so a conflicting entry never reaches
+    // config.toml, where it would fail every reload until removed.
+    let llm_manager = llm_manager(&state).await?;
+    for existing in llm_manager.tenants() {
+        if existing.id == tenant_id {
+            return Ok(failure(format!("Tenant '{tenant_id}' already exists")));
+        }
+        if existing.agent_id == agent_id {
+            return Ok(failure(format!(
+                "Agent '{agent_id}' already serves tenant '{}'",
+                existing.id
+            )));
+        }
+        if let Some(guild) = request
+            .discord_guilds
+            .iter()
+            .find(|guild| existing.discord_guilds.contains(guild))
+        {
+            return Ok(failure(format!(
+                "Guild '{guild}' belongs to tenant '{}'",
+                existing.id
+            )));
+        }
+        if let Some(workspace) = request
+            .slack_workspaces
+            .iter()
+            .find(|workspace| existing.slack_workspaces.contains(workspace))
+        {
+            return Ok(failure(format!(
+                "Workspace '{workspace}' belongs to tenant '{}'",
+                existing.id
+            )));
+        }
+    }
+
+    let agent_exists = state
+        .agent_configs
+        .load()
+        .iter()
+        .any(|agent| agent.id == agent_id);
+    if !agent_exists {
+        let Json(created) = super::agents::create_agent(
+            State(state.clone()),
+            Json(super::agents::CreateAgentRequest {
+                agent_id: agent_id.clone(),
+            }),
+        )
+        .await?;
+        if created["success"] != serde_json::Value::Bool(true) {
+            return Ok(Json(created));
+        }
+    }
+
+    let mut doc = read_config(&state).await?;
+    if doc.get("tenants").is_none() {
+        doc["tenants"] = toml_edit::Item::ArrayOfTables(toml_edit::ArrayOfTables::new());
+    }
+    let tenants = doc["tenants"]
+        .as_array_of_tables_mut()
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut table = toml_edit::Table::new();
+    table["id"] = toml_edit::value(&tenant_id);
+    if let Some(name) = &request.name {
+        table["name"] = toml_edit::value(name);
+    }
+    table["agent"] = toml_edit::value(&agent_id);
+    if !request.discord_guilds.is_empty() {
+        table["discord_guilds"] =
+            toml_edit::value(toml_edit::Array::from_iter(&request.discord_guilds));
+    }
+    if !request.slack_workspaces.is_empty() {
+        table["slack_workspaces"] =
+            toml_edit::value(toml_edit::Array::from_iter(&request.slack_workspaces));
+    }
+    if let Some(limit) = request.daily_limit_usd {
+        table["daily_limit_usd"] = toml_edit::value(limit);
+    }
+    if let Some(limit) = request.monthly_limit_usd {
+        table["monthly_limit_usd"] = toml_edit::value(limit);
+    }
+    if !request.api_keys.is_empty() {
+        let mut api_keys = toml_edit::InlineTable::new();
+        for (provider, key) in &request.api_keys {
+            api_keys.insert(provider, key.as_str().into());
+        }
+        table["api_keys"] = toml_edit::value(api_keys);
+    }
+    tenants.push(table);
+    write_config(&state, &doc).await?;
+
+    tracing::info!(%tenant_id, %agent_id, "tenant provisioned via API");
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "tenant_id": tenant_id,
+        "agent_id": agent_id,
+        "message": format!("Tenant '{tenant_id}' created, served by agent '{agent_id}'")
+    })))
+}
+
+/// Remove a tenant's `[[tenants]]` entry. Its agent and data are kept; delete
+/// the agent separately to remove them.
+pub(super) async fn delete_tenant(
+    State(state): State<Arc<ApiState>>,
+    Query(query): Query<DeleteTenantQuery>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let tenant_id = query.tenant_id.trim().to_string();
+
+    let mut doc = read_config(&state).await?;
+    let Some(tenants) = doc
+        .get_mut("tenants")
+        .and_then(|item| item.as_array_of_tables_mut())
+    else {
+        return Ok(failure(format!("Tenant '{tenant_id}' not found")));
+    };
+    let Some(index) = tenants
+        .iter()
+        .position(|table| table.get("id").and_then(|id| id.as_str()) == Some(&tenant_id))
+    else {
+        return Ok(failure(format!("Tenant '{tenant_id}' not found")));
+    };
+    tenants.remove(index);
+    write_config(&state, &doc).await?;
+
+    tracing::info!(%tenant_id, "tenant removed via API");
+
+    Ok(Json(serde_json::json!({
+        "success": true,
+        "message": format!("Tenant '{tenant_id}' removed; its agent and data are kept")
+    })))
+}
@@ -0,0 +1,382 @@
+//! Issued API keys for the programmatic endpoints.
+//!
+//! Keys are created and revoked with `spacebot api-key` or the `!apikey` chat
+//! admin command, and kept in `api_keys.json` in the instance directory. Only
+//! a SHA-256 digest of each key is stored; the key itself is shown once, when
+//! it's created. Each key has a name, the [`Scope`]s it can be used for, and
+//! an optional expiry. Revoked keys stay in the file, marked with when they
+//! were revoked, so listings still account for them.
+//!
+//! The daemon and the CLI share the file: every lookup rereads it when it has
+//! changed on disk, so a key revoked from the CLI stops working at once.
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use rand::RngCore as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Prefix on every issued key, so leaked keys are easy to recognize.
+const KEY_PREFIX: &str = "sbk_";
+
+/// An endpoint a key can be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// The WebSocket API (`[messaging.websocket]`).
+    Websocket,
+    /// The webhook adapter's `/send` and `/poll` (`[messaging.webhook]`).
+    Webhook,
+    /// The OpenAI-compatible proxy (`[proxy]`).
+    Proxy,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 3] = [Scope::Websocket, Scope::Webhook, Scope::Proxy];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Websocket => "websocket",
+            Scope::Webhook => "webhook",
+            Scope::Proxy => "proxy",
+        }
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == value)
+            .ok_or_else(|| {
+                format!("unknown scope '{value}', expected websocket, webhook, or proxy")
+            })
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parse a comma-separated scope list. An empty list means every scope.
+pub fn parse_scopes(value: &str) -> Result<Vec<Scope>, String> {
+    let mut scopes: Vec<Scope> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|scope| !scope.is_empty())
+        .map(str::parse)
+        .collect::<Result<_, _>>()?;
+    if scopes.is_empty() {
+        scopes = Scope::ALL.to_vec();
+    }
+    scopes.sort();
+    scopes.dedup();
+    Ok(scopes)
+}
+
+/// When a key issued at `now` that lasts `days` expires.
+pub fn expiry_after_days(now: DateTime<Utc>, days: u32) -> Result<DateTime<Utc>, String> {
+    chrono::Duration::try_days(i64::from(days))
+        .and_then(|lifetime| now.checked_add_signed(lifetime))
+        .ok_or_else(|| format!("an expiry {days} days out is too far in the future"))
+}
+
+/// One issued key, without the key itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiKeyRecord {
+    /// Short ID used to revoke the key.
+    pub id: String,
+    /// Who or what the key is for. WebSocket clients and webhook callers
+    /// using the key are known by this name.
+    pub name: String,
+    /// The key's first characters, to tell keys apart in listings.
+    pub prefix: String,
+    /// Hex SHA-256 digest of the key.
+    digest: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKeyRecord {
+    /// Whether the key can be used at `now`: not revoked and not expired.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| now < expires_at)
+    }
+
+    /// "active", "revoked", or "expired".
+    pub fn status(&self, now: DateTime<Utc>) -> &'static str {
+        if self.revoked_at.is_some() {
+            "revoked"
+        } else if self.is_active(now) {
+            "active"
+        } else {
+            "expired"
+        }
+    }
+}
+
+/// A newly created key. `key` is never stored and can't be shown again.
+#[derive(Debug, Clone)]
+pub struct IssuedKey {
+    pub record: ApiKeyRecord,
+    pub key: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KeyFile {
+    keys: Vec<ApiKeyRecord>,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+    /// Modification time and length of the file when it was last read.
+    stamp: Option<(SystemTime, u64)>,
+    file: KeyFile,
+}
+
+/// The issued keys in an instance directory.
+#[derive(Debug)]
+pub struct ApiKeyStore {
+    path: PathBuf,
+    cache: Mutex<Cache>,
+}
+
+impl ApiKeyStore {
+    pub fn new(instance_dir: &Path) -> Self {
+        Self {
+            path: instance_dir.join("api_keys.json"),
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    /// Reread the file if it changed since it was last read.
+    fn refresh(&self, cache: &mut Cache) -> anyhow::Result<()> {
+        let stamp = match std::fs::metadata(&self.path) {
+            Ok(metadata) => Some((metadata.modified()?, metadata.len())),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => None,
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("failed to read {}", self.path.display()));
+            }
+        };
+        if stamp == cache.stamp {
+            return Ok(());
+        }
+        cache.file = match stamp {
+            Some(_) => {
+                let content = std::fs::read_to_string(&self.path)
+                    .with_context(|| format!("failed to read {}", self.path.display()))?;
+                serde_json::from_str(&content)
+                    .with_context(|| format!("failed to parse {}", self.path.display()))?
+            }
+            None => KeyFile::default(),
+        };
+        cache.stamp = stamp;
+        Ok(())
+    }
+
+    fn save(&self, cache: &mut Cache) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let serialized = serde_json::to_string_pretty(&cache.file)?;
+        // Write a sibling and rename over the file, so a reader never sees
+        // half of it.
+        let temp_path = self.path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serialized)
+            .with_context(|| format!("failed to write {}", temp_path.display()))?;
+        std::fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("failed to write {}", self.path.display()))?;
+        // Force a reread next time, in case another process wrote since.
+        cache.stamp = None;
+        Ok(())
+    }
+
+    /// Every key ever issued, oldest first.
+    pub fn list(&self) -> anyhow::Result<Vec<ApiKeyRecord>> {
+        let mut cache = self.cache.lock().expect("api key cache poisoned");
+        self.refresh(&mut cache)?;
+        Ok(cache.file.keys.clone())
+    }
+
+    /// Issue a key. An empty `scopes` means every scope.
+    pub fn create(
+        &self,
+        name: &str,
+        scopes: Vec<Scope>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<IssuedKey> {
+        let name = name.trim();
+        anyhow::ensure!(!name.is_empty(), "an API key needs a name");
+        anyhow::ensure!(
+            !name.contains(':'),
+            "API key name '{name}' can't contain ':'"
+        );
+        let scopes = if scopes.is_empty() {
+            Scope::ALL.to_vec()
+        } else {
+            scopes
+        };
+
+        let mut secret = [0u8; 24];
+        rand::rng().fill_bytes(&mut secret);
+        let key = format!("{KEY_PREFIX}{}", hex::encode(secret));
+        let mut id = [0u8; 4];
+        rand::rng().fill_bytes(&mut id);
+
+        let record = ApiKeyRecord {
+            id: hex::encode(id),
+            name: name.to_string(),
+            prefix: key[..KEY_PREFIX.len() + 6].to_string(),
+            digest: digest(&key),
+            scopes,
+            created_at: Utc::now(),
+            expires_at,
+            revoked_at: None,
+        };
+
+        let mut cache = self.cache.lock().expect("api key cache poisoned");
+        self.refresh(&mut cache)?;
+        cache.file.keys.push(record.clone());
+        self.save(&mut cache)?;
+        Ok(IssuedKey { record, key })
+    }
+
+    /// Revoke a key by ID. Returns the key, or None if there's no such key.
+    /// Revoking a revoked key leaves it as it was.
+    pub fn revoke(&self, id: &str) -> anyhow::Result<Option<ApiKeyRecord>> {
+        let mut cache = self.cache.lock().expect("api key cache poisoned");
+        self.refresh(&mut cache)?;
+        let Some(record) = cache.file.keys.iter_mut().find(|record| record.id == id) else {
+            return Ok(None);
+        };
+        if record.revoked_at.is_none() {
+            record.revoked_at = Some(Utc::now());
+        }
+        let record = record.clone();
+        self.save(&mut cache)?;
+        Ok(Some(record))
+    }
+
+    /// The active key matching `key`, if it may be used for `scope`.
+    pub fn verify(&self, key: &str, scope: Scope) -> Option<ApiKeyRecord> {
+        let mut cache = self.cache.lock().expect("api key cache poisoned");
+        if let Err(error) = self.refresh(&mut cache) {
+            tracing::warn!(%error, "failed to load issued API keys");
+        }
+        // Compare digests so the time taken says nothing about the key.
+        let digest = digest(key);
+        let now = Utc::now();
+        cache
+            .file
+            .keys
+            .iter()
+            .find(|record| record.digest == digest)
+            .filter(|record| record.is_active(now) && record.scopes.contains(&scope))
+            .cloned()
+    }
+
+    /// Whether an active key with this name may be used for `scope`.
+    pub fn has_active(&self, name: &str, scope: Scope) -> bool {
+        let now = Utc::now();
+        self.list().unwrap_or_default().iter().any(|record| {
+            record.name == name && record.is_active(now) && record.scopes.contains(&scope)
+        })
+    }
+}
+
+/// One line per key, for `spacebot api-key list` and `!apikey list`.
+pub fn render(keys: &[ApiKeyRecord], now: DateTime<Utc>) -> String {
+    if keys.is_empty() {
+        return "No API keys issued.".into();
+    }
+    keys.iter()
+        .map(|record| {
+            let scopes: Vec<&str> = record.scopes.iter().map(|scope| scope.as_str()).collect();
+            let expires = match record.expires_at {
+                Some(expires_at) => format!("expires {}", expires_at.format("%Y-%m-%d %H:%M UTC")),
+                None => "no expiry".into(),
+            };
+            format!(
+                "{}  {}  {}...  [{}]  {}, {expires}",
+                record.id,
+                record.name,
+                record.prefix,
+                scopes.join(","),
+                record.status(now)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn digest(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn issued_keys_verify_by_scope_until_revoked_or_expired() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = ApiKeyStore::new(dir.path());
+
+        let issued = store
+            .create("ci", vec![Scope::Websocket], None)
+            .expect("create");
+        assert!(issued.key.starts_with(KEY_PREFIX));
+        assert!(issued.key.starts_with(&issued.record.prefix));
+        let stored = std::fs::read_to_string(dir.path().join("api_keys.json")).expect("read");
+        assert!(!stored.contains(&issued.key));
+
+        let verified = store.verify(&issued.key, Scope::Websocket).expect("valid");
+        assert_eq!(verified.name, "ci");
+        assert!(store.verify(&issued.key, Scope::Webhook).is_none());
+        assert!(store.verify("sbk_wrong", Scope::Websocket).is_none());
+
+        // A second store over the same directory (the CLI) sees the change.
+        let cli = ApiKeyStore::new(dir.path());
+        assert!(cli.revoke(&issued.record.id).expect("revoke").is_some());
+        assert!(store.verify(&issued.key, Scope::Websocket).is_none());
+        assert!(cli.revoke("missing").expect("revoke").is_none());
+
+        let expired = store
+            .create(
+                "old",
+                Vec::new(),
+                Some(Utc::now() - chrono::Duration::hours(1)),
+            )
+            .expect("create");
+        assert_eq!(expired.record.scopes, Scope::ALL.to_vec());
+        assert!(store.verify(&expired.key, Scope::Webhook).is_none());
+        assert_eq!(expired.record.status(Utc::now()), "expired");
+
+        assert_eq!(
+            parse_scopes("webhook, proxy,websocket,webhook"),
+            Ok(vec![Scope::Websocket, Scope::Webhook, Scope::Proxy])
+        );
+        assert!(parse_scopes("admin").is_err());
+    }
+
+    #[test]
+    fn expiries_out_of_range_are_refused() {
+        let now = Utc::now();
+        assert_eq!(
+            expiry_after_days(now, 30),
+            Ok(now + chrono::Duration::days(30))
+        );
+        assert!(expiry_after_days(now, u32::MAX).is_err());
+    }
+}
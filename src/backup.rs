@@ -0,0 +1,617 @@
+//! Instance backups: one zip archive holding `config.toml` and every agent's
+//! directory under `agents/`: the SQLite database, LanceDB vectors, redb
+//! settings, archives, and the workspace (minus gitignored files).
+//!
+//! SQLite databases are snapshotted with `VACUUM INTO`, so a backup taken
+//! while the daemon runs still has a consistent database. Worker logs are
+//! left out. An archive can be encrypted with a passphrase: the key comes
+//! from PBKDF2-HMAC-SHA256 and the archive is sealed with AES-256-GCM in
+//! 1 MiB chunks, so large archives never have to fit in memory.
+//!
+//! Backing up and restoring runs through `spacebot backup` / `spacebot
+//! restore` and the control API's `/system/backup` endpoints.
+
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::KeyInit as _;
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::io::{Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use zip::CompressionMethod;
+use zip::write::SimpleFileOptions;
+
+/// Leading bytes of an encrypted archive.
+const ENCRYPTED_MAGIC: &[u8; 8] = b"SBBACKUP";
+const ENCRYPTED_FORMAT: u8 = 1;
+const PBKDF2_ROUNDS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+/// AES-GCM's 12-byte nonce minus the stream's 4-byte counter and last-chunk flag.
+const STREAM_NONCE_LEN: usize = 7;
+const CHUNK_LEN: usize = 1024 * 1024;
+const TAG_LEN: usize = 16;
+const MANIFEST_NAME: &str = "manifest.json";
+/// Archive format version recorded in the manifest.
+const MANIFEST_FORMAT: u32 = 1;
+
+/// Describes an archive. Stored as `manifest.json`; archives from before it
+/// existed have none.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Spacebot version that wrote the archive.
+    pub version: String,
+    pub agents: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct BackupReport {
+    pub manifest: Manifest,
+    pub files: usize,
+    /// Size of the finished archive.
+    pub bytes: u64,
+}
+
+#[derive(Debug)]
+pub struct RestoreReport {
+    pub manifest: Option<Manifest>,
+    pub files_restored: usize,
+}
+
+/// Default archive name, e.g. `spacebot-backup-20260301-120000.zip`.
+pub fn default_file_name(encrypted: bool) -> String {
+    let timestamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+    let extension = if encrypted { "zip.enc" } else { "zip" };
+    format!("spacebot-backup-{timestamp}.{extension}")
+}
+
+/// Write a backup of `instance_dir` to `output`, encrypted when a passphrase
+/// is given.
+pub async fn create(
+    instance_dir: &Path,
+    output: &Path,
+    passphrase: Option<&str>,
+) -> anyhow::Result<BackupReport> {
+    let staging = tempfile::tempdir().context("failed to create staging directory")?;
+    let snapshots = snapshot_databases(&instance_dir.join("agents"), staging.path()).await?;
+
+    let instance_dir = instance_dir.to_path_buf();
+    let output = output.to_path_buf();
+    let passphrase = passphrase.map(str::to_string);
+    tokio::task::spawn_blocking(move || {
+        let manifest = Manifest {
+            format: MANIFEST_FORMAT,
+            created_at: chrono::Utc::now(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            agents: list_agents(&instance_dir.join("agents"))?,
+        };
+
+        let zip_path = match passphrase {
+            Some(_) => staging.path().join("backup.zip"),
+            None => output.clone(),
+        };
+        let file = std::fs::File::create(&zip_path)
+            .with_context(|| format!("failed to create {}", zip_path.display()))?;
+        let files = write_archive(&instance_dir, &manifest, &snapshots, file)?;
+
+        if let Some(passphrase) = &passphrase {
+            encrypt_file(&zip_path, &output, passphrase, PBKDF2_ROUNDS)?;
+        }
+
+        Ok(BackupReport {
+            manifest,
+            files,
+            bytes: std::fs::metadata(&output)?.len(),
+        })
+    })
+    .await
+    .context("backup task panicked")?
+}
+
+/// Restore `config.toml` and `agents/` from an archive into `instance_dir`,
+/// replacing what's there. The daemon must not be running.
+pub fn restore(
+    instance_dir: &Path,
+    archive: &Path,
+    passphrase: Option<&str>,
+) -> anyhow::Result<RestoreReport> {
+    std::fs::create_dir_all(instance_dir)?;
+    let restore_root = instance_dir.join(format!(".restore-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&restore_root)?;
+
+    let result = extract_and_replace(instance_dir, &restore_root, archive, passphrase);
+    let _ = std::fs::remove_dir_all(&restore_root);
+    result
+}
+
+/// Whether the file at `path` is an encrypted archive.
+pub fn is_encrypted(path: &Path) -> anyhow::Result<bool> {
+    let mut magic = [0u8; ENCRYPTED_MAGIC.len()];
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    Ok(read_full(&mut file, &mut magic)? == magic.len() && &magic == ENCRYPTED_MAGIC)
+}
+
+fn extract_and_replace(
+    instance_dir: &Path,
+    restore_root: &Path,
+    archive: &Path,
+    passphrase: Option<&str>,
+) -> anyhow::Result<RestoreReport> {
+    let zip_path = if is_encrypted(archive)? {
+        let Some(passphrase) = passphrase else {
+            anyhow::bail!("backup is encrypted: a passphrase is required");
+        };
+        let decrypted = restore_root.join("backup.zip");
+        decrypt_file(archive, &decrypted, passphrase)?;
+        decrypted
+    } else {
+        archive.to_path_buf()
+    };
+
+    let file = std::fs::File::open(&zip_path)
+        .with_context(|| format!("failed to open {}", zip_path.display()))?;
+    let mut zip = zip::ZipArchive::new(file).context("not a backup archive")?;
+    let staged = restore_root.join("files");
+    let mut manifest = None;
+    let mut files_restored = 0usize;
+
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index)?;
+        let Some(enclosed_name) = file.enclosed_name() else {
+            continue;
+        };
+
+        if enclosed_name == Path::new(MANIFEST_NAME) {
+            manifest = serde_json::from_reader(&mut file).ok();
+            continue;
+        }
+        let allowed = enclosed_name == Path::new("config.toml")
+            || enclosed_name.starts_with(Path::new("agents"));
+        if !allowed {
+            continue;
+        }
+
+        let target = staged.join(&enclosed_name);
+        if file.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            continue;
+        }
+
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut output = std::fs::File::create(&target)?;
+        std::io::copy(&mut file, &mut output)?;
+        files_restored += 1;
+    }
+
+    let restored_config = staged.join("config.toml");
+    if restored_config.exists() {
+        replace_path_atomic(&restored_config, &instance_dir.join("config.toml"))?;
+    }
+
+    let restored_agents = staged.join("agents");
+    if restored_agents.exists() {
+        replace_directory(&restored_agents, &instance_dir.join("agents"))?;
+    }
+
+    Ok(RestoreReport {
+        manifest,
+        files_restored,
+    })
+}
+
+/// Copy every agent's `spacebot.db` into `staging` with `VACUUM INTO`.
+/// Returns snapshot paths keyed by the original database path.
+async fn snapshot_databases(
+    agents_dir: &Path,
+    staging: &Path,
+) -> anyhow::Result<HashMap<PathBuf, PathBuf>> {
+    let mut snapshots = HashMap::new();
+    for agent_id in list_agents(agents_dir)? {
+        let database = agents_dir.join(&agent_id).join("data").join("spacebot.db");
+        if !database.is_file() {
+            continue;
+        }
+
+        let snapshot = staging.join(format!("{agent_id}.db"));
+        let options = sqlx::sqlite::SqliteConnectOptions::new().filename(&database);
+        let pool = sqlx::SqlitePool::connect_with(options)
+            .await
+            .with_context(|| format!("failed to open {}", database.display()))?;
+        let result = sqlx::query("VACUUM INTO ?")
+            .bind(snapshot.to_string_lossy().to_string())
+            .execute(&pool)
+            .await;
+        pool.close().await;
+        result.with_context(|| format!("failed to snapshot {}", database.display()))?;
+
+        snapshots.insert(database, snapshot);
+    }
+    Ok(snapshots)
+}
+
+fn list_agents(agents_dir: &Path) -> anyhow::Result<Vec<String>> {
+    if !agents_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut agents = Vec::new();
+    for entry in std::fs::read_dir(agents_dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            agents.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    agents.sort();
+    Ok(agents)
+}
+
+fn write_archive<W: Write + Seek>(
+    instance_dir: &Path,
+    manifest: &Manifest,
+    snapshots: &HashMap<PathBuf, PathBuf>,
+    output: W,
+) -> anyhow::Result<usize> {
+    let mut writer = ArchiveWriter {
+        zip: zip::ZipWriter::new(output),
+        options: SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .unix_permissions(0o644)
+            .large_file(true),
+        snapshots,
+        files: 0,
+    };
+
+    writer.zip.start_file(MANIFEST_NAME, writer.options)?;
+    serde_json::to_writer_pretty(&mut writer.zip, manifest)?;
+
+    let config_path = instance_dir.join("config.toml");
+    if config_path.is_file() {
+        writer.add_file(&config_path, "config.toml")?;
+    }
+    let agents_dir = instance_dir.join("agents");
+    if agents_dir.is_dir() {
+        writer.add_directory(&agents_dir, "agents")?;
+    }
+
+    writer.zip.finish()?;
+    Ok(writer.files)
+}
+
+struct ArchiveWriter<'a, W: Write + Seek> {
+    zip: zip::ZipWriter<W>,
+    options: SimpleFileOptions,
+    snapshots: &'a HashMap<PathBuf, PathBuf>,
+    files: usize,
+}
+
+impl<W: Write + Seek> ArchiveWriter<'_, W> {
+    fn add_directory(&mut self, directory_path: &Path, archive_prefix: &str) -> anyhow::Result<()> {
+        for entry in std::fs::read_dir(directory_path)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_name = entry.file_name().to_string_lossy().to_string();
+
+            if path.is_dir() && file_name == "logs" {
+                continue;
+            }
+            // The snapshot stands in for the live database and its journals.
+            if ["-wal", "-shm", "-journal"]
+                .iter()
+                .any(|suffix| file_name == format!("spacebot.db{suffix}"))
+            {
+                continue;
+            }
+
+            let name = format!("{archive_prefix}/{file_name}");
+
+            if path.is_dir() && file_name == "workspace" {
+                self.add_workspace_directory(&path, &name)?;
+            } else if path.is_dir() {
+                self.add_directory(&path, &name)?;
+            } else if path.is_file() {
+                let source = self.snapshots.get(&path).cloned().unwrap_or(path);
+                self.add_file(&source, &name)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn add_workspace_directory(
+        &mut self,
+        workspace_path: &Path,
+        archive_prefix: &str,
+    ) -> anyhow::Result<()> {
+        let walk = ignore::WalkBuilder::new(workspace_path)
+            .hidden(false)
+            .git_ignore(true)
+            .git_global(true)
+            .git_exclude(true)
+            .parents(true)
+            .build();
+
+        for result in walk {
+            let entry = match result {
+                Ok(entry) => entry,
+                Err(_) => continue,
+            };
+
+            let path = entry.path();
+            if path == workspace_path {
+                continue;
+            }
+
+            if path
+                .components()
+                .any(|component| matches!(component, Component::Normal(name) if name == ".git"))
+            {
+                continue;
+            }
+
+            let metadata = match std::fs::symlink_metadata(path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let Ok(relative) = path.strip_prefix(workspace_path) else {
+                continue;
+            };
+
+            let relative_name = relative
+                .to_string_lossy()
+                .replace(std::path::MAIN_SEPARATOR, "/");
+            let archive_name = format!("{archive_prefix}/{relative_name}");
+            self.add_file(path, &archive_name)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_file(&mut self, file_path: &Path, archive_name: &str) -> anyhow::Result<()> {
+        let mut file = std::fs::File::open(file_path)
+            .with_context(|| format!("failed to read {}", file_path.display()))?;
+        self.zip.start_file(archive_name, self.options)?;
+        std::io::copy(&mut file, &mut self.zip)?;
+        self.files += 1;
+        Ok(())
+    }
+}
+
+fn replace_path_atomic(source: &Path, destination: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let temp_destination = destination.with_extension("restore_tmp");
+    std::fs::copy(source, &temp_destination)?;
+    std::fs::rename(temp_destination, destination)?;
+    Ok(())
+}
+
+fn replace_directory(source: &Path, destination: &Path) -> anyhow::Result<()> {
+    let temp_destination = destination.with_extension("restore_tmp_dir");
+    if temp_destination.exists() {
+        std::fs::remove_dir_all(&temp_destination)?;
+    }
+
+    std::fs::rename(source, &temp_destination)?;
+
+    if destination.exists() {
+        std::fs::remove_dir_all(destination)?;
+    }
+
+    std::fs::rename(&temp_destination, destination)?;
+    Ok(())
+}
+
+/// Encrypted layout: magic, format byte, salt, PBKDF2 rounds (big-endian
+/// u32), stream nonce, then the AES-256-GCM STREAM chunks.
+fn encrypt_file(input: &Path, output: &Path, passphrase: &str, rounds: u32) -> anyhow::Result<()> {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let nonce: [u8; STREAM_NONCE_LEN] = rand::random();
+    let mut encryptor = EncryptorBE32::from_aead(
+        derive_cipher(passphrase, &salt, rounds),
+        nonce.as_slice().into(),
+    );
+
+    let mut reader = std::fs::File::open(input)?;
+    let mut writer = std::io::BufWriter::new(
+        std::fs::File::create(output)
+            .with_context(|| format!("failed to create {}", output.display()))?,
+    );
+    writer.write_all(ENCRYPTED_MAGIC)?;
+    writer.write_all(&[ENCRYPTED_FORMAT])?;
+    writer.write_all(&salt)?;
+    writer.write_all(&rounds.to_be_bytes())?;
+    writer.write_all(&nonce)?;
+
+    let seal_error = |_| anyhow::anyhow!("failed to encrypt backup");
+    let mut buffer = vec![0u8; CHUNK_LEN];
+    loop {
+        let read = read_full(&mut reader, &mut buffer)?;
+        if read < CHUNK_LEN {
+            writer.write_all(
+                &encryptor
+                    .encrypt_last(&buffer[..read])
+                    .map_err(seal_error)?,
+            )?;
+            break;
+        }
+        writer.write_all(
+            &encryptor
+                .encrypt_next(buffer.as_slice())
+                .map_err(seal_error)?,
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn decrypt_file(input: &Path, output: &Path, passphrase: &str) -> anyhow::Result<()> {
+    let mut reader = std::io::BufReader::new(std::fs::File::open(input)?);
+    let mut header = [0u8; ENCRYPTED_MAGIC.len() + 1 + SALT_LEN + 4 + STREAM_NONCE_LEN];
+    if read_full(&mut reader, &mut header)? < header.len()
+        || &header[..ENCRYPTED_MAGIC.len()] != ENCRYPTED_MAGIC.as_slice()
+        || header[ENCRYPTED_MAGIC.len()] != ENCRYPTED_FORMAT
+    {
+        anyhow::bail!("unsupported encrypted backup format");
+    }
+    let (salt, rest) = header[ENCRYPTED_MAGIC.len() + 1..].split_at(SALT_LEN);
+    let (rounds, nonce) = rest.split_at(4);
+    let rounds = u32::from_be_bytes(rounds.try_into().expect("four bytes"));
+    let mut decryptor =
+        DecryptorBE32::from_aead(derive_cipher(passphrase, salt, rounds), nonce.into());
+
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(output)?);
+    let open_error =
+        |_| anyhow::anyhow!("can't decrypt backup: wrong passphrase or damaged archive");
+    let mut buffer = vec![0u8; CHUNK_LEN + TAG_LEN];
+    loop {
+        let read = read_full(&mut reader, &mut buffer)?;
+        if read < buffer.len() {
+            writer.write_all(
+                &decryptor
+                    .decrypt_last(&buffer[..read])
+                    .map_err(open_error)?,
+            )?;
+            break;
+        }
+        writer.write_all(
+            &decryptor
+                .decrypt_next(buffer.as_slice())
+                .map_err(open_error)?,
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8], rounds: u32) -> Aes256Gcm {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, rounds, &mut key);
+    Aes256Gcm::new(&key.into())
+}
+
+/// Read until `buffer` is full or the reader is exhausted.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encryption_round_trips_across_chunks() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let plain = dir.path().join("plain");
+        let sealed = dir.path().join("sealed");
+        let opened = dir.path().join("opened");
+        let data: Vec<u8> = (0..CHUNK_LEN * 2 + 17).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&plain, &data).expect("write plaintext");
+
+        encrypt_file(&plain, &sealed, "hunter2", 1000).expect("encrypt");
+        assert!(is_encrypted(&sealed).expect("readable"));
+        assert!(!is_encrypted(&plain).expect("readable"));
+
+        decrypt_file(&sealed, &opened, "hunter2").expect("decrypt");
+        assert_eq!(std::fs::read(&opened).expect("read decrypted"), data);
+
+        assert!(decrypt_file(&sealed, &opened, "wrong").is_err());
+
+        // Dropping the final chunk must not decrypt to a shorter archive.
+        let sealed_bytes = std::fs::read(&sealed).expect("read ciphertext");
+        let header_len = ENCRYPTED_MAGIC.len() + 1 + SALT_LEN + 4 + STREAM_NONCE_LEN;
+        std::fs::write(
+            &sealed,
+            &sealed_bytes[..header_len + 2 * (CHUNK_LEN + TAG_LEN)],
+        )
+        .expect("truncate");
+        assert!(decrypt_file(&sealed, &opened, "hunter2").is_err());
+    }
+
+    #[tokio::test]
+    async fn backup_restores_config_and_agent_state() {
+        let source = tempfile::tempdir().expect("temp dir");
+        let data_dir = source.path().join("agents/main/data");
+        std::fs::create_dir_all(data_dir.join("logs")).expect("create dirs");
+        std::fs::create_dir_all(source.path().join("agents/main/workspace")).expect("create dirs");
+        std::fs::write(source.path().join("config.toml"), "[api]\n").expect("write config");
+        std::fs::write(source.path().join("agents/main/workspace/SOUL.md"), "calm")
+            .expect("write soul");
+        std::fs::write(data_dir.join("logs/worker.log"), "noise").expect("write log");
+
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(data_dir.join("spacebot.db"))
+            .create_if_missing(true)
+            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal);
+        let pool = sqlx::SqlitePool::connect_with(options)
+            .await
+            .expect("open db");
+        sqlx::query("CREATE TABLE memories (content TEXT)")
+            .execute(&pool)
+            .await
+            .expect("create table");
+        sqlx::query("INSERT INTO memories VALUES ('remember me')")
+            .execute(&pool)
+            .await
+            .expect("insert");
+
+        // Taken while the pool is open, so the row may still be in the WAL.
+        let archive = source.path().join("backup.zip.enc");
+        let report = create(source.path(), &archive, Some("secret"))
+            .await
+            .expect("backup");
+        pool.close().await;
+        assert_eq!(report.manifest.agents, vec!["main".to_string()]);
+
+        let target = tempfile::tempdir().expect("temp dir");
+        assert!(restore(target.path(), &archive, None).is_err());
+        let restored = restore(target.path(), &archive, Some("secret")).expect("restore");
+        assert_eq!(restored.manifest, Some(report.manifest));
+
+        let restored_data = target.path().join("agents/main/data");
+        assert_eq!(
+            std::fs::read_to_string(target.path().join("config.toml")).expect("config"),
+            "[api]\n"
+        );
+        assert!(
+            target
+                .path()
+                .join("agents/main/workspace/SOUL.md")
+                .is_file()
+        );
+        assert!(!restored_data.join("logs").exists());
+        assert!(!restored_data.join("spacebot.db-wal").exists());
+
+        let pool = sqlx::SqlitePool::connect_with(
+            sqlx::sqlite::SqliteConnectOptions::new().filename(restored_data.join("spacebot.db")),
+        )
+        .await
+        .expect("open restored db");
+        let content: String = sqlx::query_scalar("SELECT content FROM memories")
+            .fetch_one(&pool)
+            .await
+            .expect("restored row");
+        assert_eq!(content, "remember me");
+    }
+}
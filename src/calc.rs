@@ -0,0 +1,955 @@
+//! Deterministic calculator: exact rational arithmetic, unit conversions,
+//! and date math, for the `calculate` tool and for checking arithmetic in
+//! incoming channel messages before the LLM sees them.
+
+mod parse;
+mod units;
+
+use parse::{BinaryOp, Expr, Token};
+use units::{DIMENSIONLESS, Dims, UnitExpr};
+
+use chrono::{Months, NaiveDate, TimeDelta};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+use serde::Serialize;
+use std::ops::Range;
+
+/// Significant digits in rounded results unless the caller asks otherwise.
+pub const DEFAULT_PRECISION: usize = 15;
+
+/// Most significant digits a rounded result can have.
+pub const MAX_PRECISION: usize = 50;
+
+/// Longest expression accepted, in characters.
+const MAX_EXPRESSION_CHARS: usize = 1000;
+
+/// Largest power computed exactly, in bits of the result. Bigger ones fall
+/// back to floating point.
+const MAX_EXACT_BITS: u64 = 100_000;
+
+/// Digits kept for square roots that aren't exact.
+const SQRT_DIGITS: u32 = 60;
+
+/// Most digits in an exact result before it's shown rounded.
+const MAX_EXACT_DIGITS: usize = 1000;
+
+/// Most calculations picked out of one message.
+const MAX_CALCULATIONS: usize = 5;
+
+/// Longest run of tokens scanned for an expression in a message.
+const MAX_SCAN_TOKENS: usize = 40;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CalcError {
+    #[error("can't parse: {0}")]
+    Parse(String),
+    #[error("{0}")]
+    Eval(String),
+}
+
+/// A computed result.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Answer {
+    /// The result as text, e.g. "12.5 km" or "2026-04-15 (Wednesday)".
+    pub text: String,
+    /// Whether `text` is rounded.
+    pub approximate: bool,
+    /// The exact value as a fraction, when it's rational but has no finite
+    /// decimal form.
+    pub fraction: Option<String>,
+}
+
+/// Evaluate `expression`, with `today` for the `today` keyword and rounded
+/// results to `precision` significant digits.
+pub fn evaluate(expression: &str, today: NaiveDate, precision: usize) -> Result<Answer, CalcError> {
+    if expression.chars().count() > MAX_EXPRESSION_CHARS {
+        return Err(CalcError::Parse(format!(
+            "expressions can be at most {MAX_EXPRESSION_CHARS} characters"
+        )));
+    }
+    let tokens: Vec<Token> = parse::tokenize(expression)
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect();
+    if tokens.is_empty() {
+        return Err(CalcError::Parse("the expression is empty".into()));
+    }
+    let expr = parse::parse(&tokens)?;
+    let value = Evaluator { today }.eval(&expr)?;
+    Ok(value.render(precision.clamp(1, MAX_PRECISION)))
+}
+
+/// Arithmetic found in a message, with its result.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Calculation {
+    /// The expression as written in the message.
+    pub expression: String,
+    pub answer: Answer,
+}
+
+/// Pick out arithmetic, unit conversions, and date math from free text and
+/// compute them.
+///
+/// Only spans that compute something count, so "5 km" alone doesn't, and
+/// numbers joined without spaces by `-` or `/` are left alone since they're
+/// usually ranges, phone numbers, or dates ("3-5", "555-1234", "24/7").
+pub fn find_calculations(text: &str, today: NaiveDate) -> Vec<Calculation> {
+    let tokens = parse::tokenize(text);
+    let evaluator = Evaluator { today };
+    let mut calculations = Vec::new();
+
+    let mut index = 0;
+    while index < tokens.len() && calculations.len() < MAX_CALCULATIONS {
+        if !is_scannable(&tokens[index].0) {
+            index += 1;
+            continue;
+        }
+        let run_end = tokens[index..]
+            .iter()
+            .position(|(token, _)| !is_scannable(token))
+            .map_or(tokens.len(), |offset| index + offset)
+            .min(index + MAX_SCAN_TOKENS);
+        let run = &tokens[index..run_end];
+        index = run_end;
+
+        // Take the longest span that computes, then keep looking after it.
+        let mut start = 0;
+        while start < run.len() && calculations.len() < MAX_CALCULATIONS {
+            if start > 0 && glued(&run[start - 1], &run[start]) {
+                start += 1;
+                continue;
+            }
+            let found = (start + 1..=run.len()).rev().find_map(|end| {
+                if end < run.len() && glued(&run[end - 1], &run[end]) {
+                    return None;
+                }
+                let span = &run[start..end];
+                let answer = scan_span(span, &evaluator)?;
+                Some((end, answer))
+            });
+            match found {
+                Some((end, answer)) => {
+                    let range = run[start].1.start..run[end - 1].1.end;
+                    calculations.push(Calculation {
+                        expression: text[range].to_string(),
+                        answer,
+                    });
+                    start = end;
+                }
+                None => start += 1,
+            }
+        }
+    }
+    calculations
+}
+
+/// Whether `token` can be part of an expression in free text.
+fn is_scannable(token: &Token) -> bool {
+    match token {
+        Token::Ident(name) => {
+            matches!(
+                name.as_str(),
+                "to" | "in" | "as" | "of" | "mod" | "today" | "pi"
+            ) || parse::FUNCTIONS.contains(&name.to_lowercase().as_str())
+                || units::lookup(name).is_some()
+        }
+        Token::Other => false,
+        _ => true,
+    }
+}
+
+/// Whether two neighboring tokens are written together as in "3-5" or
+/// "24/7", so a span can't begin or end between them.
+fn glued(left: &(Token, Range<usize>), right: &(Token, Range<usize>)) -> bool {
+    left.1.end == right.1.start
+        && matches!(
+            (&left.0, &right.0),
+            (Token::Number(_), Token::Op('-' | '/')) | (Token::Op('-' | '/'), Token::Number(_))
+        )
+}
+
+fn scan_span(span: &[(Token, Range<usize>)], evaluator: &Evaluator) -> Option<Answer> {
+    let starts_value = matches!(
+        span.first()?.0,
+        Token::Number(_) | Token::Date(_) | Token::Open | Token::Op('-') | Token::Ident(_)
+    );
+    let ends_value = matches!(
+        span.last()?.0,
+        Token::Number(_) | Token::Date(_) | Token::Close | Token::Percent | Token::Ident(_)
+    );
+    if !starts_value || !ends_value {
+        return None;
+    }
+    let tight = span.windows(3).any(|window| {
+        matches!(
+            window,
+            [
+                (Token::Number(_), left),
+                (Token::Op('-' | '/'), op),
+                (Token::Number(_), right)
+            ] if left.end == op.start && op.end == right.start
+        )
+    });
+    if tight {
+        return None;
+    }
+
+    let tokens: Vec<Token> = span.iter().map(|(token, _)| token.clone()).collect();
+    let expr = parse::parse(&tokens).ok()?;
+    if !expr.computes() {
+        return None;
+    }
+    let value = evaluator.eval(&expr).ok()?;
+    Some(value.render(DEFAULT_PRECISION))
+}
+
+/// Parse a decimal like "0.25", "1e-3", or ".5", or a fraction like "5/9".
+fn parse_decimal(text: &str) -> Option<BigRational> {
+    if let Some((numerator, denominator)) = text.split_once('/') {
+        let numerator = parse_decimal(numerator)?;
+        let denominator = parse_decimal(denominator)?;
+        return (!denominator.is_zero()).then(|| numerator / denominator);
+    }
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (
+            mantissa,
+            exponent
+                .parse::<i32>()
+                .ok()
+                .filter(|exponent| exponent.abs() <= 1000)?,
+        ),
+        None => (text, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return None;
+    }
+    let digits: BigInt = format!("{whole}{fraction}").parse().ok()?;
+    let scale = exponent - i32::try_from(fraction.len()).ok()?;
+    Some(BigRational::from_integer(digits) * units::pow(&ten(), scale))
+}
+
+fn ten() -> BigRational {
+    BigRational::from_integer(BigInt::from(10))
+}
+
+fn pow10(exponent: u32) -> BigInt {
+    num_traits::pow(BigInt::from(10), exponent as usize)
+}
+
+#[derive(Clone)]
+struct Quantity {
+    /// The amount in base units.
+    value: BigRational,
+    exact: bool,
+    dims: Dims,
+    /// The units it was written in, used to show the result.
+    unit: UnitExpr,
+    /// Set for °C and °F readings. They only convert, since adding a reading
+    /// and a difference would need to know which is which.
+    absolute: bool,
+}
+
+impl Quantity {
+    fn number(value: BigRational, exact: bool) -> Self {
+        Self {
+            value,
+            exact,
+            dims: DIMENSIONLESS,
+            unit: UnitExpr::default(),
+            absolute: false,
+        }
+    }
+
+    fn with_value(&self, value: BigRational, exact: bool) -> Self {
+        Self {
+            value,
+            exact,
+            ..self.clone()
+        }
+    }
+
+    fn is_dimensionless(&self) -> bool {
+        self.dims == DIMENSIONLESS
+    }
+
+    /// How the quantity's units are written, for error messages.
+    fn describe_unit(&self) -> String {
+        if self.is_dimensionless() {
+            "a plain number".into()
+        } else if !self.unit.is_empty() {
+            self.unit.render(false)
+        } else {
+            units::render_dims(&self.dims)
+        }
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.value.to_f64().unwrap_or(f64::NAN)
+    }
+}
+
+enum Value {
+    Quantity(Quantity),
+    Date(NaiveDate),
+}
+
+impl Value {
+    fn render(&self, precision: usize) -> Answer {
+        let quantity = match self {
+            Value::Date(date) => {
+                return Answer {
+                    text: date.format("%Y-%m-%d (%A)").to_string(),
+                    approximate: false,
+                    fraction: None,
+                };
+            }
+            Value::Quantity(quantity) => quantity,
+        };
+
+        let (amount, unit) = if quantity.is_dimensionless() {
+            (quantity.value.clone(), None)
+        } else if !quantity.unit.is_empty() && quantity.unit.dims() == quantity.dims {
+            let offset = match quantity.unit.as_single() {
+                Some(unit) if quantity.absolute => unit.offset(),
+                _ => None,
+            };
+            let amount = (&quantity.value - offset.unwrap_or_else(BigRational::zero))
+                / quantity.unit.factor();
+            let unit = quantity.unit.render(amount.abs().is_one());
+            (amount, Some(unit))
+        } else {
+            (
+                quantity.value.clone(),
+                Some(units::render_dims(&quantity.dims)),
+            )
+        };
+
+        let (number, approximate, fraction) = format_amount(&amount, quantity.exact, precision);
+        Answer {
+            text: match unit {
+                Some(unit) => format!("{number} {unit}"),
+                None => number,
+            },
+            approximate,
+            fraction,
+        }
+    }
+}
+
+/// Format a number, exactly when possible. Returns the text, whether it's
+/// rounded, and the exact fraction when the decimal form doesn't end.
+fn format_amount(
+    value: &BigRational,
+    exact: bool,
+    precision: usize,
+) -> (String, bool, Option<String>) {
+    if exact {
+        if value.is_integer() {
+            let text = value.to_integer().to_string();
+            if text.len() <= MAX_EXACT_DIGITS {
+                return (text, false, None);
+            }
+        } else if let Some(text) = terminating_decimal(value) {
+            return (text, false, None);
+        }
+    }
+    let fraction = (exact && !value.is_integer())
+        .then(|| format!("{}/{}", value.numer(), value.denom()))
+        .filter(|fraction| fraction.len() <= 80);
+    (significant_digits(value, precision), true, fraction)
+}
+
+/// `value` as an exact decimal, if it has one of reasonable length.
+fn terminating_decimal(value: &BigRational) -> Option<String> {
+    let mut denominator = value.denom().clone();
+    let (mut twos, mut fives) = (0u32, 0u32);
+    let (two, five) = (BigInt::from(2), BigInt::from(5));
+    while (&denominator % &two).is_zero() {
+        denominator /= &two;
+        twos += 1;
+    }
+    while (&denominator % &five).is_zero() {
+        denominator /= &five;
+        fives += 1;
+    }
+    let places = twos.max(fives);
+    if !denominator.is_one() || places > 100 {
+        return None;
+    }
+    let scaled = (value.numer() * pow10(places) / value.denom()).abs();
+    let digits = format!(
+        "{:0>width$}",
+        scaled.to_string(),
+        width = places as usize + 1
+    );
+    let (whole, fraction) = digits.split_at(digits.len() - places as usize);
+    let sign = if value.is_negative() { "-" } else { "" };
+    Some(format!("{sign}{whole}.{}", fraction.trim_end_matches('0')))
+}
+
+/// `value` rounded to `precision` significant digits.
+fn significant_digits(value: &BigRational, precision: usize) -> String {
+    if value.is_zero() {
+        return "0".into();
+    }
+    let magnitude = value.abs();
+    // The power of ten at or just below the value.
+    let numer_digits = magnitude.numer().to_string().len() as i32;
+    let denom_digits = magnitude.denom().to_string().len() as i32;
+    let mut exponent = numer_digits - denom_digits;
+    if magnitude < units::pow(&ten(), exponent) {
+        exponent -= 1;
+    }
+
+    let precision = precision as i32;
+    let mut digits = (magnitude * units::pow(&ten(), precision - 1 - exponent))
+        .round()
+        .to_integer()
+        .to_string();
+    if digits.len() as i32 > precision {
+        // Rounding carried into a new digit, as in 9.99 -> 10.0.
+        digits.truncate(precision as usize);
+        exponent += 1;
+    }
+
+    let sign = if value.is_negative() { "-" } else { "" };
+    let trim = |text: String| {
+        if text.contains('.') {
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        } else {
+            text
+        }
+    };
+    if !(-7..21).contains(&exponent) {
+        let (first, rest) = digits.split_at(1);
+        return format!("{sign}{}e{exponent}", trim(format!("{first}.{rest}")));
+    }
+    let text = if exponent >= precision - 1 {
+        format!(
+            "{digits}{}",
+            "0".repeat((exponent - precision + 1) as usize)
+        )
+    } else if exponent >= 0 {
+        let (whole, fraction) = digits.split_at(exponent as usize + 1);
+        format!("{whole}.{fraction}")
+    } else {
+        format!("0.{}{digits}", "0".repeat((-exponent - 1) as usize))
+    };
+    format!("{sign}{}", trim(text))
+}
+
+struct Evaluator {
+    today: NaiveDate,
+}
+
+fn error<T>(message: impl Into<String>) -> Result<T, CalcError> {
+    Err(CalcError::Eval(message.into()))
+}
+
+impl Evaluator {
+    fn eval(&self, expr: &Expr) -> Result<Value, CalcError> {
+        match expr {
+            Expr::Number(value) => Ok(Value::Quantity(Quantity::number(value.clone(), true))),
+            Expr::Constant(name) => {
+                let (_, digits) = parse::CONSTANTS
+                    .iter()
+                    .find(|(constant, _)| constant == name)
+                    .expect("constants are listed");
+                let value = parse_decimal(digits).expect("constants are valid");
+                Ok(Value::Quantity(Quantity::number(value, false)))
+            }
+            Expr::Date(date) => Ok(Value::Date(*date)),
+            Expr::Today => Ok(Value::Date(self.today)),
+            Expr::WithUnit(inner, unit) => {
+                let amount = self.quantity(inner)?;
+                Ok(Value::Quantity(apply_unit(amount, unit)?))
+            }
+            Expr::Percent(inner) => {
+                let amount = self.number(inner, "a percentage")?;
+                let value = &amount.value / BigRational::from_integer(BigInt::from(100));
+                Ok(Value::Quantity(amount.with_value(value, amount.exact)))
+            }
+            Expr::Negate(inner) => {
+                // "-5 °C" is a reading of minus five, not minus 278.15 K.
+                if let Expr::WithUnit(amount, unit) = &**inner {
+                    let amount = self.quantity(amount)?;
+                    let negated = amount.with_value(-&amount.value, amount.exact);
+                    return Ok(Value::Quantity(apply_unit(negated, unit)?));
+                }
+                let amount = self.quantity(inner)?;
+                if amount.absolute {
+                    return error("°C and °F readings can only be converted; use K for arithmetic");
+                }
+                Ok(Value::Quantity(
+                    amount.with_value(-&amount.value, amount.exact),
+                ))
+            }
+            Expr::Binary(op, left, right) => self.binary(*op, left, right),
+            Expr::Call(name, arguments) => self.call(name, arguments),
+            Expr::Convert(inner, target) => {
+                let mut amount = self.quantity(inner)?;
+                if amount.dims != target.dims() {
+                    return error(format!(
+                        "can't convert {} to {}",
+                        amount.describe_unit(),
+                        target.render(false)
+                    ));
+                }
+                // Converting to °C or °F reads the amount as a temperature.
+                if target.as_single().is_some_and(|unit| unit.offset.is_some()) {
+                    amount.absolute = true;
+                }
+                amount.unit = target.clone();
+                Ok(Value::Quantity(amount))
+            }
+        }
+    }
+
+    fn quantity(&self, expr: &Expr) -> Result<Quantity, CalcError> {
+        match self.eval(expr)? {
+            Value::Quantity(quantity) => Ok(quantity),
+            Value::Date(_) => error("a date can't be used here"),
+        }
+    }
+
+    /// Evaluate `expr` as a plain number, naming it `what` in errors.
+    fn number(&self, expr: &Expr, what: &str) -> Result<Quantity, CalcError> {
+        let amount = self.quantity(expr)?;
+        if !amount.is_dimensionless() {
+            return error(format!(
+                "{what} must be a plain number, not {}",
+                amount.describe_unit()
+            ));
+        }
+        Ok(amount)
+    }
+
+    fn binary(&self, op: BinaryOp, left: &Expr, right: &Expr) -> Result<Value, CalcError> {
+        let (left, right) = (self.eval(left)?, self.eval(right)?);
+        let (left, right) = match (op, left, right) {
+            (BinaryOp::Add, Value::Date(date), Value::Quantity(shift))
+            | (BinaryOp::Add, Value::Quantity(shift), Value::Date(date)) => {
+                return shift_date(date, &shift, false).map(Value::Date);
+            }
+            (BinaryOp::Subtract, Value::Date(date), Value::Quantity(shift)) => {
+                return shift_date(date, &shift, true).map(Value::Date);
+            }
+            (BinaryOp::Subtract, Value::Date(later), Value::Date(earlier)) => {
+                let days = (later - earlier).num_days();
+                let day = units::lookup("day").expect("days are a unit");
+                return Ok(Value::Quantity(Quantity {
+                    value: BigRational::from_integer(BigInt::from(days) * BigInt::from(86_400)),
+                    exact: true,
+                    dims: day.dims,
+                    unit: UnitExpr::single(day),
+                    absolute: false,
+                }));
+            }
+            (_, Value::Quantity(left), Value::Quantity(right)) => (left, right),
+            _ => return error("dates can only have time added or subtracted, or be subtracted"),
+        };
+        if left.absolute || right.absolute {
+            return error("°C and °F readings can only be converted; use K for arithmetic");
+        }
+        let exact = left.exact && right.exact;
+
+        let result = match op {
+            BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Modulo => {
+                if left.dims != right.dims {
+                    return error(format!(
+                        "can't combine {} with {}",
+                        left.describe_unit(),
+                        right.describe_unit()
+                    ));
+                }
+                let value = match op {
+                    BinaryOp::Add => &left.value + &right.value,
+                    BinaryOp::Subtract => &left.value - &right.value,
+                    _ => {
+                        if right.value.is_zero() {
+                            return error("division by zero");
+                        }
+                        &left.value - &right.value * (&left.value / &right.value).floor()
+                    }
+                };
+                let unit = if left.unit.is_empty() {
+                    right.unit.clone()
+                } else {
+                    left.unit.clone()
+                };
+                Quantity {
+                    unit,
+                    ..left.with_value(value, exact)
+                }
+            }
+            BinaryOp::Multiply | BinaryOp::Divide => {
+                let sign = if op == BinaryOp::Multiply { 1 } else { -1 };
+                if sign < 0 && right.value.is_zero() {
+                    return error("division by zero");
+                }
+                let value = if sign > 0 {
+                    &left.value * &right.value
+                } else {
+                    &left.value / &right.value
+                };
+                let mut dims = left.dims;
+                for (total, dim) in dims.iter_mut().zip(right.dims) {
+                    *total += dim * sign;
+                }
+                let unit = if dims == DIMENSIONLESS {
+                    UnitExpr::default()
+                } else {
+                    left.unit.combine(&right.unit, sign)
+                };
+                Quantity {
+                    value,
+                    exact,
+                    dims,
+                    unit,
+                    absolute: false,
+                }
+            }
+            BinaryOp::AddPercent | BinaryOp::SubtractPercent => {
+                if !right.is_dimensionless() {
+                    return error("a percentage must be a plain number");
+                }
+                let change = &right.value / BigRational::from_integer(BigInt::from(100));
+                let factor = if op == BinaryOp::AddPercent {
+                    BigRational::one() + change
+                } else {
+                    BigRational::one() - change
+                };
+                left.with_value(&left.value * factor, exact)
+            }
+            BinaryOp::Power => power(&left, &right)?,
+        };
+        Ok(Value::Quantity(result))
+    }
+
+    fn call(&self, name: &str, arguments: &[Expr]) -> Result<Value, CalcError> {
+        let arity = |expected: std::ops::RangeInclusive<usize>| {
+            if expected.contains(&arguments.len()) {
+                Ok(())
+            } else if expected.start() == expected.end() {
+                error(format!(
+                    "{name} takes {} argument{}",
+                    expected.start(),
+                    if *expected.start() == 1 { "" } else { "s" }
+                ))
+            } else {
+                error(format!(
+                    "{name} takes {} to {} arguments",
+                    expected.start(),
+                    expected.end()
+                ))
+            }
+        };
+
+        let result = match name {
+            "min" | "max" => {
+                if arguments.is_empty() {
+                    return error(format!("{name} needs at least one argument"));
+                }
+                let mut best: Option<Quantity> = None;
+                for argument in arguments {
+                    let candidate = self.quantity(argument)?;
+                    best = Some(match best {
+                        None => candidate,
+                        Some(best) => {
+                            if best.dims != candidate.dims {
+                                return error(format!(
+                                    "can't compare {} with {}",
+                                    best.describe_unit(),
+                                    candidate.describe_unit()
+                                ));
+                            }
+                            let smaller = candidate.value < best.value;
+                            if smaller == (name == "min") {
+                                candidate
+                            } else {
+                                best
+                            }
+                        }
+                    });
+                }
+                best.expect("at least one argument")
+            }
+            "abs" => {
+                arity(1..=1)?;
+                let amount = self.quantity(&arguments[0])?;
+                amount.with_value(amount.value.abs(), amount.exact)
+            }
+            "round" | "floor" | "ceil" => {
+                arity(1..=2)?;
+                let amount = self.quantity(&arguments[0])?;
+                let places = match arguments.get(1) {
+                    None => 0,
+                    Some(places) => {
+                        let places = self.number(places, "the number of decimal places")?;
+                        match places.value.to_integer().to_u32() {
+                            Some(count) if places.value.is_integer() && count <= 50 => count,
+                            _ => {
+                                return error("decimal places must be a whole number from 0 to 50");
+                            }
+                        }
+                    }
+                };
+                round(&amount, name, places)
+            }
+            "sqrt" => {
+                arity(1..=1)?;
+                let amount = self.number(&arguments[0], "sqrt's argument")?;
+                if amount.value.is_negative() {
+                    return error("can't take the square root of a negative number");
+                }
+                let (value, exact) = sqrt(&amount.value);
+                Quantity::number(value, exact && amount.exact)
+            }
+            _ => {
+                arity(1..=1)?;
+                let amount = self.number(&arguments[0], &format!("{name}'s argument"))?;
+                let x = amount.to_f64();
+                let value = match name {
+                    "ln" | "log" | "log10" | "log2" if x <= 0.0 => {
+                        return error(format!("{name} is only defined for positive numbers"));
+                    }
+                    "ln" => x.ln(),
+                    "log" | "log10" => x.log10(),
+                    "log2" => x.log2(),
+                    "exp" => x.exp(),
+                    "sin" => x.sin(),
+                    "cos" => x.cos(),
+                    "tan" => x.tan(),
+                    _ => return error(format!("unknown function '{name}'")),
+                };
+                Quantity::number(from_f64(value)?, false)
+            }
+        };
+        Ok(Value::Quantity(result))
+    }
+}
+
+/// Attach `unit` to a plain number.
+fn apply_unit(amount: Quantity, unit: &UnitExpr) -> Result<Quantity, CalcError> {
+    if !amount.is_dimensionless() {
+        return error(format!(
+            "can't give {} the unit {}",
+            amount.describe_unit(),
+            unit.render(false)
+        ));
+    }
+    let offset = unit.as_single().and_then(|unit| unit.offset());
+    let value = &amount.value * unit.factor() + offset.clone().unwrap_or_else(BigRational::zero);
+    Ok(Quantity {
+        value,
+        exact: amount.exact,
+        dims: unit.dims(),
+        unit: unit.clone(),
+        absolute: offset.is_some(),
+    })
+}
+
+fn power(base: &Quantity, exponent: &Quantity) -> Result<Quantity, CalcError> {
+    if !exponent.is_dimensionless() {
+        return error(format!(
+            "an exponent must be a plain number, not {}",
+            exponent.describe_unit()
+        ));
+    }
+    let exact = base.exact && exponent.exact;
+
+    if exponent.value.is_integer()
+        && let Some(n) = exponent.value.to_integer().to_i32()
+    {
+        if base.value.is_zero() && n < 0 {
+            return error("division by zero");
+        }
+        let bits = base.value.numer().bits() + base.value.denom().bits();
+        if bits.saturating_mul(u64::from(n.unsigned_abs())) <= MAX_EXACT_BITS {
+            let mut dims = base.dims;
+            for dim in &mut dims {
+                *dim *= n;
+            }
+            return Ok(Quantity {
+                value: units::pow(&base.value, n),
+                exact,
+                dims,
+                unit: base.unit.powi(n),
+                absolute: false,
+            });
+        }
+    }
+
+    if !base.is_dimensionless() {
+        return error("units can only be raised to whole powers");
+    }
+    if base.value.is_negative() && !exponent.value.is_integer() {
+        return error("can't raise a negative number to a fractional power");
+    }
+    let value = base.to_f64().powf(exponent.to_f64());
+    Ok(Quantity::number(from_f64(value)?, false))
+}
+
+fn round(amount: &Quantity, mode: &str, places: u32) -> Quantity {
+    let factor = if amount.unit.is_empty() || amount.unit.dims() != amount.dims {
+        BigRational::one()
+    } else {
+        amount.unit.factor()
+    };
+    let scale = BigRational::from_integer(pow10(places));
+    let scaled = &amount.value / &factor * &scale;
+    let rounded = match mode {
+        "floor" => scaled.floor(),
+        "ceil" => scaled.ceil(),
+        _ => scaled.round(),
+    };
+    amount.with_value(rounded / scale * factor, amount.exact)
+}
+
+/// The square root of a non-negative `value`, and whether it's exact.
+fn sqrt(value: &BigRational) -> (BigRational, bool) {
+    // sqrt(n/d) = sqrt(n*d)/d
+    let product = value.numer() * value.denom();
+    let root = product.sqrt();
+    if &root * &root == product {
+        return (BigRational::new(root, value.denom().clone()), true);
+    }
+    let scale = pow10(SQRT_DIGITS);
+    let root = (product * &scale * &scale).sqrt();
+    (BigRational::new(root, value.denom() * scale), false)
+}
+
+fn from_f64(value: f64) -> Result<BigRational, CalcError> {
+    if !value.is_finite() {
+        return error("the result is undefined or too large");
+    }
+    BigRational::from_float(value).ok_or_else(|| CalcError::Eval("the result is undefined".into()))
+}
+
+/// Move `date` by `shift`, which must be a whole number of days, or of
+/// months or years to move by calendar months.
+fn shift_date(date: NaiveDate, shift: &Quantity, backwards: bool) -> Result<NaiveDate, CalcError> {
+    let time = units::lookup("s").expect("seconds are a unit").dims;
+    if shift.dims != time || shift.absolute {
+        return error(format!(
+            "dates can only move by an amount of time, not {}",
+            shift.describe_unit()
+        ));
+    }
+    let out_of_range = || CalcError::Eval("the date is out of range".into());
+
+    if let Some(unit) = shift.unit.as_single()
+        && let Some(months_per_unit) = unit.calendar_months()
+    {
+        let amount = &shift.value / unit.factor();
+        if amount.is_integer() {
+            let months = amount
+                .to_integer()
+                .to_i64()
+                .and_then(|amount| amount.checked_mul(i64::from(months_per_unit)))
+                .ok_or_else(out_of_range)?;
+            let forwards = (months >= 0) != backwards;
+            let months =
+                Months::new(u32::try_from(months.unsigned_abs()).map_err(|_| out_of_range())?);
+            let shifted = if forwards {
+                date.checked_add_months(months)
+            } else {
+                date.checked_sub_months(months)
+            };
+            return shifted.ok_or_else(out_of_range);
+        }
+    }
+
+    let days = &shift.value / BigRational::from_integer(BigInt::from(86_400));
+    if !days.is_integer() {
+        return error("dates move in whole days");
+    }
+    let days = days.to_integer().to_i64().ok_or_else(out_of_range)?;
+    let days = if backwards { -days } else { days };
+    TimeDelta::try_days(days)
+        .and_then(|delta| date.checked_add_signed(delta))
+        .ok_or_else(out_of_range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 14).unwrap()
+    }
+
+    fn calc(expression: &str) -> String {
+        let answer = evaluate(expression, today(), DEFAULT_PRECISION)
+            .unwrap_or_else(|error| panic!("{expression}: {error}"));
+        answer.text
+    }
+
+    #[test]
+    fn arithmetic_is_exact_where_it_can_be() {
+        assert_eq!(calc("0.1 + 0.2"), "0.3");
+        assert_eq!(calc("2^100"), "1267650600228229401496703205376");
+        assert_eq!(calc("1,250 * 12 - 3"), "14997");
+        assert_eq!(calc("200 + 15%"), "230");
+        assert_eq!(calc("15% of 80"), "12");
+        assert_eq!(calc("-2^2"), "-4");
+        assert_eq!(calc("17 mod 5"), "2");
+        assert_eq!(calc("round(2/3, 2)"), "0.67");
+        assert_eq!(calc("sqrt(144)"), "12");
+
+        let third = evaluate("1/3", today(), DEFAULT_PRECISION).unwrap();
+        assert_eq!(third.text, "0.333333333333333");
+        assert!(third.approximate);
+        assert_eq!(third.fraction.as_deref(), Some("1/3"));
+        assert_eq!(calc("sqrt(2)"), "1.4142135623731");
+        assert_eq!(calc("1e30 / 7"), "1.42857142857143e29");
+
+        assert!(evaluate("1 / 0", today(), DEFAULT_PRECISION).is_err());
+        assert!(evaluate("2 +", today(), DEFAULT_PRECISION).is_err());
+    }
+
+    #[test]
+    fn units_and_dates_convert() {
+        assert_eq!(calc("5 ft in cm"), "152.4 cm");
+        assert_eq!(calc("12 in to ft"), "1 ft");
+        assert_eq!(calc("10 km / 2 h"), "5 km/h");
+        assert_eq!(calc("100 km/h to mph"), "62.1371192237334 mph");
+        assert_eq!(calc("1 GiB to MB"), "1073.741824 MB");
+        assert_eq!(calc("20 °C to °F"), "68 °F");
+        assert_eq!(calc("-40 degF to C"), "-40 °C");
+        assert_eq!(calc("90 min + 1 h"), "150 min");
+        assert!(evaluate("3 kg + 5 m", today(), DEFAULT_PRECISION).is_err());
+        assert!(evaluate("20 °C + 5 °C", today(), DEFAULT_PRECISION).is_err());
+
+        assert_eq!(calc("2026-03-01 + 45 days"), "2026-04-15 (Wednesday)");
+        assert_eq!(calc("2026-01-31 + 1 month"), "2026-02-28 (Saturday)");
+        assert_eq!(calc("2026-12-25 - today"), "72 days");
+        assert_eq!(
+            calc("(2026-12-25 - today) to weeks"),
+            "10.2857142857143 weeks"
+        );
+    }
+
+    #[test]
+    fn messages_are_scanned_for_arithmetic() {
+        let found = find_calculations(
+            "Budget is 1,200 * 12 for the year, call 555-1234, we're open 24/7. What's 15% of 80? Ship 5 km.",
+            today(),
+        );
+        let found: Vec<(&str, &str)> = found
+            .iter()
+            .map(|calculation| {
+                (
+                    calculation.expression.as_str(),
+                    calculation.answer.text.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(found, [("1,200 * 12", "14400"), ("15% of 80", "12")]);
+
+        assert!(find_calculations("see you in 3-5 days", today()).is_empty());
+        assert!(find_calculations("about 1/3 + 1 of them", today()).is_empty());
+    }
+}
@@ -0,0 +1,441 @@
+//! Tokenizer and parser for calculator expressions.
+
+use super::CalcError;
+use super::units::{self, UnitExpr};
+
+use chrono::NaiveDate;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+use std::ops::Range;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Token {
+    Number(BigRational),
+    Date(NaiveDate),
+    Ident(String),
+    /// One of `+ - * / ^`.
+    Op(char),
+    Percent,
+    Comma,
+    Open,
+    Close,
+    /// Anything else, kept so messages can be scanned for expressions.
+    Other,
+}
+
+/// Split `text` into tokens with their byte ranges.
+pub(super) fn tokenize(text: &str) -> Vec<(Token, Range<usize>)> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let token = if c.is_ascii_digit() || (c == '.' && next_is_digit(text, start + 1)) {
+            let (token, end) = number(text, start);
+            while chars.peek().is_some_and(|&(index, _)| index < end) {
+                chars.next();
+            }
+            tokens.push((token, start..end));
+            continue;
+        } else if c.is_alphabetic() || c == '_' || c == '°' {
+            let mut end = start;
+            while let Some(&(index, c)) = chars.peek() {
+                if index > start && !(c.is_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = index + c.len_utf8();
+                chars.next();
+            }
+            tokens.push((Token::Ident(text[start..end].to_string()), start..end));
+            continue;
+        } else {
+            match c {
+                '+' => Token::Op('+'),
+                '-' | '−' => Token::Op('-'),
+                '*' if text[start..].starts_with("**") => {
+                    chars.next();
+                    Token::Op('^')
+                }
+                '*' | '×' => Token::Op('*'),
+                '/' | '÷' => Token::Op('/'),
+                '^' => Token::Op('^'),
+                '%' => Token::Percent,
+                ',' => Token::Comma,
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => Token::Other,
+            }
+        };
+        chars.next();
+        let end = match token {
+            Token::Op('^') if c == '*' => start + 2,
+            _ => start + c.len_utf8(),
+        };
+        tokens.push((token, start..end));
+    }
+    tokens
+}
+
+fn next_is_digit(text: &str, index: usize) -> bool {
+    text.as_bytes()
+        .get(index)
+        .is_some_and(|byte| byte.is_ascii_digit())
+}
+
+/// Read the number or date starting at `start`, returning where it ends.
+fn number(text: &str, start: usize) -> (Token, usize) {
+    let bytes = text.as_bytes();
+    let digits_from = |mut index: usize| {
+        while index < bytes.len() && bytes[index].is_ascii_digit() {
+            index += 1;
+        }
+        index
+    };
+
+    // Dates are written YYYY-MM-DD.
+    let year_end = digits_from(start);
+    if year_end - start == 4
+        && text[year_end..].len() >= 6
+        && bytes[year_end] == b'-'
+        && digits_from(year_end + 1) == year_end + 3
+        && bytes.get(year_end + 3) == Some(&b'-')
+        && digits_from(year_end + 4) == year_end + 6
+        && let Ok(date) = NaiveDate::parse_from_str(&text[start..year_end + 6], "%Y-%m-%d")
+    {
+        return (Token::Date(date), year_end + 6);
+    }
+
+    let mut literal = String::new();
+    let mut end = start;
+    // Digits, with commas grouping thousands: 1,250,000.
+    loop {
+        let group_end = digits_from(end);
+        literal.push_str(&text[end..group_end]);
+        end = group_end;
+        if bytes.get(end) == Some(&b',') && digits_from(end + 1) == end + 4 && !literal.is_empty() {
+            end += 1;
+            continue;
+        }
+        break;
+    }
+    if bytes.get(end) == Some(&b'.') && next_is_digit(text, end + 1) {
+        let fraction_end = digits_from(end + 1);
+        literal.push_str(&text[end..fraction_end]);
+        end = fraction_end;
+    }
+    if matches!(bytes.get(end), Some(b'e' | b'E')) {
+        let sign = usize::from(matches!(bytes.get(end + 1), Some(b'+' | b'-')));
+        if next_is_digit(text, end + 1 + sign) {
+            let exponent_end = digits_from(end + 1 + sign);
+            literal.push_str(&text[end..exponent_end]);
+            end = exponent_end;
+        }
+    }
+    match super::parse_decimal(&literal) {
+        Some(value) => (Token::Number(value), end),
+        None => (Token::Other, end),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Modulo,
+    /// `a + p%`: `a` increased by `p` percent.
+    AddPercent,
+    SubtractPercent,
+}
+
+pub(super) enum Expr {
+    Number(BigRational),
+    Date(NaiveDate),
+    Today,
+    Constant(&'static str),
+    WithUnit(Box<Expr>, UnitExpr),
+    Percent(Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    Convert(Box<Expr>, UnitExpr),
+}
+
+impl Expr {
+    /// Whether the expression does any arithmetic or conversion, as opposed to
+    /// just stating a value like "5 km".
+    pub fn computes(&self) -> bool {
+        match self {
+            Expr::Binary(..) | Expr::Convert(..) | Expr::Call(..) => true,
+            Expr::WithUnit(inner, _) | Expr::Percent(inner) | Expr::Negate(inner) => {
+                inner.computes()
+            }
+            Expr::Number(_) | Expr::Date(_) | Expr::Today | Expr::Constant(_) => false,
+        }
+    }
+}
+
+/// Constant names and their values to 50 digits.
+pub(super) const CONSTANTS: &[(&str, &str)] = &[
+    ("pi", "3.14159265358979323846264338327950288419716939937511"),
+    ("e", "2.71828182845904523536028747135266249775724709369996"),
+];
+
+pub(super) const FUNCTIONS: &[&str] = &[
+    "sqrt", "abs", "round", "floor", "ceil", "min", "max", "ln", "log", "log10", "log2", "exp",
+    "sin", "cos", "tan",
+];
+
+/// Parse a whole token list as one expression.
+pub(super) fn parse(tokens: &[Token]) -> Result<Expr, CalcError> {
+    let mut parser = Parser {
+        tokens,
+        position: 0,
+    };
+    let expr = parser.conversion()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(CalcError::Parse(format!("unexpected {}", describe(token)))),
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(_) => "number".into(),
+        Token::Date(_) => "date".into(),
+        Token::Ident(name) => format!("'{name}'"),
+        Token::Op(op) => format!("'{op}'"),
+        Token::Percent => "'%'".into(),
+        Token::Comma => "','".into(),
+        Token::Open => "'('".into(),
+        Token::Close => "')'".into(),
+        Token::Other => "character".into(),
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_ident(&self, offset: usize) -> Option<&str> {
+        match self.tokens.get(self.position + offset) {
+            Some(Token::Ident(name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), CalcError> {
+        if self.eat(token) {
+            return Ok(());
+        }
+        Err(CalcError::Parse(match self.peek() {
+            Some(found) => format!("expected {}, found {}", describe(token), describe(found)),
+            None => format!("expected {} at the end", describe(token)),
+        }))
+    }
+
+    /// `expr (to|in|as) unit`
+    fn conversion(&mut self) -> Result<Expr, CalcError> {
+        let expr = self.additive()?;
+        if matches!(self.peek_ident(0), Some("to" | "in" | "as")) {
+            self.position += 1;
+            let target = self
+                .unit_expr()?
+                .ok_or_else(|| CalcError::Parse("expected a unit to convert to".into()))?;
+            return Ok(Expr::Convert(Box::new(expr), target));
+        }
+        Ok(expr)
+    }
+
+    fn additive(&mut self) -> Result<Expr, CalcError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op('+')) => BinaryOp::Add,
+                Some(Token::Op('-')) => BinaryOp::Subtract,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            let right = self.multiplicative()?;
+            left = match (op, right) {
+                (BinaryOp::Add, Expr::Percent(percent)) => {
+                    Expr::Binary(BinaryOp::AddPercent, Box::new(left), percent)
+                }
+                (_, Expr::Percent(percent)) => {
+                    Expr::Binary(BinaryOp::SubtractPercent, Box::new(left), percent)
+                }
+                (op, right) => Expr::Binary(op, Box::new(left), Box::new(right)),
+            };
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, CalcError> {
+        let mut left = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op('*')) => BinaryOp::Multiply,
+                Some(Token::Op('/')) => BinaryOp::Divide,
+                Some(Token::Ident(name)) if name == "of" => BinaryOp::Multiply,
+                Some(Token::Ident(name)) if name == "mod" => BinaryOp::Modulo,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            let right = self.unary()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, CalcError> {
+        if self.eat(&Token::Op('-')) {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        if self.eat(&Token::Op('+')) {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, CalcError> {
+        let base = self.postfix()?;
+        if self.eat(&Token::Op('^')) {
+            let exponent = self.unary()?;
+            return Ok(Expr::Binary(
+                BinaryOp::Power,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    /// A value with an optional percent sign or unit after it.
+    fn postfix(&mut self) -> Result<Expr, CalcError> {
+        let expr = self.primary()?;
+        if self.eat(&Token::Percent) {
+            return Ok(Expr::Percent(Box::new(expr)));
+        }
+        // "in" after a number is inches, unless a unit follows: "5 ft in cm".
+        if self.peek_ident(0) == Some("in") && self.peek_ident(1).and_then(units::lookup).is_some()
+        {
+            return Ok(expr);
+        }
+        match self.unit_expr()? {
+            Some(unit) => Ok(Expr::WithUnit(Box::new(expr), unit)),
+            None => Ok(expr),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, CalcError> {
+        let Some(token) = self.next().cloned() else {
+            return Err(CalcError::Parse("unexpected end of expression".into()));
+        };
+        match token {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Date(date) => Ok(Expr::Date(date)),
+            Token::Open => {
+                let expr = self.conversion()?;
+                self.expect(&Token::Close)?;
+                Ok(expr)
+            }
+            Token::Ident(name) if name == "today" => Ok(Expr::Today),
+            Token::Ident(name) if self.peek() == Some(&Token::Open) => {
+                let name = name.to_lowercase();
+                if !FUNCTIONS.contains(&name.as_str()) {
+                    return Err(CalcError::Parse(format!("unknown function '{name}'")));
+                }
+                self.position += 1;
+                let mut arguments = Vec::new();
+                if !self.eat(&Token::Close) {
+                    loop {
+                        arguments.push(self.conversion()?);
+                        if self.eat(&Token::Close) {
+                            break;
+                        }
+                        self.expect(&Token::Comma)?;
+                    }
+                }
+                Ok(Expr::Call(name, arguments))
+            }
+            Token::Ident(name) => match CONSTANTS.iter().find(|(constant, _)| *constant == name) {
+                Some((constant, _)) => Ok(Expr::Constant(constant)),
+                None => Err(CalcError::Parse(format!("unknown name '{name}'"))),
+            },
+            other => Err(CalcError::Parse(format!("unexpected {}", describe(&other)))),
+        }
+    }
+
+    /// A unit like `km`, `m^2`, or `km/h`, if one comes next.
+    fn unit_expr(&mut self) -> Result<Option<UnitExpr>, CalcError> {
+        let Some(first) = self.unit_factor()? else {
+            return Ok(None);
+        };
+        let mut unit = first;
+        loop {
+            let sign = match self.peek() {
+                Some(Token::Op('*')) => 1,
+                Some(Token::Op('/')) => -1,
+                _ => break,
+            };
+            // Only continue when a unit follows, so "10 km / 2 h" divides.
+            if self.peek_ident(1).and_then(units::lookup).is_none()
+                || self.tokens.get(self.position + 2) == Some(&Token::Open)
+            {
+                break;
+            }
+            self.position += 1;
+            let factor = self.unit_factor()?.expect("a unit follows");
+            unit = unit.combine(&factor, sign);
+        }
+        Ok(Some(unit))
+    }
+
+    fn unit_factor(&mut self) -> Result<Option<UnitExpr>, CalcError> {
+        let Some(unit) = self.peek_ident(0).and_then(units::lookup) else {
+            return Ok(None);
+        };
+        // A name followed by '(' is a function call, like min(...).
+        if self.tokens.get(self.position + 1) == Some(&Token::Open) {
+            return Ok(None);
+        }
+        self.position += 1;
+        let mut power = 1;
+        if self.peek() == Some(&Token::Op('^')) {
+            let negative = self.tokens.get(self.position + 1) == Some(&Token::Op('-'));
+            let index = self.position + 1 + usize::from(negative);
+            if let Some(Token::Number(exponent)) = self.tokens.get(index)
+                && exponent.is_integer()
+                && let Some(exponent) = exponent.to_integer().to_i32()
+                && (1..=9).contains(&exponent)
+            {
+                power = if negative { -exponent } else { exponent };
+                self.position = index + 1;
+            }
+        }
+        Ok(Some(UnitExpr::single(unit).powi(power)))
+    }
+}
@@ -0,0 +1,316 @@
+//! Units the calculator knows, as multiples of the base units: meters,
+//! kilograms, seconds, bytes, and kelvin.
+
+use super::parse_decimal;
+
+use num_rational::BigRational;
+use num_traits::One;
+
+/// Exponents of the base dimensions: length, mass, time, data, temperature.
+pub(super) type Dims = [i32; 5];
+
+pub(super) const DIMENSIONLESS: Dims = [0; 5];
+
+/// Symbols for the base units, in `Dims` order.
+const BASE_SYMBOLS: [&str; 5] = ["m", "kg", "s", "B", "K"];
+
+pub(super) struct Unit {
+    /// How the unit is written in results.
+    pub symbol: &'static str,
+    /// How it's written for amounts other than one, when that differs.
+    pub plural: Option<&'static str>,
+    /// Other names it's recognized by.
+    pub aliases: &'static [&'static str],
+    pub dims: Dims,
+    /// Size of the unit in base units, as a decimal or fraction.
+    pub factor: &'static str,
+    /// Kelvin at zero on this scale, for °C and °F.
+    pub offset: Option<&'static str>,
+}
+
+impl Unit {
+    pub fn factor(&self) -> BigRational {
+        parse_decimal(self.factor).expect("unit factors are valid")
+    }
+
+    pub fn offset(&self) -> Option<BigRational> {
+        self.offset
+            .map(|offset| parse_decimal(offset).expect("unit offsets are valid"))
+    }
+
+    /// Whether adding to a date moves it by calendar months rather than a
+    /// fixed length of time, and by how many months per unit.
+    pub fn calendar_months(&self) -> Option<u32> {
+        match self.symbol {
+            "month" => Some(1),
+            "year" => Some(12),
+            _ => None,
+        }
+    }
+}
+
+const LENGTH: Dims = [1, 0, 0, 0, 0];
+const AREA: Dims = [2, 0, 0, 0, 0];
+const VOLUME: Dims = [3, 0, 0, 0, 0];
+const MASS: Dims = [0, 1, 0, 0, 0];
+const TIME: Dims = [0, 0, 1, 0, 0];
+const SPEED: Dims = [1, 0, -1, 0, 0];
+const DATA: Dims = [0, 0, 0, 1, 0];
+const TEMPERATURE: Dims = [0, 0, 0, 0, 1];
+
+const fn unit(symbol: &'static str, dims: Dims, factor: &'static str) -> Unit {
+    Unit {
+        symbol,
+        plural: None,
+        aliases: &[],
+        dims,
+        factor,
+        offset: None,
+    }
+}
+
+impl Unit {
+    const fn with_plural(mut self, plural: &'static str) -> Self {
+        self.plural = Some(plural);
+        self
+    }
+
+    const fn with_aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    const fn with_offset(mut self, offset: &'static str) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+}
+
+pub(super) const UNITS: &[Unit] = &[
+    unit("m", LENGTH, "1").with_aliases(&["meter", "meters", "metre", "metres"]),
+    unit("km", LENGTH, "1000").with_aliases(&[
+        "kilometer",
+        "kilometers",
+        "kilometre",
+        "kilometres",
+    ]),
+    unit("cm", LENGTH, "0.01").with_aliases(&[
+        "centimeter",
+        "centimeters",
+        "centimetre",
+        "centimetres",
+    ]),
+    unit("mm", LENGTH, "0.001").with_aliases(&[
+        "millimeter",
+        "millimeters",
+        "millimetre",
+        "millimetres",
+    ]),
+    unit("mi", LENGTH, "1609.344").with_aliases(&["mile", "miles"]),
+    unit("yd", LENGTH, "0.9144").with_aliases(&["yard", "yards"]),
+    unit("ft", LENGTH, "0.3048").with_aliases(&["foot", "feet"]),
+    unit("in", LENGTH, "0.0254").with_aliases(&["inch", "inches"]),
+    unit("nmi", LENGTH, "1852").with_aliases(&["nautical_mile", "nautical_miles"]),
+    unit("ha", AREA, "10000").with_aliases(&["hectare", "hectares"]),
+    unit("acre", AREA, "4046.8564224")
+        .with_plural("acres")
+        .with_aliases(&["acres"]),
+    unit("L", VOLUME, "0.001").with_aliases(&["l", "liter", "liters", "litre", "litres"]),
+    unit("mL", VOLUME, "0.000001").with_aliases(&[
+        "ml",
+        "milliliter",
+        "milliliters",
+        "millilitre",
+        "millilitres",
+    ]),
+    unit("gal", VOLUME, "0.003785411784").with_aliases(&["gallon", "gallons"]),
+    unit("kg", MASS, "1").with_aliases(&["kilogram", "kilograms", "kilo", "kilos"]),
+    unit("g", MASS, "0.001").with_aliases(&["gram", "grams"]),
+    unit("mg", MASS, "0.000001").with_aliases(&["milligram", "milligrams"]),
+    unit("t", MASS, "1000").with_aliases(&["tonne", "tonnes"]),
+    unit("lb", MASS, "0.45359237").with_aliases(&["lbs", "pound", "pounds"]),
+    unit("oz", MASS, "0.028349523125").with_aliases(&["ounce", "ounces"]),
+    unit("s", TIME, "1").with_aliases(&["sec", "secs", "second", "seconds"]),
+    unit("ms", TIME, "0.001").with_aliases(&["millisecond", "milliseconds"]),
+    unit("min", TIME, "60").with_aliases(&["mins", "minute", "minutes"]),
+    unit("h", TIME, "3600").with_aliases(&["hr", "hrs", "hour", "hours"]),
+    unit("day", TIME, "86400")
+        .with_plural("days")
+        .with_aliases(&["days", "d"]),
+    unit("week", TIME, "604800")
+        .with_plural("weeks")
+        .with_aliases(&["weeks", "wk", "wks"]),
+    // Average Gregorian month and year; dates move by calendar months.
+    unit("month", TIME, "2629746")
+        .with_plural("months")
+        .with_aliases(&["months", "mo"]),
+    unit("year", TIME, "31556952")
+        .with_plural("years")
+        .with_aliases(&["years", "yr", "yrs"]),
+    unit("km/h", SPEED, "5/18").with_aliases(&["kph", "kmh"]),
+    unit("mph", SPEED, "0.44704"),
+    unit("kn", SPEED, "1852/3600").with_aliases(&["knot", "knots"]),
+    unit("B", DATA, "1").with_aliases(&["byte", "bytes"]),
+    unit("KB", DATA, "1000").with_aliases(&["kB"]),
+    unit("MB", DATA, "1000000"),
+    unit("GB", DATA, "1000000000"),
+    unit("TB", DATA, "1000000000000"),
+    unit("PB", DATA, "1000000000000000"),
+    unit("KiB", DATA, "1024"),
+    unit("MiB", DATA, "1048576"),
+    unit("GiB", DATA, "1073741824"),
+    unit("TiB", DATA, "1099511627776"),
+    unit("bit", DATA, "1/8")
+        .with_plural("bits")
+        .with_aliases(&["bits"]),
+    unit("kbit", DATA, "125").with_aliases(&["kbits"]),
+    unit("Mbit", DATA, "125000").with_aliases(&["Mbits"]),
+    unit("Gbit", DATA, "125000000").with_aliases(&["Gbits"]),
+    unit("K", TEMPERATURE, "1").with_aliases(&["kelvin"]),
+    unit("°C", TEMPERATURE, "1")
+        .with_offset("273.15")
+        .with_aliases(&["degC", "C", "celsius"]),
+    unit("°F", TEMPERATURE, "5/9")
+        .with_offset("45967/180")
+        .with_aliases(&["degF", "F", "fahrenheit"]),
+];
+
+/// The unit called `name`, matching case only when that's ambiguous.
+pub(super) fn lookup(name: &str) -> Option<&'static Unit> {
+    UNITS
+        .iter()
+        .find(|unit| unit.symbol == name || unit.aliases.contains(&name))
+        .or_else(|| {
+            UNITS.iter().find(|unit| {
+                unit.symbol.eq_ignore_ascii_case(name)
+                    || unit
+                        .aliases
+                        .iter()
+                        .any(|alias| alias.eq_ignore_ascii_case(name))
+            })
+        })
+}
+
+/// A product of units raised to powers, like km/h or m^2.
+#[derive(Clone, Default)]
+pub(super) struct UnitExpr(pub Vec<(&'static Unit, i32)>);
+
+impl UnitExpr {
+    pub fn single(unit: &'static Unit) -> Self {
+        Self(vec![(unit, 1)])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn dims(&self) -> Dims {
+        let mut dims = DIMENSIONLESS;
+        for (unit, power) in &self.0 {
+            for (total, dim) in dims.iter_mut().zip(unit.dims) {
+                *total += dim * power;
+            }
+        }
+        dims
+    }
+
+    /// Size of the whole expression in base units.
+    pub fn factor(&self) -> BigRational {
+        self.0
+            .iter()
+            .fold(BigRational::one(), |total, (unit, power)| {
+                total * pow(&unit.factor(), *power)
+            })
+    }
+
+    /// The lone unit, when the expression is one unit to the first power.
+    pub fn as_single(&self) -> Option<&'static Unit> {
+        match self.0.as_slice() {
+            [(unit, 1)] => Some(unit),
+            _ => None,
+        }
+    }
+
+    /// `self` times `other` raised to `sign`, with repeated units combined.
+    pub fn combine(&self, other: &Self, sign: i32) -> Self {
+        let mut units = self.0.clone();
+        for (unit, power) in &other.0 {
+            match units
+                .iter_mut()
+                .find(|(existing, _)| existing.symbol == unit.symbol)
+            {
+                Some((_, existing)) => *existing += power * sign,
+                None => units.push((unit, power * sign)),
+            }
+        }
+        units.retain(|(_, power)| *power != 0);
+        Self(units)
+    }
+
+    pub fn powi(&self, exponent: i32) -> Self {
+        Self(
+            self.0
+                .iter()
+                .map(|(unit, power)| (*unit, power * exponent))
+                .collect(),
+        )
+    }
+
+    /// Render for an amount, so "1 day" but "2 days".
+    pub fn render(&self, singular: bool) -> String {
+        if let Some(unit) = self.as_single() {
+            return match unit.plural {
+                Some(plural) if !singular => plural.to_string(),
+                _ => unit.symbol.to_string(),
+            };
+        }
+        render_powers(self.0.iter().map(|(unit, power)| (unit.symbol, *power)))
+    }
+}
+
+/// Render base units for `dims`, for results no written unit describes.
+pub(super) fn render_dims(dims: &Dims) -> String {
+    render_powers(BASE_SYMBOLS.into_iter().zip(dims.iter().copied()))
+}
+
+fn render_powers<'a>(powers: impl Iterator<Item = (&'a str, i32)>) -> String {
+    let term = |symbol: &str, power: i32| {
+        if power == 1 {
+            symbol.to_string()
+        } else {
+            format!("{symbol}^{power}")
+        }
+    };
+    let (mut numerator, mut denominator) = (Vec::new(), Vec::new());
+    for (symbol, power) in powers {
+        match power {
+            0 => {}
+            p if p > 0 => numerator.push(term(symbol, p)),
+            p => denominator.push(term(symbol, -p)),
+        }
+    }
+    let numerator = if numerator.is_empty() {
+        "1".to_string()
+    } else {
+        numerator.join("*")
+    };
+    match denominator.len() {
+        0 => numerator,
+        1 => format!("{numerator}/{}", denominator[0]),
+        _ => format!("{numerator}/({})", denominator.join("*")),
+    }
+}
+
+/// `base` to an integer power. `base` can't be zero when `exponent` is
+/// negative.
+pub(super) fn pow(base: &BigRational, exponent: i32) -> BigRational {
+    if exponent == 0 {
+        return BigRational::one();
+    }
+    let magnitude = num_traits::pow(base.clone(), exponent.unsigned_abs() as usize);
+    if exponent > 0 {
+        magnitude
+    } else {
+        magnitude.recip()
+    }
+}
@@ -0,0 +1,170 @@
+//! Calendar access for the `calendar` channel tool: list upcoming events and
+//! create new ones on Google Calendar or a CalDAV server.
+//!
+//! One set of credentials is configured per agent; `[defaults.calendar.users]`
+//! binds chat users to the calendar their requests go to.
+
+pub mod caldav;
+pub mod google;
+
+use crate::config::{CalendarConfig, CalendarProvider};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CalendarError {
+    #[error("calendar request failed: {0}")]
+    Request(String),
+
+    #[error("calendar server returned {status}: {message}")]
+    Status {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+
+    #[error("unexpected calendar response: {0}")]
+    InvalidResponse(String),
+}
+
+impl From<reqwest::Error> for CalendarError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Request(error.to_string())
+    }
+}
+
+impl From<crate::egress::EgressError> for CalendarError {
+    fn from(error: crate::egress::EgressError) -> Self {
+        Self::Request(error.to_string())
+    }
+}
+
+/// An event read from a calendar.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CalendarEvent {
+    pub title: String,
+    /// For all-day events, local midnight of the first day.
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub all_day: bool,
+    pub location: Option<String>,
+    /// Link to the event in the provider's UI, when it has one.
+    pub url: Option<String>,
+}
+
+/// An event to create.
+#[derive(Debug, Clone)]
+pub struct NewEvent {
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Client for the configured calendar provider.
+#[derive(Debug, Clone)]
+pub struct CalendarClient {
+    http: crate::egress::Client,
+    config: CalendarConfig,
+}
+
+impl CalendarClient {
+    /// Returns `None` when no provider is configured.
+    pub fn new(config: CalendarConfig) -> Option<Self> {
+        config.provider.as_ref()?;
+        let http = crate::egress::client_builder()
+            .gzip(true)
+            .timeout(std::time::Duration::from_secs(30))
+            .build()
+            .expect("hardcoded reqwest client config");
+        Some(Self { http, config })
+    }
+
+    /// The calendar `user` ("platform:sender_id") is bound to, or the
+    /// default calendar.
+    pub fn calendar_for(&self, user: &str) -> Option<&str> {
+        self.config
+            .users
+            .get(user)
+            .or(self.config.calendar.as_ref())
+            .map(String::as_str)
+    }
+
+    /// Events overlapping `from..to` on `calendar`, earliest first.
+    pub async fn list(
+        &self,
+        calendar: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<CalendarEvent>, CalendarError> {
+        let mut events = match self.provider() {
+            CalendarProvider::Google {
+                client_id,
+                client_secret,
+                refresh_token,
+            } => {
+                let token =
+                    google::access_token(&self.http, client_id, client_secret, refresh_token)
+                        .await?;
+                google::list(&self.http, &token, calendar, from, to).await?
+            }
+            CalendarProvider::Caldav {
+                url,
+                username,
+                password,
+            } => {
+                let calendar_url = caldav::calendar_url(url, calendar)?;
+                caldav::list(&self.http, &calendar_url, username, password, from, to).await?
+            }
+        };
+        events.sort_by_key(|event| event.start);
+        Ok(events)
+    }
+
+    /// Create `event` on `calendar`, returning a link to it when the
+    /// provider has one.
+    pub async fn create(
+        &self,
+        calendar: &str,
+        event: &NewEvent,
+    ) -> Result<Option<String>, CalendarError> {
+        match self.provider() {
+            CalendarProvider::Google {
+                client_id,
+                client_secret,
+                refresh_token,
+            } => {
+                let token =
+                    google::access_token(&self.http, client_id, client_secret, refresh_token)
+                        .await?;
+                google::create(&self.http, &token, calendar, event).await
+            }
+            CalendarProvider::Caldav {
+                url,
+                username,
+                password,
+            } => {
+                let calendar_url = caldav::calendar_url(url, calendar)?;
+                caldav::create(&self.http, &calendar_url, username, password, event).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    fn provider(&self) -> &CalendarProvider {
+        self.config
+            .provider
+            .as_ref()
+            .expect("CalendarClient::new checks for a provider")
+    }
+}
+
+/// Read an error response into a [`CalendarError::Status`].
+async fn status_error(response: reqwest::Response) -> CalendarError {
+    let status = response.status();
+    let message = response.text().await.unwrap_or_default();
+    CalendarError::Status {
+        status,
+        message: message.chars().take(500).collect(),
+    }
+}
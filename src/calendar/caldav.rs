@@ -0,0 +1,445 @@
+//! CalDAV (RFC 4791): a time-range `REPORT` to list events and a `PUT` of
+//! an iCalendar object to create one.
+//!
+//! Only the parts of WebDAV XML and iCalendar the tool needs are handled:
+//! `calendar-data` payloads are pulled out of the multistatus response, and
+//! VEVENTs are read for their title, times, and location.
+
+use super::{CalendarError, CalendarEvent, NewEvent, status_error};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone as _, Utc};
+
+/// iCalendar UTC timestamp format.
+const ICS_UTC: &str = "%Y%m%dT%H%M%SZ";
+
+/// Resolve a calendar from the config against the server URL: an absolute
+/// URL is used as is, anything else is a path relative to `server_url`.
+pub(super) fn calendar_url(
+    server_url: &str,
+    calendar: &str,
+) -> Result<reqwest::Url, CalendarError> {
+    let base = reqwest::Url::parse(server_url)
+        .map_err(|error| CalendarError::Request(format!("invalid CalDAV url: {error}")))?;
+    // Collections are directories; without the slash, joins and PUTs land
+    // next to the calendar instead of inside it.
+    let calendar = if calendar.ends_with('/') {
+        calendar.to_string()
+    } else {
+        format!("{calendar}/")
+    };
+    base.join(&calendar)
+        .map_err(|error| CalendarError::Request(format!("invalid calendar '{calendar}': {error}")))
+}
+
+pub(super) async fn list(
+    http: &crate::egress::Client,
+    calendar_url: &reqwest::Url,
+    username: &str,
+    password: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<CalendarEvent>, CalendarError> {
+    let (start, end) = (from.format(ICS_UTC), to.format(ICS_UTC));
+    // `expand` asks the server to turn recurring events into the instances
+    // inside the range.
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<C:calendar-query xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <D:prop>
+    <C:calendar-data><C:expand start="{start}" end="{end}"/></C:calendar-data>
+  </D:prop>
+  <C:filter>
+    <C:comp-filter name="VCALENDAR">
+      <C:comp-filter name="VEVENT">
+        <C:time-range start="{start}" end="{end}"/>
+      </C:comp-filter>
+    </C:comp-filter>
+  </C:filter>
+</C:calendar-query>"#
+    );
+
+    let response = http
+        .request(
+            reqwest::Method::from_bytes(b"REPORT").expect("valid HTTP method"),
+            calendar_url.clone(),
+        )
+        .basic_auth(username, Some(password))
+        .header("Depth", "1")
+        .header("Content-Type", "application/xml; charset=utf-8")
+        .body(body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+    let xml = response.text().await?;
+
+    Ok(calendar_data(&xml)
+        .iter()
+        .flat_map(|ics| parse_events(ics))
+        .filter(|event| event.end.unwrap_or(event.start) >= from && event.start < to)
+        .collect())
+}
+
+pub(super) async fn create(
+    http: &crate::egress::Client,
+    calendar_url: &reqwest::Url,
+    username: &str,
+    password: &str,
+    event: &NewEvent,
+) -> Result<(), CalendarError> {
+    let uid = uuid::Uuid::new_v4().to_string();
+    let url = calendar_url
+        .join(&format!("{uid}.ics"))
+        .map_err(|error| CalendarError::Request(error.to_string()))?;
+
+    let response = http
+        .put(url)
+        .basic_auth(username, Some(password))
+        .header("Content-Type", "text/calendar; charset=utf-8")
+        .header("If-None-Match", "*")
+        .body(write_event(&uid, event, Utc::now()))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+    Ok(())
+}
+
+/// The contents of every `calendar-data` element in a multistatus response,
+/// whatever namespace prefix the server uses.
+fn calendar_data(xml: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let Some(tag_end) = rest.find('>') else { break };
+        let tag = &rest[..tag_end];
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        let local = name.rsplit(':').next().unwrap_or(name);
+        if local != "calendar-data" || tag.ends_with('/') {
+            continue;
+        }
+
+        let body = &rest[tag_end + 1..];
+        let close = format!("</{name}>");
+        let Some(body_end) = body.find(&close) else {
+            break;
+        };
+        blocks.push(unescape_xml(&body[..body_end]));
+        rest = &body[body_end + close.len()..];
+    }
+    blocks
+}
+
+fn unescape_xml(text: &str) -> String {
+    let text = text.trim();
+    if let Some(cdata) = text
+        .strip_prefix("<![CDATA[")
+        .and_then(|text| text.strip_suffix("]]>"))
+    {
+        return cdata.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// Every VEVENT in an iCalendar object.
+fn parse_events(ics: &str) -> Vec<CalendarEvent> {
+    // Unfold continuation lines (RFC 5545 section 3.1).
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        let line = line.trim_end_matches('\r');
+        match line.strip_prefix([' ', '\t']) {
+            Some(continuation) if !lines.is_empty() => {
+                lines
+                    .last_mut()
+                    .expect("checked non-empty")
+                    .push_str(continuation);
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<EventBuilder> = None;
+    for line in &lines {
+        let Some((head, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut params = head.split(';');
+        let name = params.next().unwrap_or_default().to_ascii_uppercase();
+        match (name.as_str(), value) {
+            ("BEGIN", "VEVENT") => current = Some(EventBuilder::default()),
+            ("END", "VEVENT") => {
+                if let Some(event) = current.take().and_then(EventBuilder::build) {
+                    events.push(event);
+                }
+            }
+            _ => {
+                let Some(event) = current.as_mut() else {
+                    continue;
+                };
+                let params: Vec<&str> = params.collect();
+                match name.as_str() {
+                    "SUMMARY" => event.title = Some(unescape_text(value)),
+                    "LOCATION" => event.location = Some(unescape_text(value)),
+                    "URL" => event.url = Some(value.to_string()),
+                    "DTSTART" => event.start = parse_ics_time(value, &params),
+                    "DTEND" => event.end = parse_ics_time(value, &params),
+                    _ => {}
+                }
+            }
+        }
+    }
+    events
+}
+
+#[derive(Default)]
+struct EventBuilder {
+    title: Option<String>,
+    start: Option<(DateTime<Utc>, bool)>,
+    end: Option<(DateTime<Utc>, bool)>,
+    location: Option<String>,
+    url: Option<String>,
+}
+
+impl EventBuilder {
+    fn build(self) -> Option<CalendarEvent> {
+        let (start, all_day) = self.start?;
+        Some(CalendarEvent {
+            title: self.title.unwrap_or_else(|| "(no title)".into()),
+            start,
+            end: self.end.map(|(end, _)| end),
+            all_day,
+            location: self.location.filter(|location| !location.is_empty()),
+            url: self.url,
+        })
+    }
+}
+
+/// Parse a DATE-TIME or DATE value. Returns the instant and whether it was an
+/// all-day date. Floating times and unknown TZIDs are read as local time.
+fn parse_ics_time(value: &str, params: &[&str]) -> Option<(DateTime<Utc>, bool)> {
+    let value = value.trim();
+    let param = |key: &str| {
+        params.iter().find_map(|param| {
+            param
+                .split_once('=')
+                .filter(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value.trim_matches('"'))
+        })
+    };
+
+    if param("VALUE").is_some_and(|kind| kind.eq_ignore_ascii_case("DATE")) || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        let midnight = chrono::Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .earliest()?;
+        return Some((midnight.with_timezone(&Utc), true));
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((naive.and_utc(), false));
+    }
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let resolved = match param("TZID").and_then(|tzid| tzid.parse::<chrono_tz::Tz>().ok()) {
+        Some(zone) => zone
+            .from_local_datetime(&naive)
+            .earliest()?
+            .with_timezone(&Utc),
+        None => chrono::Local
+            .from_local_datetime(&naive)
+            .earliest()?
+            .with_timezone(&Utc),
+    };
+    Some((resolved, false))
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(other) => text.push(other),
+            None => {}
+        }
+    }
+    text
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Serialize `event` as a VCALENDAR with CRLF line endings, folding lines at
+/// 75 octets.
+fn write_event(uid: &str, event: &NewEvent, now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Spacebot//Calendar Tool//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{uid}"),
+        format!("DTSTAMP:{}", now.format(ICS_UTC)),
+        format!("DTSTART:{}", event.start.format(ICS_UTC)),
+        format!("DTEND:{}", event.end.format(ICS_UTC)),
+        format!("SUMMARY:{}", escape_text(&event.title)),
+    ];
+    if let Some(location) = &event.location {
+        lines.push(format!("LOCATION:{}", escape_text(location)));
+    }
+    if let Some(description) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    lines.push("END:VEVENT".to_string());
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = String::new();
+    for line in lines {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > 75 {
+                ics.push_str("\r\n ");
+                width = 1;
+            }
+            ics.push(c);
+            width += c.len_utf8();
+        }
+        ics.push_str("\r\n");
+    }
+    ics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calendar_data_extraction() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:propstat><d:prop>
+      <cal:calendar-data>BEGIN:VCALENDAR&#13;
+SUMMARY:Q&amp;A &lt;team&gt;&#13;
+END:VCALENDAR</cal:calendar-data>
+    </d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:propstat><d:prop>
+      <C:calendar-data xmlns:C="urn:ietf:params:xml:ns:caldav"><![CDATA[BEGIN:VCALENDAR
+END:VCALENDAR]]></C:calendar-data>
+    </d:prop></d:propstat>
+  </d:response>
+  <d:prop><cal:calendar-data/></d:prop>
+</d:multistatus>"#;
+
+        let blocks = calendar_data(xml);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(
+            blocks[0],
+            "BEGIN:VCALENDAR\r\nSUMMARY:Q&A <team>\r\nEND:VCALENDAR"
+        );
+        assert_eq!(blocks[1], "BEGIN:VCALENDAR\nEND:VCALENDAR");
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:Planning\\, Q3 \r\n roadmap\r\n\
+DTSTART:20260305T150000Z\r\n\
+DTEND:20260305T160000Z\r\n\
+LOCATION:Room 4\\; east wing\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:Standup\r\n\
+DTSTART;TZID=America/New_York:20260306T090000\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART;VALUE=DATE:20260307\r\n\
+END:VEVENT\r\n\
+BEGIN:VTODO\r\n\
+SUMMARY:Not an event\r\n\
+END:VTODO\r\n\
+END:VCALENDAR\r\n";
+
+        let events = parse_events(ics);
+        assert_eq!(events.len(), 3);
+
+        assert_eq!(events[0].title, "Planning, Q3 roadmap");
+        assert_eq!(events[0].location.as_deref(), Some("Room 4; east wing"));
+        assert_eq!(events[0].start.to_rfc3339(), "2026-03-05T15:00:00+00:00");
+        assert_eq!(
+            events[0].end.map(|end| end.to_rfc3339()).as_deref(),
+            Some("2026-03-05T16:00:00+00:00")
+        );
+        assert!(!events[0].all_day);
+
+        assert_eq!(events[1].title, "Standup");
+        assert_eq!(events[1].start.to_rfc3339(), "2026-03-06T14:00:00+00:00");
+        assert_eq!(events[1].end, None);
+
+        assert_eq!(events[2].title, "(no title)");
+        assert!(events[2].all_day);
+    }
+
+    #[test]
+    fn test_write_event_round_trips() {
+        let event = NewEvent {
+            title: "Retro, with snacks; bring ideas".into(),
+            start: "2026-03-05T15:00:00Z".parse().unwrap(),
+            end: "2026-03-05T16:30:00Z".parse().unwrap(),
+            location: Some("A very long location name that keeps going well past the seventy-five octet line limit".into()),
+            description: Some("Line one\nLine two".into()),
+        };
+        let ics = write_event("abc", &event, "2026-03-01T00:00:00Z".parse().unwrap());
+
+        assert!(
+            ics.lines()
+                .all(|line| line.trim_end_matches('\r').len() <= 75)
+        );
+        assert!(ics.contains("SUMMARY:Retro\\, with snacks\\; bring ideas\r\n"));
+        assert!(ics.contains("DESCRIPTION:Line one\\nLine two\r\n"));
+
+        let parsed = parse_events(&ics);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].title, event.title);
+        assert_eq!(parsed[0].start, event.start);
+        assert_eq!(parsed[0].end, Some(event.end));
+        assert_eq!(parsed[0].location, event.location);
+    }
+
+    #[test]
+    fn test_calendar_url() {
+        let server = "https://dav.example.com/calendars/alice/";
+        assert_eq!(
+            calendar_url(server, "work").unwrap().as_str(),
+            "https://dav.example.com/calendars/alice/work/"
+        );
+        assert_eq!(
+            calendar_url(server, "https://other.example.com/cal/team/")
+                .unwrap()
+                .as_str(),
+            "https://other.example.com/cal/team/"
+        );
+    }
+}
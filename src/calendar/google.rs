@@ -0,0 +1,181 @@
+//! Google Calendar API v3, authenticated with an OAuth refresh token.
+
+use super::{CalendarError, CalendarEvent, NewEvent, status_error};
+use chrono::{DateTime, NaiveDate, TimeZone as _, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const API_URL: &str = "https://www.googleapis.com/calendar/v3";
+
+/// Events returned per listing; Google's default page is 250.
+const MAX_RESULTS: &str = "100";
+
+/// Access tokens by refresh token. Tools are built per turn, so the cache
+/// lives outside them.
+static ACCESS_TOKENS: LazyLock<tokio::sync::Mutex<HashMap<String, (String, DateTime<Utc>)>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventList {
+    #[serde(default)]
+    items: Vec<ApiEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiEvent {
+    summary: Option<String>,
+    start: ApiEventTime,
+    end: Option<ApiEventTime>,
+    location: Option<String>,
+    html_link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiEventTime {
+    date_time: Option<DateTime<chrono::FixedOffset>>,
+    date: Option<NaiveDate>,
+}
+
+impl ApiEventTime {
+    fn resolve(&self) -> Option<(DateTime<Utc>, bool)> {
+        if let Some(date_time) = self.date_time {
+            return Some((date_time.with_timezone(&Utc), false));
+        }
+        let date = self.date?;
+        let midnight = chrono::Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+            .earliest()?;
+        Some((midnight.with_timezone(&Utc), true))
+    }
+}
+
+/// A valid access token, refreshed when the cached one is about to expire.
+pub(super) async fn access_token(
+    http: &crate::egress::Client,
+    client_id: &str,
+    client_secret: &str,
+    refresh_token: &str,
+) -> Result<String, CalendarError> {
+    let mut tokens = ACCESS_TOKENS.lock().await;
+    if let Some((token, expires_at)) = tokens.get(refresh_token)
+        && *expires_at - chrono::Duration::minutes(5) > Utc::now()
+    {
+        return Ok(token.clone());
+    }
+
+    let response = http
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("refresh_token", refresh_token),
+            ("grant_type", "refresh_token"),
+        ])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+    let token: TokenResponse = response
+        .json()
+        .await
+        .map_err(|error| CalendarError::InvalidResponse(error.to_string()))?;
+
+    let expires_at = Utc::now() + chrono::Duration::seconds(token.expires_in);
+    tokens.insert(
+        refresh_token.to_string(),
+        (token.access_token.clone(), expires_at),
+    );
+    Ok(token.access_token)
+}
+
+pub(super) async fn list(
+    http: &crate::egress::Client,
+    token: &str,
+    calendar: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<CalendarEvent>, CalendarError> {
+    let response = http
+        .get(events_url(calendar)?)
+        .bearer_auth(token)
+        .query(&[
+            ("timeMin", from.to_rfc3339().as_str()),
+            ("timeMax", to.to_rfc3339().as_str()),
+            ("singleEvents", "true"),
+            ("orderBy", "startTime"),
+            ("maxResults", MAX_RESULTS),
+        ])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+    let list: EventList = response
+        .json()
+        .await
+        .map_err(|error| CalendarError::InvalidResponse(error.to_string()))?;
+
+    Ok(list
+        .items
+        .into_iter()
+        .filter_map(|event| {
+            let (start, all_day) = event.start.resolve()?;
+            Some(CalendarEvent {
+                title: event.summary.unwrap_or_else(|| "(no title)".into()),
+                start,
+                end: event.end.and_then(|end| end.resolve()).map(|(end, _)| end),
+                all_day,
+                location: event.location,
+                url: event.html_link,
+            })
+        })
+        .collect())
+}
+
+pub(super) async fn create(
+    http: &crate::egress::Client,
+    token: &str,
+    calendar: &str,
+    event: &NewEvent,
+) -> Result<Option<String>, CalendarError> {
+    let response = http
+        .post(events_url(calendar)?)
+        .bearer_auth(token)
+        .json(&serde_json::json!({
+            "summary": event.title,
+            "location": event.location,
+            "description": event.description,
+            "start": { "dateTime": event.start.to_rfc3339() },
+            "end": { "dateTime": event.end.to_rfc3339() },
+        }))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(status_error(response).await);
+    }
+    let created: ApiEvent = response
+        .json()
+        .await
+        .map_err(|error| CalendarError::InvalidResponse(error.to_string()))?;
+    Ok(created.html_link)
+}
+
+/// Calendar IDs are usually email addresses, so encode them as one segment.
+fn events_url(calendar: &str) -> Result<reqwest::Url, CalendarError> {
+    let mut url = reqwest::Url::parse(API_URL).expect("hardcoded Google Calendar URL");
+    url.path_segments_mut()
+        .map_err(|_| CalendarError::Request("invalid Google Calendar URL".into()))?
+        .extend(["calendars", calendar, "events"]);
+    Ok(url)
+}
@@ -0,0 +1,534 @@
+//! PNG bar and line charts, drawn without a plotting library.
+//!
+//! Charts are drawn at twice the configured size and scaled down, which
+//! smooths lines and text. Text uses a built-in bitmap font, so rendering
+//! needs no font files on the host.
+
+mod font;
+
+use crate::config::{ChartConfig, ChartTheme};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Drawing happens at this multiple of the output size.
+const SUPERSAMPLE: usize = 2;
+
+/// Most categories on the x axis.
+pub const MAX_LABELS: usize = 200;
+
+/// Most series in one chart.
+pub const MAX_SERIES: usize = 8;
+
+/// Longest x axis label, in characters.
+const MAX_LABEL_CHARS: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChartError {
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("failed to encode PNG: {0}")]
+    Encode(#[from] png::EncodingError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartKind {
+    /// Bars per category, side by side for several series.
+    Bar,
+    /// A line per series, for trends.
+    Line,
+}
+
+/// What to draw: one value per label in each series.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Categories along the x axis, e.g. months or regions.
+    pub labels: Vec<String>,
+    pub series: Vec<Series>,
+    /// What the values measure, shown above the y axis.
+    #[serde(default)]
+    pub y_label: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Series {
+    pub name: String,
+    /// One value per label. Nulls leave a gap.
+    pub values: Vec<Option<f64>>,
+}
+
+type Rgb = [u8; 3];
+
+struct Theme {
+    background: Rgb,
+    text: Rgb,
+    muted: Rgb,
+    grid: Rgb,
+    axis: Rgb,
+}
+
+const LIGHT: Theme = Theme {
+    background: [0xFF, 0xFF, 0xFF],
+    text: [0x22, 0x22, 0x22],
+    muted: [0x66, 0x66, 0x66],
+    grid: [0xE6, 0xE6, 0xE6],
+    axis: [0x99, 0x99, 0x99],
+};
+
+/// Close to the chat apps' dark backgrounds.
+const DARK: Theme = Theme {
+    background: [0x1E, 0x1F, 0x22],
+    text: [0xE6, 0xE6, 0xE6],
+    muted: [0xA0, 0xA0, 0xA0],
+    grid: [0x38, 0x3A, 0x40],
+    axis: [0x70, 0x70, 0x70],
+};
+
+/// Series colors, in order.
+const PALETTE: [Rgb; MAX_SERIES] = [
+    [0x4E, 0x79, 0xA7],
+    [0xF2, 0x8E, 0x2B],
+    [0xE1, 0x57, 0x59],
+    [0x76, 0xB7, 0xB2],
+    [0x59, 0xA1, 0x4F],
+    [0xED, 0xC9, 0x48],
+    [0xB0, 0x7A, 0xA1],
+    [0xFF, 0x9D, 0xA7],
+];
+
+impl ChartSpec {
+    fn validate(&self) -> Result<(), ChartError> {
+        let invalid = |message: String| Err(ChartError::Invalid(message));
+        if self.labels.is_empty() || self.labels.len() > MAX_LABELS {
+            return invalid(format!("give between 1 and {MAX_LABELS} labels"));
+        }
+        if self.series.is_empty() || self.series.len() > MAX_SERIES {
+            return invalid(format!("give between 1 and {MAX_SERIES} series"));
+        }
+        for series in &self.series {
+            if series.values.len() != self.labels.len() {
+                return invalid(format!(
+                    "series '{}' has {} values for {} labels",
+                    series.name,
+                    series.values.len(),
+                    self.labels.len()
+                ));
+            }
+            if series
+                .values
+                .iter()
+                .flatten()
+                .any(|value| !value.is_finite())
+            {
+                return invalid(format!("series '{}' has a non-finite value", series.name));
+            }
+        }
+        if self.values().next().is_none() {
+            return invalid("there are no values to plot".into());
+        }
+        Ok(())
+    }
+
+    fn values(&self) -> impl Iterator<Item = f64> + '_ {
+        self.series
+            .iter()
+            .flat_map(|series| series.values.iter().flatten().copied())
+    }
+}
+
+/// Render `spec` as a PNG sized and themed by `config`.
+pub fn render(spec: &ChartSpec, config: &ChartConfig) -> Result<Vec<u8>, ChartError> {
+    spec.validate()?;
+    let theme = match config.theme {
+        ChartTheme::Light => &LIGHT,
+        ChartTheme::Dark => &DARK,
+    };
+    let width = config.width as usize;
+    let height = config.height as usize;
+    let mut canvas = Canvas::new(width * SUPERSAMPLE, height * SUPERSAMPLE, theme.background);
+
+    // One font pixel, in canvas pixels.
+    let unit = SUPERSAMPLE
+        * match width {
+            0..500 => 1,
+            500..1200 => 2,
+            _ => 3,
+        };
+    let line_height = 11 * unit;
+    let char_width = font::ADVANCE * unit;
+    let pad = 8 * unit;
+
+    let mut top = pad;
+    if let Some(title) = &spec.title {
+        canvas.text(pad, top, title, theme.text, unit);
+        top += line_height + 4 * unit;
+    }
+    if spec.series.len() > 1 {
+        let mut x = pad;
+        for (series, color) in spec.series.iter().zip(PALETTE) {
+            let swatch = font::GLYPH_HEIGHT * unit;
+            canvas.fill_rect(x, top, swatch, swatch, color);
+            x += swatch + 3 * unit;
+            canvas.text(x, top, &series.name, theme.text, unit);
+            x += series.name.chars().count() * char_width + 10 * unit;
+        }
+        top += line_height + 2 * unit;
+    }
+    if let Some(y_label) = &spec.y_label {
+        canvas.text(pad, top, y_label, theme.muted, unit);
+        top += line_height;
+    }
+    top += 4 * unit;
+
+    let (low, high) = {
+        let mut low = spec.values().fold(f64::INFINITY, f64::min);
+        let mut high = spec.values().fold(f64::NEG_INFINITY, f64::max);
+        if spec.kind == ChartKind::Bar {
+            low = low.min(0.0);
+            high = high.max(0.0);
+        }
+        if low == high {
+            low -= 1.0;
+            high += 1.0;
+        }
+        (low, high)
+    };
+    let ticks = Ticks::new(low, high);
+    let tick_labels: Vec<String> = ticks.values().map(|value| ticks.format(value)).collect();
+    let tick_width = tick_labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or_default()
+        * char_width;
+
+    let left = pad + tick_width + 4 * unit;
+    let right = canvas.width - pad - 4 * unit;
+    let bottom = canvas.height - pad - line_height;
+    if right <= left + spec.labels.len() || bottom <= top + 8 * unit {
+        return Err(ChartError::Invalid(
+            "the chart is too small for this many labels".into(),
+        ));
+    }
+    let y_of = |value: f64| {
+        let fraction = (value - ticks.low) / (ticks.high - ticks.low);
+        bottom as f64 - fraction * (bottom - top) as f64
+    };
+
+    // Grid and y axis labels.
+    for (value, label) in ticks.values().zip(&tick_labels) {
+        let y = y_of(value).round() as usize;
+        canvas.fill_rect(
+            left,
+            y.saturating_sub(SUPERSAMPLE / 2),
+            right - left,
+            SUPERSAMPLE,
+            theme.grid,
+        );
+        let label_x = left - 4 * unit - label.chars().count() * char_width;
+        let label_y = y.saturating_sub(font::GLYPH_HEIGHT * unit / 2);
+        canvas.text(label_x, label_y, label, theme.muted, unit);
+    }
+    let baseline = y_of(0.0_f64.clamp(ticks.low, ticks.high)).round() as usize;
+    canvas.fill_rect(
+        left,
+        baseline.saturating_sub(SUPERSAMPLE / 2),
+        right - left,
+        SUPERSAMPLE,
+        theme.axis,
+    );
+
+    // X axis labels, skipping some when they don't fit.
+    let slot = (right - left) as f64 / spec.labels.len() as f64;
+    let label_chars = spec
+        .labels
+        .iter()
+        .map(|label| label.chars().count())
+        .max()
+        .unwrap_or_default()
+        .clamp(1, MAX_LABEL_CHARS);
+    let every = ((label_chars * char_width + 4 * unit) as f64 / slot)
+        .ceil()
+        .max(1.0) as usize;
+    for (index, label) in spec.labels.iter().enumerate().step_by(every) {
+        let label = truncate(label, label_chars);
+        let center = left as f64 + (index as f64 + 0.5) * slot;
+        let x = (center - (label.chars().count() * char_width) as f64 / 2.0).max(0.0);
+        canvas.text(x as usize, bottom + 4 * unit, &label, theme.muted, unit);
+    }
+
+    match spec.kind {
+        ChartKind::Bar => {
+            let group = slot * 0.8;
+            let bar_width = group / spec.series.len() as f64;
+            let gap = if spec.series.len() > 1 { unit } else { 0 };
+            for (series_index, (series, color)) in spec.series.iter().zip(PALETTE).enumerate() {
+                for (index, value) in series.values.iter().enumerate() {
+                    let Some(value) = value else { continue };
+                    let x = left as f64
+                        + index as f64 * slot
+                        + slot * 0.1
+                        + series_index as f64 * bar_width;
+                    let y = y_of(*value).round() as usize;
+                    let (y0, y1) = (y.min(baseline), y.max(baseline));
+                    let width = (bar_width as usize).saturating_sub(gap).max(1);
+                    canvas.fill_rect(x.round() as usize, y0, width, y1 - y0, color);
+                }
+            }
+        }
+        ChartKind::Line => {
+            let thickness = unit.max(SUPERSAMPLE * 2);
+            let markers = spec.labels.len() <= 40;
+            for (series, color) in spec.series.iter().zip(PALETTE) {
+                let points: Vec<Option<(f64, f64)>> = series
+                    .values
+                    .iter()
+                    .enumerate()
+                    .map(|(index, value)| {
+                        value.map(|value| (left as f64 + (index as f64 + 0.5) * slot, y_of(value)))
+                    })
+                    .collect();
+                for pair in points.windows(2) {
+                    if let [Some(a), Some(b)] = pair {
+                        canvas.line(*a, *b, thickness, color);
+                    }
+                }
+                if markers {
+                    for (x, y) in points.iter().flatten() {
+                        canvas.circle(*x, *y, thickness as f64 * 1.3, color);
+                    }
+                }
+            }
+        }
+    }
+
+    canvas.encode()
+}
+
+fn truncate(text: &str, chars: usize) -> String {
+    if text.chars().count() <= chars {
+        return text.to_string();
+    }
+    let mut text: String = text.chars().take(chars.saturating_sub(1)).collect();
+    text.push('.');
+    text
+}
+
+/// Evenly spaced y axis values at a round step.
+struct Ticks {
+    low: f64,
+    high: f64,
+    step: f64,
+}
+
+impl Ticks {
+    fn new(low: f64, high: f64) -> Self {
+        let rough = (high - low) / 5.0;
+        let magnitude = 10_f64.powf(rough.log10().floor());
+        let step = [1.0, 2.0, 2.5, 5.0, 10.0]
+            .into_iter()
+            .map(|factor| factor * magnitude)
+            .find(|step| *step >= rough)
+            .unwrap_or(10.0 * magnitude);
+        Ticks {
+            low: (low / step).floor() * step,
+            high: (high / step).ceil() * step,
+            step,
+        }
+    }
+
+    fn values(&self) -> impl Iterator<Item = f64> + '_ {
+        let count = ((self.high - self.low) / self.step).round() as usize;
+        (0..=count).map(|index| self.low + index as f64 * self.step)
+    }
+
+    /// `value` with as many decimals as the step needs, abbreviating
+    /// thousands and up.
+    fn format(&self, value: f64) -> String {
+        let largest = self.low.abs().max(self.high.abs());
+        let (divisor, suffix) = match largest {
+            1e9.. => (1e9, "B"),
+            1e6.. => (1e6, "M"),
+            1e4.. => (1e3, "k"),
+            _ => (1.0, ""),
+        };
+        let step = self.step / divisor;
+        let decimals = if step >= 1.0 {
+            0
+        } else {
+            (-step.log10().floor()) as usize
+                + usize::from(step * 10_f64.powf(-step.log10().floor()) % 1.0 > 1e-9)
+        };
+        let value = value / divisor;
+        // Avoid "-0".
+        let value = if value.abs() < step / 2.0 { 0.0 } else { value };
+        format!("{value:.decimals$}{suffix}")
+    }
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, background: Rgb) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![background; width * height],
+        }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for row in y.min(self.height)..(y + height).min(self.height) {
+            let start = row * self.width;
+            let span = start + x.min(self.width)..start + (x + width).min(self.width);
+            self.pixels[span].fill(color);
+        }
+    }
+
+    fn text(&mut self, x: usize, y: usize, text: &str, color: Rgb, unit: usize) {
+        for (index, c) in text.chars().enumerate() {
+            let glyph_x = x + index * font::ADVANCE * unit;
+            if glyph_x >= self.width {
+                break;
+            }
+            for (column, bits) in font::glyph(c).iter().enumerate() {
+                for row in 0..font::GLYPH_HEIGHT {
+                    if bits >> row & 1 == 1 {
+                        self.fill_rect(glyph_x + column * unit, y + row * unit, unit, unit, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// A line `thickness` pixels wide, drawn as squares along its length.
+    fn line(&mut self, from: (f64, f64), to: (f64, f64), thickness: usize, color: Rgb) {
+        let steps = (to.0 - from.0)
+            .abs()
+            .max((to.1 - from.1).abs())
+            .ceil()
+            .max(1.0) as usize;
+        let half = thickness as f64 / 2.0;
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = from.0 + (to.0 - from.0) * t - half;
+            let y = from.1 + (to.1 - from.1) * t - half;
+            self.fill_rect(
+                x.max(0.0).round() as usize,
+                y.max(0.0).round() as usize,
+                thickness,
+                thickness,
+                color,
+            );
+        }
+    }
+
+    fn circle(&mut self, x: f64, y: f64, radius: f64, color: Rgb) {
+        let reach = radius.ceil() as isize;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                if ((dx * dx + dy * dy) as f64) <= radius * radius {
+                    let (px, py) = (x as isize + dx, y as isize + dy);
+                    if px >= 0 && py >= 0 {
+                        self.fill_rect(px as usize, py as usize, 1, 1, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Scale down by [`SUPERSAMPLE`], averaging blocks, and encode as PNG.
+    fn encode(&self) -> Result<Vec<u8>, ChartError> {
+        let (width, height) = (self.width / SUPERSAMPLE, self.height / SUPERSAMPLE);
+        let mut data = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0usize; 3];
+                for dy in 0..SUPERSAMPLE {
+                    for dx in 0..SUPERSAMPLE {
+                        let pixel =
+                            self.pixels[(y * SUPERSAMPLE + dy) * self.width + x * SUPERSAMPLE + dx];
+                        for channel in 0..3 {
+                            sum[channel] += pixel[channel] as usize;
+                        }
+                    }
+                }
+                data.extend(sum.map(|total| (total / (SUPERSAMPLE * SUPERSAMPLE)) as u8));
+            }
+        }
+
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, width as u32, height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        writer.finish()?;
+        Ok(png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(kind: ChartKind) -> ChartSpec {
+        ChartSpec {
+            kind,
+            title: Some("Signups by month".into()),
+            labels: vec!["Jan".into(), "Feb".into(), "Mar".into()],
+            series: vec![
+                Series {
+                    name: "free".into(),
+                    values: vec![Some(120.0), Some(180.0), Some(240.0)],
+                },
+                Series {
+                    name: "pro".into(),
+                    values: vec![Some(10.0), None, Some(35.5)],
+                },
+            ],
+            y_label: Some("signups".into()),
+        }
+    }
+
+    #[test]
+    fn charts_render_as_png_at_the_configured_size() {
+        let config = ChartConfig {
+            width: 640,
+            height: 360,
+            theme: ChartTheme::Dark,
+        };
+        for kind in [ChartKind::Bar, ChartKind::Line] {
+            let png = render(&spec(kind), &config).unwrap();
+            let decoder = png::Decoder::new(std::io::Cursor::new(png));
+            let reader = decoder.read_info().unwrap();
+            let info = reader.info();
+            assert_eq!((info.width, info.height), (640, 360));
+        }
+
+        let mut mismatched = spec(ChartKind::Bar);
+        mismatched.series[1].values.pop();
+        assert!(render(&mismatched, &config).is_err());
+    }
+
+    #[test]
+    fn ticks_use_round_steps() {
+        let ticks = Ticks::new(0.0, 240.0);
+        assert_eq!(ticks.step, 50.0);
+        let labels: Vec<String> = ticks.values().map(|value| ticks.format(value)).collect();
+        assert_eq!(labels, ["0", "50", "100", "150", "200", "250"]);
+
+        let ticks = Ticks::new(0.0, 1.2);
+        assert_eq!(ticks.format(0.25), "0.25");
+        let ticks = Ticks::new(0.0, 1_800_000.0);
+        assert_eq!(ticks.format(1_500_000.0), "1.5M");
+    }
+}
@@ -0,0 +1,301 @@
+//! Remote config sources.
+//!
+//! With `[remote_config]` set, a background task polls an HTTP endpoint, a
+//! Consul KV key, or an etcd key for a complete `config.toml` document. When
+//! the document changes and validates, it's written over the local
+//! `config.toml`, keeping the local `[remote_config]` table so the instance
+//! can still find its source, and the file watcher applies it through the
+//! normal staged reload. A document that fails to fetch or validate is logged
+//! and left out; the running config stays as it is.
+
+use crate::config::Config;
+
+use anyhow::Context as _;
+use base64::Engine as _;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Where the config document lives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteBackend {
+    /// `GET` the document from `url`.
+    Http,
+    /// Consul KV: `GET {url}/v1/kv/{key}?raw`.
+    Consul,
+    /// etcd v3 JSON gateway: `POST {url}/v3/kv/range`.
+    Etcd,
+}
+
+impl std::str::FromStr for RemoteBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "http" => Ok(Self::Http),
+            "consul" => Ok(Self::Consul),
+            "etcd" => Ok(Self::Etcd),
+            other => Err(format!(
+                "unknown remote config backend {other:?}: must be \"http\", \"consul\", or \"etcd\""
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for RemoteBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Http => "http",
+            Self::Consul => "consul",
+            Self::Etcd => "etcd",
+        })
+    }
+}
+
+/// Remote config source settings (instance-level, under `[remote_config]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteConfig {
+    pub backend: RemoteBackend,
+    /// Document URL for `http`; agent or gateway base URL for `consul` and `etcd`.
+    pub url: String,
+    /// KV key holding the document. Required for `consul` and `etcd`.
+    pub key: Option<String>,
+    /// Sent as a bearer token (`http`), `X-Consul-Token` (`consul`), or
+    /// `Authorization` (`etcd`).
+    pub token: Option<String>,
+    pub poll_interval: Duration,
+}
+
+impl RemoteConfig {
+    /// Fetch the current document. `None` means an HTTP source answered
+    /// 304 Not Modified for `etag`.
+    async fn fetch(
+        &self,
+        client: &reqwest::Client,
+        etag: &mut Option<String>,
+    ) -> anyhow::Result<Option<String>> {
+        let base = self.url.trim_end_matches('/');
+        let key = self.key.as_deref().unwrap_or_default();
+        let request = match self.backend {
+            RemoteBackend::Http => {
+                let mut request = client.get(&self.url);
+                if let Some(token) = &self.token {
+                    request = request.bearer_auth(token);
+                }
+                if let Some(etag) = etag.as_deref() {
+                    request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+                }
+                request
+            }
+            RemoteBackend::Consul => {
+                let mut request =
+                    client.get(format!("{base}/v1/kv/{}?raw", key.trim_start_matches('/')));
+                if let Some(token) = &self.token {
+                    request = request.header("X-Consul-Token", token);
+                }
+                request
+            }
+            RemoteBackend::Etcd => {
+                let mut request =
+                    client
+                        .post(format!("{base}/v3/kv/range"))
+                        .json(&serde_json::json!({
+                            "key": base64::engine::general_purpose::STANDARD.encode(key),
+                        }));
+                if let Some(token) = &self.token {
+                    request = request.header(reqwest::header::AUTHORIZATION, token);
+                }
+                request
+            }
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|error| anyhow::anyhow!(crate::logging::redact_secrets(&error.to_string())))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        if status == reqwest::StatusCode::NOT_FOUND && self.backend == RemoteBackend::Consul {
+            anyhow::bail!("consul key {key:?} doesn't exist");
+        }
+        if !status.is_success() {
+            anyhow::bail!("{} source returned {status}", self.backend);
+        }
+
+        if self.backend == RemoteBackend::Http {
+            *etag = response
+                .headers()
+                .get(reqwest::header::ETAG)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+        }
+        let body = response
+            .text()
+            .await
+            .context("failed to read remote config body")?;
+
+        match self.backend {
+            RemoteBackend::Etcd => {
+                let range: serde_json::Value =
+                    serde_json::from_str(&body).context("etcd response is not valid JSON")?;
+                decode_etcd_range(&range, key).map(Some)
+            }
+            RemoteBackend::Http | RemoteBackend::Consul => Ok(Some(body)),
+        }
+    }
+}
+
+/// Pull the document out of an etcd `/v3/kv/range` response.
+fn decode_etcd_range(range: &serde_json::Value, key: &str) -> anyhow::Result<String> {
+    let Some(value) = range["kvs"][0]["value"].as_str() else {
+        anyhow::bail!("etcd key {key:?} doesn't exist");
+    };
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .context("etcd value is not valid base64")?;
+    String::from_utf8(bytes).context("etcd value is not valid UTF-8")
+}
+
+/// The document to write locally: `remote` with its `[remote_config]` table
+/// replaced by the one from `local`.
+fn merge_local_source(remote: &str, local: &str) -> anyhow::Result<String> {
+    let mut document: toml_edit::DocumentMut =
+        remote.parse().context("remote config is not valid TOML")?;
+    let local: toml_edit::DocumentMut = local
+        .parse()
+        .context("local config.toml is not valid TOML")?;
+
+    document.remove("remote_config");
+    if let Some(source) = local.get("remote_config") {
+        document.insert("remote_config", source.clone());
+    }
+    Ok(document.to_string())
+}
+
+/// Fetch once and write the document to `config_path` if it changed.
+/// Returns whether the file was written.
+async fn poll_once(
+    client: &reqwest::Client,
+    remote: &RemoteConfig,
+    config_path: &std::path::Path,
+    etag: &mut Option<String>,
+    last_document: &mut Option<String>,
+) -> anyhow::Result<bool> {
+    let Some(document) = remote.fetch(client, etag).await? else {
+        return Ok(false);
+    };
+    if last_document.as_deref() == Some(document.as_str()) {
+        return Ok(false);
+    }
+
+    let local = tokio::fs::read_to_string(config_path)
+        .await
+        .with_context(|| format!("failed to read {}", config_path.display()))?;
+    // Remember the document before validating so a bad one is reported once,
+    // not on every poll; a fixed document is picked up as soon as it changes.
+    *last_document = Some(document.clone());
+    let merged = merge_local_source(&document, &local)?;
+    Config::validate_toml(&merged).context("remote config is invalid")?;
+
+    if merged == local {
+        return Ok(false);
+    }
+    if let Err(error) = tokio::fs::write(config_path, &merged).await {
+        // Retry the write on the next poll.
+        *last_document = None;
+        return Err(error).with_context(|| format!("failed to write {}", config_path.display()));
+    }
+    Ok(true)
+}
+
+/// Spawn the background task that polls `remote` and writes changes to
+/// `config_path`, where the file watcher picks them up.
+pub fn spawn_remote_poller(config_path: PathBuf, remote: RemoteConfig) {
+    tokio::spawn(async move {
+        let client = match reqwest::Client::builder()
+            .user_agent(format!("spacebot/{}", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(15))
+            .build()
+        {
+            Ok(client) => client,
+            Err(error) => {
+                tracing::error!(%error, "failed to build remote config client");
+                return;
+            }
+        };
+
+        tracing::info!(
+            backend = %remote.backend,
+            url = %remote.url,
+            key = ?remote.key,
+            interval_secs = remote.poll_interval.as_secs(),
+            "polling remote config source"
+        );
+
+        let mut etag = None;
+        let mut last_document = None;
+        loop {
+            match poll_once(
+                &client,
+                &remote,
+                &config_path,
+                &mut etag,
+                &mut last_document,
+            )
+            .await
+            {
+                Ok(true) => tracing::info!(
+                    backend = %remote.backend,
+                    "remote config changed, wrote config.toml"
+                ),
+                Ok(false) => {}
+                Err(error) => tracing::warn!(
+                    backend = %remote.backend,
+                    error = format!("{error:#}"),
+                    "remote config not applied"
+                ),
+            }
+            tokio::time::sleep(remote.poll_interval).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn etcd_values_are_decoded() {
+        let encoded =
+            base64::engine::general_purpose::STANDARD.encode("[defaults]\nmax_turns = 4\n");
+        let range = serde_json::json!({ "kvs": [{ "key": "c3BhY2Vib3Q=", "value": encoded }] });
+        assert_eq!(
+            decode_etcd_range(&range, "spacebot").expect("value should decode"),
+            "[defaults]\nmax_turns = 4\n"
+        );
+
+        let empty = serde_json::json!({ "header": {}, "count": "0" });
+        assert!(decode_etcd_range(&empty, "spacebot").is_err());
+    }
+
+    #[test]
+    fn merge_keeps_the_local_source() {
+        let local = "[remote_config]\nbackend = \"consul\"\nurl = \"http://consul:8500\"\nkey = \"spacebot/config\"\n\n[defaults]\nmax_turns = 5\n";
+        let remote = "[defaults]\nmax_turns = 8\n\n[remote_config]\nurl = \"http://elsewhere\"\n";
+
+        let merged = merge_local_source(remote, local).expect("documents should merge");
+        let merged: toml::Value = toml::from_str(&merged).expect("merged document should parse");
+        assert_eq!(merged["defaults"]["max_turns"].as_integer(), Some(8));
+        assert_eq!(
+            merged["remote_config"]["url"].as_str(),
+            Some("http://consul:8500")
+        );
+        assert_eq!(
+            merged["remote_config"]["key"].as_str(),
+            Some("spacebot/config")
+        );
+
+        assert!(merge_local_source("not toml = [", local).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+//! Staged config: blue/green promotion for `config.toml`.
+//!
+//! Instead of editing the live `config.toml`, the next version is written to
+//! `config.staged.toml` next to it, by hand, with `spacebot config stage`, or
+//! through `PUT /api/config/staged`. Nothing reads the staged file until it's
+//! promoted, so it can be loaded and validated, and shadow-tested with the
+//! startup preflight checks, while the bot keeps running on the live config.
+//! Promoting copies the live file to `config.previous.toml` and writes the
+//! staged one over it in one write, which the file watcher applies as a single
+//! reload. Reverting swaps the live and previous files, so a revert can itself
+//! be reverted.
+
+use crate::config::{Config, TomlConfig, config_changes};
+
+use anyhow::Context as _;
+use std::path::{Path, PathBuf};
+
+/// The candidate config, next to `config.toml`.
+pub const STAGED_FILE: &str = "config.staged.toml";
+
+/// What the last promotion or revert replaced, next to `config.toml`.
+pub const PREVIOUS_FILE: &str = "config.previous.toml";
+
+const USAGE: &str = "Usage: !admin config [status|check|promote|revert|discard]";
+
+/// The live, staged, and previous config files of one instance.
+#[derive(Debug, Clone)]
+pub struct ConfigStage {
+    live: PathBuf,
+    staged: PathBuf,
+    previous: PathBuf,
+}
+
+/// What a shadow test of the staged config found.
+#[derive(Debug, Clone)]
+pub struct StageCheck {
+    /// Settings promoting would change, by name.
+    pub changes: Vec<String>,
+    pub failures: Vec<crate::preflight::Failure>,
+}
+
+impl ConfigStage {
+    /// The files next to `config_path`, the live `config.toml`.
+    pub fn new(config_path: &Path) -> Self {
+        let dir = config_path.parent().unwrap_or(Path::new("."));
+        Self {
+            live: config_path.to_path_buf(),
+            staged: dir.join(STAGED_FILE),
+            previous: dir.join(PREVIOUS_FILE),
+        }
+    }
+
+    fn instance_dir(&self) -> PathBuf {
+        self.live
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// Load `content` the way a reload of `config.toml` would.
+    fn load(&self, content: &str) -> anyhow::Result<Config> {
+        let toml_config: TomlConfig =
+            toml::from_str(content).context("failed to parse config TOML")?;
+        Ok(Config::from_toml(toml_config, self.instance_dir())?)
+    }
+
+    fn read_live(&self) -> anyhow::Result<String> {
+        std::fs::read_to_string(&self.live)
+            .with_context(|| format!("failed to read {}", self.live.display()))
+    }
+
+    /// Settings that differ between the live config and `config`. A live
+    /// config that no longer loads counts as changing everything.
+    fn changes_from_live(&self, config: &Config) -> Vec<String> {
+        match self.read_live().and_then(|live| self.load(&live)) {
+            Ok(live) => config_changes(&live, config),
+            Err(_) => vec!["config".to_string()],
+        }
+    }
+
+    /// Validate `content` and stage it. Returns the settings promoting it
+    /// would change.
+    pub fn stage(&self, content: &str) -> anyhow::Result<Vec<String>> {
+        let config = self.load(content).context("the config is invalid")?;
+        std::fs::write(&self.staged, content)
+            .with_context(|| format!("failed to write {}", self.staged.display()))?;
+        Ok(self.changes_from_live(&config))
+    }
+
+    /// The staged document, if there is one.
+    pub fn staged_content(&self) -> anyhow::Result<Option<String>> {
+        match std::fs::read_to_string(&self.staged) {
+            Ok(content) => Ok(Some(content)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => {
+                Err(error).with_context(|| format!("failed to read {}", self.staged.display()))
+            }
+        }
+    }
+
+    /// The staged document and the config it loads to.
+    fn load_staged(&self) -> anyhow::Result<(String, Config)> {
+        let content = self
+            .staged_content()?
+            .with_context(|| format!("nothing is staged in {STAGED_FILE}"))?;
+        let config = self
+            .load(&content)
+            .with_context(|| format!("{STAGED_FILE} is invalid"))?;
+        Ok((content, config))
+    }
+
+    /// Whether there's a previous config to revert to.
+    pub fn can_revert(&self) -> bool {
+        self.previous.is_file()
+    }
+
+    /// Validate the staged config. Returns the settings promoting it would
+    /// change.
+    pub fn diff(&self) -> anyhow::Result<Vec<String>> {
+        let (_, config) = self.load_staged()?;
+        Ok(self.changes_from_live(&config))
+    }
+
+    /// Validate the staged config and run the preflight checks against it:
+    /// its database, platform tokens, and LLM providers.
+    pub async fn check(&self) -> anyhow::Result<StageCheck> {
+        let (_, config) = self.load_staged()?;
+        Ok(StageCheck {
+            changes: self.changes_from_live(&config),
+            failures: crate::preflight::run(&config).await,
+        })
+    }
+
+    /// Make the staged config live, keeping the live one to revert to.
+    /// Returns the settings it changes.
+    pub fn promote(&self) -> anyhow::Result<Vec<String>> {
+        let (content, config) = self.load_staged()?;
+        let live = self.read_live()?;
+        if content == live {
+            anyhow::bail!("{STAGED_FILE} is the same as the live config");
+        }
+        let changes = self.changes_from_live(&config);
+        std::fs::write(&self.previous, &live)
+            .with_context(|| format!("failed to write {}", self.previous.display()))?;
+        std::fs::write(&self.live, &content)
+            .with_context(|| format!("failed to write {}", self.live.display()))?;
+        std::fs::remove_file(&self.staged)
+            .with_context(|| format!("failed to remove {}", self.staged.display()))?;
+        Ok(changes)
+    }
+
+    /// Swap the previous config back in. Returns the settings it changes.
+    pub fn revert(&self) -> anyhow::Result<Vec<String>> {
+        let previous = match std::fs::read_to_string(&self.previous) {
+            Ok(previous) => previous,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                anyhow::bail!("there's no {PREVIOUS_FILE} to revert to")
+            }
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("failed to read {}", self.previous.display()));
+            }
+        };
+        // Secrets it reads from the environment may have changed since.
+        let config = self
+            .load(&previous)
+            .with_context(|| format!("{PREVIOUS_FILE} no longer loads"))?;
+        let live = self.read_live()?;
+        let changes = self.changes_from_live(&config);
+        std::fs::write(&self.previous, &live)
+            .with_context(|| format!("failed to write {}", self.previous.display()))?;
+        std::fs::write(&self.live, &previous)
+            .with_context(|| format!("failed to write {}", self.live.display()))?;
+        Ok(changes)
+    }
+
+    /// Throw the staged config away. Returns whether there was one.
+    pub fn discard(&self) -> anyhow::Result<bool> {
+        match std::fs::remove_file(&self.staged) {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(error) => {
+                Err(error).with_context(|| format!("failed to remove {}", self.staged.display()))
+            }
+        }
+    }
+
+    /// What's staged and whether there's something to revert to.
+    pub fn status(&self) -> String {
+        let staged = match self.staged_content() {
+            Ok(None) => "Nothing is staged.".to_string(),
+            Ok(Some(_)) => match self.diff() {
+                Ok(changes) => format!("Staged config would change: {}.", changes_text(&changes)),
+                Err(error) => format!("Staged config can't be promoted: {error:#}."),
+            },
+            Err(error) => format!("Can't read the staged config: {error:#}."),
+        };
+        if self.can_revert() {
+            format!("{staged}\n{PREVIOUS_FILE} is there to revert to.")
+        } else {
+            staged
+        }
+    }
+}
+
+impl StageCheck {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn render(&self) -> String {
+        let changes = format!(
+            "Staged config would change: {}.",
+            changes_text(&self.changes)
+        );
+        if self.passed() {
+            format!("{changes}\nPreflight checks passed.")
+        } else {
+            format!("{changes}\n{}", crate::preflight::render(&self.failures))
+        }
+    }
+}
+
+/// "defaults.max_turns, egress", or "no settings" for a change only to
+/// comments or formatting.
+pub fn changes_text(changes: &[String]) -> String {
+    if changes.is_empty() {
+        "no settings".to_string()
+    } else {
+        changes.join(", ")
+    }
+}
+
+/// Run `!admin config [status|check|promote|revert|discard]` against the
+/// instance whose live config is `config_path`, and describe the result.
+pub async fn command(config_path: &Path, args: &str) -> String {
+    let stage = ConfigStage::new(config_path);
+    match args {
+        "" | "status" => stage.status(),
+        "check" => match stage.check().await {
+            Ok(check) => check.render(),
+            Err(error) => format!("Can't check the staged config: {error:#}"),
+        },
+        "promote" => match stage.promote() {
+            Ok(changes) => format!(
+                "Promoted the staged config, changing {}. It applies within a few seconds; \
+                 `!admin config revert` goes back.",
+                changes_text(&changes)
+            ),
+            Err(error) => format!("Can't promote: {error:#}"),
+        },
+        "revert" => match stage.revert() {
+            Ok(changes) => format!(
+                "Reverted to the previous config, changing {}. It applies within a few seconds.",
+                changes_text(&changes)
+            ),
+            Err(error) => format!("Can't revert: {error:#}"),
+        },
+        "discard" => match stage.discard() {
+            Ok(true) => "Discarded the staged config.".to_string(),
+            Ok(false) => "Nothing is staged.".to_string(),
+            Err(error) => format!("Can't discard: {error:#}"),
+        },
+        _ => USAGE.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn promote_and_revert_swap_the_live_config() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let live = dir.path().join("config.toml");
+        std::fs::write(&live, "[defaults]\nmax_turns = 5\n").unwrap();
+        let stage = ConfigStage::new(&live);
+        let read_live = || std::fs::read_to_string(&live).unwrap();
+
+        assert!(stage.promote().is_err());
+        assert!(stage.stage("[defaults]\nmax_turns = \"many\"\n").is_err());
+        assert_eq!(stage.staged_content().unwrap(), None);
+
+        let changes = stage.stage("[defaults]\nmax_turns = 8\n").unwrap();
+        assert_eq!(changes, ["defaults.max_turns"]);
+        assert_eq!(read_live(), "[defaults]\nmax_turns = 5\n");
+
+        assert_eq!(stage.promote().unwrap(), ["defaults.max_turns"]);
+        assert_eq!(read_live(), "[defaults]\nmax_turns = 8\n");
+        assert_eq!(stage.staged_content().unwrap(), None);
+        assert!(stage.can_revert());
+
+        stage.revert().unwrap();
+        assert_eq!(read_live(), "[defaults]\nmax_turns = 5\n");
+        stage.revert().unwrap();
+        assert_eq!(read_live(), "[defaults]\nmax_turns = 8\n");
+    }
+}
@@ -1,8 +1,14 @@
 //! Conversation history and context management.
 
+pub mod artifacts;
+pub mod batch;
 pub mod channels;
 pub mod context;
 pub mod history;
+pub mod search;
+pub mod stats;
+pub mod transcript;
 
+pub use batch::WriteBatch;
 pub use channels::ChannelStore;
 pub use history::{ConversationLogger, ProcessRunLogger, TimelineItem};
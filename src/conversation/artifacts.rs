@@ -0,0 +1,397 @@
+//! Pasted code and stack traces, kept out of the context window.
+//!
+//! A long fenced code block or stack trace in a user message is stored as a
+//! numbered artifact of its channel. The model sees the paste in full on the
+//! turn it arrives; afterwards the channel's history holds a one-line
+//! reference ("artifact #3") instead, and the `artifact_read` tool brings the
+//! content back for a single turn when the model needs it again. Artifacts
+//! live in the `code_artifacts` table, numbered per channel, and pasting the
+//! same text again reuses its number.
+
+use crate::db::{Column, SqlPool, with_pool};
+use crate::error::Result;
+
+use anyhow::Context as _;
+use regex::Regex;
+use rig::OneOrMany;
+use rig::message::{AssistantContent, Message, ToolResultContent, UserContent};
+use rig::tool::Tool as _;
+use serde::Serialize;
+use sha2::{Digest as _, Sha256};
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+/// First line of a trace: a Python traceback, a Rust panic or backtrace, a
+/// Go panic, or a Java/JS/Python exception line.
+static TRACE_HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(Traceback \(most recent call last\):|thread '.*' panicked at|stack backtrace:|panic: |goroutine \d+ \[|Caused by:|Exception in thread |([\w$]+\.)*[\w$]*(Error|Exception)(: |$))",
+    )
+    .expect("hardcoded regex")
+});
+
+/// One frame of a trace in any of the formats above.
+static TRACE_FRAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^\s+(at \S|File ".+", line \d+|\d+: \S|\S+\.(go|rs|py|js|ts|java|kt|rb|cs):\d+|\.\.\. \d+ more)"#,
+    )
+    .expect("hardcoded regex")
+});
+
+/// What kind of paste an artifact holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    Code,
+    StackTrace,
+}
+
+impl ArtifactKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Code => "code",
+            Self::StackTrace => "stack_trace",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "stack_trace" => Self::StackTrace,
+            _ => Self::Code,
+        }
+    }
+}
+
+/// A paste found in a message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paste {
+    /// Byte range in the message, fences included.
+    pub range: std::ops::Range<usize>,
+    pub kind: ArtifactKind,
+    /// The fence's info string, e.g. "rust".
+    pub language: Option<String>,
+    /// The paste without its fences.
+    pub content: String,
+}
+
+/// Find fenced code blocks and unfenced stack traces of at least
+/// `min_lines` lines, in order.
+pub fn detect(text: &str, min_lines: usize) -> Vec<Paste> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        lines.push((offset, line));
+        offset += line.len();
+    }
+
+    let mut pastes = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let (start, line) = lines[index];
+
+        if let Some(info) = line.trim_start().strip_prefix("```") {
+            let close = lines[index + 1..]
+                .iter()
+                .position(|(_, line)| line.trim() == "```");
+            if let Some(close) = close.map(|close| index + 1 + close) {
+                let body = &lines[index + 1..close];
+                if body.len() >= min_lines {
+                    let (end_offset, end_line) = lines[close];
+                    let content: String = body.iter().map(|(_, line)| *line).collect();
+                    pastes.push(Paste {
+                        range: start..end_offset + end_line.len(),
+                        kind: ArtifactKind::Code,
+                        language: Some(info.trim())
+                            .filter(|info| !info.is_empty())
+                            .map(str::to_string),
+                        content: content.trim_end_matches('\n').to_string(),
+                    });
+                }
+                index = close + 1;
+                continue;
+            }
+        }
+
+        if TRACE_HEADER.is_match(line.trim_end()) || TRACE_FRAME.is_match(line) {
+            // A trace runs while lines are frames, headers, or indented
+            // continuations (source excerpts, locals).
+            let end = lines[index + 1..]
+                .iter()
+                .position(|(_, line)| {
+                    !(TRACE_FRAME.is_match(line)
+                        || TRACE_HEADER.is_match(line.trim_end())
+                        || (line.starts_with([' ', '\t']) && !line.trim().is_empty()))
+                })
+                .map_or(lines.len(), |position| index + 1 + position);
+            let frames = lines[index..end]
+                .iter()
+                .filter(|(_, line)| TRACE_FRAME.is_match(line))
+                .count();
+            if end - index >= min_lines && frames >= 2 {
+                let (end_offset, end_line) = lines[end - 1];
+                let range = start..end_offset + end_line.len();
+                pastes.push(Paste {
+                    content: text[range.clone()].trim_end_matches('\n').to_string(),
+                    range,
+                    kind: ArtifactKind::StackTrace,
+                    language: None,
+                });
+            }
+            index = end.max(index + 1);
+            continue;
+        }
+
+        index += 1;
+    }
+    pastes
+}
+
+/// A stored paste.
+#[derive(Debug, Clone, Serialize)]
+pub struct CodeArtifact {
+    /// Number within its channel, from 1.
+    pub number: i64,
+    pub kind: ArtifactKind,
+    pub language: Option<String>,
+    pub lines: usize,
+    pub content: String,
+}
+
+/// Code artifact storage (SQLite or Postgres).
+#[derive(Debug, Clone)]
+pub struct CodeArtifactStore {
+    pool: SqlPool,
+}
+
+impl CodeArtifactStore {
+    pub fn new(pool: SqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a paste in `channel_id` and return it with its number. A paste
+    /// already stored there keeps the number it has.
+    pub async fn save(&self, channel_id: &str, paste: &Paste) -> Result<CodeArtifact> {
+        let hash = hex::encode(Sha256::digest(paste.content.as_bytes()));
+        let number: i64 = with_pool!(&self.pool, |pool| {
+            async {
+                // SQLite needs the WHERE to parse ON CONFLICT after a SELECT.
+                sqlx::query(
+                    "INSERT INTO code_artifacts \
+                     (channel_id, number, kind, language, content, content_hash) \
+                     SELECT $1, COALESCE(MAX(number), 0) + 1, $2, $3, $4, $5 \
+                     FROM code_artifacts WHERE channel_id = $1 \
+                     ON CONFLICT (channel_id, content_hash) DO NOTHING",
+                )
+                .bind(channel_id)
+                .bind(paste.kind.as_str())
+                .bind(&paste.language)
+                .bind(&paste.content)
+                .bind(&hash)
+                .execute(pool)
+                .await?;
+                sqlx::query_scalar(
+                    "SELECT number FROM code_artifacts \
+                     WHERE channel_id = $1 AND content_hash = $2",
+                )
+                .bind(channel_id)
+                .bind(&hash)
+                .fetch_one(pool)
+                .await
+            }
+            .await
+        })
+        .context("failed to save code artifact")?;
+
+        Ok(CodeArtifact {
+            number,
+            kind: paste.kind,
+            language: paste.language.clone(),
+            lines: paste.content.lines().count(),
+            content: paste.content.clone(),
+        })
+    }
+
+    /// Artifact `number` of `channel_id`, if there is one.
+    pub async fn get(&self, channel_id: &str, number: i64) -> Result<Option<CodeArtifact>> {
+        let artifact = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT number, kind, language, content FROM code_artifacts \
+                 WHERE channel_id = $1 AND number = $2",
+            )
+            .bind(channel_id)
+            .bind(number)
+            .fetch_optional(pool)
+            .await
+            .and_then(|row| row.as_ref().map(artifact_from_row).transpose())
+        })
+        .context("failed to load code artifact")?;
+
+        Ok(artifact)
+    }
+
+    /// Replace every paste in `text` with its reference, storing each one.
+    /// Returns `None` when the text has no pastes.
+    pub async fn compact(
+        &self,
+        channel_id: &str,
+        text: &str,
+        min_lines: usize,
+        prompt_engine: &crate::prompts::PromptEngine,
+    ) -> Result<Option<String>> {
+        let pastes = detect(text, min_lines);
+        if pastes.is_empty() {
+            return Ok(None);
+        }
+
+        let mut compacted = String::with_capacity(text.len() / 4);
+        let mut last = 0;
+        for paste in &pastes {
+            let artifact = self.save(channel_id, paste).await?;
+            compacted.push_str(&text[last..paste.range.start]);
+            compacted.push_str(&artifact.reference(prompt_engine)?);
+            compacted.push('\n');
+            last = paste.range.end;
+        }
+        compacted.push_str(&text[last..]);
+        Ok(Some(compacted))
+    }
+
+    /// Compact a finished turn's messages: pastes in user messages become
+    /// references, and so does what `artifact_read` returned, so content the
+    /// model read back only stays for the turn it asked for it. Returns how
+    /// many texts were replaced.
+    pub async fn compact_turn(
+        &self,
+        channel_id: &str,
+        messages: &mut [Message],
+        min_lines: usize,
+        prompt_engine: &crate::prompts::PromptEngine,
+    ) -> Result<usize> {
+        let read_calls: HashSet<String> = messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::Assistant { content, .. } => Some(content.iter()),
+                Message::User { .. } => None,
+            })
+            .flatten()
+            .filter_map(|item| match item {
+                AssistantContent::ToolCall(call)
+                    if call.function.name == crate::tools::ArtifactReadTool::NAME =>
+                {
+                    Some(call.id.clone())
+                }
+                _ => None,
+            })
+            .collect();
+
+        let mut replaced = 0;
+        for message in messages.iter_mut() {
+            let Message::User { content } = message else {
+                continue;
+            };
+            for item in content.iter_mut() {
+                match item {
+                    UserContent::Text(text) => {
+                        if let Some(compacted) = self
+                            .compact(channel_id, &text.text, min_lines, prompt_engine)
+                            .await?
+                        {
+                            text.text = compacted;
+                            replaced += 1;
+                        }
+                    }
+                    UserContent::ToolResult(result) if read_calls.contains(&result.id) => {
+                        let output = result.content.iter().find_map(|content| match content {
+                            ToolResultContent::Text(text) => {
+                                serde_json::from_str::<crate::tools::ArtifactReadOutput>(&text.text)
+                                    .ok()
+                            }
+                            _ => None,
+                        });
+                        if let Some(output) = output {
+                            let artifact = CodeArtifact {
+                                number: output.number,
+                                kind: ArtifactKind::parse(&output.kind),
+                                language: output.language,
+                                lines: output.lines,
+                                content: String::new(),
+                            };
+                            result.content = OneOrMany::one(ToolResultContent::text(
+                                artifact.reference(prompt_engine)?,
+                            ));
+                            replaced += 1;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(replaced)
+    }
+}
+
+impl CodeArtifact {
+    /// The line left in history in place of the content.
+    pub fn reference(&self, prompt_engine: &crate::prompts::PromptEngine) -> Result<String> {
+        prompt_engine.render_system_artifact_reference(
+            self.number,
+            self.kind.as_str(),
+            self.language.as_deref(),
+            self.lines,
+        )
+    }
+}
+
+fn artifact_from_row<R>(row: &R) -> std::result::Result<CodeArtifact, sqlx::Error>
+where
+    R: sqlx::Row,
+    for<'c> &'c str: sqlx::ColumnIndex<R>,
+    String: Column<R::Database>,
+    Option<String>: Column<R::Database>,
+    i64: Column<R::Database>,
+{
+    let kind: String = row.try_get("kind")?;
+    let content: String = row.try_get("content")?;
+    Ok(CodeArtifact {
+        number: row.try_get("number")?,
+        kind: ArtifactKind::parse(&kind),
+        language: row.try_get("language")?,
+        lines: content.lines().count(),
+        content,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_code_blocks_and_traces_are_detected() {
+        let code: String = (1..=5).map(|i| format!("let x{i} = {i};\n")).collect();
+        let text = format!(
+            "Why does this fail?\n```rust\n{code}```\nIt crashes with:\n\
+             Traceback (most recent call last):\n\
+             \x20 File \"app.py\", line 10, in <module>\n\
+             \x20   main()\n\
+             \x20 File \"app.py\", line 6, in main\n\
+             \x20   raise ValueError(\"bad\")\n\
+             ValueError: bad\n\
+             Thanks!\n\
+             ```\nshort\n```\n"
+        );
+
+        let pastes = detect(&text, 5);
+        assert_eq!(pastes.len(), 2);
+        assert_eq!(pastes[0].kind, ArtifactKind::Code);
+        assert_eq!(pastes[0].language.as_deref(), Some("rust"));
+        assert_eq!(pastes[0].content, code.trim_end());
+        assert!(text[pastes[0].range.clone()].ends_with("```\n"));
+
+        assert_eq!(pastes[1].kind, ArtifactKind::StackTrace);
+        assert!(pastes[1].content.starts_with("Traceback"));
+        assert!(pastes[1].content.ends_with("ValueError: bad"));
+
+        // Below the threshold, nothing is taken out.
+        assert!(detect(&text, 50).is_empty());
+    }
+}
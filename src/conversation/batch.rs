@@ -0,0 +1,241 @@
+//! Batched conversation writes.
+//!
+//! A chatty channel logs a user message, a turn record, and a reply for every
+//! exchange. [`WriteBatch`] holds those rows in memory and writes them in one
+//! transaction every `batch_flush_ms`, or as soon as `batch_max_rows` are
+//! waiting. [`WriteBatch::flush`] writes whatever is left at shutdown. Rows
+//! are stamped when they're logged, so batching doesn't reorder them.
+//!
+//! If a batch's transaction fails, its rows are retried one at a time, so one
+//! bad row doesn't lose the others.
+
+use crate::conversation::history::TurnUsage;
+use crate::db::{DatabaseConfig, SqlPool, with_pool};
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A row waiting to be written.
+#[derive(Debug, Clone)]
+pub(crate) enum PendingWrite {
+    Message {
+        id: String,
+        channel_id: String,
+        /// `user` or `assistant`.
+        role: &'static str,
+        sender_name: Option<String>,
+        sender_id: Option<String>,
+        content: String,
+        metadata: Option<String>,
+        created_at: chrono::DateTime<chrono::Utc>,
+    },
+    Turn {
+        id: String,
+        channel_id: String,
+        model: String,
+        tool_calls: String,
+        started_at: chrono::DateTime<chrono::Utc>,
+        usage: TurnUsage,
+    },
+}
+
+/// An agent's buffered conversation writes.
+#[derive(Debug)]
+pub struct WriteBatch {
+    pool: SqlPool,
+    max_rows: usize,
+    flush_interval: Duration,
+    pending: Mutex<Vec<PendingWrite>>,
+    full: tokio::sync::Notify,
+}
+
+impl WriteBatch {
+    pub fn new(pool: SqlPool, config: &DatabaseConfig) -> Arc<Self> {
+        Arc::new(Self {
+            pool,
+            max_rows: config.batch_max_rows,
+            flush_interval: config.batch_flush_interval,
+            pending: Mutex::new(Vec::new()),
+            full: tokio::sync::Notify::new(),
+        })
+    }
+
+    /// Queue `write` for the next flush.
+    pub(crate) fn push(&self, write: PendingWrite) {
+        let waiting = {
+            let mut pending = self.pending.lock().expect("write batch lock poisoned");
+            pending.push(write);
+            pending.len()
+        };
+        if waiting >= self.max_rows {
+            self.full.notify_one();
+        }
+    }
+
+    /// Flush on the interval, and whenever the batch fills up. Runs until
+    /// aborted; call [`Self::flush`] afterwards to write what's left.
+    pub fn spawn_flusher(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let batch = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(batch.flush_interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = batch.full.notified() => {}
+                }
+                batch.flush().await;
+            }
+        })
+    }
+
+    /// Write every queued row now.
+    pub async fn flush(&self) {
+        let writes = std::mem::take(&mut *self.pending.lock().expect("write batch lock poisoned"));
+        write_all(&self.pool, &writes).await;
+    }
+}
+
+/// Write `writes` in one transaction, falling back to one row at a time.
+pub(crate) async fn write_all(pool: &SqlPool, writes: &[PendingWrite]) {
+    if writes.is_empty() {
+        return;
+    }
+    let message_sql = format!(
+        "INSERT INTO conversation_messages \
+         (id, channel_id, role, sender_name, sender_id, content, metadata, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, {})",
+        pool.timestamp_placeholder(8)
+    );
+    let turn_sql = "INSERT INTO channel_turns (id, channel_id, model, tool_calls, created_at, \
+                    sender_id, input_tokens, output_tokens, cost_usd) \
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)";
+    let created_at: Vec<Option<String>> = writes
+        .iter()
+        .map(|write| match write {
+            PendingWrite::Message { created_at, .. } => Some(pool.timestamp_param(*created_at)),
+            PendingWrite::Turn { .. } => None,
+        })
+        .collect();
+
+    with_pool!(pool, |pool| {
+        let query = |index: usize| match &writes[index] {
+            PendingWrite::Message {
+                id,
+                channel_id,
+                role,
+                sender_name,
+                sender_id,
+                content,
+                metadata,
+                ..
+            } => sqlx::query(&message_sql)
+                .bind(id)
+                .bind(channel_id)
+                .bind(*role)
+                .bind(sender_name)
+                .bind(sender_id)
+                .bind(content)
+                .bind(metadata)
+                .bind(&created_at[index]),
+            PendingWrite::Turn {
+                id,
+                channel_id,
+                model,
+                tool_calls,
+                started_at,
+                usage,
+            } => sqlx::query(turn_sql)
+                .bind(id)
+                .bind(channel_id)
+                .bind(model)
+                .bind(tool_calls)
+                .bind(*started_at)
+                .bind(&usage.sender_id)
+                .bind(usage.input_tokens as i64)
+                .bind(usage.output_tokens as i64)
+                .bind(usage.cost_usd),
+        };
+
+        let batch = async {
+            let mut transaction = pool.begin().await?;
+            for index in 0..writes.len() {
+                query(index).execute(&mut *transaction).await?;
+            }
+            transaction.commit().await
+        }
+        .await;
+        let Err(error) = batch else {
+            return;
+        };
+        if writes.len() > 1 {
+            tracing::warn!(
+                %error,
+                rows = writes.len(),
+                "failed to write conversation batch, retrying row by row"
+            );
+            for index in 0..writes.len() {
+                if let Err(error) = query(index).execute(pool).await {
+                    tracing::warn!(%error, "failed to persist conversation row");
+                }
+            }
+        } else {
+            tracing::warn!(%error, "failed to persist conversation row");
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryStore;
+
+    fn message(id: &str, created_at: chrono::DateTime<chrono::Utc>) -> PendingWrite {
+        PendingWrite::Message {
+            id: id.into(),
+            channel_id: "discord:1".into(),
+            role: "user",
+            sender_name: Some("ana".into()),
+            sender_id: Some("42".into()),
+            content: format!("message {id}"),
+            metadata: None,
+            created_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn batches_flush_in_order_and_skip_bad_rows() {
+        let pool = MemoryStore::connect_in_memory().await.pool().clone();
+        let batch = WriteBatch::new(pool.clone(), &DatabaseConfig::default());
+        let start = chrono::Utc::now() - chrono::Duration::minutes(5);
+
+        batch.push(message("a", start));
+        batch.push(message("b", start + chrono::Duration::seconds(1)));
+        // Duplicate id: fails the batch's transaction, then only itself.
+        batch.push(message("a", start + chrono::Duration::seconds(2)));
+        batch.push(PendingWrite::Turn {
+            id: "t".into(),
+            channel_id: "discord:1".into(),
+            model: "anthropic/claude-sonnet-4".into(),
+            tool_calls: "[]".into(),
+            started_at: start,
+            usage: TurnUsage::default(),
+        });
+        batch.flush().await;
+
+        let logger = crate::conversation::ConversationLogger::new(pool);
+        let messages = logger
+            .load_recent(&"discord:1".into(), 10)
+            .await
+            .expect("messages should load");
+        let ids: Vec<&str> = messages.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!(messages[0].content, "message a");
+
+        let turns = logger
+            .load_turns("discord:1")
+            .await
+            .expect("turns should load");
+        assert_eq!(turns.len(), 1);
+    }
+}
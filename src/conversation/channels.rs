@@ -1,16 +1,18 @@
-//! Channel tracking and metadata (SQLite).
+//! Channel tracking and metadata (SQLite or Postgres).
 
-use sqlx::{Row as _, SqlitePool};
+use crate::db::{Column, SqlPool, with_pool};
+
+use sqlx::Row;
 use std::collections::HashMap;
 
-/// Tracks known channels in SQLite.
+/// Tracks known channels in the database.
 ///
 /// Handles upsert on channel open, activity timestamps, and channel lookups.
 /// All write methods are fire-and-forget — they spawn a tokio task and return
 /// immediately so the caller never blocks on a DB write.
 #[derive(Debug, Clone)]
 pub struct ChannelStore {
-    pool: SqlitePool,
+    pool: SqlPool,
 }
 
 /// A tracked channel with its metadata.
@@ -26,7 +28,7 @@ pub struct ChannelInfo {
 }
 
 impl ChannelStore {
-    pub fn new(pool: SqlitePool) -> Self {
+    pub fn new(pool: SqlPool) -> Self {
         Self { pool }
     }
 
@@ -43,21 +45,23 @@ impl ChannelStore {
         let platform_meta = extract_platform_meta(&platform, metadata);
 
         tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT INTO channels (id, platform, display_name, platform_meta, last_activity_at) \
-                 VALUES (?, ?, ?, ?, CURRENT_TIMESTAMP) \
-                 ON CONFLICT(id) DO UPDATE SET \
-                     display_name = COALESCE(excluded.display_name, channels.display_name), \
-                     platform_meta = COALESCE(excluded.platform_meta, channels.platform_meta), \
-                     last_activity_at = CURRENT_TIMESTAMP"
-            )
-            .bind(&channel_id)
-            .bind(&platform)
-            .bind(&display_name)
-            .bind(&platform_meta)
-            .execute(&pool)
-            .await
-            {
+            if let Err(error) = with_pool!(&pool, |pool| {
+                sqlx::query(
+                    "INSERT INTO channels (id, platform, display_name, platform_meta, last_activity_at) \
+                     VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP) \
+                     ON CONFLICT(id) DO UPDATE SET \
+                         display_name = COALESCE(excluded.display_name, channels.display_name), \
+                         platform_meta = COALESCE(excluded.platform_meta, channels.platform_meta), \
+                         last_activity_at = CURRENT_TIMESTAMP"
+                )
+                .bind(&channel_id)
+                .bind(&platform)
+                .bind(&display_name)
+                .bind(&platform_meta)
+                .execute(pool)
+                .await
+                .map(drop)
+            }) {
                 tracing::warn!(%error, %channel_id, "failed to upsert channel");
             }
         });
@@ -69,12 +73,15 @@ impl ChannelStore {
         let channel_id = channel_id.to_string();
 
         tokio::spawn(async move {
-            if let Err(error) =
-                sqlx::query("UPDATE channels SET last_activity_at = CURRENT_TIMESTAMP WHERE id = ?")
-                    .bind(&channel_id)
-                    .execute(&pool)
-                    .await
-            {
+            if let Err(error) = with_pool!(&pool, |pool| {
+                sqlx::query(
+                    "UPDATE channels SET last_activity_at = CURRENT_TIMESTAMP WHERE id = $1",
+                )
+                .bind(&channel_id)
+                .execute(pool)
+                .await
+                .map(drop)
+            }) {
                 tracing::warn!(%error, %channel_id, "failed to touch channel");
             }
         });
@@ -82,17 +89,20 @@ impl ChannelStore {
 
     /// List all active channels, most recently active first.
     pub async fn list_active(&self) -> crate::error::Result<Vec<ChannelInfo>> {
-        let rows = sqlx::query(
-            "SELECT id, platform, display_name, platform_meta, is_active, created_at, last_activity_at \
-             FROM channels \
-             WHERE is_active = 1 \
-             ORDER BY last_activity_at DESC"
-        )
-        .fetch_all(&self.pool)
-        .await
+        let channels = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT id, platform, display_name, platform_meta, is_active, created_at, last_activity_at \
+                 FROM channels \
+                 WHERE is_active = 1 \
+                 ORDER BY last_activity_at DESC"
+            )
+            .fetch_all(pool)
+            .await
+            .map(|rows| rows.into_iter().map(row_to_channel_info).collect())
+        })
         .map_err(|e| anyhow::anyhow!(e))?;
 
-        Ok(rows.into_iter().map(row_to_channel_info).collect())
+        Ok(channels)
     }
 
     /// Find a channel by partial name or ID match.
@@ -139,17 +149,20 @@ impl ChannelStore {
 
     /// Get a single channel by exact ID.
     pub async fn get(&self, channel_id: &str) -> crate::error::Result<Option<ChannelInfo>> {
-        let row = sqlx::query(
-            "SELECT id, platform, display_name, platform_meta, is_active, created_at, last_activity_at \
-             FROM channels \
-             WHERE id = ?"
-        )
-        .bind(channel_id)
-        .fetch_optional(&self.pool)
-        .await
+        let channel = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT id, platform, display_name, platform_meta, is_active, created_at, last_activity_at \
+                 FROM channels \
+                 WHERE id = $1"
+            )
+            .bind(channel_id)
+            .fetch_optional(pool)
+            .await
+            .map(|row| row.map(row_to_channel_info))
+        })
         .map_err(|e| anyhow::anyhow!(e))?;
 
-        Ok(row.map(row_to_channel_info))
+        Ok(channel)
     }
 
     /// Resolve a channel's display name by ID.
@@ -162,7 +175,14 @@ impl ChannelStore {
     }
 }
 
-fn row_to_channel_info(row: sqlx::sqlite::SqliteRow) -> ChannelInfo {
+fn row_to_channel_info<R>(row: R) -> ChannelInfo
+where
+    R: Row,
+    for<'c> &'c str: sqlx::ColumnIndex<R>,
+    String: Column<R::Database>,
+    i32: Column<R::Database>,
+    chrono::DateTime<chrono::Utc>: Column<R::Database>,
+{
     let platform_meta_str: Option<String> = row.try_get("platform_meta").ok().flatten();
     let platform_meta = platform_meta_str.and_then(|s| serde_json::from_str(&s).ok());
 
@@ -192,6 +212,15 @@ fn extract_platform(channel_id: &str) -> String {
         .to_string()
 }
 
+/// The display name an inbound message's metadata gives `channel_id`, as
+/// stored in the `channels` table.
+pub(crate) fn display_name_from(
+    channel_id: &str,
+    metadata: &HashMap<String, serde_json::Value>,
+) -> Option<String> {
+    extract_display_name(&extract_platform(channel_id), channel_id, metadata)
+}
+
 /// Pull the best display name from inbound message metadata.
 fn extract_display_name(
     platform: &str,
@@ -1,18 +1,28 @@
-//! Conversation message persistence (SQLite).
+//! Conversation message persistence (SQLite or Postgres).
 
+use crate::conversation::batch::{self, PendingWrite, WriteBatch};
+use crate::db::{DatabaseBackend, SqlPool, with_pool, with_read_pool};
 use crate::{BranchId, ChannelId, WorkerId};
 
-use serde::Serialize;
-use sqlx::{Row as _, SqlitePool};
+use serde::{Deserialize, Serialize};
+use sqlx::Row as _;
 use std::collections::HashMap;
+use std::sync::Arc;
 
-/// Persists conversation messages (user and assistant) to SQLite.
+/// Longest tool result kept in a channel turn record. Transcripts are for
+/// reading, and full results can run to megabytes.
+const TURN_TOOL_RESULT_BYTES: usize = 4_000;
+
+/// Persists conversation messages (user and assistant) to the database.
 ///
 /// All write methods are fire-and-forget — they spawn a tokio task and return
-/// immediately so the caller never blocks on a DB write.
+/// immediately so the caller never blocks on a DB write. A logger made
+/// [`batched`](Self::batched) queues them on the agent's [`WriteBatch`]
+/// instead, so they reach the database at its next flush.
 #[derive(Debug, Clone)]
 pub struct ConversationLogger {
-    pool: SqlitePool,
+    pool: SqlPool,
+    batch: Option<Arc<WriteBatch>>,
 }
 
 /// A persisted conversation message.
@@ -28,9 +38,93 @@ pub struct ConversationMessage {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
+/// One channel turn's metadata: the model it was routed to and its tool calls.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelTurn {
+    pub id: String,
+    pub model: String,
+    pub tool_calls: Vec<ToolCallRecord>,
+    pub created_at: Option<String>,
+}
+
+/// What a channel turn cost, and who it answered.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TurnUsage {
+    /// The sender the turn responded to; `None` for system re-triggers.
+    pub sender_id: Option<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// A tool call made during a channel turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub name: String,
+    pub arguments: serde_json::Value,
+    /// Truncated to a few kilobytes. `None` if the call never returned.
+    pub result: Option<String>,
+}
+
+/// Collect the tool calls, with their results, from a turn's new history.
+pub fn tool_calls_in(messages: &[rig::message::Message]) -> Vec<ToolCallRecord> {
+    let mut results = HashMap::new();
+    for message in messages {
+        if let rig::message::Message::User { content } = message {
+            for item in content.iter() {
+                if let rig::message::UserContent::ToolResult(tool_result) = item {
+                    let text: String = tool_result
+                        .content
+                        .iter()
+                        .filter_map(|content| match content {
+                            rig::message::ToolResultContent::Text(text) => Some(text.text.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    results.insert(tool_result.id.clone(), text);
+                }
+            }
+        }
+    }
+
+    let mut tool_calls = Vec::new();
+    for message in messages {
+        if let rig::message::Message::Assistant { content, .. } = message {
+            for item in content.iter() {
+                if let rig::message::AssistantContent::ToolCall(tool_call) = item {
+                    tool_calls.push(ToolCallRecord {
+                        name: tool_call.function.name.clone(),
+                        arguments: tool_call.function.arguments.clone(),
+                        result: results.get(&tool_call.id).map(|result| {
+                            crate::tools::truncate_output(result, TURN_TOOL_RESULT_BYTES)
+                        }),
+                    });
+                }
+            }
+        }
+    }
+    tool_calls
+}
+
 impl ConversationLogger {
-    pub fn new(pool: SqlitePool) -> Self {
-        Self { pool }
+    pub fn new(pool: SqlPool) -> Self {
+        Self { pool, batch: None }
+    }
+
+    /// Queue writes on `batch` instead of writing each one right away.
+    pub fn batched(mut self, batch: Arc<WriteBatch>) -> Self {
+        self.batch = Some(batch);
+        self
+    }
+
+    fn write(&self, write: PendingWrite) {
+        match &self.batch {
+            Some(batch) => batch.push(write),
+            None => {
+                let pool = self.pool.clone();
+                tokio::spawn(async move { batch::write_all(&pool, &[write]).await });
+            }
+        }
     }
 
     /// Log a user message. Fire-and-forget.
@@ -42,54 +136,80 @@ impl ConversationLogger {
         content: &str,
         metadata: &HashMap<String, serde_json::Value>,
     ) {
-        let pool = self.pool.clone();
-        let id = uuid::Uuid::new_v4().to_string();
-        let channel_id = channel_id.to_string();
-        let sender_name = sender_name.to_string();
-        let sender_id = sender_id.to_string();
-        let content = content.to_string();
-        let metadata_json = serde_json::to_string(metadata).ok();
-
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT INTO conversation_messages (id, channel_id, role, sender_name, sender_id, content, metadata) \
-                 VALUES (?, ?, 'user', ?, ?, ?, ?)"
-            )
-            .bind(&id)
-            .bind(&channel_id)
-            .bind(&sender_name)
-            .bind(&sender_id)
-            .bind(&content)
-            .bind(&metadata_json)
-            .execute(&pool)
-            .await
-            {
-                tracing::warn!(%error, "failed to persist user message");
-            }
+        self.write(PendingWrite::Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: channel_id.to_string(),
+            role: "user",
+            sender_name: Some(sender_name.to_string()),
+            sender_id: Some(sender_id.to_string()),
+            content: content.to_string(),
+            metadata: serde_json::to_string(metadata).ok(),
+            created_at: chrono::Utc::now(),
         });
     }
 
     /// Log a bot (assistant) message. Fire-and-forget.
     pub fn log_bot_message(&self, channel_id: &ChannelId, content: &str) {
-        let pool = self.pool.clone();
-        let id = uuid::Uuid::new_v4().to_string();
-        let channel_id = channel_id.to_string();
-        let content = content.to_string();
+        self.write(PendingWrite::Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: channel_id.to_string(),
+            role: "assistant",
+            sender_name: None,
+            sender_id: None,
+            content: content.to_string(),
+            metadata: None,
+            created_at: chrono::Utc::now(),
+        });
+    }
 
-        tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT INTO conversation_messages (id, channel_id, role, content) \
-                 VALUES (?, ?, 'assistant', ?)",
+    /// Record a channel turn's model, tool calls, and usage. Stamped with the
+    /// turn's start, so it sorts before the replies the turn sent.
+    /// Fire-and-forget.
+    pub fn log_turn(
+        &self,
+        channel_id: &ChannelId,
+        model: &str,
+        tool_calls: &[ToolCallRecord],
+        started_at: chrono::DateTime<chrono::Utc>,
+        usage: TurnUsage,
+    ) {
+        self.write(PendingWrite::Turn {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: channel_id.to_string(),
+            model: model.to_string(),
+            tool_calls: serde_json::to_string(tool_calls).unwrap_or_else(|_| "[]".into()),
+            started_at,
+            usage,
+        });
+    }
+
+    /// Load every turn record for a channel (oldest first).
+    pub async fn load_turns(&self, channel_id: &str) -> crate::error::Result<Vec<ChannelTurn>> {
+        let turns = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT id, model, tool_calls, created_at FROM channel_turns \
+                 WHERE channel_id = $1 ORDER BY created_at",
             )
-            .bind(&id)
-            .bind(&channel_id)
-            .bind(&content)
-            .execute(&pool)
+            .bind(channel_id)
+            .fetch_all(pool)
             .await
-            {
-                tracing::warn!(%error, "failed to persist bot message");
-            }
-        });
+            .and_then(|rows| {
+                rows.iter()
+                    .map(|row| {
+                        let tool_calls: String = row.try_get("tool_calls")?;
+                        Ok(ChannelTurn {
+                            id: row.try_get("id")?,
+                            model: row.try_get("model")?,
+                            tool_calls: serde_json::from_str(&tool_calls).unwrap_or_default(),
+                            created_at: crate::db::timestamp_text(row, "created_at")?,
+                        })
+                    })
+                    .collect::<std::result::Result<Vec<_>, sqlx::Error>>()
+            })
+        })
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(turns)
     }
 
     /// Load recent messages for a channel (oldest first).
@@ -98,34 +218,36 @@ impl ConversationLogger {
         channel_id: &ChannelId,
         limit: i64,
     ) -> crate::error::Result<Vec<ConversationMessage>> {
-        let rows = sqlx::query(
-            "SELECT id, channel_id, role, sender_name, sender_id, content, metadata, created_at \
-             FROM conversation_messages \
-             WHERE channel_id = ? \
-             ORDER BY created_at DESC \
-             LIMIT ?",
-        )
-        .bind(channel_id.as_ref())
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| anyhow::anyhow!(e))?;
-
-        let mut messages: Vec<ConversationMessage> = rows
-            .into_iter()
-            .map(|row| ConversationMessage {
-                id: row.try_get("id").unwrap_or_default(),
-                channel_id: row.try_get("channel_id").unwrap_or_default(),
-                role: row.try_get("role").unwrap_or_default(),
-                sender_name: row.try_get("sender_name").ok(),
-                sender_id: row.try_get("sender_id").ok(),
-                content: row.try_get("content").unwrap_or_default(),
-                metadata: row.try_get("metadata").ok(),
-                created_at: row
-                    .try_get("created_at")
-                    .unwrap_or_else(|_| chrono::Utc::now()),
+        let mut messages: Vec<ConversationMessage> = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT id, channel_id, role, sender_name, sender_id, content, metadata, created_at \
+                 FROM conversation_messages \
+                 WHERE channel_id = $1 \
+                 ORDER BY created_at DESC \
+                 LIMIT $2",
+            )
+            .bind(channel_id.as_ref())
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| ConversationMessage {
+                        id: row.try_get("id").unwrap_or_default(),
+                        channel_id: row.try_get("channel_id").unwrap_or_default(),
+                        role: row.try_get("role").unwrap_or_default(),
+                        sender_name: row.try_get("sender_name").ok(),
+                        sender_id: row.try_get("sender_id").ok(),
+                        content: row.try_get("content").unwrap_or_default(),
+                        metadata: row.try_get("metadata").ok(),
+                        created_at: row
+                            .try_get("created_at")
+                            .unwrap_or_else(|_| chrono::Utc::now()),
+                    })
+                    .collect()
             })
-            .collect();
+        })
+        .map_err(|e| anyhow::anyhow!(e))?;
 
         // Reverse to chronological order
         messages.reverse();
@@ -133,44 +255,172 @@ impl ConversationLogger {
         Ok(messages)
     }
 
-    /// Load recent messages from any channel (not just the current one).
-    pub async fn load_channel_transcript(
+    /// Load a channel's messages sent in `[start, end)` (oldest first).
+    pub async fn load_between(
         &self,
         channel_id: &str,
-        limit: i64,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
     ) -> crate::error::Result<Vec<ConversationMessage>> {
-        let rows = sqlx::query(
+        // SQLite compares the stored `YYYY-MM-DD HH:MM:SS` text directly;
+        // Postgres needs the bounds as timestamps.
+        let (window_clause, bound_format) = match self.pool.backend() {
+            DatabaseBackend::Sqlite => {
+                ("created_at >= $2 AND created_at < $3", "%Y-%m-%d %H:%M:%S")
+            }
+            DatabaseBackend::Postgres => (
+                "created_at >= CAST($2 AS TIMESTAMPTZ) AND created_at < CAST($3 AS TIMESTAMPTZ)",
+                "%Y-%m-%d %H:%M:%S+00:00",
+            ),
+        };
+        let query_str = format!(
             "SELECT id, channel_id, role, sender_name, sender_id, content, metadata, created_at \
              FROM conversation_messages \
-             WHERE channel_id = ? \
-             ORDER BY created_at DESC \
-             LIMIT ?",
-        )
-        .bind(channel_id)
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await
+             WHERE channel_id = $1 AND {window_clause} \
+             ORDER BY created_at"
+        );
+
+        let messages = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(&query_str)
+                .bind(channel_id)
+                .bind(start.format(bound_format).to_string())
+                .bind(end.format(bound_format).to_string())
+                .fetch_all(pool)
+                .await
+                .map(|rows| {
+                    rows.into_iter()
+                        .map(|row| ConversationMessage {
+                            id: row.try_get("id").unwrap_or_default(),
+                            channel_id: row.try_get("channel_id").unwrap_or_default(),
+                            role: row.try_get("role").unwrap_or_default(),
+                            sender_name: row.try_get("sender_name").ok(),
+                            sender_id: row.try_get("sender_id").ok(),
+                            content: row.try_get("content").unwrap_or_default(),
+                            metadata: row.try_get("metadata").ok(),
+                            created_at: row
+                                .try_get("created_at")
+                                .unwrap_or_else(|_| chrono::Utc::now()),
+                        })
+                        .collect()
+                })
+        })
         .map_err(|e| anyhow::anyhow!(e))?;
 
-        let mut messages: Vec<ConversationMessage> = rows
-            .into_iter()
-            .map(|row| ConversationMessage {
-                id: row.try_get("id").unwrap_or_default(),
-                channel_id: row.try_get("channel_id").unwrap_or_default(),
-                role: row.try_get("role").unwrap_or_default(),
-                sender_name: row.try_get("sender_name").ok(),
-                sender_id: row.try_get("sender_id").ok(),
-                content: row.try_get("content").unwrap_or_default(),
-                metadata: row.try_get("metadata").ok(),
-                created_at: row
-                    .try_get("created_at")
-                    .unwrap_or_else(|_| chrono::Utc::now()),
+        Ok(messages)
+    }
+
+    /// Load recent messages from any channel (not just the current one).
+    pub async fn load_channel_transcript(
+        &self,
+        channel_id: &str,
+        limit: i64,
+    ) -> crate::error::Result<Vec<ConversationMessage>> {
+        let mut messages: Vec<ConversationMessage> = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT id, channel_id, role, sender_name, sender_id, content, metadata, created_at \
+                 FROM conversation_messages \
+                 WHERE channel_id = $1 \
+                 ORDER BY created_at DESC \
+                 LIMIT $2",
+            )
+            .bind(channel_id)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| ConversationMessage {
+                        id: row.try_get("id").unwrap_or_default(),
+                        channel_id: row.try_get("channel_id").unwrap_or_default(),
+                        role: row.try_get("role").unwrap_or_default(),
+                        sender_name: row.try_get("sender_name").ok(),
+                        sender_id: row.try_get("sender_id").ok(),
+                        content: row.try_get("content").unwrap_or_default(),
+                        metadata: row.try_get("metadata").ok(),
+                        created_at: row
+                            .try_get("created_at")
+                            .unwrap_or_else(|_| chrono::Utc::now()),
+                    })
+                    .collect()
             })
-            .collect();
+        })
+        .map_err(|e| anyhow::anyhow!(e))?;
 
         messages.reverse();
         Ok(messages)
     }
+
+    /// Full-text search over a channel's messages, best match first. Each
+    /// message comes with a snippet around the matched words, marked `**`.
+    pub async fn search_text(
+        &self,
+        channel_id: &str,
+        query: &str,
+        limit: i64,
+    ) -> crate::error::Result<Vec<(ConversationMessage, String)>> {
+        // SQLite searches the FTS5 index with the query's words as prefix
+        // terms; Postgres parses the query itself with websearch syntax.
+        let (query_str, query) = match self.pool.backend() {
+            DatabaseBackend::Sqlite => {
+                let Some(terms) = crate::conversation::search::fts_terms(query) else {
+                    return Ok(Vec::new());
+                };
+                (
+                    "SELECT m.id, m.channel_id, m.role, m.sender_name, m.sender_id, m.content, \
+                     m.metadata, m.created_at, \
+                     snippet(conversation_messages_fts, 0, '**', '**', '…', 16) AS snippet \
+                     FROM conversation_messages_fts \
+                     JOIN conversation_messages m ON m.rowid = conversation_messages_fts.rowid \
+                     WHERE conversation_messages_fts MATCH $1 AND m.channel_id = $2 \
+                     ORDER BY bm25(conversation_messages_fts) \
+                     LIMIT $3",
+                    terms,
+                )
+            }
+            DatabaseBackend::Postgres => (
+                "SELECT id, channel_id, role, sender_name, sender_id, content, metadata, created_at, \
+                 ts_headline('simple', content, websearch_to_tsquery('simple', $1), \
+                 'StartSel=**, StopSel=**, MaxWords=24, MinWords=8') AS snippet \
+                 FROM conversation_messages \
+                 WHERE channel_id = $2 \
+                 AND to_tsvector('simple', content) @@ websearch_to_tsquery('simple', $1) \
+                 ORDER BY ts_rank(to_tsvector('simple', content), websearch_to_tsquery('simple', $1)) DESC \
+                 LIMIT $3",
+                query.to_string(),
+            ),
+        };
+
+        let hits = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(query_str)
+                .bind(&query)
+                .bind(channel_id)
+                .bind(limit)
+                .fetch_all(pool)
+                .await
+                .map(|rows| {
+                    rows.into_iter()
+                        .map(|row| {
+                            let message = ConversationMessage {
+                                id: row.try_get("id").unwrap_or_default(),
+                                channel_id: row.try_get("channel_id").unwrap_or_default(),
+                                role: row.try_get("role").unwrap_or_default(),
+                                sender_name: row.try_get("sender_name").ok(),
+                                sender_id: row.try_get("sender_id").ok(),
+                                content: row.try_get("content").unwrap_or_default(),
+                                metadata: row.try_get("metadata").ok(),
+                                created_at: row
+                                    .try_get("created_at")
+                                    .unwrap_or_else(|_| chrono::Utc::now()),
+                            };
+                            (message, row.try_get("snippet").unwrap_or_default())
+                        })
+                        .collect()
+                })
+        })
+        .map_err(|e| anyhow::anyhow!(e))?;
+
+        Ok(hits)
+    }
 }
 
 /// A unified timeline item combining messages, branch runs, and worker runs.
@@ -207,11 +457,11 @@ pub enum TimelineItem {
 /// All write methods are fire-and-forget, same pattern as ConversationLogger.
 #[derive(Debug, Clone)]
 pub struct ProcessRunLogger {
-    pool: SqlitePool,
+    pool: SqlPool,
 }
 
 impl ProcessRunLogger {
-    pub fn new(pool: SqlitePool) -> Self {
+    pub fn new(pool: SqlPool) -> Self {
         Self { pool }
     }
 
@@ -228,15 +478,18 @@ impl ProcessRunLogger {
         let description = description.to_string();
 
         tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT OR IGNORE INTO branch_runs (id, channel_id, description) VALUES (?, ?, ?)",
-            )
-            .bind(&id)
-            .bind(&channel_id)
-            .bind(&description)
-            .execute(&pool)
-            .await
-            {
+            if let Err(error) = with_pool!(&pool, |pool| {
+                sqlx::query(
+                    "INSERT INTO branch_runs (id, channel_id, description) VALUES ($1, $2, $3) \
+                     ON CONFLICT DO NOTHING",
+                )
+                .bind(&id)
+                .bind(&channel_id)
+                .bind(&description)
+                .execute(pool)
+                .await
+                .map(drop)
+            }) {
                 tracing::warn!(%error, branch_id = %id, "failed to persist branch start");
             }
         });
@@ -249,14 +502,16 @@ impl ProcessRunLogger {
         let conclusion = conclusion.to_string();
 
         tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "UPDATE branch_runs SET conclusion = ?, completed_at = CURRENT_TIMESTAMP WHERE id = ?"
-            )
-            .bind(&conclusion)
-            .bind(&id)
-            .execute(&pool)
-            .await
-            {
+            if let Err(error) = with_pool!(&pool, |pool| {
+                sqlx::query(
+                    "UPDATE branch_runs SET conclusion = $1, completed_at = CURRENT_TIMESTAMP WHERE id = $2"
+                )
+                .bind(&conclusion)
+                .bind(&id)
+                .execute(pool)
+                .await
+                .map(drop)
+            }) {
                 tracing::warn!(%error, branch_id = %id, "failed to persist branch completion");
             }
         });
@@ -275,15 +530,18 @@ impl ProcessRunLogger {
         let task = task.to_string();
 
         tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "INSERT OR IGNORE INTO worker_runs (id, channel_id, task) VALUES (?, ?, ?)",
-            )
-            .bind(&id)
-            .bind(&channel_id)
-            .bind(&task)
-            .execute(&pool)
-            .await
-            {
+            if let Err(error) = with_pool!(&pool, |pool| {
+                sqlx::query(
+                    "INSERT INTO worker_runs (id, channel_id, task) VALUES ($1, $2, $3) \
+                     ON CONFLICT DO NOTHING",
+                )
+                .bind(&id)
+                .bind(&channel_id)
+                .bind(&task)
+                .execute(pool)
+                .await
+                .map(drop)
+            }) {
                 tracing::warn!(%error, worker_id = %id, "failed to persist worker start");
             }
         });
@@ -296,12 +554,14 @@ impl ProcessRunLogger {
         let status = status.to_string();
 
         tokio::spawn(async move {
-            if let Err(error) = sqlx::query("UPDATE worker_runs SET status = ? WHERE id = ?")
-                .bind(&status)
-                .bind(&id)
-                .execute(&pool)
-                .await
-            {
+            if let Err(error) = with_pool!(&pool, |pool| {
+                sqlx::query("UPDATE worker_runs SET status = $1 WHERE id = $2")
+                    .bind(&status)
+                    .bind(&id)
+                    .execute(pool)
+                    .await
+                    .map(drop)
+            }) {
                 tracing::warn!(%error, worker_id = %id, "failed to persist worker status");
             }
         });
@@ -314,14 +574,16 @@ impl ProcessRunLogger {
         let result = result.to_string();
 
         tokio::spawn(async move {
-            if let Err(error) = sqlx::query(
-                "UPDATE worker_runs SET result = ?, status = 'done', completed_at = CURRENT_TIMESTAMP WHERE id = ?"
-            )
-            .bind(&result)
-            .bind(&id)
-            .execute(&pool)
-            .await
-            {
+            if let Err(error) = with_pool!(&pool, |pool| {
+                sqlx::query(
+                    "UPDATE worker_runs SET result = $1, status = 'done', completed_at = CURRENT_TIMESTAMP WHERE id = $2"
+                )
+                .bind(&result)
+                .bind(&id)
+                .execute(pool)
+                .await
+                .map(drop)
+            }) {
                 tracing::warn!(%error, worker_id = %id, "failed to persist worker completion");
             }
         });
@@ -338,10 +600,12 @@ impl ProcessRunLogger {
         limit: i64,
         before: Option<&str>,
     ) -> crate::error::Result<Vec<TimelineItem>> {
-        let before_clause = if before.is_some() {
-            "AND timestamp < ?3"
-        } else {
-            ""
+        // SQLite compares the stored text directly; Postgres needs the
+        // cursor as a timestamp.
+        let before_clause = match (before, self.pool.backend()) {
+            (None, _) => "",
+            (Some(_), DatabaseBackend::Sqlite) => "AND timestamp < $3",
+            (Some(_), DatabaseBackend::Postgres) => "AND timestamp < CAST($3 AS TIMESTAMPTZ)",
         };
 
         let query_str = format!(
@@ -349,78 +613,77 @@ impl ProcessRunLogger {
                 SELECT 'message' AS item_type, id, role, sender_name, sender_id, content, \
                        NULL AS description, NULL AS conclusion, NULL AS task, NULL AS result, NULL AS status, \
                        created_at AS timestamp, NULL AS completed_at \
-                FROM conversation_messages WHERE channel_id = ?1 \
+                FROM conversation_messages WHERE channel_id = $1 \
                 UNION ALL \
                 SELECT 'branch_run' AS item_type, id, NULL, NULL, NULL, NULL, \
                        description, conclusion, NULL, NULL, NULL, \
                        started_at AS timestamp, completed_at \
-                FROM branch_runs WHERE channel_id = ?1 \
+                FROM branch_runs WHERE channel_id = $1 \
                 UNION ALL \
                 SELECT 'worker_run' AS item_type, id, NULL, NULL, NULL, NULL, \
                        NULL, NULL, task, result, status, \
                        started_at AS timestamp, completed_at \
-                FROM worker_runs WHERE channel_id = ?1 \
-            ) WHERE 1=1 {before_clause} ORDER BY timestamp DESC LIMIT ?2"
+                FROM worker_runs WHERE channel_id = $1 \
+            ) AS timeline WHERE 1=1 {before_clause} ORDER BY timestamp DESC LIMIT $2"
         );
 
-        let mut query = sqlx::query(&query_str).bind(channel_id).bind(limit);
+        let mut items: Vec<TimelineItem> = with_read_pool!(&self.pool, |pool| {
+            let mut query = sqlx::query(&query_str).bind(channel_id).bind(limit);
 
-        if let Some(before_ts) = before {
-            query = query.bind(before_ts);
-        }
+            if let Some(before_ts) = before {
+                query = query.bind(before_ts);
+            }
 
-        let rows = query
-            .fetch_all(&self.pool)
-            .await
-            .map_err(|e| anyhow::anyhow!(e))?;
-
-        let mut items: Vec<TimelineItem> = rows
-            .into_iter()
-            .filter_map(|row| {
-                let item_type: String = row.try_get("item_type").ok()?;
-                match item_type.as_str() {
-                    "message" => Some(TimelineItem::Message {
-                        id: row.try_get("id").unwrap_or_default(),
-                        role: row.try_get("role").unwrap_or_default(),
-                        sender_name: row.try_get("sender_name").ok(),
-                        sender_id: row.try_get("sender_id").ok(),
-                        content: row.try_get("content").unwrap_or_default(),
-                        created_at: row
-                            .try_get::<chrono::DateTime<chrono::Utc>, _>("timestamp")
-                            .map(|t| t.to_rfc3339())
-                            .unwrap_or_default(),
-                    }),
-                    "branch_run" => Some(TimelineItem::BranchRun {
-                        id: row.try_get("id").unwrap_or_default(),
-                        description: row.try_get("description").unwrap_or_default(),
-                        conclusion: row.try_get("conclusion").ok(),
-                        started_at: row
-                            .try_get::<chrono::DateTime<chrono::Utc>, _>("timestamp")
-                            .map(|t| t.to_rfc3339())
-                            .unwrap_or_default(),
-                        completed_at: row
-                            .try_get::<chrono::DateTime<chrono::Utc>, _>("completed_at")
-                            .ok()
-                            .map(|t| t.to_rfc3339()),
-                    }),
-                    "worker_run" => Some(TimelineItem::WorkerRun {
-                        id: row.try_get("id").unwrap_or_default(),
-                        task: row.try_get("task").unwrap_or_default(),
-                        result: row.try_get("result").ok(),
-                        status: row.try_get("status").unwrap_or_default(),
-                        started_at: row
-                            .try_get::<chrono::DateTime<chrono::Utc>, _>("timestamp")
-                            .map(|t| t.to_rfc3339())
-                            .unwrap_or_default(),
-                        completed_at: row
-                            .try_get::<chrono::DateTime<chrono::Utc>, _>("completed_at")
-                            .ok()
-                            .map(|t| t.to_rfc3339()),
-                    }),
-                    _ => None,
-                }
+            query.fetch_all(pool).await.map(|rows| {
+                rows.into_iter()
+                    .filter_map(|row| {
+                        let item_type: String = row.try_get("item_type").ok()?;
+                        match item_type.as_str() {
+                            "message" => Some(TimelineItem::Message {
+                                id: row.try_get("id").unwrap_or_default(),
+                                role: row.try_get("role").unwrap_or_default(),
+                                sender_name: row.try_get("sender_name").ok(),
+                                sender_id: row.try_get("sender_id").ok(),
+                                content: row.try_get("content").unwrap_or_default(),
+                                created_at: row
+                                    .try_get::<chrono::DateTime<chrono::Utc>, _>("timestamp")
+                                    .map(|t| t.to_rfc3339())
+                                    .unwrap_or_default(),
+                            }),
+                            "branch_run" => Some(TimelineItem::BranchRun {
+                                id: row.try_get("id").unwrap_or_default(),
+                                description: row.try_get("description").unwrap_or_default(),
+                                conclusion: row.try_get("conclusion").ok(),
+                                started_at: row
+                                    .try_get::<chrono::DateTime<chrono::Utc>, _>("timestamp")
+                                    .map(|t| t.to_rfc3339())
+                                    .unwrap_or_default(),
+                                completed_at: row
+                                    .try_get::<chrono::DateTime<chrono::Utc>, _>("completed_at")
+                                    .ok()
+                                    .map(|t| t.to_rfc3339()),
+                            }),
+                            "worker_run" => Some(TimelineItem::WorkerRun {
+                                id: row.try_get("id").unwrap_or_default(),
+                                task: row.try_get("task").unwrap_or_default(),
+                                result: row.try_get("result").ok(),
+                                status: row.try_get("status").unwrap_or_default(),
+                                started_at: row
+                                    .try_get::<chrono::DateTime<chrono::Utc>, _>("timestamp")
+                                    .map(|t| t.to_rfc3339())
+                                    .unwrap_or_default(),
+                                completed_at: row
+                                    .try_get::<chrono::DateTime<chrono::Utc>, _>("completed_at")
+                                    .ok()
+                                    .map(|t| t.to_rfc3339()),
+                            }),
+                            _ => None,
+                        }
+                    })
+                    .collect()
             })
-            .collect();
+        })
+        .map_err(|e| anyhow::anyhow!(e))?;
 
         // Reverse to chronological order
         items.reverse();
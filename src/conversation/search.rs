@@ -0,0 +1,290 @@
+//! `!search`: find earlier messages in a channel.
+//!
+//! Combines two rankings. Full-text search (SQLite FTS5, or Postgres text
+//! search) finds messages with the query's words. Semantic search embeds the
+//! query and the channel's recent messages with the local embedding model
+//! and finds messages about the same thing in other words. The rankings are
+//! merged by reciprocal rank fusion, and each result links back to the
+//! original message where the platform has message permalinks.
+
+use crate::ChannelId;
+use crate::conversation::history::{ConversationLogger, ConversationMessage};
+use crate::memory::embedding::{EmbeddingModel, cosine_similarity};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Results shown for a search.
+pub const MAX_RESULTS: usize = 5;
+
+/// Recent messages semantic search compares against.
+const SEMANTIC_WINDOW: i64 = 500;
+
+/// Characters of each message embedded for semantic search.
+const EMBED_CHARS: usize = 1_000;
+
+/// Similarity below which a message isn't a semantic match.
+const MIN_SIMILARITY: f32 = 0.45;
+
+/// Characters of a message shown when there's no highlighted snippet.
+const PREVIEW_CHARS: usize = 160;
+
+/// Reciprocal rank fusion constant; damps the lead of top-ranked results.
+const RRF_K: f64 = 60.0;
+
+/// A message matching a search, with the text shown for it.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub message: ConversationMessage,
+    /// Matched words are marked `**`.
+    pub snippet: String,
+}
+
+/// The FTS5 query for a search: each word as a quoted prefix term, so
+/// punctuation can't break the query syntax and "deploy" finds "deploys".
+/// `None` when the query has no words.
+pub fn fts_terms(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Search a channel's history for `query`, best match first.
+pub async fn search(
+    conversation_logger: &ConversationLogger,
+    embedding_model: &Arc<EmbeddingModel>,
+    channel_id: &ChannelId,
+    query: &str,
+) -> crate::error::Result<Vec<SearchHit>> {
+    let text = conversation_logger
+        .search_text(channel_id, query, MAX_RESULTS as i64 * 2)
+        .await?
+        .into_iter()
+        .map(|(message, snippet)| SearchHit { message, snippet })
+        .collect();
+
+    let semantic = match semantic_hits(conversation_logger, embedding_model, channel_id, query)
+        .await
+    {
+        Ok(hits) => hits,
+        Err(error) => {
+            tracing::warn!(%error, %channel_id, "semantic search failed, using full-text results only");
+            Vec::new()
+        }
+    };
+
+    Ok(fuse(text, semantic, MAX_RESULTS))
+}
+
+/// Recent messages closest in meaning to `query`.
+async fn semantic_hits(
+    conversation_logger: &ConversationLogger,
+    embedding_model: &Arc<EmbeddingModel>,
+    channel_id: &ChannelId,
+    query: &str,
+) -> crate::error::Result<Vec<SearchHit>> {
+    let messages = conversation_logger
+        .load_recent(channel_id, SEMANTIC_WINDOW)
+        .await?;
+    if messages.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut texts = vec![query.to_string()];
+    texts.extend(
+        messages
+            .iter()
+            .map(|message| message.content.chars().take(EMBED_CHARS).collect()),
+    );
+    let mut embeddings = embedding_model.embed_many(texts).await?;
+    let query_embedding = embeddings.remove(0);
+
+    let mut scored: Vec<(f32, ConversationMessage)> = messages
+        .into_iter()
+        .zip(embeddings)
+        .map(|(message, embedding)| (cosine_similarity(&query_embedding, &embedding), message))
+        .filter(|(similarity, _)| *similarity >= MIN_SIMILARITY)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(MAX_RESULTS * 2);
+
+    Ok(scored
+        .into_iter()
+        .map(|(_, message)| SearchHit {
+            snippet: preview(&message.content),
+            message,
+        })
+        .collect())
+}
+
+/// Merge two rankings by reciprocal rank fusion: a message ranked well by
+/// either shows up, and one ranked well by both comes first. A message in
+/// both keeps its full-text snippet.
+fn fuse(text: Vec<SearchHit>, semantic: Vec<SearchHit>, limit: usize) -> Vec<SearchHit> {
+    let mut scored: Vec<(f64, SearchHit)> = Vec::new();
+    for ranking in [text, semantic] {
+        for (rank, hit) in ranking.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f64 + 1.0);
+            match scored
+                .iter_mut()
+                .find(|(_, existing)| existing.message.id == hit.message.id)
+            {
+                Some((total, _)) => *total += score,
+                None => scored.push((score, hit)),
+            }
+        }
+    }
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().take(limit).map(|(_, hit)| hit).collect()
+}
+
+/// The start of a message on one line.
+fn preview(content: &str) -> String {
+    let flat = content.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() <= PREVIEW_CHARS {
+        return flat;
+    }
+    let cut: String = flat.chars().take(PREVIEW_CHARS).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// A link that opens a message on its platform, built from the metadata its
+/// adapter stored. Discord messages always have one; Telegram only in
+/// supergroups and channels. Slack links need the workspace's domain, which
+/// the metadata doesn't carry.
+pub fn permalink(metadata: &str) -> Option<String> {
+    let metadata: HashMap<String, serde_json::Value> = serde_json::from_str(metadata).ok()?;
+    let field = |key: &str| {
+        metadata.get(key).map(|value| match value {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        })
+    };
+
+    if let (Some(channel), Some(message)) =
+        (field("discord_channel_id"), field("discord_message_id"))
+    {
+        let guild = field("discord_guild_id").unwrap_or_else(|| "@me".into());
+        return Some(format!(
+            "https://discord.com/channels/{guild}/{channel}/{message}"
+        ));
+    }
+    if let (Some(chat), Some(message)) = (field("telegram_chat_id"), field("telegram_message_id")) {
+        let chat = chat.strip_prefix("-100")?;
+        return Some(format!("https://t.me/c/{chat}/{message}"));
+    }
+    None
+}
+
+/// The `!search` reply for `platform`. Discord and Slack get bold matches and
+/// link text; other platforms get plain text and bare URLs.
+pub fn render(query: &str, hits: &[SearchHit], platform: &str) -> String {
+    if hits.is_empty() {
+        return format!("No messages in this conversation match \"{query}\".");
+    }
+
+    let markdown = matches!(platform, "discord" | "slack");
+    let mut lines = vec![format!("Messages matching \"{query}\":")];
+    for (index, hit) in hits.iter().enumerate() {
+        let sender = hit
+            .message
+            .sender_name
+            .as_deref()
+            .unwrap_or(&hit.message.role);
+        let snippet = if markdown {
+            hit.snippet.clone()
+        } else {
+            hit.snippet.replace("**", "")
+        };
+        let mut line = format!(
+            "{}. {sender}, {}: {snippet}",
+            index + 1,
+            hit.message.created_at.format("%Y-%m-%d %H:%M")
+        );
+        if let Some(link) = hit.message.metadata.as_deref().and_then(permalink) {
+            line.push_str(&match platform {
+                "discord" => format!(" [jump](<{link}>)"),
+                "slack" => format!(" [jump]({link})"),
+                _ => format!(" {link}"),
+            });
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(id: &str, content: &str, metadata: Option<&str>) -> SearchHit {
+        SearchHit {
+            message: ConversationMessage {
+                id: id.into(),
+                channel_id: "discord:1:2".into(),
+                role: "user".into(),
+                sender_name: Some("alice".into()),
+                sender_id: Some("42".into()),
+                content: content.into(),
+                metadata: metadata.map(Into::into),
+                created_at: "2026-10-01T14:02:00Z".parse().unwrap(),
+            },
+            snippet: content.into(),
+        }
+    }
+
+    #[test]
+    fn queries_become_prefix_terms() {
+        assert_eq!(
+            fts_terms("deploy \"schedule\"? (v2)").as_deref(),
+            Some("\"deploy\"* \"schedule\"* \"v2\"*")
+        );
+        assert_eq!(fts_terms(" ?! "), None);
+    }
+
+    #[test]
+    fn fusion_favours_messages_both_rankings_found() {
+        let fused = fuse(
+            vec![hit("a", "deploys", None), hit("b", "deploy day", None)],
+            vec![hit("c", "release schedule", None), hit("b", "", None)],
+            5,
+        );
+        let ids: Vec<&str> = fused.iter().map(|hit| hit.message.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "c"]);
+        assert_eq!(fused[0].snippet, "deploy day");
+    }
+
+    #[test]
+    fn results_link_to_their_messages() {
+        let discord =
+            r#"{"discord_guild_id": 1, "discord_channel_id": 2, "discord_message_id": 3}"#;
+        let telegram = r#"{"telegram_chat_id": -1001234, "telegram_message_id": 9}"#;
+        assert_eq!(
+            permalink(discord).as_deref(),
+            Some("https://discord.com/channels/1/2/3")
+        );
+        assert_eq!(
+            permalink(telegram).as_deref(),
+            Some("https://t.me/c/1234/9")
+        );
+        assert_eq!(
+            permalink(r#"{"telegram_chat_id": 55, "telegram_message_id": 9}"#),
+            None
+        );
+
+        let hits = vec![hit("a", "moved the **deploys**", Some(discord))];
+        assert_eq!(
+            render("deploys", &hits, "discord"),
+            "Messages matching \"deploys\":\n\
+             1. alice, 2026-10-01 14:02: moved the **deploys** [jump](<https://discord.com/channels/1/2/3>)"
+        );
+        assert_eq!(
+            render("deploys", &hits, "telegram"),
+            "Messages matching \"deploys\":\n\
+             1. alice, 2026-10-01 14:02: moved the deploys https://discord.com/channels/1/2/3"
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! `!stats`: who used the agent, and how much.
+//!
+//! Counts come from what's already persisted: user messages from
+//! `conversation_messages`, and each channel turn's tokens, cost, and model
+//! from `channel_turns`. A turn is credited to the sender it answered; when
+//! messages were coalesced into one turn, that's the last of them. Only the
+//! channel's own model calls are counted, not the branches and workers a
+//! turn starts.
+
+use crate::db::{DatabaseBackend, SqlPool, with_read_pool};
+use crate::error::Result;
+
+use anyhow::Context as _;
+use sqlx::Row as _;
+use std::collections::{BTreeMap, HashMap};
+
+/// Rows shown in each leaderboard.
+pub const TOP: usize = 5;
+
+/// The period `!stats` covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsWindow {
+    Day,
+    Week,
+    Month,
+    All,
+}
+
+impl StatsWindow {
+    /// Parse the `!stats` argument; no argument is a week.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "day" | "today" => Some(Self::Day),
+            "" | "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            "all" => Some(Self::All),
+            _ => None,
+        }
+    }
+
+    /// Start of the window ending at `now`.
+    pub fn since(self, now: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc> {
+        match self {
+            Self::Day => now - chrono::Duration::days(1),
+            Self::Week => now - chrono::Duration::days(7),
+            Self::Month => now - chrono::Duration::days(30),
+            Self::All => chrono::DateTime::UNIX_EPOCH,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Day => "last 24 hours",
+            Self::Week => "last 7 days",
+            Self::Month => "last 30 days",
+            Self::All => "all time",
+        }
+    }
+}
+
+/// Usage by one sender or channel.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageTotals {
+    /// Sender ID or channel ID.
+    pub key: String,
+    /// Display name, when one was recorded.
+    pub name: Option<String>,
+    pub messages: i64,
+    pub turns: i64,
+    pub tokens: i64,
+    pub cost_usd: f64,
+    /// Turns per model.
+    pub models: BTreeMap<String, i64>,
+}
+
+impl UsageTotals {
+    /// The model that ran the most turns.
+    pub fn favorite_model(&self) -> Option<&str> {
+        self.models
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(model, _)| model.as_str())
+    }
+
+    fn add_turns(&mut self, model: String, turns: i64, tokens: i64, cost_usd: f64) {
+        self.turns += turns;
+        self.tokens += tokens;
+        self.cost_usd += cost_usd;
+        *self.models.entry(model).or_default() += turns;
+    }
+}
+
+/// What a leaderboard ranks.
+#[derive(Debug, Clone, Copy)]
+enum Grouping<'a> {
+    /// Senders in one channel.
+    Senders(&'a str),
+    /// Every channel.
+    Channels,
+}
+
+/// Usage queries over persisted conversations.
+#[derive(Debug, Clone)]
+pub struct UsageStats {
+    pool: SqlPool,
+}
+
+impl UsageStats {
+    pub fn new(pool: SqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Usage per sender in `channel_id` since `since`, heaviest first.
+    pub async fn by_sender(
+        &self,
+        channel_id: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<UsageTotals>> {
+        self.tally(Grouping::Senders(channel_id), since).await
+    }
+
+    /// Usage per channel since `since`, heaviest first.
+    pub async fn by_channel(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<UsageTotals>> {
+        self.tally(Grouping::Channels, since).await
+    }
+
+    async fn tally(
+        &self,
+        grouping: Grouping<'_>,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<UsageTotals>> {
+        let backend = self.pool.backend();
+        let (message_sql, turn_sql, channel_id) = match grouping {
+            Grouping::Senders(channel_id) => (
+                format!(
+                    "SELECT sender_id AS group_key, MAX(sender_name) AS name, COUNT(*) AS messages \
+                     FROM conversation_messages \
+                     WHERE role = 'user' AND sender_id IS NOT NULL AND channel_id = $1 AND {} \
+                     GROUP BY sender_id",
+                    since_clause(backend, "created_at", "$2")
+                ),
+                format!(
+                    "SELECT sender_id AS group_key, model, COUNT(*) AS turns, \
+                     CAST(SUM(input_tokens + output_tokens) AS BIGINT) AS tokens, \
+                     SUM(cost_usd) AS cost_usd \
+                     FROM channel_turns \
+                     WHERE sender_id IS NOT NULL AND channel_id = $1 AND {} \
+                     GROUP BY sender_id, model",
+                    since_clause(backend, "created_at", "$2")
+                ),
+                Some(channel_id),
+            ),
+            Grouping::Channels => (
+                format!(
+                    "SELECT m.channel_id AS group_key, MAX(c.display_name) AS name, COUNT(*) AS messages \
+                     FROM conversation_messages m \
+                     LEFT JOIN channels c ON c.id = m.channel_id \
+                     WHERE m.role = 'user' AND {} \
+                     GROUP BY m.channel_id",
+                    since_clause(backend, "m.created_at", "$1")
+                ),
+                format!(
+                    "SELECT channel_id AS group_key, model, COUNT(*) AS turns, \
+                     CAST(SUM(input_tokens + output_tokens) AS BIGINT) AS tokens, \
+                     SUM(cost_usd) AS cost_usd \
+                     FROM channel_turns \
+                     WHERE {} \
+                     GROUP BY channel_id, model",
+                    since_clause(backend, "created_at", "$1")
+                ),
+                None,
+            ),
+        };
+        let since = self.pool.timestamp_param(since);
+
+        let mut totals: HashMap<String, UsageTotals> = HashMap::new();
+        let message_rows: Vec<(String, Option<String>, i64)> =
+            with_read_pool!(&self.pool, |pool| {
+                let mut query = sqlx::query(&message_sql);
+                if let Some(channel_id) = channel_id {
+                    query = query.bind(channel_id);
+                }
+                query.bind(&since).fetch_all(pool).await.and_then(|rows| {
+                    rows.iter()
+                        .map(|row| {
+                            Ok((
+                                row.try_get("group_key")?,
+                                row.try_get("name")?,
+                                row.try_get("messages")?,
+                            ))
+                        })
+                        .collect()
+                })
+            })
+            .context("failed to count messages")?;
+        for (key, name, messages) in message_rows {
+            let entry = totals.entry(key.clone()).or_default();
+            entry.key = key;
+            entry.name = name;
+            entry.messages = messages;
+        }
+
+        let turn_rows: Vec<(String, String, i64, i64, f64)> = with_read_pool!(&self.pool, |pool| {
+            let mut query = sqlx::query(&turn_sql);
+            if let Some(channel_id) = channel_id {
+                query = query.bind(channel_id);
+            }
+            query.bind(&since).fetch_all(pool).await.and_then(|rows| {
+                rows.iter()
+                    .map(|row| {
+                        Ok((
+                            row.try_get("group_key")?,
+                            row.try_get("model")?,
+                            row.try_get("turns")?,
+                            row.try_get("tokens")?,
+                            row.try_get("cost_usd")?,
+                        ))
+                    })
+                    .collect()
+            })
+        })
+        .context("failed to count turns")?;
+        for (key, model, turns, tokens, cost_usd) in turn_rows {
+            let entry = totals.entry(key.clone()).or_default();
+            entry.key = key;
+            entry.add_turns(model, turns, tokens, cost_usd);
+        }
+
+        Ok(rank(totals.into_values().collect()))
+    }
+}
+
+/// A lower bound on a timestamp column. SQLite rows hold timestamps in more
+/// than one text format, so both sides are normalized with `datetime()`.
+fn since_clause(backend: DatabaseBackend, column: &str, param: &str) -> String {
+    match backend {
+        DatabaseBackend::Sqlite => format!("datetime({column}) >= datetime({param})"),
+        DatabaseBackend::Postgres => format!("{column} >= CAST({param} AS TIMESTAMPTZ)"),
+    }
+}
+
+/// Heaviest first: by tokens, then messages.
+fn rank(mut totals: Vec<UsageTotals>) -> Vec<UsageTotals> {
+    totals.sort_by(|a, b| {
+        b.tokens
+            .cmp(&a.tokens)
+            .then(b.messages.cmp(&a.messages))
+            .then(a.key.cmp(&b.key))
+    });
+    totals
+}
+
+/// A token count in a few characters, e.g. "950", "12.3k", "1.2M".
+fn compact(count: i64) -> String {
+    if count < 1_000 {
+        count.to_string()
+    } else if count < 1_000_000 {
+        format!("{:.1}k", count as f64 / 1_000.0)
+    } else {
+        format!("{:.1}M", count as f64 / 1_000_000.0)
+    }
+}
+
+fn summary(totals: &UsageTotals) -> String {
+    let cost = if totals.cost_usd > 0.0 && totals.cost_usd < 0.005 {
+        "<$0.01".to_string()
+    } else {
+        format!("${:.2}", totals.cost_usd)
+    };
+    let mut summary = format!(
+        "{} messages, {} turns, {} tokens, {cost}",
+        totals.messages,
+        totals.turns,
+        compact(totals.tokens)
+    );
+    if let Some(model) = totals.favorite_model() {
+        summary.push_str(&format!(", mostly {model}"));
+    }
+    summary
+}
+
+/// The `!stats` reply: the channel's totals and top senders, plus the
+/// busiest channels when `channels` is given.
+pub fn render(
+    window: StatsWindow,
+    channel: Option<&UsageTotals>,
+    senders: &[UsageTotals],
+    channels: Option<&[UsageTotals]>,
+) -> String {
+    let mut lines = vec![format!(
+        "Usage in this conversation, {}: {}",
+        window.label(),
+        channel.map(summary).as_deref().unwrap_or("nothing yet")
+    )];
+
+    if !senders.is_empty() {
+        lines.push(String::new());
+        lines.push("Top users:".into());
+        for (index, sender) in senders.iter().take(TOP).enumerate() {
+            let name = sender.name.as_deref().unwrap_or(&sender.key);
+            lines.push(format!("{}. {name}: {}", index + 1, summary(sender)));
+        }
+    }
+
+    if let Some(channels) = channels.filter(|channels| !channels.is_empty()) {
+        lines.push(String::new());
+        lines.push("Busiest conversations:".into());
+        for (index, channel) in channels.iter().take(TOP).enumerate() {
+            let name = match &channel.name {
+                Some(name) => format!("{name} ({})", channel.key),
+                None => channel.key.clone(),
+            };
+            lines.push(format!("{}. {name}: {}", index + 1, summary(channel)));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totals(key: &str, messages: i64, turns: &[(&str, i64, i64, f64)]) -> UsageTotals {
+        let mut totals = UsageTotals {
+            key: key.into(),
+            messages,
+            ..Default::default()
+        };
+        for (model, count, tokens, cost_usd) in turns {
+            totals.add_turns(model.to_string(), *count, *tokens, *cost_usd);
+        }
+        totals
+    }
+
+    #[test]
+    fn leaderboards_rank_by_tokens_and_name_the_favorite_model() {
+        let mut alice = totals(
+            "1",
+            12,
+            &[
+                ("openai/gpt-4.1", 3, 9_000, 0.02),
+                ("anthropic/claude-sonnet-4", 8, 1_250_000, 1.5),
+            ],
+        );
+        alice.name = Some("alice".into());
+        let bob = totals("2", 40, &[("openai/gpt-4.1", 2, 800, 0.001)]);
+        let carol = totals("3", 5, &[]);
+        let senders = rank(vec![carol, bob, alice.clone()]);
+        assert_eq!(
+            senders.iter().map(|s| s.key.as_str()).collect::<Vec<_>>(),
+            vec!["1", "2", "3"]
+        );
+
+        let channel = totals(
+            "discord:1:2",
+            57,
+            &[("anthropic/claude-sonnet-4", 13, 1_259_800, 1.521)],
+        );
+        assert_eq!(
+            render(StatsWindow::Week, Some(&channel), &senders, None),
+            "Usage in this conversation, last 7 days: 57 messages, 13 turns, 1.3M tokens, $1.52, mostly anthropic/claude-sonnet-4\n\
+             \n\
+             Top users:\n\
+             1. alice: 12 messages, 11 turns, 1.3M tokens, $1.52, mostly anthropic/claude-sonnet-4\n\
+             2. 2: 40 messages, 2 turns, 800 tokens, <$0.01, mostly openai/gpt-4.1\n\
+             3. 3: 5 messages, 0 turns, 0 tokens, $0.00"
+        );
+        assert_eq!(
+            render(StatsWindow::All, None, &[], Some(&[])),
+            "Usage in this conversation, all time: nothing yet"
+        );
+        assert_eq!(StatsWindow::parse(""), Some(StatsWindow::Week));
+        assert_eq!(StatsWindow::parse("year"), None);
+    }
+}
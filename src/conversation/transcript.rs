@@ -0,0 +1,599 @@
+//! Channel transcripts rendered to Markdown or standalone HTML, for sharing
+//! or archiving a conversation.
+//!
+//! A transcript merges the channel timeline (messages, branch runs, worker
+//! runs) with its turn records, so each turn shows the model it was routed to
+//! and the tool calls it made. Code blocks are syntax-highlighted in HTML;
+//! the page has no external assets, so it opens offline.
+
+mod highlight;
+
+use crate::conversation::history::ChannelTurn;
+use crate::conversation::{ChannelStore, ConversationLogger, ProcessRunLogger, TimelineItem};
+use crate::db::SqlPool;
+use crate::error::Result;
+
+use std::fmt::Write as _;
+
+/// Output format for a rendered transcript.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl std::str::FromStr for TranscriptFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            other => Err(format!(
+                "unknown transcript format {other:?}: must be \"markdown\" or \"html\""
+            )),
+        }
+    }
+}
+
+impl TranscriptFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Markdown => "text/markdown",
+            Self::Html => "text/html",
+        }
+    }
+}
+
+/// A channel's full history, oldest first.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub agent_id: String,
+    pub channel_id: String,
+    pub channel_name: Option<String>,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub entries: Vec<TranscriptEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TranscriptEntry {
+    Timeline(TimelineItem),
+    Turn(ChannelTurn),
+}
+
+impl TranscriptEntry {
+    /// Date and time-of-day to the second, compared separately because rows
+    /// come back with either a `T` or a space between them.
+    fn timestamp(&self) -> (&str, &str) {
+        let timestamp = match self {
+            Self::Timeline(TimelineItem::Message { created_at, .. }) => created_at.as_str(),
+            Self::Timeline(TimelineItem::BranchRun { started_at, .. })
+            | Self::Timeline(TimelineItem::WorkerRun { started_at, .. }) => started_at.as_str(),
+            Self::Turn(turn) => turn.created_at.as_deref().unwrap_or_default(),
+        };
+        (
+            timestamp.get(..10).unwrap_or(timestamp),
+            timestamp.get(11..19).unwrap_or_default(),
+        )
+    }
+
+    /// Tie-break within a second: the user's message, then the turn it
+    /// started, then whatever the turn produced.
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Timeline(TimelineItem::Message { role, .. }) if role == "user" => 0,
+            Self::Turn(_) => 1,
+            Self::Timeline(_) => 2,
+        }
+    }
+}
+
+impl Transcript {
+    /// Load a channel's transcript from the agent's database.
+    pub async fn load(pool: &SqlPool, agent_id: &str, channel_id: &str) -> Result<Self> {
+        let timeline = ProcessRunLogger::new(pool.clone())
+            .load_channel_timeline(channel_id, i64::MAX, None)
+            .await?;
+        let turns = ConversationLogger::new(pool.clone())
+            .load_turns(channel_id)
+            .await?;
+        let channel_name = ChannelStore::new(pool.clone())
+            .resolve_name(channel_id)
+            .await;
+
+        let mut entries: Vec<TranscriptEntry> = timeline
+            .into_iter()
+            .map(TranscriptEntry::Timeline)
+            .chain(turns.into_iter().map(TranscriptEntry::Turn))
+            .collect();
+        entries.sort_by(|a, b| (a.timestamp(), a.rank()).cmp(&(b.timestamp(), b.rank())));
+
+        Ok(Self {
+            agent_id: agent_id.to_string(),
+            channel_id: channel_id.to_string(),
+            channel_name,
+            exported_at: chrono::Utc::now(),
+            entries,
+        })
+    }
+
+    pub fn render(&self, format: TranscriptFormat) -> String {
+        match format {
+            TranscriptFormat::Markdown => self.render_markdown(),
+            TranscriptFormat::Html => self.render_html(),
+        }
+    }
+
+    /// File name for a download, e.g. `transcript-discord-123-456.md`.
+    pub fn file_name(&self, format: TranscriptFormat) -> String {
+        let slug: String = self
+            .channel_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        format!("transcript-{slug}.{}", format.extension())
+    }
+
+    fn title(&self) -> String {
+        match &self.channel_name {
+            Some(name) => format!("#{name}"),
+            None => self.channel_id.clone(),
+        }
+    }
+
+    fn models(&self) -> Vec<&str> {
+        let mut models: Vec<&str> = Vec::new();
+        for entry in &self.entries {
+            if let TranscriptEntry::Turn(turn) = entry
+                && !models.contains(&turn.model.as_str())
+            {
+                models.push(&turn.model);
+            }
+        }
+        models
+    }
+
+    pub fn render_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Transcript: {}\n", self.title());
+        let _ = writeln!(out, "- Agent: `{}`", self.agent_id);
+        let _ = writeln!(out, "- Channel: `{}`", self.channel_id);
+        let _ = writeln!(
+            out,
+            "- Exported: {}",
+            self.exported_at.format("%Y-%m-%d %H:%M UTC")
+        );
+        let models = self.models();
+        if !models.is_empty() {
+            let _ = writeln!(out, "- Models: {}", inline_code_list(&models));
+        }
+
+        for entry in &self.entries {
+            out.push_str("\n---\n\n");
+            match entry {
+                TranscriptEntry::Timeline(TimelineItem::Message {
+                    role,
+                    sender_name,
+                    content,
+                    created_at,
+                    ..
+                }) => {
+                    let _ = writeln!(
+                        out,
+                        "**{}** · {}\n",
+                        speaker(role, sender_name.as_deref()),
+                        display_time(created_at)
+                    );
+                    let _ = writeln!(out, "{}", content.trim_end());
+                }
+                TranscriptEntry::Timeline(TimelineItem::BranchRun {
+                    description,
+                    conclusion,
+                    started_at,
+                    ..
+                }) => {
+                    let _ = writeln!(out, "*Branch* · {}\n", display_time(started_at));
+                    let _ = writeln!(out, "> {}", quote(description));
+                    if let Some(conclusion) = conclusion {
+                        let _ = writeln!(out, ">\n> **Conclusion:** {}", quote(conclusion));
+                    }
+                }
+                TranscriptEntry::Timeline(TimelineItem::WorkerRun {
+                    task,
+                    result,
+                    status,
+                    started_at,
+                    ..
+                }) => {
+                    let _ = writeln!(out, "*Worker* ({status}) · {}\n", display_time(started_at));
+                    let _ = writeln!(out, "> {}", quote(task));
+                    if let Some(result) = result {
+                        let _ = writeln!(out, ">\n> **Result:** {}", quote(result));
+                    }
+                }
+                TranscriptEntry::Turn(turn) => {
+                    let _ = writeln!(
+                        out,
+                        "<details><summary>Turn · <code>{}</code> · {} tool call(s)</summary>\n",
+                        escape_html(&turn.model),
+                        turn.tool_calls.len()
+                    );
+                    for tool_call in &turn.tool_calls {
+                        let arguments = serde_json::to_string_pretty(&tool_call.arguments)
+                            .unwrap_or_else(|_| tool_call.arguments.to_string());
+                        let _ = writeln!(out, "`{}`\n", tool_call.name);
+                        let _ = writeln!(out, "{}", fenced(&arguments, "json"));
+                        if let Some(result) = &tool_call.result {
+                            let _ = writeln!(out, "{}", fenced(result, ""));
+                        }
+                    }
+                    out.push_str("</details>\n");
+                }
+            }
+        }
+        out
+    }
+
+    pub fn render_html(&self) -> String {
+        let title = escape_html(&format!("Transcript: {}", self.title()));
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+             <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<main>"
+        );
+        let _ = writeln!(out, "<h1>{title}</h1>\n<dl class=\"meta\">");
+        let _ = writeln!(
+            out,
+            "<dt>Agent</dt><dd><code>{}</code></dd>",
+            escape_html(&self.agent_id)
+        );
+        let _ = writeln!(
+            out,
+            "<dt>Channel</dt><dd><code>{}</code></dd>",
+            escape_html(&self.channel_id)
+        );
+        let _ = writeln!(
+            out,
+            "<dt>Exported</dt><dd>{}</dd>",
+            self.exported_at.format("%Y-%m-%d %H:%M UTC")
+        );
+        let models = self.models();
+        if !models.is_empty() {
+            let models: Vec<String> = models
+                .iter()
+                .map(|model| format!("<code>{}</code>", escape_html(model)))
+                .collect();
+            let _ = writeln!(out, "<dt>Models</dt><dd>{}</dd>", models.join(", "));
+        }
+        out.push_str("</dl>\n");
+
+        for entry in &self.entries {
+            match entry {
+                TranscriptEntry::Timeline(TimelineItem::Message {
+                    role,
+                    sender_name,
+                    content,
+                    created_at,
+                    ..
+                }) => {
+                    let class = if role == "user" { "user" } else { "assistant" };
+                    let _ = writeln!(
+                        out,
+                        "<section class=\"message {class}\"><header><strong>{}</strong> \
+                         <time>{}</time></header>\n{}</section>",
+                        escape_html(&speaker(role, sender_name.as_deref())),
+                        display_time(created_at),
+                        render_message_html(content)
+                    );
+                }
+                TranscriptEntry::Timeline(TimelineItem::BranchRun {
+                    description,
+                    conclusion,
+                    started_at,
+                    ..
+                }) => {
+                    let _ = writeln!(
+                        out,
+                        "<section class=\"process\"><header><em>Branch</em> <time>{}</time></header>\n\
+                         <p>{}</p>",
+                        display_time(started_at),
+                        escape_html(description)
+                    );
+                    if let Some(conclusion) = conclusion {
+                        let _ = writeln!(
+                            out,
+                            "<p><strong>Conclusion:</strong> {}</p>",
+                            escape_html(conclusion)
+                        );
+                    }
+                    out.push_str("</section>\n");
+                }
+                TranscriptEntry::Timeline(TimelineItem::WorkerRun {
+                    task,
+                    result,
+                    status,
+                    started_at,
+                    ..
+                }) => {
+                    let _ = writeln!(
+                        out,
+                        "<section class=\"process\"><header><em>Worker</em> ({}) <time>{}</time></header>\n\
+                         <p>{}</p>",
+                        escape_html(status),
+                        display_time(started_at),
+                        escape_html(task)
+                    );
+                    if let Some(result) = result {
+                        let _ = writeln!(
+                            out,
+                            "<p><strong>Result:</strong> {}</p>",
+                            escape_html(result)
+                        );
+                    }
+                    out.push_str("</section>\n");
+                }
+                TranscriptEntry::Turn(turn) => {
+                    let _ = writeln!(
+                        out,
+                        "<details class=\"turn\"><summary>Turn · <code>{}</code> · {} tool call(s)</summary>",
+                        escape_html(&turn.model),
+                        turn.tool_calls.len()
+                    );
+                    for tool_call in &turn.tool_calls {
+                        let arguments = serde_json::to_string_pretty(&tool_call.arguments)
+                            .unwrap_or_else(|_| tool_call.arguments.to_string());
+                        let _ = writeln!(
+                            out,
+                            "<h3><code>{}</code></h3>\n{}",
+                            escape_html(&tool_call.name),
+                            code_block_html(&arguments, "json")
+                        );
+                        if let Some(result) = &tool_call.result {
+                            out.push_str(&code_block_html(result, ""));
+                        }
+                    }
+                    out.push_str("</details>\n");
+                }
+            }
+        }
+
+        out.push_str("</main>\n</body>\n</html>\n");
+        out
+    }
+}
+
+const STYLE: &str = "\
+body{margin:0;background:#f6f7f9;color:#1f2328;font:15px/1.55 -apple-system,BlinkMacSystemFont,'Segoe UI',sans-serif}\
+main{max-width:860px;margin:0 auto;padding:2rem 1rem}\
+h1{font-size:1.5rem;margin:0 0 .5rem}h3{font-size:.9rem;margin:.75rem 0 .25rem}\
+.meta{display:grid;grid-template-columns:max-content 1fr;gap:.2rem 1rem;margin:0 0 1.5rem;color:#59636e}\
+.meta dd{margin:0}\
+section,details{background:#fff;border:1px solid #d1d9e0;border-radius:8px;padding:.75rem 1rem;margin:.75rem 0}\
+section.user{border-left:4px solid #0969da}section.assistant{border-left:4px solid #1a7f37}\
+section.process{background:#f6f8fa;font-size:.9rem}\
+header{margin-bottom:.35rem}time{color:#59636e;font-size:.85rem;margin-left:.35rem}\
+details.turn{background:#fbfbfc;font-size:.9rem}summary{cursor:pointer;color:#59636e}\
+p{margin:.4rem 0}code{font:13px/1.45 ui-monospace,SFMono-Regular,Menlo,monospace}\
+p code{background:#eff1f3;padding:.1em .3em;border-radius:4px}\
+pre{background:#0d1117;color:#e6edf3;padding:.75rem;border-radius:6px;overflow-x:auto}\
+.kw{color:#ff7b72}.str{color:#a5d6ff}.com{color:#8b949e;font-style:italic}.num{color:#79c0ff}";
+
+fn speaker(role: &str, sender_name: Option<&str>) -> String {
+    match (role, sender_name) {
+        ("user", Some(name)) => name.to_string(),
+        ("user", None) => "user".to_string(),
+        _ => "assistant".to_string(),
+    }
+}
+
+fn display_time(timestamp: &str) -> String {
+    match (timestamp.get(..10), timestamp.get(11..16)) {
+        (Some(date), Some(minutes)) => format!("{date} {minutes} UTC"),
+        _ => timestamp.to_string(),
+    }
+}
+
+fn inline_code_list(items: &[&str]) -> String {
+    items
+        .iter()
+        .map(|item| format!("`{item}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Prefix continuation lines so multi-line text stays inside a blockquote.
+fn quote(text: &str) -> String {
+    text.trim_end().replace('\n', "\n> ")
+}
+
+/// A fenced code block whose fence is longer than any backtick run in `code`.
+fn fenced(code: &str, language: &str) -> String {
+    let longest_run = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{fence}{language}\n{}\n{fence}\n", code.trim_end())
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn code_block_html(code: &str, language: &str) -> String {
+    format!(
+        "<pre><code>{}</code></pre>\n",
+        highlight::highlight(code.trim_end(), language)
+    )
+}
+
+/// Render a chat message: fenced code blocks are highlighted, the rest
+/// becomes paragraphs with inline code and line breaks.
+fn render_message_html(content: &str) -> String {
+    let mut out = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut lines = content.lines();
+
+    let flush = |paragraph: &mut Vec<&str>, out: &mut String| {
+        if !paragraph.is_empty() {
+            let lines: Vec<String> = paragraph.iter().map(|line| inline_html(line)).collect();
+            let _ = writeln!(out, "<p>{}</p>", lines.join("<br>\n"));
+            paragraph.clear();
+        }
+    };
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            flush(&mut paragraph, &mut out);
+            let language = info.split_whitespace().next().unwrap_or_default();
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
+                    break;
+                }
+                code.push(line);
+            }
+            out.push_str(&code_block_html(&code.join("\n"), language));
+        } else if trimmed.is_empty() {
+            flush(&mut paragraph, &mut out);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush(&mut paragraph, &mut out);
+    out
+}
+
+/// Escape a line of text, turning `backtick spans` into `<code>`.
+fn inline_html(line: &str) -> String {
+    let mut out = String::new();
+    let mut parts = line.split('`');
+    if let Some(first) = parts.next() {
+        out.push_str(&escape_html(first));
+    }
+    let rest: Vec<&str> = parts.collect();
+    // An unpaired trailing backtick is literal text.
+    let paired = rest.len() - rest.len() % 2;
+    for (index, part) in rest.iter().enumerate() {
+        if index >= paired {
+            out.push('`');
+            out.push_str(&escape_html(part));
+        } else if index % 2 == 0 {
+            let _ = write!(out, "<code>{}</code>", escape_html(part));
+        } else {
+            out.push_str(&escape_html(part));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation::history::ToolCallRecord;
+
+    fn transcript() -> Transcript {
+        Transcript {
+            agent_id: "main".into(),
+            channel_id: "discord:1:2".into(),
+            channel_name: Some("general".into()),
+            exported_at: chrono::Utc::now(),
+            entries: vec![
+                TranscriptEntry::Timeline(TimelineItem::Message {
+                    id: "m1".into(),
+                    role: "user".into(),
+                    sender_name: Some("alice".into()),
+                    sender_id: Some("42".into()),
+                    content: "what does <b> do?\n```rust\nfn main() {}\n```".into(),
+                    created_at: "2026-03-01 10:00:00".into(),
+                }),
+                TranscriptEntry::Turn(ChannelTurn {
+                    id: "t1".into(),
+                    model: "anthropic/claude-sonnet-4".into(),
+                    tool_calls: vec![ToolCallRecord {
+                        name: "reply".into(),
+                        arguments: serde_json::json!({"content": "it's bold"}),
+                        result: Some("sent".into()),
+                    }],
+                    created_at: Some("2026-03-01 10:00:00".into()),
+                }),
+            ],
+        }
+    }
+
+    #[test]
+    fn markdown_includes_messages_and_tool_calls() {
+        let markdown = transcript().render_markdown();
+        assert!(markdown.starts_with("# Transcript: #general\n"));
+        assert!(markdown.contains("- Models: `anthropic/claude-sonnet-4`"));
+        assert!(markdown.contains("**alice** · 2026-03-01 10:00 UTC"));
+        assert!(markdown.contains("`reply`\n\n```json\n{\n  \"content\": \"it's bold\"\n}\n```"));
+    }
+
+    #[test]
+    fn html_escapes_text_and_highlights_code() {
+        let html = transcript().render_html();
+        assert!(html.contains("<p>what does &lt;b&gt; do?</p>"));
+        assert!(html.contains("<span class=\"kw\">fn</span> main"));
+        assert!(html.contains("<span class=\"str\">&quot;content&quot;</span>"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn fences_outgrow_backticks_in_code() {
+        assert_eq!(fenced("a ```` b", ""), "`````\na ```` b\n`````\n");
+        assert_eq!(inline_html("use `x` and `"), "use <code>x</code> and `");
+    }
+
+    #[test]
+    fn entries_sort_user_then_turn_then_reply() {
+        let message = |role: &str, at: &str| {
+            TranscriptEntry::Timeline(TimelineItem::Message {
+                id: role.into(),
+                role: role.into(),
+                sender_name: None,
+                sender_id: None,
+                content: String::new(),
+                created_at: at.into(),
+            })
+        };
+        let turn = TranscriptEntry::Turn(ChannelTurn {
+            id: "t".into(),
+            model: "m".into(),
+            tool_calls: Vec::new(),
+            created_at: Some("2026-03-01 10:00:00.123+00:00".into()),
+        });
+        let mut entries = [
+            message("assistant", "2026-03-01T10:00:00+00:00"),
+            turn,
+            message("user", "2026-03-01T10:00:00+00:00"),
+        ];
+        entries.sort_by(|a, b| (a.timestamp(), a.rank()).cmp(&(b.timestamp(), b.rank())));
+        assert_eq!(
+            entries
+                .iter()
+                .map(TranscriptEntry::rank)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+//! A small lexical highlighter for code blocks in HTML transcripts: comments,
+//! strings, numbers, and keywords for common languages. Output is escaped
+//! HTML with `kw`, `str`, `com`, and `num` spans.
+
+use super::escape_html;
+
+struct Language {
+    line_comment: Option<&'static str>,
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    keywords: &'static [&'static str],
+    case_insensitive: bool,
+}
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while",
+];
+const PYTHON_KEYWORDS: &[&str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "False", "finally", "for", "from", "if", "import", "in", "is", "lambda",
+    "None", "not", "or", "pass", "raise", "return", "self", "True", "try", "while", "with",
+    "yield",
+];
+const C_FAMILY_KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "for",
+    "from",
+    "func",
+    "function",
+    "go",
+    "if",
+    "implements",
+    "import",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "struct",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "yield",
+];
+const SHELL_KEYWORDS: &[&str] = &[
+    "case", "do", "done", "echo", "elif", "else", "esac", "exit", "export", "fi", "for",
+    "function", "if", "in", "local", "return", "then", "while",
+];
+const SQL_KEYWORDS: &[&str] = &[
+    "and", "as", "by", "create", "delete", "from", "group", "having", "index", "insert", "into",
+    "join", "left", "limit", "not", "null", "on", "or", "order", "primary", "key", "select", "set",
+    "table", "update", "values", "where",
+];
+const DATA_KEYWORDS: &[&str] = &["true", "false", "null"];
+
+fn language(name: &str) -> Option<Language> {
+    let language = match name.to_ascii_lowercase().as_str() {
+        "rust" | "rs" => Language {
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"'],
+            keywords: RUST_KEYWORDS,
+            case_insensitive: false,
+        },
+        "python" | "py" => Language {
+            line_comment: Some("#"),
+            block_comment: None,
+            quotes: &['"', '\''],
+            keywords: PYTHON_KEYWORDS,
+            case_insensitive: false,
+        },
+        "javascript" | "js" | "jsx" | "typescript" | "ts" | "tsx" | "go" | "java" | "c" | "cpp"
+        | "c++" | "csharp" | "cs" | "kotlin" | "swift" => Language {
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"', '\'', '`'],
+            keywords: C_FAMILY_KEYWORDS,
+            case_insensitive: false,
+        },
+        "bash" | "sh" | "shell" | "zsh" => Language {
+            line_comment: Some("#"),
+            block_comment: None,
+            quotes: &['"', '\''],
+            keywords: SHELL_KEYWORDS,
+            case_insensitive: false,
+        },
+        "sql" => Language {
+            line_comment: Some("--"),
+            block_comment: Some(("/*", "*/")),
+            quotes: &['\''],
+            keywords: SQL_KEYWORDS,
+            case_insensitive: true,
+        },
+        "json" => Language {
+            line_comment: None,
+            block_comment: None,
+            quotes: &['"'],
+            keywords: DATA_KEYWORDS,
+            case_insensitive: false,
+        },
+        "toml" | "yaml" | "yml" => Language {
+            line_comment: Some("#"),
+            block_comment: None,
+            quotes: &['"', '\''],
+            keywords: DATA_KEYWORDS,
+            case_insensitive: false,
+        },
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Highlight `code` as `language`. Unknown languages are only escaped.
+pub(super) fn highlight(code: &str, language_name: &str) -> String {
+    let Some(language) = language(language_name) else {
+        return escape_html(code);
+    };
+
+    let mut out = String::with_capacity(code.len() * 2);
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let token_len = if let Some(marker) = language.line_comment
+            && rest.starts_with(marker)
+        {
+            let len = rest.find('\n').unwrap_or(rest.len());
+            push_span(&mut out, "com", &rest[..len]);
+            len
+        } else if let Some((open, close)) = language.block_comment
+            && rest.starts_with(open)
+        {
+            let len = rest[open.len()..]
+                .find(close)
+                .map(|end| open.len() + end + close.len())
+                .unwrap_or(rest.len());
+            push_span(&mut out, "com", &rest[..len]);
+            len
+        } else if language.quotes.contains(&c) {
+            let len = string_len(rest, c);
+            push_span(&mut out, "str", &rest[..len]);
+            len
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+                .unwrap_or(rest.len());
+            push_span(&mut out, "num", &rest[..len]);
+            len
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..len];
+            let is_keyword = if language.case_insensitive {
+                language
+                    .keywords
+                    .iter()
+                    .any(|keyword| keyword.eq_ignore_ascii_case(word))
+            } else {
+                language.keywords.contains(&word)
+            };
+            if is_keyword {
+                push_span(&mut out, "kw", word);
+            } else {
+                out.push_str(&escape_html(word));
+            }
+            len
+        } else {
+            out.push_str(&escape_html(&rest[..c.len_utf8()]));
+            c.len_utf8()
+        };
+        rest = &rest[token_len..];
+    }
+    out
+}
+
+/// Length of the string literal at the start of `text`, closing quote
+/// included. Backslash escapes are skipped; an unterminated string runs to
+/// the end of the line.
+fn string_len(text: &str, quote: char) -> usize {
+    let mut chars = text.char_indices().skip(1);
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\n' => return index,
+            c if c == quote => return index + c.len_utf8(),
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+fn push_span(out: &mut String, class: &str, text: &str) {
+    out.push_str("<span class=\"");
+    out.push_str(class);
+    out.push_str("\">");
+    out.push_str(&escape_html(text));
+    out.push_str("</span>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_rust_tokens() {
+        assert_eq!(
+            highlight("let x = \"a\\\"b\"; // done\n42", "rust"),
+            "<span class=\"kw\">let</span> x = <span class=\"str\">&quot;a\\&quot;b&quot;</span>; \
+             <span class=\"com\">// done</span>\n<span class=\"num\">42</span>"
+        );
+    }
+
+    #[test]
+    fn unknown_languages_are_escaped_only() {
+        assert_eq!(highlight("<let>", "brainfuck"), "&lt;let&gt;");
+        assert_eq!(
+            highlight("SELECT 'x' -- note", "sql"),
+            "<span class=\"kw\">SELECT</span> <span class=\"str\">&#39;x&#39;</span> \
+             <span class=\"com\">-- note</span>"
+        );
+    }
+}
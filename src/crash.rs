@@ -0,0 +1,238 @@
+//! Panic capture and crash reports.
+//!
+//! [`install`] wraps the panic hook. A panic still prints what it always did,
+//! and also writes a crash report under `crashes/` in the instance
+//! directory: when and where it happened, the thread, the correlation ID of
+//! the turn that was running, the redacted panic message, and a full
+//! backtrace. With `[crash_reports] alert_target`, a notice naming the report
+//! is posted to that channel. The notice leaves out the panic message, which
+//! can quote user content.
+//!
+//! A panic inside a tokio task only ends that task. Long-lived tasks are
+//! restarted by [`crate::supervisor`], and the main loop replaces a channel
+//! whose task died when its next message arrives.
+
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use tokio::sync::broadcast;
+
+/// Crash report settings (instance-level, under `[crash_reports]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashReportsConfig {
+    /// "adapter:target" that crash notices are posted to.
+    pub alert_target: Option<String>,
+    /// Reports kept in `crashes/`; older ones are deleted.
+    pub max_reports: usize,
+}
+
+impl Default for CrashReportsConfig {
+    fn default() -> Self {
+        Self {
+            alert_target: None,
+            max_reports: 50,
+        }
+    }
+}
+
+/// What the panic hook saw.
+#[derive(Debug, Clone)]
+struct Crash {
+    at: chrono::DateTime<chrono::Utc>,
+    thread: String,
+    location: String,
+    message: String,
+    correlation_id: Option<String>,
+}
+
+impl Crash {
+    fn file_name(&self) -> String {
+        format!("crash-{}.txt", self.at.format("%Y%m%dT%H%M%S%.3fZ"))
+    }
+
+    /// The report file's contents.
+    fn report(&self, backtrace: &std::backtrace::Backtrace) -> String {
+        format!(
+            "Spacebot crash report\n\
+             time: {}\n\
+             version: {}\n\
+             thread: {}\n\
+             location: {}\n\
+             correlation_id: {}\n\
+             message: {}\n\
+             \n\
+             backtrace:\n{backtrace}\n",
+            self.at.to_rfc3339(),
+            env!("CARGO_PKG_VERSION"),
+            self.thread,
+            self.location,
+            self.correlation_id.as_deref().unwrap_or("none"),
+            self.message,
+        )
+    }
+}
+
+/// The operator-facing notice of a crash.
+#[derive(Debug, Clone)]
+pub struct CrashNotice {
+    pub thread: String,
+    pub location: String,
+    pub correlation_id: Option<String>,
+    /// File name of the report in `crashes/`, if it could be written.
+    pub report: Option<String>,
+}
+
+impl std::fmt::Display for CrashNotice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Crash: a task panicked in thread `{}` at {}",
+            self.thread, self.location
+        )?;
+        if let Some(correlation_id) = &self.correlation_id {
+            write!(f, " during turn {correlation_id}")?;
+        }
+        match &self.report {
+            Some(report) => write!(f, ". Crash report: {report}"),
+            None => write!(f, ". The crash report couldn't be written; see the logs."),
+        }
+    }
+}
+
+static NOTICES: LazyLock<broadcast::Sender<CrashNotice>> =
+    LazyLock::new(|| broadcast::channel(16).0);
+
+/// Crash notices from now on.
+pub fn subscribe() -> broadcast::Receiver<CrashNotice> {
+    NOTICES.subscribe()
+}
+
+/// Write a crash report for every panic, then run the previous hook.
+pub fn install(instance_dir: &Path, config: &CrashReportsConfig) {
+    let directory = instance_dir.join("crashes");
+    let max_reports = config.max_reports;
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("non-string panic payload");
+        let crash = Crash {
+            at: chrono::Utc::now(),
+            thread: std::thread::current()
+                .name()
+                .unwrap_or("unnamed")
+                .to_string(),
+            location: info
+                .location()
+                .map(|location| location.to_string())
+                .unwrap_or_else(|| "an unknown location".into()),
+            message: crate::logging::redact_secrets(message),
+            correlation_id: crate::logging::current_correlation_id(),
+        };
+        let backtrace = std::backtrace::Backtrace::force_capture();
+
+        let report = match write_report(&directory, &crash, &backtrace, max_reports) {
+            Ok(path) => {
+                tracing::error!(
+                    location = %crash.location,
+                    correlation_id = crash.correlation_id.as_deref().unwrap_or_default(),
+                    report = %path.display(),
+                    "panic captured"
+                );
+                path.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            }
+            Err(error) => {
+                tracing::error!(%error, location = %crash.location, "failed to write crash report");
+                None
+            }
+        };
+        // No receivers just means no alert target is configured.
+        NOTICES
+            .send(CrashNotice {
+                thread: crash.thread,
+                location: crash.location,
+                correlation_id: crash.correlation_id,
+                report,
+            })
+            .ok();
+
+        previous_hook(info);
+    }));
+}
+
+fn write_report(
+    directory: &Path,
+    crash: &Crash,
+    backtrace: &std::backtrace::Backtrace,
+    max_reports: usize,
+) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(directory)?;
+    let path = directory.join(crash.file_name());
+    std::fs::write(&path, crash.report(backtrace))?;
+
+    let names: Vec<String> = std::fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+    for stale in stale_reports(names, max_reports) {
+        std::fs::remove_file(directory.join(stale)).ok();
+    }
+    Ok(path)
+}
+
+/// Reports beyond the newest `max_reports` among the file `names` in
+/// `crashes/`. Report names sort by time.
+fn stale_reports(names: Vec<String>, max_reports: usize) -> Vec<String> {
+    let mut reports: Vec<String> = names
+        .into_iter()
+        .filter(|name| name.starts_with("crash-") && name.ends_with(".txt"))
+        .collect();
+    reports.sort_unstable_by(|a, b| b.cmp(a));
+    reports.into_iter().skip(max_reports).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_oldest_reports_beyond_the_limit_are_stale() {
+        let names: Vec<String> = [
+            "crash-20261014T090000.000Z.txt",
+            "notes.md",
+            "crash-20261015T101502.120Z.txt",
+            "crash-20261013T235959.999Z.txt",
+            "crash-20261015T080000.000Z.txt",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+        assert_eq!(
+            stale_reports(names.clone(), 2),
+            [
+                "crash-20261014T090000.000Z.txt".to_string(),
+                "crash-20261013T235959.999Z.txt".to_string(),
+            ]
+        );
+        assert!(stale_reports(names, 10).is_empty());
+    }
+
+    #[test]
+    fn notices_name_the_report_but_not_the_panic_message() {
+        let notice = CrashNotice {
+            thread: "tokio-runtime-worker".into(),
+            location: "src/agent/channel.rs:812:9".into(),
+            correlation_id: Some("3f2a9c1b7d4e".into()),
+            report: Some("crash-20261015T101502.120Z.txt".into()),
+        };
+        assert_eq!(
+            notice.to_string(),
+            "Crash: a task panicked in thread `tokio-runtime-worker` at \
+             src/agent/channel.rs:812:9 during turn 3f2a9c1b7d4e. Crash report: \
+             crash-20261015T101502.120Z.txt"
+        );
+    }
+}
@@ -181,151 +181,161 @@ impl Scheduler {
             }
         }
 
-        let handle = tokio::spawn(async move {
-            // Look up interval before entering the loop
-            let interval_secs = {
-                let j = jobs.read().await;
-                j.get(&job_id).map(|j| j.interval_secs).unwrap_or(3600)
-            };
+        // Supervised so a panic while firing restarts the timer instead of
+        // silently ending the job's schedule.
+        let (agent_id, task) = (self.context.deps.agent_id.clone(), format!("cron {job_id}"));
+        let handle = crate::supervisor::spawn(Some(agent_id), task, move || {
+            let job_id = job_id.clone();
+            let jobs = jobs.clone();
+            let context = context.clone();
+            tokio::spawn(async move {
+                // Look up interval before entering the loop
+                let interval_secs = {
+                    let j = jobs.read().await;
+                    j.get(&job_id).map(|j| j.interval_secs).unwrap_or(3600)
+                };
 
-            // For sub-daily intervals that divide evenly into 86400 (e.g. 1800s, 3600s, 21600s),
-            // align the first tick to the next UTC clock boundary so the job fires on clean marks
-            // like :00 and :30 rather than at an arbitrary offset from service start.
-            // Daily/weekly jobs are left on relative timing (interval_at with one interval offset)
-            // to avoid overcomplicating scheduling for jobs with active_hours constraints.
-            let first_tick = if interval_secs < 86400 && 86400 % interval_secs == 0 {
-                let now_unix = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-                let remainder = now_unix % interval_secs;
-                let secs_until = if remainder == 0 {
-                    interval_secs
+                // For sub-daily intervals that divide evenly into 86400 (e.g. 1800s, 3600s, 21600s),
+                // align the first tick to the next UTC clock boundary so the job fires on clean marks
+                // like :00 and :30 rather than at an arbitrary offset from service start.
+                // Daily/weekly jobs are left on relative timing (interval_at with one interval offset)
+                // to avoid overcomplicating scheduling for jobs with active_hours constraints.
+                let first_tick = if interval_secs < 86400 && 86400 % interval_secs == 0 {
+                    let now_unix = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs();
+                    let remainder = now_unix % interval_secs;
+                    let secs_until = if remainder == 0 {
+                        interval_secs
+                    } else {
+                        interval_secs - remainder
+                    };
+                    tracing::info!(
+                        cron_id = %job_id,
+                        interval_secs,
+                        secs_until_first_tick = secs_until,
+                        "clock-aligned timer: first tick in {secs_until}s"
+                    );
+                    tokio::time::Instant::now() + Duration::from_secs(secs_until)
                 } else {
-                    interval_secs - remainder
+                    tokio::time::Instant::now() + Duration::from_secs(interval_secs)
                 };
-                tracing::info!(
-                    cron_id = %job_id,
-                    interval_secs,
-                    secs_until_first_tick = secs_until,
-                    "clock-aligned timer: first tick in {secs_until}s"
-                );
-                tokio::time::Instant::now() + Duration::from_secs(secs_until)
-            } else {
-                tokio::time::Instant::now() + Duration::from_secs(interval_secs)
-            };
 
-            let mut ticker =
-                tokio::time::interval_at(first_tick, Duration::from_secs(interval_secs));
-            // Skip catch-up ticks if processing falls behind — maintain original cadence.
-            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+                let mut ticker =
+                    tokio::time::interval_at(first_tick, Duration::from_secs(interval_secs));
+                // Skip catch-up ticks if processing falls behind — maintain original cadence.
+                ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
-            loop {
-                ticker.tick().await;
+                loop {
+                    ticker.tick().await;
 
-                let job = {
-                    let j = jobs.read().await;
-                    match j.get(&job_id) {
-                        Some(j) if !j.enabled => {
-                            tracing::debug!(cron_id = %job_id, "cron job disabled, stopping timer");
-                            break;
-                        }
-                        Some(j) => j.clone(),
-                        None => {
-                            tracing::debug!(cron_id = %job_id, "cron job removed, stopping timer");
-                            break;
+                    let job = {
+                        let j = jobs.read().await;
+                        match j.get(&job_id) {
+                            Some(j) if !j.enabled => {
+                                tracing::debug!(cron_id = %job_id, "cron job disabled, stopping timer");
+                                break;
+                            }
+                            Some(j) => j.clone(),
+                            None => {
+                                tracing::debug!(cron_id = %job_id, "cron job removed, stopping timer");
+                                break;
+                            }
                         }
-                    }
-                };
-
-                // Check active hours window
-                if let Some((start, end)) = job.active_hours {
-                    let current_hour = chrono::Local::now().hour() as u8;
-                    let in_window = if start <= end {
-                        current_hour >= start && current_hour < end
-                    } else {
-                        // Wraps midnight (e.g. 22:00 - 06:00)
-                        current_hour >= start || current_hour < end
                     };
-                    if !in_window {
-                        tracing::debug!(
-                            cron_id = %job_id,
-                            current_hour,
-                            start,
-                            end,
-                            "outside active hours, skipping"
-                        );
-                        continue;
-                    }
-                }
-
-                tracing::info!(cron_id = %job_id, "cron job firing");
 
-                match run_cron_job(&job, &context).await {
-                    Ok(()) => {
-                        // Reset failure count on success
-                        let mut j = jobs.write().await;
-                        if let Some(j) = j.get_mut(&job_id) {
-                            j.consecutive_failures = 0;
+                    // Check active hours window
+                    if let Some((start, end)) = job.active_hours {
+                        let current_hour = chrono::Local::now().hour() as u8;
+                        let in_window = if start <= end {
+                            current_hour >= start && current_hour < end
+                        } else {
+                            // Wraps midnight (e.g. 22:00 - 06:00)
+                            current_hour >= start || current_hour < end
+                        };
+                        if !in_window {
+                            tracing::debug!(
+                                cron_id = %job_id,
+                                current_hour,
+                                start,
+                                end,
+                                "outside active hours, skipping"
+                            );
+                            continue;
                         }
                     }
-                    Err(error) => {
-                        tracing::error!(
-                            cron_id = %job_id,
-                            %error,
-                            "cron job execution failed"
-                        );
-
-                        let should_disable = {
+
+                    tracing::info!(cron_id = %job_id, "cron job firing");
+
+                    match run_cron_job(&job, &context).await {
+                        Ok(()) => {
+                            // Reset failure count on success
                             let mut j = jobs.write().await;
                             if let Some(j) = j.get_mut(&job_id) {
-                                j.consecutive_failures += 1;
-                                j.consecutive_failures >= MAX_CONSECUTIVE_FAILURES
-                            } else {
-                                false
+                                j.consecutive_failures = 0;
                             }
-                        };
-
-                        if should_disable {
-                            tracing::warn!(
+                        }
+                        Err(error) => {
+                            tracing::error!(
                                 cron_id = %job_id,
-                                "circuit breaker tripped after {MAX_CONSECUTIVE_FAILURES} consecutive failures, disabling"
+                                %error,
+                                "cron job execution failed"
                             );
 
-                            {
+                            let should_disable = {
                                 let mut j = jobs.write().await;
                                 if let Some(j) = j.get_mut(&job_id) {
-                                    j.enabled = false;
+                                    j.consecutive_failures += 1;
+                                    j.consecutive_failures >= MAX_CONSECUTIVE_FAILURES
+                                } else {
+                                    false
+                                }
+                            };
+
+                            if should_disable {
+                                tracing::warn!(
+                                    cron_id = %job_id,
+                                    "circuit breaker tripped after {MAX_CONSECUTIVE_FAILURES} consecutive failures, disabling"
+                                );
+
+                                {
+                                    let mut j = jobs.write().await;
+                                    if let Some(j) = j.get_mut(&job_id) {
+                                        j.enabled = false;
+                                    }
                                 }
-                            }
 
-                            // Persist the disabled state
-                            if let Err(error) = context.store.update_enabled(&job_id, false).await {
-                                tracing::error!(%error, "failed to persist cron job disabled state");
-                            }
+                                // Persist the disabled state
+                                if let Err(error) =
+                                    context.store.update_enabled(&job_id, false).await
+                                {
+                                    tracing::error!(%error, "failed to persist cron job disabled state");
+                                }
 
-                            break;
+                                break;
+                            }
                         }
                     }
-                }
 
-                if job.run_once {
-                    tracing::info!(cron_id = %job_id, "run-once cron completed, disabling");
+                    if job.run_once {
+                        tracing::info!(cron_id = %job_id, "run-once cron completed, disabling");
+
+                        {
+                            let mut j = jobs.write().await;
+                            if let Some(j) = j.get_mut(&job_id) {
+                                j.enabled = false;
+                            }
+                        }
 
-                    {
-                        let mut j = jobs.write().await;
-                        if let Some(j) = j.get_mut(&job_id) {
-                            j.enabled = false;
+                        if let Err(error) = context.store.update_enabled(&job_id, false).await {
+                            tracing::error!(%error, "failed to persist run-once cron disabled state");
                         }
-                    }
 
-                    if let Err(error) = context.store.update_enabled(&job_id, false).await {
-                        tracing::error!(%error, "failed to persist run-once cron disabled state");
+                        break;
                     }
-
-                    break;
                 }
-            }
+            })
         });
 
         // Insert the new handle. Any previously existing handle was already aborted above.
@@ -633,7 +643,7 @@ async fn run_cron_job(job: &CronJob, context: &CronContext) -> Result<()> {
     Ok(())
 }
 
-fn normalize_delivery_target(raw: &str) -> Option<DeliveryTarget> {
+pub(crate) fn normalize_delivery_target(raw: &str) -> Option<DeliveryTarget> {
     let (adapter, target) = raw.split_once(':')?;
     if adapter.is_empty() || target.is_empty() {
         return None;
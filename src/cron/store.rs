@@ -1,19 +1,19 @@
-//! Cron job CRUD storage (SQLite).
+//! Cron job CRUD storage (SQLite or Postgres).
 
 use crate::cron::scheduler::CronConfig;
+use crate::db::{SqlPool, timestamp_text, with_pool};
 use crate::error::Result;
 use anyhow::Context as _;
-use sqlx::SqlitePool;
 
 /// Cron job store for persistence.
 #[derive(Debug)]
 pub struct CronStore {
-    pool: SqlitePool,
+    pool: SqlPool,
 }
 
 impl CronStore {
     /// Create a new cron store.
-    pub fn new(pool: SqlitePool) -> Self {
+    pub fn new(pool: SqlPool) -> Self {
         Self { pool }
     }
 
@@ -22,32 +22,35 @@ impl CronStore {
         let active_start = config.active_hours.map(|h| h.0 as i64);
         let active_end = config.active_hours.map(|h| h.1 as i64);
 
-        sqlx::query(
-            r#"
-            INSERT INTO cron_jobs (id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, run_once, timeout_secs)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-            ON CONFLICT(id) DO UPDATE SET
-                prompt = excluded.prompt,
-                interval_secs = excluded.interval_secs,
-                delivery_target = excluded.delivery_target,
-                active_start_hour = excluded.active_start_hour,
-                active_end_hour = excluded.active_end_hour,
-                enabled = excluded.enabled,
-                run_once = excluded.run_once,
-                timeout_secs = excluded.timeout_secs
-            "#
-        )
-        .bind(&config.id)
-        .bind(&config.prompt)
-        .bind(config.interval_secs as i64)
-        .bind(&config.delivery_target)
-        .bind(active_start)
-        .bind(active_end)
-        .bind(config.enabled as i64)
-        .bind(config.run_once as i64)
-        .bind(config.timeout_secs.map(|t| t as i64))
-        .execute(&self.pool)
-        .await
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                INSERT INTO cron_jobs (id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, run_once, timeout_secs)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT(id) DO UPDATE SET
+                    prompt = excluded.prompt,
+                    interval_secs = excluded.interval_secs,
+                    delivery_target = excluded.delivery_target,
+                    active_start_hour = excluded.active_start_hour,
+                    active_end_hour = excluded.active_end_hour,
+                    enabled = excluded.enabled,
+                    run_once = excluded.run_once,
+                    timeout_secs = excluded.timeout_secs
+                "#
+            )
+            .bind(&config.id)
+            .bind(&config.prompt)
+            .bind(config.interval_secs as i64)
+            .bind(&config.delivery_target)
+            .bind(active_start)
+            .bind(active_end)
+            .bind(config.enabled as i64)
+            .bind(config.run_once as i64)
+            .bind(config.timeout_secs.map(|t| t as i64))
+            .execute(pool)
+            .await
+            .map(drop)
+        })
         .context("failed to save cron job")?;
 
         Ok(())
@@ -55,65 +58,73 @@ impl CronStore {
 
     /// Load all enabled cron job configurations.
     pub async fn load_all(&self) -> Result<Vec<CronConfig>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, run_once, timeout_secs
-            FROM cron_jobs
-            WHERE enabled = 1
-            ORDER BY created_at ASC
-            "#
-        )
-        .fetch_all(&self.pool)
-        .await
-        .context("failed to load cron jobs")?;
-
-        let configs = rows
-            .into_iter()
-            .map(|row| CronConfig {
-                id: row.try_get("id").unwrap_or_default(),
-                prompt: row.try_get("prompt").unwrap_or_default(),
-                interval_secs: row.try_get::<i64, _>("interval_secs").unwrap_or(3600) as u64,
-                delivery_target: row.try_get("delivery_target").unwrap_or_default(),
-                active_hours: {
-                    let start: Option<i64> = row.try_get("active_start_hour").ok();
-                    let end: Option<i64> = row.try_get("active_end_hour").ok();
-                    match (start, end) {
-                        (Some(s), Some(e)) => Some((s as u8, e as u8)),
-                        _ => None,
-                    }
-                },
-                enabled: row.try_get::<i64, _>("enabled").unwrap_or(1) != 0,
-                run_once: row.try_get::<i64, _>("run_once").unwrap_or(0) != 0,
-                timeout_secs: row
-                    .try_get::<Option<i64>, _>("timeout_secs")
-                    .ok()
-                    .flatten()
-                    .map(|t| t as u64),
+        let configs = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, run_once, timeout_secs
+                FROM cron_jobs
+                WHERE enabled = 1
+                ORDER BY created_at ASC
+                "#,
+            )
+            .fetch_all(pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| CronConfig {
+                        id: row.try_get("id").unwrap_or_default(),
+                        prompt: row.try_get("prompt").unwrap_or_default(),
+                        interval_secs: row.try_get::<i64, _>("interval_secs").unwrap_or(3600) as u64,
+                        delivery_target: row.try_get("delivery_target").unwrap_or_default(),
+                        active_hours: {
+                            let start: Option<i64> = row.try_get("active_start_hour").ok();
+                            let end: Option<i64> = row.try_get("active_end_hour").ok();
+                            match (start, end) {
+                                (Some(s), Some(e)) => Some((s as u8, e as u8)),
+                                _ => None,
+                            }
+                        },
+                        enabled: row.try_get::<i64, _>("enabled").unwrap_or(1) != 0,
+                        run_once: row.try_get::<i64, _>("run_once").unwrap_or(0) != 0,
+                        timeout_secs: row
+                            .try_get::<Option<i64>, _>("timeout_secs")
+                            .ok()
+                            .flatten()
+                            .map(|t| t as u64),
+                    })
+                    .collect()
             })
-            .collect();
+        })
+        .context("failed to load cron jobs")?;
 
         Ok(configs)
     }
 
     /// Delete a cron job.
     pub async fn delete(&self, id: &str) -> Result<()> {
-        sqlx::query("DELETE FROM cron_jobs WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
-            .await
-            .context("failed to delete cron job")?;
+        with_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM cron_jobs WHERE id = $1")
+                .bind(id)
+                .execute(pool)
+                .await
+                .map(drop)
+        })
+        .context("failed to delete cron job")?;
 
         Ok(())
     }
 
     /// Update the enabled state of a cron job (used by circuit breaker).
     pub async fn update_enabled(&self, id: &str, enabled: bool) -> Result<()> {
-        sqlx::query("UPDATE cron_jobs SET enabled = ? WHERE id = ?")
-            .bind(enabled as i64)
-            .bind(id)
-            .execute(&self.pool)
-            .await
-            .context("failed to update cron job enabled state")?;
+        with_pool!(&self.pool, |pool| {
+            sqlx::query("UPDATE cron_jobs SET enabled = $1 WHERE id = $2")
+                .bind(enabled as i64)
+                .bind(id)
+                .execute(pool)
+                .await
+                .map(drop)
+        })
+        .context("failed to update cron job enabled state")?;
 
         Ok(())
     }
@@ -127,18 +138,21 @@ impl CronStore {
     ) -> Result<()> {
         let execution_id = uuid::Uuid::new_v4().to_string();
 
-        sqlx::query(
-            r#"
-            INSERT INTO cron_executions (id, cron_id, success, result_summary)
-            VALUES (?, ?, ?, ?)
-            "#,
-        )
-        .bind(&execution_id)
-        .bind(cron_id)
-        .bind(success as i64)
-        .bind(result_summary)
-        .execute(&self.pool)
-        .await
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                INSERT INTO cron_executions (id, cron_id, success, result_summary)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(&execution_id)
+            .bind(cron_id)
+            .bind(success as i64)
+            .bind(result_summary)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
         .context("failed to log cron execution")?;
 
         Ok(())
@@ -146,41 +160,43 @@ impl CronStore {
 
     /// Load all cron job configurations (including disabled).
     pub async fn load_all_unfiltered(&self) -> Result<Vec<CronConfig>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, run_once, timeout_secs
-            FROM cron_jobs
-            ORDER BY created_at ASC
-            "#,
-        )
-        .fetch_all(&self.pool)
-        .await
-        .context("failed to load cron jobs")?;
-
-        let configs = rows
-            .into_iter()
-            .map(|row| CronConfig {
-                id: row.try_get("id").unwrap_or_default(),
-                prompt: row.try_get("prompt").unwrap_or_default(),
-                interval_secs: row.try_get::<i64, _>("interval_secs").unwrap_or(3600) as u64,
-                delivery_target: row.try_get("delivery_target").unwrap_or_default(),
-                active_hours: {
-                    let start: Option<i64> = row.try_get("active_start_hour").ok();
-                    let end: Option<i64> = row.try_get("active_end_hour").ok();
-                    match (start, end) {
-                        (Some(s), Some(e)) => Some((s as u8, e as u8)),
-                        _ => None,
-                    }
-                },
-                enabled: row.try_get::<i64, _>("enabled").unwrap_or(1) != 0,
-                run_once: row.try_get::<i64, _>("run_once").unwrap_or(0) != 0,
-                timeout_secs: row
-                    .try_get::<Option<i64>, _>("timeout_secs")
-                    .ok()
-                    .flatten()
-                    .map(|t| t as u64),
+        let configs = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, prompt, interval_secs, delivery_target, active_start_hour, active_end_hour, enabled, run_once, timeout_secs
+                FROM cron_jobs
+                ORDER BY created_at ASC
+                "#,
+            )
+            .fetch_all(pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| CronConfig {
+                        id: row.try_get("id").unwrap_or_default(),
+                        prompt: row.try_get("prompt").unwrap_or_default(),
+                        interval_secs: row.try_get::<i64, _>("interval_secs").unwrap_or(3600) as u64,
+                        delivery_target: row.try_get("delivery_target").unwrap_or_default(),
+                        active_hours: {
+                            let start: Option<i64> = row.try_get("active_start_hour").ok();
+                            let end: Option<i64> = row.try_get("active_end_hour").ok();
+                            match (start, end) {
+                                (Some(s), Some(e)) => Some((s as u8, e as u8)),
+                                _ => None,
+                            }
+                        },
+                        enabled: row.try_get::<i64, _>("enabled").unwrap_or(1) != 0,
+                        run_once: row.try_get::<i64, _>("run_once").unwrap_or(0) != 0,
+                        timeout_secs: row
+                            .try_get::<Option<i64>, _>("timeout_secs")
+                            .ok()
+                            .flatten()
+                            .map(|t| t as u64),
+                    })
+                    .collect()
             })
-            .collect();
+        })
+        .context("failed to load cron jobs")?;
 
         Ok(configs)
     }
@@ -191,92 +207,106 @@ impl CronStore {
         cron_id: &str,
         limit: i64,
     ) -> Result<Vec<CronExecutionEntry>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, executed_at, success, result_summary
-            FROM cron_executions
-            WHERE cron_id = ?
-            ORDER BY executed_at DESC
-            LIMIT ?
-            "#,
-        )
-        .bind(cron_id)
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await
-        .context("failed to load cron executions")?;
-
-        let entries = rows
-            .into_iter()
-            .map(|row| CronExecutionEntry {
-                id: row.try_get("id").unwrap_or_default(),
-                executed_at: row.try_get("executed_at").unwrap_or_default(),
-                success: row.try_get::<i64, _>("success").unwrap_or(0) != 0,
-                result_summary: row.try_get("result_summary").ok(),
+        let entries = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, executed_at, success, result_summary
+                FROM cron_executions
+                WHERE cron_id = $1
+                ORDER BY executed_at DESC
+                LIMIT $2
+                "#,
+            )
+            .bind(cron_id)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| CronExecutionEntry {
+                        id: row.try_get("id").unwrap_or_default(),
+                        executed_at: timestamp_text(&row, "executed_at")
+                            .ok()
+                            .flatten()
+                            .unwrap_or_default(),
+                        success: row.try_get::<i64, _>("success").unwrap_or(0) != 0,
+                        result_summary: row.try_get("result_summary").ok(),
+                    })
+                    .collect()
             })
-            .collect();
+        })
+        .context("failed to load cron executions")?;
 
         Ok(entries)
     }
 
     /// Load recent execution history across all cron jobs.
     pub async fn load_all_executions(&self, limit: i64) -> Result<Vec<CronExecutionEntry>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, cron_id, executed_at, success, result_summary
-            FROM cron_executions
-            ORDER BY executed_at DESC
-            LIMIT ?
-            "#,
-        )
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await
-        .context("failed to load cron executions")?;
-
-        let entries = rows
-            .into_iter()
-            .map(|row| CronExecutionEntry {
-                id: row.try_get("id").unwrap_or_default(),
-                executed_at: row.try_get("executed_at").unwrap_or_default(),
-                success: row.try_get::<i64, _>("success").unwrap_or(0) != 0,
-                result_summary: row.try_get("result_summary").ok(),
+        let entries = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, cron_id, executed_at, success, result_summary
+                FROM cron_executions
+                ORDER BY executed_at DESC
+                LIMIT $1
+                "#,
+            )
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|row| CronExecutionEntry {
+                        id: row.try_get("id").unwrap_or_default(),
+                        executed_at: timestamp_text(&row, "executed_at")
+                            .ok()
+                            .flatten()
+                            .unwrap_or_default(),
+                        success: row.try_get::<i64, _>("success").unwrap_or(0) != 0,
+                        result_summary: row.try_get("result_summary").ok(),
+                    })
+                    .collect()
             })
-            .collect();
+        })
+        .context("failed to load cron executions")?;
 
         Ok(entries)
     }
 
     /// Get execution stats for a cron job (success count, failure count, last execution).
     pub async fn get_execution_stats(&self, cron_id: &str) -> Result<CronExecutionStats> {
-        let row = sqlx::query(
-            r#"
-            SELECT
-                SUM(CASE WHEN success = 1 THEN 1 ELSE 0 END) as success_count,
-                SUM(CASE WHEN success = 0 THEN 1 ELSE 0 END) as failure_count,
-                MAX(executed_at) as last_executed_at
-            FROM cron_executions
-            WHERE cron_id = ?
-            "#,
-        )
-        .bind(cron_id)
-        .fetch_optional(&self.pool)
-        .await
-        .context("failed to load cron execution stats")?;
-
-        if let Some(row) = row {
-            let success_count: i64 = row.try_get("success_count").unwrap_or(0);
-            let failure_count: i64 = row.try_get("failure_count").unwrap_or(0);
-            let last_executed_at: Option<String> = row.try_get("last_executed_at").ok();
+        let stats = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT
+                    COALESCE(SUM(CASE WHEN success = 1 THEN 1 ELSE 0 END), 0) as success_count,
+                    COALESCE(SUM(CASE WHEN success = 0 THEN 1 ELSE 0 END), 0) as failure_count,
+                    MAX(executed_at) as last_executed_at
+                FROM cron_executions
+                WHERE cron_id = $1
+                "#,
+            )
+            .bind(cron_id)
+            .fetch_optional(pool)
+            .await
+            .map(|row| {
+                row.map(|row| {
+                    let success_count: i64 = row.try_get("success_count").unwrap_or(0);
+                    let failure_count: i64 = row.try_get("failure_count").unwrap_or(0);
+                    let last_executed_at = timestamp_text(&row, "last_executed_at").ok().flatten();
 
-            Ok(CronExecutionStats {
-                success_count: success_count as u64,
-                failure_count: failure_count as u64,
-                last_executed_at,
+                    CronExecutionStats {
+                        success_count: success_count as u64,
+                        failure_count: failure_count as u64,
+                        last_executed_at,
+                    }
+                })
+                .unwrap_or_default()
             })
-        } else {
-            Ok(CronExecutionStats::default())
-        }
+        })
+        .context("failed to load cron execution stats")?;
+
+        Ok(stats)
     }
 }
 
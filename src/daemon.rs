@@ -135,6 +135,7 @@ pub fn init_background_tracing(
 
     let filter = build_env_filter(debug);
     let fmt_layer = tracing_subscriber::fmt::layer()
+        .fmt_fields(crate::logging::RedactingFields::new(telemetry.log_content))
         .with_writer(non_blocking)
         .with_ansi(false);
 
@@ -144,6 +145,7 @@ pub fn init_background_tracing(
             tracing_subscriber::registry()
                 .with(filter)
                 .with(fmt_layer)
+                .with(crate::logging::CorrelationLayer::new(telemetry.log_content))
                 .with(tracing_opentelemetry::layer().with_tracer(tracer))
                 .init();
             Some(provider)
@@ -152,6 +154,7 @@ pub fn init_background_tracing(
             tracing_subscriber::registry()
                 .with(filter)
                 .with(fmt_layer)
+                .with(crate::logging::CorrelationLayer::new(telemetry.log_content))
                 .init();
             None
         }
@@ -166,7 +169,8 @@ pub fn init_foreground_tracing(
     telemetry: &TelemetryConfig,
 ) -> Option<SdkTracerProvider> {
     let filter = build_env_filter(debug);
-    let fmt_layer = tracing_subscriber::fmt::layer();
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .fmt_fields(crate::logging::RedactingFields::new(telemetry.log_content));
 
     match build_otlp_provider(telemetry) {
         Some(provider) => {
@@ -174,6 +178,7 @@ pub fn init_foreground_tracing(
             tracing_subscriber::registry()
                 .with(filter)
                 .with(fmt_layer)
+                .with(crate::logging::CorrelationLayer::new(telemetry.log_content))
                 .with(tracing_opentelemetry::layer().with_tracer(tracer))
                 .init();
             Some(provider)
@@ -182,20 +187,55 @@ pub fn init_foreground_tracing(
             tracing_subscriber::registry()
                 .with(filter)
                 .with(fmt_layer)
+                .with(crate::logging::CorrelationLayer::new(telemetry.log_content))
                 .init();
             None
         }
     }
 }
 
-fn build_env_filter(debug: bool) -> tracing_subscriber::EnvFilter {
-    if debug {
-        tracing_subscriber::EnvFilter::new("debug")
-    } else {
-        tracing_subscriber::EnvFilter::new("info")
+/// Initialize tracing for headless mode, where stdout carries replies.
+///
+/// Same as foreground mode, but logs go to stderr.
+pub fn init_headless_tracing(
+    debug: bool,
+    telemetry: &TelemetryConfig,
+) -> Option<SdkTracerProvider> {
+    let filter = build_env_filter(debug);
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .fmt_fields(crate::logging::RedactingFields::new(telemetry.log_content))
+        .with_writer(std::io::stderr);
+
+    match build_otlp_provider(telemetry) {
+        Some(provider) => {
+            let tracer = provider.tracer("spacebot");
+            tracing_subscriber::registry()
+                .with(filter)
+                .with(fmt_layer)
+                .with(crate::logging::CorrelationLayer::new(telemetry.log_content))
+                .with(tracing_opentelemetry::layer().with_tracer(tracer))
+                .init();
+            Some(provider)
+        }
+        None => {
+            tracing_subscriber::registry()
+                .with(filter)
+                .with(fmt_layer)
+                .with(crate::logging::CorrelationLayer::new(telemetry.log_content))
+                .init();
+            None
+        }
     }
 }
 
+/// The log filter, behind a reload handle so `!admin loglevel` can change it.
+fn build_env_filter(
+    debug: bool,
+) -> tracing_subscriber::reload::Layer<tracing_subscriber::EnvFilter, tracing_subscriber::Registry>
+{
+    crate::log_levels::reloadable(if debug { "debug" } else { "info" })
+}
+
 /// Build an OTLP `SdkTracerProvider` when an endpoint is configured.
 ///
 /// Returns `None` if neither the config field nor the `OTEL_EXPORTER_OTLP_ENDPOINT`
@@ -249,8 +289,15 @@ fn build_otlp_provider(telemetry: &TelemetryConfig) -> Option<SdkTracerProvider>
         )
         .build();
 
-    let provider = SdkTracerProvider::builder()
-        .with_span_processor(batch_processor)
+    let builder = SdkTracerProvider::builder();
+    let builder = match &telemetry.tail_sampling {
+        Some(config) => builder.with_span_processor(crate::tail_sampling::TailSampler::new(
+            batch_processor,
+            config,
+        )),
+        None => builder.with_span_processor(batch_processor),
+    };
+    let provider = builder
         .with_resource(resource)
         .with_sampler(sampler)
         .build();
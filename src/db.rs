@@ -1,14 +1,226 @@
 //! Database connection management and migrations.
+//!
+//! Relational data (memories, conversations, channels, cron, cortex events,
+//! ingestion progress) lives in SQLite under the agent's data directory by
+//! default. With `[database] backend = "postgres"`, it goes to a shared
+//! Postgres database instead, one schema per agent, so it survives hosts with
+//! ephemeral filesystems. Vector embeddings (LanceDB) and key-value settings
+//! (redb) stay in the data directory either way.
+//!
+//! SQLite runs in WAL mode with one writer connection and a pool of read-only
+//! connections. Writes queue for the writer instead of racing each other for
+//! the file lock, and searches read alongside them, so a busy channel logging
+//! messages doesn't make `!search` or memory recall fail with `SQLITE_BUSY`.
+//! The writer runs `PRAGMA optimize` when it closes, so the query planner's
+//! statistics stay current across restarts.
+
+pub mod redis;
 
 use crate::error::{DbError, Result};
 use anyhow::Context as _;
+#[cfg(feature = "sql-postgres")]
+use sqlx::Executor as _;
 use sqlx::SqlitePool;
+#[cfg(feature = "sql-postgres")]
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
 use std::path::Path;
+#[cfg(feature = "sql-postgres")]
+use std::str::FromStr as _;
+use std::time::Duration;
+
+/// Which database holds relational data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DatabaseBackend {
+    /// A SQLite file per agent in its data directory.
+    #[default]
+    Sqlite,
+    /// A schema per agent in one Postgres database.
+    Postgres,
+}
+
+impl std::str::FromStr for DatabaseBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "sqlite" => Ok(Self::Sqlite),
+            "postgres" => Ok(Self::Postgres),
+            other => Err(format!(
+                "unknown database backend {other:?}: must be \"sqlite\" or \"postgres\""
+            )),
+        }
+    }
+}
+
+/// Relational storage settings (instance-level, under `[database]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatabaseConfig {
+    pub backend: DatabaseBackend,
+    /// `postgres://[user[:password]@]host[:port]/database`. Required for `postgres`.
+    pub url: Option<String>,
+    /// Pool size per agent: Postgres connections, or SQLite read-only
+    /// connections alongside the single writer.
+    pub max_connections: u32,
+    /// How long a SQLite connection waits on a locked database before
+    /// failing with `SQLITE_BUSY`.
+    pub busy_timeout: Duration,
+    /// How often buffered conversation writes are flushed.
+    pub batch_flush_interval: Duration,
+    /// Buffered conversation writes that trigger a flush before the interval.
+    pub batch_max_rows: usize,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            backend: DatabaseBackend::Sqlite,
+            url: None,
+            max_connections: 5,
+            busy_timeout: Duration::from_secs(5),
+            batch_flush_interval: Duration::from_secs(1),
+            batch_max_rows: 100,
+        }
+    }
+}
+
+/// Connection pool for an agent's relational data.
+///
+/// Stores write their SQL once in the dialect both backends accept (`$N`
+/// placeholders, `ON CONFLICT`, `TRUE`/`FALSE`) and run it through
+/// [`with_pool!`], which expands the query for each driver. Queries that only
+/// read can use [`with_read_pool!`] instead, so they don't wait on writes.
+#[derive(Debug, Clone)]
+pub enum SqlPool {
+    /// `writer` holds one connection, so writes queue instead of contending
+    /// for the file lock. `reader` holds read-only connections.
+    Sqlite {
+        writer: SqlitePool,
+        reader: SqlitePool,
+    },
+    #[cfg(feature = "sql-postgres")]
+    Postgres(PgPool),
+}
+
+impl SqlPool {
+    pub fn backend(&self) -> DatabaseBackend {
+        match self {
+            Self::Sqlite { .. } => DatabaseBackend::Sqlite,
+            #[cfg(feature = "sql-postgres")]
+            Self::Postgres(_) => DatabaseBackend::Postgres,
+        }
+    }
+
+    /// Close every connection. The SQLite writer closes last and runs
+    /// `PRAGMA optimize` as it does.
+    pub async fn close(&self) {
+        match self {
+            Self::Sqlite { writer, reader } => {
+                reader.close().await;
+                writer.close().await;
+            }
+            #[cfg(feature = "sql-postgres")]
+            Self::Postgres(pool) => pool.close().await,
+        }
+    }
+
+    /// SQL for the timestamp bound at `$index` with [`Self::timestamp_param`].
+    /// SQLite compares the stored `YYYY-MM-DD HH:MM:SS` text directly;
+    /// Postgres needs the text cast to a timestamp.
+    pub(crate) fn timestamp_placeholder(&self, index: usize) -> String {
+        match self.backend() {
+            DatabaseBackend::Sqlite => format!("${index}"),
+            DatabaseBackend::Postgres => format!("CAST(${index} AS TIMESTAMPTZ)"),
+        }
+    }
+
+    /// `time` as a bind parameter for a timestamp column.
+    pub(crate) fn timestamp_param(&self, time: chrono::DateTime<chrono::Utc>) -> String {
+        match self.backend() {
+            DatabaseBackend::Sqlite => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            DatabaseBackend::Postgres => time.format("%Y-%m-%d %H:%M:%S+00:00").to_string(),
+        }
+    }
+}
+
+/// Reads and writes share `pool`.
+impl From<SqlitePool> for SqlPool {
+    fn from(pool: SqlitePool) -> Self {
+        Self::Sqlite {
+            writer: pool.clone(),
+            reader: pool,
+        }
+    }
+}
+
+#[cfg(feature = "sql-postgres")]
+impl From<PgPool> for SqlPool {
+    fn from(pool: PgPool) -> Self {
+        Self::Postgres(pool)
+    }
+}
+
+/// Run `$body` against the concrete pool inside a [`SqlPool`], bound to `$pool`.
+/// The body is compiled once per backend, so it must only use SQL and types
+/// both drivers support.
+macro_rules! with_pool {
+    ($sql_pool:expr, |$pool:ident| $body:expr) => {
+        match $sql_pool {
+            $crate::db::SqlPool::Sqlite { writer: $pool, .. } => $body,
+            #[cfg(feature = "sql-postgres")]
+            $crate::db::SqlPool::Postgres($pool) => $body,
+        }
+    };
+}
+
+/// Like [`with_pool!`], but binds SQLite's read-only connections. The body
+/// must not write; on Postgres it runs against the same pool.
+macro_rules! with_read_pool {
+    ($sql_pool:expr, |$pool:ident| $body:expr) => {
+        match $sql_pool {
+            $crate::db::SqlPool::Sqlite { reader: $pool, .. } => $body,
+            #[cfg(feature = "sql-postgres")]
+            $crate::db::SqlPool::Postgres($pool) => $body,
+        }
+    };
+}
+
+pub(crate) use {with_pool, with_read_pool};
+
+/// A column type both drivers can decode. Lets row helpers be generic over
+/// the row type without repeating the decode bounds for every column.
+pub(crate) trait Column<DB: sqlx::Database>:
+    for<'r> sqlx::Decode<'r, DB> + sqlx::Type<DB>
+{
+}
+
+impl<DB: sqlx::Database, T> Column<DB> for T where T: for<'r> sqlx::Decode<'r, DB> + sqlx::Type<DB> {}
+
+/// Read a timestamp column as `YYYY-MM-DD HH:MM:SS` text. SQLite stores
+/// timestamps as text, Postgres as `TIMESTAMPTZ`.
+pub(crate) fn timestamp_text<R>(
+    row: &R,
+    column: &str,
+) -> std::result::Result<Option<String>, sqlx::Error>
+where
+    R: sqlx::Row,
+    for<'c> &'c str: sqlx::ColumnIndex<R>,
+    String: Column<R::Database>,
+    chrono::DateTime<chrono::Utc>: Column<R::Database>,
+{
+    match row.try_get::<Option<String>, _>(column) {
+        Ok(text) => Ok(text),
+        Err(sqlx::Error::ColumnDecode { .. }) => Ok(row
+            .try_get::<Option<chrono::DateTime<chrono::Utc>>, _>(column)?
+            .map(|timestamp| timestamp.format("%Y-%m-%d %H:%M:%S").to_string())),
+        Err(error) => Err(error),
+    }
+}
 
 /// Database connections bundle.
 pub struct Db {
-    /// SQLite pool for relational data.
-    pub sqlite: SqlitePool,
+    /// Pool for relational data.
+    pub sql: SqlPool,
 
     /// LanceDB connection for vector storage.
     pub lance: lancedb::Connection,
@@ -21,32 +233,9 @@ use std::sync::Arc;
 
 impl Db {
     /// Connect to all databases and run migrations.
-    pub async fn connect(data_dir: &Path) -> Result<Self> {
-        // SQLite
-        let sqlite_url = format!("sqlite:{}?mode=rwc", data_dir.join("spacebot.db").display());
-        let sqlite = SqlitePool::connect(&sqlite_url)
-            .await
-            .with_context(|| "failed to connect to SQLite")?;
-
-        // Run migrations
-        sqlx::migrate!("./migrations")
-            .run(&sqlite)
-            .await
-            .with_context(|| "failed to run database migrations")?;
-
-        // LanceDB
-        let lance_path = data_dir.join("lancedb");
-        std::fs::create_dir_all(&lance_path).with_context(|| {
-            format!(
-                "failed to create LanceDB directory: {}",
-                lance_path.display()
-            )
-        })?;
-
-        let lance = lancedb::connect(lance_path.to_str().unwrap_or("./lancedb"))
-            .execute()
-            .await
-            .map_err(|e| DbError::LanceConnect(e.to_string()))?;
+    pub async fn connect(data_dir: &Path, config: &DatabaseConfig, agent_id: &str) -> Result<Self> {
+        let sql = connect_sql(data_dir, config, agent_id).await?;
+        let lance = connect_lance(data_dir).await?;
 
         // Redb
         let redb_path = data_dir.join("config.redb");
@@ -54,7 +243,7 @@ impl Db {
             .with_context(|| format!("failed to create redb at: {}", redb_path.display()))?;
 
         Ok(Self {
-            sqlite,
+            sql,
             lance,
             redb: Arc::new(redb),
         })
@@ -62,7 +251,202 @@ impl Db {
 
     /// Close all database connections gracefully.
     pub async fn close(self) {
-        self.sqlite.close().await;
+        self.sql.close().await;
         // LanceDB and redb close automatically when dropped
     }
 }
+
+/// Connect to the agent's relational database and run migrations. Unlike
+/// [`Db::connect`], this leaves redb alone, so it works while the daemon holds
+/// the redb lock.
+pub async fn connect_sql(
+    data_dir: &Path,
+    config: &DatabaseConfig,
+    #[cfg_attr(not(feature = "sql-postgres"), allow(unused_variables))] agent_id: &str,
+) -> Result<SqlPool> {
+    match (config.backend, &config.url) {
+        #[cfg(feature = "sql-postgres")]
+        (DatabaseBackend::Postgres, Some(url)) => Ok(SqlPool::Postgres(
+            connect_postgres(url, config.max_connections, agent_id).await?,
+        )),
+        #[cfg(not(feature = "sql-postgres"))]
+        (DatabaseBackend::Postgres, Some(_)) => Err(DbError::Postgres(
+            "this build doesn't include Postgres; rebuild with `--features sql-postgres`".into(),
+        )
+        .into()),
+        _ => connect_sqlite(data_dir, config).await,
+    }
+}
+
+/// Connect to the agent's LanceDB directory, creating it if needed.
+pub async fn connect_lance(data_dir: &Path) -> Result<lancedb::Connection> {
+    let lance_path = data_dir.join("lancedb");
+    std::fs::create_dir_all(&lance_path).with_context(|| {
+        format!(
+            "failed to create LanceDB directory: {}",
+            lance_path.display()
+        )
+    })?;
+
+    let lance = lancedb::connect(lance_path.to_str().unwrap_or("./lancedb"))
+        .execute()
+        .await
+        .map_err(|e| DbError::LanceConnect(e.to_string()))?;
+    Ok(lance)
+}
+
+/// Open the agent's SQLite file in WAL mode and run migrations on the writer
+/// before the readers connect.
+async fn connect_sqlite(data_dir: &Path, config: &DatabaseConfig) -> Result<SqlPool> {
+    let options = sqlite_options(&data_dir.join("spacebot.db"), config.busy_timeout);
+
+    let writer = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(
+            options
+                .clone()
+                .create_if_missing(true)
+                .journal_mode(SqliteJournalMode::Wal)
+                .optimize_on_close(true, OPTIMIZE_ANALYSIS_LIMIT),
+        )
+        .await
+        .with_context(|| "failed to connect to SQLite")?;
+
+    sqlx::migrate!("./migrations")
+        .run(&writer)
+        .await
+        .with_context(|| "failed to run database migrations")?;
+
+    let reader = SqlitePoolOptions::new()
+        .max_connections(config.max_connections)
+        .connect_with(options.read_only(true))
+        .await
+        .with_context(|| "failed to open SQLite read connections")?;
+
+    Ok(SqlPool::Sqlite { writer, reader })
+}
+
+/// Rows `PRAGMA optimize` samples per index, as SQLite recommends, so it
+/// stays quick on large databases.
+const OPTIMIZE_ANALYSIS_LIMIT: u32 = 400;
+
+/// Settings shared by the writer and readers. `NORMAL` sync is durable in WAL
+/// mode except for the last commits before a power loss.
+fn sqlite_options(path: &Path, busy_timeout: Duration) -> SqliteConnectOptions {
+    SqliteConnectOptions::new()
+        .filename(path)
+        .busy_timeout(busy_timeout)
+        .synchronous(SqliteSynchronous::Normal)
+}
+
+/// Connect to the agent's schema, creating it on first use. Sessions run in
+/// UTC so date functions agree with SQLite's.
+#[cfg(feature = "sql-postgres")]
+async fn connect_postgres(url: &str, max_connections: u32, agent_id: &str) -> Result<PgPool> {
+    let options = PgConnectOptions::from_str(url)
+        .map_err(|error| DbError::Postgres(format!("can't parse database.url: {error}")))?;
+    let schema = quote_identifier(agent_id);
+
+    let session_setup = format!("SET search_path TO {schema}; SET TimeZone TO 'UTC'");
+    let pool = PgPoolOptions::new()
+        .max_connections(max_connections)
+        .after_connect(move |connection, _| {
+            let session_setup = session_setup.clone();
+            Box::pin(async move {
+                connection.execute(session_setup.as_str()).await?;
+                Ok(())
+            })
+        })
+        .connect_with(options)
+        .await
+        .map_err(|error| DbError::Postgres(format!("can't connect: {error}")))?;
+
+    pool.execute(format!("CREATE SCHEMA IF NOT EXISTS {schema}").as_str())
+        .await
+        .with_context(|| format!("failed to create Postgres schema {schema}"))?;
+
+    sqlx::migrate!("./migrations/postgres")
+        .run(&pool)
+        .await
+        .with_context(|| format!("failed to run Postgres migrations in schema {schema}"))?;
+
+    Ok(pool)
+}
+
+/// Quote a Postgres identifier, so any agent id works as a schema name.
+#[cfg(feature = "sql-postgres")]
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "sql-postgres")]
+    fn identifiers_are_quoted() {
+        assert_eq!(quote_identifier("main"), "\"main\"");
+        assert_eq!(quote_identifier("we\"ird"), "\"we\"\"ird\"");
+    }
+
+    #[tokio::test]
+    #[cfg_attr(not(feature = "sql-postgres"), allow(irrefutable_let_patterns))]
+    async fn sqlite_reads_alongside_an_open_write() {
+        let data_dir = tempfile::tempdir().expect("tempdir");
+        let pool = connect_sql(data_dir.path(), &DatabaseConfig::default(), "main")
+            .await
+            .expect("SQLite should open");
+        let SqlPool::Sqlite { writer, reader } = &pool else {
+            unreachable!("default backend is SQLite");
+        };
+
+        let mode: String = sqlx::query_scalar("PRAGMA journal_mode")
+            .fetch_one(reader)
+            .await
+            .expect("pragma should run");
+        assert_eq!(mode, "wal");
+
+        // A reader isn't blocked by a write transaction that's still open.
+        let mut write = writer.begin().await.expect("write transaction");
+        sqlx::query("CREATE TABLE scratch (id INTEGER)")
+            .execute(&mut *write)
+            .await
+            .expect("write should run");
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master")
+            .fetch_one(reader)
+            .await
+            .expect("read should not wait on the writer");
+        assert!(count > 0);
+        write.commit().await.expect("commit");
+
+        assert!(
+            sqlx::query("CREATE TABLE other (id INTEGER)")
+                .execute(reader)
+                .await
+                .is_err(),
+            "readers are read-only"
+        );
+
+        pool.close().await;
+    }
+
+    #[tokio::test]
+    async fn timestamps_read_as_text_from_sqlite() {
+        let pool = SqlitePool::connect("sqlite::memory:")
+            .await
+            .expect("in-memory SQLite");
+        let row = sqlx::query("SELECT '2026-03-09 23:59:00' AS at, NULL AS never")
+            .fetch_one(&pool)
+            .await
+            .expect("query should run");
+        assert_eq!(
+            timestamp_text(&row, "at").expect("text should decode"),
+            Some("2026-03-09 23:59:00".into())
+        );
+        assert_eq!(
+            timestamp_text(&row, "never").expect("null should decode"),
+            None
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! Async Redis client.
+//!
+//! A thin wrapper over the `redis` crate's connection manager: one
+//! multiplexed connection per client, opened on first use and reopened in the
+//! background after Redis restarts, so concurrent callers don't queue behind
+//! each other. Replies are flattened into [`Reply`], which covers what
+//! Spacebot keeps in Redis: a few string and counter commands per LLM call and
+//! the job queue. `rediss://` URLs connect over TLS with the webpki roots.
+
+use crate::error::{DbError, Result};
+
+use redis::aio::{ConnectionManager, ConnectionManagerConfig};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A decoded reply.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+    Status(String),
+    Error(String),
+    Integer(i64),
+    /// `None` is the nil bulk string.
+    Bulk(Option<Vec<u8>>),
+    /// `None` is the nil array.
+    Array(Option<Vec<Reply>>),
+}
+
+impl Reply {
+    /// Status, bulk, or integer reply as text; `None` for nil and arrays.
+    pub fn into_string(self) -> Option<String> {
+        match self {
+            Self::Status(text) => Some(text),
+            Self::Bulk(Some(bytes)) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+            Self::Integer(value) => Some(value.to_string()),
+            Self::Error(_) | Self::Bulk(None) | Self::Array(_) => None,
+        }
+    }
+}
+
+impl From<redis::Value> for Reply {
+    fn from(value: redis::Value) -> Self {
+        match value {
+            redis::Value::Nil => Self::Bulk(None),
+            redis::Value::Int(value) => Self::Integer(value),
+            redis::Value::BulkString(bytes) => Self::Bulk(Some(bytes)),
+            redis::Value::Array(items) | redis::Value::Set(items) => {
+                Self::Array(Some(items.into_iter().map(Self::from).collect()))
+            }
+            redis::Value::SimpleString(text) => Self::Status(text),
+            redis::Value::Okay => Self::Status("OK".into()),
+            redis::Value::ServerError(error) => Self::Error(format!("{error:?}")),
+            // RESP3-only types; the connection speaks RESP2.
+            other => Self::Bulk(Some(format!("{other:?}").into_bytes())),
+        }
+    }
+}
+
+/// Redis connection settings plus the lazily opened connection. Clones share
+/// the connection.
+#[derive(Clone)]
+pub struct RedisClient {
+    client: redis::Client,
+    /// `host:port`, for errors and logs.
+    address: String,
+    connection: Arc<OnceCell<ConnectionManager>>,
+    command_timeout: Duration,
+}
+
+impl std::fmt::Debug for RedisClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisClient")
+            .field("address", &self.address)
+            .field("command_timeout", &self.command_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RedisClient {
+    /// Parse `redis[s]://[[username]:password@]host[:port][/database]`.
+    /// Doesn't connect.
+    pub fn from_url(url: &str) -> Result<Self> {
+        let invalid = |reason: &str| DbError::Redis(format!("can't use redis URL: {reason}"));
+        let parsed = reqwest::Url::parse(url).map_err(|error| invalid(&error.to_string()))?;
+        match parsed.scheme() {
+            "redis" | "rediss" => {}
+            other => return Err(invalid(&format!("unknown scheme {other:?}")).into()),
+        }
+        let host = parsed
+            .host_str()
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| invalid("missing host"))?;
+        let database = parsed.path().trim_start_matches('/');
+        if !database.is_empty() && database.parse::<u32>().is_err() {
+            return Err(invalid(&format!("database {database:?} is not a number")).into());
+        }
+        let client = redis::Client::open(url).map_err(|error| invalid(&error.to_string()))?;
+
+        Ok(Self {
+            client,
+            address: format!("{host}:{}", parsed.port().unwrap_or(6379)),
+            connection: Arc::new(OnceCell::new()),
+            command_timeout: COMMAND_TIMEOUT,
+        })
+    }
+
+    /// Give up on each command after `timeout` instead of the default 5s.
+    /// Doesn't cover opening the connection.
+    pub fn with_command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = timeout;
+        self
+    }
+
+    /// Whether the connection has been opened. Once it has, the connection
+    /// manager reopens it by itself.
+    pub fn is_connected(&self) -> bool {
+        self.connection.initialized()
+    }
+
+    /// Open the connection if it isn't open yet.
+    pub async fn connect(&self) -> Result<()> {
+        self.connection().await.map(|_| ())
+    }
+
+    async fn connection(&self) -> Result<ConnectionManager> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| async {
+                let config = ConnectionManagerConfig::new()
+                    .set_connection_timeout(CONNECT_TIMEOUT)
+                    .set_response_timeout(self.command_timeout)
+                    .set_number_of_retries(1);
+                ConnectionManager::new_with_config(self.client.clone(), config)
+                    .await
+                    .map_err(|error| {
+                        DbError::Redis(format!("can't connect to {}: {error}", self.address))
+                    })
+            })
+            .await?;
+        Ok(connection.clone())
+    }
+
+    /// Run one command.
+    pub async fn command(&self, args: &[&str]) -> Result<Reply> {
+        let mut replies = self.pipeline(&[args]).await?;
+        Ok(replies.remove(0))
+    }
+
+    /// Send several commands in one round trip and return their replies in
+    /// order. Fails if any command returns an error reply. The commands go
+    /// out back to back, so a MULTI ... EXEC pipeline isn't interleaved with
+    /// other callers' commands.
+    pub async fn pipeline(&self, commands: &[&[&str]]) -> Result<Vec<Reply>> {
+        let mut connection = self.connection().await?;
+        let mut pipeline = redis::pipe();
+        for args in commands {
+            if let Some((name, args)) = args.split_first() {
+                pipeline.cmd(name).arg(args);
+            }
+        }
+
+        let values: Vec<redis::Value> =
+            match tokio::time::timeout(self.command_timeout, pipeline.query_async(&mut connection))
+                .await
+            {
+                Ok(Ok(values)) => values,
+                Ok(Err(error)) => {
+                    return Err(DbError::Redis(format!("{}: {error}", self.address)).into());
+                }
+                Err(_) => {
+                    return Err(DbError::Redis(format!("{} timed out", self.address)).into());
+                }
+            };
+        let replies: Vec<Reply> = values.into_iter().map(Reply::from).collect();
+        if let Some(message) = replies.iter().find_map(|reply| match reply {
+            Reply::Error(message) => Some(message.clone()),
+            _ => None,
+        }) {
+            return Err(DbError::Redis(message).into());
+        }
+        Ok(replies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _};
+
+    #[test]
+    fn urls_parse_into_connection_settings() {
+        let client = RedisClient::from_url("redis://:s%40cret@cache.internal:6380/2")
+            .expect("URL should parse");
+        assert_eq!(client.address, "cache.internal:6380");
+        let info = client.client.get_connection_info();
+        assert_eq!(info.redis.username, None);
+        assert_eq!(info.redis.password.as_deref(), Some("s@cret"));
+        assert_eq!(info.redis.db, 2);
+
+        let client = RedisClient::from_url("redis://localhost").expect("URL should parse");
+        assert_eq!(client.address, "localhost:6379");
+        assert_eq!(client.client.get_connection_info().redis.db, 0);
+
+        let client = RedisClient::from_url("rediss://cache.internal").expect("URL should parse");
+        assert!(matches!(
+            client.client.get_connection_info().addr,
+            redis::ConnectionAddr::TcpTls { .. }
+        ));
+        assert!(RedisClient::from_url("http://localhost").is_err());
+        assert!(RedisClient::from_url("redis://localhost/zero").is_err());
+    }
+
+    #[test]
+    fn values_flatten_into_replies() {
+        assert_eq!(
+            Reply::from(redis::Value::Array(vec![
+                redis::Value::BulkString(b"a".to_vec()),
+                redis::Value::Nil,
+                redis::Value::Int(1),
+                redis::Value::Okay,
+            ])),
+            Reply::Array(Some(vec![
+                Reply::Bulk(Some(b"a".to_vec())),
+                Reply::Bulk(None),
+                Reply::Integer(1),
+                Reply::Status("OK".into()),
+            ]))
+        );
+    }
+
+    /// Read one RESP command from a client.
+    async fn read_command<R>(reader: &mut R) -> Option<Vec<String>>
+    where
+        R: tokio::io::AsyncBufRead + Unpin,
+    {
+        let mut line = String::new();
+        reader.read_line(&mut line).await.ok()?;
+        let count: usize = line.trim_end().strip_prefix('*')?.parse().ok()?;
+        let mut args = Vec::with_capacity(count);
+        for _ in 0..count {
+            line.clear();
+            reader.read_line(&mut line).await.ok()?;
+            let length: usize = line.trim_end().strip_prefix('$')?.parse().ok()?;
+            let mut bytes = vec![0; length + 2];
+            reader.read_exact(&mut bytes).await.ok()?;
+            bytes.truncate(length);
+            args.push(String::from_utf8(bytes).ok()?);
+        }
+        Some(args)
+    }
+
+    #[tokio::test]
+    async fn commands_authenticate_and_round_trip() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("listener should bind");
+        let address = listener.local_addr().expect("listener has an address");
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("client should connect");
+            let mut stream = tokio::io::BufStream::new(stream);
+            let mut received = Vec::new();
+            while let Some(args) = read_command(&mut stream).await {
+                let reply: &[u8] = match args[0].as_str() {
+                    "GET" => b"$3\r\nbar\r\n",
+                    "BOGUS" => b"-ERR unknown command\r\n",
+                    _ => b"+OK\r\n",
+                };
+                stream.write_all(reply).await.expect("reply should send");
+                stream.flush().await.expect("reply should flush");
+                let done = args[0] == "BOGUS";
+                received.push(args.join(" "));
+                if done {
+                    break;
+                }
+            }
+            received
+        });
+
+        let client =
+            RedisClient::from_url(&format!("redis://:pw@{address}/3")).expect("URL should parse");
+        assert!(!client.is_connected());
+        let reply = client
+            .command(&["GET", "foo"])
+            .await
+            .expect("GET should work");
+        assert_eq!(reply.into_string().as_deref(), Some("bar"));
+        assert!(client.is_connected());
+        assert!(client.command(&["BOGUS"]).await.is_err());
+
+        let received = server.await.expect("server task should finish");
+        let position = |command: &str| {
+            received
+                .iter()
+                .position(|line| line == command)
+                .unwrap_or_else(|| panic!("{command} wasn't sent: {received:?}"))
+        };
+        assert!(position("AUTH pw") < position("SELECT 3"));
+        assert!(position("SELECT 3") < position("GET foo"));
+        assert_eq!(received.last().map(String::as_str), Some("BOGUS"));
+    }
+}
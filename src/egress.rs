@@ -0,0 +1,444 @@
+//! Network policy for tools' outbound HTTP.
+//!
+//! Every HTTP client a tool uses is a [`Client`] built with
+//! [`client_builder`], so the policy in `[egress]` is enforced in one place
+//! rather than per tool:
+//!
+//! - Host names are resolved through the policy. A name outside
+//!   `allowed_domains` doesn't resolve, and neither does one with any
+//!   address in `blocked_cidrs` (by default loopback, link-local, the cloud
+//!   metadata endpoints, and private networks) that `allowed_cidrs` doesn't
+//!   carve back out.
+//! - The connection goes to the addresses that were checked, never to a
+//!   second lookup, so a name that answers with a public address once and
+//!   an internal one the next time (DNS rebinding) gets nowhere.
+//! - URLs with a literal IP address never reach the resolver, so
+//!   [`RequestBuilder::send`] checks every request's URL before it goes out.
+//! - Redirects are checked hop by hop and capped at `max_redirects`.
+//! - `HTTP_PROXY` and friends are ignored: through a proxy, the target host
+//!   would never be resolved here.
+//!
+//! The policy is process-wide and swapped on config reload; clients read it
+//! on every request.
+
+use crate::listeners::IpNet;
+
+use arc_swap::ArcSwap;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+/// Ranges no tool reaches unless `blocked_cidrs` or `allowed_cidrs` says
+/// otherwise: loopback, "this network", link-local (where AWS, GCP, and
+/// Azure serve instance metadata), the metadata addresses outside it, and
+/// private networks (RFC 1918, carrier-grade NAT, and IPv6 unique local).
+pub const DEFAULT_BLOCKED_CIDRS: [&str; 12] = [
+    "127.0.0.0/8",
+    "::1/128",
+    "0.0.0.0/8",
+    "169.254.0.0/16",
+    "fe80::/10",
+    "100.100.100.200/32",
+    "fd00:ec2::254/128",
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "100.64.0.0/10",
+    "fc00::/7",
+];
+
+static POLICY: LazyLock<ArcSwap<EgressConfig>> =
+    LazyLock::new(|| ArcSwap::from_pointee(EgressConfig::default()));
+
+/// Egress settings, from `[egress]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EgressConfig {
+    /// Domains tools may reach. `*.example.com` also matches subdomains.
+    /// Empty allows any domain.
+    pub allowed_domains: Vec<String>,
+    /// Addresses tools may not connect to.
+    pub blocked_cidrs: Vec<IpNet>,
+    /// Exceptions to `blocked_cidrs`. Where ranges in both lists hold an
+    /// address, the more specific one decides.
+    pub allowed_cidrs: Vec<IpNet>,
+    /// Redirects followed before a request fails.
+    pub max_redirects: usize,
+}
+
+impl Default for EgressConfig {
+    fn default() -> Self {
+        Self {
+            allowed_domains: Vec::new(),
+            blocked_cidrs: DEFAULT_BLOCKED_CIDRS
+                .iter()
+                .map(|cidr| cidr.parse().expect("hardcoded CIDR"))
+                .collect(),
+            allowed_cidrs: Vec::new(),
+            max_redirects: 5,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EgressError {
+    #[error("only http and https URLs are allowed, not {0}")]
+    Scheme(String),
+
+    #[error("{0} isn't in egress.allowed_domains")]
+    Domain(String),
+
+    #[error("{host} resolves to {ip}, which egress.blocked_cidrs blocks")]
+    Address { host: String, ip: IpAddr },
+
+    #[error("stopped after {0} redirects (egress.max_redirects)")]
+    TooManyRedirects(usize),
+
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+impl EgressConfig {
+    fn allows_domain(&self, host: &str) -> bool {
+        self.allowed_domains.is_empty() || domain_matches(host, &self.allowed_domains)
+    }
+
+    fn check_ip(&self, host: &str, ip: IpAddr) -> Result<(), EgressError> {
+        let most_specific = |nets: &[IpNet]| {
+            nets.iter()
+                .filter(|net| net.contains(ip))
+                .map(IpNet::prefix_len)
+                .max()
+        };
+        let blocked = most_specific(&self.blocked_cidrs);
+        // A tie goes to the allow list, which the operator wrote on purpose.
+        if blocked.is_some() && most_specific(&self.allowed_cidrs) < blocked {
+            return Err(EgressError::Address {
+                host: host.to_string(),
+                ip,
+            });
+        }
+        Ok(())
+    }
+
+    fn check_url(&self, url: &reqwest::Url) -> Result<(), EgressError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(EgressError::Scheme(url.scheme().to_string()));
+        }
+        let host = url.host_str().unwrap_or_default();
+        // Names are checked when they resolve.
+        let Ok(ip) = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        else {
+            return Ok(());
+        };
+        let host = ip.to_string();
+        if !self.allows_domain(&host) {
+            return Err(EgressError::Domain(host));
+        }
+        self.check_ip(&host, ip)
+    }
+
+    /// Look `host` up and check every address it resolves to.
+    async fn resolve(
+        &self,
+        host: &str,
+    ) -> Result<Vec<SocketAddr>, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.allows_domain(host) {
+            return Err(EgressError::Domain(host.to_string()).into());
+        }
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+        for addr in &addrs {
+            self.check_ip(host, addr.ip())?;
+        }
+        Ok(addrs)
+    }
+}
+
+/// Replace the policy every tool client follows.
+pub fn set_policy(config: EgressConfig) {
+    POLICY.store(Arc::new(config));
+}
+
+/// Whether `host` is one of `domains`, where `*.example.com` also matches
+/// `example.com` and its subdomains.
+pub fn domain_matches(host: &str, domains: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    domains.iter().any(|domain| {
+        let domain = domain.to_ascii_lowercase();
+        match domain.strip_prefix("*.") {
+            Some(domain) => host == domain || host.ends_with(&format!(".{domain}")),
+            None => host == domain,
+        }
+    })
+}
+
+/// A client builder for tool requests, with the policy's resolver and
+/// redirect checks installed and proxies turned off.
+pub fn client_builder() -> ClientBuilder {
+    ClientBuilder(
+        reqwest::Client::builder()
+            .no_proxy()
+            .dns_resolver(Arc::new(PolicyResolver))
+            .redirect(reqwest::redirect::Policy::custom(|attempt| {
+                let policy = POLICY.load();
+                // `previous` starts with the original URL.
+                if attempt.previous().len() > policy.max_redirects {
+                    let error = EgressError::TooManyRedirects(policy.max_redirects);
+                    return attempt.error(error);
+                }
+                match policy.check_url(attempt.url()) {
+                    Ok(()) => attempt.follow(),
+                    Err(error) => attempt.error(error),
+                }
+            })),
+    )
+}
+
+/// Builds a [`Client`]. Only settings that can't loosen the policy are
+/// exposed.
+#[derive(Debug)]
+pub struct ClientBuilder(reqwest::ClientBuilder);
+
+impl ClientBuilder {
+    pub fn gzip(self, enable: bool) -> Self {
+        Self(self.0.gzip(enable))
+    }
+
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self(self.0.timeout(timeout))
+    }
+
+    pub fn user_agent<V>(self, value: V) -> Self
+    where
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<axum::http::Error>,
+    {
+        Self(self.0.user_agent(value))
+    }
+
+    pub fn build(self) -> reqwest::Result<Client> {
+        self.0.build().map(Client)
+    }
+}
+
+/// An HTTP client for tool requests. Its requests can only be sent through
+/// [`RequestBuilder::send`], which checks them against the policy.
+#[derive(Debug, Clone)]
+pub struct Client(reqwest::Client);
+
+impl Client {
+    pub fn get(&self, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        self.request(reqwest::Method::GET, url)
+    }
+
+    pub fn post(&self, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        self.request(reqwest::Method::POST, url)
+    }
+
+    pub fn put(&self, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        self.request(reqwest::Method::PUT, url)
+    }
+
+    pub fn request(&self, method: reqwest::Method, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        RequestBuilder(self.0.request(method, url))
+    }
+}
+
+/// A request from a [`Client`], mirroring [`reqwest::RequestBuilder`].
+#[derive(Debug)]
+pub struct RequestBuilder(reqwest::RequestBuilder);
+
+impl RequestBuilder {
+    pub fn header<K, V>(self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<axum::http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<axum::http::Error>,
+    {
+        Self(self.0.header(key, value))
+    }
+
+    pub fn headers(self, headers: HeaderMap) -> Self {
+        Self(self.0.headers(headers))
+    }
+
+    pub fn bearer_auth(self, token: impl std::fmt::Display) -> Self {
+        Self(self.0.bearer_auth(token))
+    }
+
+    pub fn basic_auth(
+        self,
+        username: impl std::fmt::Display,
+        password: Option<impl std::fmt::Display>,
+    ) -> Self {
+        Self(self.0.basic_auth(username, password))
+    }
+
+    pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        Self(self.0.query(query))
+    }
+
+    pub fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        Self(self.0.json(json))
+    }
+
+    pub fn form<T: Serialize + ?Sized>(self, form: &T) -> Self {
+        Self(self.0.form(form))
+    }
+
+    pub fn body(self, body: impl Into<reqwest::Body>) -> Self {
+        Self(self.0.body(body))
+    }
+
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self(self.0.timeout(timeout))
+    }
+
+    /// The request as reqwest's builder once its URL passes the policy, for
+    /// helpers that take one, like [`crate::llm::http::send`].
+    pub fn checked(self) -> Result<reqwest::RequestBuilder, EgressError> {
+        let (client, request) = self.0.build_split();
+        let request = request?;
+        POLICY.load().check_url(request.url())?;
+        Ok(reqwest::RequestBuilder::from_parts(client, request))
+    }
+
+    /// Check the request against the policy, then send it.
+    pub async fn send(self) -> Result<reqwest::Response, EgressError> {
+        Ok(self.checked()?.send().await?)
+    }
+}
+
+/// Resolves names the policy allows, failing the whole lookup if any
+/// address is blocked: a name mixing public and internal answers is either
+/// misconfigured or rebinding.
+struct PolicyResolver;
+
+impl reqwest::dns::Resolve for PolicyResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = POLICY.load_full().resolve(&host).await?;
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> reqwest::Url {
+        reqwest::Url::parse(url).expect("test URL")
+    }
+
+    #[test]
+    fn literal_addresses_and_schemes_are_checked() {
+        let policy = EgressConfig::default();
+        assert!(policy.check_url(&url("https://example.com/a")).is_ok());
+        assert!(policy.check_url(&url("http://93.184.216.34/")).is_ok());
+        for blocked in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://127.0.0.1:8080/",
+            "http://[::1]/",
+            "http://[::ffff:169.254.169.254]/",
+            "http://[fd00:ec2::254]/",
+            "http://10.0.0.5/",
+            "http://172.20.1.1/",
+            "http://192.168.1.1/",
+            "http://100.72.0.1/",
+            "http://[fd12:3456::1]/",
+            "file:///etc/passwd",
+        ] {
+            assert!(policy.check_url(&url(blocked)).is_err(), "{blocked}");
+        }
+
+        let allowlisted = EgressConfig {
+            allowed_domains: vec!["*.example.com".into(), "api.github.com".into()],
+            ..EgressConfig::default()
+        };
+        assert!(allowlisted.allows_domain("example.com"));
+        assert!(allowlisted.allows_domain("Docs.Example.com."));
+        assert!(allowlisted.allows_domain("api.github.com"));
+        assert!(!allowlisted.allows_domain("github.com"));
+        assert!(!allowlisted.allows_domain("badexample.com"));
+        assert!(
+            allowlisted
+                .check_url(&url("http://93.184.216.34/"))
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_to_literal_addresses_are_checked_before_sending() {
+        let client = client_builder().build().expect("tool client");
+        for blocked in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]:9/",
+        ] {
+            let error = client.get(blocked).send().await.expect_err(blocked);
+            assert!(matches!(error, EgressError::Address { .. }), "{error:?}");
+        }
+        let error = client
+            .get("ftp://example.com/")
+            .send()
+            .await
+            .expect_err("only http and https");
+        assert!(matches!(error, EgressError::Scheme(_)), "{error:?}");
+    }
+
+    #[tokio::test]
+    async fn names_resolving_to_blocked_addresses_fail() {
+        let client = client_builder().build().expect("tool client");
+        let error = client
+            .get("http://localhost:9/")
+            .send()
+            .await
+            .expect_err("localhost is blocked");
+        let blocked = std::iter::successors(Some(&error as &dyn std::error::Error), |error| {
+            error.source()
+        })
+        .any(|error| error.to_string().contains("egress.blocked_cidrs"));
+        assert!(blocked, "{error:?}");
+
+        let policy = EgressConfig::default();
+        for host in [
+            "10.0.0.5",
+            "172.31.255.254",
+            "192.168.1.1",
+            "100.64.0.1",
+            "fd12:3456::1",
+        ] {
+            assert!(policy.resolve(host).await.is_err(), "{host}");
+        }
+        assert!(policy.resolve("93.184.216.34").await.is_ok());
+        assert!(policy.resolve("172.32.0.1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn allowed_cidrs_carve_out_of_blocked_ones() {
+        let policy = EgressConfig {
+            allowed_cidrs: ["10.1.0.0/16", "100.64.0.0/10", "fc00::/7"]
+                .iter()
+                .map(|cidr| cidr.parse().expect("test CIDR"))
+                .collect(),
+            ..EgressConfig::default()
+        };
+        for host in ["10.1.2.3", "100.64.0.1", "fd12:3456::1"] {
+            assert!(policy.resolve(host).await.is_ok(), "{host}");
+        }
+        // The rest of 10/8, and metadata addresses inside an allowed range,
+        // stay blocked.
+        for host in ["10.2.0.1", "100.100.100.200", "fd00:ec2::254"] {
+            assert!(policy.resolve(host).await.is_err(), "{host}");
+        }
+        assert!(policy.check_url(&url("http://[fd00:ec2::254]/")).is_err());
+        assert!(policy.check_url(&url("http://10.1.2.3/")).is_ok());
+    }
+}
@@ -0,0 +1,245 @@
+//! Outgoing email for the `email` channel tool, over SMTP or SendGrid.
+//!
+//! Messages are plain text. Recipients are checked against
+//! `allowed_recipients` before anything is sent, whatever the tool asks for.
+
+pub mod sendgrid;
+pub mod smtp;
+
+use crate::config::{EmailConfig, EmailProvider};
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("'{0}' is not on the recipient allowlist")]
+    NotAllowed(String),
+
+    #[error("'{0}' is not a valid email address")]
+    InvalidAddress(String),
+
+    #[error("email request failed: {0}")]
+    Request(String),
+
+    #[error("mail server rejected {command}: {reply}")]
+    Rejected { command: String, reply: String },
+}
+
+impl From<reqwest::Error> for EmailError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Request(error.to_string())
+    }
+}
+
+impl From<crate::egress::EgressError> for EmailError {
+    fn from(error: crate::egress::EgressError) -> Self {
+        Self::Request(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for EmailError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Request(error.to_string())
+    }
+}
+
+/// A message ready to send.
+#[derive(Debug, Clone)]
+pub struct OutgoingEmail {
+    /// Bare addresses.
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Client for the configured email provider.
+#[derive(Debug, Clone)]
+pub struct EmailClient {
+    http: crate::egress::Client,
+    config: EmailConfig,
+}
+
+impl EmailClient {
+    /// Returns `None` when the tool is disabled.
+    pub fn new(config: EmailConfig) -> Option<Self> {
+        if !config.is_enabled() {
+            return None;
+        }
+        let http = crate::egress::client_builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()
+            .expect("hardcoded reqwest client config");
+        Some(Self { http, config })
+    }
+
+    pub fn config(&self) -> &EmailConfig {
+        &self.config
+    }
+
+    /// Check every recipient and send `email`.
+    pub async fn send(&self, email: &OutgoingEmail) -> Result<(), EmailError> {
+        for address in &email.to {
+            if !is_valid_address(address) {
+                return Err(EmailError::InvalidAddress(address.clone()));
+            }
+            if !self.config.allows(address) {
+                return Err(EmailError::NotAllowed(address.clone()));
+            }
+        }
+
+        match self
+            .config
+            .provider
+            .as_ref()
+            .expect("EmailClient::new checks for a provider")
+        {
+            EmailProvider::Smtp {
+                host,
+                port,
+                username,
+                password,
+                tls,
+            } => {
+                let credentials = username.as_deref().zip(password.as_deref());
+                let message = format_message(&self.config.from, email, Utc::now());
+                smtp::send(
+                    host,
+                    *port,
+                    *tls,
+                    credentials,
+                    mailbox_address(&self.config.from),
+                    &email.to,
+                    &message,
+                )
+                .await
+            }
+            EmailProvider::Sendgrid { api_key } => {
+                sendgrid::send(&self.http, api_key, &self.config.from, email).await
+            }
+        }
+    }
+}
+
+/// The bare address in a mailbox: "bot@example.com" for
+/// "Spacebot <bot@example.com>", or the whole trimmed string.
+pub fn mailbox_address(mailbox: &str) -> &str {
+    let mailbox = mailbox.trim();
+    mailbox
+        .rfind('<')
+        .and_then(|open| {
+            let rest = &mailbox[open + 1..];
+            rest.find('>').map(|close| &rest[..close])
+        })
+        .unwrap_or(mailbox)
+}
+
+/// The display name in a mailbox, if it has one.
+pub fn mailbox_name(mailbox: &str) -> Option<&str> {
+    let open = mailbox.rfind('<')?;
+    let name = mailbox[..open].trim().trim_matches('"').trim();
+    (!name.is_empty()).then_some(name)
+}
+
+/// A single bare address: something before and after one '@', and nothing
+/// that could end an SMTP command or a header.
+fn is_valid_address(address: &str) -> bool {
+    let Some((local, domain)) = address.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && domain.contains('.')
+        && !domain.contains('@')
+        && !address
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || "<>,;\"".contains(c))
+}
+
+/// An RFC 5322 message with a base64 body, so any text survives SMTP
+/// without line-length limits or dot-stuffing surprises.
+fn format_message(from: &str, email: &OutgoingEmail, now: DateTime<Utc>) -> String {
+    let domain = mailbox_address(from)
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .unwrap_or("localhost");
+    let body = email.body.replace("\r\n", "\n").replace('\n', "\r\n");
+    let body = base64::engine::general_purpose::STANDARD.encode(body);
+
+    let mut message = String::new();
+    message.push_str(&format!("From: {from}\r\n"));
+    message.push_str(&format!("To: {}\r\n", email.to.join(", ")));
+    message.push_str(&format!("Subject: {}\r\n", encode_header(&email.subject)));
+    message.push_str(&format!("Date: {}\r\n", now.to_rfc2822()));
+    message.push_str(&format!(
+        "Message-ID: <{}@{domain}>\r\n",
+        uuid::Uuid::new_v4()
+    ));
+    message.push_str("MIME-Version: 1.0\r\n");
+    message.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+    message.push_str("Content-Transfer-Encoding: base64\r\n\r\n");
+    for line in body.as_bytes().chunks(76) {
+        message.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        message.push_str("\r\n");
+    }
+    message
+}
+
+/// A header value on one line, RFC 2047 encoded when it isn't plain ASCII.
+fn encode_header(value: &str) -> String {
+    let value: String = value.chars().filter(|c| !c.is_control()).collect();
+    if value.is_ascii() {
+        value
+    } else {
+        format!(
+            "=?utf-8?B?{}?=",
+            base64::engine::general_purpose::STANDARD.encode(value)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mailboxes_split_into_name_and_address() {
+        assert_eq!(
+            mailbox_address("Spacebot <bot@example.com>"),
+            "bot@example.com"
+        );
+        assert_eq!(mailbox_address(" bot@example.com "), "bot@example.com");
+        assert_eq!(
+            mailbox_name("\"Space Bot\" <bot@example.com>"),
+            Some("Space Bot")
+        );
+        assert_eq!(mailbox_name("bot@example.com"), None);
+
+        assert!(is_valid_address("alice@example.com"));
+        assert!(!is_valid_address("alice"));
+        assert!(!is_valid_address(
+            "alice@example.com>\r\nRCPT TO:<eve@evil.com"
+        ));
+        assert!(!is_valid_address("alice@example.com, eve@evil.com"));
+    }
+
+    #[test]
+    fn messages_encode_subject_and_body() {
+        let email = OutgoingEmail {
+            to: vec!["team@example.com".into()],
+            subject: "Résumé\r\nBcc: eve@evil.com".into(),
+            body: "line one\n.\nline three".into(),
+        };
+        let message = format_message("Spacebot <bot@example.com>", &email, Utc::now());
+        let (headers, body) = message.split_once("\r\n\r\n").unwrap();
+
+        assert!(!headers.contains("\r\nBcc:"));
+        assert!(headers.contains("Subject: =?utf-8?B?"));
+        assert!(headers.contains("@example.com>\r\n"));
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(body.replace("\r\n", ""))
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(decoded).unwrap(),
+            "line one\r\n.\r\nline three"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+//! SendGrid v3 mail send API.
+
+use super::{EmailError, OutgoingEmail, mailbox_address, mailbox_name};
+
+const SEND_URL: &str = "https://api.sendgrid.com/v3/mail/send";
+
+pub(super) async fn send(
+    http: &crate::egress::Client,
+    api_key: &str,
+    from: &str,
+    email: &OutgoingEmail,
+) -> Result<(), EmailError> {
+    let to: Vec<_> = email
+        .to
+        .iter()
+        .map(|address| serde_json::json!({ "email": address }))
+        .collect();
+    let mut sender = serde_json::json!({ "email": mailbox_address(from) });
+    if let Some(name) = mailbox_name(from) {
+        sender["name"] = name.into();
+    }
+
+    let response = http
+        .post(SEND_URL)
+        .bearer_auth(api_key)
+        .json(&serde_json::json!({
+            "personalizations": [{ "to": to }],
+            "from": sender,
+            "subject": email.subject,
+            "content": [{ "type": "text/plain", "value": email.body }],
+        }))
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let reply = response.text().await.unwrap_or_default();
+        return Err(EmailError::Rejected {
+            command: "mail/send".into(),
+            reply: format!("{status}: {}", reply.chars().take(500).collect::<String>()),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,351 @@
+//! A minimal SMTP submission client (RFC 5321): EHLO, optional STARTTLS,
+//! AUTH PLAIN or LOGIN, and one message per connection.
+
+use super::EmailError;
+use crate::config::SmtpTls;
+use base64::Engine as _;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt as _, AsyncRead, AsyncWrite, AsyncWriteExt as _, BufReader};
+use tokio::net::TcpStream;
+
+/// Upper bound on one whole delivery, connect to QUIT.
+const SEND_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+pub(super) async fn send(
+    host: &str,
+    port: u16,
+    tls: SmtpTls,
+    credentials: Option<(&str, &str)>,
+    from: &str,
+    recipients: &[String],
+    message: &str,
+) -> Result<(), EmailError> {
+    let delivery = async {
+        // The sender's domain is as good an EHLO name as any: servers only
+        // log it.
+        let ehlo_name = from
+            .rsplit_once('@')
+            .map_or("localhost", |(_, domain)| domain);
+        let envelope = Envelope {
+            ehlo_name,
+            credentials,
+            from,
+            recipients,
+            message,
+        };
+        let tcp = TcpStream::connect((host, port)).await?;
+
+        match tls {
+            SmtpTls::Implicit => {
+                let stream = connect_tls(host, tcp).await?;
+                Session::new(stream).deliver(&envelope).await
+            }
+            SmtpTls::None => Session::new(tcp).deliver(&envelope).await,
+            SmtpTls::Starttls => {
+                let mut session = Session::new(tcp);
+                session.expect("greeting", 220).await?;
+                let capabilities = session.ehlo(ehlo_name).await?;
+                if !capabilities
+                    .iter()
+                    .any(|line| line.eq_ignore_ascii_case("STARTTLS"))
+                {
+                    return Err(EmailError::Request(format!(
+                        "{host} doesn't offer STARTTLS; set tls = \"implicit\" or \"none\""
+                    )));
+                }
+                session.command("STARTTLS", 220).await?;
+                let stream = connect_tls(host, session.stream.into_inner()).await?;
+                let mut session = Session::new(stream);
+                let capabilities = session.ehlo(ehlo_name).await?;
+                session.submit(&envelope, &capabilities).await
+            }
+        }
+    };
+
+    tokio::time::timeout(SEND_TIMEOUT, delivery)
+        .await
+        .map_err(|_| EmailError::Request(format!("timed out talking to {host}:{port}")))?
+}
+
+async fn connect_tls(
+    host: &str,
+    tcp: TcpStream,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>, EmailError> {
+    let roots = rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|error| EmailError::Request(error.to_string()))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    let server_name = rustls::pki_types::ServerName::try_from(host.to_string())
+        .map_err(|error| EmailError::Request(format!("invalid SMTP host '{host}': {error}")))?;
+    Ok(tokio_rustls::TlsConnector::from(Arc::new(config))
+        .connect(server_name, tcp)
+        .await?)
+}
+
+struct Envelope<'a> {
+    ehlo_name: &'a str,
+    credentials: Option<(&'a str, &'a str)>,
+    from: &'a str,
+    recipients: &'a [String],
+    message: &'a str,
+}
+
+struct Session<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Session<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+        }
+    }
+
+    /// Greeting, EHLO, then [`Session::submit`].
+    async fn deliver(&mut self, envelope: &Envelope<'_>) -> Result<(), EmailError> {
+        self.expect("greeting", 220).await?;
+        let capabilities = self.ehlo(envelope.ehlo_name).await?;
+        self.submit(envelope, &capabilities).await
+    }
+
+    /// EHLO, returning the advertised extensions.
+    async fn ehlo(&mut self, name: &str) -> Result<Vec<String>, EmailError> {
+        let mut lines = self.command(&format!("EHLO {name}"), 250).await?;
+        // The first line is the server's name, not an extension.
+        lines.remove(0);
+        Ok(lines)
+    }
+
+    async fn submit(
+        &mut self,
+        envelope: &Envelope<'_>,
+        capabilities: &[String],
+    ) -> Result<(), EmailError> {
+        if let Some((username, password)) = envelope.credentials {
+            self.authenticate(username, password, capabilities).await?;
+        }
+
+        self.command(&format!("MAIL FROM:<{}>", envelope.from), 250)
+            .await?;
+        for recipient in envelope.recipients {
+            self.command(&format!("RCPT TO:<{recipient}>"), 250).await?;
+        }
+        self.command("DATA", 354).await?;
+        let data = dot_stuff(envelope.message);
+        self.stream.write_all(data.as_bytes()).await?;
+        self.command(".", 250).await?;
+        // The message is accepted; a failed QUIT doesn't change that.
+        let _ = self.command("QUIT", 221).await;
+        Ok(())
+    }
+
+    async fn authenticate(
+        &mut self,
+        username: &str,
+        password: &str,
+        capabilities: &[String],
+    ) -> Result<(), EmailError> {
+        let base64 = base64::engine::general_purpose::STANDARD;
+        let mechanisms: Vec<String> = capabilities
+            .iter()
+            .filter_map(|line| {
+                let (keyword, rest) = line.split_once(' ')?;
+                keyword
+                    .eq_ignore_ascii_case("AUTH")
+                    .then(|| rest.to_ascii_uppercase())
+            })
+            .flat_map(|rest| {
+                rest.split_whitespace()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        if mechanisms.iter().any(|mechanism| mechanism == "PLAIN") {
+            let token = base64.encode(format!("\0{username}\0{password}"));
+            self.labelled_command(&format!("AUTH PLAIN {token}"), "AUTH PLAIN", 235)
+                .await?;
+        } else if mechanisms.iter().any(|mechanism| mechanism == "LOGIN") {
+            self.command("AUTH LOGIN", 334).await?;
+            self.labelled_command(&base64.encode(username), "AUTH LOGIN", 334)
+                .await?;
+            self.labelled_command(&base64.encode(password), "AUTH LOGIN", 235)
+                .await?;
+        } else {
+            return Err(EmailError::Request(
+                "the server offers neither AUTH PLAIN nor AUTH LOGIN".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Send `line` and require reply code `expected`.
+    async fn command(&mut self, line: &str, expected: u16) -> Result<Vec<String>, EmailError> {
+        self.labelled_command(line, line, expected).await
+    }
+
+    /// Like [`Session::command`], but errors name the command `label`, so
+    /// credentials in `line` stay out of them.
+    async fn labelled_command(
+        &mut self,
+        line: &str,
+        label: &str,
+        expected: u16,
+    ) -> Result<Vec<String>, EmailError> {
+        self.stream
+            .write_all(format!("{line}\r\n").as_bytes())
+            .await?;
+        self.stream.flush().await?;
+        self.expect(label, expected).await
+    }
+
+    /// Read one (possibly multi-line) reply and check its code.
+    async fn expect(&mut self, command: &str, expected: u16) -> Result<Vec<String>, EmailError> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(EmailError::Request(format!(
+                    "connection closed waiting for the reply to {command}"
+                )));
+            }
+            let line = line.trim_end();
+            let code = line.get(..3).and_then(|code| code.parse::<u16>().ok());
+            let text = line.get(4..).unwrap_or_default().to_string();
+            let last = line.as_bytes().get(3) != Some(&b'-');
+            lines.push(text);
+            if last {
+                return match code {
+                    Some(code) if code == expected || (expected == 250 && code == 251) => Ok(lines),
+                    _ => Err(EmailError::Rejected {
+                        command: command.to_string(),
+                        reply: line.to_string(),
+                    }),
+                };
+            }
+        }
+    }
+}
+
+/// Escape lines starting with '.' so they don't end the DATA section early
+/// (RFC 5321 section 4.5.2), and make sure the data ends with CRLF.
+fn dot_stuff(message: &str) -> String {
+    let mut data = String::with_capacity(message.len() + 2);
+    for line in message.split_inclusive("\r\n") {
+        if line.starts_with('.') {
+            data.push('.');
+        }
+        data.push_str(line);
+    }
+    if !data.ends_with("\r\n") {
+        data.push_str("\r\n");
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn delivers_with_auth_plain() {
+        let (client, server) = tokio::io::duplex(4096);
+        let server = tokio::spawn(async move {
+            let (read, mut write) = tokio::io::split(server);
+            let mut read = BufReader::new(read);
+            let mut received = Vec::new();
+            write
+                .write_all(b"220 mail.example.com ESMTP\r\n")
+                .await
+                .unwrap();
+            let mut in_data = false;
+            loop {
+                let mut line = String::new();
+                if read.read_line(&mut line).await.unwrap() == 0 {
+                    break;
+                }
+                received.push(line.clone());
+                let reply: &[u8] = if in_data {
+                    if line != ".\r\n" {
+                        continue;
+                    }
+                    in_data = false;
+                    b"250 queued\r\n"
+                } else if line.starts_with("EHLO") {
+                    b"250-mail.example.com\r\n250-AUTH LOGIN PLAIN\r\n250 8BITMIME\r\n"
+                } else if line.starts_with("AUTH PLAIN") {
+                    b"235 ok\r\n"
+                } else if line == "DATA\r\n" {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line == "QUIT\r\n" {
+                    write.write_all(b"221 bye\r\n").await.unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                write.write_all(reply).await.unwrap();
+            }
+            received
+        });
+
+        let recipients = vec!["team@example.com".to_string()];
+        let envelope = Envelope {
+            ehlo_name: "example.com",
+            credentials: Some(("bot", "secret")),
+            from: "bot@example.com",
+            recipients: &recipients,
+            message: "Subject: hi\r\n\r\n.hidden\r\n",
+        };
+        Session::new(client).deliver(&envelope).await.unwrap();
+
+        let received = server.await.unwrap();
+        assert_eq!(received[0], "EHLO example.com\r\n");
+        assert_eq!(
+            received[1],
+            format!(
+                "AUTH PLAIN {}\r\n",
+                base64::engine::general_purpose::STANDARD.encode("\0bot\0secret")
+            )
+        );
+        assert_eq!(received[2], "MAIL FROM:<bot@example.com>\r\n");
+        assert_eq!(received[3], "RCPT TO:<team@example.com>\r\n");
+        assert!(received.contains(&"..hidden\r\n".to_string()));
+        assert_eq!(received.last().unwrap(), "QUIT\r\n");
+    }
+
+    #[tokio::test]
+    async fn rejected_recipients_fail_the_send() {
+        let (client, server) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let (read, mut write) = tokio::io::split(server);
+            let mut read = BufReader::new(read);
+            write.write_all(b"220 ready\r\n").await.unwrap();
+            let mut line = String::new();
+            while read.read_line(&mut line).await.unwrap() > 0 {
+                let reply: &[u8] = if line.starts_with("RCPT") {
+                    b"550 no such user\r\n"
+                } else {
+                    b"250 ok\r\n"
+                };
+                write.write_all(reply).await.unwrap();
+                line.clear();
+            }
+        });
+
+        let recipients = vec!["nobody@example.com".to_string()];
+        let envelope = Envelope {
+            ehlo_name: "example.com",
+            credentials: None,
+            from: "bot@example.com",
+            recipients: &recipients,
+            message: "Subject: hi\r\n\r\nhello\r\n",
+        };
+        let error = Session::new(client).deliver(&envelope).await.unwrap_err();
+        assert!(error.to_string().contains("550 no such user"), "{error}");
+    }
+}
@@ -29,6 +29,9 @@ pub enum Error {
     #[error(transparent)]
     Settings(#[from] SettingsError),
 
+    #[error(transparent)]
+    Storage(#[from] StorageError),
+
     #[error("database error: {0}")]
     Sqlx(#[from] sqlx::Error),
 
@@ -73,6 +76,12 @@ pub enum DbError {
     #[error("failed to connect to redb: {0}")]
     RedbConnect(#[from] redb::Error),
 
+    #[error("redis error: {0}")]
+    Redis(String),
+
+    #[error("postgres error: {0}")]
+    Postgres(String),
+
     #[error("migration failed: {0}")]
     Migration(String),
 
@@ -198,3 +207,16 @@ pub enum SettingsError {
     #[error("settings error: {0}")]
     Other(String),
 }
+
+/// Artifact storage errors.
+#[derive(Debug, thiserror::Error)]
+pub enum StorageError {
+    #[error("invalid storage config: {0}")]
+    Config(String),
+
+    #[error("can't upload {key}: {reason}")]
+    Upload { key: String, reason: String },
+
+    #[error("can't download {key}: {reason}")]
+    Download { key: String, reason: String },
+}
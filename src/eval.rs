@@ -0,0 +1,319 @@
+//! LLM-as-judge evaluation harness.
+//!
+//! An eval suite is a directory of TOML cases, each a prompt plus the criteria
+//! a good answer meets. `spacebot eval` runs every case against a model (and
+//! optionally a system prompt), has a judge model score each answer against
+//! its criteria, and prints a report. Saving reports as JSON and diffing them
+//! across runs catches regressions from prompt or routing changes.
+
+use crate::llm::SpacebotModel;
+use crate::llm::manager::LlmManager;
+use crate::llm::structured::OutputFormat;
+
+use anyhow::Context as _;
+use futures::StreamExt as _;
+use rig::completion::{CompletionModel as _, CompletionRequest};
+use rig::message::Message;
+use rig::one_or_many::OneOrMany;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Score (out of 10) a case needs to pass unless the case overrides it.
+pub const DEFAULT_PASS_SCORE: u8 = 7;
+
+/// One eval case, loaded from `<name>.toml`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EvalCase {
+    /// Defaults to the file stem.
+    #[serde(default)]
+    pub name: String,
+    pub prompt: String,
+    /// System prompt for this case. `--system-prompt` takes precedence.
+    #[serde(default)]
+    pub system: Option<String>,
+    /// What a good answer does, one criterion per entry.
+    pub criteria: Vec<String>,
+    #[serde(default)]
+    pub pass_score: Option<u8>,
+}
+
+/// What to run a suite against.
+#[derive(Debug, Clone)]
+pub struct EvalOptions {
+    /// Model under test, e.g. "openai/gpt-4.1-mini".
+    pub model: String,
+    /// Model that scores the answers.
+    pub judge: String,
+    /// System prompt applied to every case, overriding per-case prompts.
+    pub system_prompt: Option<String>,
+    /// Cases run at once.
+    pub concurrency: usize,
+}
+
+/// The judge's assessment of one answer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Verdict {
+    /// 0–10.
+    pub score: u8,
+    /// Criteria the answer didn't meet.
+    #[serde(default)]
+    pub unmet: Vec<String>,
+    pub rationale: String,
+}
+
+/// Outcome of one case.
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseResult {
+    pub name: String,
+    pub passed: bool,
+    /// `None` when the model or judge call failed.
+    pub verdict: Option<Verdict>,
+    pub answer: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Results for a whole suite.
+#[derive(Debug, Clone, Serialize)]
+pub struct EvalReport {
+    pub model: String,
+    pub judge: String,
+    pub ran_at: chrono::DateTime<chrono::Utc>,
+    pub results: Vec<CaseResult>,
+}
+
+impl EvalReport {
+    pub fn passed(&self) -> usize {
+        self.results.iter().filter(|result| result.passed).count()
+    }
+
+    /// Mean judge score over cases that got a verdict.
+    pub fn mean_score(&self) -> Option<f64> {
+        let scores: Vec<f64> = self
+            .results
+            .iter()
+            .filter_map(|result| result.verdict.as_ref())
+            .map(|verdict| f64::from(verdict.score))
+            .collect();
+        if scores.is_empty() {
+            return None;
+        }
+        Some(scores.iter().sum::<f64>() / scores.len() as f64)
+    }
+}
+
+/// Load every `*.toml` case in `dir`, sorted by file name.
+pub fn load_cases(dir: &Path) -> anyhow::Result<Vec<EvalCase>> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .with_context(|| format!("can't read eval directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("can't read eval case {}", path.display()))?;
+            parse_case(&content, path)
+        })
+        .collect()
+}
+
+fn parse_case(content: &str, path: &Path) -> anyhow::Result<EvalCase> {
+    let mut case: EvalCase = toml::from_str(content)
+        .with_context(|| format!("can't parse eval case {}", path.display()))?;
+    if case.name.is_empty() {
+        case.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+    if case.criteria.is_empty() {
+        anyhow::bail!("eval case {} has no criteria", path.display());
+    }
+    Ok(case)
+}
+
+/// Run every case and judge the answers.
+pub async fn run(
+    llm_manager: &Arc<LlmManager>,
+    cases: Vec<EvalCase>,
+    options: &EvalOptions,
+) -> EvalReport {
+    let model = SpacebotModel::make(llm_manager, &options.model);
+    let judge = SpacebotModel::make(llm_manager, &options.judge);
+
+    let results = futures::stream::iter(cases)
+        .map(|case| run_case(&model, &judge, case, options))
+        .buffered(options.concurrency.max(1))
+        .collect()
+        .await;
+
+    EvalReport {
+        model: options.model.clone(),
+        judge: options.judge.clone(),
+        ran_at: chrono::Utc::now(),
+        results,
+    }
+}
+
+async fn run_case(
+    model: &SpacebotModel,
+    judge: &SpacebotModel,
+    case: EvalCase,
+    options: &EvalOptions,
+) -> CaseResult {
+    let mut result = CaseResult {
+        name: case.name.clone(),
+        passed: false,
+        verdict: None,
+        answer: None,
+        error: None,
+    };
+
+    let system = options.system_prompt.clone().or(case.system.clone());
+    let answer = match model
+        .completion(user_request(system, case.prompt.clone(), None))
+        .await
+    {
+        Ok(response) => crate::llm::sampling::reply_text(&response.choice),
+        Err(error) => {
+            result.error = Some(format!("model failed: {error}"));
+            return result;
+        }
+    };
+    result.answer = Some(answer.clone());
+
+    let request = judge_request(&case, &answer);
+    match judge.completion(request).await {
+        Ok(response) => {
+            let text = crate::llm::sampling::reply_text(&response.choice);
+            match parse_verdict(&text) {
+                Ok(verdict) => {
+                    result.passed = verdict.score >= case.pass_score.unwrap_or(DEFAULT_PASS_SCORE);
+                    result.verdict = Some(verdict);
+                }
+                Err(error) => result.error = Some(format!("unreadable verdict: {error}")),
+            }
+        }
+        Err(error) => result.error = Some(format!("judge failed: {error}")),
+    }
+    result
+}
+
+fn user_request(
+    preamble: Option<String>,
+    prompt: String,
+    additional_params: Option<serde_json::Value>,
+) -> CompletionRequest {
+    CompletionRequest {
+        preamble,
+        chat_history: OneOrMany::one(Message::user(prompt)),
+        documents: Vec::new(),
+        tools: Vec::new(),
+        temperature: None,
+        max_tokens: None,
+        tool_choice: None,
+        additional_params,
+    }
+}
+
+/// Build the request asking the judge to score `answer` against the case.
+pub fn judge_request(case: &EvalCase, answer: &str) -> CompletionRequest {
+    let criteria: String = case
+        .criteria
+        .iter()
+        .map(|criterion| format!("- {criterion}\n"))
+        .collect();
+    let prompt = format!(
+        "Prompt:\n{}\n\nCriteria:\n{criteria}\nAnswer:\n{answer}\n",
+        case.prompt
+    );
+
+    let output_format = OutputFormat::JsonSchema {
+        name: "eval_verdict".into(),
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "score": { "type": "integer", "minimum": 0, "maximum": 10 },
+                "unmet": { "type": "array", "items": { "type": "string" } },
+                "rationale": { "type": "string" },
+            },
+            "required": ["score", "unmet", "rationale"],
+        }),
+    };
+
+    let mut request = user_request(
+        Some(crate::prompts::text::get("fragments/system/eval_judge").to_string()),
+        prompt,
+        Some(output_format.to_params()),
+    );
+    request.temperature = Some(0.0);
+    request
+}
+
+/// Parse the judge's JSON verdict, clamping the score to 0–10.
+pub fn parse_verdict(reply: &str) -> anyhow::Result<Verdict> {
+    let mut verdict: Verdict =
+        serde_json::from_str(crate::llm::structured::strip_code_fence(reply))?;
+    verdict.score = verdict.score.min(10);
+    Ok(verdict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cases_default_their_name_and_need_criteria() {
+        let path = Path::new("evals/capital.toml");
+        let case = parse_case(
+            "prompt = \"What is the capital of France?\"\ncriteria = [\"Names Paris\"]\n",
+            path,
+        )
+        .expect("case should parse");
+        assert_eq!(case.name, "capital");
+        assert_eq!(case.criteria, vec!["Names Paris".to_string()]);
+
+        assert!(parse_case("prompt = \"hi\"\ncriteria = []\n", path).is_err());
+    }
+
+    #[test]
+    fn verdicts_parse_and_report_aggregates() {
+        let verdict = parse_verdict(
+            "```json\n{\"score\": 8, \"unmet\": [], \"rationale\": \"Correct and concise.\"}\n```",
+        )
+        .expect("verdict should parse");
+        assert_eq!(verdict.score, 8);
+        assert!(parse_verdict("eight out of ten").is_err());
+
+        let report = EvalReport {
+            model: "openai/gpt-4.1-mini".into(),
+            judge: "anthropic/claude-sonnet-4".into(),
+            ran_at: chrono::Utc::now(),
+            results: vec![
+                CaseResult {
+                    name: "a".into(),
+                    passed: true,
+                    verdict: Some(verdict),
+                    answer: Some("Paris".into()),
+                    error: None,
+                },
+                CaseResult {
+                    name: "b".into(),
+                    passed: false,
+                    verdict: None,
+                    answer: None,
+                    error: Some("model failed".into()),
+                },
+            ],
+        };
+        assert_eq!(report.passed(), 1);
+        assert_eq!(report.mean_score(), Some(8.0));
+    }
+}
@@ -0,0 +1,299 @@
+//! Instance-wide event bus.
+//!
+//! Subsystems publish what happened — a message arrived, an LLM call
+//! finished, a tool ran, something failed — to one typed stream. Consumers
+//! (metrics, webhooks, audit logs, plugins) register a [`Subscriber`] and see
+//! the same events in the same order, instead of each being called inline
+//! from every site that produces them.
+
+pub mod webhooks;
+
+use crate::llm::budget::BudgetStatus;
+use crate::{AgentId, ChannelId};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use std::future::Future;
+use std::sync::{Arc, LazyLock};
+
+/// Events buffered per subscriber. A subscriber that falls further behind
+/// than this skips the oldest events and logs how many it missed.
+const CAPACITY: usize = 1024;
+
+/// Global bus. Initialized once, published to from any call site.
+static BUS: LazyLock<EventBus> = LazyLock::new(EventBus::new);
+
+/// Something that happened in the instance.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// An inbound message was routed to an agent's channel.
+    MessageReceived {
+        agent_id: AgentId,
+        conversation_id: String,
+        source: String,
+        sender_id: String,
+        message_id: String,
+    },
+    /// An LLM completion call returned, successfully or not.
+    CompletionFinished {
+        model: String,
+        duration_secs: f64,
+        error: Option<String>,
+    },
+    /// A tool call returned a result to the LLM.
+    ToolExecuted {
+        agent_id: AgentId,
+        process_id: String,
+        channel_id: Option<ChannelId>,
+        tool_name: String,
+        /// `None` when the start of the call wasn't observed.
+        duration_secs: Option<f64>,
+    },
+    /// A failure that doesn't surface anywhere else, like a failed LLM turn
+    /// or an undeliverable response.
+    ErrorOccurred {
+        agent_id: Option<AgentId>,
+        /// Which part of the system failed (`channel`, `worker`, `messaging`, ...).
+        component: String,
+        message: String,
+    },
+    /// A response was delivered to a messaging platform.
+    ReplySent {
+        agent_id: AgentId,
+        conversation_id: String,
+        text: String,
+    },
+    /// A provider crossed its budget's downgrade threshold or spend cap.
+    BudgetExceeded {
+        provider: String,
+        status: BudgetStatus,
+        daily_usd: f64,
+        monthly_usd: f64,
+    },
+    /// Content was blocked before reaching an agent, a tool, or a user.
+    ModerationBlocked {
+        agent_id: Option<AgentId>,
+        conversation_id: Option<String>,
+        /// What blocked it (`plugin:<name>`, `script`, `secret_scanner`).
+        blocked_by: String,
+        reason: String,
+    },
+    /// A guided flow collected its last step.
+    FlowCompleted {
+        agent_id: AgentId,
+        conversation_id: String,
+        flow: String,
+        slots: serde_json::Map<String, serde_json::Value>,
+    },
+    /// A route started (`burning`) or stopped burning its latency SLO's error
+    /// budget too fast.
+    SloAlert {
+        route: String,
+        burning: bool,
+        burn_rate: f64,
+        percentile: f64,
+        threshold_secs: f64,
+        /// Latency at `percentile` over the alert window.
+        observed_secs: f64,
+    },
+    /// Tool output or fetched content looked like a prompt injection.
+    InjectionDetected {
+        agent_id: AgentId,
+        conversation_id: Option<String>,
+        /// The tool, or `web_source:<url>` for a page being ingested.
+        source: String,
+        /// `strip` or `flag`.
+        action: String,
+        /// Each suspect passage as `<kind>: <excerpt>`.
+        findings: Vec<String>,
+    },
+    /// The output guard caught a secret or instructions in a reply.
+    LeakDetected {
+        agent_id: AgentId,
+        conversation_id: String,
+        /// `redact` or `block`.
+        action: String,
+        /// `credential`, `secret_pattern`, or `system_prompt`, each once.
+        kinds: Vec<String>,
+    },
+}
+
+impl Event {
+    /// Every event name, for validating subscriptions.
+    pub const KINDS: &[&str] = &[
+        "message_received",
+        "completion_finished",
+        "tool_executed",
+        "error_occurred",
+        "reply_sent",
+        "budget_exceeded",
+        "moderation_blocked",
+        "flow_completed",
+        "slo_alert",
+        "injection_detected",
+        "leak_detected",
+    ];
+
+    /// The event's name, as it appears in `type` when serialized.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::MessageReceived { .. } => "message_received",
+            Self::CompletionFinished { .. } => "completion_finished",
+            Self::ToolExecuted { .. } => "tool_executed",
+            Self::ErrorOccurred { .. } => "error_occurred",
+            Self::ReplySent { .. } => "reply_sent",
+            Self::BudgetExceeded { .. } => "budget_exceeded",
+            Self::ModerationBlocked { .. } => "moderation_blocked",
+            Self::FlowCompleted { .. } => "flow_completed",
+            Self::SloAlert { .. } => "slo_alert",
+            Self::InjectionDetected { .. } => "injection_detected",
+            Self::LeakDetected { .. } => "leak_detected",
+        }
+    }
+
+    /// The agent the event belongs to, if it belongs to one.
+    pub fn agent_id(&self) -> Option<&AgentId> {
+        match self {
+            Self::MessageReceived { agent_id, .. }
+            | Self::ToolExecuted { agent_id, .. }
+            | Self::ReplySent { agent_id, .. }
+            | Self::FlowCompleted { agent_id, .. }
+            | Self::InjectionDetected { agent_id, .. }
+            | Self::LeakDetected { agent_id, .. } => Some(agent_id),
+            Self::ErrorOccurred { agent_id, .. } | Self::ModerationBlocked { agent_id, .. } => {
+                agent_id.as_ref()
+            }
+            Self::CompletionFinished { .. }
+            | Self::BudgetExceeded { .. }
+            | Self::SloAlert { .. } => None,
+        }
+    }
+}
+
+/// A consumer of bus events.
+pub trait Subscriber: Send + Sync + 'static {
+    /// Name used in logs.
+    fn name(&self) -> &str;
+
+    /// Handle one event. Events arrive in publish order. A slow handler only
+    /// delays this subscriber; publishers never wait on it.
+    fn handle(&self, event: &Event) -> impl Future<Output = ()> + Send;
+}
+
+/// Fan-out of [`Event`]s to every registered subscriber.
+pub struct EventBus {
+    tx: broadcast::Sender<Arc<Event>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(CAPACITY);
+        Self { tx }
+    }
+
+    /// The instance-wide bus.
+    pub fn global() -> &'static EventBus {
+        &BUS
+    }
+
+    /// Publish an event. Never blocks, and is a no-op without subscribers.
+    pub fn publish(&self, event: Event) {
+        let _ = self.tx.send(Arc::new(event));
+    }
+
+    /// Register a subscriber. It sees every event published from now on, on
+    /// its own task, until the returned handle is aborted.
+    pub fn subscribe<S: Subscriber>(&self, subscriber: S) -> tokio::task::JoinHandle<()> {
+        let mut rx = self.tx.subscribe();
+        tracing::debug!(
+            subscriber = subscriber.name(),
+            "event subscriber registered"
+        );
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => subscriber.handle(&event).await,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(
+                            subscriber = subscriber.name(),
+                            skipped,
+                            "event subscriber fell behind, events dropped"
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+/// Publish an event on the global bus.
+pub fn publish(event: Event) {
+    BUS.publish(event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    struct Forward(mpsc::UnboundedSender<String>);
+
+    impl Subscriber for Forward {
+        fn name(&self) -> &str {
+            "forward"
+        }
+
+        async fn handle(&self, event: &Event) {
+            let _ = self.0.send(event.kind().to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn subscribers_see_events_in_order() {
+        let bus = EventBus::new();
+        let (first_tx, mut first_rx) = mpsc::unbounded_channel();
+        let (second_tx, mut second_rx) = mpsc::unbounded_channel();
+        bus.subscribe(Forward(first_tx));
+        bus.subscribe(Forward(second_tx));
+
+        bus.publish(Event::CompletionFinished {
+            model: "anthropic/claude-sonnet-4".into(),
+            duration_secs: 1.5,
+            error: None,
+        });
+        bus.publish(Event::ErrorOccurred {
+            agent_id: Some("main".into()),
+            component: "channel".into(),
+            message: "boom".into(),
+        });
+
+        for rx in [&mut first_rx, &mut second_rx] {
+            assert_eq!(rx.recv().await.unwrap(), "completion_finished");
+            assert_eq!(rx.recv().await.unwrap(), "error_occurred");
+        }
+    }
+
+    #[test]
+    fn events_serialize_with_type_tag() {
+        let event = Event::ToolExecuted {
+            agent_id: "main".into(),
+            process_id: "worker:1".into(),
+            channel_id: None,
+            tool_name: "shell".into(),
+            duration_secs: Some(0.25),
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["type"], event.kind());
+        assert_eq!(value["agent_id"], "main");
+        assert_eq!(event.agent_id().map(|id| &**id), Some("main"));
+    }
+}
@@ -0,0 +1,144 @@
+//! Outbound webhooks: POST selected bus events as JSON to external URLs.
+
+use super::{Event, Subscriber};
+
+use hmac::{Hmac, Mac as _};
+use serde::Serialize;
+use sha2::Sha256;
+
+use std::time::Duration;
+
+/// How long one delivery may take before it's abandoned.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One outbound webhook (instance-level, under `[[event_webhooks]]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventWebhook {
+    pub url: String,
+    /// Event names to deliver (see [`Event::KINDS`]). Empty means all.
+    pub events: Vec<String>,
+    /// Signs each delivery with HMAC-SHA256 when set.
+    pub secret: Option<String>,
+}
+
+impl EventWebhook {
+    fn wants(&self, event: &Event) -> bool {
+        self.events.is_empty() || self.events.iter().any(|kind| kind == event.kind())
+    }
+}
+
+/// The body of every delivery.
+#[derive(Serialize)]
+struct Delivery<'a> {
+    #[serde(flatten)]
+    event: &'a Event,
+    timestamp: i64,
+}
+
+/// Signature over `<timestamp>.<body>`, sent as `X-Spacebot-Signature:
+/// sha256=<hex>`. Covering the timestamp lets receivers reject replays.
+pub fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Delivers events to the configured webhooks.
+pub struct WebhookSubscriber {
+    webhooks: Vec<EventWebhook>,
+    http: reqwest::Client,
+}
+
+impl WebhookSubscriber {
+    pub fn new(webhooks: Vec<EventWebhook>) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self { webhooks, http }
+    }
+}
+
+impl Subscriber for WebhookSubscriber {
+    fn name(&self) -> &str {
+        "webhooks"
+    }
+
+    async fn handle(&self, event: &Event) {
+        let timestamp = chrono::Utc::now().timestamp();
+        let body = match serde_json::to_vec(&Delivery { event, timestamp }) {
+            Ok(body) => body,
+            Err(error) => {
+                tracing::warn!(%error, kind = event.kind(), "failed to serialize webhook event");
+                return;
+            }
+        };
+
+        for webhook in self.webhooks.iter().filter(|webhook| webhook.wants(event)) {
+            let mut request = self
+                .http
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header("X-Spacebot-Event", event.kind())
+                .header("X-Spacebot-Timestamp", timestamp)
+                .body(body.clone());
+            if let Some(secret) = &webhook.secret {
+                request =
+                    request.header("X-Spacebot-Signature", signature(secret, timestamp, &body));
+            }
+
+            // Deliver in the background so a slow endpoint doesn't hold up
+            // the events behind this one.
+            let url = webhook.url.clone();
+            let kind = event.kind();
+            tokio::spawn(async move {
+                match request
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                {
+                    Ok(_) => tracing::debug!(%url, kind, "webhook delivered"),
+                    Err(error) => tracing::warn!(%url, kind, %error, "webhook delivery failed"),
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhooks_filter_by_event_kind() {
+        let event = Event::ReplySent {
+            agent_id: "main".into(),
+            conversation_id: "discord:1".into(),
+            text: "hi".into(),
+        };
+        let webhook = |events: &[&str]| EventWebhook {
+            url: "https://hooks.example.com".into(),
+            events: events.iter().map(|kind| kind.to_string()).collect(),
+            secret: None,
+        };
+
+        assert!(webhook(&[]).wants(&event));
+        assert!(webhook(&["budget_exceeded", "reply_sent"]).wants(&event));
+        assert!(!webhook(&["moderation_blocked"]).wants(&event));
+    }
+
+    #[test]
+    fn deliveries_are_signed_over_timestamp_and_body() {
+        let body = br#"{"type":"reply_sent"}"#;
+        let signed = signature("secret", 1_700_000_000, body);
+
+        assert!(signed.starts_with("sha256="));
+        assert_eq!(signed.len(), "sha256=".len() + 64);
+        assert_eq!(signed, signature("secret", 1_700_000_000, body));
+        assert_ne!(signed, signature("secret", 1_700_000_001, body));
+        assert_ne!(signed, signature("other", 1_700_000_000, body));
+    }
+}
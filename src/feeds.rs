@@ -0,0 +1,355 @@
+//! Feed watcher: polls the RSS and Atom feeds in `[defaults.feeds]`, and
+//! posts a short summary of each new entry to the feed's delivery targets.
+//!
+//! Seen entries are recorded in the `feed_entries` table, so each is posted
+//! once across restarts. The first fetch of a feed only records what's
+//! already in it, so adding a feed doesn't post its backlog. New entries that
+//! pass the feed's keywords are queued as `feed.entry` jobs; the job asks a
+//! cheap model for the summary (or to drop the entry, when the feed has a
+//! `filter`), so a failed summary or delivery is retried by the job queue.
+
+pub mod parse;
+
+use crate::config::FeedConfig;
+use crate::db::{SqlPool, with_pool};
+use crate::error::Result;
+use crate::llm::SpacebotModel;
+use crate::{AgentDeps, OutboundResponse, ProcessType};
+
+pub use parse::Entry;
+
+use anyhow::Context as _;
+use rig::agent::AgentBuilder;
+use rig::completion::{CompletionModel as _, Prompt as _};
+use sqlx::Row as _;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Job kind for one new entry. Payload: `{"feed": name, "entry": Entry}`.
+pub const ENTRY_JOB: &str = "feed.entry";
+
+/// How often the loop checks whether a feed is due.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Most entries queued from one fetch. The rest are marked seen and
+/// skipped, so a feed that rewrites its IDs doesn't flood the channel.
+const MAX_NEW_ENTRIES: usize = 10;
+
+/// Largest feed document fetched.
+const MAX_FEED_BYTES: usize = 5 * 1024 * 1024;
+
+/// Most entry text sent to the model.
+const ENTRY_TEXT_CHARS: usize = 6_000;
+
+/// What the summary prompt replies when the entry doesn't pass the filter.
+const NOT_RELEVANT: &str = "Not relevant";
+
+/// Entries already seen, per feed.
+#[derive(Debug, Clone)]
+pub struct FeedStore {
+    pool: SqlPool,
+}
+
+impl FeedStore {
+    pub fn new(pool: SqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Whether any entry of `feed` has been recorded yet.
+    pub async fn has_entries(&self, feed: &str) -> Result<bool> {
+        let count: i64 = with_pool!(&self.pool, |pool| {
+            sqlx::query("SELECT COUNT(*) AS count FROM feed_entries WHERE feed = $1")
+                .bind(feed)
+                .fetch_one(pool)
+                .await
+                .and_then(|row| row.try_get("count"))
+        })
+        .with_context(|| format!("failed to count entries of feed {feed}"))?;
+
+        Ok(count > 0)
+    }
+
+    /// Record `entry` as seen in `feed`. Returns `false` when it already was.
+    pub async fn mark_seen(&self, feed: &str, entry: &Entry) -> Result<bool> {
+        let inserted = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "INSERT INTO feed_entries (feed, entry_id, title) VALUES ($1, $2, $3) \
+                 ON CONFLICT (feed, entry_id) DO NOTHING",
+            )
+            .bind(feed)
+            .bind(&entry.id)
+            .bind(&entry.title)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+        })
+        .with_context(|| format!("failed to record an entry of feed {feed}"))?;
+
+        Ok(inserted > 0)
+    }
+}
+
+/// Spawn the loop that polls the agent's feeds.
+///
+/// Runs until the returned JoinHandle is dropped or aborted. Feeds are
+/// re-read on every check, so added or changed feeds take effect on reload.
+pub fn spawn_feed_loop(deps: AgentDeps) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move { run_feed_loop(&deps).await })
+}
+
+async fn run_feed_loop(deps: &AgentDeps) {
+    if deps.messaging_manager.is_none() {
+        tracing::debug!("no messaging adapters, feed loop not started");
+        return;
+    }
+    tracing::info!("feed loop started");
+    // Feed URLs come from config, so fetches follow the egress policy.
+    let http = crate::egress::client_builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(concat!("spacebot/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .expect("hardcoded reqwest client config");
+    let store = FeedStore::new(deps.sql_pool.clone());
+    let mut last_polled: HashMap<String, (String, Instant)> = HashMap::new();
+
+    loop {
+        let feeds = deps.runtime_config.feeds.load_full();
+        for (name, feed) in feeds.iter() {
+            let interval = Duration::from_secs(feed.poll_interval_secs);
+            let due = last_polled
+                .get(name)
+                .is_none_or(|(url, at)| *url != feed.url || at.elapsed() >= interval);
+            if !due {
+                continue;
+            }
+            last_polled.insert(name.clone(), (feed.url.clone(), Instant::now()));
+            if let Err(error) = poll_feed(deps, &http, &store, name, feed).await {
+                tracing::warn!(feed = %name, %error, "failed to poll feed");
+            }
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Fetch `feed`, record its entries, and queue the new ones.
+async fn poll_feed(
+    deps: &AgentDeps,
+    http: &crate::egress::Client,
+    store: &FeedStore,
+    name: &str,
+    feed: &FeedConfig,
+) -> anyhow::Result<()> {
+    // Entries are marked seen as they're queued, so don't fetch what can't be queued.
+    crate::watchdog::admit_background_job()?;
+    let mut entries = fetch(http, &feed.url).await?;
+    // Feeds list newest first; post oldest first.
+    entries.reverse();
+    entries.sort_by_key(|entry| entry.published);
+
+    let baseline = !store.has_entries(name).await?;
+    let mut queued = 0;
+    let mut skipped = 0;
+    for entry in entries {
+        let new = store.mark_seen(name, &entry).await?;
+        if baseline || !new || !matches_keywords(&entry, &feed.keywords) {
+            continue;
+        }
+        if queued == MAX_NEW_ENTRIES {
+            skipped += 1;
+            continue;
+        }
+        deps.jobs
+            .enqueue_unique(
+                &deps.agent_id,
+                &format!("feed:{name}:{}", entry.id),
+                ENTRY_JOB,
+                serde_json::json!({ "feed": name, "entry": entry }),
+            )
+            .await
+            .with_context(|| format!("failed to queue entry {:?}", entry.title))?;
+        queued += 1;
+    }
+
+    if baseline {
+        tracing::info!(feed = %name, "recorded existing feed entries, posting new ones from now on");
+    } else if queued > 0 {
+        tracing::info!(feed = %name, queued, "queued new feed entries");
+    }
+    if skipped > 0 {
+        tracing::warn!(feed = %name, skipped, "too many new feed entries at once, skipped the rest");
+    }
+    Ok(())
+}
+
+async fn fetch(http: &crate::egress::Client, url: &str) -> anyhow::Result<Vec<Entry>> {
+    let response = http.get(url).send().await?.error_for_status()?;
+    let limits = crate::llm::http::BodyLimits {
+        max_response_bytes: MAX_FEED_BYTES,
+        ..crate::llm::http::BodyLimits::FETCH
+    };
+    let body = crate::llm::http::read_text(response, limits).await?;
+    parse::parse_feed(&body).context("not an RSS or Atom feed")
+}
+
+/// Whether `entry` mentions one of `keywords`, ignoring case. No keywords
+/// matches everything.
+fn matches_keywords(entry: &Entry, keywords: &[String]) -> bool {
+    if keywords.is_empty() {
+        return true;
+    }
+    let text = format!("{}\n{}", entry.title, entry.summary).to_lowercase();
+    keywords
+        .iter()
+        .any(|keyword| text.contains(&keyword.to_lowercase()))
+}
+
+/// Run a `feed.entry` job: summarize the entry and post it.
+pub async fn run_entry_job(job: &crate::jobs::Job, deps: &AgentDeps) -> anyhow::Result<()> {
+    let name = job.payload["feed"]
+        .as_str()
+        .context("feed job has no feed")?;
+    let entry: Entry = serde_json::from_value(job.payload["entry"].clone())
+        .context("feed job has an invalid entry")?;
+    let feeds = deps.runtime_config.feeds.load_full();
+    let Some(feed) = feeds.get(name) else {
+        tracing::info!(feed = %name, "feed was removed, dropping its entry");
+        return Ok(());
+    };
+    let messaging = deps
+        .messaging_manager
+        .as_ref()
+        .context("no messaging adapters to post the entry with")?;
+
+    let routing = deps.runtime_config.routing.load();
+    let model_name = feed
+        .model
+        .clone()
+        .unwrap_or_else(|| routing.resolve(ProcessType::Compactor, None).to_string());
+    let model = SpacebotModel::make(&deps.llm_manager, &model_name)
+        .with_routing((**routing).clone())
+        .with_agent(deps.agent_id.clone());
+    let preamble = deps
+        .runtime_config
+        .prompts
+        .load()
+        .render_static("feed_summary")?;
+    let agent = AgentBuilder::new(model).preamble(&preamble).build();
+    let summary = agent
+        .prompt(&render_entry(name, &entry, feed.filter.as_deref()))
+        .await
+        .context("feed entry summary failed")?;
+    let summary = summary.trim();
+    if summary
+        .trim_end_matches('.')
+        .eq_ignore_ascii_case(NOT_RELEVANT)
+    {
+        tracing::debug!(feed = %name, title = %entry.title, "feed entry filtered out");
+        return Ok(());
+    }
+
+    let post = render_post(name, &entry, summary);
+    let mut delivered = 0;
+    for raw in &feed.delivery_targets {
+        let Some(target) = crate::cron::scheduler::normalize_delivery_target(raw) else {
+            tracing::warn!(feed = %name, target = %raw, "invalid feed delivery target");
+            continue;
+        };
+        match messaging
+            .broadcast(
+                &target.adapter,
+                &target.target,
+                OutboundResponse::Text(post.clone()),
+            )
+            .await
+        {
+            Ok(()) => delivered += 1,
+            Err(error) => {
+                tracing::warn!(feed = %name, target = %target, %error, "failed to post feed entry");
+            }
+        }
+    }
+    // Retrying after a partial delivery would post twice to the targets
+    // that worked.
+    if delivered == 0 {
+        anyhow::bail!("couldn't post the entry to any delivery target");
+    }
+    tracing::info!(feed = %name, title = %entry.title, "feed entry posted");
+    Ok(())
+}
+
+/// The prompt input: the entry's fields, its text, and the feed's filter.
+fn render_entry(feed: &str, entry: &Entry, filter: Option<&str>) -> String {
+    let mut out = format!("Feed: {feed}\nTitle: {}\n", entry.title);
+    if let Some(published) = entry.published {
+        out.push_str(&format!("Published: {}\n", published.format("%Y-%m-%d")));
+    }
+    if let Some(filter) = filter {
+        out.push_str(&format!("Filter: {filter}\n"));
+    }
+    let text: String = entry.summary.chars().take(ENTRY_TEXT_CHARS).collect();
+    let omitted = if text.len() < entry.summary.len() {
+        " [...]"
+    } else {
+        ""
+    };
+    out.push_str(&format!("\n{text}{omitted}\n"));
+    out
+}
+
+/// The message posted for an entry.
+fn render_post(feed: &str, entry: &Entry, summary: &str) -> String {
+    let title = if entry.title.is_empty() {
+        "New entry"
+    } else {
+        &entry.title
+    };
+    let mut post = format!("**{title}** ({feed})\n{summary}");
+    if let Some(link) = &entry.link {
+        post.push('\n');
+        post.push_str(link);
+    }
+    post
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, title: &str, summary: &str) -> Entry {
+        Entry {
+            id: id.into(),
+            title: title.into(),
+            link: Some(format!("https://example.com/{id}")),
+            summary: summary.into(),
+            published: None,
+        }
+    }
+
+    #[test]
+    fn keywords_match_title_or_summary_ignoring_case() {
+        let release = entry("1", "Release notes", "Now written in Rust.");
+        assert!(matches_keywords(&release, &[]));
+        assert!(matches_keywords(&release, &["rust".into()]));
+        assert!(matches_keywords(&release, &["go".into(), "NOTES".into()]));
+        assert!(!matches_keywords(&release, &["python".into()]));
+
+        assert_eq!(
+            render_post("blog", &release, "A rewrite."),
+            "**Release notes** (blog)\nA rewrite.\nhttps://example.com/1"
+        );
+    }
+
+    #[tokio::test]
+    async fn entries_are_seen_once_per_feed() {
+        let store = crate::memory::MemoryStore::connect_in_memory().await;
+        let feeds = FeedStore::new(store.pool().clone());
+        let first = entry("1", "First", "");
+
+        assert!(!feeds.has_entries("blog").await.unwrap());
+        assert!(feeds.mark_seen("blog", &first).await.unwrap());
+        assert!(!feeds.mark_seen("blog", &first).await.unwrap());
+        assert!(feeds.has_entries("blog").await.unwrap());
+        assert!(feeds.mark_seen("news", &first).await.unwrap());
+    }
+}
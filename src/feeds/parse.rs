@@ -0,0 +1,340 @@
+//! RSS 2.0, RSS 1.0, and Atom parsing, just enough to list a feed's entries.
+//!
+//! Elements are matched by local name, whatever namespace prefix the feed
+//! uses, so `<content:encoded>` and `<dc:date>` are found like `<content>`
+//! and `<date>`.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// One item or entry in a feed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The `guid` or `id`, falling back to the link, then the title.
+    pub id: String,
+    pub title: String,
+    pub link: Option<String>,
+    /// The description, summary, or content, as plain text.
+    pub summary: String,
+    pub published: Option<DateTime<Utc>>,
+}
+
+/// The entries of an RSS or Atom document, in document order. `None` when
+/// `xml` isn't a feed.
+pub fn parse_feed(xml: &str) -> Option<Vec<Entry>> {
+    let root = root_name(xml)?;
+    let item = match local_name(root) {
+        "rss" | "RDF" => "item",
+        "feed" => "entry",
+        _ => return None,
+    };
+    Some(
+        elements(xml, item)
+            .into_iter()
+            .filter_map(|element| parse_entry(element.body))
+            .collect(),
+    )
+}
+
+fn parse_entry(xml: &str) -> Option<Entry> {
+    let first_text = |names: &[&str]| {
+        names.iter().find_map(|name| {
+            elements(xml, name)
+                .into_iter()
+                .map(|element| text(element.body))
+                .find(|text| !text.is_empty())
+        })
+    };
+
+    let title = first_text(&["title"]).map(|title| plain_text(&title));
+    let link = entry_link(xml);
+    let id = first_text(&["guid", "id"])
+        .or_else(|| link.clone())
+        .or_else(|| title.clone())?;
+    let summary = first_text(&["description", "summary", "encoded", "content"])
+        .map(|summary| plain_text(&summary))
+        .unwrap_or_default();
+    let published =
+        first_text(&["pubDate", "published", "updated", "date"]).and_then(|date| parse_date(&date));
+
+    Some(Entry {
+        id,
+        title: title.unwrap_or_default(),
+        link,
+        summary,
+        published,
+    })
+}
+
+/// The entry's web page: an RSS `<link>`'s text, or the `href` of an Atom
+/// `<link>` that isn't for something else (`rel="self"`, `"enclosure"`, ...).
+fn entry_link(xml: &str) -> Option<String> {
+    elements(xml, "link").into_iter().find_map(|element| {
+        match attribute(element.attributes, "href") {
+            Some(href) => {
+                let rel = attribute(element.attributes, "rel");
+                matches!(rel.as_deref(), None | Some("alternate")).then_some(href)
+            }
+            None => Some(text(element.body)).filter(|link| !link.is_empty()),
+        }
+    })
+}
+
+/// RFC 2822 (RSS) or RFC 3339 (Atom, Dublin Core) dates.
+fn parse_date(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(text)
+        .or_else(|_| DateTime::parse_from_rfc3339(text))
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// An element's raw attribute text and inner content.
+struct Element<'a> {
+    attributes: &'a str,
+    body: &'a str,
+}
+
+/// The name of the document's first element.
+fn root_name(xml: &str) -> Option<&str> {
+    let mut rest = xml;
+    loop {
+        rest = &rest[rest.find('<')? + 1..];
+        if rest.starts_with('?') || rest.starts_with('!') {
+            continue;
+        }
+        let end = rest.find(|c: char| c.is_whitespace() || c == '>' || c == '/')?;
+        return Some(&rest[..end]);
+    }
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Every element named `local` (with any prefix), without descending into
+/// the ones found. Comments and CDATA sections are skipped.
+fn elements<'a>(xml: &'a str, local: &str) -> Vec<Element<'a>> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open..];
+        if let Some(skip) = [("<![CDATA[", "]]>"), ("<!--", "-->")]
+            .into_iter()
+            .find_map(|(start, end)| rest.starts_with(start).then_some(end))
+        {
+            match rest.find(skip) {
+                Some(end) => rest = &rest[end + skip.len()..],
+                None => break,
+            }
+            continue;
+        }
+        rest = &rest[1..];
+        let Some(tag_end) = rest.find('>') else { break };
+        let tag = &rest[..tag_end];
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        if name.is_empty() || local_name(name) != local {
+            continue;
+        }
+
+        let attributes = tag[name.len()..].trim_end_matches('/');
+        let after = &rest[tag_end + 1..];
+        if tag.ends_with('/') {
+            found.push(Element {
+                attributes,
+                body: "",
+            });
+            rest = after;
+            continue;
+        }
+        let close = format!("</{name}>");
+        let Some(body_end) = after.find(&close) else {
+            break;
+        };
+        found.push(Element {
+            attributes,
+            body: &after[..body_end],
+        });
+        rest = &after[body_end + close.len()..];
+    }
+    found
+}
+
+/// The value of attribute `name` in an element's attribute text.
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while let Some(at) = rest.find(name) {
+        let before = rest[..at].chars().next_back();
+        let after = rest[at + name.len()..].trim_start();
+        rest = &rest[at + name.len()..];
+        if !before.is_none_or(char::is_whitespace) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let quote = value.chars().next()?;
+        if quote != '"' && quote != '\'' {
+            continue;
+        }
+        let value = &value[1..];
+        let end = value.find(quote)?;
+        return Some(unescape(&value[..end]));
+    }
+    None
+}
+
+/// An element's text: CDATA sections as they are, everything else
+/// unescaped.
+fn text(body: &str) -> String {
+    let mut out = String::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("<![CDATA[") {
+        out.push_str(&unescape(&rest[..start]));
+        let cdata = &rest[start + "<![CDATA[".len()..];
+        let end = cdata.find("]]>").unwrap_or(cdata.len());
+        out.push_str(&cdata[..end]);
+        rest = cdata.get(end + "]]>".len()..).unwrap_or_default();
+    }
+    out.push_str(&unescape(rest));
+    out.trim().to_string()
+}
+
+/// Text with HTML tags removed, entities decoded, and whitespace collapsed.
+fn plain_text(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        out.push(' ');
+        rest = match rest[start..].find('>') {
+            Some(end) => &rest[start + end + 1..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    unescape(&out.replace("&nbsp;", " "))
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `text` with HTML entities decoded.
+pub(crate) fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => {
+                    let code = match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => entity.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rss_items_parse() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/"
+     xmlns:atom="http://www.w3.org/2005/Atom">
+<channel>
+  <title>Example Blog</title>
+  <atom:link href="https://blog.example.com/feed.xml" rel="self"/>
+  <item>
+    <title>Rust 1.90 &amp; friends</title>
+    <link>https://blog.example.com/rust-190</link>
+    <guid isPermaLink="false">post-190</guid>
+    <pubDate>Tue, 03 Mar 2026 09:30:00 GMT</pubDate>
+    <description><![CDATA[<p>Faster <b>builds</b>&nbsp;and   more.</p>]]></description>
+  </item>
+  <item>
+    <title>No guid here</title>
+    <link>https://blog.example.com/no-guid</link>
+    <description>&lt;p&gt;Escaped &amp;amp; HTML&lt;/p&gt;</description>
+  </item>
+  <!-- <item><title>commented out</title></item> -->
+</channel>
+</rss>"#;
+        let entries = parse_feed(xml).expect("is a feed");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, "post-190");
+        assert_eq!(entries[0].title, "Rust 1.90 & friends");
+        assert_eq!(
+            entries[0].link.as_deref(),
+            Some("https://blog.example.com/rust-190")
+        );
+        assert_eq!(entries[0].summary, "Faster builds and more.");
+        assert_eq!(
+            entries[0].published.map(|date| date.to_rfc3339()),
+            Some("2026-03-03T09:30:00+00:00".into())
+        );
+        assert_eq!(entries[1].id, "https://blog.example.com/no-guid");
+        assert_eq!(entries[1].summary, "Escaped & HTML");
+        assert_eq!(entries[1].published, None);
+    }
+
+    #[test]
+    fn atom_entries_parse() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Releases</title>
+  <link href="https://example.com/releases.atom" rel="self"/>
+  <entry>
+    <id>tag:github.com,2008:Repository/1/v2.0.0</id>
+    <title type="html">v2.0.0</title>
+    <link rel="enclosure" href="https://example.com/v2.0.0.tar.gz"/>
+    <link rel="alternate" type="text/html" href="https://example.com/releases/v2.0.0"/>
+    <updated>2026-03-04T12:00:00+01:00</updated>
+    <content type="html">&lt;h2&gt;Breaking&lt;/h2&gt;&lt;p&gt;Dropped the v1 API.&lt;/p&gt;</content>
+  </entry>
+</feed>"#;
+        let entries = parse_feed(xml).expect("is a feed");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, "tag:github.com,2008:Repository/1/v2.0.0");
+        assert_eq!(
+            entries[0].link.as_deref(),
+            Some("https://example.com/releases/v2.0.0")
+        );
+        assert_eq!(entries[0].summary, "Breaking Dropped the v1 API.");
+        assert_eq!(
+            entries[0].published.map(|date| date.to_rfc3339()),
+            Some("2026-03-04T11:00:00+00:00".into())
+        );
+
+        assert_eq!(parse_feed("<html><body>not a feed</body></html>"), None);
+    }
+}
@@ -0,0 +1,692 @@
+//! Guided flows: multi-step interactions like an onboarding questionnaire or
+//! incident intake, defined as state machines in TOML.
+//!
+//! A flow is a list of steps, each asking for one or more slots. The channel
+//! starts a flow with the `flow` tool, extracts slot values from the user's
+//! replies, and submits them. The flow only accepts slots for its current
+//! step and moves on once the step's required slots are filled, so step order
+//! is enforced here rather than left to the prompt. Runs are stored in the
+//! `flow_runs` table and survive restarts.
+//!
+//! Flows are loaded from `flows/<name>.toml` in the instance directory and in
+//! the agent's workspace; a workspace flow overrides an instance flow with
+//! the same name.
+
+use crate::db::{Column, SqlPool, with_pool};
+use crate::error::Result;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Step target that finishes the flow.
+pub const END: &str = "end";
+
+/// A flow definition, parsed from `flows/<name>.toml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlowDefinition {
+    /// The file name without `.toml`.
+    #[serde(skip)]
+    pub name: String,
+    /// When to use the flow. Shown to the channel so it can pick one.
+    pub description: String,
+    pub steps: Vec<FlowStep>,
+}
+
+/// One state of a flow.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlowStep {
+    pub id: String,
+    /// What to ask the user in this step.
+    pub prompt: String,
+    #[serde(default)]
+    pub slots: Vec<FlowSlot>,
+    /// Conditional jumps, checked in order once the step is complete.
+    #[serde(default)]
+    pub transitions: Vec<FlowTransition>,
+    /// Step to go to when no transition matches. Defaults to the next step
+    /// in the file, or `end` after the last one.
+    pub next: Option<String>,
+}
+
+/// A value the flow collects.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlowSlot {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub kind: SlotKind,
+    /// Allowed values for `choice` slots.
+    #[serde(default)]
+    pub options: Vec<String>,
+    #[serde(default = "default_required")]
+    pub required: bool,
+}
+
+fn default_required() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlotKind {
+    #[default]
+    Text,
+    Number,
+    Boolean,
+    Choice,
+}
+
+/// Go to `goto` when `slot` was filled with `equals`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlowTransition {
+    pub slot: String,
+    pub equals: String,
+    pub goto: String,
+}
+
+/// Where a run stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowStatus {
+    Active,
+    Completed,
+    Cancelled,
+}
+
+impl FlowStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Active => "active",
+            Self::Completed => "completed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "completed" => Self::Completed,
+            "cancelled" => Self::Cancelled,
+            _ => Self::Active,
+        }
+    }
+}
+
+/// One run of a flow in a channel.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FlowRun {
+    pub id: String,
+    pub channel_id: String,
+    pub flow: String,
+    /// The current step, or [`END`] once completed.
+    pub step: String,
+    pub slots: Map<String, Value>,
+    pub status: FlowStatus,
+}
+
+/// What a submission did to a run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Advance {
+    /// The step still needs these required slots.
+    Stay { missing: Vec<String> },
+    /// The step is complete; the run moved to this step.
+    Step(String),
+    /// The last step is complete.
+    Completed,
+}
+
+impl FlowDefinition {
+    /// Parse and validate a definition.
+    pub fn parse(name: &str, source: &str) -> anyhow::Result<Self> {
+        let mut definition: Self = toml::from_str(source)?;
+        definition.name = name.to_string();
+        definition.validate()?;
+        Ok(definition)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(!self.steps.is_empty(), "flow has no steps");
+        let mut step_ids = HashSet::new();
+        let mut slot_names = HashSet::new();
+        for step in &self.steps {
+            anyhow::ensure!(step.id != END, "step id '{END}' is reserved");
+            anyhow::ensure!(step_ids.insert(&step.id), "duplicate step '{}'", step.id);
+            for slot in &step.slots {
+                anyhow::ensure!(
+                    slot_names.insert(&slot.name),
+                    "duplicate slot '{}'",
+                    slot.name
+                );
+                anyhow::ensure!(
+                    slot.kind != SlotKind::Choice || !slot.options.is_empty(),
+                    "choice slot '{}' has no options",
+                    slot.name
+                );
+            }
+        }
+        for step in &self.steps {
+            let targets = step
+                .transitions
+                .iter()
+                .map(|transition| &transition.goto)
+                .chain(&step.next);
+            for target in targets {
+                anyhow::ensure!(
+                    target == END || step_ids.contains(target),
+                    "step '{}' goes to unknown step '{target}'",
+                    step.id
+                );
+            }
+            for transition in &step.transitions {
+                anyhow::ensure!(
+                    step.slots.iter().any(|slot| slot.name == transition.slot),
+                    "step '{}' has a transition on slot '{}' from another step",
+                    step.id,
+                    transition.slot
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn step(&self, id: &str) -> Option<&FlowStep> {
+        self.steps.iter().find(|step| step.id == id)
+    }
+
+    /// A new run, on the first step.
+    pub fn start(&self, channel_id: &str) -> FlowRun {
+        FlowRun {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: channel_id.to_string(),
+            flow: self.name.clone(),
+            step: self.steps[0].id.clone(),
+            slots: Map::new(),
+            status: FlowStatus::Active,
+        }
+    }
+
+    /// Fill slots of the run's current step and advance it when the step is
+    /// complete. Nothing is stored if any value is rejected; the error says
+    /// why, in words the LLM can act on.
+    pub fn submit(
+        &self,
+        run: &mut FlowRun,
+        values: Map<String, Value>,
+    ) -> std::result::Result<Advance, String> {
+        let step = self
+            .step(&run.step)
+            .ok_or_else(|| format!("flow '{}' has no step '{}'", self.name, run.step))?;
+
+        let mut accepted = Vec::with_capacity(values.len());
+        for (name, value) in values {
+            let Some(slot) = step.slots.iter().find(|slot| slot.name == name) else {
+                return Err(match self.slot_step(&name) {
+                    Some(other) => format!(
+                        "'{name}' belongs to step '{}'. Finish the current step '{}' first.",
+                        other.id, step.id
+                    ),
+                    None => format!("Flow '{}' has no slot '{name}'.", self.name),
+                });
+            };
+            accepted.push((name, coerce(slot, value)?));
+        }
+        run.slots.extend(accepted);
+
+        let missing: Vec<String> = step
+            .slots
+            .iter()
+            .filter(|slot| slot.required && !run.slots.contains_key(&slot.name))
+            .map(|slot| slot.name.clone())
+            .collect();
+        if !missing.is_empty() {
+            return Ok(Advance::Stay { missing });
+        }
+
+        let next = self.next_step(step, &run.slots);
+        run.step = next.clone();
+        if next == END {
+            run.status = FlowStatus::Completed;
+            Ok(Advance::Completed)
+        } else {
+            Ok(Advance::Step(next))
+        }
+    }
+
+    fn slot_step(&self, slot: &str) -> Option<&FlowStep> {
+        self.steps
+            .iter()
+            .find(|step| step.slots.iter().any(|candidate| candidate.name == slot))
+    }
+
+    fn next_step(&self, step: &FlowStep, slots: &Map<String, Value>) -> String {
+        let matched = step.transitions.iter().find(|transition| {
+            slots
+                .get(&transition.slot)
+                .is_some_and(|value| display_value(value).eq_ignore_ascii_case(&transition.equals))
+        });
+        if let Some(transition) = matched {
+            return transition.goto.clone();
+        }
+        if let Some(next) = &step.next {
+            return next.clone();
+        }
+        let index = self
+            .steps
+            .iter()
+            .position(|candidate| candidate.id == step.id);
+        index
+            .and_then(|index| self.steps.get(index + 1))
+            .map_or_else(|| END.to_string(), |next| next.id.clone())
+    }
+}
+
+/// Check a submitted value against its slot and normalize it.
+fn coerce(slot: &FlowSlot, value: Value) -> std::result::Result<Value, String> {
+    let name = &slot.name;
+    match slot.kind {
+        SlotKind::Text => match value {
+            Value::String(text) if !text.trim().is_empty() => Ok(Value::String(text)),
+            Value::Number(_) | Value::Bool(_) => Ok(Value::String(value.to_string())),
+            _ => Err(format!("'{name}' must be non-empty text.")),
+        },
+        SlotKind::Number => match &value {
+            Value::Number(_) => Ok(value),
+            Value::String(text) => text
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| format!("'{name}' must be a number, got '{text}'.")),
+            _ => Err(format!("'{name}' must be a number.")),
+        },
+        SlotKind::Boolean => match &value {
+            Value::Bool(_) => Ok(value),
+            Value::String(text) => match text.trim().to_lowercase().as_str() {
+                "true" | "yes" | "y" => Ok(Value::Bool(true)),
+                "false" | "no" | "n" => Ok(Value::Bool(false)),
+                _ => Err(format!("'{name}' must be yes or no, got '{text}'.")),
+            },
+            _ => Err(format!("'{name}' must be yes or no.")),
+        },
+        SlotKind::Choice => {
+            let text = display_value(&value);
+            slot.options
+                .iter()
+                .find(|option| option.eq_ignore_ascii_case(text.trim()))
+                .map(|option| Value::String(option.clone()))
+                .ok_or_else(|| {
+                    format!(
+                        "'{name}' must be one of: {}. Got '{text}'.",
+                        slot.options.join(", ")
+                    )
+                })
+        }
+    }
+}
+
+/// A slot value as plain text, without JSON quotes.
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// The flows available to one agent.
+#[derive(Debug, Clone, Default)]
+pub struct FlowSet {
+    flows: BTreeMap<String, FlowDefinition>,
+}
+
+impl FlowSet {
+    /// Load flows from the instance and workspace `flows` directories.
+    /// Workspace flows override instance flows with the same name. Invalid
+    /// files are logged and skipped.
+    pub fn load(instance_flows_dir: &Path, workspace_flows_dir: &Path) -> Self {
+        let mut set = Self::default();
+        for dir in [instance_flows_dir, workspace_flows_dir] {
+            for definition in load_flows_from_dir(dir) {
+                set.flows.insert(definition.name.clone(), definition);
+            }
+        }
+        set
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FlowDefinition> {
+        self.flows.get(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.flows.is_empty()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.flows.keys().map(String::as_str)
+    }
+
+    /// The flows section of the channel system prompt: the available flows,
+    /// and where the channel's active run stands.
+    pub fn render_channel_prompt(&self, active: Option<&FlowRun>) -> String {
+        if self.flows.is_empty() {
+            return String::new();
+        }
+
+        let mut prompt = String::from(
+            "## Guided Flows\n\n\
+             Flows are step-by-step conversations with fixed questions. Start one with the `flow` tool when the user asks for it or the conversation clearly calls for it:\n",
+        );
+        for flow in self.flows.values() {
+            let _ = writeln!(prompt, "- `{}` — {}", flow.name, flow.description);
+        }
+
+        let Some(run) = active else {
+            return prompt;
+        };
+        let Some(step) = self
+            .get(&run.flow)
+            .and_then(|definition| definition.step(&run.step))
+        else {
+            return prompt;
+        };
+
+        let _ = write!(
+            prompt,
+            "\n### Active Flow: {}\n\nCurrent step `{}`: {}\n",
+            run.flow, step.id, step.prompt
+        );
+        for slot in step
+            .slots
+            .iter()
+            .filter(|slot| !run.slots.contains_key(&slot.name))
+        {
+            let _ = write!(prompt, "- needs `{}`", slot.name);
+            if !slot.description.is_empty() {
+                let _ = write!(prompt, ": {}", slot.description);
+            }
+            if slot.kind == SlotKind::Choice {
+                let _ = write!(prompt, " (one of: {})", slot.options.join(", "));
+            } else if slot.kind != SlotKind::Text {
+                let _ = write!(prompt, " ({:?})", slot.kind);
+            }
+            if !slot.required {
+                prompt.push_str(" (optional)");
+            }
+            prompt.push('\n');
+        }
+        if !run.slots.is_empty() {
+            prompt.push_str("\nCollected so far:\n");
+            for (name, value) in &run.slots {
+                let _ = writeln!(prompt, "- `{name}`: {}", display_value(value));
+            }
+        }
+        prompt.push_str(
+            "\nAsk for the current step's answers, extract them from the user's replies, and submit them with the `flow` tool. The flow only accepts answers for the current step, so don't skip ahead.\n",
+        );
+        prompt
+    }
+}
+
+fn load_flows_from_dir(dir: &Path) -> Vec<FlowDefinition> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            let loaded = std::fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|source| FlowDefinition::parse(&name, &source));
+            match loaded {
+                Ok(definition) => Some(definition),
+                Err(error) => {
+                    tracing::warn!(path = %path.display(), %error, "skipping invalid flow");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Flow run storage (SQLite or Postgres).
+#[derive(Debug, Clone)]
+pub struct FlowStore {
+    pool: SqlPool,
+}
+
+impl FlowStore {
+    pub fn new(pool: SqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a new run, cancelling any run still active in its channel.
+    pub async fn start(&self, run: &FlowRun) -> Result<()> {
+        let slots = serde_json::to_string(&run.slots).context("failed to encode slots")?;
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "UPDATE flow_runs SET status = 'cancelled', updated_at = CURRENT_TIMESTAMP \
+                 WHERE channel_id = $1 AND status = 'active'",
+            )
+            .bind(&run.channel_id)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
+        .context("failed to cancel earlier flow run")?;
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "INSERT INTO flow_runs (id, channel_id, flow, step, slots, status) \
+                 VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(&run.id)
+            .bind(&run.channel_id)
+            .bind(&run.flow)
+            .bind(&run.step)
+            .bind(&slots)
+            .bind(run.status.as_str())
+            .execute(pool)
+            .await
+            .map(drop)
+        })
+        .context("failed to save flow run")?;
+
+        Ok(())
+    }
+
+    /// Save a run's step, slots, and status.
+    pub async fn save(&self, run: &FlowRun) -> Result<()> {
+        let slots = serde_json::to_string(&run.slots).context("failed to encode slots")?;
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "UPDATE flow_runs SET step = $2, slots = $3, status = $4, \
+                 updated_at = CURRENT_TIMESTAMP WHERE id = $1",
+            )
+            .bind(&run.id)
+            .bind(&run.step)
+            .bind(&slots)
+            .bind(run.status.as_str())
+            .execute(pool)
+            .await
+            .map(drop)
+        })
+        .context("failed to update flow run")?;
+
+        Ok(())
+    }
+
+    /// The run active in `channel_id`, if any.
+    pub async fn active(&self, channel_id: &str) -> Result<Option<FlowRun>> {
+        let run = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT id, channel_id, flow, step, slots, status FROM flow_runs \
+                 WHERE channel_id = $1 AND status = 'active' \
+                 ORDER BY started_at DESC LIMIT 1",
+            )
+            .bind(channel_id)
+            .fetch_optional(pool)
+            .await
+            .and_then(|row| row.as_ref().map(run_from_row).transpose())
+        })
+        .context("failed to load flow run")?;
+
+        Ok(run)
+    }
+}
+
+fn run_from_row<R>(row: &R) -> std::result::Result<FlowRun, sqlx::Error>
+where
+    R: sqlx::Row,
+    for<'c> &'c str: sqlx::ColumnIndex<R>,
+    String: Column<R::Database>,
+{
+    let slots: String = row.try_get("slots")?;
+    let status: String = row.try_get("status")?;
+    Ok(FlowRun {
+        id: row.try_get("id")?,
+        channel_id: row.try_get("channel_id")?,
+        flow: row.try_get("flow")?,
+        step: row.try_get("step")?,
+        slots: serde_json::from_str(&slots).map_err(|error| sqlx::Error::ColumnDecode {
+            index: "slots".into(),
+            source: Box::new(error),
+        })?,
+        status: FlowStatus::parse(&status),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INCIDENT: &str = r#"
+description = "Collect the details of a production incident"
+
+[[steps]]
+id = "service"
+prompt = "Which service is affected?"
+slots = [
+  { name = "service", description = "Affected service" },
+  { name = "started", description = "When it started", required = false },
+]
+
+[[steps]]
+id = "severity"
+prompt = "How bad is it?"
+slots = [{ name = "severity", kind = "choice", options = ["sev1", "sev2", "sev3"] }]
+transitions = [{ slot = "severity", equals = "sev1", goto = "pager" }]
+next = "summary"
+
+[[steps]]
+id = "pager"
+prompt = "Should I page the on-call engineer?"
+slots = [{ name = "page", kind = "boolean" }]
+
+[[steps]]
+id = "summary"
+prompt = "Anything else?"
+slots = [{ name = "notes", required = false }]
+"#;
+
+    fn values(json: Value) -> Map<String, Value> {
+        json.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn submissions_follow_step_order_and_transitions() {
+        let flow = FlowDefinition::parse("incident_intake", INCIDENT).expect("valid flow");
+        let mut run = flow.start("discord:1:2");
+        assert_eq!(run.step, "service");
+
+        let error = flow
+            .submit(&mut run, values(serde_json::json!({"severity": "sev1"})))
+            .unwrap_err();
+        assert!(error.contains("belongs to step 'severity'"), "{error}");
+        assert!(run.slots.is_empty());
+
+        assert_eq!(
+            flow.submit(&mut run, values(serde_json::json!({"started": "09:00"}))),
+            Ok(Advance::Stay {
+                missing: vec!["service".into()]
+            })
+        );
+        assert_eq!(
+            flow.submit(&mut run, values(serde_json::json!({"service": "api"}))),
+            Ok(Advance::Step("severity".into()))
+        );
+
+        let error = flow
+            .submit(&mut run, values(serde_json::json!({"severity": "sev9"})))
+            .unwrap_err();
+        assert!(error.contains("one of: sev1, sev2, sev3"), "{error}");
+        assert_eq!(
+            flow.submit(&mut run, values(serde_json::json!({"severity": "SEV1"}))),
+            Ok(Advance::Step("pager".into()))
+        );
+        assert_eq!(run.slots["severity"], "sev1");
+
+        assert_eq!(
+            flow.submit(&mut run, values(serde_json::json!({"page": "yes"}))),
+            Ok(Advance::Step("summary".into()))
+        );
+        assert_eq!(flow.submit(&mut run, Map::new()), Ok(Advance::Completed));
+        assert_eq!(run.status, FlowStatus::Completed);
+        assert_eq!(run.slots["page"], true);
+    }
+
+    #[test]
+    fn invalid_definitions_are_rejected() {
+        for source in [
+            "description = \"x\"\nsteps = []\n",
+            "description = \"x\"\n[[steps]]\nid = \"a\"\nprompt = \"?\"\nnext = \"b\"\n",
+            "description = \"x\"\n[[steps]]\nid = \"a\"\nprompt = \"?\"\n[[steps]]\nid = \"a\"\nprompt = \"?\"\n",
+            "description = \"x\"\n[[steps]]\nid = \"a\"\nprompt = \"?\"\nslots = [{ name = \"s\", kind = \"choice\" }]\n",
+        ] {
+            assert!(FlowDefinition::parse("bad", source).is_err(), "{source}");
+        }
+    }
+
+    #[tokio::test]
+    async fn one_run_is_active_per_channel() {
+        let memory_store = crate::memory::MemoryStore::connect_in_memory().await;
+        let store = FlowStore::new(memory_store.pool().clone());
+        let flow = FlowDefinition::parse("incident_intake", INCIDENT).expect("valid flow");
+
+        let first = flow.start("discord:1:2");
+        store.start(&first).await.expect("start");
+        let mut second = flow.start("discord:1:2");
+        store.start(&second).await.expect("start");
+        assert_eq!(
+            store.active("discord:1:2").await.expect("load"),
+            Some(second.clone())
+        );
+
+        flow.submit(&mut second, values(serde_json::json!({"service": "api"})))
+            .expect("submit");
+        store.save(&second).await.expect("save");
+        let loaded = store.active("discord:1:2").await.expect("load").unwrap();
+        assert_eq!(loaded.step, "severity");
+        assert_eq!(loaded.slots["service"], "api");
+
+        second.status = FlowStatus::Cancelled;
+        store.save(&second).await.expect("save");
+        assert!(store.active("discord:1:2").await.expect("load").is_none());
+    }
+}
@@ -0,0 +1,294 @@
+//! Ops heartbeat: a status message for operators every few minutes.
+//!
+//! With `[heartbeat]` set, Spacebot posts its version and uptime, today's
+//! and this month's LLM spend, provider health (rate limit cooldowns and
+//! spend caps), and each agent's job queue depth to a designated channel.
+//! By default it keeps one pinned message up to date instead of posting a
+//! new one each time, where the platform can edit messages. The config is
+//! re-read every minute, so it hot-reloads.
+
+use crate::api::ApiState;
+use crate::jobs::QueueStats;
+use crate::llm::budget::BudgetStatus;
+use crate::llm::cooldown::ActiveCooldown;
+
+use arc_swap::ArcSwap;
+
+use std::fmt::Write as _;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+/// First line of every heartbeat, also how a pinned one is found again
+/// after a restart.
+const HEADING: &str = "**Spacebot heartbeat**";
+
+/// How often the loop checks whether a heartbeat is due.
+const TICK: Duration = Duration::from_secs(60);
+
+static CONFIG: LazyLock<ArcSwap<HeartbeatConfig>> =
+    LazyLock::new(|| ArcSwap::from_pointee(HeartbeatConfig::default()));
+
+/// Heartbeat settings, from `[heartbeat]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeartbeatConfig {
+    /// "adapter:target" to post to. `None` turns the heartbeat off.
+    pub target: Option<String>,
+    /// Minutes between heartbeats.
+    pub interval_mins: u64,
+    /// Keep one pinned message up to date rather than posting a new one.
+    pub pinned: bool,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            target: None,
+            interval_mins: 15,
+            pinned: true,
+        }
+    }
+}
+
+/// Replace the settings the heartbeat loop follows.
+pub fn configure(config: HeartbeatConfig) {
+    CONFIG.store(Arc::new(config));
+}
+
+/// Everything one heartbeat reports.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    pub version: &'static str,
+    pub uptime: Duration,
+    pub daily_usd: f64,
+    pub monthly_usd: f64,
+    pub cooldowns: Vec<ActiveCooldown>,
+    /// Providers past their downgrade threshold or at their cap.
+    pub budgets: Vec<(String, BudgetStatus)>,
+    /// Job queue per agent.
+    pub queues: Vec<(String, QueueStats)>,
+}
+
+impl Heartbeat {
+    /// Read the current state from what the API serves.
+    pub async fn collect(api_state: &ApiState) -> Self {
+        let mut heartbeat = Self {
+            version: env!("CARGO_PKG_VERSION"),
+            uptime: api_state.started_at.elapsed(),
+            daily_usd: 0.0,
+            monthly_usd: 0.0,
+            cooldowns: Vec::new(),
+            budgets: Vec::new(),
+            queues: Vec::new(),
+        };
+
+        if let Some(llm_manager) = api_state.llm_manager.read().await.clone() {
+            for spend in llm_manager.spend_snapshot().values() {
+                heartbeat.daily_usd += spend.daily_usd;
+                heartbeat.monthly_usd += spend.monthly_usd;
+            }
+            heartbeat.cooldowns = llm_manager.rate_limit_snapshot();
+            let mut providers: Vec<String> = llm_manager.provider_budgets().into_keys().collect();
+            providers.sort();
+            heartbeat.budgets = providers
+                .into_iter()
+                .map(|provider| {
+                    let status = llm_manager.budget_status(&provider);
+                    (provider, status)
+                })
+                .filter(|(_, status)| *status != BudgetStatus::Normal)
+                .collect();
+        }
+
+        if let Some(job_queue) = api_state.job_queue.read().await.clone() {
+            let mut agent_ids: Vec<String> =
+                api_state.runtime_configs.load().keys().cloned().collect();
+            agent_ids.sort();
+            for agent_id in agent_ids {
+                match job_queue.stats(&agent_id).await {
+                    Ok(stats) => heartbeat.queues.push((agent_id, stats)),
+                    Err(error) => {
+                        tracing::warn!(%error, %agent_id, "heartbeat can't read the job queue");
+                    }
+                }
+            }
+        }
+
+        heartbeat
+    }
+
+    /// The heartbeat message, updated at `now`.
+    pub fn render(&self, now: chrono::DateTime<chrono::Utc>) -> String {
+        let mut text = format!(
+            "{HEADING}\nVersion {}, up {}\nSpend: ${:.2} today, ${:.2} this month",
+            self.version,
+            uptime_text(self.uptime),
+            self.daily_usd,
+            self.monthly_usd
+        );
+
+        if self.cooldowns.is_empty() && self.budgets.is_empty() {
+            text.push_str("\nProviders: all healthy");
+        } else {
+            text.push_str("\nProviders:");
+            for cooldown in &self.cooldowns {
+                let subject = if cooldown.provider_wide {
+                    format!("every `{}` model", cooldown.name)
+                } else {
+                    format!("`{}`", cooldown.name)
+                };
+                let _ = write!(
+                    text,
+                    "\n- {subject}: rate limited, {} left",
+                    crate::llm::cooldown::remaining_text(cooldown.remaining)
+                );
+            }
+            for (provider, status) in &self.budgets {
+                let state = match status {
+                    BudgetStatus::Exhausted => "spend cap reached",
+                    _ => "past its downgrade threshold",
+                };
+                let _ = write!(text, "\n- `{provider}`: {state}");
+            }
+        }
+
+        if self.queues.is_empty() {
+            text.push_str("\nQueues: none");
+        } else {
+            text.push_str("\nQueues:");
+            for (agent_id, stats) in &self.queues {
+                let _ = write!(
+                    text,
+                    "\n- `{agent_id}`: {} queued, {} retrying, {} running, {} dead-lettered",
+                    stats.ready, stats.retrying, stats.running, stats.dead
+                );
+            }
+        }
+
+        let _ = write!(text, "\nUpdated {}", now.format("%Y-%m-%d %H:%M UTC"));
+        text
+    }
+}
+
+/// "12m", "4h 12m", or "3d 4h".
+fn uptime_text(uptime: Duration) -> String {
+    let mins = uptime.as_secs() / 60;
+    if mins < 60 {
+        format!("{mins}m")
+    } else if mins < 24 * 60 {
+        format!("{}h {}m", mins / 60, mins % 60)
+    } else {
+        format!("{}d {}h", mins / (24 * 60), mins % (24 * 60) / 60)
+    }
+}
+
+/// Spawn the loop that posts heartbeats while one is configured.
+pub fn spawn(api_state: Arc<ApiState>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move { run(&api_state).await })
+}
+
+async fn run(api_state: &ApiState) {
+    let mut last_posted: Option<Instant> = None;
+    loop {
+        let config = CONFIG.load_full();
+        let interval = Duration::from_secs(config.interval_mins * 60);
+        if let Some(target) = &config.target
+            && last_posted.is_none_or(|posted| posted.elapsed() >= interval)
+        {
+            let text = Heartbeat::collect(api_state)
+                .await
+                .render(chrono::Utc::now());
+            // A heartbeat that didn't go out is tried again on the next tick.
+            if post(api_state, target, config.pinned, text).await {
+                last_posted = Some(Instant::now());
+            }
+        }
+        tokio::time::sleep(TICK).await;
+    }
+}
+
+/// Post or update the heartbeat. Returns whether it went out.
+async fn post(api_state: &ApiState, target: &str, pinned: bool, text: String) -> bool {
+    // Validated when the config loads.
+    let Some(target) = crate::cron::scheduler::DeliveryTarget::parse(target) else {
+        return false;
+    };
+    let Some(messaging_manager) = api_state.messaging_manager.read().await.clone() else {
+        tracing::debug!(%target, "no messaging manager available for the heartbeat yet");
+        return false;
+    };
+    let posted = if pinned {
+        messaging_manager
+            .broadcast_pinned(&target.adapter, &target.target, text)
+            .await
+    } else {
+        messaging_manager
+            .broadcast(
+                &target.adapter,
+                &target.target,
+                crate::OutboundResponse::Text(text),
+            )
+            .await
+    };
+    if let Err(error) = &posted {
+        tracing::warn!(%error, %target, "failed to post heartbeat");
+    }
+    posted.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone as _;
+
+    #[test]
+    fn renders_spend_health_and_queues() {
+        let heartbeat = Heartbeat {
+            version: "1.2.3",
+            uptime: Duration::from_secs(3 * 86_400 + 4 * 3600 + 120),
+            daily_usd: 1.5,
+            monthly_usd: 42.0,
+            cooldowns: vec![ActiveCooldown {
+                name: "anthropic".into(),
+                provider_wide: true,
+                remaining: Duration::from_secs(125),
+                strikes: 2,
+            }],
+            budgets: vec![("openai".into(), BudgetStatus::Exhausted)],
+            queues: vec![(
+                "main".into(),
+                QueueStats {
+                    ready: 3,
+                    running: 1,
+                    ..QueueStats::default()
+                },
+            )],
+        };
+        let now = chrono::Utc
+            .with_ymd_and_hms(2026, 10, 15, 9, 30, 0)
+            .unwrap();
+        assert_eq!(
+            heartbeat.render(now),
+            "**Spacebot heartbeat**\n\
+             Version 1.2.3, up 3d 4h\n\
+             Spend: $1.50 today, $42.00 this month\n\
+             Providers:\n\
+             - every `anthropic` model: rate limited, 2m 05s left\n\
+             - `openai`: spend cap reached\n\
+             Queues:\n\
+             - `main`: 3 queued, 0 retrying, 1 running, 0 dead-lettered\n\
+             Updated 2026-10-15 09:30 UTC"
+        );
+
+        let quiet = Heartbeat {
+            cooldowns: Vec::new(),
+            budgets: Vec::new(),
+            queues: Vec::new(),
+            uptime: Duration::from_secs(600),
+            ..heartbeat
+        };
+        let text = quiet.render(now);
+        assert!(text.contains("up 10m\n"));
+        assert!(text.contains("Providers: all healthy\nQueues: none\n"));
+    }
+}
@@ -45,44 +45,16 @@ impl SpacebotHook {
 
     /// Scan content for potential secret leaks.
     fn scan_for_leaks(&self, content: &str) -> Option<String> {
-        use regex::Regex;
-        use std::sync::LazyLock;
-
-        static LEAK_PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
-            vec![
-                // OpenAI keys
-                Regex::new(r"sk-[a-zA-Z0-9]{20,}").expect("hardcoded regex"),
-                // Anthropic keys
-                Regex::new(r"sk-ant-[a-zA-Z0-9_-]{20,}").expect("hardcoded regex"),
-                // OpenRouter keys
-                Regex::new(r"sk-or-[a-zA-Z0-9_-]{20,}").expect("hardcoded regex"),
-                // PEM private keys
-                Regex::new(r"-----BEGIN.*PRIVATE KEY-----").expect("hardcoded regex"),
-                // GitHub personal access tokens
-                Regex::new(r"ghp_[a-zA-Z0-9]{36}").expect("hardcoded regex"),
-                // Google API keys
-                Regex::new(r"AIza[0-9A-Za-z_-]{35}").expect("hardcoded regex"),
-                // Discord bot tokens (base64 user ID . timestamp . HMAC)
-                Regex::new(r"[MN][A-Za-z0-9]{23,}\.[A-Za-z0-9_-]{6}\.[A-Za-z0-9_-]{27,}")
-                    .expect("hardcoded regex"),
-                // Slack bot tokens
-                Regex::new(r"xoxb-[0-9]{10,}-[0-9A-Za-z-]+").expect("hardcoded regex"),
-                // Slack app tokens
-                Regex::new(r"xapp-[0-9]-[A-Z0-9]+-[0-9]+-[a-f0-9]+").expect("hardcoded regex"),
-                // Telegram bot tokens
-                Regex::new(r"\d{8,}:[A-Za-z0-9_-]{35}").expect("hardcoded regex"),
-                // Brave Search API keys
-                Regex::new(r"BSA[a-zA-Z0-9]{20,}").expect("hardcoded regex"),
-            ]
-        });
-
-        for pattern in LEAK_PATTERNS.iter() {
-            if let Some(matched) = pattern.find(content) {
-                return Some(matched.as_str().to_string());
-            }
-        }
+        crate::secrets::patterns::find(content).map(str::to_string)
+    }
 
-        None
+    fn publish_leak_block(&self, tool_name: &str, reason: &str) {
+        crate::events::publish(crate::events::Event::ModerationBlocked {
+            agent_id: Some(self.agent_id.clone()),
+            conversation_id: self.channel_id.as_deref().map(str::to_string),
+            blocked_by: "secret_scanner".into(),
+            reason: format!("{tool_name}: {reason}"),
+        });
     }
 }
 
@@ -90,7 +62,6 @@ impl SpacebotHook {
 // on_tool_call and removed in on_tool_result. If the agent terminates between
 // the two hooks (e.g. leak detection), orphaned entries stay in the map.
 // Bounded by concurrent tool calls so not a practical leak.
-#[cfg(feature = "metrics")]
 static TOOL_CALL_TIMERS: std::sync::LazyLock<
     std::sync::Mutex<std::collections::HashMap<String, std::time::Instant>>,
 > = std::sync::LazyLock::new(|| std::sync::Mutex::new(std::collections::HashMap::new()));
@@ -131,7 +102,7 @@ where
         &self,
         tool_name: &str,
         _tool_call_id: Option<String>,
-        _internal_call_id: &str,
+        internal_call_id: &str,
         args: &str,
     ) -> ToolCallHookAction {
         // Scan tool arguments for secrets before execution
@@ -142,6 +113,7 @@ where
                 leak_prefix = %&leak[..leak.len().min(8)],
                 "secret leak detected in tool arguments, blocking call"
             );
+            self.publish_leak_block(tool_name, "tool arguments contained a secret");
             return ToolCallHookAction::Skip {
                 reason: "Tool call blocked: arguments contained a secret.".into(),
             };
@@ -162,9 +134,8 @@ where
             "tool call started"
         );
 
-        #[cfg(feature = "metrics")]
         if let Ok(mut timers) = TOOL_CALL_TIMERS.lock() {
-            timers.insert(_internal_call_id.to_string(), std::time::Instant::now());
+            timers.insert(internal_call_id.to_string(), std::time::Instant::now());
         }
 
         ToolCallHookAction::Continue
@@ -174,7 +145,7 @@ where
         &self,
         tool_name: &str,
         _tool_call_id: Option<String>,
-        _internal_call_id: &str,
+        internal_call_id: &str,
         _args: &str,
         result: &str,
     ) -> HookAction {
@@ -189,6 +160,7 @@ where
                 leak_prefix = %&leak[..leak.len().min(8)],
                 "secret leak detected in tool output, terminating agent"
             );
+            self.publish_leak_block(tool_name, "tool output contained a secret");
             return HookAction::Terminate {
                 reason: "Tool output contained a secret. Agent terminated to prevent exfiltration."
                     .into(),
@@ -215,23 +187,18 @@ where
             "tool call completed"
         );
 
-        #[cfg(feature = "metrics")]
-        {
-            let metrics = crate::telemetry::Metrics::global();
-            metrics
-                .tool_calls_total
-                .with_label_values(&[&*self.agent_id, tool_name])
-                .inc();
-            if let Some(start) = TOOL_CALL_TIMERS
-                .lock()
-                .ok()
-                .and_then(|mut timers| timers.remove(_internal_call_id))
-            {
-                metrics
-                    .tool_call_duration_seconds
-                    .observe(start.elapsed().as_secs_f64());
-            }
-        }
+        let duration_secs = TOOL_CALL_TIMERS
+            .lock()
+            .ok()
+            .and_then(|mut timers| timers.remove(internal_call_id))
+            .map(|start| start.elapsed().as_secs_f64());
+        crate::events::publish(crate::events::Event::ToolExecuted {
+            agent_id: self.agent_id.clone(),
+            process_id: self.process_id.to_string(),
+            channel_id: self.channel_id.clone(),
+            tool_name: tool_name.to_string(),
+            duration_secs,
+        });
 
         HookAction::Continue
     }
@@ -0,0 +1,706 @@
+//! Importing chat history from before the bot was deployed.
+//!
+//! `spacebot import` reads one of:
+//!
+//! - a Discord export from DiscordChatExporter: a channel's `.json` file, or
+//!   a directory of them;
+//! - a Slack workspace export: the `.zip` Slack produces, or its unpacked
+//!   directory (`channels.json`, `users.json`, and a directory of daily
+//!   files per channel);
+//! - JSON lines, one message per line: `channel_id`, `content`, and
+//!   `timestamp` (RFC 3339), with optional `id`, `channel_name`,
+//!   `sender_id`, `sender_name`, and `role` (`user` or `assistant`).
+//!
+//! Messages are stored as conversation history under the channel IDs the
+//! live adapters use, so `!search`, transcripts, and channel history see
+//! them. Each message gets an ID derived from its source, so importing the
+//! same export twice doesn't duplicate anything. [`write_memory_sources`]
+//! also renders each channel as a Markdown log in the agent's ingest
+//! directory, where memory ingestion turns it into memories.
+
+use crate::db::{SqlPool, with_pool};
+use crate::error::Result;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+
+/// Rows written per transaction.
+const STORE_BATCH_ROWS: usize = 500;
+
+/// What kind of export is being imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    Discord,
+    Slack,
+    Jsonl,
+}
+
+impl std::str::FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "discord" => Ok(Self::Discord),
+            "slack" => Ok(Self::Slack),
+            "jsonl" => Ok(Self::Jsonl),
+            other => Err(format!(
+                "unknown import format {other:?}: must be \"discord\", \"slack\", or \"jsonl\""
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// The bot's own platform user ID. Its messages are imported as
+    /// assistant messages.
+    pub bot_user_id: Option<String>,
+    /// Slack workspace ID (`T...`). Defaults to the `team_id` in `users.json`.
+    pub slack_team_id: Option<String>,
+}
+
+/// A message read from an export.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedMessage {
+    pub id: String,
+    pub channel_id: String,
+    pub channel_name: Option<String>,
+    /// `user` or `assistant`.
+    pub role: &'static str,
+    pub sender_id: Option<String>,
+    pub sender_name: Option<String>,
+    pub content: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Read every message in the export at `path`, oldest first.
+pub fn read(
+    path: &Path,
+    format: ImportFormat,
+    options: &ImportOptions,
+) -> anyhow::Result<Vec<ImportedMessage>> {
+    let files = read_files(path)?;
+    let mut messages = match format {
+        ImportFormat::Discord => {
+            let mut messages = Vec::new();
+            for (name, contents) in files.iter().filter(|(name, _)| name.ends_with(".json")) {
+                messages.extend(
+                    parse_discord(contents, options)
+                        .with_context(|| format!("{name} isn't a Discord export"))?,
+                );
+            }
+            messages
+        }
+        ImportFormat::Slack => parse_slack(&files, options)?,
+        ImportFormat::Jsonl => {
+            let mut messages = Vec::new();
+            for (name, contents) in &files {
+                messages.extend(parse_jsonl(contents).with_context(|| format!("in {name}"))?);
+            }
+            messages
+        }
+    };
+    messages.sort_by_key(|message| message.created_at);
+    Ok(messages)
+}
+
+/// The files at `path`, keyed by their path relative to it: one file, every
+/// file under a directory, or every file in a zip archive.
+fn read_files(path: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    if path.is_dir() {
+        let mut pending = vec![path.to_path_buf()];
+        while let Some(directory) = pending.pop() {
+            for entry in std::fs::read_dir(&directory)
+                .with_context(|| format!("failed to read {}", directory.display()))?
+            {
+                let entry_path = entry?.path();
+                if entry_path.is_dir() {
+                    pending.push(entry_path);
+                    continue;
+                }
+                let name = entry_path
+                    .strip_prefix(path)
+                    .unwrap_or(&entry_path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let contents = std::fs::read_to_string(&entry_path)
+                    .with_context(|| format!("failed to read {}", entry_path.display()))?;
+                files.insert(name, contents);
+            }
+        }
+    } else if path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+    {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let mut zip = zip::ZipArchive::new(file).context("not a zip archive")?;
+        for index in 0..zip.len() {
+            let mut file = zip.by_index(index)?;
+            let Some(name) = file.enclosed_name() else {
+                continue;
+            };
+            if file.is_dir() {
+                continue;
+            }
+            let name = name.to_string_lossy().replace('\\', "/");
+            let mut contents = String::new();
+            file.read_to_string(&mut contents)
+                .with_context(|| format!("failed to read {name} from the archive"))?;
+            files.insert(name, contents);
+        }
+    } else {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        files.insert(path.display().to_string(), contents);
+    }
+    Ok(files)
+}
+
+#[derive(Deserialize)]
+struct DiscordExport {
+    guild: DiscordGuild,
+    channel: DiscordChannel,
+    messages: Vec<DiscordMessage>,
+}
+
+#[derive(Deserialize)]
+struct DiscordGuild {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct DiscordChannel {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscordMessage {
+    id: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    content: String,
+    author: DiscordAuthor,
+    #[serde(default)]
+    attachments: Vec<DiscordAttachment>,
+}
+
+#[derive(Deserialize)]
+struct DiscordAuthor {
+    id: String,
+    name: String,
+    nickname: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscordAttachment {
+    file_name: String,
+}
+
+/// Parse one channel exported by DiscordChatExporter as JSON.
+fn parse_discord(json: &str, options: &ImportOptions) -> anyhow::Result<Vec<ImportedMessage>> {
+    let export: DiscordExport = serde_json::from_str(json)?;
+    let channel_id = format!("discord:{}:{}", export.guild.id, export.channel.id);
+    Ok(export
+        .messages
+        .into_iter()
+        .filter_map(|message| {
+            let mut content = message.content;
+            for attachment in &message.attachments {
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                let _ = write!(content, "[attachment: {}]", attachment.file_name);
+            }
+            if content.trim().is_empty() {
+                return None;
+            }
+            Some(ImportedMessage {
+                id: format!("import:discord:{}", message.id),
+                channel_id: channel_id.clone(),
+                channel_name: Some(export.channel.name.clone()),
+                role: role_of(&message.author.id, options),
+                sender_name: Some(message.author.nickname.unwrap_or(message.author.name)),
+                sender_id: Some(message.author.id),
+                content,
+                created_at: message.timestamp,
+            })
+        })
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct SlackChannel {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct SlackUser {
+    id: String,
+    name: String,
+    team_id: Option<String>,
+    #[serde(default)]
+    profile: SlackProfile,
+}
+
+#[derive(Deserialize, Default)]
+struct SlackProfile {
+    display_name: Option<String>,
+    real_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SlackMessage {
+    #[serde(rename = "type")]
+    kind: String,
+    subtype: Option<String>,
+    user: Option<String>,
+    #[serde(default)]
+    text: String,
+    ts: String,
+}
+
+/// Parse a Slack workspace export. Joins, topic changes, and other channel
+/// events are skipped; thread replies are imported into their channel.
+fn parse_slack(
+    files: &BTreeMap<String, String>,
+    options: &ImportOptions,
+) -> anyhow::Result<Vec<ImportedMessage>> {
+    let find = |name: &str| {
+        files
+            .iter()
+            .find(|(path, _)| *path == name || path.ends_with(&format!("/{name}")))
+    };
+    let (channels_path, channels_json) =
+        find("channels.json").context("no channels.json in the Slack export")?;
+    let channels: Vec<SlackChannel> =
+        serde_json::from_str(channels_json).context("channels.json isn't a Slack export")?;
+    let users: Vec<SlackUser> = match find("users.json") {
+        Some((_, json)) => serde_json::from_str(json).context("users.json isn't a Slack export")?,
+        None => Vec::new(),
+    };
+
+    let team_id = options
+        .slack_team_id
+        .clone()
+        .or_else(|| users.iter().find_map(|user| user.team_id.clone()))
+        .context("the Slack export doesn't name its workspace; pass --slack-team-id")?;
+    let names: HashMap<&str, &str> = users
+        .iter()
+        .map(|user| {
+            let name = [&user.profile.display_name, &user.profile.real_name]
+                .into_iter()
+                .flatten()
+                .find(|name| !name.is_empty())
+                .unwrap_or(&user.name);
+            (user.id.as_str(), name.as_str())
+        })
+        .collect();
+    // Channel directories sit next to channels.json.
+    let root = channels_path
+        .strip_suffix("channels.json")
+        .unwrap_or_default();
+
+    let mut messages = Vec::new();
+    for channel in &channels {
+        let prefix = format!("{root}{}/", channel.name);
+        let channel_id = format!("slack:{team_id}:{}", channel.id);
+        for (path, json) in files.range(prefix.clone()..) {
+            if !path.starts_with(&prefix) {
+                break;
+            }
+            if !path.ends_with(".json") {
+                continue;
+            }
+            let day: Vec<SlackMessage> = serde_json::from_str(json)
+                .with_context(|| format!("{path} isn't a Slack export"))?;
+            for message in day {
+                let said = matches!(
+                    message.subtype.as_deref(),
+                    None | Some("thread_broadcast" | "file_share" | "me_message")
+                );
+                if message.kind != "message" || !said || message.text.trim().is_empty() {
+                    continue;
+                }
+                let Some(created_at) = parse_slack_ts(&message.ts) else {
+                    continue;
+                };
+                let sender_name = message
+                    .user
+                    .as_deref()
+                    .and_then(|user| names.get(user))
+                    .map(|name| name.to_string());
+                messages.push(ImportedMessage {
+                    id: format!("import:slack:{}:{}", channel.id, message.ts),
+                    channel_id: channel_id.clone(),
+                    channel_name: Some(channel.name.clone()),
+                    role: message
+                        .user
+                        .as_deref()
+                        .map_or("user", |user| role_of(user, options)),
+                    sender_id: message.user,
+                    sender_name,
+                    content: resolve_slack_mentions(&message.text, &names),
+                    created_at,
+                });
+            }
+        }
+    }
+    Ok(messages)
+}
+
+/// Parse a Slack `ts` (`"1700000000.000100"`) as a time.
+fn parse_slack_ts(ts: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let (seconds, fraction) = ts.split_once('.').unwrap_or((ts, "0"));
+    let micros: u32 = format!("{fraction:0<6}").get(..6)?.parse().ok()?;
+    chrono::DateTime::from_timestamp(seconds.parse().ok()?, micros * 1_000)
+}
+
+/// Replace `<@U123>` mentions with the member's name.
+fn resolve_slack_mentions(text: &str, names: &HashMap<&str, &str>) -> String {
+    let mut resolved = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<@") {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let user = &rest[start + 2..start + end];
+        resolved.push_str(&rest[..start]);
+        match names.get(user.split('|').next().unwrap_or(user)) {
+            Some(name) => {
+                let _ = write!(resolved, "@{name}");
+            }
+            None => resolved.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    resolved.push_str(rest);
+    resolved
+}
+
+#[derive(Deserialize)]
+struct JsonlMessage {
+    id: Option<String>,
+    channel_id: String,
+    channel_name: Option<String>,
+    sender_id: Option<String>,
+    sender_name: Option<String>,
+    role: Option<String>,
+    content: String,
+    timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Parse JSON lines. Lines without an `id` get one hashed from the message,
+/// so reimporting the file still skips them.
+fn parse_jsonl(text: &str) -> anyhow::Result<Vec<ImportedMessage>> {
+    let mut messages = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: JsonlMessage = serde_json::from_str(line)
+            .with_context(|| format!("line {} isn't a message", index + 1))?;
+        let role = match message.role.as_deref() {
+            None | Some("user") => "user",
+            Some("assistant") => "assistant",
+            Some(other) => anyhow::bail!(
+                "line {}: unknown role {other:?}: must be \"user\" or \"assistant\"",
+                index + 1
+            ),
+        };
+        let id = message.id.unwrap_or_else(|| {
+            let digest = Sha256::digest(format!(
+                "{}\n{}\n{}\n{}",
+                message.channel_id,
+                message.timestamp.to_rfc3339(),
+                message.sender_id.as_deref().unwrap_or_default(),
+                message.content
+            ));
+            hex::encode(&digest[..16])
+        });
+        messages.push(ImportedMessage {
+            id: format!("import:{id}"),
+            channel_id: message.channel_id,
+            channel_name: message.channel_name,
+            role,
+            sender_id: message.sender_id,
+            sender_name: message.sender_name,
+            content: message.content,
+            created_at: message.timestamp,
+        });
+    }
+    Ok(messages)
+}
+
+fn role_of(sender_id: &str, options: &ImportOptions) -> &'static str {
+    match &options.bot_user_id {
+        Some(bot_user_id) if bot_user_id == sender_id => "assistant",
+        _ => "user",
+    }
+}
+
+/// Store `messages` as conversation history, adding their channels. Returns
+/// how many messages were new.
+pub async fn store(pool: &SqlPool, messages: &[ImportedMessage]) -> Result<u64> {
+    let mut channels: BTreeMap<&str, (&ImportedMessage, &ImportedMessage)> = BTreeMap::new();
+    for message in messages {
+        channels
+            .entry(message.channel_id.as_str())
+            .and_modify(|(_, last)| *last = message)
+            .or_insert((message, message));
+    }
+    let channel_sql = format!(
+        "INSERT INTO channels (id, platform, display_name, created_at, last_activity_at) \
+         VALUES ($1, $2, $3, {}, {}) \
+         ON CONFLICT(id) DO UPDATE SET \
+             display_name = COALESCE(channels.display_name, excluded.display_name)",
+        pool.timestamp_placeholder(4),
+        pool.timestamp_placeholder(5)
+    );
+    for (channel_id, (first, last)) in &channels {
+        let platform = channel_id.split(':').next().unwrap_or("unknown");
+        let display_name = first.channel_name.as_ref().map(|name| match platform {
+            "discord" | "slack" => format!("#{name}"),
+            _ => name.clone(),
+        });
+        let (created_at, last_activity_at) = (
+            pool.timestamp_param(first.created_at),
+            pool.timestamp_param(last.created_at),
+        );
+        with_pool!(pool, |pool| {
+            sqlx::query(&channel_sql)
+                .bind(*channel_id)
+                .bind(platform)
+                .bind(&display_name)
+                .bind(&created_at)
+                .bind(&last_activity_at)
+                .execute(pool)
+                .await
+        })
+        .with_context(|| format!("failed to add channel {channel_id}"))?;
+    }
+
+    let message_sql = format!(
+        "INSERT INTO conversation_messages \
+         (id, channel_id, role, sender_name, sender_id, content, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, {}) \
+         ON CONFLICT(id) DO NOTHING",
+        pool.timestamp_placeholder(7)
+    );
+    let mut imported = 0;
+    for batch in messages.chunks(STORE_BATCH_ROWS) {
+        let times: Vec<String> = batch
+            .iter()
+            .map(|message| pool.timestamp_param(message.created_at))
+            .collect();
+        imported += with_pool!(pool, |pool| {
+            async {
+                let mut transaction = pool.begin().await?;
+                let mut inserted = 0;
+                for (message, created_at) in batch.iter().zip(&times) {
+                    inserted += sqlx::query(&message_sql)
+                        .bind(&message.id)
+                        .bind(&message.channel_id)
+                        .bind(message.role)
+                        .bind(&message.sender_name)
+                        .bind(&message.sender_id)
+                        .bind(&message.content)
+                        .bind(created_at)
+                        .execute(&mut *transaction)
+                        .await?
+                        .rows_affected();
+                }
+                transaction.commit().await?;
+                Ok::<_, sqlx::Error>(inserted)
+            }
+            .await
+        })
+        .context("failed to store imported messages")?;
+    }
+    Ok(imported)
+}
+
+/// Render each channel's messages as a Markdown log in `ingest_dir`, for
+/// memory ingestion to pick up. Returns the files written.
+pub fn write_memory_sources(
+    ingest_dir: &Path,
+    messages: &[ImportedMessage],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut channels: BTreeMap<&str, Vec<&ImportedMessage>> = BTreeMap::new();
+    for message in messages {
+        channels
+            .entry(message.channel_id.as_str())
+            .or_default()
+            .push(message);
+    }
+
+    std::fs::create_dir_all(ingest_dir)
+        .with_context(|| format!("failed to create {}", ingest_dir.display()))?;
+    let mut written = Vec::new();
+    for (channel_id, messages) in channels {
+        let path = ingest_dir.join(format!("imported-{}.md", file_component(channel_id)));
+        std::fs::write(&path, render_log(channel_id, &messages))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn render_log(channel_id: &str, messages: &[&ImportedMessage]) -> String {
+    let name = messages
+        .iter()
+        .find_map(|message| message.channel_name.as_deref())
+        .unwrap_or(channel_id);
+    let mut log = format!("# Chat history from {name} ({channel_id})\n\n");
+    for message in messages {
+        let sender = match message.role {
+            "assistant" => "Assistant",
+            _ => message
+                .sender_name
+                .as_deref()
+                .or(message.sender_id.as_deref())
+                .unwrap_or("Someone"),
+        };
+        let _ = writeln!(
+            log,
+            "[{}] {sender}: {}",
+            message.created_at.format("%Y-%m-%d %H:%M"),
+            message.content
+        );
+    }
+    log
+}
+
+fn file_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discord_exports_parse() {
+        let json = r#"{
+            "guild": {"id": "10", "name": "Club"},
+            "channel": {"id": "20", "type": "GuildTextChat", "name": "general"},
+            "messages": [
+                {"id": "1", "type": "Default", "timestamp": "2024-05-01T09:30:00.123+00:00",
+                 "content": "morning", "author": {"id": "7", "name": "ana", "nickname": "Ana", "isBot": false},
+                 "attachments": [{"id": "9", "url": "https://cdn/x.png", "fileName": "x.png"}]},
+                {"id": "2", "type": "Default", "timestamp": "2024-05-01T09:31:00+00:00",
+                 "content": "", "author": {"id": "8", "name": "bob", "isBot": false}},
+                {"id": "3", "type": "Default", "timestamp": "2024-05-01T09:32:00+00:00",
+                 "content": "hello!", "author": {"id": "99", "name": "spacebot", "isBot": true}}
+            ]
+        }"#;
+        let options = ImportOptions {
+            bot_user_id: Some("99".into()),
+            ..Default::default()
+        };
+        let messages = parse_discord(json, &options).expect("export should parse");
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].id, "import:discord:1");
+        assert_eq!(messages[0].channel_id, "discord:10:20");
+        assert_eq!(messages[0].sender_name.as_deref(), Some("Ana"));
+        assert_eq!(messages[0].content, "morning\n[attachment: x.png]");
+        assert_eq!(messages[1].role, "assistant");
+    }
+
+    #[test]
+    fn slack_exports_parse() {
+        let files = BTreeMap::from([
+            (
+                "export/channels.json".to_string(),
+                r#"[{"id": "C1", "name": "general"}]"#.to_string(),
+            ),
+            (
+                "export/users.json".to_string(),
+                r#"[{"id": "U1", "name": "ana", "team_id": "T9",
+                     "profile": {"display_name": "", "real_name": "Ana Lima"}}]"#
+                    .to_string(),
+            ),
+            (
+                "export/general/2024-05-01.json".to_string(),
+                r#"[
+                    {"type": "message", "subtype": "channel_join", "user": "U1", "text": "<@U1> has joined", "ts": "1714550000.000100"},
+                    {"type": "message", "user": "U1", "text": "ping <@U1|ana>", "ts": "1714550400.000200"}
+                ]"#
+                .to_string(),
+            ),
+        ]);
+        let messages = parse_slack(&files, &ImportOptions::default()).expect("export should parse");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].channel_id, "slack:T9:C1");
+        assert_eq!(messages[0].sender_name.as_deref(), Some("Ana Lima"));
+        assert_eq!(messages[0].content, "ping @Ana Lima");
+        assert_eq!(
+            messages[0].created_at,
+            chrono::DateTime::from_timestamp(1714550400, 200_000).unwrap()
+        );
+    }
+
+    fn jsonl_line(content: &str, timestamp: &str) -> String {
+        serde_json::json!({
+            "channel_id": "portal:chat",
+            "content": content,
+            "timestamp": timestamp,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn jsonl_ids_are_stable_across_imports() {
+        let line = jsonl_line("hi", "2024-05-01T09:30:00Z");
+        let first = parse_jsonl(&line).expect("line should parse");
+        let second = parse_jsonl(&format!("\n{line}\n")).expect("line should parse");
+        assert_eq!(first, second);
+
+        assert!(parse_jsonl(r#"{"channel_id": "x", "content": "hi"}"#).is_err());
+        let bad_role = line.replace("\"content\"", "\"role\":\"bot\",\"content\"");
+        assert!(parse_jsonl(&bad_role).is_err());
+    }
+
+    #[tokio::test]
+    async fn reimporting_adds_nothing() {
+        let pool = crate::memory::MemoryStore::connect_in_memory()
+            .await
+            .pool()
+            .clone();
+        let messages = parse_jsonl(&format!(
+            "{}\n{}",
+            jsonl_line("one", "2024-05-01T09:30:00Z"),
+            jsonl_line("two", "2024-05-01T09:31:00Z")
+        ))
+        .expect("lines should parse");
+
+        assert_eq!(store(&pool, &messages).await.expect("store"), 2);
+        assert_eq!(store(&pool, &messages).await.expect("store"), 0);
+
+        let recent = crate::conversation::ConversationLogger::new(pool)
+            .load_recent(&"portal:chat".into(), 10)
+            .await
+            .expect("messages should load");
+        let contents: Vec<&str> = recent.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["one", "two"]);
+    }
+}
@@ -0,0 +1,728 @@
+//! Background job queue.
+//!
+//! Background work that can be described by plain data goes through a named
+//! queue per agent instead of ad-hoc `tokio::spawn`: memory ingestion (files
+//! and web pages), daily digests, feed entry summaries, and alert notices.
+//! Jobs are plain data, a kind plus a JSON payload, so they can live outside
+//! the process. A worker claims jobs, runs them through the agent's handler,
+//! retries failures with exponential backoff, and moves jobs that keep
+//! failing to a dead-letter list that `!jobs` shows.
+//!
+//! The in-memory backend is the default. With `[jobs] backend = "redis"`,
+//! queues survive restarts and replicas hosting the same agent share them.
+//! Claimed jobs hold a lease; if a replica dies mid-job, the lease expires and
+//! the job is claimed again, so handlers must tolerate running twice.
+//!
+//! Not covered yet: context compaction (and the summaries it writes) still
+//! runs in-process, since it rewrites a live channel's history, and cortex
+//! and cron batch work keep their own schedulers. There is no Postgres
+//! backend; Redis is the only one that outlives the process.
+
+use crate::db::redis::{RedisClient, Reply};
+use crate::error::Result;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How many dead-lettered jobs each queue keeps.
+const DEAD_LETTER_LIMIT: usize = 100;
+/// How long an idle worker waits before checking its queue again.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(10);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(600);
+
+/// Atomically promote due retries and expired leases, then claim the oldest
+/// ready job under a new lease. KEYS: ready, delayed, running. ARGV: now, lease (ms).
+const CLAIM_SCRIPT: &str = r#"
+local now = tonumber(ARGV[1])
+for _, key in ipairs({KEYS[2], KEYS[3]}) do
+  local due = redis.call('ZRANGEBYSCORE', key, '-inf', now, 'LIMIT', 0, 100)
+  for _, job in ipairs(due) do
+    redis.call('ZREM', key, job)
+    redis.call('LPUSH', KEYS[1], job)
+  end
+end
+local job = redis.call('RPOP', KEYS[1])
+if job then
+  redis.call('ZADD', KEYS[3], now + tonumber(ARGV[2]), job)
+end
+return job
+"#;
+
+/// Where queues live.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JobsBackend {
+    /// Per-process; queued jobs are lost on restart.
+    #[default]
+    Memory,
+    Redis,
+}
+
+impl std::str::FromStr for JobsBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "memory" => Ok(Self::Memory),
+            "redis" => Ok(Self::Redis),
+            other => Err(format!(
+                "unknown jobs backend {other:?}: must be \"memory\" or \"redis\""
+            )),
+        }
+    }
+}
+
+/// Job queue settings (instance-level, under `[jobs]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobsConfig {
+    pub backend: JobsBackend,
+    /// `redis[s]://[[username]:password@]host[:port][/database]`. Required for `redis`.
+    pub redis_url: Option<String>,
+    /// Prefix for every key, so several fleets can share one Redis.
+    pub key_prefix: String,
+    /// Attempts before a job is dead-lettered.
+    pub max_attempts: u32,
+    /// Jobs each agent runs at once.
+    pub concurrency: usize,
+    /// How long a claimed job may run before another worker may claim it again.
+    pub lease: Duration,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            backend: JobsBackend::Memory,
+            redis_url: None,
+            key_prefix: "spacebot".into(),
+            max_attempts: 3,
+            concurrency: 2,
+            lease: Duration::from_secs(600),
+        }
+    }
+}
+
+/// One unit of background work.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    /// Which handler runs it, e.g. "ingestion.file".
+    pub kind: String,
+    pub payload: serde_json::Value,
+    /// While the job is pending, enqueueing the same key again is a no-op.
+    #[serde(default)]
+    pub unique_key: Option<String>,
+    /// Attempts made so far.
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    pub enqueued_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Depth and recent failures of one queue.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueStats {
+    pub ready: u64,
+    /// Failed at least once, waiting for their retry.
+    pub retrying: u64,
+    pub running: u64,
+    pub dead: u64,
+    /// Most recently dead-lettered jobs, newest first.
+    pub recent_dead: Vec<Job>,
+}
+
+impl QueueStats {
+    /// Chat-friendly summary for the `!jobs` admin command.
+    pub fn render(&self, queue: &str) -> String {
+        let mut text = format!(
+            "Jobs for `{queue}`: {} queued, {} retrying, {} running, {} dead-lettered.",
+            self.ready, self.retrying, self.running, self.dead
+        );
+        if !self.recent_dead.is_empty() {
+            text.push_str("\nRecent failures:");
+            for job in &self.recent_dead {
+                text.push_str(&format!(
+                    "\n- {} after {} attempts: {}",
+                    job.kind,
+                    job.attempts,
+                    job.last_error.as_deref().unwrap_or("unknown error")
+                ));
+            }
+        }
+        text
+    }
+}
+
+/// What happened to a failed job.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailOutcome {
+    /// Queued again after the delay.
+    Retry(Duration),
+    DeadLettered,
+}
+
+#[derive(Debug, Default)]
+struct MemoryQueue {
+    ready: VecDeque<Job>,
+    /// Retry time (unix ms) and job.
+    delayed: Vec<(i64, Job)>,
+    running: HashMap<String, Job>,
+    dead: VecDeque<Job>,
+    unique: HashSet<String>,
+}
+
+enum Store {
+    Memory(std::sync::Mutex<HashMap<String, MemoryQueue>>),
+    Redis {
+        client: RedisClient,
+        key_prefix: String,
+    },
+}
+
+/// A claimed job plus its stored form, which identifies it in Redis.
+struct Claimed {
+    job: Job,
+    raw: String,
+}
+
+/// Named job queues on one backend.
+pub struct JobQueue {
+    store: Store,
+    max_attempts: u32,
+    concurrency: usize,
+    lease: Duration,
+}
+
+impl std::fmt::Debug for JobQueue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let backend = match self.store {
+            Store::Memory(_) => JobsBackend::Memory,
+            Store::Redis { .. } => JobsBackend::Redis,
+        };
+        f.debug_struct("JobQueue")
+            .field("backend", &backend)
+            .field("max_attempts", &self.max_attempts)
+            .field("concurrency", &self.concurrency)
+            .finish_non_exhaustive()
+    }
+}
+
+impl JobQueue {
+    /// Build the queue for `config`. Doesn't connect; the first command does.
+    pub fn from_config(config: &JobsConfig) -> Result<Self> {
+        let store = match (config.backend, &config.redis_url) {
+            (JobsBackend::Redis, Some(redis_url)) => Store::Redis {
+                client: RedisClient::from_url(redis_url)?,
+                key_prefix: config.key_prefix.clone(),
+            },
+            _ => Store::Memory(std::sync::Mutex::new(HashMap::new())),
+        };
+        Ok(Self {
+            store,
+            max_attempts: config.max_attempts.max(1),
+            concurrency: config.concurrency.max(1),
+            lease: config.lease,
+        })
+    }
+
+    /// In-memory queue with default settings.
+    pub fn in_memory() -> Self {
+        Self::from_config(&JobsConfig::default()).expect("in-memory job queue always builds")
+    }
+
+    /// Add a job to `queue`.
+    pub async fn enqueue(
+        &self,
+        queue: &str,
+        kind: &str,
+        payload: serde_json::Value,
+    ) -> Result<Job> {
+        crate::watchdog::admit_background_job()?;
+        let job = new_job(kind, payload, None);
+        self.push(queue, &job).await?;
+        Ok(job)
+    }
+
+    /// Add a job unless one with the same `unique_key` is still pending or
+    /// running. Returns `None` when it was already queued.
+    pub async fn enqueue_unique(
+        &self,
+        queue: &str,
+        unique_key: &str,
+        kind: &str,
+        payload: serde_json::Value,
+    ) -> Result<Option<Job>> {
+        crate::watchdog::admit_background_job()?;
+        match &self.store {
+            Store::Memory(queues) => {
+                let mut queues = queues.lock().expect("job queue poisoned");
+                let state = queues.entry(queue.to_string()).or_default();
+                if !state.unique.insert(unique_key.to_string()) {
+                    return Ok(None);
+                }
+                let job = new_job(kind, payload, Some(unique_key.to_string()));
+                state.ready.push_back(job.clone());
+                Ok(Some(job))
+            }
+            Store::Redis { client, .. } => {
+                let added = client
+                    .command(&["SADD", &self.key(queue, "unique"), unique_key])
+                    .await?;
+                if added != Reply::Integer(1) {
+                    return Ok(None);
+                }
+                let job = new_job(kind, payload, Some(unique_key.to_string()));
+                self.push(queue, &job).await?;
+                Ok(Some(job))
+            }
+        }
+    }
+
+    /// Current depth and recent failures of `queue`.
+    pub async fn stats(&self, queue: &str) -> Result<QueueStats> {
+        match &self.store {
+            Store::Memory(queues) => {
+                let queues = queues.lock().expect("job queue poisoned");
+                let Some(state) = queues.get(queue) else {
+                    return Ok(QueueStats::default());
+                };
+                Ok(QueueStats {
+                    ready: state.ready.len() as u64,
+                    retrying: state.delayed.len() as u64,
+                    running: state.running.len() as u64,
+                    dead: state.dead.len() as u64,
+                    recent_dead: state.dead.iter().take(5).cloned().collect(),
+                })
+            }
+            Store::Redis { client, .. } => {
+                let (ready, delayed, running, dead) = (
+                    self.key(queue, "ready"),
+                    self.key(queue, "delayed"),
+                    self.key(queue, "running"),
+                    self.key(queue, "dead"),
+                );
+                let commands: [&[&str]; 5] = [
+                    &["LLEN", &ready],
+                    &["ZCARD", &delayed],
+                    &["ZCARD", &running],
+                    &["LLEN", &dead],
+                    &["LRANGE", &dead, "0", "4"],
+                ];
+                let replies = client.pipeline(&commands).await?;
+                let count = |index: usize| match replies[index] {
+                    Reply::Integer(count) => count.max(0) as u64,
+                    _ => 0,
+                };
+                let recent_dead = match &replies[4] {
+                    Reply::Array(Some(items)) => items
+                        .iter()
+                        .filter_map(|item| item.clone().into_string())
+                        .filter_map(|raw| serde_json::from_str(&raw).ok())
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                Ok(QueueStats {
+                    ready: count(0),
+                    retrying: count(1),
+                    running: count(2),
+                    dead: count(3),
+                    recent_dead,
+                })
+            }
+        }
+    }
+
+    /// Run jobs from `queue` through `handler` until the task is aborted.
+    pub fn spawn_worker<F, Fut>(
+        self: Arc<Self>,
+        queue: String,
+        handler: F,
+    ) -> tokio::task::JoinHandle<()>
+    where
+        F: Fn(Job) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let slots = Arc::new(tokio::sync::Semaphore::new(self.concurrency));
+        tokio::spawn(async move {
+            tracing::info!(%queue, concurrency = self.concurrency, "job worker started");
+            loop {
+                let Ok(slot) = slots.clone().acquire_owned().await else {
+                    return;
+                };
+                let claimed = match self.claim(&queue).await {
+                    Ok(Some(claimed)) => claimed,
+                    Ok(None) => {
+                        drop(slot);
+                        tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+                        continue;
+                    }
+                    Err(error) => {
+                        drop(slot);
+                        tracing::warn!(%error, %queue, "failed to claim job");
+                        tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+                        continue;
+                    }
+                };
+
+                let jobs = self.clone();
+                let handler = handler.clone();
+                let queue = queue.clone();
+                tokio::spawn(async move {
+                    let _slot = slot;
+                    jobs.run_claimed(&queue, claimed, handler.as_ref()).await;
+                });
+            }
+        })
+    }
+
+    async fn run_claimed<F, Fut>(&self, queue: &str, claimed: Claimed, handler: &F)
+    where
+        F: Fn(Job) -> Fut,
+        Fut: Future<Output = anyhow::Result<()>>,
+    {
+        let Claimed { mut job, raw } = claimed;
+        job.attempts += 1;
+        tracing::debug!(%queue, job_id = %job.id, kind = %job.kind, attempt = job.attempts, "running job");
+
+        let outcome = match handler(job.clone()).await {
+            Ok(()) => self.complete(queue, &job, &raw).await.map(|()| None),
+            Err(error) => {
+                let error = format!("{error:#}");
+                let outcome = self.fail(queue, job.clone(), &raw, error.clone()).await;
+                match &outcome {
+                    Ok(FailOutcome::Retry(delay)) => tracing::warn!(
+                        %queue, job_id = %job.id, kind = %job.kind, attempt = job.attempts,
+                        retry_in_secs = delay.as_secs(), %error, "job failed, will retry"
+                    ),
+                    Ok(FailOutcome::DeadLettered) => tracing::error!(
+                        %queue, job_id = %job.id, kind = %job.kind, attempts = job.attempts,
+                        %error, "job failed too many times, dead-lettered"
+                    ),
+                    Err(_) => {}
+                }
+                outcome.map(Some)
+            }
+        };
+        if let Err(error) = outcome {
+            tracing::error!(%error, %queue, job_id = %job.id, "failed to record job result");
+        }
+    }
+
+    fn key(&self, queue: &str, part: &str) -> String {
+        let key_prefix = match &self.store {
+            Store::Redis { key_prefix, .. } => key_prefix.as_str(),
+            Store::Memory(_) => "",
+        };
+        format!("{key_prefix}:jobs:{queue}:{part}")
+    }
+
+    async fn push(&self, queue: &str, job: &Job) -> Result<()> {
+        match &self.store {
+            Store::Memory(queues) => {
+                let mut queues = queues.lock().expect("job queue poisoned");
+                queues
+                    .entry(queue.to_string())
+                    .or_default()
+                    .ready
+                    .push_back(job.clone());
+            }
+            Store::Redis { client, .. } => {
+                let raw = serde_json::to_string(job).context("failed to serialize job")?;
+                client
+                    .command(&["LPUSH", &self.key(queue, "ready"), &raw])
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn claim(&self, queue: &str) -> Result<Option<Claimed>> {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        match &self.store {
+            Store::Memory(queues) => {
+                let mut queues = queues.lock().expect("job queue poisoned");
+                let Some(state) = queues.get_mut(queue) else {
+                    return Ok(None);
+                };
+                let (due, waiting) = std::mem::take(&mut state.delayed)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|(retry_at, _)| *retry_at <= now_ms);
+                state.delayed = waiting;
+                state.ready.extend(due.into_iter().map(|(_, job)| job));
+
+                let Some(job) = state.ready.pop_front() else {
+                    return Ok(None);
+                };
+                state.running.insert(job.id.clone(), job.clone());
+                Ok(Some(Claimed {
+                    job,
+                    raw: String::new(),
+                }))
+            }
+            Store::Redis { client, .. } => {
+                let reply = client
+                    .command(&[
+                        "EVAL",
+                        CLAIM_SCRIPT,
+                        "3",
+                        &self.key(queue, "ready"),
+                        &self.key(queue, "delayed"),
+                        &self.key(queue, "running"),
+                        &now_ms.to_string(),
+                        &self.lease.as_millis().to_string(),
+                    ])
+                    .await?;
+                let Some(raw) = reply.into_string() else {
+                    return Ok(None);
+                };
+                let job = serde_json::from_str(&raw).context("failed to parse queued job")?;
+                Ok(Some(Claimed { job, raw }))
+            }
+        }
+    }
+
+    async fn complete(&self, queue: &str, job: &Job, raw: &str) -> Result<()> {
+        match &self.store {
+            Store::Memory(queues) => {
+                let mut queues = queues.lock().expect("job queue poisoned");
+                if let Some(state) = queues.get_mut(queue) {
+                    state.running.remove(&job.id);
+                    if let Some(unique_key) = &job.unique_key {
+                        state.unique.remove(unique_key);
+                    }
+                }
+            }
+            Store::Redis { client, .. } => {
+                let running = self.key(queue, "running");
+                let unique = self.key(queue, "unique");
+                let mut commands = vec![vec!["MULTI"], vec!["ZREM", running.as_str(), raw]];
+                if let Some(unique_key) = &job.unique_key {
+                    commands.push(vec!["SREM", unique.as_str(), unique_key.as_str()]);
+                }
+                commands.push(vec!["EXEC"]);
+                let commands: Vec<&[&str]> = commands.iter().map(Vec::as_slice).collect();
+                client.pipeline(&commands).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn fail(
+        &self,
+        queue: &str,
+        mut job: Job,
+        raw: &str,
+        error: String,
+    ) -> Result<FailOutcome> {
+        job.last_error = Some(error);
+        let outcome = if job.attempts >= self.max_attempts {
+            FailOutcome::DeadLettered
+        } else {
+            FailOutcome::Retry(retry_delay(job.attempts))
+        };
+        let retry_at_ms = match outcome {
+            FailOutcome::Retry(delay) => {
+                chrono::Utc::now().timestamp_millis() + delay.as_millis() as i64
+            }
+            FailOutcome::DeadLettered => 0,
+        };
+
+        match &self.store {
+            Store::Memory(queues) => {
+                let mut queues = queues.lock().expect("job queue poisoned");
+                let state = queues.entry(queue.to_string()).or_default();
+                state.running.remove(&job.id);
+                match outcome {
+                    FailOutcome::Retry(_) => state.delayed.push((retry_at_ms, job)),
+                    FailOutcome::DeadLettered => {
+                        if let Some(unique_key) = &job.unique_key {
+                            state.unique.remove(unique_key);
+                        }
+                        state.dead.push_front(job);
+                        state.dead.truncate(DEAD_LETTER_LIMIT);
+                    }
+                }
+            }
+            Store::Redis { client, .. } => {
+                let updated = serde_json::to_string(&job).context("failed to serialize job")?;
+                let (running, delayed, dead, unique) = (
+                    self.key(queue, "running"),
+                    self.key(queue, "delayed"),
+                    self.key(queue, "dead"),
+                    self.key(queue, "unique"),
+                );
+                let retry_at = retry_at_ms.to_string();
+                let dead_limit = (DEAD_LETTER_LIMIT - 1).to_string();
+                let unique_key = job.unique_key.clone().unwrap_or_default();
+
+                let mut commands: Vec<Vec<&str>> = vec![vec!["MULTI"], vec!["ZREM", &running, raw]];
+                match outcome {
+                    FailOutcome::Retry(_) => {
+                        commands.push(vec!["ZADD", &delayed, &retry_at, &updated]);
+                    }
+                    FailOutcome::DeadLettered => {
+                        commands.push(vec!["LPUSH", &dead, &updated]);
+                        commands.push(vec!["LTRIM", &dead, "0", &dead_limit]);
+                        if job.unique_key.is_some() {
+                            commands.push(vec!["SREM", &unique, &unique_key]);
+                        }
+                    }
+                }
+                commands.push(vec!["EXEC"]);
+                let commands: Vec<&[&str]> = commands.iter().map(Vec::as_slice).collect();
+                client.pipeline(&commands).await?;
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+fn new_job(kind: &str, payload: serde_json::Value, unique_key: Option<String>) -> Job {
+    Job {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind: kind.to_string(),
+        payload,
+        unique_key,
+        attempts: 0,
+        last_error: None,
+        enqueued_at: chrono::Utc::now(),
+    }
+}
+
+/// Exponential backoff after the `attempts`th failure, capped.
+fn retry_delay(attempts: u32) -> Duration {
+    let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+    RETRY_BASE_DELAY.saturating_mul(factor).min(RETRY_MAX_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_back_off_exponentially() {
+        assert_eq!(retry_delay(1), Duration::from_secs(10));
+        assert_eq!(retry_delay(2), Duration::from_secs(20));
+        assert_eq!(retry_delay(3), Duration::from_secs(40));
+        assert_eq!(retry_delay(30), RETRY_MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn failing_jobs_retry_then_dead_letter() {
+        let jobs = JobQueue::from_config(&JobsConfig {
+            max_attempts: 2,
+            ..JobsConfig::default()
+        })
+        .expect("queue should build");
+        jobs.enqueue("main", "test.fail", serde_json::json!({ "n": 1 }))
+            .await
+            .expect("enqueue should work");
+
+        let claimed = jobs
+            .claim("main")
+            .await
+            .expect("claim")
+            .expect("job is ready");
+        let mut job = claimed.job;
+        job.attempts += 1;
+        let outcome = jobs
+            .fail("main", job, "", "boom".into())
+            .await
+            .expect("fail");
+        assert_eq!(outcome, FailOutcome::Retry(Duration::from_secs(10)));
+        let stats = jobs.stats("main").await.expect("stats");
+        assert_eq!((stats.ready, stats.retrying, stats.running), (0, 1, 0));
+        assert!(jobs.claim("main").await.expect("claim").is_none());
+
+        // Make the retry due, then fail it for the last time.
+        {
+            let Store::Memory(queues) = &jobs.store else {
+                unreachable!()
+            };
+            let mut queues = queues.lock().expect("job queue poisoned");
+            queues.get_mut("main").expect("queue exists").delayed[0].0 = 0;
+        }
+        let mut job = jobs
+            .claim("main")
+            .await
+            .expect("claim")
+            .expect("retry is due")
+            .job;
+        job.attempts += 1;
+        let outcome = jobs
+            .fail("main", job, "", "boom again".into())
+            .await
+            .expect("fail");
+        assert_eq!(outcome, FailOutcome::DeadLettered);
+
+        let stats = jobs.stats("main").await.expect("stats");
+        assert_eq!((stats.ready, stats.retrying, stats.dead), (0, 0, 1));
+        assert_eq!(
+            stats.recent_dead[0].last_error.as_deref(),
+            Some("boom again")
+        );
+        assert!(stats.render("main").contains("1 dead-lettered"));
+        assert!(
+            stats
+                .render("main")
+                .contains("test.fail after 2 attempts: boom again")
+        );
+    }
+
+    #[tokio::test]
+    async fn unique_jobs_are_queued_once_until_done() {
+        let jobs = JobQueue::in_memory();
+        let first = jobs
+            .enqueue_unique("main", "file-a", "test.unique", serde_json::Value::Null)
+            .await
+            .expect("enqueue should work");
+        assert!(first.is_some());
+        let again = jobs
+            .enqueue_unique("main", "file-a", "test.unique", serde_json::Value::Null)
+            .await
+            .expect("enqueue should work");
+        assert!(again.is_none());
+
+        let claimed = jobs
+            .claim("main")
+            .await
+            .expect("claim")
+            .expect("job is ready");
+        jobs.complete("main", &claimed.job, &claimed.raw)
+            .await
+            .expect("complete should work");
+        let after = jobs
+            .enqueue_unique("main", "file-a", "test.unique", serde_json::Value::Null)
+            .await
+            .expect("enqueue should work");
+        assert!(after.is_some());
+    }
+
+    #[tokio::test]
+    async fn workers_run_jobs_to_completion() {
+        let jobs = Arc::new(JobQueue::in_memory());
+        let (done_tx, mut done_rx) = tokio::sync::mpsc::unbounded_channel();
+        let worker = jobs.clone().spawn_worker("main".into(), move |job| {
+            let done_tx = done_tx.clone();
+            async move {
+                done_tx.send(job.payload).ok();
+                Ok(())
+            }
+        });
+
+        jobs.enqueue("main", "test.echo", serde_json::json!("hello"))
+            .await
+            .expect("enqueue should work");
+        let payload = tokio::time::timeout(Duration::from_secs(5), done_rx.recv())
+            .await
+            .expect("job should run")
+            .expect("worker is alive");
+        assert_eq!(payload, serde_json::json!("hello"));
+        worker.abort();
+    }
+}
@@ -1,28 +1,65 @@
 //! Spacebot: A Rust agentic system where every LLM process has a dedicated role.
 
+pub mod access;
 pub mod agent;
+pub mod alerts;
 pub mod api;
+pub mod api_keys;
 pub mod auth;
+pub mod backup;
+pub mod calc;
+pub mod calendar;
+pub mod chart;
 pub mod config;
 pub mod conversation;
+pub mod crash;
 pub mod cron;
 pub mod daemon;
 pub mod db;
+pub mod egress;
+pub mod email;
 pub mod error;
+pub mod eval;
+pub mod events;
+pub mod feeds;
+pub mod flows;
+pub mod heartbeat;
 pub mod hooks;
 pub mod identity;
+pub mod import;
+pub mod injection;
+pub mod jobs;
+pub mod listeners;
 pub mod llm;
+pub mod log_levels;
+pub mod logging;
 pub mod memory;
 pub mod messaging;
+pub mod openapi;
 pub mod opencode;
+pub mod plugins;
+pub mod polls;
+pub mod preflight;
 pub mod prompts;
+pub mod proxy;
+pub mod reminders;
+pub mod scripting;
 pub mod secrets;
 pub mod settings;
 pub mod skills;
+pub mod sql_query;
+pub mod storage;
+pub mod supervisor;
+pub mod table;
+pub mod tail_sampling;
 #[cfg(feature = "metrics")]
 pub mod telemetry;
+pub mod tenants;
 pub mod tools;
 pub mod update;
+pub mod user_data;
+pub mod watchdog;
+pub mod weather;
 
 pub use error::{Error, Result};
 
@@ -179,8 +216,13 @@ pub struct AgentDeps {
     pub cron_tool: Option<tools::CronTool>,
     pub runtime_config: Arc<config::RuntimeConfig>,
     pub event_tx: tokio::sync::broadcast::Sender<ProcessEvent>,
-    pub sqlite_pool: sqlx::SqlitePool,
+    pub sql_pool: db::SqlPool,
+    /// Buffered conversation writes, shared by the agent's channels.
+    pub conversation_writes: Arc<conversation::WriteBatch>,
     pub messaging_manager: Option<Arc<messaging::MessagingManager>>,
+    pub jobs: Arc<jobs::JobQueue>,
+    pub artifacts: Arc<storage::ArtifactStore>,
+    pub plugins: Arc<plugins::PluginHost>,
 }
 
 impl AgentDeps {
@@ -248,6 +290,16 @@ pub enum MessageContent {
         /// Platform-specific message reference (`ts` on Slack, message ID on Discord).
         message_ts: Option<String>,
     },
+    /// A slash command for a tool, with typed arguments. The channel calls the
+    /// tool directly instead of running an LLM turn.
+    ///
+    /// Produced by Slack and Discord adapters for the tools listed in
+    /// `defaults.slash_commands`.
+    Command {
+        /// The tool to call.
+        name: String,
+        args: serde_json::Map<String, serde_json::Value>,
+    },
 }
 
 impl std::fmt::Display for MessageContent {
@@ -275,6 +327,16 @@ impl std::fmt::Display for MessageContent {
                     write!(f, "[interaction: {}]", action_id)
                 }
             }
+            MessageContent::Command { name, args } => {
+                write!(f, "/{}", name)?;
+                for (key, value) in args {
+                    match value {
+                        serde_json::Value::String(text) => write!(f, " {}={:?}", key, text)?,
+                        other => write!(f, " {}={}", key, other)?,
+                    }
+                }
+                Ok(())
+            }
         }
     }
 }
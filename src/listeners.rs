@@ -0,0 +1,566 @@
+//! TLS and IP allowlisting for the embedded HTTP servers (`[listeners]`).
+//!
+//! The control API, metrics, webhook, WebSocket, and web chat servers all bind
+//! through [`bind`]. For the servers `[listeners]` covers, connections from
+//! addresses outside `allowed_ips` are dropped before anything is read, and
+//! with `[listeners.tls]` the rest are served over rustls, optionally
+//! requiring a client certificate signed by `client_ca_path` (mTLS).
+//!
+//! Behind a reverse proxy every connection comes from the proxy. Addresses in
+//! `trusted_proxies` are let through at the connection level, and each of
+//! their requests is checked against the client address in
+//! `X-Forwarded-For` instead.
+
+use anyhow::Context as _;
+use arc_swap::ArcSwap;
+use axum::Router;
+use axum::extract::connect_info::{ConnectInfo, Connected};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse as _, Response};
+use axum::serve::IncomingStream;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Servers `[listeners]` can cover, by the name used in `servers`.
+pub const SERVERS: &[&str] = &["api", "metrics", "webhook", "websocket", "web", "proxy"];
+
+/// How long a client has to finish the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings every server reads when it binds. Set once at startup.
+static LISTENERS: LazyLock<ArcSwap<ListenersConfig>> =
+    LazyLock::new(|| ArcSwap::from_pointee(ListenersConfig::default()));
+
+/// Hardening for the embedded servers (instance-level, under `[listeners]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListenersConfig {
+    /// Which servers the settings apply to (see [`SERVERS`]).
+    pub servers: Vec<String>,
+    /// Addresses allowed to connect. Empty means any address.
+    pub allowed_ips: Vec<IpNet>,
+    /// Reverse proxies whose `X-Forwarded-For` is believed.
+    pub trusted_proxies: Vec<IpNet>,
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for ListenersConfig {
+    fn default() -> Self {
+        Self {
+            servers: SERVERS.iter().map(|server| server.to_string()).collect(),
+            allowed_ips: Vec::new(),
+            trusted_proxies: Vec::new(),
+            tls: None,
+        }
+    }
+}
+
+impl ListenersConfig {
+    /// Whether the settings change anything for `server`.
+    fn guards(&self, server: &str) -> bool {
+        self.servers.iter().any(|name| name == server)
+            && (self.tls.is_some() || !self.allowed_ips.is_empty())
+    }
+}
+
+/// Certificate and key for serving TLS (`[listeners.tls]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first.
+    pub cert_path: PathBuf,
+    /// PEM private key.
+    pub key_path: PathBuf,
+    /// PEM CA certificates. When set, clients must present a certificate
+    /// signed by one of them.
+    pub client_ca_path: Option<PathBuf>,
+}
+
+/// Install the settings servers bind with. Servers already bound keep
+/// theirs, so changes need a restart.
+pub fn configure(config: ListenersConfig) {
+    LISTENERS.store(Arc::new(config));
+}
+
+/// An IP address or CIDR range, e.g. `10.0.0.0/8` or `2001:db8::/32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+
+    /// The range's prefix length; longer is more specific.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix
+    }
+}
+
+impl std::str::FromStr for IpNet {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+        let addr: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("'{value}' isn't an IP address or CIDR range"))?;
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .trim()
+                .parse::<u8>()
+                .ok()
+                .filter(|prefix| *prefix <= max_prefix)
+                .ok_or_else(|| format!("'{value}' has an invalid prefix length"))?,
+            None => max_prefix,
+        };
+        Ok(Self {
+            addr: addr.to_canonical(),
+            prefix,
+        })
+    }
+}
+
+fn contains_any(nets: &[IpNet], ip: IpAddr) -> bool {
+    nets.iter().any(|net| net.contains(ip))
+}
+
+/// Who may connect, and whose forwarded addresses count.
+#[derive(Debug)]
+struct AccessPolicy {
+    allowed_ips: Vec<IpNet>,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl AccessPolicy {
+    fn allows(&self, ip: IpAddr) -> bool {
+        self.allowed_ips.is_empty() || contains_any(&self.allowed_ips, ip)
+    }
+
+    /// Whether a connection from `peer` is let through. Trusted proxies are,
+    /// and their requests are checked one by one.
+    fn admits_peer(&self, peer: IpAddr) -> bool {
+        self.allows(peer) || contains_any(&self.trusted_proxies, peer)
+    }
+
+    /// The client a request is from: the peer itself, or for a trusted
+    /// proxy, the last `X-Forwarded-For` hop that isn't a trusted proxy.
+    fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !contains_any(&self.trusted_proxies, peer) {
+            return peer;
+        }
+        let hops: Vec<IpAddr> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|hop| hop.trim().parse().ok())
+            .collect();
+        hops.iter()
+            .rev()
+            .find(|hop| !contains_any(&self.trusted_proxies, **hop))
+            .or(hops.first())
+            .copied()
+            .unwrap_or(peer)
+    }
+}
+
+/// The remote address of a guarded connection.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientAddr(pub SocketAddr);
+
+impl Connected<IncomingStream<'_, GuardedListener>> for ClientAddr {
+    fn connect_info(stream: IncomingStream<'_, GuardedListener>) -> Self {
+        *stream.remote_addr()
+    }
+}
+
+/// A connection accepted by a [`GuardedListener`].
+pub enum Connection {
+    Plain(TcpStream),
+    Tls(Box<tokio_rustls::server::TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Connection::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Connection::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            Connection::Tls(stream) => Pin::new(stream.as_mut()).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            Connection::Plain(stream) => stream.is_write_vectored(),
+            Connection::Tls(stream) => stream.is_write_vectored(),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Connection::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Connection::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Connection::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Accepts connections from allowed addresses, completing TLS handshakes in
+/// the background so a slow client can't hold up the others.
+pub struct GuardedListener {
+    connections: mpsc::Receiver<(Connection, ClientAddr)>,
+    local_addr: SocketAddr,
+    accept_task: tokio::task::JoinHandle<()>,
+}
+
+impl GuardedListener {
+    fn new(
+        server: &'static str,
+        listener: TcpListener,
+        policy: Arc<AccessPolicy>,
+        tls: Option<TlsAcceptor>,
+    ) -> std::io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (connection_tx, connections) = mpsc::channel(64);
+        let accept_task = tokio::spawn(accept_loop(server, listener, policy, tls, connection_tx));
+        Ok(Self {
+            connections,
+            local_addr,
+            accept_task,
+        })
+    }
+}
+
+impl Drop for GuardedListener {
+    fn drop(&mut self) {
+        // Release the port along with the server.
+        self.accept_task.abort();
+    }
+}
+
+impl axum::serve::Listener for GuardedListener {
+    type Io = Connection;
+    type Addr = ClientAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // The accept loop only ends when the listener is dropped.
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(ClientAddr(self.local_addr))
+    }
+}
+
+async fn accept_loop(
+    server: &'static str,
+    listener: TcpListener,
+    policy: Arc<AccessPolicy>,
+    tls: Option<TlsAcceptor>,
+    connection_tx: mpsc::Sender<(Connection, ClientAddr)>,
+) {
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                // Usually out of file descriptors; back off rather than spin.
+                tracing::debug!(%error, server, "failed to accept connection");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        if !policy.admits_peer(peer.ip()) {
+            tracing::debug!(server, %peer, "refusing connection from outside the allowlist");
+            continue;
+        }
+
+        let Some(tls) = tls.clone() else {
+            if connection_tx
+                .send((Connection::Plain(stream), ClientAddr(peer)))
+                .await
+                .is_err()
+            {
+                return;
+            }
+            continue;
+        };
+        let connection_tx = connection_tx.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, tls.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = connection_tx
+                        .send((Connection::Tls(Box::new(stream)), ClientAddr(peer)))
+                        .await;
+                }
+                Ok(Err(error)) => {
+                    tracing::debug!(%error, server, %peer, "TLS handshake failed");
+                }
+                Err(_) => tracing::debug!(server, %peer, "TLS handshake timed out"),
+            }
+        });
+    }
+}
+
+/// A bound server socket, guarded when `[listeners]` covers the server.
+pub struct ServerListener {
+    socket: Socket,
+    /// Set when requests from trusted proxies need checking.
+    forwarded: Option<Arc<AccessPolicy>>,
+}
+
+enum Socket {
+    Plain(TcpListener),
+    Guarded(GuardedListener),
+}
+
+/// Bind `addr` for `server` (one of [`SERVERS`]). Fails when the TLS
+/// certificate or key can't be loaded.
+pub async fn bind(
+    server: &'static str,
+    addr: impl tokio::net::ToSocketAddrs,
+) -> anyhow::Result<ServerListener> {
+    let listener = TcpListener::bind(addr).await?;
+    let config = LISTENERS.load_full();
+    if !config.guards(server) {
+        return Ok(ServerListener {
+            socket: Socket::Plain(listener),
+            forwarded: None,
+        });
+    }
+
+    let tls = config
+        .tls
+        .as_ref()
+        .map(tls_acceptor)
+        .transpose()
+        .with_context(|| format!("failed to set up TLS for the {server} server"))?;
+    let policy = Arc::new(AccessPolicy {
+        allowed_ips: config.allowed_ips.clone(),
+        trusted_proxies: config.trusted_proxies.clone(),
+    });
+    let forwarded = (!policy.allowed_ips.is_empty() && !policy.trusted_proxies.is_empty())
+        .then(|| policy.clone());
+    Ok(ServerListener {
+        socket: Socket::Guarded(GuardedListener::new(server, listener, policy, tls)?),
+        forwarded,
+    })
+}
+
+impl ServerListener {
+    /// Serve `app` until `shutdown` completes.
+    pub async fn serve(
+        self,
+        app: Router,
+        shutdown: impl Future<Output = ()> + Send + 'static,
+    ) -> std::io::Result<()> {
+        match (self.socket, self.forwarded) {
+            (Socket::Plain(listener), _) => {
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(shutdown)
+                    .await
+            }
+            (Socket::Guarded(listener), None) => {
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(shutdown)
+                    .await
+            }
+            (Socket::Guarded(listener), Some(policy)) => {
+                let app = app.layer(axum::middleware::from_fn_with_state(
+                    policy,
+                    check_forwarded,
+                ));
+                axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<ClientAddr>(),
+                )
+                .with_graceful_shutdown(shutdown)
+                .await
+            }
+        }
+    }
+}
+
+/// Refuse requests relayed by a trusted proxy for a client outside the
+/// allowlist.
+async fn check_forwarded(
+    State(policy): State<Arc<AccessPolicy>>,
+    ConnectInfo(ClientAddr(peer)): ConnectInfo<ClientAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = policy.client_ip(peer.ip(), request.headers());
+    if policy.allows(client) {
+        return next.run(request).await;
+    }
+    tracing::debug!(%client, %peer, "refusing forwarded request from outside the allowlist");
+    StatusCode::FORBIDDEN.into_response()
+}
+
+fn tls_acceptor(tls: &TlsConfig) -> anyhow::Result<TlsAcceptor> {
+    use rustls::pki_types::pem::PemObject as _;
+    use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+    let read_certs = |path: &PathBuf| -> anyhow::Result<Vec<CertificateDer<'static>>> {
+        let certs = CertificateDer::pem_file_iter(path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .with_context(|| format!("failed to read certificates from {}", path.display()))?;
+        anyhow::ensure!(!certs.is_empty(), "no certificates in {}", path.display());
+        Ok(certs)
+    };
+
+    let certs = read_certs(&tls.cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(&tls.key_path)
+        .with_context(|| format!("failed to read private key from {}", tls.key_path.display()))?;
+
+    let builder = rustls::ServerConfig::builder();
+    let builder = match &tls.client_ca_path {
+        Some(path) => {
+            let mut roots = rustls::RootCertStore::empty();
+            for cert in read_certs(path)? {
+                roots
+                    .add(cert)
+                    .with_context(|| format!("invalid CA certificate in {}", path.display()))?;
+            }
+            let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::new(roots))
+                .build()
+                .context("failed to set up client certificate verification")?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .context("certificate and private key don't match")?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nets(values: &[&str]) -> Vec<IpNet> {
+        values
+            .iter()
+            .map(|value| value.parse().expect("valid net"))
+            .collect()
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().expect("valid ip")
+    }
+
+    #[test]
+    fn nets_match_addresses_and_ranges() {
+        let allowed = nets(&["10.0.0.0/8", "203.0.113.7", "2001:db8::/32", "0.0.0.0/0"]);
+        assert!(allowed[0].contains(ip("10.20.30.40")));
+        assert!(!allowed[0].contains(ip("11.0.0.1")));
+        assert!(allowed[1].contains(ip("203.0.113.7")));
+        assert!(!allowed[1].contains(ip("203.0.113.8")));
+        // IPv4-mapped IPv6 peers (dual-stack sockets) match IPv4 ranges.
+        assert!(allowed[0].contains(ip("::ffff:10.1.1.1")));
+        assert!(allowed[2].contains(ip("2001:db8:1::1")));
+        assert!(!allowed[2].contains(ip("10.0.0.1")));
+        assert!(allowed[3].contains(ip("192.0.2.1")));
+
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("example.com".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn forwarded_clients_are_checked_only_behind_trusted_proxies() {
+        let policy = AccessPolicy {
+            allowed_ips: nets(&["198.51.100.0/24"]),
+            trusted_proxies: nets(&["100.64.0.0/10"]),
+        };
+        assert!(policy.admits_peer(ip("198.51.100.9")));
+        assert!(policy.admits_peer(ip("100.64.1.1")));
+        assert!(!policy.admits_peer(ip("192.0.2.1")));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "1.2.3.4, 198.51.100.9, 100.64.0.2".parse().unwrap(),
+        );
+        // A spoofed leading hop doesn't help: the last untrusted hop counts.
+        assert_eq!(
+            policy.client_ip(ip("100.64.1.1"), &headers),
+            ip("198.51.100.9")
+        );
+        // Headers from untrusted peers are ignored.
+        assert_eq!(policy.client_ip(ip("192.0.2.1"), &headers), ip("192.0.2.1"));
+        assert_eq!(
+            policy.client_ip(ip("100.64.1.1"), &HeaderMap::new()),
+            ip("100.64.1.1")
+        );
+    }
+}
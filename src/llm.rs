@@ -1,10 +1,26 @@
 //! LLM provider management and routing.
 
+pub mod aliases;
 pub mod anthropic;
+pub mod budget;
+pub mod canary;
+pub mod chaos;
+pub mod confidence;
+pub mod cooldown;
+pub mod discovery;
+pub mod http;
 pub mod manager;
 pub mod model;
+pub mod ollama;
+pub mod openai_compatible;
 pub mod providers;
+pub mod reasoning;
+pub mod resources;
 pub mod routing;
+pub mod sampling;
+pub mod shared;
+pub mod slo;
+pub mod structured;
 
 pub use manager::LlmManager;
 pub use model::SpacebotModel;
@@ -0,0 +1,190 @@
+//! Model aliases: short names ("sonnet", "fast") for full model names.
+//!
+//! Aliases are expanded wherever a model is named: the routing config
+//! (process models, task overrides, fallbacks, reasoning settings) when it's
+//! loaded, and any other model name when a `SpacebotModel` is made from it. An alias can pin a dated
+//! snapshot, so "sonnet" keeps meaning the same weights until the config says
+//! otherwise. Aliases pointing at a model its provider has deprecated are
+//! logged at startup and on reload.
+
+use crate::llm::routing::RoutingConfig;
+
+use std::collections::HashMap;
+
+/// Models providers have retired or scheduled for retirement, with what to
+/// move to.
+const DEPRECATED_MODELS: &[(&str, &str)] = &[
+    ("anthropic/claude-2.0", "anthropic/claude-sonnet-4-5"),
+    ("anthropic/claude-2.1", "anthropic/claude-sonnet-4-5"),
+    ("anthropic/claude-instant-1.2", "anthropic/claude-haiku-4-5"),
+    (
+        "anthropic/claude-3-sonnet-20240229",
+        "anthropic/claude-sonnet-4-5",
+    ),
+    (
+        "anthropic/claude-3-opus-20240229",
+        "anthropic/claude-opus-4-1",
+    ),
+    (
+        "anthropic/claude-3-5-sonnet-20240620",
+        "anthropic/claude-sonnet-4-5",
+    ),
+    (
+        "anthropic/claude-3-5-sonnet-20241022",
+        "anthropic/claude-sonnet-4-5",
+    ),
+    (
+        "anthropic/claude-3-5-haiku-20241022",
+        "anthropic/claude-haiku-4-5",
+    ),
+    ("openai/gpt-3.5-turbo-0613", "openai/gpt-4o-mini"),
+    ("openai/gpt-4-32k", "openai/gpt-4.1"),
+    ("openai/gpt-4-vision-preview", "openai/gpt-4o"),
+    ("openai/gpt-4.5-preview", "openai/gpt-4.1"),
+    ("openai/o1-preview", "openai/o3"),
+    ("openai/o1-mini", "openai/o4-mini"),
+];
+
+/// Alias names and the full model names they stand for (instance-level,
+/// under `[llm.aliases]`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelAliases {
+    models: HashMap<String, String>,
+}
+
+impl ModelAliases {
+    /// `models` maps each alias to its full model name, snapshot included.
+    pub fn new(models: HashMap<String, String>) -> Self {
+        Self { models }
+    }
+
+    /// The model `name` stands for: its target if it's an alias, else itself.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        self.models.get(name).map_or(name, String::as_str)
+    }
+
+    /// Expand every alias the routing config names.
+    pub fn apply(&self, routing: &mut RoutingConfig) {
+        if self.models.is_empty() {
+            return;
+        }
+        let expand = |model: &mut String| *model = self.resolve(model).to_string();
+        for model in [
+            &mut routing.channel,
+            &mut routing.branch,
+            &mut routing.worker,
+            &mut routing.compactor,
+            &mut routing.cortex,
+        ] {
+            expand(model);
+        }
+        routing.task_overrides.values_mut().for_each(expand);
+        if let Some(model) = &mut routing.confidence.second_opinion_model {
+            expand(model);
+        }
+        if let Some(model) = routing
+            .canary
+            .as_mut()
+            .and_then(|canary| canary.model.as_mut())
+        {
+            expand(model);
+        }
+        routing.fallbacks = std::mem::take(&mut routing.fallbacks)
+            .into_iter()
+            .map(|(model, mut fallbacks)| {
+                fallbacks.iter_mut().for_each(expand);
+                (self.resolve(&model).to_string(), fallbacks)
+            })
+            .collect();
+        routing.reasoning = std::mem::take(&mut routing.reasoning)
+            .into_iter()
+            .map(|(model, reasoning)| (self.resolve(&model).to_string(), reasoning))
+            .collect();
+    }
+
+    /// Aliases and their targets, sorted by alias.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        let mut aliases: Vec<_> = self
+            .models
+            .iter()
+            .map(|(alias, model)| (alias.as_str(), model.as_str()))
+            .collect();
+        aliases.sort_unstable();
+        aliases.into_iter()
+    }
+}
+
+/// What to use instead of `model`, if its provider has deprecated it. Names
+/// without a provider prefix are Anthropic's.
+pub fn deprecation(model: &str) -> Option<&'static str> {
+    let model = if model.contains('/') {
+        model.to_string()
+    } else {
+        format!("anthropic/{model}")
+    };
+    DEPRECATED_MODELS
+        .iter()
+        .find(|(deprecated, _)| *deprecated == model)
+        .map(|(_, replacement)| *replacement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases() -> ModelAliases {
+        ModelAliases::new(HashMap::from([
+            (
+                "sonnet".to_string(),
+                "anthropic/claude-sonnet-4-5-20250929".to_string(),
+            ),
+            ("fast".to_string(), "groq/llama-3.3-70b".to_string()),
+        ]))
+    }
+
+    #[test]
+    fn routing_names_aliases_are_expanded_everywhere() {
+        let mut routing = RoutingConfig {
+            channel: "sonnet".into(),
+            worker: "fast".into(),
+            task_overrides: HashMap::from([("coding".to_string(), "sonnet".to_string())]),
+            fallbacks: HashMap::from([(
+                "sonnet".to_string(),
+                vec!["fast".to_string(), "openai/gpt-4.1".to_string()],
+            )]),
+            ..RoutingConfig::default()
+        };
+        let branch = routing.branch.clone();
+
+        aliases().apply(&mut routing);
+
+        assert_eq!(routing.channel, "anthropic/claude-sonnet-4-5-20250929");
+        assert_eq!(routing.worker, "groq/llama-3.3-70b");
+        assert_eq!(routing.branch, branch);
+        assert_eq!(
+            routing.task_overrides["coding"],
+            "anthropic/claude-sonnet-4-5-20250929"
+        );
+        assert_eq!(
+            routing.get_fallbacks("anthropic/claude-sonnet-4-5-20250929"),
+            [
+                "groq/llama-3.3-70b".to_string(),
+                "openai/gpt-4.1".to_string()
+            ]
+        );
+        assert_eq!(aliases().resolve("openai/gpt-4.1"), "openai/gpt-4.1");
+    }
+
+    #[test]
+    fn deprecated_models_are_found_with_or_without_the_anthropic_prefix() {
+        assert_eq!(
+            deprecation("claude-3-opus-20240229"),
+            Some("anthropic/claude-opus-4-1")
+        );
+        assert_eq!(
+            deprecation("openai/gpt-4.5-preview"),
+            Some("openai/gpt-4.1")
+        );
+        assert_eq!(deprecation("anthropic/claude-sonnet-4-5-20250929"), None);
+    }
+}
@@ -3,12 +3,13 @@
 use super::auth::{self, AnthropicAuthPath};
 use super::cache;
 use super::tools;
+use crate::llm::reasoning::{self, Reasoning};
 
 use reqwest::RequestBuilder;
 use rig::completion::CompletionRequest;
 
 const ANTHROPIC_API_URL: &str = "https://api.anthropic.com/v1/messages";
-const CLAUDE_CODE_SYSTEM_PREAMBLE: &str =
+pub const CLAUDE_CODE_SYSTEM_PREAMBLE: &str =
     "You are Claude Code, Anthropic's official CLI for Claude.";
 
 /// Result of building an Anthropic request: the configured HTTP request builder,
@@ -34,17 +35,17 @@ fn is_opus(model_id: &str) -> bool {
 
 /// Build a fully configured Anthropic API request from a CompletionRequest.
 ///
-/// `thinking_effort` controls adaptive thinking: "auto" picks max for Opus /
-/// high for others, or pass "max", "high", "medium", "low" explicitly.
+/// `reasoning` controls thinking (see [`apply_thinking`]). With `stream`,
+/// the reply comes back as server-sent events.
 pub fn build_anthropic_request(
     http_client: &reqwest::Client,
     api_key: &str,
     model_name: &str,
     request: &CompletionRequest,
-    thinking_effort: &str,
+    reasoning: &Reasoning,
+    stream: bool,
 ) -> AnthropicRequest {
     let is_oauth = auth::detect_auth_path(api_key) == AnthropicAuthPath::OAuthToken;
-    let adaptive_thinking = supports_adaptive_thinking(model_name);
     let retention = cache::resolve_cache_retention(None);
     let cache_control = cache::get_cache_control(ANTHROPIC_API_URL, retention);
 
@@ -64,19 +65,10 @@ pub fn build_anthropic_request(
         body["temperature"] = serde_json::json!(temperature);
     }
 
-    if adaptive_thinking {
-        body["thinking"] = serde_json::json!({ "type": "adaptive" });
-        let effort = match thinking_effort {
-            "max" | "high" | "medium" | "low" => thinking_effort,
-            _ => {
-                if is_opus(model_name) {
-                    "max"
-                } else {
-                    "high"
-                }
-            }
-        };
-        body["output_config"] = serde_json::json!({ "effort": effort });
+    let extended_thinking = apply_thinking(&mut body, model_name, reasoning);
+
+    if stream {
+        body["stream"] = serde_json::json!(true);
     }
 
     let builder = http_client
@@ -84,7 +76,9 @@ pub fn build_anthropic_request(
         .header("anthropic-version", "2023-06-01")
         .header("content-type", "application/json");
 
-    let (builder, auth_path) = auth::apply_auth_headers(builder, api_key, false);
+    // Thinking between tool calls needs the interleaved thinking beta.
+    let interleaved_thinking = extended_thinking && !request.tools.is_empty();
+    let (builder, auth_path) = auth::apply_auth_headers(builder, api_key, interleaved_thinking);
     let builder = builder.json(&body);
 
     AnthropicRequest {
@@ -94,6 +88,50 @@ pub fn build_anthropic_request(
     }
 }
 
+/// Turn thinking on in `body` as `reasoning` says, returning whether a fixed
+/// budget was set.
+///
+/// 4.6 models think adaptively unless the effort is "off", guided by it:
+/// "auto" picks max for Opus and high for others. Older models with extended
+/// thinking only think when given an effort or budget, within the budget.
+/// Thinking rules out a custom temperature, and `max_tokens` must leave room
+/// for the answer after the budget.
+fn apply_thinking(body: &mut serde_json::Value, model_name: &str, reasoning: &Reasoning) -> bool {
+    if reasoning.is_off() {
+        return false;
+    }
+
+    if supports_adaptive_thinking(model_name) {
+        body["thinking"] = serde_json::json!({ "type": "adaptive" });
+        let effort = match reasoning.effort.as_str() {
+            "max" | "high" | "medium" | "low" => reasoning.effort.as_str(),
+            _ => {
+                if is_opus(model_name) {
+                    "max"
+                } else {
+                    "high"
+                }
+            }
+        };
+        body["output_config"] = serde_json::json!({ "effort": effort });
+        return false;
+    }
+
+    let Some(budget) = reasoning
+        .thinking_budget()
+        .filter(|_| reasoning::supports_extended_thinking(model_name))
+    else {
+        return false;
+    };
+    body["thinking"] = serde_json::json!({ "type": "enabled", "budget_tokens": budget });
+    let max_tokens = body["max_tokens"].as_u64().unwrap_or(0);
+    body["max_tokens"] = serde_json::json!(max_tokens.max(u64::from(budget) + 4_096));
+    if let Some(body) = body.as_object_mut() {
+        body.remove("temperature");
+    }
+    true
+}
+
 fn build_system_prompt(
     body: &mut serde_json::Value,
     request: &CompletionRequest,
@@ -200,4 +238,48 @@ mod tests {
         assert!(!supports_adaptive_thinking("claude-opus-4-0"));
         assert!(!supports_adaptive_thinking("gpt-4o"));
     }
+
+    #[test]
+    fn thinking_follows_the_effort() {
+        let body = || serde_json::json!({"max_tokens": 8_000, "temperature": 0.2});
+        let reasoning = |effort: &str| Reasoning {
+            effort: effort.into(),
+            ..Reasoning::default()
+        };
+
+        let mut adaptive = body();
+        assert!(!apply_thinking(
+            &mut adaptive,
+            "claude-opus-4-6",
+            &reasoning("auto")
+        ));
+        assert_eq!(adaptive["thinking"]["type"], "adaptive");
+        assert_eq!(adaptive["output_config"]["effort"], "max");
+
+        let mut untouched = body();
+        assert!(!apply_thinking(
+            &mut untouched,
+            "claude-sonnet-4-5",
+            &reasoning("auto")
+        ));
+        assert!(!apply_thinking(
+            &mut untouched,
+            "claude-opus-4-6",
+            &reasoning("off")
+        ));
+        assert_eq!(untouched, body());
+
+        let mut budgeted = body();
+        assert!(apply_thinking(
+            &mut budgeted,
+            "claude-sonnet-4-5",
+            &reasoning("medium")
+        ));
+        assert_eq!(
+            budgeted["thinking"],
+            serde_json::json!({"type": "enabled", "budget_tokens": 8_192})
+        );
+        assert_eq!(budgeted["max_tokens"], 12_288);
+        assert!(budgeted.get("temperature").is_none());
+    }
 }
@@ -0,0 +1,732 @@
+//! Per-provider spend caps and budget-aware model selection.
+//!
+//! Every successful completion is priced from its token usage and added to a
+//! daily and monthly ledger per provider. As a provider's spend approaches its
+//! cap, routing shifts traffic to cheaper models; once the cap is reached,
+//! paid calls to that provider are refused outright.
+//!
+//! The ledger is persisted to `budget.json` in the instance directory so caps
+//! survive restarts. Periods roll over on UTC day and month boundaries.
+
+use crate::llm::routing;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Budget configuration (instance-level, under `[llm.budget]`).
+#[derive(Debug, Clone)]
+pub struct BudgetConfig {
+    /// Where to post operator alerts, in "adapter:target" format.
+    pub alert_target: Option<String>,
+    /// Fraction of a cap (0.0–1.0) at which routing starts downgrading.
+    pub downgrade_threshold: f64,
+    /// Spend caps keyed by provider ID.
+    pub providers: HashMap<String, ProviderBudget>,
+    /// Price overrides keyed by full model name (e.g. "openai/gpt-4.1").
+    pub pricing: HashMap<String, ModelPricing>,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            alert_target: None,
+            downgrade_threshold: 0.8,
+            providers: HashMap::new(),
+            pricing: HashMap::new(),
+        }
+    }
+}
+
+impl BudgetConfig {
+    /// Whether any provider has a cap configured.
+    pub fn is_enabled(&self) -> bool {
+        self.providers
+            .values()
+            .any(|budget| budget.daily_limit_usd.is_some() || budget.monthly_limit_usd.is_some())
+    }
+
+    /// Price for a model: config overrides first, then the built-in table.
+    pub fn price_for(&self, model_name: &str) -> Option<ModelPricing> {
+        self.pricing
+            .get(model_name)
+            .copied()
+            .or_else(|| builtin_price(model_name))
+    }
+
+    /// Cheapest model in the pricing overrides that shares a provider with
+    /// `model_name` and costs less than it.
+    pub fn cheaper_model_on_provider(&self, model_name: &str) -> Option<String> {
+        let provider = routing::provider_from_model(model_name);
+        let current = self.price_for(model_name)?.blended();
+        self.pricing
+            .iter()
+            .filter(|(candidate, _)| routing::provider_from_model(candidate) == provider)
+            .filter(|(_, price)| price.blended() < current)
+            .min_by(|(_, left), (_, right)| left.blended().total_cmp(&right.blended()))
+            .map(|(candidate, _)| candidate.clone())
+    }
+
+    /// Providers with a cap but nowhere cheaper to route: no `downgrade_to` and
+    /// no cheaper same-provider model in `pricing`. Downgrades are a no-op for
+    /// these unless a cheaper fallback happens to be in the chain.
+    pub fn providers_without_downgrade_path(&self) -> Vec<String> {
+        let mut providers: Vec<String> = self
+            .providers
+            .iter()
+            .filter(|(_, budget)| budget.downgrade_to.is_empty())
+            .filter(|(provider, _)| {
+                !self
+                    .pricing
+                    .keys()
+                    .any(|model| routing::provider_from_model(model) == provider.as_str())
+            })
+            .map(|(provider, _)| provider.clone())
+            .collect();
+        providers.sort();
+        providers
+    }
+
+    /// Cost in USD of a completion, or zero for unpriced models.
+    /// `reasoning_tokens` are the part of the output tokens spent reasoning.
+    pub fn cost_of(
+        &self,
+        model_name: &str,
+        usage: &rig::completion::Usage,
+        reasoning_tokens: u64,
+    ) -> f64 {
+        self.price_for(model_name)
+            .map(|price| price.cost(usage.input_tokens, usage.output_tokens, reasoning_tokens))
+            .unwrap_or(0.0)
+    }
+}
+
+/// Spend caps for a single provider.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderBudget {
+    pub daily_limit_usd: Option<f64>,
+    pub monthly_limit_usd: Option<f64>,
+    /// Cheaper models to route to once past the downgrade threshold, in order.
+    /// When empty, the cheapest same-provider model in `pricing` is used, and
+    /// the chain is reordered cheapest-first.
+    pub downgrade_to: Vec<String>,
+}
+
+/// Price per million tokens, in USD.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPricing {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+    /// Price of reasoning tokens, when it differs from other output.
+    pub reasoning_per_million: Option<f64>,
+}
+
+impl ModelPricing {
+    /// Cost of a call. Reasoning tokens are counted in `output_tokens`, as
+    /// providers report them.
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64, reasoning_tokens: u64) -> f64 {
+        let reasoning_tokens = reasoning_tokens.min(output_tokens);
+        let reasoning_per_million = self
+            .reasoning_per_million
+            .unwrap_or(self.output_per_million);
+        (input_tokens as f64 * self.input_per_million
+            + (output_tokens - reasoning_tokens) as f64 * self.output_per_million
+            + reasoning_tokens as f64 * reasoning_per_million)
+            / 1_000_000.0
+    }
+
+    /// Single number for ordering models by price. Output tokens dominate
+    /// conversational workloads, so they're weighted the same as list price.
+    fn blended(&self) -> f64 {
+        self.input_per_million + self.output_per_million
+    }
+}
+
+/// List prices for common models, matched by longest prefix of the model name
+/// (the part after the provider, with any OpenRouter vendor segment stripped).
+const BUILTIN_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus-4", 15.0, 75.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-haiku-4.5", 1.0, 5.0),
+    ("claude-haiku-4-5", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("deepseek-chat", 0.27, 1.1),
+];
+
+fn builtin_price(model_name: &str) -> Option<ModelPricing> {
+    let bare_name = model_name.rsplit('/').next().unwrap_or(model_name);
+    BUILTIN_PRICES
+        .iter()
+        .filter(|(prefix, _, _)| bare_name.starts_with(prefix))
+        .max_by_key(|(prefix, _, _)| prefix.len())
+        .map(|(_, input, output)| ModelPricing {
+            input_per_million: *input,
+            output_per_million: *output,
+            reasoning_per_million: None,
+        })
+}
+
+/// How close a provider is to its cap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetStatus {
+    Normal,
+    /// Past the downgrade threshold; prefer cheaper models.
+    Downgrade,
+    /// Cap reached; refuse paid calls.
+    Exhausted,
+}
+
+/// Current spend for one provider.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ProviderSpend {
+    pub daily_usd: f64,
+    pub monthly_usd: f64,
+}
+
+impl ProviderSpend {
+    /// Largest fraction of any configured cap that has been spent.
+    fn utilization(&self, budget: &ProviderBudget) -> f64 {
+        let daily = budget
+            .daily_limit_usd
+            .map(|limit| fraction(self.daily_usd, limit))
+            .unwrap_or(0.0);
+        let monthly = budget
+            .monthly_limit_usd
+            .map(|limit| fraction(self.monthly_usd, limit))
+            .unwrap_or(0.0);
+        daily.max(monthly)
+    }
+
+    pub fn status(&self, budget: &ProviderBudget, downgrade_threshold: f64) -> BudgetStatus {
+        let utilization = self.utilization(budget);
+        if utilization >= 1.0 {
+            BudgetStatus::Exhausted
+        } else if utilization >= downgrade_threshold {
+            BudgetStatus::Downgrade
+        } else {
+            BudgetStatus::Normal
+        }
+    }
+}
+
+fn fraction(spent: f64, limit: f64) -> f64 {
+    if limit <= 0.0 { 1.0 } else { spent / limit }
+}
+
+/// An operator-facing notice that a provider crossed a budget level.
+#[derive(Debug, Clone)]
+pub struct BudgetAlert {
+    pub provider: String,
+    pub status: BudgetStatus,
+    pub spend: ProviderSpend,
+    pub budget: ProviderBudget,
+}
+
+impl std::fmt::Display for BudgetAlert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let headline = match self.status {
+            BudgetStatus::Normal => "back under budget",
+            BudgetStatus::Downgrade => "nearing its spend cap, routing to cheaper models",
+            BudgetStatus::Exhausted => "over budget, paid calls are blocked",
+        };
+        write!(
+            f,
+            "Budget alert: provider `{}` is {headline}.",
+            self.provider
+        )?;
+        if let Some(limit) = self.budget.daily_limit_usd {
+            write!(f, " Today: ${:.2} / ${limit:.2}.", self.spend.daily_usd)?;
+        }
+        if let Some(limit) = self.budget.monthly_limit_usd {
+            write!(
+                f,
+                " This month: ${:.2} / ${limit:.2}.",
+                self.spend.monthly_usd
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SpendLedger {
+    /// UTC day the daily totals belong to (YYYY-MM-DD).
+    day: String,
+    /// UTC month the monthly totals belong to (YYYY-MM).
+    month: String,
+    providers: HashMap<String, ProviderSpend>,
+}
+
+impl SpendLedger {
+    /// Reset totals whose period has ended.
+    fn roll_over(&mut self, now: chrono::DateTime<chrono::Utc>) {
+        let day = now.format("%Y-%m-%d").to_string();
+        let month = now.format("%Y-%m").to_string();
+        if self.month != month {
+            for spend in self.providers.values_mut() {
+                spend.monthly_usd = 0.0;
+            }
+            self.month = month;
+        }
+        if self.day != day {
+            for spend in self.providers.values_mut() {
+                spend.daily_usd = 0.0;
+            }
+            self.day = day;
+        }
+    }
+}
+
+/// Tracks spend per provider and persists it across restarts.
+#[derive(Debug)]
+pub struct SpendTracker {
+    path: Option<PathBuf>,
+    ledger: Arc<Mutex<SpendLedger>>,
+    /// Set while a ledger write is queued, so bursts of calls coalesce into one write.
+    write_pending: Arc<AtomicBool>,
+    /// Serializes ledger writes so an older snapshot can't land after a newer one.
+    write_lock: Arc<Mutex<()>>,
+    /// Highest status already alerted per provider, so each crossing alerts once.
+    alerted: Mutex<HashMap<String, BudgetStatus>>,
+}
+
+impl SpendTracker {
+    /// In-memory tracker (nothing persisted).
+    pub fn in_memory() -> Self {
+        Self::with_ledger(None, SpendLedger::default())
+    }
+
+    /// Load the ledger from `budget.json` in the instance directory.
+    pub fn load(instance_dir: &Path) -> Self {
+        Self::load_file(instance_dir.join("budget.json"))
+    }
+
+    /// Load a ledger from `path`, starting fresh when it's missing or corrupt.
+    pub fn load_file(path: PathBuf) -> Self {
+        let ledger = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                tracing::warn!(%error, path = %path.display(), "corrupt budget ledger, starting fresh");
+                SpendLedger::default()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => SpendLedger::default(),
+            Err(error) => {
+                tracing::warn!(%error, path = %path.display(), "failed to read budget ledger");
+                SpendLedger::default()
+            }
+        };
+
+        Self::with_ledger(Some(path), ledger)
+    }
+
+    fn with_ledger(path: Option<PathBuf>, ledger: SpendLedger) -> Self {
+        Self {
+            path,
+            ledger: Arc::new(Mutex::new(ledger)),
+            write_pending: Arc::new(AtomicBool::new(false)),
+            write_lock: Arc::new(Mutex::new(())),
+            alerted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Current spend for a provider.
+    pub fn spend(&self, provider: &str) -> ProviderSpend {
+        let mut ledger = self.ledger.lock().expect("budget ledger poisoned");
+        ledger.roll_over(chrono::Utc::now());
+        ledger.providers.get(provider).copied().unwrap_or_default()
+    }
+
+    /// Snapshot of spend for every provider seen this period.
+    pub fn snapshot(&self) -> HashMap<String, ProviderSpend> {
+        let mut ledger = self.ledger.lock().expect("budget ledger poisoned");
+        ledger.roll_over(chrono::Utc::now());
+        ledger.providers.clone()
+    }
+
+    /// Add spend for a provider and return the new totals.
+    ///
+    /// Persistence happens on the blocking pool; this never touches the disk
+    /// on the caller's thread when a tokio runtime is available.
+    pub fn record(&self, provider: &str, cost_usd: f64) -> ProviderSpend {
+        let spend = {
+            let mut ledger = self.ledger.lock().expect("budget ledger poisoned");
+            ledger.roll_over(chrono::Utc::now());
+            let entry = ledger.providers.entry(provider.to_string()).or_default();
+            entry.daily_usd += cost_usd;
+            entry.monthly_usd += cost_usd;
+            *entry
+        };
+
+        self.schedule_write();
+        spend
+    }
+
+    /// Raise a provider's totals to `totals` (fleet-wide spend from shared
+    /// state). Totals never go down, so a stale read can't undo local spend.
+    pub fn merge(&self, provider: &str, totals: ProviderSpend) -> ProviderSpend {
+        let spend = {
+            let mut ledger = self.ledger.lock().expect("budget ledger poisoned");
+            ledger.roll_over(chrono::Utc::now());
+            let entry = ledger.providers.entry(provider.to_string()).or_default();
+            entry.daily_usd = entry.daily_usd.max(totals.daily_usd);
+            entry.monthly_usd = entry.monthly_usd.max(totals.monthly_usd);
+            *entry
+        };
+
+        self.schedule_write();
+        spend
+    }
+
+    /// Roll totals over into the current period and drop providers with no
+    /// spend left in it, along with their alert state. Returns how many
+    /// providers were dropped.
+    pub fn prune(&self) -> usize {
+        let (dropped, remaining) = {
+            let mut ledger = self.ledger.lock().expect("budget ledger poisoned");
+            ledger.roll_over(chrono::Utc::now());
+            let before = ledger.providers.len();
+            ledger
+                .providers
+                .retain(|_, spend| spend.daily_usd > 0.0 || spend.monthly_usd > 0.0);
+            let remaining: Vec<String> = ledger.providers.keys().cloned().collect();
+            (before - remaining.len(), remaining)
+        };
+        self.alerted
+            .lock()
+            .expect("budget alert state poisoned")
+            .retain(|provider, _| remaining.contains(provider));
+
+        if dropped > 0 {
+            self.schedule_write();
+        }
+        dropped
+    }
+
+    fn schedule_write(&self) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        // A write is already queued and will pick up this change.
+        if self.write_pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let ledger = self.ledger.clone();
+        let write_pending = self.write_pending.clone();
+        let write_lock = self.write_lock.clone();
+        let write = move || {
+            let _guard = write_lock.lock().expect("budget write lock poisoned");
+            // Clear before serializing: any spend recorded after this point
+            // queues a fresh write that runs once this one releases the lock.
+            write_pending.store(false, Ordering::Release);
+            let serialized = {
+                let ledger = ledger.lock().expect("budget ledger poisoned");
+                serde_json::to_string_pretty(&*ledger)
+            };
+            let result = serialized
+                .map_err(std::io::Error::other)
+                .and_then(|serialized| write_atomically(&path, serialized.as_bytes()));
+            if let Err(error) = result {
+                tracing::warn!(%error, path = %path.display(), "failed to persist budget ledger");
+            }
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(write);
+            }
+            Err(_) => write(),
+        }
+    }
+
+    /// Update the alerted level for a provider. Returns true when a new alert
+    /// should go out: the status rose above what was last alerted, or fell
+    /// back to normal after an alert (a new period, or a raised cap).
+    pub fn should_alert(&self, provider: &str, status: BudgetStatus) -> bool {
+        let mut alerted = self.alerted.lock().expect("budget alert state poisoned");
+        let previous = alerted
+            .get(provider)
+            .copied()
+            .unwrap_or(BudgetStatus::Normal);
+        alerted.insert(provider.to_string(), status);
+        status > previous || (status < previous && status == BudgetStatus::Normal)
+    }
+}
+
+/// Write to a sibling temp file and rename over the target, so a crash
+/// mid-write leaves the previous ledger intact instead of a torn file.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let temp_path = path.with_extension("json.tmp");
+    std::fs::write(&temp_path, contents)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// Apply budget state to a model chain (primary first, then fallbacks).
+///
+/// Models on exhausted providers are dropped. Models on providers past the
+/// downgrade threshold are replaced by that provider's `downgrade_to` list, or,
+/// when none is configured, the chain is reordered cheapest-first. Returns the
+/// exhausted providers when nothing is left to call.
+pub fn plan_chain(
+    chain: &[String],
+    config: &BudgetConfig,
+    status_of: impl Fn(&str) -> BudgetStatus,
+) -> std::result::Result<Vec<String>, Vec<String>> {
+    let mut planned = Vec::with_capacity(chain.len());
+    let mut exhausted = Vec::new();
+    let mut reorder = false;
+
+    let mut push_if_allowed = |model: &str, planned: &mut Vec<String>| {
+        let provider = routing::provider_from_model(model);
+        if status_of(provider) == BudgetStatus::Exhausted {
+            if !exhausted.iter().any(|name| name == provider) {
+                exhausted.push(provider.to_string());
+            }
+        } else if !planned.iter().any(|name| name == model) {
+            planned.push(model.to_string());
+        }
+    };
+
+    for model in chain {
+        let provider = routing::provider_from_model(model);
+        match status_of(provider) {
+            BudgetStatus::Downgrade => {
+                let targets = config
+                    .providers
+                    .get(provider)
+                    .map(|budget| budget.downgrade_to.as_slice())
+                    .unwrap_or(&[]);
+                if targets.is_empty() {
+                    // Nothing explicit to downgrade to: borrow the cheapest
+                    // priced model on the same provider, then let the
+                    // cheapest-first reorder put it ahead.
+                    if let Some(cheaper) = config.cheaper_model_on_provider(model) {
+                        push_if_allowed(&cheaper, &mut planned);
+                    }
+                    reorder = true;
+                    push_if_allowed(model, &mut planned);
+                } else {
+                    for target in targets {
+                        push_if_allowed(target, &mut planned);
+                    }
+                }
+            }
+            _ => push_if_allowed(model, &mut planned),
+        }
+    }
+
+    if reorder {
+        // Stable sort keeps the configured order among equally priced models.
+        // Unpriced models (typically local) sort first — they cost nothing.
+        planned.sort_by(|left, right| {
+            let left_price = config.price_for(left).map(|p| p.blended()).unwrap_or(0.0);
+            let right_price = config.price_for(right).map(|p| p.blended()).unwrap_or(0.0);
+            left_price.total_cmp(&right_price)
+        });
+    }
+
+    if planned.is_empty() {
+        Err(exhausted)
+    } else {
+        Ok(planned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capped_config() -> BudgetConfig {
+        BudgetConfig {
+            providers: HashMap::from([
+                (
+                    "openai".to_string(),
+                    ProviderBudget {
+                        monthly_limit_usd: Some(10.0),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "anthropic".to_string(),
+                    ProviderBudget {
+                        daily_limit_usd: Some(1.0),
+                        downgrade_to: vec!["anthropic/claude-haiku-4.5".into()],
+                        ..Default::default()
+                    },
+                ),
+            ]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn status_uses_the_tightest_cap() {
+        let budget = ProviderBudget {
+            daily_limit_usd: Some(1.0),
+            monthly_limit_usd: Some(100.0),
+            ..Default::default()
+        };
+        let spend = ProviderSpend {
+            daily_usd: 0.9,
+            monthly_usd: 5.0,
+        };
+        assert_eq!(spend.status(&budget, 0.8), BudgetStatus::Downgrade);
+
+        let spend = ProviderSpend {
+            daily_usd: 1.0,
+            monthly_usd: 5.0,
+        };
+        assert_eq!(spend.status(&budget, 0.8), BudgetStatus::Exhausted);
+    }
+
+    #[test]
+    fn builtin_prices_match_longest_prefix() {
+        let config = BudgetConfig::default();
+        let mini = config.price_for("openai/gpt-4.1-mini").unwrap();
+        assert_eq!(mini.input_per_million, 0.4);
+        let full = config.price_for("openrouter/openai/gpt-4.1").unwrap();
+        assert_eq!(full.input_per_million, 2.0);
+        assert!(config.price_for("ollama/llama3").is_none());
+    }
+
+    #[test]
+    fn downgrade_substitutes_configured_models() {
+        let config = capped_config();
+        let chain = vec!["anthropic/claude-sonnet-4".to_string()];
+        let planned = plan_chain(&chain, &config, |provider| match provider {
+            "anthropic" => BudgetStatus::Downgrade,
+            _ => BudgetStatus::Normal,
+        })
+        .unwrap();
+        assert_eq!(planned, vec!["anthropic/claude-haiku-4.5".to_string()]);
+    }
+
+    #[test]
+    fn downgrade_without_targets_reorders_cheapest_first() {
+        let config = capped_config();
+        let chain = vec![
+            "openai/gpt-4.1".to_string(),
+            "openai/gpt-4.1-mini".to_string(),
+        ];
+        let planned = plan_chain(&chain, &config, |_| BudgetStatus::Downgrade).unwrap();
+        assert_eq!(planned[0], "openai/gpt-4.1-mini");
+    }
+
+    #[test]
+    fn downgrade_single_model_uses_cheaper_priced_model() {
+        let mut config = capped_config();
+        config.pricing.insert(
+            "openai/gpt-5-nano".into(),
+            ModelPricing {
+                input_per_million: 0.05,
+                output_per_million: 0.4,
+                reasoning_per_million: None,
+            },
+        );
+        let chain = vec!["openai/gpt-4.1".to_string()];
+        let planned = plan_chain(&chain, &config, |_| BudgetStatus::Downgrade).unwrap();
+        assert_eq!(
+            planned,
+            vec![
+                "openai/gpt-5-nano".to_string(),
+                "openai/gpt-4.1".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn downgrade_single_model_without_cheaper_option_is_unchanged() {
+        let config = capped_config();
+        let chain = vec!["openai/gpt-4.1".to_string()];
+        let planned = plan_chain(&chain, &config, |_| BudgetStatus::Downgrade).unwrap();
+        assert_eq!(planned, chain);
+        assert_eq!(config.providers_without_downgrade_path(), vec!["openai"]);
+    }
+
+    #[test]
+    fn exhausted_providers_are_skipped_or_refused() {
+        let config = capped_config();
+        let chain = vec!["openai/gpt-4.1".to_string(), "ollama/llama3".to_string()];
+        let planned = plan_chain(&chain, &config, |provider| match provider {
+            "openai" => BudgetStatus::Exhausted,
+            _ => BudgetStatus::Normal,
+        })
+        .unwrap();
+        assert_eq!(planned, vec!["ollama/llama3".to_string()]);
+
+        let chain = vec!["openai/gpt-4.1".to_string()];
+        let error = plan_chain(&chain, &config, |_| BudgetStatus::Exhausted).unwrap_err();
+        assert_eq!(error, vec!["openai".to_string()]);
+    }
+
+    #[test]
+    fn alerts_fire_once_per_escalation_and_recovery() {
+        let tracker = SpendTracker::in_memory();
+        assert!(!tracker.should_alert("openai", BudgetStatus::Normal));
+        assert!(tracker.should_alert("openai", BudgetStatus::Downgrade));
+        assert!(!tracker.should_alert("openai", BudgetStatus::Downgrade));
+        assert!(tracker.should_alert("openai", BudgetStatus::Exhausted));
+        // Back under budget is worth telling the operator once.
+        assert!(tracker.should_alert("openai", BudgetStatus::Normal));
+        assert!(!tracker.should_alert("openai", BudgetStatus::Normal));
+        assert!(tracker.should_alert("openai", BudgetStatus::Exhausted));
+        // A raised cap that's still past the threshold isn't a recovery.
+        assert!(!tracker.should_alert("openai", BudgetStatus::Downgrade));
+        assert!(tracker.should_alert("openai", BudgetStatus::Exhausted));
+    }
+
+    #[test]
+    fn merged_fleet_totals_never_lower_local_spend() {
+        let tracker = SpendTracker::in_memory();
+        tracker.record("openai", 2.0);
+        let merged = tracker.merge(
+            "openai",
+            ProviderSpend {
+                daily_usd: 1.0,
+                monthly_usd: 9.0,
+            },
+        );
+        assert_eq!(merged.daily_usd, 2.0);
+        assert_eq!(merged.monthly_usd, 9.0);
+        assert_eq!(tracker.spend("openai").monthly_usd, 9.0);
+    }
+
+    #[test]
+    fn pruning_drops_providers_with_nothing_spent_this_period() {
+        let tracker = SpendTracker::with_ledger(
+            None,
+            SpendLedger {
+                day: "2026-01-31".into(),
+                month: "2026-01".into(),
+                providers: HashMap::from([(
+                    "openai".to_string(),
+                    ProviderSpend {
+                        daily_usd: 1.0,
+                        monthly_usd: 5.0,
+                    },
+                )]),
+            },
+        );
+        tracker.should_alert("openai", BudgetStatus::Exhausted);
+        tracker.record("anthropic", 0.5);
+
+        assert_eq!(tracker.prune(), 1);
+        assert_eq!(
+            tracker.snapshot().into_keys().collect::<Vec<_>>(),
+            vec!["anthropic".to_string()]
+        );
+        // A fresh crossing alerts again.
+        assert!(tracker.should_alert("openai", BudgetStatus::Downgrade));
+        assert_eq!(tracker.prune(), 0);
+    }
+}
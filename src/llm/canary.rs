@@ -0,0 +1,374 @@
+//! Canary rollouts of a model or prompt change.
+//!
+//! A routing config's `canary` sends a percentage of conversations to a new
+//! channel model, a new version of the prompt overrides, or both. Each
+//! conversation is assigned by a stable hash of its channel ID, so it stays on
+//! the same side for its whole life, and raising the percentage only moves
+//! control conversations into the canary, never back. Turns, errors, and cost
+//! are tallied per side, so `!admin canary` can compare the canary against the
+//! control, and `!admin canary rollback` sends every conversation back to the
+//! control.
+
+use anyhow::Context as _;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+/// A canary on a routing config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanaryConfig {
+    /// Share of conversations in the canary, 0-100.
+    pub percent: u8,
+    /// Channel model for canary conversations.
+    pub model: Option<String>,
+    /// Prompt version for canary conversations: overrides in
+    /// `prompts/canary/<version>/`, layered over `prompts/`.
+    pub prompts: Option<String>,
+}
+
+impl CanaryConfig {
+    /// Which side `conversation` is on.
+    pub fn cohort(&self, conversation: &str) -> Cohort {
+        if bucket(conversation) < u64::from(self.percent) {
+            Cohort::Canary
+        } else {
+            Cohort::Control
+        }
+    }
+
+    /// What the canary changes, e.g. "model openai/gpt-5, prompts v2".
+    pub fn variant(&self) -> String {
+        let changes: Vec<String> = [
+            self.model.as_ref().map(|model| format!("model {model}")),
+            self.prompts
+                .as_ref()
+                .map(|version| format!("prompts {version}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        changes.join(", ")
+    }
+}
+
+/// The side of a canary a conversation is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cohort {
+    Control,
+    Canary,
+}
+
+impl Cohort {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Control => "control",
+            Self::Canary => "canary",
+        }
+    }
+}
+
+/// 0-99 from FNV-1a, which unlike `DefaultHasher` is the same in every
+/// build, so conversations keep their side across restarts.
+fn bucket(conversation: &str) -> u64 {
+    let hash = conversation
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    hash % 100
+}
+
+/// One side's totals.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CohortStats {
+    pub conversations: usize,
+    pub turns: u64,
+    pub errors: u64,
+    pub cost_usd: f64,
+}
+
+impl CohortStats {
+    pub fn error_rate(&self) -> f64 {
+        if self.turns == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.turns as f64
+        }
+    }
+
+    pub fn cost_per_turn(&self) -> f64 {
+        if self.turns == 0 {
+            0.0
+        } else {
+            self.cost_usd / self.turns as f64
+        }
+    }
+}
+
+/// Both sides of one agent's canary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub variant: String,
+    pub control: CohortStats,
+    pub canary: CohortStats,
+}
+
+#[derive(Default)]
+struct Tally {
+    conversations: HashSet<String>,
+    turns: u64,
+    errors: u64,
+    cost_usd: f64,
+}
+
+impl Tally {
+    fn stats(&self) -> CohortStats {
+        CohortStats {
+            conversations: self.conversations.len(),
+            turns: self.turns,
+            errors: self.errors,
+            cost_usd: self.cost_usd,
+        }
+    }
+}
+
+#[derive(Default)]
+struct AgentTally {
+    variant: String,
+    control: Tally,
+    canary: Tally,
+}
+
+/// Canary and control totals per agent, since the canary last changed.
+#[derive(Default)]
+pub struct CanaryStats {
+    agents: Mutex<HashMap<String, AgentTally>>,
+}
+
+impl CanaryStats {
+    /// Count a channel turn. A new `variant` starts the agent's totals over,
+    /// so the comparison always covers the canary that's running.
+    pub fn record(
+        &self,
+        agent_id: &str,
+        variant: &str,
+        cohort: Cohort,
+        conversation: &str,
+        failed: bool,
+        cost_usd: f64,
+    ) {
+        let mut agents = self.agents.lock().expect("canary stats lock poisoned");
+        let agent = agents.entry(agent_id.to_string()).or_default();
+        if agent.variant != variant {
+            *agent = AgentTally {
+                variant: variant.to_string(),
+                ..AgentTally::default()
+            };
+        }
+        let tally = match cohort {
+            Cohort::Control => &mut agent.control,
+            Cohort::Canary => &mut agent.canary,
+        };
+        if !tally.conversations.contains(conversation) {
+            tally.conversations.insert(conversation.to_string());
+        }
+        tally.turns += 1;
+        tally.errors += u64::from(failed);
+        tally.cost_usd += cost_usd;
+    }
+
+    pub fn comparison(&self, agent_id: &str) -> Option<Comparison> {
+        let agents = self.agents.lock().expect("canary stats lock poisoned");
+        agents.get(agent_id).map(|agent| Comparison {
+            variant: agent.variant.clone(),
+            control: agent.control.stats(),
+            canary: agent.canary.stats(),
+        })
+    }
+}
+
+/// The `!admin canary` reply.
+pub fn render(canary: Option<&CanaryConfig>, comparison: Option<&Comparison>) -> String {
+    let Some(canary) = canary.filter(|canary| canary.percent > 0) else {
+        return "No canary is running.".to_string();
+    };
+    let mut lines = vec![format!(
+        "Canary: {} for {}% of conversations.",
+        canary.variant(),
+        canary.percent
+    )];
+    match comparison.filter(|comparison| comparison.variant == canary.variant()) {
+        None => lines.push("No turns yet.".to_string()),
+        Some(comparison) => {
+            for (cohort, stats) in [
+                (Cohort::Control, &comparison.control),
+                (Cohort::Canary, &comparison.canary),
+            ] {
+                lines.push(format!(
+                    "- {}: {} conversations, {} turns, {:.1}% errors, ${:.4}/turn",
+                    cohort.as_str(),
+                    stats.conversations,
+                    stats.turns,
+                    stats.error_rate() * 100.0,
+                    stats.cost_per_turn()
+                ));
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+/// Set `percent = 0` on the canary in `content` (a config.toml) that
+/// applies to `agent_id`: its own `routing.canary`, or the defaults' when it
+/// has none. Returns the edited config and the table changed, or `None`
+/// when no canary applies.
+pub fn rollback_toml(content: &str, agent_id: &str) -> anyhow::Result<Option<(String, String)>> {
+    let mut doc: toml_edit::DocumentMut = content.parse().context("can't parse config.toml")?;
+
+    let agent_canary = doc
+        .get_mut("agents")
+        .and_then(|agents| agents.as_array_of_tables_mut())
+        .and_then(|agents| {
+            agents
+                .iter_mut()
+                .find(|agent| agent.get("id").and_then(|id| id.as_str()) == Some(agent_id))
+        })
+        .and_then(|agent| agent.get_mut("routing"))
+        .and_then(|routing| routing.get_mut("canary"))
+        .and_then(|canary| canary.as_table_like_mut());
+    if let Some(canary) = agent_canary {
+        canary.insert("percent", toml_edit::value(0));
+        let table = format!("agents.{agent_id}.routing.canary");
+        return Ok(Some((doc.to_string(), table)));
+    }
+
+    let defaults_canary = doc
+        .get_mut("defaults")
+        .and_then(|defaults| defaults.get_mut("routing"))
+        .and_then(|routing| routing.get_mut("canary"))
+        .and_then(|canary| canary.as_table_like_mut());
+    if let Some(canary) = defaults_canary {
+        canary.insert("percent", toml_edit::value(0));
+        let table = "defaults.routing.canary".to_string();
+        return Ok(Some((doc.to_string(), table)));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canary(percent: u8) -> CanaryConfig {
+        CanaryConfig {
+            percent,
+            model: Some("openai/gpt-5".into()),
+            prompts: Some("v2".into()),
+        }
+    }
+
+    #[test]
+    fn conversations_keep_their_side_as_the_rollout_grows() {
+        let conversations: Vec<String> =
+            (0..1000).map(|index| format!("discord:{index}")).collect();
+        let in_canary = |percent| {
+            conversations
+                .iter()
+                .filter(|conversation| canary(percent).cohort(conversation) == Cohort::Canary)
+                .cloned()
+                .collect::<HashSet<_>>()
+        };
+
+        let (ten, fifty) = (in_canary(10), in_canary(50));
+        assert!((50..150).contains(&ten.len()), "{} in canary", ten.len());
+        assert!(ten.is_subset(&fifty));
+        assert!(in_canary(0).is_empty());
+        assert_eq!(in_canary(100).len(), conversations.len());
+    }
+
+    #[test]
+    fn stats_start_over_when_the_canary_changes() {
+        let stats = CanaryStats::default();
+        stats.record("main", "model a", Cohort::Canary, "c1", true, 0.02);
+        stats.record("main", "model a", Cohort::Canary, "c1", false, 0.02);
+        stats.record("main", "model a", Cohort::Control, "c2", false, 0.01);
+
+        let comparison = stats.comparison("main").expect("comparison");
+        assert_eq!(comparison.canary.conversations, 1);
+        assert_eq!(comparison.canary.turns, 2);
+        assert_eq!(comparison.canary.error_rate(), 0.5);
+        assert_eq!(comparison.control.turns, 1);
+
+        stats.record("main", "model b", Cohort::Control, "c2", false, 0.01);
+        let comparison = stats.comparison("main").expect("comparison");
+        assert_eq!(comparison.variant, "model b");
+        assert_eq!(comparison.canary, CohortStats::default());
+        assert_eq!(comparison.control.turns, 1);
+    }
+
+    #[test]
+    fn render_compares_both_sides() {
+        let comparison = Comparison {
+            variant: "model openai/gpt-5, prompts v2".into(),
+            control: CohortStats {
+                conversations: 9,
+                turns: 40,
+                errors: 1,
+                cost_usd: 0.4,
+            },
+            canary: CohortStats {
+                conversations: 1,
+                turns: 4,
+                errors: 1,
+                cost_usd: 0.06,
+            },
+        };
+
+        assert_eq!(
+            render(Some(&canary(10)), Some(&comparison)),
+            "Canary: model openai/gpt-5, prompts v2 for 10% of conversations.\n\
+             - control: 9 conversations, 40 turns, 2.5% errors, $0.0100/turn\n\
+             - canary: 1 conversations, 4 turns, 25.0% errors, $0.0150/turn"
+        );
+        assert_eq!(render(Some(&canary(0)), None), "No canary is running.");
+    }
+
+    #[test]
+    fn rollback_zeroes_the_agents_own_canary_before_the_defaults() {
+        let content = r#"
+[defaults.routing.canary]
+percent = 20
+model = "openai/gpt-5"
+
+[[agents]]
+id = "main"
+
+[[agents]]
+id = "ops"
+
+[agents.routing.canary]
+percent = 50
+prompts = "v2"
+"#;
+
+        let (edited, table) = rollback_toml(content, "ops")
+            .expect("valid toml")
+            .expect("canary found");
+        assert_eq!(table, "agents.ops.routing.canary");
+        assert!(edited.contains("percent = 0\nprompts = \"v2\""));
+        assert!(edited.contains("percent = 20"));
+
+        let (edited, table) = rollback_toml(content, "main")
+            .expect("valid toml")
+            .expect("canary found");
+        assert_eq!(table, "defaults.routing.canary");
+        assert!(edited.contains("percent = 0\nmodel = \"openai/gpt-5\""));
+
+        assert!(
+            rollback_toml("[[agents]]\nid = \"main\"\n", "main")
+                .expect("valid toml")
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Fault injection for provider calls.
+//!
+//! When `[llm.chaos]` is enabled, each completion attempt may fail with a
+//! synthetic rate limit, timeout, malformed JSON body, or response body cut
+//! off mid-read, before the request reaches the provider. The errors carry the
+//! same text the real HTTP paths produce, so retry, fallback, and rate-limit
+//! cooldown handle them exactly as they would in production. Off by default;
+//! meant for staging and tests.
+
+use rig::completion::CompletionError;
+
+/// Fault injection settings (instance-level, under `[llm.chaos]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosConfig {
+    pub enabled: bool,
+    /// Probability (0.0–1.0) per attempt of a 429 rate limit.
+    pub rate_limit_rate: f64,
+    /// Probability per attempt of a request timeout.
+    pub timeout_rate: f64,
+    /// Probability per attempt of a 200 response with an unparseable body.
+    pub malformed_json_rate: f64,
+    /// Probability per attempt of the connection closing mid-body.
+    pub truncated_body_rate: f64,
+    /// Providers to inject into. Empty means all.
+    pub providers: Vec<String>,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            rate_limit_rate: 0.0,
+            timeout_rate: 0.0,
+            malformed_json_rate: 0.0,
+            truncated_body_rate: 0.0,
+            providers: Vec::new(),
+        }
+    }
+}
+
+/// A synthetic provider failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    RateLimit,
+    Timeout,
+    MalformedJson,
+    TruncatedBody,
+}
+
+impl ChaosConfig {
+    /// The fault to inject for a call to `provider`, given a uniform `roll` in `[0, 1)`.
+    ///
+    /// Rates are stacked in a fixed order, so their sum is the overall
+    /// failure probability.
+    pub fn pick(&self, provider: &str, roll: f64) -> Option<Fault> {
+        if !self.enabled {
+            return None;
+        }
+        if !self.providers.is_empty() && !self.providers.iter().any(|p| p == provider) {
+            return None;
+        }
+
+        let faults = [
+            (Fault::RateLimit, self.rate_limit_rate),
+            (Fault::Timeout, self.timeout_rate),
+            (Fault::MalformedJson, self.malformed_json_rate),
+            (Fault::TruncatedBody, self.truncated_body_rate),
+        ];
+        let mut threshold = 0.0;
+        for (fault, rate) in faults {
+            threshold += rate;
+            if roll < threshold {
+                return Some(fault);
+            }
+        }
+        None
+    }
+
+    /// Roll for a fault on a call to `provider`.
+    pub fn roll(&self, provider: &str) -> Option<Fault> {
+        if !self.enabled {
+            return None;
+        }
+        self.pick(provider, rand::random::<f64>())
+    }
+}
+
+impl Fault {
+    /// The error a real call would have surfaced for this failure.
+    pub fn into_error(self, provider: &str) -> CompletionError {
+        let message = match self {
+            Self::RateLimit => {
+                format!("{provider} API error (429 Too Many Requests): rate limit exceeded [chaos]")
+            }
+            Self::Timeout => {
+                format!("error sending request to {provider}: operation timeout [chaos]")
+            }
+            Self::MalformedJson => format!(
+                "{provider} response (200 OK) is not valid JSON: expected value at line 1 column 1 [chaos]"
+            ),
+            Self::TruncatedBody => {
+                "failed to read response body: connection closed before message completed [chaos]"
+                    .to_string()
+            }
+        };
+        CompletionError::ProviderError(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::routing;
+
+    #[test]
+    fn rates_stack_in_order() {
+        let chaos = ChaosConfig {
+            enabled: true,
+            rate_limit_rate: 0.1,
+            timeout_rate: 0.1,
+            malformed_json_rate: 0.1,
+            truncated_body_rate: 0.1,
+            providers: vec!["openai".into()],
+        };
+        assert_eq!(chaos.pick("openai", 0.05), Some(Fault::RateLimit));
+        assert_eq!(chaos.pick("openai", 0.15), Some(Fault::Timeout));
+        assert_eq!(chaos.pick("openai", 0.25), Some(Fault::MalformedJson));
+        assert_eq!(chaos.pick("openai", 0.35), Some(Fault::TruncatedBody));
+        assert_eq!(chaos.pick("openai", 0.5), None);
+        assert_eq!(chaos.pick("anthropic", 0.05), None);
+
+        let disabled = ChaosConfig {
+            enabled: false,
+            ..chaos
+        };
+        assert_eq!(disabled.pick("openai", 0.05), None);
+    }
+
+    #[test]
+    fn injected_errors_take_the_real_retry_paths() {
+        let retriable =
+            |fault: Fault| routing::is_retriable_error(&fault.into_error("openai").to_string());
+        assert!(retriable(Fault::RateLimit));
+        assert!(retriable(Fault::Timeout));
+        assert!(retriable(Fault::TruncatedBody));
+        assert!(!retriable(Fault::MalformedJson));
+        assert!(routing::is_rate_limit_error(
+            &Fault::RateLimit.into_error("openai").to_string()
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+//! Token logprob capture and a derived confidence score.
+//!
+//! When enabled on the routing config, OpenAI-compatible providers that
+//! support it are asked for token logprobs. The score is the geometric mean
+//! token probability of the reply text, in `[0, 1]`. Low scores either send
+//! the request to a stronger "second opinion" model or re-run it with an
+//! instruction to hedge rather than state guesses as fact.
+
+use serde::Serialize;
+
+/// Confidence handling for a routing config.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfidenceConfig {
+    /// Request logprobs from providers that support them.
+    pub enabled: bool,
+    /// Below this score the reply is regenerated with a hedging instruction.
+    pub hedge_below: f64,
+    /// Below this score the request goes to `second_opinion_model`, if set.
+    pub second_opinion_below: f64,
+    /// Stronger model to consult for low-confidence replies.
+    pub second_opinion_model: Option<String>,
+}
+
+impl Default for ConfidenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hedge_below: 0.6,
+            second_opinion_below: 0.4,
+            second_opinion_model: None,
+        }
+    }
+}
+
+/// What to do with a scored reply.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum ConfidenceAction {
+    Accept,
+    Hedge,
+    SecondOpinion(String),
+}
+
+impl ConfidenceConfig {
+    /// Decide how to handle a reply from `model_name` with `score`.
+    pub fn action_for(&self, score: f64, model_name: &str) -> ConfidenceAction {
+        if let Some(second_opinion_model) = &self.second_opinion_model
+            && score < self.second_opinion_below
+            && second_opinion_model != model_name
+        {
+            return ConfidenceAction::SecondOpinion(second_opinion_model.clone());
+        }
+        if score < self.hedge_below {
+            return ConfidenceAction::Hedge;
+        }
+        ConfidenceAction::Accept
+    }
+}
+
+/// Providers whose chat completions API returns token logprobs.
+pub fn supports_logprobs(provider: &str) -> bool {
+    matches!(
+        provider,
+        "openai" | "openrouter" | "together" | "fireworks" | "deepseek"
+    )
+}
+
+/// Ask an OpenAI-style chat completions body for token logprobs.
+pub fn request_logprobs(body: &mut serde_json::Value) {
+    body["logprobs"] = serde_json::json!(true);
+}
+
+/// Geometric mean token probability of the first choice's content.
+///
+/// `None` when the response carries no content logprobs (tool-call-only
+/// replies, or providers that ignored the request).
+pub fn score_from_body(body: &serde_json::Value) -> Option<f64> {
+    let tokens = body["choices"][0]["logprobs"]["content"].as_array()?;
+    let logprobs: Vec<f64> = tokens
+        .iter()
+        .filter_map(|token| token["logprob"].as_f64())
+        .collect();
+    if logprobs.is_empty() {
+        return None;
+    }
+    let mean = logprobs.iter().sum::<f64>() / logprobs.len() as f64;
+    Some(mean.exp().clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_is_geometric_mean_probability() {
+        let body = serde_json::json!({
+            "choices": [{ "logprobs": { "content": [
+                { "token": "Paris", "logprob": 0.0 },
+                { "token": ".", "logprob": (0.25f64).ln() },
+            ]}}]
+        });
+        let score = score_from_body(&body).expect("score");
+        assert!((score - 0.5).abs() < 1e-9);
+
+        assert_eq!(
+            score_from_body(&serde_json::json!({ "choices": [{}] })),
+            None
+        );
+    }
+
+    #[test]
+    fn low_scores_escalate_or_hedge() {
+        let config = ConfidenceConfig {
+            enabled: true,
+            second_opinion_model: Some("anthropic/claude-opus-4".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.action_for(0.2, "openai/gpt-4o-mini"),
+            ConfidenceAction::SecondOpinion("anthropic/claude-opus-4".into())
+        );
+        assert_eq!(
+            config.action_for(0.5, "openai/gpt-4o-mini"),
+            ConfidenceAction::Hedge
+        );
+        assert_eq!(
+            config.action_for(0.2, "anthropic/claude-opus-4"),
+            ConfidenceAction::Hedge
+        );
+        assert_eq!(
+            config.action_for(0.9, "openai/gpt-4o-mini"),
+            ConfidenceAction::Accept
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! Escalating rate limit cooldowns.
+//!
+//! A rate-limited model is skipped for a cooldown that doubles with each
+//! rate limit in a row, from `rate_limit_cooldown_secs` up to
+//! `rate_limit_cooldown_max_secs`. A `Retry-After` from the provider is used
+//! instead of the doubled value. The count starts over once the model has
+//! served requests for `rate_limit_reset_secs` after its last cooldown, or
+//! sat unused that long.
+//!
+//! Providers often limit a whole API key rather than one model. When a
+//! second model of a provider is rate limited while another is still cooling
+//! down, the provider itself cools down, escalating the same way, and all of
+//! its models are skipped until it's done.
+
+use crate::llm::routing::{self, RoutingConfig};
+
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+
+use std::time::{Duration, Instant};
+
+/// How cooldowns grow and when they reset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CooldownPolicy {
+    /// The first cooldown.
+    pub base: Duration,
+    /// The longest cooldown, also the cap on a `Retry-After`.
+    pub max: Duration,
+    /// Time without a rate limit after which the count starts over.
+    pub reset_after: Duration,
+}
+
+impl CooldownPolicy {
+    pub fn from_routing(routing: &RoutingConfig) -> Self {
+        Self {
+            base: Duration::from_secs(routing.rate_limit_cooldown_secs),
+            max: Duration::from_secs(
+                routing
+                    .rate_limit_cooldown_max_secs
+                    .max(routing.rate_limit_cooldown_secs),
+            ),
+            reset_after: Duration::from_secs(routing.rate_limit_reset_secs),
+        }
+    }
+
+    /// Cooldown after the `strikes`th rate limit in a row.
+    pub fn cooldown(&self, strikes: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max);
+        }
+        let factor = 2u32.saturating_pow(strikes.saturating_sub(1));
+        self.base.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Cooldown state of one model or provider.
+#[derive(Debug, Clone, Copy)]
+struct Cooldown {
+    until: Instant,
+    /// Rate limits in a row.
+    strikes: u32,
+    /// First successful request after the cooldown ended.
+    recovered_at: Option<Instant>,
+    /// `reset_after` of the policy that set it.
+    reset_after: Duration,
+}
+
+impl Cooldown {
+    /// Whether the count should start over.
+    fn is_stale(&self, now: Instant) -> bool {
+        let quiet_since = self.recovered_at.unwrap_or(self.until);
+        now >= self.until && now.saturating_duration_since(quiet_since) >= self.reset_after
+    }
+
+    /// The state after another rate limit at `now`.
+    fn escalate(
+        previous: Option<&Self>,
+        now: Instant,
+        policy: &CooldownPolicy,
+        retry_after: Option<Duration>,
+    ) -> Self {
+        let strikes = previous
+            .filter(|previous| !previous.is_stale(now))
+            .map_or(1, |previous| previous.strikes + 1);
+        Self {
+            until: now + policy.cooldown(strikes, retry_after),
+            strikes,
+            recovered_at: None,
+            reset_after: policy.reset_after,
+        }
+    }
+}
+
+/// A cooldown still running, as listed by `!admin ratelimits`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveCooldown {
+    /// The model, or the provider when `provider_wide`.
+    pub name: String,
+    pub provider_wide: bool,
+    pub remaining: Duration,
+    /// Rate limits in a row behind it.
+    pub strikes: u32,
+}
+
+/// Cooldowns per model and per provider.
+///
+/// Every completion checks `remaining` for its model, so the maps are
+/// sharded: lookups only contend with writes to the same shard, and no lock
+/// is held across the whole table.
+#[derive(Debug, Default)]
+pub struct Cooldowns {
+    models: DashMap<String, Cooldown>,
+    providers: DashMap<String, Cooldown>,
+}
+
+impl Cooldowns {
+    /// Record a rate limit on `model_name`. Returns the model's cooldown,
+    /// and the provider's when the provider cools down too.
+    pub fn strike(
+        &self,
+        model_name: &str,
+        now: Instant,
+        policy: &CooldownPolicy,
+        retry_after: Option<Duration>,
+    ) -> (Duration, Option<Duration>) {
+        self.prune(now);
+
+        let provider = routing::provider_from_model(model_name);
+        let key_wide = self.models.iter().any(|entry| {
+            entry.key() != model_name
+                && routing::provider_from_model(entry.key()) == provider
+                && entry.until > now
+        });
+
+        let model = escalate_entry(&self.models, model_name, now, policy, retry_after);
+        let provider_cooldown = key_wide.then(|| {
+            escalate_entry(&self.providers, provider, now, policy, retry_after).until - now
+        });
+        (model.until - now, provider_cooldown)
+    }
+
+    /// Time before `model_name` may be used again: the longer of its own
+    /// cooldown and its provider's.
+    pub fn remaining(&self, model_name: &str, now: Instant) -> Option<Duration> {
+        let provider = routing::provider_from_model(model_name);
+        [
+            self.models.get(model_name).map(|cooldown| cooldown.until),
+            self.providers.get(provider).map(|cooldown| cooldown.until),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|until| until.saturating_duration_since(now))
+        .filter(|remaining| !remaining.is_zero())
+        .max()
+    }
+
+    /// Whether `model_name` or its provider has any cooldown state.
+    pub fn tracks(&self, model_name: &str) -> bool {
+        self.models.contains_key(model_name)
+            || self
+                .providers
+                .contains_key(routing::provider_from_model(model_name))
+    }
+
+    /// Note that `model_name` served a request. Enough of these after a
+    /// cooldown starts the count over.
+    pub fn succeed(&self, model_name: &str, now: Instant) {
+        let provider = routing::provider_from_model(model_name);
+        for (map, key) in [(&self.models, model_name), (&self.providers, provider)] {
+            if let Some(mut cooldown) = map.get_mut(key)
+                && now >= cooldown.until
+            {
+                cooldown.recovered_at.get_or_insert(now);
+            }
+            map.remove_if(key, |_, cooldown| cooldown.is_stale(now));
+        }
+    }
+
+    /// Take on a cooldown another replica set, unless ours runs longer. It's
+    /// dropped as soon as it ends, since its count is the other replica's.
+    pub fn adopt(&self, name: &str, provider_wide: bool, until: Instant) {
+        let map = if provider_wide {
+            &self.providers
+        } else {
+            &self.models
+        };
+        let mut cooldown = map.entry(name.to_string()).or_insert(Cooldown {
+            until,
+            strikes: 1,
+            recovered_at: None,
+            reset_after: Duration::ZERO,
+        });
+        if cooldown.until < until {
+            cooldown.until = until;
+            cooldown.recovered_at = None;
+        }
+    }
+
+    /// Cooldowns still running at `now`, longest first.
+    pub fn snapshot(&self, now: Instant) -> Vec<ActiveCooldown> {
+        let mut active: Vec<ActiveCooldown> = [(&self.models, false), (&self.providers, true)]
+            .into_iter()
+            .flat_map(|(map, provider_wide)| {
+                map.iter().filter_map(move |entry| {
+                    let remaining = entry.until.saturating_duration_since(now);
+                    (!remaining.is_zero()).then(|| ActiveCooldown {
+                        name: entry.key().clone(),
+                        provider_wide,
+                        remaining,
+                        strikes: entry.strikes,
+                    })
+                })
+            })
+            .collect();
+        active.sort_by(|a, b| {
+            b.remaining
+                .cmp(&a.remaining)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        active
+    }
+
+    /// Drop state whose count would start over anyway. Returns how many
+    /// entries were dropped.
+    pub fn prune(&self, now: Instant) -> usize {
+        let mut dropped = 0;
+        for map in [&self.models, &self.providers] {
+            map.retain(|_, cooldown| {
+                let stale = cooldown.is_stale(now);
+                dropped += usize::from(stale);
+                !stale
+            });
+        }
+        dropped
+    }
+}
+
+/// Escalate `key`'s cooldown in place, under its shard's lock so two rate
+/// limits landing at once both count.
+fn escalate_entry(
+    map: &DashMap<String, Cooldown>,
+    key: &str,
+    now: Instant,
+    policy: &CooldownPolicy,
+    retry_after: Option<Duration>,
+) -> Cooldown {
+    match map.entry(key.to_string()) {
+        Entry::Occupied(mut entry) => {
+            let cooldown = Cooldown::escalate(Some(entry.get()), now, policy, retry_after);
+            entry.insert(cooldown);
+            cooldown
+        }
+        Entry::Vacant(entry) => *entry.insert(Cooldown::escalate(None, now, policy, retry_after)),
+    }
+}
+
+/// The `!admin ratelimits` reply.
+pub fn render(cooldowns: &[ActiveCooldown]) -> String {
+    if cooldowns.is_empty() {
+        return "No models are in rate limit cooldown.".into();
+    }
+    let mut text = "Rate limit cooldowns:".to_string();
+    for cooldown in cooldowns {
+        let subject = if cooldown.provider_wide {
+            format!("every `{}` model", cooldown.name)
+        } else {
+            format!("`{}`", cooldown.name)
+        };
+        let streak = match cooldown.strikes {
+            1 => "rate limited once".to_string(),
+            strikes => format!("rate limited {strikes} times in a row"),
+        };
+        text.push_str(&format!(
+            "\n- {subject}: {} left, {streak}",
+            remaining_text(cooldown.remaining)
+        ));
+    }
+    text
+}
+
+/// "45s", "2m 05s", or "1h 02m", rounded up to the second.
+pub fn remaining_text(remaining: Duration) -> String {
+    let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+    if secs < 60 {
+        format!("{secs}s")
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: CooldownPolicy = CooldownPolicy {
+        base: Duration::from_secs(60),
+        max: Duration::from_secs(300),
+        reset_after: Duration::from_secs(600),
+    };
+
+    #[test]
+    fn cooldowns_double_to_the_cap_and_reset_after_sustained_success() {
+        let cooldowns = Cooldowns::default();
+        let start = Instant::now();
+        let model = "openai/gpt-4.1";
+
+        let mut now = start;
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            let (cooldown, provider) = cooldowns.strike(model, now, &POLICY, None);
+            assert_eq!(provider, None);
+            seen.push(cooldown.as_secs());
+            now += cooldown;
+        }
+        assert_eq!(seen, vec![60, 120, 240, 300]);
+
+        // A Retry-After wins over the doubled value, but not over the cap.
+        let (cooldown, _) = cooldowns.strike(model, now, &POLICY, Some(Duration::from_secs(7)));
+        assert_eq!(cooldown, Duration::from_secs(7));
+        assert_eq!(
+            cooldowns.remaining(model, now + Duration::from_secs(5)),
+            Some(Duration::from_secs(2))
+        );
+        let (cooldown, _) = cooldowns.strike(model, now, &POLICY, Some(Duration::from_secs(3600)));
+        assert_eq!(cooldown, POLICY.max);
+
+        // Succeeding for the reset window starts the count over.
+        now += POLICY.max;
+        cooldowns.succeed(model, now);
+        assert!(cooldowns.tracks(model));
+        cooldowns.succeed(model, now + POLICY.reset_after);
+        assert!(!cooldowns.tracks(model));
+        let (cooldown, _) = cooldowns.strike(model, now + POLICY.reset_after, &POLICY, None);
+        assert_eq!(cooldown, POLICY.base);
+    }
+
+    #[test]
+    fn a_second_model_limited_at_once_cools_the_provider() {
+        let cooldowns = Cooldowns::default();
+        let now = Instant::now();
+        cooldowns.strike("anthropic/claude-sonnet-4", now, &POLICY, None);
+        assert_eq!(cooldowns.remaining("anthropic/claude-haiku-4.5", now), None);
+
+        let (_, provider) = cooldowns.strike("anthropic/claude-haiku-4.5", now, &POLICY, None);
+        assert_eq!(provider, Some(POLICY.base));
+        assert_eq!(
+            cooldowns.remaining("anthropic/claude-opus-4", now),
+            Some(POLICY.base)
+        );
+        assert_eq!(cooldowns.remaining("openai/gpt-4.1", now), None);
+
+        let later = now + Duration::from_millis(15_500);
+        assert_eq!(
+            render(&cooldowns.snapshot(later)),
+            "Rate limit cooldowns:\n\
+             - every `anthropic` model: 45s left, rate limited once\n\
+             - `anthropic/claude-haiku-4.5`: 45s left, rate limited once\n\
+             - `anthropic/claude-sonnet-4`: 45s left, rate limited once"
+        );
+        assert_eq!(
+            render(&cooldowns.snapshot(now + POLICY.base)),
+            "No models are in rate limit cooldown."
+        );
+    }
+
+    #[test]
+    fn pruning_keeps_counts_until_their_reset_window_passes() {
+        let cooldowns = Cooldowns::default();
+        let now = Instant::now();
+        cooldowns.strike("openai/gpt-4.1", now, &POLICY, None);
+        cooldowns.adopt("openai/o3", false, now + POLICY.base);
+
+        // Another replica's cooldown goes as soon as it ends; ours stays
+        // until it could no longer escalate.
+        assert_eq!(cooldowns.prune(now + POLICY.base), 1);
+        assert!(!cooldowns.tracks("openai/o3"));
+        assert!(cooldowns.tracks("openai/gpt-4.1"));
+        assert_eq!(cooldowns.prune(now + POLICY.base + POLICY.reset_after), 1);
+        assert!(!cooldowns.tracks("openai/gpt-4.1"));
+    }
+}
@@ -0,0 +1,328 @@
+//! Model list discovery and validation.
+//!
+//! At startup and on every config reload, each configured provider is asked
+//! which models it serves. Configured models a provider doesn't list are
+//! logged as warnings, so a typo in `routing.channel` shows up at boot rather
+//! than as an error on the first message. Providers that can't be reached are
+//! recorded with their error and skipped by validation, since an outage says
+//! nothing about whether a model exists.
+
+use crate::config::{ApiType, Config, ProviderConfig};
+use crate::llm::manager::LlmManager;
+use crate::llm::routing::RoutingConfig;
+
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+/// Provider model names shown per provider by `!admin models`.
+const RENDERED_MODELS_PER_PROVIDER: usize = 10;
+
+/// What one provider said it serves.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderModels {
+    /// Model IDs as the provider names them (no routing prefix). Empty when
+    /// the list couldn't be fetched.
+    pub models: Vec<String>,
+    /// Why the list couldn't be fetched.
+    pub error: Option<String>,
+    pub discovered_at: DateTime<Utc>,
+}
+
+/// The result of the latest discovery.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Discovery {
+    pub providers: BTreeMap<String, ProviderModels>,
+    /// Configured models ("provider/model") their provider doesn't list.
+    pub missing: Vec<String>,
+}
+
+/// The latest discovery, swapped in whole when a new one finishes.
+#[derive(Default)]
+pub struct ModelCatalog {
+    discovery: ArcSwap<Discovery>,
+}
+
+impl ModelCatalog {
+    pub fn snapshot(&self) -> Arc<Discovery> {
+        self.discovery.load_full()
+    }
+}
+
+/// Every model the config routes to: each agent's process models, task
+/// overrides, and fallbacks, plus the Ollama models to warm.
+pub fn configured_models(config: &Config) -> BTreeSet<String> {
+    let mut models = BTreeSet::new();
+    let agents = config.resolve_agents();
+    let routings =
+        std::iter::once(&config.defaults.routing).chain(agents.iter().map(|agent| &agent.routing));
+    for routing in routings {
+        models.extend(routing_models(routing).map(str::to_string));
+    }
+    models.extend(config.llm.ollama.warm_models.iter().map(|model| {
+        let model = model.strip_prefix("ollama/").unwrap_or(model);
+        format!("ollama/{model}")
+    }));
+    models
+}
+
+/// The models `routing` names, in the order process models, task overrides,
+/// then fallbacks.
+pub(crate) fn routing_models(routing: &RoutingConfig) -> impl Iterator<Item = &str> {
+    [
+        &routing.channel,
+        &routing.branch,
+        &routing.worker,
+        &routing.compactor,
+        &routing.cortex,
+    ]
+    .into_iter()
+    .chain(routing.task_overrides.values())
+    .chain(
+        routing
+            .fallbacks
+            .iter()
+            .flat_map(|(model, fallbacks)| std::iter::once(model).chain(fallbacks)),
+    )
+    .map(String::as_str)
+    .filter(|model| !model.is_empty())
+}
+
+/// Fetch every configured provider's model list, check `configured` against
+/// them, and replace the manager's catalog. Returns the missing models.
+pub async fn discover(manager: &LlmManager, configured: &BTreeSet<String>) -> Vec<String> {
+    let fetches = manager
+        .providers()
+        .into_iter()
+        .map(|(provider_id, provider)| async move {
+            let http_client = manager.http_client_for(&provider_id);
+            let (models, error) = match list_models(http_client, &provider_id, &provider).await {
+                Ok(models) => (models, None),
+                Err(error) => {
+                    let error = crate::logging::redact_secrets(&error.to_string());
+                    tracing::debug!(provider = %provider_id, %error, "can't list provider models");
+                    (Vec::new(), Some(error))
+                }
+            };
+            let models = ProviderModels {
+                models,
+                error,
+                discovered_at: Utc::now(),
+            };
+            (provider_id, models)
+        });
+    let providers: BTreeMap<_, _> = futures::future::join_all(fetches)
+        .await
+        .into_iter()
+        .collect();
+
+    let missing = missing_models(&providers, configured);
+    manager.model_catalog().discovery.store(Arc::new(Discovery {
+        providers,
+        missing: missing.clone(),
+    }));
+    missing
+}
+
+/// Discover in the background, warning about each configured model its
+/// provider doesn't list.
+pub fn spawn_discovery(
+    llm_manager: &Arc<LlmManager>,
+    configured: BTreeSet<String>,
+) -> tokio::task::JoinHandle<()> {
+    let llm_manager = Arc::downgrade(llm_manager);
+    tokio::spawn(async move {
+        let Some(manager) = llm_manager.upgrade() else {
+            return;
+        };
+        for model in discover(&manager, &configured).await {
+            tracing::warn!(%model, "configured model isn't in its provider's model list");
+        }
+    })
+}
+
+pub(crate) async fn list_models(
+    http_client: &reqwest::Client,
+    provider_id: &str,
+    provider: &ProviderConfig,
+) -> anyhow::Result<Vec<String>> {
+    let base_url = provider.base_url.trim_end_matches('/');
+    let request = match provider.api_type {
+        ApiType::Anthropic => http_client
+            .get(format!("{base_url}/v1/models?limit=1000"))
+            .header("x-api-key", &provider.api_key)
+            .header("anthropic-version", "2023-06-01"),
+        // Z.AI's base URL already includes the API version.
+        _ if provider_id == "zhipu" || provider_id == "zai-coding-plan" => http_client
+            .get(format!("{base_url}/models"))
+            .bearer_auth(&provider.api_key),
+        _ => return crate::llm::openai_compatible::list_models(http_client, provider).await,
+    };
+
+    let body: serde_json::Value = request.send().await?.error_for_status()?.json().await?;
+    Ok(crate::llm::openai_compatible::parse_model_ids(&body))
+}
+
+/// Configured models whose provider's list was fetched and doesn't include
+/// them. Models without a provider prefix are Anthropic's.
+fn missing_models(
+    providers: &BTreeMap<String, ProviderModels>,
+    configured: &BTreeSet<String>,
+) -> Vec<String> {
+    configured
+        .iter()
+        .filter(|model_name| {
+            let (provider, model) = model_name
+                .split_once('/')
+                .unwrap_or(("anthropic", model_name.as_str()));
+            providers
+                .get(provider)
+                .filter(|listed| listed.error.is_none())
+                .is_some_and(|listed| {
+                    !listed
+                        .models
+                        .iter()
+                        .any(|candidate| same_model(provider, candidate, model))
+                })
+        })
+        .cloned()
+        .collect()
+}
+
+/// Ollama names models with an implicit ":latest" tag.
+fn same_model(provider: &str, listed: &str, configured: &str) -> bool {
+    if provider == "ollama" {
+        crate::llm::ollama::normalize_model_name(listed)
+            == crate::llm::ollama::normalize_model_name(configured)
+    } else {
+        listed == configured
+    }
+}
+
+/// Discovered models per provider, for the `!admin models` command.
+pub fn render(discovery: &Discovery) -> String {
+    if discovery.providers.is_empty() {
+        return "No provider model lists discovered yet.".to_string();
+    }
+
+    let mut lines = vec!["Discovered models:".to_string()];
+    for (provider, listed) in &discovery.providers {
+        if let Some(error) = &listed.error {
+            lines.push(format!("- `{provider}`: can't list models ({error})"));
+            continue;
+        }
+        let shown = listed
+            .models
+            .iter()
+            .take(RENDERED_MODELS_PER_PROVIDER)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let more = listed
+            .models
+            .len()
+            .saturating_sub(RENDERED_MODELS_PER_PROVIDER);
+        let more = if more > 0 {
+            format!(" and {more} more")
+        } else {
+            String::new()
+        };
+        lines.push(format!(
+            "- `{provider}` ({}): {shown}{more}",
+            listed.models.len()
+        ));
+    }
+    if !discovery.missing.is_empty() {
+        lines.push(format!(
+            "Configured but not listed by their provider: {}",
+            discovery.missing.join(", ")
+        ));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(models: &[&str]) -> ProviderModels {
+        ProviderModels {
+            models: models.iter().map(|model| model.to_string()).collect(),
+            error: None,
+            discovered_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn missing_models_skip_providers_that_could_not_be_listed() {
+        let providers = BTreeMap::from([
+            ("anthropic".to_string(), listed(&["claude-sonnet-4"])),
+            (
+                "openrouter".to_string(),
+                listed(&["anthropic/claude-sonnet-4"]),
+            ),
+            ("ollama".to_string(), listed(&["llama3.1:latest"])),
+            (
+                "vllm".to_string(),
+                ProviderModels {
+                    error: Some("connection refused".to_string()),
+                    ..listed(&[])
+                },
+            ),
+        ]);
+        let configured: BTreeSet<String> = [
+            "claude-sonnet-4",
+            "anthropic/claude-sonnet-5-typo",
+            "openrouter/anthropic/claude-sonnet-4",
+            "ollama/llama3.1",
+            "ollama/qwen2.5",
+            "vllm/meta-llama/Llama-3.1-8B-Instruct",
+            "groq/llama-3.3-70b",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+        assert_eq!(
+            missing_models(&providers, &configured),
+            vec![
+                "anthropic/claude-sonnet-5-typo".to_string(),
+                "ollama/qwen2.5".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn render_truncates_long_lists_and_names_missing_models() {
+        let many: Vec<String> = (0..12).map(|index| format!("model-{index:02}")).collect();
+        let many: Vec<&str> = many.iter().map(String::as_str).collect();
+        let discovery = Discovery {
+            providers: BTreeMap::from([
+                ("openrouter".to_string(), listed(&many)),
+                (
+                    "vllm".to_string(),
+                    ProviderModels {
+                        error: Some("connection refused".to_string()),
+                        ..listed(&[])
+                    },
+                ),
+            ]),
+            missing: vec!["openrouter/model-99".to_string()],
+        };
+
+        assert_eq!(
+            render(&discovery),
+            "Discovered models:\n\
+             - `openrouter` (12): model-00, model-01, model-02, model-03, model-04, model-05, \
+             model-06, model-07, model-08, model-09 and 2 more\n\
+             - `vllm`: can't list models (connection refused)\n\
+             Configured but not listed by their provider: openrouter/model-99"
+        );
+        assert_eq!(
+            render(&Discovery::default()),
+            "No provider model lists discovered yet."
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! HTTP connection pools for provider calls.
+//!
+//! Every provider shares one `reqwest` client unless `[llm.http.providers]`
+//! gives it settings of its own, in which case it also gets its own pool, so
+//! a burst of streams to one provider can't hold up connections to another.
+//! Idle connections are kept around between bursts, HTTP/2 connections are
+//! pinged so they stay open while idle, and Nagle's algorithm is off so small
+//! stream frames go out immediately.
+//!
+//! Clients follow the `HTTP_PROXY`, `HTTPS_PROXY`, and `NO_PROXY` environment
+//! variables unless a proxy is configured, globally or for one provider. So a
+//! deployment can reach OpenAI through a corporate proxy and Ollama directly.
+//!
+//! Extra CA certificates are trusted on top of the system roots, for
+//! self-hosted servers (vLLM, LiteLLM) behind an internal CA. As a last
+//! resort, one provider's client can skip certificate verification entirely.
+//!
+//! Request and response bodies are capped, for provider calls and for tool
+//! fetches alike. [`send`] refuses an oversized request before it goes out,
+//! and [`read_body`] stops reading a response as soon as it passes the cap,
+//! dropping the connection, so one huge reply can't balloon memory. Responses
+//! are asked for gzip or brotli and decompressed as they're read; the cap
+//! counts decompressed bytes.
+
+use anyhow::Context as _;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// An outbound proxy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyConfig {
+    /// `http://`, `https://`, `socks5://`, or `socks5h://` (the proxy
+    /// resolves host names) URL.
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Hosts, domains (`.corp.example`), and IP ranges (`10.0.0.0/8`) reached
+    /// without the proxy.
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    fn build(&self) -> anyhow::Result<reqwest::Proxy> {
+        let mut proxy = reqwest::Proxy::all(&self.url).context("invalid proxy URL")?;
+        if let Some(username) = &self.username {
+            proxy = proxy.basic_auth(username, self.password.as_deref().unwrap_or_default());
+        }
+        if !self.no_proxy.is_empty() {
+            proxy = proxy.no_proxy(reqwest::NoProxy::from_string(&self.no_proxy.join(",")));
+        }
+        Ok(proxy)
+    }
+}
+
+/// Where a client's connections go.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ProxySetting {
+    /// Through the proxy in `HTTP_PROXY`/`HTTPS_PROXY`, if set.
+    #[default]
+    Environment,
+    /// Straight to the provider, ignoring the environment.
+    Direct,
+    /// Through this proxy.
+    Proxy(ProxyConfig),
+}
+
+/// Largest bodies sent and read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyLimits {
+    pub max_request_bytes: usize,
+    pub max_response_bytes: usize,
+}
+
+impl BodyLimits {
+    /// Provider calls: requests can carry images, replies are mostly text.
+    pub const PROVIDER: Self = Self {
+        max_request_bytes: 32 * 1024 * 1024,
+        max_response_bytes: 8 * 1024 * 1024,
+    };
+
+    /// Tool fetches: configured HTTP APIs and web search.
+    pub const FETCH: Self = Self {
+        max_request_bytes: 1024 * 1024,
+        max_response_bytes: 4 * 1024 * 1024,
+    };
+}
+
+/// A body over its [`BodyLimits`], or the request failing.
+#[derive(Debug, thiserror::Error)]
+pub enum BodyError {
+    #[error("request body is {size} bytes, over the {limit}-byte limit")]
+    RequestTooLarge { size: usize, limit: usize },
+    #[error("response body is over the {limit}-byte limit")]
+    ResponseTooLarge { limit: usize },
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+}
+
+/// Send `request` unless its body is over `limits.max_request_bytes`.
+pub async fn send(
+    request: reqwest::RequestBuilder,
+    limits: BodyLimits,
+) -> Result<reqwest::Response, BodyError> {
+    let (client, request) = request.build_split();
+    let request = request?;
+    let size = request
+        .body()
+        .and_then(reqwest::Body::as_bytes)
+        .map_or(0, <[u8]>::len);
+    if size > limits.max_request_bytes {
+        return Err(BodyError::RequestTooLarge {
+            size,
+            limit: limits.max_request_bytes,
+        });
+    }
+    Ok(client.execute(request).await?)
+}
+
+/// Read `response`'s body, giving up as soon as it passes
+/// `limits.max_response_bytes`. A declared length over the limit fails before
+/// anything is read.
+pub async fn read_body(
+    mut response: reqwest::Response,
+    limits: BodyLimits,
+) -> Result<Vec<u8>, BodyError> {
+    let limit = limits.max_response_bytes;
+    if response
+        .content_length()
+        .is_some_and(|length| length > limit as u64)
+    {
+        return Err(BodyError::ResponseTooLarge { limit });
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(BodyError::ResponseTooLarge { limit });
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// [`read_body`] as text, replacing invalid UTF-8.
+pub async fn read_text(
+    response: reqwest::Response,
+    limits: BodyLimits,
+) -> Result<String, BodyError> {
+    let body = read_body(response, limits).await?;
+    Ok(String::from_utf8(body)
+        .unwrap_or_else(|error| String::from_utf8_lossy(error.as_bytes()).into_owned()))
+}
+
+/// Pool and transport settings for one HTTP client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpPoolConfig {
+    /// Timeout for a whole request, including reading the body.
+    pub timeout_secs: u64,
+    /// Idle connections kept open per host.
+    pub pool_max_idle_per_host: usize,
+    /// How long an idle connection is kept. 0 keeps it until the server
+    /// closes it.
+    pub pool_idle_timeout_secs: u64,
+    /// Send small writes immediately instead of batching them.
+    pub tcp_nodelay: bool,
+    /// Interval between HTTP/2 keepalive pings. 0 turns pings off.
+    pub http2_keep_alive_interval_secs: u64,
+    /// How long a ping may go unanswered before the connection is dropped.
+    pub http2_keep_alive_timeout_secs: u64,
+    pub proxy: ProxySetting,
+    /// PEM files of CA certificates trusted on top of the system roots.
+    pub ca_certs: Vec<PathBuf>,
+    /// Accept any server certificate. Only allowed for a single provider.
+    pub danger_accept_invalid_certs: bool,
+    /// Ask for gzip or brotli responses.
+    pub compression: bool,
+    pub limits: BodyLimits,
+}
+
+impl Default for HttpPoolConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 120,
+            pool_max_idle_per_host: 32,
+            pool_idle_timeout_secs: 90,
+            tcp_nodelay: true,
+            http2_keep_alive_interval_secs: 30,
+            http2_keep_alive_timeout_secs: 10,
+            proxy: ProxySetting::Environment,
+            ca_certs: Vec::new(),
+            danger_accept_invalid_certs: false,
+            compression: true,
+            limits: BodyLimits::PROVIDER,
+        }
+    }
+}
+
+impl HttpPoolConfig {
+    pub fn build_client(&self) -> anyhow::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(
+                (self.pool_idle_timeout_secs > 0)
+                    .then(|| Duration::from_secs(self.pool_idle_timeout_secs)),
+            )
+            .tcp_nodelay(self.tcp_nodelay)
+            .gzip(self.compression)
+            .brotli(self.compression);
+        if self.http2_keep_alive_interval_secs > 0 {
+            builder = builder
+                .http2_keep_alive_interval(Duration::from_secs(self.http2_keep_alive_interval_secs))
+                .http2_keep_alive_timeout(Duration::from_secs(self.http2_keep_alive_timeout_secs))
+                .http2_keep_alive_while_idle(true);
+        }
+        builder = match &self.proxy {
+            ProxySetting::Environment => builder,
+            ProxySetting::Direct => builder.no_proxy(),
+            ProxySetting::Proxy(proxy) => builder.proxy(proxy.build()?),
+        };
+        for path in &self.ca_certs {
+            let pem = std::fs::read(path)
+                .with_context(|| format!("failed to read CA certificate {}", path.display()))?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("invalid CA certificate {}", path.display()))?;
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        if self.danger_accept_invalid_certs {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        builder.build().context("failed to build HTTP client")
+    }
+}
+
+/// HTTP client settings (instance-level, under `[llm.http]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpConfig {
+    /// Settings of the shared client.
+    pub pool: HttpPoolConfig,
+    /// Providers with a pool of their own, and its settings.
+    pub providers: HashMap<String, HttpPoolConfig>,
+    /// Body limits for tool fetches, under `[llm.http.fetch]`.
+    pub fetch: BodyLimits,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            pool: HttpPoolConfig::default(),
+            providers: HashMap::new(),
+            fetch: BodyLimits::FETCH,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Body limits for calls to `provider`.
+    pub fn limits_for(&self, provider: &str) -> BodyLimits {
+        self.providers.get(provider).unwrap_or(&self.pool).limits
+    }
+}
+
+/// The shared client, and one for each provider with its own settings.
+#[derive(Debug, Clone)]
+pub struct HttpClients {
+    shared: reqwest::Client,
+    providers: HashMap<String, reqwest::Client>,
+}
+
+impl HttpClients {
+    pub fn build(config: &HttpConfig) -> anyhow::Result<Self> {
+        let providers = config
+            .providers
+            .iter()
+            .map(|(provider, pool)| {
+                if pool.danger_accept_invalid_certs {
+                    tracing::warn!(
+                        %provider,
+                        "TLS certificate verification is OFF for this provider: anyone on the \
+                         network path can read and alter its traffic, API key included"
+                    );
+                }
+                let client = pool
+                    .build_client()
+                    .with_context(|| format!("failed to build HTTP client for {provider}"))?;
+                Ok((provider.clone(), client))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            shared: config.pool.build_client()?,
+            providers,
+        })
+    }
+
+    /// The client every provider without its own pool uses.
+    pub fn shared(&self) -> &reqwest::Client {
+        &self.shared
+    }
+
+    /// The client for calls to `provider`.
+    pub fn for_provider(&self, provider: &str) -> &reqwest::Client {
+        self.providers.get(provider).unwrap_or(&self.shared)
+    }
+}
@@ -9,43 +9,104 @@
 //! `get_api_key()` calls read the new values lock-free.
 
 use crate::auth::OAuthCredentials;
-use crate::config::{LlmConfig, ProviderConfig};
+use crate::config::{ApiType, LlmConfig, ProviderConfig};
 use crate::error::{LlmError, Result};
+use crate::llm::budget::{self, BudgetAlert, BudgetStatus, SpendTracker};
+use crate::llm::canary::CanaryStats;
+use crate::llm::chaos::Fault;
+use crate::llm::cooldown::{ActiveCooldown, CooldownPolicy, Cooldowns};
+use crate::llm::discovery::ModelCatalog;
+use crate::llm::http::HttpClients;
+use crate::llm::ollama::{OllamaConfig, OllamaModelStates};
+use crate::llm::resources::ResourceMonitor;
+use crate::llm::shared::SharedLimits;
+use crate::llm::slo::{SloAlert, SloTracker};
+use crate::tenants::TenantConfig;
+use crate::tenants::billing::{self, UsageLedger, UsageLine};
 
-use anyhow::Context as _;
 use arc_swap::ArcSwap;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Instant;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, broadcast};
+
+/// Default seconds between sweeps of ended cooldowns and stale spend state.
+pub const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 60;
 
 /// Manages LLM provider clients and tracks rate limit state.
 pub struct LlmManager {
-    config: ArcSwap<LlmConfig>,
-    http_client: reqwest::Client,
-    /// Models currently in rate limit cooldown, with the time they were limited.
-    rate_limited: Arc<RwLock<HashMap<String, Instant>>>,
+    config: Arc<ArcSwap<LlmConfig>>,
+    /// Shared and per-provider HTTP clients, built from `[llm.http]`.
+    http: HttpClients,
+    /// Escalating rate limit cooldowns per model and provider.
+    cooldowns: Arc<Cooldowns>,
     /// Instance directory for reading/writing OAuth credentials.
     instance_dir: Option<PathBuf>,
     /// Cached OAuth credentials (refreshed lazily).
     oauth_credentials: RwLock<Option<OAuthCredentials>>,
+    /// Daily and monthly spend per provider, checked against budget caps.
+    spend: Arc<SpendTracker>,
+    /// Daily and monthly spend per tenant, across every provider.
+    tenant_spend: Arc<SpendTracker>,
+    /// Per-day usage by tenant and model, for invoicing.
+    tenant_usage: Arc<UsageLedger>,
+    /// Cooldowns and spend shared with other replicas, when configured.
+    shared: Option<Arc<SharedLimits>>,
+    /// Fan-out for operator alerts when a provider crosses a budget level.
+    budget_alert_tx: broadcast::Sender<BudgetAlert>,
+    /// Recent latencies of routes with an SLO.
+    slo: SloTracker,
+    /// Fan-out for operator alerts when a route starts or stops burning its
+    /// latency error budget.
+    slo_alert_tx: broadcast::Sender<SloAlert>,
+    /// Pull/load progress for local Ollama models, shared with the warm-up task.
+    ollama_states: OllamaModelStates,
+    /// Latest GPU and host resource sample, used to gate local model loads.
+    resources: ResourceMonitor,
+    /// Models each provider listed at the last discovery.
+    model_catalog: ModelCatalog,
+    /// Canary and control turn totals per agent.
+    canary_stats: CanaryStats,
+    /// Sweeps ended cooldowns and stale spend state every
+    /// `cleanup_interval_secs`. Aborted on shutdown or drop.
+    cleanup_task: tokio::task::JoinHandle<()>,
 }
 
 impl LlmManager {
     /// Create a new LLM manager with the given configuration.
     pub async fn new(config: LlmConfig) -> Result<Self> {
-        let http_client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(120))
-            .build()
-            .with_context(|| "failed to build HTTP client")?;
+        let http = HttpClients::build(&config.http)?;
+
+        warn_budget_gaps(&config);
+        warn_deprecated_aliases(&config);
+        let shared = SharedLimits::from_config(&config.shared_state)?.map(Arc::new);
+        let config = Arc::new(ArcSwap::from_pointee(config));
+        let sweep = Sweep {
+            cooldowns: Arc::new(Cooldowns::default()),
+            spend: Arc::new(SpendTracker::in_memory()),
+            tenant_spend: Arc::new(SpendTracker::in_memory()),
+            shared,
+        };
 
         Ok(Self {
-            config: ArcSwap::from_pointee(config),
-            http_client,
-            rate_limited: Arc::new(RwLock::new(HashMap::new())),
+            config: config.clone(),
+            http,
+            cooldowns: sweep.cooldowns.clone(),
             instance_dir: None,
             oauth_credentials: RwLock::new(None),
+            spend: sweep.spend.clone(),
+            tenant_spend: sweep.tenant_spend.clone(),
+            tenant_usage: Arc::new(UsageLedger::in_memory()),
+            shared: sweep.shared.clone(),
+            budget_alert_tx: broadcast::channel(16).0,
+            slo: SloTracker::default(),
+            slo_alert_tx: broadcast::channel(16).0,
+            ollama_states: OllamaModelStates::default(),
+            model_catalog: ModelCatalog::default(),
+            canary_stats: CanaryStats::default(),
+            resources: ResourceMonitor::default(),
+            cleanup_task: spawn_cleanup(config, sweep),
         })
     }
 
@@ -61,10 +122,7 @@ impl LlmManager {
 
     /// Initialize with an instance directory (for use at construction time).
     pub async fn with_instance_dir(config: LlmConfig, instance_dir: PathBuf) -> Result<Self> {
-        let http_client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(120))
-            .build()
-            .with_context(|| "failed to build HTTP client")?;
+        let http = HttpClients::build(&config.http)?;
 
         let oauth_credentials = match crate::auth::load_credentials(&instance_dir) {
             Ok(Some(creds)) => {
@@ -78,19 +136,54 @@ impl LlmManager {
             }
         };
 
+        warn_budget_gaps(&config);
+        warn_deprecated_aliases(&config);
+        let shared = SharedLimits::from_config(&config.shared_state)?.map(Arc::new);
+        let config = Arc::new(ArcSwap::from_pointee(config));
+        let sweep = Sweep {
+            cooldowns: Arc::new(Cooldowns::default()),
+            spend: Arc::new(SpendTracker::load(&instance_dir)),
+            tenant_spend: Arc::new(SpendTracker::load_file(
+                instance_dir.join("tenant_budget.json"),
+            )),
+            shared,
+        };
+
         Ok(Self {
-            config: ArcSwap::from_pointee(config),
-            http_client,
-            rate_limited: Arc::new(RwLock::new(HashMap::new())),
+            config: config.clone(),
+            http,
+            cooldowns: sweep.cooldowns.clone(),
+            spend: sweep.spend.clone(),
+            tenant_spend: sweep.tenant_spend.clone(),
+            tenant_usage: Arc::new(UsageLedger::load_file(
+                instance_dir.join("tenant_usage.json"),
+            )),
+            shared: sweep.shared.clone(),
             instance_dir: Some(instance_dir),
             oauth_credentials: RwLock::new(oauth_credentials),
+            budget_alert_tx: broadcast::channel(16).0,
+            slo: SloTracker::default(),
+            slo_alert_tx: broadcast::channel(16).0,
+            ollama_states: OllamaModelStates::default(),
+            model_catalog: ModelCatalog::default(),
+            canary_stats: CanaryStats::default(),
+            resources: ResourceMonitor::default(),
+            cleanup_task: spawn_cleanup(config, sweep),
         })
     }
 
     /// Atomically swap in new provider credentials.
     pub fn reload_config(&self, config: LlmConfig) {
+        warn_budget_gaps(&config);
+        warn_deprecated_aliases(&config);
         self.config.store(Arc::new(config));
         tracing::info!("LLM provider keys reloaded");
+
+        // New caps can move a provider across a level with no new spend.
+        let config = self.config.load();
+        for (provider, spend) in self.spend.snapshot() {
+            self.check_budget(&config.budget, &provider, spend);
+        }
     }
 
     pub fn get_provider(&self, provider_id: &str) -> Result<ProviderConfig> {
@@ -154,14 +247,186 @@ impl LlmManager {
         Ok(provider.api_key)
     }
 
+    /// The tenant an agent serves, if any.
+    pub fn tenant(&self, agent_id: &str) -> Option<Arc<TenantConfig>> {
+        self.config.load().tenants.get(agent_id).cloned()
+    }
+
+    /// Every configured tenant.
+    pub fn tenants(&self) -> Vec<Arc<TenantConfig>> {
+        let mut tenants: Vec<_> = self.config.load().tenants.values().cloned().collect();
+        tenants.sort_by(|left, right| left.id.cmp(&right.id));
+        tenants
+    }
+
+    /// The key an agent's calls to a provider use when its tenant has its own.
+    pub fn tenant_api_key(&self, agent_id: &str, provider_id: &str) -> Option<String> {
+        self.config
+            .load()
+            .tenants
+            .get(agent_id)?
+            .api_keys
+            .get(&provider_id.to_lowercase())
+            .cloned()
+    }
+
+    /// Current spend for a tenant this day and month.
+    pub fn tenant_spend(&self, tenant_id: &str) -> budget::ProviderSpend {
+        self.tenant_spend.spend(tenant_id)
+    }
+
+    /// The tenant whose spend cap an agent's calls are over, if any.
+    pub fn tenant_over_budget(&self, agent_id: &str) -> Option<String> {
+        let tenant = self.tenant(agent_id)?;
+        let exhausted = tenant.has_budget()
+            && self
+                .tenant_spend
+                .spend(&tenant.id)
+                .status(&tenant.budget(), 1.0)
+                == BudgetStatus::Exhausted;
+        exhausted.then(|| tenant.id.clone())
+    }
+
+    /// Usage per tenant and model from `from` to `to` (UTC days, inclusive),
+    /// priced with the `[billing]` markups.
+    pub fn tenant_usage(
+        &self,
+        from: chrono::NaiveDate,
+        to: chrono::NaiveDate,
+        tenant_id: Option<&str>,
+    ) -> Vec<UsageLine> {
+        self.tenant_usage
+            .report(from, to, tenant_id, &self.config.load().billing)
+    }
+
+    /// Add a completion's usage and cost to the agent's tenant, if it has one.
+    pub fn record_tenant_spend(
+        &self,
+        agent_id: &str,
+        model_name: &str,
+        usage: &rig::completion::Usage,
+        reasoning_tokens: u64,
+    ) {
+        let Some(tenant) = self.tenant(agent_id) else {
+            return;
+        };
+        let cost = self.cost_of(model_name, usage, reasoning_tokens);
+        self.tenant_usage.record(
+            chrono::Utc::now().date_naive(),
+            &tenant.id,
+            model_name,
+            billing::Usage {
+                calls: 1,
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                reasoning_tokens,
+                cost_usd: cost,
+            },
+        );
+        if cost <= 0.0 {
+            return;
+        }
+        let spend = self.tenant_spend.record(&tenant.id, cost);
+        if !tenant.has_budget() {
+            return;
+        }
+        let status = spend.status(&tenant.budget(), 1.0);
+        if self.tenant_spend.should_alert(&tenant.id, status) && status == BudgetStatus::Exhausted {
+            tracing::warn!(
+                tenant = %tenant.id,
+                daily_usd = spend.daily_usd,
+                monthly_usd = spend.monthly_usd,
+                "tenant reached its spend cap, LLM calls are blocked"
+            );
+        }
+    }
+
+    /// Every configured provider, by ID.
+    pub fn providers(&self) -> Vec<(String, ProviderConfig)> {
+        let mut providers: Vec<_> = self
+            .config
+            .load()
+            .providers
+            .iter()
+            .map(|(id, provider)| (id.clone(), provider.clone()))
+            .collect();
+        providers.sort_by(|a, b| a.0.cmp(&b.0));
+        providers
+    }
+
+    /// Providers configured as self-hosted `openai_compatible` servers, by ID.
+    pub fn openai_compatible_providers(&self) -> Vec<(String, ProviderConfig)> {
+        self.providers()
+            .into_iter()
+            .filter(|(_, provider)| provider.api_type == ApiType::OpenAiCompatible)
+            .collect()
+    }
+
+    /// Models each provider listed at the last discovery, and configured
+    /// models none of them did.
+    pub fn model_catalog(&self) -> &ModelCatalog {
+        &self.model_catalog
+    }
+
+    /// Canary and control turn totals per agent, since each canary started.
+    pub fn canary_stats(&self) -> &CanaryStats {
+        &self.canary_stats
+    }
+
     /// Get configured Ollama base URL, if provided.
     pub fn ollama_base_url(&self) -> Option<String> {
         self.config.load().ollama_base_url.clone()
     }
 
+    /// Warm-up and keepalive settings for local Ollama models.
+    pub fn ollama_config(&self) -> OllamaConfig {
+        self.config.load().ollama.clone()
+    }
+
+    /// Load state of each Ollama model the warm-up task is tracking.
+    pub fn ollama_states(&self) -> &OllamaModelStates {
+        &self.ollama_states
+    }
+
+    /// Host and GPU resource monitor.
+    pub fn resources(&self) -> &ResourceMonitor {
+        &self.resources
+    }
+
     /// Get the HTTP client.
     pub fn http_client(&self) -> &reqwest::Client {
-        &self.http_client
+        self.http.shared()
+    }
+
+    /// Get the HTTP client for calls to `provider`, which has its own pool
+    /// when `[llm.http.providers]` configures one.
+    pub fn http_client_for(&self, provider: &str) -> &reqwest::Client {
+        self.http.for_provider(provider)
+    }
+
+    /// Body size limits for calls to `provider`.
+    pub fn http_limits_for(&self, provider: &str) -> crate::llm::http::BodyLimits {
+        self.config.load().http.limits_for(provider)
+    }
+
+    /// Body size limits for tool fetches, from `[llm.http.fetch]`.
+    pub fn fetch_limits(&self) -> crate::llm::http::BodyLimits {
+        self.config.load().http.fetch
+    }
+
+    /// The model `model_name` stands for, if it's an alias in `[llm.aliases]`.
+    pub fn resolve_alias(&self, model_name: &str) -> String {
+        self.config.load().aliases.resolve(model_name).to_string()
+    }
+
+    /// The alias names in `[llm.aliases]`, sorted.
+    pub fn alias_names(&self) -> Vec<String> {
+        self.config
+            .load()
+            .aliases
+            .iter()
+            .map(|(alias, _)| alias.to_string())
+            .collect()
     }
 
     /// Resolve a model name to provider and model components.
@@ -174,30 +439,396 @@ impl LlmManager {
         }
     }
 
-    /// Record that a model hit a rate limit.
-    pub async fn record_rate_limit(&self, model_name: &str) {
-        self.rate_limited
-            .write()
-            .await
-            .insert(model_name.to_string(), Instant::now());
-        tracing::warn!(model = %model_name, "model rate limited, entering cooldown");
+    /// Record that a model hit a rate limit, putting it (and possibly its
+    /// provider) in an escalating cooldown. `retry_after` is the wait the
+    /// provider asked for, if it said.
+    pub async fn record_rate_limit(
+        &self,
+        model_name: &str,
+        policy: &CooldownPolicy,
+        retry_after: Option<Duration>,
+    ) {
+        let (cooldown, provider_cooldown) =
+            self.cooldowns
+                .strike(model_name, Instant::now(), policy, retry_after);
+        #[cfg(feature = "metrics")]
+        record_cooldown_metrics(&self.cooldowns.snapshot(Instant::now()));
+        tracing::warn!(
+            model = %model_name,
+            cooldown_secs = cooldown.as_secs(),
+            provider_cooldown_secs = provider_cooldown.map(|cooldown| cooldown.as_secs()),
+            "model rate limited, entering cooldown"
+        );
+
+        if let Some(shared) = &self.shared
+            && shared.available()
+            && let Err(error) = shared
+                .record_rate_limit(model_name, cooldown, provider_cooldown)
+                .await
+        {
+            tracing::warn!(%error, model = %model_name, "failed to share rate limit cooldown");
+        }
     }
 
-    /// Check if a model is currently in rate limit cooldown.
-    pub async fn is_rate_limited(&self, model_name: &str, cooldown_secs: u64) -> bool {
-        let map = self.rate_limited.read().await;
-        if let Some(limited_at) = map.get(model_name) {
-            limited_at.elapsed().as_secs() < cooldown_secs
-        } else {
-            false
+    /// Record that a model served a request, so its cooldowns can start over.
+    pub fn record_success(&self, model_name: &str) {
+        if self.cooldowns.tracks(model_name) {
+            self.cooldowns.succeed(model_name, Instant::now());
         }
     }
 
-    /// Clean up expired rate limit entries.
-    pub async fn cleanup_rate_limits(&self, cooldown_secs: u64) {
-        self.rate_limited
-            .write()
-            .await
-            .retain(|_, limited_at| limited_at.elapsed().as_secs() < cooldown_secs);
+    /// Check if a model or its provider is currently in rate limit cooldown,
+    /// here or (with shared state) on any other replica.
+    pub async fn is_rate_limited(&self, model_name: &str) -> bool {
+        if self
+            .cooldowns
+            .remaining(model_name, Instant::now())
+            .is_some()
+        {
+            return true;
+        }
+
+        let Some(shared) = self.shared.as_ref().filter(|shared| shared.available()) else {
+            return false;
+        };
+        match shared.cooldowns_for(model_name).await {
+            Ok((None, None)) => false,
+            Ok((model, provider)) => {
+                // Cache the fleet's cooldowns so later checks stay local.
+                let now = Instant::now();
+                if let Some(remaining) = model {
+                    self.cooldowns.adopt(model_name, false, now + remaining);
+                }
+                if let Some(remaining) = provider {
+                    let provider = crate::llm::routing::provider_from_model(model_name);
+                    self.cooldowns.adopt(provider, true, now + remaining);
+                }
+                true
+            }
+            Err(error) => {
+                tracing::warn!(%error, model = %model_name, "failed to read shared rate limit cooldown");
+                false
+            }
+        }
+    }
+
+    /// Sweep ended cooldowns and stale spend state now, rather than waiting
+    /// for the background task.
+    pub fn cleanup(&self) {
+        Sweep {
+            cooldowns: self.cooldowns.clone(),
+            spend: self.spend.clone(),
+            tenant_spend: self.tenant_spend.clone(),
+            shared: self.shared.clone(),
+        }
+        .run();
+    }
+
+    /// Stop the background cleanup task.
+    pub fn shutdown(&self) {
+        self.cleanup_task.abort();
+    }
+
+    /// Every model and provider cooldown still running on this replica,
+    /// longest first. Includes cooldowns adopted from other replicas once a
+    /// lookup here has seen them.
+    pub fn rate_limit_snapshot(&self) -> Vec<ActiveCooldown> {
+        let snapshot = self.cooldowns.snapshot(Instant::now());
+        #[cfg(feature = "metrics")]
+        record_cooldown_metrics(&snapshot);
+        snapshot
+    }
+
+    /// Budget status for a provider. Providers without caps are always normal.
+    pub fn budget_status(&self, provider: &str) -> BudgetStatus {
+        let config = self.config.load();
+        let Some(provider_budget) = config.budget.providers.get(provider) else {
+            return BudgetStatus::Normal;
+        };
+        if let Some(shared) = &self.shared
+            && shared.available()
+            && shared.spend_refresh_due(provider)
+        {
+            self.refresh_shared_spend(shared.clone(), provider);
+        }
+        self.spend
+            .spend(provider)
+            .status(provider_budget, config.budget.downgrade_threshold)
+    }
+
+    /// Rewrite a model chain (primary first) according to budget state.
+    ///
+    /// Returns the providers that are over budget when no model is left.
+    pub fn plan_budget_route(
+        &self,
+        chain: Vec<String>,
+    ) -> std::result::Result<Vec<String>, Vec<String>> {
+        let config = self.config.load();
+        if !config.budget.is_enabled() {
+            return Ok(chain);
+        }
+        budget::plan_chain(&chain, &config.budget, |provider| {
+            self.budget_status(provider)
+        })
+    }
+
+    /// Price a completion and add it to the provider's spend, alerting the
+    /// operator when the provider crosses into a new budget level.
+    pub fn record_spend(
+        &self,
+        model_name: &str,
+        usage: &rig::completion::Usage,
+        reasoning_tokens: u64,
+    ) {
+        let config = self.config.load();
+        let cost = config.budget.cost_of(model_name, usage, reasoning_tokens);
+        if cost <= 0.0 {
+            return;
+        }
+
+        let provider = crate::llm::routing::provider_from_model(model_name);
+        let spend = self.spend.record(provider, cost);
+        if let Some(shared) = self.shared.clone()
+            && shared.available()
+            && let Ok(handle) = tokio::runtime::Handle::try_current()
+        {
+            let tracker = self.spend.clone();
+            let provider = provider.to_string();
+            handle.spawn(async move {
+                match shared.add_spend(&provider, cost).await {
+                    Ok(totals) => {
+                        tracker.merge(&provider, totals);
+                    }
+                    Err(error) => {
+                        tracing::warn!(%error, %provider, "failed to share budget spend");
+                    }
+                }
+            });
+        }
+
+        self.check_budget(&config.budget, provider, spend);
+    }
+
+    /// Alert the operator if `spend` puts `provider` at a new budget level.
+    fn check_budget(
+        &self,
+        budget: &budget::BudgetConfig,
+        provider: &str,
+        spend: budget::ProviderSpend,
+    ) {
+        let Some(provider_budget) = budget.providers.get(provider) else {
+            return;
+        };
+        let status = spend.status(provider_budget, budget.downgrade_threshold);
+        if self.spend.should_alert(provider, status) {
+            let alert = BudgetAlert {
+                provider: provider.to_string(),
+                status,
+                spend,
+                budget: provider_budget.clone(),
+            };
+            tracing::warn!(
+                provider,
+                ?status,
+                daily_usd = spend.daily_usd,
+                monthly_usd = spend.monthly_usd,
+                "provider crossed budget threshold"
+            );
+            if status != BudgetStatus::Normal {
+                crate::events::publish(crate::events::Event::BudgetExceeded {
+                    provider: provider.to_string(),
+                    status,
+                    daily_usd: spend.daily_usd,
+                    monthly_usd: spend.monthly_usd,
+                });
+            }
+            // No subscribers just means nobody is configured to hear it.
+            self.budget_alert_tx.send(alert).ok();
+        }
+    }
+
+    /// Pull a provider's fleet-wide spend into the local ledger in the background.
+    fn refresh_shared_spend(&self, shared: Arc<SharedLimits>, provider: &str) {
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let tracker = self.spend.clone();
+        let provider = provider.to_string();
+        handle.spawn(async move {
+            match shared.spend(&provider).await {
+                Ok(totals) => {
+                    tracker.merge(&provider, totals);
+                }
+                Err(error) => {
+                    tracing::warn!(%error, %provider, "failed to read shared budget spend");
+                }
+            }
+        });
+    }
+
+    /// Roll for an injected fault on a call to `provider` (see `llm::chaos`).
+    pub fn inject_fault(&self, provider: &str) -> Option<Fault> {
+        self.config.load().chaos.roll(provider)
+    }
+
+    /// Cost in USD of a completion, or zero for unpriced models.
+    /// `reasoning_tokens` are the part of the output tokens spent reasoning.
+    pub fn cost_of(
+        &self,
+        model_name: &str,
+        usage: &rig::completion::Usage,
+        reasoning_tokens: u64,
+    ) -> f64 {
+        self.config
+            .load()
+            .budget
+            .cost_of(model_name, usage, reasoning_tokens)
+    }
+
+    /// Current spend per provider for this day and month.
+    pub fn spend_snapshot(&self) -> HashMap<String, budget::ProviderSpend> {
+        self.spend.snapshot()
+    }
+
+    /// Configured spend caps per provider.
+    pub fn provider_budgets(&self) -> HashMap<String, budget::ProviderBudget> {
+        self.config.load().budget.providers.clone()
+    }
+
+    /// Subscribe to operator budget alerts.
+    pub fn subscribe_budget_alerts(&self) -> broadcast::Receiver<BudgetAlert> {
+        self.budget_alert_tx.subscribe()
+    }
+
+    /// Configured "adapter:target" for operator budget alerts, if any.
+    pub fn budget_alert_target(&self) -> Option<String> {
+        self.config.load().budget.alert_target.clone()
+    }
+
+    /// Record a request on an SLO route (see `llm::slo`), alerting if the
+    /// route started or stopped burning its error budget too fast.
+    pub fn record_latency(&self, route: &str, latency: std::time::Duration) {
+        let config = self.config.load();
+        let Some(alert) = self
+            .slo
+            .record(&config.slo, route, latency, std::time::Instant::now())
+        else {
+            return;
+        };
+        tracing::warn!(
+            route,
+            burning = alert.burning,
+            burn_rate = alert.burn_rate,
+            observed_secs = alert.observed_secs,
+            "latency SLO burn rate changed"
+        );
+        crate::events::publish(crate::events::Event::SloAlert {
+            route: alert.route.clone(),
+            burning: alert.burning,
+            burn_rate: alert.burn_rate,
+            percentile: alert.objective.percentile,
+            threshold_secs: alert.objective.threshold_secs,
+            observed_secs: alert.observed_secs,
+        });
+        // No subscribers just means nobody is configured to hear it.
+        self.slo_alert_tx.send(alert).ok();
+    }
+
+    /// Subscribe to operator SLO alerts.
+    pub fn subscribe_slo_alerts(&self) -> broadcast::Receiver<SloAlert> {
+        self.slo_alert_tx.subscribe()
+    }
+
+    /// Configured "adapter:target" for operator SLO alerts, if any.
+    pub fn slo_alert_target(&self) -> Option<String> {
+        self.config.load().slo.alert_target.clone()
+    }
+}
+
+impl Drop for LlmManager {
+    fn drop(&mut self) {
+        self.cleanup_task.abort();
+    }
+}
+
+/// State the cleanup task sweeps, shared with the manager.
+struct Sweep {
+    cooldowns: Arc<Cooldowns>,
+    spend: Arc<SpendTracker>,
+    tenant_spend: Arc<SpendTracker>,
+    shared: Option<Arc<SharedLimits>>,
+}
+
+impl Sweep {
+    /// Drop cooldowns whose count would start over anyway (including ones
+    /// adopted from other replicas that have ended), providers and tenants
+    /// with nothing spent this period, and expired fleet spend refresh times.
+    fn run(&self) {
+        let cooldowns = self.cooldowns.prune(Instant::now());
+        #[cfg(feature = "metrics")]
+        record_cooldown_metrics(&self.cooldowns.snapshot(Instant::now()));
+        let spend = self.spend.prune() + self.tenant_spend.prune();
+        let refreshes = self
+            .shared
+            .as_ref()
+            .map_or(0, |shared| shared.prune_refreshed());
+        if cooldowns + spend + refreshes > 0 {
+            tracing::debug!(cooldowns, spend, refreshes, "swept stale LLM manager state");
+        }
+    }
+}
+
+/// Run `sweep` every `cleanup_interval_secs`, re-reading the interval after
+/// each sweep so config reloads apply.
+fn spawn_cleanup(config: Arc<ArcSwap<LlmConfig>>, sweep: Sweep) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let interval = config.load().cleanup_interval_secs.max(1);
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+            sweep.run();
+        }
+    })
+}
+
+/// Warn about capped providers that have nowhere cheaper to route.
+fn warn_budget_gaps(config: &LlmConfig) {
+    for provider in config.budget.providers_without_downgrade_path() {
+        tracing::warn!(
+            provider,
+            "provider has a budget cap but no downgrade_to or cheaper priced model; \
+             it can only downgrade to cheaper fallbacks already in the chain"
+        );
+    }
+}
+
+/// Warn about aliases that point at a model its provider has deprecated.
+fn warn_deprecated_aliases(config: &LlmConfig) {
+    for (alias, model) in config.aliases.iter() {
+        if let Some(replacement) = crate::llm::aliases::deprecation(model) {
+            tracing::warn!(
+                alias,
+                model,
+                replacement,
+                "model alias points to a deprecated model"
+            );
+        }
+    }
+}
+
+/// Publish when each running cooldown ends, dropping ones that are over.
+#[cfg(feature = "metrics")]
+fn record_cooldown_metrics(cooldowns: &[ActiveCooldown]) {
+    let gauge = &crate::telemetry::Metrics::global().rate_limit_cooldown_until_seconds;
+    gauge.reset();
+    let now = chrono::Utc::now().timestamp();
+    for cooldown in cooldowns {
+        let scope = if cooldown.provider_wide {
+            "provider"
+        } else {
+            "model"
+        };
+        gauge
+            .with_label_values(&[scope, cooldown.name.as_str()])
+            .set(now + cooldown.remaining.as_secs_f64().ceil() as i64);
     }
 }
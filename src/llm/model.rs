@@ -1,10 +1,16 @@
 //! SpacebotModel: Custom CompletionModel implementation that routes through LlmManager.
 
 use crate::config::{ApiType, ProviderConfig};
+use crate::llm::confidence::{self, ConfidenceAction};
+use crate::llm::cooldown::CooldownPolicy;
 use crate::llm::manager::LlmManager;
+use crate::llm::reasoning::{self, Reasoning};
 use crate::llm::routing::{
-    self, MAX_FALLBACK_ATTEMPTS, MAX_RETRIES_PER_MODEL, RETRY_BASE_DELAY_MS, RoutingConfig,
+    self, MAX_FALLBACK_ATTEMPTS, MAX_RETRIES_PER_MODEL, RETRY_BASE_DELAY_MS, RETRY_MAX_DELAY_MS,
+    RoutingConfig,
 };
+use crate::llm::sampling::{self, BestOfN, Selection};
+use crate::llm::structured::OutputFormat;
 
 use rig::completion::{self, CompletionError, CompletionModel, CompletionRequest, GetTokenUsage};
 use rig::message::{
@@ -16,14 +22,29 @@ use rig::streaming::StreamingCompletionResponse;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+pub mod streaming;
+
 /// Raw provider response. Wraps the JSON so Rig can carry it through.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawResponse {
     pub body: serde_json::Value,
 }
 
-/// Streaming response placeholder. Streaming will be implemented per-provider
-/// when we wire up SSE parsing.
+impl RawResponse {
+    /// Confidence derived from token logprobs, when the provider returned them.
+    pub fn confidence(&self) -> Option<f64> {
+        confidence::score_from_body(&self.body)
+    }
+
+    /// Tokens the model spent reasoning, out of its output tokens.
+    pub fn reasoning_tokens(&self) -> u64 {
+        reasoning::reasoning_tokens(&self.body)
+    }
+}
+
+/// Streaming response placeholder. Rig's streaming interface isn't
+/// implemented; [`SpacebotModel::stream_completion`] streams in
+/// [`streaming`]'s own event format instead.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RawStreamingResponse {
     pub body: serde_json::Value,
@@ -46,6 +67,8 @@ pub struct SpacebotModel {
     provider: String,
     full_model_name: String,
     routing: Option<RoutingConfig>,
+    /// The agent making the calls, for its tenant's keys and spend cap.
+    agent_id: Option<crate::AgentId>,
 }
 
 impl SpacebotModel {
@@ -65,11 +88,88 @@ impl SpacebotModel {
         self
     }
 
+    /// Make calls on behalf of an agent, so a tenant it serves pays for them
+    /// with its own keys and within its own cap.
+    pub fn with_agent(mut self, agent_id: crate::AgentId) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+
+    /// Reasoning settings for this model from the routing config.
+    pub fn reasoning(&self) -> Reasoning {
+        self.routing
+            .as_ref()
+            .map(|routing| routing.reasoning_for_model(&self.full_model_name))
+            .unwrap_or_default()
+    }
+
+    /// Apply budget caps to a model chain, refusing the call when every
+    /// provider in it is over budget.
+    fn budget_route(&self, chain: Vec<String>) -> Result<Vec<String>, CompletionError> {
+        if let Some(tenant) = self
+            .agent_id
+            .as_ref()
+            .and_then(|agent_id| self.llm_manager.tenant_over_budget(agent_id))
+        {
+            return Err(CompletionError::ProviderError(format!(
+                "{}: spend cap reached for tenant {tenant}",
+                routing::OVER_BUDGET_MARKER
+            )));
+        }
+        let original_primary = chain.first().cloned();
+        let planned = self
+            .llm_manager
+            .plan_budget_route(chain)
+            .map_err(|providers| {
+                CompletionError::ProviderError(format!(
+                    "{}: spend cap reached for {}",
+                    routing::OVER_BUDGET_MARKER,
+                    providers.join(", ")
+                ))
+            })?;
+
+        if original_primary.as_ref() != planned.first() {
+            tracing::info!(
+                original = original_primary.as_deref().unwrap_or_default(),
+                routed = %planned[0],
+                "budget routing switched primary model"
+            );
+        }
+
+        Ok(planned)
+    }
+
     /// Direct call to the provider (no fallback logic).
     async fn attempt_completion(
         &self,
         request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
+        let output_format = OutputFormat::from_request(&request)?;
+        if let Some(fault) = self.llm_manager.inject_fault(&self.provider) {
+            tracing::debug!(model = %self.full_model_name, ?fault, "injecting provider fault");
+            return Err(fault.into_error(&self.provider));
+        }
+        let response = self.dispatch_completion(request).await?;
+        let reasoning_tokens = response.raw_response.reasoning_tokens();
+        self.llm_manager
+            .record_spend(&self.full_model_name, &response.usage, reasoning_tokens);
+        if let Some(agent_id) = &self.agent_id {
+            self.llm_manager.record_tenant_spend(
+                agent_id,
+                &self.full_model_name,
+                &response.usage,
+                reasoning_tokens,
+            );
+        }
+        if let Some(output_format) = output_format {
+            output_format.validate(&response.choice)?;
+        }
+        Ok(response)
+    }
+
+    /// The provider serving this model and its config, with the key to call
+    /// it with, once the model is ready to take requests.
+    async fn provider_target(&self) -> Result<(&str, ProviderConfig), CompletionError> {
         let provider_id = self
             .full_model_name
             .split_once('/')
@@ -81,13 +181,47 @@ impl SpacebotModel {
             .get_provider(provider_id)
             .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
 
-        // For Anthropic, prefer OAuth token from auth.json over static config key
-        if provider_id == "anthropic" {
+        // Local models may still be pulling or loading; wait rather than fail.
+        if provider_id == "ollama" {
+            let load_timeout =
+                std::time::Duration::from_secs(self.llm_manager.ollama_config().load_timeout_secs);
+            self.llm_manager
+                .ollama_states()
+                .wait_until_ready(&self.model_name, load_timeout)
+                .await
+                .map_err(|error| {
+                    CompletionError::ProviderError(format!(
+                        "ollama model {} isn't available: {error}",
+                        self.model_name
+                    ))
+                })?;
+            self.admit_local_model(&provider_config.base_url).await?;
+        }
+
+        // A tenant's own key wins; otherwise, for Anthropic, prefer the OAuth
+        // token from auth.json over the static config key
+        if let Some(key) = self
+            .agent_id
+            .as_ref()
+            .and_then(|agent_id| self.llm_manager.tenant_api_key(agent_id, provider_id))
+        {
+            provider_config.api_key = key;
+        } else if provider_id == "anthropic" {
             if let Ok(Some(token)) = self.llm_manager.get_anthropic_token().await {
                 provider_config.api_key = token;
             }
         }
 
+        Ok((provider_id, provider_config))
+    }
+
+    /// Send the request to the provider's API for this model.
+    async fn dispatch_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
+        let (provider_id, provider_config) = self.provider_target().await?;
+
         if provider_id == "zai-coding-plan" || provider_id == "zhipu" {
             let display_name = if provider_id == "zhipu" {
                 "Z.AI (GLM)"
@@ -109,12 +243,77 @@ impl SpacebotModel {
         }
 
         match provider_config.api_type {
-            ApiType::Anthropic => self.call_anthropic(request, &provider_config).await,
+            ApiType::Anthropic => {
+                // No native JSON mode; ask for the format in the system prompt.
+                let mut request = request;
+                if let Some(output_format) = OutputFormat::from_request(&request)? {
+                    output_format.apply_to_preamble(&mut request, provider_id)?;
+                }
+                self.call_anthropic(request, &provider_config).await
+            }
             ApiType::OpenAiCompletions => self.call_openai(request, &provider_config).await,
             ApiType::OpenAiResponses => self.call_openai_responses(request, &provider_config).await,
+            ApiType::OpenAiCompatible => {
+                let display_name = provider_config.name.as_deref().unwrap_or(provider_id);
+                let endpoint = format!(
+                    "{}/v1/chat/completions",
+                    provider_config.base_url.trim_end_matches('/')
+                );
+                let api_key = Some(provider_config.api_key.clone()).filter(|key| !key.is_empty());
+                self.call_openai_compatible_with_optional_auth(
+                    request,
+                    display_name,
+                    &endpoint,
+                    api_key,
+                )
+                .await
+            }
         }
     }
 
+    /// Queue (then reject) a local model load when VRAM is exhausted.
+    ///
+    /// Models Ollama already has resident skip the check, since their memory
+    /// is part of what's in use.
+    async fn admit_local_model(&self, base_url: &str) -> Result<(), CompletionError> {
+        let config = self.llm_manager.ollama_config();
+        if config.min_free_vram_mib == 0 {
+            return Ok(());
+        }
+
+        let base_url = crate::config::normalize_ollama_base_url(Some(base_url.to_string()));
+        let model = crate::llm::ollama::normalize_model_name(&self.model_name);
+        match crate::llm::ollama::list_running_models(self.llm_manager.http_client(), &base_url)
+            .await
+        {
+            Ok(running)
+                if running
+                    .iter()
+                    .any(|name| crate::llm::ollama::normalize_model_name(name) == model) =>
+            {
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(error) => {
+                tracing::debug!(%error, "can't list running Ollama models, checking VRAM anyway");
+            }
+        }
+
+        self.llm_manager
+            .resources()
+            .wait_for_vram(
+                config.min_free_vram_mib,
+                std::time::Duration::from_secs(config.vram_queue_timeout_secs),
+            )
+            .await
+            .map_err(|error| {
+                CompletionError::ProviderError(format!(
+                    "can't load ollama model {}: {error}",
+                    self.model_name
+                ))
+            })
+    }
+
     /// Try a model with retries and exponential backoff on transient errors.
     ///
     /// Returns `Ok(response)` on success, or `Err((last_error, was_rate_limit))`
@@ -126,16 +325,49 @@ impl SpacebotModel {
         model_name: &str,
         request: &CompletionRequest,
     ) -> Result<completion::CompletionResponse<RawResponse>, (CompletionError, bool)> {
+        self.retry_model(model_name, |model| {
+            let request = request.clone();
+            async move { model.attempt_completion(request).await }
+        })
+        .await
+    }
+
+    /// Run `call` against `model_name` with the retry loop of
+    /// [`attempt_with_retries`](Self::attempt_with_retries).
+    async fn retry_model<T, F, Fut>(
+        &self,
+        model_name: &str,
+        call: F,
+    ) -> Result<T, (CompletionError, bool)>
+    where
+        F: Fn(SpacebotModel) -> Fut,
+        Fut: std::future::Future<Output = Result<T, CompletionError>>,
+    {
         let model = if model_name == self.full_model_name {
             self.clone()
         } else {
-            SpacebotModel::make(&self.llm_manager, model_name)
+            SpacebotModel {
+                agent_id: self.agent_id.clone(),
+                ..SpacebotModel::make(&self.llm_manager, model_name)
+            }
         };
 
-        let mut last_error = None;
+        let mut last_error: Option<String> = None;
+        let mut delay_ms = RETRY_BASE_DELAY_MS;
+        let mut attempts = 0;
         for attempt in 0..MAX_RETRIES_PER_MODEL {
             if attempt > 0 {
-                let delay_ms = RETRY_BASE_DELAY_MS * 2u64.pow((attempt - 1) as u32);
+                // Wait as long as the provider asked, if it said; a wait too
+                // long to sit out goes to the cooldown and fallbacks instead.
+                let retry_after_ms = last_error
+                    .as_deref()
+                    .and_then(routing::retry_after_in)
+                    .map(|retry_after| retry_after.as_millis() as u64);
+                if retry_after_ms.is_some_and(|wait| wait > RETRY_MAX_DELAY_MS) {
+                    break;
+                }
+                delay_ms =
+                    retry_after_ms.unwrap_or_else(|| routing::decorrelated_backoff(delay_ms));
                 tracing::debug!(
                     model = %model_name,
                     attempt = attempt + 1,
@@ -145,7 +377,8 @@ impl SpacebotModel {
                 tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
             }
 
-            match model.attempt_completion(request.clone()).await {
+            attempts += 1;
+            match call(model.clone()).await {
                 Ok(response) => return Ok(response),
                 Err(error) => {
                     let error_str = error.to_string();
@@ -168,11 +401,253 @@ impl SpacebotModel {
         let was_rate_limit = routing::is_rate_limit_error(&error_str);
         Err((
             CompletionError::ProviderError(format!(
-                "{model_name} failed after {MAX_RETRIES_PER_MODEL} attempts: {error_str}"
+                "{model_name} failed after {attempts} attempts: {error_str}"
             )),
             was_rate_limit,
         ))
     }
+
+    /// Run a request through budget routing, retries, and the fallback chain.
+    async fn route_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
+        let Some(routing) = &self.routing else {
+            // No routing config — just call the model directly, no fallback/retry.
+            // Budget caps still apply: a downgrade can swap in a cheaper model.
+            let chain = self.budget_route(vec![self.full_model_name.clone()])?;
+            if chain[0] == self.full_model_name {
+                return self.attempt_completion(request).await;
+            }
+            // A swapped-in downgrade model gets the normal retry loop.
+            return self
+                .attempt_with_retries(&chain[0], &request)
+                .await
+                .map_err(|(error, _)| error);
+        };
+
+        let cooldown = CooldownPolicy::from_routing(routing);
+        let chain = std::iter::once(self.full_model_name.clone())
+            .chain(routing.get_fallbacks(&self.full_model_name).iter().cloned())
+            .collect();
+        let chain = self.budget_route(chain)?;
+        let (primary, fallbacks) = chain
+            .split_first()
+            .expect("budget routing never returns an empty chain");
+        let mut last_error: Option<CompletionError> = None;
+
+        // Try the primary model (with retries) unless it's in rate-limit cooldown
+        // and we have fallbacks to try instead.
+        let primary_rate_limited = self.llm_manager.is_rate_limited(primary).await;
+
+        let skip_primary = primary_rate_limited && !fallbacks.is_empty();
+
+        if skip_primary {
+            tracing::debug!(
+                model = %primary,
+                "primary model in rate-limit cooldown, skipping to fallbacks"
+            );
+        } else {
+            match self.attempt_with_retries(primary, &request).await {
+                Ok(response) => {
+                    self.llm_manager.record_success(primary);
+                    return Ok(self
+                        .review_confidence(routing, primary, &request, response)
+                        .await);
+                }
+                Err((error, was_rate_limit)) => {
+                    if was_rate_limit {
+                        let retry_after = routing::retry_after_in(&error.to_string());
+                        self.llm_manager
+                            .record_rate_limit(primary, &cooldown, retry_after)
+                            .await;
+                    }
+                    if fallbacks.is_empty() {
+                        // No fallbacks — this is the final error
+                        return Err(error);
+                    }
+                    tracing::warn!(
+                        model = %primary,
+                        "primary model exhausted retries, trying fallbacks"
+                    );
+                    last_error = Some(error);
+                }
+            }
+        }
+
+        // Try fallback chain, each with their own retry loop
+        for (index, fallback_name) in fallbacks.iter().take(MAX_FALLBACK_ATTEMPTS).enumerate() {
+            if self.llm_manager.is_rate_limited(fallback_name).await {
+                tracing::debug!(
+                    fallback = %fallback_name,
+                    "fallback model in cooldown, skipping"
+                );
+                continue;
+            }
+
+            match self.attempt_with_retries(fallback_name, &request).await {
+                Ok(response) => {
+                    self.llm_manager.record_success(fallback_name);
+                    tracing::info!(
+                        original = %self.full_model_name,
+                        fallback = %fallback_name,
+                        attempt = index + 1,
+                        "fallback model succeeded"
+                    );
+                    return Ok(self
+                        .review_confidence(routing, fallback_name, &request, response)
+                        .await);
+                }
+                Err((error, was_rate_limit)) => {
+                    if was_rate_limit {
+                        let retry_after = routing::retry_after_in(&error.to_string());
+                        self.llm_manager
+                            .record_rate_limit(fallback_name, &cooldown, retry_after)
+                            .await;
+                    }
+                    tracing::warn!(
+                        fallback = %fallback_name,
+                        "fallback model exhausted retries, continuing chain"
+                    );
+                    last_error = Some(error);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            CompletionError::ProviderError("all models in fallback chain failed".into())
+        }))
+    }
+
+    /// Draw several samples for a request and return the selected one.
+    ///
+    /// Samples after the first run in parallel, as many as the cost cap allows.
+    /// The returned usage covers every sample and the judge call.
+    async fn best_of_n(
+        &self,
+        best_of: BestOfN,
+        request: CompletionRequest,
+    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
+        let sample_request = best_of.sample_request(&request);
+        let first = self.route_completion(sample_request.clone()).await?;
+        let first_cost = self.llm_manager.cost_of(
+            &self.full_model_name,
+            &first.usage,
+            first.raw_response.reasoning_tokens(),
+        );
+        let count = best_of.affordable_samples(first_cost);
+
+        let mut samples = vec![first];
+        let rest = futures::future::join_all(
+            (1..count).map(|_| self.route_completion(sample_request.clone())),
+        )
+        .await;
+        for sample in rest {
+            match sample {
+                Ok(sample) => samples.push(sample),
+                Err(error) => tracing::warn!(%error, "best-of-n sample failed"),
+            }
+        }
+
+        let replies: Vec<String> = samples
+            .iter()
+            .map(|sample| sampling::reply_text(&sample.choice))
+            .collect();
+        let mut usage = samples
+            .iter()
+            .fold(completion::Usage::new(), |total, sample| {
+                total + sample.usage
+            });
+
+        let selected = match &best_of.selection {
+            _ if samples.len() == 1 => 0,
+            Selection::MajorityVote => sampling::majority_vote(&replies),
+            Selection::Judge { model } => {
+                let judge_request = sampling::judge_request(&request, &replies);
+                match self.attempt_with_retries(model, &judge_request).await {
+                    Ok(verdict) => {
+                        usage += verdict.usage;
+                        sampling::parse_judge_choice(
+                            &sampling::reply_text(&verdict.choice),
+                            replies.len(),
+                        )
+                        .unwrap_or_else(|| {
+                            tracing::warn!(judge = %model, "judge named no valid candidate, using majority vote");
+                            sampling::majority_vote(&replies)
+                        })
+                    }
+                    Err((error, _)) => {
+                        tracing::warn!(judge = %model, %error, "judge failed, using majority vote");
+                        sampling::majority_vote(&replies)
+                    }
+                }
+            }
+        };
+
+        tracing::debug!(
+            model = %self.full_model_name,
+            samples = samples.len(),
+            selected,
+            "best-of-n selection"
+        );
+        let mut response = samples.swap_remove(selected);
+        response.usage = usage;
+        Ok(response)
+    }
+
+    /// Act on a low-confidence reply from `model_name`.
+    ///
+    /// Very low scores go to the configured second-opinion model; low scores
+    /// are regenerated once with an instruction to hedge. The retry is not
+    /// scored again, and the original reply is kept if it fails.
+    async fn review_confidence(
+        &self,
+        routing: &RoutingConfig,
+        model_name: &str,
+        request: &CompletionRequest,
+        response: completion::CompletionResponse<RawResponse>,
+    ) -> completion::CompletionResponse<RawResponse> {
+        if !routing.confidence.enabled {
+            return response;
+        }
+        let Some(score) = response.raw_response.confidence() else {
+            return response;
+        };
+
+        let (retry_model, retry_request) = match routing.confidence.action_for(score, model_name) {
+            ConfidenceAction::Accept => return response,
+            ConfidenceAction::SecondOpinion(second_opinion_model) => {
+                tracing::info!(
+                    model = %model_name,
+                    second_opinion = %second_opinion_model,
+                    score,
+                    "low-confidence reply, asking for a second opinion"
+                );
+                (second_opinion_model, request.clone())
+            }
+            ConfidenceAction::Hedge => {
+                tracing::debug!(model = %model_name, score, "low-confidence reply, regenerating with hedging");
+                let instruction = crate::prompts::text::get("fragments/system/low_confidence");
+                let mut request = request.clone();
+                request.preamble = Some(match request.preamble.take() {
+                    Some(preamble) => format!("{preamble}\n\n{instruction}"),
+                    None => instruction.to_string(),
+                });
+                (model_name.to_string(), request)
+            }
+        };
+
+        match self
+            .attempt_with_retries(&retry_model, &retry_request)
+            .await
+        {
+            Ok(retried) => retried,
+            Err((error, _)) => {
+                tracing::warn!(model = %retry_model, %error, "low-confidence retry failed, keeping original reply");
+                response
+            }
+        }
+    }
 }
 
 impl CompletionModel for SpacebotModel {
@@ -181,7 +656,7 @@ impl CompletionModel for SpacebotModel {
     type Client = Arc<LlmManager>;
 
     fn make(client: &Self::Client, model: impl Into<String>) -> Self {
-        let full_name = model.into();
+        let full_name = client.resolve_alias(&model.into());
 
         // OpenRouter model names have the form "openrouter/provider/model",
         // so split on the first "/" only and keep the rest as the model name.
@@ -201,6 +676,7 @@ impl CompletionModel for SpacebotModel {
             provider,
             full_model_name,
             routing: None,
+            agent_id: None,
         }
     }
 
@@ -208,117 +684,24 @@ impl CompletionModel for SpacebotModel {
         &self,
         request: CompletionRequest,
     ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
-        #[cfg(feature = "metrics")]
         let start = std::time::Instant::now();
 
-        let result = async move {
-            let Some(routing) = &self.routing else {
-                // No routing config — just call the model directly, no fallback/retry
-                return self.attempt_completion(request).await;
-            };
-
-            let cooldown = routing.rate_limit_cooldown_secs;
-            let fallbacks = routing.get_fallbacks(&self.full_model_name);
-            let mut last_error: Option<CompletionError> = None;
-
-            // Try the primary model (with retries) unless it's in rate-limit cooldown
-            // and we have fallbacks to try instead.
-            let primary_rate_limited = self
-                .llm_manager
-                .is_rate_limited(&self.full_model_name, cooldown)
-                .await;
-
-            let skip_primary = primary_rate_limited && !fallbacks.is_empty();
-
-            if skip_primary {
-                tracing::debug!(
-                    model = %self.full_model_name,
-                    "primary model in rate-limit cooldown, skipping to fallbacks"
-                );
-            } else {
-                match self
-                    .attempt_with_retries(&self.full_model_name, &request)
-                    .await
-                {
-                    Ok(response) => return Ok(response),
-                    Err((error, was_rate_limit)) => {
-                        if was_rate_limit {
-                            self.llm_manager
-                                .record_rate_limit(&self.full_model_name)
-                                .await;
-                        }
-                        if fallbacks.is_empty() {
-                            // No fallbacks — this is the final error
-                            return Err(error);
-                        }
-                        tracing::warn!(
-                            model = %self.full_model_name,
-                            "primary model exhausted retries, trying fallbacks"
-                        );
-                        last_error = Some(error);
-                    }
-                }
-            }
-
-            // Try fallback chain, each with their own retry loop
-            for (index, fallback_name) in fallbacks.iter().take(MAX_FALLBACK_ATTEMPTS).enumerate() {
-                if self
-                    .llm_manager
-                    .is_rate_limited(fallback_name, cooldown)
-                    .await
-                {
-                    tracing::debug!(
-                        fallback = %fallback_name,
-                        "fallback model in cooldown, skipping"
-                    );
-                    continue;
-                }
-
-                match self.attempt_with_retries(fallback_name, &request).await {
-                    Ok(response) => {
-                        tracing::info!(
-                            original = %self.full_model_name,
-                            fallback = %fallback_name,
-                            attempt = index + 1,
-                            "fallback model succeeded"
-                        );
-                        return Ok(response);
-                    }
-                    Err((error, was_rate_limit)) => {
-                        if was_rate_limit {
-                            self.llm_manager.record_rate_limit(fallback_name).await;
-                        }
-                        tracing::warn!(
-                            fallback = %fallback_name,
-                            "fallback model exhausted retries, continuing chain"
-                        );
-                        last_error = Some(error);
-                    }
-                }
-            }
+        let result = match BestOfN::from_request(&request) {
+            Ok(Some(best_of)) => self.best_of_n(best_of, request).await,
+            Ok(None) => self.route_completion(request).await,
+            Err(error) => Err(error),
+        };
 
-            Err(last_error.unwrap_or_else(|| {
-                CompletionError::ProviderError("all models in fallback chain failed".into())
-            }))
-        }
-        .await;
-
-        #[cfg(feature = "metrics")]
-        {
-            let elapsed = start.elapsed().as_secs_f64();
-            let metrics = crate::telemetry::Metrics::global();
-            // TODO: agent_id and tier are "unknown" because SpacebotModel doesn't
-            // carry process context. Thread agent_id/ProcessType through to get
-            // per-agent, per-tier breakdowns.
-            metrics
-                .llm_requests_total
-                .with_label_values(&["unknown", &self.full_model_name, "unknown"])
-                .inc();
-            metrics
-                .llm_request_duration_seconds
-                .with_label_values(&["unknown", &self.full_model_name, "unknown"])
-                .observe(elapsed);
+        let elapsed = start.elapsed();
+        if result.is_ok() {
+            self.llm_manager
+                .record_latency(crate::llm::slo::COMPLETION, elapsed);
         }
+        crate::events::publish(crate::events::Event::CompletionFinished {
+            model: self.full_model_name.clone(),
+            duration_secs: elapsed.as_secs_f64(),
+            error: result.as_ref().err().map(|error| error.to_string()),
+        });
 
         result
     }
@@ -328,7 +711,7 @@ impl CompletionModel for SpacebotModel {
         _request: CompletionRequest,
     ) -> Result<StreamingCompletionResponse<RawStreamingResponse>, CompletionError> {
         Err(CompletionError::ProviderError(
-            "streaming not yet implemented".into(),
+            "rig streaming isn't implemented; use stream_completion".into(),
         ))
     }
 }
@@ -341,33 +724,35 @@ impl SpacebotModel {
     ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
         let api_key = provider_config.api_key.as_str();
 
-        let effort = self
-            .routing
-            .as_ref()
-            .map(|r| r.thinking_effort_for_model(&self.model_name))
-            .unwrap_or("auto");
         let anthropic_request = crate::llm::anthropic::build_anthropic_request(
-            self.llm_manager.http_client(),
+            self.llm_manager.http_client_for(&self.provider),
             &api_key,
             &self.model_name,
             &request,
-            effort,
+            &self.reasoning(),
+            false,
         );
 
         let is_oauth =
             anthropic_request.auth_path == crate::llm::anthropic::AnthropicAuthPath::OAuthToken;
         let original_tools = anthropic_request.original_tools;
 
-        let response = anthropic_request
-            .builder
-            .send()
+        let limits = self.llm_manager.http_limits_for(&self.provider);
+        let response = crate::llm::http::send(anthropic_request.builder, limits)
             .await
-            .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
+            .map_err(|e| {
+                CompletionError::ProviderError(crate::logging::redact_secrets(&e.to_string()))
+            })?;
 
         let status = response.status();
-        let response_text = response.text().await.map_err(|e| {
-            CompletionError::ProviderError(format!("failed to read response body: {e}"))
-        })?;
+        let retry_after = routing::retry_after_header(response.headers());
+        let response_text = crate::llm::http::read_text(response, limits)
+            .await
+            .map_err(|e| {
+                CompletionError::ProviderError(crate::logging::redact_secrets(&format!(
+                    "failed to read response body: {e}"
+                )))
+            })?;
 
         let response_body: serde_json::Value =
             serde_json::from_str(&response_text).map_err(|e| {
@@ -381,8 +766,9 @@ impl SpacebotModel {
             let message = response_body["error"]["message"]
                 .as_str()
                 .unwrap_or("unknown error");
-            return Err(CompletionError::ProviderError(format!(
-                "Anthropic API error ({status}): {message}"
+            return Err(CompletionError::ProviderError(routing::with_retry_after(
+                format!("Anthropic API error ({status}): {message}"),
+                retry_after,
             )));
         }
 
@@ -396,13 +782,34 @@ impl SpacebotModel {
         Ok(completion)
     }
 
-    async fn call_openai(
-        &self,
-        request: CompletionRequest,
-        provider_config: &ProviderConfig,
-    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
-        let api_key = provider_config.api_key.as_str();
+    /// Token limit, temperature, and reasoning effort for a Chat Completions
+    /// body. OpenAI reasoning models reject `max_tokens` and any temperature
+    /// but the default.
+    fn apply_chat_sampling(&self, body: &mut serde_json::Value, request: &CompletionRequest) {
+        let reasoning_model = reasoning::is_openai_reasoning_model(&self.model_name);
+        if let Some(max_tokens) = request.max_tokens {
+            let key = if reasoning_model {
+                "max_completion_tokens"
+            } else {
+                "max_tokens"
+            };
+            body[key] = serde_json::json!(max_tokens);
+        }
+
+        if let Some(temperature) = request.temperature.filter(|_| !reasoning_model) {
+            body["temperature"] = serde_json::json!(temperature);
+        }
 
+        if reasoning_model && let Some(effort) = self.reasoning().openai_effort(&self.model_name) {
+            body["reasoning_effort"] = serde_json::json!(effort);
+        }
+    }
+
+    /// A Chat Completions body for `request`.
+    fn chat_completions_body(
+        &self,
+        request: &CompletionRequest,
+    ) -> Result<serde_json::Value, CompletionError> {
         let mut messages = Vec::new();
 
         if let Some(preamble) = &request.preamble {
@@ -419,13 +826,7 @@ impl SpacebotModel {
             "messages": messages,
         });
 
-        if let Some(max_tokens) = request.max_tokens {
-            body["max_tokens"] = serde_json::json!(max_tokens);
-        }
-
-        if let Some(temperature) = request.temperature {
-            body["temperature"] = serde_json::json!(temperature);
-        }
+        self.apply_chat_sampling(&mut body, request);
 
         if !request.tools.is_empty() {
             let tools: Vec<serde_json::Value> = request
@@ -445,6 +846,31 @@ impl SpacebotModel {
             body["tools"] = serde_json::json!(tools);
         }
 
+        if let Some(output_format) = OutputFormat::from_request(request)? {
+            output_format.apply_to_chat_body(&mut body, &self.provider)?;
+        }
+
+        Ok(body)
+    }
+
+    async fn call_openai(
+        &self,
+        request: CompletionRequest,
+        provider_config: &ProviderConfig,
+    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
+        let api_key = provider_config.api_key.as_str();
+
+        let mut body = self.chat_completions_body(&request)?;
+
+        if self
+            .routing
+            .as_ref()
+            .is_some_and(|routing| routing.confidence.enabled)
+            && confidence::supports_logprobs(&self.provider)
+        {
+            confidence::request_logprobs(&mut body);
+        }
+
         let chat_completions_url = format!(
             "{}/v1/chat/completions",
             provider_config.base_url.trim_end_matches('/')
@@ -452,7 +878,7 @@ impl SpacebotModel {
 
         let mut request_builder = self
             .llm_manager
-            .http_client()
+            .http_client_for(&self.provider)
             .post(&chat_completions_url)
             .header("authorization", format!("Bearer {api_key}"))
             .header("content-type", "application/json");
@@ -463,16 +889,29 @@ impl SpacebotModel {
             request_builder = request_builder.header("user-agent", "KimiCLI/1.3");
         }
 
-        let response = request_builder
-            .json(&body)
-            .send()
+        // A cold local model can take longer than the client default to answer.
+        if self.provider == "ollama" {
+            request_builder = request_builder.timeout(std::time::Duration::from_secs(
+                self.llm_manager.ollama_config().load_timeout_secs,
+            ));
+        }
+
+        let limits = self.llm_manager.http_limits_for(&self.provider);
+        let response = crate::llm::http::send(request_builder.json(&body), limits)
             .await
-            .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
+            .map_err(|e| {
+                CompletionError::ProviderError(crate::logging::redact_secrets(&e.to_string()))
+            })?;
 
         let status = response.status();
-        let response_text = response.text().await.map_err(|e| {
-            CompletionError::ProviderError(format!("failed to read response body: {e}"))
-        })?;
+        let retry_after = routing::retry_after_header(response.headers());
+        let response_text = crate::llm::http::read_text(response, limits)
+            .await
+            .map_err(|e| {
+                CompletionError::ProviderError(crate::logging::redact_secrets(&format!(
+                    "failed to read response body: {e}"
+                )))
+            })?;
 
         let response_body: serde_json::Value =
             serde_json::from_str(&response_text).map_err(|e| {
@@ -486,23 +925,20 @@ impl SpacebotModel {
             let message = response_body["error"]["message"]
                 .as_str()
                 .unwrap_or("unknown error");
-            return Err(CompletionError::ProviderError(format!(
-                "OpenAI API error ({status}): {message}"
+            return Err(CompletionError::ProviderError(routing::with_retry_after(
+                format!("OpenAI API error ({status}): {message}"),
+                retry_after,
             )));
         }
 
         parse_openai_response(response_body, "OpenAI")
     }
 
-    async fn call_openai_responses(
+    /// A Responses API body for `request`.
+    fn responses_body(
         &self,
-        request: CompletionRequest,
-        provider_config: &ProviderConfig,
-    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
-        let base_url = provider_config.base_url.trim_end_matches('/');
-        let responses_url = format!("{base_url}/v1/responses");
-        let api_key = provider_config.api_key.as_str();
-
+        request: &CompletionRequest,
+    ) -> Result<serde_json::Value, CompletionError> {
         let input = convert_messages_to_openai_responses(&request.chat_history);
 
         let mut body = serde_json::json!({
@@ -518,10 +954,23 @@ impl SpacebotModel {
             body["max_output_tokens"] = serde_json::json!(max_tokens);
         }
 
-        if let Some(temperature) = request.temperature {
+        let reasoning_model = reasoning::is_openai_reasoning_model(&self.model_name);
+        if let Some(temperature) = request.temperature.filter(|_| !reasoning_model) {
             body["temperature"] = serde_json::json!(temperature);
         }
 
+        if reasoning_model {
+            let reasoning = self.reasoning();
+            let mut settings = serde_json::json!({});
+            if let Some(effort) = reasoning.openai_effort(&self.model_name) {
+                settings["effort"] = serde_json::json!(effort);
+            }
+            if reasoning.display == reasoning::ReasoningDisplay::Summary {
+                settings["summary"] = serde_json::json!("auto");
+            }
+            body["reasoning"] = settings;
+        }
+
         if !request.tools.is_empty() {
             let tools: Vec<serde_json::Value> = request
                 .tools
@@ -538,21 +987,45 @@ impl SpacebotModel {
             body["tools"] = serde_json::json!(tools);
         }
 
-        let response = self
+        if let Some(output_format) = OutputFormat::from_request(request)? {
+            output_format.apply_to_responses_body(&mut body, &self.provider)?;
+        }
+
+        Ok(body)
+    }
+
+    async fn call_openai_responses(
+        &self,
+        request: CompletionRequest,
+        provider_config: &ProviderConfig,
+    ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
+        let base_url = provider_config.base_url.trim_end_matches('/');
+        let responses_url = format!("{base_url}/v1/responses");
+        let api_key = provider_config.api_key.as_str();
+
+        let body = self.responses_body(&request)?;
+
+        let limits = self.llm_manager.http_limits_for(&self.provider);
+        let request = self
             .llm_manager
-            .http_client()
+            .http_client_for(&self.provider)
             .post(&responses_url)
             .header("authorization", format!("Bearer {api_key}"))
             .header("content-type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
+            .json(&body);
+        let response = crate::llm::http::send(request, limits).await.map_err(|e| {
+            CompletionError::ProviderError(crate::logging::redact_secrets(&e.to_string()))
+        })?;
 
         let status = response.status();
-        let response_text = response.text().await.map_err(|e| {
-            CompletionError::ProviderError(format!("failed to read response body: {e}"))
-        })?;
+        let retry_after = routing::retry_after_header(response.headers());
+        let response_text = crate::llm::http::read_text(response, limits)
+            .await
+            .map_err(|e| {
+                CompletionError::ProviderError(crate::logging::redact_secrets(&format!(
+                    "failed to read response body: {e}"
+                )))
+            })?;
 
         let response_body: serde_json::Value =
             serde_json::from_str(&response_text).map_err(|e| {
@@ -566,8 +1039,9 @@ impl SpacebotModel {
             let message = response_body["error"]["message"]
                 .as_str()
                 .unwrap_or("unknown error");
-            return Err(CompletionError::ProviderError(format!(
-                "OpenAI Responses API error ({status}): {message}"
+            return Err(CompletionError::ProviderError(routing::with_retry_after(
+                format!("OpenAI Responses API error ({status}): {message}"),
+                retry_after,
             )));
         }
 
@@ -584,7 +1058,9 @@ impl SpacebotModel {
     ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
         let base_url = provider_config.base_url.trim_end_matches('/');
         let endpoint_path = match provider_config.api_type {
-            ApiType::OpenAiCompletions | ApiType::OpenAiResponses => "/v1/chat/completions",
+            ApiType::OpenAiCompletions | ApiType::OpenAiResponses | ApiType::OpenAiCompatible => {
+                "/v1/chat/completions"
+            }
             ApiType::Anthropic => {
                 return Err(CompletionError::ProviderError(format!(
                     "{provider_display_name} is configured with anthropic API type, but this call expects an OpenAI-compatible API"
@@ -610,13 +1086,7 @@ impl SpacebotModel {
             "messages": messages,
         });
 
-        if let Some(max_tokens) = request.max_tokens {
-            body["max_tokens"] = serde_json::json!(max_tokens);
-        }
-
-        if let Some(temperature) = request.temperature {
-            body["temperature"] = serde_json::json!(temperature);
-        }
+        self.apply_chat_sampling(&mut body, &request);
 
         if !request.tools.is_empty() {
             let tools: Vec<serde_json::Value> = request
@@ -636,21 +1106,27 @@ impl SpacebotModel {
             body["tools"] = serde_json::json!(tools);
         }
 
-        let response = self
+        let limits = self.llm_manager.http_limits_for(&self.provider);
+        let request = self
             .llm_manager
-            .http_client()
+            .http_client_for(&self.provider)
             .post(&endpoint)
             .header("authorization", format!("Bearer {api_key}"))
             .header("content-type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
+            .json(&body);
+        let response = crate::llm::http::send(request, limits).await.map_err(|e| {
+            CompletionError::ProviderError(crate::logging::redact_secrets(&e.to_string()))
+        })?;
 
         let status = response.status();
-        let response_text = response.text().await.map_err(|e| {
-            CompletionError::ProviderError(format!("failed to read response body: {e}"))
-        })?;
+        let retry_after = routing::retry_after_header(response.headers());
+        let response_text = crate::llm::http::read_text(response, limits)
+            .await
+            .map_err(|e| {
+                CompletionError::ProviderError(crate::logging::redact_secrets(&format!(
+                    "failed to read response body: {e}"
+                )))
+            })?;
 
         let response_body: serde_json::Value =
             serde_json::from_str(&response_text).map_err(|e| {
@@ -664,8 +1140,9 @@ impl SpacebotModel {
             let message = response_body["error"]["message"]
                 .as_str()
                 .unwrap_or("unknown error");
-            return Err(CompletionError::ProviderError(format!(
-                "{provider_display_name} API error ({status}): {message}"
+            return Err(CompletionError::ProviderError(routing::with_retry_after(
+                format!("{provider_display_name} API error ({status}): {message}"),
+                retry_after,
             )));
         }
 
@@ -680,49 +1157,12 @@ impl SpacebotModel {
         endpoint: &str,
         api_key: Option<String>,
     ) -> Result<completion::CompletionResponse<RawResponse>, CompletionError> {
-        let mut messages = Vec::new();
-
-        if let Some(preamble) = &request.preamble {
-            messages.push(serde_json::json!({
-                "role": "system",
-                "content": preamble,
-            }));
-        }
-
-        messages.extend(convert_messages_to_openai(&request.chat_history));
-
-        let mut body = serde_json::json!({
-            "model": self.model_name,
-            "messages": messages,
-        });
-
-        if let Some(max_tokens) = request.max_tokens {
-            body["max_tokens"] = serde_json::json!(max_tokens);
-        }
-
-        if let Some(temperature) = request.temperature {
-            body["temperature"] = serde_json::json!(temperature);
-        }
+        let body = self.chat_completions_body(&request)?;
 
-        if !request.tools.is_empty() {
-            let tools: Vec<serde_json::Value> = request
-                .tools
-                .iter()
-                .map(|t| {
-                    serde_json::json!({
-                        "type": "function",
-                        "function": {
-                            "name": t.name,
-                            "description": t.description,
-                            "parameters": t.parameters,
-                        }
-                    })
-                })
-                .collect();
-            body["tools"] = serde_json::json!(tools);
-        }
-
-        let response = self.llm_manager.http_client().post(endpoint);
+        let response = self
+            .llm_manager
+            .http_client_for(&self.provider)
+            .post(endpoint);
 
         let response = if let Some(api_key) = api_key {
             response.header("authorization", format!("Bearer {api_key}"))
@@ -730,17 +1170,23 @@ impl SpacebotModel {
             response
         };
 
-        let response = response
+        let limits = self.llm_manager.http_limits_for(&self.provider);
+        let request = response
             .header("content-type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| CompletionError::ProviderError(e.to_string()))?;
+            .json(&body);
+        let response = crate::llm::http::send(request, limits).await.map_err(|e| {
+            CompletionError::ProviderError(crate::logging::redact_secrets(&e.to_string()))
+        })?;
 
         let status = response.status();
-        let response_text = response.text().await.map_err(|e| {
-            CompletionError::ProviderError(format!("failed to read response body: {e}"))
-        })?;
+        let retry_after = routing::retry_after_header(response.headers());
+        let response_text = crate::llm::http::read_text(response, limits)
+            .await
+            .map_err(|e| {
+                CompletionError::ProviderError(crate::logging::redact_secrets(&format!(
+                    "failed to read response body: {e}"
+                )))
+            })?;
 
         let response_body: serde_json::Value =
             serde_json::from_str(&response_text).map_err(|e| {
@@ -754,8 +1200,9 @@ impl SpacebotModel {
             let message = response_body["error"]["message"]
                 .as_str()
                 .unwrap_or("unknown error");
-            return Err(CompletionError::ProviderError(format!(
-                "{provider_display_name} API error ({status}): {message}"
+            return Err(CompletionError::ProviderError(routing::with_retry_after(
+                format!("{provider_display_name} API error ({status}): {message}"),
+                retry_after,
             )));
         }
 
@@ -764,22 +1211,6 @@ impl SpacebotModel {
 }
 // --- Helpers ---
 
-fn normalize_ollama_base_url(configured: Option<String>) -> String {
-    let mut base_url = configured
-        .unwrap_or_else(|| "http://localhost:11434".to_string())
-        .trim()
-        .trim_end_matches('/')
-        .to_string();
-
-    if base_url.ends_with("/api") {
-        base_url.truncate(base_url.len() - "/api".len());
-    } else if base_url.ends_with("/v1") {
-        base_url.truncate(base_url.len() - "/v1".len());
-    }
-
-    base_url
-}
-
 /// Reverse-map Claude Code canonical tool names back to the original names
 /// from the request's tool definitions.
 fn reverse_map_tool_names(
@@ -842,6 +1273,17 @@ pub fn convert_messages_to_anthropic(messages: &OneOrMany<Message>) -> Vec<serde
                             "name": tc.function.name,
                             "input": tc.function.arguments,
                         })),
+                        // Only signed thinking can be sent back; reasoning
+                        // from other providers is left out.
+                        AssistantContent::Reasoning(reasoning) => {
+                            reasoning.signature.as_ref().map(|signature| {
+                                serde_json::json!({
+                                    "type": "thinking",
+                                    "thinking": reasoning.reasoning.join("\n"),
+                                    "signature": signature,
+                                })
+                            })
+                        }
                         _ => None,
                     })
                     .collect();
@@ -1133,6 +1575,7 @@ fn parse_anthropic_response(
         .ok_or_else(|| CompletionError::ResponseError("missing content array".into()))?;
 
     let mut assistant_content = Vec::new();
+    let mut thinking = Vec::new();
 
     for block in content_blocks {
         match block["type"].as_str() {
@@ -1149,9 +1592,13 @@ fn parse_anthropic_response(
                 )));
             }
             Some("thinking") => {
-                // Thinking blocks contain internal reasoning, not actionable output.
-                // We'll skip them but log for debugging.
-                tracing::debug!("skipping thinking block in Anthropic response");
+                // Kept as reasoning, with its signature, so the next turn of a
+                // tool loop can send it back as Anthropic requires.
+                let text = block["thinking"].as_str().unwrap_or("");
+                thinking.push(AssistantContent::Reasoning(
+                    rig::message::Reasoning::new(text)
+                        .with_signature(block["signature"].as_str().map(str::to_string)),
+                ));
             }
             _ => {
                 // Unknown block type - log but skip
@@ -1163,15 +1610,20 @@ fn parse_anthropic_response(
         }
     }
 
-    let choice = OneOrMany::many(assistant_content).map_err(|_| {
+    // Thinking alone, cut off before an answer, is still an empty response.
+    if assistant_content.is_empty() {
         tracing::debug!(
             stop_reason = body["stop_reason"].as_str().unwrap_or("unknown"),
             content_blocks = content_blocks.len(),
             raw_content = %body["content"],
             "empty assistant_content after parsing Anthropic response"
         );
-        CompletionError::ResponseError("empty response from Anthropic".into())
-    })?;
+        return Err(CompletionError::ResponseError(
+            "empty response from Anthropic".into(),
+        ));
+    }
+    let choice = OneOrMany::many(thinking.into_iter().chain(assistant_content))
+        .expect("assistant content isn't empty");
 
     let input_tokens = body["usage"]["input_tokens"].as_u64().unwrap_or(0);
     let output_tokens = body["usage"]["output_tokens"].as_u64().unwrap_or(0);
@@ -1199,26 +1651,41 @@ fn parse_openai_response(
 
     let mut assistant_content = Vec::new();
 
-    if let Some(text) = choice["content"].as_str() {
-        if !text.is_empty() {
-            assistant_content.push(AssistantContent::Text(Text {
-                text: text.to_string(),
-            }));
-        }
+    // Reasoning comes in `reasoning_content` (DeepSeek, Kimi) or inline in
+    // `<think>` tags (DeepSeek-R1 and QwQ on most hosts).
+    let (text, inline_reasoning) = choice["content"]
+        .as_str()
+        .map(reasoning::split_think_tags)
+        .unwrap_or_default();
+    let reasoning_text = choice["reasoning_content"]
+        .as_str()
+        .filter(|reasoning| !reasoning.is_empty())
+        .map(str::to_string)
+        .or(inline_reasoning);
+    let has_tool_calls = choice["tool_calls"]
+        .as_array()
+        .is_some_and(|tool_calls| !tool_calls.is_empty());
+
+    if !text.is_empty() {
+        assistant_content.push(AssistantContent::Text(Text { text }));
     }
 
-    // Some reasoning models (e.g., NVIDIA kimi-k2.5) return reasoning in a separate field
-    if assistant_content.is_empty() {
-        if let Some(reasoning) = choice["reasoning_content"].as_str() {
-            if !reasoning.is_empty() {
-                tracing::debug!(
-                    provider = %provider_label,
-                    "extracted reasoning_content as main content"
-                );
-                assistant_content.push(AssistantContent::Text(Text {
-                    text: reasoning.to_string(),
-                }));
-            }
+    if let Some(reasoning_text) = reasoning_text {
+        // Some reasoning models (e.g., NVIDIA kimi-k2.5) answer in the
+        // reasoning field alone
+        if assistant_content.is_empty() && !has_tool_calls {
+            tracing::debug!(
+                provider = %provider_label,
+                "extracted reasoning_content as main content"
+            );
+            assistant_content.push(AssistantContent::Text(Text {
+                text: reasoning_text,
+            }));
+        } else {
+            assistant_content.insert(
+                0,
+                AssistantContent::Reasoning(rig::message::Reasoning::new(&reasoning_text)),
+            );
         }
     }
 
@@ -1293,6 +1760,21 @@ fn parse_openai_responses_response(
                     }
                 }
             }
+            Some("reasoning") => {
+                let summary: Vec<String> = output_item["summary"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|part| part["text"].as_str())
+                    .map(str::to_string)
+                    .collect();
+                if !summary.is_empty() {
+                    assistant_content.push(AssistantContent::Reasoning(
+                        rig::message::Reasoning::multi(summary)
+                            .optional_id(output_item["id"].as_str().map(str::to_string)),
+                    ));
+                }
+            }
             Some("function_call") => {
                 let call_id = output_item["call_id"]
                     .as_str()
@@ -1313,9 +1795,16 @@ fn parse_openai_responses_response(
         }
     }
 
-    let choice = OneOrMany::many(assistant_content).map_err(|_| {
-        CompletionError::ResponseError("empty response from OpenAI Responses API".into())
-    })?;
+    // A reasoning summary alone is still an empty response.
+    let answered = assistant_content
+        .iter()
+        .any(|content| !matches!(content, AssistantContent::Reasoning(_)));
+    let choice = OneOrMany::many(assistant_content)
+        .ok()
+        .filter(|_| answered)
+        .ok_or_else(|| {
+            CompletionError::ResponseError("empty response from OpenAI Responses API".into())
+        })?;
 
     let input_tokens = body["usage"]["input_tokens"].as_u64().unwrap_or(0);
     let output_tokens = body["usage"]["output_tokens"].as_u64().unwrap_or(0);
@@ -1377,4 +1866,44 @@ mod tests {
             panic!("expected ToolCall");
         }
     }
+
+    #[test]
+    fn reasoning_is_kept_apart_from_the_answer() {
+        let anthropic = parse_anthropic_response(serde_json::json!({
+            "content": [
+                {"type": "thinking", "thinking": "Check the date.", "signature": "sig"},
+                {"type": "text", "text": "It's Tuesday."},
+            ],
+            "usage": {"input_tokens": 10, "output_tokens": 20},
+        }))
+        .expect("anthropic response");
+        let replayed = convert_messages_to_anthropic(&OneOrMany::one(Message::Assistant {
+            id: None,
+            content: anthropic.choice,
+        }));
+        assert_eq!(
+            replayed[0]["content"][0],
+            serde_json::json!({"type": "thinking", "thinking": "Check the date.", "signature": "sig"})
+        );
+        assert_eq!(replayed[0]["content"][1]["text"], "It's Tuesday.");
+
+        let thinking_only = parse_anthropic_response(serde_json::json!({
+            "content": [{"type": "thinking", "thinking": "Hmm", "signature": "sig"}],
+            "stop_reason": "max_tokens",
+        }));
+        assert!(thinking_only.is_err());
+
+        let deepseek = parse_openai_response(
+            serde_json::json!({"choices": [{"message": {
+                "content": "<think>Add them.</think>\n\n4",
+            }}]}),
+            "DeepSeek",
+        )
+        .expect("chat response");
+        let choice: Vec<_> = deepseek.choice.into_iter().collect();
+        assert!(
+            matches!(&choice[0], AssistantContent::Reasoning(reasoning) if reasoning.reasoning == ["Add them."])
+        );
+        assert!(matches!(&choice[1], AssistantContent::Text(text) if text.text == "4"));
+    }
 }
@@ -0,0 +1,950 @@
+//! Streaming completions.
+//!
+//! [`SpacebotModel::stream_completion`] asks the provider for server-sent
+//! events and translates each dialect (Anthropic Messages, Chat Completions,
+//! and the Responses API) into one [`StreamEvent`] sequence. Until the first
+//! byte arrives, a streamed call takes the same path as a whole one: budget
+//! routing, retries, the fallback chain, and rate-limit cooldowns. Once the
+//! provider starts answering, the stream is committed to that model, and a
+//! failure after that ends it with an error item.
+
+use super::{SpacebotModel, truncate_body};
+use crate::config::ApiType;
+use crate::llm::cooldown::CooldownPolicy;
+use crate::llm::routing::{self, MAX_FALLBACK_ATTEMPTS};
+use crate::llm::sampling::BestOfN;
+use crate::llm::structured::OutputFormat;
+
+use futures::StreamExt as _;
+use futures::stream::BoxStream;
+use rig::completion::{self, CompletionError, CompletionRequest};
+
+use std::collections::HashMap;
+
+/// One step of a streamed reply.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent {
+    /// More reply text.
+    Text(String),
+    /// A tool call starting. Its arguments follow as [`StreamEvent::ToolCallArguments`].
+    ToolCall {
+        index: usize,
+        id: String,
+        name: String,
+    },
+    /// More of a tool call's JSON arguments.
+    ToolCallArguments { index: usize, delta: String },
+    /// The reply is complete. Always the last event of a stream that didn't
+    /// fail.
+    Finished {
+        /// `stop`, `length`, `tool_calls`, or `content_filter`.
+        finish_reason: &'static str,
+        usage: completion::Usage,
+        /// Output tokens spent reasoning, included in `usage`.
+        reasoning_tokens: u64,
+    },
+}
+
+/// A streamed reply.
+pub struct CompletionStream {
+    /// The model answering, which budget routing or a fallback may have
+    /// changed.
+    pub model: String,
+    pub events: BoxStream<'static, Result<StreamEvent, CompletionError>>,
+}
+
+impl SpacebotModel {
+    /// Stream a completion. Spend and latency are recorded when the stream
+    /// finishes; a stream dropped before then isn't billed.
+    ///
+    /// Best-of-N sampling and confidence review need the whole reply, so they
+    /// don't apply, and structured output is asked for but not validated.
+    pub async fn stream_completion(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<CompletionStream, CompletionError> {
+        let start = std::time::Instant::now();
+        let result = self.route_stream(request, start).await;
+        if let Err(error) = &result {
+            crate::events::publish(crate::events::Event::CompletionFinished {
+                model: self.full_model_name.clone(),
+                duration_secs: start.elapsed().as_secs_f64(),
+                error: Some(error.to_string()),
+            });
+        }
+        result
+    }
+
+    /// Open a stream from the first model in the chain that starts one.
+    async fn route_stream(
+        &self,
+        request: CompletionRequest,
+        start: std::time::Instant,
+    ) -> Result<CompletionStream, CompletionError> {
+        if BestOfN::from_request(&request)?.is_some() {
+            return Err(CompletionError::RequestError(
+                "best-of-n sampling can't be streamed".into(),
+            ));
+        }
+
+        let fallbacks = self
+            .routing
+            .as_ref()
+            .map(|routing| routing.get_fallbacks(&self.full_model_name).to_vec())
+            .unwrap_or_default();
+        let chain = std::iter::once(self.full_model_name.clone())
+            .chain(fallbacks)
+            .collect();
+        let chain = self.budget_route(chain)?;
+        let cooldown = self.routing.as_ref().map(CooldownPolicy::from_routing);
+        let mut last_error: Option<CompletionError> = None;
+
+        for model_name in chain.iter().take(MAX_FALLBACK_ATTEMPTS + 1) {
+            if chain.len() > 1 && self.llm_manager.is_rate_limited(model_name).await {
+                tracing::debug!(model = %model_name, "model in rate-limit cooldown, skipping");
+                continue;
+            }
+
+            let opened = self
+                .retry_model(model_name, |model| {
+                    let request = request.clone();
+                    async move { model.open_stream(request).await }
+                })
+                .await;
+            match opened {
+                Ok(events) => {
+                    self.llm_manager.record_success(model_name);
+                    if model_name != &self.full_model_name {
+                        tracing::info!(
+                            original = %self.full_model_name,
+                            routed = %model_name,
+                            "streaming from another model in the chain"
+                        );
+                    }
+                    return Ok(CompletionStream {
+                        model: model_name.clone(),
+                        events: self.accounted(model_name.clone(), events, start),
+                    });
+                }
+                Err((error, was_rate_limit)) => {
+                    if !routing::is_retriable_error(&error.to_string()) {
+                        return Err(error);
+                    }
+                    if was_rate_limit && let Some(cooldown) = &cooldown {
+                        let retry_after = routing::retry_after_in(&error.to_string());
+                        self.llm_manager
+                            .record_rate_limit(model_name, cooldown, retry_after)
+                            .await;
+                    }
+                    tracing::warn!(
+                        model = %model_name,
+                        %error,
+                        "stream failed to start, continuing chain"
+                    );
+                    last_error = Some(error);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            CompletionError::ProviderError("all models in fallback chain failed".into())
+        }))
+    }
+
+    /// Record spend, latency, and the finished event as `events` ends.
+    fn accounted(
+        &self,
+        model_name: String,
+        events: BoxStream<'static, Result<StreamEvent, CompletionError>>,
+        start: std::time::Instant,
+    ) -> BoxStream<'static, Result<StreamEvent, CompletionError>> {
+        let llm_manager = self.llm_manager.clone();
+        let agent_id = self.agent_id.clone();
+        let requested = self.full_model_name.clone();
+        Box::pin(async_stream::stream! {
+            let mut events = events;
+            let mut error = None;
+            while let Some(event) = events.next().await {
+                match &event {
+                    Ok(StreamEvent::Finished {
+                        usage,
+                        reasoning_tokens,
+                        ..
+                    }) => {
+                        llm_manager.record_spend(&model_name, usage, *reasoning_tokens);
+                        if let Some(agent_id) = &agent_id {
+                            llm_manager.record_tenant_spend(
+                                agent_id,
+                                &model_name,
+                                usage,
+                                *reasoning_tokens,
+                            );
+                        }
+                    }
+                    Err(stream_error) => error = Some(stream_error.to_string()),
+                    Ok(_) => {}
+                }
+                yield event;
+            }
+
+            let elapsed = start.elapsed();
+            if error.is_none() {
+                llm_manager.record_latency(crate::llm::slo::COMPLETION, elapsed);
+            }
+            crate::events::publish(crate::events::Event::CompletionFinished {
+                model: requested,
+                duration_secs: elapsed.as_secs_f64(),
+                error,
+            });
+        })
+    }
+
+    /// Start a stream from this model's provider, failing if the provider
+    /// refuses it.
+    async fn open_stream(
+        &self,
+        request: CompletionRequest,
+    ) -> Result<BoxStream<'static, Result<StreamEvent, CompletionError>>, CompletionError> {
+        if let Some(fault) = self.llm_manager.inject_fault(&self.provider) {
+            tracing::debug!(model = %self.full_model_name, ?fault, "injecting provider fault");
+            return Err(fault.into_error(&self.provider));
+        }
+        let (provider_id, provider_config) = self.provider_target().await?;
+        let client = self.llm_manager.http_client_for(&self.provider);
+
+        let chat = |endpoint: String, api_key: Option<&str>| {
+            let mut body = self.chat_completions_body(&request)?;
+            body["stream"] = serde_json::json!(true);
+            body["stream_options"] = serde_json::json!({"include_usage": true});
+            let mut builder = client
+                .post(&endpoint)
+                .header("content-type", "application/json");
+            if let Some(api_key) = api_key {
+                builder = builder.header("authorization", format!("Bearer {api_key}"));
+            }
+            // Kimi endpoints require a specific user-agent header.
+            if endpoint.contains("kimi.com") || endpoint.contains("moonshot.ai") {
+                builder = builder.header("user-agent", "KimiCLI/1.3");
+            }
+            Ok::<_, CompletionError>(builder.json(&body))
+        };
+
+        let base_url = provider_config.base_url.trim_end_matches('/');
+        let (builder, dialect, display_name) =
+            if provider_id == "zai-coding-plan" || provider_id == "zhipu" {
+                let display_name = if provider_id == "zhipu" {
+                    "Z.AI (GLM)"
+                } else {
+                    "Z.AI Coding Plan"
+                };
+                let builder = chat(
+                    format!("{base_url}/chat/completions"),
+                    Some(provider_config.api_key.as_str()),
+                )?;
+                (builder, Dialect::Chat, display_name.to_string())
+            } else {
+                match provider_config.api_type {
+                    ApiType::Anthropic => {
+                        // No native JSON mode; ask for the format in the system prompt.
+                        let mut request = request.clone();
+                        if let Some(output_format) = OutputFormat::from_request(&request)? {
+                            output_format.apply_to_preamble(&mut request, provider_id)?;
+                        }
+                        let anthropic_request = crate::llm::anthropic::build_anthropic_request(
+                            client,
+                            &provider_config.api_key,
+                            &self.model_name,
+                            &request,
+                            &self.reasoning(),
+                            true,
+                        );
+                        let is_oauth = anthropic_request.auth_path
+                            == crate::llm::anthropic::AnthropicAuthPath::OAuthToken;
+                        let original_tools = if is_oauth {
+                            anthropic_request.original_tools
+                        } else {
+                            Vec::new()
+                        };
+                        (
+                            anthropic_request.builder,
+                            Dialect::Anthropic { original_tools },
+                            "Anthropic".to_string(),
+                        )
+                    }
+                    ApiType::OpenAiCompletions => {
+                        let mut builder = chat(
+                            format!("{base_url}/v1/chat/completions"),
+                            Some(provider_config.api_key.as_str()),
+                        )?;
+                        // A cold local model can take longer than the client default to answer.
+                        if self.provider == "ollama" {
+                            builder = builder.timeout(std::time::Duration::from_secs(
+                                self.llm_manager.ollama_config().load_timeout_secs,
+                            ));
+                        }
+                        (builder, Dialect::Chat, "OpenAI".to_string())
+                    }
+                    ApiType::OpenAiResponses => {
+                        let mut body = self.responses_body(&request)?;
+                        body["stream"] = serde_json::json!(true);
+                        let builder = client
+                            .post(format!("{base_url}/v1/responses"))
+                            .header(
+                                "authorization",
+                                format!("Bearer {}", provider_config.api_key),
+                            )
+                            .header("content-type", "application/json")
+                            .json(&body);
+                        (
+                            builder,
+                            Dialect::Responses,
+                            "OpenAI Responses API".to_string(),
+                        )
+                    }
+                    ApiType::OpenAiCompatible => {
+                        let display_name = provider_config.name.as_deref().unwrap_or(provider_id);
+                        let api_key =
+                            Some(provider_config.api_key.as_str()).filter(|key| !key.is_empty());
+                        let builder = chat(format!("{base_url}/v1/chat/completions"), api_key)?;
+                        (builder, Dialect::Chat, display_name.to_string())
+                    }
+                }
+            };
+
+        let limits = self.llm_manager.http_limits_for(&self.provider);
+        let response = crate::llm::http::send(builder, limits).await.map_err(|e| {
+            CompletionError::ProviderError(crate::logging::redact_secrets(&e.to_string()))
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = routing::retry_after_header(response.headers());
+            let response_text = crate::llm::http::read_text(response, limits)
+                .await
+                .unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&response_text)
+                .ok()
+                .and_then(|body| body["error"]["message"].as_str().map(str::to_string))
+                .unwrap_or_else(|| truncate_body(&response_text).to_string());
+            return Err(CompletionError::ProviderError(routing::with_retry_after(
+                format!("{display_name} API error ({status}): {message}"),
+                retry_after,
+            )));
+        }
+
+        let translator = Translator::new(dialect, display_name);
+        Ok(Box::pin(events(
+            response,
+            translator,
+            limits.max_response_bytes,
+        )))
+    }
+}
+
+/// The events in a provider's event stream, ending with
+/// [`StreamEvent::Finished`] or an error.
+fn events(
+    response: reqwest::Response,
+    mut translator: Translator,
+    max_bytes: usize,
+) -> impl futures::Stream<Item = Result<StreamEvent, CompletionError>> + Send + 'static {
+    async_stream::stream! {
+        let mut decoder = SseDecoder::default();
+        let mut chunks = response.bytes_stream();
+        let mut read = 0;
+        while !translator.done {
+            let chunk = match chunks.next().await {
+                Some(Ok(chunk)) => chunk,
+                Some(Err(error)) => {
+                    yield Err(CompletionError::ProviderError(crate::logging::redact_secrets(
+                        &format!("{} stream was interrupted: {error}", translator.provider),
+                    )));
+                    return;
+                }
+                None => break,
+            };
+            read += chunk.len();
+            if read > max_bytes {
+                yield Err(CompletionError::ProviderError(format!(
+                    "{} stream is over the {max_bytes}-byte limit",
+                    translator.provider
+                )));
+                return;
+            }
+            for frame in decoder.push(&chunk) {
+                match translator.translate(&frame) {
+                    Ok(events) => {
+                        for event in events {
+                            yield Ok(event);
+                        }
+                    }
+                    Err(error) => {
+                        yield Err(error);
+                        return;
+                    }
+                }
+            }
+        }
+        yield translator.finish();
+    }
+}
+
+/// One server-sent event.
+#[derive(Debug, Clone, PartialEq, Default)]
+struct SseFrame {
+    event: Option<String>,
+    data: String,
+}
+
+/// Splits a byte stream into server-sent events. Chunks can end anywhere,
+/// including mid-line or mid-character.
+#[derive(Debug, Default)]
+struct SseDecoder {
+    buffer: Vec<u8>,
+    frame: SseFrame,
+    has_data: bool,
+}
+
+impl SseDecoder {
+    /// Feed the next chunk, returning the events it completed.
+    fn push(&mut self, chunk: &[u8]) -> Vec<SseFrame> {
+        self.buffer.extend_from_slice(chunk);
+        let mut frames = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if self.has_data {
+                    frames.push(std::mem::take(&mut self.frame));
+                }
+                self.frame = SseFrame::default();
+                self.has_data = false;
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.frame.event = Some(value.to_string()),
+                "data" => {
+                    if self.has_data {
+                        self.frame.data.push('\n');
+                    }
+                    self.frame.data.push_str(value);
+                    self.has_data = true;
+                }
+                // Comments (`: ping`), ids, and retry hints.
+                _ => {}
+            }
+        }
+        frames
+    }
+}
+
+/// The event stream format a provider answers in.
+#[derive(Debug, Clone, PartialEq)]
+enum Dialect {
+    /// Chat Completions chunks, ending with `data: [DONE]`.
+    Chat,
+    /// Anthropic Messages events. With an OAuth token, tool names come back
+    /// in Claude Code's form and are mapped to the `original_tools`.
+    Anthropic {
+        original_tools: Vec<(String, String)>,
+    },
+    /// Responses API events.
+    Responses,
+}
+
+/// Turns one dialect's events into [`StreamEvent`]s, keeping what's needed
+/// to finish the stream.
+#[derive(Debug)]
+struct Translator {
+    dialect: Dialect,
+    /// Provider name for error messages.
+    provider: String,
+    usage: completion::Usage,
+    /// Reasoning tokens as reported, or thinking text seen, for estimating
+    /// them when they aren't.
+    reasoning_tokens: Option<u64>,
+    thinking_chars: usize,
+    finish_reason: Option<&'static str>,
+    /// Tool call indexes by the provider's block or output index.
+    tool_indexes: HashMap<u64, usize>,
+    /// Set by the provider's last event; nothing after it matters.
+    done: bool,
+}
+
+impl Translator {
+    fn new(dialect: Dialect, provider: impl Into<String>) -> Self {
+        Self {
+            dialect,
+            provider: provider.into(),
+            usage: completion::Usage::new(),
+            reasoning_tokens: None,
+            thinking_chars: 0,
+            finish_reason: None,
+            tool_indexes: HashMap::new(),
+            done: false,
+        }
+    }
+
+    fn translate(&mut self, frame: &SseFrame) -> Result<Vec<StreamEvent>, CompletionError> {
+        if self.dialect == Dialect::Chat && frame.data.trim() == "[DONE]" {
+            self.done = true;
+            return Ok(Vec::new());
+        }
+        let data: serde_json::Value = serde_json::from_str(&frame.data).map_err(|error| {
+            CompletionError::ProviderError(format!(
+                "{} sent an event that isn't valid JSON: {error}\nEvent: {}",
+                self.provider,
+                truncate_body(&frame.data)
+            ))
+        })?;
+        // Chat and Responses streams report failures in the data; Anthropic
+        // sends an `error` event.
+        if let Some(message) = data["error"]["message"].as_str() {
+            return Err(CompletionError::ProviderError(format!(
+                "{} stream error: {message}",
+                self.provider
+            )));
+        }
+        match self.dialect {
+            Dialect::Chat => Ok(self.chat(&data)),
+            Dialect::Anthropic { .. } => Ok(self.anthropic(&data)),
+            Dialect::Responses => self.responses(&data),
+        }
+    }
+
+    fn chat(&mut self, data: &serde_json::Value) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        let choice = &data["choices"][0];
+        let delta = &choice["delta"];
+        if let Some(text) = delta["content"].as_str().filter(|text| !text.is_empty()) {
+            events.push(StreamEvent::Text(text.to_string()));
+        }
+        for call in delta["tool_calls"].as_array().into_iter().flatten() {
+            let index = self.tool_index(call["index"].as_u64().unwrap_or(0));
+            if let Some(name) = call["function"]["name"].as_str() {
+                events.push(StreamEvent::ToolCall {
+                    index,
+                    id: call["id"].as_str().unwrap_or_default().to_string(),
+                    name: name.trim().to_string(),
+                });
+            }
+            if let Some(delta) = call["function"]["arguments"]
+                .as_str()
+                .filter(|delta| !delta.is_empty())
+            {
+                events.push(StreamEvent::ToolCallArguments {
+                    index,
+                    delta: delta.to_string(),
+                });
+            }
+        }
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = Some(match reason {
+                "length" => "length",
+                "tool_calls" | "function_call" => "tool_calls",
+                "content_filter" => "content_filter",
+                _ => "stop",
+            });
+        }
+        // With `include_usage`, the last chunk has usage and no choices.
+        let usage = &data["usage"];
+        if usage.is_object() {
+            self.usage.input_tokens = usage["prompt_tokens"].as_u64().unwrap_or(0);
+            self.usage.output_tokens = usage["completion_tokens"].as_u64().unwrap_or(0);
+            self.usage.cached_input_tokens = usage["prompt_tokens_details"]["cached_tokens"]
+                .as_u64()
+                .unwrap_or(0);
+            self.reasoning_tokens = usage["completion_tokens_details"]["reasoning_tokens"].as_u64();
+        }
+        events
+    }
+
+    fn anthropic(&mut self, data: &serde_json::Value) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        match data["type"].as_str().unwrap_or_default() {
+            "message_start" => {
+                let usage = &data["message"]["usage"];
+                self.usage.input_tokens = usage["input_tokens"].as_u64().unwrap_or(0);
+                self.usage.cached_input_tokens =
+                    usage["cache_read_input_tokens"].as_u64().unwrap_or(0);
+                self.usage.output_tokens = usage["output_tokens"].as_u64().unwrap_or(0);
+            }
+            "content_block_start" => {
+                let block = &data["content_block"];
+                match block["type"].as_str() {
+                    Some("tool_use") => {
+                        let index = self.tool_index(data["index"].as_u64().unwrap_or(0));
+                        let name = block["name"].as_str().unwrap_or_default();
+                        let name = match &self.dialect {
+                            Dialect::Anthropic { original_tools } if !original_tools.is_empty() => {
+                                crate::llm::anthropic::from_claude_code_name(name, original_tools)
+                            }
+                            _ => name.to_string(),
+                        };
+                        events.push(StreamEvent::ToolCall {
+                            index,
+                            id: block["id"].as_str().unwrap_or_default().to_string(),
+                            name: name.trim().to_string(),
+                        });
+                    }
+                    Some("text") => {
+                        if let Some(text) = block["text"].as_str().filter(|text| !text.is_empty()) {
+                            events.push(StreamEvent::Text(text.to_string()));
+                        }
+                    }
+                    // Thinking is internal reasoning, not output.
+                    _ => {}
+                }
+            }
+            "content_block_delta" => {
+                let delta = &data["delta"];
+                match delta["type"].as_str() {
+                    Some("text_delta") => {
+                        if let Some(text) = delta["text"].as_str().filter(|text| !text.is_empty()) {
+                            events.push(StreamEvent::Text(text.to_string()));
+                        }
+                    }
+                    Some("thinking_delta") => {
+                        self.thinking_chars += delta["thinking"].as_str().map_or(0, str::len);
+                    }
+                    Some("input_json_delta") => {
+                        let block = data["index"].as_u64().unwrap_or(0);
+                        if let (Some(&index), Some(partial)) = (
+                            self.tool_indexes.get(&block),
+                            delta["partial_json"].as_str(),
+                        ) && !partial.is_empty()
+                        {
+                            events.push(StreamEvent::ToolCallArguments {
+                                index,
+                                delta: partial.to_string(),
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            "message_delta" => {
+                if let Some(reason) = data["delta"]["stop_reason"].as_str() {
+                    self.finish_reason = Some(match reason {
+                        "max_tokens" => "length",
+                        "tool_use" => "tool_calls",
+                        "refusal" => "content_filter",
+                        _ => "stop",
+                    });
+                }
+                // Output tokens here are the running total.
+                if let Some(output_tokens) = data["usage"]["output_tokens"].as_u64() {
+                    self.usage.output_tokens = output_tokens;
+                }
+            }
+            "message_stop" => self.done = true,
+            _ => {}
+        }
+        events
+    }
+
+    fn responses(&mut self, data: &serde_json::Value) -> Result<Vec<StreamEvent>, CompletionError> {
+        let mut events = Vec::new();
+        match data["type"].as_str().unwrap_or_default() {
+            "response.output_text.delta" => {
+                if let Some(text) = data["delta"].as_str().filter(|text| !text.is_empty()) {
+                    events.push(StreamEvent::Text(text.to_string()));
+                }
+            }
+            "response.output_item.added" if data["item"]["type"] == "function_call" => {
+                let item = &data["item"];
+                let index = self.tool_index(data["output_index"].as_u64().unwrap_or(0));
+                events.push(StreamEvent::ToolCall {
+                    index,
+                    id: item["call_id"]
+                        .as_str()
+                        .or_else(|| item["id"].as_str())
+                        .unwrap_or_default()
+                        .to_string(),
+                    name: item["name"].as_str().unwrap_or_default().trim().to_string(),
+                });
+            }
+            "response.function_call_arguments.delta" => {
+                let output = data["output_index"].as_u64().unwrap_or(0);
+                if let (Some(&index), Some(delta)) =
+                    (self.tool_indexes.get(&output), data["delta"].as_str())
+                    && !delta.is_empty()
+                {
+                    events.push(StreamEvent::ToolCallArguments {
+                        index,
+                        delta: delta.to_string(),
+                    });
+                }
+            }
+            "response.completed" | "response.incomplete" => {
+                let response = &data["response"];
+                let usage = &response["usage"];
+                self.usage.input_tokens = usage["input_tokens"].as_u64().unwrap_or(0);
+                self.usage.output_tokens = usage["output_tokens"].as_u64().unwrap_or(0);
+                self.usage.cached_input_tokens = usage["input_tokens_details"]["cached_tokens"]
+                    .as_u64()
+                    .unwrap_or(0);
+                self.reasoning_tokens = usage["output_tokens_details"]["reasoning_tokens"].as_u64();
+                self.finish_reason =
+                    Some(match response["incomplete_details"]["reason"].as_str() {
+                        Some("max_output_tokens") => "length",
+                        Some("content_filter") => "content_filter",
+                        _ if !self.tool_indexes.is_empty() => "tool_calls",
+                        _ => "stop",
+                    });
+                self.done = true;
+            }
+            "response.failed" => {
+                let message = data["response"]["error"]["message"]
+                    .as_str()
+                    .unwrap_or("unknown error");
+                return Err(CompletionError::ProviderError(format!(
+                    "{} stream error: {message}",
+                    self.provider
+                )));
+            }
+            _ => {}
+        }
+        Ok(events)
+    }
+
+    /// Our index for the provider's tool call `key`, numbering calls in the
+    /// order they start.
+    fn tool_index(&mut self, key: u64) -> usize {
+        let next = self.tool_indexes.len();
+        *self.tool_indexes.entry(key).or_insert(next)
+    }
+
+    /// The final event: [`StreamEvent::Finished`] if the provider finished
+    /// the reply, or an error if the stream ended early.
+    fn finish(self) -> Result<StreamEvent, CompletionError> {
+        let finish_reason = match (self.finish_reason, self.done) {
+            (Some(finish_reason), _) => finish_reason,
+            // A Chat stream can end with `[DONE]` alone.
+            (None, true) if self.dialect == Dialect::Chat => {
+                if self.tool_indexes.is_empty() {
+                    "stop"
+                } else {
+                    "tool_calls"
+                }
+            }
+            _ => {
+                return Err(CompletionError::ProviderError(format!(
+                    "{} stream ended before the reply finished",
+                    self.provider
+                )));
+            }
+        };
+        let mut usage = self.usage;
+        usage.total_tokens = usage.input_tokens + usage.output_tokens;
+        let reasoning_tokens = self
+            .reasoning_tokens
+            .unwrap_or_else(|| (self.thinking_chars as u64).div_ceil(4))
+            .min(usage.output_tokens);
+        Ok(StreamEvent::Finished {
+            finish_reason,
+            usage,
+            reasoning_tokens,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(translator: &mut Translator, stream: &str) -> Vec<StreamEvent> {
+        let mut decoder = SseDecoder::default();
+        decoder
+            .push(stream.as_bytes())
+            .iter()
+            .flat_map(|frame| translator.translate(frame).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sse_frames_survive_arbitrary_chunking() {
+        let stream =
+            "event: message_start\r\ndata: {\"a\":\n: ping\ndata: \"é\"}\n\nid: 3\ndata:[DONE]\n\n";
+        let mut whole = SseDecoder::default();
+        let expected = whole.push(stream.as_bytes());
+        assert_eq!(
+            expected,
+            vec![
+                SseFrame {
+                    event: Some("message_start".into()),
+                    data: "{\"a\":\n\"é\"}".into(),
+                },
+                SseFrame {
+                    event: None,
+                    data: "[DONE]".into(),
+                },
+            ]
+        );
+
+        // One byte at a time, splitting the two-byte character.
+        let mut bytewise = SseDecoder::default();
+        let frames: Vec<SseFrame> = stream
+            .as_bytes()
+            .chunks(1)
+            .flat_map(|byte| bytewise.push(byte))
+            .collect();
+        assert_eq!(frames, expected);
+    }
+
+    #[test]
+    fn chat_chunks_translate_with_usage() {
+        let mut translator = Translator::new(Dialect::Chat, "OpenAI");
+        let events = translate(
+            &mut translator,
+            concat!(
+                "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":\"Hi\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"function\":{\"name\":\"weather\",\"arguments\":\"\"}}]}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"city\\\":\"}}]}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n",
+                "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":10,\"completion_tokens\":4,\"prompt_tokens_details\":{\"cached_tokens\":6}}}\n\n",
+                "data: [DONE]\n\n",
+            ),
+        );
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Text("Hi".into()),
+                StreamEvent::ToolCall {
+                    index: 0,
+                    id: "call_1".into(),
+                    name: "weather".into(),
+                },
+                StreamEvent::ToolCallArguments {
+                    index: 0,
+                    delta: "{\"city\":".into(),
+                },
+            ]
+        );
+        assert!(translator.done);
+        let StreamEvent::Finished {
+            finish_reason,
+            usage,
+            reasoning_tokens,
+        } = translator.finish().unwrap()
+        else {
+            panic!("expected the finished event");
+        };
+        assert_eq!(finish_reason, "tool_calls");
+        assert_eq!(usage.total_tokens, 14);
+        assert_eq!(usage.cached_input_tokens, 6);
+        assert_eq!(reasoning_tokens, 0);
+    }
+
+    #[test]
+    fn anthropic_events_translate_with_usage() {
+        let mut translator = Translator::new(
+            Dialect::Anthropic {
+                original_tools: Vec::new(),
+            },
+            "Anthropic",
+        );
+        let events = translate(
+            &mut translator,
+            concat!(
+                "event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":20,\"cache_read_input_tokens\":15,\"output_tokens\":1}}}\n\n",
+                "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":0,\"content_block\":{\"type\":\"thinking\",\"thinking\":\"\"}}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"thinking_delta\",\"thinking\":\"Hmm\"}}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":1,\"delta\":{\"type\":\"text_delta\",\"text\":\"Checking.\"}}\n\n",
+                "event: content_block_start\ndata: {\"type\":\"content_block_start\",\"index\":2,\"content_block\":{\"type\":\"tool_use\",\"id\":\"toolu_1\",\"name\":\"weather\",\"input\":{}}}\n\n",
+                "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":2,\"delta\":{\"type\":\"input_json_delta\",\"partial_json\":\"{}\"}}\n\n",
+                "event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"tool_use\"},\"usage\":{\"output_tokens\":9}}\n\n",
+                "event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+            ),
+        );
+        assert_eq!(
+            events,
+            vec![
+                StreamEvent::Text("Checking.".into()),
+                StreamEvent::ToolCall {
+                    index: 0,
+                    id: "toolu_1".into(),
+                    name: "weather".into(),
+                },
+                StreamEvent::ToolCallArguments {
+                    index: 0,
+                    delta: "{}".into(),
+                },
+            ]
+        );
+        assert!(translator.done);
+        let StreamEvent::Finished {
+            finish_reason,
+            usage,
+            reasoning_tokens,
+        } = translator.finish().unwrap()
+        else {
+            panic!("expected the finished event");
+        };
+        assert_eq!(finish_reason, "tool_calls");
+        assert_eq!((usage.input_tokens, usage.output_tokens), (20, 9));
+        assert_eq!(usage.cached_input_tokens, 15);
+        // Estimated from the thinking seen, since Anthropic doesn't report it.
+        assert_eq!(reasoning_tokens, 1);
+    }
+
+    #[test]
+    fn responses_events_translate_with_usage() {
+        let mut translator = Translator::new(Dialect::Responses, "OpenAI Responses API");
+        let events = translate(
+            &mut translator,
+            concat!(
+                "event: response.output_text.delta\ndata: {\"type\":\"response.output_text.delta\",\"output_index\":0,\"delta\":\"Sure\"}\n\n",
+                "event: response.completed\ndata: {\"type\":\"response.completed\",\"response\":{\"usage\":{\"input_tokens\":7,\"output_tokens\":2,\"output_tokens_details\":{\"reasoning_tokens\":1}}}}\n\n",
+            ),
+        );
+        assert_eq!(events, vec![StreamEvent::Text("Sure".into())]);
+        assert!(matches!(
+            translator.finish(),
+            Ok(StreamEvent::Finished {
+                finish_reason: "stop",
+                reasoning_tokens: 1,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn failures_and_early_ends_are_errors() {
+        let mut translator = Translator::new(
+            Dialect::Anthropic {
+                original_tools: Vec::new(),
+            },
+            "Anthropic",
+        );
+        let frame = SseFrame {
+            event: Some("error".into()),
+            data: r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+                .into(),
+        };
+        let error = translator.translate(&frame).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Anthropic stream error: Overloaded")
+        );
+
+        let mut translator = Translator::new(Dialect::Chat, "OpenAI");
+        translate(
+            &mut translator,
+            "data: {\"choices\":[{\"delta\":{\"content\":\"Hal\"}}]}\n\n",
+        );
+        assert!(translator.finish().is_err());
+
+        let mut translator = Translator::new(Dialect::Chat, "OpenAI");
+        let frame = SseFrame {
+            event: None,
+            data: "{not json".into(),
+        };
+        assert!(translator.translate(&frame).is_err());
+    }
+}
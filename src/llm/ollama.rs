@@ -0,0 +1,547 @@
+//! Ollama-specific model lifecycle: pre-pull, warm-up, keepalive, and library
+//! management (list, pull, delete).
+//!
+//! Local models can take minutes to load (a 30GB model won't fit in the HTTP
+//! client's default timeout), so configured models are pulled and loaded at
+//! startup, then pinged periodically so Ollama doesn't evict them. Requests
+//! for a model that is still loading wait for it instead of timing out.
+
+use crate::llm::manager::LlmManager;
+
+use futures::StreamExt as _;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Ollama lifecycle configuration (instance-level, under `[llm.ollama]`).
+#[derive(Debug, Clone)]
+pub struct OllamaConfig {
+    /// Models to pull (if missing) and load at startup, e.g. "llama3.1:70b".
+    pub warm_models: Vec<String>,
+    /// How long Ollama keeps a model loaded after each request (Ollama duration
+    /// string, e.g. "30m", or "-1" to never unload).
+    pub keep_alive: String,
+    /// How often to ping warm models so they stay loaded. 0 disables keepalive.
+    pub keepalive_interval_secs: u64,
+    /// Pull warm models that aren't present locally.
+    pub pull_missing: bool,
+    /// How long a request waits for a loading model, and the HTTP timeout for
+    /// Ollama calls.
+    pub load_timeout_secs: u64,
+    /// Free VRAM (MiB, summed across GPUs) required before loading a model
+    /// that isn't already resident. 0 disables the check.
+    pub min_free_vram_mib: u64,
+    /// How long a request queues for VRAM before it is rejected.
+    pub vram_queue_timeout_secs: u64,
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            warm_models: Vec::new(),
+            keep_alive: "30m".into(),
+            keepalive_interval_secs: 240,
+            pull_missing: true,
+            load_timeout_secs: 1800,
+            min_free_vram_mib: 2048,
+            vram_queue_timeout_secs: 120,
+        }
+    }
+}
+
+/// Where a local model is in its lifecycle.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ModelLoadState {
+    /// Downloading layers. `percent` is `None` until Ollama reports sizes.
+    Pulling {
+        percent: Option<u8>,
+    },
+    /// Loading weights into memory.
+    Loading,
+    Ready,
+    Failed {
+        error: String,
+    },
+}
+
+impl ModelLoadState {
+    fn is_pending(&self) -> bool {
+        matches!(self, Self::Pulling { .. } | Self::Loading)
+    }
+}
+
+/// Shared load state for Ollama models, keyed by normalized model name.
+#[derive(Debug)]
+pub struct OllamaModelStates {
+    states: watch::Sender<HashMap<String, ModelLoadState>>,
+}
+
+impl Default for OllamaModelStates {
+    fn default() -> Self {
+        Self {
+            states: watch::channel(HashMap::new()).0,
+        }
+    }
+}
+
+impl OllamaModelStates {
+    pub fn set(&self, model: &str, state: ModelLoadState) {
+        let model = normalize_model_name(model);
+        self.states.send_modify(|states| {
+            states.insert(model, state);
+        });
+    }
+
+    pub fn remove(&self, model: &str) {
+        let model = normalize_model_name(model);
+        self.states.send_modify(|states| {
+            states.remove(&model);
+        });
+    }
+
+    pub fn get(&self, model: &str) -> Option<ModelLoadState> {
+        self.states
+            .borrow()
+            .get(&normalize_model_name(model))
+            .cloned()
+    }
+
+    pub fn snapshot(&self) -> HashMap<String, ModelLoadState> {
+        self.states.borrow().clone()
+    }
+
+    /// Wait until a model is no longer pulling or loading.
+    ///
+    /// Returns immediately for models that aren't tracked. Logs progress while
+    /// waiting so a slow first request is visible rather than silent.
+    pub async fn wait_until_ready(
+        &self,
+        model: &str,
+        timeout: Duration,
+    ) -> std::result::Result<(), String> {
+        let model = normalize_model_name(model);
+        let mut receiver = self.states.subscribe();
+
+        let wait = async {
+            loop {
+                let state = receiver.borrow_and_update().get(&model).cloned();
+                match state {
+                    Some(state) if state.is_pending() => {
+                        tracing::info!(model = %model, ?state, "waiting for Ollama model to load");
+                    }
+                    Some(ModelLoadState::Failed { error }) => return Err(error),
+                    _ => return Ok(()),
+                }
+                if receiver.changed().await.is_err() {
+                    return Ok(());
+                }
+            }
+        };
+
+        tokio::time::timeout(timeout, wait).await.map_err(|_| {
+            format!(
+                "timed out after {}s waiting for {model} to load",
+                timeout.as_secs()
+            )
+        })?
+    }
+}
+
+/// Normalize a model name for comparison: strip the "ollama/" routing prefix
+/// and add Ollama's implicit ":latest" tag.
+pub fn normalize_model_name(model: &str) -> String {
+    let model = model.strip_prefix("ollama/").unwrap_or(model);
+    if model.contains(':') {
+        model.to_string()
+    } else {
+        format!("{model}:latest")
+    }
+}
+
+/// A model in the local Ollama library.
+#[derive(Debug, Clone, Serialize)]
+pub struct LocalModel {
+    pub name: String,
+    /// Size on disk.
+    pub size_bytes: u64,
+    pub modified_at: Option<String>,
+}
+
+/// Models present in the local Ollama library.
+pub async fn list_local_models(
+    http_client: &reqwest::Client,
+    base_url: &str,
+) -> anyhow::Result<Vec<LocalModel>> {
+    let body: serde_json::Value = http_client
+        .get(format!("{base_url}/api/tags"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(body["models"]
+        .as_array()
+        .map(|models| models.iter().filter_map(parse_local_model).collect())
+        .unwrap_or_default())
+}
+
+fn parse_local_model(model: &serde_json::Value) -> Option<LocalModel> {
+    Some(LocalModel {
+        name: model["name"].as_str()?.to_string(),
+        size_bytes: model["size"].as_u64().unwrap_or(0),
+        modified_at: model["modified_at"].as_str().map(str::to_string),
+    })
+}
+
+/// Names of models currently loaded in memory.
+pub async fn list_running_models(
+    http_client: &reqwest::Client,
+    base_url: &str,
+) -> anyhow::Result<Vec<String>> {
+    let body: serde_json::Value = http_client
+        .get(format!("{base_url}/api/ps"))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    Ok(body["models"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|model| model["name"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Delete a model from the local Ollama library.
+pub async fn delete_model(
+    http_client: &reqwest::Client,
+    base_url: &str,
+    model: &str,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let response = http_client
+        .delete(format!("{base_url}/api/delete"))
+        .json(&serde_json::json!({ "model": model }))
+        .timeout(timeout)
+        .send()
+        .await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        anyhow::bail!("model {model} is not installed");
+    }
+    response.error_for_status()?;
+    Ok(())
+}
+
+/// Human-readable size, e.g. "4.7 GB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Pull a model, reporting download progress through `on_progress`.
+pub async fn pull_model(
+    http_client: &reqwest::Client,
+    base_url: &str,
+    model: &str,
+    mut on_progress: impl FnMut(Option<u8>),
+) -> anyhow::Result<()> {
+    let response = http_client
+        .post(format!("{base_url}/api/pull"))
+        .json(&serde_json::json!({ "model": model, "stream": true }))
+        // Pulls can run for a long time; don't inherit the client timeout.
+        .timeout(Duration::from_secs(6 * 60 * 60))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    // Progress arrives as newline-delimited JSON objects.
+    let mut stream = response.bytes_stream();
+    let mut buffer = Vec::new();
+    let mut last_percent = None;
+    while let Some(chunk) = stream.next().await {
+        buffer.extend_from_slice(&chunk?);
+        while let Some(newline) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            let Ok(event) = serde_json::from_slice::<serde_json::Value>(&line) else {
+                continue;
+            };
+            if let Some(error) = event["error"].as_str() {
+                anyhow::bail!("ollama pull failed: {error}");
+            }
+            let percent = match (event["completed"].as_u64(), event["total"].as_u64()) {
+                (Some(completed), Some(total)) if total > 0 => {
+                    Some((completed.saturating_mul(100) / total).min(100) as u8)
+                }
+                _ => None,
+            };
+            if percent.is_some() && percent != last_percent {
+                last_percent = percent;
+                on_progress(percent);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Load a model (or refresh its keep-alive) with an empty generate request.
+pub async fn load_model(
+    http_client: &reqwest::Client,
+    base_url: &str,
+    model: &str,
+    keep_alive: &str,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    http_client
+        .post(format!("{base_url}/api/generate"))
+        .json(&serde_json::json!({ "model": model, "keep_alive": keep_alive }))
+        .timeout(timeout)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Pull, load, and keep configured Ollama models warm.
+///
+/// Holds only a weak reference to the manager so the task ends when the
+/// manager is replaced. Config is re-read each cycle so hot reloads apply.
+pub fn spawn_warmup(llm_manager: &Arc<LlmManager>) -> tokio::task::JoinHandle<()> {
+    let llm_manager = Arc::downgrade(llm_manager);
+    tokio::spawn(async move {
+        let Some(base_url) = upgrade(&llm_manager).and_then(|manager| manager.ollama_base_url())
+        else {
+            return;
+        };
+        let base_url = crate::config::normalize_ollama_base_url(Some(base_url));
+
+        if let Some(manager) = upgrade(&llm_manager) {
+            warm_all(&manager, &base_url).await;
+        }
+
+        loop {
+            let interval = match upgrade(&llm_manager) {
+                Some(manager) => manager.ollama_config().keepalive_interval_secs,
+                None => return,
+            };
+            if interval == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+
+            let Some(manager) = upgrade(&llm_manager) else {
+                return;
+            };
+            let config = manager.ollama_config();
+            for model in &config.warm_models {
+                if manager.ollama_states().get(model) != Some(ModelLoadState::Ready) {
+                    continue;
+                }
+                let model_name = model.strip_prefix("ollama/").unwrap_or(model);
+                if let Err(error) = load_model(
+                    manager.http_client(),
+                    &base_url,
+                    model_name,
+                    &config.keep_alive,
+                    Duration::from_secs(config.load_timeout_secs),
+                )
+                .await
+                {
+                    tracing::warn!(model = %model_name, %error, "Ollama keepalive failed");
+                }
+            }
+        }
+    })
+}
+
+fn upgrade(llm_manager: &Weak<LlmManager>) -> Option<Arc<LlmManager>> {
+    llm_manager.upgrade()
+}
+
+async fn warm_all(manager: &LlmManager, base_url: &str) {
+    let config = manager.ollama_config();
+    if config.warm_models.is_empty() {
+        return;
+    }
+
+    let local_models = match list_local_models(manager.http_client(), base_url).await {
+        Ok(models) => models,
+        Err(error) => {
+            tracing::warn!(%error, "can't reach Ollama to warm models");
+            return;
+        }
+    };
+    let states = manager.ollama_states();
+
+    for model in &config.warm_models {
+        let model_name = model.strip_prefix("ollama/").unwrap_or(model);
+        let present = local_models
+            .iter()
+            .any(|local| normalize_model_name(&local.name) == normalize_model_name(model_name));
+
+        if !present {
+            if !config.pull_missing {
+                tracing::warn!(model = %model_name, "Ollama model not present and pull_missing is off");
+                states.set(
+                    model_name,
+                    ModelLoadState::Failed {
+                        error: "model not pulled".into(),
+                    },
+                );
+                continue;
+            }
+            tracing::info!(model = %model_name, "pulling Ollama model");
+            states.set(model_name, ModelLoadState::Pulling { percent: None });
+            let result = pull_model(manager.http_client(), base_url, model_name, |percent| {
+                if let Some(percent) = percent
+                    && percent % 10 == 0
+                {
+                    tracing::info!(model = %model_name, percent, "Ollama pull progress");
+                }
+                states.set(model_name, ModelLoadState::Pulling { percent });
+            })
+            .await;
+            if let Err(error) = result {
+                tracing::error!(model = %model_name, %error, "failed to pull Ollama model");
+                states.set(
+                    model_name,
+                    ModelLoadState::Failed {
+                        error: error.to_string(),
+                    },
+                );
+                continue;
+            }
+        }
+
+        tracing::info!(model = %model_name, "loading Ollama model");
+        states.set(model_name, ModelLoadState::Loading);
+        let started = std::time::Instant::now();
+        match load_model(
+            manager.http_client(),
+            base_url,
+            model_name,
+            &config.keep_alive,
+            Duration::from_secs(config.load_timeout_secs),
+        )
+        .await
+        {
+            Ok(()) => {
+                tracing::info!(
+                    model = %model_name,
+                    elapsed_secs = started.elapsed().as_secs(),
+                    "Ollama model loaded"
+                );
+                states.set(model_name, ModelLoadState::Ready);
+            }
+            Err(error) => {
+                tracing::error!(model = %model_name, %error, "failed to load Ollama model");
+                states.set(
+                    model_name,
+                    ModelLoadState::Failed {
+                        error: error.to_string(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_names_normalize_prefix_and_tag() {
+        assert_eq!(normalize_model_name("ollama/llama3"), "llama3:latest");
+        assert_eq!(normalize_model_name("qwen2.5:32b"), "qwen2.5:32b");
+    }
+
+    #[test]
+    fn sizes_format_in_decimal_units() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(4_661_224_676), "4.7 GB");
+        assert_eq!(format_size(0), "0 B");
+    }
+
+    #[test]
+    fn local_models_parse_from_tags_response() {
+        let model = parse_local_model(&serde_json::json!({
+            "name": "llama3.1:8b",
+            "size": 4_661_224_676u64,
+            "modified_at": "2024-08-01T10:00:00Z",
+        }))
+        .expect("model should parse");
+        assert_eq!(model.name, "llama3.1:8b");
+        assert_eq!(model.size_bytes, 4_661_224_676);
+        assert!(parse_local_model(&serde_json::json!({ "size": 1 })).is_none());
+    }
+
+    #[tokio::test]
+    async fn requests_wait_for_loading_models() {
+        let states = Arc::new(OllamaModelStates::default());
+        states.set("llama3", ModelLoadState::Loading);
+
+        let waiter = {
+            let states = states.clone();
+            tokio::spawn(async move {
+                states
+                    .wait_until_ready("ollama/llama3", Duration::from_secs(5))
+                    .await
+            })
+        };
+        tokio::task::yield_now().await;
+        states.set("llama3", ModelLoadState::Ready);
+
+        assert_eq!(waiter.await.unwrap(), Ok(()));
+        assert_eq!(
+            states
+                .wait_until_ready("untracked", Duration::from_millis(10))
+                .await,
+            Ok(())
+        );
+    }
+
+    #[tokio::test]
+    async fn waiting_times_out_and_reports_failures() {
+        let states = OllamaModelStates::default();
+        states.set("big", ModelLoadState::Loading);
+        assert!(
+            states
+                .wait_until_ready("big", Duration::from_millis(20))
+                .await
+                .is_err()
+        );
+
+        states.set(
+            "broken",
+            ModelLoadState::Failed {
+                error: "out of memory".into(),
+            },
+        );
+        assert_eq!(
+            states
+                .wait_until_ready("broken", Duration::from_secs(1))
+                .await,
+            Err("out of memory".to_string())
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Self-hosted servers speaking the OpenAI Chat Completions API (vLLM, LM
+//! Studio, llama.cpp server), configured as `openai_compatible` providers.
+//!
+//! These servers serve whatever models they were started with, so the model
+//! list comes from the server's `/v1/models` rather than a catalog. Most run
+//! without a key; when one is set it's sent as a bearer token.
+
+use crate::config::ProviderConfig;
+
+/// IDs of the models an OpenAI-style server serves, from `/v1/models`.
+pub async fn list_models(
+    http_client: &reqwest::Client,
+    provider: &ProviderConfig,
+) -> anyhow::Result<Vec<String>> {
+    let mut request = http_client.get(format!(
+        "{}/v1/models",
+        provider.base_url.trim_end_matches('/')
+    ));
+    if !provider.api_key.is_empty() {
+        request = request.bearer_auth(&provider.api_key);
+    }
+
+    let body: serde_json::Value = request.send().await?.error_for_status()?.json().await?;
+    Ok(parse_model_ids(&body))
+}
+
+/// Model IDs from an OpenAI-style `{"data": [{"id": ...}]}` model list.
+pub(crate) fn parse_model_ids(body: &serde_json::Value) -> Vec<String> {
+    let mut ids: Vec<String> = body["data"]
+        .as_array()
+        .map(|models| {
+            models
+                .iter()
+                .filter_map(|model| model["id"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_ids_come_from_the_data_list() {
+        let body = serde_json::json!({
+            "object": "list",
+            "data": [
+                {"id": "meta-llama/Llama-3.1-8B-Instruct", "object": "model", "owned_by": "vllm"},
+                {"id": "qwen2.5-coder-7b", "object": "model"},
+                {"object": "model"},
+            ],
+        });
+        assert_eq!(
+            parse_model_ids(&body),
+            vec![
+                "meta-llama/Llama-3.1-8B-Instruct".to_string(),
+                "qwen2.5-coder-7b".to_string(),
+            ]
+        );
+        assert!(parse_model_ids(&serde_json::json!({"error": "not found"})).is_empty());
+    }
+}
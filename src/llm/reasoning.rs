@@ -0,0 +1,311 @@
+//! Reasoning models: thinking budgets, effort levels, and hidden thoughts.
+//!
+//! Providers take reasoning settings their own way. Anthropic models think
+//! within a token budget (4.6 models pick their own, guided by an effort),
+//! OpenAI reasoning models take a `reasoning_effort`, and open models such as
+//! DeepSeek-R1 take nothing and write their reasoning inline in `<think>`
+//! tags. One effort per process type, overridable per model, is translated
+//! for each. Whatever form the reasoning comes back in, it's split from the
+//! answer into reasoning content, so it never reaches a channel, and its
+//! tokens are counted apart from the answer's in costs.
+
+use regex::Regex;
+use rig::message::AssistantContent;
+use serde::Deserialize;
+
+use std::sync::LazyLock;
+
+/// Values `effort` and the `*_thinking_effort` keys take.
+pub const EFFORTS: [&str; 6] = ["auto", "off", "low", "medium", "high", "max"];
+
+/// Reasoning longer than this is cut to its opening sentences in summaries.
+const SUMMARY_CHARS: usize = 600;
+
+/// Reasoning written inline before the answer. Some hosts send the opening
+/// tag as part of the prompt, so only the closing one is required.
+static THINK_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)^\s*(?:<think>)?(.*?)</think>").expect("hardcoded think tag regex")
+});
+
+/// What the proxy passes on of a model's reasoning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningDisplay {
+    /// Left out of responses.
+    #[default]
+    Hidden,
+    /// Returned in short form: the provider's own summary when it writes
+    /// one, otherwise the reasoning's opening sentences.
+    Summary,
+}
+
+/// Reasoning settings for one model, from
+/// `[defaults.routing.reasoning."<model>"]`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReasoningConfig {
+    /// Overrides the effort of the process type using the model.
+    pub effort: Option<String>,
+    /// Anthropic thinking budget, instead of the effort's.
+    pub budget_tokens: Option<u32>,
+    pub display: ReasoningDisplay,
+}
+
+/// Reasoning settings resolved for one call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reasoning {
+    /// One of [`EFFORTS`].
+    pub effort: String,
+    pub budget_tokens: Option<u32>,
+    pub display: ReasoningDisplay,
+}
+
+impl Default for Reasoning {
+    fn default() -> Self {
+        Self {
+            effort: "auto".into(),
+            budget_tokens: None,
+            display: ReasoningDisplay::default(),
+        }
+    }
+}
+
+impl Reasoning {
+    pub fn is_off(&self) -> bool {
+        self.effort == "off"
+    }
+
+    /// Thinking budget for Anthropic models with extended thinking. `None`
+    /// leaves thinking off, which is what "auto" does on these models.
+    pub fn thinking_budget(&self) -> Option<u32> {
+        if self.is_off() {
+            return None;
+        }
+        self.budget_tokens.or(match self.effort.as_str() {
+            "low" => Some(2_048),
+            "medium" => Some(8_192),
+            "high" => Some(16_384),
+            "max" => Some(32_000),
+            _ => None,
+        })
+    }
+
+    /// `reasoning_effort` for an OpenAI reasoning model, or `None` for the
+    /// provider's default.
+    pub fn openai_effort(&self, model_name: &str) -> Option<&'static str> {
+        match self.effort.as_str() {
+            // Only GPT-5 models can reason minimally; the o-series go no
+            // lower than "low".
+            "off" if bare_name(model_name).starts_with("gpt-5") => Some("minimal"),
+            "off" | "low" => Some("low"),
+            "medium" => Some("medium"),
+            "high" | "max" => Some("high"),
+            _ => None,
+        }
+    }
+}
+
+fn bare_name(model_name: &str) -> &str {
+    model_name.rsplit('/').next().unwrap_or(model_name)
+}
+
+/// Anthropic models with extended thinking and a fixed budget, before
+/// adaptive thinking.
+pub fn supports_extended_thinking(model_name: &str) -> bool {
+    let name = bare_name(model_name);
+    name.starts_with("claude-3-7-sonnet")
+        || name.starts_with("claude-sonnet-4")
+        || name.starts_with("claude-opus-4")
+        || name.starts_with("claude-haiku-4")
+}
+
+/// OpenAI models that reason, and reject `temperature` and `max_tokens`.
+pub fn is_openai_reasoning_model(model_name: &str) -> bool {
+    let name = bare_name(model_name);
+    ["o1", "o3", "o4"]
+        .iter()
+        .any(|series| name == *series || name.starts_with(&format!("{series}-")))
+        || (name.starts_with("gpt-5") && !name.starts_with("gpt-5-chat"))
+}
+
+/// Split reasoning written inline in `<think>` tags from the answer after
+/// it. Returns the answer and the reasoning, if there was any.
+pub fn split_think_tags(text: &str) -> (String, Option<String>) {
+    let Some(captures) = THINK_PREFIX.captures(text) else {
+        return (text.to_string(), None);
+    };
+    let reasoning = captures[1].trim();
+    let answer = text[captures[0].len()..].trim_start().to_string();
+    (
+        answer,
+        (!reasoning.is_empty()).then(|| reasoning.to_string()),
+    )
+}
+
+/// Reasoning content for a response, in the order it came.
+pub fn reasoning_text<'a>(
+    choice: impl IntoIterator<Item = &'a AssistantContent>,
+) -> Option<String> {
+    let text = choice
+        .into_iter()
+        .filter_map(|content| match content {
+            AssistantContent::Reasoning(reasoning) => Some(reasoning.reasoning.join("\n")),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    (!text.trim().is_empty()).then_some(text)
+}
+
+/// Reasoning cut to its opening sentences when it's long.
+pub fn summarize(reasoning: &str) -> String {
+    let reasoning = reasoning.trim();
+    if reasoning.len() <= SUMMARY_CHARS {
+        return reasoning.to_string();
+    }
+    let head = &reasoning[..reasoning.floor_char_boundary(SUMMARY_CHARS)];
+    let cut = head
+        .rfind(['.', '!', '?'])
+        .map(|end| end + 1)
+        .or_else(|| head.rfind(char::is_whitespace))
+        .unwrap_or(head.len());
+    format!("{}…", head[..cut].trim_end())
+}
+
+/// Reasoning tokens in a raw response: as the provider reports them, or
+/// estimated from the reasoning text when it doesn't. Anthropic never
+/// reports them, and 4-series models return summarized thinking, so their
+/// count is a lower bound. Never more than the output tokens.
+pub fn reasoning_tokens(body: &serde_json::Value) -> u64 {
+    let usage = &body["usage"];
+    let reported = usage["completion_tokens_details"]["reasoning_tokens"]
+        .as_u64()
+        .or_else(|| usage["output_tokens_details"]["reasoning_tokens"].as_u64());
+    let output_tokens = usage["completion_tokens"]
+        .as_u64()
+        .or_else(|| usage["output_tokens"].as_u64())
+        .unwrap_or(0);
+    let tokens = reported.unwrap_or_else(|| estimate_tokens(&reasoning_in_body(body)));
+    tokens.min(output_tokens)
+}
+
+/// Reasoning text in a raw Anthropic or Chat Completions response.
+fn reasoning_in_body(body: &serde_json::Value) -> String {
+    if let Some(blocks) = body["content"].as_array() {
+        return blocks
+            .iter()
+            .filter(|block| block["type"] == "thinking")
+            .filter_map(|block| block["thinking"].as_str())
+            .collect();
+    }
+    let message = &body["choices"][0]["message"];
+    if let Some(reasoning) = message["reasoning_content"].as_str() {
+        return reasoning.to_string();
+    }
+    message["content"]
+        .as_str()
+        .and_then(|content| split_think_tags(content).1)
+        .unwrap_or_default()
+}
+
+/// Rough token count for text, at four characters a token.
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.len() as u64).div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reasoning(effort: &str) -> Reasoning {
+        Reasoning {
+            effort: effort.into(),
+            ..Reasoning::default()
+        }
+    }
+
+    #[test]
+    fn efforts_translate_per_provider() {
+        assert_eq!(reasoning("auto").thinking_budget(), None);
+        assert_eq!(reasoning("medium").thinking_budget(), Some(8_192));
+        let budgeted = Reasoning {
+            budget_tokens: Some(4_000),
+            ..reasoning("high")
+        };
+        assert_eq!(budgeted.thinking_budget(), Some(4_000));
+        let off = Reasoning {
+            budget_tokens: Some(4_000),
+            ..reasoning("off")
+        };
+        assert_eq!(off.thinking_budget(), None);
+
+        assert_eq!(reasoning("auto").openai_effort("openai/o3"), None);
+        assert_eq!(reasoning("max").openai_effort("openai/o3"), Some("high"));
+        assert_eq!(
+            reasoning("off").openai_effort("openai/o4-mini"),
+            Some("low")
+        );
+        assert_eq!(
+            reasoning("off").openai_effort("gpt-5-mini"),
+            Some("minimal")
+        );
+
+        assert!(supports_extended_thinking(
+            "anthropic/claude-sonnet-4-20250514"
+        ));
+        assert!(!supports_extended_thinking("anthropic/claude-3-5-haiku"));
+        assert!(is_openai_reasoning_model("openrouter/openai/o3-mini"));
+        assert!(is_openai_reasoning_model("openai/gpt-5"));
+        assert!(!is_openai_reasoning_model("openai/gpt-5-chat-latest"));
+        assert!(!is_openai_reasoning_model("openai/gpt-4o"));
+    }
+
+    #[test]
+    fn think_tags_split_from_the_answer() {
+        assert_eq!(
+            split_think_tags("<think>\nThey want a number.\n</think>\n\n42"),
+            ("42".to_string(), Some("They want a number.".to_string()))
+        );
+        assert_eq!(
+            split_think_tags("counting first</think>42"),
+            ("42".to_string(), Some("counting first".to_string()))
+        );
+        assert_eq!(
+            split_think_tags("<think></think>42"),
+            ("42".to_string(), None)
+        );
+        assert_eq!(split_think_tags("42"), ("42".to_string(), None));
+    }
+
+    #[test]
+    fn reasoning_tokens_are_reported_or_estimated() {
+        let openai = serde_json::json!({"usage": {
+            "completion_tokens": 500,
+            "completion_tokens_details": {"reasoning_tokens": 320},
+        }});
+        assert_eq!(reasoning_tokens(&openai), 320);
+
+        let anthropic = serde_json::json!({
+            "content": [
+                {"type": "thinking", "thinking": "a".repeat(400), "signature": "sig"},
+                {"type": "text", "text": "Done."},
+            ],
+            "usage": {"input_tokens": 10, "output_tokens": 150},
+        });
+        assert_eq!(reasoning_tokens(&anthropic), 100);
+
+        let deepseek = serde_json::json!({
+            "choices": [{"message": {"content": format!("<think>{}</think>Hi", "b".repeat(40))}}],
+            "usage": {"completion_tokens": 5},
+        });
+        assert_eq!(reasoning_tokens(&deepseek), 5);
+    }
+
+    #[test]
+    fn long_reasoning_is_summarized_at_a_sentence() {
+        assert_eq!(summarize("  Short.  "), "Short.");
+        let long = format!("{} Second sentence.", "First sentence. ".repeat(40));
+        let summary = summarize(&long);
+        assert!(summary.len() <= SUMMARY_CHARS + "…".len());
+        assert!(summary.ends_with(".…"));
+    }
+}
@@ -0,0 +1,336 @@
+//! Host and GPU resource sampling for local models.
+//!
+//! GPU memory comes from `nvidia-smi`, host memory and load from `/proc`.
+//! Snapshots feed Prometheus gauges and the `/system/resources` endpoint,
+//! and gate Ollama requests: when free VRAM is below the configured floor a
+//! request for a model that isn't already loaded waits for memory to free
+//! up, then fails, instead of letting Ollama run out of memory mid-load.
+
+use crate::llm::manager::LlmManager;
+
+use arc_swap::ArcSwapOption;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often the background sampler refreshes the snapshot.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How often a queued request re-checks free VRAM.
+const ADMISSION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Upper bound on a single `nvidia-smi` invocation.
+const NVIDIA_SMI_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Memory and utilization for one GPU.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GpuStats {
+    pub index: u32,
+    pub name: String,
+    pub memory_used_mib: u64,
+    pub memory_total_mib: u64,
+    pub utilization_percent: u8,
+}
+
+impl GpuStats {
+    pub fn memory_free_mib(&self) -> u64 {
+        self.memory_total_mib.saturating_sub(self.memory_used_mib)
+    }
+}
+
+/// Host memory and load, read from `/proc`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HostStats {
+    pub memory_total_kib: u64,
+    pub memory_available_kib: u64,
+    pub load_average_1m: f64,
+}
+
+/// A point-in-time view of host and GPU resources.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceSnapshot {
+    /// Empty when `nvidia-smi` is missing or reports no devices.
+    pub gpus: Vec<GpuStats>,
+    /// `None` on hosts without `/proc` (e.g. macOS).
+    pub host: Option<HostStats>,
+    pub sampled_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ResourceSnapshot {
+    /// Free VRAM summed across GPUs, or `None` when no GPU is visible.
+    pub fn free_vram_mib(&self) -> Option<u64> {
+        if self.gpus.is_empty() {
+            return None;
+        }
+        Some(self.gpus.iter().map(GpuStats::memory_free_mib).sum())
+    }
+
+    fn age(&self) -> Duration {
+        (chrono::Utc::now() - self.sampled_at)
+            .to_std()
+            .unwrap_or_default()
+    }
+}
+
+/// Latest resource snapshot, shared between the sampler and request admission.
+#[derive(Debug, Default)]
+pub struct ResourceMonitor {
+    latest: ArcSwapOption<ResourceSnapshot>,
+}
+
+impl ResourceMonitor {
+    pub fn latest(&self) -> Option<Arc<ResourceSnapshot>> {
+        self.latest.load_full()
+    }
+
+    /// Take a fresh sample, store it, and update metrics.
+    pub async fn refresh(&self) -> Arc<ResourceSnapshot> {
+        let snapshot = Arc::new(sample().await);
+        #[cfg(feature = "metrics")]
+        record_metrics(&snapshot);
+        self.latest.store(Some(snapshot.clone()));
+        snapshot
+    }
+
+    /// Wait until at least `min_free_mib` of VRAM is free.
+    ///
+    /// Passes immediately when the floor is 0 or no GPU is visible. Otherwise
+    /// re-samples every couple of seconds and gives up after `timeout`.
+    pub async fn wait_for_vram(
+        &self,
+        min_free_mib: u64,
+        timeout: Duration,
+    ) -> std::result::Result<(), String> {
+        if min_free_mib == 0 {
+            return Ok(());
+        }
+
+        let started = std::time::Instant::now();
+        let mut logged = false;
+        loop {
+            let snapshot = match self.latest() {
+                Some(snapshot) if snapshot.age() < ADMISSION_POLL_INTERVAL => snapshot,
+                _ => self.refresh().await,
+            };
+            let Some(free_mib) = snapshot.free_vram_mib() else {
+                return Ok(());
+            };
+            if free_mib >= min_free_mib {
+                return Ok(());
+            }
+            if started.elapsed() >= timeout {
+                #[cfg(feature = "metrics")]
+                crate::telemetry::Metrics::global()
+                    .vram_rejections_total
+                    .inc();
+                return Err(format!(
+                    "VRAM exhausted: {free_mib} MiB free, {min_free_mib} MiB required"
+                ));
+            }
+            if !logged {
+                tracing::info!(
+                    free_mib,
+                    min_free_mib,
+                    "queueing local model request until VRAM frees up"
+                );
+                logged = true;
+            }
+            tokio::time::sleep(ADMISSION_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Sample GPUs and host resources once.
+pub async fn sample() -> ResourceSnapshot {
+    let gpus = match query_nvidia_smi().await {
+        Ok(output) => parse_nvidia_smi(&output),
+        Err(error) => {
+            tracing::trace!(%error, "nvidia-smi unavailable");
+            Vec::new()
+        }
+    };
+
+    let meminfo = tokio::fs::read_to_string("/proc/meminfo").await.ok();
+    let loadavg = tokio::fs::read_to_string("/proc/loadavg").await.ok();
+    let host = meminfo.as_deref().and_then(parse_meminfo).map(
+        |(memory_total_kib, memory_available_kib)| HostStats {
+            memory_total_kib,
+            memory_available_kib,
+            load_average_1m: loadavg.as_deref().and_then(parse_loadavg).unwrap_or(0.0),
+        },
+    );
+
+    ResourceSnapshot {
+        gpus,
+        host,
+        sampled_at: chrono::Utc::now(),
+    }
+}
+
+async fn query_nvidia_smi() -> anyhow::Result<String> {
+    let command = tokio::process::Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=index,name,memory.used,memory.total,utilization.gpu",
+            "--format=csv,noheader,nounits",
+        ])
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(NVIDIA_SMI_TIMEOUT, command).await??;
+    if !output.status.success() {
+        anyhow::bail!("nvidia-smi exited with {}", output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `nvidia-smi --format=csv,noheader,nounits` rows, skipping malformed lines.
+pub fn parse_nvidia_smi(output: &str) -> Vec<GpuStats> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let [index, name, used, total, utilization] = fields.as_slice() else {
+                return None;
+            };
+            Some(GpuStats {
+                index: index.parse().ok()?,
+                name: name.to_string(),
+                memory_used_mib: used.parse().ok()?,
+                memory_total_mib: total.parse().ok()?,
+                // Some GPUs report "[N/A]" utilization.
+                utilization_percent: utilization.parse().unwrap_or(0),
+            })
+        })
+        .collect()
+}
+
+/// Parse `MemTotal` and `MemAvailable` (in KiB) from `/proc/meminfo`.
+pub fn parse_meminfo(meminfo: &str) -> Option<(u64, u64)> {
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| rest.trim_start_matches(':').split_whitespace().next())
+            .and_then(|value| value.parse().ok())
+    };
+    Some((field("MemTotal")?, field("MemAvailable")?))
+}
+
+/// Parse the one-minute load average from `/proc/loadavg`.
+pub fn parse_loadavg(loadavg: &str) -> Option<f64> {
+    loadavg.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(feature = "metrics")]
+fn record_metrics(snapshot: &ResourceSnapshot) {
+    let metrics = crate::telemetry::Metrics::global();
+    for gpu in &snapshot.gpus {
+        let label = gpu.index.to_string();
+        metrics
+            .gpu_memory_used_bytes
+            .with_label_values(&[&label])
+            .set((gpu.memory_used_mib * 1024 * 1024) as i64);
+        metrics
+            .gpu_memory_total_bytes
+            .with_label_values(&[&label])
+            .set((gpu.memory_total_mib * 1024 * 1024) as i64);
+        metrics
+            .gpu_utilization_percent
+            .with_label_values(&[&label])
+            .set(i64::from(gpu.utilization_percent));
+    }
+    if let Some(host) = &snapshot.host {
+        metrics
+            .host_memory_available_bytes
+            .set((host.memory_available_kib * 1024) as i64);
+    }
+}
+
+/// Refresh the resource snapshot periodically while an Ollama provider is configured.
+///
+/// Holds only a weak reference to the manager so the task ends when the
+/// manager is replaced.
+pub fn spawn_sampler(llm_manager: &Arc<LlmManager>) -> tokio::task::JoinHandle<()> {
+    let llm_manager = Arc::downgrade(llm_manager);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(manager) = llm_manager.upgrade() else {
+                return;
+            };
+            if manager.ollama_base_url().is_none() {
+                return;
+            }
+            manager.resources().refresh().await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nvidia_smi_rows_parse() {
+        let output = "0, NVIDIA GeForce RTX 4090, 20480, 24564, 87\n\
+                      1, NVIDIA A100, 1024, 81920, [N/A]\n\
+                      garbage line\n";
+        let gpus = parse_nvidia_smi(output);
+        assert_eq!(gpus.len(), 2);
+        assert_eq!(gpus[0].name, "NVIDIA GeForce RTX 4090");
+        assert_eq!(gpus[0].memory_free_mib(), 4084);
+        assert_eq!(gpus[0].utilization_percent, 87);
+        assert_eq!(gpus[1].utilization_percent, 0);
+
+        let snapshot = ResourceSnapshot {
+            gpus,
+            host: None,
+            sampled_at: chrono::Utc::now(),
+        };
+        assert_eq!(snapshot.free_vram_mib(), Some(4084 + 80896));
+    }
+
+    #[test]
+    fn proc_files_parse() {
+        let meminfo =
+            "MemTotal:       65843760 kB\nMemFree:         1234 kB\nMemAvailable:   40123456 kB\n";
+        assert_eq!(parse_meminfo(meminfo), Some((65843760, 40123456)));
+        assert_eq!(parse_meminfo("MemTotal: 1 kB\n"), None);
+        assert_eq!(parse_loadavg("0.52 0.58 0.59 1/1234 5678\n"), Some(0.52));
+    }
+
+    #[tokio::test]
+    async fn admission_passes_without_a_floor_or_gpus() {
+        let monitor = ResourceMonitor::default();
+        assert_eq!(monitor.wait_for_vram(0, Duration::ZERO).await, Ok(()));
+
+        monitor.latest.store(Some(Arc::new(ResourceSnapshot {
+            gpus: Vec::new(),
+            host: None,
+            sampled_at: chrono::Utc::now(),
+        })));
+        assert_eq!(monitor.wait_for_vram(4096, Duration::ZERO).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn admission_rejects_when_vram_stays_exhausted() {
+        let monitor = ResourceMonitor::default();
+        monitor.latest.store(Some(Arc::new(ResourceSnapshot {
+            gpus: vec![GpuStats {
+                index: 0,
+                name: "test".into(),
+                memory_used_mib: 23_000,
+                memory_total_mib: 24_000,
+                utilization_percent: 100,
+            }],
+            host: None,
+            sampled_at: chrono::Utc::now(),
+        })));
+
+        let error = monitor
+            .wait_for_vram(4096, Duration::ZERO)
+            .await
+            .expect_err("should reject");
+        assert!(error.contains("1000 MiB free"));
+    }
+}
@@ -1,7 +1,12 @@
 //! Model routing configuration and resolution.
 
 use crate::ProcessType;
+use crate::llm::canary::CanaryConfig;
+use crate::llm::confidence::ConfidenceConfig;
+use crate::llm::reasoning::{Reasoning, ReasoningConfig};
+
 use std::collections::HashMap;
+use std::time::Duration;
 
 /// Model routing configuration. Lives on the agent config (via defaults).
 /// Determines which LLM model each process type uses, with task-type
@@ -23,14 +28,35 @@ pub struct RoutingConfig {
     /// try the next model in its chain.
     pub fallbacks: HashMap<String, Vec<String>>,
 
-    /// How long to deprioritize a rate-limited model (seconds).
+    /// How long to deprioritize a rate-limited model (seconds). Doubles
+    /// with each rate limit in a row.
     pub rate_limit_cooldown_secs: u64,
 
+    /// Longest cooldown after repeated rate limits (seconds).
+    pub rate_limit_cooldown_max_secs: u64,
+
+    /// Time without a rate limit after which cooldowns start over from
+    /// `rate_limit_cooldown_secs` (seconds).
+    pub rate_limit_reset_secs: u64,
+
     pub channel_thinking_effort: String,
     pub branch_thinking_effort: String,
     pub worker_thinking_effort: String,
     pub compactor_thinking_effort: String,
     pub cortex_thinking_effort: String,
+
+    /// Reasoning settings per model, overriding the process type's effort.
+    pub reasoning: HashMap<String, ReasoningConfig>,
+
+    /// Logprob capture and low-confidence handling.
+    pub confidence: ConfidenceConfig,
+
+    /// Run arithmetic found in channel messages through the calculator and
+    /// give the channel the results with the message.
+    pub auto_calculate: bool,
+
+    /// Share of conversations sent to a new channel model or prompt version.
+    pub canary: Option<CanaryConfig>,
 }
 
 impl Default for RoutingConfig {
@@ -51,11 +77,17 @@ impl RoutingConfig {
             task_overrides: HashMap::new(),
             fallbacks: HashMap::new(),
             rate_limit_cooldown_secs: 60,
+            rate_limit_cooldown_max_secs: 900,
+            rate_limit_reset_secs: 600,
             channel_thinking_effort: "auto".into(),
             branch_thinking_effort: "auto".into(),
             worker_thinking_effort: "auto".into(),
             compactor_thinking_effort: "auto".into(),
             cortex_thinking_effort: "auto".into(),
+            reasoning: HashMap::new(),
+            confidence: ConfidenceConfig::default(),
+            auto_calculate: true,
+            canary: None,
         }
     }
 }
@@ -100,6 +132,20 @@ impl RoutingConfig {
         "auto"
     }
 
+    /// Reasoning settings for calls to `model_name`: its own entry under
+    /// `reasoning`, with the effort of the process type using it when the
+    /// entry doesn't set one.
+    pub fn reasoning_for_model(&self, model_name: &str) -> Reasoning {
+        let config = self.reasoning.get(model_name).cloned().unwrap_or_default();
+        Reasoning {
+            effort: config
+                .effort
+                .unwrap_or_else(|| self.thinking_effort_for_model(model_name).to_string()),
+            budget_tokens: config.budget_tokens,
+            display: config.display,
+        }
+    }
+
     /// Get the fallback chain for a model, if any.
     pub fn get_fallbacks(&self, model_name: &str) -> &[String] {
         self.fallbacks
@@ -358,6 +404,53 @@ pub const MAX_RETRIES_PER_MODEL: usize = 3;
 /// Base delay for exponential backoff between retries (milliseconds).
 pub const RETRY_BASE_DELAY_MS: u64 = 500;
 
+/// Longest wait before retrying the same model (milliseconds). A provider
+/// asking for a longer wait gets a cooldown and the fallback chain instead.
+pub const RETRY_MAX_DELAY_MS: u64 = 8_000;
+
+/// The delay before the next retry, with decorrelated jitter: random between
+/// the base delay and three times the previous delay, capped. Spreads out
+/// retries from many callers that failed at the same moment.
+pub fn decorrelated_backoff(previous_ms: u64) -> u64 {
+    let upper = previous_ms
+        .saturating_mul(3)
+        .clamp(RETRY_BASE_DELAY_MS, RETRY_MAX_DELAY_MS);
+    rand::random_range(RETRY_BASE_DELAY_MS..=upper)
+}
+
+/// The wait a provider asked for in its `retry-after-ms` or `Retry-After`
+/// response header, in seconds or as an HTTP date.
+pub fn retry_after_header(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+    if let Some(millis) = header("retry-after-ms").and_then(|value| value.parse::<f64>().ok()) {
+        return Duration::try_from_secs_f64(millis / 1_000.0).ok();
+    }
+    let value = header("retry-after")?;
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
+/// A provider error message carrying the wait it asked for, so the wait
+/// survives the error being flattened to a string.
+pub fn with_retry_after(message: String, retry_after: Option<Duration>) -> String {
+    match retry_after {
+        Some(retry_after) => format!("{message} (retry after {}ms)", retry_after.as_millis()),
+        None => message,
+    }
+}
+
+/// The wait [`with_retry_after`] put in an error message.
+pub fn retry_after_in(error_message: &str) -> Option<Duration> {
+    let (_, rest) = error_message.rsplit_once("(retry after ")?;
+    let (millis, _) = rest.split_once("ms)")?;
+    millis.parse().ok().map(Duration::from_millis)
+}
+
 /// Whether an error indicates an actual rate limit (429) vs other transient failures.
 /// Only rate-limit errors should trigger cooldown — timeouts and 5xx errors are
 /// momentary and shouldn't lock out a model for the full cooldown period.
@@ -365,3 +458,12 @@ pub fn is_rate_limit_error(error_message: &str) -> bool {
     let lower = error_message.to_lowercase();
     lower.contains("429") || lower.contains("rate limit")
 }
+
+/// Prefix on completion errors raised when budget caps block every model.
+/// Deliberately not plain English, so no upstream provider error can match it.
+pub const OVER_BUDGET_MARKER: &str = "spacebot_over_budget";
+
+/// Whether a completion error was a budget refusal rather than a provider failure.
+pub fn is_over_budget_error(error_message: &str) -> bool {
+    error_message.contains(OVER_BUDGET_MARKER)
+}
@@ -0,0 +1,285 @@
+//! Best-of-N sampling for high-stakes answers.
+//!
+//! Callers opt in per request by setting `additional_params` to
+//! `BestOfN::to_params()`. `SpacebotModel` then draws several completions in
+//! parallel and returns one, picked either by majority vote over the reply
+//! text (self-consistency) or by a judge model. A cost cap bounds how many
+//! samples are drawn: the first sample is priced and only as many more as fit
+//! under the cap are requested.
+
+use rig::completion::{AssistantContent, CompletionError, CompletionRequest};
+use rig::message::{Message, UserContent};
+use rig::one_or_many::OneOrMany;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+
+/// Key under `additional_params` that carries the sampling options.
+const PARAMS_KEY: &str = "spacebot_best_of";
+
+/// Upper bound on samples per request, whatever the caller asks for.
+pub const MAX_SAMPLES: usize = 8;
+
+/// How to pick among samples.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Selection {
+    /// The most common reply text wins; ties go to the earliest sample.
+    MajorityVote,
+    /// A judge model reads every candidate and picks the best.
+    Judge { model: String },
+}
+
+/// Best-of-N options for one request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BestOfN {
+    /// Number of samples to draw, capped at [`MAX_SAMPLES`].
+    pub n: usize,
+    /// Most to spend on samples, in USD. Judge calls aren't counted.
+    #[serde(default)]
+    pub max_cost_usd: Option<f64>,
+    #[serde(default = "default_selection")]
+    pub selection: Selection,
+    /// Sampling temperature, overriding the request's. Samples drawn at
+    /// temperature 0 tend to be identical, which defeats the vote.
+    #[serde(default)]
+    pub temperature: Option<f64>,
+}
+
+fn default_selection() -> Selection {
+    Selection::MajorityVote
+}
+
+impl BestOfN {
+    /// `additional_params` value requesting best-of-N sampling.
+    ///
+    /// Merge it with other params (e.g. an output format) key by key.
+    pub fn to_params(&self) -> serde_json::Value {
+        serde_json::json!({ PARAMS_KEY: self })
+    }
+
+    /// The sampling options on a completion request, if any.
+    pub fn from_request(request: &CompletionRequest) -> Result<Option<Self>, CompletionError> {
+        let Some(value) = request
+            .additional_params
+            .as_ref()
+            .and_then(|params| params.get(PARAMS_KEY))
+        else {
+            return Ok(None);
+        };
+        serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|error| {
+                CompletionError::RequestError(format!("invalid {PARAMS_KEY}: {error}").into())
+            })
+    }
+
+    /// The request each sample is drawn with: sampling options removed so
+    /// samples don't fan out again, and the temperature override applied.
+    pub fn sample_request(&self, request: &CompletionRequest) -> CompletionRequest {
+        let mut request = request.clone();
+        if let Some(params) = request
+            .additional_params
+            .as_mut()
+            .and_then(|p| p.as_object_mut())
+        {
+            params.remove(PARAMS_KEY);
+        }
+        if self.temperature.is_some() {
+            request.temperature = self.temperature;
+        }
+        request
+    }
+
+    /// How many samples to draw in total, given what the first one cost.
+    ///
+    /// Unpriced models (cost 0) always get the full `n`.
+    pub fn affordable_samples(&self, first_sample_cost: f64) -> usize {
+        let n = self.n.clamp(1, MAX_SAMPLES);
+        match self.max_cost_usd {
+            Some(max_cost) if first_sample_cost > 0.0 => {
+                let affordable = (max_cost / first_sample_cost).floor().max(1.0) as usize;
+                n.min(affordable)
+            }
+            _ => n,
+        }
+    }
+}
+
+/// Concatenated text content of a reply.
+pub fn reply_text(choice: &OneOrMany<AssistantContent>) -> String {
+    choice
+        .iter()
+        .filter_map(|content| match content {
+            AssistantContent::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Index of the majority reply. Text is compared case- and
+/// whitespace-insensitively; tool-only replies never win a vote over text.
+pub fn majority_vote(replies: &[String]) -> usize {
+    let normalized: Vec<String> = replies
+        .iter()
+        .map(|reply| {
+            reply
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase()
+        })
+        .collect();
+
+    let mut votes: HashMap<&str, usize> = HashMap::new();
+    for reply in normalized.iter().filter(|reply| !reply.is_empty()) {
+        *votes.entry(reply).or_default() += 1;
+    }
+
+    let mut best = 0;
+    let mut best_votes = 0;
+    for (index, reply) in normalized.iter().enumerate() {
+        let count = votes.get(reply.as_str()).copied().unwrap_or(0);
+        if count > best_votes {
+            best = index;
+            best_votes = count;
+        }
+    }
+    best
+}
+
+/// Build the request asking a judge to pick among `candidates`.
+pub fn judge_request(original: &CompletionRequest, candidates: &[String]) -> CompletionRequest {
+    let question = last_user_text(&original.chat_history);
+    let mut prompt = format!("Question:\n{question}\n");
+    for (index, candidate) in candidates.iter().enumerate() {
+        prompt.push_str(&format!("\nCandidate {}:\n{candidate}\n", index + 1));
+    }
+
+    let output_format = crate::llm::structured::OutputFormat::JsonSchema {
+        name: "best_candidate".into(),
+        schema: serde_json::json!({
+            "type": "object",
+            "properties": { "best": { "type": "integer" } },
+            "required": ["best"],
+        }),
+    };
+
+    CompletionRequest {
+        preamble: Some(crate::prompts::text::get("fragments/system/best_of_judge").to_string()),
+        chat_history: OneOrMany::one(Message::user(prompt)),
+        documents: Vec::new(),
+        tools: Vec::new(),
+        temperature: Some(0.0),
+        max_tokens: Some(64),
+        tool_choice: None,
+        additional_params: Some(output_format.to_params()),
+    }
+}
+
+/// Zero-based index of the candidate the judge picked, if it named a valid one.
+pub fn parse_judge_choice(reply: &str, candidate_count: usize) -> Option<usize> {
+    let value: serde_json::Value =
+        serde_json::from_str(crate::llm::structured::strip_code_fence(reply)).ok()?;
+    let best = value["best"].as_u64()? as usize;
+    (1..=candidate_count).contains(&best).then(|| best - 1)
+}
+
+fn last_user_text(history: &OneOrMany<Message>) -> String {
+    history
+        .iter()
+        .filter_map(|message| match message {
+            Message::User { content } => Some(
+                content
+                    .iter()
+                    .filter_map(|content| match content {
+                        UserContent::Text(text) => Some(text.text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            _ => None,
+        })
+        .last()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cost_cap_limits_sample_count() {
+        let best_of = BestOfN {
+            n: 5,
+            max_cost_usd: Some(0.10),
+            selection: Selection::MajorityVote,
+            temperature: None,
+        };
+        assert_eq!(best_of.affordable_samples(0.03), 3);
+        assert_eq!(best_of.affordable_samples(0.50), 1);
+        assert_eq!(best_of.affordable_samples(0.0), 5);
+
+        let greedy = BestOfN { n: 100, ..best_of };
+        assert_eq!(greedy.affordable_samples(0.0), MAX_SAMPLES);
+    }
+
+    #[test]
+    fn majority_vote_ignores_case_and_whitespace() {
+        let replies = vec![
+            "Paris".to_string(),
+            "Lyon".to_string(),
+            "  paris ".to_string(),
+            String::new(),
+        ];
+        assert_eq!(majority_vote(&replies), 0);
+        assert_eq!(majority_vote(&[String::new(), "yes".into()]), 1);
+        assert_eq!(majority_vote(&["a".into(), "b".into()]), 0);
+    }
+
+    #[test]
+    fn judge_choices_are_one_based_and_bounded() {
+        assert_eq!(parse_judge_choice(r#"{"best": 2}"#, 3), Some(1));
+        assert_eq!(
+            parse_judge_choice("```json\n{\"best\": 1}\n```", 3),
+            Some(0)
+        );
+        assert_eq!(parse_judge_choice(r#"{"best": 0}"#, 3), None);
+        assert_eq!(parse_judge_choice(r#"{"best": 4}"#, 3), None);
+        assert_eq!(parse_judge_choice("candidate 2", 3), None);
+    }
+
+    #[test]
+    fn samples_drop_the_sampling_params() {
+        let best_of = BestOfN {
+            n: 3,
+            max_cost_usd: None,
+            selection: Selection::Judge {
+                model: "anthropic/claude-opus-4".into(),
+            },
+            temperature: Some(0.8),
+        };
+        let request = CompletionRequest {
+            preamble: None,
+            chat_history: OneOrMany::one(Message::user("hi")),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            tool_choice: None,
+            additional_params: Some(best_of.to_params()),
+        };
+        assert_eq!(
+            BestOfN::from_request(&request).expect("params should parse"),
+            Some(best_of.clone())
+        );
+
+        let sample = best_of.sample_request(&request);
+        assert_eq!(
+            BestOfN::from_request(&sample).expect("params should parse"),
+            None
+        );
+        assert_eq!(sample.temperature, Some(0.8));
+    }
+}
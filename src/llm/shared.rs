@@ -0,0 +1,400 @@
+//! Limiter state shared across replicas.
+//!
+//! By default each process keeps its own rate-limit cooldowns and budget
+//! spend. With `[llm.shared_state] backend = "redis"`, both are mirrored to
+//! Redis so replicas running on the same provider keys act as one: a 429 on
+//! one replica puts the model in cooldown for all of them, for as long as
+//! that replica's escalating cooldown runs, and budget caps apply to the
+//! fleet's combined spend. Local state still answers the hot path. Cooldown
+//! checks consult Redis only when the model isn't already cooling down
+//! locally, and fleet spend is pulled into the local ledger at most every few
+//! seconds. Redis commands give up after a few tens of milliseconds, and a
+//! failure opens a circuit breaker: for the next few seconds each replica
+//! carries on with its own state without asking Redis.
+//!
+//! Only cooldowns and spend are shared. Per-sender message quotas
+//! (`messages_per_hour`, see [`crate::access::Quotas`]) are still counted per
+//! process, so N replicas let a sender through up to N times the limit.
+//! There are no request token buckets to share; replicas don't pace requests
+//! to a provider ahead of its 429s.
+
+use crate::db::redis::RedisClient;
+use crate::error::Result;
+use crate::llm::budget::ProviderSpend;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Daily spend keys outlive their day so late readers still see the total.
+const DAILY_SPEND_TTL_SECS: u64 = 2 * 86_400;
+const MONTHLY_SPEND_TTL_SECS: u64 = 35 * 86_400;
+/// Minimum time between fleet spend refreshes for one provider.
+const SPEND_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+/// Cooldown reads sit in front of every LLM call, so Redis gets little time.
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(75);
+/// How long Redis is left alone after a failed command or connection.
+const BREAKER_OPEN_FOR: Duration = Duration::from_secs(10);
+
+/// Where limiter state lives.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SharedStateBackend {
+    /// Per-process only.
+    #[default]
+    Memory,
+    Redis,
+}
+
+impl std::str::FromStr for SharedStateBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "memory" => Ok(Self::Memory),
+            "redis" => Ok(Self::Redis),
+            other => Err(format!(
+                "unknown shared state backend {other:?}: must be \"memory\" or \"redis\""
+            )),
+        }
+    }
+}
+
+/// Shared limiter settings (instance-level, under `[llm.shared_state]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SharedStateConfig {
+    pub backend: SharedStateBackend,
+    /// `redis[s]://[[username]:password@]host[:port][/database]`. Required for `redis`.
+    pub redis_url: Option<String>,
+    /// Prefix for every key, so several fleets can share one Redis.
+    pub key_prefix: String,
+}
+
+impl Default for SharedStateConfig {
+    fn default() -> Self {
+        Self {
+            backend: SharedStateBackend::Memory,
+            redis_url: None,
+            key_prefix: "spacebot".into(),
+        }
+    }
+}
+
+/// Cooldowns and spend totals in Redis.
+#[derive(Debug)]
+pub struct SharedLimits {
+    client: RedisClient,
+    key_prefix: String,
+    /// When each provider's fleet spend was last pulled.
+    refreshed: Mutex<HashMap<String, Instant>>,
+    breaker: Arc<Breaker>,
+}
+
+/// Whether Redis is worth asking, shared with the task that connects to it.
+#[derive(Debug, Default)]
+struct Breaker {
+    /// Redis is skipped until then.
+    open_until: Mutex<Option<Instant>>,
+    connecting: AtomicBool,
+}
+
+impl Breaker {
+    fn is_open(&self) -> bool {
+        let open_until = self
+            .open_until
+            .lock()
+            .expect("shared state breaker poisoned");
+        open_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    fn trip(&self) {
+        *self
+            .open_until
+            .lock()
+            .expect("shared state breaker poisoned") = Some(Instant::now() + BREAKER_OPEN_FOR);
+    }
+}
+
+impl SharedLimits {
+    /// Build the shared backend, or `None` for the in-memory default.
+    /// Doesn't connect; the first [`available`](Self::available) check does.
+    pub fn from_config(config: &SharedStateConfig) -> Result<Option<Self>> {
+        let (SharedStateBackend::Redis, Some(redis_url)) = (config.backend, &config.redis_url)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            client: RedisClient::from_url(redis_url)?.with_command_timeout(COMMAND_TIMEOUT),
+            key_prefix: config.key_prefix.clone(),
+            refreshed: Mutex::new(HashMap::new()),
+            breaker: Arc::default(),
+        }))
+    }
+
+    /// Whether to ask Redis now: it's connected and hasn't failed recently.
+    /// Starts connecting in the background if it isn't connected yet, so no
+    /// caller waits on a connection.
+    pub fn available(&self) -> bool {
+        if self.breaker.is_open() {
+            return false;
+        }
+        if self.client.is_connected() {
+            return true;
+        }
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            return false;
+        };
+        if !self.breaker.connecting.swap(true, Ordering::AcqRel) {
+            let client = self.client.clone();
+            let breaker = self.breaker.clone();
+            handle.spawn(async move {
+                if let Err(error) = client.connect().await {
+                    tracing::warn!(%error, "can't connect to shared limiter state, using local state");
+                    breaker.trip();
+                }
+                breaker.connecting.store(false, Ordering::Release);
+            });
+        }
+        false
+    }
+
+    /// Open the breaker when a command fails.
+    fn track<T>(&self, result: Result<T>) -> Result<T> {
+        if result.is_err() {
+            self.breaker.trip();
+        }
+        result
+    }
+
+    fn rate_limit_key(&self, model_name: &str) -> String {
+        format!("{}:ratelimit:{model_name}", self.key_prefix)
+    }
+
+    /// Daily and monthly spend keys for the period containing `now`.
+    fn spend_keys(&self, provider: &str, now: chrono::DateTime<chrono::Utc>) -> (String, String) {
+        (
+            format!(
+                "{}:spend:{provider}:{}",
+                self.key_prefix,
+                now.format("%Y-%m-%d")
+            ),
+            format!(
+                "{}:spend:{provider}:{}",
+                self.key_prefix,
+                now.format("%Y-%m")
+            ),
+        )
+    }
+
+    fn provider_rate_limit_key(&self, provider: &str) -> String {
+        format!("{}:ratelimit-provider:{provider}", self.key_prefix)
+    }
+
+    /// Publish a model's cooldown, and its provider's when the provider is
+    /// cooling down too. Each record expires with its cooldown.
+    pub async fn record_rate_limit(
+        &self,
+        model_name: &str,
+        cooldown: Duration,
+        provider_cooldown: Option<Duration>,
+    ) -> Result<()> {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let record = |key: String, cooldown: Duration| {
+            let millis = cooldown.as_millis().max(1) as i64;
+            [key, (now_ms + millis).to_string(), millis.to_string()]
+        };
+        let mut records = vec![record(self.rate_limit_key(model_name), cooldown)];
+        if let Some(provider_cooldown) = provider_cooldown {
+            let provider = crate::llm::routing::provider_from_model(model_name);
+            records.push(record(
+                self.provider_rate_limit_key(provider),
+                provider_cooldown,
+            ));
+        }
+        let commands: Vec<[&str; 5]> = records
+            .iter()
+            .map(|[key, until, ttl]| ["SET", key.as_str(), until.as_str(), "PX", ttl.as_str()])
+            .collect();
+        let commands: Vec<&[&str]> = commands.iter().map(|command| &command[..]).collect();
+        self.track(self.client.pipeline(&commands).await)?;
+        Ok(())
+    }
+
+    /// Time left on the fleet's cooldowns for a model and for its provider.
+    pub async fn cooldowns_for(
+        &self,
+        model_name: &str,
+    ) -> Result<(Option<Duration>, Option<Duration>)> {
+        let provider = crate::llm::routing::provider_from_model(model_name);
+        let reply = self.track(
+            self.client
+                .command(&[
+                    "MGET",
+                    &self.rate_limit_key(model_name),
+                    &self.provider_rate_limit_key(provider),
+                ])
+                .await,
+        )?;
+        let values = match reply {
+            crate::db::redis::Reply::Array(Some(values)) => values,
+            _ => Vec::new(),
+        };
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let remaining = |index: usize| {
+            let until_ms: i64 = values
+                .get(index)
+                .cloned()
+                .and_then(crate::db::redis::Reply::into_string)?
+                .parse()
+                .ok()?;
+            (until_ms > now_ms).then(|| Duration::from_millis((until_ms - now_ms) as u64))
+        };
+        Ok((remaining(0), remaining(1)))
+    }
+
+    /// Add spend for a provider and return the fleet's new totals.
+    pub async fn add_spend(&self, provider: &str, cost_usd: f64) -> Result<ProviderSpend> {
+        let (daily_key, monthly_key) = self.spend_keys(provider, chrono::Utc::now());
+        let cost = cost_usd.to_string();
+        let daily_ttl = DAILY_SPEND_TTL_SECS.to_string();
+        let monthly_ttl = MONTHLY_SPEND_TTL_SECS.to_string();
+        let commands: [&[&str]; 4] = [
+            &["INCRBYFLOAT", &daily_key, &cost],
+            &["EXPIRE", &daily_key, &daily_ttl],
+            &["INCRBYFLOAT", &monthly_key, &cost],
+            &["EXPIRE", &monthly_key, &monthly_ttl],
+        ];
+        let replies = self.track(self.client.pipeline(&commands).await)?;
+        let total = |index: usize| {
+            replies[index]
+                .clone()
+                .into_string()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0.0)
+        };
+        Ok(ProviderSpend {
+            daily_usd: total(0),
+            monthly_usd: total(2),
+        })
+    }
+
+    /// The fleet's current totals for a provider.
+    pub async fn spend(&self, provider: &str) -> Result<ProviderSpend> {
+        let (daily_key, monthly_key) = self.spend_keys(provider, chrono::Utc::now());
+        let reply = self.track(
+            self.client
+                .command(&["MGET", &daily_key, &monthly_key])
+                .await,
+        )?;
+        let values = match reply {
+            crate::db::redis::Reply::Array(Some(values)) => values,
+            _ => Vec::new(),
+        };
+        let total = |index: usize| {
+            values
+                .get(index)
+                .cloned()
+                .and_then(crate::db::redis::Reply::into_string)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0.0)
+        };
+        Ok(ProviderSpend {
+            daily_usd: total(0),
+            monthly_usd: total(1),
+        })
+    }
+
+    /// Whether a provider's fleet spend is due for a refresh. Marks it
+    /// refreshed, so concurrent callers don't all fetch.
+    pub fn spend_refresh_due(&self, provider: &str) -> bool {
+        let mut refreshed = self
+            .refreshed
+            .lock()
+            .expect("shared spend refresh state poisoned");
+        match refreshed.get(provider) {
+            Some(at) if at.elapsed() < SPEND_REFRESH_INTERVAL => false,
+            _ => {
+                refreshed.insert(provider.to_string(), Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Forget refresh times old enough that the refresh is due anyway.
+    pub fn prune_refreshed(&self) -> usize {
+        let mut refreshed = self
+            .refreshed
+            .lock()
+            .expect("shared spend refresh state poisoned");
+        let before = refreshed.len();
+        refreshed.retain(|_, at| at.elapsed() < SPEND_REFRESH_INTERVAL);
+        before - refreshed.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redis_limits() -> SharedLimits {
+        SharedLimits::from_config(&SharedStateConfig {
+            backend: SharedStateBackend::Redis,
+            redis_url: Some("redis://localhost:6379".into()),
+            key_prefix: "fleet-a".into(),
+        })
+        .expect("config should be valid")
+        .expect("redis backend should be built")
+    }
+
+    #[test]
+    fn memory_backend_builds_nothing() {
+        let limits = SharedLimits::from_config(&SharedStateConfig::default())
+            .expect("default config should be valid");
+        assert!(limits.is_none());
+    }
+
+    #[test]
+    fn keys_are_prefixed_and_scoped_to_the_period() {
+        let limits = redis_limits();
+        assert_eq!(
+            limits.rate_limit_key("openai/gpt-4.1"),
+            "fleet-a:ratelimit:openai/gpt-4.1"
+        );
+        assert_eq!(
+            limits.provider_rate_limit_key("openai"),
+            "fleet-a:ratelimit-provider:openai"
+        );
+
+        let now = chrono::DateTime::parse_from_rfc3339("2026-03-09T23:59:00Z")
+            .expect("timestamp should parse")
+            .with_timezone(&chrono::Utc);
+        assert_eq!(
+            limits.spend_keys("openai", now),
+            (
+                "fleet-a:spend:openai:2026-03-09".to_string(),
+                "fleet-a:spend:openai:2026-03".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn spend_refreshes_are_throttled_per_provider() {
+        let limits = redis_limits();
+        assert!(limits.spend_refresh_due("openai"));
+        assert!(!limits.spend_refresh_due("openai"));
+        assert!(limits.spend_refresh_due("anthropic"));
+    }
+
+    #[test]
+    fn failures_open_the_breaker() {
+        let limits = redis_limits();
+        // Not connected, and no runtime to connect from.
+        assert!(!limits.available());
+        assert!(!limits.breaker.is_open());
+
+        let failed: Result<()> = Err(crate::error::DbError::Redis("down".into()).into());
+        assert!(limits.track(failed).is_err());
+        assert!(limits.breaker.is_open());
+        assert!(!limits.available());
+    }
+}
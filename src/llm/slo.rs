@@ -0,0 +1,288 @@
+//! Latency SLOs and burn-rate alerts.
+//!
+//! `[slo.objectives.<route>]` sets a latency objective for a route, like "95%
+//! of channel replies within 8 seconds". Each request on the route either
+//! meets the threshold or misses it, and the objective allows a share of
+//! misses: its error budget (5% for p95). The burn rate is the share that
+//! missed divided by that budget, so at 1.0 the budget is spent exactly as
+//! fast as the objective allows.
+//!
+//! A route is burning when its burn rate is at least `burn_rate` both over the
+//! last `window_secs` and over the last twelfth of it. The long window keeps
+//! a few slow replies from paging anyone; the short one means the alert
+//! clears soon after latency recovers. Starting and stopping to burn each
+//! send an alert to `alert_target` and publish an `slo_alert` event, which
+//! `[[event_webhooks]]` can forward.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Channel turns for user messages, from receipt to the end of the turn.
+pub const CHANNEL_REPLY: &str = "channel_reply";
+
+/// LLM completion calls that succeeded, from every process.
+pub const COMPLETION: &str = "completion";
+
+/// Routes objectives can be set for.
+pub const ROUTES: &[&str] = &[CHANNEL_REPLY, COMPLETION];
+
+/// Latency objectives and alerting (instance-level, under `[slo]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SloConfig {
+    /// "adapter:target" that alerts are posted to.
+    pub alert_target: Option<String>,
+    /// Length of the long burn-rate window. The short one is a twelfth of it.
+    pub window_secs: u64,
+    /// Burn rate both windows must reach for a route to be burning.
+    pub burn_rate: f64,
+    /// Requests the long window needs before a route can be burning.
+    pub min_requests: usize,
+    pub objectives: BTreeMap<String, LatencyObjective>,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            alert_target: None,
+            window_secs: 3600,
+            burn_rate: 2.0,
+            min_requests: 20,
+            objectives: BTreeMap::new(),
+        }
+    }
+}
+
+/// "`percentile`% of requests within `threshold_secs`".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyObjective {
+    pub percentile: f64,
+    pub threshold_secs: f64,
+}
+
+impl LatencyObjective {
+    /// Share of requests allowed to miss the threshold.
+    fn error_budget(&self) -> f64 {
+        1.0 - self.percentile / 100.0
+    }
+}
+
+impl std::fmt::Display for LatencyObjective {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "p{} < {}s", self.percentile, self.threshold_secs)
+    }
+}
+
+/// An operator-facing notice that a route started or stopped burning its
+/// error budget too fast.
+#[derive(Debug, Clone)]
+pub struct SloAlert {
+    pub route: String,
+    pub objective: LatencyObjective,
+    pub burning: bool,
+    /// Burn rate over the long window.
+    pub burn_rate: f64,
+    /// Latency at the objective's percentile over the long window.
+    pub observed_secs: f64,
+    /// Requests in the long window.
+    pub requests: usize,
+    pub window_secs: u64,
+}
+
+impl std::fmt::Display for SloAlert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let headline = if self.burning {
+            format!(
+                "SLO alert: `{}` ({}) is burning its error budget {:.1}x too fast.",
+                self.route, self.objective, self.burn_rate
+            )
+        } else {
+            format!(
+                "SLO recovered: `{}` ({}) is burning its error budget at {:.1}x.",
+                self.route, self.objective, self.burn_rate
+            )
+        };
+        write!(
+            f,
+            "{headline} p{} was {:.1}s over the last {} min ({} requests).",
+            self.objective.percentile,
+            self.observed_secs,
+            self.window_secs / 60,
+            self.requests
+        )
+    }
+}
+
+#[derive(Default)]
+struct RouteWindow {
+    /// When each request finished, and its latency in seconds.
+    samples: VecDeque<(Instant, f64)>,
+    burning: bool,
+}
+
+/// Recent latencies per route with an objective.
+#[derive(Default)]
+pub struct SloTracker {
+    routes: Mutex<HashMap<String, RouteWindow>>,
+}
+
+impl SloTracker {
+    /// Record one request on `route` that took `latency` and finished at
+    /// `now`. Returns an alert when the route starts or stops burning.
+    /// Routes without an objective aren't kept.
+    pub fn record(
+        &self,
+        config: &SloConfig,
+        route: &str,
+        latency: Duration,
+        now: Instant,
+    ) -> Option<SloAlert> {
+        let objective = *config.objectives.get(route)?;
+        let long = Duration::from_secs(config.window_secs);
+        let short = long / 12;
+
+        let mut routes = self.routes.lock().expect("slo tracker lock poisoned");
+        let window = routes.entry(route.to_string()).or_default();
+        window.samples.push_back((now, latency.as_secs_f64()));
+        while window
+            .samples
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > long)
+        {
+            window.samples.pop_front();
+        }
+
+        let latencies = |within: Duration| {
+            window
+                .samples
+                .iter()
+                .filter(move |(at, _)| now.duration_since(*at) <= within)
+                .map(|(_, latency)| *latency)
+        };
+        let long_rate = burn_rate(latencies(long), objective);
+        let short_rate = burn_rate(latencies(short), objective);
+        let requests = window.samples.len();
+        let burning = requests >= config.min_requests
+            && long_rate >= config.burn_rate
+            && short_rate >= config.burn_rate;
+        if burning == window.burning {
+            return None;
+        }
+        window.burning = burning;
+
+        Some(SloAlert {
+            route: route.to_string(),
+            objective,
+            burning,
+            burn_rate: long_rate,
+            observed_secs: percentile(latencies(long).collect(), objective.percentile),
+            requests,
+            window_secs: config.window_secs,
+        })
+    }
+}
+
+/// Share of `latencies` over the threshold, as a multiple of the error
+/// budget.
+fn burn_rate(latencies: impl Iterator<Item = f64>, objective: LatencyObjective) -> f64 {
+    let (total, missed) = latencies.fold((0_usize, 0_usize), |(total, missed), latency| {
+        (
+            total + 1,
+            missed + usize::from(latency > objective.threshold_secs),
+        )
+    });
+    if total == 0 {
+        return 0.0;
+    }
+    missed as f64 / total as f64 / objective.error_budget()
+}
+
+/// The nearest-rank `percentile` of `latencies`.
+fn percentile(mut latencies: Vec<f64>, percentile: f64) -> f64 {
+    if latencies.is_empty() {
+        return 0.0;
+    }
+    latencies.sort_by(f64::total_cmp);
+    let rank = (percentile / 100.0 * latencies.len() as f64).ceil() as usize;
+    latencies[rank.clamp(1, latencies.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SloConfig {
+        SloConfig {
+            min_requests: 10,
+            objectives: BTreeMap::from([(
+                CHANNEL_REPLY.to_string(),
+                LatencyObjective {
+                    percentile: 95.0,
+                    threshold_secs: 8.0,
+                },
+            )]),
+            ..SloConfig::default()
+        }
+    }
+
+    #[test]
+    fn a_route_burns_once_both_windows_miss_and_recovers_when_the_short_one_clears() {
+        let (config, tracker, start) = (config(), SloTracker::default(), Instant::now());
+        let record = |secs: u64, latency: f64| {
+            tracker.record(
+                &config,
+                CHANNEL_REPLY,
+                Duration::from_secs_f64(latency),
+                start + Duration::from_secs(secs),
+            )
+        };
+
+        // 5% slow is exactly the budget.
+        for index in 0..19 {
+            assert!(record(index, 2.0).is_none());
+        }
+        assert!(record(19, 12.0).is_none());
+
+        // Two more slow replies: 3 of 22 missed, a burn rate of 2.7.
+        assert!(record(20, 12.0).is_none());
+        let alert = record(21, 12.0).expect("burning alert");
+        assert!(alert.burning);
+        assert_eq!(alert.requests, 22);
+        assert!((alert.burn_rate - 3.0 / 22.0 / 0.05).abs() < 1e-9);
+        assert_eq!(alert.observed_secs, 12.0);
+
+        // Five minutes later the short window only holds fast replies.
+        let alert = record(21 + 301, 2.0).expect("recovered alert");
+        assert!(!alert.burning);
+        assert!(record(21 + 302, 2.0).is_none());
+
+        assert!(
+            tracker
+                .record(&config, COMPLETION, Duration::from_secs(60), start)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn alerts_name_the_objective_and_observed_latency() {
+        let alert = SloAlert {
+            route: CHANNEL_REPLY.to_string(),
+            objective: LatencyObjective {
+                percentile: 95.0,
+                threshold_secs: 8.0,
+            },
+            burning: true,
+            burn_rate: 3.24,
+            observed_secs: 12.43,
+            requests: 140,
+            window_secs: 3600,
+        };
+        assert_eq!(
+            alert.to_string(),
+            "SLO alert: `channel_reply` (p95 < 8s) is burning its error budget 3.2x too fast. \
+             p95 was 12.4s over the last 60 min (140 requests)."
+        );
+        assert_eq!(percentile(vec![3.0, 1.0, 2.0, 10.0], 50.0), 2.0);
+        assert_eq!(percentile(vec![3.0, 1.0, 2.0, 10.0], 99.0), 10.0);
+    }
+}
@@ -0,0 +1,248 @@
+//! Structured output: JSON mode, JSON schemas, and GBNF grammars.
+//!
+//! Callers request a format by setting `additional_params` on a completion
+//! request (or agent) to `OutputFormat::to_params()`. `SpacebotModel` turns it
+//! into the provider's native mechanism — `response_format` for OpenAI and
+//! Ollama, `text.format` for the Responses API, `grammar` for llama.cpp
+//! servers, schema instructions for Anthropic — and checks the reply parses,
+//! so local and cloud models honor the same contract.
+
+use rig::completion::{AssistantContent, CompletionError, CompletionRequest};
+use rig::one_or_many::OneOrMany;
+use serde::{Deserialize, Serialize};
+
+/// Key under `additional_params` that carries the requested format.
+const PARAMS_KEY: &str = "spacebot_output_format";
+
+/// The shape a completion's text must take.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Any valid JSON value.
+    Json,
+    /// JSON matching a JSON Schema. `name` identifies the schema to providers
+    /// that require one.
+    JsonSchema {
+        name: String,
+        schema: serde_json::Value,
+    },
+    /// Text matching a GBNF grammar. Only llama.cpp-compatible servers support this.
+    Grammar { gbnf: String },
+}
+
+impl OutputFormat {
+    /// `additional_params` value requesting this format.
+    pub fn to_params(&self) -> serde_json::Value {
+        serde_json::json!({ PARAMS_KEY: self })
+    }
+
+    /// The format requested on a completion request, if any.
+    pub fn from_request(request: &CompletionRequest) -> Result<Option<Self>, CompletionError> {
+        let Some(value) = request
+            .additional_params
+            .as_ref()
+            .and_then(|params| params.get(PARAMS_KEY))
+        else {
+            return Ok(None);
+        };
+        serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|error| {
+                CompletionError::RequestError(format!("invalid {PARAMS_KEY}: {error}").into())
+            })
+    }
+
+    fn expects_json(&self) -> bool {
+        matches!(self, Self::Json | Self::JsonSchema { .. })
+    }
+
+    /// Set the format on an OpenAI-style chat completions body.
+    ///
+    /// Ollama's OpenAI-compatible endpoint maps `response_format` onto its
+    /// native `format` option, so JSON mode and schemas work there too.
+    pub fn apply_to_chat_body(
+        &self,
+        body: &mut serde_json::Value,
+        provider: &str,
+    ) -> Result<(), CompletionError> {
+        match self {
+            Self::Json => {
+                body["response_format"] = serde_json::json!({ "type": "json_object" });
+            }
+            Self::JsonSchema { name, schema } => {
+                body["response_format"] = serde_json::json!({
+                    "type": "json_schema",
+                    "json_schema": { "name": name, "schema": schema, "strict": true },
+                });
+            }
+            Self::Grammar { .. } if provider == "ollama" => {
+                return Err(unsupported_grammar(provider));
+            }
+            Self::Grammar { gbnf } => {
+                body["grammar"] = serde_json::json!(gbnf);
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the format on an OpenAI Responses API body.
+    pub fn apply_to_responses_body(
+        &self,
+        body: &mut serde_json::Value,
+        provider: &str,
+    ) -> Result<(), CompletionError> {
+        body["text"] = match self {
+            Self::Json => serde_json::json!({ "format": { "type": "json_object" } }),
+            Self::JsonSchema { name, schema } => serde_json::json!({
+                "format": { "type": "json_schema", "name": name, "schema": schema, "strict": true },
+            }),
+            Self::Grammar { .. } => return Err(unsupported_grammar(provider)),
+        };
+        Ok(())
+    }
+
+    /// Instruct providers without a native JSON mode through the preamble.
+    pub fn apply_to_preamble(
+        &self,
+        request: &mut CompletionRequest,
+        provider: &str,
+    ) -> Result<(), CompletionError> {
+        let instruction = match self {
+            Self::Json => "Respond with a single valid JSON value and nothing else.".to_string(),
+            Self::JsonSchema { schema, .. } => format!(
+                "Respond with a single JSON value matching this JSON Schema and nothing else:\n{schema}"
+            ),
+            Self::Grammar { .. } => return Err(unsupported_grammar(provider)),
+        };
+        request.preamble = Some(match request.preamble.take() {
+            Some(preamble) => format!("{preamble}\n\n{instruction}"),
+            None => instruction,
+        });
+        Ok(())
+    }
+
+    /// Check a reply's text against the format.
+    ///
+    /// Replies that only call tools are left alone. Schemas are checked for
+    /// top-level `required` properties only; full validation is the provider's job.
+    pub fn validate(&self, choice: &OneOrMany<AssistantContent>) -> Result<(), CompletionError> {
+        if !self.expects_json() {
+            return Ok(());
+        }
+        let text: String = choice
+            .iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect();
+        if text.trim().is_empty() {
+            return Ok(());
+        }
+
+        let value: serde_json::Value =
+            serde_json::from_str(strip_code_fence(&text)).map_err(|error| {
+                CompletionError::ResponseError(format!("model returned invalid JSON: {error}"))
+            })?;
+
+        if let Self::JsonSchema { schema, .. } = self
+            && let Some(required) = schema["required"].as_array()
+        {
+            let missing: Vec<&str> = required
+                .iter()
+                .filter_map(|key| key.as_str())
+                .filter(|key| value.get(key).is_none())
+                .collect();
+            if !missing.is_empty() {
+                return Err(CompletionError::ResponseError(format!(
+                    "model output is missing required fields: {}",
+                    missing.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unsupported_grammar(provider: &str) -> CompletionError {
+    CompletionError::RequestError(
+        format!("{provider} doesn't support GBNF grammars; use a JSON schema instead").into(),
+    )
+}
+
+/// Models prompted (rather than constrained) into JSON sometimes wrap it in a
+/// markdown fence.
+pub(crate) fn strip_code_fence(text: &str) -> &str {
+    let trimmed = text.trim();
+    trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(str::trim)
+        .unwrap_or(trimmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rig::message::Text;
+
+    fn schema_format() -> OutputFormat {
+        OutputFormat::JsonSchema {
+            name: "verdict".into(),
+            schema: serde_json::json!({
+                "type": "object",
+                "properties": { "answer": { "type": "string" } },
+                "required": ["answer"],
+            }),
+        }
+    }
+
+    fn text(content: &str) -> OneOrMany<AssistantContent> {
+        OneOrMany::one(AssistantContent::Text(Text {
+            text: content.to_string(),
+        }))
+    }
+
+    #[test]
+    fn params_round_trip_through_requests() {
+        let params = schema_format().to_params();
+        let format: OutputFormat =
+            serde_json::from_value(params[PARAMS_KEY].clone()).expect("params should parse");
+        assert_eq!(format, schema_format());
+    }
+
+    #[test]
+    fn chat_bodies_get_native_formats() {
+        let mut body = serde_json::json!({});
+        schema_format()
+            .apply_to_chat_body(&mut body, "ollama")
+            .expect("schemas work on ollama");
+        assert_eq!(body["response_format"]["type"], "json_schema");
+        assert_eq!(body["response_format"]["json_schema"]["name"], "verdict");
+
+        let grammar = OutputFormat::Grammar {
+            gbnf: "root ::= \"yes\" | \"no\"".into(),
+        };
+        let mut body = serde_json::json!({});
+        grammar
+            .apply_to_chat_body(&mut body, "llama-cpp")
+            .expect("grammars pass through to llama.cpp");
+        assert_eq!(body["grammar"], "root ::= \"yes\" | \"no\"");
+        assert!(grammar.apply_to_chat_body(&mut body, "ollama").is_err());
+    }
+
+    #[test]
+    fn replies_are_validated_against_the_format() {
+        let format = schema_format();
+        assert!(format.validate(&text(r#"{"answer": "42"}"#)).is_ok());
+        assert!(
+            format
+                .validate(&text("```json\n{\"answer\": \"42\"}\n```"))
+                .is_ok()
+        );
+        assert!(format.validate(&text(r#"{"other": 1}"#)).is_err());
+        assert!(format.validate(&text("not json")).is_err());
+        assert!(OutputFormat::Json.validate(&text("[1, 2]")).is_ok());
+    }
+}
@@ -0,0 +1,192 @@
+//! Log levels that can be changed while Spacebot runs.
+//!
+//! The log filter is installed behind a reload handle, so `!admin loglevel`
+//! and `PUT /api/system/log-levels` can turn one module up to `debug` to
+//! chase a problem and back down again without a restart. Changes layer
+//! overrides on the level Spacebot started with (`info`, or `debug` with
+//! `--debug`) and last until `reset` or the process exits.
+
+use tracing_subscriber::{EnvFilter, Registry, reload};
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+/// Why nothing can change when tracing was set up without [`reloadable`].
+pub const NOT_RELOADABLE: &str = "log levels can't be changed in this process";
+
+static CONTROL: OnceLock<Control> = OnceLock::new();
+
+struct Control {
+    handle: reload::Handle<EnvFilter, Registry>,
+    startup: String,
+    levels: Mutex<Levels>,
+}
+
+/// The default level plus per-module overrides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Levels {
+    default: String,
+    modules: BTreeMap<String, String>,
+}
+
+impl Levels {
+    fn directives(&self) -> String {
+        std::iter::once(self.default.clone())
+            .chain(
+                self.modules
+                    .iter()
+                    .map(|(module, level)| format!("{module}={level}")),
+            )
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Apply `llm=debug`-style changes, separated by spaces or commas. A bare
+    /// level changes the default.
+    fn apply(&mut self, changes: &str) -> Result<(), String> {
+        let changes: Vec<&str> = changes
+            .split([' ', ','])
+            .filter(|change| !change.is_empty())
+            .collect();
+        if changes.is_empty() {
+            return Err("expected changes like `llm=debug` or `warn`".into());
+        }
+        for change in changes {
+            let (module, level) = match change.split_once('=') {
+                Some((module, level)) => (Some(module), level),
+                None => (None, change),
+            };
+            let level = level.to_ascii_lowercase();
+            if !LEVELS.contains(&level.as_str()) {
+                return Err(format!(
+                    "unknown level '{level}', expected one of {}",
+                    LEVELS.join(", ")
+                ));
+            }
+            match module {
+                Some(module) => {
+                    let module = module_target(module)?;
+                    self.modules.insert(module, level);
+                }
+                None => self.default = level,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The tracing target for a module. Paths are under Spacebot (`llm` is
+/// `spacebot::llm`) unless they start with `::`, which names another crate.
+fn module_target(module: &str) -> Result<String, String> {
+    let valid = !module.trim_start_matches(':').is_empty()
+        && module
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric() || "_:".contains(character));
+    if !valid {
+        return Err(format!("can't use '{module}' as a module name"));
+    }
+    if let Some(other_crate) = module.strip_prefix("::") {
+        Ok(other_crate.to_string())
+    } else if module == "spacebot" || module.starts_with("spacebot::") {
+        Ok(module.to_string())
+    } else {
+        Ok(format!("spacebot::{module}"))
+    }
+}
+
+/// The filter layer to install first on the registry, starting at
+/// `default_level`. Later calls return a filter that can't be changed.
+pub fn reloadable(default_level: &str) -> reload::Layer<EnvFilter, Registry> {
+    let (layer, handle) = reload::Layer::new(EnvFilter::new(default_level));
+    let _ = CONTROL.set(Control {
+        handle,
+        startup: default_level.to_string(),
+        levels: Mutex::new(Levels {
+            default: default_level.to_string(),
+            modules: BTreeMap::new(),
+        }),
+    });
+    layer
+}
+
+/// The filter in effect, in `EnvFilter` syntax.
+pub fn current() -> Option<String> {
+    let control = CONTROL.get()?;
+    Some(control.levels.lock().expect("log levels lock").directives())
+}
+
+/// Apply `llm=debug`-style changes on top of the current levels. Returns
+/// the new filter.
+pub fn set(changes: &str) -> Result<String, String> {
+    let control = CONTROL.get().ok_or(NOT_RELOADABLE)?;
+    let mut levels = control.levels.lock().expect("log levels lock");
+    let mut changed = levels.clone();
+    changed.apply(changes)?;
+    control.install(&mut levels, changed)
+}
+
+/// Go back to the level Spacebot started with. Returns the new filter.
+pub fn reset() -> Result<String, String> {
+    let control = CONTROL.get().ok_or(NOT_RELOADABLE)?;
+    let mut levels = control.levels.lock().expect("log levels lock");
+    let startup = Levels {
+        default: control.startup.clone(),
+        modules: BTreeMap::new(),
+    };
+    control.install(&mut levels, startup)
+}
+
+impl Control {
+    fn install(&self, levels: &mut Levels, changed: Levels) -> Result<String, String> {
+        let directives = changed.directives();
+        let filter = EnvFilter::try_new(&directives).map_err(|error| error.to_string())?;
+        self.handle
+            .reload(filter)
+            .map_err(|error| format!("can't change the log filter: {error}"))?;
+        tracing::info!(filter = %directives, "log levels changed");
+        *levels = changed;
+        Ok(directives)
+    }
+}
+
+/// Run `!admin loglevel [changes|reset]` and describe the result.
+pub fn command(args: &str) -> String {
+    let result = match args {
+        "" => current().ok_or_else(|| NOT_RELOADABLE.to_string()),
+        "reset" => reset(),
+        changes => set(changes),
+    };
+    match result {
+        Ok(directives) if args.is_empty() => format!("Log filter: `{directives}`"),
+        Ok(directives) => format!("Log filter is now `{directives}`"),
+        Err(error) => format!("Can't change log levels: {error}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_layer_on_the_default() {
+        let mut levels = Levels {
+            default: "info".into(),
+            modules: BTreeMap::new(),
+        };
+        levels.apply("llm=debug").unwrap();
+        levels.apply("messaging::discord=TRACE, warn").unwrap();
+        levels.apply("llm=info ::hyper::proto=debug").unwrap();
+        assert_eq!(
+            levels.directives(),
+            "warn,hyper::proto=debug,spacebot::llm=info,spacebot::messaging::discord=trace"
+        );
+
+        let before = levels.clone();
+        assert!(levels.clone().apply("llm=loud").is_err());
+        assert!(levels.clone().apply("llm[x]=debug").is_err());
+        assert!(levels.clone().apply(" ").is_err());
+        assert_eq!(levels, before);
+    }
+}
@@ -0,0 +1,534 @@
+//! Per-conversation correlation IDs and the turn log buffer.
+//!
+//! Every inbound message gets a short correlation ID in its metadata. The
+//! channel records it on the span that handles the turn, so routing, LLM
+//! calls, tool calls, branches, and workers spawned from that turn all log
+//! under it, and the outbound reply carries it as a field. [`CorrelationLayer`]
+//! keeps the formatted events for the most recent turns in memory, which is
+//! what `!debug last` prints.
+//!
+//! Both the log output and the turn buffer go through the same redaction:
+//! Authorization headers, API keys in URL query strings, and known credential
+//! formats are always replaced, and message content fields are truncated or
+//! hashed according to `telemetry.log_content`.
+
+use regex::Regex;
+use sha2::Digest as _;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::field::RecordFields;
+use tracing_subscriber::fmt::FormatFields;
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{LazyLock, Mutex};
+
+/// Metadata key and tracing field name carrying the correlation ID.
+pub const CORRELATION_ID_KEY: &str = "correlation_id";
+
+/// Turns whose logs are kept; older turns are evicted first.
+const MAX_TURNS: usize = 64;
+
+/// Lines kept per turn. Later events in a very long turn are dropped.
+const MAX_LINES_PER_TURN: usize = 200;
+
+/// Characters of message content kept under [`LogContent::Truncate`].
+const TRUNCATED_CONTENT_CHARS: usize = 64;
+
+/// Fields that carry message, prompt, or tool payload text.
+const CONTENT_FIELDS: &[&str] = &[
+    "content",
+    "text",
+    "prompt",
+    "user_text",
+    "response",
+    "body",
+    "args",
+    "result",
+];
+
+const REDACTED: &str = "[REDACTED]";
+
+static TURN_LOGS: LazyLock<TurnLogs> = LazyLock::new(TurnLogs::default);
+
+/// Authorization-style headers in `Debug` output, JSON, or header dumps.
+static AUTH_HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)\b((?:proxy-)?authorization|x-api-key|api-key|x-goog-api-key)(["']?\s*[:=]\s*["']?)(?:(bearer|basic)\s+)?[^\s"',;}]+"#,
+    )
+    .expect("hardcoded regex")
+});
+
+/// Credentials passed as URL query parameters, which reqwest errors echo.
+static QUERY_SECRET: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)([?&](?:key|api_key|apikey|api-key|access_token|token|secret)=)[^&#\s"')]+"#)
+        .expect("hardcoded regex")
+});
+
+/// A new 12-character correlation ID.
+pub fn new_correlation_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..12].to_string()
+}
+
+/// The correlation ID in a message's metadata, if one was assigned.
+pub fn correlation_id(metadata: &HashMap<String, serde_json::Value>) -> Option<&str> {
+    metadata
+        .get(CORRELATION_ID_KEY)
+        .and_then(|value| value.as_str())
+}
+
+/// The message's correlation ID, assigning a new one if it has none.
+pub fn ensure_correlation_id(metadata: &mut HashMap<String, serde_json::Value>) -> String {
+    if let Some(existing) = correlation_id(metadata) {
+        return existing.to_string();
+    }
+    let id = new_correlation_id();
+    metadata.insert(CORRELATION_ID_KEY.into(), id.clone().into());
+    id
+}
+
+/// How message content appears in logs (`telemetry.log_content`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogContent {
+    /// Log content as-is (credentials are still redacted).
+    Full,
+    /// Keep the first 64 characters, plus the length and a hash (default).
+    #[default]
+    Truncate,
+    /// Log only the length and a hash.
+    Hash,
+}
+
+impl std::fmt::Display for LogContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Full => write!(f, "full"),
+            Self::Truncate => write!(f, "truncate"),
+            Self::Hash => write!(f, "hash"),
+        }
+    }
+}
+
+impl std::str::FromStr for LogContent {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Self::Full),
+            "truncate" => Ok(Self::Truncate),
+            "hash" => Ok(Self::Hash),
+            _ => Err(format!("unknown log content mode: {s}")),
+        }
+    }
+}
+
+/// `text` with Authorization headers, API keys in query strings, and known
+/// credential formats replaced by `[REDACTED]`.
+pub fn redact_secrets(text: &str) -> String {
+    let text = AUTH_HEADER.replace_all(text, |captures: &regex::Captures| {
+        let scheme = captures
+            .get(3)
+            .map(|scheme| format!("{} ", scheme.as_str()))
+            .unwrap_or_default();
+        format!("{}{}{scheme}{REDACTED}", &captures[1], &captures[2])
+    });
+    let text = QUERY_SECRET.replace_all(&text, format!("${{1}}{REDACTED}"));
+    crate::secrets::patterns::replace_all(&text, REDACTED)
+}
+
+/// Message content as it should appear in logs under `mode`.
+pub fn summarize_content(text: &str, mode: LogContent) -> String {
+    let chars = text.chars().count();
+    match mode {
+        LogContent::Full => redact_secrets(text),
+        LogContent::Truncate if chars <= TRUNCATED_CONTENT_CHARS => redact_secrets(text),
+        LogContent::Truncate => {
+            let redacted = redact_secrets(text);
+            let head: String = redacted.chars().take(TRUNCATED_CONTENT_CHARS).collect();
+            format!("{head}… ({chars} chars, sha256:{})", short_hash(text))
+        }
+        LogContent::Hash => format!("<{chars} chars, sha256:{}>", short_hash(text)),
+    }
+}
+
+fn short_hash(text: &str) -> String {
+    let digest = format!("{:x}", sha2::Sha256::digest(text.as_bytes()));
+    digest[..12].to_string()
+}
+
+/// Field formatter for the fmt layer that applies the same redaction as the
+/// turn buffer.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RedactingFields {
+    content: LogContent,
+}
+
+impl RedactingFields {
+    pub fn new(content: LogContent) -> Self {
+        Self { content }
+    }
+}
+
+impl<'writer> FormatFields<'writer> for RedactingFields {
+    fn format_fields<R: RecordFields>(
+        &self,
+        mut writer: Writer<'writer>,
+        fields: R,
+    ) -> std::fmt::Result {
+        let mut visitor = EventFields::new(self.content);
+        fields.record(&mut visitor);
+
+        let mut parts = Vec::new();
+        if !visitor.message.is_empty() {
+            parts.push(visitor.message);
+        }
+        if let Some(correlation_id) = visitor.correlation_id {
+            parts.push(format!("{CORRELATION_ID_KEY}={correlation_id}"));
+        }
+        for (name, value) in visitor.rest {
+            parts.push(format!("{name}={value}"));
+        }
+        write!(writer, "{}", parts.join(" "))
+    }
+}
+
+/// In-memory log lines for recent turns, keyed by correlation ID.
+#[derive(Debug, Default)]
+pub struct TurnLogs {
+    turns: Mutex<VecDeque<(String, Vec<String>)>>,
+}
+
+impl TurnLogs {
+    /// The process-wide buffer [`CorrelationLayer`] writes to.
+    pub fn global() -> &'static Self {
+        &TURN_LOGS
+    }
+
+    pub fn record(&self, correlation_id: &str, line: String) {
+        let Ok(mut turns) = self.turns.lock() else {
+            return;
+        };
+        if let Some((_, lines)) = turns.iter_mut().rev().find(|(id, _)| id == correlation_id) {
+            if lines.len() < MAX_LINES_PER_TURN {
+                lines.push(line);
+            }
+            return;
+        }
+        if turns.len() == MAX_TURNS {
+            turns.pop_front();
+        }
+        turns.push_back((correlation_id.to_string(), vec![line]));
+    }
+
+    /// Log lines recorded under `correlation_id`, oldest first.
+    pub fn lines(&self, correlation_id: &str) -> Vec<String> {
+        self.turns
+            .lock()
+            .ok()
+            .and_then(|turns| {
+                turns
+                    .iter()
+                    .rev()
+                    .find(|(id, _)| id == correlation_id)
+                    .map(|(_, lines)| lines.clone())
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Tracing layer that copies events carrying a correlation ID, directly or
+/// through an enclosing span, into [`TurnLogs::global`].
+///
+/// Events are captured after the global level filter, so `--debug` is what
+/// makes LLM and tool call detail show up in the slice.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CorrelationLayer {
+    content: LogContent,
+}
+
+impl CorrelationLayer {
+    pub fn new(content: LogContent) -> Self {
+        Self { content }
+    }
+}
+
+/// Span extension holding the span's correlation ID.
+struct SpanCorrelation(String);
+
+/// The correlation ID of the turn running on this thread: the one recorded on
+/// the current span or the nearest enclosing span that has one.
+pub fn current_correlation_id() -> Option<String> {
+    let id = tracing::Span::current().id()?;
+    tracing::dispatcher::get_default(|dispatch| {
+        let registry = dispatch.downcast_ref::<tracing_subscriber::Registry>()?;
+        registry.span(&id)?.scope().find_map(|span| {
+            span.extensions()
+                .get::<SpanCorrelation>()
+                .map(|correlation| correlation.0.clone())
+        })
+    })
+}
+
+impl<S> Layer<S> for CorrelationLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = EventFields::new(self.content);
+        attrs.record(&mut fields);
+        if let Some(correlation_id) = fields.correlation_id
+            && let Some(span) = ctx.span(id)
+        {
+            span.extensions_mut()
+                .insert(SpanCorrelation(correlation_id));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut fields = EventFields::new(self.content);
+        values.record(&mut fields);
+        if let Some(correlation_id) = fields.correlation_id
+            && let Some(span) = ctx.span(id)
+        {
+            let mut extensions = span.extensions_mut();
+            extensions.remove::<SpanCorrelation>();
+            extensions.insert(SpanCorrelation(correlation_id));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = EventFields::new(self.content);
+        event.record(&mut fields);
+
+        let correlation_id = fields
+            .correlation_id
+            .take()
+            .filter(|id| !id.is_empty())
+            .or_else(|| {
+                ctx.event_scope(event)?.find_map(|span| {
+                    span.extensions()
+                        .get::<SpanCorrelation>()
+                        .map(|correlation| correlation.0.clone())
+                })
+            });
+        let Some(correlation_id) = correlation_id else {
+            return;
+        };
+
+        let metadata = event.metadata();
+        let mut line = format!(
+            "{} {} {}: {}",
+            chrono::Utc::now().format("%H:%M:%S%.3f"),
+            metadata.level(),
+            metadata.target(),
+            fields.message
+        );
+        for (name, value) in &fields.rest {
+            // Writing to a String can't fail.
+            write!(line, " {name}={value}").ok();
+        }
+        TurnLogs::global().record(&correlation_id, line);
+    }
+}
+
+/// Redacted field values of one event or span.
+struct EventFields {
+    content: LogContent,
+    correlation_id: Option<String>,
+    message: String,
+    rest: Vec<(&'static str, String)>,
+}
+
+impl EventFields {
+    fn new(content: LogContent) -> Self {
+        Self {
+            content,
+            correlation_id: None,
+            message: String::new(),
+            rest: Vec::new(),
+        }
+    }
+}
+
+impl Visit for EventFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            CORRELATION_ID_KEY => self.correlation_id = Some(value.to_string()),
+            "message" => self.message = redact_secrets(value),
+            name if CONTENT_FIELDS.contains(&name) => self
+                .rest
+                .push((name, summarize_content(value, self.content))),
+            name => self.rest.push((name, redact_secrets(value))),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{value:?}"));
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn std::error::Error + 'static)) {
+        // Include the whole source chain; reqwest puts the URL in the outer
+        // error and the cause in the source.
+        let mut chain = value.to_string();
+        let mut source = value.source();
+        while let Some(error) = source {
+            write!(chain, ": {error}").ok();
+            source = error.source();
+        }
+        self.record_str(field, &chain);
+    }
+}
+
+/// Render the last turn's log lines for a chat reply, keeping the most
+/// recent `max_lines`.
+pub fn render_slice(correlation_id: &str, lines: &[String], max_lines: usize) -> String {
+    if lines.is_empty() {
+        return format!("No logs captured for turn `{correlation_id}`.");
+    }
+    let skipped = lines.len().saturating_sub(max_lines);
+    let mut text = format!("Logs for turn `{correlation_id}`");
+    if skipped > 0 {
+        write!(text, " ({skipped} earlier lines omitted)").ok();
+    }
+    text.push_str(":\n```\n");
+    for line in &lines[skipped..] {
+        text.push_str(line);
+        text.push('\n');
+    }
+    text.push_str("```");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    #[test]
+    fn events_are_captured_under_the_enclosing_turn() {
+        let subscriber = tracing_subscriber::registry().with(CorrelationLayer::default());
+        let turn = new_correlation_id();
+        let other = new_correlation_id();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("handle_message", correlation_id = %turn);
+            let _entered = span.enter();
+            tracing::info!(tool_name = "reply", "tool call completed");
+            tracing::info_span!("branch.run").in_scope(|| tracing::warn!("branch failed"));
+            tracing::info!(correlation_id = %other, "routing outbound response");
+        });
+        tracing::info!("uncorrelated");
+
+        let lines = TurnLogs::global().lines(&turn);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("tool call completed tool_name=reply"));
+        assert!(lines[1].contains("WARN"));
+        assert_eq!(TurnLogs::global().lines(&other).len(), 1);
+    }
+
+    #[test]
+    fn credentials_are_redacted() {
+        let error = "error sending request for url (https://generativelanguage.googleapis.com/v1/models?key=abc123&alt=sse)";
+        assert_eq!(
+            redact_secrets(error),
+            "error sending request for url (https://generativelanguage.googleapis.com/v1/models?key=[REDACTED]&alt=sse)"
+        );
+        assert_eq!(
+            redact_secrets(r#"{"authorization": "Bearer tok_123", "x-api-key": "secret"}"#),
+            r#"{"authorization": "Bearer [REDACTED]", "x-api-key": "[REDACTED]"}"#
+        );
+        assert_eq!(
+            redact_secrets("using key sk-ant-REDACTED"),
+            "using key [REDACTED]"
+        );
+    }
+
+    #[test]
+    fn content_is_truncated_or_hashed() {
+        let long = "a".repeat(100);
+        let truncated = summarize_content(&long, LogContent::Truncate);
+        assert!(truncated.starts_with(&"a".repeat(64)));
+        assert!(truncated.contains("(100 chars, sha256:"));
+        assert_eq!(summarize_content("hi", LogContent::Truncate), "hi");
+
+        let hashed = summarize_content("hi", LogContent::Hash);
+        assert!(hashed.starts_with("<2 chars, sha256:"));
+        assert!(!hashed.contains("hi"));
+        assert_eq!(summarize_content(&long, LogContent::Full), long);
+    }
+
+    #[test]
+    fn formatted_and_captured_events_are_redacted() {
+        let turn = new_correlation_id();
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::registry()
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .fmt_fields(RedactingFields::new(LogContent::Hash))
+                    .with_writer(move || TestWriter(writer.clone()))
+                    .with_ansi(false),
+            )
+            .with(CorrelationLayer::new(LogContent::Hash));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(
+                correlation_id = %turn,
+                content = "my password is hunter2",
+                url = "https://api.example.com/v1?api_key=abc",
+                "message received"
+            );
+        });
+
+        let formatted =
+            String::from_utf8(output.lock().expect("lock").clone()).expect("utf-8 log output");
+        let captured = TurnLogs::global().lines(&turn).join("\n");
+        for text in [&formatted, &captured] {
+            assert!(!text.contains("hunter2"), "{text}");
+            assert!(!text.contains("api_key=abc"), "{text}");
+            assert!(text.contains("content=<22 chars, sha256:"), "{text}");
+        }
+        assert!(formatted.contains(&format!("correlation_id={turn}")));
+    }
+
+    struct TestWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for TestWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().expect("lock").extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn metadata_ids_are_assigned_once() {
+        let mut metadata = HashMap::new();
+        let id = ensure_correlation_id(&mut metadata);
+        assert_eq!(id.len(), 12);
+        assert_eq!(ensure_correlation_id(&mut metadata), id);
+        assert_eq!(correlation_id(&metadata), Some(id.as_str()));
+    }
+
+    #[test]
+    fn slices_keep_the_most_recent_lines() {
+        let lines: Vec<String> = (1..=5).map(|n| format!("line {n}")).collect();
+        let text = render_slice("abc", &lines, 2);
+        assert!(text.contains("3 earlier lines omitted"));
+        assert!(text.contains("line 4\nline 5\n"));
+        assert!(!text.contains("line 3"));
+        assert_eq!(
+            render_slice("abc", &[], 2),
+            "No logs captured for turn `abc`."
+        );
+    }
+}
@@ -43,12 +43,131 @@ enum Command {
     },
     /// Show status of the running daemon
     Status,
+    /// Run in the foreground, reading messages as JSON lines on stdin and
+    /// writing replies as JSON lines on stdout. Exits once stdin closes and
+    /// the last reply is written
+    Headless {
+        /// Agent for messages that don't name one (defaults to binding resolution)
+        #[arg(short, long)]
+        agent: Option<String>,
+    },
     /// Manage skills
     #[command(subcommand)]
     Skill(SkillCommand),
     /// Manage authentication
     #[command(subcommand)]
     Auth(AuthCommand),
+    /// Manage local Ollama models
+    #[command(subcommand)]
+    Ollama(OllamaCommand),
+    /// Run an eval suite and have a judge model score the answers
+    Eval {
+        /// Directory of eval case TOML files
+        dir: std::path::PathBuf,
+        /// Model under test (defaults to the channel model)
+        #[arg(short, long)]
+        model: Option<String>,
+        /// Model that scores the answers
+        #[arg(short, long)]
+        judge: String,
+        /// File whose contents replace every case's system prompt
+        #[arg(long)]
+        system_prompt: Option<std::path::PathBuf>,
+        /// Write the full report as JSON to this path
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+        /// Cases to run at once
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize,
+    },
+    /// Show how the assembled prompt changes between two config versions
+    PromptDiff {
+        /// The earlier config.toml; prompts, scripts, and workspaces are read
+        /// from its directory
+        #[arg(long)]
+        old: std::path::PathBuf,
+        /// The later config.toml (defaults to the current config)
+        #[arg(long)]
+        new: Option<std::path::PathBuf>,
+        /// Agent to compare (defaults to the default agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// Platform the sample conversation happens on
+        #[arg(long, default_value = "discord")]
+        platform: String,
+        /// Message in the sample conversation (repeatable)
+        #[arg(short, long = "message")]
+        messages: Vec<String>,
+    },
+    /// Back up config, databases, and agent workspaces to one archive
+    Backup {
+        /// Archive path (defaults to spacebot-backup-<timestamp>.zip)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+        /// Encrypt with a passphrase (from SPACEBOT_BACKUP_PASSPHRASE or a prompt)
+        #[arg(short, long)]
+        encrypt: bool,
+        /// Also upload the archive to the configured [storage] bucket
+        #[arg(short, long)]
+        upload: bool,
+    },
+    /// Render a channel's conversation to Markdown or standalone HTML
+    Transcript {
+        /// Channel ID or name
+        channel: String,
+        /// Agent the channel belongs to (defaults to the first agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// markdown or html
+        #[arg(short, long, default_value = "markdown")]
+        format: spacebot::conversation::transcript::TranscriptFormat,
+        /// Output path (defaults to transcript-<channel>.<md|html>)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Import chat history from a Discord or Slack export, or JSON lines
+    Import {
+        /// Export file, directory, or Slack zip archive
+        path: std::path::PathBuf,
+        /// discord, slack, or jsonl
+        #[arg(short, long)]
+        format: spacebot::import::ImportFormat,
+        /// Agent to import into (defaults to the first agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// The bot's own user ID on the platform; its messages become assistant messages
+        #[arg(long)]
+        bot_user_id: Option<String>,
+        /// Slack workspace ID, when users.json doesn't name it
+        #[arg(long)]
+        slack_team_id: Option<String>,
+        /// Only store history; don't queue it for memory ingestion
+        #[arg(long)]
+        no_memories: bool,
+    },
+    /// Export or purge everything stored about one user
+    #[command(subcommand)]
+    UserData(UserDataCommand),
+    /// Move memories and their embeddings between instances, or re-embed them
+    #[command(subcommand)]
+    Memories(MemoriesCommand),
+    /// Issue and revoke API keys for the WebSocket, webhook, and proxy endpoints
+    #[command(subcommand)]
+    ApiKey(ApiKeyCommand),
+    /// Restore a backup archive, replacing config.toml and agents/
+    Restore {
+        /// Archive path, or its name under backups/ with --from-storage
+        archive: String,
+        /// Download the archive from the configured [storage] bucket
+        #[arg(long)]
+        from_storage: bool,
+        /// Replace existing agent data
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Stage, check, promote, and revert config changes
+    #[command(subcommand)]
+    Config(ConfigCommand),
 }
 
 #[derive(Subcommand)]
@@ -115,6 +234,123 @@ enum SkillCommand {
     },
 }
 
+#[derive(Subcommand)]
+enum OllamaCommand {
+    /// List local models and their disk usage
+    List,
+    /// Pull a model from the Ollama registry
+    Pull {
+        /// Model name (e.g. llama3.1:8b)
+        model: String,
+    },
+    /// Delete a local model
+    Delete {
+        /// Model name (e.g. llama3.1:8b)
+        model: String,
+    },
+    /// Show GPU and host memory available for local models
+    Resources,
+}
+
+#[derive(Subcommand)]
+enum UserDataCommand {
+    /// Write a user's messages, private channels, and memories to a JSON file
+    Export {
+        /// Platform user ID (the sender_id on their messages)
+        user_id: String,
+        /// Only this agent (defaults to all agents)
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// Output path (defaults to spacebot-user-<user_id>.json)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Delete a user's messages, private channels, and memories
+    Purge {
+        /// Platform user ID (the sender_id on their messages)
+        user_id: String,
+        /// Only this agent (defaults to all agents)
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum MemoriesCommand {
+    /// Write an agent's memories, associations, and embeddings to an archive
+    Export {
+        /// Agent to export (defaults to the first agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// Output path (defaults to spacebot-memories-<agent>.jsonl)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Load an archive, swapping in a rebuilt vector table
+    Import {
+        /// Archive written by `spacebot memories export`
+        path: std::path::PathBuf,
+        /// Agent to import into (defaults to the first agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+        /// Rebuild the vector table with the configured embedding model,
+        /// embedding memories whose embeddings can't be reused
+        #[arg(long)]
+        reembed: bool,
+    },
+    /// Embed every stored memory with the configured model and swap in the
+    /// new vector table
+    Reembed {
+        /// Agent to re-embed (defaults to the first agent)
+        #[arg(short, long)]
+        agent: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ApiKeyCommand {
+    /// Issue a key. It's printed once and can't be shown again
+    Create {
+        /// Who or what the key is for
+        name: String,
+        /// Comma-separated scopes: websocket, webhook, proxy (defaults to all)
+        #[arg(short, long, default_value = "")]
+        scopes: String,
+        /// Days until the key expires (defaults to never)
+        #[arg(short, long)]
+        expires_in_days: Option<u32>,
+    },
+    /// List issued keys
+    List,
+    /// Revoke a key by ID
+    Revoke {
+        /// Key ID, as shown by `spacebot api-key list`
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Validate a config file and stage it as config.staged.toml
+    Stage {
+        /// Config file to stage
+        path: std::path::PathBuf,
+    },
+    /// Show what's staged and whether there's a previous config to revert to
+    Status,
+    /// Validate the staged config and run the preflight checks against it
+    Check,
+    /// Make the staged config live, keeping the current one to revert to
+    Promote,
+    /// Swap the previous config back in
+    Revert,
+    /// Throw the staged config away
+    Discard,
+}
+
 /// Tracks an active conversation channel and its message sender.
 struct ActiveChannel {
     message_tx: mpsc::Sender<spacebot::InboundMessage>,
@@ -142,8 +378,71 @@ fn main() -> anyhow::Result<()> {
             cmd_start(cli.config, cli.debug, foreground)
         }
         Command::Status => cmd_status(),
+        Command::Headless { agent } => cmd_headless(cli.config, cli.debug, agent),
         Command::Skill(skill_cmd) => cmd_skill(cli.config, skill_cmd),
         Command::Auth(auth_cmd) => cmd_auth(cli.config, auth_cmd),
+        Command::Ollama(ollama_cmd) => cmd_ollama(cli.config, ollama_cmd),
+        Command::Eval {
+            dir,
+            model,
+            judge,
+            system_prompt,
+            output,
+            concurrency,
+        } => cmd_eval(
+            cli.config,
+            dir,
+            model,
+            judge,
+            system_prompt,
+            output,
+            concurrency,
+        ),
+        Command::PromptDiff {
+            old,
+            new,
+            agent,
+            platform,
+            messages,
+        } => cmd_prompt_diff(cli.config, old, new, agent, platform, messages),
+        Command::Backup {
+            output,
+            encrypt,
+            upload,
+        } => cmd_backup(cli.config, output, encrypt, upload),
+        Command::Transcript {
+            channel,
+            agent,
+            format,
+            output,
+        } => cmd_transcript(cli.config, channel, agent, format, output),
+        Command::Import {
+            path,
+            format,
+            agent,
+            bot_user_id,
+            slack_team_id,
+            no_memories,
+        } => cmd_import(
+            cli.config,
+            path,
+            format,
+            agent,
+            spacebot::import::ImportOptions {
+                bot_user_id,
+                slack_team_id,
+            },
+            no_memories,
+        ),
+        Command::UserData(user_data_cmd) => cmd_user_data(cli.config, user_data_cmd),
+        Command::Memories(memories_cmd) => cmd_memories(cli.config, memories_cmd),
+        Command::ApiKey(api_key_cmd) => cmd_api_key(cli.config, api_key_cmd),
+        Command::Restore {
+            archive,
+            from_storage,
+            force,
+        } => cmd_restore(cli.config, archive, from_storage, force),
+        Command::Config(config_cmd) => cmd_config(cli.config, config_cmd),
     }
 }
 
@@ -202,7 +501,43 @@ fn cmd_start(
             spacebot::daemon::init_background_tracing(&paths, debug, &config.telemetry)
         };
 
-        run(config, foreground, otel_provider).await
+        run(config, foreground, otel_provider, None).await
+    })
+}
+
+fn cmd_headless(
+    config_path: Option<std::path::PathBuf>,
+    debug: bool,
+    agent: Option<String>,
+) -> anyhow::Result<()> {
+    let paths = spacebot::daemon::DaemonPaths::from_default();
+    if let Some(pid) = spacebot::daemon::is_running(&paths) {
+        eprintln!("spacebot is already running (pid {pid})");
+        std::process::exit(1);
+    }
+
+    // There's no terminal to onboard or set up providers from, so the
+    // config has to be complete already.
+    let config = load_config(&config_path)?;
+    if !config.llm.has_any_key() && !spacebot::auth::credentials_path(&config.instance_dir).exists()
+    {
+        anyhow::bail!("no LLM provider configured; headless mode can't run setup");
+    }
+    if let Some(agent) = &agent
+        && !config.agents.iter().any(|candidate| &candidate.id == agent)
+    {
+        anyhow::bail!("agent '{agent}' not found");
+    }
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to build Tokio runtime")?;
+
+    runtime.block_on(async {
+        let otel_provider = spacebot::daemon::init_headless_tracing(debug, &config.telemetry);
+        let stdio = Arc::new(spacebot::messaging::stdio::StdioAdapter::new(agent));
+        run(config, true, otel_provider, Some(stdio)).await
     })
 }
 
@@ -512,26 +847,759 @@ fn cmd_skill(
                 println!("Path: {}", skill.file_path.display());
                 println!("Base directory: {}", skill.base_dir.display());
 
-                // Show a preview of the content
-                let preview_len = skill.content.chars().take(500).count();
-                if preview_len < skill.content.len() {
-                    println!("\nContent preview (first 500 chars):\n");
-                    println!("{}", &skill.content[..preview_len]);
+                // Show a preview of the content
+                let preview_len = skill.content.chars().take(500).count();
+                if preview_len < skill.content.len() {
+                    println!("\nContent preview (first 500 chars):\n");
+                    println!("{}", &skill.content[..preview_len]);
+                    println!(
+                        "\n... ({} more characters)",
+                        skill.content.len() - preview_len
+                    );
+                } else {
+                    println!("\nContent:\n");
+                    println!("{}", skill.content);
+                }
+
+                Ok(())
+            }
+        }
+    })
+}
+
+fn cmd_ollama(
+    config_path: Option<std::path::PathBuf>,
+    ollama_cmd: OllamaCommand,
+) -> anyhow::Result<()> {
+    use spacebot::llm::ollama;
+
+    let config = load_config(&config_path)?;
+    let base_url = spacebot::config::normalize_ollama_base_url(config.llm.ollama_base_url);
+    let http_client = reqwest::Client::new();
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+
+    runtime.block_on(async {
+        match ollama_cmd {
+            OllamaCommand::List => {
+                let models = ollama::list_local_models(&http_client, &base_url)
+                    .await
+                    .with_context(|| format!("failed to list models from {base_url}"))?;
+
+                if models.is_empty() {
+                    println!("No models installed");
+                    return Ok(());
+                }
+
+                println!("Local models ({}):\n", models.len());
+                for model in &models {
+                    println!(
+                        "  {:<40} {:>10}",
+                        model.name,
+                        ollama::format_size(model.size_bytes)
+                    );
+                }
+                let total = models.iter().map(|model| model.size_bytes).sum();
+                println!("\nTotal disk usage: {}", ollama::format_size(total));
+
+                Ok(())
+            }
+            OllamaCommand::Pull { model } => {
+                println!("Pulling {model} from {base_url}");
+                ollama::pull_model(&http_client, &base_url, &model, |percent| {
+                    if let Some(percent) = percent {
+                        eprint!("\r  {percent}%");
+                    }
+                })
+                .await
+                .with_context(|| format!("failed to pull {model}"))?;
+                eprintln!();
+                println!("Pulled {model}");
+
+                Ok(())
+            }
+            OllamaCommand::Delete { model } => {
+                ollama::delete_model(
+                    &http_client,
+                    &base_url,
+                    &model,
+                    std::time::Duration::from_secs(60),
+                )
+                .await
+                .with_context(|| format!("failed to delete {model}"))?;
+                println!("Deleted {model}");
+
+                Ok(())
+            }
+            OllamaCommand::Resources => {
+                let snapshot = spacebot::llm::resources::sample().await;
+
+                if snapshot.gpus.is_empty() {
+                    println!("No GPUs detected (nvidia-smi unavailable)");
+                }
+                for gpu in &snapshot.gpus {
+                    println!(
+                        "  GPU {} {}: {} / {} MiB used ({} MiB free), {}% busy",
+                        gpu.index,
+                        gpu.name,
+                        gpu.memory_used_mib,
+                        gpu.memory_total_mib,
+                        gpu.memory_free_mib(),
+                        gpu.utilization_percent
+                    );
+                }
+                if let Some(host) = &snapshot.host {
+                    println!(
+                        "  Host: {} / {} MiB available, load {:.2}",
+                        host.memory_available_kib / 1024,
+                        host.memory_total_kib / 1024,
+                        host.load_average_1m
+                    );
+                }
+
+                Ok(())
+            }
+        }
+    })
+}
+
+fn cmd_eval(
+    config_path: Option<std::path::PathBuf>,
+    dir: std::path::PathBuf,
+    model: Option<String>,
+    judge: String,
+    system_prompt: Option<std::path::PathBuf>,
+    output: Option<std::path::PathBuf>,
+    concurrency: usize,
+) -> anyhow::Result<()> {
+    let config = load_config(&config_path)?;
+    let cases = spacebot::eval::load_cases(&dir)?;
+    if cases.is_empty() {
+        anyhow::bail!("no eval cases found in {}", dir.display());
+    }
+    let system_prompt = system_prompt
+        .map(|path| {
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read system prompt {}", path.display()))
+        })
+        .transpose()?;
+    let options = spacebot::eval::EvalOptions {
+        model: model.unwrap_or_else(|| config.defaults.routing.channel.clone()),
+        judge,
+        system_prompt,
+        concurrency,
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+
+    let report = runtime.block_on(async {
+        let llm_manager = Arc::new(
+            spacebot::llm::LlmManager::with_instance_dir(
+                config.llm.clone(),
+                config.instance_dir.clone(),
+            )
+            .await
+            .with_context(|| "failed to initialize LLM manager")?,
+        );
+        println!(
+            "Running {} cases against {} (judge: {})\n",
+            cases.len(),
+            options.model,
+            options.judge
+        );
+        anyhow::Ok(spacebot::eval::run(&llm_manager, cases, &options).await)
+    })?;
+
+    for result in &report.results {
+        let status = if result.passed { "PASS" } else { "FAIL" };
+        match (&result.verdict, &result.error) {
+            (Some(verdict), _) => {
+                println!("  {status}  {:<32} {:>2}/10", result.name, verdict.score);
+                if !result.passed {
+                    println!("        {}", verdict.rationale);
+                }
+            }
+            (None, error) => println!(
+                "  {status}  {:<32} {}",
+                result.name,
+                error.as_deref().unwrap_or("no verdict")
+            ),
+        }
+    }
+    let mean = report
+        .mean_score()
+        .map(|score| format!("{score:.1}/10"))
+        .unwrap_or_else(|| "n/a".into());
+    println!(
+        "\n{} of {} passed, mean score {mean}",
+        report.passed(),
+        report.results.len()
+    );
+
+    if let Some(path) = output {
+        let json = serde_json::to_string_pretty(&report).context("failed to serialize report")?;
+        std::fs::write(&path, json)
+            .with_context(|| format!("failed to write report to {}", path.display()))?;
+        println!("Report written to {}", path.display());
+    }
+
+    let failed = report.results.len() - report.passed();
+    if failed > 0 {
+        anyhow::bail!("{failed} eval case(s) failed");
+    }
+    Ok(())
+}
+
+fn cmd_prompt_diff(
+    config_path: Option<std::path::PathBuf>,
+    old: std::path::PathBuf,
+    new: Option<std::path::PathBuf>,
+    agent: Option<String>,
+    platform: String,
+    mut messages: Vec<String>,
+) -> anyhow::Result<()> {
+    let old_config = load_config(&Some(old.clone()))?;
+    let new_config = load_config(&new.or(config_path))?;
+    if messages.is_empty() {
+        messages.push("Hey, can you help me with something?".into());
+    }
+    let sample = spacebot::prompts::stack::Sample {
+        platform,
+        sender: "user".into(),
+        messages,
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+    let (old_stack, new_stack) = runtime.block_on(async {
+        let old_stack = spacebot::prompts::stack::assemble(&old_config, agent.as_deref(), &sample)
+            .await
+            .with_context(|| format!("failed to assemble prompt from {}", old.display()))?;
+        let new_stack = spacebot::prompts::stack::assemble(&new_config, agent.as_deref(), &sample)
+            .await
+            .context("failed to assemble prompt from the new config")?;
+        anyhow::Ok((old_stack, new_stack))
+    })?;
+
+    println!(
+        "old: {} (agent {})\nnew: {} (agent {})\n",
+        old_stack.instance_dir.display(),
+        old_stack.agent_id,
+        new_stack.instance_dir.display(),
+        new_stack.agent_id
+    );
+    match spacebot::prompts::stack::diff(&old_stack, &new_stack) {
+        Some(diff) => print!("{diff}"),
+        None => println!("No differences."),
+    }
+    Ok(())
+}
+
+fn cmd_backup(
+    config_path: Option<std::path::PathBuf>,
+    output: Option<std::path::PathBuf>,
+    encrypt: bool,
+    upload: bool,
+) -> anyhow::Result<()> {
+    let config = load_config(&config_path)?;
+    let bucket = if upload {
+        match spacebot::storage::ArtifactStore::for_instance(&config.storage)? {
+            spacebot::storage::ArtifactStore::S3(bucket) => Some(bucket),
+            spacebot::storage::ArtifactStore::Local => {
+                anyhow::bail!("--upload needs [storage] backend = \"s3\"")
+            }
+        }
+    } else {
+        None
+    };
+    if config.database.backend == spacebot::db::DatabaseBackend::Postgres {
+        eprintln!(
+            "warning: relational data lives in Postgres and isn't included; back it up with pg_dump"
+        );
+    }
+
+    let passphrase = encrypt.then(|| backup_passphrase(true)).transpose()?;
+    let output = output.unwrap_or_else(|| spacebot::backup::default_file_name(encrypt).into());
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+
+    runtime.block_on(async {
+        let report =
+            spacebot::backup::create(&config.instance_dir, &output, passphrase.as_deref()).await?;
+        println!(
+            "Backed up {} agent(s), {} files, to {} ({} bytes{})",
+            report.manifest.agents.len(),
+            report.files,
+            output.display(),
+            report.bytes,
+            if encrypt { ", encrypted" } else { "" }
+        );
+
+        if let Some(bucket) = bucket {
+            let file_name = output
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .context("backup path has no file name")?;
+            let url = bucket
+                .upload_file(
+                    &format!("backups/{file_name}"),
+                    &output,
+                    "application/octet-stream",
+                )
+                .await?;
+            println!("Uploaded to backups/{file_name}\n  {url}");
+        }
+        anyhow::Ok(())
+    })
+}
+
+fn cmd_restore(
+    config_path: Option<std::path::PathBuf>,
+    archive: String,
+    from_storage: bool,
+    force: bool,
+) -> anyhow::Result<()> {
+    let instance_dir = config_path
+        .as_deref()
+        .and_then(std::path::Path::parent)
+        .map(std::path::Path::to_path_buf)
+        .unwrap_or_else(spacebot::config::Config::default_instance_dir);
+
+    let paths = spacebot::daemon::DaemonPaths::new(&instance_dir);
+    if let Some(pid) = spacebot::daemon::is_running(&paths) {
+        anyhow::bail!("spacebot is running (pid {pid}); stop it before restoring");
+    }
+    let has_agents = std::fs::read_dir(instance_dir.join("agents"))
+        .map(|mut entries| entries.next().is_some())
+        .unwrap_or(false);
+    if has_agents && !force {
+        anyhow::bail!(
+            "{} already has agent data; pass --force to replace it",
+            instance_dir.display()
+        );
+    }
+
+    let download_dir = tempfile::tempdir().context("failed to create temp directory")?;
+    let archive_path = if from_storage {
+        let config = load_config(&config_path)?;
+        let spacebot::storage::ArtifactStore::S3(bucket) =
+            spacebot::storage::ArtifactStore::for_instance(&config.storage)?
+        else {
+            anyhow::bail!("--from-storage needs [storage] backend = \"s3\"");
+        };
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("failed to build tokio runtime")?;
+        let destination = download_dir.path().join("backup");
+        runtime.block_on(bucket.download_file(&format!("backups/{archive}"), &destination))?;
+        destination
+    } else {
+        std::path::PathBuf::from(archive)
+    };
+
+    let passphrase = if spacebot::backup::is_encrypted(&archive_path)? {
+        Some(backup_passphrase(false)?)
+    } else {
+        None
+    };
+    let report = spacebot::backup::restore(&instance_dir, &archive_path, passphrase.as_deref())?;
+
+    match &report.manifest {
+        Some(manifest) => println!(
+            "Restored {} files ({} agent(s), backed up {} by spacebot {}) to {}",
+            report.files_restored,
+            manifest.agents.len(),
+            manifest.created_at.format("%Y-%m-%d %H:%M UTC"),
+            manifest.version,
+            instance_dir.display()
+        ),
+        None => println!(
+            "Restored {} files to {}",
+            report.files_restored,
+            instance_dir.display()
+        ),
+    }
+    Ok(())
+}
+
+fn cmd_transcript(
+    config_path: Option<std::path::PathBuf>,
+    channel: String,
+    agent_id: Option<String>,
+    format: spacebot::conversation::transcript::TranscriptFormat,
+    output: Option<std::path::PathBuf>,
+) -> anyhow::Result<()> {
+    let config = load_config(&config_path)?;
+    let agent_config = get_agent_config(&config, agent_id.as_deref())?;
+    let data_dir = agent_config
+        .resolve(&config.instance_dir, &config.defaults)
+        .data_dir;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+
+    runtime.block_on(async {
+        let pool = spacebot::db::connect_sql(&data_dir, &config.database, &agent_config.id)
+            .await
+            .with_context(|| format!("failed to open {}'s database", agent_config.id))?;
+        let channel_store = spacebot::conversation::ChannelStore::new(pool.clone());
+        let channel_id = match channel_store.get(&channel).await? {
+            Some(info) => info.id,
+            None => channel_store
+                .find_by_name(&channel)
+                .await?
+                .map(|info| info.id)
+                .with_context(|| format!("no channel matches {channel:?}"))?,
+        };
+
+        let transcript = spacebot::conversation::transcript::Transcript::load(
+            &pool,
+            &agent_config.id,
+            &channel_id,
+        )
+        .await?;
+        pool.close().await;
+
+        let output = output.unwrap_or_else(|| transcript.file_name(format).into());
+        std::fs::write(&output, transcript.render(format))
+            .with_context(|| format!("failed to write {}", output.display()))?;
+        println!(
+            "Wrote {} entries from {channel_id} to {}",
+            transcript.entries.len(),
+            output.display()
+        );
+        anyhow::Ok(())
+    })
+}
+
+fn cmd_import(
+    config_path: Option<std::path::PathBuf>,
+    path: std::path::PathBuf,
+    format: spacebot::import::ImportFormat,
+    agent_id: Option<String>,
+    options: spacebot::import::ImportOptions,
+    no_memories: bool,
+) -> anyhow::Result<()> {
+    let config = load_config(&config_path)?;
+    let agent_config = get_agent_config(&config, agent_id.as_deref())?;
+    let resolved = agent_config.resolve(&config.instance_dir, &config.defaults);
+
+    let messages = spacebot::import::read(&path, format, &options)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    if messages.is_empty() {
+        println!("No messages found in {}", path.display());
+        return Ok(());
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+
+    runtime.block_on(async {
+        let pool =
+            spacebot::db::connect_sql(&resolved.data_dir, &config.database, &agent_config.id)
+                .await
+                .with_context(|| format!("failed to open {}'s database", agent_config.id))?;
+        let imported = spacebot::import::store(&pool, &messages).await;
+        pool.close().await;
+        let imported = imported?;
+
+        let channels: std::collections::HashSet<_> =
+            messages.iter().map(|message| &message.channel_id).collect();
+        println!(
+            "Imported {imported} new message(s) of {} in {} channel(s) into {}",
+            messages.len(),
+            channels.len(),
+            agent_config.id
+        );
+
+        if !no_memories {
+            let files = spacebot::import::write_memory_sources(&resolved.ingest_dir(), &messages)?;
+            println!(
+                "Queued {} channel log(s) in {} for memory ingestion",
+                files.len(),
+                resolved.ingest_dir().display()
+            );
+            if !resolved.ingestion.enabled {
+                println!("Memory ingestion is off for this agent; turn it on to process them");
+            }
+        }
+        anyhow::Ok(())
+    })
+}
+
+fn cmd_user_data(
+    config_path: Option<std::path::PathBuf>,
+    user_data_cmd: UserDataCommand,
+) -> anyhow::Result<()> {
+    let config = load_config(&config_path)?;
+    let (UserDataCommand::Export { user_id, agent, .. }
+    | UserDataCommand::Purge { user_id, agent, .. }) = &user_data_cmd;
+    let agents: Vec<_> = match agent {
+        Some(agent_id) => vec![get_agent_config(&config, Some(agent_id))?],
+        None => config.agents.iter().collect(),
+    };
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+
+    runtime.block_on(async {
+        let mut exports = std::collections::BTreeMap::new();
+        for agent_config in &agents {
+            let data_dir = agent_config
+                .resolve(&config.instance_dir, &config.defaults)
+                .data_dir;
+            let pool = spacebot::db::connect_sql(&data_dir, &config.database, &agent_config.id)
+                .await
+                .with_context(|| format!("failed to open {}'s database", agent_config.id))?;
+            let data = spacebot::user_data::export(&pool, user_id).await?;
+            pool.close().await;
+            exports.insert(agent_config.id.clone(), (data_dir, data));
+        }
+
+        match &user_data_cmd {
+            UserDataCommand::Export { output, .. } => {
+                let output = output.clone().unwrap_or_else(|| {
+                    format!("spacebot-user-{}.json", sanitize_file_component(user_id)).into()
+                });
+                let agents: std::collections::BTreeMap<_, _> = exports
+                    .iter()
+                    .filter(|(_, (_, data))| !data.is_empty())
+                    .map(|(agent_id, (_, data))| (agent_id, data))
+                    .collect();
+                let document = serde_json::json!({
+                    "user_id": user_id,
+                    "exported_at": chrono::Utc::now(),
+                    "agents": agents,
+                });
+                let json = serde_json::to_string_pretty(&document)
+                    .context("failed to serialize export")?;
+                std::fs::write(&output, json)
+                    .with_context(|| format!("failed to write {}", output.display()))?;
+                println!(
+                    "Exported data for {user_id} from {} agent(s) to {}",
+                    agents.len(),
+                    output.display()
+                );
+            }
+            UserDataCommand::Purge { yes, .. } => {
+                let stored: Vec<_> = exports
+                    .iter()
+                    .filter(|(_, (_, data))| !data.is_empty())
+                    .collect();
+                if stored.is_empty() {
+                    println!("Nothing stored for {user_id}");
+                    return Ok(());
+                }
+
+                println!("Stored data for {user_id}:");
+                for (agent_id, (_, data)) in &stored {
                     println!(
-                        "\n... ({} more characters)",
-                        skill.content.len() - preview_len
+                        "  {agent_id}: {} message(s), {} private channel(s), {} memory(ies), {} reminder(s), {} poll vote(s), {} saved location(s)",
+                        data.messages.len(),
+                        data.private_channels.len(),
+                        data.memories.len(),
+                        data.reminders.len(),
+                        data.poll_votes.len(),
+                        usize::from(data.location.is_some())
                     );
-                } else {
-                    println!("\nContent:\n");
-                    println!("{}", skill.content);
+                }
+                let confirmed = *yes
+                    || dialoguer::Confirm::new()
+                        .with_prompt("Permanently delete this data?")
+                        .default(false)
+                        .interact()
+                        .context("failed to read confirmation")?;
+                if !confirmed {
+                    println!("Aborted");
+                    return Ok(());
                 }
 
-                Ok(())
+                for (agent_id, (data_dir, _)) in stored {
+                    let pool =
+                        spacebot::db::connect_sql(data_dir, &config.database, agent_id).await?;
+                    let lance = spacebot::db::connect_lance(data_dir).await?;
+                    let embeddings =
+                        spacebot::memory::migration::IndexState::load(&data_dir.join("lancedb"))?
+                            .open(&lance)
+                            .await?;
+                    let report = spacebot::user_data::purge(&pool, &embeddings, user_id).await?;
+                    pool.close().await;
+                    println!(
+                        "  {agent_id}: deleted {} message(s), {} channel(s), {} memory(ies), {} reminder(s), {} poll vote(s), {} saved location(s)",
+                        report.messages,
+                        report.channels,
+                        report.memories,
+                        report.reminders,
+                        report.poll_votes,
+                        report.locations
+                    );
+                }
+            }
+        }
+        anyhow::Ok(())
+    })
+}
+
+fn cmd_memories(
+    config_path: Option<std::path::PathBuf>,
+    memories_cmd: MemoriesCommand,
+) -> anyhow::Result<()> {
+    let config = load_config(&config_path)?;
+    let (MemoriesCommand::Export { agent, .. }
+    | MemoriesCommand::Import { agent, .. }
+    | MemoriesCommand::Reembed { agent }) = &memories_cmd;
+    let agent_config = get_agent_config(&config, agent.as_deref())?;
+    let data_dir = agent_config
+        .resolve(&config.instance_dir, &config.defaults)
+        .data_dir;
+
+    // Import and re-embed swap the vector table out from under the daemon.
+    if !matches!(memories_cmd, MemoriesCommand::Export { .. }) {
+        let paths = spacebot::daemon::DaemonPaths::new(&config.instance_dir);
+        if let Some(pid) = spacebot::daemon::is_running(&paths) {
+            anyhow::bail!("spacebot is running (pid {pid}); stop it before changing memories");
+        }
+    }
+    let embedding_model = match &memories_cmd {
+        MemoriesCommand::Import { reembed: false, .. } | MemoriesCommand::Export { .. } => None,
+        _ => Some(std::sync::Arc::new(
+            spacebot::memory::EmbeddingModel::load(
+                &config.instance_dir.join("embedding_cache"),
+                &config.embedding.model,
+            )
+            .context("failed to initialize embedding model")?,
+        )),
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("failed to build tokio runtime")?;
+
+    runtime.block_on(async {
+        let pool = spacebot::db::connect_sql(&data_dir, &config.database, &agent_config.id)
+            .await
+            .with_context(|| format!("failed to open {}'s database", agent_config.id))?;
+        let store = spacebot::memory::MemoryStore::new(pool.clone());
+
+        match memories_cmd {
+            MemoriesCommand::Export { output, .. } => {
+                let archive =
+                    spacebot::memory::portable::export(&data_dir, &store, &agent_config.id).await?;
+                let output = output.unwrap_or_else(|| {
+                    format!(
+                        "spacebot-memories-{}.jsonl",
+                        sanitize_file_component(&agent_config.id)
+                    )
+                    .into()
+                });
+                let file = std::fs::File::create(&output)
+                    .with_context(|| format!("failed to create {}", output.display()))?;
+                archive.write(file)?;
+                let embedded = archive
+                    .memories
+                    .iter()
+                    .filter(|archived| archived.embedding.is_some())
+                    .count();
+                println!(
+                    "Exported {} memory(ies) ({embedded} embedded) and {} association(s) to {}",
+                    archive.memories.len(),
+                    archive.associations.len(),
+                    output.display()
+                );
+            }
+            MemoriesCommand::Import { path, .. } => {
+                let file = std::fs::File::open(&path)
+                    .with_context(|| format!("failed to open {}", path.display()))?;
+                let archive =
+                    spacebot::memory::portable::Archive::read(std::io::BufReader::new(file))
+                        .with_context(|| format!("failed to read {}", path.display()))?;
+                let report = spacebot::memory::portable::import(
+                    &data_dir,
+                    &store,
+                    &archive,
+                    embedding_model.as_ref(),
+                )
+                .await?;
+                println!(
+                    "Imported {} new and {} updated memory(ies) and {} association(s) into {}",
+                    report.memories_added,
+                    report.memories_updated,
+                    report.associations,
+                    agent_config.id
+                );
+                if report.embeddings_computed > 0 {
+                    println!("Embedded {} memory(ies)", report.embeddings_computed);
+                }
+                println!(
+                    "The previous vector table is in {}",
+                    data_dir.join("lancedb.previous").display()
+                );
+            }
+            MemoriesCommand::Reembed { .. } => {
+                let model = embedding_model
+                    .as_ref()
+                    .context("embedding model not loaded")?;
+                let count = spacebot::memory::portable::reembed(&data_dir, &store, model).await?;
+                println!(
+                    "Re-embedded {count} memory(ies) for {}; the previous vector table is in {}",
+                    agent_config.id,
+                    data_dir.join("lancedb.previous").display()
+                );
             }
         }
+        pool.close().await;
+        anyhow::Ok(())
     })
 }
 
+/// Keep a user ID usable as part of a file name.
+fn sanitize_file_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// `SPACEBOT_BACKUP_PASSPHRASE`, or a prompt when it's unset.
+fn backup_passphrase(confirm: bool) -> anyhow::Result<String> {
+    if let Ok(passphrase) = std::env::var("SPACEBOT_BACKUP_PASSPHRASE")
+        && !passphrase.is_empty()
+    {
+        return Ok(passphrase);
+    }
+    let mut prompt = dialoguer::Password::new().with_prompt("Backup passphrase");
+    if confirm {
+        prompt = prompt.with_confirmation("Confirm passphrase", "passphrases don't match");
+    }
+    prompt.interact().context("failed to read passphrase")
+}
+
 fn resolve_skills_dir(
     config: &spacebot::config::Config,
     agent_id: Option<&str>,
@@ -573,6 +1641,104 @@ fn get_agent_config<'a>(
         .with_context(|| format!("agent not found: {agent_id}"))
 }
 
+fn cmd_api_key(
+    config_path: Option<std::path::PathBuf>,
+    api_key_cmd: ApiKeyCommand,
+) -> anyhow::Result<()> {
+    let config = load_config(&config_path)?;
+    let store = spacebot::api_keys::ApiKeyStore::new(&config.instance_dir);
+
+    match api_key_cmd {
+        ApiKeyCommand::Create {
+            name,
+            scopes,
+            expires_in_days,
+        } => {
+            let scopes = spacebot::api_keys::parse_scopes(&scopes).map_err(anyhow::Error::msg)?;
+            let expires_at = expires_in_days
+                .map(|days| spacebot::api_keys::expiry_after_days(chrono::Utc::now(), days))
+                .transpose()
+                .map_err(anyhow::Error::msg)?;
+            let issued = store.create(&name, scopes, expires_at)?;
+            eprintln!(
+                "Issued key {} for {}. Store it now; it can't be shown again.",
+                issued.record.id, issued.record.name
+            );
+            println!("{}", issued.key);
+        }
+        ApiKeyCommand::List => {
+            println!(
+                "{}",
+                spacebot::api_keys::render(&store.list()?, chrono::Utc::now())
+            );
+        }
+        ApiKeyCommand::Revoke { id } => match store.revoke(&id)? {
+            Some(record) => println!("Revoked key {} ({})", record.id, record.name),
+            None => anyhow::bail!("no API key with ID '{id}'"),
+        },
+    }
+    Ok(())
+}
+
+fn cmd_config(
+    config_path: Option<std::path::PathBuf>,
+    config_cmd: ConfigCommand,
+) -> anyhow::Result<()> {
+    use spacebot::config::stage::{ConfigStage, changes_text};
+
+    let config_path = config_path
+        .unwrap_or_else(|| spacebot::config::Config::default_instance_dir().join("config.toml"));
+    let stage = ConfigStage::new(&config_path);
+
+    match config_cmd {
+        ConfigCommand::Stage { path } => {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            let changes = stage.stage(&content)?;
+            println!(
+                "Staged {}; promoting it would change {}",
+                path.display(),
+                changes_text(&changes)
+            );
+        }
+        ConfigCommand::Status => println!("{}", stage.status()),
+        ConfigCommand::Check => {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .context("failed to build tokio runtime")?;
+            let check = runtime.block_on(stage.check())?;
+            if !check.passed() {
+                anyhow::bail!(check.render());
+            }
+            println!("{}", check.render());
+        }
+        ConfigCommand::Promote => {
+            let changes = stage.promote()?;
+            println!(
+                "Promoted the staged config, changing {}. A running instance applies it within \
+                 a few seconds; `spacebot config revert` goes back.",
+                changes_text(&changes)
+            );
+        }
+        ConfigCommand::Revert => {
+            let changes = stage.revert()?;
+            println!(
+                "Reverted to the previous config, changing {}",
+                changes_text(&changes)
+            );
+        }
+        ConfigCommand::Discard => {
+            if stage.discard()? {
+                println!("Discarded the staged config");
+            } else {
+                println!("Nothing is staged");
+            }
+        }
+    }
+    Ok(())
+}
+
 fn load_config(
     config_path: &Option<std::path::PathBuf>,
 ) -> anyhow::Result<spacebot::config::Config> {
@@ -588,12 +1754,34 @@ async fn run(
     config: spacebot::config::Config,
     foreground: bool,
     otel_provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+    stdio: Option<Arc<spacebot::messaging::stdio::StdioAdapter>>,
 ) -> anyhow::Result<()> {
     let paths = spacebot::daemon::DaemonPaths::new(&config.instance_dir);
 
     tracing::info!("starting spacebot");
     tracing::info!(instance_dir = %config.instance_dir.display(), "configuration loaded");
 
+    // Write a crash report for every panic from here on
+    spacebot::crash::install(&config.instance_dir, &config.crash_reports);
+    spacebot::supervisor::configure(config.supervisor.clone());
+
+    // Drop caches and pause background jobs before the process runs out of memory
+    if config.memory_watchdog.enabled {
+        let memory_watchdog = config.memory_watchdog.clone();
+        spacebot::supervisor::spawn(None, "memory watchdog", move || {
+            spacebot::watchdog::spawn(memory_watchdog.clone())
+        });
+    }
+
+    // Fail on a bad token or unreachable database now, not on the first message
+    if config.preflight.enabled {
+        let failures = spacebot::preflight::run(&config).await;
+        if !failures.is_empty() {
+            anyhow::bail!(spacebot::preflight::render(&failures));
+        }
+        tracing::info!("preflight checks passed");
+    }
+
     // Start the IPC server for stop/status commands
     let (mut shutdown_rx, _ipc_handle) = spacebot::daemon::start_ipc_server(&paths)
         .await
@@ -606,6 +1794,11 @@ async fn run(
     // Channel for removing agents from the main event loop
     let (agent_remove_tx, mut agent_remove_rx) = mpsc::channel::<String>(8);
 
+    // TLS and allowlists apply to every embedded server bound from here on
+    spacebot::listeners::configure(config.listeners.clone());
+    // Tool HTTP clients read the egress policy on every request
+    spacebot::egress::set_policy(config.egress.clone());
+
     // Start HTTP API server if enabled
     let api_state = Arc::new(spacebot::api::ApiState::new_with_provider_sender(
         provider_tx,
@@ -614,7 +1807,16 @@ async fn run(
     ));
 
     // Start background update checker
+    spacebot::update::configure(config.updates.clone());
     spacebot::update::spawn_update_checker(api_state.update_status.clone());
+    spawn_update_forwarder(&api_state);
+
+    // Status message to the ops channel, while `[heartbeat]` has a target
+    spacebot::heartbeat::configure(config.heartbeat.clone());
+    let heartbeat_state = api_state.clone();
+    spacebot::supervisor::spawn(None, "heartbeat", move || {
+        spacebot::heartbeat::spawn(heartbeat_state.clone())
+    });
 
     let _http_handle = if config.api.enabled {
         // IPv6 addresses need brackets when combined with port: [::]:19898
@@ -666,10 +1868,16 @@ async fn run(
         .with_context(|| "failed to initialize LLM manager")?,
     );
 
+    // Shared job queue backend; each agent works its own queue.
+    let job_queue = Arc::new(
+        spacebot::jobs::JobQueue::from_config(&config.jobs)
+            .context("failed to initialize job queue")?,
+    );
+
     // Shared embedding model (stateless, agent-agnostic)
     let embedding_cache_dir = config.instance_dir.join("embedding_cache");
     let embedding_model = Arc::new(
-        spacebot::memory::EmbeddingModel::new(&embedding_cache_dir)
+        spacebot::memory::EmbeddingModel::load(&embedding_cache_dir, &config.embedding.model)
             .context("failed to initialize embedding model")?,
     );
 
@@ -678,9 +1886,10 @@ async fn run(
     // Initialize the language for all text lookups (must happen before PromptEngine/tools)
     spacebot::prompts::text::init("en").with_context(|| "failed to initialize language")?;
 
-    // Create the PromptEngine with bundled templates (no file watching, no user overrides)
-    let prompt_engine = spacebot::prompts::PromptEngine::new("en")
-        .with_context(|| "failed to initialize prompt engine")?;
+    // Create the PromptEngine with bundled templates and any instance overrides
+    let prompt_engine =
+        spacebot::prompts::PromptEngine::with_overrides("en", &config.instance_dir.join("prompts"))
+            .with_context(|| "failed to initialize prompt engine")?;
 
     // These hold the initialized subsystems. Empty until agents are initialized.
     let mut agents: HashMap<spacebot::AgentId, spacebot::Agent> = HashMap::new();
@@ -702,9 +1911,38 @@ async fn run(
     let config_path = config.instance_dir.join("config.toml");
     api_state.set_config_path(config_path.clone()).await;
     api_state.set_llm_manager(llm_manager.clone()).await;
+    api_state.set_job_queue(job_queue.clone()).await;
     api_state.set_embedding_model(embedding_model.clone()).await;
     api_state.set_prompt_engine(prompt_engine.clone()).await;
     api_state.set_defaults_config(config.defaults.clone()).await;
+    api_state.set_database_config(config.database.clone()).await;
+    api_state.set_storage_config(config.storage.clone()).await;
+
+    #[cfg(feature = "metrics")]
+    spacebot::events::EventBus::global().subscribe(spacebot::telemetry::MetricsSubscriber);
+    if !config.event_webhooks.is_empty() {
+        spacebot::events::EventBus::global().subscribe(
+            spacebot::events::webhooks::WebhookSubscriber::new(config.event_webhooks.clone()),
+        );
+    }
+
+    spacebot::events::EventBus::global().subscribe(spacebot::tenants::AuditLog::new(
+        config.instance_dir.clone(),
+        llm_manager.clone(),
+    ));
+
+    let plugins = spacebot::plugins::PluginHost::load(&config.plugins, &config.instance_dir).await;
+    api_state.set_plugins(plugins.clone()).await;
+
+    spawn_alert_forwarders(&llm_manager, &api_state);
+    spawn_crash_forwarder(&api_state, config.crash_reports.alert_target.clone());
+    spawn_leak_forwarder(&api_state);
+    spacebot::llm::ollama::spawn_warmup(&llm_manager);
+    spacebot::llm::resources::spawn_sampler(&llm_manager);
+    spacebot::llm::discovery::spawn_discovery(
+        &llm_manager,
+        spacebot::llm::discovery::configured_models(&config),
+    );
 
     // Track whether agents have been initialized
     let mut agents_initialized = false;
@@ -722,12 +1960,15 @@ async fn run(
         initialize_agents(
             &config,
             &llm_manager,
+            &job_queue,
+            &plugins,
             &embedding_model,
             &prompt_engine,
             &api_state,
             &mut agents,
             &mut messaging_manager,
             &mut inbound_stream,
+            stdio.as_ref(),
             &mut cron_schedulers_for_shutdown,
             &mut _ingestion_handles,
             &mut _cortex_handles,
@@ -769,6 +2010,12 @@ async fn run(
         );
     }
 
+    // Keep config.toml in sync with the remote source; the file watcher
+    // applies whatever the poller writes.
+    if let Some(remote) = config.remote_config.clone() {
+        spacebot::config::remote::spawn_remote_poller(config_path.clone(), remote);
+    }
+
     if foreground {
         eprintln!(
             "spacebot running in foreground (pid {})",
@@ -790,9 +2037,15 @@ async fn run(
                 None => std::future::pending().await,
             }
         };
+        let headless_done = async {
+            match &stdio {
+                Some(stdio) => wait_for_headless_exit(stdio, &api_state).await,
+                None => std::future::pending().await,
+            }
+        };
         tokio::select! {
             Some(mut message) = inbound_next, if agents_initialized => {
-                let agent_id = if let Some(existing) = message.agent_id.as_ref() {
+                let mut agent_id = if let Some(existing) = message.agent_id.as_ref() {
                     existing.clone()
                 } else {
                     let current_bindings = bindings.load();
@@ -805,7 +2058,53 @@ async fn run(
                     resolved
                 };
 
+                // Script hooks can rewrite, reroute, or drop the message
+                if let Some(agent) = agents.get(&agent_id) {
+                    if !agent.deps.runtime_config.scripts.load().on_message(&mut message) {
+                        spacebot::events::publish(spacebot::events::Event::ModerationBlocked {
+                            agent_id: Some(agent_id.clone()),
+                            conversation_id: Some(message.conversation_id.clone()),
+                            blocked_by: "script".into(),
+                            reason: "on_message hook dropped the message".into(),
+                        });
+                        continue;
+                    }
+                    agent_id = message.agent_id.clone().unwrap_or(agent_id);
+                }
+
                 let conversation_id = message.conversation_id.clone();
+                let correlation_id = spacebot::logging::ensure_correlation_id(&mut message.metadata);
+                tracing::debug!(
+                    %correlation_id,
+                    agent_id = %agent_id,
+                    conversation_id = %conversation_id,
+                    "routed inbound message"
+                );
+
+                // Poll votes are recorded directly instead of starting a turn
+                if let Some(agent) = agents.get(&agent_id)
+                    && spacebot::polls::record_vote(&agent.deps.sql_pool, &message)
+                {
+                    continue;
+                }
+
+                // Plugin message filters can rewrite or drop the message
+                if !plugins.filter_message(&mut message).await {
+                    continue;
+                }
+
+                // A channel whose task panicked is replaced by a fresh one
+                if active_channels
+                    .get(&conversation_id)
+                    .is_some_and(|active| active.message_tx.is_closed())
+                {
+                    active_channels.remove(&conversation_id);
+                    tracing::warn!(
+                        conversation_id = %conversation_id,
+                        %correlation_id,
+                        "channel task had stopped, starting a new one"
+                    );
+                }
 
                 // Find or create a channel for this conversation
                 if !active_channels.contains_key(&conversation_id) {
@@ -813,6 +2112,7 @@ async fn run(
                         tracing::warn!(
                             agent_id = %agent_id,
                             conversation_id = %conversation_id,
+                            %correlation_id,
                             "message routed to unknown agent, dropping"
                         );
                         continue;
@@ -880,10 +2180,22 @@ async fn run(
                         }
                     }
 
-                    // Spawn the channel's event loop
+                    // Spawn the channel's event loop. A panic only ends this
+                    // channel; the next message to it starts a new one.
+                    let channel_task = tokio::spawn(channel.run());
+                    let panicked_conversation_id = conversation_id.clone();
                     tokio::spawn(async move {
-                        if let Err(error) = channel.run().await {
-                            tracing::error!(%error, "channel event loop failed");
+                        match channel_task.await {
+                            Ok(Err(error)) => {
+                                tracing::error!(%error, "channel event loop failed");
+                            }
+                            Err(error) if error.is_panic() => {
+                                tracing::error!(
+                                    conversation_id = %panicked_conversation_id,
+                                    "channel task panicked; it restarts on the next message"
+                                );
+                            }
+                            _ => {}
                         }
                     });
 
@@ -893,11 +2205,39 @@ async fn run(
                     let latest_message = Arc::new(tokio::sync::RwLock::new(message.clone()));
                     let outbound_message = latest_message.clone();
                     let outbound_conversation_id = conversation_id.clone();
+                    let outbound_runtime_config = agent.deps.runtime_config.clone();
                     let api_event_tx = api_state.event_tx.clone();
                     let sse_agent_id = agent_id.to_string();
                     let sse_channel_id = conversation_id.clone();
                     let outbound_handle = tokio::spawn(async move {
-                        while let Some(response) = response_rx.recv().await {
+                        while let Some(mut response) = response_rx.recv().await {
+                            // Script hooks get the last word on outbound text
+                            let text = match &mut response {
+                                spacebot::OutboundResponse::Text(text)
+                                | spacebot::OutboundResponse::RichMessage { text, .. }
+                                | spacebot::OutboundResponse::ThreadReply { text, .. } => Some(text),
+                                _ => None,
+                            };
+                            if let Some(text) = text {
+                                let scripts = outbound_runtime_config.scripts.load();
+                                match scripts.before_send(&sse_agent_id, &outbound_conversation_id, text) {
+                                    Some(hooked) => *text = hooked,
+                                    None => {
+                                        tracing::info!(
+                                            conversation_id = %outbound_conversation_id,
+                                            "script hook dropped outbound message"
+                                        );
+                                        spacebot::events::publish(spacebot::events::Event::ModerationBlocked {
+                                            agent_id: Some(sse_agent_id.as_str().into()),
+                                            conversation_id: Some(outbound_conversation_id.clone()),
+                                            blocked_by: "script".into(),
+                                            reason: "before_send hook dropped the response".into(),
+                                        });
+                                        continue;
+                                    }
+                                }
+                            }
+
                             // Forward relevant events to SSE clients
                             match &response {
                                 spacebot::OutboundResponse::Text(text) => {
@@ -951,13 +2291,32 @@ async fn run(
                                 response => {
                                     tracing::info!(
                                         conversation_id = %outbound_conversation_id,
+                                        correlation_id = spacebot::logging::correlation_id(&current_message.metadata).unwrap_or_default(),
                                         "routing outbound response to messaging adapter"
                                     );
-                                    if let Err(error) = messaging_for_outbound
+                                    let reply_text = match &response {
+                                        spacebot::OutboundResponse::Text(text)
+                                        | spacebot::OutboundResponse::RichMessage { text, .. }
+                                        | spacebot::OutboundResponse::ThreadReply { text, .. } => Some(text.clone()),
+                                        _ => None,
+                                    };
+                                    let result = messaging_for_outbound
                                         .respond(&current_message, response)
-                                        .await
-                                    {
+                                        .await;
+                                    if let (Ok(()), Some(text)) = (&result, reply_text) {
+                                        spacebot::events::publish(spacebot::events::Event::ReplySent {
+                                            agent_id: sse_agent_id.as_str().into(),
+                                            conversation_id: outbound_conversation_id.clone(),
+                                            text,
+                                        });
+                                    }
+                                    if let Err(error) = result {
                                         tracing::error!(%error, "failed to send outbound response");
+                                        spacebot::events::publish(spacebot::events::Event::ErrorOccurred {
+                                            agent_id: Some(sse_agent_id.as_str().into()),
+                                            component: "messaging".into(),
+                                            message: format!("failed to send response to {outbound_conversation_id}: {error}"),
+                                        });
                                     }
                                 }
                             }
@@ -998,6 +2357,13 @@ async fn run(
                         sender_id: message.sender_id.clone(),
                         text: message.content.to_string(),
                     }).ok();
+                    spacebot::events::publish(spacebot::events::Event::MessageReceived {
+                        agent_id: agent_id.clone(),
+                        conversation_id: conversation_id.clone(),
+                        source: message.source.clone(),
+                        sender_id: message.sender_id.clone(),
+                        message_id: message.id.clone(),
+                    });
 
                     if let Err(error) = active.message_tx.send(message).await {
                         tracing::error!(
@@ -1036,10 +2402,25 @@ async fn run(
 
                 match new_config {
                     Ok(new_config) if new_config.llm.has_any_key() => {
+                        spacebot::egress::set_policy(new_config.egress.clone());
+                        spacebot::heartbeat::configure(new_config.heartbeat.clone());
+                        spacebot::update::configure(new_config.updates.clone());
                         // Rebuild LlmManager with the new keys
-                        match spacebot::llm::LlmManager::new(new_config.llm.clone()).await {
+                        match spacebot::llm::LlmManager::with_instance_dir(
+                            new_config.llm.clone(),
+                            new_config.instance_dir.clone(),
+                        )
+                        .await
+                        {
                             Ok(new_llm) => {
                                 let new_llm_manager = Arc::new(new_llm);
+                                spawn_alert_forwarders(&new_llm_manager, &api_state);
+                                spacebot::llm::ollama::spawn_warmup(&new_llm_manager);
+                                spacebot::llm::resources::spawn_sampler(&new_llm_manager);
+                                spacebot::llm::discovery::spawn_discovery(
+                                    &new_llm_manager,
+                                    spacebot::llm::discovery::configured_models(&new_config),
+                                );
                                 let mut new_watcher_agents = Vec::new();
                                 let mut new_discord_permissions = None;
                                 let mut new_slack_permissions = None;
@@ -1048,12 +2429,15 @@ async fn run(
                                 match initialize_agents(
                                     &new_config,
                                     &new_llm_manager,
+                                    &job_queue,
+                                    &plugins,
                                     &embedding_model,
                                     &prompt_engine,
                                     &api_state,
                                     &mut agents,
                                     &mut messaging_manager,
                                     &mut inbound_stream,
+                                    stdio.as_ref(),
                                     &mut cron_schedulers_for_shutdown,
                                     &mut _ingestion_handles,
                                     &mut _cortex_handles,
@@ -1106,6 +2490,10 @@ async fn run(
                 tracing::info!("shutdown signal received");
                 break;
             }
+            _ = headless_done => {
+                tracing::info!("stdin closed and all replies sent, shutting down");
+                break;
+            }
         }
     }
 
@@ -1118,9 +2506,11 @@ async fn run(
     drop(cron_schedulers_for_shutdown);
 
     messaging_manager.shutdown().await;
+    llm_manager.shutdown();
 
     for (agent_id, agent) in agents {
         tracing::info!(%agent_id, "shutting down agent");
+        agent.deps.conversation_writes.flush().await;
         agent.db.close().await;
     }
 
@@ -1141,12 +2531,198 @@ async fn run(
     std::process::exit(0);
 }
 
+/// Wait until headless mode has nothing left to do: stdin is closed, no
+/// turn is running or waiting, and no branch or worker is still going that
+/// could bring the channel back with a result.
+async fn wait_for_headless_exit(
+    stdio: &spacebot::messaging::stdio::StdioAdapter,
+    api_state: &spacebot::api::ApiState,
+) {
+    loop {
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        if !stdio.is_settled() {
+            continue;
+        }
+        let status_blocks: Vec<_> = api_state
+            .channel_status_blocks
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect();
+        let mut running = false;
+        for status_block in status_blocks {
+            let status = status_block.read().await;
+            running |= !status.active_branches.is_empty() || !status.active_workers.is_empty();
+        }
+        if !running {
+            return;
+        }
+    }
+}
+
+/// Post budget and SLO alerts from the LLM manager to their configured
+/// operator targets.
+fn spawn_alert_forwarders(
+    llm_manager: &Arc<spacebot::llm::LlmManager>,
+    api_state: &Arc<spacebot::api::ApiState>,
+) {
+    spawn_alert_forwarder(
+        "budget",
+        llm_manager.subscribe_budget_alerts(),
+        llm_manager,
+        api_state,
+        spacebot::llm::LlmManager::budget_alert_target,
+    );
+    spawn_alert_forwarder(
+        "SLO",
+        llm_manager.subscribe_slo_alerts(),
+        llm_manager,
+        api_state,
+        spacebot::llm::LlmManager::slo_alert_target,
+    );
+}
+
+/// Post alerts from `alert_rx` to the target `alert_target` reads.
+///
+/// The target is read from config on every alert so hot-reloaded changes apply,
+/// and the messaging manager is looked up through the API state because it is
+/// replaced whenever adapters are reinitialized. The task exits once the
+/// manager is dropped.
+fn spawn_alert_forwarder<A: std::fmt::Display + Clone + Send + 'static>(
+    kind: &'static str,
+    mut alert_rx: tokio::sync::broadcast::Receiver<A>,
+    llm_manager: &Arc<spacebot::llm::LlmManager>,
+    api_state: &Arc<spacebot::api::ApiState>,
+    alert_target: fn(&spacebot::llm::LlmManager) -> Option<String>,
+) {
+    // Weak so a replaced manager can drop its alert sender, which ends this task.
+    let llm_manager = Arc::downgrade(llm_manager);
+    let api_state = api_state.clone();
+    tokio::spawn(async move {
+        loop {
+            let alert = match alert_rx.recv().await {
+                Ok(alert) => alert,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(kind, skipped, "alert forwarder lagged");
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+
+            // Only borrow the manager long enough to read the target, so the
+            // strong count isn't held across the broadcast below.
+            let Some(alert_target) = llm_manager
+                .upgrade()
+                .map(|llm_manager| alert_target(&llm_manager))
+            else {
+                break;
+            };
+            if let Some(alert_target) = alert_target {
+                deliver_alert(kind, &api_state, &alert_target, alert.to_string()).await;
+            }
+        }
+    });
+}
+
+/// Post crash notices to `[crash_reports] alert_target`.
+///
+/// The target is fixed at startup, like the panic hook that sends the notices.
+fn spawn_crash_forwarder(api_state: &Arc<spacebot::api::ApiState>, alert_target: Option<String>) {
+    let Some(alert_target) = alert_target else {
+        return;
+    };
+    let mut notice_rx = spacebot::crash::subscribe();
+    let api_state = api_state.clone();
+    tokio::spawn(async move {
+        loop {
+            let notice = match notice_rx.recv().await {
+                Ok(notice) => notice,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(kind = "crash", skipped, "alert forwarder lagged");
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            deliver_alert("crash", &api_state, &alert_target, notice.to_string()).await;
+        }
+    });
+}
+
+/// Post output guard incidents to the alert target each one carries, read
+/// from the agent's config when the reply was checked.
+fn spawn_leak_forwarder(api_state: &Arc<spacebot::api::ApiState>) {
+    let mut alert_rx = spacebot::agent::output_guard::subscribe();
+    let api_state = api_state.clone();
+    tokio::spawn(async move {
+        loop {
+            let alert = match alert_rx.recv().await {
+                Ok(alert) => alert,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(kind = "leak", skipped, "alert forwarder lagged");
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            deliver_alert("leak", &api_state, &alert.alert_target, alert.to_string()).await;
+        }
+    });
+}
+
+/// Post new releases to `[updates] alert_target`, once each.
+fn spawn_update_forwarder(api_state: &Arc<spacebot::api::ApiState>) {
+    let mut alert_rx = spacebot::update::subscribe();
+    let api_state = api_state.clone();
+    tokio::spawn(async move {
+        loop {
+            let alert = match alert_rx.recv().await {
+                Ok(alert) => alert,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(kind = "update", skipped, "alert forwarder lagged");
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            deliver_alert("update", &api_state, &alert.alert_target, alert.to_string()).await;
+        }
+    });
+}
+
+/// Post `text` to an "adapter:target" alert target.
+async fn deliver_alert(
+    kind: &'static str,
+    api_state: &spacebot::api::ApiState,
+    alert_target: &str,
+    text: String,
+) {
+    let Some(target) = spacebot::cron::scheduler::DeliveryTarget::parse(alert_target) else {
+        return;
+    };
+    let Some(messaging_manager) = api_state.messaging_manager.read().await.clone() else {
+        tracing::warn!(kind, %target, "no messaging manager available for alert");
+        return;
+    };
+
+    if let Err(error) = messaging_manager
+        .broadcast(
+            &target.adapter,
+            &target.target,
+            spacebot::OutboundResponse::Text(text),
+        )
+        .await
+    {
+        tracing::error!(kind, %error, %target, "failed to deliver alert");
+    }
+}
+
 /// Initialize agents, messaging adapters, cron, cortex, and ingestion.
 /// Extracted so it can be called either at startup or after providers are configured.
 #[allow(clippy::too_many_arguments)]
 async fn initialize_agents(
     config: &spacebot::config::Config,
     llm_manager: &Arc<spacebot::llm::LlmManager>,
+    job_queue: &Arc<spacebot::jobs::JobQueue>,
+    plugins: &Arc<spacebot::plugins::PluginHost>,
     embedding_model: &Arc<spacebot::memory::EmbeddingModel>,
     prompt_engine: &spacebot::prompts::PromptEngine,
     api_state: &Arc<spacebot::api::ApiState>,
@@ -1155,6 +2731,7 @@ async fn initialize_agents(
     inbound_stream: &mut Option<
         std::pin::Pin<Box<dyn futures::Stream<Item = spacebot::InboundMessage> + Send>>,
     >,
+    stdio: Option<&Arc<spacebot::messaging::stdio::StdioAdapter>>,
     cron_schedulers_for_shutdown: &mut Vec<Arc<spacebot::cron::Scheduler>>,
     ingestion_handles: &mut Vec<tokio::task::JoinHandle<()>>,
     cortex_handles: &mut Vec<tokio::task::JoinHandle<()>>,
@@ -1169,6 +2746,8 @@ async fn initialize_agents(
     twitch_permissions: &mut Option<Arc<ArcSwap<spacebot::config::TwitchPermissions>>>,
 ) -> anyhow::Result<()> {
     let resolved_agents = config.resolve_agents();
+    let scripts = spacebot::scripting::ScriptHooks::load(&config.instance_dir.join("scripts"))
+        .context("failed to load script hooks")?;
 
     for agent_config in &resolved_agents {
         tracing::info!(agent_id = %agent_config.id, "initializing agent");
@@ -1206,14 +2785,15 @@ async fn initialize_agents(
         })?;
 
         // Per-agent database connections
-        let db = spacebot::db::Db::connect(&agent_config.data_dir)
-            .await
-            .with_context(|| {
-                format!(
-                    "failed to connect databases for agent '{}'",
-                    agent_config.id
-                )
-            })?;
+        let db =
+            spacebot::db::Db::connect(&agent_config.data_dir, &config.database, &agent_config.id)
+                .await
+                .with_context(|| {
+                    format!(
+                        "failed to connect databases for agent '{}'",
+                        agent_config.id
+                    )
+                })?;
 
         // Per-agent settings store (redb-backed)
         let settings_path = agent_config.data_dir.join("settings.redb");
@@ -1227,28 +2807,23 @@ async fn initialize_agents(
         );
 
         // Per-agent memory system
-        let memory_store = spacebot::memory::MemoryStore::new(db.sqlite.clone());
-        let embedding_table = spacebot::memory::EmbeddingTable::open_or_create(&db.lance)
-            .await
-            .with_context(|| {
-                format!("failed to init embeddings for agent '{}'", agent_config.id)
-            })?;
-
-        // Ensure FTS index exists for full-text search queries
-        if let Err(error) = embedding_table.ensure_fts_index().await {
-            tracing::warn!(%error, agent = %agent_config.id, "failed to create FTS index");
-        }
-
-        let memory_search = Arc::new(spacebot::memory::MemorySearch::new(
+        let memory_store = spacebot::memory::MemoryStore::new(db.sql.clone());
+        let (memory_search, migration) = spacebot::memory::migration::open_search(
+            &agent_config.data_dir,
+            &db.lance,
             memory_store,
-            embedding_table,
             embedding_model.clone(),
-        ));
+        )
+        .await
+        .with_context(|| format!("failed to init embeddings for agent '{}'", agent_config.id))?;
 
         // Per-agent event bus (broadcast for fan-out to multiple channels)
         let (event_tx, _event_rx) = tokio::sync::broadcast::channel(256);
 
         let agent_id: spacebot::AgentId = Arc::from(agent_config.id.as_str());
+        if let Some(migration) = migration {
+            migration.spawn(agent_id.clone());
+        }
 
         // Scaffold identity templates if missing, then load
         spacebot::identity::scaffold_identity_files(&agent_config.workspace)
@@ -1265,6 +2840,7 @@ async fn initialize_agents(
         let skills =
             spacebot::skills::SkillSet::load(&config.skills_dir(), &agent_config.skills_dir())
                 .await;
+        let flows = spacebot::flows::FlowSet::load(&config.flows_dir(), &agent_config.flows_dir());
 
         // Build the RuntimeConfig with all hot-reloadable values
         let runtime_config = Arc::new(spacebot::config::RuntimeConfig::new(
@@ -1272,8 +2848,10 @@ async fn initialize_agents(
             agent_config,
             &config.defaults,
             prompt_engine.clone(),
+            scripts.clone(),
             identity,
             skills,
+            flows,
         ));
 
         // Set the settings store in RuntimeConfig and apply config-driven defaults
@@ -1295,8 +2873,23 @@ async fn initialize_agents(
             cron_tool: None,
             runtime_config,
             event_tx,
-            sqlite_pool: db.sqlite.clone(),
+            sql_pool: db.sql.clone(),
+            conversation_writes: spacebot::conversation::WriteBatch::new(
+                db.sql.clone(),
+                &config.database,
+            ),
             messaging_manager: None,
+            jobs: job_queue.clone(),
+            artifacts: Arc::new(
+                spacebot::storage::ArtifactStore::from_config(&config.storage, &agent_config.id)
+                    .with_context(|| {
+                        format!(
+                            "failed to set up artifact storage for agent '{}'",
+                            agent_config.id
+                        )
+                    })?,
+            ),
+            plugins: plugins.clone(),
         };
 
         let agent = spacebot::Agent {
@@ -1322,7 +2915,7 @@ async fn initialize_agents(
         for (agent_id, agent) in agents.iter() {
             let event_rx = agent.deps.event_tx.subscribe();
             api_state.register_agent_events(agent_id.to_string(), event_rx);
-            agent_pools.insert(agent_id.to_string(), agent.db.sqlite.clone());
+            agent_pools.insert(agent_id.to_string(), agent.db.sql.clone());
             memory_searches.insert(agent_id.to_string(), agent.deps.memory_search.clone());
             agent_workspaces.insert(agent_id.to_string(), agent.config.workspace.clone());
             runtime_configs.insert(agent_id.to_string(), agent.deps.runtime_config.clone());
@@ -1345,6 +2938,13 @@ async fn initialize_agents(
 
     // Initialize messaging adapters
     let new_messaging_manager = spacebot::messaging::MessagingManager::new();
+    for platform in spacebot::messaging::missing_platforms(&config.messaging) {
+        tracing::warn!(
+            %platform,
+            "{platform} is enabled but this build doesn't include it, not starting it; \
+             rebuild with `--features {platform}`"
+        );
+    }
 
     // Shared Discord permissions (hot-reloadable via file watcher)
     *discord_permissions = config.messaging.discord.as_ref().map(|discord_config| {
@@ -1357,7 +2957,7 @@ async fn initialize_agents(
     }
 
     if let Some(discord_config) = &config.messaging.discord {
-        if discord_config.enabled {
+        if discord_config.enabled && cfg!(feature = "discord") {
             let adapter = spacebot::messaging::discord::DiscordAdapter::new(
                 &discord_config.token,
                 discord_permissions
@@ -1378,7 +2978,7 @@ async fn initialize_agents(
     }
 
     if let Some(slack_config) = &config.messaging.slack {
-        if slack_config.enabled {
+        if slack_config.enabled && cfg!(feature = "slack") {
             match spacebot::messaging::slack::SlackAdapter::new(
                 &slack_config.bot_token,
                 &slack_config.app_token,
@@ -1405,7 +3005,7 @@ async fn initialize_agents(
     });
 
     if let Some(telegram_config) = &config.messaging.telegram {
-        if telegram_config.enabled {
+        if telegram_config.enabled && cfg!(feature = "telegram") {
             let adapter = spacebot::messaging::telegram::TelegramAdapter::new(
                 &telegram_config.token,
                 telegram_permissions
@@ -1416,12 +3016,92 @@ async fn initialize_agents(
         }
     }
 
+    let issued_keys = Arc::new(spacebot::api_keys::ApiKeyStore::new(&config.instance_dir));
+
     if let Some(webhook_config) = &config.messaging.webhook {
         if webhook_config.enabled {
-            let adapter = spacebot::messaging::webhook::WebhookAdapter::new(
+            let mut adapter = spacebot::messaging::webhook::WebhookAdapter::new(
                 webhook_config.port,
                 &webhook_config.bind,
             );
+            if webhook_config.require_api_key {
+                adapter = adapter.with_api_keys(issued_keys.clone());
+            }
+            if let Some(signing) = &webhook_config.signing {
+                adapter = adapter.with_signing(signing.clone());
+            }
+            new_messaging_manager.register(adapter).await;
+        }
+    }
+
+    if let Some(web_config) = &config.messaging.web {
+        if web_config.enabled {
+            let agent_id = web_config
+                .agent_id
+                .clone()
+                .unwrap_or_else(|| config.default_agent_id().to_string());
+            let key: spacebot::AgentId = Arc::from(agent_id.as_str());
+            match agents.get(&key) {
+                Some(agent) => {
+                    let history = spacebot::conversation::ConversationLogger::new(
+                        agent.deps.sql_pool.clone(),
+                    );
+                    let adapter =
+                        spacebot::messaging::web::WebAdapter::new(web_config, agent_id, history);
+                    new_messaging_manager.register(adapter).await;
+                }
+                None => {
+                    tracing::error!(%agent_id, "web chat agent not found, not starting web chat");
+                }
+            }
+        }
+    }
+
+    if config.proxy.enabled {
+        let agent_id = config
+            .proxy
+            .agent_id
+            .clone()
+            .unwrap_or_else(|| config.default_agent_id().to_string());
+        let key: spacebot::AgentId = Arc::from(agent_id.as_str());
+        match agents.get(&key) {
+            Some(agent) => {
+                if let Err(error) = spacebot::proxy::start(
+                    &config.proxy,
+                    key.clone(),
+                    agent.deps.llm_manager.clone(),
+                    agent.deps.runtime_config.clone(),
+                    issued_keys.clone(),
+                )
+                .await
+                {
+                    tracing::error!(%error, "failed to start proxy server");
+                }
+            }
+            None => {
+                tracing::error!(%agent_id, "proxy agent not found, not starting proxy");
+            }
+        }
+    }
+
+    if let Some(websocket_config) = &config.messaging.websocket {
+        if websocket_config.enabled {
+            let adapter = spacebot::messaging::websocket::WebSocketAdapter::new(
+                websocket_config,
+                config.default_agent_id(),
+                issued_keys.clone(),
+            );
+            new_messaging_manager.register(adapter).await;
+        }
+    }
+
+    if let Some(grpc_config) = &config.messaging.grpc {
+        if grpc_config.enabled && cfg!(feature = "grpc") {
+            let adapter = spacebot::messaging::grpc::GrpcAdapter::new(
+                grpc_config,
+                config.default_agent_id(),
+                api_state.clone(),
+            );
             new_messaging_manager.register(adapter).await;
         }
     }
@@ -1434,7 +3114,7 @@ async fn initialize_agents(
     });
 
     if let Some(twitch_config) = &config.messaging.twitch {
-        if twitch_config.enabled {
+        if twitch_config.enabled && cfg!(feature = "twitch") {
             let adapter = spacebot::messaging::twitch::TwitchAdapter::new(
                 &twitch_config.username,
                 &twitch_config.oauth_token,
@@ -1454,6 +3134,10 @@ async fn initialize_agents(
         .await;
     api_state.set_webchat_adapter(webchat_adapter);
 
+    if let Some(stdio) = stdio {
+        new_messaging_manager.register_shared(stdio.clone()).await;
+    }
+
     *messaging_manager = Arc::new(new_messaging_manager);
     api_state
         .set_messaging_manager(messaging_manager.clone())
@@ -1473,7 +3157,7 @@ async fn initialize_agents(
     let mut cron_schedulers_map = std::collections::HashMap::new();
 
     for (agent_id, agent) in agents.iter_mut() {
-        let store = Arc::new(spacebot::cron::CronStore::new(agent.db.sqlite.clone()));
+        let store = Arc::new(spacebot::cron::CronStore::new(agent.db.sql.clone()));
         agent.deps.messaging_manager = Some(messaging_manager.clone());
 
         // Seed cron jobs from config into the database
@@ -1543,14 +3227,63 @@ async fn initialize_agents(
     api_state.set_cron_schedulers(cron_schedulers_map);
     tracing::info!("cron stores and schedulers registered with API state");
 
-    // Start memory ingestion loops for each agent
+    // Offer the tools in `defaults.slash_commands` as platform slash commands,
+    // typed from the default agent's channel tools. The channel is never run;
+    // it only registers its per-turn tools to read their definitions.
+    if !config.defaults.slash_commands.is_empty()
+        && let Some(agent) = agents.get(config.default_agent_id())
+    {
+        let (response_tx, _response_rx) = mpsc::channel(1);
+        let (channel, _channel_tx) = spacebot::agent::channel::Channel::new(
+            Arc::from("slash_commands"),
+            agent.deps.clone(),
+            response_tx,
+            agent.deps.event_tx.subscribe(),
+            agent.config.screenshot_dir(),
+            agent.config.logs_dir(),
+        );
+        let commands = channel.slash_commands().await;
+        tracing::info!(count = commands.len(), "slash commands registered");
+        messaging_manager.register_commands(commands).await;
+    }
+
+    // Start job workers, digest, feed, reminder, and poll loops, and memory ingestion loops for each agent
+    // Each loop is supervised so a panic restarts it instead of ending it for good.
     for (agent_id, agent) in agents.iter() {
+        let loops: [(
+            &'static str,
+            fn(spacebot::AgentDeps) -> tokio::task::JoinHandle<()>,
+        ); 5] = [
+            ("job worker", spacebot::agent::jobs::spawn_job_worker),
+            ("digest loop", spacebot::agent::digest::spawn_digest_loop),
+            ("feed loop", spacebot::feeds::spawn_feed_loop),
+            ("reminder loop", spacebot::reminders::spawn_reminder_loop),
+            ("poll loop", spacebot::polls::spawn_poll_loop),
+        ];
+        for (task, spawn) in loops {
+            let deps = agent.deps.clone();
+            ingestion_handles.push(spacebot::supervisor::spawn(
+                Some(agent_id.clone()),
+                task,
+                move || spawn(deps.clone()),
+            ));
+        }
+        let conversation_writes = agent.deps.conversation_writes.clone();
+        ingestion_handles.push(spacebot::supervisor::spawn(
+            Some(agent_id.clone()),
+            "conversation write flusher",
+            move || conversation_writes.spawn_flusher(),
+        ));
         let ingestion_config = **agent.deps.runtime_config.ingestion.load();
         if ingestion_config.enabled {
-            let handle = spacebot::agent::ingestion::spawn_ingestion_loop(
-                agent.config.ingest_dir(),
-                agent.deps.clone(),
-            );
+            let (ingest_dir, deps) = (agent.config.ingest_dir(), agent.deps.clone());
+            let handle =
+                spacebot::supervisor::spawn(Some(agent_id.clone()), "ingestion loop", move || {
+                    spacebot::agent::ingestion::spawn_ingestion_loop(
+                        ingest_dir.clone(),
+                        deps.clone(),
+                    )
+                });
             ingestion_handles.push(handle);
             tracing::info!(agent_id = %agent_id, "memory ingestion loop started");
         }
@@ -1558,14 +3291,24 @@ async fn initialize_agents(
 
     // Start cortex bulletin loops and association loops for each agent
     for (agent_id, agent) in agents.iter() {
-        let cortex_logger = spacebot::agent::cortex::CortexLogger::new(agent.db.sqlite.clone());
-        let bulletin_handle =
-            spacebot::agent::cortex::spawn_bulletin_loop(agent.deps.clone(), cortex_logger.clone());
+        let cortex_logger = spacebot::agent::cortex::CortexLogger::new(agent.db.sql.clone());
+        let (deps, logger) = (agent.deps.clone(), cortex_logger.clone());
+        let bulletin_handle = spacebot::supervisor::spawn(
+            Some(agent_id.clone()),
+            "cortex bulletin loop",
+            move || spacebot::agent::cortex::spawn_bulletin_loop(deps.clone(), logger.clone()),
+        );
         cortex_handles.push(bulletin_handle);
         tracing::info!(agent_id = %agent_id, "cortex bulletin loop started");
 
-        let association_handle =
-            spacebot::agent::cortex::spawn_association_loop(agent.deps.clone(), cortex_logger);
+        let deps = agent.deps.clone();
+        let association_handle = spacebot::supervisor::spawn(
+            Some(agent_id.clone()),
+            "cortex association loop",
+            move || {
+                spacebot::agent::cortex::spawn_association_loop(deps.clone(), cortex_logger.clone())
+            },
+        );
         cortex_handles.push(association_handle);
         tracing::info!(agent_id = %agent_id, "cortex association loop started");
     }
@@ -1577,19 +3320,33 @@ async fn initialize_agents(
             let browser_config = (**agent.deps.runtime_config.browser_config.load()).clone();
             let brave_search_key = (**agent.deps.runtime_config.brave_search_key.load()).clone();
             let conversation_logger =
-                spacebot::conversation::history::ConversationLogger::new(agent.db.sqlite.clone());
-            let channel_store = spacebot::conversation::ChannelStore::new(agent.db.sqlite.clone());
+                spacebot::conversation::history::ConversationLogger::new(agent.db.sql.clone());
+            let channel_store = spacebot::conversation::ChannelStore::new(agent.db.sql.clone());
             let tool_server = spacebot::tools::create_cortex_chat_tool_server(
                 agent.deps.memory_search.clone(),
+                agent.deps.llm_manager.clone(),
                 conversation_logger,
                 channel_store,
                 browser_config,
                 agent.config.screenshot_dir(),
+                agent.deps.artifacts.clone(),
                 brave_search_key,
+                (**agent.deps.runtime_config.github.load()).clone(),
+                (**agent.deps.runtime_config.railway.load()).clone(),
+                (**agent.deps.runtime_config.http_apis.load()).clone(),
+                (**agent.deps.runtime_config.sql_databases.load()).clone(),
+                (**agent.deps.runtime_config.charts.load()).clone(),
+                (**agent.deps.runtime_config.weather.load()).clone(),
+                &agent.deps.plugins,
+                std::sync::Arc::new(spacebot::injection::InjectionGuard::new(
+                    &agent.deps.runtime_config.injection.load(),
+                    agent_id.clone(),
+                    None,
+                )),
                 agent.deps.runtime_config.workspace_dir.clone(),
                 agent.deps.runtime_config.instance_dir.clone(),
             );
-            let store = spacebot::agent::cortex_chat::CortexChatStore::new(agent.db.sqlite.clone());
+            let store = spacebot::agent::cortex_chat::CortexChatStore::new(agent.db.sql.clone());
             let session = spacebot::agent::cortex_chat::CortexChatSession::new(
                 agent.deps.clone(),
                 tool_server,
@@ -1,13 +1,17 @@
 //! Memory storage and retrieval system.
 
 pub mod embedding;
+pub mod filter;
 pub mod lance;
 pub mod maintenance;
+pub mod migration;
+pub mod portable;
 pub mod search;
 pub mod store;
 pub mod types;
 
 pub use embedding::EmbeddingModel;
+pub use filter::MemoryFilter;
 pub use lance::EmbeddingTable;
 pub use search::{MemorySearch, SearchConfig, SearchMode, SearchSort, curate_results};
 pub use store::MemoryStore;
@@ -1,21 +1,63 @@
 //! Embedding generation via fastembed.
 
 use crate::error::{LlmError, Result};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+/// fastembed's default model, used unless `[embedding] model` names another.
+pub const DEFAULT_MODEL: &str = "Xenova/bge-small-en-v1.5";
+
+/// Embedding settings (instance-level, under `[embedding]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingConfig {
+    /// fastembed model code. Changing it re-embeds every agent's memories in
+    /// the background on the next start.
+    pub model: String,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self {
+            model: DEFAULT_MODEL.into(),
+        }
+    }
+}
+
+/// The fastembed model with code `name`, and its dimensions. Quantized
+/// variants share a code with the full model; the full model wins.
+pub fn lookup(name: &str) -> Option<(fastembed::EmbeddingModel, usize)> {
+    fastembed::TextEmbedding::list_supported_models()
+        .into_iter()
+        .filter(|info| info.model_code.eq_ignore_ascii_case(name))
+        .min_by_key(|info| format!("{:?}", info.model).ends_with('Q'))
+        .map(|info| (info.model, info.dim))
+}
+
 /// Embedding model wrapper with thread-safe sharing.
 ///
 /// fastembed's TextEmbedding is not Send, so we hold it behind an Arc and
 /// use spawn_blocking to call into it from async contexts.
 pub struct EmbeddingModel {
     model: Arc<fastembed::TextEmbedding>,
+    name: String,
+    dimensions: usize,
+    cache_dir: PathBuf,
 }
 
 impl EmbeddingModel {
-    /// Create a new embedding model, storing downloaded model files in `cache_dir`.
+    /// Create the default embedding model, storing downloaded model files in `cache_dir`.
     pub fn new(cache_dir: &Path) -> Result<Self> {
-        let options = fastembed::InitOptions::default()
+        Self::load(cache_dir, DEFAULT_MODEL)
+    }
+
+    /// Load the fastembed model with code `name`, storing downloaded model
+    /// files in `cache_dir`.
+    pub fn load(cache_dir: &Path, name: &str) -> Result<Self> {
+        let (model_name, dimensions) = lookup(name).ok_or_else(|| {
+            LlmError::EmbeddingFailed(format!("fastembed has no embedding model {name:?}"))
+        })?;
+        let name = model_name.to_string();
+        let options = fastembed::InitOptions::new(model_name)
             .with_cache_dir(cache_dir.to_path_buf())
             .with_show_download_progress(true);
 
@@ -24,9 +66,26 @@ impl EmbeddingModel {
 
         Ok(Self {
             model: Arc::new(model),
+            name,
+            dimensions,
+            cache_dir: cache_dir.to_path_buf(),
         })
     }
 
+    /// Load another model into the same cache directory.
+    pub fn load_other(&self, name: &str) -> Result<Self> {
+        Self::load(&self.cache_dir, name)
+    }
+
+    /// The model's canonical fastembed code.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
     /// Generate embeddings for multiple texts (blocking).
     pub fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
         self.model
@@ -40,6 +99,14 @@ impl EmbeddingModel {
         Ok(embeddings.into_iter().next().unwrap_or_default())
     }
 
+    /// Generate embeddings for multiple texts (async, spawns blocking task).
+    pub async fn embed_many(self: &Arc<Self>, texts: Vec<String>) -> Result<Vec<Vec<f32>>> {
+        let model = self.clone();
+        tokio::task::spawn_blocking(move || model.embed(texts))
+            .await
+            .map_err(|e| crate::Error::Other(anyhow::anyhow!("embedding task failed: {}", e)))?
+    }
+
     /// Generate embedding for a single text (async, spawns blocking task).
     pub async fn embed_one(self: &Arc<Self>, text: &str) -> Result<Vec<f32>> {
         let text = text.to_string();
@@ -56,6 +123,14 @@ impl EmbeddingModel {
     }
 }
 
+/// Cosine similarity of two embeddings, 0 when either is all zeros.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
 /// Async function to embed text using a shared model.
 pub async fn embed_text(model: &Arc<EmbeddingModel>, text: &str) -> Result<Vec<f32>> {
     model.embed_one(text).await
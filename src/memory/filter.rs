@@ -0,0 +1,289 @@
+//! Metadata filters for memory searches.
+//!
+//! A filter expression is space-separated `key:value` terms, all of which a
+//! memory must match:
+//!
+//! - `source:<prefix>` — its source starts with the prefix, like
+//!   `source:file:runbooks` for memories ingested from `runbooks*` files
+//! - `channel:<id>` — it was saved from that channel
+//! - `tag:<tag>` — it has the tag; repeat the term to require several
+//! - `after:<date>` — it was created at or after the start of the date
+//! - `before:<date>` — it was created before the start of the date
+//! - `during:<date>` — it was created within the date
+//!
+//! A date is a year (`2024`), a month (`2024-03`), or a day (`2024-03-15`),
+//! in UTC. "The runbooks from 2024" is `tag:runbook during:2024`.
+
+use crate::memory::Memory;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Keys a filter term can have.
+const KEYS: [&str; 6] = ["source", "channel", "tag", "after", "before", "during"];
+
+/// Metadata a search is limited to. Unset fields match every memory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryFilter {
+    /// Prefix of the memory's source.
+    pub source: Option<String>,
+    pub channel_id: Option<String>,
+    /// Tags the memory must all have, normalized with [`normalize_tag`].
+    pub tags: Vec<String>,
+    /// Earliest creation time, inclusive.
+    pub created_after: Option<DateTime<Utc>>,
+    /// Latest creation time, exclusive.
+    pub created_before: Option<DateTime<Utc>>,
+}
+
+/// A value bound for a condition from [`MemoryFilter::sql_conditions`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FilterParam {
+    Text(String),
+    Time(DateTime<Utc>),
+}
+
+impl MemoryFilter {
+    /// Parse a filter expression in which every term is a filter.
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let (filter, rest) = Self::extract(expression)?;
+        match rest.split_whitespace().next() {
+            Some(word) => Err(format!(
+                "'{word}' isn't a filter term; expected key:value, with key one of {}",
+                KEYS.join(", ")
+            )),
+            None => Ok(filter),
+        }
+    }
+
+    /// Pull the filter terms out of `text`, returning the filter and the
+    /// remaining words, like the query in `!kb tag:runbook restart the api`.
+    /// A word whose key isn't a filter key stays in the text.
+    pub fn extract(text: &str) -> Result<(Self, String), String> {
+        let mut filter = Self::default();
+        let mut rest = Vec::new();
+        for word in text.split_whitespace() {
+            match word.split_once(':') {
+                Some((key, value)) if KEYS.contains(&key) => filter.apply(key, value)?,
+                _ => rest.push(word),
+            }
+        }
+        Ok((filter, rest.join(" ")))
+    }
+
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), String> {
+        if value.is_empty() {
+            return Err(format!("'{key}:' needs a value"));
+        }
+        match key {
+            "source" => self.source = Some(value.to_string()),
+            "channel" => self.channel_id = Some(value.to_string()),
+            "tag" => {
+                let tag = normalize_tag(value).ok_or_else(|| format!("'{value}' isn't a tag"))?;
+                if !self.tags.contains(&tag) {
+                    self.tags.push(tag);
+                }
+            }
+            "after" => self.starting(period(value)?.0),
+            "before" => self.ending(period(value)?.0),
+            _ => {
+                let (start, end) = period(value)?;
+                self.starting(start);
+                self.ending(end);
+            }
+        }
+        Ok(())
+    }
+
+    fn starting(&mut self, start: DateTime<Utc>) {
+        self.created_after = Some(self.created_after.map_or(start, |after| after.max(start)));
+    }
+
+    fn ending(&mut self, end: DateTime<Utc>) {
+        self.created_before = Some(self.created_before.map_or(end, |before| before.min(end)));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn matches(&self, memory: &Memory) -> bool {
+        self.source.as_deref().is_none_or(|prefix| {
+            memory
+                .source
+                .as_deref()
+                .is_some_and(|source| source.starts_with(prefix))
+        }) && self
+            .channel_id
+            .as_deref()
+            .is_none_or(|channel_id| memory.channel_id.as_deref() == Some(channel_id))
+            && self.tags.iter().all(|tag| memory.tags.contains(tag))
+            && self
+                .created_after
+                .is_none_or(|after| memory.created_at >= after)
+            && self
+                .created_before
+                .is_none_or(|before| memory.created_at < before)
+    }
+
+    /// SQL conditions on the `memories` table, each starting with ` AND `,
+    /// with placeholders numbered from `first_param`, and the values to bind
+    /// to them in order.
+    pub(crate) fn sql_conditions(&self, first_param: usize) -> (String, Vec<FilterParam>) {
+        let mut sql = String::new();
+        let mut params = Vec::new();
+        let mut condition = |template: &str, param: FilterParam| {
+            let placeholder = format!("${}", first_param + params.len());
+            sql.push_str(" AND ");
+            sql.push_str(&template.replace('?', &placeholder));
+            params.push(param);
+        };
+        if let Some(prefix) = &self.source {
+            condition(
+                "source LIKE ? ESCAPE '\\'",
+                FilterParam::Text(format!("{}%", escape_like(prefix))),
+            );
+        }
+        if let Some(channel_id) = &self.channel_id {
+            condition("channel_id = ?", FilterParam::Text(channel_id.clone()));
+        }
+        for tag in &self.tags {
+            condition(
+                "tags LIKE ? ESCAPE '\\'",
+                FilterParam::Text(format!("%\"{}\"%", escape_like(tag))),
+            );
+        }
+        if let Some(after) = self.created_after {
+            condition("created_at >= ?", FilterParam::Time(after));
+        }
+        if let Some(before) = self.created_before {
+            condition("created_at < ?", FilterParam::Time(before));
+        }
+        (sql, params)
+    }
+}
+
+/// A tag as stored: trimmed and lowercase. `None` when it's empty or has
+/// whitespace, quotes, backslashes, or commas.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().to_lowercase();
+    let valid = !tag.is_empty()
+        && !tag
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\\' | ','));
+    valid.then_some(tag)
+}
+
+/// The start of the year, month, or day `value` names, and the start of the
+/// next one.
+fn period(value: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), String> {
+    let invalid = || format!("'{value}' isn't a date; use YYYY, YYYY-MM, or YYYY-MM-DD");
+    let parts = value
+        .split('-')
+        .map(str::parse::<u32>)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    let (start, end) = match parts[..] {
+        [year] => (
+            NaiveDate::from_ymd_opt(year as i32, 1, 1),
+            NaiveDate::from_ymd_opt(year as i32 + 1, 1, 1),
+        ),
+        [year, month] => {
+            let start = NaiveDate::from_ymd_opt(year as i32, month, 1);
+            (
+                start,
+                start.and_then(|start| start.checked_add_months(chrono::Months::new(1))),
+            )
+        }
+        [year, month, day] => {
+            let start = NaiveDate::from_ymd_opt(year as i32, month, day);
+            (start, start.and_then(|start| start.succ_opt()))
+        }
+        _ => (None, None),
+    };
+    let (Some(start), Some(end)) = (start, end) else {
+        return Err(invalid());
+    };
+    Ok((
+        start.and_time(chrono::NaiveTime::MIN).and_utc(),
+        end.and_time(chrono::NaiveTime::MIN).and_utc(),
+    ))
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryType;
+
+    fn at(date: &str) -> DateTime<Utc> {
+        period(date).unwrap().0
+    }
+
+    #[test]
+    fn expressions_parse_into_filters() {
+        let filter =
+            MemoryFilter::parse("tag:Runbook source:file:runbooks during:2024 after:2024-03")
+                .unwrap();
+        assert_eq!(filter.tags, ["runbook"]);
+        assert_eq!(filter.source.as_deref(), Some("file:runbooks"));
+        assert_eq!(filter.created_after, Some(at("2024-03")));
+        assert_eq!(filter.created_before, Some(at("2025")));
+
+        let (filter, query) =
+            MemoryFilter::extract("why did ERR:42 happen before:2024-02-29").unwrap();
+        assert_eq!(query, "why did ERR:42 happen");
+        assert_eq!(filter.created_before, Some(at("2024-02-29")));
+        assert!(MemoryFilter::extract("no filters").unwrap().0.is_empty());
+
+        assert!(MemoryFilter::parse("runbooks").is_err());
+        assert!(MemoryFilter::parse("after:2023-02-29").is_err());
+        assert!(MemoryFilter::parse("during:2024-13").is_err());
+        assert!(MemoryFilter::parse("tag:").is_err());
+        assert_eq!(period("2024-12").unwrap().1, at("2025"));
+        assert_eq!(period("2024-02-29").unwrap().1, at("2024-03-01"));
+    }
+
+    #[test]
+    fn filters_match_memory_metadata() {
+        let mut memory = Memory::new("Restart the api with `just restart`", MemoryType::Fact)
+            .with_source("file:runbooks-api.md")
+            .with_tags(["runbook", "api"]);
+        memory.created_at = at("2024-06-10");
+
+        let matches = |expression: &str| MemoryFilter::parse(expression).unwrap().matches(&memory);
+        assert!(matches(""));
+        assert!(matches(
+            "tag:runbook tag:api source:file:runbooks during:2024"
+        ));
+        assert!(!matches("tag:deploy"));
+        assert!(!matches("source:user"));
+        assert!(!matches("channel:discord:1"));
+        assert!(!matches("before:2024-06-10"));
+        assert!(matches("after:2024-06-10"));
+    }
+
+    #[test]
+    fn sql_conditions_number_their_placeholders() {
+        let filter = MemoryFilter::parse("source:file:run_books tag:api during:2024").unwrap();
+        let (sql, params) = filter.sql_conditions(3);
+        assert_eq!(
+            sql,
+            " AND source LIKE $3 ESCAPE '\\' AND tags LIKE $4 ESCAPE '\\' \
+             AND created_at >= $5 AND created_at < $6"
+        );
+        assert_eq!(
+            params[..2],
+            [
+                FilterParam::Text("file:run\\_books%".into()),
+                FilterParam::Text("%\"api\"%".into()),
+            ]
+        );
+        assert_eq!(params[3], FilterParam::Time(at("2025")));
+    }
+}
@@ -7,40 +7,53 @@ use arrow_array::{Array, RecordBatchIterator};
 use futures::TryStreamExt;
 use std::sync::Arc;
 
-/// Schema constants for the embeddings table.
-const TABLE_NAME: &str = "memory_embeddings";
-const EMBEDDING_DIM: i32 = 384; // all-MiniLM-L6-v2 dimension
+/// Schema constants for the default model's embeddings table.
+pub(crate) const TABLE_NAME: &str = "memory_embeddings";
+pub(crate) const EMBEDDING_DIM: i32 = 384; // bge-small-en-v1.5 dimension
 
 /// LanceDB table for memory embeddings with HNSW index and FTS.
 pub struct EmbeddingTable {
     table: lancedb::Table,
+    dimensions: i32,
 }
 
 impl Clone for EmbeddingTable {
     fn clone(&self) -> Self {
         Self {
             table: self.table.clone(),
+            dimensions: self.dimensions,
         }
     }
 }
 
 impl EmbeddingTable {
-    /// Open existing table or create a new one.
+    /// Open the default model's table or create a new one.
+    pub async fn open_or_create(connection: &lancedb::Connection) -> Result<Self> {
+        Self::open_or_create_named(connection, TABLE_NAME, EMBEDDING_DIM as usize).await
+    }
+
+    /// Open table `name`, holding `dimensions`-wide embeddings, or create it.
     ///
     /// If the table exists but is corrupted (e.g. process killed mid-write),
     /// it is dropped and recreated. Embeddings can be regenerated from SQLite.
-    pub async fn open_or_create(connection: &lancedb::Connection) -> Result<Self> {
+    pub async fn open_or_create_named(
+        connection: &lancedb::Connection,
+        name: &str,
+        dimensions: usize,
+    ) -> Result<Self> {
+        let dimensions = dimensions as i32;
+
         // Try to open existing table
-        match connection.open_table(TABLE_NAME).execute().await {
-            Ok(table) => return Ok(Self { table }),
+        match connection.open_table(name).execute().await {
+            Ok(table) => return Ok(Self { table, dimensions }),
             Err(error) => {
                 tracing::debug!(%error, "failed to open embeddings table, will create");
             }
         }
 
         // Table doesn't exist or is unreadable — try creating it
-        match Self::create_empty_table(connection).await {
-            Ok(table) => return Ok(Self { table }),
+        match Self::create_empty_table(connection, name, dimensions).await {
+            Ok(table) => return Ok(Self { table, dimensions }),
             Err(error) => {
                 tracing::warn!(
                     %error,
@@ -51,23 +64,32 @@ impl EmbeddingTable {
 
         // Both open and create failed — table data exists but is corrupted.
         // Drop it and recreate from scratch.
-        if let Err(error) = connection.drop_table(TABLE_NAME, &[]).await {
+        if let Err(error) = connection.drop_table(name, &[]).await {
             tracing::warn!(%error, "drop_table failed during recovery, proceeding anyway");
         }
 
-        let table = Self::create_empty_table(connection).await?;
+        let table = Self::create_empty_table(connection, name, dimensions).await?;
         tracing::info!("embeddings table recovered — embeddings will be rebuilt from memory store");
 
-        Ok(Self { table })
+        Ok(Self { table, dimensions })
+    }
+
+    /// Width of the embeddings this table holds.
+    pub fn dimensions(&self) -> usize {
+        self.dimensions as usize
     }
 
     /// Create an empty embeddings table.
-    async fn create_empty_table(connection: &lancedb::Connection) -> Result<lancedb::Table> {
-        let schema = Self::schema();
+    async fn create_empty_table(
+        connection: &lancedb::Connection,
+        name: &str,
+        dimensions: i32,
+    ) -> Result<lancedb::Table> {
+        let schema = Self::schema(dimensions);
         let batches = RecordBatchIterator::new(vec![].into_iter().map(Ok), Arc::new(schema));
 
         connection
-            .create_table(TABLE_NAME, Box::new(batches))
+            .create_table(name, Box::new(batches))
             .execute()
             .await
             .map_err(|e| DbError::LanceDb(e.to_string()).into())
@@ -76,28 +98,41 @@ impl EmbeddingTable {
     /// Store an embedding with content for a memory.
     /// The content is stored for FTS search capability.
     pub async fn store(&self, memory_id: &str, content: &str, embedding: &[f32]) -> Result<()> {
-        if embedding.len() != EMBEDDING_DIM as usize {
+        self.store_many(&[(memory_id, content, embedding)]).await
+    }
+
+    /// Store (memory_id, content, embedding) rows in one write.
+    pub async fn store_many(&self, rows: &[(&str, &str, &[f32])]) -> Result<()> {
+        if rows.is_empty() {
+            return Ok(());
+        }
+        if let Some((_, _, embedding)) = rows
+            .iter()
+            .find(|(_, _, embedding)| embedding.len() != self.dimensions as usize)
+        {
             return Err(DbError::LanceDb(format!(
                 "Embedding dimension mismatch: expected {}, got {}",
-                EMBEDDING_DIM,
+                self.dimensions,
                 embedding.len()
             ))
             .into());
         }
 
-        use arrow_array::{FixedSizeListArray, RecordBatch, StringArray};
+        use arrow_array::{RecordBatch, StringArray};
 
-        let schema = Self::schema();
+        let schema = Self::schema(self.dimensions);
 
         // Build arrays for the record batch
-        let id_array = StringArray::from(vec![memory_id]);
-        let content_array = StringArray::from(vec![content]);
+        let id_array = StringArray::from_iter_values(rows.iter().map(|(id, _, _)| *id));
+        let content_array =
+            StringArray::from_iter_values(rows.iter().map(|(_, content, _)| *content));
 
-        // Convert embedding to FixedSizeListArray
+        // Convert embeddings to FixedSizeListArray
         let embedding_array =
             arrow_array::FixedSizeListArray::from_iter_primitive::<Float32Type, _, _>(
-                vec![Some(embedding.iter().map(|v| Some(*v)).collect::<Vec<_>>())],
-                EMBEDDING_DIM,
+                rows.iter()
+                    .map(|(_, _, embedding)| Some(embedding.iter().map(|v| Some(*v)))),
+                self.dimensions,
             );
 
         let batch = RecordBatch::try_new(
@@ -111,7 +146,8 @@ impl EmbeddingTable {
         .map_err(|e| DbError::LanceDb(e.to_string()))?;
 
         // Create iterator for IntoArrow trait
-        let batches = RecordBatchIterator::new(vec![Ok(batch)], Arc::new(Self::schema()));
+        let batches =
+            RecordBatchIterator::new(vec![Ok(batch)], Arc::new(Self::schema(self.dimensions)));
 
         self.table
             .add(Box::new(batches))
@@ -133,6 +169,54 @@ impl EmbeddingTable {
         Ok(())
     }
 
+    /// Every stored row as (memory_id, content, embedding).
+    pub async fn all(&self) -> Result<Vec<(String, String, Vec<f32>)>> {
+        use lancedb::query::ExecutableQuery;
+
+        let results: Vec<arrow_array::RecordBatch> = self
+            .table
+            .query()
+            .execute()
+            .await
+            .map_err(|e| DbError::LanceDb(e.to_string()))?
+            .try_collect()
+            .await
+            .map_err(|e| DbError::LanceDb(e.to_string()))?;
+
+        let mut rows = Vec::new();
+        for batch in results {
+            let (Some(id_col), Some(content_col), Some(embedding_col)) = (
+                batch.column_by_name("id"),
+                batch.column_by_name("content"),
+                batch.column_by_name("embedding"),
+            ) else {
+                continue;
+            };
+            let ids: &arrow_array::StringArray = id_col.as_string::<i32>();
+            let contents: &arrow_array::StringArray = content_col.as_string::<i32>();
+            let Some(embeddings) = embedding_col
+                .as_any()
+                .downcast_ref::<arrow_array::FixedSizeListArray>()
+            else {
+                continue;
+            };
+
+            for i in 0..ids.len() {
+                if ids.is_valid(i) && embeddings.is_valid(i) {
+                    let values = embeddings.value(i);
+                    let embedding = values.as_primitive::<Float32Type>().values().to_vec();
+                    rows.push((
+                        ids.value(i).to_string(),
+                        contents.value(i).to_string(),
+                        embedding,
+                    ));
+                }
+            }
+        }
+
+        Ok(rows)
+    }
+
     /// Vector similarity search using cosine distance.
     /// Returns (memory_id, distance) pairs sorted by distance (ascending).
     pub async fn vector_search(
@@ -140,10 +224,10 @@ impl EmbeddingTable {
         query_embedding: &[f32],
         limit: usize,
     ) -> Result<Vec<(String, f32)>> {
-        if query_embedding.len() != EMBEDDING_DIM as usize {
+        if query_embedding.len() != self.dimensions as usize {
             return Err(DbError::LanceDb(format!(
                 "Query embedding dimension mismatch: expected {}, got {}",
-                EMBEDDING_DIM,
+                self.dimensions,
                 query_embedding.len()
             ))
             .into());
@@ -338,7 +422,7 @@ impl EmbeddingTable {
     }
 
     /// Get the Arrow schema for the embeddings table.
-    fn schema() -> arrow_schema::Schema {
+    fn schema(dimensions: i32) -> arrow_schema::Schema {
         arrow_schema::Schema::new(vec![
             arrow_schema::Field::new("id", arrow_schema::DataType::Utf8, false),
             arrow_schema::Field::new("content", arrow_schema::DataType::Utf8, false),
@@ -350,7 +434,7 @@ impl EmbeddingTable {
                         arrow_schema::DataType::Float32,
                         true,
                     )),
-                    EMBEDDING_DIM,
+                    dimensions,
                 ),
                 false,
             ),
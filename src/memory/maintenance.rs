@@ -1,5 +1,6 @@
 //! Memory maintenance: decay, prune, merge, reindex.
 
+use crate::db::with_pool;
 use crate::error::Result;
 use crate::memory::MemoryStore;
 use crate::memory::types::{Memory, MemoryType, RelationType};
@@ -98,23 +99,24 @@ async fn prune_memories(memory_store: &MemoryStore, config: &MaintenanceConfig)
     let cutoff_date = now - min_age;
 
     // Get all memories below threshold that are old enough
-    let candidates = sqlx::query(
-        r#"
-        SELECT id FROM memories
-        WHERE importance < ? 
-        AND memory_type != 'identity'
-        AND created_at < ?
-        "#,
-    )
-    .bind(config.prune_threshold)
-    .bind(cutoff_date)
-    .fetch_all(memory_store.pool())
-    .await?;
+    let candidates: Vec<String> = with_pool!(memory_store.pool(), |pool| {
+        sqlx::query_scalar(
+            r#"
+            SELECT id FROM memories
+            WHERE importance < $1 
+            AND memory_type != 'identity'
+            AND created_at < $2
+            "#,
+        )
+        .bind(config.prune_threshold)
+        .bind(cutoff_date)
+        .fetch_all(pool)
+        .await
+    })?;
 
     let mut pruned_count = 0;
 
-    for row in candidates {
-        let id: String = sqlx::Row::try_get(&row, "id")?;
+    for id in candidates {
         memory_store.delete(&id).await?;
         pruned_count += 1;
     }
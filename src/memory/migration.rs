@@ -0,0 +1,332 @@
+//! Re-embedding memories when the embedding model changes.
+//!
+//! `lancedb/index.json` records which model built an agent's vector table
+//! and what the table is called; a table built before the file existed is
+//! the default model's `memory_embeddings`. When `[embedding] model` names a
+//! different model, [`open_search`] keeps serving searches from the old
+//! table, with the old model embedding the queries, and returns a
+//! [`Migration`] that embeds every memory with the new model into a table
+//! of its own. Memories saved, edited, or deleted while it runs are caught
+//! up before searches switch over; then `index.json` is rewritten and the
+//! old table dropped. An interrupted migration starts over on the next run.
+//!
+//! Progress is logged and served at `GET /api/agents/memories/index`.
+
+use crate::AgentId;
+use crate::error::Result;
+use crate::memory::embedding::{DEFAULT_MODEL, EmbeddingModel};
+use crate::memory::lance::{EMBEDDING_DIM, TABLE_NAME};
+use crate::memory::{EmbeddingTable, Memory, MemorySearch, MemoryStore};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Name of the index state file in the LanceDB directory.
+pub const INDEX_FILE: &str = "index.json";
+/// Memories embedded per call.
+const BATCH: usize = 64;
+/// Catch-up passes before switching over regardless of new writes.
+const CATCH_UP_PASSES: usize = 3;
+/// Wait while the memory watchdog is refusing background jobs.
+const PRESSURE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Which model built an agent's vector table, and the table's name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexState {
+    pub model: String,
+    pub dimensions: usize,
+    pub table: String,
+}
+
+impl IndexState {
+    /// A table built before `index.json` existed.
+    pub fn legacy() -> Self {
+        Self {
+            model: DEFAULT_MODEL.into(),
+            dimensions: EMBEDDING_DIM as usize,
+            table: TABLE_NAME.into(),
+        }
+    }
+
+    /// A table for `model`'s embeddings.
+    pub fn for_model(model: &str, dimensions: usize) -> Self {
+        let table = if model == DEFAULT_MODEL {
+            TABLE_NAME.to_string()
+        } else {
+            let slug: String = model
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            format!("{TABLE_NAME}_{slug}")
+        };
+        Self {
+            model: model.into(),
+            dimensions,
+            table,
+        }
+    }
+
+    /// Read the state in `lance_dir`, or [`Self::legacy`] when there's none.
+    pub fn load(lance_dir: &Path) -> Result<Self> {
+        let path = lance_dir.join(INDEX_FILE);
+        match std::fs::read_to_string(&path) {
+            Ok(json) => Ok(serde_json::from_str(&json)
+                .with_context(|| format!("invalid {}", path.display()))?),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::legacy()),
+            Err(error) => Err(anyhow::Error::new(error)
+                .context(format!("failed to read {}", path.display()))
+                .into()),
+        }
+    }
+
+    /// Write the state to `lance_dir`, replacing the old file in one rename.
+    pub fn save(&self, lance_dir: &Path) -> Result<()> {
+        let path = lance_dir.join(INDEX_FILE);
+        let temp = path.with_extension("json.tmp");
+        let json = serde_json::to_string_pretty(self).context("failed to serialize index state")?;
+        std::fs::write(&temp, json)
+            .and_then(|()| std::fs::rename(&temp, &path))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    pub async fn open(&self, connection: &lancedb::Connection) -> Result<EmbeddingTable> {
+        EmbeddingTable::open_or_create_named(connection, &self.table, self.dimensions).await
+    }
+}
+
+/// How far a migration has got.
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationProgress {
+    pub from_model: String,
+    pub to_model: String,
+    pub embedded: usize,
+    pub total: usize,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Open an agent's memory search on its vector table. When the table was
+/// built with another model than `model`, searches keep using it with that
+/// model, and the [`Migration`] that moves them over is returned to spawn.
+pub async fn open_search(
+    data_dir: &Path,
+    connection: &lancedb::Connection,
+    store: Arc<MemoryStore>,
+    model: Arc<EmbeddingModel>,
+) -> Result<(Arc<MemorySearch>, Option<Migration>)> {
+    let lance_dir = data_dir.join("lancedb");
+    let state = IndexState::load(&lance_dir)?;
+    let table = state.open(connection).await?;
+    // Ensure FTS index exists for full-text search queries
+    if let Err(error) = table.ensure_fts_index().await {
+        tracing::warn!(%error, "failed to create FTS index");
+    }
+
+    if state.model == model.name() {
+        return Ok((Arc::new(MemorySearch::new(store, table, model)), None));
+    }
+
+    let old_model = model.load_other(&state.model).with_context(|| {
+        format!(
+            "failed to load {}, which built the memory index; stop spacebot and run \
+             `spacebot memories reembed` to rebuild it",
+            state.model
+        )
+    })?;
+    let search = Arc::new(MemorySearch::new(store, table, Arc::new(old_model)));
+    tracing::info!(
+        from = %state.model,
+        to = %model.name(),
+        "embedding model changed, memories will be re-embedded in the background"
+    );
+    let migration = Migration {
+        search: search.clone(),
+        connection: connection.clone(),
+        lance_dir,
+        from: state,
+        model,
+    };
+    Ok((search, Some(migration)))
+}
+
+/// Re-embeds an agent's memories with a new model, then switches searches
+/// over to them.
+#[derive(Clone)]
+pub struct Migration {
+    search: Arc<MemorySearch>,
+    connection: lancedb::Connection,
+    lance_dir: PathBuf,
+    from: IndexState,
+    model: Arc<EmbeddingModel>,
+}
+
+impl Migration {
+    /// Run under the supervisor, which retries a failed migration.
+    pub fn spawn(self, agent_id: AgentId) -> tokio::task::JoinHandle<()> {
+        crate::supervisor::spawn(Some(agent_id), "embedding migration", move || {
+            let migration = self.clone();
+            tokio::spawn(async move { migration.run().await.map_err(anyhow::Error::from) })
+        })
+    }
+
+    pub async fn run(&self) -> Result<()> {
+        let target = IndexState::for_model(self.model.name(), self.model.dimensions());
+        // A table left by an interrupted run is rebuilt from scratch.
+        if let Err(error) = self.connection.drop_table(&target.table, &[]).await {
+            tracing::debug!(%error, table = %target.table, "no earlier migration table to drop");
+        }
+        let table = target.open(&self.connection).await?;
+
+        let memories = self.search.store().get_all().await?;
+        let mut progress = MigrationProgress {
+            from_model: self.from.model.clone(),
+            to_model: target.model.clone(),
+            embedded: 0,
+            total: memories.len(),
+            started_at: chrono::Utc::now(),
+        };
+        self.search.set_migration(Some(progress.clone()));
+
+        let mut embedded = HashMap::new();
+        for chunk in memories.chunks(BATCH) {
+            while crate::watchdog::admit_background_job().is_err() {
+                tokio::time::sleep(PRESSURE_BACKOFF).await;
+            }
+            self.embed_into(&table, chunk, &mut embedded).await?;
+            progress.embedded += chunk.len();
+            self.search.set_migration(Some(progress.clone()));
+            tracing::info!(
+                embedded = progress.embedded,
+                total = progress.total,
+                to = %target.model,
+                "re-embedding memories"
+            );
+        }
+        for _ in 0..CATCH_UP_PASSES {
+            if self.catch_up(&table, &mut embedded).await? == 0 {
+                break;
+            }
+        }
+        table.ensure_fts_index().await?;
+
+        target.save(&self.lance_dir)?;
+        self.search.swap_index(table.clone(), self.model.clone());
+        // Cached alert embeddings came from the old model.
+        crate::alerts::shed_cache();
+        // Writes that reached the old table before the swap.
+        self.catch_up(&table, &mut embedded).await?;
+
+        if let Err(error) = self.connection.drop_table(&self.from.table, &[]).await {
+            tracing::warn!(%error, table = %self.from.table, "failed to drop the old embeddings table");
+        }
+        self.search.set_migration(None);
+        tracing::info!(
+            memories = embedded.len(),
+            model = %target.model,
+            elapsed_secs = (chrono::Utc::now() - progress.started_at).num_seconds(),
+            "embedding migration complete"
+        );
+        Ok(())
+    }
+
+    /// Embed `memories` into `table`, noting the content each was embedded
+    /// from in `embedded`.
+    async fn embed_into(
+        &self,
+        table: &EmbeddingTable,
+        memories: &[Memory],
+        embedded: &mut HashMap<String, String>,
+    ) -> Result<()> {
+        let texts = memories
+            .iter()
+            .map(|memory| memory.content.clone())
+            .collect();
+        let embeddings = self.model.embed_many(texts).await?;
+        let rows: Vec<(&str, &str, &[f32])> = memories
+            .iter()
+            .zip(&embeddings)
+            .map(|(memory, embedding)| {
+                (
+                    memory.id.as_str(),
+                    memory.content.as_str(),
+                    embedding.as_slice(),
+                )
+            })
+            .collect();
+        table.store_many(&rows).await?;
+        embedded.extend(
+            memories
+                .iter()
+                .map(|memory| (memory.id.clone(), memory.content.clone())),
+        );
+        Ok(())
+    }
+
+    /// Bring `table` in line with the memory store: drop deleted memories and
+    /// embed new or edited ones. Returns how many changed.
+    async fn catch_up(
+        &self,
+        table: &EmbeddingTable,
+        embedded: &mut HashMap<String, String>,
+    ) -> Result<usize> {
+        let memories = self.search.store().get_all().await?;
+        let current: HashSet<&str> = memories.iter().map(|memory| memory.id.as_str()).collect();
+        let deleted: Vec<String> = embedded
+            .keys()
+            .filter(|id| !current.contains(id.as_str()))
+            .cloned()
+            .collect();
+        for id in &deleted {
+            table.delete(id).await?;
+            embedded.remove(id);
+        }
+
+        let changed: Vec<Memory> = memories
+            .into_iter()
+            .filter(|memory| embedded.get(&memory.id) != Some(&memory.content))
+            .collect();
+        for memory in &changed {
+            if embedded.contains_key(&memory.id) {
+                table.delete(&memory.id).await?;
+            }
+        }
+        for chunk in changed.chunks(BATCH) {
+            self.embed_into(table, chunk, embedded).await?;
+        }
+        Ok(deleted.len() + changed.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_state_defaults_to_the_legacy_table_and_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(IndexState::load(dir.path()).unwrap(), IndexState::legacy());
+        assert_eq!(
+            IndexState::for_model(DEFAULT_MODEL, 384),
+            IndexState::legacy()
+        );
+
+        let state = IndexState::for_model("nomic-ai/nomic-embed-text-v1.5", 768);
+        assert_eq!(
+            state.table,
+            "memory_embeddings_nomic_ai_nomic_embed_text_v1_5"
+        );
+        state.save(dir.path()).unwrap();
+        assert_eq!(IndexState::load(dir.path()).unwrap(), state);
+        assert!(!dir.path().join("index.json.tmp").exists());
+    }
+}
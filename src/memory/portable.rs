@@ -0,0 +1,556 @@
+//! Portable memory archives.
+//!
+//! `spacebot memories export` writes an agent's memories, associations, and
+//! embeddings to a JSON lines file, and `spacebot memories import` loads one
+//! into another instance, so a knowledge base built locally can be shipped
+//! to a deployment without re-embedding it there. The first line is a
+//! [`Header`] naming the format version and the embedding model; each line
+//! after it is one memory (with its embedding, when it has one) or one
+//! association.
+//!
+//! Importing upserts memories and associations by ID, then builds the vector
+//! table in `lancedb.staging` beside the live one and swaps the directories,
+//! keeping the old table as `lancedb.previous`. Search never sees a
+//! half-built table, and an import is undone by moving the old directory
+//! back. `spacebot memories reembed` rebuilds the table the same way from the
+//! memories already stored, for when the embedding model changes.
+//!
+//! The daemon keeps the vector table open, so it has to be stopped first.
+
+use crate::error::{DbError, Result};
+use crate::memory::embedding::EmbeddingModel;
+use crate::memory::migration::IndexState;
+use crate::memory::{Association, Memory, MemoryStore};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Value of [`Header::format`].
+pub const FORMAT: &str = "spacebot-memories";
+/// Archive format version written by this build. Older versions still read.
+pub const FORMAT_VERSION: u32 = 1;
+/// Memories embedded, and vector rows written, per call.
+const EMBED_BATCH: usize = 256;
+
+/// First line of an archive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Header {
+    pub format: String,
+    pub version: u32,
+    /// Model the embeddings came from.
+    pub embedding_model: String,
+    pub dimensions: usize,
+    pub agent_id: String,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Header {
+    fn new(agent_id: &str, index: &IndexState) -> Self {
+        Self {
+            format: FORMAT.into(),
+            version: FORMAT_VERSION,
+            embedding_model: index.model.clone(),
+            dimensions: index.dimensions,
+            agent_id: agent_id.into(),
+            exported_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// A memory and, when it was embedded, its embedding.
+#[derive(Debug, Clone)]
+pub struct ArchivedMemory {
+    pub memory: Memory,
+    pub embedding: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Archive {
+    pub header: Header,
+    pub memories: Vec<ArchivedMemory>,
+    pub associations: Vec<Association>,
+}
+
+/// A line after the header.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum RecordRef<'a> {
+    Memory {
+        memory: &'a Memory,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        embedding: Option<&'a [f32]>,
+    },
+    Association {
+        association: &'a Association,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Record {
+    Memory {
+        memory: Memory,
+        #[serde(default)]
+        embedding: Option<Vec<f32>>,
+    },
+    Association {
+        association: Association,
+    },
+}
+
+impl Archive {
+    pub fn write(&self, writer: impl Write) -> anyhow::Result<()> {
+        let mut writer = std::io::BufWriter::new(writer);
+        serde_json::to_writer(&mut writer, &self.header)?;
+        writeln!(writer)?;
+        for archived in &self.memories {
+            let record = RecordRef::Memory {
+                memory: &archived.memory,
+                embedding: archived.embedding.as_deref(),
+            };
+            serde_json::to_writer(&mut writer, &record)?;
+            writeln!(writer)?;
+        }
+        for association in &self.associations {
+            serde_json::to_writer(&mut writer, &RecordRef::Association { association })?;
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn read(reader: impl BufRead) -> anyhow::Result<Self> {
+        let mut lines = reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()));
+
+        let (_, first) = lines.next().context("archive is empty")?;
+        let header: Header = serde_json::from_str(&first?)
+            .ok()
+            .filter(|header: &Header| header.format == FORMAT)
+            .context("not a spacebot memory archive")?;
+        if header.version > FORMAT_VERSION {
+            anyhow::bail!(
+                "archive format version {} is newer than this build reads ({FORMAT_VERSION}); \
+                 upgrade spacebot to import it",
+                header.version
+            );
+        }
+
+        let mut archive = Self {
+            header,
+            memories: Vec::new(),
+            associations: Vec::new(),
+        };
+        for (index, line) in lines {
+            let record: Record = serde_json::from_str(&line?)
+                .with_context(|| format!("invalid record on line {}", index + 1))?;
+            match record {
+                Record::Memory { memory, embedding } => {
+                    if let Some(embedding) = &embedding
+                        && embedding.len() != archive.header.dimensions
+                    {
+                        anyhow::bail!(
+                            "memory {} on line {} has {} dimensions, the header says {}",
+                            memory.id,
+                            index + 1,
+                            embedding.len(),
+                            archive.header.dimensions
+                        );
+                    }
+                    archive.memories.push(ArchivedMemory { memory, embedding });
+                }
+                Record::Association { association } => archive.associations.push(association),
+            }
+        }
+        Ok(archive)
+    }
+}
+
+/// Collect every memory, association, and embedding an agent has.
+pub async fn export(data_dir: &Path, store: &MemoryStore, agent_id: &str) -> Result<Archive> {
+    let state = IndexState::load(&data_dir.join("lancedb"))?;
+    let lance = crate::db::connect_lance(data_dir).await?;
+    let mut vectors: HashMap<String, Vec<f32>> = state
+        .open(&lance)
+        .await?
+        .all()
+        .await?
+        .into_iter()
+        .map(|(id, _, embedding)| (id, embedding))
+        .collect();
+    let memories = store
+        .get_all()
+        .await?
+        .into_iter()
+        .map(|memory| ArchivedMemory {
+            embedding: vectors.remove(&memory.id),
+            memory,
+        })
+        .collect();
+
+    Ok(Archive {
+        header: Header::new(agent_id, &state),
+        memories,
+        associations: store.get_all_associations().await?,
+    })
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub memories_added: usize,
+    pub memories_updated: usize,
+    pub associations: usize,
+    /// Embeddings taken from the archive.
+    pub embeddings_imported: usize,
+    /// Memories embedded during the import.
+    pub embeddings_computed: usize,
+}
+
+/// Load `archive` into an agent's stores.
+///
+/// The archive's embeddings are used when they come from the model the
+/// agent's vector table was built with, or from any model when the table is
+/// empty. Otherwise, or when some are missing, the archive is refused unless
+/// `model` is given: then the table is rebuilt for `model`, embedding every
+/// memory whose embedding can't be reused.
+pub async fn import(
+    data_dir: &Path,
+    store: &MemoryStore,
+    archive: &Archive,
+    model: Option<&Arc<EmbeddingModel>>,
+) -> Result<ImportReport> {
+    let state = IndexState::load(&data_dir.join("lancedb"))?;
+    let live = crate::db::connect_lance(data_dir).await?;
+    let existing = state.open(&live).await?.all().await?;
+    drop(live);
+
+    let target = match model {
+        Some(model) => IndexState::for_model(model.name(), model.dimensions()),
+        None if existing.is_empty() => {
+            IndexState::for_model(&archive.header.embedding_model, archive.header.dimensions)
+        }
+        None => state.clone(),
+    };
+    let archive_matches = archive.header.embedding_model == target.model
+        && archive.header.dimensions == target.dimensions;
+    if model.is_none() {
+        if !archive_matches {
+            return Err(anyhow::anyhow!(
+                "the archive's embeddings come from {}, but the memory index was built with {}; \
+                 import it with --reembed",
+                archive.header.embedding_model,
+                target.model
+            )
+            .into());
+        }
+        let missing = archive
+            .memories
+            .iter()
+            .filter(|archived| archived.embedding.is_none())
+            .count();
+        if missing > 0 {
+            return Err(anyhow::anyhow!(
+                "{missing} memory(ies) in the archive have no embedding; import it with --reembed"
+            )
+            .into());
+        }
+    }
+
+    let mut report = ImportReport::default();
+    for archived in &archive.memories {
+        if store.load(&archived.memory.id).await?.is_some() {
+            store.update(&archived.memory).await?;
+            report.memories_updated += 1;
+        } else {
+            store.save(&archived.memory).await?;
+            report.memories_added += 1;
+        }
+    }
+    for association in &archive.associations {
+        store.create_association(association).await?;
+        report.associations += 1;
+    }
+
+    // Embeddings that fit the target table, keyed by memory ID with the
+    // content they were made from. The archive's replace the table's.
+    let mut reusable: HashMap<String, (String, Vec<f32>)> = HashMap::new();
+    if state.model == target.model {
+        reusable.extend(
+            existing
+                .into_iter()
+                .map(|(id, content, embedding)| (id, (content, embedding))),
+        );
+    }
+    if archive_matches {
+        for archived in &archive.memories {
+            if let Some(embedding) = &archived.embedding {
+                reusable.insert(
+                    archived.memory.id.clone(),
+                    (archived.memory.content.clone(), embedding.clone()),
+                );
+                report.embeddings_imported += 1;
+            }
+        }
+    }
+
+    let rows = match model {
+        None => reusable
+            .into_iter()
+            .map(|(id, (content, embedding))| (id, content, embedding))
+            .collect(),
+        Some(model) => {
+            let memories = store.get_all().await?;
+            let mut rows = Vec::with_capacity(memories.len());
+            let mut stale = Vec::new();
+            for memory in &memories {
+                match reusable.remove(&memory.id) {
+                    Some((content, embedding)) if content == memory.content => {
+                        rows.push((memory.id.clone(), content, embedding));
+                    }
+                    _ => stale.push(memory),
+                }
+            }
+            report.embeddings_computed = stale.len();
+            rows.extend(embed(model, &stale).await?);
+            rows
+        }
+    };
+
+    replace_vectors(data_dir, &target, &rows).await?;
+    Ok(report)
+}
+
+/// Embed every stored memory with `model` and swap in the new vector table.
+/// Returns how many memories were embedded.
+pub async fn reembed(
+    data_dir: &Path,
+    store: &MemoryStore,
+    model: &Arc<EmbeddingModel>,
+) -> Result<usize> {
+    let memories = store.get_all().await?;
+    let memories: Vec<&Memory> = memories.iter().collect();
+    let rows = embed(model, &memories).await?;
+    let target = IndexState::for_model(model.name(), model.dimensions());
+    replace_vectors(data_dir, &target, &rows).await?;
+    Ok(rows.len())
+}
+
+async fn embed(
+    model: &Arc<EmbeddingModel>,
+    memories: &[&Memory],
+) -> Result<Vec<(String, String, Vec<f32>)>> {
+    let mut rows = Vec::with_capacity(memories.len());
+    for chunk in memories.chunks(EMBED_BATCH) {
+        let texts = chunk.iter().map(|memory| memory.content.clone()).collect();
+        let embeddings = model.embed_many(texts).await?;
+        rows.extend(
+            chunk
+                .iter()
+                .zip(embeddings)
+                .map(|(memory, embedding)| (memory.id.clone(), memory.content.clone(), embedding)),
+        );
+        tracing::info!(
+            embedded = rows.len(),
+            total = memories.len(),
+            "embedding memories"
+        );
+    }
+    Ok(rows)
+}
+
+/// Write `rows` to a fresh `target` table in `lancedb.staging`, then swap it
+/// in for `lancedb`, which is kept as `lancedb.previous`.
+async fn replace_vectors(
+    data_dir: &Path,
+    target: &IndexState,
+    rows: &[(String, String, Vec<f32>)],
+) -> Result<()> {
+    let live = data_dir.join("lancedb");
+    let staging = data_dir.join("lancedb.staging");
+    let previous = data_dir.join("lancedb.previous");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)
+            .with_context(|| format!("failed to remove {}", staging.display()))?;
+    }
+
+    {
+        let connection = lancedb::connect(&staging.to_string_lossy())
+            .execute()
+            .await
+            .map_err(|e| DbError::LanceConnect(e.to_string()))?;
+        let table = target.open(&connection).await?;
+        for chunk in rows.chunks(EMBED_BATCH) {
+            let chunk: Vec<(&str, &str, &[f32])> = chunk
+                .iter()
+                .map(|(id, content, embedding)| {
+                    (id.as_str(), content.as_str(), embedding.as_slice())
+                })
+                .collect();
+            table.store_many(&chunk).await?;
+        }
+        table.ensure_fts_index().await?;
+    }
+    target.save(&staging)?;
+
+    if previous.exists() {
+        std::fs::remove_dir_all(&previous)
+            .with_context(|| format!("failed to remove {}", previous.display()))?;
+    }
+    if live.exists() {
+        std::fs::rename(&live, &previous)
+            .with_context(|| format!("failed to move {} aside", live.display()))?;
+    }
+    std::fs::rename(&staging, &live)
+        .with_context(|| format!("failed to move {} into place", staging.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::lance::EMBEDDING_DIM;
+    use crate::memory::{EmbeddingTable, MemoryType, RelationType};
+
+    fn vector(seed: f32) -> Vec<f32> {
+        (0..EMBEDDING_DIM)
+            .map(|i| seed + i as f32 / 1000.0)
+            .collect()
+    }
+
+    fn archive_of(memories: Vec<ArchivedMemory>, associations: Vec<Association>) -> Archive {
+        Archive {
+            header: Header::new("main", &IndexState::legacy()),
+            memories,
+            associations,
+        }
+    }
+
+    #[test]
+    fn archives_round_trip_and_reject_what_they_cant_read() {
+        let fact = Memory::new("The deploy runs on Railway", MemoryType::Fact);
+        let preference = Memory::new("Ana prefers short replies", MemoryType::Preference);
+        let original = archive_of(
+            vec![
+                ArchivedMemory {
+                    memory: fact.clone(),
+                    embedding: Some(vector(0.1)),
+                },
+                ArchivedMemory {
+                    memory: preference.clone(),
+                    embedding: None,
+                },
+            ],
+            vec![Association::new(
+                &fact.id,
+                &preference.id,
+                RelationType::RelatedTo,
+            )],
+        );
+        let mut bytes = Vec::new();
+        original.write(&mut bytes).expect("archive should write");
+
+        let read = Archive::read(bytes.as_slice()).expect("archive should read");
+        assert_eq!(read.header, original.header);
+        assert_eq!(read.memories[0].memory, fact);
+        assert_eq!(read.memories[0].embedding, Some(vector(0.1)));
+        assert_eq!(read.memories[1].embedding, None);
+        assert_eq!(read.associations, original.associations);
+
+        let text = String::from_utf8(bytes).unwrap();
+        let newer = text.replacen("\"version\":1", "\"version\":2", 1);
+        let error = Archive::read(newer.as_bytes()).unwrap_err().to_string();
+        assert!(error.contains("newer"), "{error}");
+
+        let short = text.replacen(
+            &format!("\"dimensions\":{EMBEDDING_DIM}"),
+            "\"dimensions\":3",
+            1,
+        );
+        assert!(Archive::read(short.as_bytes()).is_err());
+        assert!(Archive::read("{\"channel_id\":\"discord:1\"}\n".as_bytes()).is_err());
+    }
+
+    #[tokio::test]
+    async fn imports_swap_in_a_new_vector_table() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let store = MemoryStore::connect_in_memory().await;
+
+        let existing = Memory::new("Standup is at 10", MemoryType::Fact);
+        store.save(&existing).await.unwrap();
+        let live = crate::db::connect_lance(data_dir.path()).await.unwrap();
+        EmbeddingTable::open_or_create(&live)
+            .await
+            .unwrap()
+            .store(&existing.id, &existing.content, &vector(0.5))
+            .await
+            .unwrap();
+        drop(live);
+
+        let mut updated = existing.clone();
+        updated.content = "Standup moved to 11".into();
+        let added = Memory::new("The deploy runs on Railway", MemoryType::Fact);
+        let archive = archive_of(
+            vec![
+                ArchivedMemory {
+                    memory: updated.clone(),
+                    embedding: Some(vector(0.2)),
+                },
+                ArchivedMemory {
+                    memory: added.clone(),
+                    embedding: Some(vector(0.3)),
+                },
+            ],
+            vec![Association::new(
+                &updated.id,
+                &added.id,
+                RelationType::RelatedTo,
+            )],
+        );
+
+        let report = import(data_dir.path(), &store, &archive, None)
+            .await
+            .expect("import should succeed");
+        assert_eq!(report.memories_added, 1);
+        assert_eq!(report.memories_updated, 1);
+        assert_eq!(report.associations, 1);
+        assert_eq!(report.embeddings_imported, 2);
+
+        let loaded = store.load(&existing.id).await.unwrap().unwrap();
+        assert_eq!(loaded.content, "Standup moved to 11");
+        assert!(data_dir.path().join("lancedb.previous").exists());
+        assert!(!data_dir.path().join("lancedb.staging").exists());
+
+        let live = crate::db::connect_lance(data_dir.path()).await.unwrap();
+        let rows = EmbeddingTable::open_or_create(&live)
+            .await
+            .unwrap()
+            .all()
+            .await
+            .unwrap();
+        let mut contents: Vec<&str> = rows
+            .iter()
+            .map(|(_, content, _)| content.as_str())
+            .collect();
+        contents.sort();
+        assert_eq!(
+            contents,
+            ["Standup moved to 11", "The deploy runs on Railway"]
+        );
+        let (_, _, embedding) = rows.iter().find(|(id, _, _)| *id == existing.id).unwrap();
+        assert_eq!(*embedding, vector(0.2));
+
+        let mut foreign = archive.clone();
+        foreign.header.embedding_model = "nomic-ai/nomic-embed-text-v1.5".into();
+        assert!(
+            import(data_dir.path(), &store, &foreign, None)
+                .await
+                .is_err()
+        );
+    }
+}
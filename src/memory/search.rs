@@ -1,16 +1,33 @@
-//! Memory search: hybrid (vector + FTS + RRF + graph), temporal, importance, and typed queries.
+//! Memory search: hybrid (vector + FTS + keyword + RRF + graph), temporal, importance, and typed queries.
+//!
+//! Hybrid search ranks memories four ways and merges the rankings with
+//! reciprocal rank fusion: embedding similarity, LanceDB full-text search
+//! (stemmed, so "deploys" finds "deployed"), keyword search over the SQL
+//! store (unstemmed, so identifiers, error codes, and names match as
+//! written), and graph traversal from important memories. A memory that
+//! names the exact `ERR_CONN_RESET` a query asks about ranks high even when
+//! its embedding sits far from the query's.
 
 use crate::error::Result;
+use crate::memory::migration::MigrationProgress;
 use crate::memory::types::{Memory, MemorySearchResult, MemoryType, RelationType};
-use crate::memory::{EmbeddingModel, EmbeddingTable, MemoryStore};
+use crate::memory::{EmbeddingModel, EmbeddingTable, MemoryFilter, MemoryStore};
 
+use arc_swap::ArcSwap;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+
+/// Most words of a query used for keyword search.
+const MAX_KEYWORD_TERMS: usize = 16;
+
+/// How many times more vector and FTS matches hybrid search fetches when a
+/// filter is set.
+const FILTERED_FETCH_FACTOR: usize = 4;
 
 /// Which search strategy to use.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum SearchMode {
-    /// Full hybrid: vector + FTS + graph + RRF. Requires a query string.
+    /// Full hybrid: vector + FTS + keyword + graph + RRF. Requires a query string.
     #[default]
     Hybrid,
     /// Most recent memories by creation time. No query needed.
@@ -33,19 +50,28 @@ pub enum SearchSort {
     MostAccessed,
 }
 
+/// A vector table and the model that embeds queries for it.
+struct VectorIndex {
+    table: EmbeddingTable,
+    model: Arc<EmbeddingModel>,
+}
+
 /// Bundles all memory search dependencies.
+///
+/// The vector index is swapped as a whole when a re-embedding migration
+/// finishes; clones share it.
 pub struct MemorySearch {
     store: Arc<MemoryStore>,
-    embedding_table: EmbeddingTable,
-    embedding_model: Arc<EmbeddingModel>,
+    index: Arc<ArcSwap<VectorIndex>>,
+    migration: Arc<Mutex<Option<MigrationProgress>>>,
 }
 
 impl Clone for MemorySearch {
     fn clone(&self) -> Self {
         Self {
             store: Arc::clone(&self.store),
-            embedding_table: self.embedding_table.clone(),
-            embedding_model: Arc::clone(&self.embedding_model),
+            index: Arc::clone(&self.index),
+            migration: Arc::clone(&self.migration),
         }
     }
 }
@@ -67,8 +93,11 @@ impl MemorySearch {
     ) -> Self {
         Self {
             store,
-            embedding_table,
-            embedding_model,
+            index: Arc::new(ArcSwap::from_pointee(VectorIndex {
+                table: embedding_table,
+                model: embedding_model,
+            })),
+            migration: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -77,19 +106,47 @@ impl MemorySearch {
         &self.store
     }
 
-    /// Get a reference to the embedding table.
-    pub fn embedding_table(&self) -> &EmbeddingTable {
-        &self.embedding_table
+    /// The embedding table searches currently read.
+    pub fn embedding_table(&self) -> EmbeddingTable {
+        self.index.load().table.clone()
+    }
+
+    /// The model that embeds queries for [`Self::embedding_table`].
+    pub fn embedding_model(&self) -> Arc<EmbeddingModel> {
+        self.index.load().model.clone()
+    }
+
+    /// Embed `content` and store it for memory `memory_id`, with the table
+    /// and model of one index even if a migration swaps them meanwhile.
+    pub async fn store_embedding(&self, memory_id: &str, content: &str) -> Result<()> {
+        let index = self.index.load_full();
+        let embedding = index.model.embed_one(content).await?;
+        index.table.store(memory_id, content, &embedding).await?;
+        // Safe to call repeatedly — no-ops if the index already exists.
+        if let Err(error) = index.table.ensure_fts_index().await {
+            tracing::warn!(%error, "failed to ensure FTS index after storing an embedding");
+        }
+        Ok(())
+    }
+
+    /// Progress of the re-embedding migration, while one runs.
+    pub fn migration(&self) -> Option<MigrationProgress> {
+        self.migration
+            .lock()
+            .expect("migration progress lock poisoned")
+            .clone()
     }
 
-    /// Get a reference to the embedding model.
-    pub fn embedding_model(&self) -> &EmbeddingModel {
-        &self.embedding_model
+    pub(crate) fn set_migration(&self, progress: Option<MigrationProgress>) {
+        *self
+            .migration
+            .lock()
+            .expect("migration progress lock poisoned") = progress;
     }
 
-    /// Get a shared handle to the embedding model (for async embed_one).
-    pub fn embedding_model_arc(&self) -> &Arc<EmbeddingModel> {
-        &self.embedding_model
+    /// Start searching `table` with queries embedded by `model`.
+    pub(crate) fn swap_index(&self, table: EmbeddingTable, model: Arc<EmbeddingModel>) {
+        self.index.store(Arc::new(VectorIndex { table, model }));
     }
 
     /// Unified search entry point. Dispatches to the appropriate strategy
@@ -116,7 +173,12 @@ impl MemorySearch {
     ) -> Result<Vec<MemorySearchResult>> {
         let memories = self
             .store
-            .get_sorted(sort, config.max_results as i64, config.memory_type)
+            .get_sorted_filtered(
+                sort,
+                config.max_results as i64,
+                config.memory_type,
+                &config.filter,
+            )
             .await?;
 
         let total = memories.len();
@@ -148,6 +210,15 @@ impl MemorySearch {
         query: &str,
         config: &SearchConfig,
     ) -> Result<Vec<MemorySearchResult>> {
+        let index = self.index.load_full();
+        // The vector and FTS lanes can only filter what they return, so
+        // they look further when a filter is set.
+        let lance_limit = if config.filter.is_empty() {
+            config.max_results_per_source
+        } else {
+            config.max_results_per_source * FILTERED_FETCH_FACTOR
+        };
+
         // Collect results from different sources
         let mut vector_results = Vec::new();
         let mut fts_results = Vec::new();
@@ -156,11 +227,7 @@ impl MemorySearch {
         // 1. Full-text search via LanceDB
         // FTS requires an inverted index. If the index doesn't exist yet (empty
         // table, first run) this will fail — fall back to vector + graph search.
-        match self
-            .embedding_table
-            .text_search(query, config.max_results_per_source)
-            .await
-        {
+        match index.table.text_search(query, lance_limit).await {
             Ok(fts_matches) => {
                 for (memory_id, score) in fts_matches {
                     if let Some(memory) = self.store.load(&memory_id).await? {
@@ -178,11 +245,29 @@ impl MemorySearch {
             }
         }
 
-        // 2. Vector similarity search via LanceDB
-        let query_embedding = self.embedding_model.embed_one(query).await?;
-        match self
-            .embedding_table
-            .vector_search(&query_embedding, config.max_results_per_source)
+        // 2. Keyword search over the SQL store, for words the stemmed FTS
+        // index and the embeddings blur: identifiers, error codes, names
+        let keyword_results: Vec<ScoredMemory> = self
+            .store
+            .search_text(
+                &keyword_terms(query),
+                &config.filter,
+                config.max_results_per_source as i64,
+            )
+            .await?
+            .into_iter()
+            .enumerate()
+            .map(|(rank, memory)| ScoredMemory {
+                memory,
+                score: 1.0 / (rank as f64 + 1.0),
+            })
+            .collect();
+
+        // 3. Vector similarity search via LanceDB
+        let query_embedding = index.model.embed_one(query).await?;
+        match index
+            .table
+            .vector_search(&query_embedding, lance_limit)
             .await
         {
             Ok(vector_matches) => {
@@ -203,7 +288,7 @@ impl MemorySearch {
             }
         }
 
-        // 3. Graph traversal from high-importance memories
+        // 4. Graph traversal from high-importance memories
         // Get identity and high-importance memories as starting points
         let seed_memories = self.store.get_high_importance(0.8, 20).await?;
 
@@ -225,9 +310,16 @@ impl MemorySearch {
             }
         }
 
-        // 4. Merge results using Reciprocal Rank Fusion (RRF)
-        let fused_results =
-            reciprocal_rank_fusion(&vector_results, &fts_results, &graph_results, config.rrf_k);
+        // 5. Merge results using Reciprocal Rank Fusion (RRF)
+        let fused_results = reciprocal_rank_fusion(
+            &[
+                &vector_results,
+                &fts_results,
+                &keyword_results,
+                &graph_results,
+            ],
+            config.rrf_k,
+        );
 
         // Convert to MemorySearchResult with ranks, applying optional type filter
         let results: Vec<MemorySearchResult> = fused_results
@@ -236,6 +328,7 @@ impl MemorySearch {
                 config
                     .memory_type
                     .is_none_or(|t| scored.memory.memory_type == t)
+                    && config.filter.matches(&scored.memory)
             })
             .enumerate()
             .map(|(rank, scored)| MemorySearchResult {
@@ -332,7 +425,7 @@ pub struct SearchConfig {
     pub sort_by: SearchSort,
     /// Maximum number of results to return.
     pub max_results: usize,
-    /// Maximum number of results from each source (vector, fts, graph) in hybrid mode.
+    /// Maximum number of results from each source (vector, fts, keyword, graph) in hybrid mode.
     pub max_results_per_source: usize,
     /// RRF k parameter (typically 60). Only used in hybrid mode.
     pub rrf_k: f64,
@@ -340,6 +433,8 @@ pub struct SearchConfig {
     pub min_score: f32,
     /// Maximum graph traversal depth. Only used in hybrid mode.
     pub max_graph_depth: usize,
+    /// Metadata results must match, in every mode.
+    pub filter: MemoryFilter,
 }
 
 impl Default for SearchConfig {
@@ -355,6 +450,7 @@ impl Default for SearchConfig {
             // score is ~0.016. Set threshold low enough to not discard everything.
             min_score: 0.0,
             max_graph_depth: 2,
+            filter: MemoryFilter::default(),
         }
     }
 }
@@ -368,40 +464,18 @@ struct ScoredMemory {
 
 /// Reciprocal Rank Fusion to combine results from multiple sources.
 /// RRF score = sum(1 / (k + rank)) for each list where the item appears.
-fn reciprocal_rank_fusion(
-    vector_results: &[ScoredMemory],
-    fts_results: &[ScoredMemory],
-    graph_results: &[ScoredMemory],
-    k: f64,
-) -> Vec<ScoredMemory> {
+fn reciprocal_rank_fusion(lists: &[&[ScoredMemory]], k: f64) -> Vec<ScoredMemory> {
     // Build a map of memory ID to RRF score
     let mut rrf_scores: HashMap<String, (f64, Memory)> = HashMap::new();
 
-    // Add vector results
-    for (rank, scored) in vector_results.iter().enumerate() {
-        let rrf_score = 1.0 / (k + (rank as f64 + 1.0));
-        let entry = rrf_scores
-            .entry(scored.memory.id.clone())
-            .or_insert((0.0, scored.memory.clone()));
-        entry.0 += rrf_score;
-    }
-
-    // Add FTS results
-    for (rank, scored) in fts_results.iter().enumerate() {
-        let rrf_score = 1.0 / (k + (rank as f64 + 1.0));
-        let entry = rrf_scores
-            .entry(scored.memory.id.clone())
-            .or_insert((0.0, scored.memory.clone()));
-        entry.0 += rrf_score;
-    }
-
-    // Add graph results
-    for (rank, scored) in graph_results.iter().enumerate() {
-        let rrf_score = 1.0 / (k + (rank as f64 + 1.0));
-        let entry = rrf_scores
-            .entry(scored.memory.id.clone())
-            .or_insert((0.0, scored.memory.clone()));
-        entry.0 += rrf_score;
+    for results in lists {
+        for (rank, scored) in results.iter().enumerate() {
+            let rrf_score = 1.0 / (k + (rank as f64 + 1.0));
+            let entry = rrf_scores
+                .entry(scored.memory.id.clone())
+                .or_insert((0.0, scored.memory.clone()));
+            entry.0 += rrf_score;
+        }
     }
 
     // Convert to vec and sort by RRF score
@@ -415,6 +489,25 @@ fn reciprocal_rank_fusion(
     fused
 }
 
+/// The words of `query` for keyword search, without duplicates. Quotes are
+/// dropped so a word can't break the query syntax, and punctuation around a
+/// word is trimmed, but punctuation inside it is kept: `ERR_CONN_RESET`,
+/// `api-7`, and `v2.3.1` stay whole.
+fn keyword_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in query.split_whitespace() {
+        let word: String = word.chars().filter(|c| *c != '"').collect();
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+        if !word.is_empty() && !terms.iter().any(|term| term.eq_ignore_ascii_case(word)) {
+            terms.push(word.to_string());
+        }
+        if terms.len() == MAX_KEYWORD_TERMS {
+            break;
+        }
+    }
+    terms
+}
+
 /// Curate search results to return only the most relevant.
 pub fn curate_results(
     results: &[MemorySearchResult],
@@ -438,7 +531,7 @@ mod tests {
     #[test]
     fn test_rrf_single_list() {
         let vector = vec![make_scored("a", 0.9), make_scored("b", 0.7)];
-        let fused = reciprocal_rank_fusion(&vector, &[], &[], 60.0);
+        let fused = reciprocal_rank_fusion(&[&vector], 60.0);
 
         assert_eq!(fused.len(), 2);
         assert_eq!(fused[0].memory.id, "a");
@@ -453,7 +546,7 @@ mod tests {
         let vector = vec![make_scored("a", 0.9)];
         let fts = vec![make_scored("a", 5.0)];
 
-        let fused = reciprocal_rank_fusion(&vector, &fts, &[], 60.0);
+        let fused = reciprocal_rank_fusion(&[&vector, &fts], 60.0);
         assert_eq!(fused.len(), 1);
         // Should be 2 * 1/(60+1)
         let expected = 2.0 / 61.0;
@@ -467,17 +560,35 @@ mod tests {
         let fts = vec![make_scored("a", 5.0)];
         let graph = vec![make_scored("a", 0.8)];
 
-        let fused = reciprocal_rank_fusion(&vector, &fts, &graph, 60.0);
+        let fused = reciprocal_rank_fusion(&[&vector, &fts, &graph], 60.0);
         assert_eq!(fused[0].memory.id, "a");
         assert!(fused[0].score > fused[1].score);
     }
 
     #[test]
     fn test_rrf_empty_lists() {
-        let fused = reciprocal_rank_fusion(&[], &[], &[], 60.0);
+        let fused = reciprocal_rank_fusion(&[&[], &[]], 60.0);
         assert!(fused.is_empty());
     }
 
+    #[test]
+    fn test_keyword_terms_keep_identifiers_whole() {
+        assert_eq!(
+            keyword_terms("Why did api-7 fail with \"ERR_CONN_RESET\"? (v2.3.1)"),
+            [
+                "Why",
+                "did",
+                "api-7",
+                "fail",
+                "with",
+                "ERR_CONN_RESET",
+                "v2.3.1"
+            ]
+        );
+        assert_eq!(keyword_terms("Ana ana ANA"), ["Ana"]);
+        assert!(keyword_terms(" ?! \"\" ").is_empty());
+    }
+
     #[test]
     fn test_curate_results_respects_limit() {
         let results: Vec<MemorySearchResult> = (0..10)
@@ -1,60 +1,66 @@
-//! Memory graph storage (SQLite).
+//! Memory graph storage (SQLite or Postgres).
 
+use crate::db::{Column, DatabaseBackend, SqlPool, with_pool, with_read_pool};
 use crate::error::{MemoryError, Result};
+use crate::memory::filter::{FilterParam, MemoryFilter};
 use crate::memory::search::SearchSort;
 use crate::memory::types::{Association, Memory, MemoryType, RelationType};
 
 use anyhow::Context as _;
-use sqlx::{Row, SqlitePool};
+use sqlx::Row;
 
 use std::sync::Arc;
 
 /// Memory store for CRUD and graph operations.
 pub struct MemoryStore {
-    pool: SqlitePool,
+    pool: SqlPool,
 }
 
 impl std::fmt::Debug for MemoryStore {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("MemoryStore")
-            .field("pool", &"<SqlitePool>")
+            .field("pool", &"<SqlPool>")
             .finish()
     }
 }
 
 impl MemoryStore {
-    /// Create a new memory store with the given SQLite pool.
-    pub fn new(pool: SqlitePool) -> Arc<Self> {
+    /// Create a new memory store with the given pool.
+    pub fn new(pool: SqlPool) -> Arc<Self> {
         Arc::new(Self { pool })
     }
 
-    /// Get a reference to the SQLite pool.
-    pub(crate) fn pool(&self) -> &SqlitePool {
+    /// Get a reference to the pool.
+    pub(crate) fn pool(&self) -> &SqlPool {
         &self.pool
     }
 
     /// Save a new memory to the store.
     pub async fn save(&self, memory: &Memory) -> Result<()> {
-        sqlx::query(
-            r#"
-            INSERT INTO memories (id, content, memory_type, importance, created_at, updated_at, 
-                                 last_accessed_at, access_count, source, channel_id, forgotten)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(&memory.id)
-        .bind(&memory.content)
-        .bind(memory.memory_type.to_string())
-        .bind(memory.importance)
-        .bind(memory.created_at)
-        .bind(memory.updated_at)
-        .bind(memory.last_accessed_at)
-        .bind(memory.access_count)
-        .bind(&memory.source)
-        .bind(memory.channel_id.as_ref().map(|id| id.as_ref()))
-        .bind(memory.forgotten)
-        .execute(&self.pool)
-        .await
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                INSERT INTO memories (id, content, memory_type, importance, created_at, updated_at, 
+                                     last_accessed_at, access_count, source, channel_id, forgotten, tags)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                "#,
+            )
+            .bind(&memory.id)
+            .bind(&memory.content)
+            .bind(memory.memory_type.to_string())
+            .bind(memory.importance)
+            .bind(memory.created_at)
+            .bind(memory.updated_at)
+            .bind(memory.last_accessed_at)
+            .bind(memory.access_count)
+            .bind(&memory.source)
+            .bind(memory.channel_id.as_ref().map(|id| id.as_ref()))
+            .bind(memory.forgotten)
+            .bind(tags_json(&memory.tags))
+            .execute(pool)
+            .await
+            .map(drop)
+        })
         .with_context(|| format!("failed to save memory {}", memory.id))?;
 
         Ok(())
@@ -62,45 +68,52 @@ impl MemoryStore {
 
     /// Load a memory by ID.
     pub async fn load(&self, id: &str) -> Result<Option<Memory>> {
-        let row = sqlx::query(
-            r#"
-            SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
-            FROM memories
-            WHERE id = ?
-            "#,
-        )
-        .bind(id)
-        .fetch_optional(&self.pool)
-        .await
+        let memory = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, content, memory_type, importance, created_at, updated_at,
+                       last_accessed_at, access_count, source, channel_id, forgotten, tags
+                FROM memories
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map(|row| row.map(|row| row_to_memory(&row)))
+        })
         .with_context(|| format!("failed to load memory {}", id))?;
 
-        Ok(row.map(|row| row_to_memory(&row)))
+        Ok(memory)
     }
 
     /// Update an existing memory.
     pub async fn update(&self, memory: &Memory) -> Result<()> {
-        sqlx::query(
-            r#"
-            UPDATE memories 
-            SET content = ?, memory_type = ?, importance = ?, updated_at = ?, 
-                last_accessed_at = ?, access_count = ?, source = ?, channel_id = ?,
-                forgotten = ?
-            WHERE id = ?
-            "#,
-        )
-        .bind(&memory.content)
-        .bind(memory.memory_type.to_string())
-        .bind(memory.importance)
-        .bind(memory.updated_at)
-        .bind(memory.last_accessed_at)
-        .bind(memory.access_count)
-        .bind(&memory.source)
-        .bind(memory.channel_id.as_ref().map(|id| id.as_ref()))
-        .bind(memory.forgotten)
-        .bind(&memory.id)
-        .execute(&self.pool)
-        .await
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                UPDATE memories 
+                SET content = $1, memory_type = $2, importance = $3, updated_at = $4, 
+                    last_accessed_at = $5, access_count = $6, source = $7, channel_id = $8,
+                    forgotten = $9, tags = $10
+                WHERE id = $11
+                "#,
+            )
+            .bind(&memory.content)
+            .bind(memory.memory_type.to_string())
+            .bind(memory.importance)
+            .bind(memory.updated_at)
+            .bind(memory.last_accessed_at)
+            .bind(memory.access_count)
+            .bind(&memory.source)
+            .bind(memory.channel_id.as_ref().map(|id| id.as_ref()))
+            .bind(memory.forgotten)
+            .bind(tags_json(&memory.tags))
+            .bind(&memory.id)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
         .with_context(|| format!("failed to update memory {}", memory.id))?;
 
         Ok(())
@@ -108,11 +121,14 @@ impl MemoryStore {
 
     /// Delete a memory by ID.
     pub async fn delete(&self, id: &str) -> Result<()> {
-        sqlx::query("DELETE FROM memories WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
-            .await
-            .with_context(|| format!("failed to delete memory {}", id))?;
+        with_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM memories WHERE id = $1")
+                .bind(id)
+                .execute(pool)
+                .await
+                .map(drop)
+        })
+        .with_context(|| format!("failed to delete memory {}", id))?;
 
         Ok(())
     }
@@ -121,17 +137,20 @@ impl MemoryStore {
     pub async fn record_access(&self, id: &str) -> Result<()> {
         let now = chrono::Utc::now();
 
-        sqlx::query(
-            r#"
-            UPDATE memories 
-            SET last_accessed_at = ?, access_count = access_count + 1
-            WHERE id = ?
-            "#,
-        )
-        .bind(now)
-        .bind(id)
-        .execute(&self.pool)
-        .await
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                UPDATE memories 
+                SET last_accessed_at = $1, access_count = access_count + 1
+                WHERE id = $2
+                "#,
+            )
+            .bind(now)
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
         .with_context(|| format!("failed to record access for memory {}", id))?;
 
         Ok(())
@@ -140,36 +159,42 @@ impl MemoryStore {
     /// Mark a memory as forgotten. The memory stays in the database but is
     /// excluded from search results and recall.
     pub async fn forget(&self, id: &str) -> Result<bool> {
-        let result = sqlx::query(
-            "UPDATE memories SET forgotten = 1, updated_at = ? WHERE id = ? AND forgotten = 0",
-        )
-        .bind(chrono::Utc::now())
-        .bind(id)
-        .execute(&self.pool)
-        .await
+        let rows_affected = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "UPDATE memories SET forgotten = TRUE, updated_at = $1 WHERE id = $2 AND forgotten = FALSE",
+            )
+            .bind(chrono::Utc::now())
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+        })
         .with_context(|| format!("failed to forget memory {}", id))?;
 
-        Ok(result.rows_affected() > 0)
+        Ok(rows_affected > 0)
     }
 
     /// Create an association between two memories.
     pub async fn create_association(&self, association: &Association) -> Result<()> {
-        sqlx::query(
-            r#"
-            INSERT INTO associations (id, source_id, target_id, relation_type, weight, created_at)
-            VALUES (?, ?, ?, ?, ?, ?)
-            ON CONFLICT(source_id, target_id, relation_type) DO UPDATE SET
-                weight = excluded.weight
-            "#,
-        )
-        .bind(&association.id)
-        .bind(&association.source_id)
-        .bind(&association.target_id)
-        .bind(association.relation_type.to_string())
-        .bind(association.weight)
-        .bind(association.created_at)
-        .execute(&self.pool)
-        .await
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                INSERT INTO associations (id, source_id, target_id, relation_type, weight, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT(source_id, target_id, relation_type) DO UPDATE SET
+                    weight = excluded.weight
+                "#,
+            )
+            .bind(&association.id)
+            .bind(&association.source_id)
+            .bind(&association.target_id)
+            .bind(association.relation_type.to_string())
+            .bind(association.weight)
+            .bind(association.created_at)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
         .with_context(|| {
             format!(
                 "failed to create association from {} to {}",
@@ -182,24 +207,21 @@ impl MemoryStore {
 
     /// Get all associations for a memory (both incoming and outgoing).
     pub async fn get_associations(&self, memory_id: &str) -> Result<Vec<Association>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, source_id, target_id, relation_type, weight, created_at
-            FROM associations
-            WHERE source_id = ? OR target_id = ?
-            "#,
-        )
-        .bind(memory_id)
-        .bind(memory_id)
-        .fetch_all(&self.pool)
-        .await
+        let associations = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, source_id, target_id, relation_type, weight, created_at
+                FROM associations
+                WHERE source_id = $1 OR target_id = $1
+                "#,
+            )
+            .bind(memory_id)
+            .fetch_all(pool)
+            .await
+            .map(|rows| rows.iter().map(row_to_association).collect())
+        })
         .with_context(|| format!("failed to get associations for memory {}", memory_id))?;
 
-        let associations = rows
-            .into_iter()
-            .map(|row| row_to_association(&row))
-            .collect();
-
         Ok(associations)
     }
 
@@ -213,33 +235,32 @@ impl MemoryStore {
             return Ok(Vec::new());
         }
 
-        // Build a parameterized IN clause. SQLite handles this fine for
-        // the sizes we deal with (up to ~500 IDs).
-        let placeholders: String = memory_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        // Build a parameterized IN clause. Both backends handle this fine for
+        // the sizes we deal with (up to ~500 IDs). Numbered placeholders let
+        // the source_id and target_id lists share one set of binds.
+        let placeholders: String = (1..=memory_ids.len())
+            .map(|index| format!("${index}"))
+            .collect::<Vec<_>>()
+            .join(",");
         let query_str = format!(
             "SELECT id, source_id, target_id, relation_type, weight, created_at \
              FROM associations \
              WHERE source_id IN ({placeholders}) AND target_id IN ({placeholders})"
         );
 
-        let mut query = sqlx::query(&query_str);
-        // Bind once for source_id IN, once for target_id IN
-        for id in memory_ids {
-            query = query.bind(id);
-        }
-        for id in memory_ids {
-            query = query.bind(id);
-        }
-
-        let rows = query
-            .fetch_all(&self.pool)
-            .await
-            .context("failed to get associations between memory set")?;
+        let associations = with_read_pool!(&self.pool, |pool| {
+            let mut query = sqlx::query(&query_str);
+            for id in memory_ids {
+                query = query.bind(id);
+            }
+            query
+                .fetch_all(pool)
+                .await
+                .map(|rows| rows.iter().map(row_to_association).collect())
+        })
+        .context("failed to get associations between memory set")?;
 
-        Ok(rows
-            .into_iter()
-            .map(|row| row_to_association(&row))
-            .collect())
+        Ok(associations)
     }
 
     /// Get neighbors of a memory: all associations plus the connected memories.
@@ -307,55 +328,204 @@ impl MemoryStore {
     pub async fn get_by_type(&self, memory_type: MemoryType, limit: i64) -> Result<Vec<Memory>> {
         let type_str = memory_type.to_string();
 
-        let rows = sqlx::query(
-            r#"
-            SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
-            FROM memories
-            WHERE memory_type = ? AND forgotten = 0
-            ORDER BY importance DESC, updated_at DESC
-            LIMIT ?
-            "#,
-        )
-        .bind(&type_str)
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await
+        let memories = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, content, memory_type, importance, created_at, updated_at,
+                       last_accessed_at, access_count, source, channel_id, forgotten, tags
+                FROM memories
+                WHERE memory_type = $1 AND forgotten = FALSE
+                ORDER BY importance DESC, updated_at DESC
+                LIMIT $2
+                "#,
+            )
+            .bind(&type_str)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map(|rows| rows.iter().map(row_to_memory).collect())
+        })
         .with_context(|| format!("failed to get memories by type {:?}", memory_type))?;
 
-        Ok(rows.into_iter().map(|row| row_to_memory(&row)).collect())
+        Ok(memories)
+    }
+
+    /// Get every memory saved from a channel, forgotten ones included.
+    pub async fn get_by_channel(&self, channel_id: &str) -> Result<Vec<Memory>> {
+        let memories = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, content, memory_type, importance, created_at, updated_at,
+                       last_accessed_at, access_count, source, channel_id, forgotten, tags
+                FROM memories
+                WHERE channel_id = $1
+                ORDER BY created_at
+                "#,
+            )
+            .bind(channel_id)
+            .fetch_all(pool)
+            .await
+            .map(|rows| rows.iter().map(row_to_memory).collect())
+        })
+        .with_context(|| format!("failed to get memories for channel {channel_id}"))?;
+
+        Ok(memories)
     }
 
     /// Get high-importance memories for injection into context.
     pub async fn get_high_importance(&self, threshold: f32, limit: i64) -> Result<Vec<Memory>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, content, memory_type, importance, created_at, updated_at,
-                   last_accessed_at, access_count, source, channel_id, forgotten
-            FROM memories
-            WHERE importance >= ? AND forgotten = 0
-            ORDER BY importance DESC, updated_at DESC
-            LIMIT ?
-            "#,
-        )
-        .bind(threshold)
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await
+        let memories = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, content, memory_type, importance, created_at, updated_at,
+                       last_accessed_at, access_count, source, channel_id, forgotten, tags
+                FROM memories
+                WHERE importance >= $1 AND forgotten = FALSE
+                ORDER BY importance DESC, updated_at DESC
+                LIMIT $2
+                "#,
+            )
+            .bind(threshold)
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+            .map(|rows| rows.iter().map(row_to_memory).collect())
+        })
         .with_context(|| "failed to get high importance memories")?;
 
-        Ok(rows.into_iter().map(|row| row_to_memory(&row)).collect())
+        Ok(memories)
+    }
+
+    /// Keyword search over memory content, best match first, among the
+    /// memories `filter` lets through. A memory matches when it has any of
+    /// `terms` as whole words, or in SQLite as a word prefix; a term of
+    /// several words, like `ERR_CONN_RESET`, matches them in sequence. Words
+    /// aren't stemmed, so identifiers and names match as written.
+    pub async fn search_text(
+        &self,
+        terms: &[String],
+        filter: &MemoryFilter,
+        limit: i64,
+    ) -> Result<Vec<Memory>> {
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (conditions, params) = filter.sql_conditions(3);
+        let (query_str, query) = match self.pool.backend() {
+            DatabaseBackend::Sqlite => (
+                format!(
+                    "SELECT m.id, m.content, m.memory_type, m.importance, m.created_at, \
+                     m.updated_at, m.last_accessed_at, m.access_count, m.source, m.channel_id, \
+                     m.forgotten, m.tags \
+                     FROM memories_fts \
+                     JOIN memories m ON m.rowid = memories_fts.rowid \
+                     WHERE memories_fts MATCH $1 AND m.forgotten = FALSE{conditions} \
+                     ORDER BY bm25(memories_fts) \
+                     LIMIT $2"
+                ),
+                terms
+                    .iter()
+                    .map(|term| format!("\"{term}\"*"))
+                    .collect::<Vec<_>>()
+                    .join(" OR "),
+            ),
+            DatabaseBackend::Postgres => (
+                format!(
+                    "SELECT id, content, memory_type, importance, created_at, updated_at, \
+                     last_accessed_at, access_count, source, channel_id, forgotten, tags \
+                     FROM memories \
+                     WHERE forgotten = FALSE{conditions} \
+                     AND to_tsvector('simple', content) @@ websearch_to_tsquery('simple', $1) \
+                     ORDER BY ts_rank(to_tsvector('simple', content), \
+                     websearch_to_tsquery('simple', $1)) DESC \
+                     LIMIT $2"
+                ),
+                terms
+                    .iter()
+                    .map(|term| format!("\"{term}\""))
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+            ),
+        };
+
+        let memories = with_read_pool!(&self.pool, |pool| {
+            let mut query = sqlx::query(&query_str).bind(&query).bind(limit);
+            for param in &params {
+                query = match param {
+                    FilterParam::Text(text) => query.bind(text),
+                    FilterParam::Time(time) => query.bind(*time),
+                };
+            }
+            query
+                .fetch_all(pool)
+                .await
+                .map(|rows| rows.iter().map(row_to_memory).collect())
+        })
+        .context("failed to search memory content")?;
+
+        Ok(memories)
+    }
+
+    /// Get every memory, forgotten ones included, oldest first.
+    pub async fn get_all(&self) -> Result<Vec<Memory>> {
+        let memories = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, content, memory_type, importance, created_at, updated_at,
+                       last_accessed_at, access_count, source, channel_id, forgotten, tags
+                FROM memories
+                ORDER BY created_at ASC
+                "#,
+            )
+            .fetch_all(pool)
+            .await
+            .map(|rows| rows.iter().map(row_to_memory).collect())
+        })
+        .context("failed to get all memories")?;
+
+        Ok(memories)
+    }
+
+    /// Get every association.
+    pub async fn get_all_associations(&self) -> Result<Vec<Association>> {
+        let associations = with_read_pool!(&self.pool, |pool| {
+            sqlx::query(
+                r#"
+                SELECT id, source_id, target_id, relation_type, weight, created_at
+                FROM associations
+                ORDER BY created_at ASC
+                "#,
+            )
+            .fetch_all(pool)
+            .await
+            .map(|rows| rows.iter().map(row_to_association).collect())
+        })
+        .context("failed to get all associations")?;
+
+        Ok(associations)
     }
 
     /// Get memories sorted by a flexible criterion with optional type filter.
     ///
     /// Used by non-hybrid search modes (Recent, Important, Typed) to retrieve
-    /// memories directly from SQLite without vector/FTS overhead.
+    /// memories directly from the database without vector/FTS overhead.
     pub async fn get_sorted(
         &self,
         sort: SearchSort,
         limit: i64,
         memory_type: Option<MemoryType>,
+    ) -> Result<Vec<Memory>> {
+        self.get_sorted_filtered(sort, limit, memory_type, &MemoryFilter::default())
+            .await
+    }
+
+    /// [`Self::get_sorted`], limited to the memories `filter` lets through.
+    pub async fn get_sorted_filtered(
+        &self,
+        sort: SearchSort,
+        limit: i64,
+        memory_type: Option<MemoryType>,
+        filter: &MemoryFilter,
     ) -> Result<Vec<Memory>> {
         let order_clause = match sort {
             SearchSort::Recent => "ORDER BY created_at DESC",
@@ -363,41 +533,39 @@ impl MemoryStore {
             SearchSort::MostAccessed => "ORDER BY access_count DESC, created_at DESC",
         };
 
-        let (query_str, type_filter) = if let Some(ref memory_type) = memory_type {
-            (
-                format!(
-                    "SELECT id, content, memory_type, importance, created_at, updated_at, \
-                     last_accessed_at, access_count, source, channel_id, forgotten \
-                     FROM memories WHERE memory_type = ? AND forgotten = 0 {order_clause} LIMIT ?"
-                ),
-                Some(memory_type.to_string()),
-            )
+        let type_filter = memory_type.map(|memory_type| memory_type.to_string());
+        let type_condition = if type_filter.is_some() {
+            " AND memory_type = $2"
         } else {
-            (
-                format!(
-                    "SELECT id, content, memory_type, importance, created_at, updated_at, \
-                     last_accessed_at, access_count, source, channel_id, forgotten \
-                     FROM memories WHERE forgotten = 0 {order_clause} LIMIT ?"
-                ),
-                None,
-            )
+            ""
         };
+        let (conditions, params) = filter.sql_conditions(if type_filter.is_some() { 3 } else { 2 });
+        let query_str = format!(
+            "SELECT id, content, memory_type, importance, created_at, updated_at, \
+             last_accessed_at, access_count, source, channel_id, forgotten, tags \
+             FROM memories WHERE forgotten = FALSE{type_condition}{conditions} \
+             {order_clause} LIMIT $1"
+        );
 
-        let rows = if let Some(type_str) = type_filter {
-            sqlx::query(&query_str)
-                .bind(type_str)
-                .bind(limit)
-                .fetch_all(&self.pool)
-                .await
-        } else {
-            sqlx::query(&query_str)
-                .bind(limit)
-                .fetch_all(&self.pool)
+        let memories = with_read_pool!(&self.pool, |pool| {
+            let mut query = sqlx::query(&query_str).bind(limit);
+            if let Some(type_str) = &type_filter {
+                query = query.bind(type_str);
+            }
+            for param in &params {
+                query = match param {
+                    FilterParam::Text(text) => query.bind(text),
+                    FilterParam::Time(time) => query.bind(*time),
+                };
+            }
+            query
+                .fetch_all(pool)
                 .await
-        }
+                .map(|rows| rows.iter().map(row_to_memory).collect())
+        })
         .with_context(|| format!("failed to get sorted memories ({sort:?})"))?;
 
-        Ok(rows.into_iter().map(|row| row_to_memory(&row)).collect())
+        Ok(memories)
     }
 
     /// Create an in-memory store for testing. Each call creates an isolated
@@ -420,12 +588,23 @@ impl MemoryStore {
             .run(&pool)
             .await
             .expect("migrations");
-        Arc::new(Self { pool })
+        Arc::new(Self {
+            pool: SqlPool::from(pool),
+        })
     }
 }
 
 /// Helper: Convert a database row to a Memory.
-fn row_to_memory(row: &sqlx::sqlite::SqliteRow) -> Memory {
+fn row_to_memory<R>(row: &R) -> Memory
+where
+    R: Row,
+    for<'c> &'c str: sqlx::ColumnIndex<R>,
+    String: Column<R::Database>,
+    f32: Column<R::Database>,
+    i64: Column<R::Database>,
+    bool: Column<R::Database>,
+    chrono::DateTime<chrono::Utc>: Column<R::Database>,
+{
     let mem_type_str: String = row.try_get("memory_type").unwrap_or_default();
     let memory_type = parse_memory_type(&mem_type_str);
 
@@ -449,9 +628,19 @@ fn row_to_memory(row: &sqlx::sqlite::SqliteRow) -> Memory {
         source: row.try_get("source").ok(),
         channel_id: channel_id.map(|id| Arc::from(id) as crate::ChannelId),
         forgotten: row.try_get::<bool, _>("forgotten").unwrap_or(false),
+        tags: row
+            .try_get::<String, _>("tags")
+            .ok()
+            .and_then(|tags| serde_json::from_str(&tags).ok())
+            .unwrap_or_default(),
     }
 }
 
+/// Tags as stored in the `tags` column.
+fn tags_json(tags: &[String]) -> String {
+    serde_json::to_string(tags).unwrap_or_else(|_| "[]".into())
+}
+
 /// Helper: Parse memory type from string.
 fn parse_memory_type(s: &str) -> MemoryType {
     match s {
@@ -468,7 +657,14 @@ fn parse_memory_type(s: &str) -> MemoryType {
 }
 
 /// Helper: Convert a database row to an Association.
-fn row_to_association(row: &sqlx::sqlite::SqliteRow) -> Association {
+fn row_to_association<R>(row: &R) -> Association
+where
+    R: Row,
+    for<'c> &'c str: sqlx::ColumnIndex<R>,
+    String: Column<R::Database>,
+    f32: Column<R::Database>,
+    chrono::DateTime<chrono::Utc>: Column<R::Database>,
+{
     let relation_type_str: String = row.try_get("relation_type").unwrap_or_default();
     let relation_type = parse_relation_type(&relation_type_str);
 
@@ -656,4 +852,135 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].id, visible.id);
     }
+
+    #[tokio::test]
+    async fn test_search_text_matches_identifiers_as_written() {
+        let store = MemoryStore::connect_in_memory().await;
+        let now = Utc::now();
+        let all = MemoryFilter::default();
+        let terms = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+
+        let outage = insert_memory_at(
+            &store,
+            "Deploy of api-7 failed with ERR_CONN_RESET",
+            MemoryType::Event,
+            0.5,
+            now,
+        )
+        .await;
+        insert_memory_at(
+            &store,
+            "The connection was reset by the proxy",
+            MemoryType::Fact,
+            0.5,
+            now,
+        )
+        .await;
+        let mut preference = insert_memory_at(
+            &store,
+            "Ana prefers short replies",
+            MemoryType::Preference,
+            0.5,
+            now,
+        )
+        .await;
+        let forgotten =
+            insert_memory_at(&store, "ERR_CONN_RESET again", MemoryType::Event, 0.5, now).await;
+        store.forget(&forgotten.id).await.unwrap();
+
+        let ids = |memories: Vec<Memory>| memories.into_iter().map(|m| m.id).collect::<Vec<_>>();
+        let found = store
+            .search_text(&terms(&["ERR_CONN_RESET"]), &all, 10)
+            .await
+            .unwrap();
+        assert_eq!(ids(found), [outage.id.clone()]);
+        let found = store
+            .search_text(&terms(&["api-7"]), &all, 10)
+            .await
+            .unwrap();
+        assert_eq!(ids(found), [outage.id.clone()]);
+        let found = store
+            .search_text(&terms(&["What", "does", "Ana", "prefer"]), &all, 10)
+            .await
+            .unwrap();
+        assert_eq!(ids(found), [preference.id.clone()]);
+        assert!(store.search_text(&[], &all, 10).await.unwrap().is_empty());
+
+        // Edits and deletes reach the index.
+        preference.content = "Ana likes long replies".into();
+        store.update(&preference).await.unwrap();
+        let found = store
+            .search_text(&terms(&["prefers"]), &all, 10)
+            .await
+            .unwrap();
+        assert!(found.is_empty());
+        store.delete(&outage.id).await.unwrap();
+        let found = store
+            .search_text(&terms(&["ERR_CONN_RESET"]), &all, 10)
+            .await
+            .unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_filters_limit_keyword_and_sorted_results() {
+        let store = MemoryStore::connect_in_memory().await;
+        let june = "2024-06-10T12:00:00Z".parse().unwrap();
+        let runbook = insert_memory_at(
+            &store,
+            "Restart the api with just restart",
+            MemoryType::Fact,
+            0.5,
+            june,
+        )
+        .await;
+        let mut tagged = runbook.clone().with_tags(["Runbook", "api"]);
+        tagged.source = Some("file:runbooks_api.md".into());
+        store.update(&tagged).await.unwrap();
+        insert_memory_at(
+            &store,
+            "Restart the api after every deploy",
+            MemoryType::Decision,
+            0.5,
+            june,
+        )
+        .await;
+
+        let loaded = store.load(&runbook.id).await.unwrap().unwrap();
+        assert_eq!(loaded.tags, ["runbook", "api"]);
+
+        let terms = vec!["restart".to_string()];
+        for expression in [
+            "tag:runbook",
+            "tag:runbook tag:api during:2024",
+            "source:file:runbooks_",
+        ] {
+            let filter = MemoryFilter::parse(expression).unwrap();
+            let found = store.search_text(&terms, &filter, 10).await.unwrap();
+            assert_eq!(found.len(), 1, "{expression}");
+            assert_eq!(found[0].id, runbook.id);
+            let sorted = store
+                .get_sorted_filtered(SearchSort::Recent, 10, None, &filter)
+                .await
+                .unwrap();
+            assert_eq!(sorted.len(), 1, "{expression}");
+        }
+        for expression in [
+            "tag:run",
+            "source:file:runbooksX",
+            "during:2023",
+            "channel:x",
+        ] {
+            let filter = MemoryFilter::parse(expression).unwrap();
+            let found = store.search_text(&terms, &filter, 10).await.unwrap();
+            assert!(found.is_empty(), "{expression}");
+        }
+        let filter = MemoryFilter::parse("during:2024-06").unwrap();
+        let typed = store
+            .get_sorted_filtered(SearchSort::Recent, 10, Some(MemoryType::Decision), &filter)
+            .await
+            .unwrap();
+        assert_eq!(typed.len(), 1);
+        assert_ne!(typed[0].id, runbook.id);
+    }
 }
@@ -19,6 +19,9 @@ pub struct Memory {
     /// Soft-delete flag. Forgotten memories are excluded from search and recall
     /// but remain in the database.
     pub forgotten: bool,
+    /// Lowercase labels searches can filter on.
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl Memory {
@@ -40,6 +43,7 @@ impl Memory {
             source: None,
             channel_id: None,
             forgotten: false,
+            tags: Vec::new(),
         }
     }
 
@@ -55,6 +59,20 @@ impl Memory {
         self
     }
 
+    /// Set the tags, normalized and without duplicates. Invalid tags are
+    /// dropped.
+    pub fn with_tags<S: AsRef<str>>(mut self, tags: impl IntoIterator<Item = S>) -> Self {
+        self.tags.clear();
+        for tag in tags {
+            if let Some(tag) = crate::memory::filter::normalize_tag(tag.as_ref())
+                && !self.tags.contains(&tag)
+            {
+                self.tags.push(tag);
+            }
+        }
+        self
+    }
+
     /// Set the channel ID.
     pub fn with_channel_id(mut self, channel_id: crate::ChannelId) -> Self {
         self.channel_id = Some(channel_id);
@@ -1,13 +1,87 @@
-//! Messaging adapters (Discord, Slack, Telegram, Twitch, Webhook, WebChat).
+//! Messaging adapters (Discord, Slack, Telegram, Twitch, Webhook, web, WebChat, stdio).
+//!
+//! The Discord, Slack, Telegram, Twitch, and gRPC adapters are each behind a
+//! Cargo feature of the same name, all on by default. Without its feature, a
+//! platform's module holds a stand-in that fails to start. Startup doesn't
+//! start platforms left out of the build; [`missing_platforms`] lists the
+//! enabled ones so it can say so.
 
+use crate::config::MessagingConfig;
+
+pub mod commands;
+#[cfg(feature = "discord")]
 pub mod discord;
+pub mod fences;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod manager;
+#[cfg(feature = "slack")]
 pub mod slack;
+pub mod stdio;
+#[cfg(feature = "telegram")]
 pub mod telegram;
 pub mod traits;
+#[cfg(feature = "twitch")]
 pub mod twitch;
+pub mod web;
 pub mod webchat;
 pub mod webhook;
+pub mod websocket;
+
+#[cfg(not(all(
+    feature = "discord",
+    feature = "slack",
+    feature = "telegram",
+    feature = "twitch",
+    feature = "grpc"
+)))]
+mod unavailable;
+#[cfg(not(feature = "discord"))]
+pub use unavailable::discord;
+#[cfg(not(feature = "grpc"))]
+pub use unavailable::grpc;
+#[cfg(not(feature = "slack"))]
+pub use unavailable::slack;
+#[cfg(not(feature = "telegram"))]
+pub use unavailable::telegram;
+#[cfg(not(feature = "twitch"))]
+pub use unavailable::twitch;
 
 pub use manager::MessagingManager;
 pub use traits::Messaging;
+
+/// Platforms enabled in `config` whose adapter this build doesn't include.
+pub fn missing_platforms(config: &MessagingConfig) -> Vec<&'static str> {
+    let platforms = [
+        (
+            "discord",
+            cfg!(feature = "discord"),
+            config.discord.as_ref().is_some_and(|c| c.enabled),
+        ),
+        (
+            "slack",
+            cfg!(feature = "slack"),
+            config.slack.as_ref().is_some_and(|c| c.enabled),
+        ),
+        (
+            "telegram",
+            cfg!(feature = "telegram"),
+            config.telegram.as_ref().is_some_and(|c| c.enabled),
+        ),
+        (
+            "twitch",
+            cfg!(feature = "twitch"),
+            config.twitch.as_ref().is_some_and(|c| c.enabled),
+        ),
+        (
+            "grpc",
+            cfg!(feature = "grpc"),
+            config.grpc.as_ref().is_some_and(|c| c.enabled),
+        ),
+    ];
+    platforms
+        .into_iter()
+        .filter(|(_, compiled, enabled)| *enabled && !compiled)
+        .map(|(platform, ..)| platform)
+        .collect()
+}
@@ -0,0 +1,397 @@
+//! Slash commands generated from tool definitions.
+//!
+//! Tools listed in `defaults.slash_commands` are offered as channel-native
+//! commands (Discord application commands, Slack slash commands). Each
+//! command's options come from the tool's JSON schema, and an invocation
+//! reaches the channel as [`MessageContent::Command`](crate::MessageContent),
+//! which calls the tool directly instead of starting an LLM turn.
+
+use rig::completion::ToolDefinition;
+use serde_json::{Map, Value};
+
+/// Longest command or option description Discord accepts.
+const MAX_DESCRIPTION_CHARS: usize = 100;
+
+/// Longest command or option name Discord accepts.
+const MAX_NAME_CHARS: usize = 32;
+
+/// How an option's value is typed on the platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    String,
+    Integer,
+    Number,
+    Boolean,
+}
+
+/// One typed option of a slash command.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandParam {
+    pub name: String,
+    pub description: String,
+    pub kind: ParamKind,
+    pub required: bool,
+    /// Allowed values, from the schema's `enum`. Empty means any value.
+    pub choices: Vec<String>,
+}
+
+/// A slash command that calls the tool of the same name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlashCommand {
+    pub name: String,
+    pub description: String,
+    /// Required options first, as Discord requires.
+    pub params: Vec<CommandParam>,
+}
+
+impl SlashCommand {
+    /// Build a command from a tool definition. Properties that can't be a
+    /// command option (arrays, objects) are left out, so a tool that requires
+    /// one can't be a command.
+    pub fn from_tool(definition: &ToolDefinition) -> Option<Self> {
+        if definition.name.is_empty() || definition.name.chars().count() > MAX_NAME_CHARS {
+            return None;
+        }
+        let required: Vec<&str> = definition.parameters["required"]
+            .as_array()
+            .map(|names| names.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let mut params = Vec::new();
+        if let Some(properties) = definition.parameters["properties"].as_object() {
+            for (name, schema) in properties {
+                let is_required = required.contains(&name.as_str());
+                match param_kind(schema) {
+                    Some(kind) if name.chars().count() <= MAX_NAME_CHARS => {
+                        params.push(CommandParam {
+                            name: name.to_lowercase(),
+                            description: short_description(
+                                schema["description"].as_str().unwrap_or_default(),
+                                name,
+                            ),
+                            kind,
+                            required: is_required,
+                            choices: schema["enum"]
+                                .as_array()
+                                .map(|values| {
+                                    values
+                                        .iter()
+                                        .filter_map(Value::as_str)
+                                        .map(str::to_string)
+                                        .collect()
+                                })
+                                .unwrap_or_default(),
+                        })
+                    }
+                    _ if is_required => return None,
+                    _ => {}
+                }
+            }
+        }
+        params.sort_by_key(|param| !param.required);
+
+        Some(Self {
+            name: definition.name.to_lowercase(),
+            description: short_description(&definition.description, &definition.name),
+            params,
+        })
+    }
+
+    /// Parse the free text of a command (Slack passes everything after the
+    /// command name as one string) into typed arguments.
+    ///
+    /// `key=value` tokens set options by name; other tokens fill the
+    /// remaining options in order. A word that doesn't fit the next option
+    /// continues the string option before it, so `/weather New York 3` and
+    /// `/calculate 2 + 2` need no quotes.
+    pub fn parse_text(&self, text: &str) -> Result<Map<String, Value>, String> {
+        let mut raw: Vec<(&CommandParam, String)> = Vec::new();
+        let mut positional = Vec::new();
+        for token in tokenize(text) {
+            let named = token.split_once('=').and_then(|(key, value)| {
+                self.params
+                    .iter()
+                    .find(|param| param.name.eq_ignore_ascii_case(key))
+                    .map(|param| (param, value.to_string()))
+            });
+            match named {
+                Some((param, _)) if raw.iter().any(|(set, _)| set.name == param.name) => {
+                    return Err(format!("`{}` is given twice", param.name));
+                }
+                Some(named) => raw.push(named),
+                None => positional.push(token),
+            }
+        }
+
+        let open: Vec<&CommandParam> = self
+            .params
+            .iter()
+            .filter(|param| !raw.iter().any(|(set, _)| set.name == param.name))
+            .collect();
+        let mut open = open.into_iter().peekable();
+        // Index in `raw` of the string option a stray word continues.
+        let mut continued: Option<usize> = None;
+        for token in positional {
+            let fits = open
+                .peek()
+                .is_some_and(|param| param.coerce(&token).is_ok());
+            if !fits && let Some(index) = continued {
+                let value = &mut raw[index].1;
+                value.push(' ');
+                value.push_str(&token);
+                continue;
+            }
+            let Some(param) = open.next() else {
+                return Err(format!("too many arguments for /{}", self.name));
+            };
+            raw.push((param, token));
+            continued = (param.kind == ParamKind::String && param.choices.is_empty())
+                .then(|| raw.len() - 1);
+        }
+
+        let mut args = Map::new();
+        for (param, value) in raw {
+            args.insert(param.name.clone(), param.coerce(&value)?);
+        }
+        if let Some(missing) = self
+            .params
+            .iter()
+            .find(|param| param.required && !args.contains_key(&param.name))
+        {
+            return Err(format!("`{}` is required", missing.name));
+        }
+        Ok(args)
+    }
+
+    /// Usage line, like `/weather location [days]`.
+    pub fn usage(&self) -> String {
+        let mut usage = format!("/{}", self.name);
+        for param in &self.params {
+            if param.required {
+                usage.push_str(&format!(" {}", param.name));
+            } else {
+                usage.push_str(&format!(" [{}]", param.name));
+            }
+        }
+        usage
+    }
+}
+
+impl CommandParam {
+    /// Convert one raw value to this option's type.
+    pub fn coerce(&self, raw: &str) -> Result<Value, String> {
+        let invalid = |expected: &str| format!("`{}` must be {expected}, got '{raw}'", self.name);
+        let value = match self.kind {
+            ParamKind::String => Value::String(raw.to_string()),
+            ParamKind::Integer => raw
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| invalid("a whole number"))?,
+            ParamKind::Number => raw
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number)
+                .ok_or_else(|| invalid("a number"))?,
+            ParamKind::Boolean => match raw.to_lowercase().as_str() {
+                "true" | "yes" | "y" | "1" => Value::Bool(true),
+                "false" | "no" | "n" | "0" => Value::Bool(false),
+                _ => return Err(invalid("true or false")),
+            },
+        };
+        if self.choices.is_empty() {
+            return Ok(value);
+        }
+        self.choices
+            .iter()
+            .find(|choice| choice.eq_ignore_ascii_case(raw))
+            .map(|choice| Value::String(choice.clone()))
+            .ok_or_else(|| invalid(&format!("one of {}", self.choices.join(", "))))
+    }
+}
+
+/// Commands for the enabled tools, in the order they're listed. Tools that
+/// aren't available or can't be typed as commands are skipped with a warning.
+pub fn catalog(definitions: &[ToolDefinition], enabled: &[String]) -> Vec<SlashCommand> {
+    enabled
+        .iter()
+        .filter_map(|name| {
+            let Some(definition) = definitions.iter().find(|tool| &tool.name == name) else {
+                tracing::warn!(tool = %name, "slash command names a tool channels don't have");
+                return None;
+            };
+            let command = SlashCommand::from_tool(definition);
+            if command.is_none() {
+                tracing::warn!(tool = %name, "tool parameters can't be typed as a slash command");
+            }
+            command
+        })
+        .collect()
+}
+
+/// Reply text for a tool's output. Tools return JSON; show the `message` or
+/// `result` field when there is one, the whole output otherwise.
+pub fn render_output(output: &str) -> String {
+    let Ok(value) = serde_json::from_str::<Value>(output) else {
+        return output.to_string();
+    };
+    match value.get("message").or_else(|| value.get("result")) {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Number(number)) => number.to_string(),
+        _ => match value {
+            Value::String(text) => text,
+            value => format!(
+                "```json\n{}\n```",
+                serde_json::to_string_pretty(&value).unwrap_or_else(|_| output.to_string())
+            ),
+        },
+    }
+}
+
+fn param_kind(schema: &Value) -> Option<ParamKind> {
+    let kind = match &schema["type"] {
+        Value::String(kind) => kind.as_str(),
+        // Optional fields: `["string", "null"]`
+        Value::Array(kinds) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|kind| *kind != "null")?,
+        _ if schema["enum"].is_array() => "string",
+        _ => return None,
+    };
+    match kind {
+        "string" => Some(ParamKind::String),
+        "integer" => Some(ParamKind::Integer),
+        "number" => Some(ParamKind::Number),
+        "boolean" => Some(ParamKind::Boolean),
+        _ => None,
+    }
+}
+
+/// First line of a description, cut to what Discord accepts.
+fn short_description(description: &str, fallback: &str) -> String {
+    let line = description
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or(fallback);
+    if line.chars().count() <= MAX_DESCRIPTION_CHARS {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(MAX_DESCRIPTION_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Split on whitespace, keeping quoted runs together.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut in_token = false;
+    for character in text.chars() {
+        match (quote, character) {
+            (Some(open), character) if character == open => quote = None,
+            (Some(_), character) => current.push(character),
+            (None, '"' | '\'') => {
+                quote = Some(character);
+                in_token = true;
+            }
+            (None, character) if character.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            (None, character) => {
+                current.push(character);
+                in_token = true;
+            }
+        }
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(parameters: Value) -> ToolDefinition {
+        ToolDefinition {
+            name: "weather".into(),
+            description: "Look up the weather forecast.\n\nUse this when asked about weather."
+                .into(),
+            parameters,
+        }
+    }
+
+    fn weather() -> SlashCommand {
+        SlashCommand::from_tool(&definition(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "days": { "type": "integer", "description": "Days to forecast" },
+                "location": { "type": "string", "description": "Where" },
+                "units": { "type": "string", "enum": ["metric", "imperial"] },
+            },
+            "required": ["location"]
+        })))
+        .unwrap()
+    }
+
+    #[test]
+    fn commands_are_typed_from_the_tool_schema() {
+        let command = weather();
+        assert_eq!(command.description, "Look up the weather forecast.");
+        assert_eq!(command.usage(), "/weather location [days] [units]");
+        assert_eq!(command.params[1].kind, ParamKind::Integer);
+        assert_eq!(command.params[2].choices, ["metric", "imperial"]);
+
+        // A required option that can't be typed rules the tool out.
+        let untypeable = definition(serde_json::json!({
+            "type": "object",
+            "properties": { "tags": { "type": "array" } },
+            "required": ["tags"]
+        }));
+        assert_eq!(SlashCommand::from_tool(&untypeable), None);
+    }
+
+    #[test]
+    fn text_arguments_are_parsed_by_name_and_position() {
+        let command = weather();
+
+        let args = command.parse_text("days=3 New York units=Metric").unwrap();
+        assert_eq!(args["location"], "New York");
+        assert_eq!(args["days"], 3);
+        assert_eq!(args["units"], "metric");
+
+        let args = command.parse_text("San Francisco 2 imperial").unwrap();
+        assert_eq!(args["location"], "San Francisco");
+        assert_eq!(args["days"], 2);
+        assert_eq!(args["units"], "imperial");
+
+        let args = command.parse_text("'Paris 3' 1").unwrap();
+        assert_eq!(args["location"], "Paris 3");
+
+        assert!(command.parse_text("").unwrap_err().contains("required"));
+        assert!(
+            command
+                .parse_text("Paris days=soon")
+                .unwrap_err()
+                .contains("whole number")
+        );
+        assert!(command.parse_text("Paris units=kelvin").is_err());
+    }
+
+    #[test]
+    fn tool_output_renders_its_message() {
+        assert_eq!(
+            render_output(r#"{"success":true,"message":"Started flow"}"#),
+            "Started flow"
+        );
+        assert_eq!(render_output(r#"{"result":4}"#), "4");
+        assert!(render_output(r#"{"temperature":21}"#).starts_with("```json"));
+        assert_eq!(render_output("plain text"), "plain text");
+    }
+}
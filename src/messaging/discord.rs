@@ -1,6 +1,8 @@
 //! Discord messaging adapter using serenity.
 
 use crate::config::DiscordPermissions;
+use crate::messaging::commands::{ParamKind, SlashCommand};
+use crate::messaging::fences;
 use crate::messaging::traits::{HistoryMessage, InboundStream, Messaging};
 use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
 
@@ -8,12 +10,13 @@ use anyhow::Context as _;
 use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use serenity::all::{
-    ButtonStyle, ChannelId, ChannelType, ComponentInteraction, Context, CreateActionRow,
-    CreateAttachment, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
-    CreateInteractionResponseMessage, CreateMessage, CreatePoll, CreatePollAnswer,
-    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, CreateThread, EditMessage,
-    EventHandler, GatewayIntents, GetMessages, Http, Interaction, Message, MessageId, ReactionType,
-    Ready, ShardManager, User, UserId,
+    ButtonStyle, ChannelId, ChannelType, Command, CommandDataOptionValue, CommandInteraction,
+    CommandOptionType, ComponentInteraction, Context, CreateActionRow, CreateAttachment,
+    CreateButton, CreateCommand, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreatePoll,
+    CreatePollAnswer, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, CreateThread,
+    EditMessage, EventHandler, GatewayIntents, GetMessages, Http, Interaction, Message, MessageId,
+    ReactionType, Ready, RoleId, ShardManager, User, UserId,
 };
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -30,6 +33,10 @@ pub struct DiscordAdapter {
     /// Typing handles per message. Typing stops when the handle is dropped.
     typing_tasks: Arc<RwLock<HashMap<String, serenity::http::Typing>>>,
     shard_manager: Arc<RwLock<Option<Arc<ShardManager>>>>,
+    /// Slash commands, registered as global application commands on ready.
+    commands: Arc<RwLock<Vec<SlashCommand>>>,
+    /// Maps broadcast targets to the pinned message kept up to date there.
+    pinned_messages: Arc<RwLock<HashMap<String, MessageId>>>,
 }
 
 impl DiscordAdapter {
@@ -42,6 +49,8 @@ impl DiscordAdapter {
             active_messages: Arc::new(RwLock::new(HashMap::new())),
             typing_tasks: Arc::new(RwLock::new(HashMap::new())),
             shard_manager: Arc::new(RwLock::new(None)),
+            commands: Arc::new(RwLock::new(Vec::new())),
+            pinned_messages: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -93,6 +102,7 @@ impl Messaging for DiscordAdapter {
             permissions: self.permissions.clone(),
             http_slot: self.http.clone(),
             bot_user_id_slot: self.bot_user_id.clone(),
+            commands: self.commands.clone(),
         };
 
         let intents = GatewayIntents::GUILD_MESSAGES
@@ -130,7 +140,7 @@ impl Messaging for DiscordAdapter {
             OutboundResponse::Text(text) => {
                 self.stop_typing(message).await;
 
-                for chunk in split_message(&text, 2000) {
+                for chunk in message_chunks(&text) {
                     channel_id
                         .say(&*http, &chunk)
                         .await
@@ -146,7 +156,7 @@ impl Messaging for DiscordAdapter {
             } => {
                 self.stop_typing(message).await;
 
-                let chunks = split_message(&text, 2000);
+                let chunks = message_chunks(&text);
                 for (i, chunk) in chunks.iter().enumerate() {
                     let is_last = i == chunks.len() - 1;
                     let mut msg = CreateMessage::new();
@@ -209,7 +219,7 @@ impl Messaging for DiscordAdapter {
 
                 match thread_result {
                     Ok(thread) => {
-                        for chunk in split_message(&text, 2000) {
+                        for chunk in message_chunks(&text) {
                             thread
                                 .id
                                 .say(&*http, &chunk)
@@ -225,7 +235,7 @@ impl Messaging for DiscordAdapter {
                             thread_name = %thread_name,
                             "failed to create thread, falling back to regular message"
                         );
-                        for chunk in split_message(&text, 2000) {
+                        for chunk in message_chunks(&text) {
                             channel_id
                                 .say(&*http, &chunk)
                                 .await
@@ -378,7 +388,7 @@ impl Messaging for DiscordAdapter {
         };
 
         if let OutboundResponse::Text(text) = response {
-            for chunk in split_message(&text, 2000) {
+            for chunk in message_chunks(&text) {
                 channel_id
                     .say(&*http, &chunk)
                     .await
@@ -392,7 +402,7 @@ impl Messaging for DiscordAdapter {
             ..
         } = response
         {
-            let chunks = split_message(&text, 2000);
+            let chunks = message_chunks(&text);
             for (i, chunk) in chunks.iter().enumerate() {
                 let is_last = i == chunks.len() - 1;
                 let mut msg = CreateMessage::new();
@@ -431,6 +441,63 @@ impl Messaging for DiscordAdapter {
         Ok(())
     }
 
+    async fn broadcast_pinned(&self, target: &str, text: String) -> crate::Result<()> {
+        // DMs can't be pinned to, so they get a new message each time.
+        let Ok(channel_id) = target.parse::<u64>().map(ChannelId::new) else {
+            return self.broadcast(target, OutboundResponse::Text(text)).await;
+        };
+        let http = self.get_http().await?;
+        let text = message_chunks(&text).into_iter().next().unwrap_or_default();
+
+        let known = self.pinned_messages.read().await.get(target).copied();
+        let existing = match known {
+            Some(message_id) => Some(message_id),
+            // After a restart, adopt our own pin that starts the same way.
+            None => {
+                let bot_user_id = *self.bot_user_id.read().await;
+                let heading = text.lines().next().unwrap_or_default();
+                channel_id
+                    .pins(&*http)
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .find(|pinned| {
+                        Some(pinned.author.id) == bot_user_id
+                            && pinned.content.lines().next() == Some(heading)
+                    })
+                    .map(|pinned| pinned.id)
+            }
+        };
+        if let Some(message_id) = existing {
+            let builder = EditMessage::new().content(&text);
+            match channel_id.edit_message(&*http, message_id, builder).await {
+                Ok(_) => {
+                    self.pinned_messages
+                        .write()
+                        .await
+                        .insert(target.to_string(), message_id);
+                    return Ok(());
+                }
+                Err(error) => {
+                    tracing::debug!(%error, %target, "pinned message is gone, posting a new one");
+                }
+            }
+        }
+
+        let posted = channel_id
+            .say(&*http, &text)
+            .await
+            .context("failed to post discord message to pin")?;
+        if let Err(error) = channel_id.pin(&*http, posted.id).await {
+            tracing::warn!(%error, %target, "failed to pin discord message, updating it anyway");
+        }
+        self.pinned_messages
+            .write()
+            .await
+            .insert(target.to_string(), posted.id);
+        Ok(())
+    }
+
     async fn fetch_history(
         &self,
         message: &InboundMessage,
@@ -507,6 +574,20 @@ impl Messaging for DiscordAdapter {
         Ok(history)
     }
 
+    async fn register_commands(&self, commands: &[SlashCommand]) -> crate::Result<()> {
+        *self.commands.write().await = commands.to_vec();
+
+        // Before the gateway is ready the application ID isn't known yet;
+        // the ready handler registers them then.
+        let http = self.http.read().await.clone();
+        if let Some(http) = http
+            && http.application_id().is_some()
+        {
+            set_global_commands(&http, commands).await?;
+        }
+        Ok(())
+    }
+
     async fn health_check(&self) -> crate::Result<()> {
         let http = self.get_http().await?;
         http.get_current_user()
@@ -534,6 +615,7 @@ struct Handler {
     permissions: Arc<ArcSwap<DiscordPermissions>>,
     http_slot: Arc<RwLock<Option<Arc<Http>>>>,
     bot_user_id_slot: Arc<RwLock<Option<UserId>>>,
+    commands: Arc<RwLock<Vec<SlashCommand>>>,
 }
 
 #[async_trait]
@@ -544,6 +626,13 @@ impl EventHandler for Handler {
         *self.http_slot.write().await = Some(ctx.http.clone());
         *self.bot_user_id_slot.write().await = Some(ready.user.id);
         tracing::info!(guild_count = ready.guilds.len(), "discord guilds available");
+
+        let commands = self.commands.read().await;
+        if !commands.is_empty()
+            && let Err(error) = set_global_commands(&ctx.http, &commands).await
+        {
+            tracing::warn!(%error, "failed to register discord slash commands");
+        }
     }
 
     async fn message(&self, ctx: Context, message: Message) {
@@ -639,7 +728,11 @@ impl EventHandler for Handler {
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
         let component = match interaction {
             Interaction::Component(c) => c,
-            _ => return, // Only handle component interactions
+            Interaction::Command(command) => {
+                self.handle_command(&ctx, command).await;
+                return;
+            }
+            _ => return, // Only handle component and command interactions
         };
 
         // Acknowledge the interaction immediately to prevent "This interaction failed" in the UI.
@@ -706,6 +799,9 @@ impl EventHandler for Handler {
                 serde_json::Value::Number(guild_id.get().into()),
             );
         }
+        if let Some(member) = &component.member {
+            metadata.insert("discord_role_ids".into(), role_ids(&member.roles));
+        }
 
         let formatted_author = format!("{} (<@{}>)", user.name, user.id);
         metadata.insert(
@@ -738,6 +834,157 @@ impl EventHandler for Handler {
     }
 }
 
+impl Handler {
+    /// Forward a slash command as a [`MessageContent::Command`]. Discord
+    /// already typed the options, so they pass through as JSON values.
+    async fn handle_command(&self, ctx: &Context, command: CommandInteraction) {
+        let user = &command.user;
+        let permissions = self.permissions.load();
+
+        let allowed = match command.guild_id {
+            None => permissions.dm_allowed_users.contains(&user.id.get()),
+            Some(guild_id) => {
+                permissions
+                    .guild_filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.contains(&guild_id.get()))
+                    && permissions
+                        .channel_filter
+                        .get(&guild_id.get())
+                        .is_none_or(|channels| {
+                            channels.is_empty() || channels.contains(&command.channel_id.get())
+                        })
+            }
+        };
+        let reply = if allowed {
+            format!("Running `/{}`…", command.data.name)
+        } else {
+            "Commands aren't enabled here.".to_string()
+        };
+        // Discord shows "The application did not respond" unless every
+        // command gets a response within 3 seconds. The output follows as a
+        // regular message.
+        if let Err(error) = command
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(reply)
+                        .ephemeral(true),
+                ),
+            )
+            .await
+        {
+            tracing::warn!(%error, "failed to acknowledge slash command");
+        }
+        if !allowed {
+            return;
+        }
+
+        let args = command
+            .data
+            .options
+            .iter()
+            .filter_map(|option| {
+                let value = match &option.value {
+                    CommandDataOptionValue::String(value) => serde_json::Value::from(value.clone()),
+                    CommandDataOptionValue::Integer(value) => serde_json::Value::from(*value),
+                    CommandDataOptionValue::Number(value) => serde_json::Value::from(*value),
+                    CommandDataOptionValue::Boolean(value) => serde_json::Value::from(*value),
+                    _ => return None,
+                };
+                Some((option.name.clone(), value))
+            })
+            .collect();
+
+        let conversation_id = match command.guild_id {
+            Some(guild_id) => format!("discord:{}:{}", guild_id, command.channel_id),
+            None => format!("discord:dm:{}", user.id),
+        };
+
+        let mut metadata = HashMap::new();
+        metadata.insert(
+            "discord_channel_id".into(),
+            serde_json::Value::Number(command.channel_id.get().into()),
+        );
+        if let Some(guild_id) = command.guild_id {
+            metadata.insert(
+                "discord_guild_id".into(),
+                serde_json::Value::Number(guild_id.get().into()),
+            );
+        }
+        if let Some(member) = &command.member {
+            metadata.insert("discord_role_ids".into(), role_ids(&member.roles));
+        }
+        let formatted_author = format!("{} (<@{}>)", user.name, user.id);
+        metadata.insert(
+            "discord_user_id".into(),
+            serde_json::Value::Number(user.id.get().into()),
+        );
+        metadata.insert(
+            "sender_display_name".into(),
+            serde_json::Value::String(formatted_author.clone()),
+        );
+
+        let inbound = InboundMessage {
+            id: command.id.to_string(),
+            source: "discord".into(),
+            conversation_id,
+            sender_id: user.id.to_string(),
+            agent_id: None,
+            content: MessageContent::Command {
+                name: command.data.name.clone(),
+                args,
+            },
+            timestamp: chrono::Utc::now(),
+            metadata,
+            formatted_author: Some(formatted_author),
+        };
+
+        if let Err(error) = self.inbound_tx.send(inbound).await {
+            tracing::warn!(
+                %error,
+                "failed to send inbound slash command from Discord (receiver dropped)"
+            );
+        }
+    }
+}
+
+/// Replace the bot's global application commands with `commands`.
+async fn set_global_commands(http: &Http, commands: &[SlashCommand]) -> anyhow::Result<()> {
+    let builders = commands
+        .iter()
+        .map(|command| {
+            let mut builder = CreateCommand::new(&command.name).description(&command.description);
+            for param in &command.params {
+                let kind = match param.kind {
+                    ParamKind::String => CommandOptionType::String,
+                    ParamKind::Integer => CommandOptionType::Integer,
+                    ParamKind::Number => CommandOptionType::Number,
+                    ParamKind::Boolean => CommandOptionType::Boolean,
+                };
+                let mut option = CreateCommandOption::new(kind, &param.name, &param.description)
+                    .required(param.required);
+                // Discord allows at most 25 choices per option.
+                for choice in param.choices.iter().take(25) {
+                    option = option.add_string_choice(choice, choice);
+                }
+                builder = builder.add_option(option);
+            }
+            builder
+        })
+        .collect();
+
+    let registered = Command::set_global_commands(http, builders)
+        .await
+        .context("failed to register discord slash commands")?;
+    tracing::info!(
+        count = registered.len(),
+        "discord slash commands registered"
+    );
+    Ok(())
+}
+
 fn requires_mention(
     permissions: &DiscordPermissions,
     guild_id: u64,
@@ -827,6 +1074,11 @@ fn resolve_mentions(content: &str, mentions: &[User]) -> String {
     resolved
 }
 
+/// Server role IDs as strings, for matching `access.discord_roles`.
+fn role_ids(roles: &[RoleId]) -> serde_json::Value {
+    roles.iter().map(|role| role.get().to_string()).collect()
+}
+
 async fn build_metadata(
     ctx: &Context,
     message: &Message,
@@ -868,6 +1120,9 @@ async fn build_metadata(
     if message.author.bot {
         metadata.insert("sender_is_bot".into(), true.into());
     }
+    if let Some(member) = &message.member {
+        metadata.insert("discord_role_ids".into(), role_ids(&member.roles));
+    }
 
     if let Some(guild_id) = message.guild_id {
         metadata.insert("discord_guild_id".into(), guild_id.get().into());
@@ -919,6 +1174,15 @@ async fn build_metadata(
     (metadata, formatted_author)
 }
 
+/// Chunks of an outgoing message, with code fences tagged and kept balanced
+/// across chunk breaks.
+fn message_chunks(text: &str) -> Vec<String> {
+    fences::rebalance(split_message(
+        &fences::prepare(text),
+        2000 - fences::REBALANCE_MARGIN,
+    ))
+}
+
 /// Split a message into chunks that fit within Discord's 2000 char limit.
 /// Tries to split at newlines, then spaces, then hard-cuts.
 fn split_message(text: &str, max_len: usize) -> Vec<String> {
@@ -0,0 +1,297 @@
+//! Code fence clean-up for outgoing messages.
+//!
+//! Models often open a fence without naming the language, which leaves the
+//! block unhighlighted on Discord and Slack, and sometimes stop before
+//! closing it, which turns the rest of the message into code. Before a
+//! message is split and sent, untagged fences get a language guessed from
+//! their contents and an unclosed fence is closed. Splitting can still cut a
+//! block in two, so [`rebalance`] closes a block at the end of a chunk and
+//! reopens it, with the same language, at the start of the next.
+
+/// Room a chunk needs for [`rebalance`] to close and reopen a fence.
+pub const REBALANCE_MARGIN: usize = 32;
+
+/// Longest language tag carried over when a fence is reopened.
+const MAX_REOPEN_TAG: usize = REBALANCE_MARGIN - "\n```".len() - "```\n".len();
+
+/// Score a language needs before a fence is tagged with it.
+const MIN_SCORE: usize = 2;
+
+/// Signals of each language: a substring, and whether it must start a line.
+const SIGNALS: &[(&str, &[(&str, bool)])] = &[
+    (
+        "rust",
+        &[
+            ("fn ", true),
+            ("pub fn ", true),
+            ("let mut ", false),
+            ("impl ", true),
+            ("use std::", true),
+            ("println!(", false),
+            ("-> Result<", false),
+            ("&str", false),
+            ("#[derive(", true),
+            ("match ", false),
+        ],
+    ),
+    (
+        "python",
+        &[
+            ("def ", true),
+            ("import ", true),
+            ("from ", true),
+            ("elif ", true),
+            ("print(", false),
+            ("self.", false),
+            ("None", false),
+            ("__init__", false),
+            ("class ", true),
+        ],
+    ),
+    (
+        "typescript",
+        &[
+            ("interface ", true),
+            (": string", false),
+            (": number", false),
+            ("export type ", true),
+            ("as const", false),
+        ],
+    ),
+    (
+        "javascript",
+        &[
+            ("const ", true),
+            ("=> ", false),
+            ("function ", true),
+            ("console.log(", false),
+            ("require(", false),
+            ("export default ", true),
+            ("await ", false),
+            ("===", false),
+        ],
+    ),
+    (
+        "go",
+        &[
+            ("package ", true),
+            ("func ", true),
+            (":= ", false),
+            ("fmt.", false),
+            ("if err != nil", false),
+        ],
+    ),
+    (
+        "java",
+        &[
+            ("public class ", true),
+            ("public static void ", false),
+            ("System.out.", false),
+            ("private ", true),
+            ("@Override", true),
+        ],
+    ),
+    (
+        "cpp",
+        &[
+            ("#include ", true),
+            ("std::", false),
+            ("int main(", false),
+            ("cout <<", false),
+        ],
+    ),
+    (
+        "bash",
+        &[
+            ("#!/bin/", true),
+            ("$ ", true),
+            ("sudo ", true),
+            ("cd ", true),
+            ("echo ", true),
+            ("export ", true),
+            ("npm ", true),
+            ("cargo ", true),
+            ("git ", true),
+            ("apt ", true),
+        ],
+    ),
+    (
+        "sql",
+        &[
+            ("SELECT ", true),
+            ("FROM ", false),
+            ("WHERE ", false),
+            ("INSERT INTO ", true),
+            ("CREATE TABLE ", true),
+            ("UPDATE ", true),
+        ],
+    ),
+    (
+        "html",
+        &[
+            ("<div", false),
+            ("</", false),
+            ("<!DOCTYPE", true),
+            ("<html", true),
+        ],
+    ),
+    (
+        "toml",
+        &[("[", true), (" = \"", false), ("[dependencies]", true)],
+    ),
+    ("diff", &[("@@ ", true), ("+++ ", true), ("--- ", true)]),
+];
+
+/// Guess a code block's language from its contents, or `None` when no
+/// language is a clear fit.
+pub fn detect_language(code: &str) -> Option<&'static str> {
+    let trimmed = code.trim();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return Some("json");
+    }
+
+    let mut best: Option<(&str, usize)> = None;
+    for (language, signals) in SIGNALS {
+        let score = signals
+            .iter()
+            .filter(|(signal, line_start)| {
+                if *line_start {
+                    code.lines()
+                        .any(|line| line.trim_start().starts_with(signal))
+                } else {
+                    code.contains(signal)
+                }
+            })
+            .count();
+        if score >= MIN_SCORE && best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((language, score));
+        }
+    }
+    best.map(|(language, _)| language)
+}
+
+/// A fence line's info string, if the line opens or closes a fence.
+fn fence_info(line: &str) -> Option<&str> {
+    line.trim_start().strip_prefix("```").map(str::trim)
+}
+
+/// Tag untagged fences with a detected language and close a fence left open
+/// at the end.
+pub fn prepare(text: &str) -> String {
+    let lines: Vec<&str> = text.split('\n').collect();
+    let mut output = String::with_capacity(text.len() + 16);
+    let mut open = false;
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            output.push('\n');
+        }
+        match fence_info(line) {
+            Some(info) if !open => {
+                open = true;
+                if info.is_empty() {
+                    let body: Vec<&str> = lines[index + 1..]
+                        .iter()
+                        .take_while(|line| fence_info(line).is_none())
+                        .copied()
+                        .collect();
+                    if let Some(language) = detect_language(&body.join("\n")) {
+                        output.push_str(line.trim_end());
+                        output.push_str(language);
+                        continue;
+                    }
+                }
+            }
+            Some(_) => open = false,
+            None => {}
+        }
+        output.push_str(line);
+    }
+    if open {
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        output.push_str("```");
+    }
+    output
+}
+
+/// Close a fence that runs past the end of a chunk and reopen it at the
+/// start of the next, so every chunk renders on its own. Chunks must leave
+/// [`REBALANCE_MARGIN`] bytes free.
+pub fn rebalance(chunks: Vec<String>) -> Vec<String> {
+    let mut output = Vec::with_capacity(chunks.len());
+    // The info string of a fence still open from the previous chunk.
+    let mut carried: Option<String> = None;
+    for chunk in chunks {
+        let mut text = String::with_capacity(chunk.len() + REBALANCE_MARGIN);
+        let mut open = carried.clone();
+        if let Some(info) = &carried {
+            text.push_str("```");
+            text.push_str(info);
+            text.push('\n');
+        }
+        for line in chunk.split('\n') {
+            if let Some(info) = fence_info(line) {
+                open = match open {
+                    Some(_) => None,
+                    None => Some(info.to_string()),
+                };
+            }
+        }
+        text.push_str(&chunk);
+        if open.is_some() {
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text.push_str("```");
+        }
+        carried = open.map(|info| {
+            if info.len() > MAX_REOPEN_TAG {
+                String::new()
+            } else {
+                info
+            }
+        });
+        output.push(text);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn untagged_fences_get_a_language_and_open_fences_close() {
+        let text = "Try this:\n```\nfn main() {\n    let mut x = 1;\n    println!(\"{x}\");\n}\n```\n\
+                    And run:\n```\ncargo run\n```\nOutput:\n```\n{\"ok\": true}\n```\nThen\n```\nplain words";
+        assert_eq!(
+            prepare(text),
+            "Try this:\n```rust\nfn main() {\n    let mut x = 1;\n    println!(\"{x}\");\n}\n```\n\
+             And run:\n```\ncargo run\n```\nOutput:\n```json\n{\"ok\": true}\n```\nThen\n```\nplain words\n```"
+        );
+
+        // Tagged fences are left alone.
+        let tagged = "```python\nfn main() {}\nlet mut x = 1;\n```";
+        assert_eq!(prepare(tagged), tagged);
+    }
+
+    #[test]
+    fn fences_split_across_chunks_are_reopened() {
+        let chunks = vec![
+            "Here:\n```rust\nfn a() {}".to_string(),
+            "fn b() {}\n```\nDone, and:\n```".to_string(),
+            "tail".to_string(),
+        ];
+        assert_eq!(
+            rebalance(chunks),
+            vec![
+                "Here:\n```rust\nfn a() {}\n```",
+                "```rust\nfn b() {}\n```\nDone, and:\n```\n```",
+                "```\ntail\n```",
+            ]
+        );
+    }
+}
@@ -0,0 +1,639 @@
+//! gRPC messaging adapter: the `AgentService` from `proto/spacebot.proto`.
+//!
+//! For internal services that would rather make typed calls than post to a
+//! webhook and wait for a reply. `Chat` sends a message and returns once the
+//! agent's turn is over; `StreamChat` returns the same turn as a stream of
+//! events. `GetUsage` reports LLM spend and `ReloadConfig` applies
+//! config.toml without waiting for the file watcher.
+//!
+//! Callers authenticate with a key from `[messaging.grpc.api_keys]`. Their
+//! conversations are kept per service as `grpc:<service>:<conversation_id>`.
+//! A conversation has at most one call in flight, and replies only reach a
+//! caller while its call is open.
+
+use crate::api::ApiState;
+use crate::config::GrpcConfig;
+use crate::llm::budget::BudgetStatus;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+
+use anyhow::Context as _;
+use sha2::{Digest as _, Sha256};
+use tokio::sync::{RwLock, mpsc};
+use tonic::{Request, Response, Status};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Types generated from `proto/spacebot.proto`.
+pub mod proto {
+    #![allow(clippy::all)]
+    tonic::include_proto!("spacebot.v1");
+}
+
+use proto::agent_service_server::{AgentService, AgentServiceServer};
+use proto::chat_event::Event;
+
+/// gRPC adapter state.
+pub struct GrpcAdapter {
+    port: u16,
+    bind: String,
+    shared: Arc<Shared>,
+    /// API key digests, by service name.
+    api_keys: Arc<BTreeMap<String, [u8; 32]>>,
+    /// Text streamed so far, by conversation ID, so the final `message`
+    /// event can carry the whole reply.
+    streams: tokio::sync::Mutex<HashMap<String, String>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+/// State shared with the service.
+struct Shared {
+    default_agent: String,
+    request_timeout: Duration,
+    api_state: Arc<ApiState>,
+    inbound_tx: RwLock<Option<mpsc::Sender<InboundMessage>>>,
+    /// Event sink for the call in flight, by conversation ID.
+    calls: Mutex<HashMap<String, mpsc::Sender<Event>>>,
+}
+
+/// The authenticated caller, set by the interceptor.
+#[derive(Clone)]
+struct Caller(String);
+
+impl GrpcAdapter {
+    /// `default_agent` handles messages that don't name an agent. Usage and
+    /// reloads go through `api_state`.
+    pub fn new(
+        config: &GrpcConfig,
+        default_agent: impl Into<String>,
+        api_state: Arc<ApiState>,
+    ) -> Self {
+        let api_keys = config
+            .api_keys
+            .iter()
+            .map(|(service, key)| (service.clone(), digest(key)))
+            .collect();
+        Self {
+            port: config.port,
+            bind: config.bind.clone(),
+            api_keys: Arc::new(api_keys),
+            shared: Arc::new(Shared {
+                default_agent: default_agent.into(),
+                request_timeout: Duration::from_secs(config.request_timeout_secs),
+                api_state,
+                inbound_tx: RwLock::new(None),
+                calls: Mutex::new(HashMap::new()),
+            }),
+            streams: tokio::sync::Mutex::new(HashMap::new()),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Send an event to the call in flight for a conversation, if any.
+    async fn deliver(&self, conversation_id: &str, event: Event) -> bool {
+        let sender = self
+            .shared
+            .calls
+            .lock()
+            .expect("grpc calls poisoned")
+            .get(conversation_id)
+            .cloned();
+        match sender {
+            Some(sender) => sender.send(event).await.is_ok(),
+            None => false,
+        }
+    }
+
+    /// The event for an outbound response, tracking streamed text.
+    async fn event_for(
+        &self,
+        conversation_id: &str,
+        reply_to: Option<&str>,
+        response: OutboundResponse,
+    ) -> Option<Event> {
+        Some(match response {
+            OutboundResponse::StreamStart => {
+                self.streams
+                    .lock()
+                    .await
+                    .insert(conversation_id.to_string(), String::new());
+                Event::StreamStart(proto::StreamStart {})
+            }
+            OutboundResponse::StreamChunk(content) => {
+                if let Some(text) = self.streams.lock().await.get_mut(conversation_id) {
+                    text.push_str(&content);
+                }
+                Event::Token(content)
+            }
+            OutboundResponse::StreamEnd => {
+                let content = self.streams.lock().await.remove(conversation_id)?;
+                Event::Message(proto::Reply {
+                    content,
+                    reply_to: reply_to.unwrap_or_default().to_string(),
+                    streamed: true,
+                })
+            }
+            response => response_event(reply_to, response)?,
+        })
+    }
+}
+
+impl Messaging for GrpcAdapter {
+    fn name(&self) -> &str {
+        "grpc"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+
+        *self.shared.inbound_tx.write().await = Some(inbound_tx);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let service = AgentServiceServer::with_interceptor(
+            Service {
+                shared: self.shared.clone(),
+            },
+            Authenticator {
+                api_keys: self.api_keys.clone(),
+            },
+        );
+
+        let bind = if self.bind.contains(':') {
+            format!("[{}]:{}", self.bind, self.port)
+        } else {
+            format!("{}:{}", self.bind, self.port)
+        };
+        let listener = tokio::net::TcpListener::bind(&bind)
+            .await
+            .with_context(|| format!("failed to bind grpc server to {bind}"))?;
+        let incoming =
+            tonic::transport::server::TcpIncoming::from_listener(listener, true, None)
+                .map_err(|error| anyhow::anyhow!("failed to accept grpc connections: {error}"))?;
+        tracing::info!(%bind, "grpc server listening");
+
+        tokio::spawn(async move {
+            if let Err(error) = tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, async move {
+                    let _ = shutdown_rx.recv().await;
+                })
+                .await
+            {
+                tracing::error!(%error, "grpc server exited with error");
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        if let Some(event) = self
+            .event_for(&message.conversation_id, Some(&message.id), response)
+            .await
+        {
+            self.deliver(&message.conversation_id, event).await;
+        }
+        Ok(())
+    }
+
+    async fn send_status(
+        &self,
+        message: &InboundMessage,
+        status: StatusUpdate,
+    ) -> crate::Result<()> {
+        let event = match status {
+            StatusUpdate::Thinking => Event::Thinking(proto::Thinking {}),
+            StatusUpdate::StopTyping => Event::Done(proto::Done {}),
+            StatusUpdate::ToolStarted { tool_name } => Event::ToolStarted(tool_name),
+            StatusUpdate::ToolCompleted { tool_name } => Event::ToolCompleted(tool_name),
+            _ => return Ok(()),
+        };
+        self.deliver(&message.conversation_id, event).await;
+        Ok(())
+    }
+
+    /// `target` is `<service>:<conversation_id>`. Only reaches the service
+    /// while it has a call open for that conversation.
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        let Some((service, conversation)) = target.split_once(':') else {
+            return Err(anyhow::anyhow!(
+                "grpc target '{target}' must be <service>:<conversation_id>"
+            )
+            .into());
+        };
+        let conversation_id = conversation_id(service, conversation);
+        let Some(event) = self.event_for(&conversation_id, None, response).await else {
+            return Ok(());
+        };
+        if !self.deliver(&conversation_id, event).await {
+            return Err(anyhow::anyhow!("no grpc call in flight for '{target}'").into());
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        tracing::info!("grpc adapter shut down");
+        Ok(())
+    }
+}
+
+fn conversation_id(service: &str, conversation_id: &str) -> String {
+    format!("grpc:{service}:{conversation_id}")
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+/// The service whose API key this is.
+fn service_for_key(api_keys: &BTreeMap<String, [u8; 32]>, key: &str) -> Option<String> {
+    // Compare digests so the time taken says nothing about the key.
+    let digest = digest(key);
+    api_keys
+        .iter()
+        .find(|(_, expected)| **expected == digest)
+        .map(|(service, _)| service.clone())
+}
+
+/// Checks the bearer key on every call and records who is calling.
+#[derive(Clone)]
+struct Authenticator {
+    api_keys: Arc<BTreeMap<String, [u8; 32]>>,
+}
+
+impl tonic::service::Interceptor for Authenticator {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let service = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|key| service_for_key(&self.api_keys, key));
+        let Some(service) = service else {
+            tracing::warn!("grpc call with a missing or unknown API key");
+            return Err(Status::unauthenticated("missing or unknown API key"));
+        };
+        request.extensions_mut().insert(Caller(service));
+        Ok(request)
+    }
+}
+
+/// The event for a non-streaming outbound response. `None` for responses
+/// with no meaning to callers.
+fn response_event(reply_to: Option<&str>, response: OutboundResponse) -> Option<Event> {
+    Some(match response {
+        OutboundResponse::Text(content)
+        | OutboundResponse::ThreadReply { text: content, .. }
+        | OutboundResponse::RichMessage { text: content, .. }
+        | OutboundResponse::Ephemeral { text: content, .. }
+        | OutboundResponse::ScheduledMessage { text: content, .. } => {
+            Event::Message(proto::Reply {
+                content,
+                reply_to: reply_to.unwrap_or_default().to_string(),
+                streamed: false,
+            })
+        }
+        OutboundResponse::File {
+            filename,
+            data,
+            mime_type,
+            caption,
+        } => Event::File(proto::File {
+            filename,
+            mime_type,
+            data,
+            caption: caption.unwrap_or_default(),
+        }),
+        OutboundResponse::StreamStart
+        | OutboundResponse::StreamChunk(_)
+        | OutboundResponse::StreamEnd
+        | OutboundResponse::Reaction(_)
+        | OutboundResponse::RemoveReaction(_)
+        | OutboundResponse::Status(_) => return None,
+    })
+}
+
+/// The inbound message for a chat request. Empty optional fields take
+/// their defaults.
+fn inbound_message(
+    service: &str,
+    default_agent: &str,
+    request: proto::ChatRequest,
+) -> InboundMessage {
+    let non_empty = |value: String| (!value.is_empty()).then_some(value);
+    let sender_id = non_empty(request.sender_id).unwrap_or_else(|| service.to_string());
+    let sender_name = non_empty(request.sender_name).unwrap_or_else(|| sender_id.clone());
+    let agent_id = non_empty(request.agent_id).unwrap_or_else(|| default_agent.to_string());
+
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(sender_name.clone()),
+    );
+    metadata.insert(
+        "grpc_service".into(),
+        serde_json::Value::String(service.to_string()),
+    );
+
+    InboundMessage {
+        id: non_empty(request.message_id).unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        source: "grpc".into(),
+        conversation_id: conversation_id(service, &request.conversation_id),
+        sender_id,
+        agent_id: Some(agent_id.into()),
+        content: MessageContent::Text(request.content),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(sender_name),
+    }
+}
+
+fn status_name(status: BudgetStatus) -> &'static str {
+    match status {
+        BudgetStatus::Normal => "normal",
+        BudgetStatus::Downgrade => "downgrade",
+        BudgetStatus::Exhausted => "exhausted",
+    }
+}
+
+/// A chat call in flight. Dropping it frees the conversation for the next
+/// call, including when a streaming caller goes away early.
+struct Call {
+    shared: Arc<Shared>,
+    conversation_id: String,
+    /// The caller's own conversation ID.
+    caller_conversation_id: String,
+    message_id: String,
+    events: mpsc::Receiver<Event>,
+    deadline: tokio::time::Instant,
+}
+
+impl Call {
+    /// The next event of the turn. `Err` once the deadline passes.
+    async fn next(&mut self) -> Result<Option<Event>, Status> {
+        tokio::time::timeout_at(self.deadline, self.events.recv())
+            .await
+            .map_err(|_| Status::deadline_exceeded("the agent didn't finish its turn in time"))
+    }
+
+    fn chat_event(&self, event: Event) -> proto::ChatEvent {
+        proto::ChatEvent {
+            conversation_id: self.caller_conversation_id.clone(),
+            event: Some(event),
+        }
+    }
+}
+
+impl Drop for Call {
+    fn drop(&mut self) {
+        self.shared
+            .calls
+            .lock()
+            .expect("grpc calls poisoned")
+            .remove(&self.conversation_id);
+    }
+}
+
+struct Service {
+    shared: Arc<Shared>,
+}
+
+impl Service {
+    /// Queue the message and open a call to collect the turn's events.
+    async fn start_call(&self, request: Request<proto::ChatRequest>) -> Result<Call, Status> {
+        let Some(Caller(service)) = request.extensions().get::<Caller>().cloned() else {
+            return Err(Status::unauthenticated("missing or unknown API key"));
+        };
+        let request = request.into_inner();
+        if request.conversation_id.is_empty() || request.content.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "conversation_id and content must not be empty",
+            ));
+        }
+
+        let caller_conversation_id = request.conversation_id.clone();
+        let message = inbound_message(&service, &self.shared.default_agent, request);
+        let (events_tx, events) = mpsc::channel(256);
+        {
+            let mut calls = self.shared.calls.lock().expect("grpc calls poisoned");
+            if calls.contains_key(&message.conversation_id) {
+                return Err(Status::aborted(
+                    "a call for this conversation is already in flight",
+                ));
+            }
+            calls.insert(message.conversation_id.clone(), events_tx);
+        }
+        let call = Call {
+            shared: self.shared.clone(),
+            conversation_id: message.conversation_id.clone(),
+            caller_conversation_id,
+            message_id: message.id.clone(),
+            events,
+            deadline: tokio::time::Instant::now() + self.shared.request_timeout,
+        };
+
+        let Some(inbound_tx) = self.shared.inbound_tx.read().await.clone() else {
+            return Err(Status::unavailable("grpc adapter not initialized"));
+        };
+        inbound_tx
+            .send(message)
+            .await
+            .map_err(|_| Status::unavailable("channel closed"))?;
+        Ok(call)
+    }
+}
+
+#[tonic::async_trait]
+impl AgentService for Service {
+    async fn chat(
+        &self,
+        request: Request<proto::ChatRequest>,
+    ) -> Result<Response<proto::ChatResponse>, Status> {
+        let mut call = self.start_call(request).await?;
+        let mut response = proto::ChatResponse {
+            conversation_id: call.caller_conversation_id.clone(),
+            reply_to: call.message_id.clone(),
+            ..Default::default()
+        };
+        while let Some(event) = call.next().await? {
+            match event {
+                Event::Message(reply) => response.messages.push(reply.content),
+                Event::File(file) => response.files.push(file),
+                Event::Done(_) => break,
+                _ => {}
+            }
+        }
+        Ok(Response::new(response))
+    }
+
+    type StreamChatStream =
+        Pin<Box<dyn futures::Stream<Item = Result<proto::ChatEvent, Status>> + Send>>;
+
+    async fn stream_chat(
+        &self,
+        request: Request<proto::ChatRequest>,
+    ) -> Result<Response<Self::StreamChatStream>, Status> {
+        let mut call = self.start_call(request).await?;
+        let stream = async_stream::stream! {
+            yield Ok(call.chat_event(Event::Accepted(call.message_id.clone())));
+            loop {
+                match call.next().await {
+                    Ok(Some(event)) => {
+                        let done = matches!(event, Event::Done(_));
+                        yield Ok(call.chat_event(event));
+                        if done {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(status) => {
+                        yield Err(status);
+                        break;
+                    }
+                }
+            }
+        };
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_usage(
+        &self,
+        _request: Request<proto::GetUsageRequest>,
+    ) -> Result<Response<proto::GetUsageResponse>, Status> {
+        let Some(llm_manager) = self.shared.api_state.llm_manager.read().await.clone() else {
+            return Err(Status::unavailable("LLM manager not initialized"));
+        };
+        let spend = llm_manager.spend_snapshot();
+        let budgets = llm_manager.provider_budgets();
+        let providers: BTreeSet<&String> = spend.keys().chain(budgets.keys()).collect();
+
+        let providers = providers
+            .into_iter()
+            .map(|provider| {
+                let spent = spend.get(provider).copied().unwrap_or_default();
+                let budget = budgets.get(provider);
+                proto::ProviderUsage {
+                    provider: provider.clone(),
+                    daily_usd: spent.daily_usd,
+                    monthly_usd: spent.monthly_usd,
+                    daily_limit_usd: budget.and_then(|budget| budget.daily_limit_usd),
+                    monthly_limit_usd: budget.and_then(|budget| budget.monthly_limit_usd),
+                    status: status_name(llm_manager.budget_status(provider)).to_string(),
+                }
+            })
+            .collect();
+        Ok(Response::new(proto::GetUsageResponse { providers }))
+    }
+
+    async fn reload_config(
+        &self,
+        request: Request<proto::ReloadConfigRequest>,
+    ) -> Result<Response<proto::ReloadConfigResponse>, Status> {
+        let service = request
+            .extensions()
+            .get::<Caller>()
+            .map(|Caller(service)| service.clone())
+            .unwrap_or_default();
+        let state = &self.shared.api_state;
+        let config_path = state.config_path.read().await.clone();
+        let config = crate::config::Config::load_from_path(&config_path).map_err(|error| {
+            Status::failed_precondition(format!("can't load {}: {error}", config_path.display()))
+        })?;
+
+        if let Some(llm_manager) = state.llm_manager.read().await.as_ref() {
+            llm_manager.reload_config(config.llm.clone());
+            crate::llm::discovery::spawn_discovery(
+                llm_manager,
+                crate::llm::discovery::configured_models(&config),
+            );
+        }
+        if let Some(bindings) = state.bindings.read().await.as_ref() {
+            bindings.store(Arc::new(config.bindings.clone()));
+        }
+        crate::egress::set_policy(config.egress.clone());
+        crate::heartbeat::configure(config.heartbeat.clone());
+        crate::update::configure(config.updates.clone());
+        let runtime_configs = state.runtime_configs.load();
+        let mut agents = Vec::new();
+        for (agent_id, runtime_config) in runtime_configs.iter() {
+            runtime_config.reload_config(&config, agent_id);
+            agents.push(agent_id.clone());
+        }
+        agents.sort();
+
+        tracing::info!(%service, agents = agents.len(), "config reloaded over grpc");
+        Ok(Response::new(proto::ReloadConfigResponse { agents }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::service::Interceptor as _;
+
+    #[test]
+    fn requests_and_responses_map_to_the_proto() {
+        let api_keys = BTreeMap::from([("billing".to_string(), digest("billing-key-0123456789"))]);
+        assert_eq!(
+            service_for_key(&api_keys, "billing-key-0123456789").as_deref(),
+            Some("billing")
+        );
+        assert_eq!(service_for_key(&api_keys, "billing-key"), None);
+
+        let mut request = Request::new(());
+        request.metadata_mut().insert(
+            "authorization",
+            "Bearer billing-key-0123456789".parse().unwrap(),
+        );
+        let mut authenticator = Authenticator {
+            api_keys: Arc::new(api_keys),
+        };
+        let request = authenticator.call(request).unwrap();
+        assert!(matches!(
+            request.extensions().get::<Caller>(),
+            Some(Caller(service)) if service == "billing"
+        ));
+        assert!(authenticator.call(Request::new(())).is_err());
+
+        let message = inbound_message(
+            "billing",
+            "main",
+            proto::ChatRequest {
+                conversation_id: "invoice-7".into(),
+                content: "summarize".into(),
+                message_id: "m1".into(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(message.conversation_id, "grpc:billing:invoice-7");
+        assert_eq!(message.id, "m1");
+        assert_eq!(message.sender_id, "billing");
+        assert_eq!(message.agent_id.as_deref(), Some("main"));
+
+        assert_eq!(
+            response_event(Some("m1"), OutboundResponse::Text("done".into())),
+            Some(Event::Message(proto::Reply {
+                content: "done".into(),
+                reply_to: "m1".into(),
+                streamed: false,
+            }))
+        );
+        assert_eq!(response_event(None, OutboundResponse::StreamEnd), None);
+    }
+}
@@ -1,5 +1,6 @@
 //! MessagingManager: Fan-in and routing for all adapters.
 
+use crate::messaging::commands::SlashCommand;
 use crate::messaging::traits::{HistoryMessage, InboundStream, Messaging, MessagingDyn};
 use crate::{InboundMessage, OutboundResponse, StatusUpdate};
 
@@ -14,20 +15,25 @@ use tokio::sync::{RwLock, mpsc};
 /// Adapters forward messages into a shared mpsc channel, so new adapters
 /// can be registered after `start()` without replacing the inbound stream.
 pub struct MessagingManager {
-    adapters: RwLock<HashMap<String, Arc<dyn MessagingDyn>>>,
+    /// Shared with forwarders, which restart an adapter only while it is
+    /// still the one registered under its name.
+    adapters: Arc<RwLock<HashMap<String, Arc<dyn MessagingDyn>>>>,
     /// Sender side of the fan-in channel. Cloned for each adapter's forwarding task.
     fan_in_tx: mpsc::Sender<InboundMessage>,
     /// Receiver side, taken once by `start()`.
     fan_in_rx: RwLock<Option<mpsc::Receiver<InboundMessage>>>,
+    /// Slash commands, kept so adapters added later register them too.
+    commands: RwLock<Vec<SlashCommand>>,
 }
 
 impl MessagingManager {
     pub fn new() -> Self {
         let (fan_in_tx, fan_in_rx) = mpsc::channel(512);
         Self {
-            adapters: RwLock::new(HashMap::new()),
+            adapters: Arc::new(RwLock::new(HashMap::new())),
             fan_in_tx,
             fan_in_rx: RwLock::new(Some(fan_in_rx)),
+            commands: RwLock::new(Vec::new()),
         }
     }
 
@@ -58,7 +64,13 @@ impl MessagingManager {
         let adapters = self.adapters.read().await;
         for (name, adapter) in adapters.iter() {
             match adapter.start().await {
-                Ok(stream) => Self::spawn_forwarder(name.clone(), stream, self.fan_in_tx.clone()),
+                Ok(stream) => Self::spawn_forwarder(
+                    self.adapters.clone(),
+                    name.clone(),
+                    Arc::clone(adapter),
+                    stream,
+                    self.fan_in_tx.clone(),
+                ),
                 Err(error) => {
                     tracing::warn!(
                         adapter = %name,
@@ -66,6 +78,7 @@ impl MessagingManager {
                         "adapter failed to start, will retry in background"
                     );
                     Self::spawn_retry_task(
+                        self.adapters.clone(),
                         name.clone(),
                         Arc::clone(adapter),
                         self.fan_in_tx.clone(),
@@ -95,14 +108,13 @@ impl MessagingManager {
     pub async fn register_and_start(&self, adapter: impl Messaging) -> crate::Result<()> {
         let name = adapter.name().to_string();
 
-        // Shut down existing adapter with the same name if present
-        {
-            let adapters = self.adapters.read().await;
-            if let Some(existing) = adapters.get(&name) {
-                tracing::info!(adapter = %name, "shutting down existing adapter before replacement");
-                if let Err(error) = existing.shutdown().await {
-                    tracing::warn!(adapter = %name, %error, "failed to shut down existing adapter");
-                }
+        // Shut down existing adapter with the same name if present. It is
+        // unregistered first so its forwarder doesn't restart it.
+        let existing = self.adapters.write().await.remove(&name);
+        if let Some(existing) = existing {
+            tracing::info!(adapter = %name, "shutting down existing adapter before replacement");
+            if let Err(error) = existing.shutdown().await {
+                tracing::warn!(adapter = %name, %error, "failed to shut down existing adapter");
             }
         }
 
@@ -112,9 +124,26 @@ impl MessagingManager {
             .start()
             .await
             .with_context(|| format!("failed to start adapter '{name}'"))?;
-        Self::spawn_forwarder(name.clone(), stream, self.fan_in_tx.clone());
 
-        self.adapters.write().await.insert(name.clone(), adapter);
+        let commands = self.commands.read().await;
+        if !commands.is_empty()
+            && let Err(error) = adapter.register_commands(&commands).await
+        {
+            tracing::warn!(adapter = %name, %error, "failed to register slash commands");
+        }
+        drop(commands);
+
+        self.adapters
+            .write()
+            .await
+            .insert(name.clone(), adapter.clone());
+        Self::spawn_forwarder(
+            self.adapters.clone(),
+            name.clone(),
+            adapter,
+            stream,
+            self.fan_in_tx.clone(),
+        );
 
         tracing::info!(adapter = %name, "adapter registered and started at runtime");
         Ok(())
@@ -130,6 +159,7 @@ impl MessagingManager {
     /// Once the adapter starts successfully, its stream is forwarded into the
     /// existing fan-in channel — the same mechanism used by `register_and_start`.
     fn spawn_retry_task(
+        adapters: Arc<RwLock<HashMap<String, Arc<dyn MessagingDyn>>>>,
         name: String,
         adapter: Arc<dyn MessagingDyn>,
         fan_in_tx: mpsc::Sender<InboundMessage>,
@@ -148,7 +178,7 @@ impl MessagingManager {
                             attempt,
                             "adapter started successfully after retry"
                         );
-                        Self::spawn_forwarder(name, stream, fan_in_tx);
+                        Self::spawn_forwarder(adapters, name, adapter, stream, fan_in_tx);
                         return;
                     }
                     Err(error) => {
@@ -174,23 +204,76 @@ impl MessagingManager {
         });
     }
 
-    /// Spawn a task that forwards messages from an adapter stream into the fan-in channel.
+    /// Spawn a supervised task that forwards messages from an adapter stream
+    /// into the fan-in channel.
+    ///
+    /// A stream that ends while the adapter is still registered means its
+    /// connection died, so the task fails and the supervisor starts the
+    /// adapter again under its restart policy. A stream that ends because the
+    /// adapter was replaced or removed just stops the task.
     fn spawn_forwarder(
+        adapters: Arc<RwLock<HashMap<String, Arc<dyn MessagingDyn>>>>,
         name: String,
-        mut stream: InboundStream,
+        adapter: Arc<dyn MessagingDyn>,
+        stream: InboundStream,
         fan_in_tx: mpsc::Sender<InboundMessage>,
     ) {
-        tokio::spawn(async move {
-            while let Some(message) = stream.next().await {
-                if fan_in_tx.send(message).await.is_err() {
-                    tracing::warn!(adapter = %name, "fan-in channel closed, stopping forwarder");
-                    break;
+        let first_stream = Arc::new(std::sync::Mutex::new(Some(stream)));
+        crate::supervisor::spawn(None, format!("{name} adapter"), move || {
+            let adapters = adapters.clone();
+            let name = name.clone();
+            let adapter = adapter.clone();
+            let first_stream = first_stream.clone();
+            let fan_in_tx = fan_in_tx.clone();
+            tokio::spawn(async move {
+                let first_stream = first_stream
+                    .lock()
+                    .expect("adapter stream lock poisoned")
+                    .take();
+                let mut stream = match first_stream {
+                    Some(stream) => stream,
+                    None => adapter
+                        .start()
+                        .await
+                        .with_context(|| format!("failed to restart adapter '{name}'"))?,
+                };
+                while let Some(message) = stream.next().await {
+                    if fan_in_tx.send(message).await.is_err() {
+                        tracing::warn!(adapter = %name, "fan-in channel closed, stopping forwarder");
+                        return Ok(());
+                    }
                 }
-            }
-            tracing::info!(adapter = %name, "adapter stream ended");
+
+                let registered = adapters
+                    .read()
+                    .await
+                    .get(&name)
+                    .is_some_and(|current| Arc::ptr_eq(current, &adapter));
+                if !registered {
+                    tracing::info!(adapter = %name, "adapter stream ended");
+                    return Ok(());
+                }
+                Err(anyhow::anyhow!(
+                    "adapter '{name}' stream ended unexpectedly"
+                ))
+            })
         });
     }
 
+    /// Offer slash commands on every adapter, including ones added later.
+    pub async fn register_commands(&self, commands: Vec<SlashCommand>) {
+        let adapters = self.adapters.read().await;
+        for (name, adapter) in adapters.iter() {
+            match adapter.register_commands(&commands).await {
+                Ok(()) => tracing::debug!(adapter = %name, "slash commands registered"),
+                Err(error) => {
+                    tracing::warn!(adapter = %name, %error, "failed to register slash commands")
+                }
+            }
+        }
+        *self.commands.write().await = commands;
+    }
+
     /// Inject a message directly into the fan-in channel, bypassing adapter streams.
     pub async fn inject_message(&self, message: InboundMessage) -> crate::Result<()> {
         self.fan_in_tx
@@ -239,6 +322,21 @@ impl MessagingManager {
         adapter.broadcast(target, response).await
     }
 
+    /// Post or update a single pinned message (see
+    /// [`Messaging::broadcast_pinned`](super::traits::Messaging::broadcast_pinned)).
+    pub async fn broadcast_pinned(
+        &self,
+        adapter_name: &str,
+        target: &str,
+        text: String,
+    ) -> crate::Result<()> {
+        let adapters = self.adapters.read().await;
+        let adapter = adapters
+            .get(adapter_name)
+            .with_context(|| format!("no messaging adapter named '{adapter_name}'"))?;
+        adapter.broadcast_pinned(target, text).await
+    }
+
     /// Fetch recent message history from the platform for context backfill.
     pub async fn fetch_history(
         &self,
@@ -262,9 +360,10 @@ impl MessagingManager {
         Ok(())
     }
 
-    /// Shut down all adapters gracefully.
+    /// Shut down all adapters gracefully. They are unregistered first so
+    /// their forwarders don't restart them.
     pub async fn shutdown(&self) {
-        let adapters = self.adapters.read().await;
+        let adapters = std::mem::take(&mut *self.adapters.write().await);
         for (name, adapter) in adapters.iter() {
             if let Err(error) = adapter.shutdown().await {
                 tracing::warn!(adapter = %name, %error, "failed to shut down adapter");
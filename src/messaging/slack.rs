@@ -22,6 +22,8 @@
 //! - DM broadcast via `conversations.open`
 
 use crate::config::{SlackCommandConfig, SlackPermissions};
+use crate::messaging::commands::SlashCommand;
+use crate::messaging::fences;
 use crate::messaging::traits::{HistoryMessage, InboundStream, Messaging};
 use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
 
@@ -41,6 +43,9 @@ struct SlackAdapterState {
     /// Maps slash command string (e.g. `"/ask"`) → agent_id.
     /// Built once at start() from the config; read-only afterwards.
     commands: Arc<HashMap<String, String>>,
+    /// Tools offered as slash commands, matched by name when a command isn't
+    /// in `commands`.
+    tool_commands: Arc<RwLock<Vec<SlashCommand>>>,
     /// Cache of resolved user identities to avoid repeated `users.info` API calls.
     user_identity_cache: Arc<RwLock<HashMap<String, SlackUserIdentity>>>,
     /// Cache of resolved channel names to avoid repeated `conversations.info` API calls.
@@ -69,6 +74,8 @@ pub struct SlackAdapter {
     shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
     /// Slash command routing: command string → agent_id.
     commands: Arc<HashMap<String, String>>,
+    /// Tools offered as slash commands (`/weather`, `/calculate`, ...).
+    tool_commands: Arc<RwLock<Vec<SlashCommand>>>,
 }
 
 impl SlackAdapter {
@@ -96,6 +103,7 @@ impl SlackAdapter {
             active_messages: Arc::new(RwLock::new(HashMap::new())),
             shutdown_tx: Arc::new(RwLock::new(None)),
             commands: Arc::new(commands_map),
+            tool_commands: Arc::new(RwLock::new(Vec::new())),
         })
     }
 
@@ -322,8 +330,10 @@ fn slack_error_handler(
 /// immediately with an empty 200 and dispatches the command as an `InboundMessage`
 /// asynchronously. The agent's reply arrives via the normal `respond()` path.
 ///
-/// Commands not listed in the config are acknowledged but produce a brief
-/// "not configured" reply so the user gets feedback instead of silence.
+/// Commands not listed in the config are matched against the tools offered
+/// as slash commands; their text is parsed into typed arguments and the
+/// channel calls the tool directly. Anything else is acknowledged with a
+/// brief "not configured" reply so the user gets feedback instead of silence.
 ///
 /// Workspace and channel permission filters are applied identically to how
 /// regular messages are filtered — a command from an unauthorized workspace
@@ -381,22 +391,52 @@ async fn handle_command_event(
         }
     }
 
-    if !adapter_state.commands.contains_key(&command_str) {
-        tracing::warn!(
-            command = %command_str,
-            user_id = %user_id,
-            "slash command not configured — ignoring"
-        );
-        return Ok(SlackCommandEventResponse {
-            content: SlackMessageContent::new().with_text(format!(
-                "`{}` is not configured on this Spacebot instance.",
-                command_str
-            )),
-            response_type: Some(SlackMessageResponseType::Ephemeral),
-        });
-    }
+    let agent_id = adapter_state.commands.get(&command_str).cloned();
+    let tool_command = if agent_id.is_some() {
+        None
+    } else {
+        let name = command_str.trim_start_matches('/');
+        adapter_state
+            .tool_commands
+            .read()
+            .await
+            .iter()
+            .find(|command| command.name == name)
+            .cloned()
+    };
 
-    let agent_id = adapter_state.commands[&command_str].clone();
+    let content = match (&agent_id, tool_command) {
+        (Some(_), _) => {
+            MessageContent::Text(format!("{} {}", command_str, text).trim().to_string())
+        }
+        (None, Some(command)) => match command.parse_text(&text) {
+            Ok(args) => MessageContent::Command {
+                name: command.name,
+                args,
+            },
+            Err(error) => {
+                return Ok(SlackCommandEventResponse {
+                    content: SlackMessageContent::new()
+                        .with_text(format!("{error}. Usage: `{}`", command.usage())),
+                    response_type: Some(SlackMessageResponseType::Ephemeral),
+                });
+            }
+        },
+        (None, None) => {
+            tracing::warn!(
+                command = %command_str,
+                user_id = %user_id,
+                "slash command not configured — ignoring"
+            );
+            return Ok(SlackCommandEventResponse {
+                content: SlackMessageContent::new().with_text(format!(
+                    "`{}` is not configured on this Spacebot instance.",
+                    command_str
+                )),
+                response_type: Some(SlackMessageResponseType::Ephemeral),
+            });
+        }
+    };
 
     let conversation_id = format!("slack:{}:{}", team_id, channel_id);
 
@@ -427,12 +467,12 @@ async fn handle_command_event(
     );
     // Embed the agent_id hint so the router can honour command-specific routing
     // without requiring a separate binding entry per command.
-    metadata.insert(
-        "slack_command_agent_id".into(),
-        serde_json::Value::String(agent_id),
-    );
-
-    let content = MessageContent::Text(format!("{} {}", command_str, text).trim().to_string());
+    if let Some(agent_id) = agent_id {
+        metadata.insert(
+            "slack_command_agent_id".into(),
+            serde_json::Value::String(agent_id),
+        );
+    }
 
     let inbound = InboundMessage {
         id: msg_id,
@@ -660,6 +700,7 @@ impl Messaging for SlackAdapter {
             bot_token: self.bot_token.clone(),
             bot_user_id,
             commands: self.commands.clone(),
+            tool_commands: self.tool_commands.clone(),
             user_identity_cache: Arc::new(RwLock::new(HashMap::new())),
             channel_name_cache: Arc::new(RwLock::new(HashMap::new())),
         });
@@ -790,7 +831,7 @@ impl Messaging for SlackAdapter {
             OutboundResponse::Text(text) => {
                 let thread_ts = extract_thread_ts(message);
 
-                for chunk in split_message(&text, 12_000) {
+                for chunk in message_chunks(&text) {
                     let mut req = SlackApiChatPostMessageRequest::new(
                         channel_id.clone(),
                         markdown_content(chunk),
@@ -808,7 +849,7 @@ impl Messaging for SlackAdapter {
             } => {
                 let thread_ts = extract_thread_ts(message).or_else(|| extract_message_ts(message));
 
-                for chunk in split_message(&text, 12_000) {
+                for chunk in message_chunks(&text) {
                     let mut req = SlackApiChatPostMessageRequest::new(
                         channel_id.clone(),
                         markdown_content(chunk),
@@ -1017,7 +1058,7 @@ impl Messaging for SlackAdapter {
 
         match response {
             OutboundResponse::Text(text) => {
-                for chunk in split_message(&text, 12_000) {
+                for chunk in message_chunks(&text) {
                     let req = SlackApiChatPostMessageRequest::new(
                         channel_id.clone(),
                         markdown_content(chunk),
@@ -1138,6 +1179,18 @@ impl Messaging for SlackAdapter {
         Ok(result)
     }
 
+    async fn register_commands(&self, commands: &[SlashCommand]) -> crate::Result<()> {
+        // Slack has no API for adding slash commands at runtime; they're
+        // created in the app's settings, so list what needs adding.
+        let usage: Vec<String> = commands.iter().map(SlashCommand::usage).collect();
+        tracing::info!(
+            commands = %usage.join(", "),
+            "slack slash commands enabled; add any missing ones in the Slack app settings"
+        );
+        *self.tool_commands.write().await = commands.to_vec();
+        Ok(())
+    }
+
     async fn health_check(&self) -> crate::Result<()> {
         let session = self.session();
         session
@@ -1446,6 +1499,15 @@ fn variant_name(response: &OutboundResponse) -> &'static str {
     }
 }
 
+/// Chunks of an outgoing message, with code fences tagged and kept balanced
+/// across chunk breaks.
+fn message_chunks(text: &str) -> Vec<String> {
+    fences::rebalance(split_message(
+        &fences::prepare(text),
+        12_000 - fences::REBALANCE_MARGIN,
+    ))
+}
+
 /// Split a message into UTF-8-safe chunks at line/word boundaries.
 fn split_message(text: &str, max_len: usize) -> Vec<String> {
     if text.len() <= max_len {
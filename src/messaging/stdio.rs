@@ -0,0 +1,431 @@
+//! Stdio messaging adapter for headless mode (`spacebot headless`).
+//!
+//! Reads newline-delimited JSON events from stdin and writes replies as
+//! newline-delimited JSON to stdout, so the agent can be driven from a
+//! script, embedded as a subprocess, or tested in a container without a
+//! chat platform. Logs go to stderr in this mode; stdout carries nothing
+//! but events.
+
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _};
+use tokio::sync::{Mutex, mpsc};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long output must be quiet after stdin closes before the adapter
+/// counts as settled, so a reply that follows a worker or branch result
+/// isn't cut off.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// How long a conversation can go without any event before it stops
+/// counting as busy, for messages the channel never starts a turn for.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Stdio adapter state.
+pub struct StdioAdapter {
+    /// Agent for messages that don't name one.
+    default_agent: Option<String>,
+    output: Arc<Mutex<tokio::io::Stdout>>,
+    activity: Arc<std::sync::Mutex<Activity>>,
+}
+
+/// What the adapter has seen, for deciding when headless mode is done.
+#[derive(Debug)]
+struct Activity {
+    stdin_closed: bool,
+    /// Conversations with a message waiting for, or in the middle of, a
+    /// turn, and when each last had an event.
+    busy: HashMap<String, Instant>,
+    last_event: Instant,
+}
+
+/// One line of stdin.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum InputEvent {
+    Message {
+        /// Reuse the same ID to continue a conversation.
+        #[serde(default = "default_conversation")]
+        conversation_id: String,
+        /// Echoed back as `reply_to` on the events it causes.
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default = "default_sender")]
+        sender_id: String,
+        #[serde(default)]
+        sender_name: Option<String>,
+        content: String,
+        /// Agent to route to (overrides binding resolution).
+        #[serde(default)]
+        agent_id: Option<String>,
+    },
+}
+
+fn default_conversation() -> String {
+    "default".into()
+}
+
+fn default_sender() -> String {
+    "stdin".into()
+}
+
+/// One line of stdout.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OutputEvent {
+    Text {
+        conversation_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reply_to: Option<String>,
+        content: String,
+    },
+    File {
+        conversation_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reply_to: Option<String>,
+        filename: String,
+        mime_type: String,
+        /// The file's bytes, base64-encoded.
+        data: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+    },
+    Reaction {
+        conversation_id: String,
+        reply_to: Option<String>,
+        emoji: String,
+    },
+    StreamStart {
+        conversation_id: String,
+    },
+    StreamChunk {
+        conversation_id: String,
+        content: String,
+    },
+    StreamEnd {
+        conversation_id: String,
+    },
+    Status {
+        conversation_id: String,
+        /// `thinking`, `done`, `tool_started`, or `tool_completed`.
+        status: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        tool: Option<String>,
+    },
+    /// A line of stdin that couldn't be used.
+    Error {
+        line: usize,
+        message: String,
+    },
+}
+
+impl StdioAdapter {
+    pub fn new(default_agent: Option<String>) -> Self {
+        Self {
+            default_agent,
+            output: Arc::new(Mutex::new(tokio::io::stdout())),
+            activity: Arc::new(std::sync::Mutex::new(Activity {
+                stdin_closed: false,
+                busy: HashMap::new(),
+                last_event: Instant::now(),
+            })),
+        }
+    }
+
+    /// Whether stdin has closed, no conversation is waiting on a turn, and
+    /// nothing has been written for a moment. Running branches and workers
+    /// aren't visible here; the caller checks those.
+    pub fn is_settled(&self) -> bool {
+        let activity = self.activity.lock().expect("stdio activity poisoned");
+        activity.stdin_closed
+            && activity.last_event.elapsed() >= SETTLE_TIME
+            && activity
+                .busy
+                .values()
+                .all(|last_event| last_event.elapsed() >= BUSY_TIMEOUT)
+    }
+
+    fn touch(&self, conversation_id: &str, busy: Option<bool>) {
+        touch(&self.activity, conversation_id, busy);
+    }
+}
+
+fn touch(activity: &std::sync::Mutex<Activity>, conversation_id: &str, busy: Option<bool>) {
+    let mut activity = activity.lock().expect("stdio activity poisoned");
+    activity.last_event = Instant::now();
+    match busy {
+        Some(true) => {
+            activity
+                .busy
+                .insert(conversation_id.to_string(), Instant::now());
+        }
+        Some(false) => {
+            activity.busy.remove(conversation_id);
+        }
+        None => {
+            if let Some(last_event) = activity.busy.get_mut(conversation_id) {
+                *last_event = Instant::now();
+            }
+        }
+    }
+}
+
+impl Messaging for StdioAdapter {
+    fn name(&self) -> &str {
+        "stdio"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let output = self.output.clone();
+        let activity = self.activity.clone();
+        let default_agent = self.default_agent.clone();
+
+        tokio::spawn(async move {
+            let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
+            let mut number = 0;
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(error) => {
+                        tracing::error!(%error, "failed to read stdin");
+                        break;
+                    }
+                };
+                number += 1;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let event = match serde_json::from_str::<InputEvent>(&line) {
+                    Ok(event) => event,
+                    Err(error) => {
+                        write_event(
+                            &output,
+                            &OutputEvent::Error {
+                                line: number,
+                                message: error.to_string(),
+                            },
+                        )
+                        .await;
+                        continue;
+                    }
+                };
+                let message = inbound_message(event, default_agent.as_deref());
+                touch(&activity, &message.conversation_id, Some(true));
+                if inbound_tx.send(message).await.is_err() {
+                    break;
+                }
+            }
+            tracing::info!("stdin closed");
+            activity
+                .lock()
+                .expect("stdio activity poisoned")
+                .stdin_closed = true;
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let Some(event) = output_event(&message.conversation_id, Some(&message.id), response)
+        else {
+            return Ok(());
+        };
+        self.touch(&message.conversation_id, None);
+        write_event(&self.output, &event).await;
+        Ok(())
+    }
+
+    async fn send_status(
+        &self,
+        message: &InboundMessage,
+        status: StatusUpdate,
+    ) -> crate::Result<()> {
+        let conversation_id = message.conversation_id.clone();
+        let (status, tool, busy) = match status {
+            StatusUpdate::Thinking => ("thinking", None, true),
+            StatusUpdate::StopTyping => ("done", None, false),
+            StatusUpdate::ToolStarted { tool_name } => ("tool_started", Some(tool_name), true),
+            StatusUpdate::ToolCompleted { tool_name } => ("tool_completed", Some(tool_name), true),
+            _ => return Ok(()),
+        };
+        self.touch(&conversation_id, Some(busy));
+        write_event(
+            &self.output,
+            &OutputEvent::Status {
+                conversation_id,
+                status,
+                tool,
+            },
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        let conversation_id = format!("stdio:{target}");
+        if let Some(event) = output_event(&conversation_id, None, response) {
+            self.touch(&conversation_id, None);
+            write_event(&self.output, &event).await;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        let _ = self.output.lock().await.flush().await;
+        tracing::info!("stdio adapter shut down");
+        Ok(())
+    }
+}
+
+fn inbound_message(event: InputEvent, default_agent: Option<&str>) -> InboundMessage {
+    let InputEvent::Message {
+        conversation_id,
+        id,
+        sender_id,
+        sender_name,
+        content,
+        agent_id,
+    } = event;
+
+    let sender_name = sender_name.unwrap_or_else(|| sender_id.clone());
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(sender_name.clone()),
+    );
+
+    InboundMessage {
+        id: id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        source: "stdio".into(),
+        conversation_id: format!("stdio:{conversation_id}"),
+        sender_id,
+        agent_id: agent_id
+            .or_else(|| default_agent.map(str::to_string))
+            .map(Into::into),
+        content: MessageContent::Text(content),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(sender_name),
+    }
+}
+
+/// The event for an outbound response. `None` for responses with no
+/// meaning on stdout.
+fn output_event(
+    conversation_id: &str,
+    reply_to: Option<&str>,
+    response: OutboundResponse,
+) -> Option<OutputEvent> {
+    // Events name the conversation as it was given on stdin.
+    let conversation_id = conversation_id
+        .strip_prefix("stdio:")
+        .unwrap_or(conversation_id)
+        .to_string();
+    let reply_to = reply_to.map(str::to_string);
+    let text = |content: String| OutputEvent::Text {
+        conversation_id: conversation_id.clone(),
+        reply_to: reply_to.clone(),
+        content,
+    };
+
+    Some(match response {
+        OutboundResponse::Text(content)
+        | OutboundResponse::ThreadReply { text: content, .. }
+        | OutboundResponse::RichMessage { text: content, .. }
+        | OutboundResponse::Ephemeral { text: content, .. }
+        | OutboundResponse::ScheduledMessage { text: content, .. } => text(content),
+        OutboundResponse::File {
+            filename,
+            data,
+            mime_type,
+            caption,
+        } => OutputEvent::File {
+            conversation_id,
+            reply_to,
+            filename,
+            mime_type,
+            data: base64::engine::general_purpose::STANDARD.encode(data),
+            caption,
+        },
+        OutboundResponse::Reaction(emoji) => OutputEvent::Reaction {
+            conversation_id,
+            reply_to,
+            emoji,
+        },
+        OutboundResponse::StreamStart => OutputEvent::StreamStart { conversation_id },
+        OutboundResponse::StreamChunk(content) => OutputEvent::StreamChunk {
+            conversation_id,
+            content,
+        },
+        OutboundResponse::StreamEnd => OutputEvent::StreamEnd { conversation_id },
+        OutboundResponse::RemoveReaction(_) | OutboundResponse::Status(_) => return None,
+    })
+}
+
+/// Write one event as a line of JSON. Lines are written whole, so
+/// concurrent conversations never interleave.
+async fn write_event(output: &Mutex<tokio::io::Stdout>, event: &OutputEvent) {
+    let mut line = serde_json::to_string(event).expect("output events serialize");
+    line.push('\n');
+    let mut output = output.lock().await;
+    if let Err(error) = output.write_all(line.as_bytes()).await {
+        tracing::error!(%error, "failed to write to stdout");
+        return;
+    }
+    let _ = output.flush().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_round_trip_as_json_lines() {
+        let event: InputEvent = serde_json::from_str(
+            r#"{"type": "message", "conversation_id": "ci", "id": "m1", "content": "hi"}"#,
+        )
+        .expect("valid event");
+        let message = inbound_message(event, Some("main"));
+        assert_eq!(message.conversation_id, "stdio:ci");
+        assert_eq!(message.id, "m1");
+        assert_eq!(message.sender_id, "stdin");
+        assert_eq!(message.agent_id.as_deref(), Some("main"));
+        assert!(serde_json::from_str::<InputEvent>(r#"{"type": "ping"}"#).is_err());
+
+        let reply = output_event(
+            &message.conversation_id,
+            Some(&message.id),
+            OutboundResponse::Text("hello".into()),
+        )
+        .expect("text is written");
+        assert_eq!(
+            serde_json::to_string(&reply).expect("serializes"),
+            r#"{"type":"text","conversation_id":"ci","reply_to":"m1","content":"hello"}"#
+        );
+        assert_eq!(
+            output_event(
+                "stdio:ci",
+                None,
+                OutboundResponse::RemoveReaction("👀".into())
+            ),
+            None
+        );
+    }
+}
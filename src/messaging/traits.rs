@@ -1,6 +1,7 @@
 //! Messaging trait and dynamic dispatch companion.
 
 use crate::error::Result;
+use crate::messaging::commands::SlashCommand;
 use crate::{InboundMessage, OutboundResponse, StatusUpdate};
 use futures::Stream;
 use std::pin::Pin;
@@ -50,6 +51,17 @@ pub trait Messaging: Send + Sync + 'static {
         async { Ok(()) }
     }
 
+    /// Post `text` to `target` as one message kept up to date: the first
+    /// call posts and pins it, later calls edit it in place. Adapters that
+    /// can't edit messages post a new one each time.
+    fn broadcast_pinned(
+        &self,
+        target: &str,
+        text: String,
+    ) -> impl std::future::Future<Output = Result<()>> + Send {
+        self.broadcast(target, OutboundResponse::Text(text))
+    }
+
     /// Fetch recent message history from the platform for context backfill.
     /// Returns messages in chronological order (oldest first).
     /// `before` is the message that triggered channel creation — fetch messages before it.
@@ -62,6 +74,16 @@ pub trait Messaging: Send + Sync + 'static {
         async { Ok(Vec::new()) }
     }
 
+    /// Offer tools as slash commands on the platform. Adapters without
+    /// native commands ignore this.
+    fn register_commands(
+        &self,
+        commands: &[SlashCommand],
+    ) -> impl std::future::Future<Output = Result<()>> + Send {
+        let _ = commands;
+        async { Ok(()) }
+    }
+
     /// Health check.
     fn health_check(&self) -> impl std::future::Future<Output = Result<()>> + Send;
 
@@ -98,12 +120,23 @@ pub trait MessagingDyn: Send + Sync + 'static {
         response: OutboundResponse,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
 
+    fn broadcast_pinned<'a>(
+        &'a self,
+        target: &'a str,
+        text: String,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
+
     fn fetch_history<'a>(
         &'a self,
         message: &'a InboundMessage,
         limit: usize,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<Vec<HistoryMessage>>> + Send + 'a>>;
 
+    fn register_commands<'a>(
+        &'a self,
+        commands: &'a [SlashCommand],
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
+
     fn health_check<'a>(
         &'a self,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>>;
@@ -148,6 +181,14 @@ impl<T: Messaging> MessagingDyn for T {
         Box::pin(Messaging::broadcast(self, target, response))
     }
 
+    fn broadcast_pinned<'a>(
+        &'a self,
+        target: &'a str,
+        text: String,
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(Messaging::broadcast_pinned(self, target, text))
+    }
+
     fn fetch_history<'a>(
         &'a self,
         message: &'a InboundMessage,
@@ -156,6 +197,13 @@ impl<T: Messaging> MessagingDyn for T {
         Box::pin(Messaging::fetch_history(self, message, limit))
     }
 
+    fn register_commands<'a>(
+        &'a self,
+        commands: &'a [SlashCommand],
+    ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(Messaging::register_commands(self, commands))
+    }
+
     fn health_check<'a>(
         &'a self,
     ) -> Pin<Box<dyn std::future::Future<Output = Result<()>> + Send + 'a>> {
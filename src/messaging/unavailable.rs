@@ -0,0 +1,113 @@
+//! Stand-ins for adapters left out of the build.
+//!
+//! Each takes the same constructor arguments as the adapter it replaces, so
+//! call sites don't change, and fails to start with an error naming the
+//! missing feature. Startup skips these platforms; hot-starting one from the
+//! API or a config change logs that error.
+
+use crate::config;
+
+use std::sync::Arc;
+
+fn missing(platform: &str) -> crate::Error {
+    anyhow::anyhow!(
+        "this build doesn't include the {platform} adapter; rebuild with `--features {platform}`"
+    )
+    .into()
+}
+
+macro_rules! unavailable_adapter {
+    ($feature:literal, $module:ident, $adapter:ident) => {
+        #[cfg(not(feature = $feature))]
+        pub mod $module {
+            use crate::messaging::traits::{InboundStream, Messaging};
+            use crate::{InboundMessage, OutboundResponse};
+
+            pub struct $adapter;
+
+            impl Messaging for $adapter {
+                fn name(&self) -> &str {
+                    $feature
+                }
+
+                async fn start(&self) -> crate::Result<InboundStream> {
+                    Err(super::missing($feature))
+                }
+
+                async fn respond(
+                    &self,
+                    _message: &InboundMessage,
+                    _response: OutboundResponse,
+                ) -> crate::Result<()> {
+                    Err(super::missing($feature))
+                }
+
+                async fn health_check(&self) -> crate::Result<()> {
+                    Err(super::missing($feature))
+                }
+            }
+        }
+    };
+}
+
+unavailable_adapter!("discord", discord, DiscordAdapter);
+unavailable_adapter!("slack", slack, SlackAdapter);
+unavailable_adapter!("telegram", telegram, TelegramAdapter);
+unavailable_adapter!("twitch", twitch, TwitchAdapter);
+unavailable_adapter!("grpc", grpc, GrpcAdapter);
+
+#[cfg(not(feature = "discord"))]
+impl discord::DiscordAdapter {
+    pub fn new(
+        _token: impl Into<String>,
+        _permissions: Arc<arc_swap::ArcSwap<config::DiscordPermissions>>,
+    ) -> Self {
+        Self
+    }
+}
+
+#[cfg(not(feature = "slack"))]
+impl slack::SlackAdapter {
+    pub fn new(
+        _bot_token: impl Into<String>,
+        _app_token: impl Into<String>,
+        _permissions: Arc<arc_swap::ArcSwap<config::SlackPermissions>>,
+        _commands: Vec<config::SlackCommandConfig>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self)
+    }
+}
+
+#[cfg(not(feature = "telegram"))]
+impl telegram::TelegramAdapter {
+    pub fn new(
+        _token: impl Into<String>,
+        _permissions: Arc<arc_swap::ArcSwap<config::TelegramPermissions>>,
+    ) -> Self {
+        Self
+    }
+}
+
+#[cfg(not(feature = "twitch"))]
+impl twitch::TwitchAdapter {
+    pub fn new(
+        _username: impl Into<String>,
+        _oauth_token: impl Into<String>,
+        _channels: Vec<String>,
+        _trigger_prefix: Option<String>,
+        _permissions: Arc<arc_swap::ArcSwap<config::TwitchPermissions>>,
+    ) -> Self {
+        Self
+    }
+}
+
+#[cfg(not(feature = "grpc"))]
+impl grpc::GrpcAdapter {
+    pub fn new(
+        _config: &config::GrpcConfig,
+        _default_agent: impl Into<String>,
+        _api_state: Arc<crate::api::ApiState>,
+    ) -> Self {
+        Self
+    }
+}
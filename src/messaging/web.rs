@@ -0,0 +1,577 @@
+//! Web messaging adapter: a browser chat UI served on its own port.
+//!
+//! For people who should be able to talk to the agent without an account on
+//! the team's chat platforms. Each user in `[messaging.web.users]` signs in
+//! with their access token and gets a session cookie; their messages go
+//! through the same pipeline as any other platform, in one conversation per
+//! user (`web:<user>`). Replies, streamed chunks, and typing state reach the
+//! page over server-sent events.
+//!
+//! This is separate from the `webchat` adapter, which backs the dashboard's
+//! chat panel behind the local API server.
+
+use crate::config::WebConfig;
+use crate::conversation::ConversationLogger;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+
+use anyhow::Context as _;
+use axum::Router;
+use axum::extract::{Json, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{get, post};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tokio::sync::{RwLock, broadcast, mpsc};
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const INDEX_HTML: &str = include_str!("web/index.html");
+const APP_JS: &str = include_str!("web/app.js");
+const STYLE_CSS: &str = include_str!("web/style.css");
+
+/// Name of the session cookie.
+const SESSION_COOKIE: &str = "spacebot_web_session";
+
+/// Most messages the history endpoint returns.
+const MAX_HISTORY: i64 = 200;
+
+/// Web adapter state.
+pub struct WebAdapter {
+    port: u16,
+    bind: String,
+    shared: Arc<Shared>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+/// State shared with the axum handlers.
+struct Shared {
+    title: String,
+    agent_id: String,
+    /// Access token digests, by user name.
+    users: BTreeMap<String, [u8; 32]>,
+    session_ttl: Duration,
+    history: ConversationLogger,
+    inbound_tx: RwLock<Option<mpsc::Sender<InboundMessage>>>,
+    /// Signed-in sessions, by session token.
+    sessions: RwLock<HashMap<String, Session>>,
+    /// Event fan-out to every open page, by conversation ID.
+    streams: RwLock<HashMap<String, broadcast::Sender<WebEvent>>>,
+}
+
+struct Session {
+    user: String,
+    expires_at: Instant,
+}
+
+/// An event sent to the page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum WebEvent {
+    Text {
+        content: String,
+    },
+    File {
+        filename: String,
+        mime_type: String,
+        /// The file's bytes, base64-encoded.
+        data: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+    },
+    StreamStart,
+    StreamChunk {
+        content: String,
+    },
+    StreamEnd,
+    Thinking,
+    ToolStarted {
+        tool: String,
+    },
+    ToolCompleted {
+        tool: String,
+    },
+    Done,
+}
+
+impl WebAdapter {
+    /// `agent_id` is the agent every user talks to, and `history` reads its
+    /// conversation log.
+    pub fn new(
+        config: &WebConfig,
+        agent_id: impl Into<String>,
+        history: ConversationLogger,
+    ) -> Self {
+        let users = config
+            .users
+            .iter()
+            .map(|(user, token)| (user.clone(), digest(token)))
+            .collect();
+        Self {
+            port: config.port,
+            bind: config.bind.clone(),
+            shared: Arc::new(Shared {
+                title: config.title.clone(),
+                agent_id: agent_id.into(),
+                users,
+                session_ttl: Duration::from_secs(config.session_ttl_secs),
+                history,
+                inbound_tx: RwLock::new(None),
+                sessions: RwLock::new(HashMap::new()),
+                streams: RwLock::new(HashMap::new()),
+            }),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    async fn publish(&self, conversation_id: &str, event: WebEvent) {
+        if let Some(stream) = self.shared.streams.read().await.get(conversation_id) {
+            // No receivers just means nobody has the page open.
+            let _ = stream.send(event);
+        }
+    }
+}
+
+impl Messaging for WebAdapter {
+    fn name(&self) -> &str {
+        "web"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+
+        *self.shared.inbound_tx.write().await = Some(inbound_tx);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let app = Router::new()
+            .route("/", get(handle_index))
+            .route("/app.js", get(handle_app_js))
+            .route("/style.css", get(handle_style_css))
+            .route("/api/login", post(handle_login))
+            .route("/api/logout", post(handle_logout))
+            .route("/api/session", get(handle_session))
+            .route("/api/history", get(handle_history))
+            .route("/api/send", post(handle_send))
+            .route("/api/events", get(handle_events))
+            .with_state(self.shared.clone());
+
+        let bind = if self.bind.contains(':') {
+            format!("[{}]:{}", self.bind, self.port)
+        } else {
+            format!("{}:{}", self.bind, self.port)
+        };
+        let listener = crate::listeners::bind("web", bind.as_str())
+            .await
+            .with_context(|| format!("failed to bind web chat server to {bind}"))?;
+        tracing::info!(%bind, "web chat server listening");
+
+        tokio::spawn(async move {
+            if let Err(error) = listener
+                .serve(app, async move {
+                    let _ = shutdown_rx.recv().await;
+                })
+                .await
+            {
+                tracing::error!(%error, "web chat server exited with error");
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        if let Some(event) = web_event(response) {
+            self.publish(&message.conversation_id, event).await;
+        }
+        Ok(())
+    }
+
+    async fn send_status(
+        &self,
+        message: &InboundMessage,
+        status: StatusUpdate,
+    ) -> crate::Result<()> {
+        let event = match status {
+            StatusUpdate::Thinking => WebEvent::Thinking,
+            StatusUpdate::StopTyping => WebEvent::Done,
+            StatusUpdate::ToolStarted { tool_name } => WebEvent::ToolStarted { tool: tool_name },
+            StatusUpdate::ToolCompleted { tool_name } => {
+                WebEvent::ToolCompleted { tool: tool_name }
+            }
+            _ => return Ok(()),
+        };
+        self.publish(&message.conversation_id, event).await;
+        Ok(())
+    }
+
+    /// `target` is a user name.
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        if !self.shared.users.contains_key(target) {
+            return Err(anyhow::anyhow!("no web chat user named '{target}'").into());
+        }
+        if let Some(event) = web_event(response) {
+            self.publish(&conversation_id(target), event).await;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        tracing::info!("web chat adapter shut down");
+        Ok(())
+    }
+}
+
+fn conversation_id(user: &str) -> String {
+    format!("web:{user}")
+}
+
+fn digest(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+/// The page event for an outbound response. `None` for responses the page
+/// has no use for.
+fn web_event(response: OutboundResponse) -> Option<WebEvent> {
+    Some(match response {
+        OutboundResponse::Text(content)
+        | OutboundResponse::ThreadReply { text: content, .. }
+        | OutboundResponse::RichMessage { text: content, .. }
+        | OutboundResponse::Ephemeral { text: content, .. }
+        | OutboundResponse::ScheduledMessage { text: content, .. } => WebEvent::Text { content },
+        OutboundResponse::File {
+            filename,
+            data,
+            mime_type,
+            caption,
+        } => WebEvent::File {
+            filename,
+            mime_type,
+            data: base64::engine::general_purpose::STANDARD.encode(data),
+            caption,
+        },
+        OutboundResponse::StreamStart => WebEvent::StreamStart,
+        OutboundResponse::StreamChunk(content) => WebEvent::StreamChunk { content },
+        OutboundResponse::StreamEnd => WebEvent::StreamEnd,
+        OutboundResponse::Reaction(_)
+        | OutboundResponse::RemoveReaction(_)
+        | OutboundResponse::Status(_) => return None,
+    })
+}
+
+/// The user whose token this is.
+fn user_for_token(users: &BTreeMap<String, [u8; 32]>, token: &str) -> Option<String> {
+    // Compare digests so the time taken says nothing about the token.
+    let digest = digest(token);
+    users
+        .iter()
+        .find(|(_, expected)| **expected == digest)
+        .map(|(user, _)| user.clone())
+}
+
+/// The session token from the cookie, or from an `Authorization: Bearer`
+/// header for scripts.
+fn session_token(headers: &HeaderMap) -> Option<&str> {
+    let cookie = headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (name, value) = pair.trim().split_once('=')?;
+            (name == SESSION_COOKIE).then_some(value)
+        });
+    cookie.or_else(|| {
+        headers
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")
+    })
+}
+
+/// The signed-in user, or 401.
+async fn require_user(shared: &Shared, headers: &HeaderMap) -> Result<String, StatusCode> {
+    let token = session_token(headers).ok_or(StatusCode::UNAUTHORIZED)?;
+    let sessions = shared.sessions.read().await;
+    let session = sessions.get(token).ok_or(StatusCode::UNAUTHORIZED)?;
+    if session.expires_at <= Instant::now() {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(session.user.clone())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// -- Axum handlers --
+
+async fn handle_index(State(shared): State<Arc<Shared>>) -> Html<String> {
+    Html(INDEX_HTML.replace("{{title}}", &escape_html(&shared.title)))
+}
+
+async fn handle_app_js() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript; charset=utf-8")],
+        APP_JS,
+    )
+}
+
+async fn handle_style_css() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/css; charset=utf-8")],
+        STYLE_CSS,
+    )
+}
+
+#[derive(Deserialize)]
+struct LoginRequest {
+    token: String,
+}
+
+#[derive(Serialize)]
+struct SessionResponse {
+    user: String,
+    title: String,
+}
+
+async fn handle_login(
+    State(shared): State<Arc<Shared>>,
+    Json(request): Json<LoginRequest>,
+) -> Result<Response, StatusCode> {
+    let Some(user) = user_for_token(&shared.users, request.token.trim()) else {
+        tracing::warn!("web chat sign-in with an unknown token");
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    let token = hex::encode(rand::random::<[u8; 32]>());
+    let now = Instant::now();
+    let mut sessions = shared.sessions.write().await;
+    sessions.retain(|_, session| session.expires_at > now);
+    sessions.insert(
+        token.clone(),
+        Session {
+            user: user.clone(),
+            expires_at: now + shared.session_ttl,
+        },
+    );
+    drop(sessions);
+    tracing::info!(%user, "web chat user signed in");
+
+    let cookie = format!(
+        "{SESSION_COOKIE}={token}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}",
+        shared.session_ttl.as_secs()
+    );
+    let body = SessionResponse {
+        user,
+        title: shared.title.clone(),
+    };
+    Ok(([(header::SET_COOKIE, cookie)], Json(body)).into_response())
+}
+
+async fn handle_logout(State(shared): State<Arc<Shared>>, headers: HeaderMap) -> Response {
+    if let Some(token) = session_token(&headers) {
+        shared.sessions.write().await.remove(token);
+    }
+    let cookie = format!("{SESSION_COOKIE}=; Path=/; HttpOnly; SameSite=Strict; Max-Age=0");
+    ([(header::SET_COOKIE, cookie)], StatusCode::NO_CONTENT).into_response()
+}
+
+async fn handle_session(
+    State(shared): State<Arc<Shared>>,
+    headers: HeaderMap,
+) -> Result<Json<SessionResponse>, StatusCode> {
+    let user = require_user(&shared, &headers).await?;
+    Ok(Json(SessionResponse {
+        user,
+        title: shared.title.clone(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    #[serde(default = "default_history_limit")]
+    limit: i64,
+}
+
+fn default_history_limit() -> i64 {
+    50
+}
+
+#[derive(Serialize)]
+struct HistoryMessage {
+    role: String,
+    content: String,
+    created_at: String,
+}
+
+async fn handle_history(
+    State(shared): State<Arc<Shared>>,
+    headers: HeaderMap,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<HistoryMessage>>, StatusCode> {
+    let user = require_user(&shared, &headers).await?;
+    let channel_id: crate::ChannelId = Arc::from(conversation_id(&user).as_str());
+    let messages = shared
+        .history
+        .load_recent(&channel_id, query.limit.clamp(1, MAX_HISTORY))
+        .await
+        .map_err(|error| {
+            tracing::warn!(%error, "failed to load web chat history");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(
+        messages
+            .into_iter()
+            .map(|message| HistoryMessage {
+                role: message.role,
+                content: message.content,
+                created_at: message.created_at.to_rfc3339(),
+            })
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+struct SendRequest {
+    message: String,
+}
+
+async fn handle_send(
+    State(shared): State<Arc<Shared>>,
+    headers: HeaderMap,
+    Json(request): Json<SendRequest>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let user = require_user(&shared, &headers)
+        .await
+        .map_err(|status| (status, "sign in first".into()))?;
+    if request.message.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "message is empty".into()));
+    }
+
+    let tx = shared.inbound_tx.read().await;
+    let Some(tx) = tx.as_ref() else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "web chat not initialized".into(),
+        ));
+    };
+
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(user.clone()),
+    );
+    let inbound = InboundMessage {
+        id: uuid::Uuid::new_v4().to_string(),
+        source: "web".into(),
+        conversation_id: conversation_id(&user),
+        sender_id: user.clone(),
+        agent_id: Some(shared.agent_id.as_str().into()),
+        content: MessageContent::Text(request.message),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(user),
+    };
+
+    tx.send(inbound)
+        .await
+        .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "channel closed".into()))?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn handle_events(
+    State(shared): State<Arc<Shared>>,
+    headers: HeaderMap,
+) -> Result<Sse<impl futures::Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let user = require_user(&shared, &headers).await?;
+    let mut event_rx = shared
+        .streams
+        .write()
+        .await
+        .entry(conversation_id(&user))
+        .or_insert_with(|| broadcast::channel(256).0)
+        .subscribe();
+
+    let stream = async_stream::stream! {
+        loop {
+            match event_rx.recv().await {
+                Ok(event) => {
+                    if let Ok(json) = serde_json::to_string(&event) {
+                        yield Ok(Event::default().data(json));
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "web chat event stream lagged");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    };
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_and_sessions_are_read_from_requests() {
+        let users = BTreeMap::from([("alice".to_string(), digest("alice-token-0123456789"))]);
+        assert_eq!(
+            user_for_token(&users, "alice-token-0123456789").as_deref(),
+            Some("alice")
+        );
+        assert_eq!(user_for_token(&users, "alice-token"), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            format!("theme=dark; {SESSION_COOKIE}=abc123")
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(session_token(&headers), Some("abc123"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer def456".parse().unwrap());
+        assert_eq!(session_token(&headers), Some("def456"));
+        assert_eq!(session_token(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn responses_become_page_events() {
+        assert_eq!(
+            serde_json::to_string(&web_event(OutboundResponse::Text("hi".into()))).unwrap(),
+            r#"{"type":"text","content":"hi"}"#
+        );
+        assert_eq!(
+            web_event(OutboundResponse::StreamStart),
+            Some(WebEvent::StreamStart)
+        );
+        assert_eq!(web_event(OutboundResponse::Reaction("👍".into())), None);
+    }
+}
@@ -0,0 +1,159 @@
+// Web chat page: sign in with an access token, then chat over
+// /api/send and the /api/events stream.
+
+const $ = (id) => document.getElementById(id);
+
+let events = null;
+// The assistant message a stream is being written into.
+let streaming = null;
+
+async function api(path, body) {
+  const options = body === undefined
+    ? { credentials: "same-origin" }
+    : {
+        method: "POST",
+        credentials: "same-origin",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify(body),
+      };
+  const response = await fetch(path, options);
+  if (response.status === 401) {
+    showLogin();
+    throw new Error("signed out");
+  }
+  if (!response.ok) {
+    throw new Error(await response.text() || response.statusText);
+  }
+  return response.status === 204 || response.status === 202 ? null : response.json();
+}
+
+function addMessage(role, text) {
+  const item = document.createElement("li");
+  item.className = role;
+  item.textContent = text;
+  $("messages").append(item);
+  item.scrollIntoView({ block: "end" });
+  return item;
+}
+
+function addFile(event) {
+  const item = addMessage("assistant", event.caption ? event.caption + "\n" : "");
+  const link = document.createElement("a");
+  link.href = `data:${event.mime_type};base64,${event.data}`;
+  link.download = event.filename;
+  link.textContent = event.filename;
+  item.append(link);
+}
+
+function setTyping(text) {
+  $("typing").textContent = text || "";
+  $("typing").hidden = !text;
+}
+
+function handleEvent(event) {
+  switch (event.type) {
+    case "thinking":
+      setTyping("Thinking…");
+      break;
+    case "tool_started":
+      setTyping(`Using ${event.tool}…`);
+      break;
+    case "tool_completed":
+      setTyping("Thinking…");
+      break;
+    case "stream_start":
+      streaming = addMessage("assistant", "");
+      break;
+    case "stream_chunk":
+      if (!streaming) streaming = addMessage("assistant", "");
+      streaming.textContent += event.content;
+      streaming.scrollIntoView({ block: "end" });
+      break;
+    case "stream_end":
+      streaming = null;
+      break;
+    case "text":
+      addMessage("assistant", event.content);
+      break;
+    case "file":
+      addFile(event);
+      break;
+    case "done":
+      setTyping(null);
+      break;
+  }
+}
+
+function connect() {
+  events?.close();
+  events = new EventSource("/api/events");
+  events.onmessage = (message) => handleEvent(JSON.parse(message.data));
+  events.onerror = () => {
+    // The browser reconnects by itself; a lost session won't come back.
+    api("/api/session").catch(() => {});
+  };
+}
+
+async function showChat(session) {
+  $("login").hidden = true;
+  $("chat").hidden = false;
+  $("logout").hidden = false;
+  $("user").textContent = session.user;
+  $("messages").replaceChildren();
+  const history = await api("/api/history?limit=50");
+  for (const message of history) {
+    addMessage(message.role === "user" ? "user" : "assistant", message.content);
+  }
+  connect();
+  $("input").focus();
+}
+
+function showLogin() {
+  events?.close();
+  events = null;
+  $("chat").hidden = true;
+  $("logout").hidden = true;
+  $("user").textContent = "";
+  $("login").hidden = false;
+  $("token").focus();
+}
+
+$("login").addEventListener("submit", async (submit) => {
+  submit.preventDefault();
+  $("login-error").hidden = true;
+  try {
+    const session = await api("/api/login", { token: $("token").value });
+    $("token").value = "";
+    await showChat(session);
+  } catch {
+    $("login-error").textContent = "That token wasn't accepted.";
+    $("login-error").hidden = false;
+  }
+});
+
+$("composer").addEventListener("submit", async (submit) => {
+  submit.preventDefault();
+  const text = $("input").value.trim();
+  if (!text) return;
+  $("input").value = "";
+  addMessage("user", text);
+  try {
+    await api("/api/send", { message: text });
+  } catch (error) {
+    addMessage("assistant", `Couldn't send: ${error.message}`);
+  }
+});
+
+$("input").addEventListener("keydown", (key) => {
+  if (key.key === "Enter" && !key.shiftKey) {
+    key.preventDefault();
+    $("composer").requestSubmit();
+  }
+});
+
+$("logout").addEventListener("click", async () => {
+  await api("/api/logout", {}).catch(() => {});
+  showLogin();
+});
+
+api("/api/session").then(showChat).catch(() => {});
@@ -4,16 +4,26 @@
 //! delivers responses via a per-conversation polling endpoint. This is
 //! the integration point for scripts, CI pipelines, and other programs
 //! that need to interact with Spacebot programmatically.
+//!
+//! With `require_api_key`, both endpoints need an issued key with the
+//! `webhook` scope, and each key's conversations are kept apart as
+//! `webhook:<key name>:<conversation_id>`. With `[messaging.webhook.signing]`,
+//! `/send` also needs a valid HMAC signature over the body (see [`signing`]).
+
+pub mod signing;
 
+use crate::api_keys::{ApiKeyStore, Scope};
 use crate::messaging::traits::{InboundStream, Messaging};
 use crate::{InboundMessage, MessageContent, OutboundResponse};
 
 use anyhow::Context as _;
 use axum::Router;
+use axum::body::Bytes;
 use axum::extract::{Json, State};
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode, header};
 use axum::routing::{get, post};
 use serde::{Deserialize, Serialize};
+use signing::{SigningConfig, Verifier};
 
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -26,6 +36,10 @@ pub struct WebhookAdapter {
     inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
     /// Buffered responses per conversation_id, waiting to be polled.
     response_buffers: Arc<RwLock<HashMap<String, Vec<WebhookResponse>>>>,
+    /// Issued keys callers must present, when keys are required.
+    api_keys: Option<Arc<ApiKeyStore>>,
+    /// Checks signatures on `/send`, when signing is configured.
+    verifier: Option<Arc<Verifier>>,
     shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
 }
 
@@ -34,6 +48,8 @@ pub struct WebhookAdapter {
 struct AppState {
     inbound_tx: Arc<RwLock<Option<mpsc::Sender<InboundMessage>>>>,
     response_buffers: Arc<RwLock<HashMap<String, Vec<WebhookResponse>>>>,
+    api_keys: Option<Arc<ApiKeyStore>>,
+    verifier: Option<Arc<Verifier>>,
 }
 
 /// Inbound webhook request body.
@@ -78,9 +94,23 @@ impl WebhookAdapter {
             bind: bind.into(),
             inbound_tx: Arc::new(RwLock::new(None)),
             response_buffers: Arc::new(RwLock::new(HashMap::new())),
+            api_keys: None,
+            verifier: None,
             shutdown_tx: Arc::new(RwLock::new(None)),
         }
     }
+
+    /// Require an issued key with the `webhook` scope on every request.
+    pub fn with_api_keys(mut self, api_keys: Arc<ApiKeyStore>) -> Self {
+        self.api_keys = Some(api_keys);
+        self
+    }
+
+    /// Require a valid signature on `/send`, refusing replays.
+    pub fn with_signing(mut self, signing: SigningConfig) -> Self {
+        self.verifier = Some(Arc::new(Verifier::new(signing)));
+        self
+    }
 }
 
 impl Messaging for WebhookAdapter {
@@ -98,6 +128,8 @@ impl Messaging for WebhookAdapter {
         let state = AppState {
             inbound_tx: self.inbound_tx.clone(),
             response_buffers: self.response_buffers.clone(),
+            api_keys: self.api_keys.clone(),
+            verifier: self.verifier.clone(),
         };
 
         let app = Router::new()
@@ -111,14 +143,14 @@ impl Messaging for WebhookAdapter {
         } else {
             format!("{}:{}", self.bind, self.port)
         };
-        let listener = tokio::net::TcpListener::bind(&bind)
+        let listener = crate::listeners::bind("webhook", bind.as_str())
             .await
             .with_context(|| format!("failed to bind webhook server to {bind}"))?;
         tracing::info!(%bind, "webhook server listening");
 
         tokio::spawn(async move {
-            if let Err(error) = axum::serve(listener, app)
-                .with_graceful_shutdown(async move {
+            if let Err(error) = listener
+                .serve(app, async move {
                     let _ = shutdown_rx.recv().await;
                 })
                 .await
@@ -223,12 +255,51 @@ impl Messaging for WebhookAdapter {
     }
 }
 
+/// The caller's key name when keys are required, or None when they aren't.
+fn authorize(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Option<String>, (StatusCode, String)> {
+    let Some(api_keys) = &state.api_keys else {
+        return Ok(None);
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|key| api_keys.verify(key, Scope::Webhook))
+        .map(|record| Some(record.name))
+        .ok_or_else(|| {
+            tracing::warn!("webhook request with a missing or invalid API key");
+            (StatusCode::UNAUTHORIZED, "invalid API key".into())
+        })
+}
+
+/// The internal conversation ID, namespaced by the caller's key name.
+fn internal_conversation_id(caller: Option<&str>, conversation_id: &str) -> String {
+    match caller {
+        Some(caller) => format!("webhook:{caller}:{conversation_id}"),
+        None => format!("webhook:{conversation_id}"),
+    }
+}
+
 // -- Axum handlers --
 
 async fn handle_send(
     State(state): State<AppState>,
-    Json(request): Json<WebhookRequest>,
+    headers: HeaderMap,
+    body: Bytes,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    let caller = authorize(&state, &headers)?;
+    // The signature covers the raw body, so check it before parsing.
+    if let Some(verifier) = &state.verifier
+        && let Err(rejection) = verifier.verify(&headers, &body, chrono::Utc::now().timestamp())
+    {
+        tracing::warn!(%rejection, "webhook request refused");
+        return Err((StatusCode::UNAUTHORIZED, rejection.to_string()));
+    }
+    let request: WebhookRequest = serde_json::from_slice(&body)
+        .map_err(|error| (StatusCode::BAD_REQUEST, format!("invalid request: {error}")))?;
     let tx = state.inbound_tx.read().await;
     let Some(tx) = tx.as_ref() else {
         return Err((
@@ -247,7 +318,7 @@ async fn handle_send(
         serde_json::Value::String(request.sender_id.clone()),
     );
 
-    let conversation_id = format!("webhook:{}", request.conversation_id);
+    let conversation_id = internal_conversation_id(caller.as_deref(), &request.conversation_id);
 
     let inbound = InboundMessage {
         id: uuid::Uuid::new_v4().to_string(),
@@ -270,9 +341,11 @@ async fn handle_send(
 
 async fn handle_poll(
     State(state): State<AppState>,
+    headers: HeaderMap,
     axum::extract::Path(conversation_id): axum::extract::Path<String>,
-) -> Json<PollResponse> {
-    let key = format!("webhook:{conversation_id}");
+) -> Result<Json<PollResponse>, (StatusCode, String)> {
+    let caller = authorize(&state, &headers)?;
+    let key = internal_conversation_id(caller.as_deref(), &conversation_id);
     let messages = state
         .response_buffers
         .write()
@@ -280,7 +353,7 @@ async fn handle_poll(
         .remove(&key)
         .unwrap_or_default();
 
-    Json(PollResponse { messages })
+    Ok(Json(PollResponse { messages }))
 }
 
 async fn handle_health() -> StatusCode {
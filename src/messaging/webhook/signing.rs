@@ -0,0 +1,363 @@
+//! Signature checks and replay protection for inbound webhook requests.
+//!
+//! Each mode verifies an HMAC-SHA256 over the raw request body the way one
+//! sender signs it, so `/send` can be called straight from that sender's
+//! signing code. Signed timestamps older or newer than the tolerance are
+//! refused, and every accepted request's signature is remembered until its
+//! timestamp expires, so a captured request can't be sent again. Only signed
+//! material identifies a request: a delivery ID in an unsigned header could
+//! be changed on a replay. Signatures from modes that sign no timestamp are
+//! remembered for [`UNTIMED_RETENTION`] times the tolerance from when they
+//! were first seen.
+
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// How many tolerances a signature without a signed timestamp is remembered
+/// for: a day at the default 300 seconds. Nothing in such a request says when
+/// it was sent, so a replay can only be refused while it's remembered.
+pub const UNTIMED_RETENTION: i64 = 288;
+
+/// How a sender signs requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SigningMode {
+    /// `X-Spacebot-Signature: sha256=<hex>` over `<timestamp>.<body>`, with
+    /// `X-Spacebot-Timestamp`, as outbound event webhooks sign.
+    Spacebot,
+    /// `X-Hub-Signature-256: sha256=<hex>` over the body. GitHub signs no
+    /// timestamp, so a replay is caught only while its signature is
+    /// remembered.
+    Github,
+    /// `X-Slack-Signature: v0=<hex>` over `v0:<timestamp>:<body>`, with
+    /// `X-Slack-Request-Timestamp`.
+    Slack,
+    /// `Stripe-Signature: t=<timestamp>,v1=<hex>` over `<timestamp>.<body>`.
+    /// Any of several `v1` entries may match, as during secret rotation.
+    Stripe,
+}
+
+impl SigningMode {
+    pub const NAMES: &[&str] = &["spacebot", "github", "slack", "stripe"];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "spacebot" => Some(Self::Spacebot),
+            "github" => Some(Self::Github),
+            "slack" => Some(Self::Slack),
+            "stripe" => Some(Self::Stripe),
+            _ => None,
+        }
+    }
+}
+
+/// Signature checks on `/send` (`[messaging.webhook.signing]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SigningConfig {
+    pub mode: SigningMode,
+    pub secret: String,
+    /// How far a signed timestamp may be from now. Signatures are remembered
+    /// for [`UNTIMED_RETENTION`] times this when the mode signs no timestamp.
+    pub tolerance_secs: u64,
+}
+
+/// Why a request was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    MissingHeader(&'static str),
+    Malformed(&'static str),
+    Stale,
+    BadSignature,
+    Replayed,
+}
+
+impl std::fmt::Display for Rejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rejection::MissingHeader(header) => write!(f, "missing {header} header"),
+            Rejection::Malformed(header) => write!(f, "malformed {header} header"),
+            Rejection::Stale => write!(f, "timestamp outside the allowed tolerance"),
+            Rejection::BadSignature => write!(f, "signature doesn't match"),
+            Rejection::Replayed => write!(f, "request was already received"),
+        }
+    }
+}
+
+/// A signature to check: the hex digests to try and the signed prefix
+/// that goes before the body.
+struct Signed {
+    digests: Vec<String>,
+    prefix: String,
+    timestamp: Option<i64>,
+}
+
+/// Verifies signed requests and remembers the ones it accepted.
+pub struct Verifier {
+    config: SigningConfig,
+    /// Accepted request (signed timestamp and signature) → unix time it can
+    /// be forgotten at.
+    seen: Mutex<HashMap<String, i64>>,
+}
+
+impl Verifier {
+    pub fn new(config: SigningConfig) -> Self {
+        Self {
+            config,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Check a request's signature and timestamp at `now` (unix seconds). An
+    /// accepted request is refused from then on.
+    pub fn verify(&self, headers: &HeaderMap, body: &[u8], now: i64) -> Result<(), Rejection> {
+        let signed = self.signed(headers)?;
+        let tolerance = i64::try_from(self.config.tolerance_secs).unwrap_or(i64::MAX);
+        if let Some(timestamp) = signed.timestamp
+            && now.abs_diff(timestamp) > self.config.tolerance_secs
+        {
+            return Err(Rejection::Stale);
+        }
+
+        let digest = signed
+            .digests
+            .iter()
+            .find(|digest| self.matches(&signed.prefix, body, digest))
+            .ok_or(Rejection::BadSignature)?;
+
+        // Only remember verified requests, so unsigned ones can't fill the cache.
+        // Hex case doesn't change the signature, so it can't dodge the check.
+        let digest = digest.to_ascii_lowercase();
+        let key = match signed.timestamp {
+            Some(timestamp) => format!("{timestamp}:{digest}"),
+            None => digest,
+        };
+        let expires_at = match signed.timestamp {
+            Some(timestamp) => timestamp.saturating_add(tolerance),
+            // Fixed from the first delivery; a refused replay doesn't extend it.
+            None => now.saturating_add(tolerance.saturating_mul(UNTIMED_RETENTION)),
+        };
+        let mut seen = self.seen.lock().expect("webhook replay cache poisoned");
+        seen.retain(|_, expiry| *expiry >= now);
+        if seen.contains_key(&key) {
+            return Err(Rejection::Replayed);
+        }
+        seen.insert(key, expires_at);
+        Ok(())
+    }
+
+    fn signed(&self, headers: &HeaderMap) -> Result<Signed, Rejection> {
+        let header = |name: &'static str| -> Result<&str, Rejection> {
+            headers
+                .get(name)
+                .ok_or(Rejection::MissingHeader(name))?
+                .to_str()
+                .map_err(|_| Rejection::Malformed(name))
+        };
+        let timestamp = |name: &'static str, value: &str| -> Result<i64, Rejection> {
+            value.trim().parse().map_err(|_| Rejection::Malformed(name))
+        };
+        let digest = |name: &'static str, prefix: &str| -> Result<String, Rejection> {
+            header(name)?
+                .trim()
+                .strip_prefix(prefix)
+                .map(str::to_string)
+                .ok_or(Rejection::Malformed(name))
+        };
+
+        match self.config.mode {
+            SigningMode::Spacebot => {
+                let ts = timestamp("x-spacebot-timestamp", header("x-spacebot-timestamp")?)?;
+                Ok(Signed {
+                    digests: vec![digest("x-spacebot-signature", "sha256=")?],
+                    prefix: format!("{ts}."),
+                    timestamp: Some(ts),
+                })
+            }
+            SigningMode::Github => Ok(Signed {
+                digests: vec![digest("x-hub-signature-256", "sha256=")?],
+                prefix: String::new(),
+                timestamp: None,
+            }),
+            SigningMode::Slack => {
+                let ts = timestamp(
+                    "x-slack-request-timestamp",
+                    header("x-slack-request-timestamp")?,
+                )?;
+                Ok(Signed {
+                    digests: vec![digest("x-slack-signature", "v0=")?],
+                    prefix: format!("v0:{ts}:"),
+                    timestamp: Some(ts),
+                })
+            }
+            SigningMode::Stripe => {
+                let mut ts = None;
+                let mut digests = Vec::new();
+                for field in header("stripe-signature")?.split(',') {
+                    match field.trim().split_once('=') {
+                        Some(("t", value)) => ts = Some(timestamp("stripe-signature", value)?),
+                        Some(("v1", value)) => digests.push(value.to_string()),
+                        _ => {}
+                    }
+                }
+                let ts = ts.ok_or(Rejection::Malformed("stripe-signature"))?;
+                if digests.is_empty() {
+                    return Err(Rejection::Malformed("stripe-signature"));
+                }
+                Ok(Signed {
+                    digests,
+                    prefix: format!("{ts}."),
+                    timestamp: Some(ts),
+                })
+            }
+        }
+    }
+
+    fn matches(&self, prefix: &str, body: &[u8], digest: &str) -> bool {
+        let Ok(expected) = hex::decode(digest) else {
+            return false;
+        };
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.config.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(prefix.as_bytes());
+        mac.update(body);
+        // Constant-time, so timing doesn't reveal how much of a guess matched.
+        mac.verify_slice(&expected).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign(secret: &str, message: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(message.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    fn verifier(mode: SigningMode) -> Verifier {
+        Verifier::new(SigningConfig {
+            mode,
+            secret: "shh".into(),
+            tolerance_secs: 300,
+        })
+    }
+
+    fn headers(pairs: &[(&'static str, String)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn each_mode_verifies_its_senders_signature_once() {
+        let body = br#"{"conversation_id":"c1","content":"hi"}"#;
+        let text = std::str::from_utf8(body).unwrap();
+        let now = 1_700_000_000;
+
+        let slack = verifier(SigningMode::Slack);
+        let signed = headers(&[
+            ("x-slack-request-timestamp", now.to_string()),
+            (
+                "x-slack-signature",
+                format!("v0={}", sign("shh", &format!("v0:{now}:{text}"))),
+            ),
+        ]);
+        assert_eq!(slack.verify(&signed, body, now + 10), Ok(()));
+        assert_eq!(
+            slack.verify(&signed, body, now + 20),
+            Err(Rejection::Replayed)
+        );
+        assert_eq!(
+            slack.verify(&signed, body, now + 600),
+            Err(Rejection::Stale)
+        );
+        assert_eq!(
+            slack.verify(&signed, b"tampered", now),
+            Err(Rejection::BadSignature)
+        );
+
+        let stripe = verifier(SigningMode::Stripe);
+        let signed = headers(&[(
+            "stripe-signature",
+            format!(
+                "t={now},v1={},v1={}",
+                sign("old", "x"),
+                sign("shh", &format!("{now}.{text}"))
+            ),
+        )]);
+        assert_eq!(stripe.verify(&signed, body, now), Ok(()));
+
+        let github = verifier(SigningMode::Github);
+        let delivery = |id: &str| {
+            headers(&[
+                ("x-github-delivery", id.to_string()),
+                (
+                    "x-hub-signature-256",
+                    format!("sha256={}", sign("shh", text)),
+                ),
+            ])
+        };
+        assert_eq!(github.verify(&delivery("d1"), body, now), Ok(()));
+        // A fresh delivery ID isn't signed, so it doesn't make a replay new.
+        assert_eq!(
+            github.verify(&delivery("d2"), body, now),
+            Err(Rejection::Replayed)
+        );
+        assert_eq!(
+            github.verify(&delivery("d1"), body, now + 60),
+            Err(Rejection::Replayed)
+        );
+        // Without a signed timestamp, signatures outlive the tolerance by far.
+        assert_eq!(
+            github.verify(&delivery("d1"), body, now + 301),
+            Err(Rejection::Replayed)
+        );
+        assert_eq!(
+            github.verify(&delivery("d1"), body, now + 300 * UNTIMED_RETENTION),
+            Err(Rejection::Replayed)
+        );
+        assert_eq!(
+            github.verify(&delivery("d1"), body, now + 300 * UNTIMED_RETENTION + 1),
+            Ok(())
+        );
+
+        let spacebot = verifier(SigningMode::Spacebot);
+        assert_eq!(
+            spacebot.verify(&HeaderMap::new(), body, now),
+            Err(Rejection::MissingHeader("x-spacebot-timestamp"))
+        );
+        let signed = headers(&[
+            ("x-spacebot-timestamp", now.to_string()),
+            (
+                "x-spacebot-signature",
+                crate::events::webhooks::signature("shh", now, body),
+            ),
+        ]);
+        assert_eq!(spacebot.verify(&signed, body, now), Ok(()));
+        let mut renamed = signed.clone();
+        renamed.insert("x-spacebot-nonce", "fresh".parse().unwrap());
+        assert_eq!(
+            spacebot.verify(&renamed, body, now + 1),
+            Err(Rejection::Replayed)
+        );
+        let upper = headers(&[
+            ("x-spacebot-timestamp", now.to_string()),
+            (
+                "x-spacebot-signature",
+                crate::events::webhooks::signature("shh", now, body)
+                    .to_ascii_uppercase()
+                    .replacen("SHA256=", "sha256=", 1),
+            ),
+        ]);
+        assert_eq!(
+            spacebot.verify(&upper, body, now + 1),
+            Err(Rejection::Replayed)
+        );
+    }
+}
@@ -0,0 +1,625 @@
+//! WebSocket messaging adapter: a JSON API for programmatic clients.
+//!
+//! Each client in `[messaging.websocket.api_keys]`, or holding an issued key
+//! with the `websocket` scope, connects to `/ws` with its API key and
+//! exchanges JSON frames tagged by `type`. Client frames send
+//! messages; server frames report acceptance, streamed tokens, tool calls,
+//! and the final message. Clients name their own conversations, which are
+//! kept per client as `websocket:<client>:<conversation_id>`, so two clients
+//! using the same name never see each other's replies.
+//!
+//! The protocol is documented in `docs/content/docs/(messaging)/messaging.mdx`.
+
+use crate::api_keys::{ApiKeyStore, Scope};
+use crate::config::WebSocketConfig;
+use crate::messaging::traits::{InboundStream, Messaging};
+use crate::{InboundMessage, MessageContent, OutboundResponse, StatusUpdate};
+
+use anyhow::Context as _;
+use axum::Router;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tokio::sync::{Mutex, RwLock, broadcast, mpsc};
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// WebSocket adapter state.
+pub struct WebSocketAdapter {
+    port: u16,
+    bind: String,
+    shared: Arc<Shared>,
+    /// Text streamed so far, by conversation ID, so the final `message`
+    /// frame can carry the whole reply.
+    streams: Mutex<HashMap<String, String>>,
+    shutdown_tx: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+}
+
+/// State shared with the axum handlers.
+struct Shared {
+    default_agent: String,
+    /// API key digests, by client name.
+    api_keys: BTreeMap<String, [u8; 32]>,
+    /// Issued keys, whose clients are known by the key's name.
+    issued_keys: Arc<ApiKeyStore>,
+    inbound_tx: RwLock<Option<mpsc::Sender<InboundMessage>>>,
+    /// Event fan-out to every open socket, by client name.
+    clients: RwLock<HashMap<String, broadcast::Sender<ServerFrame>>>,
+}
+
+/// A frame sent by the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientFrame {
+    Message(MessageFrame),
+    Ping,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageFrame {
+    conversation_id: String,
+    content: String,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    sender_id: Option<String>,
+    #[serde(default)]
+    sender_name: Option<String>,
+    #[serde(default)]
+    agent_id: Option<String>,
+}
+
+/// A frame sent to the client.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerFrame {
+    Ready {
+        client: String,
+    },
+    Accepted {
+        id: String,
+        conversation_id: String,
+    },
+    Pong,
+    Thinking {
+        conversation_id: String,
+    },
+    StreamStart {
+        conversation_id: String,
+    },
+    Token {
+        conversation_id: String,
+        content: String,
+    },
+    ToolStarted {
+        conversation_id: String,
+        tool: String,
+    },
+    ToolCompleted {
+        conversation_id: String,
+        tool: String,
+    },
+    /// A complete reply. For streamed replies this follows the last token
+    /// and repeats the whole text.
+    Message {
+        conversation_id: String,
+        content: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reply_to: Option<String>,
+        streamed: bool,
+    },
+    File {
+        conversation_id: String,
+        filename: String,
+        mime_type: String,
+        /// The file's bytes, base64-encoded.
+        data: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        caption: Option<String>,
+    },
+    Done {
+        conversation_id: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl WebSocketAdapter {
+    /// `default_agent` handles messages that don't name an agent.
+    pub fn new(
+        config: &WebSocketConfig,
+        default_agent: impl Into<String>,
+        issued_keys: Arc<ApiKeyStore>,
+    ) -> Self {
+        let api_keys = config
+            .api_keys
+            .iter()
+            .map(|(client, key)| (client.clone(), digest(key)))
+            .collect();
+        Self {
+            port: config.port,
+            bind: config.bind.clone(),
+            shared: Arc::new(Shared {
+                default_agent: default_agent.into(),
+                api_keys,
+                issued_keys,
+                inbound_tx: RwLock::new(None),
+                clients: RwLock::new(HashMap::new()),
+            }),
+            streams: Mutex::new(HashMap::new()),
+            shutdown_tx: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    async fn publish(&self, client: &str, frame: ServerFrame) {
+        if let Some(sender) = self.shared.clients.read().await.get(client) {
+            // No receivers just means the client isn't connected.
+            let _ = sender.send(frame);
+        }
+    }
+
+    /// The frame for an outbound response, tracking streamed text.
+    async fn frame_for(
+        &self,
+        conversation_id: &str,
+        client_conversation: &str,
+        reply_to: Option<&str>,
+        response: OutboundResponse,
+    ) -> Option<ServerFrame> {
+        let conversation_id_out = client_conversation.to_string();
+        Some(match response {
+            OutboundResponse::StreamStart => {
+                self.streams
+                    .lock()
+                    .await
+                    .insert(conversation_id.to_string(), String::new());
+                ServerFrame::StreamStart {
+                    conversation_id: conversation_id_out,
+                }
+            }
+            OutboundResponse::StreamChunk(content) => {
+                if let Some(text) = self.streams.lock().await.get_mut(conversation_id) {
+                    text.push_str(&content);
+                }
+                ServerFrame::Token {
+                    conversation_id: conversation_id_out,
+                    content,
+                }
+            }
+            OutboundResponse::StreamEnd => {
+                let content = self.streams.lock().await.remove(conversation_id)?;
+                ServerFrame::Message {
+                    conversation_id: conversation_id_out,
+                    content,
+                    reply_to: reply_to.map(str::to_string),
+                    streamed: true,
+                }
+            }
+            response => server_frame(conversation_id_out, reply_to, response)?,
+        })
+    }
+}
+
+impl Messaging for WebSocketAdapter {
+    fn name(&self) -> &str {
+        "websocket"
+    }
+
+    async fn start(&self) -> crate::Result<InboundStream> {
+        let (inbound_tx, inbound_rx) = mpsc::channel(256);
+        let (shutdown_tx, mut shutdown_rx) = mpsc::channel::<()>(1);
+
+        *self.shared.inbound_tx.write().await = Some(inbound_tx);
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+
+        let app = Router::new()
+            .route("/ws", get(handle_upgrade))
+            .route("/health", get(handle_health))
+            .with_state(self.shared.clone());
+
+        let bind = if self.bind.contains(':') {
+            format!("[{}]:{}", self.bind, self.port)
+        } else {
+            format!("{}:{}", self.bind, self.port)
+        };
+        let listener = crate::listeners::bind("websocket", bind.as_str())
+            .await
+            .with_context(|| format!("failed to bind websocket server to {bind}"))?;
+        tracing::info!(%bind, "websocket server listening");
+
+        tokio::spawn(async move {
+            if let Err(error) = listener
+                .serve(app, async move {
+                    let _ = shutdown_rx.recv().await;
+                })
+                .await
+            {
+                tracing::error!(%error, "websocket server exited with error");
+            }
+        });
+
+        let stream = tokio_stream::wrappers::ReceiverStream::new(inbound_rx);
+        Ok(Box::pin(stream))
+    }
+
+    async fn respond(
+        &self,
+        message: &InboundMessage,
+        response: OutboundResponse,
+    ) -> crate::Result<()> {
+        let Some((client, conversation)) = split_conversation_id(&message.conversation_id) else {
+            return Ok(());
+        };
+        if let Some(frame) = self
+            .frame_for(
+                &message.conversation_id,
+                conversation,
+                Some(&message.id),
+                response,
+            )
+            .await
+        {
+            self.publish(client, frame).await;
+        }
+        Ok(())
+    }
+
+    async fn send_status(
+        &self,
+        message: &InboundMessage,
+        status: StatusUpdate,
+    ) -> crate::Result<()> {
+        let Some((client, conversation)) = split_conversation_id(&message.conversation_id) else {
+            return Ok(());
+        };
+        let conversation_id = conversation.to_string();
+        let frame = match status {
+            StatusUpdate::Thinking => ServerFrame::Thinking { conversation_id },
+            StatusUpdate::StopTyping => ServerFrame::Done { conversation_id },
+            StatusUpdate::ToolStarted { tool_name } => ServerFrame::ToolStarted {
+                conversation_id,
+                tool: tool_name,
+            },
+            StatusUpdate::ToolCompleted { tool_name } => ServerFrame::ToolCompleted {
+                conversation_id,
+                tool: tool_name,
+            },
+            _ => return Ok(()),
+        };
+        self.publish(client, frame).await;
+        Ok(())
+    }
+
+    /// `target` is `<client>:<conversation_id>`.
+    async fn broadcast(&self, target: &str, response: OutboundResponse) -> crate::Result<()> {
+        let Some((client, conversation)) = target.split_once(':') else {
+            return Err(anyhow::anyhow!(
+                "websocket target '{target}' must be <client>:<conversation_id>"
+            )
+            .into());
+        };
+        if !self.shared.api_keys.contains_key(client)
+            && !self.shared.issued_keys.has_active(client, Scope::Websocket)
+        {
+            return Err(anyhow::anyhow!("no websocket client named '{client}'").into());
+        }
+        let conversation_id = internal_conversation_id(client, conversation);
+        if let Some(frame) = self
+            .frame_for(&conversation_id, conversation, None, response)
+            .await
+        {
+            self.publish(client, frame).await;
+        }
+        Ok(())
+    }
+
+    async fn health_check(&self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> crate::Result<()> {
+        if let Some(tx) = self.shutdown_tx.read().await.as_ref() {
+            tx.send(()).await.ok();
+        }
+        tracing::info!("websocket adapter shut down");
+        Ok(())
+    }
+}
+
+fn internal_conversation_id(client: &str, conversation_id: &str) -> String {
+    format!("websocket:{client}:{conversation_id}")
+}
+
+/// The client name and the client's own conversation ID.
+fn split_conversation_id(conversation_id: &str) -> Option<(&str, &str)> {
+    conversation_id.strip_prefix("websocket:")?.split_once(':')
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+/// The frame for a non-streaming outbound response. `None` for responses
+/// with no meaning to API clients.
+fn server_frame(
+    conversation_id: String,
+    reply_to: Option<&str>,
+    response: OutboundResponse,
+) -> Option<ServerFrame> {
+    Some(match response {
+        OutboundResponse::Text(content)
+        | OutboundResponse::ThreadReply { text: content, .. }
+        | OutboundResponse::RichMessage { text: content, .. }
+        | OutboundResponse::Ephemeral { text: content, .. }
+        | OutboundResponse::ScheduledMessage { text: content, .. } => ServerFrame::Message {
+            conversation_id,
+            content,
+            reply_to: reply_to.map(str::to_string),
+            streamed: false,
+        },
+        OutboundResponse::File {
+            filename,
+            data,
+            mime_type,
+            caption,
+        } => ServerFrame::File {
+            conversation_id,
+            filename,
+            mime_type,
+            data: base64::engine::general_purpose::STANDARD.encode(data),
+            caption,
+        },
+        OutboundResponse::StreamStart
+        | OutboundResponse::StreamChunk(_)
+        | OutboundResponse::StreamEnd
+        | OutboundResponse::Reaction(_)
+        | OutboundResponse::RemoveReaction(_)
+        | OutboundResponse::Status(_) => return None,
+    })
+}
+
+/// The client whose API key this is.
+fn client_for_key(api_keys: &BTreeMap<String, [u8; 32]>, key: &str) -> Option<String> {
+    // Compare digests so the time taken says nothing about the key.
+    let digest = digest(key);
+    api_keys
+        .iter()
+        .find(|(_, expected)| **expected == digest)
+        .map(|(client, _)| client.clone())
+}
+
+/// The API key from an `Authorization: Bearer` header, or from the
+/// `api_key` query parameter for clients that can't set headers.
+fn api_key<'a>(headers: &'a HeaderMap, query: &'a AuthQuery) -> Option<&'a str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(query.api_key.as_deref())
+}
+
+/// The inbound message for a client `message` frame.
+fn inbound_message(client: &str, default_agent: &str, frame: MessageFrame) -> InboundMessage {
+    let MessageFrame {
+        conversation_id,
+        content,
+        id,
+        sender_id,
+        sender_name,
+        agent_id,
+    } = frame;
+    let sender_id = sender_id.unwrap_or_else(|| client.to_string());
+    let sender_name = sender_name.unwrap_or_else(|| sender_id.clone());
+    let mut metadata = HashMap::new();
+    metadata.insert(
+        "sender_display_name".into(),
+        serde_json::Value::String(sender_name.clone()),
+    );
+    metadata.insert(
+        "websocket_client".into(),
+        serde_json::Value::String(client.to_string()),
+    );
+
+    InboundMessage {
+        id: id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        source: "websocket".into(),
+        conversation_id: internal_conversation_id(client, &conversation_id),
+        sender_id,
+        agent_id: Some(agent_id.unwrap_or_else(|| default_agent.to_string()).into()),
+        content: MessageContent::Text(content),
+        timestamp: chrono::Utc::now(),
+        metadata,
+        formatted_author: Some(sender_name),
+    }
+}
+
+// -- Axum handlers --
+
+#[derive(Deserialize)]
+struct AuthQuery {
+    api_key: Option<String>,
+}
+
+async fn handle_health() -> StatusCode {
+    StatusCode::OK
+}
+
+async fn handle_upgrade(
+    State(shared): State<Arc<Shared>>,
+    headers: HeaderMap,
+    Query(query): Query<AuthQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let Some(client) = api_key(&headers, &query).and_then(|key| {
+        client_for_key(&shared.api_keys, key).or_else(|| {
+            shared
+                .issued_keys
+                .verify(key, Scope::Websocket)
+                .map(|record| record.name)
+        })
+    }) else {
+        tracing::warn!("websocket connection with a missing or unknown API key");
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    upgrade.on_upgrade(move |socket| handle_socket(shared, client, socket))
+}
+
+async fn handle_socket(shared: Arc<Shared>, client: String, mut socket: WebSocket) {
+    let mut events = shared
+        .clients
+        .write()
+        .await
+        .entry(client.clone())
+        .or_insert_with(|| broadcast::channel(256).0)
+        .subscribe();
+    tracing::info!(%client, "websocket client connected");
+
+    if send_frame(
+        &mut socket,
+        &ServerFrame::Ready {
+            client: client.clone(),
+        },
+    )
+    .await
+    .is_err()
+    {
+        return;
+    }
+
+    loop {
+        let reply = tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => handle_frame(&shared, &client, text.as_str()).await,
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => continue,
+                Some(Err(error)) => {
+                    tracing::debug!(%client, %error, "websocket receive failed");
+                    break;
+                }
+            },
+            event = events.recv() => match event {
+                Ok(frame) => Some(frame),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!(%client, skipped, "websocket client lagged");
+                    Some(ServerFrame::Error {
+                        message: format!("{skipped} events were dropped because the client fell behind"),
+                    })
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+        if let Some(frame) = reply
+            && send_frame(&mut socket, &frame).await.is_err()
+        {
+            break;
+        }
+    }
+    tracing::info!(%client, "websocket client disconnected");
+}
+
+/// Handle one client frame, returning the frame to send back directly.
+async fn handle_frame(shared: &Shared, client: &str, text: &str) -> Option<ServerFrame> {
+    let frame = match serde_json::from_str::<ClientFrame>(text) {
+        Ok(frame) => frame,
+        Err(error) => {
+            return Some(ServerFrame::Error {
+                message: format!("invalid frame: {error}"),
+            });
+        }
+    };
+
+    let frame = match frame {
+        ClientFrame::Ping => return Some(ServerFrame::Pong),
+        ClientFrame::Message(frame) => frame,
+    };
+    if frame.conversation_id.is_empty() || frame.content.trim().is_empty() {
+        return Some(ServerFrame::Error {
+            message: "conversation_id and content must not be empty".into(),
+        });
+    }
+
+    let conversation_id = frame.conversation_id.clone();
+    let message = inbound_message(client, &shared.default_agent, frame);
+    let accepted = ServerFrame::Accepted {
+        id: message.id.clone(),
+        conversation_id,
+    };
+
+    let tx = shared.inbound_tx.read().await;
+    let Some(tx) = tx.as_ref() else {
+        return Some(ServerFrame::Error {
+            message: "websocket adapter not initialized".into(),
+        });
+    };
+    if tx.send(message).await.is_err() {
+        return Some(ServerFrame::Error {
+            message: "channel closed".into(),
+        });
+    }
+    Some(accepted)
+}
+
+async fn send_frame(socket: &mut WebSocket, frame: &ServerFrame) -> Result<(), axum::Error> {
+    let json = serde_json::to_string(frame).expect("server frames always serialize");
+    socket.send(Message::Text(json.into())).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_and_frames_follow_the_protocol() {
+        let api_keys = BTreeMap::from([("ci".to_string(), digest("ci-key-0123456789abc"))]);
+        assert_eq!(
+            client_for_key(&api_keys, "ci-key-0123456789abc").as_deref(),
+            Some("ci")
+        );
+        assert_eq!(client_for_key(&api_keys, "ci-key"), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer abc".parse().unwrap());
+        let query = AuthQuery {
+            api_key: Some("def".into()),
+        };
+        assert_eq!(api_key(&headers, &query), Some("abc"));
+        assert_eq!(api_key(&HeaderMap::new(), &query), Some("def"));
+
+        let frame: ClientFrame = serde_json::from_str(
+            r#"{"type":"message","conversation_id":"build-42","content":"status?"}"#,
+        )
+        .unwrap();
+        let ClientFrame::Message(frame) = frame else {
+            panic!("expected a message frame");
+        };
+        let message = inbound_message("ci", "main", frame);
+        assert_eq!(message.conversation_id, "websocket:ci:build-42");
+        assert_eq!(message.sender_id, "ci");
+        assert_eq!(message.agent_id.as_deref(), Some("main"));
+        assert_eq!(
+            split_conversation_id(&message.conversation_id),
+            Some(("ci", "build-42"))
+        );
+
+        assert_eq!(
+            serde_json::to_string(&server_frame(
+                "build-42".into(),
+                Some("m1"),
+                OutboundResponse::Text("green".into())
+            ))
+            .unwrap(),
+            r#"{"type":"message","conversation_id":"build-42","content":"green","reply_to":"m1","streamed":false}"#
+        );
+        assert_eq!(
+            server_frame("build-42".into(), None, OutboundResponse::StreamEnd),
+            None
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! OpenAPI 3 spec ingestion for the HTTP API tools.
+//!
+//! Each operation in a spec becomes an [`ApiOperation`]: a tool name, the
+//! method and path to call, and a JSON schema for the tool's arguments with
+//! every `$ref` inlined, since tool schemas have to stand alone.
+
+use serde_json::{Map, Value, json};
+
+/// Most operations one API may offer as tools. Past this, tool definitions
+/// crowd out the rest of the context, so the config has to pick.
+pub const MAX_OPERATIONS: usize = 40;
+
+/// How many `$ref`s deep schemas are inlined before the rest is left as a
+/// plain object, which also stops recursive schemas.
+const MAX_REF_DEPTH: usize = 6;
+
+/// Longest tool name LLM providers accept.
+const MAX_TOOL_NAME: usize = 64;
+
+/// Schema keys that only document a spec and just take up context.
+const DOC_ONLY_KEYS: &[&str] = &["examples", "externalDocs", "xml"];
+
+/// One operation from a spec, offered as its own tool.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiOperation {
+    /// "{api}_{operationId}", limited to characters tool names allow.
+    pub tool_name: String,
+    pub method: reqwest::Method,
+    /// Path template relative to the API's base URL, e.g. "/invoices/{id}".
+    pub path: String,
+    pub description: String,
+    pub parameters: Vec<ApiParameter>,
+    /// JSON schema of the tool's arguments: one property per parameter,
+    /// plus `body` when the operation takes a request body.
+    pub input_schema: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiParameter {
+    pub name: String,
+    pub location: ParameterLocation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterLocation {
+    Path,
+    Query,
+    Header,
+}
+
+/// Parse a spec document, JSON or YAML.
+pub fn parse_spec(text: &str) -> Result<Value, String> {
+    if text.trim_start().starts_with('{') {
+        return serde_json::from_str(text).map_err(|error| error.to_string());
+    }
+    let documents =
+        yaml_rust2::YamlLoader::load_from_str(text).map_err(|error| error.to_string())?;
+    documents
+        .into_iter()
+        .next()
+        .map(yaml_to_json)
+        .ok_or_else(|| "empty document".to_string())
+}
+
+/// The first absolute server URL in the spec, if any.
+pub fn server_url(spec: &Value) -> Option<String> {
+    spec.get("servers")?
+        .as_array()?
+        .iter()
+        .filter_map(|server| server.get("url")?.as_str())
+        .find(|url| url.starts_with("http://") || url.starts_with("https://"))
+        .map(str::to_string)
+}
+
+/// The operations of `spec` to offer for the API called `api`.
+///
+/// `only` limits them to those operation IDs, and must name real ones.
+/// `read_only` keeps GET and HEAD operations.
+pub fn operations(
+    api: &str,
+    spec: &Value,
+    only: &[String],
+    read_only: bool,
+) -> Result<Vec<ApiOperation>, String> {
+    let paths = spec
+        .get("paths")
+        .and_then(Value::as_object)
+        .ok_or("the spec has no paths")?;
+
+    let mut operations = Vec::new();
+    let mut seen_ids = Vec::new();
+    for (path, item) in paths {
+        let item = resolve(item, spec, 0);
+        let shared_parameters = item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+
+        for method in ["get", "head", "post", "put", "patch", "delete"] {
+            let Some(operation) = item.get(method) else {
+                continue;
+            };
+            let operation_id = operation
+                .get("operationId")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{method}_{path}"));
+            seen_ids.push(operation_id.clone());
+            if !only.is_empty() && !only.contains(&operation_id) {
+                continue;
+            }
+            if read_only && !matches!(method, "get" | "head") {
+                continue;
+            }
+
+            let method = method
+                .to_ascii_uppercase()
+                .parse()
+                .expect("hardcoded HTTP method");
+            operations.push(build_operation(
+                api,
+                &operation_id,
+                method,
+                path,
+                operation,
+                &shared_parameters,
+                spec,
+            ));
+        }
+    }
+
+    if let Some(missing) = only.iter().find(|id| !seen_ids.contains(id)) {
+        return Err(format!("the spec has no operation '{missing}'"));
+    }
+    if operations.len() > MAX_OPERATIONS {
+        return Err(format!(
+            "the spec has {} operations, more than the {MAX_OPERATIONS} one API may offer; list the ones to keep in operations",
+            operations.len()
+        ));
+    }
+
+    // Distinct IDs can sanitize to the same name.
+    let mut names: Vec<String> = Vec::new();
+    for operation in &mut operations {
+        let base = operation.tool_name.clone();
+        let mut suffix = 2;
+        while names.contains(&operation.tool_name) {
+            let tail = format!("_{suffix}");
+            operation.tool_name = format!("{}{tail}", truncate(&base, MAX_TOOL_NAME - tail.len()));
+            suffix += 1;
+        }
+        names.push(operation.tool_name.clone());
+    }
+    Ok(operations)
+}
+
+fn build_operation(
+    api: &str,
+    operation_id: &str,
+    method: reqwest::Method,
+    path: &str,
+    operation: &Value,
+    shared_parameters: &[Value],
+    spec: &Value,
+) -> ApiOperation {
+    let text = |key: &str| operation.get(key).and_then(Value::as_str).map(str::trim);
+    let description = match (text("summary"), text("description")) {
+        (Some(summary), Some(description)) if !description.starts_with(summary) => {
+            format!("{summary}. {description}")
+        }
+        (_, Some(description)) => description.to_string(),
+        (Some(summary), None) => summary.to_string(),
+        (None, None) => format!("{method} {path}"),
+    };
+
+    // Operation parameters override shared ones with the same name and
+    // location.
+    let mut declared: Vec<Value> = Vec::new();
+    let own = operation
+        .get("parameters")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    for parameter in own.iter().chain(shared_parameters) {
+        let parameter = resolve(parameter, spec, 0);
+        let key = |p: &Value| (p.get("name").cloned(), p.get("in").cloned());
+        if !declared.iter().any(|seen| key(seen) == key(&parameter)) {
+            declared.push(parameter);
+        }
+    }
+
+    let mut parameters = Vec::new();
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for parameter in declared {
+        let Some(name) = parameter.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let location = match parameter.get("in").and_then(Value::as_str) {
+            Some("path") => ParameterLocation::Path,
+            Some("query") => ParameterLocation::Query,
+            Some("header") => ParameterLocation::Header,
+            // Cookie parameters would need a cookie jar per API.
+            _ => continue,
+        };
+        let mut schema = parameter
+            .get("schema")
+            .map(|schema| resolve(schema, spec, 0))
+            .unwrap_or_else(|| json!({ "type": "string" }));
+        if let (Some(object), Some(description)) = (
+            schema.as_object_mut(),
+            parameter.get("description").and_then(Value::as_str),
+        ) {
+            object.insert("description".into(), description.into());
+        }
+        if location == ParameterLocation::Path
+            || parameter.get("required").and_then(Value::as_bool) == Some(true)
+        {
+            required.push(Value::from(name));
+        }
+        properties.insert(name.to_string(), schema);
+        parameters.push(ApiParameter {
+            name: name.to_string(),
+            location,
+        });
+    }
+
+    if let Some(request_body) = operation.get("requestBody") {
+        let request_body = resolve(request_body, spec, 0);
+        let content = request_body.get("content").and_then(Value::as_object);
+        let schema = content
+            .and_then(|content| {
+                content
+                    .iter()
+                    .find(|(media_type, _)| media_type.contains("json"))
+                    .or_else(|| content.iter().next())
+            })
+            .and_then(|(_, media)| media.get("schema"))
+            .map(|schema| resolve(schema, spec, 0))
+            .unwrap_or_else(|| json!({ "type": "object" }));
+        properties.insert("body".into(), schema);
+        if request_body.get("required").and_then(Value::as_bool) == Some(true) {
+            required.push("body".into());
+        }
+    }
+
+    ApiOperation {
+        tool_name: tool_name(api, operation_id),
+        method,
+        path: path.to_string(),
+        description,
+        parameters,
+        input_schema: json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        }),
+    }
+}
+
+/// `value` with local `$ref`s inlined and documentation-only keys dropped.
+fn resolve(value: &Value, root: &Value, depth: usize) -> Value {
+    match value {
+        Value::Object(object) => {
+            if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+                let target = reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| root.pointer(pointer));
+                return match target {
+                    Some(target) if depth < MAX_REF_DEPTH => resolve(target, root, depth + 1),
+                    _ => json!({ "type": "object" }),
+                };
+            }
+            Value::Object(
+                object
+                    .iter()
+                    .filter(|(key, _)| !DOC_ONLY_KEYS.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), resolve(value, root, depth)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| resolve(item, root, depth))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// `<prefix>_<name>`, sanitized and cut to the longest name LLM providers
+/// accept.
+pub(crate) fn tool_name(prefix: &str, name: &str) -> String {
+    truncate(&format!("{prefix}_{}", sanitize(name)), MAX_TOOL_NAME)
+}
+
+/// Tool names allow ASCII letters, digits, '_', and '-'.
+fn sanitize(name: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        let c = if c.is_ascii_alphanumeric() || c == '-' {
+            c
+        } else {
+            '_'
+        };
+        if !(c == '_' && sanitized.ends_with('_')) {
+            sanitized.push(c);
+        }
+    }
+    sanitized.trim_matches('_').to_string()
+}
+
+fn truncate(name: &str, max: usize) -> String {
+    name.chars().take(max).collect()
+}
+
+fn yaml_to_json(yaml: yaml_rust2::Yaml) -> Value {
+    use yaml_rust2::Yaml;
+    match yaml {
+        Yaml::String(text) => Value::String(text),
+        Yaml::Integer(number) => Value::from(number),
+        Yaml::Real(text) => text
+            .parse::<f64>()
+            .map(Value::from)
+            .unwrap_or(Value::String(text)),
+        Yaml::Boolean(flag) => Value::Bool(flag),
+        Yaml::Array(items) => Value::Array(items.into_iter().map(yaml_to_json).collect()),
+        Yaml::Hash(entries) => Value::Object(
+            entries
+                .into_iter()
+                .filter_map(|(key, value)| {
+                    let key = match key {
+                        Yaml::String(key) => key,
+                        Yaml::Integer(key) => key.to_string(),
+                        Yaml::Boolean(key) => key.to_string(),
+                        _ => return None,
+                    };
+                    Some((key, yaml_to_json(value)))
+                })
+                .collect(),
+        ),
+        Yaml::Null | Yaml::BadValue | Yaml::Alias(_) => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r##"
+openapi: 3.0.0
+servers:
+  - url: https://billing.example.com/v1
+paths:
+  /invoices/{id}:
+    parameters:
+      - name: id
+        in: path
+        schema: { type: string }
+    get:
+      operationId: getInvoice
+      summary: Fetch an invoice
+      parameters:
+        - name: expand
+          in: query
+          description: Related objects to include
+          schema: { type: boolean }
+    delete:
+      operationId: deleteInvoice
+  /invoices:
+    post:
+      operationId: create.invoice
+      requestBody:
+        required: true
+        content:
+          application/json:
+            schema: { $ref: "#/components/schemas/Invoice" }
+components:
+  schemas:
+    Invoice:
+      type: object
+      properties:
+        amount: { type: integer }
+        parent: { $ref: "#/components/schemas/Invoice" }
+"##;
+
+    #[test]
+    fn operations_become_tools_with_inlined_schemas() {
+        let spec = parse_spec(SPEC).unwrap();
+        assert_eq!(
+            server_url(&spec).as_deref(),
+            Some("https://billing.example.com/v1")
+        );
+
+        let operations = operations("billing", &spec, &[], false).unwrap();
+        let names: Vec<&str> = operations.iter().map(|op| op.tool_name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "billing_create_invoice",
+                "billing_getInvoice",
+                "billing_deleteInvoice"
+            ]
+        );
+
+        let get = &operations[1];
+        assert_eq!(get.method, reqwest::Method::GET);
+        assert_eq!(get.description, "Fetch an invoice");
+        assert_eq!(
+            get.parameters,
+            [
+                ApiParameter {
+                    name: "expand".into(),
+                    location: ParameterLocation::Query
+                },
+                ApiParameter {
+                    name: "id".into(),
+                    location: ParameterLocation::Path
+                },
+            ]
+        );
+        assert_eq!(get.input_schema["required"], json!(["id"]));
+        assert_eq!(
+            get.input_schema["properties"]["expand"]["description"],
+            "Related objects to include"
+        );
+
+        let create = &operations[0];
+        assert_eq!(create.input_schema["required"], json!(["body"]));
+        let body = &create.input_schema["properties"]["body"];
+        assert_eq!(body["properties"]["amount"]["type"], "integer");
+        // The recursive reference is cut off instead of looping.
+        assert!(body.to_string().len() < 2000);
+    }
+
+    #[test]
+    fn operations_can_be_filtered() {
+        let spec = parse_spec(SPEC).unwrap();
+        let read_only = operations("billing", &spec, &[], true).unwrap();
+        assert_eq!(read_only.len(), 1);
+
+        let only = operations("billing", &spec, &["deleteInvoice".into()], false).unwrap();
+        assert_eq!(only[0].tool_name, "billing_deleteInvoice");
+
+        assert!(operations("billing", &spec, &["nope".into()], false).is_err());
+    }
+}
@@ -542,7 +542,7 @@ impl Drop for OpenCodeServer {
 pub struct OpenCodeServerPool {
     servers: Mutex<HashMap<PathBuf, Arc<Mutex<OpenCodeServer>>>>,
     opencode_path: String,
-    permissions: OpenCodePermissions,
+    permissions: arc_swap::ArcSwap<OpenCodePermissions>,
     max_servers: usize,
 }
 
@@ -556,11 +556,17 @@ impl OpenCodeServerPool {
         Self {
             servers: Mutex::new(HashMap::new()),
             opencode_path: opencode_path.into(),
-            permissions,
+            permissions: arc_swap::ArcSwap::from_pointee(permissions),
             max_servers,
         }
     }
 
+    /// Replace the permissions given to servers spawned from now on.
+    /// Servers already in the pool keep the permissions they started with.
+    pub fn set_permissions(&self, permissions: OpenCodePermissions) {
+        self.permissions.store(Arc::new(permissions));
+    }
+
     /// Get or create a server for the given directory.
     ///
     /// On first access for a directory, checks the deterministic port for
@@ -593,9 +599,12 @@ impl OpenCodeServerPool {
 
         // Not in pool yet. Try reattaching to an existing server on the
         // deterministic port (left over from a previous spacebot run).
-        if let Some(reattached) =
-            OpenCodeServer::reattach(canonical.clone(), &self.opencode_path, &self.permissions)
-                .await
+        if let Some(reattached) = OpenCodeServer::reattach(
+            canonical.clone(),
+            &self.opencode_path,
+            &self.permissions.load(),
+        )
+        .await
         {
             let server = Arc::new(Mutex::new(reattached));
             servers.insert(canonical, Arc::clone(&server));
@@ -610,9 +619,12 @@ impl OpenCodeServerPool {
             );
         }
 
-        let server =
-            OpenCodeServer::spawn(canonical.clone(), &self.opencode_path, &self.permissions)
-                .await?;
+        let server = OpenCodeServer::spawn(
+            canonical.clone(),
+            &self.opencode_path,
+            &self.permissions.load(),
+        )
+        .await?;
 
         let server = Arc::new(Mutex::new(server));
         servers.insert(canonical, Arc::clone(&server));
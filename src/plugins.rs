@@ -0,0 +1,76 @@
+//! WASM plugins: third-party tools and message filters, loaded from
+//! `<name>.wasm` components in the plugins directory.
+//!
+//! Plugins implement the `plugin` world in `wit/plugin.wit`. Each call gets
+//! a fresh instance with a fuel and memory budget, so a plugin can't hold
+//! state between calls or run away with the host. WASI is linked with no
+//! network and no files; a plugin only sees the environment variables and
+//! read-only directories granted to it in `[plugins.grants.<name>]`, and can
+//! only reach the hosts in its `http_hosts` grant through the host's
+//! `http-request` function.
+//!
+//! The runtime is behind the `plugins-wasm` Cargo feature, on by default.
+//! Without it, [`PluginHost`] loads no plugins and says so when `[plugins]`
+//! is enabled.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[cfg(not(feature = "plugins-wasm"))]
+mod unavailable;
+#[cfg(feature = "plugins-wasm")]
+mod wasm;
+
+#[cfg(not(feature = "plugins-wasm"))]
+pub use unavailable::{Plugin, PluginHost};
+#[cfg(feature = "plugins-wasm")]
+pub use wasm::{Plugin, PluginHost};
+
+/// Plugin settings (instance-level, under `[plugins]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginsConfig {
+    pub enabled: bool,
+    /// Directory scanned for `.wasm` components. Relative paths are under
+    /// the instance directory.
+    pub dir: PathBuf,
+    /// Fuel for each call into a plugin, roughly one unit per instruction.
+    pub fuel: u64,
+    /// Most linear memory one plugin instance may use, in bytes.
+    pub max_memory_bytes: usize,
+    /// Capability grants by plugin name (the file name without `.wasm`).
+    pub grants: BTreeMap<String, PluginGrants>,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: PathBuf::from("plugins"),
+            fuel: 1_000_000_000,
+            max_memory_bytes: 64 * 1024 * 1024,
+            grants: BTreeMap::new(),
+        }
+    }
+}
+
+/// What one plugin may touch. Everything not granted is denied.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PluginGrants {
+    /// Environment variables the plugin sees.
+    pub env: BTreeMap<String, String>,
+    /// Host directories mounted read-only at the same path.
+    pub read_dirs: Vec<PathBuf>,
+    /// Hosts `http-request` may reach.
+    pub http_hosts: Vec<String>,
+}
+
+/// A tool a plugin provides.
+#[derive(Debug, Clone)]
+pub struct PluginToolSpec {
+    /// Name the plugin knows the tool by.
+    pub name: String,
+    /// Name the LLM sees: `<plugin>_<name>`.
+    pub tool_name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
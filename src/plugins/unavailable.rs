@@ -0,0 +1,54 @@
+//! Stand-ins for [`PluginHost`] and [`Plugin`] in builds without the
+//! `plugins-wasm` feature.
+
+use crate::InboundMessage;
+use crate::plugins::{PluginToolSpec, PluginsConfig};
+
+use std::path::Path;
+use std::sync::Arc;
+
+/// A host that never has plugins.
+pub struct PluginHost;
+
+/// Never constructed; tools from plugins can't exist in this build.
+pub enum Plugin {}
+
+impl PluginHost {
+    /// A host with no plugins.
+    pub fn empty() -> Arc<Self> {
+        Arc::new(Self)
+    }
+
+    /// No plugins; logs an error when `[plugins]` is enabled.
+    pub async fn load(config: &PluginsConfig, _instance_dir: &Path) -> Arc<Self> {
+        if config.enabled {
+            tracing::error!(
+                "plugins are enabled but this build doesn't include the plugin runtime, \
+                 no plugins loaded; rebuild with `--features plugins-wasm`"
+            );
+        }
+        Self::empty()
+    }
+
+    pub fn tools(&self) -> impl Iterator<Item = (&Arc<Plugin>, &PluginToolSpec)> {
+        std::iter::empty()
+    }
+
+    pub async fn filter_message(&self, _message: &mut InboundMessage) -> bool {
+        true
+    }
+}
+
+impl Plugin {
+    pub fn name(&self) -> &str {
+        match *self {}
+    }
+
+    pub fn call_tool(
+        &self,
+        _name: &str,
+        _arguments: &str,
+    ) -> anyhow::Result<std::result::Result<String, String>> {
+        match *self {}
+    }
+}
@@ -0,0 +1,487 @@
+//! The wasmtime runtime behind [`PluginHost`].
+
+use crate::InboundMessage;
+use crate::MessageContent;
+use crate::error::Result;
+use crate::plugins::{PluginGrants, PluginToolSpec, PluginsConfig};
+
+use anyhow::Context as _;
+use wasmtime::component::{Component, HasSelf, Linker, ResourceTable};
+use wasmtime::{Engine, Store, StoreLimits, StoreLimitsBuilder};
+use wasmtime_wasi::{DirPerms, FilePerms, WasiCtx, WasiCtxView, WasiView};
+
+use std::path::Path;
+use std::sync::Arc;
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        world: "plugin",
+        path: "wit/plugin.wit",
+    });
+}
+
+use bindings::spacebot::plugin::host::{self, HttpResponse, LogLevel};
+use bindings::spacebot::plugin::types::{self, FilterAction};
+
+/// Longest HTTP response body handed to a plugin, in bytes.
+const MAX_HTTP_BODY_BYTES: usize = 1024 * 1024;
+
+/// Loaded plugins, shared by every agent.
+pub struct PluginHost {
+    plugins: Vec<Arc<Plugin>>,
+}
+
+/// One loaded plugin.
+pub struct Plugin {
+    name: String,
+    runtime: Arc<Runtime>,
+    component: Component,
+    grants: PluginGrants,
+    tools: Vec<PluginToolSpec>,
+    filters_messages: bool,
+}
+
+/// Compiler and linker shared by every plugin.
+struct Runtime {
+    engine: Engine,
+    linker: Linker<PluginState>,
+    fuel: u64,
+    max_memory_bytes: usize,
+    http: crate::egress::Client,
+    handle: tokio::runtime::Handle,
+}
+
+/// Per-call store data.
+struct PluginState {
+    plugin: String,
+    http_hosts: Vec<String>,
+    http: crate::egress::Client,
+    handle: tokio::runtime::Handle,
+    wasi: WasiCtx,
+    table: ResourceTable,
+    limits: StoreLimits,
+}
+
+impl WasiView for PluginState {
+    fn ctx(&mut self) -> WasiCtxView<'_> {
+        WasiCtxView {
+            ctx: &mut self.wasi,
+            table: &mut self.table,
+        }
+    }
+}
+
+impl types::Host for PluginState {}
+
+impl host::Host for PluginState {
+    fn log(&mut self, level: LogLevel, message: String) {
+        let plugin = &self.plugin;
+        match level {
+            LogLevel::Debug => tracing::debug!(%plugin, "{message}"),
+            LogLevel::Info => tracing::info!(%plugin, "{message}"),
+            LogLevel::Warn => tracing::warn!(%plugin, "{message}"),
+            LogLevel::Error => tracing::error!(%plugin, "{message}"),
+        }
+    }
+
+    fn http_request(
+        &mut self,
+        method: String,
+        url: String,
+        body: Option<String>,
+    ) -> std::result::Result<HttpResponse, String> {
+        let url = reqwest::Url::parse(&url).map_err(|error| format!("invalid URL: {error}"))?;
+        if !host_allowed(&url, &self.http_hosts) {
+            tracing::warn!(plugin = %self.plugin, %url, "plugin HTTP request to a host it wasn't granted");
+            return Err(format!(
+                "{} isn't in this plugin's http_hosts grant",
+                url.host_str().unwrap_or_default()
+            ));
+        }
+        let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())
+            .map_err(|_| format!("invalid method '{method}'"))?;
+
+        let mut request = self.http.request(method, url);
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        // Plugin calls run on a blocking thread, so waiting here is fine.
+        self.handle.block_on(async move {
+            let response = request.send().await.map_err(|error| error.to_string())?;
+            let status = response.status().as_u16();
+            let bytes = response.bytes().await.map_err(|error| error.to_string())?;
+            let bytes = &bytes[..bytes.len().min(MAX_HTTP_BODY_BYTES)];
+            Ok(HttpResponse {
+                status,
+                body: String::from_utf8_lossy(bytes).into_owned(),
+            })
+        })
+    }
+}
+
+/// Whether a URL's host is granted. `*.example.com` also matches
+/// subdomains. Only HTTP and HTTPS are allowed.
+fn host_allowed(url: &reqwest::Url, http_hosts: &[String]) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    crate::egress::domain_matches(host, http_hosts)
+}
+
+impl PluginHost {
+    /// A host with no plugins.
+    pub fn empty() -> Arc<Self> {
+        Arc::new(Self {
+            plugins: Vec::new(),
+        })
+    }
+
+    /// Compile and describe every plugin in the plugins directory. A plugin
+    /// that fails to load is logged and skipped.
+    pub async fn load(config: &PluginsConfig, instance_dir: &Path) -> Arc<Self> {
+        if !config.enabled {
+            return Self::empty();
+        }
+        let dir = if config.dir.is_absolute() {
+            config.dir.clone()
+        } else {
+            instance_dir.join(&config.dir)
+        };
+        let config = config.clone();
+        let handle = tokio::runtime::Handle::current();
+        let loaded =
+            tokio::task::spawn_blocking(move || Self::load_blocking(&config, &dir, handle)).await;
+        match loaded {
+            Ok(Ok(host)) => Arc::new(host),
+            Ok(Err(error)) => {
+                tracing::error!(%error, "failed to start the plugin runtime, no plugins loaded");
+                Self::empty()
+            }
+            Err(error) => {
+                tracing::error!(%error, "plugin loading panicked, no plugins loaded");
+                Self::empty()
+            }
+        }
+    }
+
+    fn load_blocking(
+        config: &PluginsConfig,
+        dir: &Path,
+        handle: tokio::runtime::Handle,
+    ) -> Result<Self> {
+        let mut engine_config = wasmtime::Config::new();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config)
+            .map_err(|error| anyhow::anyhow!("failed to create wasm engine: {error}"))?;
+
+        let mut linker = Linker::new(&engine);
+        wasmtime_wasi::p2::add_to_linker_sync(&mut linker)
+            .map_err(|error| anyhow::anyhow!("failed to link WASI: {error}"))?;
+        bindings::Plugin::add_to_linker::<_, HasSelf<_>>(&mut linker, |state| state)
+            .map_err(|error| anyhow::anyhow!("failed to link plugin host functions: {error}"))?;
+
+        let runtime = Arc::new(Runtime {
+            engine,
+            linker,
+            fuel: config.fuel,
+            max_memory_bytes: config.max_memory_bytes,
+            http: crate::egress::client_builder()
+                .timeout(std::time::Duration::from_secs(30))
+                .build()
+                .context("failed to build plugin HTTP client")?,
+            handle,
+        });
+
+        let mut paths = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .is_some_and(|extension| extension == "wasm")
+                })
+                .collect::<Vec<_>>(),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!(dir = %dir.display(), "plugins directory doesn't exist, no plugins loaded");
+                Vec::new()
+            }
+            Err(error) => {
+                return Err(anyhow::anyhow!(error)
+                    .context(format!(
+                        "failed to read plugins directory {}",
+                        dir.display()
+                    ))
+                    .into());
+            }
+        };
+        paths.sort();
+
+        let mut plugins = Vec::new();
+        for path in paths {
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            // The name prefixes tool names, so it has to be a valid one.
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                tracing::error!(path = %path.display(), "plugin file names may only use ASCII letters, digits, '-' and '_', skipping");
+                continue;
+            }
+            let grants = config.grants.get(name).cloned().unwrap_or_default();
+            match Plugin::load(name, &path, runtime.clone(), grants) {
+                Ok(plugin) => {
+                    tracing::info!(
+                        plugin = %plugin.name,
+                        tools = plugin.tools.len(),
+                        filters_messages = plugin.filters_messages,
+                        "plugin loaded"
+                    );
+                    plugins.push(Arc::new(plugin));
+                }
+                Err(error) => {
+                    tracing::error!(%error, path = %path.display(), "failed to load plugin, skipping");
+                }
+            }
+        }
+        for name in config.grants.keys() {
+            if !plugins.iter().any(|plugin| &plugin.name == name) {
+                tracing::warn!(plugin = %name, "plugins.grants names a plugin that isn't loaded");
+            }
+        }
+
+        Ok(Self { plugins })
+    }
+
+    /// Every tool from every plugin.
+    pub fn tools(&self) -> impl Iterator<Item = (&Arc<Plugin>, &PluginToolSpec)> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| plugin.tools.iter().map(move |tool| (plugin, tool)))
+    }
+
+    /// Run the message filters, in plugin name order. Returns `false` when a
+    /// plugin drops the message. A filter that fails lets the message
+    /// through unchanged.
+    pub async fn filter_message(&self, message: &mut InboundMessage) -> bool {
+        let text = match &message.content {
+            MessageContent::Text(text) => text,
+            MessageContent::Media {
+                text: Some(text), ..
+            } => text,
+            _ => return true,
+        };
+        let mut filtered = types::Message {
+            source: message.source.clone(),
+            conversation_id: message.conversation_id.clone(),
+            sender_id: message.sender_id.clone(),
+            agent_id: message.agent_id.as_deref().unwrap_or_default().to_string(),
+            content: text.clone(),
+        };
+
+        let mut changed = false;
+        for plugin in self.plugins.iter().filter(|plugin| plugin.filters_messages) {
+            let call_plugin = plugin.clone();
+            let call_message = filtered.clone();
+            let action =
+                tokio::task::spawn_blocking(move || call_plugin.filter_message(&call_message))
+                    .await
+                    .map_err(|error| anyhow::anyhow!(error))
+                    .and_then(|result| result);
+            match action {
+                Ok(FilterAction::Pass) => {}
+                Ok(FilterAction::Replace(content)) => {
+                    filtered.content = content;
+                    changed = true;
+                }
+                Ok(FilterAction::Drop(reason)) => {
+                    tracing::info!(
+                        plugin = %plugin.name,
+                        conversation_id = %message.conversation_id,
+                        %reason,
+                        "plugin dropped inbound message"
+                    );
+                    crate::events::publish(crate::events::Event::ModerationBlocked {
+                        agent_id: message.agent_id.clone(),
+                        conversation_id: Some(message.conversation_id.clone()),
+                        blocked_by: format!("plugin:{}", plugin.name),
+                        reason,
+                    });
+                    return false;
+                }
+                Err(error) => {
+                    tracing::warn!(plugin = %plugin.name, %error, "plugin message filter failed, passing message through");
+                }
+            }
+        }
+
+        if changed {
+            match &mut message.content {
+                MessageContent::Text(text)
+                | MessageContent::Media {
+                    text: Some(text), ..
+                } => *text = filtered.content,
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+impl Plugin {
+    fn load(
+        name: &str,
+        path: &Path,
+        runtime: Arc<Runtime>,
+        grants: PluginGrants,
+    ) -> anyhow::Result<Self> {
+        let component = Component::from_file(&runtime.engine, path)
+            .map_err(|error| anyhow::anyhow!("failed to compile component: {error}"))?;
+        let mut plugin = Self {
+            name: name.to_string(),
+            runtime,
+            component,
+            grants,
+            tools: Vec::new(),
+            filters_messages: false,
+        };
+
+        let (mut store, instance) = plugin.instantiate()?;
+        let manifest = instance
+            .call_describe(&mut store)
+            .map_err(|error| anyhow::anyhow!("describe failed: {error}"))?;
+        plugin.filters_messages = manifest.filters_messages;
+        plugin.tools = manifest
+            .tools
+            .into_iter()
+            .map(|tool| tool_spec(name, tool))
+            .collect();
+        Ok(plugin)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// A fresh instance with this plugin's grants and budget.
+    fn instantiate(&self) -> anyhow::Result<(Store<PluginState>, bindings::Plugin)> {
+        let mut wasi = WasiCtx::builder();
+        for (key, value) in &self.grants.env {
+            wasi.env(key, value);
+        }
+        for dir in &self.grants.read_dirs {
+            let guest_path = dir.to_string_lossy();
+            wasi.preopened_dir(dir, guest_path.as_ref(), DirPerms::READ, FilePerms::READ)
+                .map_err(|error| {
+                    anyhow::anyhow!("failed to mount {} for the plugin: {error}", dir.display())
+                })?;
+        }
+
+        let runtime = &self.runtime;
+        let state = PluginState {
+            plugin: self.name.clone(),
+            http_hosts: self.grants.http_hosts.clone(),
+            http: runtime.http.clone(),
+            handle: runtime.handle.clone(),
+            wasi: wasi.build(),
+            table: ResourceTable::new(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(runtime.max_memory_bytes)
+                .build(),
+        };
+        let mut store = Store::new(&runtime.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(runtime.fuel)?;
+        let instance = bindings::Plugin::instantiate(&mut store, &self.component, &runtime.linker)
+            .map_err(|error| anyhow::anyhow!("failed to instantiate: {error}"))?;
+        Ok((store, instance))
+    }
+
+    /// Run one of the plugin's tools. Blocks; call from a blocking thread.
+    pub fn call_tool(
+        &self,
+        name: &str,
+        arguments: &str,
+    ) -> anyhow::Result<std::result::Result<String, String>> {
+        let (mut store, instance) = self.instantiate()?;
+        instance
+            .call_call_tool(&mut store, name, arguments)
+            .map_err(|error| anyhow::anyhow!("plugin '{}' trapped: {error}", self.name))
+    }
+
+    /// Blocks; call from a blocking thread.
+    fn filter_message(&self, message: &types::Message) -> anyhow::Result<FilterAction> {
+        let (mut store, instance) = self.instantiate()?;
+        instance
+            .call_filter_message(&mut store, message)
+            .map_err(|error| anyhow::anyhow!("plugin '{}' trapped: {error}", self.name))
+    }
+}
+
+/// The tool as exposed to the LLM. Unparseable schemas fall back to an
+/// open object so the tool stays usable.
+fn tool_spec(plugin: &str, tool: types::Tool) -> PluginToolSpec {
+    let parameters = serde_json::from_str(&tool.parameters).unwrap_or_else(|error| {
+        tracing::warn!(%plugin, tool = %tool.name, %error, "plugin tool has an invalid parameter schema");
+        serde_json::json!({"type": "object"})
+    });
+    PluginToolSpec {
+        tool_name: crate::openapi::tool_name(plugin, &tool.name),
+        name: tool.name,
+        description: tool.description,
+        parameters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_granted_hosts_are_reachable() {
+        let hosts = vec!["api.example.com".to_string(), "*.internal.dev".to_string()];
+        let allowed = |url: &str| host_allowed(&reqwest::Url::parse(url).unwrap(), &hosts);
+
+        assert!(allowed("https://api.example.com/v1"));
+        assert!(allowed("https://API.example.com/v1"));
+        assert!(allowed("http://internal.dev/"));
+        assert!(allowed("https://metrics.internal.dev/"));
+        assert!(!allowed("https://example.com/"));
+        assert!(!allowed("https://api.example.com.evil.io/"));
+        assert!(!allowed("https://evilinternal.dev/"));
+        assert!(!allowed("ftp://api.example.com/"));
+        assert!(!host_allowed(
+            &reqwest::Url::parse("https://api.example.com").unwrap(),
+            &[]
+        ));
+    }
+
+    #[test]
+    fn tool_specs_are_namespaced_by_plugin() {
+        let spec = tool_spec(
+            "invoice-tools",
+            types::Tool {
+                name: "lookup invoice".into(),
+                description: "Find an invoice".into(),
+                parameters: r#"{"type":"object","properties":{"id":{"type":"string"}}}"#.into(),
+            },
+        );
+        assert_eq!(spec.tool_name, "invoice-tools_lookup_invoice");
+        assert_eq!(spec.name, "lookup invoice");
+        assert_eq!(spec.parameters["properties"]["id"]["type"], "string");
+
+        let spec = tool_spec(
+            "broken",
+            types::Tool {
+                name: "t".into(),
+                description: String::new(),
+                parameters: "not json".into(),
+            },
+        );
+        assert_eq!(spec.parameters, serde_json::json!({"type": "object"}));
+    }
+}
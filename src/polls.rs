@@ -0,0 +1,602 @@
+//! Button polls: the poll tool posts a question with one button per option,
+//! clicks are recorded as votes, and when the poll closes the tally is posted
+//! back in its channel.
+//!
+//! Polls and votes are stored in the `polls` and `poll_votes` tables, and a
+//! polling loop closes them, so a poll outlives restarts: one whose deadline
+//! passed while the agent was down is reported on the next check. Each voter
+//! has one vote per poll; clicking another option moves it.
+
+use crate::conversation::{ChannelStore, ConversationLogger};
+use crate::db::{Column, SqlPool, with_pool};
+use crate::error::Result;
+use crate::{AgentDeps, InboundMessage, MessageContent, OutboundResponse};
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+/// Prefix of the button IDs a poll posts: `poll:{poll_id}:{option_index}`.
+const ACTION_PREFIX: &str = "poll:";
+
+/// How often the loop checks for polls to close.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Attempts to post results before a poll is closed without them.
+const MAX_ATTEMPTS: i64 = 5;
+
+/// Longest button label; Slack's limit, Discord's is 80.
+const MAX_LABEL_CHARS: usize = 75;
+
+/// Most options a poll can have: Discord fits five rows of five buttons, but
+/// more than ten buttons is hard to read.
+pub const MAX_OPTIONS: usize = 10;
+
+/// A stored poll.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelPoll {
+    pub id: String,
+    pub channel_id: String,
+    pub question: String,
+    pub options: Vec<String>,
+    pub closes_at: DateTime<Utc>,
+}
+
+/// A vote someone cast, for data export.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PollVote {
+    pub question: String,
+    pub option: String,
+}
+
+impl ChannelPoll {
+    /// The poll message: plain text plus one button per option for Discord
+    /// and a Block Kit actions block for Slack.
+    pub fn message(&self) -> OutboundResponse {
+        let closes = self
+            .closes_at
+            .with_timezone(&chrono::Local)
+            .format("%a %Y-%m-%d %H:%M");
+        let mut text = format!("**Poll:** {}\n", self.question);
+        for (index, option) in self.options.iter().enumerate() {
+            let _ = writeln!(text, "{}. {option}", index + 1);
+        }
+        let _ = write!(text, "Voting closes {closes}.");
+
+        let buttons: Vec<crate::Button> = self
+            .options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| crate::Button {
+                label: button_label(option),
+                custom_id: Some(self.action_id(index)),
+                style: crate::ButtonStyle::Primary,
+                url: None,
+            })
+            .collect();
+        let interactive_elements = buttons
+            .chunks(5)
+            .map(|row| crate::InteractiveElements::Buttons {
+                buttons: row.to_vec(),
+            })
+            .collect();
+
+        let slack_buttons: Vec<serde_json::Value> = self
+            .options
+            .iter()
+            .enumerate()
+            .map(|(index, option)| {
+                serde_json::json!({
+                    "type": "button",
+                    "text": { "type": "plain_text", "text": button_label(option) },
+                    "action_id": self.action_id(index),
+                    "value": index.to_string(),
+                })
+            })
+            .collect();
+        let blocks = vec![
+            serde_json::json!({
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": format!("*Poll:* {}", self.question) },
+            }),
+            serde_json::json!({ "type": "actions", "elements": slack_buttons }),
+            serde_json::json!({
+                "type": "context",
+                "elements": [{ "type": "mrkdwn", "text": format!("Voting closes {closes}.") }],
+            }),
+        ];
+
+        OutboundResponse::RichMessage {
+            text,
+            blocks,
+            cards: Vec::new(),
+            interactive_elements,
+            poll: None,
+        }
+    }
+
+    fn action_id(&self, index: usize) -> String {
+        format!("{ACTION_PREFIX}{}:{index}", self.id)
+    }
+}
+
+fn button_label(option: &str) -> String {
+    match option.char_indices().nth(MAX_LABEL_CHARS) {
+        Some((end, _)) => option[..end].to_string(),
+        None => option.to_string(),
+    }
+}
+
+/// Vote counts per option, in option order.
+pub fn tally(options: &[String], votes: &[i64]) -> Vec<u64> {
+    let mut counts = vec![0; options.len()];
+    for &vote in votes {
+        if let Some(count) = usize::try_from(vote)
+            .ok()
+            .and_then(|index| counts.get_mut(index))
+        {
+            *count += 1;
+        }
+    }
+    counts
+}
+
+/// The results message for a closed poll.
+pub fn render_results(poll: &ChannelPoll, counts: &[u64]) -> String {
+    let total: u64 = counts.iter().sum();
+    let mut text = format!("**Poll closed:** {}\n", poll.question);
+    if total == 0 {
+        text.push_str("No votes were cast.");
+        return text;
+    }
+
+    for (option, &count) in poll.options.iter().zip(counts) {
+        let percent = count * 100 / total;
+        let votes = if count == 1 { "vote" } else { "votes" };
+        let _ = writeln!(text, "- {option}: {count} {votes} ({percent}%)");
+    }
+    let top = counts.iter().copied().max().unwrap_or_default();
+    let leaders: Vec<&str> = poll
+        .options
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| **count == top)
+        .map(|(option, _)| option.as_str())
+        .collect();
+    let voters = if total == 1 { "vote" } else { "votes" };
+    match leaders.as_slice() {
+        [winner] => {
+            let _ = write!(text, "{total} {voters} in total. Result: **{winner}**");
+        }
+        tied => {
+            let _ = write!(text, "{total} {voters} in total. Tie: {}", tied.join(", "));
+        }
+    }
+    text
+}
+
+/// Poll storage (SQLite or Postgres).
+#[derive(Debug, Clone)]
+pub struct PollStore {
+    pool: SqlPool,
+}
+
+impl PollStore {
+    pub fn new(pool: SqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a new poll.
+    pub async fn create(&self, poll: &ChannelPoll) -> Result<()> {
+        let options = serde_json::to_string(&poll.options).context("failed to encode options")?;
+        let query_str = format!(
+            "INSERT INTO polls (id, channel_id, question, options, closes_at) \
+             VALUES ($1, $2, $3, $4, {})",
+            self.pool.timestamp_placeholder(5)
+        );
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(&query_str)
+                .bind(&poll.id)
+                .bind(&poll.channel_id)
+                .bind(&poll.question)
+                .bind(&options)
+                .bind(self.pool.timestamp_param(poll.closes_at))
+                .execute(pool)
+                .await
+                .map(drop)
+        })
+        .context("failed to save poll")?;
+
+        Ok(())
+    }
+
+    /// An open poll in `channel_id`.
+    pub async fn open_in_channel(&self, id: &str, channel_id: &str) -> Result<Option<ChannelPoll>> {
+        let poll = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT id, channel_id, question, options, closes_at FROM polls \
+                 WHERE id = $1 AND channel_id = $2 AND status = 'open'",
+            )
+            .bind(id)
+            .bind(channel_id)
+            .fetch_optional(pool)
+            .await
+            .and_then(|row| row.as_ref().map(poll_from_row).transpose())
+        })
+        .context("failed to load poll")?;
+
+        Ok(poll)
+    }
+
+    /// Open polls whose deadline is at or before `now`, oldest first.
+    pub async fn due(&self, now: DateTime<Utc>) -> Result<Vec<ChannelPoll>> {
+        let query_str = format!(
+            "SELECT id, channel_id, question, options, closes_at FROM polls \
+             WHERE status = 'open' AND closes_at <= {} \
+             ORDER BY closes_at LIMIT 20",
+            self.pool.timestamp_placeholder(1)
+        );
+        let polls = with_pool!(&self.pool, |pool| {
+            sqlx::query(&query_str)
+                .bind(self.pool.timestamp_param(now))
+                .fetch_all(pool)
+                .await
+                .and_then(|rows| rows.iter().map(poll_from_row).collect())
+        })
+        .context("failed to load due polls")?;
+
+        Ok(polls)
+    }
+
+    /// Record `voter_id`'s vote, replacing an earlier one. Returns `false`
+    /// when the poll isn't open.
+    pub async fn vote(&self, poll_id: &str, voter_id: &str, option: i64) -> Result<bool> {
+        let recorded = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "INSERT INTO poll_votes (poll_id, voter_id, option_index) \
+                 SELECT $1, $2, $3 WHERE EXISTS \
+                 (SELECT 1 FROM polls WHERE id = $1 AND status = 'open') \
+                 ON CONFLICT (poll_id, voter_id) DO UPDATE SET \
+                 option_index = excluded.option_index, voted_at = CURRENT_TIMESTAMP",
+            )
+            .bind(poll_id)
+            .bind(voter_id)
+            .bind(option)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+        })
+        .context("failed to record vote")?;
+
+        Ok(recorded > 0)
+    }
+
+    /// The option index of every vote on a poll.
+    pub async fn votes(&self, poll_id: &str) -> Result<Vec<i64>> {
+        let votes = with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar("SELECT option_index FROM poll_votes WHERE poll_id = $1")
+                .bind(poll_id)
+                .fetch_all(pool)
+                .await
+        })
+        .context("failed to load votes")?;
+
+        Ok(votes)
+    }
+
+    /// Every vote `voter_id` cast.
+    pub async fn votes_by(&self, voter_id: &str) -> Result<Vec<PollVote>> {
+        let rows: Vec<(String, String, i64)> = with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT polls.question, polls.options, poll_votes.option_index \
+                 FROM poll_votes JOIN polls ON polls.id = poll_votes.poll_id \
+                 WHERE poll_votes.voter_id = $1 ORDER BY poll_votes.voted_at",
+            )
+            .bind(voter_id)
+            .fetch_all(pool)
+            .await
+        })
+        .with_context(|| format!("failed to load votes from {voter_id}"))?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(question, options, index)| {
+                let options: Vec<String> = serde_json::from_str(&options).unwrap_or_default();
+                let option = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| options.into_iter().nth(index))
+                    .unwrap_or_default();
+                PollVote { question, option }
+            })
+            .collect())
+    }
+
+    /// Delete every vote `voter_id` cast. Returns how many were deleted.
+    pub async fn delete_votes_by(&self, voter_id: &str) -> Result<u64> {
+        let deleted = with_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM poll_votes WHERE voter_id = $1")
+                .bind(voter_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })
+        .with_context(|| format!("failed to delete votes from {voter_id}"))?;
+
+        Ok(deleted)
+    }
+
+    /// Move an open poll's deadline to now, so the loop reports it on its
+    /// next check.
+    pub async fn close_early(&self, id: &str, channel_id: &str) -> Result<bool> {
+        let updated = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "UPDATE polls SET closes_at = CURRENT_TIMESTAMP \
+                 WHERE id = $1 AND channel_id = $2 AND status = 'open'",
+            )
+            .bind(id)
+            .bind(channel_id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+        })
+        .context("failed to close poll")?;
+
+        Ok(updated > 0)
+    }
+
+    pub async fn mark_closed(&self, id: &str) -> Result<()> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query("UPDATE polls SET status = 'closed' WHERE id = $1")
+                .bind(id)
+                .execute(pool)
+                .await
+                .map(drop)
+        })
+        .context("failed to mark poll closed")?;
+
+        Ok(())
+    }
+
+    /// Count a failed attempt to post results. After `MAX_ATTEMPTS`, or right
+    /// away when `permanent`, the poll is closed without them.
+    pub async fn record_failure(&self, id: &str, permanent: bool) -> Result<()> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "UPDATE polls SET attempts = attempts + 1, \
+                 status = CASE WHEN $2 OR attempts + 1 >= $3 THEN 'closed' ELSE status END \
+                 WHERE id = $1",
+            )
+            .bind(id)
+            .bind(permanent)
+            .bind(MAX_ATTEMPTS)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
+        .context("failed to record poll failure")?;
+
+        Ok(())
+    }
+}
+
+fn poll_from_row<R>(row: &R) -> std::result::Result<ChannelPoll, sqlx::Error>
+where
+    R: sqlx::Row,
+    for<'c> &'c str: sqlx::ColumnIndex<R>,
+    String: Column<R::Database>,
+    DateTime<Utc>: Column<R::Database>,
+{
+    let options: String = row.try_get("options")?;
+    Ok(ChannelPoll {
+        id: row.try_get("id")?,
+        channel_id: row.try_get("channel_id")?,
+        question: row.try_get("question")?,
+        options: serde_json::from_str(&options).map_err(|error| sqlx::Error::ColumnDecode {
+            index: "options".into(),
+            source: Box::new(error),
+        })?,
+        closes_at: row.try_get("closes_at")?,
+    })
+}
+
+/// `(poll_id, option_index)` when `message` is a click on a poll button.
+fn parse_vote(message: &InboundMessage) -> Option<(&str, i64)> {
+    let MessageContent::Interaction { action_id, .. } = &message.content else {
+        return None;
+    };
+    let (poll_id, index) = action_id.strip_prefix(ACTION_PREFIX)?.rsplit_once(':')?;
+    Some((poll_id, index.parse().ok()?))
+}
+
+/// Handle `message` if it's a poll vote, recording it in the background.
+/// Returns whether it was one; votes aren't passed on to the channel.
+pub fn record_vote(pool: &SqlPool, message: &InboundMessage) -> bool {
+    let Some((poll_id, option)) = parse_vote(message) else {
+        return false;
+    };
+    let store = PollStore::new(pool.clone());
+    let poll_id = poll_id.to_string();
+    let voter_id = message.sender_id.clone();
+    tokio::spawn(async move {
+        match store.vote(&poll_id, &voter_id, option).await {
+            Ok(true) => tracing::debug!(%poll_id, %voter_id, option, "poll vote recorded"),
+            Ok(false) => tracing::debug!(%poll_id, %voter_id, "vote on a closed poll ignored"),
+            Err(error) => tracing::warn!(%error, %poll_id, "failed to record poll vote"),
+        }
+    });
+    true
+}
+
+/// Spawn the loop that closes polls and posts their results.
+///
+/// Runs until the returned JoinHandle is dropped or aborted.
+pub fn spawn_poll_loop(deps: AgentDeps) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move { run_poll_loop(&deps).await })
+}
+
+async fn run_poll_loop(deps: &AgentDeps) {
+    let Some(messaging) = deps.messaging_manager.clone() else {
+        tracing::debug!("no messaging adapters, poll loop not started");
+        return;
+    };
+    tracing::info!("poll loop started");
+    let store = PollStore::new(deps.sql_pool.clone());
+    let channel_store = ChannelStore::new(deps.sql_pool.clone());
+    let conversation_logger =
+        ConversationLogger::new(deps.sql_pool.clone()).batched(deps.conversation_writes.clone());
+
+    loop {
+        match store.due(Utc::now()).await {
+            Ok(polls) => {
+                for poll in polls {
+                    let channel = channel_store.get(&poll.channel_id).await;
+                    let target = match &channel {
+                        Ok(Some(info)) => {
+                            crate::tools::send_message_to_another_channel::resolve_broadcast_target(
+                                info,
+                            )
+                        }
+                        _ => None,
+                    };
+                    let Some((adapter, target)) = target else {
+                        tracing::warn!(
+                            poll_id = %poll.id,
+                            channel_id = %poll.channel_id,
+                            "can't post poll results: channel not found or not a broadcast target"
+                        );
+                        if let Err(error) = store.record_failure(&poll.id, channel.is_ok()).await {
+                            tracing::error!(%error, "failed to record poll failure");
+                        }
+                        continue;
+                    };
+
+                    let votes = match store.votes(&poll.id).await {
+                        Ok(votes) => votes,
+                        Err(error) => {
+                            tracing::warn!(%error, poll_id = %poll.id, "failed to load poll votes");
+                            continue;
+                        }
+                    };
+                    let text = render_results(&poll, &tally(&poll.options, &votes));
+                    let sent = messaging
+                        .broadcast(&adapter, &target, OutboundResponse::Text(text.clone()))
+                        .await;
+                    let recorded = match sent {
+                        Ok(()) => {
+                            conversation_logger
+                                .log_bot_message(&poll.channel_id.as_str().into(), &text);
+                            tracing::info!(poll_id = %poll.id, votes = votes.len(), "poll closed");
+                            store.mark_closed(&poll.id).await
+                        }
+                        Err(error) => {
+                            tracing::warn!(%error, poll_id = %poll.id, "failed to post poll results");
+                            store.record_failure(&poll.id, false).await
+                        }
+                    };
+                    if let Err(error) = recorded {
+                        tracing::error!(%error, poll_id = %poll.id, "failed to update poll");
+                    }
+                }
+            }
+            Err(error) => tracing::warn!(%error, "failed to load due polls"),
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(closes_at: DateTime<Utc>) -> ChannelPoll {
+        ChannelPoll {
+            id: "p1".into(),
+            channel_id: "discord:1:2".into(),
+            question: "Deploy Friday?".into(),
+            options: vec!["Yes".into(), "No".into(), "Next week".into()],
+            closes_at,
+        }
+    }
+
+    #[test]
+    fn results_report_the_winner_or_a_tie() {
+        let poll = poll(Utc::now());
+        let counts = tally(&poll.options, &[0, 0, 1, 7, -1]);
+        assert_eq!(counts, vec![2, 1, 0]);
+        assert_eq!(
+            render_results(&poll, &counts),
+            "**Poll closed:** Deploy Friday?\n\
+             - Yes: 2 votes (66%)\n\
+             - No: 1 vote (33%)\n\
+             - Next week: 0 votes (0%)\n\
+             3 votes in total. Result: **Yes**"
+        );
+        assert!(render_results(&poll, &[1, 1, 0]).ends_with("2 votes in total. Tie: Yes, No"));
+        assert!(render_results(&poll, &[0, 0, 0]).ends_with("No votes were cast."));
+    }
+
+    #[tokio::test]
+    async fn votes_count_once_per_voter_until_the_poll_closes() {
+        let memory_store = crate::memory::MemoryStore::connect_in_memory().await;
+        let pool = memory_store.pool().clone();
+        let store = PollStore::new(pool.clone());
+        let now = Utc::now();
+        let poll = poll(now + chrono::Duration::hours(1));
+        store.create(&poll).await.expect("create");
+
+        let click = |sender_id: &str, option: usize| InboundMessage {
+            id: uuid::Uuid::new_v4().to_string(),
+            source: "discord".into(),
+            conversation_id: poll.channel_id.clone(),
+            sender_id: sender_id.into(),
+            agent_id: None,
+            content: MessageContent::Interaction {
+                action_id: poll.action_id(option),
+                block_id: None,
+                values: Vec::new(),
+                label: None,
+                message_ts: None,
+            },
+            timestamp: now,
+            metadata: Default::default(),
+            formatted_author: None,
+        };
+        assert_eq!(parse_vote(&click("42", 2)), Some(("p1", 2)));
+        assert!(store.vote("p1", "42", 0).await.expect("vote"));
+        assert!(store.vote("p1", "42", 1).await.expect("vote"));
+        assert!(store.vote("p1", "7", 1).await.expect("vote"));
+        assert_eq!(
+            tally(&poll.options, &store.votes("p1").await.expect("votes")),
+            vec![0, 2, 0]
+        );
+        assert_eq!(
+            store.votes_by("42").await.expect("votes"),
+            vec![PollVote {
+                question: "Deploy Friday?".into(),
+                option: "No".into(),
+            }]
+        );
+
+        assert!(store.due(now).await.expect("due").is_empty());
+        assert!(store.close_early("p1", "discord:1:2").await.expect("close"));
+        let due = store
+            .due(now + chrono::Duration::seconds(5))
+            .await
+            .expect("due");
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].options, poll.options);
+
+        store.mark_closed("p1").await.expect("mark closed");
+        assert!(!store.vote("p1", "9", 0).await.expect("vote"));
+        assert!(
+            store
+                .open_in_channel("p1", "discord:1:2")
+                .await
+                .expect("load")
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,457 @@
+//! Startup preflight checks (`[preflight]`).
+//!
+//! Before anything binds or any agent starts, [`run`] checks what would
+//! otherwise only fail on the first message: that every model the config
+//! routes to has a configured provider, that the database accepts
+//! connections, that each enabled messaging platform is in the build and
+//! accepts its token, and that at least one LLM provider answers. Each
+//! failure names what to change, and startup stops with all of them listed
+//! rather than the first.
+
+use crate::config::{Config, MessagingConfig, ProviderConfig};
+use crate::db::DatabaseBackend;
+
+#[cfg(feature = "sql-postgres")]
+use sqlx::Connection as _;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+#[cfg(feature = "sql-postgres")]
+use std::future::Future;
+#[cfg(feature = "sql-postgres")]
+use std::str::FromStr as _;
+use std::time::Duration;
+
+/// Preflight settings (instance-level, under `[preflight]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreflightConfig {
+    pub enabled: bool,
+    /// How long each network check may take.
+    pub timeout_secs: u64,
+}
+
+impl Default for PreflightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout_secs: 10,
+        }
+    }
+}
+
+/// A check that failed, and what to do about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// What was checked: "config", "database", "llm", or a platform name.
+    pub check: String,
+    pub problem: String,
+    pub remedy: String,
+}
+
+impl Failure {
+    fn new(check: &str, problem: impl Into<String>, remedy: impl Into<String>) -> Self {
+        Self {
+            check: check.to_string(),
+            problem: problem.into(),
+            remedy: remedy.into(),
+        }
+    }
+}
+
+/// Run every check. LLM checks are skipped when no provider is configured,
+/// since startup then goes into setup mode.
+pub async fn run(config: &Config) -> Vec<Failure> {
+    let timeout = Duration::from_secs(config.preflight.timeout_secs);
+    let http_client = match reqwest::Client::builder().timeout(timeout).build() {
+        Ok(client) => client,
+        Err(error) => {
+            return vec![Failure::new(
+                "preflight",
+                format!("can't build an HTTP client: {error}"),
+                "check the system's TLS certificates",
+            )];
+        }
+    };
+    let anthropic_oauth = crate::auth::credentials_path(&config.instance_dir).exists();
+    let has_providers = config.llm.has_any_key() || anthropic_oauth;
+
+    let mut failures: Vec<Failure> = crate::messaging::missing_platforms(&config.messaging)
+        .into_iter()
+        .map(|platform| {
+            Failure::new(
+                platform,
+                "it's enabled, but this build doesn't include it",
+                format!(
+                    "rebuild with `--features {platform}`, or set [messaging.{platform}] \
+                     enabled = false"
+                ),
+            )
+        })
+        .collect();
+    if has_providers {
+        failures.extend(unconfigured_providers(
+            &crate::llm::discovery::configured_models(config),
+            &config.llm.providers,
+            anthropic_oauth,
+        ));
+    }
+
+    let (database, messaging, llm) = tokio::join!(
+        check_database(config, timeout),
+        check_messaging(&config.messaging, &http_client),
+        async {
+            if has_providers {
+                check_llm(&config.llm.providers, &http_client).await
+            } else {
+                None
+            }
+        },
+    );
+    failures.extend(database);
+    failures.extend(messaging);
+    failures.extend(llm);
+    failures
+}
+
+/// A failure per provider that routed models name but `providers` doesn't
+/// configure. Anthropic counts as configured when OAuth credentials exist.
+fn unconfigured_providers(
+    models: &BTreeSet<String>,
+    providers: &HashMap<String, ProviderConfig>,
+    anthropic_oauth: bool,
+) -> Vec<Failure> {
+    let mut unconfigured: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for model in models {
+        let provider = crate::llm::routing::provider_from_model(model).to_lowercase();
+        if providers.contains_key(&provider) || (provider == "anthropic" && anthropic_oauth) {
+            continue;
+        }
+        unconfigured.entry(provider).or_default().push(model);
+    }
+    unconfigured
+        .into_iter()
+        .map(|(provider, models)| {
+            Failure::new(
+                "config",
+                format!(
+                    "{} routed to provider `{provider}`, which isn't configured",
+                    models.join(", ")
+                ),
+                format!(
+                    "set `{provider}_key` under [llm], add [llm.provider.{provider}], or route \
+                     to a configured provider"
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Fail with "timed out" when `check` takes longer than `timeout`.
+#[cfg(feature = "sql-postgres")]
+async fn within(
+    timeout: Duration,
+    check: impl Future<Output = Result<(), String>>,
+) -> Result<(), String> {
+    tokio::time::timeout(timeout, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {}s", timeout.as_secs())))
+}
+
+/// Postgres must accept a connection; each agent's SQLite data directory
+/// must be writable.
+async fn check_database(
+    config: &Config,
+    #[cfg_attr(not(feature = "sql-postgres"), allow(unused_variables))] timeout: Duration,
+) -> Vec<Failure> {
+    match (config.database.backend, &config.database.url) {
+        #[cfg(feature = "sql-postgres")]
+        (DatabaseBackend::Postgres, Some(url)) => {
+            let connect = async {
+                let options = sqlx::postgres::PgConnectOptions::from_str(url)
+                    .map_err(|error| format!("can't parse the URL: {error}"))?;
+                let mut connection = sqlx::postgres::PgConnection::connect_with(&options)
+                    .await
+                    .map_err(|error| crate::logging::redact_secrets(&error.to_string()))?;
+                connection.ping().await.map_err(|error| error.to_string())?;
+                connection.close().await.map_err(|error| error.to_string())
+            };
+            match within(timeout, connect).await {
+                Ok(()) => Vec::new(),
+                Err(error) => vec![Failure::new(
+                    "database",
+                    format!("can't connect to Postgres: {error}"),
+                    "check [database] url, and that the server is up and accepts connections \
+                     from this host",
+                )],
+            }
+        }
+        #[cfg(not(feature = "sql-postgres"))]
+        (DatabaseBackend::Postgres, Some(_)) => vec![Failure::new(
+            "database",
+            "this build doesn't include Postgres",
+            "rebuild with `--features sql-postgres`, or set [database] backend = \"sqlite\"",
+        )],
+        _ => config
+            .resolve_agents()
+            .iter()
+            .filter_map(|agent| {
+                let probe = agent.data_dir.join(".preflight");
+                let writable = std::fs::create_dir_all(&agent.data_dir)
+                    .and_then(|()| std::fs::write(&probe, b""))
+                    .and_then(|()| std::fs::remove_file(&probe));
+                writable.err().map(|error| {
+                    Failure::new(
+                        "database",
+                        format!(
+                            "agent `{}` can't write its data directory {}: {error}",
+                            agent.id,
+                            agent.data_dir.display()
+                        ),
+                        "make the directory writable by this user, or mount a persistent volume \
+                         there",
+                    )
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Ask each enabled platform who its token belongs to.
+async fn check_messaging(
+    messaging: &MessagingConfig,
+    http_client: &reqwest::Client,
+) -> Vec<Failure> {
+    let mut failures = Vec::new();
+
+    if let Some(discord) = messaging.discord.as_ref().filter(|discord| discord.enabled) {
+        let request = http_client
+            .get("https://discord.com/api/v10/users/@me")
+            .header("Authorization", format!("Bot {}", discord.token));
+        if let Err(error) = send(request).await {
+            failures.push(Failure::new(
+                "discord",
+                format!("can't verify the bot token: {error}"),
+                "reset the token under Bot in the Discord developer portal and set it as \
+                 [messaging.discord] token",
+            ));
+        }
+    }
+
+    if let Some(slack) = messaging.slack.as_ref().filter(|slack| slack.enabled) {
+        let request = http_client
+            .post("https://slack.com/api/auth.test")
+            .bearer_auth(&slack.bot_token);
+        if let Err(error) = send_ok(request).await {
+            failures.push(Failure::new(
+                "slack",
+                format!("can't verify the bot token: {error}"),
+                "copy the Bot User OAuth Token (xoxb-...) from OAuth & Permissions in the Slack \
+                 app settings into [messaging.slack] bot_token",
+            ));
+        }
+        if !slack.app_token.starts_with("xapp-") {
+            failures.push(Failure::new(
+                "slack",
+                "the app token isn't an app-level token",
+                "generate an app-level token (xapp-...) with connections:write under Basic \
+                 Information in the Slack app settings and set it as [messaging.slack] app_token",
+            ));
+        }
+    }
+
+    if let Some(telegram) = messaging
+        .telegram
+        .as_ref()
+        .filter(|telegram| telegram.enabled)
+    {
+        let request = http_client.get(format!(
+            "https://api.telegram.org/bot{}/getMe",
+            telegram.token
+        ));
+        if let Err(error) = send_ok(request).await {
+            failures.push(Failure::new(
+                "telegram",
+                format!("can't verify the bot token: {error}"),
+                "get the bot's token from @BotFather and set it as [messaging.telegram] token",
+            ));
+        }
+    }
+
+    if let Some(twitch) = messaging.twitch.as_ref().filter(|twitch| twitch.enabled) {
+        let token = twitch
+            .oauth_token
+            .strip_prefix("oauth:")
+            .unwrap_or(&twitch.oauth_token);
+        let request = http_client
+            .get("https://id.twitch.tv/oauth2/validate")
+            .header("Authorization", format!("OAuth {token}"));
+        if let Err(error) = send(request).await {
+            failures.push(Failure::new(
+                "twitch",
+                format!("can't verify the OAuth token: {error}"),
+                "generate a new chat token for the bot account and set it as \
+                 [messaging.twitch] oauth_token",
+            ));
+        }
+    }
+
+    failures
+}
+
+/// Send `request` and require a success status. Errors leave out the URL,
+/// which carries the token for Telegram.
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|error| error.without_url().to_string())?;
+    let status = response.status();
+    if status.is_success() {
+        Ok(response)
+    } else {
+        Err(format!("HTTP {status}"))
+    }
+}
+
+/// [`send`] for APIs that answer `{"ok": false, ...}` with a 200.
+async fn send_ok(request: reqwest::RequestBuilder) -> Result<(), String> {
+    let body: serde_json::Value = send(request)
+        .await?
+        .json()
+        .await
+        .map_err(|error| error.without_url().to_string())?;
+    if body["ok"].as_bool() == Some(true) {
+        return Ok(());
+    }
+    let reason = body["error"]
+        .as_str()
+        .or_else(|| body["description"].as_str())
+        .unwrap_or("no reason given");
+    Err(reason.to_string())
+}
+
+/// At least one provider must list its models. Providers answering nothing
+/// means every conversation would fail.
+async fn check_llm(
+    providers: &HashMap<String, ProviderConfig>,
+    http_client: &reqwest::Client,
+) -> Option<Failure> {
+    // OAuth tokens can't list models, so those providers are left out.
+    let listable: Vec<_> = providers
+        .iter()
+        .filter(|(_, provider)| {
+            crate::llm::anthropic::detect_auth_path(&provider.api_key)
+                != crate::llm::anthropic::AnthropicAuthPath::OAuthToken
+        })
+        .collect();
+    if listable.is_empty() {
+        return None;
+    }
+    let results =
+        futures::future::join_all(listable.into_iter().map(|(provider_id, provider)| async {
+            let result =
+                crate::llm::discovery::list_models(http_client, provider_id, provider).await;
+            (provider_id, result)
+        }))
+        .await;
+
+    let mut errors = Vec::new();
+    for (provider_id, result) in results {
+        match result {
+            Ok(_) => return None,
+            Err(error) => errors.push(format!(
+                "{provider_id}: {}",
+                crate::logging::redact_secrets(&error.to_string())
+            )),
+        }
+    }
+    errors.sort();
+    Some(Failure::new(
+        "llm",
+        format!("no LLM provider answered ({})", errors.join("; ")),
+        "check the provider keys and base URLs under [llm], and that this host can reach them",
+    ))
+}
+
+/// The error startup fails with.
+pub fn render(failures: &[Failure]) -> String {
+    let mut lines = vec![match failures.len() {
+        1 => "preflight found a problem:".to_string(),
+        count => format!("preflight found {count} problems:"),
+    }];
+    for failure in failures {
+        lines.push(format!("- {}: {}", failure.check, failure.problem));
+        lines.push(format!("  fix: {}", failure.remedy));
+    }
+    lines.push("Set `enabled = false` under [preflight] to start without these checks.".into());
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApiType;
+
+    #[test]
+    fn routed_models_without_a_configured_provider_fail_per_provider() {
+        let models: BTreeSet<String> = [
+            "claude-sonnet-4",
+            "anthropic/claude-haiku-4-5",
+            "openrouter/openai/gpt-4.1",
+            "groq/llama-3.3-70b",
+            "groq/llama-3.1-8b",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+        let providers = HashMap::from([(
+            "openrouter".to_string(),
+            ProviderConfig {
+                api_type: ApiType::OpenAiCompletions,
+                base_url: "https://openrouter.ai/api".into(),
+                api_key: "key".into(),
+                name: None,
+            },
+        )]);
+
+        let failures = unconfigured_providers(&models, &providers, false);
+        assert_eq!(failures.len(), 2);
+        assert_eq!(
+            failures[0].problem,
+            "anthropic/claude-haiku-4-5, claude-sonnet-4 routed to provider `anthropic`, which \
+             isn't configured"
+        );
+        assert_eq!(
+            failures[1].problem,
+            "groq/llama-3.1-8b, groq/llama-3.3-70b routed to provider `groq`, which isn't \
+             configured"
+        );
+
+        let failures = unconfigured_providers(&models, &providers, true);
+        assert_eq!(failures.len(), 1);
+        assert!(failures[0].remedy.contains("[llm.provider.groq]"));
+    }
+
+    #[test]
+    fn render_lists_every_failure_with_its_fix() {
+        let failures = vec![
+            Failure::new(
+                "database",
+                "can't connect to Postgres: refused",
+                "check the url",
+            ),
+            Failure::new(
+                "discord",
+                "can't verify the bot token: HTTP 401",
+                "reset it",
+            ),
+        ];
+        assert_eq!(
+            render(&failures),
+            "preflight found 2 problems:\n\
+             - database: can't connect to Postgres: refused\n\
+             \x20 fix: check the url\n\
+             - discord: can't verify the bot token: HTTP 401\n\
+             \x20 fix: reset it\n\
+             Set `enabled = false` under [preflight] to start without these checks."
+        );
+    }
+}
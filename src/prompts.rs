@@ -1,4 +1,5 @@
 pub mod engine;
+pub mod stack;
 pub mod text;
 
 pub use engine::{PromptEngine, SkillInfo};
@@ -2,13 +2,15 @@ use crate::error::Result;
 use anyhow::Context;
 use minijinja::{Environment, Value, context};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 /// Template engine for rendering system prompts with dynamic variables.
 ///
 /// Prompts are bundled in the binary as `include_str!` embedded templates.
-/// Language selection is done at initialization and templates are not
-/// reloadable at runtime (no file watching, no hot reload).
+/// Any of them can be overridden by a file in the instance's `prompts/`
+/// directory (see [`PromptEngine::with_overrides`]); the file watcher rebuilds
+/// the engine when an override changes.
 #[derive(Clone)]
 pub struct PromptEngine {
     /// The MiniJinja environment holding all templates for the configured language.
@@ -49,6 +51,9 @@ impl PromptEngine {
             crate::prompts::text::get("memory_persistence"),
         )?;
         env.add_template("ingestion", crate::prompts::text::get("ingestion"))?;
+        env.add_template("digest", crate::prompts::text::get("digest"))?;
+        env.add_template("alert_triage", crate::prompts::text::get("alert_triage"))?;
+        env.add_template("feed_summary", crate::prompts::text::get("feed_summary"))?;
         env.add_template("cortex_chat", crate::prompts::text::get("cortex_chat"))?;
         env.add_template(
             "cortex_profile",
@@ -86,6 +91,18 @@ impl PromptEngine {
             "fragments/system/truncation",
             crate::prompts::text::get("fragments/system/truncation"),
         )?;
+        env.add_template(
+            "fragments/system/duplicate_collapsed",
+            crate::prompts::text::get("fragments/system/duplicate_collapsed"),
+        )?;
+        env.add_template(
+            "fragments/system/artifact_reference",
+            crate::prompts::text::get("fragments/system/artifact_reference"),
+        )?;
+        env.add_template(
+            "fragments/system/unsupported_claims",
+            crate::prompts::text::get("fragments/system/unsupported_claims"),
+        )?;
         env.add_template(
             "fragments/system/worker_overflow",
             crate::prompts::text::get("fragments/system/worker_overflow"),
@@ -118,10 +135,18 @@ impl PromptEngine {
             "fragments/system/tool_syntax_correction",
             crate::prompts::text::get("fragments/system/tool_syntax_correction"),
         )?;
+        env.add_template(
+            "fragments/system/calculator_results",
+            crate::prompts::text::get("fragments/system/calculator_results"),
+        )?;
         env.add_template(
             "fragments/coalesce_hint",
             crate::prompts::text::get("fragments/coalesce_hint"),
         )?;
+        env.add_template(
+            "replies/over_budget",
+            crate::prompts::text::get("replies/over_budget"),
+        )?;
 
         Ok(Self {
             env: Arc::new(env),
@@ -129,6 +154,49 @@ impl PromptEngine {
         })
     }
 
+    /// Create an engine where files in `overrides_dir` replace bundled
+    /// templates of the same name, laid out like `prompts/en/` (e.g.
+    /// `channel.md.j2`, `fragments/worker_capabilities.md.j2`).
+    ///
+    /// Templates without an override file keep the bundled text. An override
+    /// that can't be read or doesn't parse is an error, so a bad edit never
+    /// replaces a working prompt.
+    pub fn with_overrides(language: &str, overrides_dir: &Path) -> anyhow::Result<Self> {
+        Self::with_override_dirs(language, &[overrides_dir])
+    }
+
+    /// Like [`PromptEngine::with_overrides`], with several override
+    /// directories layered in order: a file in a later one wins over the same
+    /// file in an earlier one. Used for canary prompt versions, which only
+    /// hold the templates they change.
+    pub fn with_override_dirs(language: &str, overrides_dirs: &[&Path]) -> anyhow::Result<Self> {
+        let mut engine = Self::new(language)?;
+        let env = Arc::get_mut(&mut engine.env).context("prompt engine env is shared")?;
+        let names: Vec<String> = env.templates().map(|(name, _)| name.to_string()).collect();
+        for overrides_dir in overrides_dirs.iter().filter(|dir| dir.is_dir()) {
+            for name in &names {
+                let path = overrides_dir.join(format!("{name}.md.j2"));
+                let source = match std::fs::read_to_string(&path) {
+                    Ok(source) => source,
+                    Err(error) if error.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(error) => {
+                        return Err(error).with_context(|| {
+                            format!("can't read prompt override {}", path.display())
+                        });
+                    }
+                };
+                // The environment only holds 'static sources (owned templates
+                // need minijinja's `loader` feature). Overrides are small and
+                // only re-read when their files change, so leaking them is
+                // bounded.
+                env.add_template(name.clone().leak(), source.leak())
+                    .with_context(|| format!("can't parse prompt override {}", path.display()))?;
+            }
+        }
+
+        Ok(engine)
+    }
+
     /// Render a template by name with the given context variables.
     ///
     /// # Arguments
@@ -167,10 +235,16 @@ impl PromptEngine {
     }
 
     /// Convenience method for rendering worker capabilities fragment.
+    #[allow(clippy::too_many_arguments)]
     pub fn render_worker_capabilities(
         &self,
         browser_enabled: bool,
         web_search_enabled: bool,
+        github_enabled: bool,
+        railway_enabled: bool,
+        http_apis: &[String],
+        sql_databases: &[String],
+        weather_enabled: bool,
         opencode_enabled: bool,
     ) -> Result<String> {
         self.render(
@@ -178,6 +252,11 @@ impl PromptEngine {
             context! {
                 browser_enabled => browser_enabled,
                 web_search_enabled => web_search_enabled,
+                github_enabled => github_enabled,
+                railway_enabled => railway_enabled,
+                http_apis => http_apis,
+                sql_databases => sql_databases,
+                weather_enabled => weather_enabled,
                 opencode_enabled => opencode_enabled,
             },
         )
@@ -273,6 +352,58 @@ impl PromptEngine {
         )
     }
 
+    /// Convenience method for rendering the marker left in place of a
+    /// collapsed near-duplicate message.
+    pub fn render_system_duplicate_collapsed(&self, preview: &str) -> Result<String> {
+        self.render(
+            "fragments/system/duplicate_collapsed",
+            context! {
+                preview => preview,
+            },
+        )
+    }
+
+    /// Convenience method for rendering the reference left in history in
+    /// place of a pasted code artifact.
+    pub fn render_system_artifact_reference(
+        &self,
+        number: i64,
+        kind: &str,
+        language: Option<&str>,
+        lines: usize,
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/artifact_reference",
+            context! {
+                number => number,
+                kind => kind,
+                language => language,
+                lines => lines,
+            },
+        )
+    }
+
+    /// Note appended to an answer whose unsupported claims were removed
+    /// (`removed`) or left in place (`claims`).
+    pub fn render_system_unsupported_claims(
+        &self,
+        removed: usize,
+        claims: &[String],
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/unsupported_claims",
+            context! {
+                removed => removed,
+                claims => claims,
+            },
+        )
+    }
+
+    /// Reply sent to the user when every model is blocked by budget caps.
+    pub fn render_over_budget(&self) -> Result<String> {
+        self.render_static("replies/over_budget")
+    }
+
     /// Convenience method for rendering worker overflow recovery message.
     pub fn render_system_worker_overflow(&self) -> Result<String> {
         self.render_static("fragments/system/worker_overflow")
@@ -353,6 +484,19 @@ impl PromptEngine {
         )
     }
 
+    /// Render calculator results for arithmetic found in a user message.
+    pub fn render_system_calculator_results(
+        &self,
+        calculations: &[crate::calc::Calculation],
+    ) -> Result<String> {
+        self.render(
+            "fragments/system/calculator_results",
+            context! {
+                calculations => calculations,
+            },
+        )
+    }
+
     /// Render the coalesce hint fragment for batched messages.
     pub fn render_coalesce_hint(
         &self,
@@ -371,11 +515,13 @@ impl PromptEngine {
     }
 
     /// Render the complete channel system prompt with all dynamic components.
+    #[allow(clippy::too_many_arguments)]
     pub fn render_channel_prompt(
         &self,
         identity_context: Option<String>,
         memory_bulletin: Option<String>,
         skills_prompt: Option<String>,
+        flows_prompt: Option<String>,
         worker_capabilities: String,
         conversation_context: Option<String>,
         status_text: Option<String>,
@@ -388,6 +534,7 @@ impl PromptEngine {
                 identity_context => identity_context,
                 memory_bulletin => memory_bulletin,
                 skills_prompt => skills_prompt,
+                flows_prompt => flows_prompt,
                 worker_capabilities => worker_capabilities,
                 conversation_context => conversation_context,
                 status_text => status_text,
@@ -440,3 +587,51 @@ pub struct ChannelEntry {
 
 // All templates are now loaded from the centralized text registry (src/prompts/text.rs)
 // to support multiple languages at compile time.
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_replace_bundled_templates() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::create_dir_all(dir.path().join("fragments")).expect("create fragments dir");
+        std::fs::write(
+            dir.path().join("fragments/worker_capabilities.md.j2"),
+            "browser={{ browser_enabled }}",
+        )
+        .expect("write override");
+
+        let engine = PromptEngine::with_overrides("en", dir.path()).expect("engine should build");
+        assert_eq!(
+            engine
+                .render_worker_capabilities(true, false, false, false, &[], &[], false, false)
+                .expect("render"),
+            "browser=true"
+        );
+
+        std::fs::write(dir.path().join("compactor.md.j2"), "{% if %}").expect("write override");
+        assert!(PromptEngine::with_overrides("en", dir.path()).is_err());
+    }
+
+    #[test]
+    fn later_override_dirs_win() {
+        let base = tempfile::tempdir().expect("tempdir");
+        let canary = tempfile::tempdir().expect("tempdir");
+        std::fs::write(base.path().join("branch.md.j2"), "base branch").expect("write override");
+        std::fs::write(base.path().join("worker.md.j2"), "base worker").expect("write override");
+        std::fs::write(canary.path().join("worker.md.j2"), "canary worker")
+            .expect("write override");
+
+        let engine = PromptEngine::with_override_dirs("en", &[base.path(), canary.path()])
+            .expect("engine should build");
+        assert_eq!(
+            engine.render_static("branch").expect("render"),
+            "base branch"
+        );
+        assert_eq!(
+            engine.render_static("worker").expect("render"),
+            "canary worker"
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! The prompt stack an agent's channel assembles, rendered from a config
+//! alone, and line diffs between two of them (`spacebot prompt-diff`).
+//!
+//! A stack is the routing decision, the system prompt, and each message of a
+//! sample conversation as the model would receive it. Rendering one for the
+//! config before a change and one for the config after shows exactly what the
+//! model will see differently. Sections that only exist while the agent runs
+//! (memory bulletin, status block, channel list, active flow) are left out.
+
+use crate::agent::snapshot::PromptLayers;
+use crate::config::{Config, RuntimeConfig};
+use crate::prompts::PromptEngine;
+use crate::{ProcessType, scripting::ScriptHooks};
+
+use anyhow::Context as _;
+
+use std::fmt::Write as _;
+
+/// Lines of unchanged context around each change.
+const CONTEXT_LINES: usize = 3;
+
+/// The conversation a stack is rendered for.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    /// Platform named in the conversation context, e.g. "discord".
+    pub platform: String,
+    pub sender: String,
+    pub messages: Vec<String>,
+}
+
+/// One named part of a stack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub text: String,
+}
+
+/// Everything the model is given for a sample conversation.
+#[derive(Debug, Clone)]
+pub struct PromptStack {
+    pub agent_id: String,
+    pub instance_dir: std::path::PathBuf,
+    pub sections: Vec<Section>,
+}
+
+/// Render the stack `config` gives `agent_id` (the default agent when
+/// `None`), reading prompt overrides, scripts, identity files, skills, and
+/// flows from the config's instance directory.
+pub async fn assemble(
+    config: &Config,
+    agent_id: Option<&str>,
+    sample: &Sample,
+) -> anyhow::Result<PromptStack> {
+    let agent_id = agent_id.unwrap_or_else(|| config.default_agent_id());
+    let agents = config.resolve_agents();
+    let agent = agents
+        .iter()
+        .find(|agent| agent.id == agent_id)
+        .with_context(|| format!("no agent '{agent_id}' in this config"))?;
+
+    let prompt_engine = PromptEngine::with_overrides("en", &config.instance_dir.join("prompts"))
+        .context("failed to load prompt templates")?;
+    let scripts = ScriptHooks::load(&config.instance_dir.join("scripts"))
+        .context("failed to load script hooks")?;
+    let identity = crate::identity::Identity::load(&agent.workspace).await;
+    let skills = crate::skills::SkillSet::load(&config.skills_dir(), &agent.skills_dir()).await;
+    let flows = crate::flows::FlowSet::load(&config.flows_dir(), &agent.flows_dir());
+    let rc = RuntimeConfig::new(
+        &config.instance_dir,
+        agent,
+        &config.defaults,
+        prompt_engine,
+        scripts,
+        identity,
+        skills,
+        flows,
+    );
+
+    let prompt_engine = rc.prompts.load();
+    let routing = rc.routing.load();
+    let model = routing.resolve(ProcessType::Channel, None);
+    let mut routing_text = format!(
+        "model: {model}\nthinking_effort: {}\nmax_turns: {}\n",
+        routing.thinking_effort_for_model(model),
+        **rc.max_turns.load()
+    );
+    for fallback in routing.get_fallbacks(model) {
+        let _ = writeln!(routing_text, "fallback: {fallback}");
+    }
+
+    let layers = PromptLayers {
+        conversation_context: Some(prompt_engine.render_conversation_context(
+            &sample.platform,
+            None,
+            None,
+        )?),
+        ..PromptLayers::from_config(&rc, &prompt_engine)
+    };
+    let mut sections = vec![
+        Section {
+            name: "routing".into(),
+            text: routing_text,
+        },
+        Section {
+            name: "system prompt".into(),
+            text: layers.render(&prompt_engine)?,
+        },
+    ];
+
+    let scripts = rc.scripts.load();
+    for (index, message) in sample.messages.iter().enumerate() {
+        let mut text = format!("{}: {message}", sample.sender);
+        if let Some(note) = crate::agent::channel::calculator_note(&rc, message) {
+            text.push_str("\n\n");
+            text.push_str(&note);
+        }
+        sections.push(Section {
+            name: format!("message {}", index + 1),
+            text: scripts.before_llm(agent_id, "prompt-diff", &text),
+        });
+    }
+
+    Ok(PromptStack {
+        agent_id: agent_id.to_string(),
+        instance_dir: config.instance_dir.clone(),
+        sections,
+    })
+}
+
+/// A unified diff of every section that differs between `old` and `new`,
+/// or `None` when the stacks are the same.
+pub fn diff(old: &PromptStack, new: &PromptStack) -> Option<String> {
+    let mut output = String::new();
+    let mut names: Vec<&str> = old.sections.iter().map(|s| s.name.as_str()).collect();
+    for section in &new.sections {
+        if !names.contains(&section.name.as_str()) {
+            names.push(&section.name);
+        }
+    }
+
+    for name in names {
+        let text = |stack: &PromptStack| {
+            stack
+                .sections
+                .iter()
+                .find(|section| section.name == name)
+                .map(|section| section.text.clone())
+                .unwrap_or_default()
+        };
+        let (old_text, new_text) = (text(old), text(new));
+        if old_text == new_text {
+            continue;
+        }
+        let _ = writeln!(output, "--- old/{name}\n+++ new/{name}");
+        output.push_str(&unified(&old_text, &new_text));
+    }
+
+    (!output.is_empty()).then_some(output)
+}
+
+/// A line edit between two texts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit<'a> {
+    Keep(&'a str),
+    Remove(&'a str),
+    Add(&'a str),
+}
+
+/// Line edits turning `old` into `new`, from their longest common
+/// subsequence of lines.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    // Common leading and trailing lines need no table.
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    // lengths[i][j]: longest common subsequence of old_mid[i..] and new_mid[j..].
+    let width = new_mid.len() + 1;
+    let mut lengths = vec![0u32; (old_mid.len() + 1) * width];
+    for i in (0..old_mid.len()).rev() {
+        for j in (0..new_mid.len()).rev() {
+            lengths[i * width + j] = if old_mid[i] == new_mid[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut result: Vec<Edit> = old[..prefix].iter().map(|line| Edit::Keep(line)).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_mid.len() && j < new_mid.len() {
+        if old_mid[i] == new_mid[j] {
+            result.push(Edit::Keep(old_mid[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            result.push(Edit::Remove(old_mid[i]));
+            i += 1;
+        } else {
+            result.push(Edit::Add(new_mid[j]));
+            j += 1;
+        }
+    }
+    result.extend(old_mid[i..].iter().map(|line| Edit::Remove(line)));
+    result.extend(new_mid[j..].iter().map(|line| Edit::Add(line)));
+    result.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Edit::Keep(line)),
+    );
+    result
+}
+
+/// Hunks in unified diff format, each with a few lines of context.
+fn unified(old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = edits(&old_lines, &new_lines);
+
+    let changed: Vec<usize> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Keep(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    // Group changes whose context would overlap into one hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for index in changed {
+        let start = index.saturating_sub(CONTEXT_LINES);
+        let end = (index + CONTEXT_LINES + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(hunk) if start <= hunk.1 => hunk.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut output = String::new();
+    for (start, end) in hunks {
+        // Line numbers where the hunk starts in each text.
+        let count = |edits: &[Edit], old_side: bool| {
+            edits
+                .iter()
+                .filter(|edit| match edit {
+                    Edit::Keep(_) => true,
+                    Edit::Remove(_) => old_side,
+                    Edit::Add(_) => !old_side,
+                })
+                .count()
+        };
+        let (old_start, new_start) = (count(&edits[..start], true), count(&edits[..start], false));
+        let (old_len, new_len) = (
+            count(&edits[start..end], true),
+            count(&edits[start..end], false),
+        );
+        let _ = writeln!(
+            output,
+            "@@ -{},{old_len} +{},{new_len} @@",
+            old_start + 1,
+            new_start + 1
+        );
+        for edit in &edits[start..end] {
+            let _ = match edit {
+                Edit::Keep(line) => writeln!(output, " {line}"),
+                Edit::Remove(line) => writeln!(output, "-{line}"),
+                Edit::Add(line) => writeln!(output, "+{line}"),
+            };
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(sections: &[(&str, &str)]) -> PromptStack {
+        PromptStack {
+            agent_id: "main".into(),
+            instance_dir: ".".into(),
+            sections: sections
+                .iter()
+                .map(|(name, text)| Section {
+                    name: name.to_string(),
+                    text: text.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn diffs_show_changed_sections_with_context() {
+        let old = stack(&[
+            ("routing", "model: anthropic/claude-sonnet-4\n"),
+            ("system prompt", "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\n"),
+        ]);
+        let new = stack(&[
+            ("routing", "model: anthropic/claude-sonnet-4\n"),
+            ("system prompt", "a\nb\nc\nd\ne\nF\ng\nh\ni\nj\nk\nl\n"),
+            ("message 1", "user: hi"),
+        ]);
+
+        let diff = diff(&old, &new).expect("stacks differ");
+        assert_eq!(
+            diff,
+            "--- old/system prompt\n+++ new/system prompt\n\
+             @@ -3,9 +3,10 @@\n c\n d\n e\n-f\n+F\n g\n h\n i\n j\n k\n+l\n\
+             --- old/message 1\n+++ new/message 1\n\
+             @@ -1,0 +1,1 @@\n+user: hi\n"
+        );
+        assert!(super::diff(&old, &old).is_none());
+    }
+}
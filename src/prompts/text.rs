@@ -63,6 +63,9 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "compactor") => include_str!("../../prompts/en/compactor.md.j2"),
         ("en", "memory_persistence") => include_str!("../../prompts/en/memory_persistence.md.j2"),
         ("en", "ingestion") => include_str!("../../prompts/en/ingestion.md.j2"),
+        ("en", "digest") => include_str!("../../prompts/en/digest.md.j2"),
+        ("en", "alert_triage") => include_str!("../../prompts/en/alert_triage.md.j2"),
+        ("en", "feed_summary") => include_str!("../../prompts/en/feed_summary.md.j2"),
         ("en", "cortex_chat") => include_str!("../../prompts/en/cortex_chat.md.j2"),
 
         // Fragment Templates
@@ -89,6 +92,15 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "fragments/system/truncation") => {
             include_str!("../../prompts/en/fragments/system/truncation.md.j2")
         }
+        ("en", "fragments/system/duplicate_collapsed") => {
+            include_str!("../../prompts/en/fragments/system/duplicate_collapsed.md.j2")
+        }
+        ("en", "fragments/system/artifact_reference") => {
+            include_str!("../../prompts/en/fragments/system/artifact_reference.md.j2")
+        }
+        ("en", "fragments/system/unsupported_claims") => {
+            include_str!("../../prompts/en/fragments/system/unsupported_claims.md.j2")
+        }
         ("en", "fragments/system/worker_overflow") => {
             include_str!("../../prompts/en/fragments/system/worker_overflow.md.j2")
         }
@@ -110,15 +122,38 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "fragments/system/history_backfill") => {
             include_str!("../../prompts/en/fragments/system/history_backfill.md.j2")
         }
+        ("en", "fragments/system/calculator_results") => {
+            include_str!("../../prompts/en/fragments/system/calculator_results.md.j2")
+        }
         ("en", "fragments/system/tool_syntax_correction") => {
             include_str!("../../prompts/en/fragments/system/tool_syntax_correction.md.j2")
         }
+        ("en", "fragments/system/low_confidence") => {
+            include_str!("../../prompts/en/fragments/system/low_confidence.md.j2")
+        }
+        ("en", "fragments/system/best_of_judge") => {
+            include_str!("../../prompts/en/fragments/system/best_of_judge.md.j2")
+        }
+        ("en", "fragments/system/eval_judge") => {
+            include_str!("../../prompts/en/fragments/system/eval_judge.md.j2")
+        }
+        ("en", "fragments/system/claim_judge") => {
+            include_str!("../../prompts/en/fragments/system/claim_judge.md.j2")
+        }
+        ("en", "fragments/system/selftest") => {
+            include_str!("../../prompts/en/fragments/system/selftest.md.j2")
+        }
 
         // Coalesce Hint
         ("en", "fragments/coalesce_hint") => {
             include_str!("../../prompts/en/fragments/coalesce_hint.md.j2")
         }
 
+        // Replies (sent to users verbatim, not to the LLM)
+        ("en", "replies/over_budget") => {
+            include_str!("../../prompts/en/replies/over_budget.md.j2")
+        }
+
         // Tool Descriptions
         ("en", "tools/reply") => include_str!("../../prompts/en/tools/reply_description.md.j2"),
         ("en", "tools/branch") => include_str!("../../prompts/en/tools/branch_description.md.j2"),
@@ -139,6 +174,39 @@ fn lookup(lang: &str, key: &str) -> &'static str {
         ("en", "tools/web_search") => {
             include_str!("../../prompts/en/tools/web_search_description.md.j2")
         }
+        ("en", "tools/calculate") => {
+            include_str!("../../prompts/en/tools/calculate_description.md.j2")
+        }
+        ("en", "tools/calendar") => {
+            include_str!("../../prompts/en/tools/calendar_description.md.j2")
+        }
+        ("en", "tools/chart") => include_str!("../../prompts/en/tools/chart_description.md.j2"),
+        ("en", "tools/artifact_read") => {
+            include_str!("../../prompts/en/tools/artifact_read_description.md.j2")
+        }
+        ("en", "tools/geocode") => {
+            include_str!("../../prompts/en/tools/geocode_description.md.j2")
+        }
+        ("en", "tools/weather") => {
+            include_str!("../../prompts/en/tools/weather_description.md.j2")
+        }
+        ("en", "tools/csv_analysis") => {
+            include_str!("../../prompts/en/tools/csv_analysis_description.md.j2")
+        }
+        ("en", "tools/email") => include_str!("../../prompts/en/tools/email_description.md.j2"),
+        ("en", "tools/http_request") => {
+            include_str!("../../prompts/en/tools/http_request_description.md.j2")
+        }
+        ("en", "tools/sql_query") => {
+            include_str!("../../prompts/en/tools/sql_query_description.md.j2")
+        }
+        ("en", "tools/github") => include_str!("../../prompts/en/tools/github_description.md.j2"),
+        ("en", "tools/railway") => {
+            include_str!("../../prompts/en/tools/railway_description.md.j2")
+        }
+        ("en", "tools/ollama_models") => {
+            include_str!("../../prompts/en/tools/ollama_models_description.md.j2")
+        }
         ("en", "tools/memory_save") => {
             include_str!("../../prompts/en/tools/memory_save_description.md.j2")
         }
@@ -155,6 +223,9 @@ fn lookup(lang: &str, key: &str) -> &'static str {
             include_str!("../../prompts/en/tools/send_file_description.md.j2")
         }
         ("en", "tools/cron") => include_str!("../../prompts/en/tools/cron_description.md.j2"),
+        ("en", "tools/poll") => include_str!("../../prompts/en/tools/poll_description.md.j2"),
+        ("en", "tools/flow") => include_str!("../../prompts/en/tools/flow_description.md.j2"),
+        ("en", "tools/remind") => include_str!("../../prompts/en/tools/remind_description.md.j2"),
         ("en", "tools/send_message_to_another_channel") => {
             include_str!("../../prompts/en/tools/send_message_description.md.j2")
         }
@@ -0,0 +1,438 @@
+//! OpenAI- and Anthropic-compatible proxy.
+//!
+//! With `[proxy]` enabled, spacebot serves `POST /v1/chat/completions` and
+//! `POST /v1/messages` so any OpenAI or Anthropic client can use the models
+//! it's configured with, whichever provider serves them, and lists those
+//! models at `GET /v1/models`. Requests take
+//! the same path as an agent's own calls — budget routing, retries, the
+//! fallback chain, rate-limit cooldowns, prompt caching, and spend
+//! accounting — on behalf of one agent, whose routing, tenant keys, and
+//! spend cap apply. Callers need an issued API key with the `proxy` scope,
+//! sent as a bearer token or an `x-api-key` header. See [`route`] for
+//! choosing the models per request. With `stream: true`, the reply is relayed
+//! in the client's event format as the provider sends it.
+
+pub mod anthropic;
+pub mod openai;
+pub mod route;
+
+use crate::AgentId;
+use crate::api_keys::{ApiKeyStore, Scope};
+use crate::config::RuntimeConfig;
+use crate::llm::discovery::{Discovery, routing_models};
+use crate::llm::manager::LlmManager;
+use crate::llm::model::streaming::CompletionStream;
+use crate::llm::model::{RawResponse, SpacebotModel};
+use crate::llm::routing;
+use anthropic::MessagesRequest;
+use openai::ChatCompletionRequest;
+use route::{ROUTE_HEADER, Route};
+
+use anyhow::Context as _;
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{Json, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::sse::{Event, KeepAlive};
+use axum::response::{IntoResponse, Response, Sse};
+use axum::routing::{get, post};
+use futures::{Stream, StreamExt as _};
+use rig::completion::{CompletionModel as _, CompletionResponse};
+
+use std::collections::BTreeSet;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+/// Proxy settings (instance-level, under `[proxy]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyServerConfig {
+    pub enabled: bool,
+    pub port: u16,
+    pub bind: String,
+    /// Agent the proxy calls on behalf of. `None` is the default agent.
+    pub agent_id: Option<String>,
+}
+
+impl Default for ProxyServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 18793,
+            bind: "127.0.0.1".into(),
+            agent_id: None,
+        }
+    }
+}
+
+/// Shared state for axum handlers.
+#[derive(Clone)]
+struct ProxyState {
+    agent_id: AgentId,
+    llm_manager: Arc<LlmManager>,
+    runtime_config: Arc<RuntimeConfig>,
+    api_keys: Arc<ApiKeyStore>,
+}
+
+/// Bind the proxy and serve it in the background until the process exits.
+pub async fn start(
+    config: &ProxyServerConfig,
+    agent_id: AgentId,
+    llm_manager: Arc<LlmManager>,
+    runtime_config: Arc<RuntimeConfig>,
+    api_keys: Arc<ApiKeyStore>,
+) -> anyhow::Result<tokio::task::JoinHandle<()>> {
+    let raw_bind = config.bind.trim_start_matches('[').trim_end_matches(']');
+    let bind = if raw_bind.contains(':') {
+        format!("[{}]:{}", raw_bind, config.port)
+    } else {
+        format!("{}:{}", raw_bind, config.port)
+    };
+
+    let state = ProxyState {
+        agent_id,
+        llm_manager,
+        runtime_config,
+        api_keys,
+    };
+    let app = Router::new()
+        .route("/v1/chat/completions", post(chat_completions))
+        .route("/v1/messages", post(messages))
+        .route("/v1/models", get(models))
+        .route("/health", get(|| async { StatusCode::OK }))
+        .with_state(state);
+
+    let listener = crate::listeners::bind("proxy", bind.as_str())
+        .await
+        .with_context(|| format!("failed to bind proxy server to {bind}"))?;
+    tracing::info!(%bind, "proxy server listening");
+
+    Ok(tokio::spawn(async move {
+        if let Err(error) = listener.serve(app, std::future::pending()).await {
+            tracing::error!(%error, "proxy server exited with error");
+        }
+    }))
+}
+
+/// A failed proxy request, answered in the shape the client's SDK expects.
+struct ProxyError {
+    status: StatusCode,
+    message: String,
+    error_type: &'static str,
+}
+
+impl ProxyError {
+    fn invalid_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+            error_type: "invalid_request_error",
+        }
+    }
+
+    /// Budget refusals and rate limits are 429s; other failures are the
+    /// upstream provider's.
+    fn upstream(error: &rig::completion::CompletionError) -> Self {
+        let message = error.to_string();
+        let (status, error_type) = if routing::is_over_budget_error(&message) {
+            (StatusCode::TOO_MANY_REQUESTS, "insufficient_quota")
+        } else if routing::is_rate_limit_error(&message) {
+            (StatusCode::TOO_MANY_REQUESTS, "rate_limit_error")
+        } else {
+            (StatusCode::BAD_GATEWAY, "upstream_error")
+        };
+        Self {
+            status,
+            message,
+            error_type,
+        }
+    }
+}
+
+impl IntoResponse for ProxyError {
+    fn into_response(self) -> Response {
+        let body = openai::error_body(&self.message, self.error_type);
+        (self.status, Json(body)).into_response()
+    }
+}
+
+/// A [`ProxyError`] answered in the Messages API's shape.
+struct AnthropicError(ProxyError);
+
+impl From<ProxyError> for AnthropicError {
+    fn from(error: ProxyError) -> Self {
+        Self(error)
+    }
+}
+
+impl AnthropicError {
+    /// The Messages API's name for the error's type.
+    fn error_type(&self) -> &'static str {
+        match self.0.error_type {
+            "insufficient_quota" => "rate_limit_error",
+            "upstream_error" => "api_error",
+            error_type => error_type,
+        }
+    }
+}
+
+impl IntoResponse for AnthropicError {
+    fn into_response(self) -> Response {
+        let body = anthropic::error_body(&self.0.message, self.error_type());
+        (self.0.status, Json(body)).into_response()
+    }
+}
+
+/// Check the caller's key, returning its name. OpenAI SDKs send it as a
+/// bearer token, Anthropic SDKs in `x-api-key`.
+fn authorize(state: &ProxyState, headers: &HeaderMap) -> Result<String, ProxyError> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    bearer
+        .or_else(|| {
+            headers
+                .get("x-api-key")
+                .and_then(|value| value.to_str().ok())
+        })
+        .and_then(|key| state.api_keys.verify(key, Scope::Proxy))
+        .map(|record| record.name)
+        .ok_or_else(|| {
+            tracing::warn!("proxy request with a missing or invalid API key");
+            ProxyError {
+                status: StatusCode::UNAUTHORIZED,
+                message: "invalid API key".into(),
+                error_type: "authentication_error",
+            }
+        })
+}
+
+/// The model to call for a request naming `model`, with the fallback chain
+/// its route sets.
+fn routed_model(
+    state: &ProxyState,
+    headers: &HeaderMap,
+    model: &str,
+) -> Result<SpacebotModel, ProxyError> {
+    let header = headers
+        .get(ROUTE_HEADER)
+        .map(|value| value.to_str())
+        .transpose()
+        .map_err(|_| ProxyError::invalid_request(format!("{ROUTE_HEADER} isn't valid text")))?;
+    let route = Route::resolve(model, header, |name| {
+        state.llm_manager.get_provider(name).is_ok()
+    })
+    .map_err(ProxyError::invalid_request)?;
+
+    let model = SpacebotModel::make(&state.llm_manager, route.model);
+    let mut routing = (**state.runtime_config.routing.load()).clone();
+    if let Some(fallbacks) = route.fallbacks {
+        routing
+            .fallbacks
+            .insert(model.full_model_name().to_string(), fallbacks);
+    }
+    Ok(model
+        .with_routing(routing)
+        .with_agent(state.agent_id.clone()))
+}
+
+/// Every model a request can name: the models the agent's routing config
+/// uses, the aliases, and each model a provider listed at the latest
+/// discovery.
+fn listed_models<'a>(
+    routes: impl Iterator<Item = &'a str>,
+    aliases: Vec<String>,
+    discovery: &Discovery,
+) -> BTreeSet<String> {
+    let mut models: BTreeSet<String> = routes.map(str::to_string).collect();
+    models.extend(aliases);
+    for (provider, listed) in &discovery.providers {
+        models.extend(
+            listed
+                .models
+                .iter()
+                .map(|model| format!("{provider}/{model}")),
+        );
+    }
+    models
+}
+
+/// The model that answered, which a fallback may have changed.
+fn answered_model(response: &CompletionResponse<RawResponse>, model: &SpacebotModel) -> String {
+    response.raw_response.body["model"]
+        .as_str()
+        .unwrap_or(model.full_model_name())
+        .to_string()
+}
+
+// -- Axum handlers --
+
+async fn models(
+    State(state): State<ProxyState>,
+    headers: HeaderMap,
+) -> Result<Response, ProxyError> {
+    authorize(&state, &headers)?;
+    let routing_config = state.runtime_config.routing.load();
+    let discovery = state.llm_manager.model_catalog().snapshot();
+    let models = listed_models(
+        routing_models(&routing_config),
+        state.llm_manager.alias_names(),
+        &discovery,
+    );
+    Ok(Json(openai::models_response(models.iter().map(String::as_str))).into_response())
+}
+
+async fn chat_completions(
+    State(state): State<ProxyState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ProxyError> {
+    let caller = authorize(&state, &headers)?;
+    let request: ChatCompletionRequest = serde_json::from_slice(&body)
+        .map_err(|error| ProxyError::invalid_request(format!("invalid request: {error}")))?;
+    let model = routed_model(&state, &headers, &request.model)?;
+    let completion_request = request
+        .to_completion_request()
+        .map_err(ProxyError::invalid_request)?;
+
+    tracing::debug!(
+        %caller,
+        model = %model.full_model_name(),
+        stream = request.stream,
+        "proxying chat completion"
+    );
+    if request.stream {
+        let include_usage = request
+            .stream_options
+            .as_ref()
+            .is_some_and(|options| options.include_usage);
+        let stream = model
+            .stream_completion(completion_request)
+            .await
+            .map_err(|error| {
+                tracing::warn!(%caller, %error, "proxied chat completion stream failed to start");
+                ProxyError::upstream(&error)
+            })?;
+        return Ok(relay_stream(stream, include_usage, caller).into_response());
+    }
+
+    let response = model
+        .completion(completion_request)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%caller, %error, "proxied chat completion failed");
+            ProxyError::upstream(&error)
+        })?;
+
+    let answered = answered_model(&response, &model);
+    let display = model.reasoning().display;
+    Ok(Json(openai::to_response(&response, &answered, display)).into_response())
+}
+
+/// Relay a streamed reply as server-sent `chat.completion.chunk` events,
+/// ending with `data: [DONE]`. A failure partway ends it with an error event
+/// instead, so clients don't take a cut-off reply for a whole one.
+fn relay_stream(
+    stream: CompletionStream,
+    include_usage: bool,
+    caller: String,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let writer = openai::ChunkWriter::new(&stream.model, include_usage);
+    let mut events = stream.events;
+    let chunks = async_stream::stream! {
+        yield Ok(Event::default().data(writer.start().to_string()));
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(event) => {
+                    for chunk in writer.chunks(&event) {
+                        yield Ok(Event::default().data(chunk.to_string()));
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!(%caller, %error, "proxied chat completion stream failed");
+                    let error = ProxyError::upstream(&error);
+                    let body = openai::error_body(&error.message, error.error_type);
+                    yield Ok(Event::default().data(body.to_string()));
+                    return;
+                }
+            }
+        }
+        yield Ok(Event::default().data("[DONE]"));
+    };
+    Sse::new(chunks).keep_alive(KeepAlive::default())
+}
+
+async fn messages(
+    State(state): State<ProxyState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, AnthropicError> {
+    let caller = authorize(&state, &headers)?;
+    let request: MessagesRequest = serde_json::from_slice(&body)
+        .map_err(|error| ProxyError::invalid_request(format!("invalid request: {error}")))?;
+    let model = routed_model(&state, &headers, &request.model)?;
+    let completion_request = request
+        .to_completion_request()
+        .map_err(ProxyError::invalid_request)?;
+
+    tracing::debug!(
+        %caller,
+        model = %model.full_model_name(),
+        stream = request.stream,
+        "proxying messages request"
+    );
+    if request.stream {
+        let stream = model
+            .stream_completion(completion_request)
+            .await
+            .map_err(|error| {
+                tracing::warn!(%caller, %error, "proxied messages stream failed to start");
+                ProxyError::upstream(&error)
+            })?;
+        return Ok(relay_messages_stream(stream, caller).into_response());
+    }
+
+    let response = model
+        .completion(completion_request)
+        .await
+        .map_err(|error| {
+            tracing::warn!(%caller, %error, "proxied messages request failed");
+            ProxyError::upstream(&error)
+        })?;
+    let answered = answered_model(&response, &model);
+    let display = model.reasoning().display;
+    Ok(Json(anthropic::to_response(&response, &answered, display)).into_response())
+}
+
+/// Relay a streamed reply as Messages API events, ending with `message_stop`,
+/// or with an `error` event if it fails partway.
+fn relay_messages_stream(
+    stream: CompletionStream,
+    caller: String,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let mut writer = anthropic::EventWriter::new(&stream.model);
+    let mut events = stream.events;
+    let sse = |(name, data): (&str, serde_json::Value)| {
+        Ok::<_, Infallible>(Event::default().event(name).data(data.to_string()))
+    };
+    let messages = async_stream::stream! {
+        yield sse(writer.start());
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(event) => {
+                    for message in writer.events(&event) {
+                        yield sse(message);
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!(%caller, %error, "proxied messages stream failed");
+                    let error = AnthropicError(ProxyError::upstream(&error));
+                    let body = anthropic::error_body(&error.0.message, error.error_type());
+                    yield sse(("error", body));
+                    return;
+                }
+            }
+        }
+    };
+    Sse::new(messages).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,556 @@
+//! Translation between Anthropic Messages and Rig completion requests.
+
+use crate::llm::model::RawResponse;
+use crate::llm::model::streaming::StreamEvent;
+use crate::llm::reasoning::{self, ReasoningDisplay};
+
+use rig::completion::{self, CompletionRequest, ToolDefinition};
+use rig::message::{
+    AssistantContent, ImageMediaType, Message, MimeType as _, ToolResultContent, UserContent,
+};
+use rig::one_or_many::OneOrMany;
+use serde::Deserialize;
+
+use std::collections::HashMap;
+
+/// A `POST /v1/messages` body. Fields the proxy doesn't act on are ignored.
+#[derive(Debug, Deserialize)]
+pub struct MessagesRequest {
+    #[serde(default)]
+    pub model: String,
+    pub max_tokens: Option<u64>,
+    pub messages: Vec<serde_json::Value>,
+    /// A string, or a list of text blocks.
+    pub system: Option<serde_json::Value>,
+    #[serde(default)]
+    pub tools: Vec<serde_json::Value>,
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub stream: bool,
+}
+
+impl MessagesRequest {
+    /// The request as Rig sees it. Tool results stay in the user turn that
+    /// carries them; thinking blocks are dropped.
+    pub fn to_completion_request(&self) -> Result<CompletionRequest, String> {
+        let mut history = Vec::new();
+        for message in &self.messages {
+            let content = &message["content"];
+            match message["role"].as_str().unwrap_or_default() {
+                "user" => history.push(Message::User {
+                    content: user_content(content)?,
+                }),
+                "assistant" => {
+                    if let Some(content) = assistant_content(content)? {
+                        history.push(Message::Assistant { id: None, content });
+                    }
+                }
+                role => return Err(format!("unsupported message role '{role}'")),
+            }
+        }
+        let chat_history = OneOrMany::many(history)
+            .map_err(|_| "messages has no user or assistant message".to_string())?;
+
+        let tools = self
+            .tools
+            .iter()
+            .map(|tool| -> Result<ToolDefinition, String> {
+                let name = tool["name"].as_str().ok_or("tool without a name")?;
+                Ok(ToolDefinition {
+                    name: name.to_string(),
+                    description: tool["description"].as_str().unwrap_or_default().to_string(),
+                    parameters: tool
+                        .get("input_schema")
+                        .cloned()
+                        .unwrap_or_else(|| serde_json::json!({"type": "object"})),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let preamble = self
+            .system
+            .as_ref()
+            .map(blocks_text)
+            .filter(|system| !system.is_empty());
+
+        Ok(CompletionRequest {
+            preamble,
+            chat_history,
+            documents: Vec::new(),
+            tools,
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            tool_choice: None,
+            additional_params: None,
+        })
+    }
+}
+
+/// Text of content given as a string or a list of blocks.
+fn blocks_text(content: &serde_json::Value) -> String {
+    match content {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(blocks) => blocks
+            .iter()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn user_content(content: &serde_json::Value) -> Result<OneOrMany<UserContent>, String> {
+    let serde_json::Value::Array(blocks) = content else {
+        return Ok(OneOrMany::one(UserContent::text(blocks_text(content))));
+    };
+    let mut items = Vec::new();
+    for block in blocks {
+        match block["type"].as_str() {
+            Some("text") => {
+                if let Some(text) = block["text"].as_str() {
+                    items.push(UserContent::text(text));
+                }
+            }
+            Some("image") => {
+                let source = &block["source"];
+                match source["type"].as_str() {
+                    Some("base64") => items.push(UserContent::image_base64(
+                        source["data"].as_str().unwrap_or_default(),
+                        source["media_type"]
+                            .as_str()
+                            .and_then(ImageMediaType::from_mime_type),
+                        None,
+                    )),
+                    Some("url") => items.push(UserContent::image_url(
+                        source["url"].as_str().unwrap_or_default(),
+                        None,
+                        None,
+                    )),
+                    other => return Err(format!("unsupported image source {other:?}")),
+                }
+            }
+            Some("tool_result") => {
+                let id = block["tool_use_id"]
+                    .as_str()
+                    .ok_or("tool_result without a tool_use_id")?;
+                let mut text = blocks_text(&block["content"]);
+                if block["is_error"] == true {
+                    text = format!("Error: {text}");
+                }
+                items.push(UserContent::tool_result(
+                    id,
+                    OneOrMany::one(ToolResultContent::text(text)),
+                ));
+            }
+            _ => {}
+        }
+    }
+    OneOrMany::many(items).map_err(|_| "user message without text, images, or tool results".into())
+}
+
+/// The text and tool calls of an assistant turn, or `None` when it has
+/// neither.
+fn assistant_content(
+    content: &serde_json::Value,
+) -> Result<Option<OneOrMany<AssistantContent>>, String> {
+    let serde_json::Value::Array(blocks) = content else {
+        let text = blocks_text(content);
+        return Ok((!text.is_empty()).then(|| OneOrMany::one(AssistantContent::text(text))));
+    };
+    let mut items = Vec::new();
+    for block in blocks {
+        match block["type"].as_str() {
+            Some("text") => {
+                if let Some(text) = block["text"].as_str().filter(|text| !text.is_empty()) {
+                    items.push(AssistantContent::text(text));
+                }
+            }
+            Some("tool_use") => {
+                let id = block["id"].as_str().ok_or("tool_use without an id")?;
+                let name = block["name"].as_str().ok_or("tool_use without a name")?;
+                items.push(AssistantContent::tool_call(
+                    id,
+                    name,
+                    block["input"].clone(),
+                ));
+            }
+            // Thinking from another model can't be replayed to this one.
+            _ => {}
+        }
+    }
+    Ok(OneOrMany::many(items).ok())
+}
+
+/// A `message` object for a response from `model`. Its reasoning comes
+/// first as thinking blocks when `display` passes it on.
+pub fn to_response(
+    response: &completion::CompletionResponse<RawResponse>,
+    model: &str,
+    display: ReasoningDisplay,
+) -> serde_json::Value {
+    let mut content = Vec::new();
+    let mut called_tools = false;
+    for item in response.choice.iter() {
+        match item {
+            AssistantContent::Text(text) => {
+                content.push(serde_json::json!({"type": "text", "text": text.text}));
+            }
+            AssistantContent::Reasoning(thoughts) if display == ReasoningDisplay::Summary => {
+                content.push(serde_json::json!({
+                    "type": "thinking",
+                    "thinking": reasoning::summarize(&thoughts.reasoning.join("\n")),
+                    "signature": thoughts.signature.clone().unwrap_or_default(),
+                }));
+            }
+            AssistantContent::ToolCall(call) => {
+                called_tools = true;
+                content.push(serde_json::json!({
+                    "type": "tool_use",
+                    "id": call.id,
+                    "name": call.function.name,
+                    "input": call.function.arguments,
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    serde_json::json!({
+        "id": message_id(),
+        "type": "message",
+        "role": "assistant",
+        "model": model,
+        "content": content,
+        "stop_reason": if called_tools { "tool_use" } else { "end_turn" },
+        "stop_sequence": null,
+        "usage": usage(&response.usage),
+    })
+}
+
+fn message_id() -> String {
+    format!("msg_{}", uuid::Uuid::new_v4().simple())
+}
+
+fn usage(usage: &completion::Usage) -> serde_json::Value {
+    serde_json::json!({
+        "input_tokens": usage.input_tokens,
+        "output_tokens": usage.output_tokens,
+        "cache_read_input_tokens": usage.cached_input_tokens,
+    })
+}
+
+/// The `stop_reason` for a stream's finish reason.
+fn stop_reason(finish_reason: &str) -> &'static str {
+    match finish_reason {
+        "length" => "max_tokens",
+        "tool_calls" => "tool_use",
+        "content_filter" => "refusal",
+        _ => "end_turn",
+    }
+}
+
+/// Writes a streamed reply as Messages events: `message_start`, a
+/// start/delta/stop run per content block, `message_delta`, and
+/// `message_stop`.
+pub struct EventWriter {
+    id: String,
+    model: String,
+    /// Blocks started so far.
+    blocks: usize,
+    /// The block taking text, if the last one started is a text block.
+    text_block: Option<usize>,
+    /// Whether the last block started is still open.
+    open: bool,
+    /// Block indexes by tool call index.
+    tool_blocks: HashMap<usize, usize>,
+}
+
+impl EventWriter {
+    pub fn new(model: &str) -> Self {
+        Self {
+            id: message_id(),
+            model: model.to_string(),
+            blocks: 0,
+            text_block: None,
+            open: false,
+            tool_blocks: HashMap::new(),
+        }
+    }
+
+    /// The first event, opening the message.
+    pub fn start(&self) -> (&'static str, serde_json::Value) {
+        (
+            "message_start",
+            serde_json::json!({
+                "type": "message_start",
+                "message": {
+                    "id": self.id,
+                    "type": "message",
+                    "role": "assistant",
+                    "model": self.model,
+                    "content": [],
+                    "stop_reason": null,
+                    "stop_sequence": null,
+                    "usage": {"input_tokens": 0, "output_tokens": 0},
+                },
+            }),
+        )
+    }
+
+    /// The events for one event of the reply, as `(event name, data)`.
+    pub fn events(&mut self, event: &StreamEvent) -> Vec<(&'static str, serde_json::Value)> {
+        let mut events = Vec::new();
+        match event {
+            StreamEvent::Text(text) => {
+                let index = match self.text_block {
+                    Some(index) => index,
+                    None => {
+                        let index = self.start_block(
+                            &mut events,
+                            serde_json::json!({"type": "text", "text": ""}),
+                        );
+                        self.text_block = Some(index);
+                        index
+                    }
+                };
+                events.push(delta(
+                    index,
+                    serde_json::json!({"type": "text_delta", "text": text}),
+                ));
+            }
+            StreamEvent::ToolCall { index, id, name } => {
+                let block = self.start_block(
+                    &mut events,
+                    serde_json::json!({"type": "tool_use", "id": id, "name": name, "input": {}}),
+                );
+                self.tool_blocks.insert(*index, block);
+            }
+            StreamEvent::ToolCallArguments { index, delta: json } => {
+                if let Some(&block) = self.tool_blocks.get(index) {
+                    events.push(delta(
+                        block,
+                        serde_json::json!({"type": "input_json_delta", "partial_json": json}),
+                    ));
+                }
+            }
+            StreamEvent::Finished {
+                finish_reason,
+                usage: finished_usage,
+                ..
+            } => {
+                self.stop_block(&mut events);
+                events.push((
+                    "message_delta",
+                    serde_json::json!({
+                        "type": "message_delta",
+                        "delta": {
+                            "stop_reason": stop_reason(finish_reason),
+                            "stop_sequence": null,
+                        },
+                        "usage": usage(finished_usage),
+                    }),
+                ));
+                events.push(("message_stop", serde_json::json!({"type": "message_stop"})));
+            }
+        }
+        events
+    }
+
+    /// Close the open block and start another, returning its index.
+    fn start_block(
+        &mut self,
+        events: &mut Vec<(&'static str, serde_json::Value)>,
+        content_block: serde_json::Value,
+    ) -> usize {
+        self.stop_block(events);
+        let index = self.blocks;
+        self.blocks += 1;
+        self.open = true;
+        events.push((
+            "content_block_start",
+            serde_json::json!({
+                "type": "content_block_start",
+                "index": index,
+                "content_block": content_block,
+            }),
+        ));
+        index
+    }
+
+    fn stop_block(&mut self, events: &mut Vec<(&'static str, serde_json::Value)>) {
+        if self.open {
+            events.push((
+                "content_block_stop",
+                serde_json::json!({"type": "content_block_stop", "index": self.blocks - 1}),
+            ));
+        }
+        self.open = false;
+        self.text_block = None;
+    }
+}
+
+fn delta(index: usize, delta: serde_json::Value) -> (&'static str, serde_json::Value) {
+    (
+        "content_block_delta",
+        serde_json::json!({
+            "type": "content_block_delta",
+            "index": index,
+            "delta": delta,
+        }),
+    )
+}
+
+/// An Anthropic-style error body.
+pub fn error_body(message: &str, error_type: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "error",
+        "error": {
+            "type": error_type,
+            "message": message,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_requests_translate_to_completion_requests() {
+        let request: MessagesRequest = serde_json::from_value(serde_json::json!({
+            "model": "claude-sonnet-4",
+            "max_tokens": 300,
+            "system": [{"type": "text", "text": "Be brief."}],
+            "messages": [
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What's in this image, and the weather?"},
+                    {"type": "image", "source": {
+                        "type": "base64", "media_type": "image/png", "data": "iVBORw0K",
+                    }},
+                ]},
+                {"role": "assistant", "content": [
+                    {"type": "thinking", "thinking": "Look it up.", "signature": "sig"},
+                    {"type": "tool_use", "id": "toolu_1", "name": "weather",
+                     "input": {"city": "Oslo"}},
+                ]},
+                {"role": "user", "content": [
+                    {"type": "tool_result", "tool_use_id": "toolu_1", "content": "4°C"},
+                ]},
+            ],
+            "tools": [{"name": "weather", "input_schema": {"type": "object"}}],
+        }))
+        .unwrap();
+        let converted = request.to_completion_request().unwrap();
+
+        assert_eq!(converted.preamble.as_deref(), Some("Be brief."));
+        assert_eq!(converted.chat_history.len(), 3);
+        assert_eq!(converted.tools[0].name, "weather");
+        assert_eq!(converted.max_tokens, Some(300));
+
+        let Message::User { content } = converted.chat_history.first() else {
+            panic!("expected a user message");
+        };
+        assert!(matches!(
+            content.iter().nth(1),
+            Some(UserContent::Image(image)) if image.media_type == Some(ImageMediaType::PNG)
+        ));
+        let Some(Message::Assistant { content, .. }) = converted.chat_history.iter().nth(1) else {
+            panic!("expected an assistant message");
+        };
+        assert_eq!(content.len(), 1);
+        assert!(matches!(
+            converted.chat_history.iter().last(),
+            Some(Message::User { content }) if matches!(content.first(), UserContent::ToolResult(_))
+        ));
+    }
+
+    #[test]
+    fn responses_translate_to_messages() {
+        let response = completion::CompletionResponse {
+            choice: OneOrMany::many(vec![
+                AssistantContent::Reasoning(
+                    rig::message::Reasoning::new("They want weather.")
+                        .with_signature(Some("sig".into())),
+                ),
+                AssistantContent::text("Checking."),
+                AssistantContent::tool_call(
+                    "call_1",
+                    "weather",
+                    serde_json::json!({"city": "Oslo"}),
+                ),
+            ])
+            .unwrap(),
+            usage: completion::Usage {
+                input_tokens: 12,
+                output_tokens: 5,
+                total_tokens: 17,
+                cached_input_tokens: 4,
+            },
+            raw_response: RawResponse {
+                body: serde_json::json!({}),
+            },
+        };
+        let body = to_response(&response, "openai/gpt-4.1", ReasoningDisplay::Hidden);
+
+        assert_eq!(body["model"], "openai/gpt-4.1");
+        assert_eq!(body["stop_reason"], "tool_use");
+        assert_eq!(body["content"][0]["text"], "Checking.");
+        assert_eq!(body["content"][1]["input"]["city"], "Oslo");
+        assert_eq!(body["usage"]["cache_read_input_tokens"], 4);
+
+        let body = to_response(&response, "openai/gpt-4.1", ReasoningDisplay::Summary);
+        assert_eq!(body["content"][0]["type"], "thinking");
+        assert_eq!(body["content"][0]["thinking"], "They want weather.");
+        assert_eq!(body["content"][0]["signature"], "sig");
+        assert_eq!(body["content"][1]["text"], "Checking.");
+    }
+
+    #[test]
+    fn stream_events_translate_to_content_blocks() {
+        let mut writer = EventWriter::new("openai/gpt-4.1");
+        assert_eq!(writer.start().0, "message_start");
+
+        let events: Vec<_> = [
+            StreamEvent::Text("Check".into()),
+            StreamEvent::Text("ing.".into()),
+            StreamEvent::ToolCall {
+                index: 0,
+                id: "call_1".into(),
+                name: "weather".into(),
+            },
+            StreamEvent::ToolCallArguments {
+                index: 0,
+                delta: "{}".into(),
+            },
+            StreamEvent::Finished {
+                finish_reason: "tool_calls",
+                usage: completion::Usage::new(),
+                reasoning_tokens: 0,
+            },
+        ]
+        .iter()
+        .flat_map(|event| writer.events(event))
+        .collect();
+
+        let names: Vec<_> = events.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            [
+                "content_block_start",
+                "content_block_delta",
+                "content_block_delta",
+                "content_block_stop",
+                "content_block_start",
+                "content_block_delta",
+                "content_block_stop",
+                "message_delta",
+                "message_stop",
+            ]
+        );
+        assert_eq!(events[4].1["index"], 1);
+        assert_eq!(events[4].1["content_block"]["name"], "weather");
+        assert_eq!(events[5].1["delta"]["partial_json"], "{}");
+        assert_eq!(events[7].1["delta"]["stop_reason"], "tool_use");
+    }
+}
@@ -0,0 +1,548 @@
+//! Translation between OpenAI Chat Completions and Rig completion requests.
+
+use crate::llm::model::RawResponse;
+use crate::llm::model::streaming::StreamEvent;
+use crate::llm::reasoning::{self, ReasoningDisplay};
+use crate::llm::structured::OutputFormat;
+
+use rig::completion::{self, CompletionRequest, ToolDefinition};
+use rig::message::{
+    AssistantContent, ImageMediaType, Message, MimeType as _, ToolResultContent, UserContent,
+};
+use rig::one_or_many::OneOrMany;
+use serde::Deserialize;
+
+/// A `POST /v1/chat/completions` body. Fields the proxy doesn't act on are
+/// ignored.
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionRequest {
+    #[serde(default)]
+    pub model: String,
+    pub messages: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub tools: Vec<serde_json::Value>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u64>,
+    pub max_completion_tokens: Option<u64>,
+    pub response_format: Option<serde_json::Value>,
+    #[serde(default)]
+    pub stream: bool,
+    pub stream_options: Option<StreamOptions>,
+}
+
+/// Options for a streamed reply.
+#[derive(Debug, Default, Deserialize)]
+pub struct StreamOptions {
+    /// Send a last chunk with the reply's usage.
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+impl ChatCompletionRequest {
+    /// The request as Rig sees it: system and developer messages become the
+    /// preamble, tool messages become tool results.
+    pub fn to_completion_request(&self) -> Result<CompletionRequest, String> {
+        let mut system = Vec::new();
+        let mut history: Vec<Message> = Vec::new();
+        for message in &self.messages {
+            match message["role"].as_str().unwrap_or_default() {
+                "system" | "developer" => system.push(content_text(&message["content"])),
+                "user" => history.push(Message::User {
+                    content: user_content(&message["content"])?,
+                }),
+                "assistant" => {
+                    if let Some(content) = assistant_content(message)? {
+                        history.push(Message::Assistant { id: None, content });
+                    }
+                }
+                "tool" => {
+                    let id = message["tool_call_id"]
+                        .as_str()
+                        .ok_or("tool message without a tool_call_id")?;
+                    let result = UserContent::tool_result(
+                        id,
+                        OneOrMany::one(ToolResultContent::text(content_text(&message["content"]))),
+                    );
+                    // Results of one turn's calls go back together.
+                    match history.last_mut() {
+                        Some(Message::User { content })
+                            if content
+                                .iter()
+                                .all(|item| matches!(item, UserContent::ToolResult(_))) =>
+                        {
+                            content.push(result)
+                        }
+                        _ => history.push(Message::User {
+                            content: OneOrMany::one(result),
+                        }),
+                    }
+                }
+                role => return Err(format!("unsupported message role '{role}'")),
+            }
+        }
+        let chat_history = OneOrMany::many(history)
+            .map_err(|_| "messages has no user or assistant message".to_string())?;
+
+        let tools = self
+            .tools
+            .iter()
+            .map(|tool| -> Result<ToolDefinition, String> {
+                let function = &tool["function"];
+                let name = function["name"]
+                    .as_str()
+                    .ok_or("tool without a function name")?;
+                Ok(ToolDefinition {
+                    name: name.to_string(),
+                    description: function["description"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    parameters: function
+                        .get("parameters")
+                        .cloned()
+                        .unwrap_or_else(|| serde_json::json!({"type": "object"})),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let additional_params = self
+            .response_format
+            .as_ref()
+            .map(output_format)
+            .transpose()?
+            .flatten()
+            .map(|format| format.to_params());
+
+        Ok(CompletionRequest {
+            preamble: (!system.is_empty()).then(|| system.join("\n\n")),
+            chat_history,
+            documents: Vec::new(),
+            tools,
+            temperature: self.temperature,
+            max_tokens: self.max_completion_tokens.or(self.max_tokens),
+            tool_choice: None,
+            additional_params,
+        })
+    }
+}
+
+/// Text of a message's content, given as a string or a list of parts.
+fn content_text(content: &serde_json::Value) -> String {
+    match content {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn user_content(content: &serde_json::Value) -> Result<OneOrMany<UserContent>, String> {
+    let items = match content {
+        serde_json::Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part["type"].as_str() {
+                Some("text") => part["text"].as_str().map(UserContent::text),
+                Some("image_url") => part["image_url"]["url"].as_str().map(image),
+                _ => None,
+            })
+            .collect(),
+        other => vec![UserContent::text(content_text(other))],
+    };
+    OneOrMany::many(items).map_err(|_| "user message without text or images".to_string())
+}
+
+/// An image part from a data URL or a link.
+fn image(url: &str) -> UserContent {
+    let data_url = url
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"));
+    match data_url {
+        Some((mime_type, data)) => {
+            UserContent::image_base64(data, ImageMediaType::from_mime_type(mime_type), None)
+        }
+        None => UserContent::image_url(url, None, None),
+    }
+}
+
+/// The text and tool calls of an assistant message, or `None` when it has
+/// neither.
+fn assistant_content(
+    message: &serde_json::Value,
+) -> Result<Option<OneOrMany<AssistantContent>>, String> {
+    let mut content = Vec::new();
+    let text = content_text(&message["content"]);
+    if !text.is_empty() {
+        content.push(AssistantContent::text(text));
+    }
+    for call in message["tool_calls"].as_array().into_iter().flatten() {
+        let id = call["id"].as_str().ok_or("tool call without an id")?;
+        let function = &call["function"];
+        let name = function["name"]
+            .as_str()
+            .ok_or("tool call without a function name")?;
+        // Arguments arrive as a JSON string.
+        let arguments = match &function["arguments"] {
+            serde_json::Value::String(arguments) => serde_json::from_str(arguments)
+                .map_err(|error| format!("tool call {id} has invalid arguments: {error}"))?,
+            serde_json::Value::Null => serde_json::json!({}),
+            arguments => arguments.clone(),
+        };
+        content.push(AssistantContent::tool_call(id, name, arguments));
+    }
+    Ok(OneOrMany::many(content).ok())
+}
+
+/// The output format a `response_format` asks for. Plain text is `None`.
+fn output_format(response_format: &serde_json::Value) -> Result<Option<OutputFormat>, String> {
+    match response_format["type"].as_str() {
+        Some("text") | None => Ok(None),
+        Some("json_object") => Ok(Some(OutputFormat::Json)),
+        Some("json_schema") => {
+            let schema = &response_format["json_schema"];
+            Ok(Some(OutputFormat::JsonSchema {
+                name: schema["name"].as_str().unwrap_or("response").to_string(),
+                schema: schema["schema"].clone(),
+            }))
+        }
+        Some(other) => Err(format!("unsupported response_format type '{other}'")),
+    }
+}
+
+/// A `chat.completion` object for a response from `model`. Its reasoning
+/// goes in `reasoning_content` when `display` passes it on.
+pub fn to_response(
+    response: &completion::CompletionResponse<RawResponse>,
+    model: &str,
+    display: ReasoningDisplay,
+) -> serde_json::Value {
+    let mut text = Vec::new();
+    let mut tool_calls = Vec::new();
+    for content in response.choice.iter() {
+        match content {
+            AssistantContent::Text(part) => text.push(part.text.as_str()),
+            AssistantContent::ToolCall(call) => tool_calls.push(serde_json::json!({
+                "id": call.id,
+                "type": "function",
+                "function": {
+                    "name": call.function.name,
+                    "arguments": call.function.arguments.to_string(),
+                },
+            })),
+            _ => {}
+        }
+    }
+
+    let mut message = serde_json::json!({
+        "role": "assistant",
+        "content": (!text.is_empty()).then(|| text.join("\n")),
+    });
+    if display == ReasoningDisplay::Summary
+        && let Some(thoughts) = reasoning::reasoning_text(response.choice.iter())
+    {
+        message["reasoning_content"] = serde_json::json!(reasoning::summarize(&thoughts));
+    }
+    let finish_reason = if tool_calls.is_empty() {
+        "stop"
+    } else {
+        message["tool_calls"] = serde_json::json!(tool_calls);
+        "tool_calls"
+    };
+
+    serde_json::json!({
+        "id": format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+        "object": "chat.completion",
+        "created": chrono::Utc::now().timestamp(),
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": message,
+            "finish_reason": finish_reason,
+        }],
+        "usage": usage(&response.usage, response.raw_response.reasoning_tokens()),
+    })
+}
+
+/// Writes a streamed reply as `chat.completion.chunk` objects, all sharing
+/// one id.
+pub struct ChunkWriter {
+    id: String,
+    created: i64,
+    model: String,
+    include_usage: bool,
+}
+
+impl ChunkWriter {
+    pub fn new(model: &str, include_usage: bool) -> Self {
+        Self {
+            id: format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+            created: chrono::Utc::now().timestamp(),
+            model: model.to_string(),
+            include_usage,
+        }
+    }
+
+    /// The first chunk, opening the assistant's message.
+    pub fn start(&self) -> serde_json::Value {
+        self.chunk(
+            serde_json::json!({"role": "assistant", "content": ""}),
+            None,
+        )
+    }
+
+    /// The chunks for one event of the reply. The finishing event gets the
+    /// finish reason and, when asked for, a usage chunk with no choices.
+    pub fn chunks(&self, event: &StreamEvent) -> Vec<serde_json::Value> {
+        match event {
+            StreamEvent::Text(text) => vec![self.chunk(serde_json::json!({"content": text}), None)],
+            StreamEvent::ToolCall { index, id, name } => vec![self.chunk(
+                serde_json::json!({"tool_calls": [{
+                    "index": index,
+                    "id": id,
+                    "type": "function",
+                    "function": {"name": name, "arguments": ""},
+                }]}),
+                None,
+            )],
+            StreamEvent::ToolCallArguments { index, delta } => vec![self.chunk(
+                serde_json::json!({"tool_calls": [{
+                    "index": index,
+                    "function": {"arguments": delta},
+                }]}),
+                None,
+            )],
+            StreamEvent::Finished {
+                finish_reason,
+                usage: finished_usage,
+                reasoning_tokens,
+            } => {
+                let mut chunks = vec![self.chunk(serde_json::json!({}), Some(finish_reason))];
+                if self.include_usage {
+                    let mut last = self.chunk(serde_json::json!({}), None);
+                    last["choices"] = serde_json::json!([]);
+                    last["usage"] = usage(finished_usage, *reasoning_tokens);
+                    chunks.push(last);
+                }
+                chunks
+            }
+        }
+    }
+
+    fn chunk(&self, delta: serde_json::Value, finish_reason: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{
+                "index": 0,
+                "delta": delta,
+                "finish_reason": finish_reason,
+            }],
+        })
+    }
+}
+
+fn usage(usage: &completion::Usage, reasoning_tokens: u64) -> serde_json::Value {
+    serde_json::json!({
+        "prompt_tokens": usage.input_tokens,
+        "completion_tokens": usage.output_tokens,
+        "total_tokens": usage.input_tokens + usage.output_tokens,
+        "prompt_tokens_details": {"cached_tokens": usage.cached_input_tokens},
+        "completion_tokens_details": {"reasoning_tokens": reasoning_tokens},
+    })
+}
+
+/// A `GET /v1/models` list of `models`. Each is owned by its provider
+/// prefix; aliases, which have none, by spacebot.
+pub fn models_response<'a>(models: impl IntoIterator<Item = &'a str>) -> serde_json::Value {
+    let data: Vec<_> = models
+        .into_iter()
+        .map(|model| {
+            let owned_by = model
+                .split_once('/')
+                .map_or("spacebot", |(provider, _)| provider);
+            serde_json::json!({
+                "id": model,
+                "object": "model",
+                "created": 0,
+                "owned_by": owned_by,
+            })
+        })
+        .collect();
+    serde_json::json!({"object": "list", "data": data})
+}
+
+/// An OpenAI-style error body.
+pub fn error_body(message: &str, error_type: &str) -> serde_json::Value {
+    serde_json::json!({
+        "error": {
+            "message": message,
+            "type": error_type,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_lists_name_each_model_owner() {
+        let list = models_response(["fast", "openrouter/anthropic/claude-sonnet-4"]);
+        assert_eq!(list["object"], "list");
+        assert_eq!(list["data"][0]["id"], "fast");
+        assert_eq!(list["data"][0]["owned_by"], "spacebot");
+        assert_eq!(
+            list["data"][1]["id"],
+            "openrouter/anthropic/claude-sonnet-4"
+        );
+        assert_eq!(list["data"][1]["owned_by"], "openrouter");
+        assert_eq!(list["data"][1]["object"], "model");
+    }
+
+    #[test]
+    fn chat_requests_translate_to_completion_requests() {
+        let request: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4.1",
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "What's in this image, and the weather?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0K"}},
+                ]},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function",
+                     "function": {"name": "weather", "arguments": "{\"city\":\"Oslo\"}"}},
+                    {"id": "call_2", "type": "function",
+                     "function": {"name": "weather", "arguments": "{\"city\":\"Bergen\"}"}},
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "4°C"},
+                {"role": "tool", "tool_call_id": "call_2", "content": "7°C"},
+            ],
+            "tools": [{"type": "function", "function": {"name": "weather", "parameters": {}}}],
+            "max_completion_tokens": 200,
+            "response_format": {"type": "json_object"},
+        }))
+        .unwrap();
+        let converted = request.to_completion_request().unwrap();
+
+        assert_eq!(converted.preamble.as_deref(), Some("Be brief."));
+        assert_eq!(converted.chat_history.len(), 3);
+        assert_eq!(converted.tools[0].name, "weather");
+        assert_eq!(converted.max_tokens, Some(200));
+        assert!(OutputFormat::from_request(&converted).unwrap() == Some(OutputFormat::Json));
+
+        let Message::User { content } = converted.chat_history.first() else {
+            panic!("expected a user message");
+        };
+        assert!(matches!(
+            content.iter().nth(1),
+            Some(UserContent::Image(image)) if image.media_type == Some(ImageMediaType::PNG)
+        ));
+        let Some(Message::User { content: results }) = converted.chat_history.iter().last() else {
+            panic!("expected tool results");
+        };
+        assert_eq!(results.len(), 2);
+
+        let empty: ChatCompletionRequest = serde_json::from_value(serde_json::json!({
+            "model": "gpt-4.1",
+            "messages": [{"role": "system", "content": "Be brief."}],
+        }))
+        .unwrap();
+        assert!(empty.to_completion_request().is_err());
+    }
+
+    #[test]
+    fn responses_translate_to_chat_completions() {
+        let response = completion::CompletionResponse {
+            choice: OneOrMany::many(vec![
+                AssistantContent::Reasoning(rig::message::Reasoning::new("They want weather.")),
+                AssistantContent::text("Checking."),
+                AssistantContent::tool_call(
+                    "call_1",
+                    "weather",
+                    serde_json::json!({"city": "Oslo"}),
+                ),
+            ])
+            .unwrap(),
+            usage: completion::Usage {
+                input_tokens: 12,
+                output_tokens: 5,
+                total_tokens: 17,
+                cached_input_tokens: 4,
+            },
+            raw_response: RawResponse {
+                body: serde_json::json!({}),
+            },
+        };
+        let body = to_response(&response, "openai/gpt-4.1", ReasoningDisplay::Hidden);
+
+        assert_eq!(body["model"], "openai/gpt-4.1");
+        let choice = &body["choices"][0];
+        assert!(choice["message"].get("reasoning_content").is_none());
+        assert_eq!(choice["finish_reason"], "tool_calls");
+        assert_eq!(choice["message"]["content"], "Checking.");
+        assert_eq!(
+            choice["message"]["tool_calls"][0]["function"]["arguments"],
+            "{\"city\":\"Oslo\"}"
+        );
+        assert_eq!(body["usage"]["total_tokens"], 17);
+        assert_eq!(body["usage"]["prompt_tokens_details"]["cached_tokens"], 4);
+
+        let body = to_response(&response, "openai/gpt-4.1", ReasoningDisplay::Summary);
+        assert_eq!(
+            body["choices"][0]["message"]["reasoning_content"],
+            "They want weather."
+        );
+    }
+
+    #[test]
+    fn stream_events_translate_to_chunks() {
+        let writer = ChunkWriter::new("anthropic/claude-sonnet-4", true);
+        let start = writer.start();
+        assert_eq!(start["object"], "chat.completion.chunk");
+        assert_eq!(start["choices"][0]["delta"]["role"], "assistant");
+
+        let call = &writer.chunks(&StreamEvent::ToolCall {
+            index: 0,
+            id: "toolu_1".into(),
+            name: "weather".into(),
+        })[0];
+        assert_eq!(call["id"], start["id"]);
+        assert_eq!(
+            call["choices"][0]["delta"]["tool_calls"][0]["id"],
+            "toolu_1"
+        );
+        assert!(call["choices"][0]["finish_reason"].is_null());
+
+        let finished = writer.chunks(&StreamEvent::Finished {
+            finish_reason: "tool_calls",
+            usage: completion::Usage {
+                input_tokens: 12,
+                output_tokens: 5,
+                total_tokens: 17,
+                cached_input_tokens: 0,
+            },
+            reasoning_tokens: 3,
+        });
+        assert_eq!(finished.len(), 2);
+        assert_eq!(finished[0]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(finished[1]["choices"], serde_json::json!([]));
+        assert_eq!(finished[1]["usage"]["total_tokens"], 17);
+        assert_eq!(
+            finished[1]["usage"]["completion_tokens_details"]["reasoning_tokens"],
+            3
+        );
+
+        let without_usage = ChunkWriter::new("openai/gpt-4.1", false);
+        let finished = without_usage.chunks(&StreamEvent::Finished {
+            finish_reason: "stop",
+            usage: completion::Usage::new(),
+            reasoning_tokens: 0,
+        });
+        assert_eq!(finished.len(), 1);
+    }
+}
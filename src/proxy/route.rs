@@ -0,0 +1,179 @@
+//! Per-request routing overrides.
+//!
+//! A proxied request normally goes to the model it names, falling back
+//! through the chain `[defaults.routing.fallbacks]` configures for that model.
+//! Callers can override that for one request with an `x-spacebot-route`
+//! header, or, when their client can't set headers, with a `route:` model:
+//!
+//! ```text
+//! x-spacebot-route: openai/gpt-4.1, anthropic/claude-sonnet-4
+//! "model": "route:openai/gpt-4.1,anthropic/claude-sonnet-4"
+//! ```
+//!
+//! A route is a comma-separated chain of models (or aliases): the first is
+//! tried, then the rest in order, and no configured fallbacks. A route
+//! header entry that names a configured provider, like `openrouter`, serves
+//! the request's model from that provider, under its full upstream path:
+//! `openrouter` serves `anthropic/claude-sonnet-4` as
+//! `openrouter/anthropic/claude-sonnet-4`.
+
+/// Header that overrides routing for one request.
+pub const ROUTE_HEADER: &str = "x-spacebot-route";
+/// Model prefix that overrides routing, for clients that can't set headers.
+pub const ROUTE_PREFIX: &str = "route:";
+
+/// Which models serve a request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// The model tried first.
+    pub model: String,
+    /// Models tried in order after it. `None` uses the configured fallbacks.
+    pub fallbacks: Option<Vec<String>>,
+}
+
+impl Route {
+    /// The route for a request naming `model`, with the route header's value
+    /// if it had one. `is_provider` says whether a name is a configured
+    /// provider.
+    pub fn resolve(
+        model: &str,
+        header: Option<&str>,
+        is_provider: impl Fn(&str) -> bool,
+    ) -> Result<Self, String> {
+        let model = model.trim();
+        let prefixed = model.strip_prefix(ROUTE_PREFIX);
+        if let Some(header) = header.filter(|header| !header.trim().is_empty()) {
+            let requested = prefixed.is_none().then_some(model);
+            return chain(header, requested, &is_provider);
+        }
+        if let Some(spec) = prefixed {
+            return chain(spec, None, &is_provider);
+        }
+        if model.is_empty() {
+            return Err("the request names no model".into());
+        }
+        Ok(Self {
+            model: model.to_string(),
+            fallbacks: None,
+        })
+    }
+}
+
+/// Parse a route chain. Provider entries serve `requested` from that
+/// provider, and are refused when there's no requested model.
+///
+/// The model keeps its vendor segment, since aggregators need it: only a
+/// provider entry that is the vendor itself drops the prefix, and a model
+/// requested through an aggregator (`openrouter/anthropic/...`) is
+/// re-served under its upstream path (`anthropic/...`).
+fn chain(
+    spec: &str,
+    requested: Option<&str>,
+    is_provider: &impl Fn(&str) -> bool,
+) -> Result<Route, String> {
+    let models = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            if entry.contains('/') || !is_provider(entry) {
+                return Ok(entry.to_string());
+            }
+            let requested = requested.filter(|model| !model.is_empty()).ok_or_else(|| {
+                format!("route entry '{entry}' is a provider, but the request names no model")
+            })?;
+            let upstream = match requested.split_once('/') {
+                Some((provider, upstream)) if is_provider(provider) && upstream.contains('/') => {
+                    upstream
+                }
+                _ => requested,
+            };
+            match upstream.split_once('/') {
+                Some((vendor, _)) if vendor == entry => Ok(upstream.to_string()),
+                _ => Ok(format!("{entry}/{upstream}")),
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let Some((model, fallbacks)) = models.split_first() else {
+        return Err(format!("route '{spec}' names no models"));
+    };
+    Ok(Route {
+        model: model.clone(),
+        fallbacks: Some(fallbacks.to_vec()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(model: &str, header: Option<&str>) -> Result<Route, String> {
+        Route::resolve(model, header, |name| {
+            ["openai", "anthropic", "openrouter"].contains(&name)
+        })
+    }
+
+    #[test]
+    fn routes_come_from_the_header_then_the_model() {
+        assert_eq!(
+            resolve("anthropic/claude-sonnet-4", None).unwrap(),
+            Route {
+                model: "anthropic/claude-sonnet-4".into(),
+                fallbacks: None,
+            }
+        );
+        assert_eq!(
+            resolve(
+                "anthropic/claude-sonnet-4",
+                Some("openai/gpt-4.1, fast ,anthropic/claude-haiku-4.5")
+            )
+            .unwrap(),
+            Route {
+                model: "openai/gpt-4.1".into(),
+                fallbacks: Some(vec!["fast".into(), "anthropic/claude-haiku-4.5".into()]),
+            }
+        );
+        assert_eq!(
+            resolve("route:openai/gpt-4.1", None).unwrap(),
+            Route {
+                model: "openai/gpt-4.1".into(),
+                fallbacks: Some(Vec::new()),
+            }
+        );
+        assert_eq!(
+            resolve("route:openai/gpt-4.1", Some("anthropic/claude-sonnet-4"))
+                .unwrap()
+                .model,
+            "anthropic/claude-sonnet-4"
+        );
+    }
+
+    #[test]
+    fn provider_entries_serve_the_requested_model() {
+        let route = resolve("anthropic/claude-sonnet-4", Some("openrouter, anthropic")).unwrap();
+        assert_eq!(route.model, "openrouter/anthropic/claude-sonnet-4");
+        assert_eq!(
+            route.fallbacks,
+            Some(vec!["anthropic/claude-sonnet-4".into()])
+        );
+        let route = resolve(
+            "openrouter/anthropic/claude-sonnet-4",
+            Some("anthropic, openrouter"),
+        )
+        .unwrap();
+        assert_eq!(route.model, "anthropic/claude-sonnet-4");
+        assert_eq!(
+            route.fallbacks,
+            Some(vec!["openrouter/anthropic/claude-sonnet-4".into()])
+        );
+        assert_eq!(
+            resolve("gpt-4.1", Some("openai")).unwrap().model,
+            "openai/gpt-4.1"
+        );
+
+        assert!(resolve("route:openai", None).is_err());
+        assert!(resolve("route:", None).is_err());
+        assert!(resolve("", None).is_err());
+        assert!(resolve("gpt-4.1", Some(" , ")).is_err());
+    }
+}
@@ -0,0 +1,415 @@
+//! Reminders: one-off messages a user asked for ("remind me in 2 hours to
+//! ..."), posted back in the channel they asked in with a mention.
+//!
+//! Reminders are stored in the `reminders` table and delivered by a polling
+//! loop, so they survive restarts: anything that fell due while the agent was
+//! down goes out on the next poll, marked with the time it was due.
+
+pub mod parse;
+
+use crate::conversation::{ChannelStore, ConversationLogger};
+use crate::db::{Column, SqlPool, with_pool};
+use crate::error::Result;
+use crate::{AgentDeps, InboundMessage, OutboundResponse};
+
+use anyhow::Context as _;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+
+/// How often the loop checks for due reminders.
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Delivery attempts before a reminder is marked failed.
+const MAX_ATTEMPTS: i64 = 5;
+
+/// A reminder delivered this much after its due time says when it was due.
+const LATE_AFTER: chrono::Duration = chrono::Duration::minutes(2);
+
+/// Who asked for a reminder, and how to mention them on their platform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requester {
+    pub sender_id: String,
+    pub mention: String,
+}
+
+impl Requester {
+    /// The sender of `message`, or `None` for system re-triggers.
+    pub fn from_message(message: &InboundMessage) -> Option<Self> {
+        if message.source == "system" {
+            return None;
+        }
+        let metadata_str = |key: &str| message.metadata.get(key).and_then(|value| value.as_str());
+        let display_name = metadata_str("sender_display_name").unwrap_or(&message.sender_id);
+        let mention = match message.source.as_str() {
+            "discord" | "slack" => format!("<@{}>", message.sender_id),
+            "telegram" => match metadata_str("telegram_username") {
+                Some(username) => format!("@{username}"),
+                None => display_name.to_string(),
+            },
+            _ => display_name.to_string(),
+        };
+        Some(Self {
+            sender_id: message.sender_id.clone(),
+            mention,
+        })
+    }
+}
+
+/// A stored reminder.
+#[derive(Debug, Clone, Serialize)]
+pub struct Reminder {
+    pub id: String,
+    pub channel_id: String,
+    pub sender_id: String,
+    pub mention: String,
+    pub message: String,
+    pub due_at: DateTime<Utc>,
+}
+
+/// Reminder storage (SQLite or Postgres).
+#[derive(Debug, Clone)]
+pub struct ReminderStore {
+    pool: SqlPool,
+}
+
+impl ReminderStore {
+    pub fn new(pool: SqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// Store a new reminder.
+    pub async fn create(&self, reminder: &Reminder) -> Result<()> {
+        let query_str = format!(
+            "INSERT INTO reminders (id, channel_id, sender_id, mention, message, due_at) \
+             VALUES ($1, $2, $3, $4, $5, {})",
+            self.pool.timestamp_placeholder(6)
+        );
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(&query_str)
+                .bind(&reminder.id)
+                .bind(&reminder.channel_id)
+                .bind(&reminder.sender_id)
+                .bind(&reminder.mention)
+                .bind(&reminder.message)
+                .bind(self.pool.timestamp_param(reminder.due_at))
+                .execute(pool)
+                .await
+                .map(drop)
+        })
+        .context("failed to save reminder")?;
+
+        Ok(())
+    }
+
+    /// Pending reminders `sender_id` set in `channel_id`, soonest first.
+    pub async fn pending(&self, channel_id: &str, sender_id: &str) -> Result<Vec<Reminder>> {
+        let reminders = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT id, channel_id, sender_id, mention, message, due_at FROM reminders \
+                 WHERE channel_id = $1 AND sender_id = $2 AND status = 'pending' \
+                 ORDER BY due_at",
+            )
+            .bind(channel_id)
+            .bind(sender_id)
+            .fetch_all(pool)
+            .await
+            .and_then(|rows| rows.iter().map(reminder_from_row).collect())
+        })
+        .context("failed to load reminders")?;
+
+        Ok(reminders)
+    }
+
+    /// Every reminder `sender_id` set, in any state.
+    pub async fn by_sender(&self, sender_id: &str) -> Result<Vec<Reminder>> {
+        let reminders = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT id, channel_id, sender_id, mention, message, due_at FROM reminders \
+                 WHERE sender_id = $1 ORDER BY due_at",
+            )
+            .bind(sender_id)
+            .fetch_all(pool)
+            .await
+            .and_then(|rows| rows.iter().map(reminder_from_row).collect())
+        })
+        .with_context(|| format!("failed to load reminders from {sender_id}"))?;
+
+        Ok(reminders)
+    }
+
+    /// Pending reminders due at or before `now`, oldest first.
+    pub async fn due(&self, now: DateTime<Utc>) -> Result<Vec<Reminder>> {
+        let query_str = format!(
+            "SELECT id, channel_id, sender_id, mention, message, due_at FROM reminders \
+             WHERE status = 'pending' AND due_at <= {} \
+             ORDER BY due_at LIMIT 50",
+            self.pool.timestamp_placeholder(1)
+        );
+        let reminders = with_pool!(&self.pool, |pool| {
+            sqlx::query(&query_str)
+                .bind(self.pool.timestamp_param(now))
+                .fetch_all(pool)
+                .await
+                .and_then(|rows| rows.iter().map(reminder_from_row).collect())
+        })
+        .context("failed to load due reminders")?;
+
+        Ok(reminders)
+    }
+
+    /// Delete a pending reminder set by `sender_id`. Returns whether one was
+    /// deleted.
+    pub async fn cancel(&self, id: &str, sender_id: &str) -> Result<bool> {
+        let deleted = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "DELETE FROM reminders WHERE id = $1 AND sender_id = $2 AND status = 'pending'",
+            )
+            .bind(id)
+            .bind(sender_id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+        })
+        .context("failed to cancel reminder")?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Delete every reminder `sender_id` set. Returns how many were deleted.
+    pub async fn delete_by_sender(&self, sender_id: &str) -> Result<u64> {
+        let deleted = with_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM reminders WHERE sender_id = $1")
+                .bind(sender_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })
+        .with_context(|| format!("failed to delete reminders from {sender_id}"))?;
+
+        Ok(deleted)
+    }
+
+    pub async fn mark_delivered(&self, id: &str) -> Result<()> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "UPDATE reminders SET status = 'delivered', delivered_at = CURRENT_TIMESTAMP \
+                 WHERE id = $1",
+            )
+            .bind(id)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
+        .context("failed to mark reminder delivered")?;
+
+        Ok(())
+    }
+
+    /// Count a failed delivery attempt. After `MAX_ATTEMPTS`, or right away
+    /// when `permanent`, the reminder is marked failed and no longer retried.
+    pub async fn record_failure(&self, id: &str, permanent: bool) -> Result<()> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "UPDATE reminders SET attempts = attempts + 1, \
+                 status = CASE WHEN $2 OR attempts + 1 >= $3 THEN 'failed' ELSE status END \
+                 WHERE id = $1",
+            )
+            .bind(id)
+            .bind(permanent)
+            .bind(MAX_ATTEMPTS)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
+        .context("failed to record reminder failure")?;
+
+        Ok(())
+    }
+}
+
+fn reminder_from_row<R>(row: &R) -> std::result::Result<Reminder, sqlx::Error>
+where
+    R: sqlx::Row,
+    for<'c> &'c str: sqlx::ColumnIndex<R>,
+    String: Column<R::Database>,
+    DateTime<Utc>: Column<R::Database>,
+{
+    Ok(Reminder {
+        id: row.try_get("id")?,
+        channel_id: row.try_get("channel_id")?,
+        sender_id: row.try_get("sender_id")?,
+        mention: row.try_get("mention")?,
+        message: row.try_get("message")?,
+        due_at: row.try_get("due_at")?,
+    })
+}
+
+/// Spawn the loop that delivers due reminders.
+///
+/// Runs until the returned JoinHandle is dropped or aborted.
+pub fn spawn_reminder_loop(deps: AgentDeps) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move { run_reminder_loop(&deps).await })
+}
+
+async fn run_reminder_loop(deps: &AgentDeps) {
+    let Some(messaging) = deps.messaging_manager.clone() else {
+        tracing::debug!("no messaging adapters, reminder loop not started");
+        return;
+    };
+    tracing::info!("reminder loop started");
+    let store = ReminderStore::new(deps.sql_pool.clone());
+    let channel_store = ChannelStore::new(deps.sql_pool.clone());
+    let conversation_logger =
+        ConversationLogger::new(deps.sql_pool.clone()).batched(deps.conversation_writes.clone());
+
+    loop {
+        let now = Utc::now();
+        match store.due(now).await {
+            Ok(reminders) => {
+                for reminder in reminders {
+                    let channel = channel_store.get(&reminder.channel_id).await;
+                    let target = match &channel {
+                        Ok(Some(info)) => {
+                            crate::tools::send_message_to_another_channel::resolve_broadcast_target(
+                                info,
+                            )
+                        }
+                        _ => None,
+                    };
+                    let Some((adapter, target)) = target else {
+                        tracing::warn!(
+                            reminder_id = %reminder.id,
+                            channel_id = %reminder.channel_id,
+                            "can't deliver reminder: channel not found or not a broadcast target"
+                        );
+                        if let Err(error) =
+                            store.record_failure(&reminder.id, channel.is_ok()).await
+                        {
+                            tracing::error!(%error, "failed to record reminder failure");
+                        }
+                        continue;
+                    };
+
+                    let text = delivery_text(&reminder, now);
+                    let sent = messaging
+                        .broadcast(&adapter, &target, OutboundResponse::Text(text.clone()))
+                        .await;
+                    let recorded = match sent {
+                        Ok(()) => {
+                            conversation_logger
+                                .log_bot_message(&reminder.channel_id.as_str().into(), &text);
+                            store.mark_delivered(&reminder.id).await
+                        }
+                        Err(error) => {
+                            tracing::warn!(
+                                %error,
+                                reminder_id = %reminder.id,
+                                "failed to deliver reminder"
+                            );
+                            store.record_failure(&reminder.id, false).await
+                        }
+                    };
+                    if let Err(error) = recorded {
+                        tracing::error!(%error, reminder_id = %reminder.id, "failed to update reminder");
+                    }
+                }
+            }
+            Err(error) => tracing::warn!(%error, "failed to load due reminders"),
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// The posted reminder; a late one (e.g. after a restart) says when it was
+/// due, in local time.
+fn delivery_text(reminder: &Reminder, now: DateTime<Utc>) -> String {
+    let mut text = format!("{} Reminder: {}", reminder.mention, reminder.message.trim());
+    if now - reminder.due_at > LATE_AFTER {
+        text.push_str(&format!(
+            " (was due {})",
+            reminder
+                .due_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+        ));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reminder(sender_id: &str, due_at: DateTime<Utc>) -> Reminder {
+        Reminder {
+            id: uuid::Uuid::new_v4().to_string(),
+            channel_id: "discord:1:2".into(),
+            sender_id: sender_id.into(),
+            mention: format!("<@{sender_id}>"),
+            message: "stretch".into(),
+            due_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn due_reminders_are_delivered_once() {
+        let memory_store = crate::memory::MemoryStore::connect_in_memory().await;
+        let store = ReminderStore::new(memory_store.pool().clone());
+        let now = Utc::now();
+        let overdue = reminder("42", now - chrono::Duration::hours(1));
+        let upcoming = reminder("42", now + chrono::Duration::hours(1));
+        store.create(&overdue).await.expect("create");
+        store.create(&upcoming).await.expect("create");
+
+        let due = store.due(now).await.expect("due");
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, overdue.id);
+        assert_eq!(due[0].due_at.timestamp(), overdue.due_at.timestamp());
+        assert!(delivery_text(&due[0], now).starts_with("<@42> Reminder: stretch (was due "));
+
+        store.mark_delivered(&overdue.id).await.expect("deliver");
+        assert!(store.due(now).await.expect("due").is_empty());
+        assert_eq!(
+            store
+                .pending("discord:1:2", "42")
+                .await
+                .expect("pending")
+                .len(),
+            1
+        );
+
+        assert!(!store.cancel(&upcoming.id, "7").await.expect("cancel"));
+        assert!(store.cancel(&upcoming.id, "42").await.expect("cancel"));
+        assert!(
+            store
+                .pending("discord:1:2", "42")
+                .await
+                .expect("pending")
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn reminders_fail_after_repeated_errors() {
+        let memory_store = crate::memory::MemoryStore::connect_in_memory().await;
+        let store = ReminderStore::new(memory_store.pool().clone());
+        let now = Utc::now();
+        let overdue = reminder("42", now - chrono::Duration::minutes(1));
+        store.create(&overdue).await.expect("create");
+
+        for _ in 1..MAX_ATTEMPTS {
+            store
+                .record_failure(&overdue.id, false)
+                .await
+                .expect("failure");
+        }
+        assert_eq!(store.due(now).await.expect("due").len(), 1);
+        store
+            .record_failure(&overdue.id, false)
+            .await
+            .expect("failure");
+        assert!(store.due(now).await.expect("due").is_empty());
+    }
+}
@@ -0,0 +1,394 @@
+//! Natural-language reminder times: "in 2 hours", "tomorrow 9am",
+//! "next tuesday at 3:30pm", "march 5", "2026-03-05 14:00".
+//!
+//! Parsing works on local wall-clock time. A day without a time means 9am;
+//! a time without a day means its next occurrence.
+
+use chrono::{Datelike as _, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+/// Time used when only a day is given.
+const DEFAULT_HOUR: u32 = 9;
+
+/// Resolve `text` against the local time `now`. Returns `None` when the
+/// text isn't a time this parser understands; the result may be in the past
+/// (e.g. "today 8am" in the afternoon).
+pub fn parse_when(text: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let text = text
+        .trim()
+        .trim_end_matches(['.', '!', '?'])
+        .to_lowercase()
+        .replace(',', " ");
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    match tokens.split_first() {
+        Some((&"in", rest)) => parse_offset(rest, now),
+        Some(_) => parse_moment(&tokens, now),
+        None => None,
+    }
+}
+
+/// "in ..." offsets: "2 hours", "an hour and 30 minutes", "1h30m", "half an
+/// hour", "3 weeks", "2 months".
+fn parse_offset(tokens: &[&str], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let mut seconds = 0.0;
+    let mut months = 0;
+    let mut amount: Option<f64> = None;
+    let mut parsed_any = false;
+
+    for &token in tokens {
+        match token {
+            "and" if amount.is_none() => continue,
+            "a" | "an" if amount.is_none() || amount == Some(0.5) => {
+                amount.get_or_insert(1.0);
+                continue;
+            }
+            "half" if amount.is_none() => {
+                amount = Some(0.5);
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some(value) = amount.take() {
+            add_unit(token, value, &mut seconds, &mut months)?;
+            parsed_any = true;
+        } else if let Ok(value) = token.parse::<f64>() {
+            amount = Some(value);
+        } else {
+            // Compact forms: "2h", "90min", "1h30m".
+            for (value, unit) in split_compact(token)? {
+                add_unit(unit, value, &mut seconds, &mut months)?;
+            }
+            parsed_any = true;
+        }
+    }
+
+    if amount.is_some() || !parsed_any {
+        return None;
+    }
+    // Offsets too large for a `Duration` aren't times.
+    now.checked_add_months(Months::new(months))?
+        .checked_add_signed(Duration::try_seconds(seconds as i64)?)
+}
+
+fn add_unit(unit: &str, value: f64, seconds: &mut f64, months: &mut u32) -> Option<()> {
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    let unit_seconds = match unit {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3_600.0,
+        "d" | "day" | "days" => 86_400.0,
+        "w" | "wk" | "wks" | "week" | "weeks" => 604_800.0,
+        "mo" | "month" | "months" if value.fract() == 0.0 => {
+            *months = months.checked_add(value as u32)?;
+            return Some(());
+        }
+        _ => return None,
+    };
+    *seconds += value * unit_seconds;
+    Some(())
+}
+
+/// Split "1h30m" into `[(1, "h"), (30, "m")]`.
+fn split_compact(token: &str) -> Option<Vec<(f64, &str)>> {
+    let mut parts = Vec::new();
+    let mut rest = token;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter(|&end| end > 0)?;
+        let unit_len = rest[digits..]
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len() - digits);
+        if unit_len == 0 {
+            return None;
+        }
+        parts.push((
+            rest[..digits].parse().ok()?,
+            &rest[digits..digits + unit_len],
+        ));
+        rest = &rest[digits + unit_len..];
+    }
+    Some(parts)
+}
+
+/// A day and/or a time of day, in any order: "tomorrow 9am", "5pm friday",
+/// "next tuesday at noon", "march 5th", "2026-03-05T14:00".
+fn parse_moment(tokens: &[&str], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let today = now.date();
+    let mut date: Option<NaiveDate> = None;
+    let mut time: Option<NaiveTime> = None;
+    let mut default_time = NaiveTime::from_hms_opt(DEFAULT_HOUR, 0, 0)?;
+    let mut after_at = false;
+    let mut index = 0;
+
+    while index < tokens.len() {
+        let token = tokens[index];
+        let next = tokens.get(index + 1).copied();
+        let mut consumed = 1;
+
+        match token {
+            "at" => {
+                after_at = true;
+                index += 1;
+                continue;
+            }
+            "on" | "the" | "of" | "this" | "next" if next.is_some() => {}
+            "today" => date = Some(today),
+            "tonight" => {
+                date = Some(today);
+                default_time = NaiveTime::from_hms_opt(20, 0, 0)?;
+            }
+            "tomorrow" => date = Some(today.succ_opt()?),
+            "week" if index > 0 && tokens[index - 1] == "next" => {
+                date = Some(today + Duration::days(7));
+            }
+            _ => {
+                if let Some(weekday) = parse_weekday(token) {
+                    date = Some(next_weekday(today, weekday));
+                } else if let Some((day, len)) = parse_month_day(&tokens[index..], today) {
+                    date = Some(day);
+                    consumed = len;
+                } else if let Some((day, clock)) = parse_iso(token) {
+                    date = Some(day);
+                    if clock.is_some() {
+                        time = clock;
+                    }
+                } else if let Some((clock, len)) = parse_clock(token, next, after_at) {
+                    time = Some(clock);
+                    consumed = len;
+                } else {
+                    return None;
+                }
+            }
+        }
+
+        after_at = false;
+        index += consumed;
+    }
+
+    match (date, time) {
+        (Some(date), time) => Some(date.and_time(time.unwrap_or(default_time))),
+        (None, Some(time)) => {
+            let candidate = today.and_time(time);
+            if candidate > now {
+                Some(candidate)
+            } else {
+                Some(today.succ_opt()?.and_time(time))
+            }
+        }
+        (None, None) => None,
+    }
+}
+
+fn parse_weekday(token: &str) -> Option<Weekday> {
+    let weekday = match token {
+        "monday" | "mon" => Weekday::Mon,
+        "tuesday" | "tue" | "tues" => Weekday::Tue,
+        "wednesday" | "wed" => Weekday::Wed,
+        "thursday" | "thu" | "thur" | "thurs" => Weekday::Thu,
+        "friday" | "fri" => Weekday::Fri,
+        "saturday" | "sat" => Weekday::Sat,
+        "sunday" | "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(weekday)
+}
+
+/// The first `weekday` after `today`, never `today` itself.
+fn next_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    today + Duration::days(if ahead == 0 { 7 } else { i64::from(ahead) })
+}
+
+fn parse_month(token: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    MONTHS
+        .iter()
+        .position(|month| token == *month || (token.len() >= 3 && month.starts_with(token)))
+        .map(|index| index as u32 + 1)
+}
+
+/// "5", "5th", "21st".
+fn parse_day_of_month(token: &str) -> Option<u32> {
+    let digits = token.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &token[digits.len()..];
+    if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+    digits.parse().ok().filter(|day| (1..=31).contains(day))
+}
+
+/// "march 5", "mar 5th", "5 march", "5th of march". Without a year, the
+/// next such day (this year or next).
+fn parse_month_day(tokens: &[&str], today: NaiveDate) -> Option<(NaiveDate, usize)> {
+    let (month, day, len) = match tokens {
+        [month, day, ..] if parse_month(month).is_some() => {
+            (parse_month(month)?, parse_day_of_month(day)?, 2)
+        }
+        [day, "of", month, ..] => (parse_month(month)?, parse_day_of_month(day)?, 3),
+        [day, month, ..] => (parse_month(month)?, parse_day_of_month(day)?, 2),
+        _ => return None,
+    };
+    let this_year = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    if this_year >= today {
+        Some((this_year, len))
+    } else {
+        Some((NaiveDate::from_ymd_opt(today.year() + 1, month, day)?, len))
+    }
+}
+
+/// "2026-03-05" or "2026-03-05t14:00".
+fn parse_iso(token: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let (date, time) = match token.split_once('t') {
+        Some((date, time)) => (date, Some(time)),
+        None => (token, None),
+    };
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    let time = match time {
+        Some(time) => Some(parse_clock(time, None, true)?.0),
+        None => None,
+    };
+    Some((date, time))
+}
+
+/// A time of day: "9am", "9 am", "9:30pm", "21:00", "noon", "evening", or a
+/// bare hour after "at". Returns the time and how many tokens it used.
+fn parse_clock(token: &str, next: Option<&str>, after_at: bool) -> Option<(NaiveTime, usize)> {
+    let named = match token {
+        "noon" | "midday" => Some(12),
+        "midnight" => Some(0),
+        "morning" => Some(9),
+        "afternoon" => Some(15),
+        "evening" => Some(18),
+        "night" => Some(20),
+        _ => None,
+    };
+    if let Some(hour) = named {
+        return Some((NaiveTime::from_hms_opt(hour, 0, 0)?, 1));
+    }
+
+    let (clock, meridiem, len) = if let Some(clock) = token.strip_suffix("am") {
+        (clock, Some(false), 1)
+    } else if let Some(clock) = token.strip_suffix("pm") {
+        (clock, Some(true), 1)
+    } else if let Some(meridiem @ ("am" | "pm")) = next {
+        (token, Some(meridiem == "pm"), 2)
+    } else {
+        (token, None, 1)
+    };
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour, minute),
+        None if meridiem.is_some() || after_at => (clock, "00"),
+        None => return None,
+    };
+    let mut hour: u32 = hour.parse().ok()?;
+    // Seconds ("14:00:00") are dropped.
+    let minute = minute
+        .split(':')
+        .next()
+        .filter(|minute| minute.len() == 2)?;
+    let minute: u32 = minute.parse().ok()?;
+    if let Some(pm) = meridiem {
+        if !(1..=12).contains(&hour) {
+            return None;
+        }
+        hour = match (hour, pm) {
+            (12, false) => 0,
+            (12, true) => 12,
+            (hour, true) => hour + 12,
+            (hour, false) => hour,
+        };
+    }
+    Some((NaiveTime::from_hms_opt(hour, minute, 0)?, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Monday 2026-03-02, 14:30.
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 3, 2)
+            .and_then(|date| date.and_hms_opt(14, 30, 0))
+            .expect("valid time")
+    }
+
+    fn at(month: u32, day: u32, hour: u32, minute: u32) -> Option<NaiveDateTime> {
+        NaiveDate::from_ymd_opt(2026, month, day).and_then(|date| date.and_hms_opt(hour, minute, 0))
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(parse_when("in 2 hours", now()), at(3, 2, 16, 30));
+        assert_eq!(
+            parse_when("in an hour and 15 minutes", now()),
+            at(3, 2, 15, 45)
+        );
+        assert_eq!(parse_when("in half an hour", now()), at(3, 2, 15, 0));
+        assert_eq!(parse_when("in 1h30m", now()), at(3, 2, 16, 0));
+        assert_eq!(parse_when("in 3 days", now()), at(3, 5, 14, 30));
+        assert_eq!(parse_when("in a month", now()), at(4, 2, 14, 30));
+        assert_eq!(parse_when("in", now()), None);
+        assert_eq!(parse_when("in 2", now()), None);
+        assert_eq!(parse_when("in 2 fortnights", now()), None);
+    }
+
+    #[test]
+    fn rejects_offsets_out_of_range() {
+        assert_eq!(parse_when("in 99999999999999999999 seconds", now()), None);
+        assert_eq!(parse_when("in 1e300 weeks", now()), None);
+        assert_eq!(parse_when("in 4294967295 months", now()), None);
+    }
+
+    #[test]
+    fn parses_days_and_times() {
+        assert_eq!(parse_when("tomorrow 9am", now()), at(3, 3, 9, 0));
+        assert_eq!(
+            parse_when("Next Tuesday at 3:30pm", now()),
+            at(3, 3, 15, 30)
+        );
+        assert_eq!(parse_when("monday", now()), at(3, 9, 9, 0));
+        assert_eq!(parse_when("5 pm friday", now()), at(3, 6, 17, 0));
+        assert_eq!(parse_when("tonight", now()), at(3, 2, 20, 0));
+        assert_eq!(parse_when("tomorrow morning", now()), at(3, 3, 9, 0));
+        assert_eq!(parse_when("at 17:45", now()), at(3, 2, 17, 45));
+        assert_eq!(parse_when("at 8", now()), at(3, 3, 8, 0));
+        assert_eq!(parse_when("12am", now()), at(3, 3, 0, 0));
+        assert_eq!(parse_when("next week", now()), at(3, 9, 9, 0));
+    }
+
+    #[test]
+    fn parses_calendar_dates() {
+        assert_eq!(parse_when("march 5th at noon", now()), at(3, 5, 12, 0));
+        assert_eq!(
+            parse_when("the 1st of march", now()),
+            NaiveDate::from_ymd_opt(2027, 3, 1).and_then(|date| date.and_hms_opt(9, 0, 0))
+        );
+        assert_eq!(parse_when("2026-04-10T08:15", now()), at(4, 10, 8, 15));
+        assert_eq!(parse_when("2026-04-10 6pm", now()), at(4, 10, 18, 0));
+    }
+
+    #[test]
+    fn rejects_unknown_text() {
+        assert_eq!(parse_when("", now()), None);
+        assert_eq!(parse_when("whenever", now()), None);
+        assert_eq!(parse_when("tomorrow-ish", now()), None);
+        assert_eq!(parse_when("13pm", now()), None);
+    }
+}
@@ -0,0 +1,32 @@
+//! Script hooks: operator-written [rhai](https://rhai.rs) scripts that run at
+//! fixed points in a message's path, for routing tweaks, filters, and
+//! formatting without a rebuild.
+//!
+//! Every `*.rhai` file in the instance's `scripts/` directory is loaded, in
+//! file name order, and may define any of these functions:
+//!
+//! - `on_message(message)`: an inbound message, before it reaches a channel.
+//! - `before_llm(turn)`: the user text a channel is about to send to the LLM.
+//! - `after_llm(turn)`: a reply the LLM wrote, before it goes out.
+//! - `before_send(turn)`: any outbound text, just before the adapter sends it.
+//!
+//! A hook gets a map and returns it (changed or not), a string to replace
+//! its text, `()` to leave it alone, or `false` to drop it. When several
+//! scripts define a hook, each sees the previous one's output. Scripts run
+//! with operation and size limits and no file or network access; a hook that
+//! fails leaves its input unchanged.
+//!
+//! The rhai engine is behind the `scripting` Cargo feature, on by default.
+//! Without it, [`ScriptHooks`] runs no hooks, and loading fails while the
+//! scripts directory holds any `*.rhai` file, so hooks an operator wrote are
+//! never silently skipped.
+
+#[cfg(feature = "scripting")]
+mod hooks;
+#[cfg(not(feature = "scripting"))]
+mod unavailable;
+
+#[cfg(feature = "scripting")]
+pub use hooks::ScriptHooks;
+#[cfg(not(feature = "scripting"))]
+pub use unavailable::ScriptHooks;
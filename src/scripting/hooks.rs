@@ -0,0 +1,383 @@
+//! Script hooks on the rhai engine.
+
+use crate::{InboundMessage, MessageContent};
+
+use anyhow::Context as _;
+use rhai::{AST, CallFnOptions, Dynamic, Engine, Map, Scope};
+
+use std::path::Path;
+use std::sync::Arc;
+
+/// Most operations one hook call may run before it's stopped.
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 1024 * 1024;
+const MAX_COLLECTION_SIZE: usize = 10_000;
+
+/// Loaded scripts. Cheap to clone; the file watcher swaps in a new set when
+/// a script changes.
+#[derive(Clone)]
+pub struct ScriptHooks {
+    engine: Arc<Engine>,
+    scripts: Vec<Script>,
+}
+
+#[derive(Clone)]
+struct Script {
+    name: String,
+    ast: Arc<AST>,
+}
+
+impl Default for ScriptHooks {
+    fn default() -> Self {
+        Self {
+            engine: Arc::new(sandboxed_engine()),
+            scripts: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ScriptHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptHooks")
+            .field(
+                "scripts",
+                &self.scripts.iter().map(|s| &s.name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+fn sandboxed_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(MAX_CALL_LEVELS)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_COLLECTION_SIZE)
+        .set_max_map_size(MAX_COLLECTION_SIZE)
+        .set_max_expr_depths(64, 32);
+    engine.disable_symbol("eval");
+    engine.on_print(|text| tracing::info!(target: "spacebot::scripts", "{text}"));
+    engine.on_debug(|text, source, position| {
+        tracing::debug!(
+            target: "spacebot::scripts",
+            script = source.unwrap_or_default(),
+            %position,
+            "{text}"
+        );
+    });
+    engine
+}
+
+impl ScriptHooks {
+    /// Compile every `*.rhai` file in `dir`. A missing directory means no
+    /// hooks. A script that can't be read or doesn't compile is an error, so
+    /// a bad edit never replaces working hooks.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let mut hooks = Self::default();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(hooks),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("can't read scripts directory {}", dir.display()));
+            }
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "rhai")
+            })
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let source = std::fs::read_to_string(&path)
+                .with_context(|| format!("can't read script {}", path.display()))?;
+            let mut ast = hooks.engine.compile(&source).map_err(|error| {
+                anyhow::anyhow!("can't compile script {}: {error}", path.display())
+            })?;
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            ast.set_source(name.as_str());
+            hooks.scripts.push(Script {
+                name,
+                ast: Arc::new(ast),
+            });
+        }
+        if !hooks.scripts.is_empty() {
+            tracing::info!(scripts = ?hooks, "script hooks loaded");
+        }
+        Ok(hooks)
+    }
+
+    /// Run `on_message`. Scripts may rewrite `content` or route the message
+    /// by setting `agent_id`. Returns `false` when a script drops it.
+    pub fn on_message(&self, message: &mut InboundMessage) -> bool {
+        if self.scripts.is_empty() {
+            return true;
+        }
+        let content = match &message.content {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Media { text, .. } => text.clone().unwrap_or_default(),
+            MessageContent::Interaction { .. } | MessageContent::Command { .. } => {
+                message.content.to_string()
+            }
+        };
+        let sender_name = message
+            .metadata
+            .get("sender_display_name")
+            .and_then(|value| value.as_str())
+            .unwrap_or(&message.sender_id)
+            .to_string();
+
+        let mut input = Map::new();
+        input.insert("source".into(), message.source.clone().into());
+        input.insert(
+            "conversation_id".into(),
+            message.conversation_id.clone().into(),
+        );
+        input.insert("sender_id".into(), message.sender_id.clone().into());
+        input.insert("sender_name".into(), sender_name.into());
+        input.insert(
+            "agent_id".into(),
+            message.agent_id.as_deref().unwrap_or_default().into(),
+        );
+        input.insert("content".into(), content.clone().into());
+
+        let Some(output) = self.run("on_message", input, "content") else {
+            tracing::info!(
+                conversation_id = %message.conversation_id,
+                "script hook dropped inbound message"
+            );
+            return false;
+        };
+
+        if let Some(new_content) = string_field(&output, "content")
+            && new_content != content
+        {
+            match &mut message.content {
+                MessageContent::Text(text) => *text = new_content,
+                MessageContent::Media { text, .. } => *text = Some(new_content),
+                // Interactions and commands are structured; their text can't
+                // be rewritten.
+                MessageContent::Interaction { .. } | MessageContent::Command { .. } => {}
+            }
+        }
+        if let Some(agent_id) = string_field(&output, "agent_id")
+            && !agent_id.is_empty()
+            && message.agent_id.as_deref() != Some(agent_id.as_str())
+        {
+            tracing::debug!(
+                conversation_id = %message.conversation_id,
+                %agent_id,
+                "script hook routed inbound message"
+            );
+            message.agent_id = Some(Arc::from(agent_id));
+        }
+        true
+    }
+
+    /// Run `before_llm` on the user text of a channel turn. Turns can't be
+    /// dropped here, so `false` leaves the text unchanged.
+    pub fn before_llm(&self, agent_id: &str, conversation_id: &str, text: &str) -> String {
+        self.text_hook("before_llm", agent_id, conversation_id, text)
+            .unwrap_or_else(|| text.to_string())
+    }
+
+    /// Run `after_llm` on a reply the LLM wrote. `None` when a script drops
+    /// the reply.
+    pub fn after_llm(&self, agent_id: &str, conversation_id: &str, text: &str) -> Option<String> {
+        self.text_hook("after_llm", agent_id, conversation_id, text)
+    }
+
+    /// Run `before_send` on outbound text. `None` when a script drops it.
+    pub fn before_send(&self, agent_id: &str, conversation_id: &str, text: &str) -> Option<String> {
+        self.text_hook("before_send", agent_id, conversation_id, text)
+    }
+
+    fn text_hook(
+        &self,
+        hook: &str,
+        agent_id: &str,
+        conversation_id: &str,
+        text: &str,
+    ) -> Option<String> {
+        if self.scripts.is_empty() {
+            return Some(text.to_string());
+        }
+        let mut input = Map::new();
+        input.insert("agent_id".into(), agent_id.into());
+        input.insert("conversation_id".into(), conversation_id.into());
+        input.insert(
+            "source".into(),
+            conversation_id.split(':').next().unwrap_or_default().into(),
+        );
+        input.insert("text".into(), text.into());
+
+        let output = self.run(hook, input, "text")?;
+        Some(string_field(&output, "text").unwrap_or_else(|| text.to_string()))
+    }
+
+    /// Call `hook` in every script that defines it, feeding each the last
+    /// one's output. `None` when a script returns `false`.
+    fn run(&self, hook: &str, mut input: Map, text_key: &str) -> Option<Map> {
+        for script in &self.scripts {
+            let defined = script
+                .ast
+                .iter_functions()
+                .any(|function| function.name == hook && function.params.len() == 1);
+            if !defined {
+                continue;
+            }
+
+            let result = self.engine.call_fn_with_options::<Dynamic>(
+                CallFnOptions::new().eval_ast(false),
+                &mut Scope::new(),
+                &script.ast,
+                hook,
+                (input.clone(),),
+            );
+            match result {
+                Ok(value) if value.is_unit() => {}
+                Ok(value) if value.as_bool() == Ok(false) => return None,
+                Ok(value) if value.is_map() => input = value.cast::<Map>(),
+                Ok(value) if value.is_string() => {
+                    input.insert(text_key.into(), value);
+                }
+                Ok(value) => {
+                    tracing::warn!(
+                        script = %script.name,
+                        hook,
+                        value_type = value.type_name(),
+                        "script hook returned an unsupported value, ignoring it"
+                    );
+                }
+                Err(error) => {
+                    tracing::warn!(
+                        script = %script.name,
+                        hook,
+                        %error,
+                        "script hook failed, leaving its input unchanged"
+                    );
+                }
+            }
+        }
+        Some(input)
+    }
+}
+
+fn string_field(map: &Map, key: &str) -> Option<String> {
+    map.get(key)
+        .and_then(|value| value.clone().into_string().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hooks(scripts: &[(&str, &str)]) -> ScriptHooks {
+        let dir = tempfile::tempdir().expect("tempdir");
+        for (name, source) in scripts {
+            std::fs::write(dir.path().join(name), source).expect("write script");
+        }
+        ScriptHooks::load(dir.path()).expect("scripts should compile")
+    }
+
+    fn message(text: &str) -> InboundMessage {
+        InboundMessage {
+            id: "1".into(),
+            source: "discord".into(),
+            conversation_id: "discord:1".into(),
+            sender_id: "42".into(),
+            agent_id: Some(Arc::from("main")),
+            content: MessageContent::Text(text.into()),
+            timestamp: chrono::Utc::now(),
+            metadata: Default::default(),
+            formatted_author: None,
+        }
+    }
+
+    #[test]
+    fn on_message_rewrites_routes_and_drops() {
+        let hooks = hooks(&[
+            (
+                "10-route.rhai",
+                r#"
+fn on_message(message) {
+    if message.content.starts_with("!ops ") {
+        message.agent_id = "ops";
+        message.content = message.content.sub_string(5);
+    }
+    message
+}
+"#,
+            ),
+            (
+                "20-filter.rhai",
+                r#"
+fn on_message(message) {
+    if message.content.contains("spam") { return false; }
+    message.content.to_upper()
+}
+"#,
+            ),
+        ]);
+
+        let mut routed = message("!ops disk is full");
+        assert!(hooks.on_message(&mut routed));
+        assert_eq!(routed.agent_id.as_deref(), Some("ops"));
+        assert!(matches!(&routed.content, MessageContent::Text(text) if text == "DISK IS FULL"));
+
+        assert!(!hooks.on_message(&mut message("buy spam now")));
+    }
+
+    #[test]
+    fn text_hooks_chain_and_fail_open() {
+        let hooks = hooks(&[
+            (
+                "a.rhai",
+                r#"
+fn before_send(turn) { turn.text + " [bot]" }
+fn after_llm(turn) { if turn.text == "" { false } }
+fn before_llm(turn) { loop {} }
+"#,
+            ),
+            (
+                "b.rhai",
+                r#"fn before_send(turn) { let text = turn.text; text.replace("secret", "***"); text }"#,
+            ),
+        ]);
+
+        assert_eq!(
+            hooks
+                .before_send("main", "discord:1", "the secret")
+                .as_deref(),
+            Some("the *** [bot]")
+        );
+        assert_eq!(hooks.after_llm("main", "discord:1", ""), None);
+        assert_eq!(
+            hooks.after_llm("main", "discord:1", "hi").as_deref(),
+            Some("hi")
+        );
+        // The runaway loop hits the operation limit and leaves the text alone.
+        assert_eq!(hooks.before_llm("main", "discord:1", "hello"), "hello");
+    }
+
+    #[test]
+    fn bad_scripts_fail_to_load() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(dir.path().join("broken.rhai"), "fn on_message(m) {").expect("write");
+        assert!(ScriptHooks::load(dir.path()).is_err());
+        assert!(
+            ScriptHooks::load(&dir.path().join("missing"))
+                .expect("missing dir is fine")
+                .scripts
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! Stand-in for [`ScriptHooks`] in builds without the `scripting` feature.
+
+use crate::InboundMessage;
+
+use anyhow::Context as _;
+use std::path::Path;
+
+/// No hooks: every message and reply passes through unchanged.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptHooks;
+
+impl ScriptHooks {
+    /// Empty hooks, or an error naming the first `*.rhai` file in `dir`,
+    /// which this build can't run.
+    pub fn load(dir: &Path) -> anyhow::Result<Self> {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Self),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("can't read scripts directory {}", dir.display()));
+            }
+        };
+        let mut scripts: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "rhai")
+            })
+            .collect();
+        scripts.sort();
+        match scripts.first() {
+            Some(path) => anyhow::bail!(
+                "can't run script {}: this build doesn't include script hooks; rebuild with \
+                 `--features scripting`",
+                path.display()
+            ),
+            None => Ok(Self),
+        }
+    }
+
+    pub fn on_message(&self, _message: &mut InboundMessage) -> bool {
+        true
+    }
+
+    pub fn before_llm(&self, _agent_id: &str, _conversation_id: &str, text: &str) -> String {
+        text.to_string()
+    }
+
+    pub fn after_llm(&self, _agent_id: &str, _conversation_id: &str, text: &str) -> Option<String> {
+        Some(text.to_string())
+    }
+
+    pub fn before_send(
+        &self,
+        _agent_id: &str,
+        _conversation_id: &str,
+        text: &str,
+    ) -> Option<String> {
+        Some(text.to_string())
+    }
+}
@@ -1,3 +1,4 @@
 //! Encrypted secrets storage.
 
+pub mod patterns;
 pub mod store;
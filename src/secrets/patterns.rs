@@ -0,0 +1,65 @@
+//! Patterns for well-known credential formats.
+//!
+//! Shared by the hook's leak detection on tool arguments and results, the
+//! output guard on replies, and log redaction.
+
+use regex::Regex;
+
+use std::sync::LazyLock;
+
+static PATTERNS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    vec![
+        // OpenAI keys
+        Regex::new(r"sk-[a-zA-Z0-9]{20,}").expect("hardcoded regex"),
+        // Anthropic keys
+        Regex::new(r"sk-ant-[a-zA-Z0-9_-]{20,}").expect("hardcoded regex"),
+        // OpenRouter keys
+        Regex::new(r"sk-or-[a-zA-Z0-9_-]{20,}").expect("hardcoded regex"),
+        // PEM private keys
+        Regex::new(r"-----BEGIN.*PRIVATE KEY-----").expect("hardcoded regex"),
+        // GitHub personal access tokens
+        Regex::new(r"ghp_[a-zA-Z0-9]{36}").expect("hardcoded regex"),
+        // Google API keys
+        Regex::new(r"AIza[0-9A-Za-z_-]{35}").expect("hardcoded regex"),
+        // Discord bot tokens (base64 user ID . timestamp . HMAC)
+        Regex::new(r"[MN][A-Za-z0-9]{23,}\.[A-Za-z0-9_-]{6}\.[A-Za-z0-9_-]{27,}")
+            .expect("hardcoded regex"),
+        // Slack bot tokens
+        Regex::new(r"xoxb-[0-9]{10,}-[0-9A-Za-z-]+").expect("hardcoded regex"),
+        // Slack app tokens
+        Regex::new(r"xapp-[0-9]-[A-Z0-9]+-[0-9]+-[a-f0-9]+").expect("hardcoded regex"),
+        // Telegram bot tokens
+        Regex::new(r"\d{8,}:[A-Za-z0-9_-]{35}").expect("hardcoded regex"),
+        // Brave Search API keys
+        Regex::new(r"BSA[a-zA-Z0-9]{20,}").expect("hardcoded regex"),
+    ]
+});
+
+/// The first credential-looking substring in `content`.
+pub fn find(content: &str) -> Option<&str> {
+    PATTERNS
+        .iter()
+        .find_map(|pattern| pattern.find(content))
+        .map(|matched| matched.as_str())
+}
+
+/// Byte ranges of every credential-looking substring in `content`. Ranges
+/// found by different patterns can overlap.
+pub fn find_all(content: &str) -> Vec<std::ops::Range<usize>> {
+    PATTERNS
+        .iter()
+        .flat_map(|pattern| pattern.find_iter(content))
+        .map(|matched| matched.range())
+        .collect()
+}
+
+/// `content` with every credential-looking substring replaced by `replacement`.
+pub fn replace_all(content: &str, replacement: &str) -> String {
+    let mut content = content.to_string();
+    for pattern in PATTERNS.iter() {
+        if let std::borrow::Cow::Owned(replaced) = pattern.replace_all(&content, replacement) {
+            content = replaced;
+        }
+    }
+    content
+}
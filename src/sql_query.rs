@@ -0,0 +1,548 @@
+//! Read-only SQL for the `sql_query` tool: statement checks, connections
+//! to the configured databases, and results as Markdown tables.
+//!
+//! Read-only is enforced twice. A query must parse as a single SELECT with
+//! no writes or side-effecting functions anywhere inside it, and it then
+//! runs in a read-only transaction (Postgres), a read-only session (MySQL),
+//! or on a read-only connection (SQLite). Neither replaces connecting as a
+//! database user that can only read.
+
+use crate::config::{SqlBackend, SqlDatabaseConfig};
+use futures::TryStreamExt as _;
+use sqlparser::ast::{Expr, Query, SetExpr, Statement, Visit, Visitor};
+#[cfg(any(feature = "sql-postgres", feature = "sql-mysql"))]
+use sqlx::ValueRef as _;
+use sqlx::{Column as _, Row};
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::str::FromStr as _;
+use std::sync::{LazyLock, Mutex};
+
+/// Functions that read or write outside the database, change settings,
+/// touch other sessions, or just stall a connection, by backend.
+const DENIED_FUNCTIONS: &[&str] = &[
+    // Postgres
+    "dblink",
+    "dblink_exec",
+    "lo_export",
+    "lo_import",
+    "nextval",
+    "pg_cancel_backend",
+    "pg_read_binary_file",
+    "pg_read_file",
+    "pg_ls_dir",
+    "pg_reload_conf",
+    "pg_sleep",
+    "pg_terminate_backend",
+    "set_config",
+    "setval",
+    // MySQL
+    "benchmark",
+    "get_lock",
+    "load_file",
+    "sleep",
+    // SQLite
+    "load_extension",
+    "readfile",
+    "writefile",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SqlQueryError {
+    #[error("query rejected: {0}")]
+    Rejected(String),
+
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("query timed out after {0}s")]
+    Timeout(u64),
+
+    #[error("this build doesn't include {0}; rebuild with `--features {1}`")]
+    Unavailable(&'static str, &'static str),
+}
+
+/// Rows from a query, rendered for the LLM.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Whether more rows existed than were kept.
+    pub truncated: bool,
+}
+
+/// Check that `sql` is one read-only query in `backend`'s dialect.
+pub fn check_read_only(sql: &str, backend: SqlBackend) -> Result<(), SqlQueryError> {
+    let statements = match backend {
+        SqlBackend::Postgres => {
+            sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::PostgreSqlDialect {}, sql)
+        }
+        SqlBackend::Mysql => {
+            sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::MySqlDialect {}, sql)
+        }
+        SqlBackend::Sqlite => {
+            sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::SQLiteDialect {}, sql)
+        }
+    }
+    .map_err(|error| SqlQueryError::Rejected(format!("can't parse the query: {error}")))?;
+
+    let [statement] = statements.as_slice() else {
+        return Err(SqlQueryError::Rejected("send exactly one statement".into()));
+    };
+    match statement.visit(&mut ReadOnlyCheck) {
+        ControlFlow::Continue(()) => Ok(()),
+        ControlFlow::Break(reason) => Err(SqlQueryError::Rejected(reason)),
+    }
+}
+
+struct ReadOnlyCheck;
+
+impl Visitor for ReadOnlyCheck {
+    type Break = String;
+
+    /// Data-modifying CTEs and the like show up as nested statements.
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<String> {
+        match statement {
+            Statement::Query(_) => ControlFlow::Continue(()),
+            other => {
+                let keyword = other.to_string();
+                let keyword = keyword.split_whitespace().next().unwrap_or_default();
+                ControlFlow::Break(format!(
+                    "only SELECT queries are allowed, not {}",
+                    keyword.to_ascii_uppercase()
+                ))
+            }
+        }
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<String> {
+        if !query.locks.is_empty() {
+            return ControlFlow::Break("FOR UPDATE and FOR SHARE lock rows".into());
+        }
+        if selects_into(&query.body) {
+            return ControlFlow::Break("SELECT ... INTO writes a table".into());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<String> {
+        if let Expr::Function(function) = expr {
+            let name = function.name.to_string().to_ascii_lowercase();
+            let name = name
+                .rsplit('.')
+                .next()
+                .unwrap_or_default()
+                .trim_matches('"');
+            if DENIED_FUNCTIONS.contains(&name) {
+                return ControlFlow::Break(format!("{name}() isn't allowed"));
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Whether any SELECT in a set expression has an INTO. Nested queries are
+/// checked when the visitor reaches them.
+fn selects_into(body: &SetExpr) -> bool {
+    match body {
+        SetExpr::Select(select) => select.into.is_some(),
+        SetExpr::SetOperation { left, right, .. } => selects_into(left) || selects_into(right),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Pool {
+    #[cfg(feature = "sql-postgres")]
+    Postgres(sqlx::PgPool),
+    #[cfg(feature = "sql-mysql")]
+    Mysql(sqlx::MySqlPool),
+    Sqlite(sqlx::SqlitePool),
+}
+
+/// Pools by URL and timeout. Tools are built per worker, so pools live
+/// outside them.
+static POOLS: LazyLock<Mutex<HashMap<String, Pool>>> = LazyLock::new(Default::default);
+
+fn pool(config: &SqlDatabaseConfig) -> Result<Pool, SqlQueryError> {
+    let key = format!("{}#{}", config.url, config.timeout_secs);
+    let mut pools = POOLS.lock().expect("sql pool cache poisoned");
+    if let Some(pool) = pools.get(&key) {
+        return Ok(pool.clone());
+    }
+
+    #[cfg(any(feature = "sql-postgres", feature = "sql-mysql"))]
+    let timeout_ms = config.timeout_secs * 1000;
+    let pool = match config.backend {
+        #[cfg(feature = "sql-postgres")]
+        SqlBackend::Postgres => Pool::Postgres(
+            sqlx::postgres::PgPoolOptions::new()
+                .max_connections(2)
+                .after_connect(move |connection, _| {
+                    Box::pin(async move {
+                        sqlx::query("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+                            .execute(&mut *connection)
+                            .await?;
+                        sqlx::query(&format!("SET statement_timeout = {timeout_ms}"))
+                            .execute(&mut *connection)
+                            .await?;
+                        Ok(())
+                    })
+                })
+                .connect_lazy_with(sqlx::postgres::PgConnectOptions::from_str(&config.url)?),
+        ),
+        #[cfg(not(feature = "sql-postgres"))]
+        SqlBackend::Postgres => {
+            return Err(SqlQueryError::Unavailable("Postgres", "sql-postgres"));
+        }
+        #[cfg(feature = "sql-mysql")]
+        SqlBackend::Mysql => Pool::Mysql(
+            sqlx::mysql::MySqlPoolOptions::new()
+                .max_connections(2)
+                .after_connect(move |connection, _| {
+                    Box::pin(async move {
+                        sqlx::query("SET SESSION TRANSACTION READ ONLY")
+                            .execute(&mut *connection)
+                            .await?;
+                        // Only applies to SELECT, which is all that runs.
+                        sqlx::query(&format!("SET SESSION MAX_EXECUTION_TIME = {timeout_ms}"))
+                            .execute(&mut *connection)
+                            .await?;
+                        Ok(())
+                    })
+                })
+                .connect_lazy_with(sqlx::mysql::MySqlConnectOptions::from_str(&config.url)?),
+        ),
+        #[cfg(not(feature = "sql-mysql"))]
+        SqlBackend::Mysql => return Err(SqlQueryError::Unavailable("MySQL", "sql-mysql")),
+        SqlBackend::Sqlite => Pool::Sqlite(
+            sqlx::sqlite::SqlitePoolOptions::new()
+                .max_connections(2)
+                .connect_lazy_with(
+                    sqlx::sqlite::SqliteConnectOptions::from_str(&config.url)?.read_only(true),
+                ),
+        ),
+    };
+    pools.insert(key, pool.clone());
+    Ok(pool)
+}
+
+/// Check and run `sql` on `config`'s database, keeping at most
+/// `config.max_rows` rows.
+pub async fn run(config: &SqlDatabaseConfig, sql: &str) -> Result<QueryResult, SqlQueryError> {
+    check_read_only(sql, config.backend)?;
+    let pool = pool(config)?;
+    let max_rows = config.max_rows;
+
+    let query = async {
+        match pool {
+            #[cfg(feature = "sql-postgres")]
+            Pool::Postgres(pool) => {
+                let mut transaction = pool.begin().await?;
+                sqlx::query("SET TRANSACTION READ ONLY")
+                    .execute(&mut *transaction)
+                    .await?;
+                let result = collect(
+                    sqlx::query(sql).fetch(&mut *transaction),
+                    max_rows,
+                    postgres_cell,
+                )
+                .await;
+                transaction.rollback().await?;
+                result
+            }
+            #[cfg(feature = "sql-mysql")]
+            Pool::Mysql(pool) => {
+                let mut transaction = pool.begin().await?;
+                let result = collect(
+                    sqlx::query(sql).fetch(&mut *transaction),
+                    max_rows,
+                    mysql_cell,
+                )
+                .await;
+                transaction.rollback().await?;
+                result
+            }
+            Pool::Sqlite(pool) => {
+                collect(sqlx::query(sql).fetch(&pool), max_rows, sqlite_cell).await
+            }
+        }
+    };
+
+    // The server-side timeouts should fire first; this covers connecting.
+    tokio::time::timeout(
+        std::time::Duration::from_secs(config.timeout_secs + 5),
+        query,
+    )
+    .await
+    .map_err(|_| SqlQueryError::Timeout(config.timeout_secs))?
+    .map_err(SqlQueryError::from)
+}
+
+async fn collect<R: Row>(
+    rows: impl futures::Stream<Item = Result<R, sqlx::Error>>,
+    max_rows: usize,
+    cell: fn(&R, usize) -> String,
+) -> Result<QueryResult, sqlx::Error> {
+    let mut rows = std::pin::pin!(rows);
+    let mut result = QueryResult {
+        columns: Vec::new(),
+        rows: Vec::new(),
+        truncated: false,
+    };
+    while let Some(row) = rows.try_next().await? {
+        if result.rows.len() == max_rows {
+            result.truncated = true;
+            break;
+        }
+        if result.columns.is_empty() {
+            result.columns = row
+                .columns()
+                .iter()
+                .map(|column| column.name().to_string())
+                .collect();
+        }
+        result
+            .rows
+            .push((0..row.len()).map(|index| cell(&row, index)).collect());
+    }
+    Ok(result)
+}
+
+/// The first of `types` that `$row[$index]` decodes as, shown with Display.
+macro_rules! decode_as {
+    ($row:expr, $index:expr; $($ty:ty),+ $(,)?) => {
+        None$(.or_else(|| {
+            $row.try_get::<Option<$ty>, _>($index)
+                .ok()
+                .map(|value| value.map_or_else(|| "NULL".to_string(), |value| value.to_string()))
+        }))+
+    };
+}
+
+#[cfg(feature = "sql-postgres")]
+fn postgres_cell(row: &sqlx::postgres::PgRow, index: usize) -> String {
+    if let Some(text) = decode_as!(row, index;
+        String, i64, i32, i16, f64, f32, bool, uuid::Uuid,
+        chrono::DateTime<chrono::Utc>, chrono::NaiveDateTime, chrono::NaiveDate, chrono::NaiveTime,
+    ) {
+        return text;
+    }
+    let Ok(value) = row.try_get_raw(index) else {
+        return "?".into();
+    };
+    if value.is_null() {
+        return "NULL".into();
+    }
+    // Without sqlx's decimal and JSON features, read their wire formats.
+    let type_name = value.type_info().to_string();
+    match (type_name.as_str(), value.as_bytes()) {
+        ("NUMERIC", Ok(bytes)) => pg_numeric(bytes).unwrap_or_else(|| "(numeric)".into()),
+        ("JSON", Ok(bytes)) => String::from_utf8_lossy(bytes).into_owned(),
+        ("JSONB", Ok([1, bytes @ ..])) => String::from_utf8_lossy(bytes).into_owned(),
+        ("BYTEA", Ok(bytes)) => format!("({} bytes)", bytes.len()),
+        _ => format!("({type_name})"),
+    }
+}
+
+#[cfg(feature = "sql-mysql")]
+fn mysql_cell(row: &sqlx::mysql::MySqlRow, index: usize) -> String {
+    if let Some(text) = decode_as!(row, index;
+        String, i64, u64, f64, f32,
+        chrono::DateTime<chrono::Utc>, chrono::NaiveDateTime, chrono::NaiveDate, chrono::NaiveTime,
+    ) {
+        return text;
+    }
+    let Ok(value) = row.try_get_raw(index) else {
+        return "?".into();
+    };
+    if value.is_null() {
+        return "NULL".into();
+    }
+    // DECIMAL arrives as text in both protocols. Decoding as bytes skips
+    // the type check `try_get` does.
+    let type_name = value.type_info().to_string();
+    match <&[u8] as sqlx::Decode<sqlx::MySql>>::decode(value) {
+        Ok(bytes) if type_name == "DECIMAL" => String::from_utf8_lossy(bytes).into_owned(),
+        Ok(bytes) if type_name.contains("BLOB") || type_name.contains("BINARY") => {
+            format!("({} bytes)", bytes.len())
+        }
+        _ => format!("({type_name})"),
+    }
+}
+
+fn sqlite_cell(row: &sqlx::sqlite::SqliteRow, index: usize) -> String {
+    if let Some(text) = decode_as!(row, index; i64, f64, String) {
+        return text;
+    }
+    match row.try_get::<Option<Vec<u8>>, _>(index) {
+        Ok(Some(bytes)) => format!("({} bytes)", bytes.len()),
+        Ok(None) => "NULL".into(),
+        Err(_) => "?".into(),
+    }
+}
+
+/// A Postgres NUMERIC in binary format: digit count, weight, sign, and
+/// display scale, then base-10000 digits.
+#[cfg(feature = "sql-postgres")]
+fn pg_numeric(bytes: &[u8]) -> Option<String> {
+    let word = |index: usize| {
+        bytes
+            .get(index * 2..index * 2 + 2)
+            .map(|pair| i16::from_be_bytes([pair[0], pair[1]]))
+    };
+    let digit_count = word(0)? as usize;
+    let weight = word(1)? as isize;
+    let sign = word(2)? as u16;
+    let scale = word(3)? as u16 as usize;
+    let digits: Vec<i16> = (0..digit_count)
+        .map(|i| word(4 + i))
+        .collect::<Option<_>>()?;
+    match sign {
+        0x0000 | 0x4000 => {}
+        0xC000 => return Some("NaN".into()),
+        0xD000 => return Some("Infinity".into()),
+        0xF000 => return Some("-Infinity".into()),
+        _ => return None,
+    }
+    let digit = |index: isize| {
+        usize::try_from(index)
+            .ok()
+            .and_then(|index| digits.get(index))
+            .copied()
+            .unwrap_or(0)
+    };
+
+    let mut text = String::new();
+    if sign == 0x4000 {
+        text.push('-');
+    }
+    if weight < 0 {
+        text.push('0');
+    } else {
+        text.push_str(&digit(0).to_string());
+        for index in 1..=weight {
+            text.push_str(&format!("{:04}", digit(index)));
+        }
+    }
+    if scale > 0 {
+        let mut fraction = String::new();
+        let mut index = weight + 1;
+        while fraction.len() < scale {
+            fraction.push_str(&format!("{:04}", digit(index)));
+            index += 1;
+        }
+        fraction.truncate(scale);
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    Some(text)
+}
+
+impl QueryResult {
+    /// The rows as a Markdown table, and how many rows fit.
+    pub fn to_markdown(&self) -> (String, usize) {
+        crate::table::markdown_table(&self.columns, &self.rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_read_only_queries_pass() {
+        for sql in [
+            "SELECT 1",
+            "select name, count(*) from users group by name order by 2 desc limit 5",
+            "WITH recent AS (SELECT * FROM orders WHERE created_at > now() - interval '7 days') SELECT count(*) FROM recent",
+            "SELECT * FROM a UNION SELECT * FROM b",
+        ] {
+            check_read_only(sql, SqlBackend::Postgres).unwrap();
+        }
+
+        for sql in [
+            "DELETE FROM users",
+            "SELECT 1; DROP TABLE users",
+            "WITH gone AS (DELETE FROM users RETURNING *) SELECT * FROM gone",
+            "SELECT * INTO backup FROM users",
+            "SELECT * FROM users FOR UPDATE",
+            "SELECT pg_terminate_backend(42)",
+            "SELECT * FROM users WHERE id IN (SELECT pg_catalog.set_config('x', 'y', false))",
+            "SET default_transaction_read_only = off",
+        ] {
+            assert!(check_read_only(sql, SqlBackend::Postgres).is_err(), "{sql}");
+        }
+        assert!(check_read_only("SELECT SLEEP(100)", SqlBackend::Mysql).is_err());
+        assert!(check_read_only("PRAGMA writable_schema = 1", SqlBackend::Sqlite).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "sql-postgres")]
+    fn numerics_decode_from_wire_format() {
+        let encode = |words: &[i16]| -> Vec<u8> {
+            words.iter().flat_map(|word| word.to_be_bytes()).collect()
+        };
+        // 12345.678: digits [1, 2345, 6780], weight 1, scale 3.
+        assert_eq!(
+            pg_numeric(&encode(&[3, 1, 0, 3, 1, 2345, 6780])).as_deref(),
+            Some("12345.678")
+        );
+        // -0.05
+        assert_eq!(
+            pg_numeric(&encode(&[1, -1, 0x4000, 2, 500])).as_deref(),
+            Some("-0.05")
+        );
+        // 10000 has a trailing zero group that isn't stored.
+        assert_eq!(
+            pg_numeric(&encode(&[1, 1, 0, 0, 1])).as_deref(),
+            Some("10000")
+        );
+    }
+
+    #[tokio::test]
+    async fn sqlite_queries_render_as_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("analytics.db");
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let setup = sqlx::SqlitePool::connect(&url).await.unwrap();
+        sqlx::query("CREATE TABLE signups (day TEXT, plan TEXT, total INTEGER)")
+            .execute(&setup)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO signups VALUES ('2026-01-01', 'pro|team', 3), ('2026-01-02', NULL, 5), ('2026-01-03', 'free', 8)")
+            .execute(&setup)
+            .await
+            .unwrap();
+
+        let config = SqlDatabaseConfig {
+            url: format!("sqlite://{}", path.display()),
+            backend: SqlBackend::Sqlite,
+            description: None,
+            max_rows: 2,
+            timeout_secs: 5,
+        };
+        let result = run(&config, "SELECT day, plan, total FROM signups ORDER BY day")
+            .await
+            .unwrap();
+        assert!(result.truncated);
+        let (table, shown) = result.to_markdown();
+        assert_eq!(shown, 2);
+        assert_eq!(
+            table,
+            "| day | plan | total |\n| --- | --- | --- |\n| 2026-01-01 | pro\\|team | 3 |\n| 2026-01-02 | NULL | 5 |\n"
+        );
+
+        // The connection itself refuses writes, whatever gets past the parser.
+        let pool = pool(&config).unwrap();
+        let Pool::Sqlite(pool) = pool else {
+            unreachable!()
+        };
+        assert!(
+            sqlx::query("DELETE FROM signups")
+                .execute(&pool)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Supervised long-lived tasks.
+//!
+//! [`spawn`] runs a task that should live as long as the process: a messaging
+//! adapter, the config watcher, a cron timer, an agent's background loops.
+//! When the task panics or returns an error, it is started again after a
+//! backoff that doubles with each failure. A task that fails more than
+//! `max_restarts` times within `window_secs` is given up on and reported as
+//! failed, so a broken adapter stops retrying without taking the rest of the
+//! bot down. A task that ends cleanly or is aborted isn't restarted.
+//!
+//! [`statuses`] lists every supervised task with its state and restart count,
+//! served at `GET /api/system/tasks`.
+
+use crate::AgentId;
+
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// Restart policy for supervised tasks (instance-level, under `[supervisor]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SupervisorConfig {
+    /// Restarts allowed within `window_secs` before a task is given up on.
+    pub max_restarts: u32,
+    pub window_secs: u64,
+    /// Wait before the first restart. Doubles with each failure in the window.
+    pub initial_backoff_secs: u64,
+    pub max_backoff_secs: u64,
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            window_secs: 600,
+            initial_backoff_secs: 1,
+            max_backoff_secs: 64,
+        }
+    }
+}
+
+impl SupervisorConfig {
+    /// Record a failure at `now` in `failures`, the task's recent failures.
+    /// Returns how long to wait before restarting, or `None` once the task
+    /// has failed more than `max_restarts` times within the window.
+    fn restart_delay(&self, failures: &mut VecDeque<Instant>, now: Instant) -> Option<Duration> {
+        let window = Duration::from_secs(self.window_secs);
+        while failures
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > window)
+        {
+            failures.pop_front();
+        }
+        failures.push_back(now);
+        if failures.len() > self.max_restarts as usize {
+            return None;
+        }
+
+        let doublings = (failures.len() - 1).min(16) as u32;
+        let backoff = self.initial_backoff_secs.saturating_mul(1 << doublings);
+        Some(Duration::from_secs(backoff.min(self.max_backoff_secs)))
+    }
+}
+
+/// How a supervised task ended on its own.
+pub trait TaskExit: Send + 'static {
+    fn into_result(self) -> anyhow::Result<()>;
+}
+
+impl TaskExit for () {
+    fn into_result(self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl TaskExit for anyhow::Result<()> {
+    fn into_result(self) -> anyhow::Result<()> {
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// Failed and waiting out its backoff.
+    Restarting,
+    /// Failed too often and won't be restarted.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub agent_id: Option<String>,
+    pub state: TaskState,
+    /// Restarts since the task was first started.
+    pub restarts: u32,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub last_error: Option<String>,
+    pub last_failure_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+struct Supervisor {
+    config: Mutex<SupervisorConfig>,
+    /// Keyed by agent and task name. The ID tells a task apart from one that
+    /// replaced it under the same name.
+    tasks: Mutex<BTreeMap<String, (u64, TaskStatus)>>,
+    next_id: AtomicU64,
+}
+
+static SUPERVISOR: LazyLock<Supervisor> = LazyLock::new(|| Supervisor {
+    config: Mutex::new(SupervisorConfig::default()),
+    tasks: Mutex::new(BTreeMap::new()),
+    next_id: AtomicU64::new(0),
+});
+
+/// Set the restart policy for every supervised task.
+pub fn configure(config: SupervisorConfig) {
+    *SUPERVISOR.config.lock().expect("supervisor lock poisoned") = config;
+}
+
+/// Every supervised task that is running, restarting, or has failed.
+pub fn statuses() -> Vec<TaskStatus> {
+    let tasks = SUPERVISOR.tasks.lock().expect("supervisor lock poisoned");
+    tasks.values().map(|(_, status)| status.clone()).collect()
+}
+
+/// Run the task `start` spawns, starting it again whenever it panics or
+/// returns an error. A task registered under the same agent and name
+/// replaces this one in [`statuses`]. Aborting the returned handle aborts the
+/// task too.
+pub fn spawn<F, T>(agent_id: Option<AgentId>, name: impl Into<String>, start: F) -> JoinHandle<()>
+where
+    F: Fn() -> JoinHandle<T> + Send + 'static,
+    T: TaskExit,
+{
+    let name = name.into();
+    let key = match &agent_id {
+        Some(agent_id) => format!("{agent_id}/{name}"),
+        None => name.clone(),
+    };
+    let id = SUPERVISOR.next_id.fetch_add(1, Ordering::Relaxed);
+    SUPERVISOR
+        .tasks
+        .lock()
+        .expect("supervisor lock poisoned")
+        .insert(
+            key.clone(),
+            (
+                id,
+                TaskStatus {
+                    name: name.clone(),
+                    agent_id: agent_id.as_ref().map(ToString::to_string),
+                    state: TaskState::Running,
+                    restarts: 0,
+                    started_at: chrono::Utc::now(),
+                    last_error: None,
+                    last_failure_at: None,
+                },
+            ),
+        );
+
+    tokio::spawn(async move {
+        let _registration = Registration {
+            key: key.clone(),
+            id,
+        };
+        let mut failures = VecDeque::new();
+        loop {
+            let mut task = AbortOnDrop(start());
+            let error = match (&mut task.0).await {
+                Ok(exit) => match exit.into_result() {
+                    Ok(()) => return,
+                    Err(error) => format!("{error:#}"),
+                },
+                Err(error) if error.is_panic() => "panicked".to_string(),
+                Err(_) => return,
+            };
+
+            let config = SUPERVISOR
+                .config
+                .lock()
+                .expect("supervisor lock poisoned")
+                .clone();
+            let delay = config.restart_delay(&mut failures, Instant::now());
+            update(&key, id, |status| {
+                status.state = match delay {
+                    Some(_) => TaskState::Restarting,
+                    None => TaskState::Failed,
+                };
+                status.last_error = Some(error.clone());
+                status.last_failure_at = Some(chrono::Utc::now());
+            });
+
+            let Some(delay) = delay else {
+                tracing::error!(
+                    task = %key,
+                    %error,
+                    failures = failures.len(),
+                    window_secs = config.window_secs,
+                    "supervised task failed too often, giving up"
+                );
+                crate::events::publish(crate::events::Event::ErrorOccurred {
+                    agent_id: agent_id.clone(),
+                    component: name.clone(),
+                    message: format!(
+                        "{name} failed {} times in {}s and won't be restarted: {error}",
+                        failures.len(),
+                        config.window_secs
+                    ),
+                });
+                return;
+            };
+            tracing::error!(
+                task = %key,
+                %error,
+                delay_secs = delay.as_secs(),
+                "supervised task failed, restarting it"
+            );
+            crate::events::publish(crate::events::Event::ErrorOccurred {
+                agent_id: agent_id.clone(),
+                component: name.clone(),
+                message: format!("{name} failed and was restarted: {error}"),
+            });
+
+            tokio::time::sleep(delay).await;
+            update(&key, id, |status| {
+                status.state = TaskState::Running;
+                status.restarts += 1;
+            });
+        }
+    })
+}
+
+fn update(key: &str, id: u64, change: impl FnOnce(&mut TaskStatus)) {
+    let mut tasks = SUPERVISOR.tasks.lock().expect("supervisor lock poisoned");
+    if let Some((task_id, status)) = tasks.get_mut(key)
+        && *task_id == id
+    {
+        change(status);
+    }
+}
+
+/// Drops a task from [`statuses`] once its supervisor ends, unless it failed
+/// for good or was replaced.
+struct Registration {
+    key: String,
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let Ok(mut tasks) = SUPERVISOR.tasks.lock() else {
+            return;
+        };
+        if tasks
+            .get(&self.key)
+            .is_some_and(|(id, status)| *id == self.id && status.state != TaskState::Failed)
+        {
+            tasks.remove(&self.key);
+        }
+    }
+}
+
+/// Aborts the supervised task when its supervisor is aborted.
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_until_a_task_fails_too_often_within_the_window() {
+        let config = SupervisorConfig {
+            max_restarts: 3,
+            window_secs: 600,
+            initial_backoff_secs: 2,
+            max_backoff_secs: 5,
+        };
+        let (mut failures, start) = (VecDeque::new(), Instant::now());
+        let at = |secs: u64| start + Duration::from_secs(secs);
+
+        assert_eq!(
+            config.restart_delay(&mut failures, at(0)),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(
+            config.restart_delay(&mut failures, at(10)),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(
+            config.restart_delay(&mut failures, at(20)),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(config.restart_delay(&mut failures, at(30)), None);
+
+        // Failures older than the window no longer count.
+        let mut failures = VecDeque::from([at(0), at(10), at(20)]);
+        assert_eq!(
+            config.restart_delay(&mut failures, at(615)),
+            Some(Duration::from_secs(4))
+        );
+        assert_eq!(failures.len(), 2);
+    }
+}
@@ -0,0 +1,870 @@
+//! In-memory tables: loading CSVs, the small step language the
+//! `csv_analysis` tool runs on them, and rendering results as Markdown
+//! tables and text charts.
+//!
+//! The point is that arithmetic happens here rather than in the model, so
+//! the steps are deliberately limited to filters, computed columns,
+//! grouping with aggregates, sorting, and limits.
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Most rows a CSV may have.
+pub const MAX_ROWS: usize = 500_000;
+
+/// Longest rendered table, in characters. Rows past it are dropped.
+const MAX_TABLE_CHARS: usize = 16_000;
+
+/// Longest cell, in characters.
+const MAX_CELL_CHARS: usize = 200;
+
+/// Most bars in a bar chart, and points in a sparkline.
+const MAX_CHART_POINTS: usize = 60;
+
+/// Width of the longest bar, in characters.
+const BAR_WIDTH: usize = 30;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TableError {
+    #[error("can't read the CSV: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("the CSV has more than {MAX_ROWS} rows")]
+    TooLarge,
+
+    #[error("{0}")]
+    Step(String),
+}
+
+/// A cell. Cells that parse as finite numbers are numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Empty,
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        if text.is_empty() {
+            return Value::Empty;
+        }
+        match text.parse::<f64>() {
+            Ok(number) if number.is_finite() => Value::Number(number),
+            _ => Value::Text(text.to_string()),
+        }
+    }
+
+    fn from_json(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => Value::Empty,
+            serde_json::Value::Number(number) => {
+                number.as_f64().map_or(Value::Empty, Value::Number)
+            }
+            serde_json::Value::String(text) => Value::parse(text),
+            other => Value::Text(other.to_string()),
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// Numbers before text, empty cells last.
+    fn compare(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.cmp(b),
+            (Value::Number(_), _) | (Value::Text(_), Value::Empty) => Ordering::Less,
+            (Value::Empty, Value::Empty) => Ordering::Equal,
+            _ => Ordering::Greater,
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Empty => Ok(()),
+            Value::Number(number) => f.write_str(&format_number(*number)),
+            Value::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+/// Integers without a decimal point, everything else to six places with
+/// trailing zeros dropped.
+fn format_number(number: f64) -> String {
+    if number.fract() == 0.0 && number.abs() < 1e15 {
+        return format!("{number:.0}");
+    }
+    let text = format!("{number:.6}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl Table {
+    /// Parse a CSV with a header row. Tab- and semicolon-separated files are
+    /// detected from the header.
+    pub fn from_csv(data: &[u8]) -> Result<Self, TableError> {
+        let header = data.split(|byte| *byte == b'\n').next().unwrap_or_default();
+        let count = |delimiter: u8| header.iter().filter(|byte| **byte == delimiter).count();
+        let delimiter = [b',', b'\t', b';']
+            .into_iter()
+            .max_by_key(|delimiter| count(*delimiter))
+            .filter(|delimiter| count(*delimiter) > 0)
+            .unwrap_or(b',');
+
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(data);
+
+        let mut columns: Vec<String> = Vec::new();
+        for (index, name) in reader.headers()?.iter().enumerate() {
+            let mut name = name.trim().to_string();
+            if name.is_empty() {
+                name = format!("column_{}", index + 1);
+            }
+            while columns.contains(&name) {
+                name.push('_');
+            }
+            columns.push(name);
+        }
+
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            if rows.len() == MAX_ROWS {
+                return Err(TableError::TooLarge);
+            }
+            let record = record?;
+            let mut row: Vec<Value> = record
+                .iter()
+                .take(columns.len())
+                .map(Value::parse)
+                .collect();
+            row.resize(columns.len(), Value::Empty);
+            rows.push(row);
+        }
+        Ok(Table { columns, rows })
+    }
+
+    fn column(&self, name: &str) -> Result<usize, TableError> {
+        self.columns
+            .iter()
+            .position(|column| column == name)
+            .or_else(|| {
+                self.columns
+                    .iter()
+                    .position(|column| column.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| {
+                TableError::Step(format!(
+                    "no column named '{name}'; columns: {}",
+                    self.columns.join(", ")
+                ))
+            })
+    }
+
+    /// Run `steps` in order.
+    pub fn apply(mut self, steps: &[Step]) -> Result<Self, TableError> {
+        for step in steps {
+            self = self.apply_step(step)?;
+        }
+        Ok(self)
+    }
+
+    fn apply_step(mut self, step: &Step) -> Result<Self, TableError> {
+        match step {
+            Step::Filter { column, op, value } => {
+                let index = self.column(column)?;
+                let value = value.as_ref().map(Value::from_json);
+                if value.is_none() && !matches!(op, FilterOp::Empty | FilterOp::NotEmpty) {
+                    return Err(TableError::Step(format!(
+                        "filter on '{column}' needs a 'value'"
+                    )));
+                }
+                self.rows
+                    .retain(|row| op.matches(&row[index], value.as_ref()));
+                Ok(self)
+            }
+            Step::FilterIn { column, values } => {
+                let index = self.column(column)?;
+                let values: Vec<Value> = values.iter().map(Value::from_json).collect();
+                self.rows.retain(|row| values.contains(&row[index]));
+                Ok(self)
+            }
+            Step::Select { columns } => {
+                let indices = columns
+                    .iter()
+                    .map(|name| self.column(name))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Table {
+                    columns: indices.iter().map(|i| self.columns[*i].clone()).collect(),
+                    rows: self
+                        .rows
+                        .into_iter()
+                        .map(|row| indices.iter().map(|i| row[*i].clone()).collect())
+                        .collect(),
+                })
+            }
+            Step::Compute {
+                name,
+                left,
+                op,
+                right,
+            } => {
+                let left = self.operand(left)?;
+                let right = self.operand(right)?;
+                for row in &mut self.rows {
+                    let result = match (left.value(row), right.value(row)) {
+                        (Some(a), Some(b)) => op.apply(a, b),
+                        _ => None,
+                    };
+                    row.push(result.map_or(Value::Empty, Value::Number));
+                }
+                self.columns.push(name.clone());
+                Ok(self)
+            }
+            Step::GroupBy {
+                columns,
+                aggregates,
+            } => self.group_by(columns, aggregates),
+            Step::Sort { column, descending } => {
+                let index = self.column(column)?;
+                self.rows.sort_by(|a, b| {
+                    let ordering = a[index].compare(&b[index]);
+                    // Empty cells stay last either way.
+                    match (&a[index], &b[index]) {
+                        (Value::Empty, _) | (_, Value::Empty) => ordering,
+                        _ if *descending => ordering.reverse(),
+                        _ => ordering,
+                    }
+                });
+                Ok(self)
+            }
+            Step::Limit { rows } => {
+                self.rows.truncate(*rows);
+                Ok(self)
+            }
+        }
+    }
+
+    fn operand(&self, operand: &serde_json::Value) -> Result<Operand, TableError> {
+        match operand {
+            serde_json::Value::Number(number) => {
+                Ok(Operand::Constant(number.as_f64().unwrap_or_default()))
+            }
+            serde_json::Value::String(name) => self.column(name).map(Operand::Column),
+            other => Err(TableError::Step(format!(
+                "operands are column names or numbers, not {other}"
+            ))),
+        }
+    }
+
+    fn group_by(self, columns: &[String], aggregates: &[Aggregate]) -> Result<Self, TableError> {
+        let keys = columns
+            .iter()
+            .map(|name| self.column(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        let inputs = aggregates
+            .iter()
+            .map(|aggregate| match &aggregate.column {
+                Some(name) => self.column(name).map(Some),
+                None if aggregate.function == AggregateFunction::Count => Ok(None),
+                None => Err(TableError::Step(format!(
+                    "{:?} needs a 'column'",
+                    aggregate.function
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Groups in order of first appearance.
+        let mut groups: Vec<(Vec<Value>, Vec<&Vec<Value>>)> = Vec::new();
+        let mut positions: HashMap<Vec<String>, usize> = HashMap::new();
+        for row in &self.rows {
+            let key: Vec<String> = keys.iter().map(|i| row[*i].to_string()).collect();
+            let position = *positions.entry(key).or_insert_with(|| {
+                groups.push((keys.iter().map(|i| row[*i].clone()).collect(), Vec::new()));
+                groups.len() - 1
+            });
+            groups[position].1.push(row);
+        }
+        if keys.is_empty() && groups.is_empty() {
+            groups.push((Vec::new(), Vec::new()));
+        }
+
+        let mut names: Vec<String> = keys.iter().map(|i| self.columns[*i].clone()).collect();
+        names.extend(aggregates.iter().map(Aggregate::output_name));
+        let rows = groups
+            .into_iter()
+            .map(|(mut key, rows)| {
+                for (aggregate, input) in aggregates.iter().zip(&inputs) {
+                    let cells: Vec<&Value> = match input {
+                        Some(index) => rows.iter().map(|row| &row[*index]).collect(),
+                        None => Vec::new(),
+                    };
+                    key.push(aggregate.function.apply(rows.len(), &cells));
+                }
+                key
+            })
+            .collect();
+        Ok(Table {
+            columns: names,
+            rows,
+        })
+    }
+
+    /// A summary of each column: its type, how many cells are filled, and
+    /// for numbers, the range and mean.
+    pub fn describe(&self) -> Table {
+        let rows = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let cells: Vec<&Value> = self.rows.iter().map(|row| &row[index]).collect();
+                let filled = cells.iter().filter(|cell| **cell != &Value::Empty).count();
+                let numbers: Vec<f64> = cells.iter().filter_map(|cell| cell.as_number()).collect();
+                let numeric = filled > 0 && numbers.len() == filled;
+                let stat = |function: AggregateFunction| {
+                    if numeric {
+                        function.apply(filled, &cells)
+                    } else {
+                        Value::Empty
+                    }
+                };
+                vec![
+                    Value::Text(name.clone()),
+                    Value::Text(if numeric { "number" } else { "text" }.into()),
+                    Value::Number(filled as f64),
+                    AggregateFunction::CountDistinct.apply(filled, &cells),
+                    stat(AggregateFunction::Min),
+                    stat(AggregateFunction::Max),
+                    stat(AggregateFunction::Mean),
+                ]
+            })
+            .collect();
+        Table {
+            columns: ["column", "type", "filled", "distinct", "min", "max", "mean"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            rows,
+        }
+    }
+
+    /// The table as Markdown, and how many rows fit.
+    pub fn to_markdown(&self, max_rows: usize) -> (String, usize) {
+        let rows: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .take(max_rows)
+            .map(|row| row.iter().map(Value::to_string).collect())
+            .collect();
+        markdown_table(&self.columns, &rows)
+    }
+
+    /// A text chart of the `value` column, labelled by `label` for bar charts.
+    pub fn chart(&self, chart: &Chart) -> Result<String, TableError> {
+        let values = self.column(&chart.value)?;
+        let points: Vec<(String, f64)> = match &chart.label {
+            Some(label) => {
+                let labels = self.column(label)?;
+                self.rows
+                    .iter()
+                    .filter_map(|row| Some((row[labels].to_string(), row[values].as_number()?)))
+                    .collect()
+            }
+            None => self
+                .rows
+                .iter()
+                .enumerate()
+                .filter_map(|(i, row)| Some(((i + 1).to_string(), row[values].as_number()?)))
+                .collect(),
+        };
+        if points.is_empty() {
+            return Err(TableError::Step(format!(
+                "'{}' has no numbers to chart",
+                chart.value
+            )));
+        }
+        let points = &points[..points.len().min(MAX_CHART_POINTS)];
+        Ok(match chart.kind {
+            ChartKind::Bar => bar_chart(points),
+            ChartKind::Sparkline => sparkline(&chart.value, points),
+        })
+    }
+}
+
+enum Operand {
+    Column(usize),
+    Constant(f64),
+}
+
+impl Operand {
+    fn value(&self, row: &[Value]) -> Option<f64> {
+        match self {
+            Operand::Column(index) => row[*index].as_number(),
+            Operand::Constant(number) => Some(*number),
+        }
+    }
+}
+
+/// One step of an analysis, as the tool receives it, e.g.
+/// `{"filter": {"column": "region", "op": "eq", "value": "EU"}}`.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// Keep rows whose `column` compares to `value` by `op`.
+    Filter {
+        column: String,
+        op: FilterOp,
+        #[serde(default)]
+        value: Option<serde_json::Value>,
+    },
+    /// Keep rows whose `column` is one of `values`.
+    FilterIn {
+        column: String,
+        values: Vec<serde_json::Value>,
+    },
+    /// Keep only these columns, in this order.
+    Select { columns: Vec<String> },
+    /// Add a column `name` = `left` `op` `right`, where each side is a
+    /// column name or a number.
+    Compute {
+        name: String,
+        left: serde_json::Value,
+        op: ArithmeticOp,
+        right: serde_json::Value,
+    },
+    /// One row per distinct combination of `columns` (or one row for the
+    /// whole table when empty), with the aggregates as extra columns.
+    GroupBy {
+        #[serde(default)]
+        columns: Vec<String>,
+        aggregates: Vec<Aggregate>,
+    },
+    /// Stable sort by `column`, so chained sorts break ties.
+    Sort {
+        column: String,
+        #[serde(default)]
+        descending: bool,
+    },
+    /// Keep the first `rows` rows.
+    Limit { rows: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    /// Case-insensitive substring match.
+    Contains,
+    StartsWith,
+    Empty,
+    NotEmpty,
+}
+
+impl FilterOp {
+    /// Whether `cell` passes. Comparisons between numbers and text never do.
+    fn matches(self, cell: &Value, value: Option<&Value>) -> bool {
+        let Some(value) = value else {
+            return match self {
+                FilterOp::Empty => *cell == Value::Empty,
+                FilterOp::NotEmpty => *cell != Value::Empty,
+                _ => false,
+            };
+        };
+        let text = || cell.to_string().to_lowercase();
+        match self {
+            FilterOp::Empty => *cell == Value::Empty,
+            FilterOp::NotEmpty => *cell != Value::Empty,
+            FilterOp::Eq => cell == value,
+            FilterOp::Ne => cell != value,
+            FilterOp::Contains => text().contains(&value.to_string().to_lowercase()),
+            FilterOp::StartsWith => text().starts_with(&value.to_string().to_lowercase()),
+            FilterOp::Gt | FilterOp::Ge | FilterOp::Lt | FilterOp::Le => {
+                let ordering = match (cell, value) {
+                    (Value::Number(_), Value::Number(_)) | (Value::Text(_), Value::Text(_)) => {
+                        cell.compare(value)
+                    }
+                    _ => return false,
+                };
+                match self {
+                    FilterOp::Gt => ordering == Ordering::Greater,
+                    FilterOp::Ge => ordering != Ordering::Less,
+                    FilterOp::Lt => ordering == Ordering::Less,
+                    _ => ordering != Ordering::Greater,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+pub enum ArithmeticOp {
+    #[serde(rename = "+")]
+    Add,
+    #[serde(rename = "-")]
+    Subtract,
+    #[serde(rename = "*")]
+    Multiply,
+    #[serde(rename = "/")]
+    Divide,
+}
+
+impl ArithmeticOp {
+    fn apply(self, a: f64, b: f64) -> Option<f64> {
+        let result = match self {
+            ArithmeticOp::Add => a + b,
+            ArithmeticOp::Subtract => a - b,
+            ArithmeticOp::Multiply => a * b,
+            ArithmeticOp::Divide => a / b,
+        };
+        result.is_finite().then_some(result)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    /// The column to aggregate. Optional for `count`, which then counts rows.
+    #[serde(default)]
+    pub column: Option<String>,
+    /// Name of the result column. Defaults to e.g. "sum_revenue".
+    #[serde(default, rename = "as")]
+    pub name: Option<String>,
+}
+
+impl Aggregate {
+    fn output_name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let function = serde_json::to_value(self.function)
+            .ok()
+            .and_then(|value| value.as_str().map(String::from))
+            .unwrap_or_default();
+        match &self.column {
+            Some(column) => format!("{function}_{column}"),
+            None => function,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregateFunction {
+    /// Filled cells, or rows without a column.
+    Count,
+    CountDistinct,
+    Sum,
+    Mean,
+    Median,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    /// The aggregate over `cells`, from a group of `rows` rows. Numeric
+    /// aggregates skip cells that aren't numbers.
+    fn apply(self, rows: usize, cells: &[&Value]) -> Value {
+        let numbers = || cells.iter().filter_map(|cell| cell.as_number());
+        let number = |value: Option<f64>| value.map_or(Value::Empty, Value::Number);
+        match self {
+            AggregateFunction::Count if cells.is_empty() => Value::Number(rows as f64),
+            AggregateFunction::Count => {
+                Value::Number(cells.iter().filter(|cell| ***cell != Value::Empty).count() as f64)
+            }
+            AggregateFunction::CountDistinct => {
+                let mut distinct: Vec<String> = cells
+                    .iter()
+                    .filter(|cell| ***cell != Value::Empty)
+                    .map(|cell| cell.to_string())
+                    .collect();
+                distinct.sort();
+                distinct.dedup();
+                Value::Number(distinct.len() as f64)
+            }
+            AggregateFunction::Sum => Value::Number(numbers().sum()),
+            AggregateFunction::Mean => {
+                let count = numbers().count();
+                number((count > 0).then(|| numbers().sum::<f64>() / count as f64))
+            }
+            AggregateFunction::Median => {
+                let mut sorted: Vec<f64> = numbers().collect();
+                sorted.sort_by(f64::total_cmp);
+                let middle = sorted.len() / 2;
+                number(match sorted.len() {
+                    0 => None,
+                    n if n % 2 == 1 => Some(sorted[middle]),
+                    _ => Some((sorted[middle - 1] + sorted[middle]) / 2.0),
+                })
+            }
+            AggregateFunction::Min => number(numbers().min_by(f64::total_cmp)),
+            AggregateFunction::Max => number(numbers().max_by(f64::total_cmp)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Chart {
+    pub kind: ChartKind,
+    /// The numeric column to plot.
+    pub value: String,
+    /// Column naming each bar. Rows are numbered without it.
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartKind {
+    /// One horizontal bar per row.
+    Bar,
+    /// The values as a single line of block characters, for trends.
+    Sparkline,
+}
+
+fn bar_chart(points: &[(String, f64)]) -> String {
+    let label_width = points
+        .iter()
+        .map(|(label, _)| label.chars().count().min(24))
+        .max()
+        .unwrap_or_default();
+    let largest = points
+        .iter()
+        .map(|(_, value)| value.abs())
+        .fold(0.0, f64::max);
+
+    let mut chart = String::from("```\n");
+    for (label, value) in points {
+        let label: String = label.chars().take(24).collect();
+        let eighths = if largest > 0.0 {
+            (value.abs() / largest * (BAR_WIDTH * 8) as f64).round() as usize
+        } else {
+            0
+        };
+        let mut bar = "█".repeat(eighths / 8);
+        if eighths % 8 > 0 {
+            bar.push([' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'][eighths % 8]);
+        }
+        chart.push_str(&format!(
+            "{label:<label_width$}  {bar:<BAR_WIDTH$}  {}\n",
+            format_number(*value)
+        ));
+    }
+    chart.push_str("```");
+    chart
+}
+
+fn sparkline(name: &str, points: &[(String, f64)]) -> String {
+    const LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let min = points
+        .iter()
+        .map(|(_, value)| *value)
+        .fold(f64::INFINITY, f64::min);
+    let max = points
+        .iter()
+        .map(|(_, value)| *value)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let line: String = points
+        .iter()
+        .map(|(_, value)| {
+            let level = if max > min {
+                ((value - min) / (max - min) * 7.0).round() as usize
+            } else {
+                0
+            };
+            LEVELS[level]
+        })
+        .collect();
+    format!(
+        "```\n{name}: {line}\nfrom {} ({}) to {} ({}), min {}, max {}\n```",
+        format_number(points[0].1),
+        points[0].0,
+        format_number(points[points.len() - 1].1),
+        points[points.len() - 1].0,
+        format_number(min),
+        format_number(max)
+    )
+}
+
+/// `rows` as a Markdown table, cut to fit the output budget, and how many
+/// rows fit.
+pub fn markdown_table(columns: &[String], rows: &[Vec<String>]) -> (String, usize) {
+    if columns.is_empty() {
+        return ("(no rows)".into(), 0);
+    }
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().map(|cell| markdown_cell(cell)).collect();
+        format!("| {} |\n", cells.join(" | "))
+    };
+
+    let mut table = line(columns);
+    table.push_str(&format!("|{}\n", " --- |".repeat(columns.len())));
+    let mut shown = 0;
+    for row in rows {
+        let row = line(row);
+        if table.len() + row.len() > MAX_TABLE_CHARS {
+            break;
+        }
+        table.push_str(&row);
+        shown += 1;
+    }
+    (table, shown)
+}
+
+fn markdown_cell(cell: &str) -> String {
+    let mut text: String = cell
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .take(MAX_CELL_CHARS)
+        .collect();
+    if cell.chars().count() > MAX_CELL_CHARS {
+        text.push('…');
+    }
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SALES: &str = "region,product,units,price\n\
+        EU,widget,10,2.5\n\
+        US,widget,4,2.5\n\
+        EU,gadget,3,10\n\
+        US,gadget,,10\n\
+        APAC,widget,7,2.5\n";
+
+    fn steps(json: serde_json::Value) -> Vec<Step> {
+        serde_json::from_value(json).expect("valid steps")
+    }
+
+    #[test]
+    fn steps_compute_group_and_sort() {
+        let table = Table::from_csv(SALES.as_bytes()).unwrap();
+        let result = table
+            .apply(&steps(serde_json::json!([
+                { "compute": { "name": "revenue", "left": "units", "op": "*", "right": "price" } },
+                { "group_by": {
+                    "columns": ["region"],
+                    "aggregates": [
+                        { "function": "sum", "column": "revenue", "as": "revenue" },
+                        { "function": "count" },
+                        { "function": "mean", "column": "units" }
+                    ]
+                } },
+                { "sort": { "column": "revenue", "descending": true } }
+            ])))
+            .unwrap();
+
+        assert_eq!(result.columns, ["region", "revenue", "count", "mean_units"]);
+        let (markdown, shown) = result.to_markdown(50);
+        assert_eq!(shown, 3);
+        assert_eq!(
+            markdown,
+            "| region | revenue | count | mean_units |\n\
+             | --- | --- | --- | --- |\n\
+             | EU | 55 | 2 | 6.5 |\n\
+             | APAC | 17.5 | 1 | 7 |\n\
+             | US | 10 | 2 | 4 |\n"
+        );
+    }
+
+    #[test]
+    fn filters_compare_numbers_and_text() {
+        let table = Table::from_csv(SALES.as_bytes()).unwrap();
+        let count = |json: serde_json::Value| table.clone().apply(&steps(json)).unwrap().rows.len();
+
+        assert_eq!(
+            count(serde_json::json!([{ "filter": { "column": "units", "op": "ge", "value": 4 } }])),
+            3
+        );
+        assert_eq!(
+            count(
+                serde_json::json!([{ "filter": { "column": "units", "op": "gt", "value": "4" } }])
+            ),
+            2
+        );
+        assert_eq!(
+            count(
+                serde_json::json!([{ "filter": { "column": "Region", "op": "eq", "value": "EU" } }])
+            ),
+            2
+        );
+        assert_eq!(
+            count(serde_json::json!([{ "filter": { "column": "units", "op": "empty" } }])),
+            1
+        );
+        assert_eq!(
+            count(
+                serde_json::json!([{ "filter_in": { "column": "region", "values": ["EU", "APAC"] } }])
+            ),
+            3
+        );
+        assert!(
+            table
+                .clone()
+                .apply(&steps(
+                    serde_json::json!([{ "select": { "columns": ["missing"] } }])
+                ))
+                .is_err()
+        );
+        assert!(
+            table
+                .apply(&steps(
+                    serde_json::json!([{ "filter": { "column": "units", "op": "gt" } }])
+                ))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn tab_separated_files_and_charts() {
+        let table = Table::from_csv(b"month\tusers\nJan\t10\nFeb\t20\nMar\t40\n").unwrap();
+        assert_eq!(table.columns, ["month", "users"]);
+        assert_eq!(table.describe().rows[1][1], Value::Text("number".into()));
+
+        let bars = table
+            .chart(&Chart {
+                kind: ChartKind::Bar,
+                value: "users".into(),
+                label: Some("month".into()),
+            })
+            .unwrap();
+        assert!(
+            bars.contains(&format!("Mar  {}  40", "█".repeat(BAR_WIDTH))),
+            "{bars}"
+        );
+
+        let line = table
+            .chart(&Chart {
+                kind: ChartKind::Sparkline,
+                value: "users".into(),
+                label: Some("month".into()),
+            })
+            .unwrap();
+        assert!(line.contains("users: ▁▃█"), "{line}");
+    }
+}
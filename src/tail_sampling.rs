@@ -0,0 +1,215 @@
+//! Tail sampling for exported traces.
+//!
+//! With `[telemetry.tail_sampling]`, finished spans are held until their
+//! trace's root span ends. A trace where something failed, or whose root
+//! took at least `slow_ms`, is exported in full. Any other trace is exported
+//! as its root span alone, which still shows what ran and for how long
+//! without paying to store every LLM and tool call under it. Spans that end
+//! after their root follow the decision made for it.
+
+use opentelemetry::Context;
+use opentelemetry::trace::{Span as _, SpanId, Status, TraceContextExt as _, TraceId};
+use opentelemetry_sdk::error::OTelSdkResult;
+use opentelemetry_sdk::trace::{Span, SpanData, SpanProcessor};
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Traces held at once. Past this, new traces are exported in full.
+const MAX_TRACES: usize = 4096;
+
+/// Spans held per trace. Past this, further spans are dropped.
+const MAX_SPANS_PER_TRACE: usize = 1024;
+
+/// Decisions remembered for spans that end after their root.
+const MAX_DECISIONS: usize = 4096;
+
+/// Tail sampling settings, from `[telemetry.tail_sampling]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TailSamplingConfig {
+    /// A trace whose root span took at least this long is kept in full.
+    pub slow_ms: u64,
+}
+
+impl Default for TailSamplingConfig {
+    fn default() -> Self {
+        Self { slow_ms: 2000 }
+    }
+}
+
+/// A span processor that holds spans until their trace is decided, then
+/// passes what's kept on to `inner`.
+#[derive(Debug)]
+pub struct TailSampler<P> {
+    inner: P,
+    slow: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    traces: HashMap<TraceId, Trace>,
+    /// Whether each recently decided trace was kept in full, oldest first.
+    decided: VecDeque<(TraceId, bool)>,
+}
+
+#[derive(Debug)]
+struct Trace {
+    root: SpanId,
+    spans: Vec<SpanData>,
+    failed: bool,
+}
+
+impl<P: SpanProcessor> TailSampler<P> {
+    pub fn new(inner: P, config: &TailSamplingConfig) -> Self {
+        Self {
+            inner,
+            slow: Duration::from_millis(config.slow_ms),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// The spans to export now that `span` has ended.
+    fn finish(&self, span: SpanData) -> Vec<SpanData> {
+        let trace_id = span.span_context.trace_id();
+        let mut state = self.state.lock().expect("tail sampler lock");
+        let Some(trace) = state.traces.get_mut(&trace_id) else {
+            // Either decided already, or never held because too many traces were.
+            let kept = state
+                .decided
+                .iter()
+                .find(|(decided, _)| *decided == trace_id)
+                .is_none_or(|(_, kept)| *kept);
+            return if kept { vec![span] } else { Vec::new() };
+        };
+        trace.failed |= matches!(span.status, Status::Error { .. });
+        if span.span_context.span_id() != trace.root {
+            if trace.spans.len() < MAX_SPANS_PER_TRACE {
+                trace.spans.push(span);
+            }
+            return Vec::new();
+        }
+
+        let mut trace = state.traces.remove(&trace_id).expect("trace just found");
+        let duration = span
+            .end_time
+            .duration_since(span.start_time)
+            .unwrap_or_default();
+        let keep = trace.failed || duration >= self.slow;
+        if state.decided.len() == MAX_DECISIONS {
+            state.decided.pop_front();
+        }
+        state.decided.push_back((trace_id, keep));
+        if !keep {
+            return vec![span];
+        }
+        trace.spans.push(span);
+        trace.spans
+    }
+}
+
+impl<P: SpanProcessor> SpanProcessor for TailSampler<P> {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        // Roots of the local trace: nothing above them, or only a caller in
+        // another process.
+        let parent = cx.span();
+        if !cx.has_active_span() || parent.span_context().is_remote() {
+            let context = span.span_context();
+            let mut state = self.state.lock().expect("tail sampler lock");
+            if state.traces.len() < MAX_TRACES {
+                state.traces.insert(
+                    context.trace_id(),
+                    Trace {
+                        root: context.span_id(),
+                        spans: Vec::new(),
+                        failed: false,
+                    },
+                );
+            }
+        }
+        self.inner.on_start(span, cx);
+    }
+
+    fn on_end(&self, span: SpanData) {
+        for span in self.finish(span) {
+            self.inner.on_end(span);
+        }
+    }
+
+    fn force_flush(&self) -> OTelSdkResult {
+        self.inner.force_flush()
+    }
+
+    fn shutdown(&self) -> OTelSdkResult {
+        self.inner.shutdown()
+    }
+
+    fn set_resource(&mut self, resource: &opentelemetry_sdk::Resource) {
+        self.inner.set_resource(resource);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use opentelemetry::trace::{Tracer as _, TracerProvider as _};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    /// Names of the spans passed on, in order.
+    #[derive(Debug, Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl SpanProcessor for Recorder {
+        fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+        fn on_end(&self, span: SpanData) {
+            self.0.lock().unwrap().push(span.name.to_string());
+        }
+
+        fn force_flush(&self) -> OTelSdkResult {
+            Ok(())
+        }
+
+        fn shutdown(&self) -> OTelSdkResult {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn only_failed_or_slow_traces_are_kept_in_full() {
+        let recorder = Recorder::default();
+        let provider = SdkTracerProvider::builder()
+            .with_span_processor(TailSampler::new(
+                recorder.clone(),
+                &TailSamplingConfig { slow_ms: 1000 },
+            ))
+            .build();
+        let tracer = provider.tracer("test");
+
+        let trace = |name: &'static str, root_ms: u64, fail: bool| {
+            let start = SystemTime::now();
+            let root = tracer.start(name);
+            let cx = Context::current_with_span(root);
+            let mut child = tracer.start_with_context("llm_call", &cx);
+            if fail {
+                child.set_status(Status::error("provider returned 500"));
+            }
+            child.end();
+            cx.span()
+                .end_with_timestamp(start + Duration::from_millis(root_ms));
+        };
+        trace("quiet", 0, false);
+        trace("failed", 0, true);
+        trace("slow", 5000, false);
+
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            ["quiet", "llm_call", "failed", "llm_call", "slow"]
+        );
+    }
+}
@@ -2,6 +2,8 @@
 
 mod registry;
 mod server;
+mod subscriber;
 
 pub use registry::Metrics;
 pub use server::start_metrics_server;
+pub use subscriber::MetricsSubscriber;
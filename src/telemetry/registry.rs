@@ -1,7 +1,8 @@
 //! Global metrics registry and metric handle definitions.
 
 use prometheus::{
-    Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+    Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry,
 };
 
 use std::sync::LazyLock;
@@ -31,6 +32,16 @@ pub struct Metrics {
     /// Total memory save (write) operations.
     pub memory_writes_total: IntCounter,
 
+    /// Local model requests rejected because VRAM stayed exhausted.
+    pub vram_rejections_total: IntCounter,
+
+    /// Cache entries dropped by the memory watchdog.
+    /// Label: cache (e.g. "weather_responses", "prompt_snapshots").
+    pub cache_evictions_total: IntCounterVec,
+
+    /// Background jobs refused because memory was nearly exhausted.
+    pub background_jobs_rejected_total: IntCounter,
+
     // -- Histograms --
     /// LLM request duration in seconds.
     pub llm_request_duration_seconds: HistogramVec,
@@ -48,6 +59,28 @@ pub struct Metrics {
     // TODO: Not wired to any call site. Needs periodic store queries or
     // inc/dec in MemoryStore::save()/delete() to reflect actual counts.
     pub memory_entry_count: IntGaugeVec,
+
+    /// GPU memory in use, in bytes.
+    /// Label: gpu (device index).
+    pub gpu_memory_used_bytes: IntGaugeVec,
+
+    /// GPU memory capacity, in bytes.
+    /// Label: gpu (device index).
+    pub gpu_memory_total_bytes: IntGaugeVec,
+
+    /// GPU utilization percentage.
+    /// Label: gpu (device index).
+    pub gpu_utilization_percent: IntGaugeVec,
+
+    /// Host memory available for new allocations, in bytes.
+    pub host_memory_available_bytes: IntGauge,
+
+    /// Resident memory of the Spacebot process, in bytes.
+    pub memory_rss_bytes: IntGauge,
+
+    /// Unix time a rate limit cooldown ends.
+    /// Labels: scope ("model" or "provider"), name.
+    pub rate_limit_cooldown_until_seconds: IntGaugeVec,
 }
 
 impl Metrics {
@@ -81,6 +114,27 @@ impl Metrics {
         )
         .expect("hardcoded metric descriptor");
 
+        let vram_rejections_total = IntCounter::new(
+            "spacebot_vram_rejections_total",
+            "Local model requests rejected because VRAM stayed exhausted",
+        )
+        .expect("hardcoded metric descriptor");
+
+        let cache_evictions_total = IntCounterVec::new(
+            Opts::new(
+                "spacebot_cache_evictions_total",
+                "Cache entries dropped under memory pressure",
+            ),
+            &["cache"],
+        )
+        .expect("hardcoded metric descriptor");
+
+        let background_jobs_rejected_total = IntCounter::new(
+            "spacebot_background_jobs_rejected_total",
+            "Background jobs refused because memory was nearly exhausted",
+        )
+        .expect("hardcoded metric descriptor");
+
         let llm_request_duration_seconds = HistogramVec::new(
             HistogramOpts::new(
                 "spacebot_llm_request_duration_seconds",
@@ -118,6 +172,45 @@ impl Metrics {
         )
         .expect("hardcoded metric descriptor");
 
+        let gpu_memory_used_bytes = IntGaugeVec::new(
+            Opts::new("spacebot_gpu_memory_used_bytes", "GPU memory in use"),
+            &["gpu"],
+        )
+        .expect("hardcoded metric descriptor");
+
+        let gpu_memory_total_bytes = IntGaugeVec::new(
+            Opts::new("spacebot_gpu_memory_total_bytes", "GPU memory capacity"),
+            &["gpu"],
+        )
+        .expect("hardcoded metric descriptor");
+
+        let gpu_utilization_percent = IntGaugeVec::new(
+            Opts::new("spacebot_gpu_utilization_percent", "GPU utilization"),
+            &["gpu"],
+        )
+        .expect("hardcoded metric descriptor");
+
+        let host_memory_available_bytes = IntGauge::new(
+            "spacebot_host_memory_available_bytes",
+            "Host memory available for new allocations",
+        )
+        .expect("hardcoded metric descriptor");
+
+        let memory_rss_bytes = IntGauge::new(
+            "spacebot_memory_rss_bytes",
+            "Resident memory of the Spacebot process",
+        )
+        .expect("hardcoded metric descriptor");
+
+        let rate_limit_cooldown_until_seconds = IntGaugeVec::new(
+            Opts::new(
+                "spacebot_rate_limit_cooldown_until_seconds",
+                "Unix time a rate limit cooldown ends",
+            ),
+            &["scope", "name"],
+        )
+        .expect("hardcoded metric descriptor");
+
         registry
             .register(Box::new(llm_requests_total.clone()))
             .expect("hardcoded metric");
@@ -142,6 +235,33 @@ impl Metrics {
         registry
             .register(Box::new(memory_entry_count.clone()))
             .expect("hardcoded metric");
+        registry
+            .register(Box::new(vram_rejections_total.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(cache_evictions_total.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(background_jobs_rejected_total.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(gpu_memory_used_bytes.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(gpu_memory_total_bytes.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(gpu_utilization_percent.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(host_memory_available_bytes.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(memory_rss_bytes.clone()))
+            .expect("hardcoded metric");
+        registry
+            .register(Box::new(rate_limit_cooldown_until_seconds.clone()))
+            .expect("hardcoded metric");
 
         Self {
             registry,
@@ -149,10 +269,19 @@ impl Metrics {
             tool_calls_total,
             memory_reads_total,
             memory_writes_total,
+            vram_rejections_total,
+            cache_evictions_total,
+            background_jobs_rejected_total,
             llm_request_duration_seconds,
             tool_call_duration_seconds,
             active_workers,
             memory_entry_count,
+            gpu_memory_used_bytes,
+            gpu_memory_total_bytes,
+            gpu_utilization_percent,
+            host_memory_available_bytes,
+            memory_rss_bytes,
+            rate_limit_cooldown_until_seconds,
         }
     }
 
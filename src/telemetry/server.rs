@@ -34,9 +34,11 @@ pub async fn start_metrics_server(
         .route("/metrics", get(metrics_handler))
         .route("/health", get(health_handler));
 
-    let listener = tokio::net::TcpListener::bind(bind)
+    let listener = crate::listeners::bind("metrics", bind)
         .await
-        .map_err(|error| anyhow::anyhow!("failed to bind metrics server to {}: {}", bind, error))?;
+        .map_err(|error| {
+            anyhow::anyhow!("failed to bind metrics server to {}: {:#}", bind, error)
+        })?;
 
     tracing::info!(address = %bind, "metrics server started");
 
@@ -46,10 +48,7 @@ pub async fn start_metrics_server(
             let _ = shutdown_rx.wait_for(|shutdown| *shutdown).await;
         };
 
-        if let Err(error) = axum::serve(listener, app)
-            .with_graceful_shutdown(shutdown_signal)
-            .await
-        {
+        if let Err(error) = listener.serve(app, shutdown_signal).await {
             tracing::error!(%error, "metrics server failed");
         }
     });
@@ -0,0 +1,51 @@
+//! Event bus subscriber that records LLM and tool call metrics.
+
+use super::Metrics;
+use crate::events::{Event, Subscriber};
+
+/// Turns completion and tool events into Prometheus counters and histograms.
+pub struct MetricsSubscriber;
+
+impl Subscriber for MetricsSubscriber {
+    fn name(&self) -> &str {
+        "metrics"
+    }
+
+    async fn handle(&self, event: &Event) {
+        let metrics = Metrics::global();
+        match event {
+            Event::CompletionFinished {
+                model,
+                duration_secs,
+                ..
+            } => {
+                // TODO: agent_id and tier are "unknown" because SpacebotModel doesn't
+                // carry process context. Thread agent_id/ProcessType through to get
+                // per-agent, per-tier breakdowns.
+                metrics
+                    .llm_requests_total
+                    .with_label_values(&["unknown", model, "unknown"])
+                    .inc();
+                metrics
+                    .llm_request_duration_seconds
+                    .with_label_values(&["unknown", model, "unknown"])
+                    .observe(*duration_secs);
+            }
+            Event::ToolExecuted {
+                agent_id,
+                tool_name,
+                duration_secs,
+                ..
+            } => {
+                metrics
+                    .tool_calls_total
+                    .with_label_values(&[&**agent_id, tool_name])
+                    .inc();
+                if let Some(duration_secs) = duration_secs {
+                    metrics.tool_call_duration_seconds.observe(*duration_secs);
+                }
+            }
+            _ => {}
+        }
+    }
+}
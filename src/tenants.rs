@@ -0,0 +1,175 @@
+//! Tenants: one deployment serving several organizations.
+//!
+//! A tenant is a set of Discord guilds and Slack workspaces served by one
+//! agent of its own. The agent already isolates memory, databases, identity,
+//! and config overrides; the tenant adds routing that always sends its guilds
+//! and workspaces to that agent, provider keys and spend caps of its own, and
+//! an audit log of every event the agent produces.
+
+pub mod billing;
+
+use crate::config::Binding;
+use crate::events::{Event, Subscriber};
+use crate::llm::LlmManager;
+use crate::llm::budget::ProviderBudget;
+
+use tokio::io::AsyncWriteExt as _;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One tenant (instance-level, under `[[tenants]]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantConfig {
+    pub id: String,
+    pub name: Option<String>,
+    /// The agent serving this tenant. No other tenant may use it.
+    pub agent_id: String,
+    /// Discord guild IDs that belong to this tenant.
+    pub discord_guilds: Vec<String>,
+    /// Slack workspace (team) IDs that belong to this tenant.
+    pub slack_workspaces: Vec<String>,
+    /// Provider API keys used instead of the instance's, by provider ID.
+    pub api_keys: HashMap<String, String>,
+    /// Spend caps across every provider. Past either, the agent's LLM calls
+    /// are refused until the period rolls over.
+    pub daily_limit_usd: Option<f64>,
+    pub monthly_limit_usd: Option<f64>,
+}
+
+impl TenantConfig {
+    /// Bindings that route the tenant's guilds and workspaces to its agent.
+    pub fn bindings(&self) -> Vec<Binding> {
+        let binding = |channel: &str| Binding {
+            agent_id: self.agent_id.clone(),
+            channel: channel.into(),
+            guild_id: None,
+            workspace_id: None,
+            chat_id: None,
+            channel_ids: Vec::new(),
+            require_mention: false,
+            dm_allowed_users: Vec::new(),
+        };
+        let guilds = self.discord_guilds.iter().map(|guild_id| Binding {
+            guild_id: Some(guild_id.clone()),
+            ..binding("discord")
+        });
+        let workspaces = self.slack_workspaces.iter().map(|workspace_id| Binding {
+            workspace_id: Some(workspace_id.clone()),
+            ..binding("slack")
+        });
+        guilds.chain(workspaces).collect()
+    }
+
+    /// The caps as a budget, for checking against the tenant's spend.
+    pub fn budget(&self) -> ProviderBudget {
+        ProviderBudget {
+            daily_limit_usd: self.daily_limit_usd,
+            monthly_limit_usd: self.monthly_limit_usd,
+            downgrade_to: Vec::new(),
+        }
+    }
+
+    pub fn has_budget(&self) -> bool {
+        self.daily_limit_usd.is_some() || self.monthly_limit_usd.is_some()
+    }
+
+    /// Where the tenant's audit log is written.
+    pub fn audit_log_path(&self, instance_dir: &Path) -> PathBuf {
+        instance_dir
+            .join("tenants")
+            .join(&self.id)
+            .join("audit.jsonl")
+    }
+}
+
+/// Appends every event from a tenant's agent to the tenant's audit log, one
+/// JSON object per line.
+pub struct AuditLog {
+    instance_dir: PathBuf,
+    llm_manager: Arc<LlmManager>,
+}
+
+impl AuditLog {
+    /// Tenants are looked up through the LLM manager, which holds the
+    /// hot-reloaded tenant table alongside the keys and caps it applies.
+    pub fn new(instance_dir: PathBuf, llm_manager: Arc<LlmManager>) -> Self {
+        Self {
+            instance_dir,
+            llm_manager,
+        }
+    }
+
+    async fn append(path: &Path, line: &str) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(line.as_bytes()).await
+    }
+}
+
+impl Subscriber for AuditLog {
+    fn name(&self) -> &str {
+        "tenant_audit_log"
+    }
+
+    async fn handle(&self, event: &Event) {
+        let Some(tenant) = event
+            .agent_id()
+            .and_then(|agent_id| self.llm_manager.tenant(agent_id))
+        else {
+            return;
+        };
+        let Ok(serde_json::Value::Object(mut entry)) = serde_json::to_value(event) else {
+            return;
+        };
+        entry.insert("timestamp".into(), chrono::Utc::now().to_rfc3339().into());
+        let line = format!("{}\n", serde_json::Value::Object(entry));
+
+        let path = tenant.audit_log_path(&self.instance_dir);
+        if let Err(error) = Self::append(&path, &line).await {
+            tracing::warn!(%error, tenant = %tenant.id, "failed to write tenant audit log");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tenant_bindings_cover_guilds_and_workspaces() {
+        let tenant = TenantConfig {
+            id: "acme".into(),
+            name: None,
+            agent_id: "acme-bot".into(),
+            discord_guilds: vec!["111".into(), "222".into()],
+            slack_workspaces: vec!["T1".into()],
+            api_keys: HashMap::new(),
+            daily_limit_usd: None,
+            monthly_limit_usd: Some(50.0),
+        };
+
+        let bindings = tenant.bindings();
+        assert_eq!(bindings.len(), 3);
+        assert!(
+            bindings
+                .iter()
+                .all(|binding| binding.agent_id == "acme-bot")
+        );
+        assert_eq!(bindings[1].guild_id.as_deref(), Some("222"));
+        assert_eq!(bindings[2].channel, "slack");
+        assert_eq!(bindings[2].workspace_id.as_deref(), Some("T1"));
+        assert!(tenant.has_budget());
+        assert_eq!(
+            tenant.audit_log_path(Path::new("/data")),
+            PathBuf::from("/data/tenants/acme/audit.jsonl")
+        );
+    }
+}
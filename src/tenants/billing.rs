@@ -0,0 +1,375 @@
+//! Per-tenant usage for invoicing.
+//!
+//! Every completion a tenant's agent makes is counted by UTC day, tenant, and
+//! model: calls, tokens (with the output tokens spent reasoning counted
+//! apart), and cost at provider prices. Reports sum a date range
+//! per tenant and model and apply the `[billing]` markup, so an operator
+//! running the bot as a service can invoice each tenant for what it used.
+//!
+//! The ledger is persisted to `tenant_usage.json` in the instance directory.
+//! Markups are applied when a report is made, not when usage is recorded.
+
+use crate::llm::budget::write_atomically;
+use crate::llm::routing;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Markups applied to provider cost when billing tenants (`[billing]`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BillingConfig {
+    /// Percent added to cost for models without a more specific markup.
+    pub markup_percent: f64,
+    /// Percent by full model name (e.g. "openai/gpt-4.1") or provider ID.
+    /// A model's own entry wins over its provider's.
+    pub markups: HashMap<String, f64>,
+}
+
+impl BillingConfig {
+    /// The markup, in percent, for a model.
+    pub fn markup_for(&self, model_name: &str) -> f64 {
+        self.markups
+            .get(model_name)
+            .or_else(|| self.markups.get(routing::provider_from_model(model_name)))
+            .copied()
+            .unwrap_or(self.markup_percent)
+    }
+}
+
+/// Usage totals for one tenant and model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Output tokens spent reasoning, included in `output_tokens`.
+    #[serde(default)]
+    pub reasoning_tokens: u64,
+    /// Cost at provider prices, before markup.
+    pub cost_usd: f64,
+}
+
+impl Usage {
+    fn add(&mut self, other: &Usage) {
+        self.calls += other.calls;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.reasoning_tokens += other.reasoning_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+/// One row of a usage report: a tenant's use of one model over the range.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UsageLine {
+    pub tenant_id: String,
+    pub model: String,
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub cost_usd: f64,
+    pub markup_percent: f64,
+    pub billed_usd: f64,
+}
+
+/// A tenant's usage over a report's range, with a line per model.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TenantUsage {
+    pub tenant_id: String,
+    pub calls: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub cost_usd: f64,
+    pub billed_usd: f64,
+    pub models: Vec<UsageLine>,
+}
+
+/// Group report lines by tenant. Lines must be sorted by tenant, as
+/// [`UsageLedger::report`] returns them.
+pub fn summarize(lines: Vec<UsageLine>) -> Vec<TenantUsage> {
+    let mut tenants: Vec<TenantUsage> = Vec::new();
+    for line in lines {
+        let tenant = match tenants.last_mut() {
+            Some(tenant) if tenant.tenant_id == line.tenant_id => tenant,
+            _ => {
+                tenants.push(TenantUsage {
+                    tenant_id: line.tenant_id.clone(),
+                    calls: 0,
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    reasoning_tokens: 0,
+                    cost_usd: 0.0,
+                    billed_usd: 0.0,
+                    models: Vec::new(),
+                });
+                tenants.last_mut().expect("just pushed")
+            }
+        };
+        tenant.calls += line.calls;
+        tenant.input_tokens += line.input_tokens;
+        tenant.output_tokens += line.output_tokens;
+        tenant.reasoning_tokens += line.reasoning_tokens;
+        tenant.cost_usd += line.cost_usd;
+        tenant.billed_usd += line.billed_usd;
+        tenant.models.push(line);
+    }
+    tenants
+}
+
+/// Report lines as CSV, one row per tenant and model, with a header.
+pub fn to_csv(lines: &[UsageLine]) -> anyhow::Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    if lines.is_empty() {
+        writer.write_record([
+            "tenant_id",
+            "model",
+            "calls",
+            "input_tokens",
+            "output_tokens",
+            "reasoning_tokens",
+            "cost_usd",
+            "markup_percent",
+            "billed_usd",
+        ])?;
+    }
+    for line in lines {
+        writer.serialize(line)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Usage by UTC day (YYYY-MM-DD), then tenant, then model.
+type Days = BTreeMap<String, BTreeMap<String, BTreeMap<String, Usage>>>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LedgerData {
+    days: Days,
+}
+
+/// Records tenant usage and persists it across restarts.
+#[derive(Debug)]
+pub struct UsageLedger {
+    path: Option<PathBuf>,
+    data: Arc<Mutex<LedgerData>>,
+    /// Set while a write is queued, so bursts of calls coalesce into one write.
+    write_pending: Arc<AtomicBool>,
+    /// Serializes writes so an older snapshot can't land after a newer one.
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl UsageLedger {
+    /// In-memory ledger (nothing persisted).
+    pub fn in_memory() -> Self {
+        Self::with_data(None, LedgerData::default())
+    }
+
+    /// Load the ledger from `path`, starting fresh when it's missing or corrupt.
+    pub fn load_file(path: PathBuf) -> Self {
+        let data = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                tracing::warn!(%error, path = %path.display(), "corrupt usage ledger, starting fresh");
+                LedgerData::default()
+            }),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => LedgerData::default(),
+            Err(error) => {
+                tracing::warn!(%error, path = %path.display(), "failed to read usage ledger");
+                LedgerData::default()
+            }
+        };
+
+        Self::with_data(Some(path), data)
+    }
+
+    fn with_data(path: Option<PathBuf>, data: LedgerData) -> Self {
+        Self {
+            path,
+            data: Arc::new(Mutex::new(data)),
+            write_pending: Arc::new(AtomicBool::new(false)),
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Count one completion against a tenant on `day`.
+    pub fn record(&self, day: NaiveDate, tenant_id: &str, model_name: &str, usage: Usage) {
+        {
+            let mut data = self.data.lock().expect("usage ledger poisoned");
+            data.days
+                .entry(day.format("%Y-%m-%d").to_string())
+                .or_default()
+                .entry(tenant_id.to_string())
+                .or_default()
+                .entry(model_name.to_string())
+                .or_default()
+                .add(&usage);
+        }
+        self.schedule_write();
+    }
+
+    /// Usage per tenant and model from `from` to `to` (both inclusive),
+    /// sorted by tenant, then model. `tenant_id` limits it to one tenant.
+    pub fn report(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        tenant_id: Option<&str>,
+        billing: &BillingConfig,
+    ) -> Vec<UsageLine> {
+        let from = from.format("%Y-%m-%d").to_string();
+        let to = to.format("%Y-%m-%d").to_string();
+
+        let mut totals: BTreeMap<(String, String), Usage> = BTreeMap::new();
+        {
+            let data = self.data.lock().expect("usage ledger poisoned");
+            for tenants in data.days.range(from..=to).map(|(_, tenants)| tenants) {
+                for (tenant, models) in tenants {
+                    if tenant_id.is_some_and(|tenant_id| tenant_id != tenant) {
+                        continue;
+                    }
+                    for (model, usage) in models {
+                        totals
+                            .entry((tenant.clone(), model.clone()))
+                            .or_default()
+                            .add(usage);
+                    }
+                }
+            }
+        }
+
+        totals
+            .into_iter()
+            .map(|((tenant_id, model), usage)| {
+                let markup_percent = billing.markup_for(&model);
+                UsageLine {
+                    billed_usd: usage.cost_usd * (1.0 + markup_percent / 100.0),
+                    tenant_id,
+                    model,
+                    calls: usage.calls,
+                    input_tokens: usage.input_tokens,
+                    output_tokens: usage.output_tokens,
+                    reasoning_tokens: usage.reasoning_tokens,
+                    cost_usd: usage.cost_usd,
+                    markup_percent,
+                }
+            })
+            .collect()
+    }
+
+    fn schedule_write(&self) {
+        let Some(path) = self.path.clone() else {
+            return;
+        };
+        // A write is already queued and will pick up this change.
+        if self.write_pending.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let data = self.data.clone();
+        let write_pending = self.write_pending.clone();
+        let write_lock = self.write_lock.clone();
+        let write = move || {
+            let _guard = write_lock.lock().expect("usage write lock poisoned");
+            write_pending.store(false, Ordering::Release);
+            let serialized = {
+                let data = data.lock().expect("usage ledger poisoned");
+                serde_json::to_string(&*data)
+            };
+            let result = serialized
+                .map_err(std::io::Error::other)
+                .and_then(|serialized| write_atomically(&path, serialized.as_bytes()));
+            if let Err(error) = result {
+                tracing::warn!(%error, path = %path.display(), "failed to persist usage ledger");
+            }
+        };
+
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(write);
+            }
+            Err(_) => write(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(value: &str) -> NaiveDate {
+        value.parse().expect("valid date")
+    }
+
+    fn usage(cost_usd: f64) -> Usage {
+        Usage {
+            calls: 1,
+            input_tokens: 1000,
+            output_tokens: 100,
+            reasoning_tokens: 40,
+            cost_usd,
+        }
+    }
+
+    #[test]
+    fn reports_sum_the_range_and_apply_markups() {
+        let ledger = UsageLedger::in_memory();
+        ledger.record(day("2026-09-30"), "acme", "openai/gpt-4.1", usage(5.0));
+        ledger.record(day("2026-10-01"), "acme", "openai/gpt-4.1", usage(1.0));
+        ledger.record(day("2026-10-02"), "acme", "openai/gpt-4.1", usage(2.0));
+        ledger.record(
+            day("2026-10-02"),
+            "acme",
+            "anthropic/claude-sonnet-4",
+            usage(4.0),
+        );
+        ledger.record(day("2026-10-03"), "globex", "openai/gpt-4.1", usage(8.0));
+
+        let billing = BillingConfig {
+            markup_percent: 10.0,
+            markups: HashMap::from([("openai".into(), 50.0), ("openai/gpt-4.1-mini".into(), 0.0)]),
+        };
+        let lines = ledger.report(day("2026-10-01"), day("2026-10-31"), None, &billing);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].tenant_id, "acme");
+        assert_eq!(lines[0].model, "anthropic/claude-sonnet-4");
+        assert_eq!(lines[0].markup_percent, 10.0);
+        assert!((lines[0].billed_usd - 4.4).abs() < 1e-9);
+        assert_eq!(lines[1].calls, 2);
+        assert_eq!(lines[1].input_tokens, 2000);
+        assert_eq!(lines[1].reasoning_tokens, 80);
+        assert!((lines[1].billed_usd - 4.5).abs() < 1e-9);
+
+        let tenants = summarize(lines);
+        assert_eq!(tenants.len(), 2);
+        assert_eq!(tenants[0].calls, 3);
+        assert!((tenants[0].cost_usd - 7.0).abs() < 1e-9);
+        assert!((tenants[1].billed_usd - 12.0).abs() < 1e-9);
+
+        let only_globex = ledger.report(
+            day("2026-10-01"),
+            day("2026-10-31"),
+            Some("globex"),
+            &billing,
+        );
+        assert_eq!(only_globex.len(), 1);
+
+        let csv = to_csv(&only_globex).expect("csv");
+        let mut rows = csv.lines();
+        assert_eq!(
+            rows.next(),
+            Some(
+                "tenant_id,model,calls,input_tokens,output_tokens,reasoning_tokens,cost_usd,markup_percent,billed_usd"
+            )
+        );
+        assert_eq!(
+            rows.next(),
+            Some("globex,openai/gpt-4.1,1,1000,100,40,8.0,50.0,12.0")
+        );
+        assert!(to_csv(&[]).expect("csv").starts_with("tenant_id,model,"));
+    }
+}
@@ -6,9 +6,12 @@
 //! ## ToolServer Topology
 //!
 //! **Channel ToolServer** (one per channel):
-//! - `reply`, `branch`, `spawn_worker`, `route`, `cancel`, `skip`, `react` — added
+//! - `reply`, `branch`, `spawn_worker`, `route`, `cancel`, `skip`, `react`, `poll`, `remind` —
+//!   added
 //!   dynamically per conversation turn via `add_channel_tools()` /
 //!   `remove_channel_tools()` because they hold per-channel state.
+//! - `flow` — added the same way when the agent has guided flows.
+//! - `artifact_read` — added the same way when pasted code artifacts are on.
 //! - No memory tools — the channel delegates memory work to branches.
 //!
 //! **Branch ToolServer** (one per branch, isolated):
@@ -17,43 +20,87 @@
 //! **Worker ToolServer** (one per worker, created at spawn time):
 //! - `shell`, `file`, `exec` — stateless, registered at creation
 //! - `set_status` — per-worker instance, registered at creation
+//! - `<plugin>_<tool>` — one per tool from each loaded WASM plugin
 //!
 //! **Cortex ToolServer** (one per agent):
 //! - `memory_save` — registered at startup
+//!
+//! **Cortex Chat ToolServer** (one per agent, admin-only):
+//! - memory, channel recall, `shell`, `file`, `exec`, `browser`, `web_search`
+//! - `ollama_models` — when an Ollama provider is configured
+//! - `<plugin>_<tool>` — one per tool from each loaded WASM plugin
+//!
+//! Tools that return outside content (`browser`, `web_search`, `github`,
+//! `railway`, HTTP API and plugin tools, `email`) are registered wrapped in
+//! [`Scanned`], which checks their output for prompt injection.
 
+pub mod artifact_read;
 pub mod branch_tool;
+#[cfg(feature = "browser")]
 pub mod browser;
+pub mod calculate;
+pub mod calendar;
 pub mod cancel;
 pub mod channel_recall;
+pub mod chart;
 pub mod cron;
+pub mod csv_analysis;
+pub mod email;
 pub mod exec;
 pub mod file;
+pub mod flow;
+pub mod geocode;
+pub mod github;
+pub mod http_request;
 pub mod memory_delete;
 pub mod memory_recall;
 pub mod memory_save;
+pub mod ollama_models;
+pub mod plugin;
+pub mod poll;
+pub mod railway;
 pub mod react;
+pub mod remind;
 pub mod reply;
 pub mod route;
+pub mod scanned;
 pub mod send_file;
 pub mod send_message_to_another_channel;
 pub mod set_status;
 pub mod shell;
 pub mod skip;
 pub mod spawn_worker;
+pub mod sql_query;
+pub mod weather;
 pub mod web_search;
 
+pub use artifact_read::{
+    ArtifactReadArgs, ArtifactReadError, ArtifactReadOutput, ArtifactReadTool,
+};
 pub use branch_tool::{BranchArgs, BranchError, BranchOutput, BranchTool};
+#[cfg(feature = "browser")]
 pub use browser::{
     ActKind, BrowserAction, BrowserArgs, BrowserError, BrowserOutput, BrowserTool, ElementSummary,
     TabInfo,
 };
+pub use calculate::{CalculateArgs, CalculateError, CalculateOutput, CalculateTool};
+pub use calendar::{CalendarArgs, CalendarOutput, CalendarTool, CalendarToolError, EventEntry};
 pub use cancel::{CancelArgs, CancelError, CancelOutput, CancelTool};
 pub use channel_recall::{
     ChannelRecallArgs, ChannelRecallError, ChannelRecallOutput, ChannelRecallTool,
 };
+pub use chart::{ChartArgs, ChartOutput, ChartTool, ChartToolError};
 pub use cron::{CronArgs, CronError, CronOutput, CronTool};
+pub use csv_analysis::{CsvAnalysisArgs, CsvAnalysisError, CsvAnalysisOutput, CsvAnalysisTool};
+pub use email::{EmailArgs, EmailOutput, EmailTool, EmailToolError};
 pub use exec::{EnvVar, ExecArgs, ExecError, ExecOutput, ExecResult, ExecTool};
 pub use file::{FileArgs, FileEntry, FileEntryOutput, FileError, FileOutput, FileTool, FileType};
+pub use flow::{FlowArgs, FlowError, FlowOutput, FlowTool};
+pub use geocode::{GeocodeArgs, GeocodeError, GeocodeOutput, GeocodeTool};
+pub use github::{CheckEntry, GithubArgs, GithubError, GithubOutput, GithubTool, IssueEntry};
+pub use http_request::{
+    ApiOperationTool, HttpRequestArgs, HttpRequestError, HttpRequestOutput, HttpRequestTool,
+};
 pub use memory_delete::{
     MemoryDeleteArgs, MemoryDeleteError, MemoryDeleteOutput, MemoryDeleteTool,
 };
@@ -63,9 +110,19 @@ pub use memory_recall::{
 pub use memory_save::{
     AssociationInput, MemorySaveArgs, MemorySaveError, MemorySaveOutput, MemorySaveTool,
 };
+pub use ollama_models::{
+    OllamaModelsArgs, OllamaModelsError, OllamaModelsOutput, OllamaModelsTool,
+};
+pub use plugin::{PluginTool, PluginToolError};
+pub use poll::{PollArgs, PollError, PollOutput, PollTool};
+pub use railway::{
+    DeploymentEntry, RailwayArgs, RailwayError, RailwayOutput, RailwayTool, VariableEntry,
+};
 pub use react::{ReactArgs, ReactError, ReactOutput, ReactTool};
+pub use remind::{RemindArgs, RemindError, RemindOutput, RemindTool, ReminderEntry};
 pub use reply::{RepliedFlag, ReplyArgs, ReplyError, ReplyOutput, ReplyTool, new_replied_flag};
 pub use route::{RouteArgs, RouteError, RouteOutput, RouteTool};
+pub use scanned::{Scanned, ScannedError};
 pub use send_file::{SendFileArgs, SendFileError, SendFileOutput, SendFileTool};
 pub use send_message_to_another_channel::{
     SendMessageArgs, SendMessageError, SendMessageOutput, SendMessageTool,
@@ -74,11 +131,22 @@ pub use set_status::{SetStatusArgs, SetStatusError, SetStatusOutput, SetStatusTo
 pub use shell::{ShellArgs, ShellError, ShellOutput, ShellResult, ShellTool};
 pub use skip::{SkipArgs, SkipError, SkipFlag, SkipOutput, SkipTool, new_skip_flag};
 pub use spawn_worker::{SpawnWorkerArgs, SpawnWorkerError, SpawnWorkerOutput, SpawnWorkerTool};
+pub use sql_query::{SqlQueryArgs, SqlQueryError, SqlQueryOutput, SqlQueryTool};
+pub use weather::{WeatherArgs, WeatherOutput, WeatherTool, WeatherToolError};
 pub use web_search::{SearchResult, WebSearchArgs, WebSearchError, WebSearchOutput, WebSearchTool};
 
 use crate::agent::channel::ChannelState;
-use crate::config::BrowserConfig;
+use crate::agent::output_guard::OutputGuard;
+use crate::config::{
+    BrowserConfig, ChartConfig, GithubConfig, HttpApiConfig, RailwayConfig, SqlDatabaseConfig,
+    WeatherConfig,
+};
+use crate::injection::InjectionGuard;
+use crate::llm::LlmManager;
 use crate::memory::MemorySearch;
+use crate::plugins::PluginHost;
+use crate::storage::ArtifactStore;
+use crate::weather::WeatherClient;
 use crate::{AgentId, ChannelId, OutboundResponse, ProcessEvent, WorkerId};
 use rig::tool::Tool as _;
 use rig::tool::server::{ToolServer, ToolServerHandle};
@@ -123,6 +191,7 @@ pub fn truncate_output(value: &str, max_bytes: usize) -> String {
 /// Called when a conversation turn begins. These tools hold per-turn state
 /// (response sender, skip flag) that changes between turns. Cleaned up via
 /// `remove_channel_tools()` when the turn ends.
+#[allow(clippy::too_many_arguments)]
 pub async fn add_channel_tools(
     handle: &ToolServerHandle,
     state: ChannelState,
@@ -130,16 +199,30 @@ pub async fn add_channel_tools(
     conversation_id: impl Into<String>,
     skip_flag: SkipFlag,
     replied_flag: RepliedFlag,
+    output_guard: Arc<OutputGuard>,
     cron_tool: Option<CronTool>,
+    remind_tool: Option<RemindTool>,
+    calendar_tool: Option<CalendarTool>,
+    email_tool: Option<EmailTool>,
+    weather_tool: Option<WeatherTool>,
 ) -> Result<(), rig::tool::server::ToolServerError> {
     handle
-        .add_tool(ReplyTool::new(
-            response_tx.clone(),
-            conversation_id,
-            state.conversation_logger.clone(),
-            state.channel_id.clone(),
-            replied_flag.clone(),
-        ))
+        .add_tool(
+            ReplyTool::new(
+                response_tx.clone(),
+                conversation_id,
+                state.conversation_logger.clone(),
+                state.channel_id.clone(),
+                replied_flag.clone(),
+            )
+            .with_scripts(
+                state.deps.agent_id.clone(),
+                state.deps.runtime_config.scripts.load_full(),
+            )
+            .with_citations(**state.deps.runtime_config.citations.load())
+            .with_post_processing(state.deps.runtime_config.post_processing.load_full())
+            .with_output_guard(output_guard),
+        )
         .await?;
     handle.add_tool(BranchTool::new(state.clone())).await?;
     handle.add_tool(SpawnWorkerTool::new(state.clone())).await?;
@@ -152,6 +235,49 @@ pub async fn add_channel_tools(
             ))
             .await?;
     }
+    handle
+        .add_tool(PollTool::new(
+            crate::polls::PollStore::new(state.deps.sql_pool.clone()),
+            state.channel_id.clone(),
+            response_tx.clone(),
+            state.conversation_logger.clone(),
+        ))
+        .await?;
+    let runtime_config = &state.deps.runtime_config;
+    if runtime_config.compaction.load().artifacts {
+        handle
+            .add_tool(ArtifactReadTool::new(
+                crate::conversation::artifacts::CodeArtifactStore::new(
+                    state.deps.sql_pool.clone(),
+                ),
+                state.channel_id.clone(),
+            ))
+            .await?;
+    }
+    let flows = runtime_config.flows.load_full();
+    if !flows.is_empty() {
+        handle
+            .add_tool(FlowTool::new(
+                crate::flows::FlowStore::new(state.deps.sql_pool.clone()),
+                flows,
+                state.deps.agent_id.clone(),
+                state.channel_id.clone(),
+            ))
+            .await?;
+    }
+    handle
+        .add_tool(
+            ChartTool::new(
+                (**runtime_config.charts.load()).clone(),
+                runtime_config.workspace_dir.clone(),
+            )
+            .with_attachments(response_tx.clone()),
+        )
+        .await?;
+    handle.add_tool(CalculateTool::new()).await?;
+    if let Some(client) = WeatherClient::new((**runtime_config.weather.load()).clone()) {
+        handle.add_tool(GeocodeTool::new(client)).await?;
+    }
     handle.add_tool(CancelTool::new(state)).await?;
     handle
         .add_tool(SkipTool::new(skip_flag, response_tx.clone()))
@@ -163,6 +289,39 @@ pub async fn add_channel_tools(
     if let Some(cron) = cron_tool {
         handle.add_tool(cron).await?;
     }
+    if let Some(remind) = remind_tool {
+        handle.add_tool(remind).await?;
+    }
+    if let Some(calendar) = calendar_tool {
+        handle.add_tool(calendar).await?;
+    }
+    if let Some(email) = email_tool {
+        let guard = InjectionGuard::new(
+            &runtime_config.injection.load(),
+            state.deps.agent_id.clone(),
+            Some(state.channel_id.clone()),
+        );
+        handle
+            .add_tool(Scanned::new(email, Arc::new(guard)))
+            .await?;
+    }
+    if let Some(weather) = weather_tool {
+        handle.add_tool(weather).await?;
+    }
+    Ok(())
+}
+
+/// Remove the per-channel tools a sender's role doesn't allow, right after
+/// `add_channel_tools`.
+pub async fn restrict_channel_tools(
+    handle: &ToolServerHandle,
+    policy: &crate::access::RolePolicy,
+) -> Result<(), rig::tool::server::ToolServerError> {
+    for definition in handle.get_tool_defs(None).await? {
+        if !policy.allows_tool(&definition.name) {
+            handle.remove_tool(&definition.name).await?;
+        }
+    }
     Ok(())
 }
 
@@ -174,16 +333,31 @@ pub async fn remove_channel_tools(
     handle: &ToolServerHandle,
 ) -> Result<(), rig::tool::server::ToolServerError> {
     handle.remove_tool(ReplyTool::NAME).await?;
-    handle.remove_tool(BranchTool::NAME).await?;
-    handle.remove_tool(SpawnWorkerTool::NAME).await?;
-    handle.remove_tool(RouteTool::NAME).await?;
-    handle.remove_tool(CancelTool::NAME).await?;
     handle.remove_tool(SkipTool::NAME).await?;
-    handle.remove_tool(SendFileTool::NAME).await?;
-    handle.remove_tool(ReactTool::NAME).await?;
-    // Cron and send_message removal is best-effort since not all channels have them
-    let _ = handle.remove_tool(CronTool::NAME).await;
-    let _ = handle.remove_tool(SendMessageTool::NAME).await;
+    // Everything else is best-effort: config decides which tools a turn has,
+    // and `restrict_channel_tools` may have taken some away already.
+    for name in [
+        BranchTool::NAME,
+        SpawnWorkerTool::NAME,
+        RouteTool::NAME,
+        CancelTool::NAME,
+        SendFileTool::NAME,
+        ReactTool::NAME,
+        PollTool::NAME,
+        ChartTool::NAME,
+        CalculateTool::NAME,
+        CronTool::NAME,
+        SendMessageTool::NAME,
+        RemindTool::NAME,
+        CalendarTool::NAME,
+        EmailTool::NAME,
+        WeatherTool::NAME,
+        GeocodeTool::NAME,
+        FlowTool::NAME,
+        ArtifactReadTool::NAME,
+    ] {
+        let _ = handle.remove_tool(name).await;
+    }
     Ok(())
 }
 
@@ -205,14 +379,41 @@ pub fn create_branch_tool_server(
         .run()
 }
 
+/// Create a per-chunk ToolServer for file ingestion.
+///
+/// The branch tools, except that `memory_save` records `source` on every
+/// memory, so searches can filter on the file a memory came from, and pushes
+/// the ID of each memory it saves onto `saved`.
+pub fn create_ingestion_tool_server(
+    memory_search: Arc<MemorySearch>,
+    conversation_logger: crate::conversation::history::ConversationLogger,
+    channel_store: crate::conversation::ChannelStore,
+    source: String,
+    saved: Arc<std::sync::Mutex<Vec<String>>>,
+) -> ToolServerHandle {
+    ToolServer::new()
+        .tool(
+            MemorySaveTool::new(memory_search.clone())
+                .with_source(source)
+                .with_saved_ids(saved),
+        )
+        .tool(MemoryRecallTool::new(memory_search.clone()))
+        .tool(MemoryDeleteTool::new(memory_search))
+        .tool(ChannelRecallTool::new(conversation_logger, channel_store))
+        .run()
+}
+
 /// Create a per-worker ToolServer with task-appropriate tools.
 ///
 /// Each worker gets its own isolated ToolServer. The `set_status` tool is bound to
 /// the specific worker's ID so status updates route correctly. The browser tool
-/// is included when browser automation is enabled in the agent config.
+/// is included when browser automation is enabled in the agent config and the
+/// build has the `browser` feature, and the GitHub and Railway tools when their
+/// credentials are configured.
 ///
 /// File operations are restricted to `workspace`. Shell and exec commands are
 /// blocked from accessing sensitive files in `instance_dir`.
+#[allow(clippy::too_many_arguments)]
 pub fn create_worker_tool_server(
     agent_id: AgentId,
     worker_id: WorkerId,
@@ -220,24 +421,75 @@ pub fn create_worker_tool_server(
     event_tx: broadcast::Sender<ProcessEvent>,
     browser_config: BrowserConfig,
     screenshot_dir: PathBuf,
+    artifacts: Arc<ArtifactStore>,
     brave_search_key: Option<String>,
+    fetch_limits: crate::llm::http::BodyLimits,
+    github: GithubConfig,
+    railway: RailwayConfig,
+    http_apis: std::collections::BTreeMap<String, HttpApiConfig>,
+    sql_databases: std::collections::BTreeMap<String, SqlDatabaseConfig>,
+    charts: ChartConfig,
+    weather: WeatherConfig,
+    plugins: &PluginHost,
+    injection: Arc<InjectionGuard>,
     workspace: PathBuf,
     instance_dir: PathBuf,
 ) -> ToolServerHandle {
     let mut server = ToolServer::new()
         .tool(ShellTool::new(instance_dir.clone(), workspace.clone()))
         .tool(FileTool::new(workspace.clone()))
+        .tool(CsvAnalysisTool::new(workspace.clone()))
+        .tool(ChartTool::new(charts, workspace.clone()))
+        .tool(CalculateTool::new())
         .tool(ExecTool::new(instance_dir, workspace))
         .tool(SetStatusTool::new(
             agent_id, worker_id, channel_id, event_tx,
         ));
 
+    #[cfg(feature = "browser")]
     if browser_config.enabled {
-        server = server.tool(BrowserTool::new(browser_config, screenshot_dir));
+        server = server.tool(Scanned::new(
+            BrowserTool::new(browser_config, screenshot_dir, artifacts),
+            injection.clone(),
+        ));
     }
+    #[cfg(not(feature = "browser"))]
+    let _ = (browser_config, screenshot_dir, artifacts);
 
     if let Some(key) = brave_search_key {
-        server = server.tool(WebSearchTool::new(key));
+        server = server.tool(Scanned::new(
+            WebSearchTool::new(key, fetch_limits),
+            injection.clone(),
+        ));
+    }
+
+    if github.is_enabled() {
+        server = server.tool(Scanned::new(GithubTool::new(github), injection.clone()));
+    }
+
+    if railway.is_enabled() {
+        server = server.tool(Scanned::new(RailwayTool::new(railway), injection.clone()));
+    }
+
+    let (http_request, operations) = http_request::http_api_tools(http_apis, fetch_limits);
+    if let Some(http_request) = http_request {
+        server = server.tool(Scanned::new(http_request, injection.clone()));
+    }
+    for operation in operations {
+        server = server.tool(Scanned::new(operation, injection.clone()));
+    }
+    for tool in plugin::plugin_tools(plugins) {
+        server = server.tool(Scanned::new(tool, injection.clone()));
+    }
+
+    if !sql_databases.is_empty() {
+        server = server.tool(SqlQueryTool::new(sql_databases));
+    }
+
+    if let Some(client) = WeatherClient::new(weather) {
+        server = server
+            .tool(WeatherTool::new(client.clone()))
+            .tool(GeocodeTool::new(client));
     }
 
     server.run()
@@ -258,16 +510,30 @@ pub fn create_cortex_tool_server(memory_search: Arc<MemorySearch>) -> ToolServer
 /// Combines branch tools (memory) with worker tools (shell, file, exec) to give
 /// the interactive cortex full capabilities. Does not include channel-specific
 /// tools (reply, react, skip) since the cortex chat doesn't talk to platforms.
+/// Admin-only tools like `ollama_models` live here since only the dashboard
+/// can reach cortex chat.
+#[allow(clippy::too_many_arguments)]
 pub fn create_cortex_chat_tool_server(
     memory_search: Arc<MemorySearch>,
+    llm_manager: Arc<LlmManager>,
     conversation_logger: crate::conversation::history::ConversationLogger,
     channel_store: crate::conversation::ChannelStore,
     browser_config: BrowserConfig,
     screenshot_dir: PathBuf,
+    artifacts: Arc<ArtifactStore>,
     brave_search_key: Option<String>,
+    github: GithubConfig,
+    railway: RailwayConfig,
+    http_apis: std::collections::BTreeMap<String, HttpApiConfig>,
+    sql_databases: std::collections::BTreeMap<String, SqlDatabaseConfig>,
+    charts: ChartConfig,
+    weather: WeatherConfig,
+    plugins: &PluginHost,
+    injection: Arc<InjectionGuard>,
     workspace: PathBuf,
     instance_dir: PathBuf,
 ) -> ToolServerHandle {
+    let fetch_limits = llm_manager.fetch_limits();
     let mut server = ToolServer::new()
         .tool(MemorySaveTool::new(memory_search.clone()))
         .tool(MemoryRecallTool::new(memory_search.clone()))
@@ -275,14 +541,59 @@ pub fn create_cortex_chat_tool_server(
         .tool(ChannelRecallTool::new(conversation_logger, channel_store))
         .tool(ShellTool::new(instance_dir.clone(), workspace.clone()))
         .tool(FileTool::new(workspace.clone()))
+        .tool(CsvAnalysisTool::new(workspace.clone()))
+        .tool(ChartTool::new(charts, workspace.clone()))
+        .tool(CalculateTool::new())
         .tool(ExecTool::new(instance_dir, workspace));
 
+    #[cfg(feature = "browser")]
     if browser_config.enabled {
-        server = server.tool(BrowserTool::new(browser_config, screenshot_dir));
+        server = server.tool(Scanned::new(
+            BrowserTool::new(browser_config, screenshot_dir, artifacts),
+            injection.clone(),
+        ));
     }
+    #[cfg(not(feature = "browser"))]
+    let _ = (browser_config, screenshot_dir, artifacts);
 
     if let Some(key) = brave_search_key {
-        server = server.tool(WebSearchTool::new(key));
+        server = server.tool(Scanned::new(
+            WebSearchTool::new(key, fetch_limits),
+            injection.clone(),
+        ));
+    }
+
+    if github.is_enabled() {
+        server = server.tool(Scanned::new(GithubTool::new(github), injection.clone()));
+    }
+
+    if railway.is_enabled() {
+        server = server.tool(Scanned::new(RailwayTool::new(railway), injection.clone()));
+    }
+
+    let (http_request, operations) = http_request::http_api_tools(http_apis, fetch_limits);
+    if let Some(http_request) = http_request {
+        server = server.tool(Scanned::new(http_request, injection.clone()));
+    }
+    for operation in operations {
+        server = server.tool(Scanned::new(operation, injection.clone()));
+    }
+    for tool in plugin::plugin_tools(plugins) {
+        server = server.tool(Scanned::new(tool, injection.clone()));
+    }
+
+    if !sql_databases.is_empty() {
+        server = server.tool(SqlQueryTool::new(sql_databases));
+    }
+
+    if let Some(client) = WeatherClient::new(weather) {
+        server = server
+            .tool(WeatherTool::new(client.clone()))
+            .tool(GeocodeTool::new(client));
+    }
+
+    if llm_manager.ollama_base_url().is_some() {
+        server = server.tool(OllamaModelsTool::new(llm_manager));
     }
 
     server.run()
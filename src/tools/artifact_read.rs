@@ -0,0 +1,92 @@
+//! Artifact read tool: bring back code or a stack trace pasted earlier in
+//! the channel.
+
+use crate::ChannelId;
+use crate::conversation::artifacts::{CodeArtifact, CodeArtifactStore};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+#[error("Artifact read failed: {0}")]
+pub struct ArtifactReadError(String);
+
+/// Tool for reading a channel's pasted code artifacts.
+#[derive(Debug, Clone)]
+pub struct ArtifactReadTool {
+    store: CodeArtifactStore,
+    channel_id: ChannelId,
+}
+
+impl ArtifactReadTool {
+    pub fn new(store: CodeArtifactStore, channel_id: ChannelId) -> Self {
+        Self { store, channel_id }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ArtifactReadArgs {
+    /// The artifact's number, from its reference.
+    pub number: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArtifactReadOutput {
+    pub number: i64,
+    pub kind: String,
+    pub language: Option<String>,
+    pub lines: usize,
+    pub content: String,
+}
+
+impl From<CodeArtifact> for ArtifactReadOutput {
+    fn from(artifact: CodeArtifact) -> Self {
+        Self {
+            number: artifact.number,
+            kind: artifact.kind.as_str().to_string(),
+            language: artifact.language,
+            lines: artifact.lines,
+            content: artifact.content,
+        }
+    }
+}
+
+impl Tool for ArtifactReadTool {
+    const NAME: &'static str = "artifact_read";
+
+    type Error = ArtifactReadError;
+    type Args = ArtifactReadArgs;
+    type Output = ArtifactReadOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/artifact_read").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "number": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "The artifact's number, e.g. 3 for \"artifact #3\"."
+                    }
+                },
+                "required": ["number"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let artifact = self
+            .store
+            .get(&self.channel_id, args.number)
+            .await
+            .map_err(|error| ArtifactReadError(error.to_string()))?
+            .ok_or_else(|| {
+                ArtifactReadError(format!("no artifact #{} in this conversation", args.number))
+            })?;
+
+        Ok(artifact.into())
+    }
+}
@@ -5,6 +5,7 @@
 //! ref system for LLM-friendly element addressing.
 
 use crate::config::BrowserConfig;
+use crate::storage::ArtifactStore;
 
 use chromiumoxide::browser::{Browser, BrowserConfig as ChromeConfig};
 use chromiumoxide::page::ScreenshotParams;
@@ -32,6 +33,7 @@ pub struct BrowserTool {
     state: Arc<Mutex<BrowserState>>,
     config: BrowserConfig,
     screenshot_dir: PathBuf,
+    artifacts: Arc<ArtifactStore>,
 }
 
 /// Internal browser state managed across tool invocations within a single worker.
@@ -73,7 +75,11 @@ struct ElementRef {
 }
 
 impl BrowserTool {
-    pub fn new(config: BrowserConfig, screenshot_dir: PathBuf) -> Self {
+    pub fn new(
+        config: BrowserConfig,
+        screenshot_dir: PathBuf,
+        artifacts: Arc<ArtifactStore>,
+    ) -> Self {
         Self {
             state: Arc::new(Mutex::new(BrowserState {
                 browser: None,
@@ -85,6 +91,7 @@ impl BrowserTool {
             })),
             config,
             screenshot_dir,
+            artifacts,
         }
     }
 }
@@ -198,6 +205,9 @@ pub struct BrowserOutput {
     /// Path to saved screenshot file.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub screenshot_path: Option<String>,
+    /// Signed URL of the screenshot, when screenshots go to object storage.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot_url: Option<String>,
     /// JavaScript evaluation result.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub eval_result: Option<serde_json::Value>,
@@ -216,6 +226,7 @@ impl BrowserOutput {
             elements: None,
             tabs: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: None,
         }
@@ -461,6 +472,7 @@ impl BrowserTool {
             tabs: None,
             elements: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: None,
             success: true,
@@ -496,6 +508,7 @@ impl BrowserTool {
             elements: None,
             tabs: Some(tabs),
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: None,
         })
@@ -635,6 +648,7 @@ impl BrowserTool {
             elements: Some(elements),
             tabs: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: None,
         })
@@ -746,27 +760,31 @@ impl BrowserTool {
                 .map_err(|error| BrowserError::new(format!("screenshot failed: {error}")))?
         };
 
-        // Save to disk
         let filename = format!(
             "screenshot_{}.png",
             chrono::Utc::now().format("%Y%m%d_%H%M%S_%3f")
         );
         let filepath = self.screenshot_dir.join(&filename);
+        let size_kb = screenshot_data.len() / 1024;
 
-        tokio::fs::create_dir_all(&self.screenshot_dir)
-            .await
-            .map_err(|error| {
-                BrowserError::new(format!("failed to create screenshot dir: {error}"))
-            })?;
-
-        tokio::fs::write(&filepath, &screenshot_data)
+        let location = self
+            .artifacts
+            .save(
+                &filepath,
+                &format!("screenshots/{filename}"),
+                screenshot_data,
+                "image/png",
+            )
             .await
             .map_err(|error| BrowserError::new(format!("failed to save screenshot: {error}")))?;
 
-        let path_str = filepath.to_string_lossy().to_string();
-        let size_kb = screenshot_data.len() / 1024;
+        tracing::debug!(location = %location, size_kb, "screenshot saved");
 
-        tracing::debug!(path = %path_str, size_kb, "screenshot saved");
+        let (screenshot_path, screenshot_url) = if self.artifacts.is_remote() {
+            (None, Some(location))
+        } else {
+            (Some(location), None)
+        };
 
         Ok(BrowserOutput {
             success: true,
@@ -775,7 +793,8 @@ impl BrowserTool {
             url: None,
             elements: None,
             tabs: None,
-            screenshot_path: Some(path_str),
+            screenshot_path,
+            screenshot_url,
             eval_result: None,
             content: None,
         })
@@ -810,6 +829,7 @@ impl BrowserTool {
             elements: None,
             tabs: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: value,
             content: None,
         })
@@ -846,6 +866,7 @@ impl BrowserTool {
             elements: None,
             tabs: None,
             screenshot_path: None,
+            screenshot_url: None,
             eval_result: None,
             content: Some(truncated),
         })
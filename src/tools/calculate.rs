@@ -0,0 +1,89 @@
+//! Calculate tool: evaluate arithmetic, unit conversions, and date math
+//! exactly instead of leaving it to the model.
+
+use crate::calc::{self, DEFAULT_PRECISION, MAX_PRECISION};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+#[error("Calculation failed: {0}")]
+pub struct CalculateError(String);
+
+/// Tool for evaluating expressions with the deterministic calculator.
+#[derive(Debug, Clone, Default)]
+pub struct CalculateTool;
+
+impl CalculateTool {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CalculateArgs {
+    /// The expression, e.g. "1,250 * 12 * 1.08" or "5 ft to cm".
+    pub expression: String,
+    /// Significant digits for results that have to be rounded.
+    #[serde(default)]
+    pub precision: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalculateOutput {
+    pub success: bool,
+    pub expression: String,
+    pub result: String,
+    /// Whether `result` is rounded.
+    pub approximate: bool,
+    /// The exact value as a fraction, when its decimal form doesn't end.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fraction: Option<String>,
+}
+
+impl Tool for CalculateTool {
+    const NAME: &'static str = "calculate";
+
+    type Error = CalculateError;
+    type Args = CalculateArgs;
+    type Output = CalculateOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/calculate").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "expression": {
+                        "type": "string",
+                        "description": "The expression, e.g. '(1,250 * 12) + 8%', '100 km/h to mph', 'sqrt(2) * 3', or '2026-03-01 + 45 days'."
+                    },
+                    "precision": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": MAX_PRECISION,
+                        "description": "Significant digits for results that have to be rounded. Defaults to 15."
+                    }
+                },
+                "required": ["expression"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let today = chrono::Local::now().date_naive();
+        let precision = args.precision.unwrap_or(DEFAULT_PRECISION);
+        let answer = calc::evaluate(&args.expression, today, precision)
+            .map_err(|error| CalculateError(error.to_string()))?;
+
+        Ok(CalculateOutput {
+            success: true,
+            expression: args.expression,
+            result: answer.text,
+            approximate: answer.approximate,
+            fraction: answer.fraction,
+        })
+    }
+}
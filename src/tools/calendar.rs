@@ -0,0 +1,297 @@
+//! Calendar tool: list the requester's upcoming events and create new ones
+//! from natural-language times.
+
+use crate::calendar::{CalendarClient, NewEvent};
+use crate::reminders::parse::parse_when;
+use chrono::TimeZone as _;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Days listed when no range is given.
+const DEFAULT_DAYS: u32 = 7;
+
+/// The longest range `list` covers.
+const MAX_DAYS: u32 = 60;
+
+/// Event length when neither a duration nor an end is given.
+const DEFAULT_DURATION: chrono::Duration = chrono::Duration::hours(1);
+
+/// Tool for reading and adding to the requester's calendar.
+///
+/// Bound to the requester of the current turn, whose calendar binding
+/// decides which calendar is used.
+#[derive(Debug, Clone)]
+pub struct CalendarTool {
+    client: CalendarClient,
+    /// "platform:sender_id", the key of `[defaults.calendar.users]`.
+    user: String,
+}
+
+impl CalendarTool {
+    pub fn new(client: CalendarClient, user: impl Into<String>) -> Self {
+        Self {
+            client,
+            user: user.into(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Calendar operation failed: {0}")]
+pub struct CalendarToolError(String);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CalendarArgs {
+    /// The operation to perform: "list" or "create".
+    pub action: String,
+    /// For "list": how many days ahead to look (1-60, default 7).
+    #[serde(default)]
+    pub days: Option<u32>,
+    /// Required for "create": the event title.
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Required for "create": when the event starts, as the user said it (e.g. "next tuesday 3pm").
+    #[serde(default)]
+    pub start: Option<String>,
+    /// For "create": how long it lasts (e.g. "30 minutes", "2h"). Defaults to an hour.
+    #[serde(default)]
+    pub duration: Option<String>,
+    /// For "create": where it happens.
+    #[serde(default)]
+    pub location: Option<String>,
+    /// For "create": notes for the event.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarOutput {
+    pub success: bool,
+    pub message: String,
+    /// Populated on "list", earliest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<EventEntry>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventEntry {
+    pub title: String,
+    /// Local time, `YYYY-MM-DD HH:MM`, or `YYYY-MM-DD` for all-day events.
+    pub start: String,
+    pub end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+impl Tool for CalendarTool {
+    const NAME: &'static str = "calendar";
+
+    type Error = CalendarToolError;
+    type Args = CalendarArgs;
+    type Output = CalendarOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/calendar").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["list", "create"],
+                        "description": "The operation: list the user's upcoming events, or add an event to their calendar."
+                    },
+                    "days": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": MAX_DAYS,
+                        "description": "For 'list': how many days ahead to look, starting now. Defaults to 7."
+                    },
+                    "title": {
+                        "type": "string",
+                        "description": "For 'create': the event title (e.g. 'Design review')."
+                    },
+                    "start": {
+                        "type": "string",
+                        "description": "For 'create': when it starts, in plain words: 'tomorrow 2pm', 'next tuesday at 10:30am', 'march 5 at noon', or '2026-03-05 14:00'."
+                    },
+                    "duration": {
+                        "type": "string",
+                        "description": "For 'create': how long it lasts: '30 minutes', '1h30m', '2 hours'. Defaults to 1 hour."
+                    },
+                    "location": {
+                        "type": "string",
+                        "description": "For 'create': where it happens, or a meeting link."
+                    },
+                    "description": {
+                        "type": "string",
+                        "description": "For 'create': notes or agenda."
+                    }
+                },
+                "required": ["action"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let Some(calendar) = self.client.calendar_for(&self.user) else {
+            return Ok(CalendarOutput {
+                success: false,
+                message: "No calendar is set up for this user. An admin can bind one in [defaults.calendar.users].".into(),
+                events: None,
+            });
+        };
+
+        match args.action.as_str() {
+            "list" => self.list(calendar, args).await,
+            "create" => self.create(calendar, args).await,
+            other => Ok(CalendarOutput {
+                success: false,
+                message: format!("Unknown action '{other}'. Use 'list' or 'create'."),
+                events: None,
+            }),
+        }
+    }
+}
+
+impl CalendarTool {
+    async fn list(
+        &self,
+        calendar: &str,
+        args: CalendarArgs,
+    ) -> Result<CalendarOutput, CalendarToolError> {
+        let days = args.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+        let from = chrono::Utc::now();
+        let to = from + chrono::Duration::days(days.into());
+
+        let events = self
+            .client
+            .list(calendar, from, to)
+            .await
+            .map_err(|error| CalendarToolError(error.to_string()))?;
+
+        let local = |time: chrono::DateTime<chrono::Utc>, all_day: bool| {
+            let format = if all_day {
+                "%Y-%m-%d"
+            } else {
+                "%Y-%m-%d %H:%M"
+            };
+            time.with_timezone(&chrono::Local)
+                .format(format)
+                .to_string()
+        };
+        let entries: Vec<EventEntry> = events
+            .into_iter()
+            .map(|event| EventEntry {
+                title: event.title,
+                start: local(event.start, event.all_day),
+                end: event.end.map(|end| local(end, event.all_day)),
+                location: event.location,
+            })
+            .collect();
+
+        Ok(CalendarOutput {
+            success: true,
+            message: format!("{} event(s) in the next {days} day(s)", entries.len()),
+            events: Some(entries),
+        })
+    }
+
+    async fn create(
+        &self,
+        calendar: &str,
+        args: CalendarArgs,
+    ) -> Result<CalendarOutput, CalendarToolError> {
+        let title = args
+            .title
+            .filter(|title| !title.trim().is_empty())
+            .ok_or_else(|| CalendarToolError("'title' is required for create".into()))?;
+        let start_text = args
+            .start
+            .ok_or_else(|| CalendarToolError("'start' is required for create".into()))?;
+
+        let now = chrono::Local::now();
+        let Some(start) = parse_when(&start_text, now.naive_local()) else {
+            return Ok(CalendarOutput {
+                success: false,
+                message: format!(
+                    "Couldn't understand '{start_text}' as a time. Try 'tomorrow 2pm' or 'next tuesday at 10:30am'."
+                ),
+                events: None,
+            });
+        };
+        // Durations reuse the "in ..." offsets: "in 90 minutes" from the start.
+        let end = match &args.duration {
+            Some(duration) => {
+                let Some(end) = parse_when(&format!("in {duration}"), start) else {
+                    return Ok(CalendarOutput {
+                        success: false,
+                        message: format!(
+                            "Couldn't understand '{duration}' as a duration. Try '30 minutes' or '2 hours'."
+                        ),
+                        events: None,
+                    });
+                };
+                end
+            }
+            None => start + DEFAULT_DURATION,
+        };
+        let (Some(start), Some(end)) = (
+            chrono::Local.from_local_datetime(&start).earliest(),
+            chrono::Local.from_local_datetime(&end).earliest(),
+        ) else {
+            return Err(CalendarToolError(
+                "that time doesn't exist in the local time zone".into(),
+            ));
+        };
+        if start <= now {
+            return Ok(CalendarOutput {
+                success: false,
+                message: format!(
+                    "'{start_text}' is {}, which has already passed.",
+                    start.format("%Y-%m-%d %H:%M")
+                ),
+                events: None,
+            });
+        }
+
+        let event = NewEvent {
+            title,
+            start: start.with_timezone(&chrono::Utc),
+            end: end.with_timezone(&chrono::Utc),
+            location: args.location,
+            description: args.description,
+        };
+        let link = self
+            .client
+            .create(calendar, &event)
+            .await
+            .map_err(|error| CalendarToolError(error.to_string()))?;
+
+        tracing::info!(user = %self.user, start = %event.start, "calendar event created");
+
+        let end_format = if end.date_naive() == start.date_naive() {
+            "%H:%M (UTC%:z)"
+        } else {
+            "%A %Y-%m-%d %H:%M (UTC%:z)"
+        };
+        let mut message = format!(
+            "Created '{}' on {}, {} to {}.",
+            event.title,
+            start.format("%A %Y-%m-%d"),
+            start.format("%H:%M"),
+            end.format(end_format)
+        );
+        if let Some(link) = link {
+            message.push_str(&format!(" {link}"));
+        }
+        Ok(CalendarOutput {
+            success: true,
+            message,
+            events: None,
+        })
+    }
+}
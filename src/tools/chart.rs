@@ -0,0 +1,192 @@
+//! Chart tool: render a bar or line chart as a PNG. Workers save it to the
+//! workspace; in channels it's also attached to the conversation.
+
+use crate::OutboundResponse;
+use crate::chart::ChartSpec;
+use crate::config::ChartConfig;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Chart failed: {0}")]
+pub struct ChartToolError(String);
+
+/// Tool for drawing charts.
+#[derive(Debug, Clone)]
+pub struct ChartTool {
+    config: ChartConfig,
+    workspace: PathBuf,
+    /// Where to attach the chart, for channels.
+    response_tx: Option<mpsc::Sender<OutboundResponse>>,
+}
+
+impl ChartTool {
+    pub fn new(config: ChartConfig, workspace: PathBuf) -> Self {
+        Self {
+            config,
+            workspace,
+            response_tx: None,
+        }
+    }
+
+    /// Also attach each chart to the conversation.
+    pub fn with_attachments(mut self, response_tx: mpsc::Sender<OutboundResponse>) -> Self {
+        self.response_tx = Some(response_tx);
+        self
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ChartArgs {
+    #[serde(flatten)]
+    pub spec: ChartSpec,
+    /// Message sent with the chart, in channels.
+    #[serde(default)]
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChartOutput {
+    pub success: bool,
+    /// Where the PNG was saved.
+    pub path: String,
+    pub message: String,
+}
+
+impl Tool for ChartTool {
+    const NAME: &'static str = "chart";
+
+    type Error = ChartToolError;
+    type Args = ChartArgs;
+    type Output = ChartOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let mut description = crate::prompts::text::get("tools/chart").to_string();
+        if self.response_tx.is_some() {
+            description.push_str(" The chart is posted in this conversation with the caption.");
+        } else {
+            description.push_str(
+                " The chart is saved as a PNG in the workspace; include its path in your result so it can be sent.",
+            );
+        }
+
+        let mut properties = serde_json::json!({
+            "kind": {
+                "type": "string",
+                "enum": ["bar", "line"],
+                "description": "bar to compare categories, line for trends over time."
+            },
+            "title": { "type": "string" },
+            "labels": {
+                "type": "array",
+                "items": { "type": "string" },
+                "maxItems": crate::chart::MAX_LABELS,
+                "description": "Categories along the x axis, e.g. months or regions."
+            },
+            "series": {
+                "type": "array",
+                "maxItems": crate::chart::MAX_SERIES,
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "values": {
+                            "type": "array",
+                            "items": { "type": ["number", "null"] },
+                            "description": "One value per label, in the same order. Null leaves a gap."
+                        }
+                    },
+                    "required": ["name", "values"]
+                },
+                "description": "Data to plot. Use the exact numbers from a query or analysis, not estimates."
+            },
+            "y_label": {
+                "type": "string",
+                "description": "What the values measure, e.g. 'revenue (USD)'."
+            }
+        });
+        if self.response_tx.is_some() {
+            properties["caption"] = serde_json::json!({
+                "type": "string",
+                "description": "Message to post with the chart."
+            });
+        }
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description,
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": properties,
+                "required": ["kind", "labels", "series"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let spec = args.spec;
+        let config = self.config.clone();
+        let title = spec.title.clone();
+        let png = tokio::task::spawn_blocking(move || crate::chart::render(&spec, &config))
+            .await
+            .map_err(|error| ChartToolError(error.to_string()))?
+            .map_err(|error| ChartToolError(error.to_string()))?;
+
+        let slug: String = title
+            .as_deref()
+            .unwrap_or("chart")
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .take(40)
+            .collect();
+        let slug = slug.trim_matches('-');
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let filename = format!(
+            "{}-{}.png",
+            if slug.is_empty() { "chart" } else { slug },
+            &id[..8]
+        );
+        let directory = self.workspace.join("charts");
+        let path = directory.join(&filename);
+        tokio::fs::create_dir_all(&directory)
+            .await
+            .map_err(|error| {
+                ChartToolError(format!("can't create {}: {error}", directory.display()))
+            })?;
+        tokio::fs::write(&path, &png)
+            .await
+            .map_err(|error| ChartToolError(format!("can't write {}: {error}", path.display())))?;
+
+        let message = match &self.response_tx {
+            Some(response_tx) => {
+                response_tx
+                    .send(OutboundResponse::File {
+                        filename: filename.clone(),
+                        data: png,
+                        mime_type: "image/png".into(),
+                        caption: args.caption,
+                    })
+                    .await
+                    .map_err(|error| ChartToolError(format!("failed to send chart: {error}")))?;
+                "Chart posted.".to_string()
+            }
+            None => format!("Chart saved to {}.", path.display()),
+        };
+
+        Ok(ChartOutput {
+            success: true,
+            path: path.display().to_string(),
+            message,
+        })
+    }
+}
@@ -0,0 +1,312 @@
+//! CSV analysis tool: load a CSV from the workspace and run filters,
+//! computed columns, and aggregates on it, so totals and averages are
+//! computed rather than guessed.
+
+use crate::table::{Chart, Step, Table};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Largest CSV the tool loads, in bytes.
+const MAX_FILE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Rows shown when the call doesn't say.
+const DEFAULT_SHOWN_ROWS: usize = 50;
+
+/// Most rows one call may show.
+const MAX_SHOWN_ROWS: usize = 200;
+
+/// A loaded file with its path and modification time.
+type LoadedTable = (PathBuf, SystemTime, Arc<Table>);
+
+#[derive(Debug, thiserror::Error)]
+#[error("CSV analysis failed: {0}")]
+pub struct CsvAnalysisError(String);
+
+/// Tool for analyzing CSV files in the workspace.
+#[derive(Debug, Clone)]
+pub struct CsvAnalysisTool {
+    workspace: PathBuf,
+    /// The last file loaded, by path and modification time, since analyses
+    /// usually take several calls on the same file.
+    last: Arc<Mutex<Option<LoadedTable>>>,
+}
+
+impl CsvAnalysisTool {
+    pub fn new(workspace: PathBuf) -> Self {
+        Self {
+            workspace,
+            last: Arc::default(),
+        }
+    }
+
+    /// `raw` as a path inside the workspace.
+    fn resolve_path(&self, raw: &str) -> Result<PathBuf, CsvAnalysisError> {
+        let path = Path::new(raw);
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.workspace.join(path)
+        };
+        let path = path
+            .canonicalize()
+            .map_err(|error| CsvAnalysisError(format!("can't open '{raw}': {error}")))?;
+        let workspace = self
+            .workspace
+            .canonicalize()
+            .unwrap_or_else(|_| self.workspace.clone());
+        if !path.starts_with(&workspace) {
+            return Err(CsvAnalysisError(format!(
+                "'{raw}' is outside the workspace ({})",
+                self.workspace.display()
+            )));
+        }
+        Ok(path)
+    }
+
+    async fn load(&self, path: &Path) -> Result<Arc<Table>, CsvAnalysisError> {
+        let metadata = tokio::fs::metadata(path)
+            .await
+            .map_err(|error| CsvAnalysisError(error.to_string()))?;
+        if metadata.len() > MAX_FILE_BYTES {
+            return Err(CsvAnalysisError(format!(
+                "the file is {} MB; the limit is {} MB",
+                metadata.len() / (1024 * 1024),
+                MAX_FILE_BYTES / (1024 * 1024)
+            )));
+        }
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        if let Some((last_path, last_modified, table)) = &*self.last.lock().expect("poisoned")
+            && last_path == path
+            && *last_modified == modified
+        {
+            return Ok(table.clone());
+        }
+
+        let data = tokio::fs::read(path)
+            .await
+            .map_err(|error| CsvAnalysisError(error.to_string()))?;
+        let table = tokio::task::spawn_blocking(move || Table::from_csv(&data))
+            .await
+            .map_err(|error| CsvAnalysisError(error.to_string()))?
+            .map_err(|error| CsvAnalysisError(error.to_string()))?;
+        let table = Arc::new(table);
+        *self.last.lock().expect("poisoned") = Some((path.to_path_buf(), modified, table.clone()));
+        Ok(table)
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CsvAnalysisArgs {
+    /// Path to the CSV, relative to the workspace.
+    pub path: String,
+    /// Steps to run in order. Without steps, the columns are summarized.
+    #[serde(default)]
+    pub steps: Vec<Step>,
+    /// A text chart of the result.
+    #[serde(default)]
+    pub chart: Option<Chart>,
+    /// Most result rows to show.
+    #[serde(default)]
+    pub max_rows: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CsvAnalysisOutput {
+    pub success: bool,
+    pub message: String,
+    /// The result as a Markdown table.
+    pub table: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chart: Option<String>,
+}
+
+impl Tool for CsvAnalysisTool {
+    const NAME: &'static str = "csv_analysis";
+
+    type Error = CsvAnalysisError;
+    type Args = CsvAnalysisArgs;
+    type Output = CsvAnalysisOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let column = serde_json::json!({ "type": "string", "description": "Column name." });
+        let aggregate = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "function": {
+                    "type": "string",
+                    "enum": ["count", "count_distinct", "sum", "mean", "median", "min", "max"]
+                },
+                "column": { "type": "string", "description": "Column to aggregate. Omit with count to count rows." },
+                "as": { "type": "string", "description": "Result column name. Defaults to e.g. sum_revenue." }
+            },
+            "required": ["function"]
+        });
+        let step = |name: &str, properties: serde_json::Value, required: &[&str]| {
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    name: { "type": "object", "properties": properties, "required": required }
+                },
+                "required": [name],
+                "additionalProperties": false
+            })
+        };
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/csv_analysis").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path to the CSV (or TSV) file, relative to the workspace, e.g. 'uploads/sales.csv'."
+                    },
+                    "steps": {
+                        "type": "array",
+                        "description": "Steps run in order on the rows. Leave out to summarize the columns.",
+                        "items": {
+                            "oneOf": [
+                                step("filter", serde_json::json!({
+                                    "column": column,
+                                    "op": {
+                                        "type": "string",
+                                        "enum": ["eq", "ne", "gt", "ge", "lt", "le", "contains", "starts_with", "empty", "not_empty"]
+                                    },
+                                    "value": { "description": "Number or string to compare with. Not used by empty and not_empty." }
+                                }), &["column", "op"]),
+                                step("filter_in", serde_json::json!({
+                                    "column": column,
+                                    "values": { "type": "array", "description": "Keep rows whose column is one of these." }
+                                }), &["column", "values"]),
+                                step("select", serde_json::json!({
+                                    "columns": { "type": "array", "items": { "type": "string" } }
+                                }), &["columns"]),
+                                step("compute", serde_json::json!({
+                                    "name": { "type": "string", "description": "Name of the new column." },
+                                    "left": { "description": "Column name or number." },
+                                    "op": { "type": "string", "enum": ["+", "-", "*", "/"] },
+                                    "right": { "description": "Column name or number." }
+                                }), &["name", "left", "op", "right"]),
+                                step("group_by", serde_json::json!({
+                                    "columns": { "type": "array", "items": { "type": "string" }, "description": "Columns to group by. Empty aggregates the whole table." },
+                                    "aggregates": { "type": "array", "items": aggregate }
+                                }), &["aggregates"]),
+                                step("sort", serde_json::json!({
+                                    "column": column,
+                                    "descending": { "type": "boolean" }
+                                }), &["column"]),
+                                step("limit", serde_json::json!({
+                                    "rows": { "type": "integer", "minimum": 0 }
+                                }), &["rows"])
+                            ]
+                        }
+                    },
+                    "chart": {
+                        "type": "object",
+                        "description": "Draw the result as a text chart.",
+                        "properties": {
+                            "kind": { "type": "string", "enum": ["bar", "sparkline"] },
+                            "value": { "type": "string", "description": "Numeric column to plot." },
+                            "label": { "type": "string", "description": "Column naming each bar or point." }
+                        },
+                        "required": ["kind", "value"]
+                    },
+                    "max_rows": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": MAX_SHOWN_ROWS,
+                        "description": "Most result rows to show. Defaults to 50."
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let path = self.resolve_path(&args.path)?;
+        let source = self.load(&path).await?;
+        let source_rows = source.rows.len();
+
+        let summarize = args.steps.is_empty();
+        let result = if summarize {
+            source.describe()
+        } else {
+            let steps = args.steps;
+            let table = (*source).clone();
+            tokio::task::spawn_blocking(move || table.apply(&steps))
+                .await
+                .map_err(|error| CsvAnalysisError(error.to_string()))?
+                .map_err(|error| CsvAnalysisError(error.to_string()))?
+        };
+
+        let chart = args
+            .chart
+            .map(|chart| result.chart(&chart))
+            .transpose()
+            .map_err(|error| CsvAnalysisError(error.to_string()))?;
+        let max_rows = args
+            .max_rows
+            .unwrap_or(DEFAULT_SHOWN_ROWS)
+            .clamp(1, MAX_SHOWN_ROWS);
+        let (table, shown) = result.to_markdown(max_rows);
+
+        let mut message = if summarize {
+            format!("{source_rows} rows, {} columns.", source.columns.len())
+        } else {
+            format!("{} result rows from {source_rows} rows.", result.rows.len())
+        };
+        if shown < result.rows.len() {
+            message.push_str(&format!(
+                " Showing the first {shown}; add steps to narrow it down."
+            ));
+        }
+
+        Ok(CsvAnalysisOutput {
+            success: true,
+            message,
+            table,
+            chart,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn files_load_from_the_workspace_only() {
+        let workspace = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(
+            workspace.path().join("sales.csv"),
+            "region,units\nEU,3\nUS,4\n",
+        )
+        .unwrap();
+        std::fs::write(outside.path().join("secret.csv"), "a\n1\n").unwrap();
+        let tool = CsvAnalysisTool::new(workspace.path().to_path_buf());
+
+        let args: CsvAnalysisArgs = serde_json::from_value(serde_json::json!({
+            "path": "sales.csv",
+            "steps": [{ "group_by": { "aggregates": [{ "function": "sum", "column": "units" }] } }]
+        }))
+        .unwrap();
+        let output = tool.call(args).await.unwrap();
+        assert_eq!(output.table, "| sum_units |\n| --- |\n| 7 |\n");
+
+        let path = outside.path().join("secret.csv");
+        let args: CsvAnalysisArgs =
+            serde_json::from_value(serde_json::json!({ "path": path.to_str().unwrap() })).unwrap();
+        assert!(tool.call(args).await.is_err());
+        let args: CsvAnalysisArgs =
+            serde_json::from_value(serde_json::json!({ "path": "../secret.csv" })).unwrap();
+        assert!(tool.call(args).await.is_err());
+    }
+}
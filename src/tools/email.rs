@@ -0,0 +1,277 @@
+//! Email tool: send a plain-text email, optionally from a configured
+//! template, to addresses on the allowlist. Only admins get this tool.
+
+use crate::email::{EmailClient, OutgoingEmail};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Most recipients one call may address.
+const MAX_RECIPIENTS: usize = 20;
+
+/// Tool for emailing people on the allowlist.
+#[derive(Debug, Clone)]
+pub struct EmailTool {
+    client: EmailClient,
+    /// "platform:sender_id" of the admin the tool was built for, for the log.
+    sender: String,
+}
+
+impl EmailTool {
+    pub fn new(client: EmailClient, sender: impl Into<String>) -> Self {
+        Self {
+            client,
+            sender: sender.into(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Email operation failed: {0}")]
+pub struct EmailToolError(String);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EmailArgs {
+    /// Recipient addresses.
+    pub to: Vec<String>,
+    /// The subject line. Optional when the template has one.
+    #[serde(default)]
+    pub subject: Option<String>,
+    /// The message text. With a template, available to it as `body`.
+    #[serde(default)]
+    pub body: Option<String>,
+    /// Name of a configured template to render.
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Extra values for the template.
+    #[serde(default)]
+    pub variables: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailOutput {
+    pub success: bool,
+    pub message: String,
+}
+
+impl Tool for EmailTool {
+    const NAME: &'static str = "email";
+
+    type Error = EmailToolError;
+    type Args = EmailArgs;
+    type Output = EmailOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let config = self.client.config();
+        let mut template = serde_json::json!({
+            "type": "string",
+            "description": "Name of a configured template to render instead of sending 'body' as is. The template sees 'body', 'subject', 'date', and any 'variables'."
+        });
+        if !config.templates.is_empty() {
+            template["enum"] = config.templates.keys().cloned().collect();
+        }
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "{} Allowed recipients: {}.",
+                crate::prompts::text::get("tools/email"),
+                config.allowed_recipients.join(", ")
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "to": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "maxItems": MAX_RECIPIENTS,
+                        "description": "Recipient email addresses. Each must be on the allowlist."
+                    },
+                    "subject": {
+                        "type": "string",
+                        "description": "The subject line. Optional when the template provides one."
+                    },
+                    "body": {
+                        "type": "string",
+                        "description": "The message, as plain text."
+                    },
+                    "template": template,
+                    "variables": {
+                        "type": "object",
+                        "description": "Extra values the template uses, e.g. {\"period\": \"this week\"}."
+                    }
+                },
+                "required": ["to"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let mut to: Vec<String> = Vec::new();
+        for address in args.to {
+            let address = address.trim().to_string();
+            if !to.iter().any(|seen| seen.eq_ignore_ascii_case(&address)) {
+                to.push(address);
+            }
+        }
+        if to.is_empty() {
+            return Err(EmailToolError("'to' needs at least one address".into()));
+        }
+        if to.len() > MAX_RECIPIENTS {
+            return Err(EmailToolError(format!(
+                "at most {MAX_RECIPIENTS} recipients per email"
+            )));
+        }
+        let blocked: Vec<&str> = to
+            .iter()
+            .filter(|address| !self.client.config().allows(address))
+            .map(String::as_str)
+            .collect();
+        if !blocked.is_empty() {
+            return Ok(EmailOutput {
+                success: false,
+                message: format!(
+                    "Not sent: {} not on the recipient allowlist.",
+                    blocked.join(", ")
+                ),
+            });
+        }
+
+        let (subject, body) =
+            self.compose(args.subject, args.body, args.template, args.variables)?;
+        let email = OutgoingEmail { to, subject, body };
+        self.client
+            .send(&email)
+            .await
+            .map_err(|error| EmailToolError(error.to_string()))?;
+
+        tracing::info!(
+            sender = %self.sender,
+            recipients = %email.to.join(", "),
+            subject = %email.subject,
+            "email sent"
+        );
+
+        Ok(EmailOutput {
+            success: true,
+            message: format!("Sent '{}' to {}.", email.subject, email.to.join(", ")),
+        })
+    }
+}
+
+impl EmailTool {
+    /// The subject and body to send, rendering the template if one is named.
+    fn compose(
+        &self,
+        subject: Option<String>,
+        body: Option<String>,
+        template: Option<String>,
+        variables: Option<serde_json::Map<String, serde_json::Value>>,
+    ) -> Result<(String, String), EmailToolError> {
+        let subject = subject.filter(|subject| !subject.trim().is_empty());
+        let Some(name) = template else {
+            let subject = subject.ok_or_else(|| EmailToolError("'subject' is required".into()))?;
+            let body = body
+                .filter(|body| !body.trim().is_empty())
+                .ok_or_else(|| EmailToolError("'body' is required without a template".into()))?;
+            return Ok((subject, body));
+        };
+
+        let config = self.client.config();
+        let template = config.templates.get(&name).ok_or_else(|| {
+            let names: Vec<&str> = config.templates.keys().map(String::as_str).collect();
+            EmailToolError(format!(
+                "no template named '{name}'; configured templates: {}",
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            ))
+        })?;
+
+        let mut context = variables.unwrap_or_default();
+        context.insert("body".into(), body.unwrap_or_default().into());
+        context.insert(
+            "date".into(),
+            chrono::Local::now().format("%Y-%m-%d").to_string().into(),
+        );
+
+        let subject = match (subject, &template.subject) {
+            (Some(subject), _) => subject,
+            (None, Some(source)) => render(&name, source, &context)?,
+            (None, None) => {
+                return Err(EmailToolError(format!(
+                    "template '{name}' has no subject, so 'subject' is required"
+                )));
+            }
+        };
+        context.insert("subject".into(), subject.clone().into());
+        let body = render(&name, &template.body, &context)?;
+        Ok((subject, body))
+    }
+}
+
+fn render(
+    name: &str,
+    source: &str,
+    context: &serde_json::Map<String, serde_json::Value>,
+) -> Result<String, EmailToolError> {
+    minijinja::Environment::new()
+        .render_str(source, context)
+        .map(|text| text.trim().to_string())
+        .map_err(|error| EmailToolError(format!("template '{name}' failed: {error}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{EmailConfig, EmailProvider, EmailTemplate};
+
+    fn tool() -> EmailTool {
+        let config = EmailConfig {
+            provider: Some(EmailProvider::Sendgrid {
+                api_key: "key".into(),
+            }),
+            from: "bot@example.com".into(),
+            allowed_recipients: vec!["@example.com".into()],
+            templates: [(
+                "summary".to_string(),
+                EmailTemplate {
+                    subject: Some("{{ team }} summary for {{ date }}".into()),
+                    body: "Hi {{ team }},\n\n{{ body }}\n\nSubject: {{ subject }}".into(),
+                },
+            )]
+            .into(),
+        };
+        EmailTool::new(EmailClient::new(config).unwrap(), "discord:1")
+    }
+
+    #[test]
+    fn templates_render_with_body_and_variables() {
+        let variables = serde_json::json!({ "team": "Ops" });
+        let (subject, body) = tool()
+            .compose(
+                None,
+                Some("All green.".into()),
+                Some("summary".into()),
+                variables.as_object().cloned(),
+            )
+            .unwrap();
+
+        assert!(subject.starts_with("Ops summary for 20"), "{subject}");
+        assert_eq!(body, format!("Hi Ops,\n\nAll green.\n\nSubject: {subject}"));
+
+        assert!(
+            tool()
+                .compose(None, None, Some("missing".into()), None)
+                .is_err()
+        );
+        assert!(
+            tool()
+                .compose(None, Some("text".into()), None, None)
+                .is_err()
+        );
+    }
+}
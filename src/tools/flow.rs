@@ -0,0 +1,257 @@
+//! Flow tool: start a guided flow in the current channel, submit the slot
+//! values extracted from the user's replies, check progress, or cancel.
+
+use crate::flows::{Advance, FlowSet, FlowStatus, FlowStore};
+use crate::{AgentId, ChannelId};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Tool for running guided flows in this channel.
+#[derive(Debug, Clone)]
+pub struct FlowTool {
+    store: FlowStore,
+    flows: Arc<FlowSet>,
+    agent_id: AgentId,
+    channel_id: ChannelId,
+}
+
+impl FlowTool {
+    pub fn new(
+        store: FlowStore,
+        flows: Arc<FlowSet>,
+        agent_id: AgentId,
+        channel_id: ChannelId,
+    ) -> Self {
+        Self {
+            store,
+            flows,
+            agent_id,
+            channel_id,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Flow operation failed: {0}")]
+pub struct FlowError(String);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FlowArgs {
+    /// The operation to perform: "start", "submit", "status", or "cancel".
+    pub action: String,
+    /// Required for "start": the flow to run.
+    #[serde(default)]
+    pub flow: Option<String>,
+    /// Required for "submit": slot values for the current step, by slot name.
+    #[serde(default)]
+    pub slots: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FlowOutput {
+    pub success: bool,
+    pub message: String,
+    /// Every slot filled so far.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slots: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl Tool for FlowTool {
+    const NAME: &'static str = "flow";
+
+    type Error = FlowError;
+    type Args = FlowArgs;
+    type Output = FlowOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let flows: Vec<&str> = self.flows.names().collect();
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/flow").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["start", "submit", "status", "cancel"],
+                        "description": "The operation: start a flow, submit answers for the current step, show progress, or cancel the active flow."
+                    },
+                    "flow": {
+                        "type": "string",
+                        "enum": flows,
+                        "description": "For 'start': the flow to run."
+                    },
+                    "slots": {
+                        "type": "object",
+                        "description": "For 'submit': the current step's answers extracted from the user's messages, by slot name (e.g. {\"service\": \"billing-api\"})."
+                    }
+                },
+                "required": ["action"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match args.action.as_str() {
+            "start" => self.start(args).await,
+            "submit" => self.submit(args).await,
+            "status" => self.status().await,
+            "cancel" => self.cancel().await,
+            other => Ok(Self::failure(format!(
+                "Unknown action '{other}'. Use 'start', 'submit', 'status', or 'cancel'."
+            ))),
+        }
+    }
+}
+
+impl FlowTool {
+    fn failure(message: impl Into<String>) -> FlowOutput {
+        FlowOutput {
+            success: false,
+            message: message.into(),
+            slots: None,
+        }
+    }
+
+    /// What to ask next: the step's prompt and the slots it still needs.
+    fn ask(&self, flow: &str, step: &str, missing: &[String]) -> String {
+        let prompt = self
+            .flows
+            .get(flow)
+            .and_then(|definition| definition.step(step))
+            .map_or("", |step| step.prompt.as_str());
+        if missing.is_empty() {
+            format!("Step '{step}': ask the user: {prompt}")
+        } else {
+            format!(
+                "Step '{step}' still needs {}. Ask the user: {prompt}",
+                missing.join(", ")
+            )
+        }
+    }
+
+    async fn start(&self, args: FlowArgs) -> Result<FlowOutput, FlowError> {
+        let name = args
+            .flow
+            .ok_or_else(|| FlowError("'flow' is required for start".into()))?;
+        let Some(definition) = self.flows.get(&name) else {
+            let available: Vec<&str> = self.flows.names().collect();
+            return Ok(Self::failure(format!(
+                "No flow named '{name}'. Available: {}.",
+                available.join(", ")
+            )));
+        };
+
+        let run = definition.start(&self.channel_id);
+        self.store
+            .start(&run)
+            .await
+            .map_err(|error| FlowError(error.to_string()))?;
+
+        Ok(FlowOutput {
+            success: true,
+            message: format!("Started flow '{name}'. {}", self.ask(&name, &run.step, &[])),
+            slots: None,
+        })
+    }
+
+    async fn submit(&self, args: FlowArgs) -> Result<FlowOutput, FlowError> {
+        let values = args
+            .slots
+            .filter(|slots| !slots.is_empty())
+            .ok_or_else(|| FlowError("'slots' is required for submit".into()))?;
+        let Some(mut run) = self.active().await? else {
+            return Ok(Self::failure("No flow is active in this channel."));
+        };
+        let Some(definition) = self.flows.get(&run.flow) else {
+            return Ok(Self::failure(format!(
+                "Flow '{}' no longer exists. Cancel it.",
+                run.flow
+            )));
+        };
+
+        let advance = match definition.submit(&mut run, values) {
+            Ok(advance) => advance,
+            Err(message) => return Ok(Self::failure(message)),
+        };
+        self.store
+            .save(&run)
+            .await
+            .map_err(|error| FlowError(error.to_string()))?;
+
+        let message = match advance {
+            Advance::Stay { missing } => self.ask(&run.flow, &run.step, &missing),
+            Advance::Step(step) => format!("Step complete. {}", self.ask(&run.flow, &step, &[])),
+            Advance::Completed => {
+                crate::events::publish(crate::events::Event::FlowCompleted {
+                    agent_id: self.agent_id.clone(),
+                    conversation_id: self.channel_id.to_string(),
+                    flow: run.flow.clone(),
+                    slots: run.slots.clone(),
+                });
+                format!(
+                    "Flow '{}' is complete. Confirm what was collected with the user.",
+                    run.flow
+                )
+            }
+        };
+        Ok(FlowOutput {
+            success: true,
+            message,
+            slots: Some(run.slots),
+        })
+    }
+
+    async fn status(&self) -> Result<FlowOutput, FlowError> {
+        let Some(run) = self.active().await? else {
+            return Ok(Self::failure("No flow is active in this channel."));
+        };
+        let missing: Vec<String> = self
+            .flows
+            .get(&run.flow)
+            .and_then(|definition| definition.step(&run.step))
+            .map(|step| {
+                step.slots
+                    .iter()
+                    .filter(|slot| slot.required && !run.slots.contains_key(&slot.name))
+                    .map(|slot| slot.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(FlowOutput {
+            success: true,
+            message: format!(
+                "Flow '{}' is active. {}",
+                run.flow,
+                self.ask(&run.flow, &run.step, &missing)
+            ),
+            slots: Some(run.slots),
+        })
+    }
+
+    async fn cancel(&self) -> Result<FlowOutput, FlowError> {
+        let Some(mut run) = self.active().await? else {
+            return Ok(Self::failure("No flow is active in this channel."));
+        };
+        run.status = FlowStatus::Cancelled;
+        self.store
+            .save(&run)
+            .await
+            .map_err(|error| FlowError(error.to_string()))?;
+        Ok(FlowOutput {
+            success: true,
+            message: format!("Cancelled flow '{}'.", run.flow),
+            slots: Some(run.slots),
+        })
+    }
+
+    async fn active(&self) -> Result<Option<crate::flows::FlowRun>, FlowError> {
+        self.store
+            .active(&self.channel_id)
+            .await
+            .map_err(|error| FlowError(error.to_string()))
+    }
+}
@@ -0,0 +1,86 @@
+//! Geocode tool: find places by name with Open-Meteo's geocoding API.
+
+use crate::weather::{MAX_SEARCH_RESULTS, Place, WeatherClient};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Places returned when the call doesn't say.
+const DEFAULT_COUNT: u32 = 5;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Geocoding failed: {0}")]
+pub struct GeocodeError(String);
+
+/// Tool for looking up places and their coordinates.
+#[derive(Debug, Clone)]
+pub struct GeocodeTool {
+    client: WeatherClient,
+}
+
+impl GeocodeTool {
+    pub fn new(client: WeatherClient) -> Self {
+        Self { client }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GeocodeArgs {
+    /// A place name, e.g. "Springfield".
+    pub query: String,
+    /// Most places to return.
+    #[serde(default)]
+    pub count: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GeocodeOutput {
+    pub success: bool,
+    /// Matching places, most relevant first.
+    pub places: Vec<Place>,
+}
+
+impl Tool for GeocodeTool {
+    const NAME: &'static str = "geocode";
+
+    type Error = GeocodeError;
+    type Args = GeocodeArgs;
+    type Output = GeocodeOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/geocode").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "A place name: a city, town, or region. Just the name, without the country."
+                    },
+                    "count": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": MAX_SEARCH_RESULTS,
+                        "description": "Most places to return. Defaults to 5."
+                    }
+                },
+                "required": ["query"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let places = self
+            .client
+            .search(&args.query, args.count.unwrap_or(DEFAULT_COUNT))
+            .await
+            .map_err(|error| GeocodeError(error.to_string()))?;
+
+        Ok(GeocodeOutput {
+            success: true,
+            places,
+        })
+    }
+}
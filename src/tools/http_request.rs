@@ -0,0 +1,376 @@
+//! HTTP API tools: `http_request` calls any registered API by method and
+//! path, and every operation from an API's OpenAPI spec gets its own tool.
+//!
+//! Requests only go to a registered API's base URL, and its credential is
+//! added here, so the LLM never sees it.
+
+use crate::config::HttpApiConfig;
+use crate::llm::http::{BodyError, BodyLimits};
+use crate::openapi::{ApiOperation, ParameterLocation};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Longest response body returned to the LLM, in characters.
+const MAX_RESPONSE_CHARS: usize = 20_000;
+
+#[derive(Debug, thiserror::Error)]
+#[error("HTTP request failed: {0}")]
+pub struct HttpRequestError(String);
+
+#[derive(Debug, Serialize)]
+pub struct HttpRequestOutput {
+    pub success: bool,
+    /// HTTP status code, when a response came back.
+    pub status: u16,
+    pub body: String,
+    /// Whether `body` was cut to fit.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// A registered API and the shared client its tools call it with.
+#[derive(Debug)]
+struct Api {
+    name: String,
+    config: HttpApiConfig,
+    client: crate::egress::Client,
+    limits: BodyLimits,
+}
+
+impl Api {
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        query: Vec<(String, String)>,
+        headers: Vec<(String, String)>,
+        body: Option<serde_json::Value>,
+    ) -> Result<HttpRequestOutput, HttpRequestError> {
+        if self.config.read_only && !matches!(method, reqwest::Method::GET | reqwest::Method::HEAD)
+        {
+            return Err(HttpRequestError(format!(
+                "API '{}' is read-only; only GET and HEAD are allowed",
+                self.name
+            )));
+        }
+        let url = api_url(&self.config.base_url, path)?;
+
+        let mut request = self.client.request(method, url).query(&query);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        // Set last so no argument can replace the credential.
+        if let Some((header, value)) = &self.config.auth {
+            request = request.header(header, value);
+        }
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let request = request
+            .checked()
+            .map_err(|error| HttpRequestError(error.to_string()))?;
+
+        let response = crate::llm::http::send(request, self.limits)
+            .await
+            .map_err(|error| match error {
+                BodyError::Http(error) => HttpRequestError(error.without_url().to_string()),
+                error => HttpRequestError(error.to_string()),
+            })?;
+        let status = response.status();
+        let text = crate::llm::http::read_text(response, self.limits)
+            .await
+            .map_err(|error| HttpRequestError(error.to_string()))?;
+        let truncated = text.chars().count() > MAX_RESPONSE_CHARS;
+        Ok(HttpRequestOutput {
+            success: status.is_success(),
+            status: status.as_u16(),
+            body: text.chars().take(MAX_RESPONSE_CHARS).collect(),
+            truncated,
+        })
+    }
+}
+
+/// `path` joined onto `base_url`. Only relative paths under the base are
+/// accepted, so requests (and the credential) can't leave the API.
+fn api_url(base_url: &str, path: &str) -> Result<reqwest::Url, HttpRequestError> {
+    if path.contains("://")
+        || path.starts_with("//")
+        || path.split(['/', '?']).any(|segment| segment == "..")
+    {
+        return Err(HttpRequestError(format!(
+            "'{path}' must be a path relative to the API, like /users/42"
+        )));
+    }
+    let url = format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    );
+    reqwest::Url::parse(&url).map_err(|error| HttpRequestError(format!("invalid URL: {error}")))
+}
+
+/// Build every HTTP API tool for `apis`: one `http_request` tool covering
+/// all of them, plus a tool per spec operation. Bodies are capped at `limits`.
+pub fn http_api_tools(
+    apis: BTreeMap<String, HttpApiConfig>,
+    limits: BodyLimits,
+) -> (Option<HttpRequestTool>, Vec<ApiOperationTool>) {
+    if apis.is_empty() {
+        return (None, Vec::new());
+    }
+    let client = crate::egress::client_builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .expect("hardcoded reqwest client config");
+    let apis: BTreeMap<String, Arc<Api>> = apis
+        .into_iter()
+        .map(|(name, config)| {
+            let api = Api {
+                name: name.clone(),
+                config,
+                client: client.clone(),
+                limits,
+            };
+            (name, Arc::new(api))
+        })
+        .collect();
+
+    let operations = apis
+        .values()
+        .flat_map(|api| {
+            api.config
+                .operations
+                .iter()
+                .map(|operation| ApiOperationTool {
+                    api: api.clone(),
+                    operation: operation.clone(),
+                })
+        })
+        .collect();
+    (Some(HttpRequestTool { apis }), operations)
+}
+
+/// Tool for calling a registered API by method and path.
+#[derive(Debug, Clone)]
+pub struct HttpRequestTool {
+    apis: BTreeMap<String, Arc<Api>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct HttpRequestArgs {
+    /// Name of the registered API.
+    pub api: String,
+    /// HTTP method, e.g. "GET".
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// Path relative to the API's base URL, e.g. "/users/42".
+    pub path: String,
+    /// Query parameters.
+    #[serde(default)]
+    pub query: BTreeMap<String, serde_json::Value>,
+    /// JSON request body.
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+}
+
+fn default_method() -> String {
+    "GET".into()
+}
+
+impl Tool for HttpRequestTool {
+    const NAME: &'static str = "http_request";
+
+    type Error = HttpRequestError;
+    type Args = HttpRequestArgs;
+    type Output = HttpRequestOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let apis: Vec<String> = self
+            .apis
+            .values()
+            .map(|api| {
+                let mut line = format!("- {}: {}", api.name, api.config.base_url);
+                if let Some(description) = &api.config.description {
+                    line.push_str(&format!(" ({description})"));
+                }
+                if api.config.read_only {
+                    line.push_str(" [read-only]");
+                }
+                line
+            })
+            .collect();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "{}\n\nRegistered APIs:\n{}",
+                crate::prompts::text::get("tools/http_request"),
+                apis.join("\n")
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "api": {
+                        "type": "string",
+                        "enum": self.apis.keys().collect::<Vec<_>>(),
+                        "description": "The registered API to call."
+                    },
+                    "method": {
+                        "type": "string",
+                        "enum": ["GET", "HEAD", "POST", "PUT", "PATCH", "DELETE"],
+                        "description": "HTTP method. Defaults to GET."
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Path relative to the API's base URL, e.g. '/users/42'."
+                    },
+                    "query": {
+                        "type": "object",
+                        "description": "Query parameters, e.g. {\"limit\": 10}."
+                    },
+                    "body": {
+                        "description": "JSON request body, for POST, PUT, and PATCH."
+                    }
+                },
+                "required": ["api", "path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let api = self.apis.get(&args.api).ok_or_else(|| {
+            HttpRequestError(format!(
+                "no API named '{}'; registered: {}",
+                args.api,
+                self.apis.keys().cloned().collect::<Vec<_>>().join(", ")
+            ))
+        })?;
+        let method = match args.method.to_ascii_uppercase().as_str() {
+            "GET" => reqwest::Method::GET,
+            "HEAD" => reqwest::Method::HEAD,
+            "POST" => reqwest::Method::POST,
+            "PUT" => reqwest::Method::PUT,
+            "PATCH" => reqwest::Method::PATCH,
+            "DELETE" => reqwest::Method::DELETE,
+            other => {
+                return Err(HttpRequestError(format!("unsupported method '{other}'")));
+            }
+        };
+        let query = args
+            .query
+            .into_iter()
+            .map(|(name, value)| (name, query_value(&value)))
+            .collect();
+        api.send(method, &args.path, query, Vec::new(), args.body)
+            .await
+    }
+}
+
+/// Tool for one operation from an API's OpenAPI spec.
+#[derive(Debug, Clone)]
+pub struct ApiOperationTool {
+    api: Arc<Api>,
+    operation: ApiOperation,
+}
+
+impl Tool for ApiOperationTool {
+    /// Placeholder: every operation tool is named after its operation.
+    const NAME: &'static str = "api_operation";
+
+    type Error = HttpRequestError;
+    type Args = serde_json::Map<String, serde_json::Value>;
+    type Output = HttpRequestOutput;
+
+    fn name(&self) -> String {
+        self.operation.tool_name.clone()
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.operation.tool_name.clone(),
+            description: format!(
+                "{} ({} {} on the {} API)",
+                self.operation.description,
+                self.operation.method,
+                self.operation.path,
+                self.api.name
+            ),
+            parameters: self.operation.input_schema.clone(),
+        }
+    }
+
+    async fn call(&self, mut args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let mut path = self.operation.path.clone();
+        let mut query = Vec::new();
+        let mut headers = Vec::new();
+        for parameter in &self.operation.parameters {
+            let Some(value) = args.get(&parameter.name) else {
+                if parameter.location == ParameterLocation::Path {
+                    return Err(HttpRequestError(format!(
+                        "'{}' is required",
+                        parameter.name
+                    )));
+                }
+                continue;
+            };
+            let value = query_value(value);
+            match parameter.location {
+                ParameterLocation::Path => {
+                    path = path.replace(
+                        &format!("{{{}}}", parameter.name),
+                        &urlencoding::encode(&value),
+                    );
+                }
+                ParameterLocation::Query => query.push((parameter.name.clone(), value)),
+                ParameterLocation::Header => headers.push((parameter.name.clone(), value)),
+            }
+        }
+
+        self.api
+            .send(
+                self.operation.method.clone(),
+                &path,
+                query,
+                headers,
+                args.remove("body"),
+            )
+            .await
+    }
+}
+
+/// A JSON value as it goes in a query string or header: strings bare,
+/// anything else as JSON.
+fn query_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_stay_under_the_base_url() {
+        assert_eq!(
+            api_url("https://api.example.com/v1/", "/users/42")
+                .unwrap()
+                .as_str(),
+            "https://api.example.com/v1/users/42"
+        );
+        assert_eq!(
+            api_url("https://api.example.com/v1", "users?active=true")
+                .unwrap()
+                .as_str(),
+            "https://api.example.com/v1/users?active=true"
+        );
+        assert!(api_url("https://api.example.com/v1", "https://evil.example.com/").is_err());
+        assert!(api_url("https://api.example.com/v1", "//evil.example.com/").is_err());
+        assert!(api_url("https://api.example.com/v1", "/../admin").is_err());
+    }
+}
@@ -1,9 +1,9 @@
 //! Memory recall tool for branches.
 
 use crate::error::Result;
-use crate::memory::MemorySearch;
 use crate::memory::search::{SearchConfig, SearchMode, SearchSort, curate_results};
 use crate::memory::types::Memory;
+use crate::memory::{MemoryFilter, MemorySearch};
 
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
@@ -53,6 +53,9 @@ pub struct MemoryRecallArgs {
     /// Sort order for non-hybrid modes: "recent" (default), "importance", "most_accessed".
     #[serde(default)]
     pub sort_by: Option<String>,
+    /// Optional metadata filter expression, like "tag:runbook during:2024".
+    #[serde(default)]
+    pub filter: Option<String>,
 }
 
 fn default_max_results() -> usize {
@@ -176,6 +179,10 @@ impl Tool for MemoryRecallTool {
                         "enum": ["recent", "importance", "most_accessed"],
                         "default": "recent",
                         "description": "Sort order for non-hybrid modes. Default: recent."
+                    },
+                    "filter": {
+                        "type": "string",
+                        "description": "Optional metadata filter, applied in every mode: space-separated source:<prefix>, channel:<id>, tag:<tag>, after:<date>, before:<date>, during:<date>, with dates as YYYY, YYYY-MM, or YYYY-MM-DD. Example: \"tag:runbook during:2024\"."
                     }
                 }
             }),
@@ -199,6 +206,14 @@ impl Tool for MemoryRecallTool {
             .map(parse_memory_type)
             .transpose()?;
 
+        let filter = args
+            .filter
+            .as_deref()
+            .map(MemoryFilter::parse)
+            .transpose()
+            .map_err(|error| MemoryRecallError(format!("invalid filter: {error}")))?
+            .unwrap_or_default();
+
         // Validate mode-specific requirements
         if mode == SearchMode::Hybrid && args.query.as_ref().is_none_or(|q| q.is_empty()) {
             return Err(MemoryRecallError(
@@ -217,6 +232,7 @@ impl Tool for MemoryRecallTool {
             sort_by,
             max_results: args.max_results,
             max_results_per_source: args.max_results * 2,
+            filter,
             ..Default::default()
         };
 
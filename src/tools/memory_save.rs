@@ -7,18 +7,38 @@ use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// Tool for saving memories to the store.
 #[derive(Debug, Clone)]
 pub struct MemorySaveTool {
     memory_search: Arc<MemorySearch>,
+    /// Source recorded on every saved memory, overriding the model's.
+    source: Option<String>,
+    /// IDs of the memories this tool saved, for callers that track them.
+    saved: Option<Arc<Mutex<Vec<String>>>>,
 }
 
 impl MemorySaveTool {
     /// Create a new memory save tool.
     pub fn new(memory_search: Arc<MemorySearch>) -> Self {
-        Self { memory_search }
+        Self {
+            memory_search,
+            source: None,
+            saved: None,
+        }
+    }
+
+    /// Record `source` on every memory this tool saves.
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Push the ID of every memory this tool saves onto `saved`.
+    pub fn with_saved_ids(mut self, saved: Arc<Mutex<Vec<String>>>) -> Self {
+        self.saved = Some(saved);
+        self
     }
 }
 
@@ -47,6 +67,9 @@ pub struct MemorySaveArgs {
     pub source: Option<String>,
     /// Optional channel ID to associate this memory with the conversation it came from.
     pub channel_id: Option<String>,
+    /// Optional tags to label the memory with, like "runbook".
+    #[serde(default)]
+    pub tags: Vec<String>,
     /// Optional associations to create with other memories.
     #[serde(default)]
     pub associations: Vec<AssociationInput>,
@@ -128,6 +151,11 @@ impl Tool for MemorySaveTool {
                         "type": "string",
                         "description": "Optional channel ID to associate this memory with the conversation it came from"
                     },
+                    "tags": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Optional lowercase labels that searches can filter on with tag:<tag>, like the kind of document the memory came from ('runbook', 'design-doc')"
+                    },
                     "associations": {
                         "type": "array",
                         "description": "Optional associations to link this memory to other memories",
@@ -173,13 +201,13 @@ impl Tool for MemorySaveTool {
             _ => MemoryType::Fact,
         };
 
-        let mut memory = Memory::new(&args.content, memory_type);
+        let mut memory = Memory::new(&args.content, memory_type).with_tags(&args.tags);
 
         if let Some(importance) = args.importance {
             memory = memory.with_importance(importance);
         }
 
-        if let Some(source) = args.source {
+        if let Some(source) = self.source.clone().or(args.source) {
             memory = memory.with_source(source);
         }
 
@@ -219,36 +247,25 @@ impl Tool for MemorySaveTool {
             }
         }
 
-        // Generate and store embedding (async to avoid blocking the tokio runtime)
-        let embedding = self
-            .memory_search
-            .embedding_model_arc()
-            .embed_one(&args.content)
-            .await
-            .map_err(|e| MemorySaveError(format!("Failed to generate embedding: {e}")))?;
-
+        // Generate and store the embedding, and make sure the FTS index
+        // exists so full_text_search queries work.
         self.memory_search
-            .embedding_table()
-            .store(&memory.id, &args.content, &embedding)
+            .store_embedding(&memory.id, &args.content)
             .await
             .map_err(|e| MemorySaveError(format!("Failed to store embedding: {e}")))?;
 
-        // Ensure the FTS index exists so full_text_search queries work.
-        // Safe to call repeatedly — no-ops if the index already exists.
-        if let Err(error) = self
-            .memory_search
-            .embedding_table()
-            .ensure_fts_index()
-            .await
-        {
-            tracing::warn!(%error, "failed to ensure FTS index after memory save");
-        }
-
         #[cfg(feature = "metrics")]
         crate::telemetry::Metrics::global()
             .memory_writes_total
             .inc();
 
+        if let Some(saved) = &self.saved {
+            saved
+                .lock()
+                .expect("saved memory ids lock poisoned")
+                .push(memory.id.clone());
+        }
+
         Ok(MemorySaveOutput {
             memory_id: memory.id,
             success: true,
@@ -270,6 +287,7 @@ pub async fn save_fact(
         importance: None,
         source: None,
         channel_id: channel_id.map(|id| id.to_string()),
+        tags: vec![],
         associations: vec![],
     };
 
@@ -0,0 +1,187 @@
+//! Ollama model management tool for listing, pulling, and deleting local models (cortex chat only).
+
+use crate::llm::LlmManager;
+use crate::llm::ollama::{self, LocalModel, ModelLoadState};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Tool for managing the local Ollama model library.
+///
+/// Only registered on the cortex chat ToolServer, which is reachable from the
+/// admin dashboard, so regular channel users can't pull or delete models.
+#[derive(Clone)]
+pub struct OllamaModelsTool {
+    llm_manager: Arc<LlmManager>,
+}
+
+impl OllamaModelsTool {
+    pub fn new(llm_manager: Arc<LlmManager>) -> Self {
+        Self { llm_manager }
+    }
+
+    fn base_url(&self) -> String {
+        crate::config::normalize_ollama_base_url(self.llm_manager.ollama_base_url())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Ollama operation failed: {0}")]
+pub struct OllamaModelsError(String);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct OllamaModelsArgs {
+    /// The operation to perform: "list", "pull", or "delete".
+    pub action: String,
+    /// Required for "pull" and "delete": the model name (e.g. "llama3.1:8b").
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OllamaModelsOutput {
+    pub success: bool,
+    pub message: String,
+    /// Populated on "list" action.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models: Option<Vec<LocalModel>>,
+    /// Disk space used by all local models, populated on "list" action.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_size_bytes: Option<u64>,
+}
+
+impl Tool for OllamaModelsTool {
+    const NAME: &'static str = "ollama_models";
+
+    type Error = OllamaModelsError;
+    type Args = OllamaModelsArgs;
+    type Output = OllamaModelsOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/ollama_models").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["list", "pull", "delete"],
+                        "description": "The operation: list local models with disk usage, pull a model, or delete one."
+                    },
+                    "model": {
+                        "type": "string",
+                        "description": "For 'pull' and 'delete': the model name (e.g. 'llama3.1:8b', 'qwen2.5:32b')."
+                    }
+                },
+                "required": ["action"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match args.action.as_str() {
+            "list" => self.list().await,
+            "pull" => self.pull(args).await,
+            "delete" => self.delete(args).await,
+            other => Ok(OllamaModelsOutput {
+                success: false,
+                message: format!("Unknown action '{other}'. Use 'list', 'pull', or 'delete'."),
+                models: None,
+                total_size_bytes: None,
+            }),
+        }
+    }
+}
+
+impl OllamaModelsTool {
+    async fn list(&self) -> Result<OllamaModelsOutput, OllamaModelsError> {
+        let models = ollama::list_local_models(self.llm_manager.http_client(), &self.base_url())
+            .await
+            .map_err(|error| OllamaModelsError(format!("failed to list: {error}")))?;
+
+        let total_size_bytes = models.iter().map(|model| model.size_bytes).sum();
+        Ok(OllamaModelsOutput {
+            success: true,
+            message: format!(
+                "{} local model(s) using {}.",
+                models.len(),
+                ollama::format_size(total_size_bytes)
+            ),
+            models: Some(models),
+            total_size_bytes: Some(total_size_bytes),
+        })
+    }
+
+    async fn pull(&self, args: OllamaModelsArgs) -> Result<OllamaModelsOutput, OllamaModelsError> {
+        let model = args
+            .model
+            .ok_or_else(|| OllamaModelsError("'model' is required for pull".into()))?;
+        let model = model.strip_prefix("ollama/").unwrap_or(&model);
+
+        let states = self.llm_manager.ollama_states();
+        states.set(model, ModelLoadState::Pulling { percent: None });
+        let result = ollama::pull_model(
+            self.llm_manager.http_client(),
+            &self.base_url(),
+            model,
+            |percent| states.set(model, ModelLoadState::Pulling { percent }),
+        )
+        .await;
+
+        if let Err(error) = result {
+            states.set(
+                model,
+                ModelLoadState::Failed {
+                    error: error.to_string(),
+                },
+            );
+            return Err(OllamaModelsError(format!(
+                "failed to pull {model}: {error}"
+            )));
+        }
+
+        // Pulled but not loaded; the first request (or warm-up) loads it.
+        states.set(model, ModelLoadState::Ready);
+        tracing::info!(model = %model, "Ollama model pulled via tool");
+
+        Ok(OllamaModelsOutput {
+            success: true,
+            message: format!("Pulled {model}. Use it as 'ollama/{model}'."),
+            models: None,
+            total_size_bytes: None,
+        })
+    }
+
+    async fn delete(
+        &self,
+        args: OllamaModelsArgs,
+    ) -> Result<OllamaModelsOutput, OllamaModelsError> {
+        let model = args
+            .model
+            .ok_or_else(|| OllamaModelsError("'model' is required for delete".into()))?;
+        let model = model.strip_prefix("ollama/").unwrap_or(&model);
+
+        ollama::delete_model(
+            self.llm_manager.http_client(),
+            &self.base_url(),
+            model,
+            Duration::from_secs(60),
+        )
+        .await
+        .map_err(|error| OllamaModelsError(format!("failed to delete {model}: {error}")))?;
+
+        self.llm_manager.ollama_states().remove(model);
+        tracing::info!(model = %model, "Ollama model deleted via tool");
+
+        Ok(OllamaModelsOutput {
+            success: true,
+            message: format!("Deleted {model}."),
+            models: None,
+            total_size_bytes: None,
+        })
+    }
+}
@@ -0,0 +1,67 @@
+//! Plugin tools: every tool a WASM plugin declares gets its own tool, named
+//! `<plugin>_<tool>`.
+
+use crate::plugins::{Plugin, PluginHost, PluginToolSpec};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Plugin tool failed: {0}")]
+pub struct PluginToolError(String);
+
+/// One tool from a loaded plugin.
+#[derive(Clone)]
+pub struct PluginTool {
+    plugin: Arc<Plugin>,
+    spec: PluginToolSpec,
+}
+
+/// A tool for every tool the loaded plugins provide.
+pub fn plugin_tools(plugins: &PluginHost) -> Vec<PluginTool> {
+    plugins
+        .tools()
+        .map(|(plugin, spec)| PluginTool {
+            plugin: plugin.clone(),
+            spec: spec.clone(),
+        })
+        .collect()
+}
+
+impl Tool for PluginTool {
+    /// Placeholder: every plugin tool is named after its plugin and tool.
+    const NAME: &'static str = "plugin_tool";
+
+    type Error = PluginToolError;
+    type Args = serde_json::Map<String, serde_json::Value>;
+    type Output = serde_json::Value;
+
+    fn name(&self) -> String {
+        self.spec.tool_name.clone()
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.spec.tool_name.clone(),
+            description: format!(
+                "{} (from the {} plugin)",
+                self.spec.description,
+                self.plugin.name()
+            ),
+            parameters: self.spec.parameters.clone(),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let arguments = serde_json::Value::Object(args).to_string();
+        let plugin = self.plugin.clone();
+        let name = self.spec.name.clone();
+        let output = tokio::task::spawn_blocking(move || plugin.call_tool(&name, &arguments))
+            .await
+            .map_err(|error| PluginToolError(error.to_string()))?
+            .map_err(|error| PluginToolError(error.to_string()))?
+            .map_err(PluginToolError)?;
+        // Plugins return JSON, but plain text is fine too.
+        Ok(serde_json::from_str(&output).unwrap_or(serde_json::Value::String(output)))
+    }
+}
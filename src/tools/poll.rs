@@ -0,0 +1,280 @@
+//! Poll tool: post a button poll in the current channel, check on it, or
+//! close it early.
+
+use crate::ChannelId;
+use crate::OutboundResponse;
+use crate::polls::{ChannelPoll, MAX_OPTIONS, PollStore};
+use crate::reminders::parse::parse_when;
+use chrono::TimeZone as _;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+/// How long a poll stays open when no deadline is given.
+const DEFAULT_DURATION: chrono::Duration = chrono::Duration::hours(24);
+
+/// Tool for running polls in this channel.
+#[derive(Debug, Clone)]
+pub struct PollTool {
+    store: PollStore,
+    channel_id: ChannelId,
+    response_tx: mpsc::Sender<OutboundResponse>,
+    conversation_logger: crate::conversation::ConversationLogger,
+}
+
+impl PollTool {
+    pub fn new(
+        store: PollStore,
+        channel_id: ChannelId,
+        response_tx: mpsc::Sender<OutboundResponse>,
+        conversation_logger: crate::conversation::ConversationLogger,
+    ) -> Self {
+        Self {
+            store,
+            channel_id,
+            response_tx,
+            conversation_logger,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Poll operation failed: {0}")]
+pub struct PollError(String);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PollArgs {
+    /// The operation to perform: "create", "results", or "close".
+    pub action: String,
+    /// Required for "create": the question to vote on.
+    #[serde(default)]
+    pub question: Option<String>,
+    /// Required for "create": two to ten answer options.
+    #[serde(default)]
+    pub options: Option<Vec<String>>,
+    /// Optional for "create": when voting closes, in plain words (e.g. "in 2 hours", "friday 5pm"). Defaults to 24 hours.
+    #[serde(default)]
+    pub closes: Option<String>,
+    /// Required for "results" and "close": the poll ID returned by "create".
+    #[serde(default)]
+    pub poll_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollOutput {
+    pub success: bool,
+    pub message: String,
+    /// Populated on "create".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub poll_id: Option<String>,
+}
+
+impl Tool for PollTool {
+    const NAME: &'static str = "poll";
+
+    type Error = PollError;
+    type Args = PollArgs;
+    type Output = PollOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/poll").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["create", "results", "close"],
+                        "description": "The operation: post a new poll, check the votes so far, or close a poll now and post its results."
+                    },
+                    "question": {
+                        "type": "string",
+                        "description": "For 'create': the question (e.g. 'Should we deploy on Friday?')."
+                    },
+                    "options": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "For 'create': 2-10 short answer options, one button each (e.g. ['Yes', 'No'])."
+                    },
+                    "closes": {
+                        "type": "string",
+                        "description": "For 'create': when voting ends, in plain words: 'in 2 hours', 'tomorrow 9am', 'friday at 5pm'. Defaults to 24 hours from now."
+                    },
+                    "poll_id": {
+                        "type": "string",
+                        "description": "For 'results' and 'close': the ID returned by 'create'."
+                    }
+                },
+                "required": ["action"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match args.action.as_str() {
+            "create" => self.create(args).await,
+            "results" => self.results(args).await,
+            "close" => self.close(args).await,
+            other => Ok(PollOutput {
+                success: false,
+                message: format!("Unknown action '{other}'. Use 'create', 'results', or 'close'."),
+                poll_id: None,
+            }),
+        }
+    }
+}
+
+impl PollTool {
+    fn failure(message: impl Into<String>) -> PollOutput {
+        PollOutput {
+            success: false,
+            message: message.into(),
+            poll_id: None,
+        }
+    }
+
+    async fn create(&self, args: PollArgs) -> Result<PollOutput, PollError> {
+        let question = args
+            .question
+            .filter(|question| !question.trim().is_empty())
+            .ok_or_else(|| PollError("'question' is required for create".into()))?;
+        let options: Vec<String> = args
+            .options
+            .unwrap_or_default()
+            .into_iter()
+            .map(|option| option.trim().to_string())
+            .filter(|option| !option.is_empty())
+            .collect();
+        if !(2..=MAX_OPTIONS).contains(&options.len()) {
+            return Ok(Self::failure(format!(
+                "A poll needs 2 to {MAX_OPTIONS} options, got {}.",
+                options.len()
+            )));
+        }
+
+        // Votes arrive as button clicks, which only these adapters deliver.
+        let platform = self.channel_id.split(':').next().unwrap_or_default();
+        if !matches!(platform, "discord" | "slack") {
+            return Ok(Self::failure(format!(
+                "Polls need buttons, which {platform} channels don't support."
+            )));
+        }
+
+        let now = chrono::Local::now();
+        let closes_at = match &args.closes {
+            Some(closes) => {
+                let Some(closes_at) = parse_when(closes, now.naive_local())
+                    .and_then(|local| chrono::Local.from_local_datetime(&local).earliest())
+                else {
+                    return Ok(Self::failure(format!(
+                        "Couldn't understand '{closes}' as a time. Try 'in 2 hours' or 'friday at 5pm'."
+                    )));
+                };
+                if closes_at <= now {
+                    return Ok(Self::failure(format!(
+                        "'{closes}' is {}, which has already passed.",
+                        closes_at.format("%Y-%m-%d %H:%M")
+                    )));
+                }
+                closes_at
+            }
+            None => now + DEFAULT_DURATION,
+        };
+
+        let poll = ChannelPoll {
+            id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
+            channel_id: self.channel_id.to_string(),
+            question,
+            options,
+            closes_at: closes_at.with_timezone(&chrono::Utc),
+        };
+        self.store
+            .create(&poll)
+            .await
+            .map_err(|error| PollError(format!("failed to save poll: {error}")))?;
+
+        let message = poll.message();
+        if let OutboundResponse::RichMessage { text, .. } = &message {
+            self.conversation_logger
+                .log_bot_message(&self.channel_id, text);
+        }
+        self.response_tx
+            .send(message)
+            .await
+            .map_err(|error| PollError(format!("failed to post poll: {error}")))?;
+
+        tracing::info!(poll_id = %poll.id, channel_id = %self.channel_id, closes_at = %poll.closes_at, "poll created");
+
+        Ok(PollOutput {
+            success: true,
+            message: format!(
+                "Poll posted. Voting closes {}; the results will be posted here then.",
+                closes_at.format("%A %Y-%m-%d %H:%M (UTC%:z)")
+            ),
+            poll_id: Some(poll.id),
+        })
+    }
+
+    async fn results(&self, args: PollArgs) -> Result<PollOutput, PollError> {
+        let poll_id = args
+            .poll_id
+            .ok_or_else(|| PollError("'poll_id' is required".into()))?;
+        let poll = self
+            .store
+            .open_in_channel(&poll_id, &self.channel_id)
+            .await
+            .map_err(|error| PollError(format!("failed to load poll: {error}")))?;
+        let Some(poll) = poll else {
+            return Ok(Self::failure("No open poll with that ID in this channel."));
+        };
+        let votes = self
+            .store
+            .votes(&poll.id)
+            .await
+            .map_err(|error| PollError(format!("failed to load votes: {error}")))?;
+        let counts = crate::polls::tally(&poll.options, &votes);
+        let summary: Vec<String> = poll
+            .options
+            .iter()
+            .zip(&counts)
+            .map(|(option, count)| format!("{option}: {count}"))
+            .collect();
+
+        Ok(PollOutput {
+            success: true,
+            message: format!(
+                "{} vote(s) so far. {}. Closes {}.",
+                votes.len(),
+                summary.join(", "),
+                poll.closes_at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+            ),
+            poll_id: None,
+        })
+    }
+
+    async fn close(&self, args: PollArgs) -> Result<PollOutput, PollError> {
+        let poll_id = args
+            .poll_id
+            .ok_or_else(|| PollError("'poll_id' is required".into()))?;
+        let closed = self
+            .store
+            .close_early(&poll_id, &self.channel_id)
+            .await
+            .map_err(|error| PollError(format!("failed to close poll: {error}")))?;
+
+        Ok(if closed {
+            PollOutput {
+                success: true,
+                message: "Poll closed. The results will be posted shortly.".into(),
+                poll_id: None,
+            }
+        } else {
+            Self::failure("No open poll with that ID in this channel.")
+        })
+    }
+}
@@ -0,0 +1,742 @@
+//! Railway tool: deployment status, build and deploy logs, and service
+//! variables via Railway's GraphQL API (task workers and cortex chat).
+//!
+//! Read-only unless `allow_variable_writes` is set in `[defaults.railway]`.
+
+use crate::config::RailwayConfig;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Deployments listed by `status` when no limit is given.
+const DEFAULT_DEPLOYMENT_LIMIT: u32 = 5;
+
+/// Log lines returned by `logs` when no limit is given.
+const DEFAULT_LOG_LINES: u32 = 100;
+
+/// Log lines quoted in a failed deployment's summary.
+const FAILURE_EXCERPT_LINES: usize = 5;
+
+const DEPLOYMENTS_QUERY: &str = "query Deployments($input: DeploymentListInput!, $first: Int) {
+  deployments(input: $input, first: $first) {
+    edges { node { id status createdAt staticUrl service { name } } }
+  }
+}";
+
+const SERVICES_QUERY: &str = "query Services($id: String!) {
+  project(id: $id) { services { edges { node { id name } } } }
+}";
+
+const BUILD_LOGS_QUERY: &str = "query BuildLogs($deploymentId: String!, $limit: Int) {
+  buildLogs(deploymentId: $deploymentId, limit: $limit) { timestamp message severity }
+}";
+
+const DEPLOY_LOGS_QUERY: &str = "query DeployLogs($deploymentId: String!, $limit: Int) {
+  deploymentLogs(deploymentId: $deploymentId, limit: $limit) { timestamp message severity }
+}";
+
+const VARIABLES_QUERY: &str =
+    "query Variables($projectId: String!, $environmentId: String!, $serviceId: String) {
+  variables(projectId: $projectId, environmentId: $environmentId, serviceId: $serviceId)
+}";
+
+const VARIABLE_UPSERT_MUTATION: &str = "mutation VariableUpsert($input: VariableUpsertInput!) {
+  variableUpsert(input: $input)
+}";
+
+/// Tool for checking on Railway deployments.
+#[derive(Debug, Clone)]
+pub struct RailwayTool {
+    client: crate::egress::Client,
+    config: RailwayConfig,
+}
+
+impl RailwayTool {
+    pub fn new(config: RailwayConfig) -> Self {
+        let client = crate::egress::client_builder()
+            .gzip(true)
+            .build()
+            .expect("hardcoded reqwest client config");
+
+        Self { client, config }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Railway operation failed: {0}")]
+pub struct RailwayError(String);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RailwayArgs {
+    /// The operation: "status", "logs", "variables", or "set_variable".
+    pub action: String,
+    /// Service name or ID. Defaults to the configured service.
+    #[serde(default)]
+    pub service: Option<String>,
+    /// For "logs": the deployment to read. Defaults to the service's latest deployment.
+    #[serde(default)]
+    pub deployment_id: Option<String>,
+    /// For "logs": "build" or "deploy". Defaults to the step a failed deployment stopped at, otherwise "deploy".
+    #[serde(default)]
+    pub log_type: Option<String>,
+    /// For "status": deployments to list (default 5). For "logs": lines to return (default 100).
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// Required for "set_variable": the variable name.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Required for "set_variable": the new value.
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RailwayOutput {
+    pub success: bool,
+    pub message: String,
+    /// Populated on "status", newest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deployments: Option<Vec<DeploymentEntry>>,
+    /// Populated on "logs", oldest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logs: Option<String>,
+    /// Populated on "variables", sorted by name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variables: Option<Vec<VariableEntry>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeploymentEntry {
+    pub id: String,
+    pub service: Option<String>,
+    /// Railway's status, e.g. "SUCCESS", "BUILDING", "FAILED", "CRASHED".
+    pub status: String,
+    pub created_at: String,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VariableEntry {
+    pub name: String,
+    /// None when `show_variable_values` is off.
+    pub value: Option<String>,
+}
+
+impl RailwayOutput {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            success: true,
+            message: message.into(),
+            deployments: None,
+            logs: None,
+            variables: None,
+        }
+    }
+}
+
+// -- Railway API response types (private, only model what we need) --
+
+#[derive(Debug, Deserialize)]
+struct GraphqlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphqlError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphqlError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Connection<T> {
+    edges: Vec<Edge<T>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Edge<T> {
+    node: T,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeploymentsData {
+    deployments: Connection<ApiDeployment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiDeployment {
+    id: String,
+    status: String,
+    created_at: String,
+    static_url: Option<String>,
+    service: Option<ApiServiceName>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiServiceName {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServicesData {
+    project: ApiProject,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiProject {
+    services: Connection<ApiService>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiService {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildLogsData {
+    build_logs: Vec<LogLine>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeployLogsData {
+    deployment_logs: Vec<LogLine>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LogLine {
+    timestamp: String,
+    message: String,
+    severity: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VariablesData {
+    variables: std::collections::BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VariableUpsertData {
+    variable_upsert: bool,
+}
+
+impl Tool for RailwayTool {
+    const NAME: &'static str = "railway";
+
+    type Error = RailwayError;
+    type Args = RailwayArgs;
+    type Output = RailwayOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let mut actions = vec!["status", "logs", "variables"];
+        if self.config.allow_variable_writes {
+            actions.push("set_variable");
+        }
+        let service_description = match &self.config.service {
+            Some(service) => format!("Service name or ID. Defaults to '{service}'."),
+            None => {
+                "Service name or ID. Leave out for every service in the environment.".to_string()
+            }
+        };
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/railway").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": actions,
+                        "description": "The operation: recent deployments and why the latest failed, a deployment's logs, the service's variables, or (when allowed) set a variable."
+                    },
+                    "service": {
+                        "type": "string",
+                        "description": service_description
+                    },
+                    "deployment_id": {
+                        "type": "string",
+                        "description": "For 'logs': the deployment to read, from 'status'. Defaults to the latest deployment."
+                    },
+                    "log_type": {
+                        "type": "string",
+                        "enum": ["build", "deploy"],
+                        "description": "For 'logs': build output or the running service's output. Defaults to the step a failed deployment stopped at, otherwise 'deploy'."
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "maximum": 500,
+                        "description": "For 'status': how many deployments (default 5). For 'logs': how many of the latest lines (default 100)."
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "For 'set_variable': the variable name."
+                    },
+                    "value": {
+                        "type": "string",
+                        "description": "For 'set_variable': the new value. Setting it triggers a redeploy."
+                    }
+                },
+                "required": ["action"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match args.action.as_str() {
+            "status" => self.status(args).await,
+            "logs" => self.logs(args).await,
+            "variables" => self.variables(args).await,
+            "set_variable" if self.config.allow_variable_writes => self.set_variable(args).await,
+            "set_variable" => Ok(RailwayOutput {
+                success: false,
+                ..RailwayOutput::new(
+                    "Setting variables is turned off. An admin can enable it with allow_variable_writes in [defaults.railway].",
+                )
+            }),
+            other => Ok(RailwayOutput {
+                success: false,
+                ..RailwayOutput::new(format!(
+                    "Unknown action '{other}'. Use 'status', 'logs', or 'variables'."
+                ))
+            }),
+        }
+    }
+}
+
+impl RailwayTool {
+    async fn status(&self, args: RailwayArgs) -> Result<RailwayOutput, RailwayError> {
+        let limit = args.limit.unwrap_or(DEFAULT_DEPLOYMENT_LIMIT).clamp(1, 50);
+        let service_id = self.service_id(args.service.as_deref()).await?;
+        let deployments = self.deployments(service_id.as_deref(), limit).await?;
+
+        let Some(latest) = deployments.first() else {
+            return Ok(RailwayOutput {
+                deployments: Some(Vec::new()),
+                ..RailwayOutput::new("No deployments found.")
+            });
+        };
+
+        let service = latest
+            .service
+            .as_ref()
+            .map(|service| service.name.as_str())
+            .unwrap_or("the service");
+        let mut message = format!(
+            "Latest deploy of {service} ({}) is {}.",
+            latest.created_at,
+            latest.status.to_lowercase()
+        );
+        if matches!(latest.status.as_str(), "FAILED" | "CRASHED") {
+            let deploy_logs = self.deploy_logs(&latest.id, 50).await?;
+            let stage = failure_stage(&latest.status, !deploy_logs.is_empty());
+            let logs = match stage {
+                FailureStage::Build => self.build_logs(&latest.id, 200).await?,
+                _ => deploy_logs,
+            };
+            message = format!(
+                "Latest deploy of {service} ({}) {}.",
+                latest.created_at,
+                stage.describe()
+            );
+            let excerpt = failure_excerpt(&logs);
+            if !excerpt.is_empty() {
+                message.push_str(&format!("\nLast log lines:\n{excerpt}"));
+            }
+        }
+
+        let entries = deployments
+            .into_iter()
+            .map(|deployment| DeploymentEntry {
+                id: deployment.id,
+                service: deployment.service.map(|service| service.name),
+                status: deployment.status,
+                created_at: deployment.created_at,
+                url: deployment.static_url.map(|url| format!("https://{url}")),
+            })
+            .collect();
+
+        Ok(RailwayOutput {
+            deployments: Some(entries),
+            ..RailwayOutput::new(message)
+        })
+    }
+
+    async fn logs(&self, args: RailwayArgs) -> Result<RailwayOutput, RailwayError> {
+        let limit = args.limit.unwrap_or(DEFAULT_LOG_LINES).clamp(1, 500);
+
+        let (deployment_id, status) = match args.deployment_id {
+            Some(deployment_id) => (deployment_id, None),
+            None => {
+                let service_id = self.service_id(args.service.as_deref()).await?;
+                let latest = self
+                    .deployments(service_id.as_deref(), 1)
+                    .await?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| RailwayError("no deployments found".into()))?;
+                (latest.id, Some(latest.status))
+            }
+        };
+
+        let lines = match args.log_type.as_deref() {
+            Some("build") => self.build_logs(&deployment_id, limit).await?,
+            Some("deploy") => self.deploy_logs(&deployment_id, limit).await?,
+            Some(other) => {
+                return Err(RailwayError(format!(
+                    "unknown log_type '{other}', use 'build' or 'deploy'"
+                )));
+            }
+            // A deployment that never started has no deploy logs; show the
+            // build output instead.
+            None => {
+                let deploy_logs = self.deploy_logs(&deployment_id, limit).await?;
+                if deploy_logs.is_empty() && status.as_deref() != Some("SUCCESS") {
+                    self.build_logs(&deployment_id, limit).await?
+                } else {
+                    deploy_logs
+                }
+            }
+        };
+
+        let text = lines
+            .iter()
+            .map(format_log_line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(RailwayOutput {
+            logs: Some(crate::tools::truncate_output(&text, 50_000)),
+            ..RailwayOutput::new(format!(
+                "{} log line(s) from deployment {deployment_id}.",
+                lines.len()
+            ))
+        })
+    }
+
+    async fn variables(&self, args: RailwayArgs) -> Result<RailwayOutput, RailwayError> {
+        let (project_id, environment_id) = self.scope()?;
+        let service_id = self.service_id(args.service.as_deref()).await?;
+        let data: VariablesData = self
+            .query(
+                VARIABLES_QUERY,
+                serde_json::json!({
+                    "projectId": project_id,
+                    "environmentId": environment_id,
+                    "serviceId": service_id,
+                }),
+            )
+            .await?;
+
+        let show_values = self.config.show_variable_values;
+        let entries: Vec<VariableEntry> = data
+            .variables
+            .into_iter()
+            .map(|(name, value)| VariableEntry {
+                name,
+                value: show_values.then_some(value),
+            })
+            .collect();
+
+        Ok(RailwayOutput {
+            variables: Some(entries),
+            ..RailwayOutput::new(if show_values {
+                "Variables listed.".to_string()
+            } else {
+                "Variable names listed; values are hidden by configuration.".to_string()
+            })
+        })
+    }
+
+    async fn set_variable(&self, args: RailwayArgs) -> Result<RailwayOutput, RailwayError> {
+        let name = args
+            .name
+            .filter(|name| !name.trim().is_empty())
+            .ok_or_else(|| RailwayError("'name' is required for set_variable".into()))?;
+        let value = args
+            .value
+            .ok_or_else(|| RailwayError("'value' is required for set_variable".into()))?;
+        let (project_id, environment_id) = self.scope()?;
+        let Some(service_id) = self.service_id(args.service.as_deref()).await? else {
+            return Err(RailwayError(
+                "'service' is required for set_variable".into(),
+            ));
+        };
+
+        let data: VariableUpsertData = self
+            .query(
+                VARIABLE_UPSERT_MUTATION,
+                serde_json::json!({
+                    "input": {
+                        "projectId": project_id,
+                        "environmentId": environment_id,
+                        "serviceId": service_id,
+                        "name": name,
+                        "value": value,
+                    }
+                }),
+            )
+            .await?;
+        if !data.variable_upsert {
+            return Err(RailwayError(format!("Railway didn't set '{name}'")));
+        }
+
+        tracing::info!(service_id = %service_id, variable = %name, "railway variable set");
+
+        Ok(RailwayOutput::new(format!(
+            "Set '{name}'. Railway redeploys the service to apply it."
+        )))
+    }
+
+    async fn deployments(
+        &self,
+        service_id: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<ApiDeployment>, RailwayError> {
+        let (project_id, environment_id) = self.scope()?;
+        let data: DeploymentsData = self
+            .query(
+                DEPLOYMENTS_QUERY,
+                serde_json::json!({
+                    "input": {
+                        "projectId": project_id,
+                        "environmentId": environment_id,
+                        "serviceId": service_id,
+                    },
+                    "first": limit,
+                }),
+            )
+            .await?;
+        Ok(data
+            .deployments
+            .edges
+            .into_iter()
+            .map(|edge| edge.node)
+            .collect())
+    }
+
+    async fn build_logs(
+        &self,
+        deployment_id: &str,
+        limit: u32,
+    ) -> Result<Vec<LogLine>, RailwayError> {
+        let data: BuildLogsData = self
+            .query(
+                BUILD_LOGS_QUERY,
+                serde_json::json!({ "deploymentId": deployment_id, "limit": limit }),
+            )
+            .await?;
+        Ok(data.build_logs)
+    }
+
+    async fn deploy_logs(
+        &self,
+        deployment_id: &str,
+        limit: u32,
+    ) -> Result<Vec<LogLine>, RailwayError> {
+        let data: DeployLogsData = self
+            .query(
+                DEPLOY_LOGS_QUERY,
+                serde_json::json!({ "deploymentId": deployment_id, "limit": limit }),
+            )
+            .await?;
+        Ok(data.deployment_logs)
+    }
+
+    /// Resolve a service name or ID, falling back to the configured
+    /// service. None means every service in the environment.
+    async fn service_id(&self, service: Option<&str>) -> Result<Option<String>, RailwayError> {
+        let Some(service) = service.or(self.config.service.as_deref()) else {
+            return Ok(None);
+        };
+        if uuid::Uuid::parse_str(service).is_ok() {
+            return Ok(Some(service.to_string()));
+        }
+
+        let (project_id, _) = self.scope()?;
+        let data: ServicesData = self
+            .query(SERVICES_QUERY, serde_json::json!({ "id": project_id }))
+            .await?;
+        let services: Vec<ApiService> = data
+            .project
+            .services
+            .edges
+            .into_iter()
+            .map(|edge| edge.node)
+            .collect();
+        match services
+            .iter()
+            .find(|candidate| candidate.name.eq_ignore_ascii_case(service))
+        {
+            Some(found) => Ok(Some(found.id.clone())),
+            None => {
+                let names: Vec<&str> = services
+                    .iter()
+                    .map(|service| service.name.as_str())
+                    .collect();
+                Err(RailwayError(format!(
+                    "no service named '{service}'. Services: {}",
+                    names.join(", ")
+                )))
+            }
+        }
+    }
+
+    fn scope(&self) -> Result<(&str, &str), RailwayError> {
+        let project_id = self
+            .config
+            .project_id
+            .as_deref()
+            .ok_or_else(|| RailwayError("no project_id configured".into()))?;
+        let environment_id = self
+            .config
+            .environment_id
+            .as_deref()
+            .ok_or_else(|| RailwayError("no environment_id configured".into()))?;
+        Ok((project_id, environment_id))
+    }
+
+    async fn query<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: serde_json::Value,
+    ) -> Result<T, RailwayError> {
+        let mut request = self
+            .client
+            .post(&self.config.api_url)
+            .json(&serde_json::json!({ "query": query, "variables": variables }));
+        request = match (&self.config.token, &self.config.project_token) {
+            (Some(token), _) => request.bearer_auth(token),
+            (None, Some(project_token)) => request.header("Project-Access-Token", project_token),
+            (None, None) => return Err(RailwayError("no Railway token configured".into())),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|error| RailwayError(format!("request failed: {error}")))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(RailwayError(format!("HTTP {status}: {body}")));
+        }
+
+        let body: GraphqlResponse<T> = response
+            .json()
+            .await
+            .map_err(|error| RailwayError(format!("unexpected response: {error}")))?;
+        if !body.errors.is_empty() {
+            let messages: Vec<String> =
+                body.errors.into_iter().map(|error| error.message).collect();
+            return Err(RailwayError(messages.join("; ")));
+        }
+        body.data
+            .ok_or_else(|| RailwayError("response had no data".into()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FailureStage {
+    /// Never got past the build, so the service never started.
+    Build,
+    /// Built, but failed to start or pass its health check.
+    Deploy,
+    /// Started, then exited.
+    Runtime,
+}
+
+impl FailureStage {
+    fn describe(self) -> &'static str {
+        match self {
+            Self::Build => "failed at the build step",
+            Self::Deploy => "built but failed to deploy",
+            Self::Runtime => "crashed after starting",
+        }
+    }
+}
+
+/// Where a FAILED or CRASHED deployment stopped. Railway doesn't report the
+/// step, but a deployment whose container never ran has no deploy logs.
+fn failure_stage(status: &str, has_deploy_logs: bool) -> FailureStage {
+    match (status, has_deploy_logs) {
+        ("CRASHED", _) => FailureStage::Runtime,
+        (_, false) => FailureStage::Build,
+        (_, true) => FailureStage::Deploy,
+    }
+}
+
+/// The last few error lines, or the last few lines if none are marked as
+/// errors.
+fn failure_excerpt(lines: &[LogLine]) -> String {
+    let errors: Vec<&LogLine> = lines
+        .iter()
+        .filter(|line| line.severity.as_deref() == Some("error"))
+        .collect();
+    let pick: Vec<&LogLine> = if errors.is_empty() {
+        lines.iter().collect()
+    } else {
+        errors
+    };
+    pick[pick.len().saturating_sub(FAILURE_EXCERPT_LINES)..]
+        .iter()
+        .map(|line| line.message.trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_log_line(line: &LogLine) -> String {
+    match line.severity.as_deref() {
+        Some(severity) if severity != "info" => {
+            format!("{} [{severity}] {}", line.timestamp, line.message)
+        }
+        _ => format!("{} {}", line.timestamp, line.message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(message: &str, severity: &str) -> LogLine {
+        LogLine {
+            timestamp: "2026-03-05T10:00:00Z".into(),
+            message: message.into(),
+            severity: Some(severity.into()),
+        }
+    }
+
+    #[test]
+    fn test_failure_stage() {
+        assert_eq!(failure_stage("FAILED", false), FailureStage::Build);
+        assert_eq!(failure_stage("FAILED", true), FailureStage::Deploy);
+        assert_eq!(failure_stage("CRASHED", true), FailureStage::Runtime);
+    }
+
+    #[test]
+    fn test_failure_excerpt_prefers_errors() {
+        let lines = vec![
+            line("Compiling spacebot", "info"),
+            line("error[E0425]: cannot find value `x`", "error"),
+            line("Build failed", "info"),
+        ];
+        assert_eq!(
+            failure_excerpt(&lines),
+            "error[E0425]: cannot find value `x`"
+        );
+
+        let lines: Vec<LogLine> = (1..=8)
+            .map(|index| line(&format!("step {index}"), "info"))
+            .collect();
+        assert_eq!(
+            failure_excerpt(&lines),
+            "step 4\nstep 5\nstep 6\nstep 7\nstep 8"
+        );
+        assert_eq!(failure_excerpt(&[]), "");
+    }
+}
@@ -0,0 +1,243 @@
+//! Reminder tool: set, list, and cancel the requester's reminders in the
+//! current channel.
+
+use crate::ChannelId;
+use crate::reminders::parse::parse_when;
+use crate::reminders::{Reminder, ReminderStore, Requester};
+use chrono::TimeZone as _;
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Tool for setting reminders that are posted back in this channel.
+///
+/// Holds the requester of the current turn, so each user only sees and
+/// cancels their own reminders.
+#[derive(Debug, Clone)]
+pub struct RemindTool {
+    store: ReminderStore,
+    channel_id: ChannelId,
+    requester: Requester,
+}
+
+impl RemindTool {
+    pub fn new(store: ReminderStore, channel_id: ChannelId, requester: Requester) -> Self {
+        Self {
+            store,
+            channel_id,
+            requester,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Reminder operation failed: {0}")]
+pub struct RemindError(String);
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RemindArgs {
+    /// The operation to perform: "create", "list", or "cancel".
+    pub action: String,
+    /// Required for "create": when to remind, as the user said it (e.g. "in 2 hours", "next tuesday 9am").
+    #[serde(default)]
+    pub when: Option<String>,
+    /// Required for "create": what to remind the user about.
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Required for "cancel": the ID of the reminder to cancel.
+    #[serde(default)]
+    pub cancel_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemindOutput {
+    pub success: bool,
+    pub message: String,
+    /// Populated on "list" action.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reminders: Option<Vec<ReminderEntry>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReminderEntry {
+    pub id: String,
+    pub message: String,
+    /// Local time, `YYYY-MM-DD HH:MM`.
+    pub due_at: String,
+}
+
+impl Tool for RemindTool {
+    const NAME: &'static str = "remind";
+
+    type Error = RemindError;
+    type Args = RemindArgs;
+    type Output = RemindOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/remind").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["create", "list", "cancel"],
+                        "description": "The operation: set a reminder, list the user's pending reminders in this channel, or cancel one."
+                    },
+                    "when": {
+                        "type": "string",
+                        "description": "For 'create': when to remind, in plain words: 'in 2 hours', 'in 1h30m', 'tomorrow 9am', 'next tuesday at 3pm', 'march 5 at noon', or '2026-03-05 14:00'."
+                    },
+                    "message": {
+                        "type": "string",
+                        "description": "For 'create': what to remind the user about, phrased for them (e.g. 'call the dentist')."
+                    },
+                    "cancel_id": {
+                        "type": "string",
+                        "description": "For 'cancel': the ID of the reminder, from 'list'."
+                    }
+                },
+                "required": ["action"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        match args.action.as_str() {
+            "create" => self.create(args).await,
+            "list" => self.list().await,
+            "cancel" => self.cancel(args).await,
+            other => Ok(RemindOutput {
+                success: false,
+                message: format!("Unknown action '{other}'. Use 'create', 'list', or 'cancel'."),
+                reminders: None,
+            }),
+        }
+    }
+}
+
+impl RemindTool {
+    async fn create(&self, args: RemindArgs) -> Result<RemindOutput, RemindError> {
+        let when = args
+            .when
+            .ok_or_else(|| RemindError("'when' is required for create".into()))?;
+        let message = args
+            .message
+            .filter(|message| !message.trim().is_empty())
+            .ok_or_else(|| RemindError("'message' is required for create".into()))?;
+
+        // The platforms `resolve_broadcast_target` can post to.
+        let platform = self.channel_id.split(':').next().unwrap_or_default();
+        if !matches!(platform, "discord" | "slack" | "telegram") {
+            return Ok(RemindOutput {
+                success: false,
+                message: format!("Reminders can't be delivered on {platform} channels."),
+                reminders: None,
+            });
+        }
+
+        let now = chrono::Local::now();
+        let Some(due_at) = parse_when(&when, now.naive_local())
+            .and_then(|local| chrono::Local.from_local_datetime(&local).earliest())
+        else {
+            return Ok(RemindOutput {
+                success: false,
+                message: format!(
+                    "Couldn't understand '{when}' as a time. Try 'in 2 hours', 'tomorrow 9am', or 'next tuesday at 3pm'."
+                ),
+                reminders: None,
+            });
+        };
+        if due_at <= now {
+            return Ok(RemindOutput {
+                success: false,
+                message: format!(
+                    "'{when}' is {}, which has already passed.",
+                    due_at.format("%Y-%m-%d %H:%M")
+                ),
+                reminders: None,
+            });
+        }
+
+        let reminder = Reminder {
+            id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
+            channel_id: self.channel_id.to_string(),
+            sender_id: self.requester.sender_id.clone(),
+            mention: self.requester.mention.clone(),
+            message,
+            due_at: due_at.with_timezone(&chrono::Utc),
+        };
+        self.store
+            .create(&reminder)
+            .await
+            .map_err(|error| RemindError(format!("failed to save reminder: {error}")))?;
+
+        tracing::info!(
+            reminder_id = %reminder.id,
+            channel_id = %self.channel_id,
+            due_at = %reminder.due_at,
+            "reminder created"
+        );
+
+        Ok(RemindOutput {
+            success: true,
+            message: format!(
+                "Reminder '{}' set for {}.",
+                reminder.id,
+                due_at.format("%A %Y-%m-%d %H:%M (UTC%:z)")
+            ),
+            reminders: None,
+        })
+    }
+
+    async fn list(&self) -> Result<RemindOutput, RemindError> {
+        let reminders = self
+            .store
+            .pending(&self.channel_id, &self.requester.sender_id)
+            .await
+            .map_err(|error| RemindError(format!("failed to list reminders: {error}")))?;
+
+        let entries: Vec<ReminderEntry> = reminders
+            .into_iter()
+            .map(|reminder| ReminderEntry {
+                id: reminder.id,
+                message: reminder.message,
+                due_at: reminder
+                    .due_at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+            })
+            .collect();
+
+        Ok(RemindOutput {
+            success: true,
+            message: format!("{} pending reminder(s)", entries.len()),
+            reminders: Some(entries),
+        })
+    }
+
+    async fn cancel(&self, args: RemindArgs) -> Result<RemindOutput, RemindError> {
+        let id = args
+            .cancel_id
+            .ok_or_else(|| RemindError("'cancel_id' is required for cancel".into()))?;
+
+        let cancelled = self
+            .store
+            .cancel(&id, &self.requester.sender_id)
+            .await
+            .map_err(|error| RemindError(format!("failed to cancel reminder: {error}")))?;
+
+        Ok(RemindOutput {
+            success: cancelled,
+            message: if cancelled {
+                format!("Reminder '{id}' cancelled.")
+            } else {
+                format!("No pending reminder '{id}' of yours to cancel.")
+            },
+            reminders: None,
+        })
+    }
+}
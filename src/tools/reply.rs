@@ -1,8 +1,11 @@
 //! Reply tool for sending messages to users (channel only).
 
+use crate::agent::output_guard::OutputGuard;
+use crate::agent::postprocess::PostProcessConfig;
 use crate::conversation::ConversationLogger;
 
-use crate::{ChannelId, OutboundResponse};
+use crate::scripting::ScriptHooks;
+use crate::{AgentId, ChannelId, OutboundResponse};
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use regex::Regex;
@@ -45,6 +48,10 @@ pub struct ReplyTool {
     conversation_logger: ConversationLogger,
     channel_id: ChannelId,
     replied_flag: RepliedFlag,
+    scripts: Option<(AgentId, Arc<ScriptHooks>)>,
+    citations: bool,
+    post_processing: Arc<PostProcessConfig>,
+    output_guard: Option<Arc<OutputGuard>>,
 }
 
 impl ReplyTool {
@@ -62,8 +69,36 @@ impl ReplyTool {
             conversation_logger,
             channel_id,
             replied_flag,
+            scripts: None,
+            citations: false,
+            post_processing: Arc::new(PostProcessConfig::default()),
+            output_guard: None,
         }
     }
+
+    /// Run the agent's `after_llm` script hooks on every reply.
+    pub fn with_scripts(mut self, agent_id: AgentId, scripts: Arc<ScriptHooks>) -> Self {
+        self.scripts = Some((agent_id, scripts));
+        self
+    }
+
+    /// Offer the `sources` argument and list cited sources under the reply.
+    pub fn with_citations(mut self, citations: bool) -> Self {
+        self.citations = citations;
+        self
+    }
+
+    /// Run replies through this channel's post-processors before sending.
+    pub fn with_post_processing(mut self, post_processing: Arc<PostProcessConfig>) -> Self {
+        self.post_processing = post_processing;
+        self
+    }
+
+    /// Check replies for secrets and instructions last, just before sending.
+    pub fn with_output_guard(mut self, output_guard: Arc<OutputGuard>) -> Self {
+        self.output_guard = Some(output_guard);
+        self
+    }
 }
 
 /// Error type for reply tool.
@@ -93,6 +128,17 @@ pub struct ReplyArgs {
     /// Optional: a poll to attach to the message.
     #[serde(default)]
     pub poll: Option<crate::Poll>,
+    /// Optional: sources the reply draws on, cited in the content as [1], [2]
+    /// in the order listed. Only offered when citations are enabled.
+    #[serde(default)]
+    pub sources: Option<Vec<Source>>,
+}
+
+/// A web page or document a reply cites.
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct Source {
+    pub title: String,
+    pub url: String,
 }
 
 /// Output from reply tool.
@@ -182,6 +228,32 @@ async fn convert_mentions(
     result
 }
 
+/// The numbered source list appended to a reply: links on Discord and Slack,
+/// bare URLs everywhere else (Telegram, Twitch's IRC chat, webhooks).
+fn render_citations(sources: &[Source], source: &str) -> Option<String> {
+    let lines: Vec<String> = sources
+        .iter()
+        .filter(|cited| cited.url.starts_with("https://") || cited.url.starts_with("http://"))
+        .enumerate()
+        .map(|(index, cited)| {
+            let number = index + 1;
+            let url = cited.url.trim();
+            let title = match cited.title.trim() {
+                "" => url,
+                title => title,
+            };
+            match source {
+                // Angle brackets stop Discord unfurling every link into an embed.
+                "discord" => format!("[{number}] [{}](<{url}>)", title.replace(['[', ']'], "")),
+                "slack" => format!("[{number}] [{}]({url})", title.replace(['[', ']'], "")),
+                _ => format!("[{number}] {title}: {url}"),
+            }
+        })
+        .collect();
+
+    (!lines.is_empty()).then(|| format!("Sources:\n{}", lines.join("\n")))
+}
+
 fn sanitize_discord_user_id(user_id: &str) -> Option<String> {
     let trimmed = user_id.trim();
     if trimmed.len() >= 15
@@ -225,7 +297,9 @@ pub(crate) fn normalize_discord_mention_tokens(content: &str, source: &str) -> S
 
 #[cfg(test)]
 mod tests {
-    use super::{normalize_discord_mention_tokens, sanitize_discord_user_id};
+    use super::{
+        Source, normalize_discord_mention_tokens, render_citations, sanitize_discord_user_id,
+    };
 
     #[test]
     fn normalizes_broken_discord_mentions() {
@@ -256,6 +330,36 @@ mod tests {
         let parsed = sanitize_discord_user_id(">234152400653385729").expect("should parse id");
         assert_eq!(parsed, "234152400653385729");
     }
+
+    #[test]
+    fn renders_citations_per_platform() {
+        let sources = vec![
+            Source {
+                title: "Rust [Book]".into(),
+                url: "https://doc.rust-lang.org/book/".into(),
+            },
+            Source {
+                title: "notes".into(),
+                url: "file:///etc/passwd".into(),
+            },
+            Source {
+                title: "".into(),
+                url: "https://example.com".into(),
+            },
+        ];
+
+        assert_eq!(
+            render_citations(&sources, "discord").unwrap(),
+            "Sources:\n[1] [Rust Book](<https://doc.rust-lang.org/book/>)\n\
+             [2] [https://example.com](<https://example.com>)"
+        );
+        assert_eq!(
+            render_citations(&sources, "twitch").unwrap(),
+            "Sources:\n[1] Rust [Book]: https://doc.rust-lang.org/book/\n\
+             [2] https://example.com: https://example.com"
+        );
+        assert!(render_citations(&sources[1..2], "slack").is_none());
+    }
 }
 
 impl Tool for ReplyTool {
@@ -266,7 +370,7 @@ impl Tool for ReplyTool {
     type Output = ReplyOutput;
 
     async fn definition(&self, _prompt: String) -> ToolDefinition {
-        let parameters = serde_json::json!({
+        let mut parameters = serde_json::json!({
             "type": "object",
             "properties": {
                 "content": {
@@ -365,6 +469,21 @@ impl Tool for ReplyTool {
             "required": ["content"]
         });
 
+        if self.citations {
+            parameters["properties"]["sources"] = serde_json::json!({
+                "type": "array",
+                "description": "Web pages and documents this reply draws on, from worker results or recalled memories. Mark each claim with its source number in content, e.g. [1], in the order listed here. Only list sources you were given; never invent URLs.",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "title": { "type": "string" },
+                        "url": { "type": "string" }
+                    },
+                    "required": ["title", "url"]
+                }
+            });
+        }
+
         ToolDefinition {
             name: Self::NAME.to_string(),
             description: crate::prompts::text::get("tools/reply").to_string(),
@@ -383,15 +502,67 @@ impl Tool for ReplyTool {
         // Extract source from conversation_id (format: "platform:id")
         let source = self.conversation_id.split(':').next().unwrap_or("unknown");
 
+        let content = match &self.scripts {
+            Some((agent_id, scripts)) => {
+                match scripts.after_llm(agent_id, &self.conversation_id, &args.content) {
+                    Some(content) => content,
+                    None => {
+                        tracing::info!(conversation_id = %self.conversation_id, "script hook dropped reply");
+                        self.replied_flag.store(true, Ordering::Relaxed);
+                        return Ok(ReplyOutput {
+                            success: false,
+                            conversation_id: self.conversation_id.clone(),
+                            content: String::new(),
+                        });
+                    }
+                }
+            }
+            None => args.content.clone(),
+        };
+
         // Auto-convert @mentions to platform-specific syntax
         let converted_content = convert_mentions(
-            &args.content,
+            &content,
             &self.channel_id,
             &self.conversation_logger,
             source,
         )
         .await;
 
+        // Sources go after mention conversion so URLs are left untouched.
+        let converted_content = match args
+            .sources
+            .as_deref()
+            .filter(|_| self.citations)
+            .and_then(|sources| render_citations(sources, source))
+        {
+            Some(citations) => format!("{converted_content}\n\n{citations}"),
+            None => converted_content,
+        };
+
+        let converted_content = self
+            .post_processing
+            .apply(&self.conversation_id, &converted_content);
+        if converted_content.is_empty() {
+            tracing::info!(conversation_id = %self.conversation_id, "post-processing left an empty reply");
+            self.replied_flag.store(true, Ordering::Relaxed);
+            return Ok(ReplyOutput {
+                success: false,
+                conversation_id: self.conversation_id.clone(),
+                content: String::new(),
+            });
+        }
+        let Some(converted_content) = (match &self.output_guard {
+            Some(output_guard) => output_guard.check(&converted_content),
+            None => Some(converted_content),
+        }) else {
+            return Err(ReplyError(
+                "it contained a secret or part of your instructions, so it wasn't sent. \
+                 Rewrite it without them."
+                    .into(),
+            ));
+        };
+
         self.conversation_logger
             .log_bot_message(&self.channel_id, &converted_content);
 
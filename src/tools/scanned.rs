@@ -0,0 +1,54 @@
+//! Wrapper that scans a tool's output for prompt injection before the model
+//! sees it. Tools returning outside content (web search, the browser, HTTP
+//! APIs, email, GitHub, plugins) are registered through it.
+
+use crate::injection::InjectionGuard;
+
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+
+use std::sync::Arc;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScannedError<E: std::error::Error + 'static> {
+    #[error(transparent)]
+    Tool(E),
+
+    #[error("Failed to read tool output: {0}")]
+    Output(#[from] serde_json::Error),
+}
+
+/// `T`, with its output passed through an [`InjectionGuard`].
+#[derive(Clone)]
+pub struct Scanned<T> {
+    tool: T,
+    guard: Arc<InjectionGuard>,
+}
+
+impl<T> Scanned<T> {
+    pub fn new(tool: T, guard: Arc<InjectionGuard>) -> Self {
+        Self { tool, guard }
+    }
+}
+
+impl<T: Tool> Tool for Scanned<T> {
+    const NAME: &'static str = T::NAME;
+
+    type Error = ScannedError<T::Error>;
+    type Args = T::Args;
+    type Output = serde_json::Value;
+
+    fn name(&self) -> String {
+        self.tool.name()
+    }
+
+    async fn definition(&self, prompt: String) -> ToolDefinition {
+        self.tool.definition(prompt).await
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let output = self.tool.call(args).await.map_err(ScannedError::Tool)?;
+        let output = serde_json::to_value(output)?;
+        Ok(self.guard.inspect(&self.tool.name(), output))
+    }
+}
@@ -144,7 +144,7 @@ impl Tool for SendMessageTool {
 /// For Discord: adapter="discord", target=discord_channel_id (u64 as string)
 /// For Slack: adapter="slack", target=slack_channel_id (string)
 /// For Telegram: adapter="telegram", target=chat_id (parsed from channel ID)
-fn resolve_broadcast_target(
+pub(crate) fn resolve_broadcast_target(
     channel: &crate::conversation::channels::ChannelInfo,
 ) -> Option<(String, String)> {
     match channel.platform.as_str() {
@@ -75,15 +75,43 @@ impl Tool for SpawnWorkerTool {
         let rc = &self.state.deps.runtime_config;
         let browser_enabled = rc.browser_config.load().enabled;
         let web_search_enabled = rc.brave_search_key.load().is_some();
+        let github_enabled = rc.github.load().is_enabled();
+        let railway_enabled = rc.railway.load().is_enabled();
+        let http_apis_enabled = !rc.http_apis.load().is_empty();
+        let sql_enabled = !rc.sql_databases.load().is_empty();
+        let weather_enabled = rc.weather.load().enabled;
         let opencode_enabled = rc.opencode.load().enabled;
 
-        let mut tools_list = vec!["shell", "file", "exec"];
+        let mut tools_list = vec![
+            "shell",
+            "file",
+            "exec",
+            "csv_analysis",
+            "chart",
+            "calculate",
+        ];
         if browser_enabled {
             tools_list.push("browser");
         }
         if web_search_enabled {
             tools_list.push("web_search");
         }
+        if github_enabled {
+            tools_list.push("github");
+        }
+        if railway_enabled {
+            tools_list.push("railway");
+        }
+        if http_apis_enabled {
+            tools_list.push("http_request");
+        }
+        if sql_enabled {
+            tools_list.push("sql_query");
+        }
+        if weather_enabled {
+            tools_list.push("weather");
+            tools_list.push("geocode");
+        }
 
         let opencode_note = if opencode_enabled {
             " Set worker_type to \"opencode\" with a directory path for complex coding tasks — this spawns a full OpenCode coding agent with codebase exploration, context management, and its own tool suite."
@@ -0,0 +1,128 @@
+//! SQL query tool: run a read-only query on a registered database and get
+//! the rows back as a Markdown table.
+
+use crate::config::{SqlBackend, SqlDatabaseConfig};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, thiserror::Error)]
+#[error("SQL query failed: {0}")]
+pub struct SqlQueryError(String);
+
+/// Tool for querying the registered databases.
+#[derive(Debug, Clone)]
+pub struct SqlQueryTool {
+    databases: BTreeMap<String, SqlDatabaseConfig>,
+}
+
+impl SqlQueryTool {
+    pub fn new(databases: BTreeMap<String, SqlDatabaseConfig>) -> Self {
+        Self { databases }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SqlQueryArgs {
+    /// Name of the registered database.
+    pub database: String,
+    /// A single SELECT statement.
+    pub query: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SqlQueryOutput {
+    pub success: bool,
+    pub message: String,
+    /// The rows as a Markdown table.
+    pub table: String,
+}
+
+impl Tool for SqlQueryTool {
+    const NAME: &'static str = "sql_query";
+
+    type Error = SqlQueryError;
+    type Args = SqlQueryArgs;
+    type Output = SqlQueryOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let databases: Vec<String> = self
+            .databases
+            .iter()
+            .map(|(name, database)| {
+                let dialect = match database.backend {
+                    SqlBackend::Postgres => "Postgres",
+                    SqlBackend::Mysql => "MySQL",
+                    SqlBackend::Sqlite => "SQLite",
+                };
+                let mut line = format!("- {name} ({dialect}, up to {} rows)", database.max_rows);
+                if let Some(description) = &database.description {
+                    line.push_str(&format!(": {description}"));
+                }
+                line
+            })
+            .collect();
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: format!(
+                "{}\n\nRegistered databases:\n{}",
+                crate::prompts::text::get("tools/sql_query"),
+                databases.join("\n")
+            ),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "database": {
+                        "type": "string",
+                        "enum": self.databases.keys().collect::<Vec<_>>(),
+                        "description": "The registered database to query."
+                    },
+                    "query": {
+                        "type": "string",
+                        "description": "A single read-only SELECT statement in the database's dialect."
+                    }
+                },
+                "required": ["database", "query"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let database = self.databases.get(&args.database).ok_or_else(|| {
+            SqlQueryError(format!(
+                "no database named '{}'; registered: {}",
+                args.database,
+                self.databases
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?;
+
+        let result = crate::sql_query::run(database, &args.query)
+            .await
+            .map_err(|error| SqlQueryError(error.to_string()))?;
+        let (table, shown) = result.to_markdown();
+
+        let message = if shown < result.rows.len() {
+            format!(
+                "Showing {shown} of {} rows; the rest didn't fit. Select fewer columns or aggregate.",
+                result.rows.len()
+            )
+        } else if result.truncated {
+            format!("Showing the first {shown} rows; there are more. Add a LIMIT or aggregate.")
+        } else {
+            format!("{shown} rows.")
+        };
+
+        Ok(SqlQueryOutput {
+            success: true,
+            message,
+            table,
+        })
+    }
+}
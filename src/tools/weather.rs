@@ -0,0 +1,185 @@
+//! Weather tool: current conditions and a daily forecast from Open-Meteo,
+//! for a named place, coordinates, or the asker's saved default location.
+
+use crate::weather::{Forecast, LocationStore, MAX_FORECAST_DAYS, Place, WeatherClient};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Days forecast when the call doesn't say.
+const DEFAULT_DAYS: u32 = 3;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Weather failed: {0}")]
+pub struct WeatherToolError(String);
+
+/// Tool for looking up the weather.
+#[derive(Debug, Clone)]
+pub struct WeatherTool {
+    client: WeatherClient,
+    /// Where the asker's default location is kept, and their sender id, in
+    /// channels.
+    user: Option<(LocationStore, String)>,
+}
+
+impl WeatherTool {
+    pub fn new(client: WeatherClient) -> Self {
+        Self { client, user: None }
+    }
+
+    /// Use and remember `sender_id`'s default location.
+    pub fn with_user(mut self, locations: LocationStore, sender_id: impl Into<String>) -> Self {
+        self.user = Some((locations, sender_id.into()));
+        self
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WeatherArgs {
+    /// A place name ("Lyon", "Springfield, Illinois") or "latitude,longitude".
+    /// Leave out to use the asker's saved location.
+    #[serde(default)]
+    pub location: Option<String>,
+    /// Days of forecast, starting today.
+    #[serde(default)]
+    pub days: Option<u32>,
+    /// Save `location` as the asker's default.
+    #[serde(default)]
+    pub remember: bool,
+    /// Forget the asker's saved location instead of looking up the weather.
+    #[serde(default)]
+    pub forget: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WeatherOutput {
+    pub success: bool,
+    /// Conditions now and per day, as plain text.
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub place: Option<Place>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<Forecast>,
+    /// Whether the location was saved as the asker's default.
+    pub remembered: bool,
+}
+
+impl Tool for WeatherTool {
+    const NAME: &'static str = "weather";
+
+    type Error = WeatherToolError;
+    type Args = WeatherArgs;
+    type Output = WeatherOutput;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        let mut properties = serde_json::json!({
+            "location": {
+                "type": "string",
+                "description": "A place name, optionally with region or country ('Lyon', 'Springfield, Illinois'), or 'latitude,longitude'."
+            },
+            "days": {
+                "type": "integer",
+                "minimum": 1,
+                "maximum": MAX_FORECAST_DAYS,
+                "description": "Days of forecast, starting today. Defaults to 3."
+            }
+        });
+        if self.user.is_some() {
+            properties["location"]["description"] = serde_json::json!(
+                "A place name, optionally with region or country ('Lyon', 'Springfield, Illinois'), or 'latitude,longitude'. Leave out to use the asker's saved location."
+            );
+            properties["remember"] = serde_json::json!({
+                "type": "boolean",
+                "description": "Save this location as the asker's default, when they say where they live or ask you to remember it."
+            });
+            properties["forget"] = serde_json::json!({
+                "type": "boolean",
+                "description": "Forget the asker's saved location instead of looking up the weather."
+            });
+        }
+
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: crate::prompts::text::get("tools/weather").to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": properties,
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        if args.forget {
+            let (locations, sender_id) = self.user.as_ref().ok_or_else(|| {
+                WeatherToolError("there's nobody to forget a location for".into())
+            })?;
+            let deleted = locations
+                .delete(sender_id)
+                .await
+                .map_err(|error| WeatherToolError(error.to_string()))?;
+            let summary = if deleted > 0 {
+                "Forgot the saved location."
+            } else {
+                "There was no saved location to forget."
+            };
+            return Ok(WeatherOutput {
+                success: true,
+                summary: summary.into(),
+                place: None,
+                forecast: None,
+                remembered: false,
+            });
+        }
+
+        let place = match args.location.as_deref().map(str::trim) {
+            Some(location) if !location.is_empty() => self
+                .client
+                .resolve(location)
+                .await
+                .map_err(|error| WeatherToolError(error.to_string()))?,
+            _ => {
+                let Some((locations, sender_id)) = &self.user else {
+                    return Err(WeatherToolError("give a location".into()));
+                };
+                locations
+                    .get(sender_id)
+                    .await
+                    .map_err(|error| WeatherToolError(error.to_string()))?
+                    .map(|saved| saved.place)
+                    .ok_or_else(|| {
+                        WeatherToolError(
+                            "no location given and the asker hasn't saved one; ask where they are"
+                                .into(),
+                        )
+                    })?
+            }
+        };
+
+        let remembered = match (&self.user, args.remember && args.location.is_some()) {
+            (Some((locations, sender_id)), true) => {
+                locations
+                    .set(sender_id, &place)
+                    .await
+                    .map_err(|error| WeatherToolError(error.to_string()))?;
+                true
+            }
+            _ => false,
+        };
+
+        let days = args.days.unwrap_or(DEFAULT_DAYS);
+        let forecast = self
+            .client
+            .forecast(&place, days)
+            .await
+            .map_err(|error| WeatherToolError(error.to_string()))?;
+
+        Ok(WeatherOutput {
+            success: true,
+            summary: forecast.summary(&place.label()),
+            place: Some(place),
+            forecast: Some(forecast),
+            remembered,
+        })
+    }
+}
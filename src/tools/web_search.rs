@@ -1,5 +1,7 @@
 //! Web search tool using the Brave Search API (task workers only).
 
+use crate::llm::http::BodyLimits;
+
 use rig::completion::ToolDefinition;
 use rig::tool::Tool;
 use schemars::JsonSchema;
@@ -10,13 +12,14 @@ const BRAVE_WEB_SEARCH_URL: &str = "https://api.search.brave.com/res/v1/web/sear
 /// Tool for searching the web via Brave Search.
 #[derive(Debug, Clone)]
 pub struct WebSearchTool {
-    client: reqwest::Client,
+    client: crate::egress::Client,
     api_key: String,
+    limits: BodyLimits,
 }
 
 impl WebSearchTool {
-    pub fn new(api_key: impl Into<String>) -> Self {
-        let client = reqwest::Client::builder()
+    pub fn new(api_key: impl Into<String>, limits: BodyLimits) -> Self {
+        let client = crate::egress::client_builder()
             .gzip(true)
             .build()
             .expect("hardcoded reqwest client config");
@@ -24,6 +27,7 @@ impl WebSearchTool {
         Self {
             client,
             api_key: api_key.into(),
+            limits,
         }
     }
 }
@@ -176,8 +180,10 @@ impl Tool for WebSearchTool {
             request = request.query(&[("freshness", freshness)]);
         }
 
-        let response = request
-            .send()
+        let request = request
+            .checked()
+            .map_err(|error| WebSearchError::RequestFailed(error.to_string()))?;
+        let response = crate::llm::http::send(request, self.limits)
             .await
             .map_err(|error| WebSearchError::RequestFailed(error.to_string()))?;
 
@@ -186,8 +192,7 @@ impl Tool for WebSearchTool {
             return Err(WebSearchError::RateLimited);
         }
         if !status.is_success() {
-            let body = response
-                .text()
+            let body = crate::llm::http::read_text(response, self.limits)
                 .await
                 .unwrap_or_else(|_| "failed to read response body".into());
             return Err(WebSearchError::RequestFailed(format!(
@@ -195,9 +200,10 @@ impl Tool for WebSearchTool {
             )));
         }
 
-        let api_response: BraveApiResponse = response
-            .json()
+        let body = crate::llm::http::read_body(response, self.limits)
             .await
+            .map_err(|error| WebSearchError::RequestFailed(error.to_string()))?;
+        let api_response: BraveApiResponse = serde_json::from_slice(&body)
             .map_err(|error| WebSearchError::InvalidResponse(error.to_string()))?;
 
         let results: Vec<SearchResult> = api_response
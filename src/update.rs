@@ -1,12 +1,16 @@
-//! Update checking and Docker self-update.
+//! Build info, update checking, and Docker self-update.
 //!
-//! Checks GitHub releases for new versions and optionally performs
-//! in-place container updates when the Docker socket is available.
+//! Checks a release feed (GitHub releases by default) for new versions,
+//! optionally tells operators when one comes out, and performs in-place
+//! container updates when the Docker socket is available. The commit and
+//! build time embedded by `build.rs` are served by `!version` and
+//! `/healthz`.
 
 use arc_swap::ArcSwap;
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, OnceLock};
 use std::time::Duration;
 
 /// GitHub repository for release checks.
@@ -15,6 +19,117 @@ const GITHUB_REPO: &str = "spacedriveapp/spacebot";
 /// Current binary version from Cargo.toml.
 pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Commit the binary was built from, shortened to 12 characters, or
+/// "unknown".
+pub const GIT_HASH: &str = env!("SPACEBOT_GIT_HASH");
+
+/// When the binary was built, in Unix seconds.
+const BUILD_TIMESTAMP: &str = env!("SPACEBOT_BUILD_TIMESTAMP");
+
+static CONFIG: LazyLock<ArcSwap<UpdatesConfig>> =
+    LazyLock::new(|| ArcSwap::from_pointee(UpdatesConfig::default()));
+
+/// The status the API serves, for `!version`.
+static STATUS: OnceLock<SharedUpdateStatus> = OnceLock::new();
+
+static ALERTS: LazyLock<broadcast::Sender<UpdateAlert>> = LazyLock::new(|| broadcast::channel(4).0);
+
+/// Update check settings, from `[updates]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdatesConfig {
+    /// Check the feed every hour.
+    pub check: bool,
+    /// URL answering like GitHub's "latest release" API. `None` is
+    /// Spacebot's GitHub releases.
+    pub feed_url: Option<String>,
+    /// "adapter:target" to tell when a newer version comes out.
+    pub alert_target: Option<String>,
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self {
+            check: true,
+            feed_url: None,
+            alert_target: None,
+        }
+    }
+}
+
+/// Replace the settings the update checker follows.
+pub fn configure(config: UpdatesConfig) {
+    CONFIG.store(Arc::new(config));
+}
+
+/// A newer version, for the `alert_target`.
+#[derive(Debug, Clone)]
+pub struct UpdateAlert {
+    pub version: String,
+    pub release_url: String,
+    pub alert_target: String,
+}
+
+impl std::fmt::Display for UpdateAlert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Spacebot {} is available (running {CURRENT_VERSION}): {}",
+            self.version, self.release_url
+        )
+    }
+}
+
+/// Newer versions found from now on, once each.
+pub fn subscribe() -> broadcast::Receiver<UpdateAlert> {
+    ALERTS.subscribe()
+}
+
+/// What this binary is.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    pub build_time: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: CURRENT_VERSION,
+        git_hash: GIT_HASH,
+        build_time: BUILD_TIMESTAMP
+            .parse()
+            .ok()
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0)),
+    }
+}
+
+/// The `!version` reply. `update` adds what the last check found.
+pub fn version_text(build: &BuildInfo, update: Option<&UpdateStatus>) -> String {
+    let mut text = format!("Spacebot {} (commit {}", build.version, build.git_hash);
+    if let Some(build_time) = build.build_time {
+        text.push_str(&format!(
+            ", built {}",
+            build_time.format("%Y-%m-%d %H:%M UTC")
+        ));
+    }
+    text.push(')');
+    if let Some(update) = update
+        && update.update_available
+        && let Some(latest) = &update.latest_version
+    {
+        text.push_str(&format!("\nVersion {latest} is available"));
+        if let Some(url) = &update.release_url {
+            text.push_str(&format!(": {url}"));
+        }
+    }
+    text
+}
+
+/// What the last update check found, once the API state is built.
+pub fn latest_status() -> Option<Arc<UpdateStatus>> {
+    STATUS.get().map(|status| status.load_full())
+}
+
 /// Default check interval (1 hour).
 const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
 
@@ -77,7 +192,9 @@ pub fn new_shared_status() -> SharedUpdateStatus {
     let mut status = UpdateStatus::default();
     // Probe Docker socket availability on init
     status.can_apply = status.deployment == Deployment::Docker && docker_socket_available();
-    Arc::new(ArcSwap::from_pointee(status))
+    let shared = Arc::new(ArcSwap::from_pointee(status));
+    let _ = STATUS.set(shared.clone());
+    shared
 }
 
 /// Minimal GitHub release response.
@@ -88,9 +205,11 @@ struct GitHubRelease {
     body: Option<String>,
 }
 
-/// Check GitHub for the latest release and compare with current version.
+/// Check the release feed for the latest release and compare with the
+/// current version.
 pub async fn check_for_update(status: &SharedUpdateStatus) {
-    let result = fetch_latest_release().await;
+    let config = CONFIG.load_full();
+    let result = fetch_latest_release(config.feed_url.as_deref()).await;
 
     let current = status.load();
     let mut next = UpdateStatus {
@@ -111,7 +230,7 @@ pub async fn check_for_update(status: &SharedUpdateStatus) {
 
             next.latest_version = Some(tag.to_string());
             next.update_available = is_newer;
-            next.release_url = Some(release.html_url);
+            next.release_url = Some(release.html_url.clone());
             next.release_notes = release.body;
 
             if is_newer {
@@ -120,6 +239,17 @@ pub async fn check_for_update(status: &SharedUpdateStatus) {
                     latest = tag,
                     "new version available"
                 );
+                let already_known =
+                    current.update_available && current.latest_version.as_deref() == Some(tag);
+                if let Some(alert_target) = &config.alert_target
+                    && !already_known
+                {
+                    let _ = ALERTS.send(UpdateAlert {
+                        version: tag.to_string(),
+                        release_url: release.html_url.clone(),
+                        alert_target: alert_target.clone(),
+                    });
+                }
             }
         }
         Err(error) => {
@@ -136,21 +266,25 @@ pub fn spawn_update_checker(status: SharedUpdateStatus) {
     tokio::spawn(async move {
         // Initial check after a short delay to not block startup
         tokio::time::sleep(Duration::from_secs(10)).await;
-        check_for_update(&status).await;
-
         loop {
+            // `[updates] check = false` pauses checks until it's turned back on.
+            if CONFIG.load().check {
+                check_for_update(&status).await;
+            }
             tokio::time::sleep(CHECK_INTERVAL).await;
-            check_for_update(&status).await;
         }
     });
 }
 
-/// Fetch the latest release from GitHub.
-async fn fetch_latest_release() -> anyhow::Result<GitHubRelease> {
-    let url = format!(
-        "https://api.github.com/repos/{}/releases/latest",
-        GITHUB_REPO
-    );
+/// Fetch the latest release from the feed, or from GitHub.
+async fn fetch_latest_release(feed_url: Option<&str>) -> anyhow::Result<GitHubRelease> {
+    let url = match feed_url {
+        Some(feed_url) => feed_url.to_string(),
+        None => format!(
+            "https://api.github.com/repos/{}/releases/latest",
+            GITHUB_REPO
+        ),
+    };
 
     let client = reqwest::Client::builder()
         .user_agent(format!("spacebot/{}", CURRENT_VERSION))
@@ -160,7 +294,7 @@ async fn fetch_latest_release() -> anyhow::Result<GitHubRelease> {
     let response = client.get(&url).send().await?;
 
     if !response.status().is_success() {
-        anyhow::bail!("GitHub API returned {}", response.status());
+        anyhow::bail!("release feed returned {}", response.status());
     }
 
     Ok(response.json().await?)
@@ -437,6 +571,35 @@ mod tests {
         assert!(!is_newer_version("0.0.9", "0.1.0"));
     }
 
+    #[test]
+    fn test_version_text() {
+        let build = BuildInfo {
+            version: "0.3.0",
+            git_hash: "0123456789ab",
+            build_time: chrono::DateTime::from_timestamp(1_760_000_000, 0),
+        };
+        assert_eq!(
+            version_text(&build, None),
+            "Spacebot 0.3.0 (commit 0123456789ab, built 2025-10-09 08:53 UTC)"
+        );
+
+        let update = UpdateStatus {
+            latest_version: Some("0.4.0".into()),
+            update_available: true,
+            release_url: Some("https://example.com/v0.4.0".into()),
+            ..Default::default()
+        };
+        let unknown = BuildInfo {
+            build_time: None,
+            ..build
+        };
+        assert_eq!(
+            version_text(&unknown, Some(&update)),
+            "Spacebot 0.3.0 (commit 0123456789ab)\n\
+             Version 0.4.0 is available: https://example.com/v0.4.0"
+        );
+    }
+
     #[test]
     fn test_resolve_target_image() {
         assert_eq!(
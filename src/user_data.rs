@@ -0,0 +1,362 @@
+//! Per-user data export and purge, for data-subject (GDPR-style) requests.
+//!
+//! A user is identified by their platform sender id, the `sender_id` stored
+//! on their messages (a Discord user id, a Slack member id, ...). What's
+//! stored about them in an agent:
+//!
+//! - every message they sent, in any channel;
+//! - the reminders they set, the poll votes they cast, and the default
+//!   location they asked the weather tool to remember;
+//! - channels only they talk in (DMs, in practice): the whole timeline,
+//!   including the agent's replies and the branch and worker runs it started,
+//!   the turn records (model and tool calls), the code artifacts pasted there,
+//!   and the memories saved from those channels.
+//!
+//! Memories saved from shared channels aren't attributed to one speaker, so
+//! they're neither exported nor purged. A purge is written to the agent's
+//! cortex event log as a `user_data_purged` event, without the deleted
+//! content.
+
+use crate::conversation::history::ChannelTurn;
+use crate::conversation::{ConversationLogger, ProcessRunLogger, TimelineItem};
+use crate::db::{SqlPool, with_pool};
+use crate::error::Result;
+use crate::memory::{EmbeddingTable, Memory, MemoryStore};
+use crate::polls::{PollStore, PollVote};
+use crate::reminders::{Reminder, ReminderStore};
+use crate::weather::{LocationStore, SavedLocation};
+
+use anyhow::Context as _;
+use serde::Serialize;
+use sqlx::Row as _;
+
+/// Everything one agent stores about a user.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserData {
+    pub messages: Vec<UserMessage>,
+    pub private_channels: Vec<PrivateChannel>,
+    pub memories: Vec<Memory>,
+    pub reminders: Vec<Reminder>,
+    pub poll_votes: Vec<PollVote>,
+    pub location: Option<SavedLocation>,
+}
+
+impl UserData {
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+            && self.private_channels.is_empty()
+            && self.memories.is_empty()
+            && self.reminders.is_empty()
+            && self.poll_votes.is_empty()
+            && self.location.is_none()
+    }
+}
+
+/// A message the user sent.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserMessage {
+    pub id: String,
+    pub channel_id: String,
+    pub sender_name: Option<String>,
+    pub content: String,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: Option<String>,
+}
+
+/// A channel nobody but the user talks in, with its full timeline.
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivateChannel {
+    pub id: String,
+    pub platform: Option<String>,
+    pub display_name: Option<String>,
+    pub timeline: Vec<TimelineItem>,
+    pub turns: Vec<ChannelTurn>,
+}
+
+/// Row counts removed by [`purge`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PurgeReport {
+    pub messages: u64,
+    pub channels: u64,
+    pub memories: u64,
+    pub reminders: u64,
+    pub poll_votes: u64,
+    pub locations: u64,
+}
+
+/// Collect what the agent behind `pool` stores about `user_id`.
+pub async fn export(pool: &SqlPool, user_id: &str) -> Result<UserData> {
+    let messages = with_pool!(pool, |pool| {
+        sqlx::query(
+            "SELECT id, channel_id, sender_name, content, metadata, created_at \
+             FROM conversation_messages WHERE sender_id = $1 ORDER BY created_at",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+        .and_then(|rows| {
+            rows.iter()
+                .map(|row| {
+                    let metadata: Option<String> = row.try_get("metadata")?;
+                    Ok(UserMessage {
+                        id: row.try_get("id")?,
+                        channel_id: row.try_get("channel_id")?,
+                        sender_name: row.try_get("sender_name")?,
+                        content: row.try_get("content")?,
+                        metadata: metadata.and_then(|json| serde_json::from_str(&json).ok()),
+                        created_at: crate::db::timestamp_text(row, "created_at")?,
+                    })
+                })
+                .collect::<std::result::Result<Vec<_>, sqlx::Error>>()
+        })
+    })
+    .with_context(|| format!("failed to load messages from {user_id}"))?;
+
+    let run_logger = ProcessRunLogger::new(pool.clone());
+    let conversation_logger = ConversationLogger::new(pool.clone());
+    let memory_store = MemoryStore::new(pool.clone());
+    let mut private_channels = Vec::new();
+    let mut memories = Vec::new();
+    for channel_id in private_channel_ids(pool, user_id).await? {
+        let (platform, display_name) = with_pool!(pool, |pool| {
+            sqlx::query("SELECT platform, display_name FROM channels WHERE id = $1")
+                .bind(&channel_id)
+                .fetch_optional(pool)
+                .await
+                .and_then(|row| match row {
+                    Some(row) => Ok((row.try_get("platform")?, row.try_get("display_name")?)),
+                    None => Ok((None, None)),
+                })
+        })
+        .with_context(|| format!("failed to load channel {channel_id}"))?;
+
+        private_channels.push(PrivateChannel {
+            timeline: run_logger
+                .load_channel_timeline(&channel_id, i64::MAX, None)
+                .await?,
+            turns: conversation_logger.load_turns(&channel_id).await?,
+            id: channel_id.clone(),
+            platform,
+            display_name,
+        });
+        memories.extend(memory_store.get_by_channel(&channel_id).await?);
+    }
+
+    Ok(UserData {
+        messages,
+        private_channels,
+        memories,
+        reminders: ReminderStore::new(pool.clone()).by_sender(user_id).await?,
+        poll_votes: PollStore::new(pool.clone()).votes_by(user_id).await?,
+        location: LocationStore::new(pool.clone()).get(user_id).await?,
+    })
+}
+
+/// Delete what the agent stores about `user_id`, including the memories'
+/// embeddings, and record the purge in the cortex event log.
+pub async fn purge(
+    pool: &SqlPool,
+    embeddings: &EmbeddingTable,
+    user_id: &str,
+) -> Result<PurgeReport> {
+    let memory_store = MemoryStore::new(pool.clone());
+    let mut report = PurgeReport::default();
+
+    for channel_id in private_channel_ids(pool, user_id).await? {
+        for memory in memory_store.get_by_channel(&channel_id).await? {
+            embeddings.delete(&memory.id).await?;
+            memory_store.delete(&memory.id).await?;
+            report.memories += 1;
+        }
+
+        report.messages += with_pool!(pool, |pool| {
+            async {
+                let messages =
+                    sqlx::query("DELETE FROM conversation_messages WHERE channel_id = $1")
+                        .bind(&channel_id)
+                        .execute(pool)
+                        .await?
+                        .rows_affected();
+                for table in [
+                    "branch_runs",
+                    "worker_runs",
+                    "channel_turns",
+                    "code_artifacts",
+                ] {
+                    sqlx::query(&format!("DELETE FROM {table} WHERE channel_id = $1"))
+                        .bind(&channel_id)
+                        .execute(pool)
+                        .await?;
+                }
+                sqlx::query("DELETE FROM channels WHERE id = $1")
+                    .bind(&channel_id)
+                    .execute(pool)
+                    .await?;
+                Ok::<_, sqlx::Error>(messages)
+            }
+            .await
+        })
+        .with_context(|| format!("failed to purge channel {channel_id}"))?;
+        report.channels += 1;
+    }
+
+    report.messages += with_pool!(pool, |pool| {
+        sqlx::query("DELETE FROM conversation_messages WHERE sender_id = $1")
+            .bind(user_id)
+            .execute(pool)
+            .await
+            .map(|result| result.rows_affected())
+    })
+    .with_context(|| format!("failed to purge messages from {user_id}"))?;
+    report.reminders = ReminderStore::new(pool.clone())
+        .delete_by_sender(user_id)
+        .await?;
+    report.poll_votes = PollStore::new(pool.clone())
+        .delete_votes_by(user_id)
+        .await?;
+    report.locations = LocationStore::new(pool.clone()).delete(user_id).await?;
+
+    let details = serde_json::json!({
+        "user_id": user_id,
+        "messages": report.messages,
+        "channels": report.channels,
+        "memories": report.memories,
+        "reminders": report.reminders,
+        "poll_votes": report.poll_votes,
+        "locations": report.locations,
+    })
+    .to_string();
+    with_pool!(pool, |pool| {
+        sqlx::query(
+            "INSERT INTO cortex_events (id, event_type, summary, details) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind("user_data_purged")
+        .bind(format!("Purged stored data for user {user_id}"))
+        .bind(&details)
+        .execute(pool)
+        .await
+        .map(drop)
+    })
+    .context("failed to record the purge in the cortex event log")?;
+
+    Ok(report)
+}
+
+/// Channels where every user message came from `user_id`.
+async fn private_channel_ids(pool: &SqlPool, user_id: &str) -> Result<Vec<String>> {
+    let channel_ids = with_pool!(pool, |pool| {
+        sqlx::query_scalar(
+            "SELECT channel_id FROM conversation_messages WHERE role = 'user' \
+             GROUP BY channel_id \
+             HAVING MIN(sender_id) = $1 AND MAX(sender_id) = $1 \
+             AND COUNT(*) = COUNT(sender_id) \
+             ORDER BY channel_id",
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    })
+    .with_context(|| format!("failed to find private channels for {user_id}"))?;
+
+    Ok(channel_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryType;
+
+    #[cfg_attr(not(feature = "sql-postgres"), allow(irrefutable_let_patterns))]
+    async fn insert_message(pool: &SqlPool, channel_id: &str, role: &str, sender_id: Option<&str>) {
+        let SqlPool::Sqlite { writer: pool, .. } = pool else {
+            unreachable!("tests use SQLite");
+        };
+        sqlx::query(
+            "INSERT INTO conversation_messages (id, channel_id, role, sender_id, content) \
+             VALUES ($1, $2, $3, $4, 'hello')",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(channel_id)
+        .bind(role)
+        .bind(sender_id)
+        .execute(pool)
+        .await
+        .expect("insert message");
+    }
+
+    #[tokio::test]
+    async fn exports_and_purges_only_the_users_data() {
+        let store = MemoryStore::connect_in_memory().await;
+        let pool = store.pool().clone();
+        let lance_dir = tempfile::tempdir().expect("temp dir");
+        let lance = crate::db::connect_lance(lance_dir.path())
+            .await
+            .expect("lance");
+        let embeddings = EmbeddingTable::open_or_create(&lance)
+            .await
+            .expect("embedding table");
+
+        insert_message(&pool, "discord:dm:42", "user", Some("42")).await;
+        insert_message(&pool, "discord:dm:42", "assistant", None).await;
+        insert_message(&pool, "discord:1:general", "user", Some("42")).await;
+        insert_message(&pool, "discord:1:general", "user", Some("7")).await;
+        insert_message(&pool, "discord:1:general", "assistant", None).await;
+
+        let private = Memory::new("likes tea", MemoryType::Preference)
+            .with_channel_id(std::sync::Arc::from("discord:dm:42"));
+        let shared = Memory::new("standup is at 10", MemoryType::Fact)
+            .with_channel_id(std::sync::Arc::from("discord:1:general"));
+        store.save(&private).await.expect("save");
+        store.save(&shared).await.expect("save");
+        ReminderStore::new(pool.clone())
+            .create(&Reminder {
+                id: "r1".into(),
+                channel_id: "discord:1:general".into(),
+                sender_id: "42".into(),
+                mention: "<@42>".into(),
+                message: "stretch".into(),
+                due_at: chrono::Utc::now(),
+            })
+            .await
+            .expect("create reminder");
+        let lyon = crate::weather::Place::from_coordinates("45.76,4.84").expect("coordinates");
+        LocationStore::new(pool.clone())
+            .set("42", &lyon)
+            .await
+            .expect("save location");
+
+        let data = export(&pool, "42").await.expect("export");
+        assert_eq!(data.messages.len(), 2);
+        assert_eq!(data.private_channels.len(), 1);
+        assert_eq!(data.private_channels[0].id, "discord:dm:42");
+        assert_eq!(data.private_channels[0].timeline.len(), 2);
+        assert_eq!(data.memories.len(), 1);
+        assert_eq!(data.memories[0].id, private.id);
+        assert_eq!(data.reminders.len(), 1);
+        assert!(data.location.is_some());
+
+        let report = purge(&pool, &embeddings, "42").await.expect("purge");
+        assert_eq!(
+            report,
+            PurgeReport {
+                messages: 3,
+                channels: 1,
+                memories: 1,
+                reminders: 1,
+                poll_votes: 0,
+                locations: 1,
+            }
+        );
+        assert!(export(&pool, "42").await.expect("export").is_empty());
+        assert!(store.load(&private.id).await.expect("load").is_none());
+        assert!(store.load(&shared.id).await.expect("load").is_some());
+        assert_eq!(export(&pool, "7").await.expect("export").messages.len(), 1);
+
+        let events = crate::agent::cortex::CortexLogger::new(pool.clone())
+            .load_events(10, 0, Some("user_data_purged"))
+            .await
+            .expect("events");
+        assert_eq!(events.len(), 1);
+    }
+}
@@ -0,0 +1,242 @@
+//! Memory watchdog.
+//!
+//! [`spawn`] samples the process's resident memory against its limit: the
+//! configured `limit_mb`, else the container's cgroup limit, else the host's
+//! total memory. Past `shed_percent` of the limit, in-memory caches are
+//! dropped: cached weather responses, alert description embeddings, the
+//! model catalog, and the prompt snapshot each channel keeps for
+//! `!snapshot`. Past `reject_percent`, new background jobs are refused too,
+//! so ingestion, digests, and feeds wait for memory to come back instead of
+//! getting the container OOM-killed. Rejected work is picked up again on the
+//! next scan.
+//!
+//! Memory is read from `/proc`; on hosts without it the watchdog stays off.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::time::Duration;
+
+/// Memory watchdog settings (instance-level, under `[memory_watchdog]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryWatchdogConfig {
+    pub enabled: bool,
+    /// Memory the process may use. `None` uses the cgroup limit, then the
+    /// host's total memory.
+    pub limit_mb: Option<u64>,
+    /// Percent of the limit at which caches are dropped.
+    pub shed_percent: u8,
+    /// Percent of the limit at which new background jobs are refused.
+    pub reject_percent: u8,
+    pub interval_secs: u64,
+}
+
+impl Default for MemoryWatchdogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            limit_mb: None,
+            shed_percent: 80,
+            reject_percent: 90,
+            interval_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pressure {
+    Normal,
+    /// Caches are being dropped.
+    High,
+    /// Caches are being dropped and background jobs refused.
+    Critical,
+}
+
+impl Pressure {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Normal,
+            1 => Self::High,
+            _ => Self::Critical,
+        }
+    }
+}
+
+static PRESSURE: AtomicU8 = AtomicU8::new(Pressure::Normal as u8);
+
+/// The pressure the watchdog saw at its last sample.
+pub fn pressure() -> Pressure {
+    Pressure::from_u8(PRESSURE.load(Ordering::Relaxed))
+}
+
+/// Whether caches should be dropped instead of filled.
+pub fn shedding() -> bool {
+    pressure() >= Pressure::High
+}
+
+/// Fails while memory is critical, so background work is put off.
+pub fn admit_background_job() -> anyhow::Result<()> {
+    if pressure() < Pressure::Critical {
+        return Ok(());
+    }
+    #[cfg(feature = "metrics")]
+    crate::telemetry::Metrics::global()
+        .background_jobs_rejected_total
+        .inc();
+    anyhow::bail!("memory is nearly exhausted, background jobs are paused")
+}
+
+/// Count `count` entries evicted from `cache` under memory pressure.
+pub fn record_evictions(cache: &str, count: usize) {
+    if count == 0 {
+        return;
+    }
+    #[cfg(feature = "metrics")]
+    crate::telemetry::Metrics::global()
+        .cache_evictions_total
+        .with_label_values(&[cache])
+        .inc_by(count as u64);
+    tracing::debug!(cache, count, "evicted cache entries under memory pressure");
+}
+
+impl MemoryWatchdogConfig {
+    fn classify(&self, rss_bytes: u64, limit_bytes: u64) -> Pressure {
+        let percent = rss_bytes.saturating_mul(100) / limit_bytes.max(1);
+        if percent >= u64::from(self.reject_percent) {
+            Pressure::Critical
+        } else if percent >= u64::from(self.shed_percent) {
+            Pressure::High
+        } else {
+            Pressure::Normal
+        }
+    }
+}
+
+/// Sample memory every `interval_secs` and update [`pressure`].
+pub fn spawn(config: MemoryWatchdogConfig) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let limit_bytes = match config.limit_mb {
+            Some(limit_mb) => limit_mb * 1024 * 1024,
+            None => match detect_limit().await {
+                Some(limit_bytes) => limit_bytes,
+                None => {
+                    tracing::info!("memory watchdog off: no memory limit could be read");
+                    return;
+                }
+            },
+        };
+        tracing::info!(
+            limit_mb = limit_bytes / 1024 / 1024,
+            "memory watchdog started"
+        );
+
+        let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+        loop {
+            interval.tick().await;
+            let Some(rss_bytes) = read_rss().await else {
+                tracing::info!("memory watchdog off: process memory could not be read");
+                PRESSURE.store(Pressure::Normal as u8, Ordering::Relaxed);
+                return;
+            };
+            #[cfg(feature = "metrics")]
+            crate::telemetry::Metrics::global()
+                .memory_rss_bytes
+                .set(rss_bytes as i64);
+
+            let current = config.classify(rss_bytes, limit_bytes);
+            let previous = Pressure::from_u8(PRESSURE.swap(current as u8, Ordering::Relaxed));
+            if current != previous {
+                let rss_mb = rss_bytes / 1024 / 1024;
+                let limit_mb = limit_bytes / 1024 / 1024;
+                match current {
+                    Pressure::Normal => {
+                        tracing::info!(rss_mb, limit_mb, "memory pressure cleared")
+                    }
+                    Pressure::High => {
+                        tracing::warn!(rss_mb, limit_mb, "memory pressure high, dropping caches")
+                    }
+                    Pressure::Critical => tracing::error!(
+                        rss_mb,
+                        limit_mb,
+                        "memory nearly exhausted, dropping caches and pausing background jobs"
+                    ),
+                }
+            }
+            if current >= Pressure::High {
+                shed_caches();
+            }
+        }
+    })
+}
+
+/// Drop every process-wide cache. Channels drop their snapshots themselves
+/// while [`shedding`] is set.
+fn shed_caches() {
+    record_evictions("weather_responses", crate::weather::shed_cache());
+    record_evictions("alert_embeddings", crate::alerts::shed_cache());
+    record_evictions("model_catalog", crate::api::shed_models_cache());
+}
+
+/// The cgroup v2 or v1 memory limit, or the host's total memory.
+async fn detect_limit() -> Option<u64> {
+    for path in [
+        "/sys/fs/cgroup/memory.max",
+        "/sys/fs/cgroup/memory/memory.limit_in_bytes",
+    ] {
+        if let Ok(contents) = tokio::fs::read_to_string(path).await
+            && let Some(limit) = parse_cgroup_limit(&contents)
+        {
+            return Some(limit);
+        }
+    }
+    let meminfo = tokio::fs::read_to_string("/proc/meminfo").await.ok()?;
+    crate::llm::resources::parse_meminfo(&meminfo).map(|(total_kib, _)| total_kib * 1024)
+}
+
+async fn read_rss() -> Option<u64> {
+    let status = tokio::fs::read_to_string("/proc/self/status").await.ok()?;
+    parse_rss(&status)
+}
+
+/// Parse a cgroup memory limit in bytes. `max` and cgroup v1's
+/// page-rounded `i64::MAX` both mean unlimited.
+fn parse_cgroup_limit(contents: &str) -> Option<u64> {
+    let limit: u64 = contents.trim().parse().ok()?;
+    (limit > 0 && limit < 1 << 62).then_some(limit)
+}
+
+/// Parse `VmRSS` from `/proc/self/status`, in bytes.
+fn parse_rss(status: &str) -> Option<u64> {
+    let kib: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pressure_follows_the_share_of_the_limit_in_use() {
+        let config = MemoryWatchdogConfig::default();
+        let gib = 1024 * 1024 * 1024;
+        assert_eq!(config.classify(gib / 2, gib), Pressure::Normal);
+        assert_eq!(config.classify(gib * 85 / 100, gib), Pressure::High);
+        assert_eq!(config.classify(gib * 95 / 100, gib), Pressure::Critical);
+        assert_eq!(config.classify(2 * gib, gib), Pressure::Critical);
+    }
+
+    #[test]
+    fn proc_and_cgroup_files_parse() {
+        let status = "Name:\tspacebot\nVmPeak:\t  812344 kB\nVmRSS:\t  204800 kB\nThreads:\t24\n";
+        assert_eq!(parse_rss(status), Some(204800 * 1024));
+        assert_eq!(parse_rss("Name:\tspacebot\n"), None);
+
+        assert_eq!(parse_cgroup_limit("2147483648\n"), Some(2147483648));
+        assert_eq!(parse_cgroup_limit("max\n"), None);
+        assert_eq!(parse_cgroup_limit("9223372036854771712\n"), None);
+    }
+}
@@ -0,0 +1,632 @@
+//! Weather forecasts and place search from [Open-Meteo](https://open-meteo.com),
+//! for the `weather` and `geocode` tools. Open-Meteo needs no API key.
+//!
+//! Responses are cached in memory for `cache_secs`, shared by every agent in
+//! the process, so a busy channel asking about the same city doesn't refetch
+//! it each time. People's default locations are stored in the
+//! `user_locations` table, keyed by sender id like reminders, so "what's the
+//! weather?" works without naming a place once they've told the agent where
+//! they are.
+
+use crate::config::{WeatherConfig, WeatherUnits};
+use crate::db::{SqlPool, with_pool};
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use sqlx::Row as _;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+
+/// Most days Open-Meteo forecasts.
+pub const MAX_FORECAST_DAYS: u32 = 16;
+
+/// Most places one search returns.
+pub const MAX_SEARCH_RESULTS: u32 = 10;
+
+/// Most responses kept in the cache.
+const MAX_CACHE_ENTRIES: usize = 512;
+
+/// Cached response bodies by request URL, with when they were fetched.
+static CACHE: LazyLock<Mutex<HashMap<String, (Instant, serde_json::Value)>>> =
+    LazyLock::new(Mutex::default);
+
+#[derive(Debug, thiserror::Error)]
+pub enum WeatherError {
+    #[error("weather request failed: {0}")]
+    Request(String),
+
+    #[error("Open-Meteo returned {status}: {message}")]
+    Status {
+        status: reqwest::StatusCode,
+        message: String,
+    },
+
+    #[error("unexpected Open-Meteo response: {0}")]
+    InvalidResponse(String),
+
+    #[error("no place called '{0}' was found")]
+    NotFound(String),
+}
+
+impl From<reqwest::Error> for WeatherError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Request(error.to_string())
+    }
+}
+
+impl From<crate::egress::EgressError> for WeatherError {
+    fn from(error: crate::egress::EgressError) -> Self {
+        Self::Request(error.to_string())
+    }
+}
+
+/// A place with coordinates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Place {
+    pub name: String,
+    /// State, province, or region.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin1: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// IANA time zone, e.g. "Europe/Paris".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub population: Option<u64>,
+}
+
+impl Place {
+    /// "Paris, Île-de-France, France".
+    pub fn label(&self) -> String {
+        let mut parts = vec![self.name.as_str()];
+        for part in [&self.admin1, &self.country].into_iter().flatten() {
+            if !parts.contains(&part.as_str()) {
+                parts.push(part);
+            }
+        }
+        parts.join(", ")
+    }
+
+    /// A place given as "latitude,longitude", e.g. "48.85,2.35".
+    pub fn from_coordinates(text: &str) -> Option<Self> {
+        let (latitude, longitude) = text.split_once(',')?;
+        let latitude: f64 = latitude.trim().parse().ok()?;
+        let longitude: f64 = longitude.trim().parse().ok()?;
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return None;
+        }
+        Some(Self {
+            name: format!("{latitude:.4},{longitude:.4}"),
+            admin1: None,
+            country: None,
+            latitude,
+            longitude,
+            timezone: None,
+            population: None,
+        })
+    }
+}
+
+/// Conditions right now.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CurrentWeather {
+    /// Local time of the reading, e.g. "2026-10-14T15:00".
+    pub time: String,
+    pub conditions: String,
+    pub temperature: f64,
+    pub feels_like: f64,
+    /// Relative humidity in percent.
+    pub humidity: f64,
+    pub wind_speed: f64,
+}
+
+/// One day of the forecast.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DailyForecast {
+    pub date: String,
+    pub conditions: String,
+    pub temperature_max: f64,
+    pub temperature_min: f64,
+    pub precipitation: f64,
+    /// Highest chance of precipitation during the day, in percent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precipitation_chance: Option<f64>,
+}
+
+/// Unit symbols for the values in a forecast.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct UnitLabels {
+    pub temperature: &'static str,
+    pub wind_speed: &'static str,
+    pub precipitation: &'static str,
+}
+
+impl UnitLabels {
+    fn for_units(units: WeatherUnits) -> Self {
+        match units {
+            WeatherUnits::Metric => Self {
+                temperature: "°C",
+                wind_speed: "km/h",
+                precipitation: "mm",
+            },
+            WeatherUnits::Imperial => Self {
+                temperature: "°F",
+                wind_speed: "mph",
+                precipitation: "in",
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Forecast {
+    /// The forecast's time zone, e.g. "Europe/Paris".
+    pub timezone: String,
+    pub units: UnitLabels,
+    pub current: CurrentWeather,
+    pub daily: Vec<DailyForecast>,
+}
+
+impl Forecast {
+    /// A short plain-text summary, one line for now and one per day.
+    pub fn summary(&self, place: &str) -> String {
+        let units = self.units;
+        let current = &self.current;
+        let mut lines = vec![format!(
+            "{place} now: {}, {:.0}{} (feels like {:.0}{}), humidity {:.0}%, wind {:.0} {}",
+            current.conditions,
+            current.temperature,
+            units.temperature,
+            current.feels_like,
+            units.temperature,
+            current.humidity,
+            current.wind_speed,
+            units.wind_speed,
+        )];
+        for day in &self.daily {
+            let mut line = format!(
+                "{}: {}, {:.0}{} to {:.0}{}",
+                day.date,
+                day.conditions,
+                day.temperature_min,
+                units.temperature,
+                day.temperature_max,
+                units.temperature,
+            );
+            if let Some(chance) = day.precipitation_chance {
+                line.push_str(&format!(", {chance:.0}% chance of precipitation"));
+            }
+            if day.precipitation > 0.0 {
+                line.push_str(&format!(" ({} {})", day.precipitation, units.precipitation));
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+/// Client for Open-Meteo's forecast and geocoding APIs.
+#[derive(Debug, Clone)]
+pub struct WeatherClient {
+    http: crate::egress::Client,
+    config: WeatherConfig,
+}
+
+impl WeatherClient {
+    /// Returns `None` when the weather tools are disabled.
+    pub fn new(config: WeatherConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+        let http = crate::egress::client_builder()
+            .gzip(true)
+            .timeout(Duration::from_secs(15))
+            .build()
+            .expect("hardcoded reqwest client config");
+        Some(Self { http, config })
+    }
+
+    /// Places matching `query`, most relevant first.
+    pub async fn search(&self, query: &str, count: u32) -> Result<Vec<Place>, WeatherError> {
+        let count = count.clamp(1, MAX_SEARCH_RESULTS).to_string();
+        let body = self
+            .get(
+                GEOCODING_URL,
+                &[
+                    ("name", query.trim()),
+                    ("count", &count),
+                    ("language", "en"),
+                    ("format", "json"),
+                ],
+            )
+            .await?;
+        parse_places(&body)
+    }
+
+    /// The place `location` names: coordinates as "latitude,longitude", or
+    /// the best match for a place name.
+    pub async fn resolve(&self, location: &str) -> Result<Place, WeatherError> {
+        if let Some(place) = Place::from_coordinates(location) {
+            return Ok(place);
+        }
+        // "Springfield, Illinois": search the name, prefer a match on the rest.
+        let (name, qualifier) = match location.split_once(',') {
+            Some((name, qualifier)) => (name.trim(), Some(qualifier.trim().to_lowercase())),
+            None => (location.trim(), None),
+        };
+        let places = self.search(name, MAX_SEARCH_RESULTS).await?;
+        let qualified = qualifier.and_then(|qualifier| {
+            places.iter().find(|place| {
+                [&place.admin1, &place.country]
+                    .into_iter()
+                    .flatten()
+                    .any(|part| part.to_lowercase().contains(&qualifier))
+            })
+        });
+        qualified
+            .or(places.first())
+            .cloned()
+            .ok_or_else(|| WeatherError::NotFound(location.trim().to_string()))
+    }
+
+    /// Current conditions and a `days`-day forecast at `place`.
+    pub async fn forecast(&self, place: &Place, days: u32) -> Result<Forecast, WeatherError> {
+        let latitude = place.latitude.to_string();
+        let longitude = place.longitude.to_string();
+        let days = days.clamp(1, MAX_FORECAST_DAYS).to_string();
+        let mut query = vec![
+            ("latitude", latitude.as_str()),
+            ("longitude", longitude.as_str()),
+            (
+                "current",
+                "temperature_2m,relative_humidity_2m,apparent_temperature,weather_code,wind_speed_10m",
+            ),
+            (
+                "daily",
+                "weather_code,temperature_2m_max,temperature_2m_min,precipitation_sum,precipitation_probability_max",
+            ),
+            ("timezone", "auto"),
+            ("forecast_days", days.as_str()),
+        ];
+        if self.config.units == WeatherUnits::Imperial {
+            query.extend([
+                ("temperature_unit", "fahrenheit"),
+                ("wind_speed_unit", "mph"),
+                ("precipitation_unit", "inch"),
+            ]);
+        }
+        let body = self.get(FORECAST_URL, &query).await?;
+        parse_forecast(&body, self.config.units)
+    }
+
+    /// GET `url` with `query`, from the cache when it's fresh.
+    async fn get(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<serde_json::Value, WeatherError> {
+        let url = reqwest::Url::parse_with_params(url, query)
+            .map_err(|error| WeatherError::Request(error.to_string()))?;
+        let ttl = Duration::from_secs(self.config.cache_secs);
+        if let Some(body) = cached(url.as_str(), ttl) {
+            return Ok(body);
+        }
+
+        let response = self.http.get(url.clone()).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body: serde_json::Value = response.json().await.unwrap_or_default();
+            let message = body["reason"]
+                .as_str()
+                .unwrap_or("request failed")
+                .to_string();
+            return Err(WeatherError::Status { status, message });
+        }
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|error| WeatherError::InvalidResponse(error.to_string()))?;
+        if !ttl.is_zero() && !crate::watchdog::shedding() {
+            store(url.to_string(), body.clone(), ttl);
+        }
+        Ok(body)
+    }
+}
+
+/// Drop every cached response. Returns how many were dropped.
+pub fn shed_cache() -> usize {
+    let mut cache = CACHE.lock().expect("poisoned");
+    let dropped = cache.len();
+    *cache = HashMap::new();
+    dropped
+}
+
+fn cached(url: &str, ttl: Duration) -> Option<serde_json::Value> {
+    let cache = CACHE.lock().expect("poisoned");
+    let (fetched_at, body) = cache.get(url)?;
+    (fetched_at.elapsed() < ttl).then(|| body.clone())
+}
+
+fn store(url: String, body: serde_json::Value, ttl: Duration) {
+    let mut cache = CACHE.lock().expect("poisoned");
+    if cache.len() >= MAX_CACHE_ENTRIES {
+        cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < ttl);
+    }
+    if cache.len() >= MAX_CACHE_ENTRIES
+        && let Some(oldest) = cache
+            .iter()
+            .min_by_key(|(_, (fetched_at, _))| *fetched_at)
+            .map(|(url, _)| url.clone())
+    {
+        cache.remove(&oldest);
+    }
+    cache.insert(url, (Instant::now(), body));
+}
+
+fn parse_places(body: &serde_json::Value) -> Result<Vec<Place>, WeatherError> {
+    // No matches come back without a `results` key at all.
+    let Some(results) = body.get("results") else {
+        return Ok(Vec::new());
+    };
+    serde_json::from_value(results.clone())
+        .map_err(|error| WeatherError::InvalidResponse(error.to_string()))
+}
+
+fn parse_forecast(body: &serde_json::Value, units: WeatherUnits) -> Result<Forecast, WeatherError> {
+    let number = |value: &serde_json::Value, what: &str| {
+        value
+            .as_f64()
+            .ok_or_else(|| WeatherError::InvalidResponse(format!("missing {what}")))
+    };
+
+    let current = &body["current"];
+    let current = CurrentWeather {
+        time: current["time"].as_str().unwrap_or_default().to_string(),
+        conditions: describe_code(current["weather_code"].as_u64()).to_string(),
+        temperature: number(&current["temperature_2m"], "current temperature")?,
+        feels_like: number(&current["apparent_temperature"], "apparent temperature")?,
+        humidity: number(&current["relative_humidity_2m"], "humidity")?,
+        wind_speed: number(&current["wind_speed_10m"], "wind speed")?,
+    };
+
+    let daily = &body["daily"];
+    let dates = daily["time"]
+        .as_array()
+        .ok_or_else(|| WeatherError::InvalidResponse("missing daily forecast".into()))?;
+    let series = |key: &str, index: usize| &daily[key][index];
+    let daily = dates
+        .iter()
+        .enumerate()
+        .map(|(index, date)| {
+            Ok(DailyForecast {
+                date: date.as_str().unwrap_or_default().to_string(),
+                conditions: describe_code(series("weather_code", index).as_u64()).to_string(),
+                temperature_max: number(series("temperature_2m_max", index), "high")?,
+                temperature_min: number(series("temperature_2m_min", index), "low")?,
+                precipitation: series("precipitation_sum", index).as_f64().unwrap_or(0.0),
+                precipitation_chance: series("precipitation_probability_max", index).as_f64(),
+            })
+        })
+        .collect::<Result<Vec<_>, WeatherError>>()?;
+
+    Ok(Forecast {
+        timezone: body["timezone"].as_str().unwrap_or("GMT").to_string(),
+        units: UnitLabels::for_units(units),
+        current,
+        daily,
+    })
+}
+
+/// Words for a WMO weather interpretation code.
+fn describe_code(code: Option<u64>) -> &'static str {
+    match code {
+        Some(0) => "clear sky",
+        Some(1) => "mainly clear",
+        Some(2) => "partly cloudy",
+        Some(3) => "overcast",
+        Some(45 | 48) => "fog",
+        Some(51) => "light drizzle",
+        Some(53) => "drizzle",
+        Some(55) => "heavy drizzle",
+        Some(56 | 57) => "freezing drizzle",
+        Some(61) => "light rain",
+        Some(63) => "rain",
+        Some(65) => "heavy rain",
+        Some(66 | 67) => "freezing rain",
+        Some(71) => "light snow",
+        Some(73) => "snow",
+        Some(75) => "heavy snow",
+        Some(77) => "snow grains",
+        Some(80) => "light rain showers",
+        Some(81) => "rain showers",
+        Some(82) => "violent rain showers",
+        Some(85) => "light snow showers",
+        Some(86) => "heavy snow showers",
+        Some(95) => "thunderstorm",
+        Some(96 | 99) => "thunderstorm with hail",
+        _ => "unknown conditions",
+    }
+}
+
+/// A person's saved default location.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SavedLocation {
+    pub place: Place,
+    pub updated_at: Option<String>,
+}
+
+/// Default locations people have asked the agent to remember.
+#[derive(Debug, Clone)]
+pub struct LocationStore {
+    pool: SqlPool,
+}
+
+impl LocationStore {
+    pub fn new(pool: SqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// The default location saved for `sender_id`, if any.
+    pub async fn get(&self, sender_id: &str) -> crate::error::Result<Option<SavedLocation>> {
+        let location = with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "SELECT name, admin1, country, latitude, longitude, timezone, updated_at \
+                 FROM user_locations WHERE sender_id = $1",
+            )
+            .bind(sender_id)
+            .fetch_optional(pool)
+            .await
+            .and_then(|row| {
+                let Some(row) = row else { return Ok(None) };
+                Ok(Some(SavedLocation {
+                    place: Place {
+                        name: row.try_get("name")?,
+                        admin1: row.try_get("admin1")?,
+                        country: row.try_get("country")?,
+                        latitude: row.try_get("latitude")?,
+                        longitude: row.try_get("longitude")?,
+                        timezone: row.try_get("timezone")?,
+                        population: None,
+                    },
+                    updated_at: crate::db::timestamp_text(&row, "updated_at")?,
+                }))
+            })
+        })
+        .with_context(|| format!("failed to load the location of {sender_id}"))?;
+
+        Ok(location)
+    }
+
+    /// Save `place` as the default location for `sender_id`, replacing any
+    /// earlier one.
+    pub async fn set(&self, sender_id: &str, place: &Place) -> crate::error::Result<()> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query(
+                "INSERT INTO user_locations \
+                 (sender_id, name, admin1, country, latitude, longitude, timezone) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7) \
+                 ON CONFLICT (sender_id) DO UPDATE SET \
+                 name = excluded.name, admin1 = excluded.admin1, country = excluded.country, \
+                 latitude = excluded.latitude, longitude = excluded.longitude, \
+                 timezone = excluded.timezone, updated_at = CURRENT_TIMESTAMP",
+            )
+            .bind(sender_id)
+            .bind(&place.name)
+            .bind(&place.admin1)
+            .bind(&place.country)
+            .bind(place.latitude)
+            .bind(place.longitude)
+            .bind(&place.timezone)
+            .execute(pool)
+            .await
+            .map(drop)
+        })
+        .with_context(|| format!("failed to save the location of {sender_id}"))?;
+
+        Ok(())
+    }
+
+    /// Forget the default location for `sender_id`. Returns how many were
+    /// deleted.
+    pub async fn delete(&self, sender_id: &str) -> crate::error::Result<u64> {
+        let deleted = with_pool!(&self.pool, |pool| {
+            sqlx::query("DELETE FROM user_locations WHERE sender_id = $1")
+                .bind(sender_id)
+                .execute(pool)
+                .await
+                .map(|result| result.rows_affected())
+        })
+        .with_context(|| format!("failed to delete the location of {sender_id}"))?;
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forecasts_parse_with_units() {
+        let body = serde_json::json!({
+            "timezone": "Europe/Paris",
+            "current": {
+                "time": "2026-10-14T15:00",
+                "temperature_2m": 17.4,
+                "relative_humidity_2m": 62,
+                "apparent_temperature": 16.1,
+                "weather_code": 2,
+                "wind_speed_10m": 11.3
+            },
+            "daily": {
+                "time": ["2026-10-14", "2026-10-15"],
+                "weather_code": [2, 63],
+                "temperature_2m_max": [18.2, 15.0],
+                "temperature_2m_min": [9.1, 10.4],
+                "precipitation_sum": [0.0, 6.2],
+                "precipitation_probability_max": [5, 80]
+            }
+        });
+        let forecast = parse_forecast(&body, WeatherUnits::Metric).unwrap();
+        assert_eq!(forecast.current.conditions, "partly cloudy");
+        assert_eq!(forecast.daily.len(), 2);
+        assert_eq!(forecast.daily[1].conditions, "rain");
+        assert_eq!(
+            forecast.summary("Paris, France"),
+            "Paris, France now: partly cloudy, 17°C (feels like 16°C), humidity 62%, wind 11 km/h\n\
+             2026-10-14: partly cloudy, 9°C to 18°C, 5% chance of precipitation\n\
+             2026-10-15: rain, 10°C to 15°C, 80% chance of precipitation (6.2 mm)"
+        );
+
+        assert!(parse_forecast(&serde_json::json!({}), WeatherUnits::Imperial).is_err());
+    }
+
+    #[test]
+    fn places_parse_and_label() {
+        let body = serde_json::json!({
+            "results": [{
+                "id": 2988507,
+                "name": "Paris",
+                "latitude": 48.85341,
+                "longitude": 2.3488,
+                "country": "France",
+                "admin1": "Île-de-France",
+                "timezone": "Europe/Paris",
+                "population": 2138551
+            }]
+        });
+        let places = parse_places(&body).unwrap();
+        assert_eq!(places[0].label(), "Paris, Île-de-France, France");
+        assert!(parse_places(&serde_json::json!({})).unwrap().is_empty());
+
+        let place = Place::from_coordinates("40.7128, -74.006").unwrap();
+        assert_eq!((place.latitude, place.longitude), (40.7128, -74.006));
+        assert!(Place::from_coordinates("Paris, France").is_none());
+        assert!(Place::from_coordinates("95,10").is_none());
+    }
+
+    #[tokio::test]
+    async fn locations_are_saved_per_sender() {
+        let store = crate::memory::MemoryStore::connect_in_memory().await;
+        let locations = LocationStore::new(store.pool().clone());
+        let mut place = Place::from_coordinates("48.85,2.35").unwrap();
+
+        assert!(locations.get("42").await.unwrap().is_none());
+        locations.set("42", &place).await.unwrap();
+        place.name = "Paris".into();
+        locations.set("42", &place).await.unwrap();
+        let saved = locations.get("42").await.unwrap().unwrap();
+        assert_eq!(saved.place.name, "Paris");
+        assert!(locations.get("7").await.unwrap().is_none());
+
+        assert_eq!(locations.delete("42").await.unwrap(), 1);
+        assert!(locations.get("42").await.unwrap().is_none());
+    }
+}
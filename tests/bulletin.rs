@@ -30,11 +30,11 @@ async fn bootstrap_deps() -> anyhow::Result<spacebot::AgentDeps> {
     let resolved_agents = config.resolve_agents();
     let agent_config = resolved_agents.first().context("no agents configured")?;
 
-    let db = spacebot::db::Db::connect(&agent_config.data_dir)
+    let db = spacebot::db::Db::connect(&agent_config.data_dir, &config.database, &agent_config.id)
         .await
         .context("failed to connect databases")?;
 
-    let memory_store = spacebot::memory::MemoryStore::new(db.sqlite.clone());
+    let memory_store = spacebot::memory::MemoryStore::new(db.sql.clone());
 
     let embedding_table = spacebot::memory::EmbeddingTable::open_or_create(&db.lance)
         .await
@@ -61,8 +61,10 @@ async fn bootstrap_deps() -> anyhow::Result<spacebot::AgentDeps> {
         agent_config,
         &config.defaults,
         prompts,
+        spacebot::scripting::ScriptHooks::default(),
         identity,
         skills,
+        spacebot::flows::FlowSet::default(),
     ));
 
     let (event_tx, _) = tokio::sync::broadcast::channel(16);
@@ -76,8 +78,15 @@ async fn bootstrap_deps() -> anyhow::Result<spacebot::AgentDeps> {
         cron_tool: None,
         runtime_config,
         event_tx,
-        sqlite_pool: db.sqlite.clone(),
+        sql_pool: db.sql.clone(),
+        conversation_writes: spacebot::conversation::WriteBatch::new(
+            db.sql.clone(),
+            &config.database,
+        ),
         messaging_manager: None,
+        jobs: Arc::new(spacebot::jobs::JobQueue::in_memory()),
+        artifacts: Arc::new(spacebot::storage::ArtifactStore::Local),
+        plugins: spacebot::plugins::PluginHost::empty(),
     })
 }
 
@@ -152,7 +161,7 @@ async fn test_bulletin_generation() {
     assert!(before.is_empty(), "bulletin should start empty");
 
     // Generate the bulletin
-    let logger = spacebot::agent::cortex::CortexLogger::new(deps.sqlite_pool.clone());
+    let logger = spacebot::agent::cortex::CortexLogger::new(deps.sql_pool.clone());
     let success = spacebot::agent::cortex::generate_bulletin(&deps, &logger).await;
     assert!(success, "bulletin generation should succeed");
 
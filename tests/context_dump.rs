@@ -30,11 +30,11 @@ async fn bootstrap_deps() -> anyhow::Result<(spacebot::AgentDeps, spacebot::conf
     let resolved_agents = config.resolve_agents();
     let agent_config = resolved_agents.first().context("no agents configured")?;
 
-    let db = spacebot::db::Db::connect(&agent_config.data_dir)
+    let db = spacebot::db::Db::connect(&agent_config.data_dir, &config.database, &agent_config.id)
         .await
         .context("failed to connect databases")?;
 
-    let memory_store = spacebot::memory::MemoryStore::new(db.sqlite.clone());
+    let memory_store = spacebot::memory::MemoryStore::new(db.sql.clone());
 
     let embedding_table = spacebot::memory::EmbeddingTable::open_or_create(&db.lance)
         .await
@@ -61,8 +61,10 @@ async fn bootstrap_deps() -> anyhow::Result<(spacebot::AgentDeps, spacebot::conf
         agent_config,
         &config.defaults,
         prompts,
+        spacebot::scripting::ScriptHooks::default(),
         identity,
         skills,
+        spacebot::flows::FlowSet::default(),
     ));
 
     let (event_tx, _) = tokio::sync::broadcast::channel(16);
@@ -76,8 +78,15 @@ async fn bootstrap_deps() -> anyhow::Result<(spacebot::AgentDeps, spacebot::conf
         cron_tool: None,
         runtime_config,
         event_tx,
-        sqlite_pool: db.sqlite.clone(),
+        sql_pool: db.sql.clone(),
+        conversation_writes: spacebot::conversation::WriteBatch::new(
+            db.sql.clone(),
+            &config.database,
+        ),
         messaging_manager: None,
+        jobs: Arc::new(spacebot::jobs::JobQueue::in_memory()),
+        artifacts: Arc::new(spacebot::storage::ArtifactStore::Local),
+        plugins: spacebot::plugins::PluginHost::empty(),
     };
 
     Ok((deps, config))
@@ -143,6 +152,7 @@ fn build_channel_system_prompt(rc: &spacebot::config::RuntimeConfig) -> String {
             empty_to_none(identity_context),
             empty_to_none(memory_bulletin.to_string()),
             empty_to_none(skills_prompt),
+            None,
             worker_capabilities,
             conversation_context,
             None,
@@ -165,8 +175,8 @@ async fn dump_channel_context() {
 
     // Build the actual channel tool server with real tools registered
     let conversation_logger =
-        spacebot::conversation::ConversationLogger::new(deps.sqlite_pool.clone());
-    let channel_store = spacebot::conversation::ChannelStore::new(deps.sqlite_pool.clone());
+        spacebot::conversation::ConversationLogger::new(deps.sql_pool.clone());
+    let channel_store = spacebot::conversation::ChannelStore::new(deps.sql_pool.clone());
     let channel_id: spacebot::ChannelId = Arc::from("test-channel");
     let status_block = Arc::new(tokio::sync::RwLock::new(
         spacebot::agent::status::StatusBlock::new(),
@@ -178,7 +188,7 @@ async fn dump_channel_context() {
         history: Arc::new(tokio::sync::RwLock::new(Vec::new())),
         active_branches: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
         worker_handles: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
-        process_run_logger: spacebot::conversation::ProcessRunLogger::new(deps.sqlite_pool.clone()),
+        process_run_logger: spacebot::conversation::ProcessRunLogger::new(deps.sql_pool.clone()),
         active_workers: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
         worker_inputs: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
         status_block,
@@ -249,8 +259,8 @@ async fn dump_branch_context() {
 
     // Build the actual branch tool server
     let conversation_logger =
-        spacebot::conversation::ConversationLogger::new(deps.sqlite_pool.clone());
-    let channel_store = spacebot::conversation::ChannelStore::new(deps.sqlite_pool.clone());
+        spacebot::conversation::ConversationLogger::new(deps.sql_pool.clone());
+    let channel_store = spacebot::conversation::ChannelStore::new(deps.sql_pool.clone());
     let branch_tool_server = spacebot::tools::create_branch_tool_server(
         deps.memory_search.clone(),
         conversation_logger,
@@ -312,6 +322,7 @@ async fn dump_worker_context() {
         deps.event_tx.clone(),
         browser_config,
         std::path::PathBuf::from("/tmp/screenshots"),
+        deps.artifacts.clone(),
         brave_search_key,
         std::path::PathBuf::from("/tmp"),
         std::path::PathBuf::from("/tmp"),
@@ -359,7 +370,7 @@ async fn dump_all_contexts() {
     let workspace_dir = rc.workspace_dir.to_string_lossy();
 
     // Generate bulletin so channel context is complete
-    let logger = spacebot::agent::cortex::CortexLogger::new(deps.sqlite_pool.clone());
+    let logger = spacebot::agent::cortex::CortexLogger::new(deps.sql_pool.clone());
     let bulletin_success = spacebot::agent::cortex::generate_bulletin(&deps, &logger).await;
     if bulletin_success {
         let bulletin = rc.memory_bulletin.load();
@@ -372,8 +383,8 @@ async fn dump_all_contexts() {
     }
 
     let conversation_logger =
-        spacebot::conversation::ConversationLogger::new(deps.sqlite_pool.clone());
-    let channel_store = spacebot::conversation::ChannelStore::new(deps.sqlite_pool.clone());
+        spacebot::conversation::ConversationLogger::new(deps.sql_pool.clone());
+    let channel_store = spacebot::conversation::ChannelStore::new(deps.sql_pool.clone());
 
     // ── Channel ──
     let channel_prompt = build_channel_system_prompt(rc);
@@ -385,7 +396,7 @@ async fn dump_all_contexts() {
         history: Arc::new(tokio::sync::RwLock::new(Vec::new())),
         active_branches: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
         worker_handles: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
-        process_run_logger: spacebot::conversation::ProcessRunLogger::new(deps.sqlite_pool.clone()),
+        process_run_logger: spacebot::conversation::ProcessRunLogger::new(deps.sql_pool.clone()),
         active_workers: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
         worker_inputs: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
         status_block: Arc::new(tokio::sync::RwLock::new(
@@ -459,6 +470,7 @@ async fn dump_all_contexts() {
         deps.event_tx.clone(),
         browser_config,
         std::path::PathBuf::from("/tmp/screenshots"),
+        deps.artifacts.clone(),
         brave_search_key,
         std::path::PathBuf::from("/tmp"),
         std::path::PathBuf::from("/tmp"),
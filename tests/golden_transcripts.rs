@@ -0,0 +1,313 @@
+//! Golden transcript regression tests.
+//!
+//! Each file in `tests/golden/` is a saved conversation: user turns, the LLM
+//! replies to script, and the behavior the pipeline produced when the
+//! transcript was recorded. The harness serves the scripted replies from a
+//! mock OpenAI-compatible server, replays the user turns through the channel
+//! agent (routing, `SpacebotModel`, the hook, and the real reply/skip/react
+//! tools), and compares the models requested, the tool calls made, and the
+//! outbound messages against the recording.
+//!
+//! After an intended behavior change, re-record with:
+//! `UPDATE_GOLDEN=1 cargo test --test golden_transcripts`
+
+use anyhow::Context as _;
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use rig::completion::{CompletionModel as _, Prompt as _};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Transcript {
+    description: String,
+    /// Extra config TOML (routing, budgets); the mock provider is added automatically.
+    #[serde(default)]
+    config: String,
+    /// Platform the conversation came from, e.g. "discord".
+    source: String,
+    system_prompt: String,
+    turns: Vec<Turn>,
+    expected: Expected,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Turn {
+    user: String,
+    /// LLM replies served in order, whichever model asks.
+    script: Vec<ScriptedReply>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+enum ScriptedReply {
+    Error { status: u16 },
+    ToolCalls { tool_calls: Vec<ScriptedToolCall> },
+    Text { text: String },
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct ScriptedToolCall {
+    name: String,
+    arguments: serde_json::Value,
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+struct Expected {
+    /// Model named in each request the mock received, in order.
+    requests: Vec<String>,
+    /// Tools the agent invoked, in order.
+    tool_calls: Vec<String>,
+    /// Messages sent to the platform, status updates excluded.
+    outbound: Vec<serde_json::Value>,
+}
+
+/// Mock LLM: serves scripted chat completions and records requested models.
+#[derive(Default)]
+struct MockLlm {
+    script: VecDeque<ScriptedReply>,
+    requested_models: Vec<String>,
+    call_count: usize,
+}
+
+async fn chat_completions(
+    State(mock): State<Arc<Mutex<MockLlm>>>,
+    Json(body): Json<serde_json::Value>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let mut mock = mock.lock().expect("mock lock poisoned");
+    mock.requested_models
+        .push(body["model"].as_str().unwrap_or_default().to_string());
+    mock.call_count += 1;
+    let call_id = format!("call_{}", mock.call_count);
+
+    let message = match mock.script.pop_front() {
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": { "message": "script exhausted" } })),
+            );
+        }
+        Some(ScriptedReply::Error { status }) => {
+            let status = StatusCode::from_u16(status).expect("scripted status is valid");
+            return (
+                status,
+                Json(serde_json::json!({ "error": { "message": format!("scripted {status}") } })),
+            );
+        }
+        Some(ScriptedReply::Text { text }) => {
+            serde_json::json!({ "role": "assistant", "content": text })
+        }
+        Some(ScriptedReply::ToolCalls { tool_calls }) => {
+            let tool_calls: Vec<serde_json::Value> = tool_calls
+                .iter()
+                .enumerate()
+                .map(|(index, call)| {
+                    serde_json::json!({
+                        "id": format!("{call_id}_{index}"),
+                        "type": "function",
+                        "function": {
+                            "name": call.name,
+                            "arguments": call.arguments.to_string(),
+                        },
+                    })
+                })
+                .collect();
+            serde_json::json!({ "role": "assistant", "content": null, "tool_calls": tool_calls })
+        }
+    };
+
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "choices": [{ "index": 0, "message": message }],
+            "usage": { "prompt_tokens": 10, "completion_tokens": 5 },
+        })),
+    )
+}
+
+async fn start_mock(mock: Arc<Mutex<MockLlm>>) -> anyhow::Result<String> {
+    let app = axum::Router::new()
+        .route(
+            "/v1/chat/completions",
+            axum::routing::post(chat_completions),
+        )
+        .with_state(mock);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let address = listener.local_addr()?;
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    Ok(format!("http://{address}"))
+}
+
+/// Replay a transcript and return what the pipeline did.
+async fn replay(transcript: &Transcript) -> anyhow::Result<Expected> {
+    let mock = Arc::new(Mutex::new(MockLlm::default()));
+    let base_url = start_mock(mock.clone()).await?;
+
+    let instance_dir = tempfile::tempdir()?;
+    let config_path = instance_dir.path().join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "{}\n\n[llm.provider.mock]\napi_type = \"openai_completions\"\nbase_url = \"{base_url}\"\napi_key = \"test\"\n",
+            transcript.config
+        ),
+    )?;
+    let config = spacebot::config::Config::load_from_path(&config_path)?;
+    let routing = config.defaults.routing.clone();
+    let llm_manager = Arc::new(spacebot::llm::LlmManager::new(config.llm.clone()).await?);
+
+    let sqlite = sqlx::SqlitePool::connect(&format!(
+        "sqlite:{}?mode=rwc",
+        instance_dir.path().join("spacebot.db").display()
+    ))
+    .await?;
+    sqlx::migrate!("./migrations").run(&sqlite).await?;
+    let conversation_logger = spacebot::conversation::ConversationLogger::new(sqlite.into());
+
+    let channel_id: spacebot::ChannelId = Arc::from(format!("{}:golden", transcript.source));
+    let (response_tx, mut response_rx) = tokio::sync::mpsc::channel(64);
+    let (event_tx, mut event_rx) = tokio::sync::broadcast::channel(64);
+    let hook = spacebot::hooks::SpacebotHook::new(
+        Arc::from("golden"),
+        spacebot::ProcessId::Channel(channel_id.clone()),
+        spacebot::ProcessType::Channel,
+        Some(channel_id.clone()),
+        event_tx,
+    );
+
+    let mut actual = Expected::default();
+    let mut history = Vec::new();
+    for turn in &transcript.turns {
+        mock.lock()
+            .expect("mock lock poisoned")
+            .script
+            .extend(turn.script.iter().cloned());
+
+        let skip_flag = spacebot::tools::new_skip_flag();
+        let replied_flag = spacebot::tools::new_replied_flag();
+        let tool_server = rig::tool::server::ToolServer::new().run();
+        tool_server
+            .add_tool(spacebot::tools::ReplyTool::new(
+                response_tx.clone(),
+                channel_id.to_string(),
+                conversation_logger.clone(),
+                channel_id.clone(),
+                replied_flag.clone(),
+            ))
+            .await?;
+        tool_server
+            .add_tool(spacebot::tools::SkipTool::new(
+                skip_flag.clone(),
+                response_tx.clone(),
+            ))
+            .await?;
+        tool_server
+            .add_tool(spacebot::tools::ReactTool::new(response_tx.clone()))
+            .await?;
+
+        let model_name = routing.resolve(spacebot::ProcessType::Channel, None);
+        let model = spacebot::llm::SpacebotModel::make(&llm_manager, model_name)
+            .with_routing(routing.clone());
+        let agent = spacebot::agent::channel::build_channel_agent(
+            model,
+            &transcript.system_prompt,
+            5,
+            tool_server,
+        );
+
+        let result = agent
+            .prompt(turn.user.as_str())
+            .with_history(&mut history)
+            .with_hook(hook.clone())
+            .await;
+
+        let skipped = skip_flag.load(std::sync::atomic::Ordering::Relaxed);
+        let replied = replied_flag.load(std::sync::atomic::Ordering::Relaxed);
+        match result {
+            Ok(text) if !skipped && !replied => {
+                let text = spacebot::agent::channel::fallback_reply_text(&text, &transcript.source);
+                if !text.is_empty() {
+                    response_tx
+                        .send(spacebot::OutboundResponse::Text(text))
+                        .await?;
+                }
+            }
+            Ok(_) => {}
+            Err(error) => actual
+                .outbound
+                .push(serde_json::json!({ "error": error.to_string() })),
+        }
+    }
+
+    drop(response_tx);
+    while let Some(response) = response_rx.recv().await {
+        if matches!(response, spacebot::OutboundResponse::Status(_)) {
+            continue;
+        }
+        actual.outbound.push(serde_json::to_value(&response)?);
+    }
+    while let Ok(event) = event_rx.try_recv() {
+        if let spacebot::ProcessEvent::ToolStarted { tool_name, .. } = event {
+            actual.tool_calls.push(tool_name);
+        }
+    }
+    actual.requests = mock
+        .lock()
+        .expect("mock lock poisoned")
+        .requested_models
+        .clone();
+
+    Ok(actual)
+}
+
+fn transcript_paths() -> anyhow::Result<Vec<PathBuf>> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .with_context(|| format!("can't read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+#[tokio::test]
+async fn golden_transcripts_replay_unchanged() -> anyhow::Result<()> {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    for path in transcript_paths()? {
+        let content = std::fs::read_to_string(&path)?;
+        let mut transcript: Transcript = serde_json::from_str(&content)
+            .with_context(|| format!("can't parse {}", path.display()))?;
+        let actual = replay(&transcript)
+            .await
+            .with_context(|| format!("can't replay {}", path.display()))?;
+
+        if update {
+            transcript.expected = actual;
+            std::fs::write(&path, serde_json::to_string_pretty(&transcript)? + "\n")?;
+        } else if actual != transcript.expected {
+            failures.push(format!(
+                "{} ({}):\n  expected: {}\n  actual:   {}",
+                path.display(),
+                transcript.description,
+                serde_json::to_string(&transcript.expected)?,
+                serde_json::to_string(&actual)?,
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "golden transcripts changed (re-record with UPDATE_GOLDEN=1 if intended):\n{}",
+        failures.join("\n")
+    );
+    Ok(())
+}